@@ -12,6 +12,7 @@ use openssl::x509::X509;
 use yaml_rust::Yaml;
 
 use g3_histogram::HistogramMetricsConfig;
+use g3_types::net::TlsCertStatus;
 
 static BACKEND_CONFIG_LOCK: OnceLock<Arc<OpensslBackendConfig>> = OnceLock::new();
 
@@ -32,8 +33,9 @@ pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
     if let Yaml::Hash(map) = value {
         let mut no_append_ca_cert = false;
         let mut ca_cert_pem = Vec::new();
-        let mut ca_cert: Option<X509> = None;
+        let mut ca_certs: Vec<X509> = Vec::new();
         let mut ca_key: Option<PKey<Private>> = None;
+        let mut allow_expired = false;
         let mut keep_serial = false;
         let mut max_ttl = 24 * 3600; // 1 day
         let mut duration_stats = HistogramMetricsConfig::default();
@@ -49,12 +51,10 @@ pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
                     })?;
                     ca_cert_pem.extend(pem);
                 }
-
-                let cert = certs
-                    .into_iter()
-                    .next()
-                    .ok_or_else(|| anyhow!("no valid openssl certificate key found"))?;
-                ca_cert = Some(cert);
+                if certs.is_empty() {
+                    return Err(anyhow!("no valid openssl certificate key found"));
+                }
+                ca_certs = certs;
                 Ok(())
             }
             "ca_private_key" => {
@@ -63,6 +63,10 @@ pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
                 ca_key = Some(key);
                 Ok(())
             }
+            "allow_expired" => {
+                allow_expired = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
             "no_append_ca_cert" => {
                 no_append_ca_cert = g3_yaml::value::as_bool(v)?;
                 Ok(())
@@ -85,13 +89,18 @@ pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
             _ => Err(anyhow!("invalid key {k}")),
         })?;
 
-        let Some(ca_cert) = ca_cert else {
+        let Some(ca_cert) = ca_certs.first().cloned() else {
             return Err(anyhow!("no ca certificate set"));
         };
         let Some(ca_key) = ca_key else {
             return Err(anyhow!("no ca private key set"));
         };
 
+        let mut status = TlsCertStatus::check_openssl(&ca_certs, &ca_key)
+            .context("invalid ca certificate and private key")?;
+        status.set_allow_expired(allow_expired);
+        g3_daemon::tls::check_and_record_certs("backend".to_string(), vec![status])?;
+
         if no_append_ca_cert {
             ca_cert_pem.clear();
         }
@@ -3,8 +3,10 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::net::IpAddr;
 use std::sync::Arc;
 
+use http::HeaderName;
 use slog::Logger;
 
 use g3_dpi::{
@@ -13,11 +15,13 @@ use g3_dpi::{
 };
 use g3_icap_client::reqmod::IcapReqmodClient;
 use g3_icap_client::respmod::IcapRespmodClient;
+use g3_types::acl::AclNetworkRule;
+use g3_types::net::UpstreamAddr;
 
 use super::Auditor;
 #[cfg(feature = "quic")]
 use super::StreamDetourClient;
-use crate::config::audit::AuditorConfig;
+use crate::config::audit::{AdaptationBypassCheck, AuditBodyDigestConfig, AuditorConfig};
 use crate::inspect::tls::TlsInterceptionContext;
 
 pub(crate) struct AuditHandle {
@@ -29,6 +33,7 @@ pub(crate) struct AuditHandle {
     intercept_logger: Option<Logger>,
     icap_reqmod_client: Option<IcapReqmodClient>,
     icap_respmod_client: Option<IcapRespmodClient>,
+    adaptation_bypass_source_acl: Option<AclNetworkRule>,
     #[cfg(feature = "quic")]
     stream_detour_client: Option<Arc<StreamDetourClient>>,
     pub(crate) h2_inspect_policy: ProtocolInspectPolicy,
@@ -56,6 +61,11 @@ impl AuditHandle {
             intercept_logger: crate::log::intercept::get_logger(auditor.config.name()),
             icap_reqmod_client: icap_reqmod_service,
             icap_respmod_client: icap_respmod_service,
+            adaptation_bypass_source_acl: auditor
+                .config
+                .adaptation_bypass_header
+                .as_ref()
+                .map(|c| c.build_source_acl()),
             #[cfg(feature = "quic")]
             stream_detour_client: auditor.stream_detour_service.clone(),
             h2_inspect_policy: auditor.config.h2_inspect_policy.build(),
@@ -104,6 +114,11 @@ impl AuditHandle {
         self.auditor_config.log_uri_max_chars
     }
 
+    #[inline]
+    pub(crate) fn log_icap_timing(&self) -> bool {
+        self.auditor_config.log_icap_timing
+    }
+
     #[inline]
     pub(crate) fn h1_interception(&self) -> &H1InterceptionConfig {
         &self.auditor_config.h1_interception
@@ -134,6 +149,36 @@ impl AuditHandle {
         self.icap_respmod_client.as_ref()
     }
 
+    #[inline]
+    pub(crate) fn response_body_digest(&self) -> Option<&AuditBodyDigestConfig> {
+        self.auditor_config.response_body_digest.as_ref()
+    }
+
+    #[inline]
+    pub(crate) fn adaptation_bypass_header(&self) -> Option<&HeaderName> {
+        self.auditor_config
+            .adaptation_bypass_header
+            .as_ref()
+            .map(|c| c.header())
+    }
+
+    /// Check the value of the adaptation bypass header sent by the client
+    pub(crate) fn check_adaptation_bypass(
+        &self,
+        client_ip: IpAddr,
+        upstream: &UpstreamAddr,
+        value: &[u8],
+    ) -> AdaptationBypassCheck {
+        let (Some(config), Some(acl)) = (
+            &self.auditor_config.adaptation_bypass_header,
+            &self.adaptation_bypass_source_acl,
+        ) else {
+            return AdaptationBypassCheck::Invalid;
+        };
+        let now = chrono::Utc::now().timestamp();
+        config.check(acl, client_ip, upstream, value, now)
+    }
+
     #[cfg(feature = "quic")]
     #[inline]
     pub(crate) fn stream_detour_client(&self) -> Option<&Arc<StreamDetourClient>> {
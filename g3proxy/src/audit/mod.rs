@@ -8,7 +8,7 @@ use std::sync::Arc;
 use anyhow::Context;
 
 use g3_dpi::ProtocolPortMap;
-use g3_icap_client::IcapServiceClient;
+use g3_icap_client::{IcapDebugCaptureConfig, IcapServiceClient};
 use g3_types::metrics::NodeName;
 use g3_types::net::{OpensslTicketKey, RollingTicketer};
 
@@ -17,7 +17,7 @@ use crate::inspect::tls::TlsInterceptionContext;
 
 mod ops;
 pub use ops::load_all;
-pub(crate) use ops::reload;
+pub(crate) use ops::{reload, set_icap_debug_capture};
 
 mod registry;
 pub(crate) use registry::{get_names, get_or_insert_default};
@@ -132,6 +132,19 @@ impl Auditor {
         Ok(())
     }
 
+    /// Change the debug capture of all the ICAP services at runtime, return the count of them
+    fn set_icap_debug_capture(&self, capture: Option<IcapDebugCaptureConfig>) -> usize {
+        let mut count = 0;
+        for service in [&self.icap_reqmod_service, &self.icap_respmod_service]
+            .into_iter()
+            .flatten()
+        {
+            service.set_debug_capture(capture.clone());
+            count += 1;
+        }
+        count
+    }
+
     pub(crate) fn build_handle(&self) -> anyhow::Result<Arc<AuditHandle>> {
         let mut handle = AuditHandle::new(self);
 
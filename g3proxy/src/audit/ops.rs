@@ -9,6 +9,7 @@ use anyhow::{Context, anyhow};
 use log::debug;
 use tokio::sync::Mutex;
 
+use g3_icap_client::IcapDebugCaptureConfig;
 use g3_types::metrics::NodeName;
 use g3_yaml::YamlDocPosition;
 
@@ -96,6 +97,23 @@ pub(crate) async fn reload(
     Ok(())
 }
 
+/// Start or stop the ICAP debug capture of the auditor at runtime.
+///
+/// The clients are shared with the running tasks, so the change applies to new ICAP transactions
+/// at once. It will be reset to the config value when the auditor is reloaded.
+pub(crate) fn set_icap_debug_capture(
+    name: &NodeName,
+    capture: Option<IcapDebugCaptureConfig>,
+) -> anyhow::Result<usize> {
+    let Some(auditor) = registry::get(name) else {
+        return Err(anyhow!("no auditor with name {name} found"));
+    };
+    match auditor.set_icap_debug_capture(capture) {
+        0 => Err(anyhow!("no ICAP service is set in auditor {name}")),
+        n => Ok(n),
+    }
+}
+
 async fn reload_old_unlocked(old: AuditorConfig, new: AuditorConfig) -> anyhow::Result<()> {
     let name = old.name();
     let Some(old_auditor) = registry::get(name) else {
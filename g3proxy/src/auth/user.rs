@@ -55,6 +55,11 @@ pub(crate) struct User {
 }
 
 impl User {
+    #[inline]
+    pub(crate) fn group(&self) -> &NodeName {
+        &self.group
+    }
+
     #[inline]
     pub(crate) fn task_max_idle_count(&self) -> Option<usize> {
         self.config.task_idle_max_count
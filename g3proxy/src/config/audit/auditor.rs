@@ -19,13 +19,14 @@ use g3_icap_client::IcapServiceConfig;
 use g3_tls_ticket::TlsTicketConfig;
 use g3_types::metrics::NodeName;
 use g3_types::net::{
-    OpensslInterceptionClientConfigBuilder, OpensslInterceptionServerConfigBuilder,
+    OpensslInterceptionClientConfigBuilder, OpensslInterceptionServerConfigBuilder, TlsCertStatus,
 };
 use g3_udpdump::StreamDumpConfig;
 use g3_yaml::YamlDocPosition;
 
 #[cfg(feature = "quic")]
 use super::AuditStreamDetourConfig;
+use super::{AuditAdaptationBypassHeaderConfig, AuditBodyDigestConfig};
 
 #[derive(Clone)]
 pub(crate) struct AuditorConfig {
@@ -40,6 +41,7 @@ pub(crate) struct AuditorConfig {
     pub(crate) tls_interception_server: OpensslInterceptionServerConfigBuilder,
     pub(crate) tls_stream_dump: Option<StreamDumpConfig>,
     pub(crate) log_uri_max_chars: usize,
+    pub(crate) log_icap_timing: bool,
     pub(crate) h1_interception: H1InterceptionConfig,
     pub(crate) h2_inspect_policy: ProtocolInspectPolicyBuilder,
     pub(crate) h2_interception: H2InterceptionConfig,
@@ -50,6 +52,8 @@ pub(crate) struct AuditorConfig {
     pub(crate) imap_interception: ImapInterceptionConfig,
     pub(crate) icap_reqmod_service: Option<Arc<IcapServiceConfig>>,
     pub(crate) icap_respmod_service: Option<Arc<IcapServiceConfig>>,
+    pub(crate) response_body_digest: Option<AuditBodyDigestConfig>,
+    pub(crate) adaptation_bypass_header: Option<AuditAdaptationBypassHeaderConfig>,
     #[cfg(feature = "quic")]
     pub(crate) stream_detour_service: Option<Arc<AuditStreamDetourConfig>>,
     pub(crate) task_audit_ratio: Bernoulli,
@@ -64,6 +68,24 @@ impl AuditorConfig {
         self.position.clone()
     }
 
+    /// get the status of all the client certificates used by the audit services
+    pub(crate) fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::new();
+        for service in [&self.icap_reqmod_service, &self.icap_respmod_service]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(builder) = service.tls_client() {
+                all.extend(builder.cert_status()?);
+            }
+        }
+        #[cfg(feature = "quic")]
+        if let Some(service) = &self.stream_detour_service {
+            all.extend(service.tls_client.cert_status()?);
+        }
+        Ok(all)
+    }
+
     fn with_name(name: NodeName, position: Option<YamlDocPosition>) -> Self {
         AuditorConfig {
             name,
@@ -77,6 +99,7 @@ impl AuditorConfig {
             tls_interception_server: Default::default(),
             tls_stream_dump: None,
             log_uri_max_chars: 1024,
+            log_icap_timing: false,
             h1_interception: Default::default(),
             h2_inspect_policy: Default::default(),
             h2_interception: Default::default(),
@@ -87,6 +110,8 @@ impl AuditorConfig {
             imap_interception: Default::default(),
             icap_reqmod_service: None,
             icap_respmod_service: None,
+            response_body_digest: None,
+            adaptation_bypass_header: None,
             #[cfg(feature = "quic")]
             stream_detour_service: None,
             task_audit_ratio: Bernoulli::new(1.0).unwrap(),
@@ -180,6 +205,10 @@ impl AuditorConfig {
                     .context(format!("invalid usize value for key {k}"))?;
                 Ok(())
             }
+            "log_icap_timing" => {
+                self.log_icap_timing = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
             "h1_interception" => {
                 self.h1_interception = g3_yaml::value::as_h1_interception_config(v)
                     .context(format!("invalid h1 interception value for key {k}"))?;
@@ -239,6 +268,20 @@ impl AuditorConfig {
                 self.icap_respmod_service = Some(Arc::new(service));
                 Ok(())
             }
+            "response_body_digest" => {
+                self.response_body_digest = AuditBodyDigestConfig::parse_yaml(v)
+                    .context(format!("invalid body digest config value for key {k}"))?;
+                Ok(())
+            }
+            "adaptation_bypass_header" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let config = AuditAdaptationBypassHeaderConfig::parse_yaml(v, Some(lookup_dir))
+                    .context(format!(
+                        "invalid adaptation bypass header config value for key {k}"
+                    ))?;
+                self.adaptation_bypass_header = Some(config);
+                Ok(())
+            }
             #[cfg(feature = "quic")]
             "stream_detour_service" => {
                 let service = AuditStreamDetourConfig::parse(v, self.position.as_ref()).context(
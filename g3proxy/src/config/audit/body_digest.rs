@@ -0,0 +1,85 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::str::FromStr;
+
+use anyhow::{Context, anyhow};
+use mime::Mime;
+use yaml_rust::Yaml;
+
+/// Config for the SHA-256 digest of relayed response bodies
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AuditBodyDigestConfig {
+    skip_content_types: Vec<Mime>,
+}
+
+impl AuditBodyDigestConfig {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Option<Self>> {
+        match v {
+            Yaml::Boolean(true) => Ok(Some(AuditBodyDigestConfig::default())),
+            Yaml::Boolean(false) => Ok(None),
+            Yaml::Hash(map) => {
+                let mut config = AuditBodyDigestConfig::default();
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "skip_content_types" | "skip_content_type" => {
+                        config.skip_content_types = g3_yaml::value::as_list(v, as_mime)
+                            .context(format!("invalid mime type list value for key {k}"))?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+                Ok(Some(config))
+            }
+            _ => Err(anyhow!("invalid yaml value type")),
+        }
+    }
+
+    /// Check if the hashing should be skipped for the body of this content type
+    pub(crate) fn skip_content_type(&self, content_type: Option<&str>) -> bool {
+        if self.skip_content_types.is_empty() {
+            return false;
+        }
+        let Some(mime) = content_type.and_then(|s| Mime::from_str(s).ok()) else {
+            return false;
+        };
+        self.skip_content_types.iter().any(|m| {
+            if m.type_() == mime::STAR {
+                return true;
+            }
+            m.type_() == mime.type_()
+                && (m.subtype() == mime::STAR || m.subtype() == mime.subtype())
+        })
+    }
+}
+
+fn as_mime(v: &Yaml) -> anyhow::Result<Mime> {
+    let s = g3_yaml::value::as_string(v)?;
+    Mime::from_str(&s).map_err(|e| anyhow!("invalid mime type {s}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_content_type() {
+        let config = AuditBodyDigestConfig {
+            skip_content_types: vec![
+                Mime::from_str("video/*").unwrap(),
+                Mime::from_str("application/octet-stream").unwrap(),
+            ],
+        };
+        assert!(config.skip_content_type(Some("video/mp4")));
+        assert!(config.skip_content_type(Some("Video/MP4; codecs=avc1")));
+        assert!(config.skip_content_type(Some("application/octet-stream")));
+        assert!(!config.skip_content_type(Some("application/json")));
+        assert!(!config.skip_content_type(Some("audio/mpeg")));
+        assert!(!config.skip_content_type(Some("invalid")));
+        assert!(!config.skip_content_type(None));
+
+        let config = AuditBodyDigestConfig::default();
+        assert!(!config.skip_content_type(Some("video/mp4")));
+    }
+}
@@ -0,0 +1,332 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io::Read;
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use http::HeaderName;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::sign::Signer;
+use yaml_rust::Yaml;
+
+use g3_types::acl::{AclAction, AclNetworkRule, AclNetworkRuleBuilder};
+use g3_types::net::UpstreamAddr;
+
+/// The result of the check of an adaptation bypass header
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum AdaptationBypassCheck {
+    Trusted,
+    /// the signature is invalid, or the timestamp is out of the validity window
+    Invalid,
+    /// the client is not in the allowed source networks
+    Denied,
+}
+
+/// Config for the signed header which allows internal callers to bypass ICAP adaptation
+///
+/// The header value should be `[<timestamp>:]<signature>`, where the signature is the
+/// hex encoded HMAC-SHA256 of the upstream address `<host>:<port>`, prefixed with
+/// `<timestamp>:` if the timestamp, in unix seconds, is present.
+#[derive(Clone)]
+pub(crate) struct AuditAdaptationBypassHeaderConfig {
+    header: HeaderName,
+    hmac_key: PKey<Private>,
+    validity: Option<Duration>,
+    source_networks: AclNetworkRuleBuilder,
+}
+
+impl AuditAdaptationBypassHeaderConfig {
+    pub(crate) fn parse_yaml(v: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for adaptation bypass header should be 'map'"
+            ));
+        };
+
+        let mut header = None;
+        let mut hmac_key = None;
+        let mut validity = None;
+        let mut source_networks = AclNetworkRuleBuilder::new(AclAction::Forbid);
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "header" | "header_name" => {
+                let name = g3_yaml::value::as_http_header_name(v)
+                    .context(format!("invalid http header name value for key {k}"))?;
+                header = Some(name);
+                Ok(())
+            }
+            "hmac_key" | "hmac_key_file" | "key_file" => {
+                let (mut file, path) = g3_yaml::value::as_file(v, lookup_dir)
+                    .context(format!("invalid file path value for key {k}"))?;
+                let mut key = Vec::new();
+                file.read_to_end(&mut key)
+                    .map_err(|e| anyhow!("failed to read key file {}: {e}", path.display()))?;
+                let key = key.trim_ascii();
+                if key.is_empty() {
+                    return Err(anyhow!("empty hmac key in file {}", path.display()));
+                }
+                let key = PKey::hmac(key).map_err(|e| anyhow!("invalid hmac key: {e}"))?;
+                hmac_key = Some(key);
+                Ok(())
+            }
+            "validity" | "max_age" => {
+                let duration = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                validity = Some(duration);
+                Ok(())
+            }
+            "source_networks" | "source_network" => {
+                source_networks = AclNetworkRuleBuilder::new(AclAction::Forbid);
+                if let Yaml::Array(seq) = v {
+                    for (i, v) in seq.iter().enumerate() {
+                        let net = g3_yaml::value::as_ip_network(v)
+                            .context(format!("invalid ip network value for {k}#{i}"))?;
+                        source_networks.add_network(net, AclAction::Permit);
+                    }
+                } else {
+                    let net = g3_yaml::value::as_ip_network(v)
+                        .context(format!("invalid ip network value for key {k}"))?;
+                    source_networks.add_network(net, AclAction::Permit);
+                }
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        let Some(header) = header else {
+            return Err(anyhow!("no header name set"));
+        };
+        let Some(hmac_key) = hmac_key else {
+            return Err(anyhow!("no hmac key set"));
+        };
+        Ok(AuditAdaptationBypassHeaderConfig {
+            header,
+            hmac_key,
+            validity,
+            source_networks,
+        })
+    }
+
+    #[inline]
+    pub(crate) fn header(&self) -> &HeaderName {
+        &self.header
+    }
+
+    pub(crate) fn build_source_acl(&self) -> AclNetworkRule {
+        self.source_networks.build()
+    }
+
+    fn sign(&self, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.hmac_key)?;
+        signer.update(payload)?;
+        let hmac = signer.sign_to_vec()?;
+        Ok(hmac)
+    }
+
+    /// Verify the header value sent by the client
+    ///
+    /// `now` is the current time in unix seconds.
+    pub(crate) fn check(
+        &self,
+        source_acl: &AclNetworkRule,
+        client_ip: IpAddr,
+        upstream: &UpstreamAddr,
+        value: &[u8],
+        now: i64,
+    ) -> AdaptationBypassCheck {
+        let (_, action) = source_acl.check(client_ip);
+        if action.forbid_early() {
+            return AdaptationBypassCheck::Denied;
+        }
+
+        if self.verify(upstream, value, now) {
+            AdaptationBypassCheck::Trusted
+        } else {
+            AdaptationBypassCheck::Invalid
+        }
+    }
+
+    fn verify(&self, upstream: &UpstreamAddr, value: &[u8], now: i64) -> bool {
+        let (timestamp, signature) = match memchr::memrchr(b':', value) {
+            Some(p) => (Some(&value[..p]), &value[p + 1..]),
+            None => (None, value),
+        };
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+
+        let payload = match timestamp {
+            Some(ts) => {
+                let Some(ts) = std::str::from_utf8(ts)
+                    .ok()
+                    .and_then(|s| s.parse::<i64>().ok())
+                else {
+                    return false;
+                };
+                if self
+                    .validity
+                    .is_some_and(|validity| now.abs_diff(ts) > validity.as_secs())
+                {
+                    return false;
+                }
+                format!("{ts}:{upstream}")
+            }
+            None => {
+                if self.validity.is_some() {
+                    // the timestamp is required if the validity window is set
+                    return false;
+                }
+                upstream.to_string()
+            }
+        };
+
+        let Ok(expected) = self.sign(payload.as_bytes()) else {
+            return false;
+        };
+        expected.len() == signature.len() && openssl::memcmp::eq(&expected, &signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use yaml_rust::YamlLoader;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn new_config(key: &[u8], validity: Option<Duration>) -> AuditAdaptationBypassHeaderConfig {
+        let mut source_networks = AclNetworkRuleBuilder::new(AclAction::Forbid);
+        source_networks.add_network("10.0.0.0/8".parse().unwrap(), AclAction::Permit);
+        AuditAdaptationBypassHeaderConfig {
+            header: HeaderName::from_static("x-g3-bypass"),
+            hmac_key: PKey::hmac(key).unwrap(),
+            validity,
+            source_networks,
+        }
+    }
+
+    fn header_value(key: &[u8], ts: Option<i64>, upstream: &UpstreamAddr) -> Vec<u8> {
+        let signer = new_config(key, None);
+        let (prefix, payload) = match ts {
+            Some(ts) => (format!("{ts}:"), format!("{ts}:{upstream}")),
+            None => (String::new(), upstream.to_string()),
+        };
+        let signature = hex::encode(signer.sign(payload.as_bytes()).unwrap());
+        format!("{prefix}{signature}").into_bytes()
+    }
+
+    fn check(
+        config: &AuditAdaptationBypassHeaderConfig,
+        client_ip: &str,
+        value: &[u8],
+    ) -> AdaptationBypassCheck {
+        let upstream = UpstreamAddr::from_str("www.example.net:443").unwrap();
+        let acl = config.build_source_acl();
+        config.check(&acl, client_ip.parse().unwrap(), &upstream, value, NOW)
+    }
+
+    #[test]
+    fn valid() {
+        let upstream = UpstreamAddr::from_str("www.example.net:443").unwrap();
+        let config = new_config(b"secret", None);
+        let value = header_value(b"secret", None, &upstream);
+        assert_eq!(
+            check(&config, "10.1.2.3", &value),
+            AdaptationBypassCheck::Trusted
+        );
+        let value = header_value(b"secret", Some(NOW - 10), &upstream);
+        assert_eq!(
+            check(&config, "10.1.2.3", &value),
+            AdaptationBypassCheck::Trusted
+        );
+
+        let config = new_config(b"secret", Some(Duration::from_secs(60)));
+        assert_eq!(
+            check(&config, "10.1.2.3", &value),
+            AdaptationBypassCheck::Trusted
+        );
+
+        // signed for another upstream
+        let other = UpstreamAddr::from_str("www.example.com:443").unwrap();
+        let value = header_value(b"secret", Some(NOW), &other);
+        assert_eq!(
+            check(&config, "10.1.2.3", &value),
+            AdaptationBypassCheck::Invalid
+        );
+    }
+
+    #[test]
+    fn expired() {
+        let upstream = UpstreamAddr::from_str("www.example.net:443").unwrap();
+        let config = new_config(b"secret", Some(Duration::from_secs(60)));
+        let value = header_value(b"secret", Some(NOW - 61), &upstream);
+        assert_eq!(
+            check(&config, "10.1.2.3", &value),
+            AdaptationBypassCheck::Invalid
+        );
+        let value = header_value(b"secret", Some(NOW + 61), &upstream);
+        assert_eq!(
+            check(&config, "10.1.2.3", &value),
+            AdaptationBypassCheck::Invalid
+        );
+        // no timestamp
+        let value = header_value(b"secret", None, &upstream);
+        assert_eq!(
+            check(&config, "10.1.2.3", &value),
+            AdaptationBypassCheck::Invalid
+        );
+    }
+
+    #[test]
+    fn wrong_key() {
+        let upstream = UpstreamAddr::from_str("www.example.net:443").unwrap();
+        let config = new_config(b"secret", None);
+        let value = header_value(b"other-secret", None, &upstream);
+        assert_eq!(
+            check(&config, "10.1.2.3", &value),
+            AdaptationBypassCheck::Invalid
+        );
+        assert_eq!(
+            check(&config, "10.1.2.3", b"not-hex"),
+            AdaptationBypassCheck::Invalid
+        );
+        assert_eq!(
+            check(&config, "10.1.2.3", b""),
+            AdaptationBypassCheck::Invalid
+        );
+    }
+
+    #[test]
+    fn disallowed_source() {
+        let upstream = UpstreamAddr::from_str("www.example.net:443").unwrap();
+        let config = new_config(b"secret", None);
+        let value = header_value(b"secret", None, &upstream);
+        assert_eq!(
+            check(&config, "192.168.1.1", &value),
+            AdaptationBypassCheck::Denied
+        );
+        assert_eq!(
+            check(&config, "127.0.0.1", &value),
+            AdaptationBypassCheck::Denied
+        );
+    }
+
+    #[test]
+    fn parse_err() {
+        let parse = |s: &str| {
+            let yaml = YamlLoader::load_from_str(s).unwrap();
+            AuditAdaptationBypassHeaderConfig::parse_yaml(&yaml[0], None)
+        };
+        assert!(parse("true").is_err());
+        assert!(parse("header: x-g3-bypass\n").is_err());
+        assert!(parse("header: x-g3-bypass\nhmac_key: /no/such/file\n").is_err());
+        assert!(parse("no_such_key: 1\n").is_err());
+    }
+}
@@ -5,7 +5,7 @@
 
 use std::path::Path;
 
-use anyhow::anyhow;
+use anyhow::{Context, anyhow};
 use yaml_rust::{Yaml, yaml};
 
 use g3_yaml::{HybridParser, YamlDocPosition};
@@ -16,6 +16,12 @@ pub(crate) use registry::{clear, get_all};
 mod auditor;
 pub(crate) use auditor::AuditorConfig;
 
+mod body_digest;
+pub(crate) use body_digest::AuditBodyDigestConfig;
+
+mod bypass_header;
+pub(crate) use bypass_header::{AdaptationBypassCheck, AuditAdaptationBypassHeaderConfig};
+
 #[cfg(feature = "quic")]
 mod detour;
 #[cfg(feature = "quic")]
@@ -47,5 +53,11 @@ fn load_auditor(
 ) -> anyhow::Result<AuditorConfig> {
     let mut auditor = AuditorConfig::new(position);
     auditor.parse(map)?;
+    let certs = auditor
+        .cert_status()
+        .context(format!("invalid certificate in auditor {}", auditor.name()))?;
+    g3_daemon::tls::check_and_record_certs(format!("auditor/{}", auditor.name()), certs).context(
+        format!("certificate check failed for auditor {}", auditor.name()),
+    )?;
     Ok(auditor)
 }
@@ -3,14 +3,17 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, anyhow};
 use ascii::AsciiString;
+use ip_network::IpNetwork;
 use log::warn;
 use yaml_rust::{Yaml, yaml};
 
+use g3_socket::BindAddr;
 use g3_types::acl::{AclAction, AclNetworkRuleBuilder};
 use g3_types::metrics::{MetricTagMap, NodeName};
 #[cfg(any(
@@ -31,6 +34,137 @@ use super::{AnyEscaperConfig, EscaperConfig, EscaperConfigDiffAction, GeneralEsc
 
 const ESCAPER_CONFIG_TYPE: &str = "DirectFixed";
 
+/// Config for the NAT style endpoint mapping of udp relay sockets
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct UdpRelayMappingConfig {
+    pub(crate) idle_lifetime: Duration,
+    pub(crate) max_entries: usize,
+    pub(crate) max_total_entries: usize,
+}
+
+impl Default for UdpRelayMappingConfig {
+    fn default() -> Self {
+        UdpRelayMappingConfig {
+            idle_lifetime: Duration::from_secs(60),
+            max_entries: 64,
+            max_total_entries: 65536,
+        }
+    }
+}
+
+impl UdpRelayMappingConfig {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let mut config = UdpRelayMappingConfig::default();
+        match v {
+            Yaml::Hash(map) => {
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "idle_lifetime" | "idle_timeout" => {
+                        config.idle_lifetime = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "max_entries" => {
+                        config.max_entries = g3_yaml::value::as_usize(v)?;
+                        Ok(())
+                    }
+                    "max_total_entries" => {
+                        config.max_total_entries = g3_yaml::value::as_usize(v)?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+            }
+            Yaml::Boolean(true) => {}
+            _ => return Err(anyhow!("invalid yaml value type")),
+        }
+
+        if config.idle_lifetime.is_zero() {
+            return Err(anyhow!("idle lifetime should not be zero"));
+        }
+        if config.max_entries == 0 {
+            return Err(anyhow!("max entries should not be zero"));
+        }
+        if config.max_total_entries < config.max_entries {
+            return Err(anyhow!(
+                "max total entries should not be less than max entries"
+            ));
+        }
+        Ok(config)
+    }
+}
+
+/// Select the bind ip by the destination network
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct EgressBindRule {
+    pub(crate) net: IpNetwork,
+    pub(crate) bind: IpAddr,
+}
+
+impl EgressBindRule {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for 'egress bind rule' should be 'map'"
+            ));
+        };
+
+        let mut net: Option<IpNetwork> = None;
+        let mut bind: Option<IpAddr> = None;
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "net" | "network" | "dst_net" => {
+                let v = g3_yaml::value::as_ip_network(v)
+                    .context(format!("invalid ip network value for key {k}"))?;
+                net = Some(v);
+                Ok(())
+            }
+            "bind" | "bind_ip" => {
+                let v = g3_yaml::value::as_ipaddr(v)
+                    .context(format!("invalid ip address value for key {k}"))?;
+                bind = Some(v);
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        let Some(net) = net else {
+            return Err(anyhow!("net is not set"));
+        };
+        let Some(bind) = bind else {
+            return Err(anyhow!("bind ip is not set"));
+        };
+        match (net, bind) {
+            (IpNetwork::V4(_), IpAddr::V4(_)) | (IpNetwork::V6(_), IpAddr::V6(_)) => {}
+            _ => {
+                return Err(anyhow!(
+                    "the bind ip {bind} is not in the same address family as network {net}"
+                ));
+            }
+        }
+        Ok(EgressBindRule { net, bind })
+    }
+}
+
+fn parse_egress_bind_map(v: &Yaml) -> anyhow::Result<Vec<EgressBindRule>> {
+    match v {
+        Yaml::Array(seq) => {
+            let mut rules = Vec::with_capacity(seq.len());
+            for (i, v) in seq.iter().enumerate() {
+                let rule = EgressBindRule::parse_yaml(v)
+                    .context(format!("invalid egress bind rule value for #{i}"))?;
+                rules.push(rule);
+            }
+            Ok(rules)
+        }
+        Yaml::Hash(_) => {
+            let rule = EgressBindRule::parse_yaml(v)?;
+            Ok(vec![rule])
+        }
+        _ => Err(anyhow!(
+            "yaml value type for 'egress bind map' should be 'seq' or 'map'"
+        )),
+    }
+}
+
 #[derive(Clone, Eq, PartialEq)]
 pub(crate) struct DirectFixedEscaperConfig {
     pub(crate) name: NodeName,
@@ -44,6 +178,14 @@ pub(crate) struct DirectFixedEscaperConfig {
         target_os = "solaris"
     ))]
     pub(crate) bind_interface: Option<Interface>,
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "illumos",
+        target_os = "solaris"
+    ))]
+    pub(crate) bind_device: Option<Interface>,
     pub(crate) bind4: Vec<IpAddr>,
     pub(crate) bind6: Vec<IpAddr>,
     pub(crate) no_ipv4: bool,
@@ -57,6 +199,10 @@ pub(crate) struct DirectFixedEscaperConfig {
     pub(crate) tcp_keepalive: TcpKeepAliveConfig,
     pub(crate) tcp_misc_opts: TcpMiscSockOpts,
     pub(crate) udp_misc_opts: UdpMiscSockOpts,
+    pub(crate) udp_relay_mapping: Option<UdpRelayMappingConfig>,
+    pub(crate) udp_relay_normalize_ipv4_mapped: bool,
+    pub(crate) udp_relay_bind: Option<BindAddr>,
+    pub(crate) egress_bind_map: Vec<EgressBindRule>,
     pub(crate) enable_path_selection: bool,
     pub(crate) use_proxy_protocol: Option<ProxyProtocolVersion>,
     pub(crate) extra_metrics_tags: Option<Arc<MetricTagMap>>,
@@ -76,6 +222,14 @@ impl DirectFixedEscaperConfig {
                 target_os = "solaris"
             ))]
             bind_interface: None,
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "macos",
+                target_os = "illumos",
+                target_os = "solaris"
+            ))]
+            bind_device: None,
             bind4: Vec::new(),
             bind6: Vec::new(),
             no_ipv4: false,
@@ -89,13 +243,17 @@ impl DirectFixedEscaperConfig {
             tcp_keepalive: Default::default(),
             tcp_misc_opts: Default::default(),
             udp_misc_opts: Default::default(),
+            udp_relay_mapping: None,
+            udp_relay_normalize_ipv4_mapped: true,
+            udp_relay_bind: None,
+            egress_bind_map: Vec::new(),
             enable_path_selection: false,
             use_proxy_protocol: None,
             extra_metrics_tags: None,
         }
     }
 
-    pub(super) fn parse(
+    pub(crate) fn parse(
         map: &yaml::Hash,
         position: Option<YamlDocPosition>,
     ) -> anyhow::Result<Self> {
@@ -138,6 +296,29 @@ impl DirectFixedEscaperConfig {
                 self.bind_interface = Some(interface);
                 Ok(())
             }
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "macos",
+                target_os = "illumos",
+                target_os = "solaris"
+            ))]
+            "bind_device" | "vrf" => {
+                let interface = g3_yaml::value::as_interface(v)
+                    .context(format!("invalid interface name value for key {k}"))?;
+                self.bind_device = Some(interface);
+                Ok(())
+            }
+            #[cfg(not(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "macos",
+                target_os = "illumos",
+                target_os = "solaris"
+            )))]
+            "bind_device" | "vrf" => Err(anyhow!(
+                "binding to device is not supported on this platform, found key {k}"
+            )),
             "bind_ip" => {
                 let ips = g3_yaml::value::as_list(v, g3_yaml::value::as_ipaddr)
                     .context(format!("invalid ip address list value for key {k}"))?;
@@ -188,6 +369,21 @@ impl DirectFixedEscaperConfig {
                 warn!("deprecated config key '{k}', please use 'udp_sock_speed_limit' instead");
                 self.set("udp_sock_speed_limit", v)
             }
+            "udp_relay_normalize_ipv4_mapped" => {
+                self.udp_relay_normalize_ipv4_mapped = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "udp_relay_bind" | "udp_relay_bind_ip" => {
+                let bind = parse_udp_relay_bind(v)
+                    .context(format!("invalid udp relay bind value for key {k}"))?;
+                self.udp_relay_bind = Some(bind);
+                Ok(())
+            }
+            "egress_bind_map" => {
+                self.egress_bind_map = parse_egress_bind_map(v)
+                    .context(format!("invalid egress bind map value for key {k}"))?;
+                Ok(())
+            }
             "tcp_keepalive" => {
                 self.tcp_keepalive = g3_yaml::value::as_tcp_keepalive_config(v)
                     .context(format!("invalid tcp keepalive config value for key {k}"))?;
@@ -203,6 +399,17 @@ impl DirectFixedEscaperConfig {
                     .context(format!("invalid udp misc sock opts value for key {k}"))?;
                 Ok(())
             }
+            "udp_relay_mapping" => {
+                if let Yaml::Boolean(false) = v {
+                    self.udp_relay_mapping = None;
+                } else {
+                    let config = UdpRelayMappingConfig::parse_yaml(v).context(format!(
+                        "invalid udp relay mapping config value for key {k}"
+                    ))?;
+                    self.udp_relay_mapping = Some(config);
+                }
+                Ok(())
+            }
             "no_ipv4" => {
                 self.no_ipv4 = g3_yaml::value::as_bool(v)?;
                 Ok(())
@@ -241,6 +448,18 @@ impl DirectFixedEscaperConfig {
         if self.no_ipv4 && self.no_ipv6 {
             return Err(anyhow!("both ipv4 and ipv6 are disabled"));
         }
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "illumos",
+            target_os = "solaris"
+        ))]
+        if self.bind_device.is_some() && self.bind_interface.is_some() {
+            return Err(anyhow!(
+                "bind_device and bind_interface can not be set at the same time"
+            ));
+        }
         self.resolve_strategy
             .update_query_strategy(self.no_ipv4, self.no_ipv6)
             .context("found incompatible resolver strategy")?;
@@ -265,6 +484,43 @@ impl DirectFixedEscaperConfig {
     }
 }
 
+fn parse_udp_relay_bind(v: &Yaml) -> anyhow::Result<BindAddr> {
+    match v {
+        Yaml::Hash(map) => {
+            let mut ip4: Option<Ipv4Addr> = None;
+            let mut ip6: Option<Ipv6Addr> = None;
+            g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                "ipv4" | "v4" => {
+                    let ip = g3_yaml::value::as_ipv4addr(v)
+                        .context(format!("invalid ipv4 address value for key {k}"))?;
+                    ip4 = Some(ip);
+                    Ok(())
+                }
+                "ipv6" | "v6" => {
+                    let ip = g3_yaml::value::as_ipv6addr(v)
+                        .context(format!("invalid ipv6 address value for key {k}"))?;
+                    ip6 = Some(ip);
+                    Ok(())
+                }
+                _ => Err(anyhow!("invalid key {k}")),
+            })?;
+            match (ip4, ip6) {
+                (Some(ip4), Some(ip6)) => Ok(BindAddr::IpPair(ip4, ip6)),
+                (Some(ip4), None) => Ok(BindAddr::Ip(IpAddr::V4(ip4))),
+                (None, Some(ip6)) => Ok(BindAddr::Ip(IpAddr::V6(ip6))),
+                (None, None) => Err(anyhow!("neither ipv4 nor ipv6 address is set")),
+            }
+        }
+        Yaml::String(_) => {
+            let ip = g3_yaml::value::as_ipaddr(v)?;
+            Ok(BindAddr::Ip(ip))
+        }
+        _ => Err(anyhow!(
+            "yaml value type for 'udp relay bind' should be 'map' or 'ip address str'"
+        )),
+    }
+}
+
 impl EscaperConfig for DirectFixedEscaperConfig {
     fn name(&self) -> &NodeName {
         &self.name
@@ -298,3 +554,54 @@ impl EscaperConfig for DirectFixedEscaperConfig {
         self.shared_logger.as_ref().map(|s| s.as_str())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use yaml_rust::YamlLoader;
+
+    #[test]
+    fn egress_bind_map() {
+        let yaml = r#"
+- net: 192.0.2.0/24
+  bind: 198.51.100.1
+- network: 2001:db8::/32
+  bind_ip: 2001:db8::1
+"#;
+        let doc = YamlLoader::load_from_str(yaml).unwrap();
+        let rules = parse_egress_bind_map(&doc[0]).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].bind, IpAddr::from_str("198.51.100.1").unwrap());
+        assert!(
+            rules[1]
+                .net
+                .contains(IpAddr::from_str("2001:db8::2").unwrap())
+        );
+
+        let doc = YamlLoader::load_from_str("net: 192.0.2.0/24\nbind: 2001:db8::1").unwrap();
+        assert!(parse_egress_bind_map(&doc[0]).is_err());
+
+        let doc = YamlLoader::load_from_str("net: 192.0.2.0/24").unwrap();
+        assert!(parse_egress_bind_map(&doc[0]).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn bind_device() {
+        let parse = |s: &str| {
+            let doc = YamlLoader::load_from_str(s).unwrap();
+            DirectFixedEscaperConfig::parse(doc[0].as_hash().unwrap(), None)
+        };
+
+        let config = parse("name: test\nresolver: default\nvrf: lo\nbind_ip: 127.0.0.1").unwrap();
+        assert_eq!(config.bind_device.unwrap().name(), "lo");
+        assert!(config.bind_interface.is_none());
+
+        assert!(
+            parse("name: test\nresolver: default\nbind_device: lo\nbind_interface: lo").is_err()
+        );
+        assert!(parse("name: test\nresolver: default\nbind_device: no-such-device").is_err());
+    }
+}
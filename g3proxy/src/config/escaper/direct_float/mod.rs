@@ -17,6 +17,7 @@ use g3_types::net::{HappyEyeballsConfig, TcpKeepAliveConfig, TcpMiscSockOpts, Ud
 use g3_types::resolve::{QueryStrategy, ResolveRedirectionBuilder, ResolveStrategy};
 use g3_yaml::YamlDocPosition;
 
+use super::direct_fixed::UdpRelayMappingConfig;
 use super::{AnyEscaperConfig, EscaperConfig, EscaperConfigDiffAction, GeneralEscaperConfig};
 
 mod bind;
@@ -42,6 +43,8 @@ pub(crate) struct DirectFloatEscaperConfig {
     pub(crate) tcp_keepalive: TcpKeepAliveConfig,
     pub(crate) tcp_misc_opts: TcpMiscSockOpts,
     pub(crate) udp_misc_opts: UdpMiscSockOpts,
+    pub(crate) udp_relay_mapping: Option<UdpRelayMappingConfig>,
+    pub(crate) udp_relay_normalize_ipv4_mapped: bool,
     pub(crate) extra_metrics_tags: Option<Arc<MetricTagMap>>,
 }
 
@@ -64,6 +67,8 @@ impl DirectFloatEscaperConfig {
             tcp_keepalive: TcpKeepAliveConfig::default_enabled(),
             tcp_misc_opts: Default::default(),
             udp_misc_opts: Default::default(),
+            udp_relay_mapping: None,
+            udp_relay_normalize_ipv4_mapped: true,
             extra_metrics_tags: None,
         }
     }
@@ -135,6 +140,10 @@ impl DirectFloatEscaperConfig {
                 warn!("deprecated config key '{k}', please use 'udp_sock_speed_limit' instead");
                 self.set("udp_sock_speed_limit", v)
             }
+            "udp_relay_normalize_ipv4_mapped" => {
+                self.udp_relay_normalize_ipv4_mapped = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
             "no_ipv4" => {
                 self.no_ipv4 = g3_yaml::value::as_bool(v)?;
                 Ok(())
@@ -184,6 +193,17 @@ impl DirectFloatEscaperConfig {
                     .context(format!("invalid udp misc sock opts value for key {k}"))?;
                 Ok(())
             }
+            "udp_relay_mapping" => {
+                if let Yaml::Boolean(false) = v {
+                    self.udp_relay_mapping = None;
+                } else {
+                    let config = UdpRelayMappingConfig::parse_yaml(v).context(format!(
+                        "invalid udp relay mapping config value for key {k}"
+                    ))?;
+                    self.udp_relay_mapping = Some(config);
+                }
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
@@ -7,14 +7,16 @@ use std::collections::BTreeSet;
 use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::anyhow;
+use anyhow::{Context, anyhow};
 use slog::Logger;
 use yaml_rust::{Yaml, yaml};
 
 use g3_daemon::config::TopoMap;
 use g3_macros::AnyConfig;
 use g3_types::metrics::NodeName;
-use g3_types::net::{TcpConnectConfig, TcpSockSpeedLimitConfig, UdpSockSpeedLimitConfig};
+use g3_types::net::{
+    TcpConnectConfig, TcpSockSpeedLimitConfig, TlsCertStatus, UdpSockSpeedLimitConfig,
+};
 use g3_yaml::{HybridParser, YamlDocPosition};
 
 pub(crate) mod comply_audit;
@@ -52,6 +54,16 @@ pub(crate) enum EscaperConfigDiffAction {
     Reload,
 }
 
+impl EscaperConfigDiffAction {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            EscaperConfigDiffAction::NoAction => "NoAction",
+            EscaperConfigDiffAction::SpawnNew => "SpawnNew",
+            EscaperConfigDiffAction::Reload => "Reload",
+        }
+    }
+}
+
 pub(crate) trait EscaperConfig {
     fn name(&self) -> &NodeName;
     fn position(&self) -> Option<YamlDocPosition>;
@@ -73,6 +85,11 @@ pub(crate) trait EscaperConfig {
             crate::log::escape::get_logger(self.r#type(), self.name())
         }
     }
+
+    /// get the status of all the certificates configured in this escaper
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        Ok(Vec::new())
+    }
 }
 
 #[derive(Clone, Default, Eq, PartialEq)]
@@ -88,6 +105,7 @@ pub(crate) struct GeneralEscaperConfig {
 #[def_fn(dependent_escaper, Option<BTreeSet<NodeName>>)]
 #[def_fn(resolver, &NodeName)]
 #[def_fn(diff_action, &Self, EscaperConfigDiffAction)]
+#[def_fn(cert_status, anyhow::Result<Vec<TlsCertStatus>>)]
 pub(crate) enum AnyEscaperConfig {
     ComplyAudit(comply_audit::ComplyAuditEscaperConfig),
     DirectFixed(direct_fixed::DirectFixedEscaperConfig),
@@ -114,6 +132,7 @@ pub(crate) fn load_all(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
     let parser = HybridParser::new(conf_dir, g3_daemon::opts::config_file_extension());
     parser.foreach_map(v, |map, position| {
         let escaper = load_escaper(map, position)?;
+        check_certs(&escaper)?;
         if let Some(old_escaper) = registry::add(escaper) {
             Err(anyhow!(
                 "escaper with name {} already exists",
@@ -131,21 +150,32 @@ pub(crate) fn load_at_position(position: &YamlDocPosition) -> anyhow::Result<Any
     let doc = g3_yaml::load_doc(position)?;
     if let Yaml::Hash(map) = doc {
         let escaper = load_escaper(&map, Some(position.clone()))?;
-        let old_escaper = registry::add(escaper.clone());
-        if let Err(e) = build_topology_map() {
-            // rollback
-            match old_escaper {
-                Some(escaper) => {
-                    registry::add(escaper);
-                }
-                None => registry::del(escaper.name()),
+        add_single(escaper)
+    } else {
+        Err(anyhow!("yaml doc {position} is not a map"))
+    }
+}
+
+/// Load the escaper with the given name in the config file, all other escapers will be skipped
+pub(crate) fn load_named_in_file(path: &Path, name: &NodeName) -> anyhow::Result<AnyEscaperConfig> {
+    let escaper = super::load_named_in_file(path, "escaper", name.as_str(), load_escaper)?;
+    add_single(escaper)
+}
+
+fn add_single(escaper: AnyEscaperConfig) -> anyhow::Result<AnyEscaperConfig> {
+    check_certs(&escaper)?;
+    let old_escaper = registry::add(escaper.clone());
+    if let Err(e) = build_topology_map() {
+        // rollback
+        match old_escaper {
+            Some(escaper) => {
+                registry::add(escaper);
             }
-            Err(e)
-        } else {
-            Ok(escaper)
+            None => registry::del(escaper.name()),
         }
+        Err(e)
     } else {
-        Err(anyhow!("yaml doc {position} is not a map"))
+        Ok(escaper)
     }
 }
 
@@ -235,6 +265,15 @@ fn load_escaper(
     }
 }
 
+fn check_certs(escaper: &AnyEscaperConfig) -> anyhow::Result<()> {
+    let certs = escaper
+        .cert_status()
+        .context(format!("invalid certificate in escaper {}", escaper.name()))?;
+    g3_daemon::tls::check_and_record_certs(format!("escaper/{}", escaper.name()), certs).context(
+        format!("certificate check failed for escaper {}", escaper.name()),
+    )
+}
+
 fn build_topology_map() -> anyhow::Result<TopoMap> {
     let mut topo_map = TopoMap::default();
 
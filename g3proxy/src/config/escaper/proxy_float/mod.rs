@@ -24,7 +24,7 @@ use g3_types::metrics::{MetricTagMap, NodeName};
 ))]
 use g3_types::net::Interface;
 use g3_types::net::{
-    OpensslClientConfigBuilder, TcpKeepAliveConfig, TcpMiscSockOpts, UdpMiscSockOpts,
+    OpensslClientConfigBuilder, TcpKeepAliveConfig, TcpMiscSockOpts, TlsCertStatus, UdpMiscSockOpts,
 };
 use g3_yaml::YamlDocPosition;
 
@@ -263,4 +263,8 @@ impl EscaperConfig for ProxyFloatEscaperConfig {
     fn dependent_escaper(&self) -> Option<BTreeSet<NodeName>> {
         None
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        self.tls_config.cert_status()
+    }
 }
@@ -25,7 +25,7 @@ use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::net::Interface;
 use g3_types::net::{
     HappyEyeballsConfig, Host, HttpForwardCapability, OpensslClientConfigBuilder,
-    ProxyProtocolVersion, TcpKeepAliveConfig, TcpMiscSockOpts, WeightedUpstreamAddr,
+    ProxyProtocolVersion, TcpKeepAliveConfig, TcpMiscSockOpts, TlsCertStatus, WeightedUpstreamAddr,
 };
 use g3_types::resolve::{QueryStrategy, ResolveStrategy};
 use g3_yaml::YamlDocPosition;
@@ -388,4 +388,8 @@ impl EscaperConfig for ProxyHttpsEscaperConfig {
     fn shared_logger(&self) -> Option<&str> {
         self.shared_logger.as_ref().map(|s| s.as_str())
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        self.tls_config.cert_status()
+    }
 }
@@ -31,6 +31,7 @@ use g3_types::net::{
 use g3_types::resolve::{QueryStrategy, ResolveStrategy};
 use g3_yaml::YamlDocPosition;
 
+use super::direct_fixed::UdpRelayMappingConfig;
 use super::{AnyEscaperConfig, EscaperConfig, EscaperConfigDiffAction, GeneralEscaperConfig};
 
 const ESCAPER_CONFIG_TYPE: &str = "ProxySocks5";
@@ -67,6 +68,7 @@ pub(crate) struct ProxySocks5EscaperConfig {
     pub(crate) peer_negotiation_timeout: Duration,
     transmute_udp_peer_ip: Option<FxHashMap<IpAddr, IpAddr>>,
     pub(crate) end_on_control_closed: bool,
+    pub(crate) udp_relay_mapping: Option<UdpRelayMappingConfig>,
     pub(crate) extra_metrics_tags: Option<Arc<MetricTagMap>>,
 }
 
@@ -103,6 +105,7 @@ impl ProxySocks5EscaperConfig {
             peer_negotiation_timeout: Duration::from_secs(10),
             transmute_udp_peer_ip: None,
             end_on_control_closed: false,
+            udp_relay_mapping: None,
             extra_metrics_tags: None,
         }
     }
@@ -268,6 +271,17 @@ impl ProxySocks5EscaperConfig {
                 self.end_on_control_closed = g3_yaml::value::as_bool(v)?;
                 Ok(())
             }
+            "udp_relay_mapping" => {
+                if let Yaml::Boolean(false) = v {
+                    self.udp_relay_mapping = None;
+                } else {
+                    let config = UdpRelayMappingConfig::parse_yaml(v).context(format!(
+                        "invalid udp relay mapping config value for key {k}"
+                    ))?;
+                    self.udp_relay_mapping = Some(config);
+                }
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
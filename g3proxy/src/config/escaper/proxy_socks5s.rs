@@ -26,7 +26,7 @@ use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::net::Interface;
 use g3_types::net::{
     HappyEyeballsConfig, Host, OpensslClientConfigBuilder, SocksAuth, TcpKeepAliveConfig,
-    TcpMiscSockOpts, UdpMiscSockOpts, WeightedUpstreamAddr,
+    TcpMiscSockOpts, TlsCertStatus, UdpMiscSockOpts, WeightedUpstreamAddr,
 };
 use g3_types::resolve::{QueryStrategy, ResolveStrategy};
 use g3_yaml::YamlDocPosition;
@@ -407,4 +407,8 @@ impl EscaperConfig for ProxySocks5sEscaperConfig {
     fn shared_logger(&self) -> Option<&str> {
         self.shared_logger.as_ref().map(|s| s.as_str())
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        self.tls_config.cert_status()
+    }
 }
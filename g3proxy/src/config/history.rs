@@ -0,0 +1,72 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+use g3_types::sync::GlobalInit;
+
+const DEFAULT_RETENTION: usize = 8;
+
+static CONFIG_HISTORY_CONFIG: GlobalInit<ConfigHistoryConfig> =
+    GlobalInit::new(ConfigHistoryConfig::new());
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ConfigHistoryConfig {
+    /// the max number of config generations to keep in memory
+    pub(crate) retention: usize,
+    /// the directory to write the summary of each config generation to
+    pub(crate) state_dir: Option<PathBuf>,
+}
+
+impl ConfigHistoryConfig {
+    const fn new() -> Self {
+        ConfigHistoryConfig {
+            retention: DEFAULT_RETENTION,
+            state_dir: None,
+        }
+    }
+
+    fn parse(v: &Yaml, conf_dir: &Path) -> anyhow::Result<Self> {
+        let mut config = ConfigHistoryConfig::new();
+        match v {
+            Yaml::Hash(map) => {
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "retention" => {
+                        config.retention = g3_yaml::value::as_usize(v)?;
+                        Ok(())
+                    }
+                    "state_dir" => {
+                        let dir = g3_yaml::value::as_dir_path(v, conf_dir, true)
+                            .context(format!("invalid directory path value for key {k}"))?;
+                        config.state_dir = Some(dir);
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+            }
+            Yaml::Integer(_) => {
+                config.retention = g3_yaml::value::as_usize(v)?;
+            }
+            _ => return Err(anyhow!("invalid value type")),
+        }
+        if config.retention == 0 {
+            return Err(anyhow!("retention should not be zero"));
+        }
+        Ok(config)
+    }
+}
+
+pub(crate) fn load(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
+    let config = ConfigHistoryConfig::parse(v, conf_dir)?;
+    CONFIG_HISTORY_CONFIG.set(config);
+    Ok(())
+}
+
+pub(crate) fn get() -> &'static ConfigHistoryConfig {
+    CONFIG_HISTORY_CONFIG.as_ref()
+}
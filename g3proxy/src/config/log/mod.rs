@@ -3,14 +3,20 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::collections::BTreeMap;
 use std::path::Path;
+use std::str::FromStr;
 
 use anyhow::{Context, anyhow};
 use yaml_rust::Yaml;
 
+use g3_daemon::log::task::TaskLogFieldNames;
 use g3_daemon::log::{LogConfig, LogConfigContainer};
 use g3_types::sync::GlobalInit;
 
+mod ptr_lookup;
+pub(crate) use ptr_lookup::TaskLogPtrLookupConfig;
+
 static RESOLVE_DEFAULT_LOG_CONFIG_CONTAINER: GlobalInit<LogConfigContainer> =
     GlobalInit::new(LogConfigContainer::new());
 static ESCAPE_DEFAULT_LOG_CONFIG_CONTAINER: GlobalInit<LogConfigContainer> =
@@ -19,6 +25,8 @@ static AUDIT_DEFAULT_LOG_CONFIG_CONTAINER: GlobalInit<LogConfigContainer> =
     GlobalInit::new(LogConfigContainer::new());
 static TASK_DEFAULT_LOG_CONFIG_CONTAINER: GlobalInit<LogConfigContainer> =
     GlobalInit::new(LogConfigContainer::new());
+static SHARED_LOGGER_PTR_LOOKUP_CONFIG: GlobalInit<BTreeMap<String, TaskLogPtrLookupConfig>> =
+    GlobalInit::new(BTreeMap::new());
 
 pub(crate) fn load(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
     let mut default_log_config: Option<LogConfig> = None;
@@ -71,6 +79,29 @@ pub(crate) fn load(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
                     TASK_DEFAULT_LOG_CONFIG_CONTAINER.with_mut(|l| l.set(config));
                     Ok(())
                 }
+                "task_field_names" => {
+                    let s = g3_yaml::value::as_string(v)?;
+                    let names = TaskLogFieldNames::from_str(&s)
+                        .map_err(|_| anyhow!("invalid task log field names value {s}"))?;
+                    g3_daemon::log::task::set_field_names(names);
+                    Ok(())
+                }
+                "shared_logger_ptr_lookup" => {
+                    let mut ptr_map = BTreeMap::new();
+                    if let Yaml::Hash(map) = v {
+                        g3_yaml::foreach_kv(map, |name, v| {
+                            let config = TaskLogPtrLookupConfig::parse_yaml(v).context(format!(
+                                "invalid ptr lookup config value for shared logger {name}"
+                            ))?;
+                            ptr_map.insert(name.to_string(), config);
+                            Ok(())
+                        })?;
+                    } else {
+                        return Err(anyhow!("invalid map value for key {k}"));
+                    }
+                    SHARED_LOGGER_PTR_LOOKUP_CONFIG.set(ptr_map);
+                    Ok(())
+                }
                 _ => Err(anyhow!("invalid key {k}")),
             })?;
         }
@@ -109,3 +140,7 @@ pub(crate) fn get_task_default_config() -> LogConfig {
         .as_ref()
         .get(crate::build::PKG_NAME)
 }
+
+pub(crate) fn get_shared_logger_ptr_lookup_config(name: &str) -> Option<TaskLogPtrLookupConfig> {
+    SHARED_LOGGER_PTR_LOOKUP_CONFIG.as_ref().get(name).cloned()
+}
@@ -0,0 +1,127 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+const DEFAULT_MAX_ENTRIES: NonZeroUsize = NonZeroUsize::new(4096).unwrap();
+
+/// The config to enrich the client address in task logs with the DNS PTR name
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct TaskLogPtrLookupConfig {
+    pub(crate) resolver: SocketAddr,
+    pub(crate) request_timeout: Duration,
+    pub(crate) ttl: Duration,
+    pub(crate) negative_ttl: Duration,
+    pub(crate) max_entries: NonZeroUsize,
+}
+
+impl TaskLogPtrLookupConfig {
+    fn new(resolver: SocketAddr) -> Self {
+        TaskLogPtrLookupConfig {
+            resolver,
+            request_timeout: Duration::from_secs(2),
+            ttl: Duration::from_secs(3600),
+            negative_ttl: Duration::from_secs(300),
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        match v {
+            Yaml::Hash(map) => {
+                let v = g3_yaml::hash_get_required(map, "resolver")?;
+                let resolver = g3_yaml::value::as_env_sockaddr(v)
+                    .context("invalid socket address value for key resolver")?;
+                let mut config = TaskLogPtrLookupConfig::new(resolver);
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "resolver" => Ok(()),
+                    "request_timeout" | "timeout" => {
+                        config.request_timeout = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "ttl" | "cache_ttl" => {
+                        config.ttl = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "negative_ttl" => {
+                        config.negative_ttl = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "max_entries" | "cache_size" => {
+                        config.max_entries = g3_yaml::value::as_nonzero_usize(v)
+                            .context(format!("invalid nonzero usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+                config.check()?;
+                Ok(config)
+            }
+            Yaml::String(_) => {
+                let resolver = g3_yaml::value::as_env_sockaddr(v)
+                    .context("invalid socket address value for the resolver")?;
+                Ok(TaskLogPtrLookupConfig::new(resolver))
+            }
+            _ => Err(anyhow!(
+                "yaml value type for task log ptr lookup config should be 'string' or 'map'"
+            )),
+        }
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.request_timeout.is_zero() {
+            return Err(anyhow!("request timeout should not be zero"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<TaskLogPtrLookupConfig> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        TaskLogPtrLookupConfig::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_ok() {
+        let config = parse("127.0.0.1:53").unwrap();
+        assert_eq!(config.resolver, SocketAddr::from(([127, 0, 0, 1], 53)));
+        assert_eq!(config.max_entries, DEFAULT_MAX_ENTRIES);
+
+        let config = parse(
+            r#"
+            resolver: 127.0.0.1:5353
+            ttl: 10m
+            negative_ttl: 30s
+            max_entries: 16
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.resolver, SocketAddr::from(([127, 0, 0, 1], 5353)));
+        assert_eq!(config.ttl, Duration::from_secs(600));
+        assert_eq!(config.negative_ttl, Duration::from_secs(30));
+        assert_eq!(config.max_entries.get(), 16);
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("ttl: 10m").is_err());
+        assert!(parse("resolver: 127.0.0.1:53\nmax_entries: 0").is_err());
+        assert!(parse("resolver: 127.0.0.1:53\nrequest_timeout: 0").is_err());
+        assert!(parse("resolver: 127.0.0.1:53\nunknown: 1").is_err());
+    }
+}
@@ -3,10 +3,13 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::cell::RefCell;
 use std::path::Path;
 
-use anyhow::anyhow;
-use yaml_rust::{Yaml, yaml};
+use anyhow::{Context, anyhow};
+use yaml_rust::{Yaml, YamlLoader, yaml};
+
+use g3_yaml::{HybridParser, YamlDocPosition};
 
 mod graphviz;
 pub use graphviz::graphviz_graph;
@@ -20,10 +23,14 @@ pub use plantuml::plantuml_graph;
 pub(crate) mod audit;
 pub(crate) mod auth;
 pub(crate) mod escaper;
+pub(crate) mod history;
 pub(crate) mod log;
 pub(crate) mod resolver;
 pub(crate) mod server;
 
+mod check;
+pub(crate) use check::{ServerCheckAction, check_file};
+
 pub fn load() -> anyhow::Result<&'static Path> {
     let config_file =
         g3_daemon::opts::config_file().ok_or_else(|| anyhow!("no config file set"))?;
@@ -37,7 +44,114 @@ pub fn load() -> anyhow::Result<&'static Path> {
     Ok(config_file)
 }
 
+/// Check the config file and print the report of all servers, without loading it
+pub fn dry_run() -> anyhow::Result<()> {
+    let config_file =
+        g3_daemon::opts::config_file().ok_or_else(|| anyhow!("no config file set"))?;
+
+    let report = check_file(config_file);
+    let content = serde_json::to_string_pretty(&report.to_json())
+        .context("failed to encode the check report")?;
+    println!("{content}");
+    if report.has_error() {
+        Err(anyhow!(
+            "errors found in config file {}",
+            config_file.display()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Get the json schema of the config keys that have been registered
+pub(crate) fn schema() -> serde_json::Value {
+    serde_json::json!({
+        "server": server::schema(),
+    })
+}
+
+/// Print the json schema of the config keys, without loading any config file
+pub fn dump_schema() -> anyhow::Result<()> {
+    let content =
+        serde_json::to_string_pretty(&schema()).context("failed to encode the config schema")?;
+    println!("{content}");
+    Ok(())
+}
+
+/// Find and load the entry with the given name under the top level `key` in a config file.
+///
+/// The docs in the file may be main config docs, or included docs that contain only the entries.
+/// All other entries are skipped without being parsed, so errors in them won't affect this one.
+fn load_named_in_file<T, F>(path: &Path, key: &str, name: &str, load: F) -> anyhow::Result<T>
+where
+    F: Fn(&yaml::Hash, Option<YamlDocPosition>) -> anyhow::Result<T>,
+{
+    let conf = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read config file {}: {e}", path.display()))?;
+    let docs = YamlLoader::load_from_str(&conf)
+        .map_err(|e| anyhow!("invalid yaml file {}: {e}", path.display()))?;
+    load_named_in_docs(&docs, path, key, name, load)
+}
+
+fn load_named_in_docs<T, F>(
+    docs: &[Yaml],
+    path: &Path,
+    key: &str,
+    name: &str,
+    load: F,
+) -> anyhow::Result<T>
+where
+    F: Fn(&yaml::Hash, Option<YamlDocPosition>) -> anyhow::Result<T>,
+{
+    let conf_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let parser = HybridParser::new(conf_dir, g3_daemon::opts::config_file_extension());
+    let found = RefCell::new(None);
+    let check_map = |map: &yaml::Hash, position: Option<YamlDocPosition>| -> anyhow::Result<()> {
+        if found.borrow().is_none()
+            && g3_yaml::hash_get_required_str(map, "name").ok() == Some(name)
+        {
+            *found.borrow_mut() = Some(load(map, position));
+        }
+        Ok(())
+    };
+
+    for (i, doc) in docs.iter().enumerate() {
+        let Yaml::Hash(map) = doc else {
+            continue;
+        };
+        let Some(v) = map.iter().find_map(|(k, v)| match k {
+            Yaml::String(k) if g3_yaml::key::normalize(k) == key => Some(v),
+            _ => None,
+        }) else {
+            // this may be an included doc
+            let position = YamlDocPosition {
+                path: path.to_path_buf(),
+                index: i,
+            };
+            check_map(map, Some(position))?;
+            continue;
+        };
+        let entries: Vec<&Yaml> = match v {
+            Yaml::Array(seq) => seq.iter().collect(),
+            _ => vec![v],
+        };
+        for entry in entries {
+            // the invalid entries are ignored, as they are not the one we are looking for
+            let entry = Yaml::Array(vec![entry.clone()]);
+            let _ = parser.foreach_map(&entry, &check_map);
+        }
+    }
+
+    found.into_inner().ok_or_else(|| {
+        anyhow!(
+            "no {key} with name {name} found in config file {}",
+            path.display()
+        )
+    })?
+}
+
 fn clear_all() {
+    g3_daemon::tls::clear_recorded_certs();
     escaper::clear();
     audit::clear();
     auth::clear();
@@ -67,7 +181,9 @@ fn reload_doc(map: &yaml::Hash) -> anyhow::Result<()> {
     let conf_dir =
         g3_daemon::opts::config_dir().ok_or_else(|| anyhow!("no valid config dir has been set"))?;
     g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
-        "runtime" | "worker" | "log" | "stat" | "controller" => Ok(()),
+        "runtime" | "worker" | "log" | "stat" | "controller" | "config_history" | "client_ban" => {
+            Ok(())
+        }
         "escaper" => escaper::load_all(v, conf_dir),
         "server" => server::load_all(v, conf_dir),
         "resolver" => resolver::load_all(v, conf_dir),
@@ -87,6 +203,8 @@ fn load_doc(map: &yaml::Hash) -> anyhow::Result<()> {
         "log" => log::load(v, conf_dir),
         "stat" => g3_daemon::stat::config::load(v, crate::build::PKG_NAME),
         "controller" => g3_daemon::control::config::load(v),
+        "client_ban" => g3_daemon::ban::config::load(v),
+        "config_history" => history::load(v, conf_dir),
         "escaper" => escaper::load_all(v, conf_dir),
         "server" => server::load_all(v, conf_dir),
         "resolver" => resolver::load_all(v, conf_dir),
@@ -96,3 +214,86 @@ fn load_doc(map: &yaml::Hash) -> anyhow::Result<()> {
     })?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    use server::AnyServerConfig;
+
+    fn load_server(docs: &[Yaml], name: &str) -> anyhow::Result<AnyServerConfig> {
+        let path = Path::new("/etc/g3proxy/main.yaml");
+        load_named_in_docs(docs, path, "server", name, server::load_server)
+    }
+
+    #[test]
+    fn load_named_server() {
+        let running = YamlLoader::load_from_str(
+            r#"
+- name: stream1
+  type: tcp_stream
+  escaper: default
+  listen: 127.0.0.1:8080
+  upstream: 127.0.0.1:80
+- name: stream2
+  type: tcp_stream
+  escaper: default
+  listen: 127.0.0.1:8081
+  upstream: 127.0.0.1:80
+- name: close
+  type: dummy_close
+"#,
+        )
+        .unwrap();
+        let running: Vec<AnyServerConfig> = running[0]
+            .as_vec()
+            .unwrap()
+            .iter()
+            .map(|v| server::load_server(v.as_hash().unwrap(), None).unwrap())
+            .collect();
+
+        let docs = YamlLoader::load_from_str(
+            r#"
+server:
+  - name: stream1
+    type: tcp_stream
+    escaper: default
+    listen: 127.0.0.1:9080
+    upstream: 127.0.0.1:80
+  - name: stream2
+    type: tcp_stream
+    escaper: default
+    listen_addr: 127.0.0.1:9081
+    upstream: 127.0.0.1:80
+  - name: close
+    type: dummy_close
+---
+name: close2
+type: dummy_close
+"#,
+        )
+        .unwrap();
+
+        // only the named one is reloaded, the error in the others won't affect it
+        let stream1 = load_server(&docs, "stream1").unwrap();
+        assert_eq!(
+            running[0].diff_action(&stream1).as_str(),
+            "ReloadAndRespawn"
+        );
+        let close = load_server(&docs, "close").unwrap();
+        assert_eq!(running[2].diff_action(&close).as_str(), "NoAction");
+
+        assert!(load_server(&docs, "stream2").is_err());
+        assert!(load_server(&docs, "stream3").is_err());
+
+        let close2 = load_server(&docs, "close2").unwrap();
+        assert_eq!(
+            close2.position(),
+            Some(YamlDocPosition {
+                path: PathBuf::from("/etc/g3proxy/main.yaml"),
+                index: 1,
+            })
+        );
+    }
+}
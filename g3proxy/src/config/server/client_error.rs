@@ -0,0 +1,221 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+const CLASS_COUNT: usize = 6;
+
+/// The class of task failures whose client visible behavior can be configured
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ClientErrorClass {
+    UpstreamConnectRefused,
+    UpstreamConnectTimeout,
+    IngressDenied,
+    AdaptationFailed,
+    IdleKilled,
+    LifetimeExpired,
+}
+
+impl FromStr for ClientErrorClass {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upstream_connect_refused" | "connect_refused" => {
+                Ok(ClientErrorClass::UpstreamConnectRefused)
+            }
+            "upstream_connect_timeout" | "connect_timeout" => {
+                Ok(ClientErrorClass::UpstreamConnectTimeout)
+            }
+            "ingress_denied" | "denied" => Ok(ClientErrorClass::IngressDenied),
+            "adaptation_failed" => Ok(ClientErrorClass::AdaptationFailed),
+            "idle_killed" | "idle" => Ok(ClientErrorClass::IdleKilled),
+            "lifetime_expired" => Ok(ClientErrorClass::LifetimeExpired),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What the client will see when the task failed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ClientErrorAction {
+    /// close the connection without sending anything
+    Close,
+    /// reset the connection
+    Reset,
+    /// send a socks reply with this code, only available before the socks reply has been sent
+    SocksReply(u8),
+}
+
+impl ClientErrorAction {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        match v {
+            Yaml::String(s) => match g3_yaml::key::normalize(s).as_str() {
+                "close" => Ok(ClientErrorAction::Close),
+                "reset" | "rst" => Ok(ClientErrorAction::Reset),
+                _ => Err(anyhow!("invalid client error action {s}")),
+            },
+            Yaml::Hash(map) => {
+                let mut action = None;
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "socks_reply" | "socks5_reply" => {
+                        let code = g3_yaml::value::as_u8(v)
+                            .context(format!("invalid u8 value for key {k}"))?;
+                        if code == 0 {
+                            return Err(anyhow!("socks reply code should not be 0"));
+                        }
+                        action = Some(ClientErrorAction::SocksReply(code));
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+                action.ok_or_else(|| anyhow!("no client error action set"))
+            }
+            _ => Err(anyhow!(
+                "yaml value type for client error action should be 'string' or 'map'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ClientErrorAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientErrorAction::Close => f.write_str("close"),
+            ClientErrorAction::Reset => f.write_str("reset"),
+            ClientErrorAction::SocksReply(code) => write!(f, "socks_reply:{code}"),
+        }
+    }
+}
+
+/// The client error actions set for each class of failure
+///
+/// The server specific default behavior will be used for the classes not set here.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ClientErrorActionMap {
+    actions: [Option<ClientErrorAction>; CLASS_COUNT],
+}
+
+impl ClientErrorActionMap {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for client error actions should be 'map'"
+            ));
+        };
+
+        let mut actions = ClientErrorActionMap::default();
+        g3_yaml::foreach_kv(map, |k, v| {
+            let class = ClientErrorClass::from_str(&g3_yaml::key::normalize(k))
+                .map_err(|_| anyhow!("invalid client error class {k}"))?;
+            let action = ClientErrorAction::parse_yaml(v)
+                .context(format!("invalid client error action value for key {k}"))?;
+            actions.set(class, action);
+            Ok(())
+        })?;
+        Ok(actions)
+    }
+
+    pub(crate) fn set(&mut self, class: ClientErrorClass, action: ClientErrorAction) {
+        self.actions[class as usize] = Some(action);
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, class: ClientErrorClass) -> Option<ClientErrorAction> {
+        self.actions[class as usize]
+    }
+
+    /// Get the action for failures after the client stream has been established
+    ///
+    /// The socks reply action will fall back to close, as the socks reply has been sent.
+    pub(crate) fn get_established(&self, class: ClientErrorClass) -> Option<ClientErrorAction> {
+        self.get(class).map(|action| match action {
+            ClientErrorAction::SocksReply(_) => ClientErrorAction::Close,
+            action => action,
+        })
+    }
+
+    /// Check if the socks reply action is used, which is only available in socks servers
+    pub(crate) fn check_no_socks_reply(&self) -> anyhow::Result<()> {
+        if self
+            .actions
+            .iter()
+            .any(|a| matches!(a, Some(ClientErrorAction::SocksReply(_))))
+        {
+            Err(anyhow!("socks reply client error action is not supported"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<ClientErrorActionMap> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        ClientErrorActionMap::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_ok() {
+        let map = parse(
+            "upstream_connect_refused: reset\nconnect_timeout: close\n\
+             ingress-denied: {socks_reply: 2}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            map.get(ClientErrorClass::UpstreamConnectRefused),
+            Some(ClientErrorAction::Reset)
+        );
+        assert_eq!(
+            map.get(ClientErrorClass::UpstreamConnectTimeout),
+            Some(ClientErrorAction::Close)
+        );
+        assert_eq!(
+            map.get(ClientErrorClass::IngressDenied),
+            Some(ClientErrorAction::SocksReply(2))
+        );
+        assert!(map.get(ClientErrorClass::IdleKilled).is_none());
+        assert!(map.check_no_socks_reply().is_err());
+
+        assert_eq!(
+            map.get_established(ClientErrorClass::IngressDenied),
+            Some(ClientErrorAction::Close)
+        );
+        assert_eq!(
+            map.get_established(ClientErrorClass::UpstreamConnectRefused),
+            Some(ClientErrorAction::Reset)
+        );
+        assert!(map.get_established(ClientErrorClass::IdleKilled).is_none());
+
+        let map = parse("idle_killed: reset\n").unwrap();
+        assert!(map.check_no_socks_reply().is_ok());
+        assert_eq!(
+            map.get(ClientErrorClass::IdleKilled).unwrap().to_string(),
+            "reset"
+        );
+        assert_eq!(
+            ClientErrorAction::SocksReply(5).to_string(),
+            "socks_reply:5"
+        );
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("reset").is_err());
+        assert!(parse("no_such_class: close\n").is_err());
+        assert!(parse("idle_killed: drop\n").is_err());
+        assert!(parse("idle_killed: {socks_reply: 0}\n").is_err());
+        assert!(parse("idle_killed: {socks_reply: 256}\n").is_err());
+        assert!(parse("idle_killed: {}\n").is_err());
+    }
+}
@@ -23,13 +23,13 @@ use g3_types::acl_set::AclDstHostRuleSetBuilder;
 use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::net::{
     Host, HttpKeepAliveConfig, HttpServerId, OpensslClientConfigBuilder, RustlsServerConfigBuilder,
-    TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig,
+    TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig, TlsCertStatus,
 };
 use g3_yaml::YamlDocPosition;
 
 use super::{
     AnyServerConfig, IDLE_CHECK_DEFAULT_DURATION, IDLE_CHECK_DEFAULT_MAX_COUNT,
-    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction,
+    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction, TaskLogSampleConfig,
 };
 
 const SERVER_CONFIG_TYPE: &str = "HttpProxy";
@@ -75,10 +75,12 @@ pub(crate) struct HttpProxyServerConfig {
     pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
     pub(crate) timeout: HttpProxyServerTimeoutConfig,
     pub(crate) task_idle_check_duration: Duration,
+    pub(crate) task_idle_check_jitter: u8,
     pub(crate) task_idle_max_count: usize,
     pub(crate) flush_task_log_on_created: bool,
     pub(crate) flush_task_log_on_connected: bool,
     pub(crate) task_log_flush_interval: Option<Duration>,
+    pub(crate) task_log_sample: TaskLogSampleConfig,
     pub(crate) tcp_copy: StreamCopyConfig,
     pub(crate) tcp_misc_opts: TcpMiscSockOpts,
     pub(crate) req_hdr_max_size: usize,
@@ -123,10 +125,12 @@ impl HttpProxyServerConfig {
             tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
             timeout: HttpProxyServerTimeoutConfig::default(),
             task_idle_check_duration: IDLE_CHECK_DEFAULT_DURATION,
+            task_idle_check_jitter: 0,
             task_idle_max_count: IDLE_CHECK_DEFAULT_MAX_COUNT,
             flush_task_log_on_created: false,
             flush_task_log_on_connected: false,
             task_log_flush_interval: None,
+            task_log_sample: TaskLogSampleConfig::default(),
             tcp_copy: Default::default(),
             tcp_misc_opts: Default::default(),
             req_hdr_max_size: 65536, // 64KiB
@@ -299,6 +303,12 @@ impl HttpProxyServerConfig {
                     .context(format!("invalid humanize duration value for key {k}"))?;
                 Ok(())
             }
+            "task_idle_check_jitter" => {
+                let jitter =
+                    g3_yaml::value::as_u8(v).context(format!("invalid u8 value for key {k}"))?;
+                self.task_idle_check_jitter = jitter.min(100);
+                Ok(())
+            }
             "task_idle_max_count" => {
                 self.task_idle_max_count = g3_yaml::value::as_usize(v)
                     .context(format!("invalid usize value for key {k}"))?;
@@ -318,6 +328,16 @@ impl HttpProxyServerConfig {
                 self.task_log_flush_interval = Some(interval);
                 Ok(())
             }
+            "task_log_sample_ratio" => self
+                .task_log_sample
+                .parse_ratio(v)
+                .context(format!("invalid ratio value for key {k}")),
+            "always_log_errors" => {
+                let enable = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                self.task_log_sample.set_always_log_errors(enable);
+                Ok(())
+            }
             "req_header_recv_timeout" => {
                 self.timeout.recv_req_header = g3_yaml::humanize::as_duration(v)
                     .context(format!("invalid humanize duration value for key {k}"))?;
@@ -497,4 +517,13 @@ impl ServerConfig for HttpProxyServerConfig {
     fn task_max_idle_count(&self) -> usize {
         self.task_idle_max_count
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::new();
+        if let Some(builder) = &self.server_tls_config {
+            all.extend(builder.cert_status()?);
+        }
+        all.extend(self.client_tls_config.cert_status()?);
+        Ok(all)
+    }
 }
@@ -6,7 +6,9 @@
 use anyhow::{Context, anyhow};
 use yaml_rust::Yaml;
 
-use g3_types::net::{Host, OpensslClientConfigBuilder, RustlsServerConfigBuilder, UpstreamAddr};
+use g3_types::net::{
+    Host, OpensslClientConfigBuilder, RustlsServerConfigBuilder, TlsCertStatus, UpstreamAddr,
+};
 use g3_yaml::{YamlDocPosition, YamlMapCallback};
 
 #[derive(Debug, PartialEq)]
@@ -32,6 +34,17 @@ impl HttpHostConfig {
     pub(crate) fn upstream(&self) -> &UpstreamAddr {
         &self.upstream
     }
+
+    pub(crate) fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::new();
+        if let Some(builder) = &self.tls_server_builder {
+            all.extend(builder.cert_status()?);
+        }
+        if let Some(builder) = &self.tls_client_builder {
+            all.extend(builder.cert_status()?);
+        }
+        Ok(all)
+    }
 }
 
 impl YamlMapCallback for HttpHostConfig {
@@ -3,6 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::collections::HashSet;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 use std::time::Duration;
@@ -18,14 +19,14 @@ use g3_types::acl::AclNetworkRuleBuilder;
 use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::net::{
     HttpForwardedHeaderType, HttpKeepAliveConfig, HttpServerId, RustlsServerConfigBuilder,
-    TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig,
+    TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig, TlsCertStatus,
 };
 use g3_types::route::HostMatch;
 use g3_yaml::YamlDocPosition;
 
 use super::{
     AnyServerConfig, IDLE_CHECK_DEFAULT_DURATION, IDLE_CHECK_DEFAULT_MAX_COUNT,
-    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction,
+    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction, TaskLogSampleConfig,
 };
 
 mod host;
@@ -66,10 +67,12 @@ pub(crate) struct HttpRProxyServerConfig {
     pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
     pub(crate) timeout: HttpRProxyServerTimeoutConfig,
     pub(crate) task_idle_check_duration: Duration,
+    pub(crate) task_idle_check_jitter: u8,
     pub(crate) task_idle_max_count: usize,
     pub(crate) flush_task_log_on_created: bool,
     pub(crate) flush_task_log_on_connected: bool,
     pub(crate) task_log_flush_interval: Option<Duration>,
+    pub(crate) task_log_sample: TaskLogSampleConfig,
     pub(crate) tcp_copy: StreamCopyConfig,
     pub(crate) tcp_misc_opts: TcpMiscSockOpts,
     pub(crate) req_hdr_max_size: usize,
@@ -106,10 +109,12 @@ impl HttpRProxyServerConfig {
             tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
             timeout: HttpRProxyServerTimeoutConfig::default(),
             task_idle_check_duration: IDLE_CHECK_DEFAULT_DURATION,
+            task_idle_check_jitter: 0,
             task_idle_max_count: IDLE_CHECK_DEFAULT_MAX_COUNT,
             flush_task_log_on_created: false,
             flush_task_log_on_connected: false,
             task_log_flush_interval: None,
+            task_log_sample: TaskLogSampleConfig::default(),
             tcp_copy: Default::default(),
             tcp_misc_opts: Default::default(),
             req_hdr_max_size: 65536, // 64KiB
@@ -228,6 +233,12 @@ impl HttpRProxyServerConfig {
                     .context(format!("invalid humanize duration value for key {k}"))?;
                 Ok(())
             }
+            "task_idle_check_jitter" => {
+                let jitter =
+                    g3_yaml::value::as_u8(v).context(format!("invalid u8 value for key {k}"))?;
+                self.task_idle_check_jitter = jitter.min(100);
+                Ok(())
+            }
             "task_idle_max_count" => {
                 self.task_idle_max_count = g3_yaml::value::as_usize(v)
                     .context(format!("invalid usize value for key {k}"))?;
@@ -247,6 +258,16 @@ impl HttpRProxyServerConfig {
                 self.task_log_flush_interval = Some(interval);
                 Ok(())
             }
+            "task_log_sample_ratio" => self
+                .task_log_sample
+                .parse_ratio(v)
+                .context(format!("invalid ratio value for key {k}")),
+            "always_log_errors" => {
+                let enable = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                self.task_log_sample.set_always_log_errors(enable);
+                Ok(())
+            }
             "req_header_recv_timeout" => {
                 self.timeout.recv_req_header = g3_yaml::humanize::as_duration(v)
                     .context(format!("invalid humanize duration value for key {k}"))?;
@@ -430,4 +451,18 @@ impl ServerConfig for HttpRProxyServerConfig {
     fn task_max_idle_count(&self) -> usize {
         self.task_idle_max_count
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::new();
+        if let Some(builder) = &self.global_tls_server {
+            all.extend(builder.cert_status()?);
+        }
+        let mut visited = HashSet::new();
+        for host in self.hosts.values() {
+            if visited.insert(Arc::as_ptr(host)) {
+                all.extend(host.cert_status()?);
+            }
+        }
+        Ok(all)
+    }
 }
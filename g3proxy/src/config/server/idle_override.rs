@@ -0,0 +1,246 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::fmt;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use ip_network::IpNetwork;
+use yaml_rust::Yaml;
+
+use g3_types::metrics::NodeName;
+
+use super::IDLE_CHECK_MAXIMUM_DURATION;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum TaskIdleOverrideMatch {
+    Network(IpNetwork),
+    UserGroup(NodeName),
+}
+
+impl fmt::Display for TaskIdleOverrideMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskIdleOverrideMatch::Network(net) => write!(f, "net:{net}"),
+            TaskIdleOverrideMatch::UserGroup(name) => write!(f, "user_group:{name}"),
+        }
+    }
+}
+
+/// Alternative task idle check values for tasks from a client network or a user group
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct TaskIdleOverride {
+    match_rule: TaskIdleOverrideMatch,
+    check_duration: Option<Duration>,
+    max_count: Option<usize>,
+}
+
+impl TaskIdleOverride {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for 'task idle override' should be 'map'"
+            ));
+        };
+
+        let mut match_rule = None;
+        let mut check_duration = None;
+        let mut max_count = None;
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "net" | "network" | "match_net" => {
+                if match_rule.is_some() {
+                    return Err(anyhow!("only one of net and user_group can be set"));
+                }
+                let net = g3_yaml::value::as_ip_network(v)
+                    .context(format!("invalid ip network value for key {k}"))?;
+                match_rule = Some(TaskIdleOverrideMatch::Network(net));
+                Ok(())
+            }
+            "user_group" => {
+                if match_rule.is_some() {
+                    return Err(anyhow!("only one of net and user_group can be set"));
+                }
+                let name = g3_yaml::value::as_metric_node_name(v)
+                    .context(format!("invalid user group name value for key {k}"))?;
+                match_rule = Some(TaskIdleOverrideMatch::UserGroup(name));
+                Ok(())
+            }
+            "task_idle_check_duration" | "check_duration" => {
+                let duration = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                check_duration = Some(duration.min(IDLE_CHECK_MAXIMUM_DURATION));
+                Ok(())
+            }
+            "task_idle_max_count" | "max_count" => {
+                let count = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                max_count = Some(count);
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        let Some(match_rule) = match_rule else {
+            return Err(anyhow!("neither net nor user_group is set"));
+        };
+        if check_duration.is_none() && max_count.is_none() {
+            return Err(anyhow!(
+                "neither task_idle_check_duration nor task_idle_max_count is set"
+            ));
+        }
+        Ok(TaskIdleOverride {
+            match_rule,
+            check_duration,
+            max_count,
+        })
+    }
+
+    /// Get the name to be recorded in task logs
+    pub(crate) fn name(&self) -> String {
+        self.match_rule.to_string()
+    }
+
+    pub(crate) fn check_duration(&self, default: Duration) -> Duration {
+        self.check_duration.unwrap_or(default)
+    }
+
+    pub(crate) fn max_count(&self, default: usize) -> usize {
+        self.max_count.unwrap_or(default)
+    }
+}
+
+/// The task idle overrides of a server, which will be resolved at task start.
+///
+/// The network one with the longest prefix wins if the client ip matches,
+/// or the first user group one will be used if the user group matches.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct TaskIdleOverrides {
+    overrides: Vec<TaskIdleOverride>,
+}
+
+impl TaskIdleOverrides {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let mut overrides = Vec::new();
+        match v {
+            Yaml::Array(seq) => {
+                for (i, v) in seq.iter().enumerate() {
+                    let o = TaskIdleOverride::parse_yaml(v)
+                        .context(format!("invalid task idle override value for #{i}"))?;
+                    overrides.push(o);
+                }
+            }
+            Yaml::Hash(_) => {
+                let o = TaskIdleOverride::parse_yaml(v)?;
+                overrides.push(o);
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for 'task idle overrides' should be 'seq' or 'map'"
+                ));
+            }
+        }
+        Ok(TaskIdleOverrides { overrides })
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &TaskIdleOverride> {
+        self.overrides.iter()
+    }
+
+    /// Get the index of the matched override
+    pub(crate) fn select(&self, client_ip: IpAddr, user_group: Option<&NodeName>) -> Option<usize> {
+        let mut net_matched: Option<(usize, u8)> = None;
+        let mut group_matched = None;
+        for (i, o) in self.overrides.iter().enumerate() {
+            match &o.match_rule {
+                TaskIdleOverrideMatch::Network(net) => {
+                    if !net.contains(client_ip) {
+                        continue;
+                    }
+                    let prefix = net.netmask();
+                    if net_matched.map(|(_, p)| prefix > p).unwrap_or(true) {
+                        net_matched = Some((i, prefix));
+                    }
+                }
+                TaskIdleOverrideMatch::UserGroup(name) => {
+                    if group_matched.is_none() && user_group == Some(name) {
+                        group_matched = Some(i);
+                    }
+                }
+            }
+        }
+        net_matched.map(|(i, _)| i).or(group_matched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use yaml_rust::YamlLoader;
+
+    fn load_yaml(s: &str) -> Yaml {
+        YamlLoader::load_from_str(s).unwrap().pop().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let overrides = TaskIdleOverrides::parse_yaml(&load_yaml(
+            r#"
+- net: 10.0.0.0/8
+  task_idle_check_duration: 1h
+- user_group: long
+  max_count: 100
+"#,
+        ))
+        .unwrap();
+        let all: Vec<_> = overrides.iter().collect();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].name(), "net:10.0.0.0/8");
+        assert_eq!(
+            all[0].check_duration(Duration::from_secs(60)),
+            IDLE_CHECK_MAXIMUM_DURATION
+        );
+        assert_eq!(all[0].max_count(5), 5);
+        assert_eq!(all[1].name(), "user_group:long");
+        assert_eq!(all[1].max_count(5), 100);
+
+        assert!(TaskIdleOverrides::parse_yaml(&load_yaml("net: 10.0.0.0/8")).is_err());
+        assert!(TaskIdleOverrides::parse_yaml(&load_yaml("max_count: 1")).is_err());
+        assert!(
+            TaskIdleOverrides::parse_yaml(&load_yaml(
+                "{net: 10.0.0.0/8, user_group: long, max_count: 1}"
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn select() {
+        let overrides = TaskIdleOverrides::parse_yaml(&load_yaml(
+            r#"
+- user_group: long
+  max_count: 1
+- net: 10.0.0.0/8
+  max_count: 2
+- net: 10.1.0.0/16
+  max_count: 3
+"#,
+        ))
+        .unwrap();
+        let group = NodeName::from_str("long").unwrap();
+        let other_group = NodeName::from_str("other").unwrap();
+
+        let ip = IpAddr::from_str("10.1.0.1").unwrap();
+        assert_eq!(overrides.select(ip, None), Some(2));
+        assert_eq!(overrides.select(ip, Some(&group)), Some(2));
+        let ip = IpAddr::from_str("10.2.0.1").unwrap();
+        assert_eq!(overrides.select(ip, Some(&group)), Some(1));
+        let ip = IpAddr::from_str("192.168.0.1").unwrap();
+        assert_eq!(overrides.select(ip, Some(&group)), Some(0));
+        assert_eq!(overrides.select(ip, Some(&other_group)), None);
+        assert_eq!(overrides.select(ip, None), None);
+    }
+}
@@ -16,6 +16,7 @@ use g3_daemon::config::TopoMap;
 use g3_io_ext::StreamCopyConfig;
 use g3_macros::AnyConfig;
 use g3_types::metrics::NodeName;
+use g3_types::net::TlsCertStatus;
 use g3_yaml::{HybridParser, YamlDocPosition};
 
 use crate::audit::AuditHandle;
@@ -42,12 +43,44 @@ pub(crate) mod tcp_stream;
 ))]
 pub(crate) mod tcp_tproxy;
 pub(crate) mod tls_stream;
+#[cfg(target_os = "linux")]
+pub(crate) mod udp_tproxy;
+
+mod idle_override;
+pub(crate) use idle_override::TaskIdleOverrides;
+
+mod client_error;
+pub(crate) use client_error::{ClientErrorAction, ClientErrorActionMap, ClientErrorClass};
+
+mod udp_port_range_map;
+pub(crate) use udp_port_range_map::{UdpPortRangeMap, UdpPortRangeTier};
+
+mod task_log_sample;
+pub(crate) use task_log_sample::TaskLogSampleConfig;
+
+mod udp_dest_verify;
+pub(crate) use udp_dest_verify::{UdpDestVerifyConfig, UdpDestVerifyMode};
 
 mod registry;
-pub(crate) use registry::clear;
+pub(crate) use registry::{clear, replace_all};
+
+/// The key schema of the server config types that use a key registry
+const SERVER_CONFIG_SCHEMAS: &[g3_yaml::schema::YamlMapSchema] = &[
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+    ))]
+    tcp_tproxy::SERVER_CONFIG_SCHEMA,
+];
+
+pub(crate) fn schema() -> Vec<serde_json::Value> {
+    SERVER_CONFIG_SCHEMAS.iter().map(|s| s.to_json()).collect()
+}
 
-const CONFIG_KEY_SERVER_TYPE: &str = "type";
-const CONFIG_KEY_SERVER_NAME: &str = "name";
+pub(super) const CONFIG_KEY_SERVER_TYPE: &str = "type";
+pub(super) const CONFIG_KEY_SERVER_NAME: &str = "name";
 
 const IDLE_CHECK_MAXIMUM_DURATION: Duration = Duration::from_secs(1800);
 const IDLE_CHECK_DEFAULT_DURATION: Duration = Duration::from_secs(60);
@@ -62,6 +95,18 @@ pub(crate) enum ServerConfigDiffAction {
     UpdateInPlace(u64), // to support server custom hot update, take a flags param
 }
 
+impl ServerConfigDiffAction {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ServerConfigDiffAction::NoAction => "NoAction",
+            ServerConfigDiffAction::SpawnNew => "SpawnNew",
+            ServerConfigDiffAction::ReloadNoRespawn => "ReloadNoRespawn",
+            ServerConfigDiffAction::ReloadAndRespawn => "ReloadAndRespawn",
+            ServerConfigDiffAction::UpdateInPlace(_) => "UpdateInPlace",
+        }
+    }
+}
+
 pub(crate) trait ServerConfig {
     fn name(&self) -> &NodeName;
     fn position(&self) -> Option<YamlDocPosition>;
@@ -98,6 +143,11 @@ pub(crate) trait ServerConfig {
         1
     }
 
+    /// get the status of all the certificates configured in this server
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        Ok(Vec::new())
+    }
+
     fn get_user_group(&self) -> Option<Arc<UserGroup>> {
         if self.user_group().is_empty() {
             None
@@ -128,6 +178,7 @@ pub(crate) trait ServerConfig {
 #[def_fn(user_group, &NodeName)]
 #[def_fn(auditor, &NodeName)]
 #[def_fn(diff_action, &Self, ServerConfigDiffAction)]
+#[def_fn(cert_status, anyhow::Result<Vec<TlsCertStatus>>)]
 pub(crate) enum AnyServerConfig {
     DummyClose(dummy_close::DummyCloseServerConfig),
     PlainTcpPort(plain_tcp_port::PlainTcpPortConfig),
@@ -145,6 +196,8 @@ pub(crate) enum AnyServerConfig {
     ))]
     TcpTProxy(tcp_tproxy::TcpTProxyServerConfig),
     TlsStream(tls_stream::TlsStreamServerConfig),
+    #[cfg(target_os = "linux")]
+    UdpTProxy(udp_tproxy::UdpTProxyServerConfig),
     SniProxy(sni_proxy::SniProxyServerConfig),
     SocksProxy(socks_proxy::SocksProxyServerConfig),
     HttpProxy(http_proxy::HttpProxyServerConfig),
@@ -155,6 +208,7 @@ pub(crate) fn load_all(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
     let parser = HybridParser::new(conf_dir, g3_daemon::opts::config_file_extension());
     parser.foreach_map(v, |map, position| {
         let server = load_server(map, position)?;
+        check_certs(&server)?;
         if let Some(old_server) = registry::add(server) {
             Err(anyhow!(
                 "server with name {} already exists",
@@ -172,25 +226,36 @@ pub(crate) fn load_at_position(position: &YamlDocPosition) -> anyhow::Result<Any
     let doc = g3_yaml::load_doc(position)?;
     if let Yaml::Hash(map) = doc {
         let server = load_server(&map, Some(position.clone()))?;
-        let old_server = registry::add(server.clone());
-        if let Err(e) = build_topology_map() {
-            // rollback
-            match old_server {
-                Some(server) => {
-                    registry::add(server);
-                }
-                None => registry::del(server.name()),
+        add_single(server)
+    } else {
+        Err(anyhow!("yaml doc {position} is not a map"))
+    }
+}
+
+/// Load the server with the given name in the config file, all other servers will be skipped
+pub(crate) fn load_named_in_file(path: &Path, name: &NodeName) -> anyhow::Result<AnyServerConfig> {
+    let server = super::load_named_in_file(path, "server", name.as_str(), load_server)?;
+    add_single(server)
+}
+
+fn add_single(server: AnyServerConfig) -> anyhow::Result<AnyServerConfig> {
+    check_certs(&server)?;
+    let old_server = registry::add(server.clone());
+    if let Err(e) = build_topology_map() {
+        // rollback
+        match old_server {
+            Some(server) => {
+                registry::add(server);
             }
-            Err(e)
-        } else {
-            Ok(server)
+            None => registry::del(server.name()),
         }
+        Err(e)
     } else {
-        Err(anyhow!("yaml doc {position} is not a map"))
+        Ok(server)
     }
 }
 
-fn load_server(
+pub(crate) fn load_server(
     map: &yaml::Hash,
     position: Option<YamlDocPosition>,
 ) -> anyhow::Result<AnyServerConfig> {
@@ -249,6 +314,12 @@ fn load_server(
                 .context("failed to load this TLsStream server")?;
             Ok(AnyServerConfig::TlsStream(server))
         }
+        #[cfg(target_os = "linux")]
+        "udp_tproxy" | "udptproxy" => {
+            let server = udp_tproxy::UdpTProxyServerConfig::parse(map, position)
+                .context("failed to load this UdpTProxy server")?;
+            Ok(AnyServerConfig::UdpTProxy(server))
+        }
         "sni_proxy" | "sniproxy" => {
             let server = sni_proxy::SniProxyServerConfig::parse(map, position)
                 .context("failed to load this SniProxy server")?;
@@ -274,6 +345,15 @@ fn load_server(
     }
 }
 
+fn check_certs(server: &AnyServerConfig) -> anyhow::Result<()> {
+    let certs = server
+        .cert_status()
+        .context(format!("invalid certificate in server {}", server.name()))?;
+    g3_daemon::tls::check_and_record_certs(format!("server/{}", server.name()), certs).context(
+        format!("certificate check failed for server {}", server.name()),
+    )
+}
+
 fn build_topology_map() -> anyhow::Result<TopoMap> {
     let mut topo_map = TopoMap::default();
 
@@ -12,7 +12,9 @@ use yaml_rust::{Yaml, yaml};
 use g3_tls_ticket::TlsTicketConfig;
 use g3_types::acl::AclNetworkRuleBuilder;
 use g3_types::metrics::NodeName;
-use g3_types::net::{ProxyProtocolVersion, RustlsServerConfigBuilder, TcpListenConfig};
+use g3_types::net::{
+    ProxyProtocolVersion, RustlsServerConfigBuilder, TcpListenConfig, TlsCertStatus,
+};
 use g3_yaml::YamlDocPosition;
 
 use super::ServerConfig;
@@ -182,4 +184,11 @@ impl ServerConfig for PlainTlsPortConfig {
         set.insert(self.server.clone());
         Some(set)
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        match &self.server_tls_config {
+            Some(builder) => builder.cert_status(),
+            None => Ok(Vec::new()),
+        }
+    }
 }
@@ -27,6 +27,14 @@ pub(super) fn add(server: AnyServerConfig) -> Option<AnyServerConfig> {
     ht.insert(name, server).map(|v| v.as_ref().clone())
 }
 
+pub(crate) fn replace_all(servers: &[Arc<AnyServerConfig>]) {
+    let mut ht = INITIAL_SERVER_CONFIG_REGISTRY.lock().unwrap();
+    ht.clear();
+    for server in servers {
+        ht.insert(server.name().clone(), server.clone());
+    }
+}
+
 pub(super) fn del(name: &NodeName) {
     let mut ht = INITIAL_SERVER_CONFIG_REGISTRY.lock().unwrap();
     ht.remove(name);
@@ -21,7 +21,7 @@ use g3_yaml::YamlDocPosition;
 
 use super::{
     AnyServerConfig, IDLE_CHECK_DEFAULT_DURATION, IDLE_CHECK_DEFAULT_MAX_COUNT,
-    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction,
+    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction, TaskLogSampleConfig,
 };
 
 mod host;
@@ -41,10 +41,12 @@ pub(crate) struct SniProxyServerConfig {
     pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
     pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
     pub(crate) task_idle_check_duration: Duration,
+    pub(crate) task_idle_check_jitter: u8,
     pub(crate) task_idle_max_count: usize,
     pub(crate) flush_task_log_on_created: bool,
     pub(crate) flush_task_log_on_connected: bool,
     pub(crate) task_log_flush_interval: Option<Duration>,
+    pub(crate) task_log_sample: TaskLogSampleConfig,
     pub(crate) tcp_copy: StreamCopyConfig,
     pub(crate) tcp_misc_opts: TcpMiscSockOpts,
     pub(crate) tls_max_client_hello_size: u32,
@@ -70,10 +72,12 @@ impl SniProxyServerConfig {
             ingress_net_filter: None,
             tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
             task_idle_check_duration: IDLE_CHECK_DEFAULT_DURATION,
+            task_idle_check_jitter: 0,
             task_idle_max_count: IDLE_CHECK_DEFAULT_MAX_COUNT,
             flush_task_log_on_created: false,
             flush_task_log_on_connected: false,
             task_log_flush_interval: None,
+            task_log_sample: TaskLogSampleConfig::default(),
             tcp_copy: Default::default(),
             tcp_misc_opts: Default::default(),
             tls_max_client_hello_size: 1 << 16,
@@ -177,6 +181,12 @@ impl SniProxyServerConfig {
                     .context(format!("invalid humanize duration value for key {k}"))?;
                 Ok(())
             }
+            "task_idle_check_jitter" => {
+                let jitter =
+                    g3_yaml::value::as_u8(v).context(format!("invalid u8 value for key {k}"))?;
+                self.task_idle_check_jitter = jitter.min(100);
+                Ok(())
+            }
             "task_idle_max_count" => {
                 self.task_idle_max_count = g3_yaml::value::as_usize(v)
                     .context(format!("invalid usize value for key {k}"))?;
@@ -196,6 +206,16 @@ impl SniProxyServerConfig {
                 self.task_log_flush_interval = Some(interval);
                 Ok(())
             }
+            "task_log_sample_ratio" => self
+                .task_log_sample
+                .parse_ratio(v)
+                .context(format!("invalid ratio value for key {k}")),
+            "always_log_errors" => {
+                let enable = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                self.task_log_sample.set_always_log_errors(enable);
+                Ok(())
+            }
             "request_wait_timeout" => {
                 self.request_wait_timeout = g3_yaml::humanize::as_duration(v)
                     .context(format!("invalid humanize duration value for key {k}"))?;
@@ -4,6 +4,8 @@
  */
 
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -13,7 +15,9 @@ use log::warn;
 use rustc_hash::FxHashMap;
 use yaml_rust::{Yaml, yaml};
 
-use g3_io_ext::{LimitedUdpRelayConfig, StreamCopyConfig};
+use g3_io_ext::{
+    LimitedUdpRelayConfig, StreamCopyConfig, UdpDuplicateFilterConfig, UdpOversizePolicy,
+};
 use g3_types::acl::{AclExactPortRule, AclNetworkRuleBuilder};
 use g3_types::acl_set::AclDstHostRuleSetBuilder;
 use g3_types::metrics::{MetricTagMap, NodeName};
@@ -24,8 +28,10 @@ use g3_types::net::{
 use g3_yaml::YamlDocPosition;
 
 use super::{
-    AnyServerConfig, IDLE_CHECK_DEFAULT_DURATION, IDLE_CHECK_DEFAULT_MAX_COUNT,
-    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction,
+    AnyServerConfig, ClientErrorActionMap, IDLE_CHECK_DEFAULT_DURATION,
+    IDLE_CHECK_DEFAULT_MAX_COUNT, IDLE_CHECK_MAXIMUM_DURATION, ServerConfig,
+    ServerConfigDiffAction, TaskIdleOverrides, TaskLogSampleConfig, UdpDestVerifyConfig,
+    UdpPortRangeMap,
 };
 
 const SERVER_CONFIG_TYPE: &str = "SocksProxy";
@@ -49,6 +55,69 @@ impl Default for SocksProxyServerTimeoutConfig {
     }
 }
 
+/// How to handle the client requested port in udp associate request
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum SocksProxyUdpClientPortPolicy {
+    /// always select a random relay port
+    #[default]
+    Ignore,
+    /// try to use the requested port, and fallback to a random one
+    Try,
+    /// fail the udp associate request if the requested port is not available
+    Require,
+}
+
+impl SocksProxyUdpClientPortPolicy {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            SocksProxyUdpClientPortPolicy::Ignore => "ignore",
+            SocksProxyUdpClientPortPolicy::Try => "try",
+            SocksProxyUdpClientPortPolicy::Require => "require",
+        }
+    }
+}
+
+impl FromStr for SocksProxyUdpClientPortPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ignore" => Ok(SocksProxyUdpClientPortPolicy::Ignore),
+            "try" => Ok(SocksProxyUdpClientPortPolicy::Try),
+            "require" => Ok(SocksProxyUdpClientPortPolicy::Require),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Which bytes of the udp datagrams should be counted by the udp socket speed limit
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum SocksProxyUdpSpeedLimitBytes {
+    /// count all the bytes on the wire, including the socks5 udp header
+    #[default]
+    Wire,
+    /// count only the payload bytes, excluding the socks5 udp header
+    Payload,
+}
+
+impl SocksProxyUdpSpeedLimitBytes {
+    pub(crate) fn is_payload(&self) -> bool {
+        matches!(self, SocksProxyUdpSpeedLimitBytes::Payload)
+    }
+}
+
+impl FromStr for SocksProxyUdpSpeedLimitBytes {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "wire" => Ok(SocksProxyUdpSpeedLimitBytes::Wire),
+            "payload" => Ok(SocksProxyUdpSpeedLimitBytes::Payload),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub(crate) struct SocksProxyServerConfig {
     name: NodeName,
@@ -63,23 +132,35 @@ pub(crate) struct SocksProxyServerConfig {
     pub(crate) udp_bind4: Vec<IpAddr>,
     pub(crate) udp_bind6: Vec<IpAddr>,
     pub(crate) udp_bind_port_range: Option<PortRange>,
+    pub(crate) udp_port_range_map: UdpPortRangeMap,
+    pub(crate) udp_honor_client_port: SocksProxyUdpClientPortPolicy,
     pub(crate) udp_socket_buffer: SocketBufferConfig,
     pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
     pub(crate) dst_host_filter: Option<AclDstHostRuleSetBuilder>,
     pub(crate) dst_port_filter: Option<AclExactPortRule>,
     pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
     pub(crate) udp_sock_speed_limit: UdpSockSpeedLimitConfig,
+    pub(crate) udp_sock_speed_limit_bytes: SocksProxyUdpSpeedLimitBytes,
     pub(crate) timeout: SocksProxyServerTimeoutConfig,
     pub(crate) task_idle_check_duration: Duration,
+    pub(crate) task_idle_check_jitter: u8,
     pub(crate) task_idle_max_count: usize,
+    pub(crate) idle_overrides: TaskIdleOverrides,
     pub(crate) flush_task_log_on_created: bool,
     pub(crate) flush_task_log_on_connected: bool,
     pub(crate) task_log_flush_interval: Option<Duration>,
+    pub(crate) task_log_sample: TaskLogSampleConfig,
+    pub(crate) client_error_actions: ClientErrorActionMap,
     pub(crate) tcp_copy: StreamCopyConfig,
     pub(crate) udp_relay: LimitedUdpRelayConfig,
     pub(crate) tcp_misc_opts: TcpMiscSockOpts,
     pub(crate) udp_misc_opts: UdpMiscSockOpts,
     pub(crate) transmute_udp_echo_ip: Option<FxHashMap<IpAddr, IpAddr>>,
+    pub(crate) udp_associate_idle_echo: Option<Duration>,
+    pub(crate) udp_client_duplicate_filter: Option<UdpDuplicateFilterConfig>,
+    pub(crate) udp_capture_dir: Option<PathBuf>,
+    pub(crate) udp_capture_max_bytes: usize,
+    pub(crate) udp_dest_verify: UdpDestVerifyConfig,
     pub(crate) extra_metrics_tags: Option<Arc<MetricTagMap>>,
 }
 
@@ -98,23 +179,35 @@ impl SocksProxyServerConfig {
             udp_bind4: Vec::new(),
             udp_bind6: Vec::new(),
             udp_bind_port_range: None,
+            udp_port_range_map: UdpPortRangeMap::default(),
+            udp_honor_client_port: SocksProxyUdpClientPortPolicy::default(),
             udp_socket_buffer: SocketBufferConfig::default(),
             ingress_net_filter: None,
             dst_host_filter: None,
             dst_port_filter: None,
             tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
             udp_sock_speed_limit: UdpSockSpeedLimitConfig::default(),
+            udp_sock_speed_limit_bytes: SocksProxyUdpSpeedLimitBytes::default(),
             timeout: SocksProxyServerTimeoutConfig::default(),
             task_idle_check_duration: IDLE_CHECK_DEFAULT_DURATION,
+            task_idle_check_jitter: 0,
             task_idle_max_count: IDLE_CHECK_DEFAULT_MAX_COUNT,
+            idle_overrides: TaskIdleOverrides::default(),
             flush_task_log_on_created: false,
             flush_task_log_on_connected: false,
             task_log_flush_interval: None,
+            task_log_sample: TaskLogSampleConfig::default(),
+            client_error_actions: ClientErrorActionMap::default(),
             tcp_copy: Default::default(),
             udp_relay: Default::default(),
             tcp_misc_opts: Default::default(),
             udp_misc_opts: Default::default(),
             transmute_udp_echo_ip: None,
+            udp_associate_idle_echo: None,
+            udp_client_duplicate_filter: None,
+            udp_capture_dir: None,
+            udp_capture_max_bytes: 64 * 1024 * 1024,
+            udp_dest_verify: UdpDestVerifyConfig::default(),
             extra_metrics_tags: None,
         }
     }
@@ -195,6 +288,17 @@ impl SocksProxyServerConfig {
                 self.udp_bind_port_range = Some(range);
                 Ok(())
             }
+            "udp_port_range_map" => {
+                self.udp_port_range_map = UdpPortRangeMap::parse_yaml(v)
+                    .context(format!("invalid udp port range map value for key {k}"))?;
+                Ok(())
+            }
+            "udp_honor_client_port" => {
+                let s = g3_yaml::value::as_string(v)?;
+                self.udp_honor_client_port = SocksProxyUdpClientPortPolicy::from_str(&s)
+                    .map_err(|_| anyhow!("invalid udp client port policy value for key {k}"))?;
+                Ok(())
+            }
             "udp_socket_buffer" => {
                 self.udp_socket_buffer = g3_yaml::value::as_socket_buffer_config(v)
                     .context(format!("invalid socket buffer config value for key {k}"))?;
@@ -233,6 +337,12 @@ impl SocksProxyServerConfig {
                     .context(format!("invalid udp socket speed limit value for key {k}"))?;
                 Ok(())
             }
+            "udp_sock_speed_limit_bytes" => {
+                let s = g3_yaml::value::as_string(v)?;
+                self.udp_sock_speed_limit_bytes = SocksProxyUdpSpeedLimitBytes::from_str(&s)
+                    .map_err(|_| anyhow!("invalid udp speed limit bytes value for key {k}"))?;
+                Ok(())
+            }
             "udp_relay_speed_limit" | "udp_relay_limit" | "relay_limit" => {
                 warn!("deprecated config key '{k}', please use 'udp_sock_speed_limit' instead");
                 self.set("udp_sock_speed_limit", v)
@@ -255,6 +365,19 @@ impl SocksProxyServerConfig {
                 self.udp_relay.set_packet_size(packet_size);
                 Ok(())
             }
+            "udp_relay_packet_size_max" => {
+                let packet_size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                self.udp_relay.set_packet_size_max(packet_size);
+                Ok(())
+            }
+            "udp_relay_oversize_policy" => {
+                let s = g3_yaml::value::as_string(v)?;
+                let policy = UdpOversizePolicy::from_str(&s)
+                    .map_err(|_| anyhow!("invalid udp oversize policy {s}"))?;
+                self.udp_relay.set_oversize_policy(policy);
+                Ok(())
+            }
             "udp_relay_yield_size" => {
                 let yield_size = g3_yaml::humanize::as_usize(v)
                     .context(format!("invalid humanize usize value for key {k}"))?;
@@ -266,6 +389,21 @@ impl SocksProxyServerConfig {
                 self.udp_relay.set_batch_size(batch_size);
                 Ok(())
             }
+            "udp_relay_batch_size_min" => {
+                let batch_size = g3_yaml::value::as_usize(v)?;
+                self.udp_relay.set_batch_size_min(batch_size);
+                Ok(())
+            }
+            "udp_relay_batch_grow_threshold" => {
+                let threshold = g3_yaml::value::as_usize(v)?;
+                self.udp_relay.set_batch_grow_threshold(threshold);
+                Ok(())
+            }
+            "udp_relay_batch_shrink_threshold" => {
+                let threshold = g3_yaml::value::as_usize(v)?;
+                self.udp_relay.set_batch_shrink_threshold(threshold);
+                Ok(())
+            }
             "tcp_misc_opts" => {
                 self.tcp_misc_opts = g3_yaml::value::as_tcp_misc_sock_opts(v)
                     .context(format!("invalid tcp misc sock opts value for key {k}"))?;
@@ -291,11 +429,22 @@ impl SocksProxyServerConfig {
                     .context(format!("invalid humanize duration value for key {k}"))?;
                 Ok(())
             }
+            "task_idle_check_jitter" => {
+                let jitter =
+                    g3_yaml::value::as_u8(v).context(format!("invalid u8 value for key {k}"))?;
+                self.task_idle_check_jitter = jitter.min(100);
+                Ok(())
+            }
             "task_idle_max_count" => {
                 self.task_idle_max_count = g3_yaml::value::as_usize(v)
                     .context(format!("invalid usize value for key {k}"))?;
                 Ok(())
             }
+            "idle_overrides" | "task_idle_overrides" => {
+                self.idle_overrides = TaskIdleOverrides::parse_yaml(v)
+                    .context(format!("invalid task idle overrides value for key {k}"))?;
+                Ok(())
+            }
             "flush_task_log_on_created" => {
                 self.flush_task_log_on_created = g3_yaml::value::as_bool(v)?;
                 Ok(())
@@ -310,6 +459,21 @@ impl SocksProxyServerConfig {
                 self.task_log_flush_interval = Some(interval);
                 Ok(())
             }
+            "task_log_sample_ratio" => self
+                .task_log_sample
+                .parse_ratio(v)
+                .context(format!("invalid ratio value for key {k}")),
+            "always_log_errors" => {
+                let enable = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                self.task_log_sample.set_always_log_errors(enable);
+                Ok(())
+            }
+            "client_error_actions" => {
+                self.client_error_actions = ClientErrorActionMap::parse_yaml(v)
+                    .context(format!("invalid client error actions value for key {k}"))?;
+                Ok(())
+            }
             "transmute_udp_echo_ip" => {
                 if let Yaml::Hash(_) = v {
                     let map = g3_yaml::value::as_hashmap(
@@ -326,6 +490,38 @@ impl SocksProxyServerConfig {
                 }
                 Ok(())
             }
+            "udp_associate_idle_echo" => {
+                let interval = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                self.udp_associate_idle_echo = if interval.is_zero() {
+                    None
+                } else {
+                    Some(interval)
+                };
+                Ok(())
+            }
+            "udp_client_duplicate_filter" => {
+                self.udp_client_duplicate_filter = parse_udp_duplicate_filter(v)
+                    .context(format!("invalid udp duplicate filter value for key {k}"))?;
+                Ok(())
+            }
+            "udp_capture_dir" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let dir = g3_yaml::value::as_dir_path(v, lookup_dir, true)
+                    .context(format!("invalid dir path value for key {k}"))?;
+                self.udp_capture_dir = Some(dir);
+                Ok(())
+            }
+            "udp_capture_max_bytes" => {
+                self.udp_capture_max_bytes = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                Ok(())
+            }
+            "udp_dest_verify" => {
+                self.udp_dest_verify = UdpDestVerifyConfig::parse_yaml(v)
+                    .context(format!("invalid udp dest verify config value for key {k}"))?;
+                Ok(())
+            }
             "auto_reply_local_ip_map" => {
                 warn!("deprecated config key '{k}', please use 'transmute_udp_echo_ip' instead");
                 self.set("transmute_udp_echo_ip", v)
@@ -364,6 +560,34 @@ impl SocksProxyServerConfig {
     }
 }
 
+fn parse_udp_duplicate_filter(v: &Yaml) -> anyhow::Result<Option<UdpDuplicateFilterConfig>> {
+    match v {
+        Yaml::Hash(map) => {
+            let mut config = UdpDuplicateFilterConfig::default();
+            g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                "window_size" | "window" => {
+                    let size = g3_yaml::value::as_usize(v)
+                        .context(format!("invalid usize value for key {k}"))?;
+                    config.set_window_size(size);
+                    Ok(())
+                }
+                "horizon" => {
+                    let horizon = g3_yaml::humanize::as_duration(v)
+                        .context(format!("invalid humanize duration value for key {k}"))?;
+                    config.set_horizon(horizon);
+                    Ok(())
+                }
+                _ => Err(anyhow!("invalid key {k}")),
+            })?;
+            Ok(Some(config))
+        }
+        _ => {
+            let enable = g3_yaml::value::as_bool(v)?;
+            Ok(enable.then(UdpDuplicateFilterConfig::default))
+        }
+    }
+}
+
 impl ServerConfig for SocksProxyServerConfig {
     fn name(&self) -> &NodeName {
         &self.name
@@ -423,3 +647,78 @@ impl ServerConfig for SocksProxyServerConfig {
         self.task_idle_max_count
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    use crate::config::server::{ClientErrorAction, ClientErrorClass};
+
+    #[test]
+    fn client_error_actions() {
+        let load = |s: &str| YamlLoader::load_from_str(s).unwrap().remove(0);
+
+        let mut config = SocksProxyServerConfig::new(None);
+        assert_eq!(config.client_error_actions, ClientErrorActionMap::default());
+        config
+            .set(
+                "client_error_actions",
+                &load("{connect_refused: {socks_reply: 5}, ingress_denied: reset, idle_killed: close}"),
+            )
+            .unwrap();
+        let actions = &config.client_error_actions;
+        assert_eq!(
+            actions.get(ClientErrorClass::UpstreamConnectRefused),
+            Some(ClientErrorAction::SocksReply(5))
+        );
+        assert_eq!(
+            actions.get(ClientErrorClass::IngressDenied),
+            Some(ClientErrorAction::Reset)
+        );
+        assert_eq!(
+            actions.get(ClientErrorClass::IdleKilled),
+            Some(ClientErrorAction::Close)
+        );
+        assert!(
+            actions
+                .get(ClientErrorClass::UpstreamConnectTimeout)
+                .is_none()
+        );
+        // the socks reply can't be sent after the reply for the connect request
+        assert_eq!(
+            actions.get_established(ClientErrorClass::UpstreamConnectRefused),
+            Some(ClientErrorAction::Close)
+        );
+
+        assert!(
+            config
+                .set("client_error_actions", &load("{idle_killed: drop}"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn udp_sock_speed_limit_bytes() {
+        let load = |s: &str| YamlLoader::load_from_str(s).unwrap().remove(0);
+
+        let mut config = SocksProxyServerConfig::new(None);
+        assert!(!config.udp_sock_speed_limit_bytes.is_payload());
+        config
+            .set("udp_sock_speed_limit_bytes", &load("Payload"))
+            .unwrap();
+        assert!(config.udp_sock_speed_limit_bytes.is_payload());
+        config
+            .set("udp_sock_speed_limit_bytes", &load("wire"))
+            .unwrap();
+        assert_eq!(
+            config.udp_sock_speed_limit_bytes,
+            SocksProxyUdpSpeedLimitBytes::Wire
+        );
+        assert!(
+            config
+                .set("udp_sock_speed_limit_bytes", &load("header"))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,83 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use anyhow::anyhow;
+use yaml_rust::Yaml;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TaskLogSampleConfig {
+    ratio: f64,
+    always_log_errors: bool,
+}
+
+impl Default for TaskLogSampleConfig {
+    fn default() -> Self {
+        TaskLogSampleConfig {
+            ratio: 1.0,
+            always_log_errors: true,
+        }
+    }
+}
+
+// the ratio is always checked to be in range [0, 1], so it's safe to compare the bits
+impl PartialEq for TaskLogSampleConfig {
+    fn eq(&self, other: &Self) -> bool {
+        self.ratio.to_bits() == other.ratio.to_bits()
+            && self.always_log_errors == other.always_log_errors
+    }
+}
+
+impl Eq for TaskLogSampleConfig {}
+
+impl TaskLogSampleConfig {
+    #[inline]
+    pub(crate) fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    #[inline]
+    pub(crate) fn always_log_errors(&self) -> bool {
+        self.always_log_errors
+    }
+
+    pub(crate) fn set_ratio(&mut self, ratio: f64) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&ratio) {
+            return Err(anyhow!(
+                "invalid task log sample ratio {ratio}, it should be in range [0, 1]"
+            ));
+        }
+        self.ratio = ratio;
+        Ok(())
+    }
+
+    pub(crate) fn set_always_log_errors(&mut self, enable: bool) {
+        self.always_log_errors = enable;
+    }
+
+    pub(crate) fn parse_ratio(&mut self, v: &Yaml) -> anyhow::Result<()> {
+        let ratio = g3_yaml::value::as_f64(v)?;
+        self.set_ratio(ratio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ratio() {
+        let mut config = TaskLogSampleConfig::default();
+        config.parse_ratio(&Yaml::Real("0.25".to_string())).unwrap();
+        assert_eq!(config.ratio(), 0.25);
+        config.parse_ratio(&Yaml::Integer(0)).unwrap();
+        assert_eq!(config.ratio(), 0.0);
+
+        assert!(config.parse_ratio(&Yaml::Real("1.5".to_string())).is_err());
+        assert!(config.parse_ratio(&Yaml::Integer(-1)).is_err());
+        assert!(config.parse_ratio(&Yaml::Boolean(true)).is_err());
+        assert_eq!(config.ratio(), 0.0);
+        assert_ne!(config, TaskLogSampleConfig::default());
+    }
+}
@@ -17,13 +17,14 @@ use g3_types::collection::SelectivePickPolicy;
 use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::net::{
     Host, OpensslClientConfigBuilder, TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig,
-    WeightedUpstreamAddr,
+    TlsCertStatus, WeightedUpstreamAddr,
 };
 use g3_yaml::YamlDocPosition;
 
 use super::{
-    AnyServerConfig, IDLE_CHECK_DEFAULT_DURATION, IDLE_CHECK_DEFAULT_MAX_COUNT,
-    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction,
+    AnyServerConfig, ClientErrorActionMap, IDLE_CHECK_DEFAULT_DURATION,
+    IDLE_CHECK_DEFAULT_MAX_COUNT, IDLE_CHECK_MAXIMUM_DURATION, ServerConfig,
+    ServerConfigDiffAction, TaskLogSampleConfig,
 };
 
 const SERVER_CONFIG_TYPE: &str = "TcpStream";
@@ -44,10 +45,13 @@ pub(crate) struct TcpStreamServerConfig {
     pub(crate) upstream_tls_name: Option<Host>,
     pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
     pub(crate) task_idle_check_duration: Duration,
+    pub(crate) task_idle_check_jitter: u8,
     pub(crate) task_idle_max_count: usize,
     pub(crate) flush_task_log_on_created: bool,
     pub(crate) flush_task_log_on_connected: bool,
     pub(crate) task_log_flush_interval: Option<Duration>,
+    pub(crate) task_log_sample: TaskLogSampleConfig,
+    pub(crate) client_error_actions: ClientErrorActionMap,
     pub(crate) tcp_copy: StreamCopyConfig,
     pub(crate) tcp_misc_opts: TcpMiscSockOpts,
     pub(crate) extra_metrics_tags: Option<Arc<MetricTagMap>>,
@@ -70,10 +74,13 @@ impl TcpStreamServerConfig {
             upstream_tls_name: None,
             tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
             task_idle_check_duration: IDLE_CHECK_DEFAULT_DURATION,
+            task_idle_check_jitter: 0,
             task_idle_max_count: IDLE_CHECK_DEFAULT_MAX_COUNT,
             flush_task_log_on_created: false,
             flush_task_log_on_connected: false,
             task_log_flush_interval: None,
+            task_log_sample: TaskLogSampleConfig::default(),
+            client_error_actions: ClientErrorActionMap::default(),
             tcp_copy: Default::default(),
             tcp_misc_opts: Default::default(),
             extra_metrics_tags: None,
@@ -203,6 +210,12 @@ impl TcpStreamServerConfig {
                     .context(format!("invalid humanize duration value for key {k}"))?;
                 Ok(())
             }
+            "task_idle_check_jitter" => {
+                let jitter =
+                    g3_yaml::value::as_u8(v).context(format!("invalid u8 value for key {k}"))?;
+                self.task_idle_check_jitter = jitter.min(100);
+                Ok(())
+            }
             "task_idle_max_count" => {
                 self.task_idle_max_count = g3_yaml::value::as_usize(v)
                     .context(format!("invalid usize value for key {k}"))?;
@@ -222,6 +235,21 @@ impl TcpStreamServerConfig {
                 self.task_log_flush_interval = Some(interval);
                 Ok(())
             }
+            "task_log_sample_ratio" => self
+                .task_log_sample
+                .parse_ratio(v)
+                .context(format!("invalid ratio value for key {k}")),
+            "always_log_errors" => {
+                let enable = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                self.task_log_sample.set_always_log_errors(enable);
+                Ok(())
+            }
+            "client_error_actions" => {
+                self.client_error_actions = ClientErrorActionMap::parse_yaml(v)
+                    .context(format!("invalid client error actions value for key {k}"))?;
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
@@ -236,6 +264,7 @@ impl TcpStreamServerConfig {
         if self.upstream.is_empty() {
             return Err(anyhow!("upstream is not set"));
         }
+        self.client_error_actions.check_no_socks_reply()?;
         if self.task_idle_check_duration > IDLE_CHECK_MAXIMUM_DURATION {
             self.task_idle_check_duration = IDLE_CHECK_MAXIMUM_DURATION;
         }
@@ -307,4 +336,56 @@ impl ServerConfig for TcpStreamServerConfig {
     fn task_max_idle_count(&self) -> usize {
         self.task_idle_max_count
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        match &self.client_tls_config {
+            Some(builder) => builder.cert_status(),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    use crate::config::server::{ClientErrorAction, ClientErrorClass};
+
+    #[test]
+    fn client_error_actions() {
+        let load = |s: &str| YamlLoader::load_from_str(s).unwrap().remove(0);
+
+        let mut config = TcpStreamServerConfig::new(None);
+        assert_eq!(config.client_error_actions, ClientErrorActionMap::default());
+        config
+            .set(
+                "client_error_actions",
+                &load("{connect_timeout: reset, adaptation_failed: close, lifetime_expired: rst}"),
+            )
+            .unwrap();
+        let actions = &config.client_error_actions;
+        assert_eq!(
+            actions.get(ClientErrorClass::UpstreamConnectTimeout),
+            Some(ClientErrorAction::Reset)
+        );
+        assert_eq!(
+            actions.get(ClientErrorClass::AdaptationFailed),
+            Some(ClientErrorAction::Close)
+        );
+        assert_eq!(
+            actions.get(ClientErrorClass::LifetimeExpired),
+            Some(ClientErrorAction::Reset)
+        );
+        assert!(actions.get(ClientErrorClass::IngressDenied).is_none());
+        assert!(actions.check_no_socks_reply().is_ok());
+
+        config
+            .set(
+                "client_error_actions",
+                &load("{ingress_denied: {socks_reply: 2}}"),
+            )
+            .unwrap();
+        assert!(config.client_error_actions.check_no_socks_reply().is_err());
+    }
 }
@@ -0,0 +1,185 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::SocketAddr;
+
+use anyhow::{Context, anyhow};
+use ip_network::IpNetwork;
+use yaml_rust::Yaml;
+
+/// The alternate address to connect to
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TcpTProxyFallbackTarget {
+    Addr(SocketAddr),
+    /// use the original ip with another port
+    Port(u16),
+}
+
+/// Rewrite the original destination to an alternate address
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct TcpTProxyFallbackRule {
+    match_net: IpNetwork,
+    match_port: Option<u16>,
+    target: TcpTProxyFallbackTarget,
+}
+
+impl TcpTProxyFallbackRule {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for 'tcp tproxy fallback rule' should be 'map'"
+            ));
+        };
+
+        let mut match_net = None;
+        let mut match_port = None;
+        let mut target = None;
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "match_net" | "net" => {
+                let net = g3_yaml::value::as_ip_network(v)
+                    .context(format!("invalid ip network value for key {k}"))?;
+                match_net = Some(net);
+                Ok(())
+            }
+            "match_port" | "port" => {
+                let port =
+                    g3_yaml::value::as_u16(v).context(format!("invalid u16 value for key {k}"))?;
+                match_port = Some(port);
+                Ok(())
+            }
+            "to" | "to_addr" => {
+                let addr = g3_yaml::value::as_sockaddr(v)
+                    .context(format!("invalid socket address value for key {k}"))?;
+                target = Some(TcpTProxyFallbackTarget::Addr(addr));
+                Ok(())
+            }
+            "to_port" => {
+                let port =
+                    g3_yaml::value::as_u16(v).context(format!("invalid u16 value for key {k}"))?;
+                target = Some(TcpTProxyFallbackTarget::Port(port));
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        let Some(match_net) = match_net else {
+            return Err(anyhow!("match_net is not set"));
+        };
+        let Some(target) = target else {
+            return Err(anyhow!("neither to nor to_port is set"));
+        };
+        Ok(TcpTProxyFallbackRule {
+            match_net,
+            match_port,
+            target,
+        })
+    }
+
+    fn rewrite(&self, dst: SocketAddr) -> Option<SocketAddr> {
+        if !self.match_net.contains(dst.ip()) {
+            return None;
+        }
+        if let Some(port) = self.match_port {
+            if port != dst.port() {
+                return None;
+            }
+        }
+        match self.target {
+            TcpTProxyFallbackTarget::Addr(addr) => Some(addr),
+            TcpTProxyFallbackTarget::Port(port) => Some(SocketAddr::new(dst.ip(), port)),
+        }
+    }
+}
+
+/// Alternate addresses to try if the original destination is unreachable
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct TcpTProxyUpstreamFallback {
+    rules: Vec<TcpTProxyFallbackRule>,
+}
+
+impl TcpTProxyUpstreamFallback {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let mut rules = Vec::new();
+        match v {
+            Yaml::Array(seq) => {
+                for (i, v) in seq.iter().enumerate() {
+                    let rule = TcpTProxyFallbackRule::parse_yaml(v)
+                        .context(format!("invalid fallback rule value for #{i}"))?;
+                    rules.push(rule);
+                }
+            }
+            Yaml::Hash(_) => rules.push(TcpTProxyFallbackRule::parse_yaml(v)?),
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for 'tcp tproxy upstream fallback' should be 'seq' or 'map'"
+                ));
+            }
+        }
+        Ok(TcpTProxyUpstreamFallback { rules })
+    }
+
+    /// Get the alternate addresses for the original destination, in the order of the rules
+    pub(crate) fn alternates(&self, dst: SocketAddr) -> Vec<SocketAddr> {
+        let mut addrs: Vec<SocketAddr> = Vec::new();
+        for rule in &self.rules {
+            if let Some(addr) = rule.rewrite(dst) {
+                if addr != dst && !addrs.contains(&addr) {
+                    addrs.push(addr);
+                }
+            }
+        }
+        addrs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use yaml_rust::YamlLoader;
+
+    #[test]
+    fn alternates() {
+        let yaml = r#"
+- match_net: 192.0.2.0/24
+  match_port: 443
+  to_port: 8443
+- match_net: 192.0.2.0/24
+  to: 198.51.100.1:443
+- net: 192.0.2.1
+  to: 192.0.2.1:443
+"#;
+        let doc = YamlLoader::load_from_str(yaml).unwrap();
+        let fallback = TcpTProxyUpstreamFallback::parse_yaml(&doc[0]).unwrap();
+
+        let dst = SocketAddr::from_str("192.0.2.1:443").unwrap();
+        assert_eq!(
+            fallback.alternates(dst),
+            vec![
+                SocketAddr::from_str("192.0.2.1:8443").unwrap(),
+                SocketAddr::from_str("198.51.100.1:443").unwrap(),
+            ]
+        );
+
+        let dst = SocketAddr::from_str("192.0.2.2:80").unwrap();
+        assert_eq!(
+            fallback.alternates(dst),
+            vec![SocketAddr::from_str("198.51.100.1:443").unwrap()]
+        );
+
+        let dst = SocketAddr::from_str("203.0.113.1:443").unwrap();
+        assert!(fallback.alternates(dst).is_empty());
+    }
+
+    #[test]
+    fn invalid_rule() {
+        let doc = YamlLoader::load_from_str("match_net: 192.0.2.0/24").unwrap();
+        assert!(TcpTProxyUpstreamFallback::parse_yaml(&doc[0]).is_err());
+
+        let doc = YamlLoader::load_from_str("to_port: 8443").unwrap();
+        assert!(TcpTProxyUpstreamFallback::parse_yaml(&doc[0]).is_err());
+    }
+}
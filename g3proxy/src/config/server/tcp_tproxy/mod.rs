@@ -0,0 +1,655 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2024-2025 ByteDance and/or its affiliates.
+ */
+
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use ascii::AsciiString;
+use log::warn;
+use yaml_rust::{Yaml, yaml};
+
+use g3_daemon::listen::ListenWorkerConfig;
+use g3_daemon::server::TransferMetricsConfig;
+use g3_histogram::HistogramMetricsConfig;
+use g3_io_ext::StreamCopyConfig;
+use g3_types::acl::AclNetworkRuleBuilder;
+use g3_types::limit::RateLimitQuotaConfig;
+use g3_types::metrics::{MetricTagMap, NodeName};
+use g3_types::net::{TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig};
+use g3_yaml::YamlDocPosition;
+use g3_yaml::schema::{YamlKeySchema, YamlMapSchema, YamlValueKind};
+
+use super::{
+    AnyServerConfig, ClientErrorActionMap, IDLE_CHECK_DEFAULT_DURATION,
+    IDLE_CHECK_DEFAULT_MAX_COUNT, IDLE_CHECK_MAXIMUM_DURATION, ServerConfig,
+    ServerConfigDiffAction, TaskIdleOverrides, TaskLogSampleConfig,
+};
+
+mod fallback;
+pub(crate) use fallback::TcpTProxyUpstreamFallback;
+
+const SERVER_CONFIG_TYPE: &str = "TcpTProxy";
+
+pub(crate) const SERVER_CONFIG_SCHEMA: YamlMapSchema = YamlMapSchema::new("tcp_tproxy", KEYS);
+
+const KEYS: &[YamlKeySchema] = &[
+    YamlKeySchema::new(
+        super::CONFIG_KEY_SERVER_TYPE,
+        YamlValueKind::String,
+        "tcp_tproxy",
+    ),
+    YamlKeySchema::new(
+        super::CONFIG_KEY_SERVER_NAME,
+        YamlValueKind::String,
+        "tproxy",
+    ),
+    YamlKeySchema::new("escaper", YamlValueKind::String, "default"),
+    YamlKeySchema::new("auditor", YamlValueKind::String, "default"),
+    YamlKeySchema::new("shared_logger", YamlValueKind::String, "shared"),
+    YamlKeySchema::new("extra_metrics_tags", YamlValueKind::Map, "{cluster: test}"),
+    YamlKeySchema::new(
+        "listen",
+        YamlValueKind::Object("tcp_listen"),
+        "127.0.0.1:1234",
+    ),
+    YamlKeySchema::new("listen_in_worker", YamlValueKind::Bool, "true").default_value("false"),
+    YamlKeySchema::new("listen_worker_set", YamlValueKind::Seq, "[0, 1]"),
+    YamlKeySchema::new(
+        "connect_duration_stats",
+        YamlValueKind::Object("histogram_metrics"),
+        "{quantile: [0.5, 0.9]}",
+    )
+    .aliases(&["connect_duration_metrics"]),
+    YamlKeySchema::new(
+        "detailed_transfer_metrics",
+        YamlValueKind::Object("transfer_metrics"),
+        "true",
+    ),
+    YamlKeySchema::new(
+        "ingress_network_filter",
+        YamlValueKind::Object("network_acl_rule"),
+        "{default: allow}",
+    )
+    .aliases(&["ingress_net_filter"]),
+    YamlKeySchema::new(
+        "tcp_sock_speed_limit",
+        YamlValueKind::Object("tcp_sock_speed_limit"),
+        "10M",
+    ),
+    YamlKeySchema::new(
+        "tcp_conn_speed_limit",
+        YamlValueKind::Object("tcp_sock_speed_limit"),
+        "10M",
+    )
+    .aliases(&["tcp_conn_limit", "conn_limit"])
+    .deprecated("tcp_sock_speed_limit"),
+    YamlKeySchema::new(
+        "tcp_copy_buffer_size",
+        YamlValueKind::HumanizeInteger,
+        "32K",
+    )
+    .default_value("16K"),
+    YamlKeySchema::new("tcp_copy_yield_size", YamlValueKind::HumanizeInteger, "2M")
+        .default_value("1M"),
+    YamlKeySchema::new(
+        "tcp_misc_opts",
+        YamlValueKind::Object("tcp_misc_sock_opts"),
+        "{no_delay: true}",
+    ),
+    YamlKeySchema::new(
+        "client_misc_opts",
+        YamlValueKind::Object("tcp_misc_sock_opts"),
+        "{no_delay: true}",
+    ),
+    YamlKeySchema::new(
+        "upstream_misc_opts",
+        YamlValueKind::Object("tcp_misc_sock_opts"),
+        "{no_delay: false}",
+    ),
+    YamlKeySchema::new(
+        "task_idle_check_duration",
+        YamlValueKind::HumanizeDuration,
+        "30s",
+    )
+    .default_value("60s"),
+    YamlKeySchema::new("task_idle_check_jitter", YamlValueKind::Integer, "10").default_value("0"),
+    YamlKeySchema::new("task_idle_max_count", YamlValueKind::Integer, "10").default_value("5"),
+    YamlKeySchema::new(
+        "idle_overrides",
+        YamlValueKind::Object("task_idle_overrides"),
+        "[{net: 10.0.0.0/8, max_count: 10}]",
+    )
+    .aliases(&["task_idle_overrides"]),
+    YamlKeySchema::new("flush_task_log_on_created", YamlValueKind::Bool, "true")
+        .default_value("false"),
+    YamlKeySchema::new("flush_task_log_on_connected", YamlValueKind::Bool, "true")
+        .default_value("false"),
+    YamlKeySchema::new(
+        "task_log_flush_interval",
+        YamlValueKind::HumanizeDuration,
+        "10s",
+    ),
+    YamlKeySchema::new("task_log_sample_ratio", YamlValueKind::Float, "0.5").default_value("1.0"),
+    YamlKeySchema::new("always_log_errors", YamlValueKind::Bool, "false").default_value("true"),
+    YamlKeySchema::new(
+        "client_error_actions",
+        YamlValueKind::Object("client_error_actions"),
+        "{idle_killed: reset}",
+    ),
+    YamlKeySchema::new("max_connections", YamlValueKind::Integer, "1000")
+        .aliases(&["max_conn"])
+        .default_value("0"),
+    YamlKeySchema::new(
+        "accept_rate_limit",
+        YamlValueKind::Object("rate_limit_quota"),
+        "100/s",
+    ),
+    YamlKeySchema::new("accept_error_log_rate", YamlValueKind::Integer, "10"),
+    YamlKeySchema::new(
+        "upstream_fallback",
+        YamlValueKind::Object("tcp_tproxy_upstream_fallback"),
+        "{match_net: 10.0.0.0/8, to_port: 8080}",
+    ),
+    YamlKeySchema::new("connect_retry_count", YamlValueKind::Integer, "2").default_value("0"),
+    YamlKeySchema::new("connect_retry_delay", YamlValueKind::HumanizeDuration, "1s")
+        .default_value("100ms"),
+    YamlKeySchema::new(
+        "upstream_connect_timeout",
+        YamlValueKind::HumanizeDuration,
+        "30s",
+    ),
+    YamlKeySchema::new(
+        "upstream_connect_attempt_timeout",
+        YamlValueKind::HumanizeDuration,
+        "10s",
+    ),
+    YamlKeySchema::new(
+        "upstream_connect_timeout_reset",
+        YamlValueKind::Bool,
+        "false",
+    )
+    .default_value("true"),
+    YamlKeySchema::new("normalize_mapped_dst", YamlValueKind::Bool, "false").default_value("true"),
+    #[cfg(target_os = "linux")]
+    YamlKeySchema::new("redirect_original_dst", YamlValueKind::Bool, "true").default_value("false"),
+];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct TcpTProxyServerConfig {
+    name: NodeName,
+    position: Option<YamlDocPosition>,
+    pub(crate) escaper: NodeName,
+    pub(crate) auditor: NodeName,
+    pub(crate) shared_logger: Option<AsciiString>,
+    pub(crate) listen: TcpListenConfig,
+    pub(crate) listen_in_worker: bool,
+    pub(crate) listen_worker: ListenWorkerConfig,
+    pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
+    pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
+    pub(crate) task_idle_check_duration: Duration,
+    pub(crate) task_idle_check_jitter: u8,
+    pub(crate) task_idle_max_count: usize,
+    pub(crate) idle_overrides: TaskIdleOverrides,
+    pub(crate) flush_task_log_on_created: bool,
+    pub(crate) flush_task_log_on_connected: bool,
+    pub(crate) task_log_flush_interval: Option<Duration>,
+    pub(crate) task_log_sample: TaskLogSampleConfig,
+    pub(crate) client_error_actions: ClientErrorActionMap,
+    pub(crate) tcp_copy: StreamCopyConfig,
+    pub(crate) client_misc_opts: TcpMiscSockOpts,
+    pub(crate) upstream_misc_opts: Option<TcpMiscSockOpts>,
+    pub(crate) extra_metrics_tags: Option<Arc<MetricTagMap>>,
+    pub(crate) max_connections: usize,
+    pub(crate) accept_rate_limit: Option<RateLimitQuotaConfig>,
+    pub(crate) connect_duration_stats: HistogramMetricsConfig,
+    pub(crate) detailed_transfer_metrics: Option<TransferMetricsConfig>,
+    pub(crate) accept_error_log_rate: Option<NonZeroU32>,
+    pub(crate) upstream_fallback: TcpTProxyUpstreamFallback,
+    pub(crate) connect_retry_count: usize,
+    pub(crate) connect_retry_delay: Duration,
+    pub(crate) upstream_connect_timeout: Option<Duration>,
+    pub(crate) upstream_connect_attempt_timeout: Option<Duration>,
+    pub(crate) upstream_connect_timeout_reset: bool,
+    pub(crate) normalize_mapped_dst: bool,
+    #[cfg(target_os = "linux")]
+    pub(crate) redirect_original_dst: bool,
+}
+
+impl TcpTProxyServerConfig {
+    fn new(position: Option<YamlDocPosition>) -> Self {
+        TcpTProxyServerConfig {
+            name: NodeName::default(),
+            position,
+            escaper: NodeName::default(),
+            auditor: NodeName::default(),
+            shared_logger: None,
+            listen: TcpListenConfig::default(),
+            listen_in_worker: false,
+            listen_worker: ListenWorkerConfig::default(),
+            ingress_net_filter: None,
+            tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
+            task_idle_check_duration: IDLE_CHECK_DEFAULT_DURATION,
+            task_idle_check_jitter: 0,
+            task_idle_max_count: IDLE_CHECK_DEFAULT_MAX_COUNT,
+            idle_overrides: TaskIdleOverrides::default(),
+            flush_task_log_on_created: false,
+            flush_task_log_on_connected: false,
+            task_log_flush_interval: None,
+            task_log_sample: TaskLogSampleConfig::default(),
+            client_error_actions: ClientErrorActionMap::default(),
+            tcp_copy: Default::default(),
+            client_misc_opts: Default::default(),
+            upstream_misc_opts: None,
+            extra_metrics_tags: None,
+            max_connections: 0,
+            accept_rate_limit: None,
+            connect_duration_stats: HistogramMetricsConfig::default(),
+            detailed_transfer_metrics: None,
+            accept_error_log_rate: None,
+            upstream_fallback: TcpTProxyUpstreamFallback::default(),
+            connect_retry_count: 0,
+            connect_retry_delay: Duration::from_millis(100),
+            upstream_connect_timeout: None,
+            upstream_connect_attempt_timeout: None,
+            upstream_connect_timeout_reset: true,
+            normalize_mapped_dst: true,
+            #[cfg(target_os = "linux")]
+            redirect_original_dst: false,
+        }
+    }
+
+    pub(crate) fn parse(
+        map: &yaml::Hash,
+        position: Option<YamlDocPosition>,
+    ) -> anyhow::Result<Self> {
+        let mut server = TcpTProxyServerConfig::new(position);
+
+        g3_yaml::foreach_kv(map, |k, v| server.set(k, v))?;
+
+        server.check()?;
+        Ok(server)
+    }
+
+    fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match SERVER_CONFIG_SCHEMA.canonical_key(k)? {
+            super::CONFIG_KEY_SERVER_TYPE => Ok(()),
+            super::CONFIG_KEY_SERVER_NAME => {
+                self.name = g3_yaml::value::as_metric_node_name(v)?;
+                Ok(())
+            }
+            "escaper" => {
+                self.escaper = g3_yaml::value::as_metric_node_name(v)?;
+                Ok(())
+            }
+            "auditor" => {
+                self.auditor = g3_yaml::value::as_metric_node_name(v)?;
+                Ok(())
+            }
+            "shared_logger" => {
+                let name = g3_yaml::value::as_ascii(v)?;
+                self.shared_logger = Some(name);
+                Ok(())
+            }
+            "extra_metrics_tags" => {
+                let tags = g3_yaml::value::as_static_metrics_tags(v)
+                    .context(format!("invalid static metrics tags value for key {k}"))?;
+                self.extra_metrics_tags = Some(Arc::new(tags));
+                Ok(())
+            }
+            "listen" => {
+                self.listen = g3_yaml::value::as_tcp_listen_config(v)
+                    .context(format!("invalid tcp listen config value for key {k}"))?;
+                Ok(())
+            }
+            "listen_in_worker" => {
+                self.listen_in_worker = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "listen_worker_set" => self
+                .listen_worker
+                .set_worker_set(v)
+                .context(format!("invalid worker set value for key {k}")),
+            "connect_duration_stats" => {
+                self.connect_duration_stats = g3_yaml::value::as_histogram_metrics_config(v)
+                    .context(format!(
+                        "invalid histogram metrics config value for key {k}"
+                    ))?;
+                Ok(())
+            }
+            "detailed_transfer_metrics" => {
+                self.detailed_transfer_metrics = TransferMetricsConfig::parse_yaml(v)
+                    .context(format!("invalid transfer metrics config value for key {k}"))?;
+                Ok(())
+            }
+            "ingress_network_filter" => {
+                let filter = g3_yaml::value::acl::as_ingress_network_rule_builder(v).context(
+                    format!("invalid ingress network acl rule value for key {k}"),
+                )?;
+                self.ingress_net_filter = Some(filter);
+                Ok(())
+            }
+            "tcp_sock_speed_limit" => {
+                self.tcp_sock_speed_limit = g3_yaml::value::as_tcp_sock_speed_limit(v)
+                    .context(format!("invalid tcp socket speed limit value for key {k}"))?;
+                Ok(())
+            }
+            "tcp_conn_speed_limit" => {
+                warn!("deprecated config key '{k}', please use 'tcp_sock_speed_limit' instead");
+                self.set("tcp_sock_speed_limit", v)
+            }
+            "tcp_copy_buffer_size" => {
+                let buffer_size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                self.tcp_copy.set_buffer_size(buffer_size);
+                Ok(())
+            }
+            "tcp_copy_yield_size" => {
+                let yield_size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                self.tcp_copy.set_yield_size(yield_size);
+                Ok(())
+            }
+            "tcp_misc_opts" => {
+                // set for both the client and the upstream sockets
+                let opts = g3_yaml::value::as_tcp_misc_sock_opts(v)
+                    .context(format!("invalid tcp misc sock opts value for key {k}"))?;
+                self.upstream_misc_opts = Some(opts.clone());
+                self.client_misc_opts = opts;
+                Ok(())
+            }
+            "client_misc_opts" => {
+                self.client_misc_opts = g3_yaml::value::as_tcp_misc_sock_opts(v)
+                    .context(format!("invalid tcp misc sock opts value for key {k}"))?;
+                Ok(())
+            }
+            "upstream_misc_opts" => {
+                let opts = g3_yaml::value::as_tcp_misc_sock_opts(v)
+                    .context(format!("invalid tcp misc sock opts value for key {k}"))?;
+                self.upstream_misc_opts = Some(opts);
+                Ok(())
+            }
+            "task_idle_check_duration" => {
+                self.task_idle_check_duration = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "task_idle_check_jitter" => {
+                let jitter =
+                    g3_yaml::value::as_u8(v).context(format!("invalid u8 value for key {k}"))?;
+                self.task_idle_check_jitter = jitter.min(100);
+                Ok(())
+            }
+            "task_idle_max_count" => {
+                self.task_idle_max_count = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                Ok(())
+            }
+            "idle_overrides" => {
+                self.idle_overrides = TaskIdleOverrides::parse_yaml(v)
+                    .context(format!("invalid task idle overrides value for key {k}"))?;
+                Ok(())
+            }
+            "flush_task_log_on_created" => {
+                self.flush_task_log_on_created = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "flush_task_log_on_connected" => {
+                self.flush_task_log_on_connected = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "task_log_flush_interval" => {
+                let interval = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                self.task_log_flush_interval = Some(interval);
+                Ok(())
+            }
+            "task_log_sample_ratio" => self
+                .task_log_sample
+                .parse_ratio(v)
+                .context(format!("invalid ratio value for key {k}")),
+            "always_log_errors" => {
+                let enable = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                self.task_log_sample.set_always_log_errors(enable);
+                Ok(())
+            }
+            "client_error_actions" => {
+                self.client_error_actions = ClientErrorActionMap::parse_yaml(v)
+                    .context(format!("invalid client error actions value for key {k}"))?;
+                Ok(())
+            }
+            "max_connections" => {
+                self.max_connections = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                Ok(())
+            }
+            "accept_rate_limit" => {
+                let quota = g3_yaml::value::as_rate_limit_quota(v)
+                    .context(format!("invalid rate limit quota value for key {k}"))?;
+                self.accept_rate_limit = Some(quota);
+                Ok(())
+            }
+            "accept_error_log_rate" => {
+                let rate =
+                    g3_yaml::value::as_u32(v).context(format!("invalid u32 value for key {k}"))?;
+                self.accept_error_log_rate = NonZeroU32::new(rate);
+                Ok(())
+            }
+            "upstream_fallback" => {
+                self.upstream_fallback = TcpTProxyUpstreamFallback::parse_yaml(v)
+                    .context(format!("invalid upstream fallback value for key {k}"))?;
+                Ok(())
+            }
+            "connect_retry_count" => {
+                self.connect_retry_count = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                Ok(())
+            }
+            "connect_retry_delay" => {
+                self.connect_retry_delay = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "upstream_connect_timeout" => {
+                let timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                self.upstream_connect_timeout = (!timeout.is_zero()).then_some(timeout);
+                Ok(())
+            }
+            "upstream_connect_attempt_timeout" => {
+                let timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                self.upstream_connect_attempt_timeout = (!timeout.is_zero()).then_some(timeout);
+                Ok(())
+            }
+            "upstream_connect_timeout_reset" => {
+                self.upstream_connect_timeout_reset = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "normalize_mapped_dst" => {
+                self.normalize_mapped_dst = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            #[cfg(target_os = "linux")]
+            "redirect_original_dst" => {
+                self.redirect_original_dst = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name is not set"));
+        }
+        if self.escaper.is_empty() {
+            return Err(anyhow!("escaper is not set"));
+        }
+        if self.task_idle_check_duration > IDLE_CHECK_MAXIMUM_DURATION {
+            self.task_idle_check_duration = IDLE_CHECK_MAXIMUM_DURATION;
+        }
+        self.client_error_actions.check_no_socks_reply()?;
+
+        #[cfg(target_os = "linux")]
+        self.listen.set_transparent();
+        self.listen.check()?;
+        self.listen_worker
+            .check()
+            .context("invalid listen worker config")?;
+
+        Ok(())
+    }
+}
+
+impl ServerConfig for TcpTProxyServerConfig {
+    fn name(&self) -> &NodeName {
+        &self.name
+    }
+
+    fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
+    fn r#type(&self) -> &'static str {
+        SERVER_CONFIG_TYPE
+    }
+
+    fn escaper(&self) -> &NodeName {
+        &self.escaper
+    }
+
+    fn user_group(&self) -> &NodeName {
+        Default::default()
+    }
+
+    fn auditor(&self) -> &NodeName {
+        &self.auditor
+    }
+
+    fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
+        let AnyServerConfig::TcpTProxy(new) = new else {
+            return ServerConfigDiffAction::SpawnNew;
+        };
+
+        if self.eq(new) {
+            return ServerConfigDiffAction::NoAction;
+        }
+
+        if self.listen != new.listen || self.listen_worker != new.listen_worker {
+            return ServerConfigDiffAction::ReloadAndRespawn;
+        }
+
+        ServerConfigDiffAction::ReloadNoRespawn
+    }
+
+    fn shared_logger(&self) -> Option<&str> {
+        self.shared_logger.as_ref().map(|s| s.as_str())
+    }
+
+    fn task_log_flush_interval(&self) -> Option<Duration> {
+        self.task_log_flush_interval
+    }
+
+    #[inline]
+    fn limited_copy_config(&self) -> StreamCopyConfig {
+        self.tcp_copy
+    }
+
+    #[inline]
+    fn task_max_idle_count(&self) -> usize {
+        self.task_idle_max_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    use crate::config::server::{ClientErrorAction, ClientErrorClass};
+
+    #[test]
+    fn schema_keys() {
+        for key in SERVER_CONFIG_SCHEMA.keys {
+            let v = &YamlLoader::load_from_str(key.example).unwrap()[0];
+            for name in std::iter::once(&key.name).chain(key.aliases) {
+                let mut config = TcpTProxyServerConfig::new(None);
+                config
+                    .set(name, v)
+                    .unwrap_or_else(|e| panic!("invalid sample value for key {name}: {e:?}"));
+            }
+        }
+        assert!(SERVER_CONFIG_SCHEMA.canonical_key("no_such_key").is_err());
+    }
+
+    #[test]
+    fn misc_opts_per_direction() {
+        let load = |s: &str| YamlLoader::load_from_str(s).unwrap().remove(0);
+
+        let mut config = TcpTProxyServerConfig::new(None);
+        config
+            .set("tcp_misc_opts", &load("{no_delay: true, ttl: 32}"))
+            .unwrap();
+        assert_eq!(config.client_misc_opts.no_delay, Some(true));
+        let upstream = config.upstream_misc_opts.as_ref().unwrap();
+        assert_eq!(upstream, &config.client_misc_opts);
+
+        config
+            .set("upstream_misc_opts", &load("{no_delay: false}"))
+            .unwrap();
+        assert_eq!(config.client_misc_opts.no_delay, Some(true));
+        assert_eq!(config.client_misc_opts.time_to_live, Some(32));
+        let upstream = config.upstream_misc_opts.as_ref().unwrap();
+        assert_eq!(upstream.no_delay, Some(false));
+        assert_eq!(upstream.time_to_live, None);
+
+        let mut config = TcpTProxyServerConfig::new(None);
+        config
+            .set("client_misc_opts", &load("{no_delay: true}"))
+            .unwrap();
+        assert_eq!(config.client_misc_opts.no_delay, Some(true));
+        assert!(config.upstream_misc_opts.is_none());
+    }
+
+    #[test]
+    fn client_error_actions() {
+        let load = |s: &str| YamlLoader::load_from_str(s).unwrap().remove(0);
+
+        let mut config = TcpTProxyServerConfig::new(None);
+        assert_eq!(config.client_error_actions, ClientErrorActionMap::default());
+        config
+            .set(
+                "client_error_actions",
+                &load("{connect_timeout: reset, adaptation_failed: close, lifetime_expired: rst}"),
+            )
+            .unwrap();
+        let actions = &config.client_error_actions;
+        assert_eq!(
+            actions.get(ClientErrorClass::UpstreamConnectTimeout),
+            Some(ClientErrorAction::Reset)
+        );
+        assert_eq!(
+            actions.get(ClientErrorClass::AdaptationFailed),
+            Some(ClientErrorAction::Close)
+        );
+        assert_eq!(
+            actions.get(ClientErrorClass::LifetimeExpired),
+            Some(ClientErrorAction::Reset)
+        );
+        assert!(actions.get(ClientErrorClass::IngressDenied).is_none());
+        assert!(actions.check_no_socks_reply().is_ok());
+
+        config
+            .set(
+                "client_error_actions",
+                &load("{ingress_denied: {socks_reply: 2}}"),
+            )
+            .unwrap();
+        assert!(config.client_error_actions.check_no_socks_reply().is_err());
+    }
+}
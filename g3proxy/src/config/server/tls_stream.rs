@@ -18,13 +18,13 @@ use g3_types::collection::SelectivePickPolicy;
 use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::net::{
     Host, OpensslClientConfigBuilder, RustlsServerConfigBuilder, TcpListenConfig, TcpMiscSockOpts,
-    TcpSockSpeedLimitConfig, WeightedUpstreamAddr,
+    TcpSockSpeedLimitConfig, TlsCertStatus, WeightedUpstreamAddr,
 };
 use g3_yaml::YamlDocPosition;
 
 use super::{
     AnyServerConfig, IDLE_CHECK_DEFAULT_DURATION, IDLE_CHECK_DEFAULT_MAX_COUNT,
-    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction,
+    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction, TaskLogSampleConfig,
 };
 
 const SERVER_CONFIG_TYPE: &str = "TlsStream";
@@ -47,10 +47,12 @@ pub(crate) struct TlsStreamServerConfig {
     pub(crate) upstream_tls_name: Option<Host>,
     pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
     pub(crate) task_idle_check_duration: Duration,
+    pub(crate) task_idle_check_jitter: u8,
     pub(crate) task_idle_max_count: usize,
     pub(crate) flush_task_log_on_created: bool,
     pub(crate) flush_task_log_on_connected: bool,
     pub(crate) task_log_flush_interval: Option<Duration>,
+    pub(crate) task_log_sample: TaskLogSampleConfig,
     pub(crate) tcp_copy: StreamCopyConfig,
     pub(crate) tcp_misc_opts: TcpMiscSockOpts,
     pub(crate) extra_metrics_tags: Option<Arc<MetricTagMap>>,
@@ -75,10 +77,12 @@ impl TlsStreamServerConfig {
             upstream_tls_name: None,
             tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
             task_idle_check_duration: IDLE_CHECK_DEFAULT_DURATION,
+            task_idle_check_jitter: 0,
             task_idle_max_count: IDLE_CHECK_DEFAULT_MAX_COUNT,
             flush_task_log_on_created: false,
             flush_task_log_on_connected: false,
             task_log_flush_interval: None,
+            task_log_sample: TaskLogSampleConfig::default(),
             tcp_copy: Default::default(),
             tcp_misc_opts: Default::default(),
             extra_metrics_tags: None,
@@ -222,6 +226,12 @@ impl TlsStreamServerConfig {
                     .context(format!("invalid humanize duration value for key {k}"))?;
                 Ok(())
             }
+            "task_idle_check_jitter" => {
+                let jitter =
+                    g3_yaml::value::as_u8(v).context(format!("invalid u8 value for key {k}"))?;
+                self.task_idle_check_jitter = jitter.min(100);
+                Ok(())
+            }
             "task_idle_max_count" => {
                 self.task_idle_max_count = g3_yaml::value::as_usize(v)
                     .context(format!("invalid usize value for key {k}"))?;
@@ -241,6 +251,16 @@ impl TlsStreamServerConfig {
                 self.task_log_flush_interval = Some(interval);
                 Ok(())
             }
+            "task_log_sample_ratio" => self
+                .task_log_sample
+                .parse_ratio(v)
+                .context(format!("invalid ratio value for key {k}")),
+            "always_log_errors" => {
+                let enable = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                self.task_log_sample.set_always_log_errors(enable);
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
@@ -331,4 +351,12 @@ impl ServerConfig for TlsStreamServerConfig {
     fn task_max_idle_count(&self) -> usize {
         self.task_idle_max_count
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = self.server_tls_config.cert_status()?;
+        if let Some(builder) = &self.client_tls_config {
+            all.extend(builder.cert_status()?);
+        }
+        Ok(all)
+    }
 }
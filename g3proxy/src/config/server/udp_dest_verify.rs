@@ -0,0 +1,231 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+const DEFAULT_CACHE_SIZE: NonZeroUsize = NonZeroUsize::new(4096).unwrap();
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum UdpDestVerifyMode {
+    #[default]
+    None,
+    DnsPtrAllowlist,
+    TcpProbe,
+}
+
+impl FromStr for UdpDestVerifyMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(UdpDestVerifyMode::None),
+            "dns_ptr_allowlist" | "ptr_allowlist" => Ok(UdpDestVerifyMode::DnsPtrAllowlist),
+            "tcp_probe" => Ok(UdpDestVerifyMode::TcpProbe),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The policy to verify the new udp relay destinations before sending out any packet to them
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct UdpDestVerifyConfig {
+    pub(crate) mode: UdpDestVerifyMode,
+    /// the allowed domain suffixes, in lower case and without the leading dot
+    pub(crate) ptr_allowed_suffixes: Vec<String>,
+    pub(crate) probe_timeout: Duration,
+    pub(crate) cache_ttl: Duration,
+    pub(crate) cache_size: NonZeroUsize,
+    pub(crate) block_duration: Duration,
+    /// the max number of packets to hold for each destination while verifying
+    pub(crate) queue_size: usize,
+}
+
+impl Default for UdpDestVerifyConfig {
+    fn default() -> Self {
+        UdpDestVerifyConfig {
+            mode: UdpDestVerifyMode::None,
+            ptr_allowed_suffixes: Vec::new(),
+            probe_timeout: Duration::from_secs(2),
+            cache_ttl: Duration::from_secs(300),
+            cache_size: DEFAULT_CACHE_SIZE,
+            block_duration: Duration::from_secs(60),
+            queue_size: 4,
+        }
+    }
+}
+
+impl UdpDestVerifyConfig {
+    #[inline]
+    pub(crate) fn enabled(&self) -> bool {
+        self.mode != UdpDestVerifyMode::None
+    }
+
+    /// Check if the host name is in the allowed suffix list
+    pub(crate) fn ptr_name_allowed(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('.').to_lowercase();
+        self.ptr_allowed_suffixes.iter().any(|suffix| {
+            if let Some(prefix) = name.strip_suffix(suffix.as_str()) {
+                prefix.is_empty() || prefix.ends_with('.')
+            } else {
+                false
+            }
+        })
+    }
+
+    fn add_ptr_allowed_suffix(&mut self, v: &Yaml) -> anyhow::Result<()> {
+        let s = g3_yaml::value::as_string(v)?;
+        let suffix = s.trim_matches('.').to_lowercase();
+        if suffix.is_empty() {
+            return Err(anyhow!("empty domain suffix"));
+        }
+        self.ptr_allowed_suffixes.push(suffix);
+        Ok(())
+    }
+
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let mut config = UdpDestVerifyConfig::default();
+        match v {
+            Yaml::String(s) => {
+                config.mode = UdpDestVerifyMode::from_str(s)
+                    .map_err(|_| anyhow!("invalid udp dest verify mode {s}"))?;
+            }
+            Yaml::Hash(map) => {
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "mode" => {
+                        let s = g3_yaml::value::as_string(v)?;
+                        config.mode = UdpDestVerifyMode::from_str(&s)
+                            .map_err(|_| anyhow!("invalid udp dest verify mode {s}"))?;
+                        Ok(())
+                    }
+                    "ptr_allowed_suffixes" | "allowed_suffixes" | "ptr_allowlist" => {
+                        config.ptr_allowed_suffixes.clear();
+                        if let Yaml::Array(seq) = v {
+                            for (i, v) in seq.iter().enumerate() {
+                                config
+                                    .add_ptr_allowed_suffix(v)
+                                    .context(format!("invalid domain suffix value for {k}#{i}"))?;
+                            }
+                        } else {
+                            config
+                                .add_ptr_allowed_suffix(v)
+                                .context(format!("invalid domain suffix value for key {k}"))?;
+                        }
+                        Ok(())
+                    }
+                    "probe_timeout" | "tcp_probe_timeout" => {
+                        config.probe_timeout = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "cache_ttl" => {
+                        config.cache_ttl = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "cache_size" => {
+                        config.cache_size = g3_yaml::value::as_nonzero_usize(v)
+                            .context(format!("invalid nonzero usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "block_duration" | "block_time" => {
+                        config.block_duration = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "queue_size" | "hold_queue_size" => {
+                        config.queue_size = g3_yaml::value::as_usize(v)
+                            .context(format!("invalid usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for udp dest verify config should be 'string' or 'map'"
+                ));
+            }
+        }
+
+        config.check()?;
+        Ok(config)
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        match self.mode {
+            UdpDestVerifyMode::None => {}
+            UdpDestVerifyMode::DnsPtrAllowlist => {
+                if self.ptr_allowed_suffixes.is_empty() {
+                    return Err(anyhow!(
+                        "allowed domain suffixes should be set for dns_ptr_allowlist mode"
+                    ));
+                }
+            }
+            UdpDestVerifyMode::TcpProbe => {
+                if self.probe_timeout.is_zero() {
+                    return Err(anyhow!("probe timeout should not be zero"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<UdpDestVerifyConfig> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        UdpDestVerifyConfig::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_ok() {
+        let config = parse("tcp_probe").unwrap();
+        assert_eq!(config.mode, UdpDestVerifyMode::TcpProbe);
+        assert!(config.enabled());
+
+        let config = parse("none").unwrap();
+        assert!(!config.enabled());
+
+        let config = parse(
+            "mode: dns_ptr_allowlist\nptr_allowed_suffixes: [.Example.NET, example.org]\n\
+             cache_ttl: 10s\ncache_size: 16\nblock_duration: 1m\nqueue_size: 2\n",
+        )
+        .unwrap();
+        assert_eq!(config.mode, UdpDestVerifyMode::DnsPtrAllowlist);
+        assert_eq!(config.ptr_allowed_suffixes, ["example.net", "example.org"]);
+        assert_eq!(config.cache_ttl, Duration::from_secs(10));
+        assert_eq!(config.cache_size.get(), 16);
+        assert_eq!(config.block_duration, Duration::from_secs(60));
+        assert_eq!(config.queue_size, 2);
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("dns_ptr").is_err());
+        assert!(parse("dns_ptr_allowlist").is_err());
+        assert!(parse("mode: tcp_probe\nprobe_timeout: 0\n").is_err());
+        assert!(parse("mode: none\ncache_size: 0\n").is_err());
+        assert!(parse("mode: none\nptr_allowed_suffixes: ['.']\n").is_err());
+        assert!(parse("1").is_err());
+    }
+
+    #[test]
+    fn ptr_name_allowed() {
+        let config = parse("mode: dns_ptr_allowlist\nptr_allowed_suffixes: example.net\n").unwrap();
+        assert!(config.ptr_name_allowed("example.net"));
+        assert!(config.ptr_name_allowed("Host.Example.NET."));
+        assert!(!config.ptr_name_allowed("badexample.net"));
+        assert!(!config.ptr_name_allowed("example.net.evil.com"));
+    }
+}
@@ -0,0 +1,244 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+
+use anyhow::{Context, anyhow};
+use regex::Regex;
+use yaml_rust::Yaml;
+
+use g3_types::metrics::NodeName;
+use g3_types::net::PortRange;
+
+#[derive(Clone, Debug)]
+enum UdpPortRangeMatch {
+    UserGroup(NodeName),
+    User(Regex),
+}
+
+impl PartialEq for UdpPortRangeMatch {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (UdpPortRangeMatch::UserGroup(a), UdpPortRangeMatch::UserGroup(b)) => a == b,
+            (UdpPortRangeMatch::User(a), UdpPortRangeMatch::User(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for UdpPortRangeMatch {}
+
+/// The udp relay port range for the tasks of a user group or users with matched names
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct UdpPortRangeTier {
+    name: Arc<str>,
+    match_rule: UdpPortRangeMatch,
+    port_range: PortRange,
+}
+
+impl UdpPortRangeTier {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for 'udp port range tier' should be 'map'"
+            ));
+        };
+
+        let mut name = None;
+        let mut match_rule = None;
+        let mut port_range = None;
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "name" | "tier" => {
+                let value = g3_yaml::value::as_metric_tag_value(v)
+                    .context(format!("invalid metric tag value for key {k}"))?;
+                name = Some(Arc::from(value.as_str()));
+                Ok(())
+            }
+            "user_group" => {
+                if match_rule.is_some() {
+                    return Err(anyhow!("only one of user and user_group can be set"));
+                }
+                let name = g3_yaml::value::as_metric_node_name(v)
+                    .context(format!("invalid user group name value for key {k}"))?;
+                match_rule = Some(UdpPortRangeMatch::UserGroup(name));
+                Ok(())
+            }
+            "user" | "user_name" | "username" => {
+                if match_rule.is_some() {
+                    return Err(anyhow!("only one of user and user_group can be set"));
+                }
+                let regex = g3_yaml::value::as_regex(v)
+                    .context(format!("invalid regex value for key {k}"))?;
+                match_rule = Some(UdpPortRangeMatch::User(regex));
+                Ok(())
+            }
+            "port_range" | "range" => {
+                let range = if let Yaml::Integer(_) = v {
+                    // a single port range is allowed here
+                    let port = g3_yaml::value::as_u16(v)
+                        .context(format!("invalid port value for key {k}"))?;
+                    if port == 0 {
+                        return Err(anyhow!("the port should not be 0"));
+                    }
+                    PortRange::new(port, port)
+                } else {
+                    g3_yaml::value::as_port_range(v)
+                        .context(format!("invalid port range value for key {k}"))?
+                };
+                port_range = Some(range);
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        let Some(match_rule) = match_rule else {
+            return Err(anyhow!("neither user nor user_group is set"));
+        };
+        let Some(port_range) = port_range else {
+            return Err(anyhow!("no port_range set"));
+        };
+        let name = match name {
+            Some(name) => name,
+            None => match &match_rule {
+                UdpPortRangeMatch::UserGroup(group) => Arc::from(group.as_str()),
+                UdpPortRangeMatch::User(_) => {
+                    return Err(anyhow!("name is required for user match tier"));
+                }
+            },
+        };
+        Ok(UdpPortRangeTier {
+            name,
+            match_rule,
+            port_range,
+        })
+    }
+
+    /// Get the name to be recorded in task logs and used as the metrics tag value
+    #[inline]
+    pub(crate) fn name(&self) -> &Arc<str> {
+        &self.name
+    }
+
+    #[inline]
+    pub(crate) fn port_range(&self) -> PortRange {
+        self.port_range
+    }
+
+    fn is_match(&self, user_name: &str, user_group: &NodeName) -> bool {
+        match &self.match_rule {
+            UdpPortRangeMatch::UserGroup(name) => user_group == name,
+            UdpPortRangeMatch::User(regex) => regex.is_match(user_name),
+        }
+    }
+}
+
+/// The udp relay port ranges for users, which will be resolved when setting up the relay socket.
+///
+/// The first matched tier will be used, and there will be no fallback to other port ranges
+/// if all ports in the selected range are in use.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct UdpPortRangeMap {
+    tiers: Vec<UdpPortRangeTier>,
+}
+
+impl UdpPortRangeMap {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let mut tiers = Vec::new();
+        match v {
+            Yaml::Array(seq) => {
+                for (i, v) in seq.iter().enumerate() {
+                    let tier = UdpPortRangeTier::parse_yaml(v)
+                        .context(format!("invalid udp port range tier value for #{i}"))?;
+                    tiers.push(tier);
+                }
+            }
+            Yaml::Hash(_) => {
+                let tier = UdpPortRangeTier::parse_yaml(v)?;
+                tiers.push(tier);
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for 'udp port range map' should be 'seq' or 'map'"
+                ));
+            }
+        }
+        Ok(UdpPortRangeMap { tiers })
+    }
+
+    pub(crate) fn select(
+        &self,
+        user_name: &str,
+        user_group: &NodeName,
+    ) -> Option<&UdpPortRangeTier> {
+        self.tiers
+            .iter()
+            .find(|tier| tier.is_match(user_name, user_group))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use yaml_rust::YamlLoader;
+
+    fn load_yaml(s: &str) -> Yaml {
+        YamlLoader::load_from_str(s).unwrap().pop().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let map = UdpPortRangeMap::parse_yaml(&load_yaml(
+            r#"
+- user_group: gold
+  port_range: 20000-20999
+- name: silver
+  user: ^silver-
+  port_range: 21000
+"#,
+        ))
+        .unwrap();
+        assert_eq!(map.tiers.len(), 2);
+        assert_eq!(map.tiers[0].name().as_ref(), "gold");
+        assert_eq!(map.tiers[0].port_range(), PortRange::new(20000, 20999));
+        assert_eq!(map.tiers[1].name().as_ref(), "silver");
+        assert_eq!(map.tiers[1].port_range(), PortRange::new(21000, 21000));
+
+        assert!(UdpPortRangeMap::parse_yaml(&load_yaml("user_group: gold")).is_err());
+        assert!(UdpPortRangeMap::parse_yaml(&load_yaml("port_range: 20000-20999")).is_err());
+        assert!(
+            UdpPortRangeMap::parse_yaml(&load_yaml("{name: a, user: a, port_range: 0}")).is_err()
+        );
+        assert!(UdpPortRangeMap::parse_yaml(&load_yaml("{user: a, port_range: 20000}")).is_err());
+        assert!(
+            UdpPortRangeMap::parse_yaml(&load_yaml(
+                "{name: a, user: a, user_group: b, port_range: 20000-20999}"
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn select() {
+        let map = UdpPortRangeMap::parse_yaml(&load_yaml(
+            r#"
+- name: vip
+  user: ^vip-
+  port_range: 20000-20999
+- user_group: gold
+  port_range: 21000-21999
+"#,
+        ))
+        .unwrap();
+        let gold = NodeName::from_str("gold").unwrap();
+        let other = NodeName::from_str("other").unwrap();
+
+        let tier = map.select("vip-a", &gold).unwrap();
+        assert_eq!(tier.name().as_ref(), "vip");
+        let tier = map.select("a", &gold).unwrap();
+        assert_eq!(tier.name().as_ref(), "gold");
+        assert!(map.select("a", &other).is_none());
+    }
+}
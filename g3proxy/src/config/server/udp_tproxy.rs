@@ -1,6 +1,6 @@
 /*
  * SPDX-License-Identifier: Apache-2.0
- * Copyright 2024-2025 ByteDance and/or its affiliates.
+ * Copyright 2025 ByteDance and/or its affiliates.
  */
 
 use std::sync::Arc;
@@ -8,62 +8,71 @@ use std::time::Duration;
 
 use anyhow::{Context, anyhow};
 use ascii::AsciiString;
-use log::warn;
 use yaml_rust::{Yaml, yaml};
 
-use g3_io_ext::StreamCopyConfig;
+use g3_io_ext::LimitedUdpRelayConfig;
 use g3_types::acl::AclNetworkRuleBuilder;
 use g3_types::metrics::{MetricTagMap, NodeName};
-use g3_types::net::{TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig};
+use g3_types::net::{
+    SocketBufferConfig, UdpListenConfig, UdpMiscSockOpts, UdpSockSpeedLimitConfig,
+};
 use g3_yaml::YamlDocPosition;
 
 use super::{
     AnyServerConfig, IDLE_CHECK_DEFAULT_DURATION, IDLE_CHECK_DEFAULT_MAX_COUNT,
-    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction,
+    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, ServerConfigDiffAction, TaskLogSampleConfig,
 };
 
-const SERVER_CONFIG_TYPE: &str = "TcpTProxy";
+const SERVER_CONFIG_TYPE: &str = "UdpTProxy";
+
+const DEFAULT_SESSION_QUEUE_SIZE: usize = 64;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub(crate) struct TcpTProxyServerConfig {
+pub(crate) struct UdpTProxyServerConfig {
     name: NodeName,
     position: Option<YamlDocPosition>,
     pub(crate) escaper: NodeName,
-    pub(crate) auditor: NodeName,
     pub(crate) shared_logger: Option<AsciiString>,
-    pub(crate) listen: TcpListenConfig,
+    pub(crate) listen: UdpListenConfig,
     pub(crate) listen_in_worker: bool,
     pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
-    pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
+    pub(crate) udp_socket_buffer: SocketBufferConfig,
+    pub(crate) udp_sock_speed_limit: UdpSockSpeedLimitConfig,
+    pub(crate) udp_relay: LimitedUdpRelayConfig,
+    pub(crate) udp_misc_opts: UdpMiscSockOpts,
+    pub(crate) session_queue_size: usize,
     pub(crate) task_idle_check_duration: Duration,
+    pub(crate) task_idle_check_jitter: u8,
     pub(crate) task_idle_max_count: usize,
     pub(crate) flush_task_log_on_created: bool,
     pub(crate) flush_task_log_on_connected: bool,
     pub(crate) task_log_flush_interval: Option<Duration>,
-    pub(crate) tcp_copy: StreamCopyConfig,
-    pub(crate) tcp_misc_opts: TcpMiscSockOpts,
+    pub(crate) task_log_sample: TaskLogSampleConfig,
     pub(crate) extra_metrics_tags: Option<Arc<MetricTagMap>>,
 }
 
-impl TcpTProxyServerConfig {
+impl UdpTProxyServerConfig {
     fn new(position: Option<YamlDocPosition>) -> Self {
-        TcpTProxyServerConfig {
+        UdpTProxyServerConfig {
             name: NodeName::default(),
             position,
             escaper: NodeName::default(),
-            auditor: NodeName::default(),
             shared_logger: None,
-            listen: TcpListenConfig::default(),
+            listen: UdpListenConfig::default(),
             listen_in_worker: false,
             ingress_net_filter: None,
-            tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
+            udp_socket_buffer: SocketBufferConfig::default(),
+            udp_sock_speed_limit: UdpSockSpeedLimitConfig::default(),
+            udp_relay: Default::default(),
+            udp_misc_opts: Default::default(),
+            session_queue_size: DEFAULT_SESSION_QUEUE_SIZE,
             task_idle_check_duration: IDLE_CHECK_DEFAULT_DURATION,
+            task_idle_check_jitter: 0,
             task_idle_max_count: IDLE_CHECK_DEFAULT_MAX_COUNT,
             flush_task_log_on_created: false,
             flush_task_log_on_connected: false,
             task_log_flush_interval: None,
-            tcp_copy: Default::default(),
-            tcp_misc_opts: Default::default(),
+            task_log_sample: TaskLogSampleConfig::default(),
             extra_metrics_tags: None,
         }
     }
@@ -72,7 +81,7 @@ impl TcpTProxyServerConfig {
         map: &yaml::Hash,
         position: Option<YamlDocPosition>,
     ) -> anyhow::Result<Self> {
-        let mut server = TcpTProxyServerConfig::new(position);
+        let mut server = UdpTProxyServerConfig::new(position);
 
         g3_yaml::foreach_kv(map, |k, v| server.set(k, v))?;
 
@@ -91,10 +100,6 @@ impl TcpTProxyServerConfig {
                 self.escaper = g3_yaml::value::as_metric_node_name(v)?;
                 Ok(())
             }
-            "auditor" => {
-                self.auditor = g3_yaml::value::as_metric_node_name(v)?;
-                Ok(())
-            }
             "shared_logger" => {
                 let name = g3_yaml::value::as_ascii(v)?;
                 self.shared_logger = Some(name);
@@ -107,8 +112,8 @@ impl TcpTProxyServerConfig {
                 Ok(())
             }
             "listen" => {
-                self.listen = g3_yaml::value::as_tcp_listen_config(v)
-                    .context(format!("invalid tcp listen config value for key {k}"))?;
+                self.listen = g3_yaml::value::as_udp_listen_config(v)
+                    .context(format!("invalid udp listen config value for key {k}"))?;
                 Ok(())
             }
             "listen_in_worker" => {
@@ -122,30 +127,56 @@ impl TcpTProxyServerConfig {
                 self.ingress_net_filter = Some(filter);
                 Ok(())
             }
-            "tcp_sock_speed_limit" => {
-                self.tcp_sock_speed_limit = g3_yaml::value::as_tcp_sock_speed_limit(v)
-                    .context(format!("invalid tcp socket speed limit value for key {k}"))?;
+            "udp_socket_buffer" => {
+                self.udp_socket_buffer = g3_yaml::value::as_socket_buffer_config(v)
+                    .context(format!("invalid socket buffer config value for key {k}"))?;
                 Ok(())
             }
-            "tcp_conn_speed_limit" | "tcp_conn_limit" | "conn_limit" => {
-                warn!("deprecated config key '{k}', please use 'tcp_sock_speed_limit' instead");
-                self.set("tcp_sock_speed_limit", v)
+            "udp_sock_speed_limit" => {
+                self.udp_sock_speed_limit = g3_yaml::value::as_udp_sock_speed_limit(v)
+                    .context(format!("invalid udp socket speed limit value for key {k}"))?;
+                Ok(())
             }
-            "tcp_copy_buffer_size" => {
-                let buffer_size = g3_yaml::humanize::as_usize(v)
+            "udp_relay_packet_size" => {
+                let packet_size = g3_yaml::humanize::as_usize(v)
                     .context(format!("invalid humanize usize value for key {k}"))?;
-                self.tcp_copy.set_buffer_size(buffer_size);
+                self.udp_relay.set_packet_size(packet_size);
                 Ok(())
             }
-            "tcp_copy_yield_size" => {
+            "udp_relay_yield_size" => {
                 let yield_size = g3_yaml::humanize::as_usize(v)
                     .context(format!("invalid humanize usize value for key {k}"))?;
-                self.tcp_copy.set_yield_size(yield_size);
+                self.udp_relay.set_yield_size(yield_size);
+                Ok(())
+            }
+            "udp_relay_batch_size" => {
+                let batch_size = g3_yaml::value::as_usize(v)?;
+                self.udp_relay.set_batch_size(batch_size);
+                Ok(())
+            }
+            "udp_relay_batch_size_min" => {
+                let batch_size = g3_yaml::value::as_usize(v)?;
+                self.udp_relay.set_batch_size_min(batch_size);
+                Ok(())
+            }
+            "udp_relay_batch_grow_threshold" => {
+                let threshold = g3_yaml::value::as_usize(v)?;
+                self.udp_relay.set_batch_grow_threshold(threshold);
+                Ok(())
+            }
+            "udp_relay_batch_shrink_threshold" => {
+                let threshold = g3_yaml::value::as_usize(v)?;
+                self.udp_relay.set_batch_shrink_threshold(threshold);
                 Ok(())
             }
-            "tcp_misc_opts" => {
-                self.tcp_misc_opts = g3_yaml::value::as_tcp_misc_sock_opts(v)
-                    .context(format!("invalid tcp misc sock opts value for key {k}"))?;
+            "udp_misc_opts" => {
+                self.udp_misc_opts = g3_yaml::value::as_udp_misc_sock_opts(v)
+                    .context(format!("invalid udp misc sock opts value for key {k}"))?;
+                Ok(())
+            }
+            "session_queue_size" => {
+                self.session_queue_size = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
                 Ok(())
             }
             "task_idle_check_duration" => {
@@ -153,6 +184,12 @@ impl TcpTProxyServerConfig {
                     .context(format!("invalid humanize duration value for key {k}"))?;
                 Ok(())
             }
+            "task_idle_check_jitter" => {
+                let jitter =
+                    g3_yaml::value::as_u8(v).context(format!("invalid u8 value for key {k}"))?;
+                self.task_idle_check_jitter = jitter.min(100);
+                Ok(())
+            }
             "task_idle_max_count" => {
                 self.task_idle_max_count = g3_yaml::value::as_usize(v)
                     .context(format!("invalid usize value for key {k}"))?;
@@ -172,6 +209,16 @@ impl TcpTProxyServerConfig {
                 self.task_log_flush_interval = Some(interval);
                 Ok(())
             }
+            "task_log_sample_ratio" => self
+                .task_log_sample
+                .parse_ratio(v)
+                .context(format!("invalid ratio value for key {k}")),
+            "always_log_errors" => {
+                let enable = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                self.task_log_sample.set_always_log_errors(enable);
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
@@ -186,8 +233,10 @@ impl TcpTProxyServerConfig {
         if self.task_idle_check_duration > IDLE_CHECK_MAXIMUM_DURATION {
             self.task_idle_check_duration = IDLE_CHECK_MAXIMUM_DURATION;
         }
+        if self.session_queue_size == 0 {
+            self.session_queue_size = DEFAULT_SESSION_QUEUE_SIZE;
+        }
 
-        #[cfg(target_os = "linux")]
         self.listen.set_transparent();
         self.listen.check()?;
 
@@ -195,7 +244,7 @@ impl TcpTProxyServerConfig {
     }
 }
 
-impl ServerConfig for TcpTProxyServerConfig {
+impl ServerConfig for UdpTProxyServerConfig {
     fn name(&self) -> &NodeName {
         &self.name
     }
@@ -217,11 +266,11 @@ impl ServerConfig for TcpTProxyServerConfig {
     }
 
     fn auditor(&self) -> &NodeName {
-        &self.auditor
+        Default::default()
     }
 
     fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
-        let AnyServerConfig::TcpTProxy(new) = new else {
+        let AnyServerConfig::UdpTProxy(new) = new else {
             return ServerConfigDiffAction::SpawnNew;
         };
 
@@ -244,11 +293,6 @@ impl ServerConfig for TcpTProxyServerConfig {
         self.task_log_flush_interval
     }
 
-    #[inline]
-    fn limited_copy_config(&self) -> StreamCopyConfig {
-        self.tcp_copy
-    }
-
     #[inline]
     fn task_max_idle_count(&self) -> usize {
         self.task_idle_max_count
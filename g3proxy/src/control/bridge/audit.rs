@@ -0,0 +1,43 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::path::PathBuf;
+
+use anyhow::{Context, anyhow};
+use rand::distr::Bernoulli;
+
+use g3_icap_client::IcapDebugCaptureConfig;
+use g3_types::metrics::NodeName;
+
+/// Start the ICAP debug capture of the auditor, and return the count of ICAP services changed
+pub(in crate::control) fn start_icap_debug_capture(
+    auditor: &str,
+    dir: &str,
+    sample_ratio: f64,
+    max_file_size: u64,
+) -> anyhow::Result<usize> {
+    let name = unsafe { NodeName::new_unchecked(auditor) };
+    let dir = PathBuf::from(dir);
+    if !dir.is_absolute() {
+        return Err(anyhow!("the capture dir {} is not absolute", dir.display()));
+    }
+    std::fs::create_dir_all(&dir)
+        .context(format!("failed to create capture dir {}", dir.display()))?;
+    let sample_ratio = Bernoulli::new(sample_ratio)
+        .map_err(|e| anyhow!("invalid sample ratio {sample_ratio}: {e}"))?;
+
+    let mut capture = IcapDebugCaptureConfig::new(dir);
+    capture.set_sample_ratio(sample_ratio);
+    if max_file_size > 0 {
+        capture.set_max_file_size(usize::try_from(max_file_size).unwrap_or(usize::MAX));
+    }
+    crate::audit::set_icap_debug_capture(&name, Some(capture))
+}
+
+/// Stop the ICAP debug capture of the auditor, and return the count of ICAP services changed
+pub(in crate::control) fn stop_icap_debug_capture(auditor: &str) -> anyhow::Result<usize> {
+    let name = unsafe { NodeName::new_unchecked(auditor) };
+    crate::audit::set_icap_debug_capture(&name, None)
+}
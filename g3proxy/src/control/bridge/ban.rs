@@ -0,0 +1,43 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use g3_daemon::ban::ClientBanList;
+
+fn global_ban_list() -> anyhow::Result<&'static ClientBanList> {
+    g3_daemon::ban::global().ok_or_else(|| anyhow!("client ban is not enabled"))
+}
+
+pub(in crate::control) fn list_client_ban() -> anyhow::Result<serde_json::Value> {
+    let mut bans = global_ban_list()?.list();
+    bans.sort_by_key(|ban| ban.ip);
+    let bans = bans
+        .into_iter()
+        .map(|ban| {
+            serde_json::json!({
+                "ip": ban.ip.to_string(),
+                "level": ban.level,
+                "duration": ban.duration.as_secs(),
+                "remaining": ban.remaining.as_secs(),
+            })
+        })
+        .collect();
+    Ok(serde_json::Value::Array(bans))
+}
+
+pub(in crate::control) fn clear_client_ban(ip: &str) -> anyhow::Result<usize> {
+    let list = global_ban_list()?;
+    let ip = if ip.is_empty() {
+        None
+    } else {
+        let ip = IpAddr::from_str(ip).map_err(|e| anyhow!("invalid ip address {ip}: {e}"))?;
+        Some(ip)
+    };
+    Ok(list.clear(ip))
+}
@@ -0,0 +1,14 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use anyhow::anyhow;
+
+pub(in crate::control) async fn config_rollback(generation: Option<u64>) -> anyhow::Result<u64> {
+    g3_daemon::runtime::main_handle()
+        .ok_or(anyhow!("unable to get main runtime handle"))?
+        .spawn(async move { crate::signal::rollback(generation).await })
+        .await
+        .map_err(|e| anyhow!("failed to spawn rollback task: {e}"))?
+}
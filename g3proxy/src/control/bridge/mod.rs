@@ -3,6 +3,18 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+mod audit;
+pub(super) use audit::{start_icap_debug_capture, stop_icap_debug_capture};
+
+mod ban;
+pub(super) use ban::{clear_client_ban, list_client_ban};
+
+mod check;
+pub(super) use check::check_config;
+
+mod history;
+pub(super) use history::config_rollback;
+
 mod reload;
 pub(super) use reload::{
     reload_auditor, reload_escaper, reload_resolver, reload_server, reload_user_group,
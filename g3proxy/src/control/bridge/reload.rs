@@ -3,6 +3,8 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::path::PathBuf;
+
 use anyhow::anyhow;
 
 use g3_types::metrics::NodeName;
@@ -24,8 +26,29 @@ macro_rules! impl_reload {
     };
 }
 
+macro_rules! impl_reload_named {
+    ($f:ident, $m:tt) => {
+        /// Reload the named one only, and return the action that has been taken
+        pub(in crate::control) async fn $f(
+            name: String,
+            path: Option<PathBuf>,
+        ) -> anyhow::Result<&'static str> {
+            let name = unsafe { NodeName::new_unchecked(name) };
+            g3_daemon::runtime::main_handle()
+                .ok_or(anyhow!("unable to get main runtime handle"))?
+                .spawn(async move {
+                    crate::$m::reload(&name, path)
+                        .await
+                        .map(|action| action.as_str())
+                })
+                .await
+                .map_err(|e| anyhow!("failed to spawn reload task: {e}"))?
+        }
+    };
+}
+
 impl_reload!(reload_user_group, auth);
 impl_reload!(reload_auditor, audit);
 impl_reload!(reload_resolver, resolve);
-impl_reload!(reload_escaper, escape);
-impl_reload!(reload_server, serve);
+impl_reload_named!(reload_escaper, escape);
+impl_reload_named!(reload_server, serve);
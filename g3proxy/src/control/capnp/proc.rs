@@ -3,6 +3,8 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::path::PathBuf;
+
 use capnp::capability::Promise;
 use capnp_rpc::pry;
 
@@ -174,10 +176,21 @@ impl proc_control::Server for ProcControlImpl {
         params: proc_control::ReloadEscaperParams,
         mut results: proc_control::ReloadEscaperResults,
     ) -> Promise<(), capnp::Error> {
-        let escaper = pry!(pry!(pry!(params.get()).get_name()).to_string());
+        let params = pry!(params.get());
+        let escaper = pry!(pry!(params.get_name()).to_string());
+        let path = pry!(pry!(params.get_path()).to_string());
+        let path = (!path.is_empty()).then(|| PathBuf::from(path));
         Promise::from_future(async move {
-            let r = crate::control::bridge::reload_escaper(escaper, None).await;
-            set_operation_result(results.get().init_result(), r);
+            let r = crate::control::bridge::reload_escaper(escaper, path).await;
+            let mut builder = results.get().init_result();
+            match r {
+                Ok(action) => builder.set_ok(action),
+                Err(e) => {
+                    let mut ev = builder.init_err();
+                    ev.set_code(-1);
+                    ev.set_reason(format!("{e:?}").as_str());
+                }
+            }
             Ok(())
         })
     }
@@ -187,10 +200,21 @@ impl proc_control::Server for ProcControlImpl {
         params: proc_control::ReloadServerParams,
         mut results: proc_control::ReloadServerResults,
     ) -> Promise<(), capnp::Error> {
-        let server = pry!(pry!(pry!(params.get()).get_name()).to_string());
+        let params = pry!(params.get());
+        let server = pry!(pry!(params.get_name()).to_string());
+        let path = pry!(pry!(params.get_path()).to_string());
+        let path = (!path.is_empty()).then(|| PathBuf::from(path));
         Promise::from_future(async move {
-            let r = crate::control::bridge::reload_server(server, None).await;
-            set_operation_result(results.get().init_result(), r);
+            let r = crate::control::bridge::reload_server(server, path).await;
+            let mut builder = results.get().init_result();
+            match r {
+                Ok(action) => builder.set_ok(action),
+                Err(e) => {
+                    let mut ev = builder.init_err();
+                    ev.set_code(-1);
+                    ev.set_reason(format!("{e:?}").as_str());
+                }
+            }
             Ok(())
         })
     }
@@ -269,6 +293,153 @@ impl proc_control::Server for ProcControlImpl {
         results.get().init_result().set_ok("success");
         Promise::ok(())
     }
+
+    fn check_config(
+        &mut self,
+        params: proc_control::CheckConfigParams,
+        mut results: proc_control::CheckConfigResults,
+    ) -> Promise<(), capnp::Error> {
+        let path = pry!(pry!(pry!(params.get()).get_path()).to_string());
+        Promise::from_future(async move {
+            let r = crate::control::bridge::check_config(path).await;
+            let mut builder = results.get().init_result();
+            match r {
+                Ok(report) => builder.set_data(report.as_str().into())?,
+                Err(e) => {
+                    let mut ev = builder.init_err();
+                    ev.set_code(-1);
+                    ev.set_reason(format!("{e:?}").as_str());
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn config_history(
+        &mut self,
+        _params: proc_control::ConfigHistoryParams,
+        mut results: proc_control::ConfigHistoryResults,
+    ) -> Promise<(), capnp::Error> {
+        let history = crate::serve::config_history().to_string();
+        pry!(
+            results
+                .get()
+                .init_result()
+                .set_data(history.as_str().into())
+        );
+        Promise::ok(())
+    }
+
+    fn config_rollback(
+        &mut self,
+        params: proc_control::ConfigRollbackParams,
+        mut results: proc_control::ConfigRollbackResults,
+    ) -> Promise<(), capnp::Error> {
+        let generation = pry!(params.get()).get_generation();
+        let generation = (generation != 0).then_some(generation);
+        Promise::from_future(async move {
+            let r = crate::control::bridge::config_rollback(generation).await;
+            let mut builder = results.get().init_result();
+            match r {
+                Ok(id) => builder.set_ok(format!("rolled back as generation {id}").as_str()),
+                Err(e) => {
+                    let mut ev = builder.init_err();
+                    ev.set_code(-1);
+                    ev.set_reason(format!("{e:?}").as_str());
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn config_schema(
+        &mut self,
+        _params: proc_control::ConfigSchemaParams,
+        mut results: proc_control::ConfigSchemaResults,
+    ) -> Promise<(), capnp::Error> {
+        let schema = crate::config::schema().to_string();
+        pry!(results.get().init_result().set_data(schema.as_str().into()));
+        Promise::ok(())
+    }
+
+    fn list_client_ban(
+        &mut self,
+        _params: proc_control::ListClientBanParams,
+        mut results: proc_control::ListClientBanResults,
+    ) -> Promise<(), capnp::Error> {
+        let mut builder = results.get().init_result();
+        match crate::control::bridge::list_client_ban() {
+            Ok(bans) => pry!(builder.set_data(bans.to_string().as_str().into())),
+            Err(e) => {
+                let mut ev = builder.init_err();
+                ev.set_code(-1);
+                ev.set_reason(format!("{e:?}").as_str());
+            }
+        }
+        Promise::ok(())
+    }
+
+    fn clear_client_ban(
+        &mut self,
+        params: proc_control::ClearClientBanParams,
+        mut results: proc_control::ClearClientBanResults,
+    ) -> Promise<(), capnp::Error> {
+        let ip = pry!(pry!(pry!(params.get()).get_ip()).to_str());
+        let mut builder = results.get().init_result();
+        match crate::control::bridge::clear_client_ban(ip) {
+            Ok(n) => builder.set_ok(format!("{n} bans cleared").as_str()),
+            Err(e) => {
+                let mut ev = builder.init_err();
+                ev.set_code(-1);
+                ev.set_reason(format!("{e:?}").as_str());
+            }
+        }
+        Promise::ok(())
+    }
+
+    fn start_icap_debug_capture(
+        &mut self,
+        params: proc_control::StartIcapDebugCaptureParams,
+        mut results: proc_control::StartIcapDebugCaptureResults,
+    ) -> Promise<(), capnp::Error> {
+        let params = pry!(params.get());
+        let auditor = pry!(pry!(params.get_auditor()).to_str());
+        let dir = pry!(pry!(params.get_dir()).to_str());
+        let r = crate::control::bridge::start_icap_debug_capture(
+            auditor,
+            dir,
+            params.get_sample_ratio(),
+            params.get_max_file_size(),
+        );
+        let mut builder = results.get().init_result();
+        match r {
+            Ok(n) => builder.set_ok(format!("capture started for {n} ICAP services").as_str()),
+            Err(e) => {
+                let mut ev = builder.init_err();
+                ev.set_code(-1);
+                ev.set_reason(format!("{e:?}").as_str());
+            }
+        }
+        Promise::ok(())
+    }
+
+    fn stop_icap_debug_capture(
+        &mut self,
+        params: proc_control::StopIcapDebugCaptureParams,
+        mut results: proc_control::StopIcapDebugCaptureResults,
+    ) -> Promise<(), capnp::Error> {
+        let auditor = pry!(pry!(pry!(params.get()).get_auditor()).to_str());
+        let mut builder = results.get().init_result();
+        match crate::control::bridge::stop_icap_debug_capture(auditor) {
+            Ok(n) => builder.set_ok(format!("capture stopped for {n} ICAP services").as_str()),
+            Err(e) => {
+                let mut ev = builder.init_err();
+                ev.set_code(-1);
+                ev.set_reason(format!("{e:?}").as_str());
+            }
+        }
+        Promise::ok(())
+    }
 }
 
 fn set_fetch_result<'a, T>(
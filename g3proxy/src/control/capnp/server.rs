@@ -3,13 +3,20 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
 use capnp::capability::Promise;
+use capnp_rpc::pry;
+use uuid::Uuid;
 
 use g3_types::metrics::NodeName;
 
 use g3proxy_proto::server_capnp::server_control;
 
-use crate::serve::ArcServer;
+use super::set_operation_result;
+use crate::serve::{ArcServer, TASK_LIST_DEFAULT_LIMIT};
 
 pub(super) struct ServerControlImpl {
     server: ArcServer,
@@ -29,17 +36,177 @@ impl server_control::Server for ServerControlImpl {
         _params: server_control::StatusParams,
         mut results: server_control::StatusResults,
     ) -> Promise<(), capnp::Error> {
+        let listen_stats = self.server.get_listen_stats();
+        let mut builder = results.get().init_status();
         if let Some(stats) = self.server.get_server_stats() {
-            let mut builder = results.get().init_status();
             builder.set_online(stats.is_online());
-            builder.set_alive_task_count(stats.get_alive_count());
             builder.set_total_conn_count(stats.get_conn_total());
             builder.set_total_task_count(stats.get_task_total());
-            Promise::ok(())
         } else {
-            Promise::err(capnp::Error::failed(
-                "server status is not supported on this server".to_string(),
-            ))
+            // no server stats for port servers, use the listen state instead
+            builder.set_online(listen_stats.is_running());
+        }
+        builder.set_alive_task_count(self.server.alive_count());
+        builder.set_listening(listen_stats.is_running());
+        let listen_addrs = listen_stats.listen_addrs();
+        let mut addrs_builder = builder
+            .reborrow()
+            .init_listen_addrs(listen_addrs.len() as u32);
+        for (i, addr) in listen_addrs.iter().enumerate() {
+            addrs_builder.set(i as u32, addr.to_string().as_str());
+        }
+        if let Some(e) = listen_stats.last_error() {
+            builder.set_last_listen_error(e.as_str());
+        }
+        Promise::ok(())
+    }
+
+    fn check(
+        &mut self,
+        params: server_control::CheckParams,
+        mut results: server_control::CheckResults,
+    ) -> Promise<(), capnp::Error> {
+        let sni = pry!(pry!(pry!(params.get()).get_sni()).to_string());
+        let server = self.server.clone();
+        Promise::from_future(async move {
+            let sni = if sni.is_empty() {
+                None
+            } else {
+                Some(sni.as_str())
+            };
+            let r = server.loopback_check(sni).await;
+            let builder = results.get().init_result();
+            match r {
+                Ok(report) => {
+                    let mut report_builder = builder.init_report();
+                    report_builder.set_connect_time(report.connect_time.as_micros() as u64);
+                    if let Some(handshake_time) = report.handshake_time {
+                        report_builder.set_handshake_time(handshake_time.as_micros() as u64);
+                    }
+                }
+                Err(e) => builder.set_err(format!("{e:?}").as_str()),
+            }
+            Ok(())
+        })
+    }
+
+    fn start_udp_capture(
+        &mut self,
+        params: server_control::StartUdpCaptureParams,
+        mut results: server_control::StartUdpCaptureResults,
+    ) -> Promise<(), capnp::Error> {
+        let params = pry!(params.get());
+        let ratio = params.get_ratio();
+        let max_packets = params.get_max_packets() as usize;
+        let builder = results.get().init_result();
+        match self.server.start_udp_capture(ratio, max_packets) {
+            Ok(path) => builder.set_ok(path.display().to_string().as_str()),
+            Err(e) => set_operation_result(builder, Err(e)),
+        }
+        Promise::ok(())
+    }
+
+    fn stop_udp_capture(
+        &mut self,
+        _params: server_control::StopUdpCaptureParams,
+        mut results: server_control::StopUdpCaptureResults,
+    ) -> Promise<(), capnp::Error> {
+        let r = self.server.stop_udp_capture();
+        set_operation_result(results.get().init_result(), r);
+        Promise::ok(())
+    }
+
+    fn list_tasks(
+        &mut self,
+        params: server_control::ListTasksParams,
+        mut results: server_control::ListTasksResults,
+    ) -> Promise<(), capnp::Error> {
+        let params = pry!(params.get());
+        let after = pry!(pry!(params.get_after()).to_str());
+        let limit = match params.get_limit() {
+            0 => TASK_LIST_DEFAULT_LIMIT,
+            n => n as usize,
+        };
+        let r = if after.is_empty() {
+            self.server.list_tasks(None, limit)
+        } else {
+            Uuid::from_str(after)
+                .map_err(|e| anyhow!("invalid task id {after}: {e}"))
+                .and_then(|id| self.server.list_tasks(Some(id), limit))
+        };
+        let builder = results.get().init_result();
+        match r {
+            Ok(tasks) => {
+                let mut tasks_builder = builder.init_tasks(tasks.len() as u32);
+                for (i, task) in tasks.iter().enumerate() {
+                    let mut task_builder = tasks_builder.reborrow().get(i as u32);
+                    task_builder.set_id(task.id().to_string().as_str());
+                    task_builder.set_client_addr(task.client_addr().to_string().as_str());
+                    if let Some(upstream) = task.upstream() {
+                        task_builder.set_upstream_addr(upstream.to_string().as_str());
+                    }
+                    task_builder.set_start_time(task.start_at().timestamp());
+                    task_builder.set_last_active_time(task.last_active());
+                    let io_bytes = task.io_bytes();
+                    task_builder.set_client_rd_bytes(io_bytes.clt_rd);
+                    task_builder.set_client_wr_bytes(io_bytes.clt_wr);
+                    task_builder.set_remote_rd_bytes(io_bytes.ups_rd);
+                    task_builder.set_remote_wr_bytes(io_bytes.ups_wr);
+                    let batch_size = task.batch_size();
+                    task_builder.set_client_recv_batch_size(batch_size.clt_recv as u32);
+                    task_builder.set_remote_recv_batch_size(batch_size.ups_recv as u32);
+                }
+            }
+            Err(e) => {
+                let mut ev = builder.init_err();
+                ev.set_code(-1);
+                ev.set_reason(format!("{e:?}").as_str());
+            }
         }
+        Promise::ok(())
+    }
+
+    fn kill_task(
+        &mut self,
+        params: server_control::KillTaskParams,
+        mut results: server_control::KillTaskResults,
+    ) -> Promise<(), capnp::Error> {
+        let id = pry!(pry!(pry!(params.get()).get_id()).to_str());
+        let r = Uuid::from_str(id)
+            .map_err(|e| anyhow!("invalid task id {id}: {e}"))
+            .and_then(|id| self.server.kill_task(&id));
+        set_operation_result(results.get().init_result(), r);
+        Promise::ok(())
+    }
+
+    fn set_task_log_sample_ratio(
+        &mut self,
+        params: server_control::SetTaskLogSampleRatioParams,
+        mut results: server_control::SetTaskLogSampleRatioResults,
+    ) -> Promise<(), capnp::Error> {
+        let ratio = pry!(params.get()).get_ratio();
+        let r = match self.server.task_log_sampler() {
+            Some(sampler) => sampler.set_ratio(ratio),
+            None => Err(anyhow!("task log sampling is not supported by this server")),
+        };
+        set_operation_result(results.get().init_result(), r);
+        Promise::ok(())
+    }
+
+    fn force_full_task_log(
+        &mut self,
+        params: server_control::ForceFullTaskLogParams,
+        mut results: server_control::ForceFullTaskLogResults,
+    ) -> Promise<(), capnp::Error> {
+        let seconds = pry!(params.get()).get_seconds();
+        let r = match self.server.task_log_sampler() {
+            Some(sampler) => {
+                sampler.force_full(Duration::from_secs(seconds as u64));
+                Ok(())
+            }
+            None => Err(anyhow!("task log sampling is not supported by this server")),
+        };
+        set_operation_result(results.get().init_result(), r);
+        Promise::ok(())
     }
 }
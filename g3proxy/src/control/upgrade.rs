@@ -18,6 +18,15 @@ pub struct UpgradeActor {
     proc_control: proc_control::Client,
 }
 
+impl UpgradeActor {
+    /// Serve the listen sockets to the new process at the default handover socket path
+    #[cfg(unix)]
+    pub fn spawn_handover_server() -> anyhow::Result<()> {
+        let path = g3_daemon::listen::handover::default_socket_path(crate::opts::daemon_group());
+        g3_daemon::listen::handover::spawn_server(path)
+    }
+}
+
 impl UpgradeAction for UpgradeActor {
     async fn connect_rpc() -> anyhow::Result<(RpcSystem<Side>, Self)> {
         LocalController::connect_rpc::<proc_control::Client>(
@@ -0,0 +1,141 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use g3_socket::BindAddr;
+
+use super::stats::EgressBindRuleStats;
+
+pub(super) type ArcEgressBindMap = Arc<Vec<Arc<EgressBindRuleStats>>>;
+
+/// Get the bind ip of the first rule that matches the peer ip
+pub(super) fn select_egress_bind(
+    map: &[Arc<EgressBindRuleStats>],
+    peer_ip: IpAddr,
+) -> Option<BindAddr> {
+    let s = map.iter().find(|s| s.rule.net.contains(peer_ip))?;
+    s.add_selected();
+    Some(BindAddr::Ip(s.rule.bind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use g3_types::metrics::NodeName;
+
+    use crate::config::escaper::direct_fixed::EgressBindRule;
+    use crate::escape::EscaperStats;
+    use crate::escape::direct_fixed::DirectFixedEscaperStats;
+
+    fn build_map(stats: &DirectFixedEscaperStats) -> ArcEgressBindMap {
+        let rules = [
+            EgressBindRule {
+                net: "127.0.1.0/24".parse().unwrap(),
+                bind: IpAddr::from_str("127.0.0.2").unwrap(),
+            },
+            EgressBindRule {
+                net: "127.0.0.0/8".parse().unwrap(),
+                bind: IpAddr::from_str("127.0.0.3").unwrap(),
+            },
+        ];
+        stats.update_egress_bind_rules(&rules)
+    }
+
+    #[test]
+    fn select() {
+        let stats = DirectFixedEscaperStats::new(&NodeName::from_str("test").unwrap());
+        let map = build_map(&stats);
+
+        let ip = IpAddr::from_str("127.0.1.1").unwrap();
+        assert_eq!(
+            select_egress_bind(&map, ip),
+            Some(BindAddr::Ip(IpAddr::from_str("127.0.0.2").unwrap()))
+        );
+        let ip = IpAddr::from_str("127.0.2.1").unwrap();
+        assert_eq!(
+            select_egress_bind(&map, ip),
+            Some(BindAddr::Ip(IpAddr::from_str("127.0.0.3").unwrap()))
+        );
+        let ip = IpAddr::from_str("192.0.2.1").unwrap();
+        assert!(select_egress_bind(&map, ip).is_none());
+        let ip = IpAddr::from_str("::1").unwrap();
+        assert!(select_egress_bind(&map, ip).is_none());
+
+        assert_eq!(stats.egress_bind_snapshot(), Some(vec![1, 1]));
+
+        // the stats of unchanged rules are kept after reload
+        let map = stats.update_egress_bind_rules(&[map[1].rule.clone()]);
+        assert_eq!(map.len(), 1);
+        assert_eq!(stats.egress_bind_snapshot(), Some(vec![1]));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn bind_by_destination() {
+        use tokio::net::{TcpListener, UdpSocket};
+
+        use g3_socket::util::AddressFamily;
+        use g3_types::net::{
+            SocketBufferConfig, TcpKeepAliveConfig, TcpMiscSockOpts, UdpMiscSockOpts,
+        };
+
+        let stats = DirectFixedEscaperStats::new(&NodeName::from_str("test").unwrap());
+        let map = stats.update_egress_bind_rules(&[EgressBindRule {
+            net: "127.0.0.1/32".parse().unwrap(),
+            bind: IpAddr::from_str("127.0.0.2").unwrap(),
+        }]);
+        let default_bind = BindAddr::Ip(IpAddr::from_str("127.0.0.3").unwrap());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let other_listener = TcpListener::bind("127.0.0.4:0").await.unwrap();
+        for (listener, expected) in [(&listener, "127.0.0.2"), (&other_listener, "127.0.0.3")] {
+            let peer = listener.local_addr().unwrap();
+            let bind = select_egress_bind(&map, peer.ip()).unwrap_or(default_bind);
+            let sock = g3_socket::tcp::new_socket_to(
+                peer.ip(),
+                &bind,
+                &TcpKeepAliveConfig::default(),
+                &TcpMiscSockOpts::default(),
+                true,
+            )
+            .unwrap();
+            let stream = sock.connect(peer).await.unwrap();
+            assert_eq!(
+                stream.local_addr().unwrap().ip(),
+                IpAddr::from_str(expected).unwrap()
+            );
+        }
+
+        let peer_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let other_peer_socket = UdpSocket::bind("127.0.0.4:0").await.unwrap();
+        for (peer_socket, expected) in [
+            (&peer_socket, "127.0.0.2"),
+            (&other_peer_socket, "127.0.0.3"),
+        ] {
+            let peer = peer_socket.local_addr().unwrap();
+            let bind = select_egress_bind(&map, peer.ip()).unwrap_or(default_bind);
+            let (socket, bind_addr) = g3_socket::udp::new_std_bind_relay(
+                &bind,
+                AddressFamily::Ipv4,
+                SocketBufferConfig::default(),
+                UdpMiscSockOpts::default(),
+            )
+            .unwrap();
+            assert_eq!(bind_addr.ip(), IpAddr::from_str(expected).unwrap());
+
+            let socket = UdpSocket::from_std(socket).unwrap();
+            socket.send_to(b"test", peer).await.unwrap();
+            let mut buf = [0u8; 16];
+            let (_, from) = peer_socket.recv_from(&mut buf).await.unwrap();
+            assert_eq!(from, bind_addr);
+        }
+
+        assert_eq!(stats.egress_bind_snapshot(), Some(vec![2]));
+    }
+}
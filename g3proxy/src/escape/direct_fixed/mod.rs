@@ -51,6 +51,9 @@ use crate::serve::ServerTaskNotes;
 mod stats;
 pub(crate) use stats::DirectFixedEscaperStats;
 
+mod egress_bind;
+use egress_bind::ArcEgressBindMap;
+
 mod ftp_connect;
 pub(crate) mod http_forward;
 pub(crate) mod tcp_connect;
@@ -64,6 +67,7 @@ pub(super) struct DirectFixedEscaper {
     resolver_handle: ArcIntegratedResolverHandle,
     egress_net_filter: Arc<AclNetworkRule>,
     resolve_redirection: Option<ResolveRedirection>,
+    egress_bind_map: ArcEgressBindMap,
     escape_logger: Option<Logger>,
 }
 
@@ -83,6 +87,7 @@ impl DirectFixedEscaper {
         let escape_logger = config.get_escape_logger();
 
         stats.set_extra_tags(config.extra_metrics_tags.clone());
+        let egress_bind_map = stats.update_egress_bind_rules(&config.egress_bind_map);
 
         let escaper = DirectFixedEscaper {
             config: Arc::new(config),
@@ -90,6 +95,7 @@ impl DirectFixedEscaper {
             resolver_handle,
             egress_net_filter,
             resolve_redirection,
+            egress_bind_map,
             escape_logger,
         };
 
@@ -157,6 +163,16 @@ impl DirectFixedEscaper {
         }
     }
 
+    /// Get the bind address by the egress bind map first, and then fall back to the default one
+    fn get_bind_for_peer(
+        &self,
+        peer_ip: IpAddr,
+        path_selection: Option<&EgressPathSelection>,
+    ) -> BindAddr {
+        egress_bind::select_egress_bind(&self.egress_bind_map, peer_ip)
+            .unwrap_or_else(|| self.get_bind_random(AddressFamily::from(&peer_ip), path_selection))
+    }
+
     fn get_resolve_strategy(&self, task_notes: &ServerTaskNotes) -> ResolveStrategy {
         if let Some(user_ctx) = task_notes.user_ctx() {
             if let Some(rs) = user_ctx.resolve_strategy() {
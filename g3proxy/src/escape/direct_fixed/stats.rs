@@ -4,14 +4,16 @@
  */
 
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-use arc_swap::ArcSwapOption;
+use arc_swap::{ArcSwap, ArcSwapOption};
 
 use g3_daemon::stat::remote::TcpConnectionTaskRemoteStats;
 use g3_io_ext::{LimitedReaderStats, LimitedWriterStats};
 use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::stats::{StatId, TcpIoSnapshot, UdpIoSnapshot};
 
+use crate::config::escaper::direct_fixed::EgressBindRule;
 use crate::escape::{
     EscaperForbiddenSnapshot, EscaperForbiddenStats, EscaperInterfaceStats, EscaperInternalStats,
     EscaperStats, EscaperTcpConnectSnapshot, EscaperTcpStats, EscaperUdpStats,
@@ -19,7 +21,32 @@ use crate::escape::{
 use crate::module::ftp_over_http::{FtpTaskRemoteControlStats, FtpTaskRemoteTransferStats};
 use crate::module::http_forward::HttpForwardTaskRemoteStats;
 use crate::module::udp_connect::UdpConnectTaskRemoteStats;
-use crate::module::udp_relay::UdpRelayTaskRemoteStats;
+use crate::module::udp_relay::{
+    UdpRelayIcmpErrorSnapshot, UdpRelayIcmpErrorStats, UdpRelayMappingSnapshot,
+    UdpRelayMappingStats, UdpRelayTaskRemoteStats,
+};
+
+pub(crate) struct EgressBindRuleStats {
+    pub(crate) rule: EgressBindRule,
+    selected: AtomicU64,
+}
+
+impl EgressBindRuleStats {
+    fn new(rule: EgressBindRule) -> Self {
+        EgressBindRuleStats {
+            rule,
+            selected: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn add_selected(&self) {
+        self.selected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get_selected(&self) -> u64 {
+        self.selected.load(Ordering::Relaxed)
+    }
+}
 
 pub(crate) struct DirectFixedEscaperStats {
     name: NodeName,
@@ -29,6 +56,9 @@ pub(crate) struct DirectFixedEscaperStats {
     pub(crate) interface: EscaperInterfaceStats,
     pub(crate) udp: EscaperUdpStats,
     pub(crate) tcp: EscaperTcpStats,
+    pub(crate) udp_mapping: Arc<UdpRelayMappingStats>,
+    pub(crate) udp_icmp_error: Arc<UdpRelayIcmpErrorStats>,
+    egress_bind: ArcSwap<Vec<Arc<EgressBindRuleStats>>>,
 }
 
 impl DirectFixedEscaperStats {
@@ -41,12 +71,35 @@ impl DirectFixedEscaperStats {
             interface: Default::default(),
             udp: Default::default(),
             tcp: Default::default(),
+            udp_mapping: Default::default(),
+            udp_icmp_error: Default::default(),
+            egress_bind: ArcSwap::from_pointee(Vec::new()),
         }
     }
 
     pub(crate) fn set_extra_tags(&self, tags: Option<Arc<MetricTagMap>>) {
         self.extra_metrics_tags.store(tags);
     }
+
+    /// Update the egress bind rules, the stats of the unchanged rules will be kept
+    pub(crate) fn update_egress_bind_rules(
+        &self,
+        rules: &[EgressBindRule],
+    ) -> Arc<Vec<Arc<EgressBindRuleStats>>> {
+        let old = self.egress_bind.load();
+        let new = rules
+            .iter()
+            .map(|rule| {
+                old.iter()
+                    .find(|s| s.rule.eq(rule))
+                    .cloned()
+                    .unwrap_or_else(|| Arc::new(EgressBindRuleStats::new(rule.clone())))
+            })
+            .collect::<Vec<_>>();
+        let new = Arc::new(new);
+        self.egress_bind.store(new.clone());
+        new
+    }
 }
 
 impl EscaperInternalStats for DirectFixedEscaperStats {
@@ -108,6 +161,24 @@ impl EscaperStats for DirectFixedEscaperStats {
     fn forbidden_snapshot(&self) -> Option<EscaperForbiddenSnapshot> {
         Some(self.forbidden.snapshot())
     }
+
+    #[inline]
+    fn udp_mapping_snapshot(&self) -> Option<UdpRelayMappingSnapshot> {
+        Some(self.udp_mapping.snapshot())
+    }
+
+    #[inline]
+    fn udp_icmp_error_snapshot(&self) -> Option<UdpRelayIcmpErrorSnapshot> {
+        Some(self.udp_icmp_error.snapshot())
+    }
+
+    fn egress_bind_snapshot(&self) -> Option<Vec<u64>> {
+        let rules = self.egress_bind.load();
+        if rules.is_empty() {
+            return None;
+        }
+        Some(rules.iter().map(|s| s.get_selected()).collect())
+    }
 }
 
 impl LimitedReaderStats for DirectFixedEscaperStats {
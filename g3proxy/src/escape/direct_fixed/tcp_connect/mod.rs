@@ -15,7 +15,6 @@ use tokio::time::Instant;
 use g3_daemon::stat::remote::ArcTcpConnectionTaskRemoteStats;
 use g3_io_ext::{LimitedReader, LimitedWriter};
 use g3_socket::BindAddr;
-use g3_socket::util::AddressFamily;
 use g3_types::acl::AclAction;
 use g3_types::net::{
     ConnectError, Host, TcpConnectConfig, TcpKeepAliveConfig, TcpMiscSockOpts, UpstreamAddr,
@@ -89,18 +88,45 @@ impl DirectFixedEscaper {
         self.handle_tcp_target_ip_acl_action(action, task_notes)?;
 
         if bind.is_none() {
-            bind = self.get_bind_random(AddressFamily::from(&peer_ip), task_notes.egress_path());
+            bind = self.get_bind_for_peer(peer_ip, task_notes.egress_path());
         }
 
-        let sock = g3_socket::tcp::new_socket_to(
+        let sock = self
+            .new_tcp_socket_to(peer_ip, &bind, connect_config)
+            .map_err(TcpConnectError::SetupSocketFailed)?;
+        Ok((sock, bind))
+    }
+
+    fn new_tcp_socket_to(
+        &self,
+        peer_ip: IpAddr,
+        bind: &BindAddr,
+        connect_config: &DirectTcpConnectConfig<'_>,
+    ) -> io::Result<TcpSocket> {
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "illumos",
+            target_os = "solaris"
+        ))]
+        if let Some(device) = &self.config.bind_device {
+            return g3_socket::tcp::new_socket_to_in_device(
+                peer_ip,
+                bind,
+                device,
+                &connect_config.keepalive,
+                &connect_config.misc_opts,
+                true,
+            );
+        }
+        g3_socket::tcp::new_socket_to(
             peer_ip,
-            &bind,
+            bind,
             &connect_config.keepalive,
             &connect_config.misc_opts,
             true,
         )
-        .map_err(TcpConnectError::SetupSocketFailed)?;
-        Ok((sock, bind))
     }
 
     async fn fixed_try_connect(
@@ -131,6 +157,7 @@ impl DirectFixedEscaper {
                     .map_err(TcpConnectError::SetupSocketFailed)?;
                 self.stats.tcp.connect.add_established();
                 tcp_notes.local = Some(local_addr);
+                tcp_notes.record_tcp_rtt(&ups_stream);
                 tcp_notes.chained.target_addr = Some(peer);
                 tcp_notes.chained.outgoing_addr = Some(local_addr);
                 Ok(ups_stream)
@@ -260,6 +287,7 @@ impl DirectFixedEscaper {
                                             .map_err(TcpConnectError::SetupSocketFailed)?;
                                         self.stats.tcp.connect.add_established();
                                         tcp_notes.local = Some(local_addr);
+                                        tcp_notes.record_tcp_rtt(&ups_stream);
                                         tcp_notes.chained.target_addr = Some(peer_addr);
                                         tcp_notes.chained.outgoing_addr = Some(local_addr);
                                         return Ok(ups_stream);
@@ -353,6 +381,10 @@ impl DirectFixedEscaper {
             config.keepalive = config.keepalive.adjust_to(user_config.tcp_remote_keepalive);
             config.misc_opts = user_config.tcp_remote_misc_opts(&self.config.tcp_misc_opts);
         }
+        if let Some(timeout) = task_notes.tcp_connect_each_timeout {
+            let each_timeout = config.connect.each_timeout().min(timeout);
+            config.connect.set_each_timeout(each_timeout);
+        }
 
         match task_conf.upstream.host() {
             Host::Ip(ip) => {
@@ -454,6 +486,9 @@ impl DirectFixedEscaper {
         let mut stream = self
             .tcp_connect_to(task_conf, tcp_notes, task_notes)
             .await?;
+        task_notes
+            .set_tcp_upstream_misc_opts(&stream)
+            .map_err(TcpConnectError::SetupSocketFailed)?;
         if let Some(version) = self.config.use_proxy_protocol {
             self.send_tcp_proxy_protocol_header(version, &mut stream, task_notes, true)
                 .await?;
@@ -8,7 +8,6 @@ use std::sync::Arc;
 use tokio::net::UdpSocket;
 
 use g3_io_ext::{LimitedUdpRecv, LimitedUdpSend};
-use g3_socket::util::AddressFamily;
 use g3_types::acl::AclAction;
 
 use super::DirectFixedEscaper;
@@ -72,8 +71,7 @@ impl DirectFixedEscaper {
         let (_, action) = self.egress_net_filter.check(peer_addr.ip());
         self.handle_udp_target_ip_acl_action(action, task_notes)?;
 
-        let family = AddressFamily::from(&peer_addr);
-        let bind = self.get_bind_random(family, task_notes.egress_path());
+        let bind = self.get_bind_for_peer(peer_addr.ip(), task_notes.egress_path());
         udp_notes.bind = bind;
 
         let misc_opts = if let Some(user_ctx) = task_notes.user_ctx() {
@@ -84,13 +82,43 @@ impl DirectFixedEscaper {
             self.config.udp_misc_opts
         };
 
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "illumos",
+            target_os = "solaris"
+        ))]
+        let socket = if let Some(device) = &self.config.bind_device {
+            g3_socket::udp::new_std_socket_to_in_device(
+                peer_addr,
+                &udp_notes.bind,
+                device,
+                task_conf.sock_buf,
+                misc_opts,
+            )
+        } else {
+            g3_socket::udp::new_std_socket_to(
+                peer_addr,
+                &udp_notes.bind,
+                task_conf.sock_buf,
+                misc_opts,
+            )
+        };
+        #[cfg(not(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "illumos",
+            target_os = "solaris"
+        )))]
         let socket = g3_socket::udp::new_std_socket_to(
             peer_addr,
             &udp_notes.bind,
             task_conf.sock_buf,
             misc_opts,
-        )
-        .map_err(UdpConnectError::SetupSocketFailed)?;
+        );
+        let socket = socket.map_err(UdpConnectError::SetupSocketFailed)?;
         socket
             .connect(peer_addr)
             .map_err(UdpConnectError::SetupSocketFailed)?;
@@ -3,18 +3,24 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::io;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
 
 use g3_io_ext::{LimitedUdpRecv, LimitedUdpSend, UdpRecvHalf, UdpSendHalf};
+use g3_socket::BindAddr;
 use g3_socket::util::AddressFamily;
+use g3_types::net::{Host, SocketBufferConfig, UdpMiscSockOpts};
 
 use tokio::net::UdpSocket;
 
-use super::{DirectFixedEscaper, DirectFixedEscaperStats};
+use super::{DirectFixedEscaper, DirectFixedEscaperStats, egress_bind};
+use crate::config::escaper::direct_fixed::{DirectFixedEscaperConfig, UdpRelayMappingConfig};
 use crate::module::udp_relay::{
-    ArcUdpRelayTaskRemoteStats, UdpRelayRemoteWrapperStats, UdpRelaySetupError,
-    UdpRelaySetupResult, UdpRelayTaskConf,
+    ArcUdpRelayTaskRemoteStats, UdpRelayMappingSocketFactory, UdpRelayMappingTable,
+    UdpRelayRemoteWrapperStats, UdpRelaySetupError, UdpRelaySetupResult, UdpRelayTaskConf,
 };
 use crate::serve::ServerTaskNotes;
 
@@ -24,6 +30,10 @@ mod send;
 pub(crate) use recv::DirectUdpRelayRemoteRecv;
 pub(crate) use send::DirectUdpRelayRemoteSend;
 
+pub(crate) type DirectUdpRelayMappingTable =
+    UdpRelayMappingTable<LimitedUdpRecv<UdpRecvHalf>, LimitedUdpSend<UdpSendHalf>>;
+pub(crate) type ArcDirectUdpRelayMappingTable = Arc<Mutex<DirectUdpRelayMappingTable>>;
+
 impl DirectFixedEscaper {
     pub(super) async fn udp_setup_relay(
         &self,
@@ -43,15 +53,27 @@ impl DirectFixedEscaper {
             &self.resolver_handle,
             self.config.resolve_strategy,
         );
+        recv.set_normalize_ipv4_mapped(self.config.udp_relay_normalize_ipv4_mapped);
+        send.set_normalize_ipv4_mapped(self.config.udp_relay_normalize_ipv4_mapped);
+        recv.enable_icmp_errors(self.stats.udp_icmp_error.clone());
+        self.check_udp_relay_bind()?;
+
+        if let Some(config) = self.config.udp_relay_mapping {
+            let table = self.new_mapping_table(config, task_conf, task_notes, &wrapper_stats);
+            let table = Arc::new(Mutex::new(table));
+            recv.enable_mapping(table.clone());
+            send.enable_mapping(table);
+            return Ok((Box::new(recv), Box::new(send), self.escape_logger.clone()));
+        }
 
-        if !self.config.no_ipv4 {
+        if self.udp_relay_family_enabled(AddressFamily::Ipv4) {
             let (bind, r, w) =
                 self.get_relay_socket(AddressFamily::Ipv4, task_conf, task_notes, &wrapper_stats)?;
             recv.enable_v4(r, bind);
             send.enable_v4(w, bind);
         }
 
-        if !self.config.no_ipv6 {
+        if self.udp_relay_family_enabled(AddressFamily::Ipv6) {
             let (bind, r, w) =
                 self.get_relay_socket(AddressFamily::Ipv6, task_conf, task_notes, &wrapper_stats)?;
             recv.enable_v6(r, bind);
@@ -75,37 +97,281 @@ impl DirectFixedEscaper {
         ),
         UdpRelaySetupError,
     > {
-        let bind = self.get_bind_random(family, task_notes.egress_path());
+        // the relay socket is shared by all peers, so only the initial peer can be used here
+        let bind = match task_conf.initial_peer.host() {
+            Host::Ip(ip) if AddressFamily::from(ip) == family => {
+                egress_bind::select_egress_bind(&self.egress_bind_map, *ip)
+            }
+            _ => None,
+        }
+        .unwrap_or_else(|| self.get_udp_relay_bind(family, task_notes));
+        let misc_opts = self.get_udp_misc_opts(task_notes);
+
+        new_relay_socket(
+            &self.config,
+            &bind,
+            family,
+            task_conf.sock_buf,
+            misc_opts,
+            stats,
+        )
+        .map_err(UdpRelaySetupError::SetupSocketFailed)
+    }
 
-        let misc_opts = if let Some(user_ctx) = task_notes.user_ctx() {
+    fn udp_relay_family_enabled(&self, family: AddressFamily) -> bool {
+        match family {
+            AddressFamily::Ipv4 => !self.config.no_ipv4,
+            AddressFamily::Ipv6 => !self.config.no_ipv6,
+        }
+    }
+
+    /// The configured udp relay bind should cover all the enabled address families,
+    /// we won't fall back to the wildcard address or disable the other family silently
+    fn check_udp_relay_bind(&self) -> Result<(), UdpRelaySetupError> {
+        let Some(bind) = &self.config.udp_relay_bind else {
+            return Ok(());
+        };
+        for family in [AddressFamily::Ipv4, AddressFamily::Ipv6] {
+            if !self.udp_relay_family_enabled(family) {
+                continue;
+            }
+            if let Err(e) = bind.check_family(family) {
+                return Err(UdpRelaySetupError::EscaperNotUsable(anyhow!(
+                    "invalid udp_relay_bind: {e}, {family} should be disabled explicitly"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn get_udp_relay_bind(&self, family: AddressFamily, task_notes: &ServerTaskNotes) -> BindAddr {
+        match self.config.udp_relay_bind {
+            Some(bind) => bind,
+            None => self.get_bind_random(family, task_notes.egress_path()),
+        }
+    }
+
+    fn get_udp_misc_opts(&self, task_notes: &ServerTaskNotes) -> UdpMiscSockOpts {
+        if let Some(user_ctx) = task_notes.user_ctx() {
             user_ctx
                 .user_config()
                 .udp_remote_misc_opts(&self.config.udp_misc_opts)
         } else {
             self.config.udp_misc_opts
+        }
+    }
+
+    fn new_mapping_table(
+        &self,
+        config: UdpRelayMappingConfig,
+        task_conf: &UdpRelayTaskConf<'_>,
+        task_notes: &ServerTaskNotes,
+        stats: &Arc<UdpRelayRemoteWrapperStats>,
+    ) -> DirectUdpRelayMappingTable {
+        let bind_v4 = self
+            .udp_relay_family_enabled(AddressFamily::Ipv4)
+            .then(|| self.get_udp_relay_bind(AddressFamily::Ipv4, task_notes));
+        let bind_v6 = self
+            .udp_relay_family_enabled(AddressFamily::Ipv6)
+            .then(|| self.get_udp_relay_bind(AddressFamily::Ipv6, task_notes));
+        let sock_buf = task_conf.sock_buf;
+        let misc_opts = self.get_udp_misc_opts(task_notes);
+        let escaper_config = self.config.clone();
+        let stats = stats.clone();
+        let egress_bind_map = self.egress_bind_map.clone();
+
+        let factory: UdpRelayMappingSocketFactory<_, _> = Box::new(move |peer: SocketAddr| {
+            let family = AddressFamily::from(&peer);
+            let bind = match family {
+                AddressFamily::Ipv4 => bind_v4.as_ref(),
+                AddressFamily::Ipv6 => bind_v6.as_ref(),
+            };
+            let Some(bind) = bind else {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "address family disabled",
+                ));
+            };
+            let bind =
+                egress_bind::select_egress_bind(&egress_bind_map, peer.ip()).unwrap_or(*bind);
+            new_relay_socket(&escaper_config, &bind, family, sock_buf, misc_opts, &stats)
+        });
+
+        UdpRelayMappingTable::new(
+            config,
+            task_conf.client_addr,
+            self.stats.udp_mapping.clone(),
+            factory,
+        )
+    }
+}
+
+fn new_relay_socket(
+    escaper_config: &DirectFixedEscaperConfig,
+    bind: &BindAddr,
+    family: AddressFamily,
+    sock_buf: SocketBufferConfig,
+    misc_opts: UdpMiscSockOpts,
+    stats: &Arc<UdpRelayRemoteWrapperStats>,
+) -> io::Result<(
+    SocketAddr,
+    LimitedUdpRecv<UdpRecvHalf>,
+    LimitedUdpSend<UdpSendHalf>,
+)> {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "illumos",
+        target_os = "solaris"
+    ))]
+    let (socket, bind_addr) = if let Some(device) = &escaper_config.bind_device {
+        g3_socket::udp::new_std_bind_relay_in_device(bind, device, family, sock_buf, misc_opts)?
+    } else {
+        g3_socket::udp::new_std_bind_relay(bind, family, sock_buf, misc_opts)?
+    };
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "illumos",
+        target_os = "solaris"
+    )))]
+    let (socket, bind_addr) =
+        g3_socket::udp::new_std_bind_relay(bind, family, sock_buf, misc_opts)?;
+    let speed_limit = &escaper_config.general.udp_sock_speed_limit;
+    let socket = UdpSocket::from_std(socket)?;
+
+    let (recv, send) = g3_io_ext::split_udp(socket);
+    let recv = LimitedUdpRecv::local_limited(
+        recv,
+        speed_limit.shift_millis,
+        speed_limit.max_south_packets,
+        speed_limit.max_south_bytes,
+        stats.clone(),
+    );
+    let send = LimitedUdpSend::local_limited(
+        send,
+        speed_limit.shift_millis,
+        speed_limit.max_north_packets,
+        speed_limit.max_north_bytes,
+        stats.clone(),
+    );
+
+    Ok((bind_addr, recv, send))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use yaml_rust::YamlLoader;
+
+    use g3_daemon::server::ClientConnectionInfo;
+    use g3_resolver::{ResolveError, ResolveLocalError};
+    use g3_types::metrics::NodeName;
+    use g3_types::net::UpstreamAddr;
+
+    use crate::config::escaper::EscaperConfig;
+    use crate::resolve::{BoxLoggedResolveJob, IntegratedResolverHandle};
+
+    struct NoResolverHandle(NodeName);
+
+    impl IntegratedResolverHandle for NoResolverHandle {
+        fn name(&self) -> &NodeName {
+            &self.0
+        }
+
+        fn is_closed(&self) -> bool {
+            false
+        }
+
+        fn query_v4(&self, _domain: Arc<str>) -> Result<BoxLoggedResolveJob, ResolveError> {
+            Err(ResolveLocalError::NoResolverRunning.into())
+        }
+
+        fn query_v6(&self, _domain: Arc<str>) -> Result<BoxLoggedResolveJob, ResolveError> {
+            Err(ResolveLocalError::NoResolverRunning.into())
+        }
+
+        fn clone_inner(&self) -> Option<g3_resolver::ResolverHandle> {
+            None
+        }
+    }
+
+    fn new_escaper(conf: &str) -> DirectFixedEscaper {
+        let yaml = YamlLoader::load_from_str(conf).unwrap();
+        let config = DirectFixedEscaperConfig::parse(yaml[0].as_hash().unwrap(), None).unwrap();
+        let stats = Arc::new(DirectFixedEscaperStats::new(config.name()));
+        let egress_bind_map = stats.update_egress_bind_rules(&config.egress_bind_map);
+        DirectFixedEscaper {
+            resolver_handle: Arc::new(NoResolverHandle(config.resolver.clone())),
+            egress_net_filter: Arc::new(config.egress_net_filter.build()),
+            resolve_redirection: None,
+            egress_bind_map,
+            escape_logger: None,
+            stats,
+            config: Arc::new(config),
+        }
+    }
+
+    async fn setup_relay(escaper: &DirectFixedEscaper) -> UdpRelaySetupResult {
+        let initial_peer = UpstreamAddr::from_str("127.0.0.1:53").unwrap();
+        let client_addr = SocketAddr::from_str("127.0.0.1:10000").unwrap();
+        let server_addr = SocketAddr::from_str("127.0.0.1:1080").unwrap();
+        let task_conf = UdpRelayTaskConf {
+            initial_peer: &initial_peer,
+            client_addr,
+            sock_buf: Default::default(),
         };
+        let task_notes = ServerTaskNotes::new(
+            ClientConnectionInfo::new(client_addr, server_addr),
+            None,
+            Duration::ZERO,
+        );
+        let task_stats = Arc::new(DirectFixedEscaperStats::new(escaper.config.name()));
+        escaper
+            .udp_setup_relay(&task_conf, &task_notes, task_stats)
+            .await
+    }
 
-        let (socket, bind_addr) =
-            g3_socket::udp::new_std_bind_relay(&bind, family, task_conf.sock_buf, misc_opts)
-                .map_err(UdpRelaySetupError::SetupSocketFailed)?;
-        let socket = UdpSocket::from_std(socket).map_err(UdpRelaySetupError::SetupSocketFailed)?;
-
-        let (recv, send) = g3_io_ext::split_udp(socket);
-        let recv = LimitedUdpRecv::local_limited(
-            recv,
-            self.config.general.udp_sock_speed_limit.shift_millis,
-            self.config.general.udp_sock_speed_limit.max_south_packets,
-            self.config.general.udp_sock_speed_limit.max_south_bytes,
-            stats.clone(),
+    #[tokio::test]
+    async fn relay_bind_family_mismatch() {
+        let escaper = new_escaper(
+            r#"
+                name: direct
+                resolver: default
+                udp_relay_bind: 127.0.0.1
+            "#,
         );
-        let send = LimitedUdpSend::local_limited(
-            send,
-            self.config.general.udp_sock_speed_limit.shift_millis,
-            self.config.general.udp_sock_speed_limit.max_north_packets,
-            self.config.general.udp_sock_speed_limit.max_north_bytes,
-            stats.clone(),
+        let r = setup_relay(&escaper).await;
+        assert!(matches!(r, Err(UdpRelaySetupError::EscaperNotUsable(_))));
+
+        let escaper = new_escaper(
+            r#"
+                name: direct
+                resolver: default
+                udp_relay_bind: 127.0.0.1
+                udp_relay_mapping: true
+            "#,
         );
+        let r = setup_relay(&escaper).await;
+        assert!(matches!(r, Err(UdpRelaySetupError::EscaperNotUsable(_))));
+    }
 
-        Ok((bind_addr, recv, send))
+    #[tokio::test]
+    async fn relay_bind_family_disabled() {
+        let escaper = new_escaper(
+            r#"
+                name: direct
+                resolver: default
+                udp_relay_bind: 127.0.0.1
+                no_ipv6: true
+            "#,
+        );
+        let r = setup_relay(&escaper).await;
+        assert!(r.is_ok());
     }
 }
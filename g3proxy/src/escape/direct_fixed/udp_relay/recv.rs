@@ -3,7 +3,9 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::io;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
 use std::task::{Context, Poll, ready};
 
 use g3_io_ext::{AsyncUdpRecv, UdpRelayRemoteError, UdpRelayRemoteRecv};
@@ -17,13 +19,20 @@ use g3_io_ext::{AsyncUdpRecv, UdpRelayRemoteError, UdpRelayRemoteRecv};
     target_os = "solaris",
 ))]
 use g3_io_ext::{UdpRelayPacket, UdpRelayPacketMeta};
+use g3_std_ext::net::SocketAddrExt;
 use g3_types::net::UpstreamAddr;
 
+use super::ArcDirectUdpRelayMappingTable;
+use crate::module::udp_relay::{UdpRelayIcmpErrorStats, UdpRelayIcmpErrorTracker};
+
 pub(crate) struct DirectUdpRelayRemoteRecv<T> {
     inner_v4: Option<T>,
     inner_v6: Option<T>,
     bind_v4: SocketAddr,
     bind_v6: SocketAddr,
+    mapping: Option<ArcDirectUdpRelayMappingTable>,
+    normalize_ipv4_mapped: bool,
+    icmp_errors: Option<UdpRelayIcmpErrorTracker>,
 }
 
 impl<T> DirectUdpRelayRemoteRecv<T> {
@@ -33,6 +42,34 @@ impl<T> DirectUdpRelayRemoteRecv<T> {
             inner_v6: None,
             bind_v4: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
             bind_v6: SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+            mapping: None,
+            normalize_ipv4_mapped: true,
+            icmp_errors: None,
+        }
+    }
+
+    pub(crate) fn enable_mapping(&mut self, table: ArcDirectUdpRelayMappingTable) {
+        self.mapping = Some(table);
+    }
+
+    /// Convert the IPv4-mapped IPv6 source addresses received on dual-stack sockets
+    /// back to IPv4 addresses, so the client will see the same address it sent to
+    pub(crate) fn set_normalize_ipv4_mapped(&mut self, enable: bool) {
+        self.normalize_ipv4_mapped = enable;
+    }
+
+    /// Check the socket error queue on recv errors, which is only filled if `recv_error` is
+    /// enabled in udp socket misc opts. ICMP errors will be counted and won't stop the relay,
+    /// unless port unreachable errors are received repeatedly from the same destination.
+    pub(crate) fn enable_icmp_errors(&mut self, stats: Arc<UdpRelayIcmpErrorStats>) {
+        self.icmp_errors = Some(UdpRelayIcmpErrorTracker::new(stats));
+    }
+
+    fn upstream_addr(&self, addr: SocketAddr) -> UpstreamAddr {
+        if self.normalize_ipv4_mapped {
+            UpstreamAddr::from(addr.to_canonical())
+        } else {
+            UpstreamAddr::from(addr)
         }
     }
 }
@@ -51,36 +88,82 @@ where
         self.bind_v6 = bind;
     }
 
+    /// Drain the socket error queue after a recv error.
+    ///
+    /// Return `Ok` if the error is caused by the queued errors, so the recv can be retried.
+    fn check_recv_error(
+        inner: &mut T,
+        bind_addr: SocketAddr,
+        e: io::Error,
+        icmp_errors: &mut Option<UdpRelayIcmpErrorTracker>,
+    ) -> Result<(), UdpRelayRemoteError> {
+        let Some(tracker) = icmp_errors else {
+            return Err(UdpRelayRemoteError::RecvFailed(bind_addr, e));
+        };
+
+        let mut drained = false;
+        while let Ok(Some(err)) = inner.try_recv_err() {
+            drained = true;
+            if let Some(dst) = tracker.record(&err) {
+                return Err(UdpRelayRemoteError::PortUnreachable(bind_addr, dst));
+            }
+        }
+        if drained {
+            Ok(())
+        } else {
+            Err(UdpRelayRemoteError::RecvFailed(bind_addr, e))
+        }
+    }
+
+    fn poll_recv_from(
+        inner: &mut T,
+        bind_addr: SocketAddr,
+        icmp_errors: &mut Option<UdpRelayIcmpErrorTracker>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<(usize, usize, SocketAddr), UdpRelayRemoteError>> {
+        loop {
+            match ready!(inner.poll_recv_from(cx, buf)) {
+                Ok((nr, addr)) => {
+                    if let Some(tracker) = icmp_errors {
+                        tracker.mark_alive(addr);
+                    }
+                    return Poll::Ready(Ok((0, nr, addr)));
+                }
+                Err(e) => Self::check_recv_error(inner, bind_addr, e, icmp_errors)?,
+            }
+        }
+    }
+
     fn poll_recv_packet(
         &mut self,
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<Result<(usize, usize, SocketAddr), UdpRelayRemoteError>> {
+        if let Some(mapping) = &self.mapping {
+            // the client side of the association is fixed, so only the mapped remote is needed
+            let (nr, key) = ready!(mapping.lock().unwrap().poll_recv_from(cx, buf))?;
+            if let Some(tracker) = &mut self.icmp_errors {
+                tracker.mark_alive(key.remote);
+            }
+            return Poll::Ready(Ok((0, nr, key.remote)));
+        }
+
+        let icmp_errors = &mut self.icmp_errors;
         match (&mut self.inner_v4, &mut self.inner_v6) {
             (Some(inner_v4), Some(inner_v6)) => {
-                let ret = match inner_v4.poll_recv_from(cx, buf) {
-                    Poll::Ready(t) => {
-                        let (nr, addr) =
-                            t.map_err(|e| UdpRelayRemoteError::RecvFailed(self.bind_v4, e))?;
-                        Ok((0, nr, addr))
-                    }
+                match Self::poll_recv_from(inner_v4, self.bind_v4, icmp_errors, cx, buf) {
+                    Poll::Ready(r) => Poll::Ready(r),
                     Poll::Pending => {
-                        let (nr, addr) = ready!(inner_v6.poll_recv_from(cx, buf))
-                            .map_err(|e| UdpRelayRemoteError::RecvFailed(self.bind_v6, e))?;
-                        Ok((0, nr, addr))
+                        Self::poll_recv_from(inner_v6, self.bind_v6, icmp_errors, cx, buf)
                     }
-                };
-                Poll::Ready(ret)
+                }
             }
             (Some(inner_v4), None) => {
-                let (nr, addr) = ready!(inner_v4.poll_recv_from(cx, buf))
-                    .map_err(|e| UdpRelayRemoteError::RecvFailed(self.bind_v4, e))?;
-                Poll::Ready(Ok((0, nr, addr)))
+                Self::poll_recv_from(inner_v4, self.bind_v4, icmp_errors, cx, buf)
             }
             (None, Some(inner_v6)) => {
-                let (nr, addr) = ready!(inner_v6.poll_recv_from(cx, buf))
-                    .map_err(|e| UdpRelayRemoteError::RecvFailed(self.bind_v6, e))?;
-                Poll::Ready(Ok((0, nr, addr)))
+                Self::poll_recv_from(inner_v6, self.bind_v6, icmp_errors, cx, buf)
             }
             (None, None) => Poll::Ready(Err(UdpRelayRemoteError::NoListenSocket)),
         }
@@ -98,6 +181,8 @@ where
     fn poll_recv_packets(
         inner: &mut T,
         bind_addr: SocketAddr,
+        normalize_ipv4_mapped: bool,
+        icmp_errors: &mut Option<UdpRelayIcmpErrorTracker>,
         cx: &mut Context<'_>,
         packets: &mut [UdpRelayPacket],
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
@@ -108,8 +193,12 @@ where
             .map(|p| RecvMsgHdr::new([std::io::IoSliceMut::new(p.buf_mut())]))
             .collect();
 
-        let count = ready!(inner.poll_batch_recvmsg(cx, &mut hdr_v))
-            .map_err(|e| UdpRelayRemoteError::RecvFailed(bind_addr, e))?;
+        let count = loop {
+            match ready!(inner.poll_batch_recvmsg(cx, &mut hdr_v)) {
+                Ok(count) => break count,
+                Err(e) => Self::check_recv_error(inner, bind_addr, e, icmp_errors)?,
+            }
+        };
 
         let mut r = Vec::with_capacity(count);
         for h in hdr_v.into_iter().take(count) {
@@ -118,8 +207,15 @@ where
                 SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
                 SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
             });
-            let ups = UpstreamAddr::from(addr);
-            r.push(UdpRelayPacketMeta::new(iov, 0, h.n_recv, ups))
+            if let Some(tracker) = icmp_errors.as_mut() {
+                tracker.mark_alive(addr);
+            }
+            let ups = if normalize_ipv4_mapped {
+                UpstreamAddr::from(addr.to_canonical())
+            } else {
+                UpstreamAddr::from(addr)
+            };
+            r.push(UdpRelayPacketMeta::new(iov, 0, h.n_recv, ups).with_truncated(h.truncated()))
         }
         for (m, p) in r.into_iter().zip(packets.iter_mut()) {
             m.set_packet(p);
@@ -143,7 +239,7 @@ where
         buf: &mut [u8],
     ) -> Poll<Result<(usize, usize, UpstreamAddr), UdpRelayRemoteError>> {
         let (off, nr, addr) = ready!(self.poll_recv_packet(cx, buf))?;
-        Poll::Ready(Ok((off, nr, UpstreamAddr::from(addr))))
+        Poll::Ready(Ok((off, nr, self.upstream_addr(addr))))
     }
 
     #[cfg(any(
@@ -160,16 +256,167 @@ where
         cx: &mut Context<'_>,
         packets: &mut [UdpRelayPacket],
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+        if self.mapping.is_some() {
+            let Some(p) = packets.first_mut() else {
+                return Poll::Ready(Ok(0));
+            };
+            let (off, nr, addr) = ready!(self.poll_recv_packet(cx, p.buf_mut()))?;
+            let iov = std::io::IoSliceMut::new(p.buf_mut());
+            let meta = UdpRelayPacketMeta::new(&iov, off, nr, self.upstream_addr(addr));
+            meta.set_packet(p);
+            return Poll::Ready(Ok(1));
+        }
+
+        let normalize = self.normalize_ipv4_mapped;
+        let icmp_errors = &mut self.icmp_errors;
         match (&mut self.inner_v4, &mut self.inner_v6) {
             (Some(inner_v4), Some(inner_v6)) => {
-                match Self::poll_recv_packets(inner_v4, self.bind_v4, cx, packets) {
+                match Self::poll_recv_packets(
+                    inner_v4,
+                    self.bind_v4,
+                    normalize,
+                    icmp_errors,
+                    cx,
+                    packets,
+                ) {
                     Poll::Ready(r) => Poll::Ready(r),
-                    Poll::Pending => Self::poll_recv_packets(inner_v6, self.bind_v6, cx, packets),
+                    Poll::Pending => Self::poll_recv_packets(
+                        inner_v6,
+                        self.bind_v6,
+                        normalize,
+                        icmp_errors,
+                        cx,
+                        packets,
+                    ),
                 }
             }
-            (Some(inner_v4), None) => Self::poll_recv_packets(inner_v4, self.bind_v4, cx, packets),
-            (None, Some(inner_v6)) => Self::poll_recv_packets(inner_v6, self.bind_v6, cx, packets),
+            (Some(inner_v4), None) => {
+                Self::poll_recv_packets(inner_v4, self.bind_v4, normalize, icmp_errors, cx, packets)
+            }
+            (None, Some(inner_v6)) => {
+                Self::poll_recv_packets(inner_v6, self.bind_v6, normalize, icmp_errors, cx, packets)
+            }
             (None, None) => Poll::Ready(Err(UdpRelayRemoteError::NoListenSocket)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use std::time::Duration;
+
+    use tokio::net::UdpSocket;
+
+    use g3_io_ext::UdpRecvHalf;
+    use g3_socket::BindAddr;
+    use g3_socket::util::AddressFamily;
+
+    async fn recv_from_v4_peer(normalize: bool) -> Option<(UpstreamAddr, SocketAddr)> {
+        // the relay socket is dual-stack unless ipv6 only is set by the system
+        let (socket, bind) = g3_socket::udp::new_std_bind_relay(
+            &BindAddr::None,
+            AddressFamily::Ipv6,
+            Default::default(),
+            Default::default(),
+        )
+        .ok()?;
+        let socket = UdpSocket::from_std(socket).unwrap();
+        let (recv, _send) = g3_io_ext::split_udp(socket);
+
+        let mut relay_recv = DirectUdpRelayRemoteRecv::<UdpRecvHalf>::new();
+        relay_recv.enable_v6(recv, bind);
+        relay_recv.set_normalize_ipv4_mapped(normalize);
+
+        let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let peer_addr = peer.local_addr().unwrap();
+        peer.send_to(
+            b"hello",
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), bind.port()),
+        )
+        .await
+        .unwrap();
+
+        let mut buf = [0u8; 16];
+        let (off, nr, ups) = tokio::time::timeout(
+            Duration::from_secs(1),
+            poll_fn(|cx| UdpRelayRemoteRecv::poll_recv_packet(&mut relay_recv, cx, &mut buf)),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(&buf[off..off + nr], b"hello");
+        Some((ups, peer_addr))
+    }
+
+    #[tokio::test]
+    async fn normalize_ipv4_mapped() {
+        // skip if ipv6 is not available
+        let Some((ups, peer_addr)) = recv_from_v4_peer(true).await else {
+            return;
+        };
+        assert_eq!(ups, UpstreamAddr::from(peer_addr));
+    }
+
+    #[tokio::test]
+    async fn keep_ipv4_mapped() {
+        let Some((ups, peer_addr)) = recv_from_v4_peer(false).await else {
+            return;
+        };
+        let IpAddr::V4(ip4) = peer_addr.ip() else {
+            unreachable!()
+        };
+        let mapped = SocketAddr::new(IpAddr::V6(ip4.to_ipv6_mapped()), peer_addr.port());
+        assert_eq!(ups, UpstreamAddr::from(mapped));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn port_unreachable() {
+        use g3_io_ext::AsyncUdpSend;
+        use g3_types::net::UdpMiscSockOpts;
+
+        let misc_opts = UdpMiscSockOpts {
+            recv_error: Some(true),
+            ..Default::default()
+        };
+        let (socket, bind) = g3_socket::udp::new_std_bind_relay(
+            &BindAddr::None,
+            AddressFamily::Ipv4,
+            Default::default(),
+            misc_opts,
+        )
+        .unwrap();
+        let socket = UdpSocket::from_std(socket).unwrap();
+        let (recv, mut send) = g3_io_ext::split_udp(socket);
+
+        let stats = Arc::new(UdpRelayIcmpErrorStats::default());
+        let mut relay_recv = DirectUdpRelayRemoteRecv::<UdpRecvHalf>::new();
+        relay_recv.enable_v4(recv, bind);
+        relay_recv.enable_icmp_errors(stats.clone());
+
+        // find a local port that is not listened
+        let closed = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let mut buf = [0u8; 16];
+        let mut dead = None;
+        for _ in 0..10 {
+            // the pending socket error may be returned by send, just ignore it
+            let _ = poll_fn(|cx| send.poll_send_to(cx, b"ping", closed_addr)).await;
+            let r = tokio::time::timeout(
+                Duration::from_millis(100),
+                poll_fn(|cx| UdpRelayRemoteRecv::poll_recv_packet(&mut relay_recv, cx, &mut buf)),
+            )
+            .await;
+            if let Ok(Err(UdpRelayRemoteError::PortUnreachable(_, dst))) = r {
+                dead = Some(dst);
+                break;
+            }
+        }
+        assert_eq!(dead, Some(closed_addr));
+        assert!(stats.snapshot().port_unreachable >= 3);
+    }
+}
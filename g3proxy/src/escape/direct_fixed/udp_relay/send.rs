@@ -22,11 +22,12 @@ use lru::LruCache;
 use g3_io_ext::UdpRelayPacket;
 use g3_io_ext::{AsyncUdpSend, UdpRelayRemoteError, UdpRelayRemoteSend};
 use g3_resolver::{ResolveError, ResolveLocalError};
+use g3_std_ext::net::SocketAddrExt;
 use g3_types::acl::{AclAction, AclNetworkRule};
 use g3_types::net::{Host, UpstreamAddr};
 use g3_types::resolve::ResolveStrategy;
 
-use super::DirectFixedEscaperStats;
+use super::{ArcDirectUdpRelayMappingTable, DirectFixedEscaperStats};
 use crate::auth::UserContext;
 use crate::resolve::{ArcIntegratedResolverHandle, ArriveFirstResolveJob};
 
@@ -46,6 +47,8 @@ pub(crate) struct DirectUdpRelayRemoteSend<T> {
     resolver_job: Option<ArriveFirstResolveJob>,
     resolve_retry_domain: Option<Arc<str>>,
     resolved_lru: LruCache<Arc<str>, IpAddr>,
+    mapping: Option<ArcDirectUdpRelayMappingTable>,
+    normalize_ipv4_mapped: bool,
 }
 
 impl<T> DirectUdpRelayRemoteSend<T> {
@@ -70,8 +73,20 @@ impl<T> DirectUdpRelayRemoteSend<T> {
             resolver_job: None,
             resolve_retry_domain: None,
             resolved_lru: LruCache::new(LRU_CACHE_SIZE),
+            mapping: None,
+            normalize_ipv4_mapped: true,
         }
     }
+
+    pub(crate) fn enable_mapping(&mut self, table: ArcDirectUdpRelayMappingTable) {
+        self.mapping = Some(table);
+    }
+
+    /// Convert IPv4-mapped IPv6 target addresses to IPv4 addresses, and send packets to IPv4
+    /// targets through the dual-stack IPv6 socket if there is no IPv4 socket
+    pub(crate) fn set_normalize_ipv4_mapped(&mut self, enable: bool) {
+        self.normalize_ipv4_mapped = enable;
+    }
 }
 
 impl<T> DirectUdpRelayRemoteSend<T>
@@ -89,7 +104,7 @@ where
     }
 
     pub(crate) fn usable(&self) -> bool {
-        self.inner_v4.is_some() || self.inner_v6.is_some()
+        self.inner_v4.is_some() || self.inner_v6.is_some() || self.mapping.is_some()
     }
 
     fn poll_send_packet(
@@ -215,6 +230,11 @@ where
         buf: &[u8],
         to: SocketAddr,
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+        let to = if self.normalize_ipv4_mapped {
+            to.to_canonical()
+        } else {
+            to
+        };
         match to {
             SocketAddr::V4(_) => self.poll_send_v4_packet(cx, buf, to),
             SocketAddr::V6(_) => self.poll_send_v6_packet(cx, buf, to),
@@ -228,6 +248,9 @@ where
         to: SocketAddr,
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
         self.check_egress_ip(to)?;
+        if let Some(mapping) = &self.mapping {
+            return mapping.lock().unwrap().poll_send_to(cx, buf, to);
+        }
         if let Some(inner) = &mut self.inner_v4 {
             let nw = ready!(inner.poll_send_to(cx, buf, to))
                 .map_err(|e| UdpRelayRemoteError::SendFailed(self.bind_v4, to, e))?;
@@ -240,6 +263,9 @@ where
             } else {
                 Poll::Ready(Ok(nw))
             }
+        } else if self.normalize_ipv4_mapped && self.inner_v6.is_some() {
+            // the ipv6 relay socket is dual-stack
+            self.poll_send_v6_socket(cx, buf, to)
         } else {
             Poll::Ready(Err(UdpRelayRemoteError::AddressNotSupported))
         }
@@ -252,6 +278,19 @@ where
         to: SocketAddr,
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
         self.check_egress_ip(to)?;
+        if let Some(mapping) = &self.mapping {
+            return mapping.lock().unwrap().poll_send_to(cx, buf, to);
+        }
+        self.poll_send_v6_socket(cx, buf, to)
+    }
+
+    fn poll_send_v6_socket(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        to: SocketAddr,
+    ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+        let to = to_ipv6_socket_addr(to);
         if let Some(inner) = &mut self.inner_v6 {
             let nw = ready!(inner.poll_send_to(cx, buf, to))
                 .map_err(|e| UdpRelayRemoteError::SendFailed(self.bind_v6, to, e))?;
@@ -269,6 +308,26 @@ where
         }
     }
 
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+    ))]
+    fn packet_target_ip(&mut self, p: &UdpRelayPacket) -> Option<IpAddr> {
+        let ip = match p.upstream().host() {
+            Host::Ip(ip) => *ip,
+            Host::Domain(domain) => *self.resolved_lru.get(domain)?,
+        };
+        if self.normalize_ipv4_mapped {
+            Some(ip.to_canonical())
+        } else {
+            Some(ip)
+        }
+    }
+
     #[cfg(any(
         target_os = "linux",
         target_os = "android",
@@ -297,6 +356,10 @@ where
                         .map(|ip| SocketAddr::new(*ip, p.upstream().port()))
                         .unwrap(),
                 };
+                let addr = match bind_addr {
+                    SocketAddr::V4(_) => addr.to_canonical(),
+                    SocketAddr::V6(_) => to_ipv6_socket_addr(addr),
+                };
                 SendMsgHdr::new([IoSlice::new(p.payload())], Some(addr))
             })
             .collect();
@@ -314,6 +377,13 @@ where
     }
 }
 
+fn to_ipv6_socket_addr(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4(a4) => SocketAddr::new(IpAddr::V6(a4.ip().to_ipv6_mapped()), a4.port()),
+        SocketAddr::V6(_) => addr,
+    }
+}
+
 impl<T> UdpRelayRemoteSend for DirectUdpRelayRemoteSend<T>
 where
     T: AsyncUdpSend + Send,
@@ -344,29 +414,23 @@ where
             return Poll::Ready(Ok(0));
         };
 
-        let ip = match p.upstream().host() {
-            Host::Ip(ip) => *ip,
-            Host::Domain(domain) => match self.resolved_lru.get(domain) {
-                Some(ip) => *ip,
-                None => {
-                    let _ = ready!(self.poll_send_packet(cx, p.payload(), p.upstream()))?;
-                    return Poll::Ready(Ok(1));
-                }
-            },
+        if self.mapping.is_some() {
+            let _ = ready!(self.poll_send_packet(cx, p.payload(), p.upstream()))?;
+            return Poll::Ready(Ok(1));
+        }
+
+        let Some(ip) = self.packet_target_ip(p) else {
+            let _ = ready!(self.poll_send_packet(cx, p.payload(), p.upstream()))?;
+            return Poll::Ready(Ok(1));
         };
 
+        let normalize = self.normalize_ipv4_mapped;
         match ip {
             IpAddr::V4(_) => {
                 let mut count = 0;
                 for p in packets {
-                    let ip = match p.upstream().host() {
-                        Host::Ip(IpAddr::V4(v4)) => IpAddr::V4(*v4),
-                        Host::Ip(IpAddr::V6(_)) => break,
-                        Host::Domain(domain) => match self.resolved_lru.get(domain) {
-                            Some(IpAddr::V4(v4)) => IpAddr::V4(*v4),
-                            Some(IpAddr::V6(_)) => break,
-                            None => break,
-                        },
+                    let Some(ip @ IpAddr::V4(_)) = self.packet_target_ip(p) else {
+                        break;
                     };
 
                     if let Err(e) = self.check_egress_ip(SocketAddr::new(ip, p.upstream().port())) {
@@ -388,6 +452,15 @@ where
                         cx,
                         &packets[0..count],
                     )
+                } else if let Some(inner) = self.inner_v6.as_mut().filter(|_| normalize) {
+                    // the ipv6 relay socket is dual-stack
+                    Self::poll_send_packets(
+                        inner,
+                        &mut self.resolved_lru,
+                        self.bind_v6,
+                        cx,
+                        &packets[0..count],
+                    )
                 } else {
                     Poll::Ready(Err(UdpRelayRemoteError::AddressNotSupported))
                 }
@@ -395,14 +468,8 @@ where
             IpAddr::V6(_) => {
                 let mut count = 0;
                 for p in packets {
-                    let ip = match p.upstream().host() {
-                        Host::Ip(IpAddr::V4(_)) => break,
-                        Host::Ip(IpAddr::V6(v6)) => IpAddr::V6(*v6),
-                        Host::Domain(domain) => match self.resolved_lru.get(domain) {
-                            Some(IpAddr::V4(_)) => break,
-                            Some(IpAddr::V6(v6)) => IpAddr::V6(*v6),
-                            None => break,
-                        },
+                    let Some(ip @ IpAddr::V6(_)) = self.packet_target_ip(p) else {
+                        break;
                     };
 
                     if let Err(e) = self.check_egress_ip(SocketAddr::new(ip, p.upstream().port())) {
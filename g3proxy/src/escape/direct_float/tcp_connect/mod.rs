@@ -130,6 +130,7 @@ impl DirectFloatEscaper {
                     .map_err(TcpConnectError::SetupSocketFailed)?;
                 self.stats.tcp.connect.add_established();
                 tcp_notes.local = Some(local_addr);
+                tcp_notes.record_tcp_rtt(&ups_stream);
                 tcp_notes.chained.target_addr = Some(peer);
                 tcp_notes.chained.outgoing_addr = Some(local_addr);
                 Ok((ups_stream, bind))
@@ -261,6 +262,7 @@ impl DirectFloatEscaper {
                                             .map_err(TcpConnectError::SetupSocketFailed)?;
                                         self.stats.tcp.connect.add_established();
                                         tcp_notes.local = Some(local_addr);
+                                        tcp_notes.record_tcp_rtt(&ups_stream);
                                         tcp_notes.chained.target_addr = Some(peer_addr);
                                         tcp_notes.chained.outgoing_addr = Some(local_addr);
                                         return Ok((ups_stream, bind));
@@ -354,6 +356,10 @@ impl DirectFloatEscaper {
             config.keepalive = config.keepalive.adjust_to(user_config.tcp_remote_keepalive);
             config.misc_opts = user_config.tcp_remote_misc_opts(&self.config.tcp_misc_opts);
         }
+        if let Some(timeout) = task_notes.tcp_connect_each_timeout {
+            let each_timeout = config.connect.each_timeout().min(timeout);
+            config.connect.set_each_timeout(each_timeout);
+        }
 
         match task_conf.upstream.host() {
             Host::Ip(ip) => {
@@ -455,6 +461,9 @@ impl DirectFloatEscaper {
         let (stream, _) = self
             .tcp_connect_to(task_conf, tcp_notes, task_notes)
             .await?;
+        task_notes
+            .set_tcp_upstream_misc_opts(&stream)
+            .map_err(TcpConnectError::SetupSocketFailed)?;
         let (r, w) = stream.into_split();
 
         let mut wrapper_stats = TcpConnectRemoteWrapperStats::new(self.stats.clone(), task_stats);
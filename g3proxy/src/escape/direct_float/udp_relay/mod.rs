@@ -3,8 +3,9 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::net::SocketAddr;
-use std::sync::Arc;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
 
 use anyhow::anyhow;
 use tokio::net::UdpSocket;
@@ -12,12 +13,17 @@ use tokio::net::UdpSocket;
 use g3_io_ext::{LimitedUdpRecv, LimitedUdpSend, UdpRecvHalf, UdpSendHalf};
 use g3_socket::BindAddr;
 use g3_socket::util::AddressFamily;
+use g3_types::net::{SocketBufferConfig, UdpMiscSockOpts};
 
 use super::DirectFloatEscaper;
-use crate::escape::direct_fixed::udp_relay::{DirectUdpRelayRemoteRecv, DirectUdpRelayRemoteSend};
+use crate::config::escaper::direct_fixed::UdpRelayMappingConfig;
+use crate::config::escaper::direct_float::DirectFloatEscaperConfig;
+use crate::escape::direct_fixed::udp_relay::{
+    DirectUdpRelayMappingTable, DirectUdpRelayRemoteRecv, DirectUdpRelayRemoteSend,
+};
 use crate::module::udp_relay::{
-    ArcUdpRelayTaskRemoteStats, UdpRelayRemoteWrapperStats, UdpRelaySetupError,
-    UdpRelaySetupResult, UdpRelayTaskConf,
+    ArcUdpRelayTaskRemoteStats, UdpRelayMappingSocketFactory, UdpRelayMappingTable,
+    UdpRelayRemoteWrapperStats, UdpRelaySetupError, UdpRelaySetupResult, UdpRelayTaskConf,
 };
 use crate::serve::ServerTaskNotes;
 
@@ -40,6 +46,17 @@ impl DirectFloatEscaper {
             &self.resolver_handle,
             self.config.resolve_strategy,
         );
+        recv.set_normalize_ipv4_mapped(self.config.udp_relay_normalize_ipv4_mapped);
+        send.set_normalize_ipv4_mapped(self.config.udp_relay_normalize_ipv4_mapped);
+        recv.enable_icmp_errors(self.stats.udp_icmp_error.clone());
+
+        if let Some(config) = self.config.udp_relay_mapping {
+            let table = self.new_mapping_table(config, task_conf, task_notes, &wrapper_stats)?;
+            let table = Arc::new(Mutex::new(table));
+            recv.enable_mapping(table.clone());
+            send.enable_mapping(table);
+            return Ok((Box::new(recv), Box::new(send), self.escape_logger.clone()));
+        }
 
         if !self.config.no_ipv4 {
             if let Ok((bind, r, w)) =
@@ -68,6 +85,16 @@ impl DirectFloatEscaper {
         Ok((Box::new(recv), Box::new(send), self.escape_logger.clone()))
     }
 
+    fn get_udp_misc_opts(&self, task_notes: &ServerTaskNotes) -> UdpMiscSockOpts {
+        if let Some(user_ctx) = task_notes.user_ctx() {
+            user_ctx
+                .user_config()
+                .udp_remote_misc_opts(&self.config.udp_misc_opts)
+        } else {
+            self.config.udp_misc_opts
+        }
+    }
+
     fn get_relay_socket(
         &self,
         family: AddressFamily,
@@ -85,40 +112,107 @@ impl DirectFloatEscaper {
         let bind = self
             .select_bind(family, task_notes)
             .map_err(UdpRelaySetupError::EscaperNotUsable)?;
+        let misc_opts = self.get_udp_misc_opts(task_notes);
 
-        let misc_opts = if let Some(user_ctx) = task_notes.user_ctx() {
-            user_ctx
-                .user_config()
-                .udp_remote_misc_opts(&self.config.udp_misc_opts)
-        } else {
-            self.config.udp_misc_opts
-        };
-
-        let (socket, bind_addr) = g3_socket::udp::new_std_bind_relay(
-            &BindAddr::Ip(bind.ip),
+        new_relay_socket(
+            &self.config,
+            bind.ip,
             family,
             task_conf.sock_buf,
             misc_opts,
+            stats,
         )
-        .map_err(UdpRelaySetupError::SetupSocketFailed)?;
-        let socket = UdpSocket::from_std(socket).map_err(UdpRelaySetupError::SetupSocketFailed)?;
-
-        let (recv, send) = g3_io_ext::split_udp(socket);
-        let recv = LimitedUdpRecv::local_limited(
-            recv,
-            self.config.general.udp_sock_speed_limit.shift_millis,
-            self.config.general.udp_sock_speed_limit.max_south_packets,
-            self.config.general.udp_sock_speed_limit.max_south_bytes,
-            stats.clone(),
-        );
-        let send = LimitedUdpSend::local_limited(
-            send,
-            self.config.general.udp_sock_speed_limit.shift_millis,
-            self.config.general.udp_sock_speed_limit.max_north_packets,
-            self.config.general.udp_sock_speed_limit.max_north_bytes,
-            stats.clone(),
-        );
+        .map_err(UdpRelaySetupError::SetupSocketFailed)
+    }
 
-        Ok((bind_addr, recv, send))
+    /// The bind IP is selected once for each address family, and shared by all mapping entries
+    fn new_mapping_table(
+        &self,
+        config: UdpRelayMappingConfig,
+        task_conf: &UdpRelayTaskConf<'_>,
+        task_notes: &ServerTaskNotes,
+        stats: &Arc<UdpRelayRemoteWrapperStats>,
+    ) -> Result<DirectUdpRelayMappingTable, UdpRelaySetupError> {
+        let bind_v4 = if self.config.no_ipv4 {
+            None
+        } else {
+            self.select_bind(AddressFamily::Ipv4, task_notes)
+                .ok()
+                .map(|bind| bind.ip)
+        };
+        let bind_v6 = if self.config.no_ipv6 {
+            None
+        } else {
+            self.select_bind(AddressFamily::Ipv6, task_notes)
+                .ok()
+                .map(|bind| bind.ip)
+        };
+        if bind_v4.is_none() && bind_v6.is_none() {
+            return Err(UdpRelaySetupError::EscaperNotUsable(anyhow!(
+                "no ipv4 / ipv6 bind address found"
+            )));
+        }
+        let sock_buf = task_conf.sock_buf;
+        let misc_opts = self.get_udp_misc_opts(task_notes);
+        let escaper_config = self.config.clone();
+        let stats = stats.clone();
+
+        let factory: UdpRelayMappingSocketFactory<_, _> = Box::new(move |peer: SocketAddr| {
+            let family = AddressFamily::from(&peer);
+            let bind = match family {
+                AddressFamily::Ipv4 => bind_v4,
+                AddressFamily::Ipv6 => bind_v6,
+            };
+            let Some(bind) = bind else {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "no bind address found for this address family",
+                ));
+            };
+            new_relay_socket(&escaper_config, bind, family, sock_buf, misc_opts, &stats)
+        });
+
+        Ok(UdpRelayMappingTable::new(
+            config,
+            task_conf.client_addr,
+            self.stats.udp_mapping.clone(),
+            factory,
+        ))
     }
 }
+
+fn new_relay_socket(
+    escaper_config: &DirectFloatEscaperConfig,
+    bind: IpAddr,
+    family: AddressFamily,
+    sock_buf: SocketBufferConfig,
+    misc_opts: UdpMiscSockOpts,
+    stats: &Arc<UdpRelayRemoteWrapperStats>,
+) -> io::Result<(
+    SocketAddr,
+    LimitedUdpRecv<UdpRecvHalf>,
+    LimitedUdpSend<UdpSendHalf>,
+)> {
+    let (socket, bind_addr) =
+        g3_socket::udp::new_std_bind_relay(&BindAddr::Ip(bind), family, sock_buf, misc_opts)?;
+    let speed_limit = &escaper_config.general.udp_sock_speed_limit;
+    let socket = UdpSocket::from_std(socket)?;
+
+    let (recv, send) = g3_io_ext::split_udp(socket);
+    let recv = LimitedUdpRecv::local_limited(
+        recv,
+        speed_limit.shift_millis,
+        speed_limit.max_south_packets,
+        speed_limit.max_south_bytes,
+        stats.clone(),
+    );
+    let send = LimitedUdpSend::local_limited(
+        send,
+        speed_limit.shift_millis,
+        speed_limit.max_north_packets,
+        speed_limit.max_north_bytes,
+        stats.clone(),
+    );
+
+    Ok((bind_addr, recv, send))
+}
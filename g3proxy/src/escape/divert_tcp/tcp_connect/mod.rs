@@ -106,6 +106,7 @@ impl DivertTcpEscaper {
                     .map_err(TcpConnectError::SetupSocketFailed)?;
                 self.stats.tcp.connect.add_established();
                 tcp_notes.local = Some(local_addr);
+                tcp_notes.record_tcp_rtt(&ups_stream);
                 // the chained outgoing addr is not detected at here
                 Ok(ups_stream)
             }
@@ -232,6 +233,7 @@ impl DivertTcpEscaper {
                                             .map_err(TcpConnectError::SetupSocketFailed)?;
                                         self.stats.tcp.connect.add_established();
                                         tcp_notes.local = Some(local_addr);
+                                        tcp_notes.record_tcp_rtt(&ups_stream);
                                         // the chained outgoing addr is not detected at here
                                         return Ok(ups_stream);
                                     }
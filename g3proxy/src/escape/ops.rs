@@ -4,6 +4,7 @@
  */
 
 use std::collections::HashSet;
+use std::path::PathBuf;
 
 use anyhow::{Context, anyhow};
 use async_recursion::async_recursion;
@@ -11,7 +12,6 @@ use log::{debug, warn};
 use tokio::sync::Mutex;
 
 use g3_types::metrics::NodeName;
-use g3_yaml::YamlDocPosition;
 
 use super::registry;
 use crate::config::escaper::{AnyEscaperConfig, EscaperConfigDiffAction};
@@ -80,10 +80,14 @@ pub(crate) fn get_escaper(name: &NodeName) -> anyhow::Result<ArcEscaper> {
     }
 }
 
+/// Reload the named escaper only, all other escapers will be left untouched.
+///
+/// The config will be loaded from `path` if set, or else from the recorded config position
+/// of the escaper, or else from the main config file.
 pub(crate) async fn reload(
     name: &NodeName,
-    position: Option<YamlDocPosition>,
-) -> anyhow::Result<()> {
+    path: Option<PathBuf>,
+) -> anyhow::Result<EscaperConfigDiffAction> {
     let _guard = ESCAPER_OPS_LOCK.lock().await;
 
     let old_config = match registry::get_config(name) {
@@ -91,35 +95,47 @@ pub(crate) async fn reload(
         None => return Err(anyhow!("no escaper with name {name} found")),
     };
 
-    let position = match position {
-        Some(position) => position,
-        None => match old_config.position() {
-            Some(position) => position,
-            None => {
+    let config = match (path, old_config.position()) {
+        (None, Some(position)) => {
+            let position2 = position.clone();
+            let config = tokio::task::spawn_blocking(move || {
+                crate::config::escaper::load_at_position(&position2)
+            })
+            .await
+            .map_err(|e| anyhow!("unable to join conf load task: {e}"))?
+            .context(format!("unload to load conf at position {position}"))?;
+            if name != config.name() {
                 return Err(anyhow!(
-                    "no config position for escaper {name} found, reload is not supported"
+                    "escaper at position {position} has name {}, while we expect {name}",
+                    config.name()
                 ));
             }
-        },
-    };
-
-    let position2 = position.clone();
-    let config =
-        tokio::task::spawn_blocking(move || crate::config::escaper::load_at_position(&position2))
+            debug!("reloading escaper {name} from position {position}");
+            config
+        }
+        (path, _) => {
+            let path = match path {
+                Some(path) => path,
+                None => g3_daemon::opts::config_file()
+                    .ok_or_else(|| anyhow!("no config file set"))?
+                    .to_path_buf(),
+            };
+            let path2 = path.clone();
+            let name2 = name.clone();
+            let config = tokio::task::spawn_blocking(move || {
+                crate::config::escaper::load_named_in_file(&path2, &name2)
+            })
             .await
             .map_err(|e| anyhow!("unable to join conf load task: {e}"))?
-            .context(format!("unload to load conf at position {position}"))?;
-    if name != config.name() {
-        return Err(anyhow!(
-            "escaper at position {position} has name {}, while we expect {name}",
-            config.name()
-        ));
-    }
+            .context(format!("unable to load conf from file {}", path.display()))?;
+            debug!("reloading escaper {name} from file {}", path.display());
+            config
+        }
+    };
 
-    debug!("reloading escaper {name} from position {position}");
-    reload_unlocked(old_config, config).await?;
+    let action = reload_unlocked(old_config, config).await?;
     debug!("escaper {name} reload OK");
-    Ok(())
+    Ok(action)
 }
 
 pub(crate) async fn update_dependency_to_resolver(resolver: &NodeName, status: &str) {
@@ -198,22 +214,26 @@ async fn update_dependency_to_escaper_unlocked(target: &NodeName, status: &str)
     }
 }
 
-async fn reload_unlocked(old: AnyEscaperConfig, new: AnyEscaperConfig) -> anyhow::Result<()> {
+async fn reload_unlocked(
+    old: AnyEscaperConfig,
+    new: AnyEscaperConfig,
+) -> anyhow::Result<EscaperConfigDiffAction> {
     let name = old.name();
-    match old.diff_action(&new) {
+    let action = old.diff_action(&new);
+    match action {
         EscaperConfigDiffAction::NoAction => {
             debug!("escaper {name} reload: no action is needed");
-            Ok(())
         }
         EscaperConfigDiffAction::SpawnNew => {
             debug!("escaper {name} reload: will create a totally new one");
-            spawn_new_unlocked(new).await
+            spawn_new_unlocked(new).await?;
         }
         EscaperConfigDiffAction::Reload => {
             debug!("escaper {name} reload: will reload from existed");
-            reload_existed_unlocked(name, Some(new)).await
+            reload_existed_unlocked(name, Some(new)).await?;
         }
     }
+    Ok(action)
 }
 
 async fn delete_existed_unlocked(name: &NodeName) {
@@ -93,6 +93,7 @@ impl ProxyFloatEscaper {
                     .map_err(TcpConnectError::SetupSocketFailed)?;
                 self.stats.tcp.connect.add_established();
                 tcp_notes.local = Some(local_addr);
+                tcp_notes.record_tcp_rtt(&ups_stream);
                 Ok(ups_stream)
             }
             Ok(Err(e)) => {
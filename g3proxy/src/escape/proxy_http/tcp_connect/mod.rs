@@ -102,6 +102,7 @@ impl ProxyHttpEscaper {
                     .map_err(TcpConnectError::SetupSocketFailed)?;
                 self.stats.tcp.connect.add_established();
                 tcp_notes.local = Some(local_addr);
+                tcp_notes.record_tcp_rtt(&ups_stream);
                 // the chained outgoing addr is not detected at here
                 Ok(ups_stream)
             }
@@ -228,6 +229,7 @@ impl ProxyHttpEscaper {
                                             .map_err(TcpConnectError::SetupSocketFailed)?;
                                         self.stats.tcp.connect.add_established();
                                         tcp_notes.local = Some(local_addr);
+                                        tcp_notes.record_tcp_rtt(&ups_stream);
                                         // the chained outgoing addr is not detected at here
                                         return Ok(ups_stream);
                                     }
@@ -16,9 +16,9 @@ use g3_daemon::stat::remote::{
 };
 use g3_io_ext::{AsyncStream, LimitedReader, LimitedStream, LimitedWriter};
 use g3_openssl::{SslConnector, SslStream};
-use g3_socket::BindAddr;
+use g3_socket::{BindAddr, RawSocket};
 use g3_socks::v5;
-use g3_types::net::{SocketBufferConfig, UpstreamAddr};
+use g3_types::net::{SocketBufferConfig, TcpKeepAliveConfig, UpstreamAddr};
 
 use super::ProxySocks5Escaper;
 use crate::log::escape::tls_handshake::{EscapeLogForTlsHandshake, TlsApplication};
@@ -76,6 +76,12 @@ impl ProxySocks5Escaper {
             .tcp_new_connection(&tcp_task_conf, tcp_notes, task_notes)
             .await
             .map_err(io::Error::other)?;
+        if !self.config.tcp_keepalive.is_enabled() {
+            // the control connection will be idle during the whole udp session,
+            // keep it alive so that the close of the peer side can be detected
+            RawSocket::from(ctl_stream.get_ref())
+                .set_tcp_keepalive(&TcpKeepAliveConfig::default_enabled())?;
+        }
         let local_tcp_addr = tcp_notes
             .local
             .ok_or_else(|| io::Error::other("no local tcp address"))?;
@@ -18,7 +18,9 @@ use crate::escape::{
 };
 use crate::module::http_forward::HttpForwardTaskRemoteStats;
 use crate::module::udp_connect::UdpConnectTaskRemoteStats;
-use crate::module::udp_relay::UdpRelayTaskRemoteStats;
+use crate::module::udp_relay::{
+    UdpRelayMappingSnapshot, UdpRelayMappingStats, UdpRelayTaskRemoteStats,
+};
 
 pub(crate) struct ProxySocks5EscaperStats {
     name: NodeName,
@@ -27,6 +29,7 @@ pub(crate) struct ProxySocks5EscaperStats {
     pub(crate) interface: EscaperInterfaceStats,
     pub(crate) udp: EscaperUdpStats,
     pub(crate) tcp: EscaperTcpStats,
+    pub(crate) udp_mapping: Arc<UdpRelayMappingStats>,
 }
 
 impl ProxySocks5EscaperStats {
@@ -38,6 +41,7 @@ impl ProxySocks5EscaperStats {
             interface: EscaperInterfaceStats::default(),
             udp: EscaperUdpStats::default(),
             tcp: EscaperTcpStats::default(),
+            udp_mapping: Default::default(),
         }
     }
 
@@ -98,6 +102,10 @@ impl EscaperStats for ProxySocks5EscaperStats {
     fn udp_io_snapshot(&self) -> Option<UdpIoSnapshot> {
         Some(self.udp.io.snapshot())
     }
+
+    fn udp_mapping_snapshot(&self) -> Option<UdpRelayMappingSnapshot> {
+        Some(self.udp_mapping.snapshot())
+    }
 }
 
 impl LimitedReaderStats for ProxySocks5EscaperStats {
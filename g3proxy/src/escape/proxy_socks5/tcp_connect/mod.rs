@@ -101,6 +101,7 @@ impl ProxySocks5Escaper {
                     .map_err(TcpConnectError::SetupSocketFailed)?;
                 self.stats.tcp.connect.add_established();
                 tcp_notes.local = Some(local_addr);
+                tcp_notes.record_tcp_rtt(&ups_stream);
                 // the chained outgoing addr is not detected at here
                 Ok(ups_stream)
             }
@@ -227,6 +228,7 @@ impl ProxySocks5Escaper {
                                             .map_err(TcpConnectError::SetupSocketFailed)?;
                                         self.stats.tcp.connect.add_established();
                                         tcp_notes.local = Some(local_addr);
+                                        tcp_notes.record_tcp_rtt(&ups_stream);
                                         // the chained outgoing addr is not detected at here
                                         return Ok(ups_stream);
                                     }
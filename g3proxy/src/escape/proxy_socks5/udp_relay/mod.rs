@@ -3,15 +3,15 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use g3_io_ext::{LimitedUdpRecv, LimitedUdpSend};
 
 use super::ProxySocks5Escaper;
 use crate::module::tcp_connect::TcpConnectTaskNotes;
 use crate::module::udp_relay::{
-    ArcUdpRelayTaskRemoteStats, UdpRelayRemoteWrapperStats, UdpRelaySetupError,
-    UdpRelaySetupResult, UdpRelayTaskConf,
+    ArcUdpRelayTaskRemoteStats, UdpRelayMappingTracker, UdpRelayRemoteWrapperStats,
+    UdpRelaySetupError, UdpRelaySetupResult, UdpRelayTaskConf,
 };
 use crate::serve::ServerTaskNotes;
 
@@ -54,15 +54,321 @@ impl ProxySocks5Escaper {
             wrapper_stats,
         );
 
-        let recv = ProxySocks5UdpRelayRemoteRecv::new(
+        let mut recv = ProxySocks5UdpRelayRemoteRecv::new(
             recv,
             udp_local_addr,
             udp_peer_addr,
             ctl_stream,
             self.config.end_on_control_closed,
         );
-        let send = ProxySocks5UdpRelayRemoteSend::new(send, udp_local_addr, udp_peer_addr);
+        let mut send = ProxySocks5UdpRelayRemoteSend::new(send, udp_local_addr, udp_peer_addr);
+        if let Some(config) = self.config.udp_relay_mapping {
+            let tracker = UdpRelayMappingTracker::new(config, self.stats.udp_mapping.clone());
+            let tracker = Arc::new(Mutex::new(tracker));
+            recv.enable_mapping(tracker.clone());
+            send.enable_mapping(tracker);
+        }
 
         Ok((Box::new(recv), Box::new(send), self.escape_logger.clone()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream, UdpSocket};
+    use tokio::sync::oneshot;
+
+    use g3_io_ext::{
+        UdpRecvHalf, UdpRelayRemoteError, UdpRelayRemoteRecv, UdpRelayRemoteSend, UdpSendHalf,
+    };
+    use g3_socks::v5::{Socks5Reply, Socks5Request, UdpInput, UdpOutput};
+    use g3_socks::{SocksAuthMethod, SocksCommand};
+    use g3_types::net::{SocksAuth, UpstreamAddr};
+
+    use crate::config::escaper::direct_fixed::UdpRelayMappingConfig;
+    use crate::module::udp_relay::UdpRelayMappingStats;
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// A miniature upstream socks5 server which echoes back the first udp packet.
+    ///
+    /// The control connection will be closed before the echo if `close` is None,
+    /// or after the echo when notified.
+    async fn run_upstream_server(
+        listener: TcpListener,
+        close: Option<oneshot::Receiver<()>>,
+    ) -> UpstreamAddr {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let relay = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let (r, mut w) = stream.split();
+        let mut r = BufReader::new(r);
+        assert_eq!(r.read_u8().await.unwrap(), 0x05);
+        let methods = g3_socks::v5::auth::recv_methods_from_client(&mut r)
+            .await
+            .unwrap();
+        assert!(methods.contains(&SocksAuthMethod::None));
+        g3_socks::v5::auth::send_method_to_client(&mut w, &SocksAuthMethod::None)
+            .await
+            .unwrap();
+        let req = Socks5Request::recv(&mut r).await.unwrap();
+        assert!(matches!(req.command, SocksCommand::UdpAssociate));
+        Socks5Reply::Succeeded(relay.local_addr().unwrap())
+            .send(&mut w)
+            .await
+            .unwrap();
+        let ctl_stream = close.is_some().then_some(stream);
+
+        let mut buf = [0u8; 512];
+        let (nr, client_addr) = relay.recv_from(&mut buf).await.unwrap();
+        let (off, target) = UdpInput::parse_header(&buf[..nr]).unwrap();
+        let payload = buf[off..nr].to_vec();
+
+        // reply as if it comes from the target
+        let hdr_len = UdpOutput::calc_header_len(&target);
+        let mut rsp = vec![0u8; hdr_len + payload.len()];
+        UdpOutput::generate_header(&mut rsp, &target);
+        rsp[hdr_len..].copy_from_slice(&payload);
+        relay.send_to(&rsp, client_addr).await.unwrap();
+
+        if let Some(close) = close {
+            let _ = close.await;
+        }
+        drop(ctl_stream);
+        target
+    }
+
+    async fn associate(
+        server_addr: SocketAddr,
+        end_on_control_closed: bool,
+    ) -> (
+        ProxySocks5UdpRelayRemoteRecv<UdpRecvHalf, TcpStream>,
+        ProxySocks5UdpRelayRemoteSend<UdpSendHalf>,
+    ) {
+        let mut ctl_stream = TcpStream::connect(server_addr).await.unwrap();
+        let local_udp_addr = SocketAddr::new(server_addr.ip(), 0);
+        let peer_udp_addr = g3_socks::v5::client::socks5_udp_associate(
+            &mut ctl_stream,
+            &SocksAuth::None,
+            local_udp_addr,
+        )
+        .await
+        .unwrap();
+
+        let socket = UdpSocket::bind(local_udp_addr).await.unwrap();
+        socket.connect(peer_udp_addr).await.unwrap();
+        let local_addr = socket.local_addr().unwrap();
+        let (recv, send) = g3_io_ext::split_udp(socket);
+        let recv = ProxySocks5UdpRelayRemoteRecv::new(
+            recv,
+            local_addr,
+            peer_udp_addr,
+            ctl_stream,
+            end_on_control_closed,
+        );
+        let send = ProxySocks5UdpRelayRemoteSend::new(send, local_addr, peer_udp_addr);
+        (recv, send)
+    }
+
+    async fn send_hello(send: &mut ProxySocks5UdpRelayRemoteSend<UdpSendHalf>) -> UpstreamAddr {
+        let target = UpstreamAddr::from_host_str_and_port("echo.example.net", 7).unwrap();
+        let nw = poll_fn(|cx| send.poll_send_packet(cx, b"hello", &target))
+            .await
+            .unwrap();
+        assert!(nw > 5);
+        target
+    }
+
+    async fn round_trip(end_on_control_closed: bool) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let (close_sender, close_receiver) = oneshot::channel();
+        let server = tokio::spawn(run_upstream_server(listener, Some(close_receiver)));
+
+        let (mut recv, mut send) = associate(server_addr, end_on_control_closed).await;
+        let target = send_hello(&mut send).await;
+
+        let mut buf = [0u8; 512];
+        let (off, nr, ups) =
+            tokio::time::timeout(TIMEOUT, poll_fn(|cx| recv.poll_recv_packet(cx, &mut buf)))
+                .await
+                .unwrap()
+                .unwrap();
+        // the socks5 udp header should be stripped
+        assert_eq!(&buf[off..nr], b"hello");
+        assert_eq!(ups, target);
+
+        // the close of the upstream control connection should always end the relay
+        // after some packets have been received
+        close_sender.send(()).unwrap();
+        assert_eq!(server.await.unwrap(), target);
+        let r = tokio::time::timeout(TIMEOUT, poll_fn(|cx| recv.poll_recv_packet(cx, &mut buf)))
+            .await
+            .unwrap();
+        assert!(matches!(
+            r,
+            Err(UdpRelayRemoteError::RemoteSessionClosed(_, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn round_trip_end_on_control_closed() {
+        round_trip(true).await;
+    }
+
+    #[tokio::test]
+    async fn round_trip_default() {
+        round_trip(false).await;
+    }
+
+    #[tokio::test]
+    async fn early_control_close_end() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(run_upstream_server(listener, None));
+
+        let (mut recv, mut send) = associate(server_addr, true).await;
+        let target = send_hello(&mut send).await;
+        assert_eq!(server.await.unwrap(), target);
+
+        let mut buf = [0u8; 512];
+        let r = tokio::time::timeout(TIMEOUT, poll_fn(|cx| recv.poll_recv_packet(cx, &mut buf)))
+            .await
+            .unwrap();
+        assert!(matches!(
+            r,
+            Err(UdpRelayRemoteError::RemoteSessionClosed(_, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn early_control_close_ignored() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(run_upstream_server(listener, None));
+
+        let (mut recv, mut send) = associate(server_addr, false).await;
+        let target = send_hello(&mut send).await;
+        assert_eq!(server.await.unwrap(), target);
+
+        // the control connection is closed before the echo, which should be ignored
+        let mut buf = [0u8; 512];
+        let (off, nr, ups) =
+            tokio::time::timeout(TIMEOUT, poll_fn(|cx| recv.poll_recv_packet(cx, &mut buf)))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(&buf[off..nr], b"hello");
+        assert_eq!(ups, target);
+
+        // and the relay should still be alive
+        let r = tokio::time::timeout(
+            Duration::from_millis(100),
+            poll_fn(|cx| recv.poll_recv_packet(cx, &mut buf)),
+        )
+        .await;
+        assert!(r.is_err());
+    }
+
+    #[tokio::test]
+    async fn mapping_overflow_drop() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let _server = tokio::spawn(run_upstream_server(listener, None));
+
+        let stats = Arc::new(UdpRelayMappingStats::default());
+        let config = UdpRelayMappingConfig {
+            max_entries: 1,
+            max_total_entries: 1,
+            ..Default::default()
+        };
+        // take the only entry by another association
+        let mut other = UdpRelayMappingTracker::new(config, stats.clone());
+        let other_target = UpstreamAddr::from_host_str_and_port("other.example.net", 7).unwrap();
+        assert!(other.check_send(&other_target));
+
+        let (mut recv, mut send) = associate(server_addr, false).await;
+        let tracker = Arc::new(Mutex::new(UdpRelayMappingTracker::new(
+            config,
+            stats.clone(),
+        )));
+        recv.enable_mapping(tracker.clone());
+        send.enable_mapping(tracker);
+
+        // the packet should be dropped without the socks5 header sent
+        let target = UpstreamAddr::from_host_str_and_port("echo.example.net", 7).unwrap();
+        let nw = poll_fn(|cx| send.poll_send_packet(cx, b"hello", &target))
+            .await
+            .unwrap();
+        assert_eq!(nw, 5);
+        let snap = stats.snapshot();
+        assert_eq!(snap.alive, 1);
+        assert_eq!(snap.dropped_overflow, 1);
+
+        // and the relay should still be alive
+        let mut buf = [0u8; 512];
+        let r = tokio::time::timeout(
+            Duration::from_millis(100),
+            poll_fn(|cx| recv.poll_recv_packet(cx, &mut buf)),
+        )
+        .await;
+        assert!(r.is_err());
+
+        drop(other);
+        let nw = poll_fn(|cx| send.poll_send_packet(cx, b"hello", &target))
+            .await
+            .unwrap();
+        assert!(nw > 5);
+        let (off, nr, ups) =
+            tokio::time::timeout(TIMEOUT, poll_fn(|cx| recv.poll_recv_packet(cx, &mut buf)))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(&buf[off..nr], b"hello");
+        assert_eq!(ups, target);
+        assert_eq!(stats.snapshot().alive, 1);
+    }
+
+    #[tokio::test]
+    async fn local_close() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let relay = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let (r, mut w) = stream.split();
+            let mut r = BufReader::new(r);
+            let _version = r.read_u8().await.unwrap();
+            g3_socks::v5::auth::recv_methods_from_client(&mut r)
+                .await
+                .unwrap();
+            g3_socks::v5::auth::send_method_to_client(&mut w, &SocksAuthMethod::None)
+                .await
+                .unwrap();
+            Socks5Request::recv(&mut r).await.unwrap();
+            Socks5Reply::Succeeded(relay.local_addr().unwrap())
+                .send(&mut w)
+                .await
+                .unwrap();
+            // wait for the close of the control connection
+            let mut buf = [0u8; 16];
+            r.read(&mut buf).await.unwrap()
+        });
+
+        let (recv, send) = associate(server_addr, false).await;
+        drop(send);
+        drop(recv);
+        let nr = tokio::time::timeout(TIMEOUT, server)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(nr, 0);
+    }
+}
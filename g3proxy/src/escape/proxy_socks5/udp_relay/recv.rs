@@ -6,6 +6,7 @@
 use std::io;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll, ready};
 
 use tokio::io::{AsyncRead, ReadBuf};
@@ -24,13 +25,19 @@ use g3_io_ext::{UdpRelayPacket, UdpRelayPacketMeta};
 use g3_socks::v5::UdpInput;
 use g3_types::net::UpstreamAddr;
 
+use crate::module::udp_relay::UdpRelayMappingTracker;
+
 pub(crate) struct ProxySocks5UdpRelayRemoteRecv<T, C> {
     local_addr: SocketAddr,
     peer_addr: SocketAddr,
     inner: T,
     ctl_stream: C,
+    /// end the relay if the control connection is closed by the peer,
+    /// it will always be set after the first packet is received
     end_on_control_closed: bool,
+    /// the control connection has been closed before any packet is received
     ignore_ctl_stream: bool,
+    mapping: Option<Arc<Mutex<UdpRelayMappingTracker>>>,
 }
 
 impl<T, C> ProxySocks5UdpRelayRemoteRecv<T, C>
@@ -52,9 +59,14 @@ where
             ctl_stream,
             end_on_control_closed,
             ignore_ctl_stream: false,
+            mapping: None,
         }
     }
 
+    pub(super) fn enable_mapping(&mut self, tracker: Arc<Mutex<UdpRelayMappingTracker>>) {
+        self.mapping = Some(tracker);
+    }
+
     fn check_tcp_close(&mut self, cx: &mut Context<'_>) -> Result<(), UdpRelayRemoteError> {
         const MAX_MSG_SIZE: usize = 4;
         let mut buf = [0u8; MAX_MSG_SIZE];
@@ -104,7 +116,7 @@ where
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<Result<(usize, usize, UpstreamAddr), UdpRelayRemoteError>> {
-        if self.ignore_ctl_stream {
+        if !self.ignore_ctl_stream {
             self.check_tcp_close(cx)?;
         }
 
@@ -114,6 +126,9 @@ where
         let (off, upstream) = UdpInput::parse_header(buf)
             .map_err(|e| UdpRelayRemoteError::InvalidPacket(self.local_addr, e.to_string()))?;
 
+        if let Some(mapping) = &self.mapping {
+            mapping.lock().unwrap().mark_received(&upstream);
+        }
         self.end_on_control_closed = true;
         Poll::Ready(Ok((off, nr, upstream)))
     }
@@ -134,7 +149,7 @@ where
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
         use g3_io_sys::udp::RecvMsgHdr;
 
-        if self.ignore_ctl_stream {
+        if !self.ignore_ctl_stream {
             self.check_tcp_close(cx)?;
         }
 
@@ -151,11 +166,17 @@ where
             let iov = &h.iov[0];
             let (off, ups) = UdpInput::parse_header(&iov[0..h.n_recv])
                 .map_err(|e| UdpRelayRemoteError::InvalidPacket(self.local_addr, e.to_string()))?;
-            r.push(UdpRelayPacketMeta::new(iov, off, h.n_recv, ups))
+            r.push(UdpRelayPacketMeta::new(iov, off, h.n_recv, ups).with_truncated(h.truncated()))
         }
         for (m, p) in r.into_iter().zip(packets.iter_mut()) {
             m.set_packet(p);
         }
+        if let Some(mapping) = &self.mapping {
+            let mut mapping = mapping.lock().unwrap();
+            for p in &packets[..count] {
+                mapping.mark_received(p.upstream());
+            }
+        }
 
         self.end_on_control_closed = true;
         Poll::Ready(Ok(count))
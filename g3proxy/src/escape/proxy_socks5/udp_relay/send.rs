@@ -5,6 +5,7 @@
 
 use std::io::{self, IoSlice};
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll, ready};
 
 #[cfg(any(
@@ -22,11 +23,14 @@ use g3_io_sys::udp::SendMsgHdr;
 use g3_socks::v5::SocksUdpHeader;
 use g3_types::net::UpstreamAddr;
 
+use crate::module::udp_relay::UdpRelayMappingTracker;
+
 pub(crate) struct ProxySocks5UdpRelayRemoteSend<T> {
     local_addr: SocketAddr,
     peer_addr: SocketAddr,
     inner: T,
     socks_headers: Vec<SocksUdpHeader>,
+    mapping: Option<Arc<Mutex<UdpRelayMappingTracker>>>,
 }
 
 impl<T> ProxySocks5UdpRelayRemoteSend<T>
@@ -39,8 +43,13 @@ where
             peer_addr,
             inner: send,
             socks_headers: vec![SocksUdpHeader::default(); 4],
+            mapping: None,
         }
     }
+
+    pub(super) fn enable_mapping(&mut self, tracker: Arc<Mutex<UdpRelayMappingTracker>>) {
+        self.mapping = Some(tracker);
+    }
 }
 
 impl<T> UdpRelayRemoteSend for ProxySocks5UdpRelayRemoteSend<T>
@@ -53,6 +62,13 @@ where
         buf: &[u8],
         to: &UpstreamAddr,
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+        if let Some(mapping) = &self.mapping {
+            if !mapping.lock().unwrap().check_send(to) {
+                // drop this packet only, as the max mapping entries limit is reached
+                return Poll::Ready(Ok(buf.len()));
+            }
+        }
+
         let socks_header = self.socks_headers.get_mut(0).unwrap();
         let hdr = SendMsgHdr::new(
             [IoSlice::new(socks_header.encode(to)), IoSlice::new(buf)],
@@ -84,6 +100,13 @@ where
         cx: &mut Context<'_>,
         packets: &[UdpRelayPacket],
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+        if self.mapping.is_some() {
+            let Some(p) = packets.first() else {
+                return Poll::Ready(Ok(0));
+            };
+            let _ = ready!(self.poll_send_packet(cx, p.payload(), p.upstream()))?;
+            return Poll::Ready(Ok(1));
+        }
         if packets.len() > self.socks_headers.len() {
             self.socks_headers.resize(packets.len(), Default::default());
         }
@@ -117,6 +140,13 @@ where
         cx: &mut Context<'_>,
         packets: &[UdpRelayPacket],
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+        if self.mapping.is_some() {
+            let Some(p) = packets.first() else {
+                return Poll::Ready(Ok(0));
+            };
+            let _ = ready!(self.poll_send_packet(cx, p.payload(), p.upstream()))?;
+            return Poll::Ready(Ok(1));
+        }
         if packets.len() > self.socks_headers.len() {
             self.socks_headers.resize(packets.len(), Default::default());
         }
@@ -11,6 +11,8 @@ use arc_swap::ArcSwapOption;
 use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::stats::{StatId, TcpIoSnapshot, TcpIoStats, UdpIoSnapshot, UdpIoStats};
 
+use crate::module::udp_relay::{UdpRelayIcmpErrorSnapshot, UdpRelayMappingSnapshot};
+
 pub(crate) trait EscaperInternalStats {
     fn add_http_forward_request_attempted(&self);
     fn add_https_forward_request_attempted(&self);
@@ -48,6 +50,19 @@ pub(crate) trait EscaperStats: EscaperInternalStats {
     fn forbidden_snapshot(&self) -> Option<EscaperForbiddenSnapshot> {
         None
     }
+
+    fn udp_mapping_snapshot(&self) -> Option<UdpRelayMappingSnapshot> {
+        None
+    }
+
+    fn udp_icmp_error_snapshot(&self) -> Option<UdpRelayIcmpErrorSnapshot> {
+        None
+    }
+
+    /// selected count for each of the egress bind rules, in the order of the rules
+    fn egress_bind_snapshot(&self) -> Option<Vec<u64>> {
+        None
+    }
 }
 
 pub(crate) type ArcEscaperInternalStats = Arc<dyn EscaperInternalStats + Send + Sync>;
@@ -21,7 +21,7 @@ use g3_icap_client::reqmod::h1::{
     ReqmodAdaptationMidState, ReqmodAdaptationRunState, ReqmodRecvHttpResponseBody,
 };
 use g3_io_ext::{LimitedWriteExt, StreamCopy, StreamCopyError};
-use g3_slog_types::{LtDateTime, LtDuration, LtUpstreamAddr, LtUuid};
+use g3_slog_types::{LtDateTime, LtDuration, LtIoErrorKind, LtUpstreamAddr, LtUuid};
 use g3_types::net::UpstreamAddr;
 
 use super::{HttpRequest, HttpRequestIo, HttpResponseIo};
@@ -31,7 +31,7 @@ use crate::module::http_forward::HttpProxyClientResponse;
 use crate::serve::{ServerIdleChecker, ServerTaskError, ServerTaskResult};
 
 macro_rules! intercept_log {
-    ($obj:tt, $r:expr, $($args:tt)+) => {
+    ($obj:tt, $r:expr, $io_err:expr, $($args:tt)+) => {
         if let Some(logger) = $obj.ctx.intercept_logger() {
             slog_info!(logger, $($args)+;
                 "intercept_type" => "HttpConnect",
@@ -42,6 +42,7 @@ macro_rules! intercept_log {
                 "received_at" => LtDateTime(&$obj.http_notes.receive_datetime),
                 "rsp_status" => $obj.http_notes.rsp_status,
                 "origin_status" => $obj.http_notes.origin_status,
+                "io_error_kind" => $io_err.map(LtIoErrorKind),
                 "dur_req_send_hdr" => LtDuration($obj.http_notes.dur_req_send_hdr),
                 "dur_req_pipeline" => LtDuration($obj.http_notes.dur_req_pipeline),
                 "dur_rsp_recv_hdr" => LtDuration($obj.http_notes.dur_rsp_recv_hdr),
@@ -144,14 +145,14 @@ where
     {
         match self.do_forward(rsp_io, reqmod_client).await {
             Ok(v) => {
-                intercept_log!(self, &v, "ok");
+                intercept_log!(self, &v, None, "ok");
                 v
             }
             Err(e) => {
                 if self.send_error_response {
                     self.reply_task_err(&e, &mut rsp_io.clt_w).await;
                 }
-                intercept_log!(self, &None, "{e}");
+                intercept_log!(self, &None, e.io_error_kind(), "{e}");
                 None
             }
         }
@@ -168,14 +169,14 @@ where
     {
         match self.send_request(None, rsp_io).await {
             Ok(v) => {
-                intercept_log!(self, &v, "ok");
+                intercept_log!(self, &v, None, "ok");
                 v
             }
             Err(e) => {
                 if self.send_error_response {
                     self.reply_task_err(&e, &mut rsp_io.clt_w).await;
                 }
-                intercept_log!(self, &None, "{e}");
+                intercept_log!(self, &None, e.io_error_kind(), "{e}");
                 None
             }
         }
@@ -202,6 +203,7 @@ where
         {
             Ok(mut adapter) => {
                 adapter.set_client_addr(self.ctx.task_notes.client_addr);
+                adapter.set_task_id(self.ctx.task_notes.task_id());
                 if let Some(username) = self.ctx.raw_user_name() {
                     adapter.set_client_username(username.clone());
                 }
@@ -25,8 +25,13 @@ use g3_icap_client::reqmod::h1::{
 use g3_icap_client::respmod::h1::{
     HttpResponseAdapter, RespmodAdaptationEndState, RespmodAdaptationRunState,
 };
-use g3_io_ext::{LimitedBufReadExt, LimitedWriteExt, StreamCopy, StreamCopyError};
-use g3_slog_types::{LtDateTime, LtDuration, LtHttpHeaderValue, LtHttpMethod, LtHttpUri, LtUuid};
+use g3_io_ext::{
+    LimitedBufReadExt, LimitedWriteExt, SHA256_DIGEST_LENGTH, Sha256BufReader, StreamCopy,
+    StreamCopyError,
+};
+use g3_slog_types::{
+    LtDateTime, LtDuration, LtHttpHeaderValue, LtHttpMethod, LtHttpUri, LtIoErrorKind, LtUuid,
+};
 use g3_types::net::HttpHeaderMap;
 
 use super::{HttpRequest, HttpRequestIo, HttpResponseIo};
@@ -39,7 +44,7 @@ mod adaptation;
 pub(crate) use adaptation::HttpRequestWriterForAdaptation;
 
 macro_rules! intercept_log {
-    ($obj:tt, $($args:tt)+) => {
+    ($obj:tt, $io_err:expr, $($args:tt)+) => {
         if let Some(logger) = $obj.ctx.intercept_logger() {
             slog_info!(logger, $($args)+;
                 "intercept_type" => "HttpForward",
@@ -52,16 +57,32 @@ macro_rules! intercept_log {
                 "host" => $obj.req.end_to_end_headers.get(header::HOST).map(|v| LtHttpHeaderValue(v.inner())),
                 "rsp_status" => $obj.http_notes.rsp_status,
                 "origin_status" => $obj.http_notes.origin_status,
+                "io_error_kind" => $io_err.map(LtIoErrorKind),
                 "dur_req_send_hdr" => LtDuration($obj.http_notes.dur_req_send_hdr),
                 "dur_req_pipeline" => LtDuration($obj.http_notes.dur_req_pipeline),
                 "dur_req_send_all" => LtDuration($obj.http_notes.dur_req_send_all),
                 "dur_rsp_recv_hdr" => LtDuration($obj.http_notes.dur_rsp_recv_hdr),
                 "dur_rsp_recv_all" => LtDuration($obj.http_notes.dur_rsp_recv_all),
+                "rsp_body_sha256" => $obj.http_notes.rsp_body_digest.as_ref().and_then(|d| d.hex_digest()),
+                "rsp_body_hashed" => $obj.http_notes.rsp_body_digest.as_ref().map(|d| d.hashed),
+                "rsp_body_hash_partial" => $obj.http_notes.rsp_body_digest.as_ref().map(|d| d.partial),
             );
         }
     };
 }
 
+struct ResponseBodyDigest {
+    digest: Option<[u8; SHA256_DIGEST_LENGTH]>,
+    hashed: u64,
+    partial: bool,
+}
+
+impl ResponseBodyDigest {
+    fn hex_digest(&self) -> Option<String> {
+        self.digest.map(hex::encode)
+    }
+}
+
 struct HttpForwardTaskNotes {
     rsp_status: u16,
     origin_status: u16,
@@ -72,6 +93,8 @@ struct HttpForwardTaskNotes {
     dur_req_send_all: Duration,
     dur_rsp_recv_hdr: Duration,
     dur_rsp_recv_all: Duration,
+    rsp_recv_all: bool,
+    rsp_body_digest: Option<ResponseBodyDigest>,
 }
 
 impl HttpForwardTaskNotes {
@@ -87,6 +110,8 @@ impl HttpForwardTaskNotes {
             dur_req_send_all: Duration::default(),
             dur_rsp_recv_hdr: Duration::default(),
             dur_rsp_recv_all: Duration::default(),
+            rsp_recv_all: false,
+            rsp_body_digest: None,
         }
     }
 
@@ -108,10 +133,12 @@ impl HttpForwardTaskNotes {
 
     pub(crate) fn mark_rsp_no_body(&mut self) {
         self.dur_rsp_recv_all = self.dur_rsp_recv_hdr;
+        self.rsp_recv_all = true;
     }
 
     pub(crate) fn mark_rsp_recv_all(&mut self) {
         self.dur_rsp_recv_all = self.receive_ins.elapsed();
+        self.rsp_recv_all = true;
     }
 }
 
@@ -181,9 +208,9 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
             if self.send_error_response {
                 self.reply_task_err(&e, &mut rsp_io.clt_w).await;
             }
-            intercept_log!(self, "{e}");
+            intercept_log!(self, e.io_error_kind(), "{e}");
         } else {
-            intercept_log!(self, "ok");
+            intercept_log!(self, None, "ok");
         }
     }
 
@@ -209,6 +236,7 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
         {
             Ok(mut adapter) => {
                 adapter.set_client_addr(self.ctx.task_notes.client_addr);
+                adapter.set_task_id(self.ctx.task_notes.task_id());
                 if let Some(username) = self.ctx.raw_user_name() {
                     adapter.set_client_username(username.clone());
                 }
@@ -220,7 +248,7 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
                 } else {
                     let e = ServerTaskError::InternalAdapterError(e);
                     self.reply_task_err(&e, &mut rsp_io.clt_w).await;
-                    intercept_log!(self, "{e:?}");
+                    intercept_log!(self, e.io_error_kind(), "{e:?}");
                 }
                 return;
             }
@@ -243,13 +271,13 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
 
         match r {
             Ok(_) => {
-                intercept_log!(self, "ok");
+                intercept_log!(self, None, "ok");
             }
             Err(e) => {
                 if self.send_error_response {
                     self.reply_task_err(&e, &mut rsp_io.clt_w).await;
                 }
-                intercept_log!(self, "{e}");
+                intercept_log!(self, e.io_error_kind(), "{e}");
             }
         }
     }
@@ -271,13 +299,13 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
         };
         match r {
             Ok(_) => {
-                intercept_log!(self, "ok");
+                intercept_log!(self, None, "ok");
             }
             Err(e) => {
                 if self.send_error_response {
                     self.reply_task_err(&e, &mut rsp_io.clt_w).await;
                 }
-                intercept_log!(self, "{e}");
+                intercept_log!(self, e.io_error_kind(), "{e}");
             }
         }
     }
@@ -669,6 +697,60 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
         self.http_notes.rsp_status = 0;
         self.http_notes.mark_rsp_recv_hdr();
 
+        let body_digest = rsp.body_type(&self.req.method).is_some()
+            && self
+                .ctx
+                .audit_handle
+                .response_body_digest()
+                .is_some_and(|config| {
+                    let content_type = rsp
+                        .end_to_end_headers
+                        .get(header::CONTENT_TYPE)
+                        .map(|v| v.to_str());
+                    !config.skip_content_type(content_type)
+                });
+        if body_digest {
+            let mut ups_r = Sha256BufReader::new(&mut rsp_io.ups_r);
+            let r = self
+                .do_send_response(
+                    rsp,
+                    rsp_head,
+                    &mut ups_r,
+                    &mut rsp_io.clt_w,
+                    adaptation_respond_shared_headers,
+                )
+                .await;
+            let (digest, hashed) = ups_r.finish();
+            self.http_notes.rsp_body_digest = Some(ResponseBodyDigest {
+                partial: digest.is_none() || !self.http_notes.rsp_recv_all,
+                digest,
+                hashed,
+            });
+            r
+        } else {
+            self.do_send_response(
+                rsp,
+                rsp_head,
+                &mut rsp_io.ups_r,
+                &mut rsp_io.clt_w,
+                adaptation_respond_shared_headers,
+            )
+            .await
+        }
+    }
+
+    async fn do_send_response<UR, CW>(
+        &mut self,
+        rsp: HttpTransparentResponse,
+        rsp_head: Bytes,
+        ups_r: &mut UR,
+        clt_w: &mut CW,
+        adaptation_respond_shared_headers: Option<HttpHeaderMap>,
+    ) -> ServerTaskResult<()>
+    where
+        UR: AsyncBufRead + Unpin,
+        CW: AsyncWrite + Send + Unpin,
+    {
         if let Some(respmod) = self.ctx.audit_handle.icap_respmod_client() {
             match respmod
                 .h1_adapter(
@@ -684,20 +766,32 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
                         self.http_notes.dur_rsp_recv_hdr,
                     );
                     adapter.set_client_addr(self.ctx.task_notes.client_addr);
+                    adapter.set_task_id(self.ctx.task_notes.task_id());
                     if let Some(username) = self.ctx.raw_user_name() {
                         adapter.set_client_username(username.clone());
                     }
                     adapter.set_respond_shared_headers(adaptation_respond_shared_headers);
                     let r = self
-                        .send_response_with_adaptation(rsp, rsp_io, adapter, &mut adaptation_state)
+                        .send_response_with_adaptation(
+                            rsp,
+                            ups_r,
+                            clt_w,
+                            adapter,
+                            &mut adaptation_state,
+                        )
                         .await;
-                    if !adaptation_state.clt_write_finished || !adaptation_state.ups_read_finished {
+                    if !adaptation_state.clt_write_finished()
+                        || !adaptation_state.ups_read_finished()
+                    {
                         self.should_close = true;
                     }
-                    if let Some(dur) = adaptation_state.dur_ups_recv_all {
+                    if adaptation_state.ups_read_finished() {
+                        self.http_notes.rsp_recv_all = true;
+                    }
+                    if let Some(dur) = adaptation_state.dur_ups_recv_all() {
                         self.http_notes.dur_rsp_recv_all = dur;
                     }
-                    self.send_error_response = !adaptation_state.clt_write_started;
+                    self.send_error_response = !adaptation_state.clt_write_started();
                     return r;
                 }
                 Err(e) => {
@@ -708,30 +802,24 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
             }
         }
 
-        self.send_response_without_adaptation(rsp, rsp_head, rsp_io)
+        self.send_response_without_adaptation(rsp, rsp_head, ups_r, clt_w)
             .await
     }
 
-    async fn send_response_with_adaptation<CW, UR, UW>(
+    async fn send_response_with_adaptation<UR, CW>(
         &mut self,
         rsp: HttpTransparentResponse,
-        rsp_io: &mut HttpResponseIo<CW, UR, UW>,
+        ups_r: &mut UR,
+        clt_w: &mut CW,
         icap_adapter: HttpResponseAdapter<ServerIdleChecker>,
         adaptation_state: &mut RespmodAdaptationRunState,
     ) -> ServerTaskResult<()>
     where
-        UR: AsyncRead + Unpin,
+        UR: AsyncBufRead + Unpin,
         CW: AsyncWrite + Send + Unpin,
-        UW: AsyncWrite + Unpin,
     {
         match icap_adapter
-            .xfer(
-                adaptation_state,
-                self.req,
-                &rsp,
-                &mut rsp_io.ups_r,
-                &mut rsp_io.clt_w,
-            )
+            .xfer(adaptation_state, self.req, &rsp, ups_r, clt_w)
             .await
         {
             Ok(RespmodAdaptationEndState::OriginalTransferred) => {
@@ -746,31 +834,25 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
         }
     }
 
-    async fn send_response_without_adaptation<CW, UR, UW>(
+    async fn send_response_without_adaptation<UR, CW>(
         &mut self,
         rsp: HttpTransparentResponse,
         rsp_head: Bytes,
-        rsp_io: &mut HttpResponseIo<CW, UR, UW>,
+        ups_r: &mut UR,
+        clt_w: &mut CW,
     ) -> ServerTaskResult<()>
     where
-        UR: AsyncRead + Unpin,
+        UR: AsyncBufRead + Unpin,
         CW: AsyncWrite + Unpin,
-        UW: AsyncWrite + Unpin,
     {
         self.send_error_response = false;
 
         if let Some(body_type) = rsp.body_type(&self.req.method) {
             self.http_notes.rsp_status = self.http_notes.origin_status; // the following function must send rsp header out
-            self.send_response_body(
-                rsp_head.into(),
-                &mut rsp_io.ups_r,
-                &mut rsp_io.clt_w,
-                body_type,
-            )
-            .await
+            self.send_response_body(rsp_head.into(), ups_r, clt_w, body_type)
+                .await
         } else {
-            self.send_response_header(&mut rsp_io.clt_w, rsp_head)
-                .await?;
+            self.send_response_header(clt_w, rsp_head).await?;
             self.http_notes.rsp_status = self.http_notes.origin_status;
             self.http_notes.mark_rsp_no_body();
             Ok(())
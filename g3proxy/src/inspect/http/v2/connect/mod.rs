@@ -179,6 +179,7 @@ impl<'a, SC: ServerConfig> ExchangeHead<'a, SC> {
                     let mut adaptation_state =
                         ReqmodAdaptationRunState::new(self.http_notes.started_ins);
                     adapter.set_client_addr(self.ctx.task_notes.client_addr);
+                    adapter.set_task_id(self.ctx.task_notes.task_id());
                     if let Some(username) = self.ctx.raw_user_name() {
                         adapter.set_client_username(username.clone());
                     }
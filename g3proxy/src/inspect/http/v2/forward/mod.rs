@@ -242,6 +242,7 @@ where
                     let mut adaptation_state =
                         ReqmodAdaptationRunState::new(self.http_notes.started_ins);
                     adapter.set_client_addr(self.ctx.task_notes.client_addr);
+                    adapter.set_task_id(self.ctx.task_notes.task_id());
                     if let Some(username) = self.ctx.raw_user_name() {
                         adapter.set_client_username(username.clone());
                     }
@@ -545,6 +546,7 @@ where
                         self.http_notes.dur_rsp_recv_hdr,
                     );
                     adapter.set_client_addr(self.ctx.task_notes.client_addr);
+                    adapter.set_task_id(self.ctx.task_notes.task_id());
                     if let Some(username) = self.ctx.raw_user_name() {
                         adapter.set_client_username(username);
                     }
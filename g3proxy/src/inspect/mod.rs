@@ -17,13 +17,16 @@ use g3_dpi::{
     ProtocolInspectAction, ProtocolInspector, SmtpInterceptionConfig,
 };
 use g3_io_ext::IdleWheel;
+use g3_types::metrics::NodeName;
 use g3_types::net::{Host, OpensslClientConfig};
 
 use crate::audit::AuditHandle;
 use crate::auth::{User, UserForbiddenStats, UserSite};
 use crate::config::server::ServerConfig;
 use crate::module::tcp_connect::TcpConnectTaskNotes;
-use crate::serve::{ArcServerStats, ServerIdleChecker, ServerTaskNotes};
+use crate::serve::{
+    ArcServerStats, ServerIdleChecker, ServerIdleOverride, ServerTaskNotes, ServerTaskVars,
+};
 
 mod error;
 pub(crate) use error::InterceptionError;
@@ -45,7 +48,6 @@ pub(crate) mod smtp;
 
 #[derive(Clone)]
 pub(super) struct StreamInspectUserContext {
-    raw_user_name: Option<Arc<str>>,
     user: Arc<User>,
     user_site: Option<Arc<UserSite>>,
     forbidden_stats: Arc<UserForbiddenStats>,
@@ -67,6 +69,7 @@ pub(crate) struct StreamInspectTaskNotes {
     pub(crate) server_addr: SocketAddr,
     worker_id: Option<usize>,
     user_ctx: Option<StreamInspectUserContext>,
+    vars: ServerTaskVars,
 }
 
 impl StreamInspectTaskNotes {
@@ -74,16 +77,21 @@ impl StreamInspectTaskNotes {
         self.user_ctx.as_ref().map(|ctx| &ctx.user)
     }
 
+    #[inline]
     pub(crate) fn raw_username(&self) -> Option<&Arc<str>> {
-        self.user_ctx
-            .as_ref()
-            .and_then(|ctx| ctx.raw_user_name.as_ref())
+        self.vars.user()
     }
 
     #[inline]
     pub(crate) fn task_id(&self) -> &Uuid {
         &self.task_id
     }
+
+    /// Get the escaper that handled the connection, as the inspection starts after connected
+    #[inline]
+    pub(crate) fn escaper(&self) -> Option<&NodeName> {
+        self.vars.escaper()
+    }
 }
 
 impl From<&ServerTaskNotes> for StreamInspectTaskNotes {
@@ -94,11 +102,11 @@ impl From<&ServerTaskNotes> for StreamInspectTaskNotes {
             server_addr: task_notes.server_addr(),
             worker_id: task_notes.worker_id(),
             user_ctx: task_notes.user_ctx().map(|ctx| StreamInspectUserContext {
-                raw_user_name: ctx.raw_user_name().cloned(),
                 user: ctx.user().clone(),
                 user_site: ctx.user_site().cloned(),
                 forbidden_stats: ctx.forbidden_stats().clone(),
             }),
+            vars: task_notes.vars().clone(),
         }
     }
 }
@@ -175,6 +183,14 @@ impl<SC: ServerConfig> StreamInspectContext<SC> {
         }
     }
 
+    /// Use the idle check values of the task idle override, the user one still takes precedence
+    pub(crate) fn set_idle_override(&mut self, idle_override: &ServerIdleOverride) {
+        self.idle_wheel = idle_override.idle_wheel.clone();
+        if self.user().and_then(|u| u.task_max_idle_count()).is_none() {
+            self.max_idle_count = idle_override.max_idle_count;
+        }
+    }
+
     #[inline]
     fn user(&self) -> Option<&User> {
         self.task_notes.user().map(|u| u.as_ref())
@@ -194,6 +210,11 @@ impl<SC: ServerConfig> StreamInspectContext<SC> {
         self.task_notes.task_id()
     }
 
+    #[inline]
+    pub(crate) fn server_task_escaper(&self) -> Option<&NodeName> {
+        self.task_notes.escaper()
+    }
+
     #[inline]
     fn server_force_quit(&self) -> bool {
         self.server_quit_policy.force_quit()
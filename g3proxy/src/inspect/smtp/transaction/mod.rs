@@ -335,6 +335,7 @@ impl<'a, SC: ServerConfig> Transaction<'a, SC> {
         UW: AsyncWrite + Unpin,
     {
         adapter.set_client_addr(self.ctx.task_notes.client_addr);
+        adapter.set_task_id(self.ctx.task_notes.task_id());
         if let Some(username) = self.ctx.raw_user_name() {
             adapter.set_client_username(username.clone());
         }
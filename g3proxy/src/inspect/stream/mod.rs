@@ -9,9 +9,12 @@ use slog::slog_info;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio::time::Instant;
 
-use g3_daemon::server::ServerQuitPolicy;
+use g3_daemon::server::{ServerQuitPolicy, TransferStats};
 use g3_dpi::{MaybeProtocol, ProtocolInspectionConfig, ProtocolInspector};
-use g3_io_ext::{IdleInterval, OptionalInterval, StreamCopy, StreamCopyConfig, StreamCopyError};
+use g3_io_ext::{
+    IdleDirection, IdleInterval, IdleTracker, OptionalInterval, StreamCopy, StreamCopyConfig,
+    StreamCopyError,
+};
 use g3_slog_types::LtUuid;
 use g3_types::net::UpstreamAddr;
 
@@ -19,11 +22,26 @@ use super::{StreamInspectContext, StreamInspection};
 use crate::auth::User;
 use crate::config::server::ServerConfig;
 use crate::log::task::TaskEvent;
-use crate::serve::{ServerTaskError, ServerTaskForbiddenError, ServerTaskResult};
+use crate::serve::{RunningTask, ServerTaskError, ServerTaskForbiddenError, ServerTaskResult};
 
 mod object;
 pub(crate) use object::StreamInspectObject;
 
+/// Get the idle side of a copy, `None` will be returned if the copy is active
+fn idle_direction<R, W>(copy: &StreamCopy<'_, R, W>, read: IdleDirection) -> Option<IdleDirection>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    if copy.is_active() {
+        None
+    } else if copy.no_cached_data() {
+        Some(read)
+    } else {
+        Some(IdleDirection::WriteBlocked)
+    }
+}
+
 pub(crate) trait StreamTransitTask {
     fn copy_config(&self) -> StreamCopyConfig;
     fn idle_check_interval(&self) -> IdleInterval;
@@ -34,6 +52,14 @@ pub(crate) trait StreamTransitTask {
     fn log_flush_interval(&self) -> Option<Duration>;
     fn quit_policy(&self) -> &ServerQuitPolicy;
     fn user(&self) -> Option<&User>;
+    fn transfer_stats(&self) -> Option<&TransferStats> {
+        None
+    }
+    fn running_task(&self) -> Option<&RunningTask> {
+        None
+    }
+    /// Called if the transit failed, before the client stream is closed
+    fn on_transit_error(&self, _e: &ServerTaskError) {}
 
     async fn transit_transparent<CR, CW, UR, UW>(
         &self,
@@ -49,10 +75,18 @@ pub(crate) trait StreamTransitTask {
         UW: AsyncWrite + Unpin,
     {
         let copy_config = self.copy_config();
-        let clt_to_ups = StreamCopy::new(&mut clt_r, &mut ups_w, &copy_config);
-        let ups_to_clt = StreamCopy::new(&mut ups_r, &mut clt_w, &copy_config);
+        let mut clt_to_ups = StreamCopy::new(&mut clt_r, &mut ups_w, &copy_config);
+        let mut ups_to_clt = StreamCopy::new(&mut ups_r, &mut clt_w, &copy_config);
+        if let Some(stats) = self.transfer_stats() {
+            clt_to_ups.set_recorder(stats.upload_recorder());
+            ups_to_clt.set_recorder(stats.download_recorder());
+        }
 
-        self.transit_transparent2(clt_to_ups, ups_to_clt).await
+        let r = self.transit_transparent2(clt_to_ups, ups_to_clt).await;
+        if let Err(e) = &r {
+            self.on_transit_error(e);
+        }
+        r
     }
 
     async fn transit_transparent2<CR, CW, UR, UW>(
@@ -75,7 +109,7 @@ pub(crate) trait StreamTransitTask {
                 OptionalInterval::with(interval)
             })
             .unwrap_or_default();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
         let max_idle_count = self
             .user()
             .and_then(|u| u.task_max_idle_count())
@@ -87,7 +121,7 @@ pub(crate) trait StreamTransitTask {
                         Ok(_) => {
                             let _ = clt_to_ups.writer().shutdown().await;
                             self.log_client_shutdown();
-                            self.transit_south(ups_to_clt, log_interval, idle_interval, idle_count, max_idle_count).await
+                            self.transit_south(ups_to_clt, log_interval, idle_interval, idle_tracker, max_idle_count).await
                         }
                         Err(StreamCopyError::ReadFailed(e)) => Err(ServerTaskError::ClientTcpReadFailed(e)),
                         Err(StreamCopyError::WriteFailed(e)) => {
@@ -101,7 +135,7 @@ pub(crate) trait StreamTransitTask {
                         Ok(_) => {
                             let _ = ups_to_clt.writer().shutdown().await;
                             self.log_upstream_shutdown();
-                            self.transit_north(clt_to_ups, log_interval, idle_interval, idle_count, max_idle_count).await
+                            self.transit_north(clt_to_ups, log_interval, idle_interval, idle_tracker, max_idle_count).await
                         }
                         Err(StreamCopyError::ReadFailed(e)) => Err(ServerTaskError::UpstreamReadFailed(e)),
                         Err(StreamCopyError::WriteFailed(e)) => {
@@ -114,23 +148,24 @@ pub(crate) trait StreamTransitTask {
                     self.log_periodic();
                 }
                 n = idle_interval.tick() => {
-                    if clt_to_ups.is_idle() && ups_to_clt.is_idle() {
-                        idle_count += n;
-
+                    let clt_idle = idle_direction(&clt_to_ups, IdleDirection::ClientRead);
+                    let ups_idle = idle_direction(&ups_to_clt, IdleDirection::UpstreamRead);
+                    if idle_tracker.record(n, &[clt_idle, ups_idle]) {
                         if let Some(user) = self.user() {
                             if user.is_blocked() {
                                 return Err(ServerTaskError::CanceledAsUserBlocked);
                             }
                         }
 
-                        if idle_count >= max_idle_count {
-                            return Err(ServerTaskError::Idle(idle_interval.period(), idle_count));
+                        if idle_tracker.idle_count() >= max_idle_count {
+                            return Err(ServerTaskError::idle(idle_interval.period(), &idle_tracker));
                         }
                     } else {
-                        idle_count = 0;
-
                         clt_to_ups.reset_active();
                         ups_to_clt.reset_active();
+                        if let Some(task) = self.running_task() {
+                            task.mark_active();
+                        }
                     }
 
                     if let Some(user) = self.user() {
@@ -142,6 +177,12 @@ pub(crate) trait StreamTransitTask {
                     if self.quit_policy().force_quit() {
                         return Err(ServerTaskError::CanceledAsServerQuit)
                     }
+
+                    if let Some(task) = self.running_task() {
+                        if task.is_killed() {
+                            return Err(ServerTaskError::KilledByOperator);
+                        }
+                    }
                 }
             }
         }
@@ -152,7 +193,7 @@ pub(crate) trait StreamTransitTask {
         mut clt_to_ups: StreamCopy<'_, CR, UW>,
         mut log_interval: OptionalInterval,
         mut idle_interval: IdleInterval,
-        mut idle_count: usize,
+        mut idle_tracker: IdleTracker,
         max_idle_count: usize,
     ) -> ServerTaskResult<()>
     where
@@ -175,22 +216,22 @@ pub(crate) trait StreamTransitTask {
                     self.log_periodic();
                 }
                 n = idle_interval.tick() => {
-                    if clt_to_ups.is_idle() {
-                        idle_count += n;
-
+                    let idle = idle_direction(&clt_to_ups, IdleDirection::ClientRead);
+                    if idle_tracker.record(n, &[idle]) {
                         if let Some(user) = self.user() {
                             if user.is_blocked() {
                                 return Err(ServerTaskError::CanceledAsUserBlocked);
                             }
                         }
 
-                        if idle_count >= max_idle_count {
-                            return Err(ServerTaskError::Idle(idle_interval.period(), idle_count));
+                        if idle_tracker.idle_count() >= max_idle_count {
+                            return Err(ServerTaskError::idle(idle_interval.period(), &idle_tracker));
                         }
                     } else {
-                        idle_count = 0;
-
                         clt_to_ups.reset_active();
+                        if let Some(task) = self.running_task() {
+                            task.mark_active();
+                        }
                     }
 
                     if let Some(user) = self.user() {
@@ -202,6 +243,12 @@ pub(crate) trait StreamTransitTask {
                     if self.quit_policy().force_quit() {
                         return Err(ServerTaskError::CanceledAsServerQuit)
                     }
+
+                    if let Some(task) = self.running_task() {
+                        if task.is_killed() {
+                            return Err(ServerTaskError::KilledByOperator);
+                        }
+                    }
                 }
             }
         }
@@ -212,7 +259,7 @@ pub(crate) trait StreamTransitTask {
         mut ups_to_clt: StreamCopy<'_, UR, CW>,
         mut log_interval: OptionalInterval,
         mut idle_interval: IdleInterval,
-        mut idle_count: usize,
+        mut idle_tracker: IdleTracker,
         max_idle_count: usize,
     ) -> ServerTaskResult<()>
     where
@@ -235,22 +282,22 @@ pub(crate) trait StreamTransitTask {
                     self.log_periodic();
                 }
                 n = idle_interval.tick() => {
-                    if ups_to_clt.is_idle() {
-                        idle_count += n;
-
+                    let idle = idle_direction(&ups_to_clt, IdleDirection::UpstreamRead);
+                    if idle_tracker.record(n, &[idle]) {
                         if let Some(user) = self.user() {
                             if user.is_blocked() {
                                 return Err(ServerTaskError::CanceledAsUserBlocked);
                             }
                         }
 
-                        if idle_count >= max_idle_count {
-                            return Err(ServerTaskError::Idle(idle_interval.period(), idle_count));
+                        if idle_tracker.idle_count() >= max_idle_count {
+                            return Err(ServerTaskError::idle(idle_interval.period(), &idle_tracker));
                         }
                     } else {
-                        idle_count = 0;
-
                         ups_to_clt.reset_active();
+                        if let Some(task) = self.running_task() {
+                            task.mark_active();
+                        }
                     }
 
                     if let Some(user) = self.user() {
@@ -262,6 +309,12 @@ pub(crate) trait StreamTransitTask {
                     if self.quit_policy().force_quit() {
                         return Err(ServerTaskError::CanceledAsServerQuit)
                     }
+
+                    if let Some(task) = self.running_task() {
+                        if task.is_killed() {
+                            return Err(ServerTaskError::KilledByOperator);
+                        }
+                    }
                 }
             }
         }
@@ -38,6 +38,9 @@ impl EscapeLogForUdpRelaySendto<'_> {
             UdpRelayRemoteError::RemoteSessionError(bind, to, _) => {
                 (Some(*bind), Some(*to), "RemoteSessionError")
             }
+            UdpRelayRemoteError::PortUnreachable(bind, to) => {
+                (Some(*bind), Some(*to), "PortUnreachable")
+            }
             UdpRelayRemoteError::InternalServerError(_) => (None, None, "InternalServerError"),
         };
         slog_info!(logger, "{}", e;
@@ -25,6 +25,7 @@ impl<'a, SC: ServerConfig> StreamInspectLog<'a, SC> {
         if let Some(logger) = self.ctx.inspect_logger() {
             slog_info!(logger, "";
                 "task_id" => LtUuid(self.ctx.server_task_id()),
+                "escaper" => self.ctx.server_task_escaper().map(|s| s.as_str()),
                 "depth" => self.ctx.current_inspection_depth(),
                 "source" => source.as_str(),
                 "protocol" => protocol.as_str(),
@@ -28,6 +28,9 @@ pub(crate) struct TaskLogForFtpOverHttp<'a> {
 
 impl TaskLogForFtpOverHttp<'_> {
     pub(crate) fn log_created(&self) {
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -52,6 +55,9 @@ impl TaskLogForFtpOverHttp<'_> {
     }
 
     pub(crate) fn log_connected(&self) {
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -87,6 +93,9 @@ impl TaskLogForFtpOverHttp<'_> {
     }
 
     pub(crate) fn log_periodic(&self) {
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -131,6 +140,9 @@ impl TaskLogForFtpOverHttp<'_> {
     }
 
     pub(crate) fn log(&self, e: ServerTaskError) {
+        if self.task_notes.finish_log_sampled_out(&e) {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -3,10 +3,12 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use slog::{Logger, slog_info};
+use slog::{KV, Logger, Record, Serializer, Value, slog_info};
 
+use g3_icap_client::IcapTransactionTiming;
 use g3_slog_types::{
-    LtDateTime, LtDuration, LtHttpMethod, LtHttpUri, LtIpAddr, LtUpstreamAddr, LtUuid,
+    LtDateTime, LtDuration, LtHttpMethod, LtHttpUri, LtIoErrorKind, LtIpAddr, LtUpstreamAddr,
+    LtUuid,
 };
 use g3_types::net::UpstreamAddr;
 
@@ -15,6 +17,24 @@ use crate::module::http_forward::HttpForwardTaskNotes;
 use crate::module::tcp_connect::TcpConnectTaskNotes;
 use crate::serve::{ServerTaskError, ServerTaskNotes};
 
+struct IcapTimingLogKv<'a> {
+    prefix: &'static str,
+    timing: Option<&'a IcapTransactionTiming>,
+}
+
+impl KV for IcapTimingLogKv<'_> {
+    fn serialize(&self, record: &Record, serializer: &mut dyn Serializer) -> slog::Result {
+        let Some(timing) = self.timing else {
+            return Ok(());
+        };
+        for (phase, dur) in timing.iter() {
+            let key = format!("{}_{}", self.prefix, phase.as_str());
+            LtDuration(dur).serialize(record, key.into(), serializer)?;
+        }
+        Ok(())
+    }
+}
+
 pub(crate) struct TaskLogForHttpForward<'a> {
     pub(crate) logger: &'a Logger,
     pub(crate) upstream: &'a UpstreamAddr,
@@ -30,6 +50,9 @@ pub(crate) struct TaskLogForHttpForward<'a> {
 
 impl TaskLogForHttpForward<'_> {
     pub(crate) fn log_created(&self) {
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -55,6 +78,9 @@ impl TaskLogForHttpForward<'_> {
     }
 
     pub(crate) fn log_connected(&self) {
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -89,6 +115,9 @@ impl TaskLogForHttpForward<'_> {
     }
 
     pub(crate) fn log_periodic(&self) {
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -134,12 +163,23 @@ impl TaskLogForHttpForward<'_> {
     }
 
     pub(crate) fn log(&self, e: &ServerTaskError) {
+        if self.task_notes.finish_log_sampled_out(e) {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
             }
         }
 
+        let icap_reqmod_timing = IcapTimingLogKv {
+            prefix: "icap_reqmod",
+            timing: self.http_notes.icap_reqmod_timing.as_ref(),
+        };
+        let icap_respmod_timing = IcapTimingLogKv {
+            prefix: "icap_respmod",
+            timing: self.http_notes.icap_respmod_timing.as_ref(),
+        };
         slog_info!(self.logger, "{}", e;
             "task_type" => "HttpForward",
             "task_id" => LtUuid(&self.task_notes.id),
@@ -158,6 +198,7 @@ impl TaskLogForHttpForward<'_> {
             "tcp_connect_tries" => self.tcp_notes.tries,
             "tcp_connect_spend" => LtDuration(self.tcp_notes.duration),
             "reason" => e.brief(),
+            "io_error_kind" => e.io_error_kind().map(LtIoErrorKind),
             "pipeline_wait" => LtDuration(self.http_notes.pipeline_wait),
             "reuse_connection" => self.http_notes.reused_connection,
             "method" => LtHttpMethod(&self.http_notes.method),
@@ -171,11 +212,17 @@ impl TaskLogForHttpForward<'_> {
             "dur_req_send_all" => LtDuration(self.http_notes.dur_req_send_all),
             "dur_rsp_recv_hdr" => LtDuration(self.http_notes.dur_rsp_recv_hdr),
             "dur_rsp_recv_all" => LtDuration(self.http_notes.dur_rsp_recv_all),
+            "icap_reqmod_retry" => self.http_notes.icap_reqmod_retried,
+            "icap_reqmod_preview" => self.http_notes.icap_reqmod_preview_size,
+            "icap_respmod_preview" => self.http_notes.icap_respmod_preview_size,
+            "icap_bypassed" => self.http_notes.icap_bypassed,
             "total_time" => LtDuration(self.task_notes.time_elapsed()),
             "c_rd_bytes" => self.client_rd_bytes,
             "c_wr_bytes" => self.client_wr_bytes,
             "r_rd_bytes" => self.remote_rd_bytes,
             "r_wr_bytes" => self.remote_wr_bytes,
+            icap_reqmod_timing,
+            icap_respmod_timing,
         )
     }
 }
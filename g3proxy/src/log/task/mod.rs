@@ -12,6 +12,11 @@ pub(crate) mod http_forward;
 pub(crate) mod tcp_connect;
 pub(crate) mod udp_associate;
 pub(crate) mod udp_connect;
+#[cfg(target_os = "linux")]
+pub(crate) mod udp_tproxy;
+
+mod ptr_lookup;
+pub(crate) use ptr_lookup::{TaskLogPtrCacheStats, foreach_cache as foreach_ptr_cache};
 
 use super::shared::SharedLoggerType;
 
@@ -34,11 +39,19 @@ pub(crate) fn get_shared_logger(
     server_name: &NodeName,
 ) -> Option<Logger> {
     let logger_name = format!("lt-{name}");
+    let ptr_cache = ptr_lookup::get_cache(name, &logger_name);
     super::shared::get_shared_logger(SharedLoggerType::Task, logger_name, |logger| {
-        logger.new(slog_o!(
+        let logger = logger.new(slog_o!(
             "server_type" => server_type.to_string(),
             "server_name" => server_name.to_string(),
-        ))
+        ));
+        match &ptr_cache {
+            Some(cache) => Logger::root(
+                ptr_lookup::ClientPtrDrain::new(logger, cache.clone()),
+                slog_o!(),
+            ),
+            None => logger,
+        }
     })
 }
 
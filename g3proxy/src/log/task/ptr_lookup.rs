@@ -0,0 +1,352 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::anyhow;
+use hickory_client::client::{Client, ClientHandle};
+use hickory_proto::rr::{DNSClass, Name, RData, RecordType};
+use lru::LruCache;
+use slog::{
+    BorrowedKV, Drain, KV, Key, Logger, Never, OwnedKVList, Record, RecordStatic, Serializer,
+    SingleKV,
+};
+
+use g3_socket::{BindAddr, UdpConnectInfo};
+
+use crate::config::log::TaskLogPtrLookupConfig;
+
+const LOG_KEY_CLIENT_PTR: &str = "client_ptr";
+
+static PTR_CACHE_REGISTRY: Mutex<BTreeMap<String, Arc<TaskLogPtrCache>>> =
+    Mutex::new(BTreeMap::new());
+
+/// Get the ptr cache for the shared logger, created on first use if enabled in config
+pub(super) fn get_cache(name: &str, logger_name: &str) -> Option<Arc<TaskLogPtrCache>> {
+    let mut registry = PTR_CACHE_REGISTRY.lock().unwrap();
+    if let Some(cache) = registry.get(logger_name) {
+        return Some(cache.clone());
+    }
+    let config = crate::config::log::get_shared_logger_ptr_lookup_config(name)?;
+    let cache = Arc::new(TaskLogPtrCache::new(config));
+    registry.insert(logger_name.to_string(), cache.clone());
+    Some(cache)
+}
+
+pub(crate) fn foreach_cache<F>(mut f: F)
+where
+    F: FnMut(&str, &TaskLogPtrCacheStats),
+{
+    let registry = PTR_CACHE_REGISTRY.lock().unwrap();
+    for (name, cache) in registry.iter() {
+        f(name, &cache.stats);
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct TaskLogPtrCacheStats {
+    hit: AtomicU64,
+    miss: AtomicU64,
+    inflight: AtomicU64,
+}
+
+impl TaskLogPtrCacheStats {
+    pub(crate) fn hit(&self) -> u64 {
+        self.hit.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn miss(&self) -> u64 {
+        self.miss.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn inflight(&self) -> u64 {
+        self.inflight.load(Ordering::Relaxed)
+    }
+}
+
+enum PtrCacheEntry {
+    Pending,
+    Resolved {
+        name: Option<Arc<str>>,
+        expire: Instant,
+    },
+}
+
+/// Async cache for the PTR names of client ips, the lookup never waits for the DNS query
+pub(crate) struct TaskLogPtrCache {
+    config: TaskLogPtrLookupConfig,
+    cache: Mutex<LruCache<IpAddr, PtrCacheEntry>>,
+    stats: TaskLogPtrCacheStats,
+}
+
+impl TaskLogPtrCache {
+    fn new(config: TaskLogPtrLookupConfig) -> Self {
+        let cache = LruCache::new(config.max_entries);
+        TaskLogPtrCache {
+            config,
+            cache: Mutex::new(cache),
+            stats: TaskLogPtrCacheStats::default(),
+        }
+    }
+
+    /// Get the cached name, a background query will be spawned if not found or expired
+    fn lookup(self: &Arc<Self>, ip: IpAddr) -> Option<Arc<str>> {
+        let mut cache = self.cache.lock().unwrap();
+        match cache.get(&ip) {
+            Some(PtrCacheEntry::Pending) => {
+                self.stats.miss.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+            Some(PtrCacheEntry::Resolved { name, expire }) => {
+                if *expire > Instant::now() {
+                    self.stats.hit.fetch_add(1, Ordering::Relaxed);
+                    return name.clone();
+                }
+            }
+            None => {}
+        }
+        self.stats.miss.fetch_add(1, Ordering::Relaxed);
+
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return None;
+        };
+        cache.put(ip, PtrCacheEntry::Pending);
+        drop(cache);
+
+        self.stats.inflight.fetch_add(1, Ordering::Relaxed);
+        let ptr_cache = self.clone();
+        handle.spawn(async move {
+            // failed queries are cached the same as the empty results
+            let name = ptr_cache.query(ip).await.ok().flatten();
+            ptr_cache.save(ip, name);
+            ptr_cache.stats.inflight.fetch_sub(1, Ordering::Relaxed);
+        });
+        None
+    }
+
+    fn save(&self, ip: IpAddr, name: Option<Arc<str>>) {
+        let ttl = if name.is_some() {
+            self.config.ttl
+        } else {
+            self.config.negative_ttl
+        };
+        let entry = PtrCacheEntry::Resolved {
+            name,
+            expire: Instant::now() + ttl,
+        };
+        self.cache.lock().unwrap().put(ip, entry);
+    }
+
+    async fn query(&self, ip: IpAddr) -> anyhow::Result<Option<Arc<str>>> {
+        let connect_info = UdpConnectInfo {
+            server: self.config.resolver,
+            bind: BindAddr::None,
+            buf_conf: Default::default(),
+            misc_opts: Default::default(),
+        };
+        let client_connect =
+            g3_hickory_client::io::udp::connect(connect_info, self.config.request_timeout);
+        let (mut client, bg) = Client::connect(Box::pin(client_connect)).await?;
+        tokio::spawn(bg);
+
+        let query = client.query(Name::from(ip), DNSClass::IN, RecordType::PTR);
+        let rsp = tokio::time::timeout(self.config.request_timeout, query)
+            .await
+            .map_err(|_| anyhow!("ptr query timed out"))??;
+        let name = rsp.answers().iter().find_map(|r| match r.data() {
+            RData::PTR(ptr) => {
+                let name = ptr.0.to_utf8();
+                Some(Arc::from(name.trim_end_matches('.')))
+            }
+            _ => None,
+        });
+        Ok(name)
+    }
+}
+
+/// Find the client ip in the record, by using either the unified or the legacy field name
+#[derive(Default)]
+struct ClientIpFinder(Option<IpAddr>);
+
+impl Serializer for ClientIpFinder {
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+        if self.0.is_none() && (key == "client_addr" || key == "tcp_client_addr") {
+            if let Ok(addr) = SocketAddr::from_str(&val.to_string()) {
+                self.0 = Some(addr.ip());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Drain that adds the client PTR name to the records if it's already in the cache
+pub(super) struct ClientPtrDrain {
+    logger: Logger,
+    cache: Arc<TaskLogPtrCache>,
+}
+
+impl ClientPtrDrain {
+    pub(super) fn new(logger: Logger, cache: Arc<TaskLogPtrCache>) -> Self {
+        ClientPtrDrain { logger, cache }
+    }
+}
+
+impl Drain for ClientPtrDrain {
+    type Ok = ();
+    type Err = Never;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        let mut finder = ClientIpFinder::default();
+        let _ = record.kv().serialize(record, &mut finder);
+        let Some(ip) = finder.0 else {
+            return self.logger.log(record, values);
+        };
+        let Some(name) = self.cache.lookup(ip) else {
+            return self.logger.log(record, values);
+        };
+
+        let kv = (record.kv(), SingleKV(Key::from(LOG_KEY_CLIENT_PTR), &*name));
+        let rs = RecordStatic {
+            location: record.location(),
+            tag: record.tag(),
+            level: record.level(),
+        };
+        self.logger
+            .log(&Record::new(&rs, record.msg(), BorrowedKV(&kv)), values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::num::NonZeroUsize;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    use hickory_proto::op::{Message, MessageType, ResponseCode};
+    use hickory_proto::rr::Record as DnsRecord;
+    use hickory_proto::rr::rdata::PTR;
+    use hickory_proto::serialize::binary::{BinDecodable, BinEncodable};
+    use slog::{slog_info, slog_o};
+    use tokio::net::UdpSocket;
+
+    const KNOWN_CLIENT_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    const UNKNOWN_CLIENT_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+
+    /// Stub DNS server which only knows the PTR name of `KNOWN_CLIENT_IP`
+    async fn spawn_stub_dns(queries: Arc<AtomicUsize>) -> SocketAddr {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            loop {
+                let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+                queries.fetch_add(1, Ordering::Relaxed);
+                let req = Message::from_vec(&buf[..len]).unwrap();
+                let mut rsp = Message::new();
+                rsp.set_id(req.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(req.op_code())
+                    .set_recursion_desired(req.recursion_desired());
+                rsp.add_queries(req.queries().to_vec());
+                let query_name = req.queries()[0].name().clone();
+                if query_name == Name::from(KNOWN_CLIENT_IP) {
+                    let target = Name::from_ascii("client.example.net.").unwrap();
+                    rsp.add_answer(DnsRecord::from_rdata(
+                        query_name,
+                        60,
+                        RData::PTR(PTR(target)),
+                    ));
+                } else {
+                    rsp.set_response_code(ResponseCode::NXDomain);
+                }
+                socket.send_to(&rsp.to_vec().unwrap(), peer).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[derive(Clone, Default)]
+    struct CaptureDrain(Arc<Mutex<Vec<Option<String>>>>);
+
+    struct PtrCapture(Option<String>);
+
+    impl Serializer for PtrCapture {
+        fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+            if key == LOG_KEY_CLIENT_PTR {
+                self.0 = Some(val.to_string());
+            }
+            Ok(())
+        }
+    }
+
+    impl Drain for CaptureDrain {
+        type Ok = ();
+        type Err = Never;
+
+        fn log(&self, record: &Record, _values: &OwnedKVList) -> Result<(), Never> {
+            let mut capture = PtrCapture(None);
+            let _ = record.kv().serialize(record, &mut capture);
+            self.0.lock().unwrap().push(capture.0);
+            Ok(())
+        }
+    }
+
+    async fn wait_inflight(cache: &TaskLogPtrCache) {
+        for _ in 0..200 {
+            if cache.stats.inflight() == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("ptr query not finished in time");
+    }
+
+    #[tokio::test]
+    async fn enrich_client_addr() {
+        let queries = Arc::new(AtomicUsize::new(0));
+        let resolver = spawn_stub_dns(queries.clone()).await;
+        let cache = Arc::new(TaskLogPtrCache::new(TaskLogPtrLookupConfig {
+            resolver,
+            request_timeout: Duration::from_secs(2),
+            ttl: Duration::from_secs(60),
+            negative_ttl: Duration::from_secs(60),
+            max_entries: NonZeroUsize::new(16).unwrap(),
+        }));
+        let capture = CaptureDrain::default();
+        let logger = Logger::root(
+            ClientPtrDrain::new(Logger::root(capture.clone(), slog_o!()), cache.clone()),
+            slog_o!(),
+        );
+
+        let known = SocketAddr::new(KNOWN_CLIENT_IP, 10001);
+        slog_info!(logger, ""; "client_addr" => known);
+        wait_inflight(&cache).await;
+        slog_info!(logger, ""; "client_addr" => known);
+        slog_info!(logger, ""; "tcp_client_addr" => known);
+
+        let unknown = SocketAddr::new(UNKNOWN_CLIENT_IP, 10002);
+        slog_info!(logger, ""; "client_addr" => unknown);
+        wait_inflight(&cache).await;
+        slog_info!(logger, ""; "client_addr" => unknown);
+        slog_info!(logger, ""; "server_addr" => known);
+
+        let records = capture.0.lock().unwrap().clone();
+        let name = Some("client.example.net".to_string());
+        assert_eq!(records, vec![None, name.clone(), name, None, None, None]);
+
+        // the negative result should be cached
+        assert_eq!(queries.load(Ordering::Relaxed), 2);
+        assert_eq!(cache.stats.hit(), 3);
+        assert_eq!(cache.stats.miss(), 2);
+    }
+}
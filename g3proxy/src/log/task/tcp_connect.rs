@@ -3,13 +3,15 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::net::SocketAddr;
+
 use slog::{Logger, slog_info};
 
-use g3_slog_types::{LtDateTime, LtDuration, LtIpAddr, LtUpstreamAddr, LtUuid};
+use g3_daemon::log::task::{TaskLogIoBytes, TaskLogRecord};
 use g3_types::net::UpstreamAddr;
 
 use super::TaskEvent;
-use crate::module::tcp_connect::TcpConnectTaskNotes;
+use crate::module::tcp_connect::{TcpConnectAttempts, TcpConnectTaskNotes};
 use crate::serve::{ServerTaskError, ServerTaskNotes};
 
 pub(crate) struct TaskLogForTcpConnect<'a> {
@@ -17,120 +19,128 @@ pub(crate) struct TaskLogForTcpConnect<'a> {
     pub(crate) upstream: &'a UpstreamAddr,
     pub(crate) task_notes: &'a ServerTaskNotes,
     pub(crate) tcp_notes: &'a TcpConnectTaskNotes,
+    /// only set if the upstream has been tried more than once
+    pub(crate) connect_attempts: Option<&'a TcpConnectAttempts>,
+    /// the phase in which the upstream connect timed out, only set by tcp_tproxy server
+    pub(crate) connect_timeout: Option<&'static str>,
+    /// the original destination before normalization, only set by tcp_tproxy server
+    pub(crate) original_dst: Option<SocketAddr>,
     pub(crate) client_rd_bytes: u64,
     pub(crate) client_wr_bytes: u64,
     pub(crate) remote_rd_bytes: u64,
     pub(crate) remote_wr_bytes: u64,
 }
 
-impl TaskLogForTcpConnect<'_> {
-    pub(crate) fn log_created(&self) {
-        if let Some(user_ctx) = self.task_notes.user_ctx() {
-            if user_ctx.skip_log() {
-                return;
-            }
-        }
-
-        slog_info!(self.logger, "";
-            "task_type" => "TcpConnect",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Created.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "user" => self.task_notes.raw_user_name(),
-            "server_addr" => self.task_notes.server_addr(),
-            "client_addr" => self.task_notes.client_addr(),
-            "upstream" => LtUpstreamAddr(self.upstream),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
+impl<'a> TaskLogForTcpConnect<'a> {
+    fn skip_log(&self) -> bool {
+        self.task_notes.user_ctx().is_some_and(|ctx| ctx.skip_log())
+    }
+
+    fn record(&self, task_event: TaskEvent) -> TaskLogRecord<'a> {
+        let record = TaskLogRecord::new(
+            "TcpConnect",
+            &self.task_notes.id,
+            self.task_notes.stage.brief(),
+            &self.task_notes.start_at,
         )
+        .task_event(task_event.as_str())
+        .user(self.task_notes.raw_user_name().map(|s| s.as_ref()))
+        .server_addr(self.task_notes.server_addr())
+        .client_addr(self.task_notes.client_addr())
+        .upstream(self.upstream)
+        .wait_time(self.task_notes.wait_time);
+        match self.original_dst {
+            Some(addr) => record.extension("original_dst", addr),
+            None => record,
+        }
+    }
+
+    fn io_bytes(&self) -> TaskLogIoBytes {
+        TaskLogIoBytes {
+            client_rd: self.client_rd_bytes,
+            client_wr: self.client_wr_bytes,
+            remote_rd: self.remote_rd_bytes,
+            remote_wr: self.remote_wr_bytes,
+        }
+    }
+
+    fn with_next_hop(&self, record: TaskLogRecord<'a>, bind_ip: bool) -> TaskLogRecord<'a> {
+        let record = record.extension("escaper", self.tcp_notes.escaper.as_str());
+        let record = if bind_ip {
+            record.extension("next_bind_ip", self.tcp_notes.bind.ip())
+        } else {
+            record
+        };
+        record
+            .extension("next_bound_addr", self.tcp_notes.local)
+            .extension("next_peer_addr", self.tcp_notes.next)
+            .extension("next_expire", self.tcp_notes.expire.as_ref())
+    }
+
+    fn with_connect_stats(&self, record: TaskLogRecord<'a>) -> TaskLogRecord<'a> {
+        record
+            .extension("tcp_connect_tries", self.tcp_notes.tries)
+            .extension("tcp_connect_spend", self.tcp_notes.duration)
+            .extension("tcp_connect_rtt", self.tcp_notes.rtt)
+    }
+
+    pub(crate) fn log_created(&self) {
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
+        if self.skip_log() {
+            return;
+        }
+
+        slog_info!(self.logger, ""; self.record(TaskEvent::Created))
     }
 
     pub(crate) fn log_connected(&self) {
-        if let Some(user_ctx) = self.task_notes.user_ctx() {
-            if user_ctx.skip_log() {
-                return;
-            }
-        }
-
-        slog_info!(self.logger, "";
-            "task_type" => "TcpConnect",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Connected.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "user" => self.task_notes.raw_user_name(),
-            "server_addr" => self.task_notes.server_addr(),
-            "client_addr" => self.task_notes.client_addr(),
-            "upstream" => LtUpstreamAddr(self.upstream),
-            "escaper" => self.tcp_notes.escaper.as_str(),
-            "next_bind_ip" => self.tcp_notes.bind.ip().map(LtIpAddr),
-            "next_bound_addr" => self.tcp_notes.local,
-            "next_peer_addr" => self.tcp_notes.next,
-            "next_expire" => self.tcp_notes.expire.as_ref().map(LtDateTime),
-            "tcp_connect_tries" => self.tcp_notes.tries,
-            "tcp_connect_spend" => LtDuration(self.tcp_notes.duration),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-        )
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
+        if self.skip_log() {
+            return;
+        }
+
+        let record = self.with_next_hop(self.record(TaskEvent::Connected), true);
+        let record = self
+            .with_connect_stats(record)
+            .extension(
+                "tcp_connect_attempts",
+                self.connect_attempts.map(|a| a.to_string()),
+            )
+            .ready_time(self.task_notes.ready_time);
+        slog_info!(self.logger, ""; record)
     }
 
     pub(crate) fn log_periodic(&self) {
-        if let Some(user_ctx) = self.task_notes.user_ctx() {
-            if user_ctx.skip_log() {
-                return;
-            }
-        }
-
-        slog_info!(self.logger, "";
-            "task_type" => "TcpConnect",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Periodic.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "user" => self.task_notes.raw_user_name(),
-            "server_addr" => self.task_notes.server_addr(),
-            "client_addr" => self.task_notes.client_addr(),
-            "upstream" => LtUpstreamAddr(self.upstream),
-            "escaper" => self.tcp_notes.escaper.as_str(),
-            "next_bind_ip" => self.tcp_notes.bind.ip().map(LtIpAddr),
-            "next_bound_addr" => self.tcp_notes.local,
-            "next_peer_addr" => self.tcp_notes.next,
-            "next_expire" => self.tcp_notes.expire.as_ref().map(LtDateTime),
-            "tcp_connect_tries" => self.tcp_notes.tries,
-            "tcp_connect_spend" => LtDuration(self.tcp_notes.duration),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-            "total_time" => LtDuration(self.task_notes.time_elapsed()),
-            "c_rd_bytes" => self.client_rd_bytes,
-            "c_wr_bytes" => self.client_wr_bytes,
-            "r_rd_bytes" => self.remote_rd_bytes,
-            "r_wr_bytes" => self.remote_wr_bytes,
-        )
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
+        if self.skip_log() {
+            return;
+        }
+
+        let record = self.with_next_hop(self.record(TaskEvent::Periodic), true);
+        let record = self
+            .with_connect_stats(record)
+            .ready_time(self.task_notes.ready_time)
+            .total_time(self.task_notes.time_elapsed())
+            .io_bytes(self.io_bytes());
+        slog_info!(self.logger, ""; record)
     }
 
     fn log_partial_shutdown(&self, task_event: TaskEvent) {
-        slog_info!(self.logger, "";
-            "task_type" => "TcpConnect",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => task_event.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "user" => self.task_notes.raw_user_name(),
-            "server_addr" => self.task_notes.server_addr(),
-            "client_addr" => self.task_notes.client_addr(),
-            "upstream" => LtUpstreamAddr(self.upstream),
-            "escaper" => self.tcp_notes.escaper.as_str(),
-            "next_bound_addr" => self.tcp_notes.local,
-            "next_peer_addr" => self.tcp_notes.next,
-            "next_expire" => self.tcp_notes.expire.as_ref().map(LtDateTime),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-            "total_time" => LtDuration(self.task_notes.time_elapsed()),
-            "c_rd_bytes" => self.client_rd_bytes,
-            "c_wr_bytes" => self.client_wr_bytes,
-            "r_rd_bytes" => self.remote_rd_bytes,
-            "r_wr_bytes" => self.remote_wr_bytes,
-        )
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
+        let record = self
+            .with_next_hop(self.record(task_event), false)
+            .ready_time(self.task_notes.ready_time)
+            .total_time(self.task_notes.time_elapsed())
+            .io_bytes(self.io_bytes());
+        slog_info!(self.logger, ""; record)
     }
 
     pub(crate) fn log_client_shutdown(&self) {
@@ -142,37 +152,249 @@ impl TaskLogForTcpConnect<'_> {
     }
 
     pub(crate) fn log(&self, e: ServerTaskError) {
-        if let Some(user_ctx) = self.task_notes.user_ctx() {
-            if user_ctx.skip_log() {
-                return;
-            }
-        }
-
-        slog_info!(self.logger, "{}", e;
-            "task_type" => "TcpConnect",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Finished.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "user" => self.task_notes.raw_user_name(),
-            "server_addr" => self.task_notes.server_addr(),
-            "client_addr" => self.task_notes.client_addr(),
-            "upstream" => LtUpstreamAddr(self.upstream),
-            "escaper" => self.tcp_notes.escaper.as_str(),
-            "next_bind_ip" => self.tcp_notes.bind.ip().map(LtIpAddr),
-            "next_bound_addr" => self.tcp_notes.local,
-            "next_peer_addr" => self.tcp_notes.next,
-            "next_expire" => self.tcp_notes.expire.as_ref().map(LtDateTime),
-            "tcp_connect_tries" => self.tcp_notes.tries,
-            "tcp_connect_spend" => LtDuration(self.tcp_notes.duration),
-            "reason" => e.brief(),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-            "total_time" => LtDuration(self.task_notes.time_elapsed()),
-            "c_rd_bytes" => self.client_rd_bytes,
-            "c_wr_bytes" => self.client_wr_bytes,
-            "r_rd_bytes" => self.remote_rd_bytes,
-            "r_wr_bytes" => self.remote_wr_bytes,
+        if self.task_notes.finish_log_sampled_out(&e) {
+            return;
+        }
+        if self.skip_log() {
+            return;
+        }
+
+        let record = self.with_next_hop(self.record(TaskEvent::Finished), true);
+        let record = self
+            .with_connect_stats(record)
+            .extension(
+                "tcp_connect_attempts",
+                self.connect_attempts.map(|a| a.to_string()),
+            )
+            .reason(e.brief());
+        let record = match self.connect_timeout {
+            Some(phase) => record.extension("tcp_connect_timeout", phase),
+            None => record,
+        };
+        let record = record
+            .extension(
+                "idle_override",
+                self.task_notes.vars().idle_override().map(|s| s.as_ref()),
+            )
+            .extension(
+                "client_error_action",
+                self.task_notes
+                    .vars()
+                    .client_error_action()
+                    .map(|a| a.to_string()),
+            )
+            .ready_time(self.task_notes.ready_time)
+            .total_time(self.task_notes.time_elapsed())
+            .io_bytes(self.io_bytes());
+        slog_info!(self.logger, "{}", e; record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::io;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use slog::{Drain, Key, OwnedKVList, Record, Serializer};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use g3_daemon::server::ClientConnectionInfo;
+    use g3_socket::BindAddr;
+    use g3_types::metrics::NodeName;
+
+    use crate::config::server::TaskLogSampleConfig;
+    use crate::serve::TaskLogSampler;
+
+    #[derive(Clone, Default)]
+    struct CollectDrain(Arc<Mutex<HashMap<String, String>>>);
+
+    impl Serializer for CollectDrain {
+        fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+            let mut map = self.0.lock().unwrap();
+            map.insert(key.to_string(), val.to_string());
+            Ok(())
+        }
+    }
+
+    impl Drain for CollectDrain {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(&self, record: &Record, _values: &OwnedKVList) -> Result<(), slog::Never> {
+            let mut serializer = self.clone();
+            record.kv().serialize(record, &mut serializer).unwrap();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn connected_fields() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listen_addr = listener.local_addr().unwrap();
+        let (ups_stream, accepted) =
+            tokio::join!(TcpStream::connect(listen_addr), listener.accept());
+        let ups_stream = ups_stream.unwrap();
+        let (clt_stream, clt_addr) = accepted.unwrap();
+
+        let local_addr = ups_stream.local_addr().unwrap();
+        let mut tcp_notes = TcpConnectTaskNotes {
+            escaper: NodeName::from_str("direct").unwrap(),
+            bind: BindAddr::Ip(local_addr.ip()),
+            next: Some(listen_addr),
+            tries: 2,
+            local: Some(local_addr),
+            duration: Duration::from_millis(3),
+            ..Default::default()
+        };
+        tcp_notes.record_tcp_rtt(&ups_stream);
+        let task_notes = ServerTaskNotes::new(
+            ClientConnectionInfo::new(clt_addr, clt_stream.local_addr().unwrap()),
+            None,
+            Duration::ZERO,
+        );
+        let upstream = UpstreamAddr::from(listen_addr);
+
+        let drain = CollectDrain::default();
+        let logger = Logger::root(drain.clone(), slog::o!());
+        let task_log = TaskLogForTcpConnect {
+            logger: &logger,
+            upstream: &upstream,
+            task_notes: &task_notes,
+            tcp_notes: &tcp_notes,
+            connect_attempts: None,
+            connect_timeout: None,
+            original_dst: None,
+            client_rd_bytes: 0,
+            client_wr_bytes: 0,
+            remote_rd_bytes: 0,
+            remote_wr_bytes: 0,
+        };
+        task_log.log_connected();
+
+        let fields = drain.0.lock().unwrap();
+        assert_eq!(fields["escaper"], "direct");
+        assert_eq!(fields["next_bind_ip"], local_addr.ip().to_string());
+        assert_eq!(fields["next_bound_addr"], local_addr.to_string());
+        assert_eq!(fields["next_peer_addr"], listen_addr.to_string());
+        assert_eq!(fields["tcp_connect_tries"], "2");
+        assert_ne!(fields["tcp_connect_spend"], "None");
+        #[cfg(target_os = "linux")]
+        assert_ne!(fields["tcp_connect_rtt"], "None");
+        #[cfg(not(target_os = "linux"))]
+        assert_eq!(fields["tcp_connect_rtt"], "None");
+        g3_daemon::log::task::validate_task_log(
+            fields.iter().map(|(k, v)| (k.as_str(), v.as_str())),
         )
+        .unwrap();
+    }
+
+    #[test]
+    fn connect_timeout() {
+        let clt_addr = SocketAddr::from_str("192.0.2.1:10001").unwrap();
+        let dst_addr = SocketAddr::from_str("10.255.255.1:80").unwrap();
+        let task_notes = ServerTaskNotes::new(
+            ClientConnectionInfo::new(clt_addr, dst_addr),
+            None,
+            Duration::ZERO,
+        );
+        let tcp_notes = TcpConnectTaskNotes::default();
+        let upstream = UpstreamAddr::from(dst_addr);
+
+        let drain = CollectDrain::default();
+        let logger = Logger::root(drain.clone(), slog::o!());
+        let task_log = TaskLogForTcpConnect {
+            logger: &logger,
+            upstream: &upstream,
+            task_notes: &task_notes,
+            tcp_notes: &tcp_notes,
+            connect_attempts: None,
+            connect_timeout: Some("escaper_setup"),
+            client_rd_bytes: 0,
+            client_wr_bytes: 0,
+            remote_rd_bytes: 0,
+            remote_wr_bytes: 0,
+        };
+        task_log.log(ServerTaskError::from(
+            crate::module::tcp_connect::TcpConnectError::TimeoutByRule,
+        ));
+
+        let fields = drain.0.lock().unwrap();
+        assert_eq!(fields["tcp_connect_timeout"], "escaper_setup");
+        assert_eq!(fields["upstream"], "10.255.255.1:80");
+        g3_daemon::log::task::validate_task_log(
+            fields.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+        )
+        .unwrap();
+    }
+
+    #[derive(Clone, Default)]
+    struct CountDrain(Arc<AtomicUsize>);
+
+    impl Drain for CountDrain {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(&self, _record: &Record, _values: &OwnedKVList) -> Result<(), slog::Never> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sampled_finish_log() {
+        let mut config = TaskLogSampleConfig::default();
+        config.set_ratio(0.2).unwrap();
+        let sampler = TaskLogSampler::new(&config);
+
+        let clt_addr = SocketAddr::from_str("192.0.2.1:10001").unwrap();
+        let dst_addr = SocketAddr::from_str("192.0.2.2:80").unwrap();
+        let tcp_notes = TcpConnectTaskNotes::default();
+        let upstream = UpstreamAddr::from(dst_addr);
+
+        let drain = CountDrain::default();
+        let logger = Logger::root(drain.clone(), slog::o!());
+        let run = |e: fn() -> ServerTaskError| {
+            let mut task_notes = ServerTaskNotes::new(
+                ClientConnectionInfo::new(clt_addr, dst_addr),
+                None,
+                Duration::ZERO,
+            );
+            task_notes.set_log_sample(sampler.sample());
+            let task_log = TaskLogForTcpConnect {
+                logger: &logger,
+                upstream: &upstream,
+                task_notes: &task_notes,
+                tcp_notes: &tcp_notes,
+                connect_attempts: None,
+                connect_timeout: None,
+                original_dst: None,
+                client_rd_bytes: 0,
+                client_wr_bytes: 0,
+                remote_rd_bytes: 0,
+                remote_wr_bytes: 0,
+            };
+            task_log.log_created();
+            task_log.log(e());
+        };
+
+        for _ in 0..5000 {
+            run(|| ServerTaskError::Finished);
+        }
+        // 2 logs for each sampled task
+        let count = drain.0.swap(0, Ordering::Relaxed);
+        assert!((1600..2400).contains(&count), "count: {count}");
+
+        for _ in 0..1000 {
+            run(|| ServerTaskError::UpstreamReadFailed(io::Error::other("reset")));
+        }
+        // the finish log of all failed tasks
+        let count = drain.0.swap(0, Ordering::Relaxed);
+        assert!((1000..1400).contains(&count), "count: {count}");
     }
 }
@@ -4,13 +4,15 @@
  */
 
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use slog::{Logger, slog_info};
 
-use g3_slog_types::{LtDateTime, LtDuration, LtUpstreamAddr, LtUuid};
+use g3_daemon::log::task::{TaskLogIoBytes, TaskLogRecord};
 use g3_types::net::UpstreamAddr;
 
 use super::TaskEvent;
+use crate::config::server::socks_proxy::SocksProxyUdpClientPortPolicy;
 use crate::module::udp_relay::UdpRelayTaskNotes;
 use crate::serve::{ServerTaskError, ServerTaskNotes};
 
@@ -21,132 +23,157 @@ pub(crate) struct TaskLogForUdpAssociate<'a> {
     pub(crate) tcp_client_addr: SocketAddr,
     pub(crate) udp_listen_addr: Option<SocketAddr>,
     pub(crate) udp_client_addr: Option<SocketAddr>,
+    pub(crate) udp_port_policy: SocksProxyUdpClientPortPolicy,
+    pub(crate) udp_port_honored: Option<bool>,
     pub(crate) initial_peer: &'a UpstreamAddr,
     pub(crate) udp_notes: &'a UdpRelayTaskNotes,
     pub(crate) client_rd_bytes: u64,
     pub(crate) client_rd_packets: u64,
+    pub(crate) client_rd_overhead: u64,
     pub(crate) client_wr_bytes: u64,
     pub(crate) client_wr_packets: u64,
+    pub(crate) client_wr_overhead: u64,
+    pub(crate) client_dup_dropped: u64,
+    pub(crate) client_verify_dropped: u64,
+    pub(crate) client_truncated: u64,
+    pub(crate) client_oversize_dropped: u64,
+    pub(crate) client_rd_throttled: Duration,
+    pub(crate) client_wr_throttled: Duration,
     pub(crate) remote_rd_bytes: u64,
     pub(crate) remote_rd_packets: u64,
     pub(crate) remote_wr_bytes: u64,
     pub(crate) remote_wr_packets: u64,
+    pub(crate) remote_truncated: u64,
+    pub(crate) remote_oversize_dropped: u64,
 }
 
-impl TaskLogForUdpAssociate<'_> {
+impl<'a> TaskLogForUdpAssociate<'a> {
+    fn skip_log(&self) -> bool {
+        self.task_notes.user_ctx().is_some_and(|ctx| ctx.skip_log())
+    }
+
+    fn record(&self, task_event: TaskEvent) -> TaskLogRecord<'a> {
+        TaskLogRecord::new(
+            "UdpAssociate",
+            &self.task_notes.id,
+            self.task_notes.stage.brief(),
+            &self.task_notes.start_at,
+        )
+        .task_event(task_event.as_str())
+        .user(self.task_notes.raw_user_name().map(|s| s.as_ref()))
+        .server_addr_renamed("tcp_server_addr", self.tcp_server_addr)
+        .client_addr_renamed("tcp_client_addr", self.tcp_client_addr)
+        .wait_time(self.task_notes.wait_time)
+    }
+
+    fn with_relay(&self, record: TaskLogRecord<'a>, port_policy: bool) -> TaskLogRecord<'a> {
+        let record = record
+            .extension("udp_listen_addr", self.udp_listen_addr)
+            .extension("udp_client_addr", self.udp_client_addr)
+            .extension(
+                "udp_port_range_tier",
+                self.task_notes
+                    .vars()
+                    .udp_port_range_tier()
+                    .map(|s| s.as_ref()),
+            )
+            .extension(
+                "udp_port_range",
+                self.task_notes
+                    .vars()
+                    .udp_port_range()
+                    .map(|r| r.to_string()),
+            );
+        let record = if port_policy {
+            record
+                .extension("udp_port_policy", self.udp_port_policy.as_str())
+                .extension("udp_port_honored", self.udp_port_honored)
+        } else {
+            record
+        };
+        record
+            .upstream_renamed("initial_peer", self.initial_peer)
+            .extension("escaper", self.udp_notes.escaper.as_str())
+            .ready_time(self.task_notes.ready_time)
+    }
+
+    fn with_io_stats(&self, record: TaskLogRecord<'a>) -> TaskLogRecord<'a> {
+        record
+            .io_bytes(TaskLogIoBytes {
+                client_rd: self.client_rd_bytes,
+                client_wr: self.client_wr_bytes,
+                remote_rd: self.remote_rd_bytes,
+                remote_wr: self.remote_wr_bytes,
+            })
+            .extension("c_rd_packets", self.client_rd_packets)
+            .extension("c_wr_packets", self.client_wr_packets)
+            .extension("c_rd_overhead", self.client_rd_overhead)
+            .extension("c_wr_overhead", self.client_wr_overhead)
+            .extension("c_dup_dropped", self.client_dup_dropped)
+            .extension("c_verify_dropped", self.client_verify_dropped)
+            .extension("c_truncated", self.client_truncated)
+            .extension("c_oversize_dropped", self.client_oversize_dropped)
+            .extension("c_rd_throttled", self.client_rd_throttled)
+            .extension("c_wr_throttled", self.client_wr_throttled)
+            .extension("r_rd_packets", self.remote_rd_packets)
+            .extension("r_wr_packets", self.remote_wr_packets)
+            .extension("r_truncated", self.remote_truncated)
+            .extension("r_oversize_dropped", self.remote_oversize_dropped)
+    }
+
     pub(crate) fn log_created(&self) {
-        if let Some(user_ctx) = self.task_notes.user_ctx() {
-            if user_ctx.skip_log() {
-                return;
-            }
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
+        if self.skip_log() {
+            return;
         }
 
-        slog_info!(self.logger, "";
-            "task_type" => "UdpAssociate",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Created.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "user" => self.task_notes.raw_user_name(),
-            "tcp_server_addr" => self.tcp_server_addr,
-            "tcp_client_addr" => self.tcp_client_addr,
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-        )
+        slog_info!(self.logger, ""; self.record(TaskEvent::Created))
     }
 
     pub(crate) fn log_connected(&self) {
-        if let Some(user_ctx) = self.task_notes.user_ctx() {
-            if user_ctx.skip_log() {
-                return;
-            }
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
+        if self.skip_log() {
+            return;
         }
 
-        slog_info!(self.logger, "";
-            "task_type" => "UdpAssociate",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Connected.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "user" => self.task_notes.raw_user_name(),
-            "tcp_server_addr" => self.tcp_server_addr,
-            "tcp_client_addr" => self.tcp_client_addr,
-            "udp_listen_addr" => self.udp_listen_addr,
-            "udp_client_addr" => self.udp_client_addr,
-            "initial_peer" => LtUpstreamAddr(self.initial_peer),
-            "escaper" => self.udp_notes.escaper.as_str(),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-            "c_rd_bytes" => self.client_rd_bytes,
-            "c_rd_packets" => self.client_rd_packets,
-        )
+        let record = self.with_relay(self.record(TaskEvent::Connected), true);
+        slog_info!(self.logger, ""; self.with_io_stats(record))
     }
 
     pub(crate) fn log_periodic(&self) {
-        if let Some(user_ctx) = self.task_notes.user_ctx() {
-            if user_ctx.skip_log() {
-                return;
-            }
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
+        if self.skip_log() {
+            return;
         }
 
-        slog_info!(self.logger, "";
-            "task_type" => "UdpAssociate",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Periodic.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "user" => self.task_notes.raw_user_name(),
-            "tcp_server_addr" => self.tcp_server_addr,
-            "tcp_client_addr" => self.tcp_client_addr,
-            "udp_listen_addr" => self.udp_listen_addr,
-            "udp_client_addr" => self.udp_client_addr,
-            "initial_peer" => LtUpstreamAddr(self.initial_peer),
-            "escaper" => self.udp_notes.escaper.as_str(),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-            "total_time" => LtDuration(self.task_notes.time_elapsed()),
-            "c_rd_bytes" => self.client_rd_bytes,
-            "c_rd_packets" => self.client_rd_packets,
-            "c_wr_bytes" => self.client_wr_bytes,
-            "c_wr_packets" => self.client_wr_packets,
-            "r_rd_bytes" => self.remote_rd_bytes,
-            "r_rd_packets" => self.remote_rd_packets,
-            "r_wr_bytes" => self.remote_wr_bytes,
-            "r_wr_packets" => self.remote_wr_packets,
-        )
+        let record = self
+            .with_relay(self.record(TaskEvent::Periodic), false)
+            .total_time(self.task_notes.time_elapsed());
+        slog_info!(self.logger, ""; self.with_io_stats(record))
     }
 
     pub(crate) fn log(&self, e: ServerTaskError) {
-        if let Some(user_ctx) = self.task_notes.user_ctx() {
-            if user_ctx.skip_log() {
-                return;
-            }
+        if self.task_notes.finish_log_sampled_out(&e) {
+            return;
+        }
+        if self.skip_log() {
+            return;
         }
 
-        slog_info!(self.logger, "{}", e;
-            "task_type" => "UdpAssociate",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Finished.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "user" => self.task_notes.raw_user_name(),
-            "tcp_server_addr" => self.tcp_server_addr,
-            "tcp_client_addr" => self.tcp_client_addr,
-            "udp_listen_addr" => self.udp_listen_addr,
-            "udp_client_addr" => self.udp_client_addr,
-            "initial_peer" => LtUpstreamAddr(self.initial_peer),
-            "escaper" => self.udp_notes.escaper.as_str(),
-            "reason" => e.brief(),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-            "total_time" => LtDuration(self.task_notes.time_elapsed()),
-            "c_rd_bytes" => self.client_rd_bytes,
-            "c_rd_packets" => self.client_rd_packets,
-            "c_wr_bytes" => self.client_wr_bytes,
-            "c_wr_packets" => self.client_wr_packets,
-            "r_rd_bytes" => self.remote_rd_bytes,
-            "r_rd_packets" => self.remote_rd_packets,
-            "r_wr_bytes" => self.remote_wr_bytes,
-            "r_wr_packets" => self.remote_wr_packets,
-        )
+        let record = self
+            .with_relay(self.record(TaskEvent::Finished), true)
+            .reason(e.brief())
+            .extension(
+                "idle_override",
+                self.task_notes.vars().idle_override().map(|s| s.as_ref()),
+            )
+            .total_time(self.task_notes.time_elapsed());
+        slog_info!(self.logger, "{}", e; self.with_io_stats(record))
     }
 }
@@ -25,8 +25,11 @@ pub(crate) struct TaskLogForUdpConnect<'a> {
     pub(crate) udp_notes: &'a UdpConnectTaskNotes,
     pub(crate) client_rd_bytes: u64,
     pub(crate) client_rd_packets: u64,
+    pub(crate) client_rd_overhead: u64,
     pub(crate) client_wr_bytes: u64,
     pub(crate) client_wr_packets: u64,
+    pub(crate) client_wr_overhead: u64,
+    pub(crate) client_dup_dropped: u64,
     pub(crate) remote_rd_bytes: u64,
     pub(crate) remote_rd_packets: u64,
     pub(crate) remote_wr_bytes: u64,
@@ -35,6 +38,9 @@ pub(crate) struct TaskLogForUdpConnect<'a> {
 
 impl TaskLogForUdpConnect<'_> {
     pub(crate) fn log_created(&self) {
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -55,6 +61,9 @@ impl TaskLogForUdpConnect<'_> {
     }
 
     pub(crate) fn log_connected(&self) {
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -72,6 +81,8 @@ impl TaskLogForUdpConnect<'_> {
             "tcp_client_addr" => self.tcp_client_addr,
             "udp_listen_addr" => self.udp_listen_addr,
             "udp_client_addr" => self.udp_client_addr,
+            "udp_port_range_tier" => self.task_notes.vars().udp_port_range_tier(),
+            "udp_port_range" => self.task_notes.vars().udp_port_range().map(|r| r.to_string()),
             "upstream" => self.upstream.map(LtUpstreamAddr),
             "escaper" => self.udp_notes.escaper.as_str(),
             "next_bind_ip" => self.udp_notes.bind.ip().map(LtIpAddr),
@@ -82,10 +93,14 @@ impl TaskLogForUdpConnect<'_> {
             "ready_time" => LtDuration(self.task_notes.ready_time),
             "c_rd_bytes" => self.client_rd_bytes,
             "c_rd_packets" => self.client_rd_packets,
+            "c_rd_overhead" => self.client_rd_overhead,
         )
     }
 
     pub(crate) fn log_periodic(&self) {
+        if self.task_notes.log_sampled_out() {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -103,6 +118,8 @@ impl TaskLogForUdpConnect<'_> {
             "tcp_client_addr" => self.tcp_client_addr,
             "udp_listen_addr" => self.udp_listen_addr,
             "udp_client_addr" => self.udp_client_addr,
+            "udp_port_range_tier" => self.task_notes.vars().udp_port_range_tier(),
+            "udp_port_range" => self.task_notes.vars().udp_port_range().map(|r| r.to_string()),
             "upstream" => self.upstream.map(LtUpstreamAddr),
             "escaper" => self.udp_notes.escaper.as_str(),
             "next_bind_ip" => self.udp_notes.bind.ip().map(LtIpAddr),
@@ -114,8 +131,11 @@ impl TaskLogForUdpConnect<'_> {
             "total_time" => LtDuration(self.task_notes.time_elapsed()),
             "c_rd_bytes" => self.client_rd_bytes,
             "c_rd_packets" => self.client_rd_packets,
+            "c_rd_overhead" => self.client_rd_overhead,
             "c_wr_bytes" => self.client_wr_bytes,
             "c_wr_packets" => self.client_wr_packets,
+            "c_wr_overhead" => self.client_wr_overhead,
+            "c_dup_dropped" => self.client_dup_dropped,
             "r_rd_bytes" => self.remote_rd_bytes,
             "r_rd_packets" => self.remote_rd_packets,
             "r_wr_bytes" => self.remote_wr_bytes,
@@ -124,6 +144,9 @@ impl TaskLogForUdpConnect<'_> {
     }
 
     pub(crate) fn log(&self, e: ServerTaskError) {
+        if self.task_notes.finish_log_sampled_out(&e) {
+            return;
+        }
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -141,6 +164,8 @@ impl TaskLogForUdpConnect<'_> {
             "tcp_client_addr" => self.tcp_client_addr,
             "udp_listen_addr" => self.udp_listen_addr,
             "udp_client_addr" => self.udp_client_addr,
+            "udp_port_range_tier" => self.task_notes.vars().udp_port_range_tier(),
+            "udp_port_range" => self.task_notes.vars().udp_port_range().map(|r| r.to_string()),
             "upstream" => self.upstream.map(LtUpstreamAddr),
             "escaper" => self.udp_notes.escaper.as_str(),
             "next_bind_ip" => self.udp_notes.bind.ip().map(LtIpAddr),
@@ -148,13 +173,17 @@ impl TaskLogForUdpConnect<'_> {
             "next_peer_addr" => self.udp_notes.next,
             "next_expire" => self.udp_notes.expire.as_ref().map(LtDateTime),
             "reason" => e.brief(),
+            "idle_override" => self.task_notes.vars().idle_override(),
             "wait_time" => LtDuration(self.task_notes.wait_time),
             "ready_time" => LtDuration(self.task_notes.ready_time),
             "total_time" => LtDuration(self.task_notes.time_elapsed()),
             "c_rd_bytes" => self.client_rd_bytes,
             "c_rd_packets" => self.client_rd_packets,
+            "c_rd_overhead" => self.client_rd_overhead,
             "c_wr_bytes" => self.client_wr_bytes,
             "c_wr_packets" => self.client_wr_packets,
+            "c_wr_overhead" => self.client_wr_overhead,
+            "c_dup_dropped" => self.client_dup_dropped,
             "r_rd_bytes" => self.remote_rd_bytes,
             "r_rd_packets" => self.remote_rd_packets,
             "r_wr_bytes" => self.remote_wr_bytes,
@@ -319,6 +319,8 @@ impl HttpProxyClientResponse {
         let r = match e {
             ServerTaskError::InternalServerError(_)
             | ServerTaskError::InternalAdapterError(_)
+            | ServerTaskError::IcapServerReadFailed(_, _)
+            | ServerTaskError::IcapServerWriteFailed(_, _)
             | ServerTaskError::InternalResolverError(_)
             | ServerTaskError::UnclassifiedError(_) => HttpProxyClientResponse::from_standard(
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -394,18 +396,22 @@ impl HttpProxyClientResponse {
             ServerTaskError::CanceledAsUserBlocked => {
                 HttpProxyClientResponse::from_standard(StatusCode::FORBIDDEN, version, true)
             }
-            ServerTaskError::CanceledAsServerQuit => HttpProxyClientResponse::from_standard(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                version,
-                true,
-            ),
+            ServerTaskError::CanceledAsServerQuit | ServerTaskError::KilledByOperator => {
+                HttpProxyClientResponse::from_standard(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    version,
+                    true,
+                )
+            }
             ServerTaskError::ClientTcpReadFailed(_)
             | ServerTaskError::ClientTcpWriteFailed(_)
             | ServerTaskError::ClientUdpRecvFailed(_)
             | ServerTaskError::ClientUdpSendFailed(_)
             | ServerTaskError::ClosedByClient
             | ServerTaskError::ClosedEarlyByClient
+            | ServerTaskError::ControlClosed
             | ServerTaskError::Idle(_, _)
+            | ServerTaskError::DirectionalIdle(_, _, _)
             | ServerTaskError::InterceptionError(_, _)
             | ServerTaskError::Finished => return None,
         };
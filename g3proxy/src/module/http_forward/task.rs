@@ -6,6 +6,8 @@
 use http::{Method, Uri};
 use tokio::time::{Duration, Instant};
 
+use g3_icap_client::IcapTransactionTiming;
+
 pub(crate) struct HttpForwardTaskNotes {
     pub(crate) method: Method,
     pub(crate) uri: Uri,
@@ -20,6 +22,12 @@ pub(crate) struct HttpForwardTaskNotes {
     pub(crate) dur_rsp_recv_hdr: Duration,
     pub(crate) dur_rsp_recv_all: Duration,
     pub(crate) retry_new_connection: bool,
+    pub(crate) icap_reqmod_retried: bool,
+    pub(crate) icap_reqmod_preview_size: Option<usize>,
+    pub(crate) icap_respmod_preview_size: Option<usize>,
+    pub(crate) icap_reqmod_timing: Option<IcapTransactionTiming>,
+    pub(crate) icap_respmod_timing: Option<IcapTransactionTiming>,
+    pub(crate) icap_bypassed: Option<&'static str>,
 }
 
 impl HttpForwardTaskNotes {
@@ -44,6 +52,12 @@ impl HttpForwardTaskNotes {
             dur_rsp_recv_hdr: Duration::default(),
             dur_rsp_recv_all: Duration::default(),
             retry_new_connection: false,
+            icap_reqmod_retried: false,
+            icap_reqmod_preview_size: None,
+            icap_respmod_preview_size: None,
+            icap_reqmod_timing: None,
+            icap_respmod_timing: None,
+            icap_bypassed: None,
         }
     }
 
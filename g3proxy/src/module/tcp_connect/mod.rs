@@ -11,7 +11,9 @@ mod task;
 
 pub(crate) use error::TcpConnectError;
 pub(crate) use stats::TcpConnectRemoteWrapperStats;
-pub(crate) use task::{TcpConnectTaskConf, TcpConnectTaskNotes, TlsConnectTaskConf};
+pub(crate) use task::{
+    TcpConnectAttempts, TcpConnectTaskConf, TcpConnectTaskNotes, TlsConnectTaskConf,
+};
 
 pub(crate) type TcpConnection = (
     Box<dyn AsyncRead + Unpin + Send + Sync>,
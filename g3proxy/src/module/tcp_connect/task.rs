@@ -3,11 +3,13 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::fmt;
 use std::net::SocketAddr;
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use openssl::ssl::Ssl;
+use tokio::net::TcpStream;
 
 use g3_socket::BindAddr;
 use g3_types::metrics::NodeName;
@@ -51,6 +53,47 @@ impl TcpConnectChainedNotes {
     }
 }
 
+/// The outcome of a single connect attempt to the upstream
+#[derive(Debug, Clone)]
+pub(crate) struct TcpConnectAttempt {
+    pub(crate) addr: SocketAddr,
+    pub(crate) error: Option<String>,
+    pub(crate) duration: Duration,
+}
+
+/// All connect attempts made by the task, in order
+#[derive(Debug, Default, Clone)]
+pub(crate) struct TcpConnectAttempts(Vec<TcpConnectAttempt>);
+
+impl TcpConnectAttempts {
+    pub(crate) fn push(&mut self, addr: SocketAddr, error: Option<String>, duration: Duration) {
+        self.0.push(TcpConnectAttempt {
+            addr,
+            error,
+            duration,
+        });
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl fmt::Display for TcpConnectAttempts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, attempt) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            match &attempt.error {
+                Some(e) => write!(f, "{} failed in {:?}: {e}", attempt.addr, attempt.duration)?,
+                None => write!(f, "{} ok in {:?}", attempt.addr, attempt.duration)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub(crate) struct TcpConnectTaskNotes {
     pub(crate) escaper: NodeName,
@@ -58,6 +101,7 @@ pub(crate) struct TcpConnectTaskNotes {
     pub(crate) next: Option<SocketAddr>,
     pub(crate) tries: usize,
     pub(crate) local: Option<SocketAddr>,
+    pub(crate) rtt: Option<Duration>,
     pub(crate) expire: Option<DateTime<Utc>>,
     pub(crate) egress: Option<EgressInfo>,
     pub(crate) chained: TcpConnectChainedNotes,
@@ -71,9 +115,19 @@ impl TcpConnectTaskNotes {
         self.next = None;
         self.tries = 0;
         self.local = None;
+        self.rtt = None;
         self.expire = None;
         self.egress = None;
         self.chained.reset();
         self.duration = Duration::ZERO;
     }
+
+    /// Record the TCP RTT of the connection to the next peer, if the platform supports it
+    #[cfg(target_os = "linux")]
+    pub(crate) fn record_tcp_rtt(&mut self, stream: &TcpStream) {
+        self.rtt = g3_socket::RawSocket::from(stream).tcp_rtt().ok();
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn record_tcp_rtt(&mut self, _stream: &TcpStream) {}
 }
@@ -0,0 +1,128 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use arc_swap::ArcSwapOption;
+use log::{info, warn};
+
+use g3_io_ext::{UdpRelayCapture, UdpRelayDirection, UdpRelayPacketTap};
+use g3_types::metrics::NodeName;
+use g3_types::net::UpstreamAddr;
+
+const MAX_CAPTURE_PACKETS: usize = 1_000_000;
+const MAX_CAPTURE_BYTES: usize = 1 << 30;
+
+/// Server level handle for the runtime udp relay packet capture
+#[derive(Default)]
+pub(crate) struct UdpRelayCaptureHandle {
+    current: ArcSwapOption<UdpRelayCapture>,
+}
+
+impl UdpRelayCaptureHandle {
+    /// Start a new capture, the pcap file will be written to `dir` when it is full or stopped
+    pub(crate) fn start(
+        self: &Arc<Self>,
+        dir: &Path,
+        server: &NodeName,
+        ratio: f64,
+        max_packets: usize,
+        max_bytes: usize,
+    ) -> anyhow::Result<PathBuf> {
+        if !(ratio > 0.0 && ratio <= 1.0) {
+            return Err(anyhow!("invalid sample ratio {ratio}, should be in (0, 1]"));
+        }
+        if max_packets == 0 || max_packets > MAX_CAPTURE_PACKETS {
+            return Err(anyhow!(
+                "invalid max packets {max_packets}, should be in [1, {MAX_CAPTURE_PACKETS}]"
+            ));
+        }
+
+        if max_bytes == 0 || max_bytes > MAX_CAPTURE_BYTES {
+            return Err(anyhow!(
+                "invalid max bytes {max_bytes}, should be in [1, {MAX_CAPTURE_BYTES}]"
+            ));
+        }
+
+        let capture = Arc::new(UdpRelayCapture::new(ratio, max_packets, max_bytes));
+        let prev = self
+            .current
+            .compare_and_swap(&None::<Arc<_>>, Some(capture.clone()));
+        if prev.is_some() {
+            return Err(anyhow!("udp capture is already running"));
+        }
+
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let path = dir.join(format!("{server}-udp-{}.pcap", time.as_secs()));
+        let handle = self.clone();
+        let file = path.clone();
+        tokio::spawn(async move {
+            capture.wait_done().await;
+            handle.current.rcu(|cur| match cur {
+                Some(c) if Arc::ptr_eq(c, &capture) => None,
+                _ => cur.clone(),
+            });
+            handle.save(&capture, file).await;
+        });
+        Ok(path)
+    }
+
+    pub(crate) fn stop(&self) -> anyhow::Result<()> {
+        let Some(capture) = self.current.load_full() else {
+            return Err(anyhow!("no udp capture is running"));
+        };
+        capture.stop();
+        Ok(())
+    }
+
+    async fn save(&self, capture: &UdpRelayCapture, path: PathBuf) {
+        let mut data = Vec::new();
+        let count = match capture.write_pcap(&mut data) {
+            Ok(n) => n,
+            Err(e) => {
+                warn!("failed to encode udp capture {}: {e}", path.display());
+                return;
+            }
+        };
+        match tokio::fs::write(&path, data).await {
+            Ok(_) => info!(
+                "udp capture saved to {}, {count} packets captured, {} dropped",
+                path.display(),
+                capture.dropped()
+            ),
+            Err(e) => warn!("failed to write udp capture {}: {e}", path.display()),
+        }
+    }
+
+    /// Get the per task packet tap
+    pub(crate) fn task_tap(
+        self: &Arc<Self>,
+        client_addr: SocketAddr,
+    ) -> Arc<dyn UdpRelayPacketTap> {
+        Arc::new(UdpRelayCaptureTap {
+            handle: self.clone(),
+            client_addr,
+        })
+    }
+}
+
+struct UdpRelayCaptureTap {
+    handle: Arc<UdpRelayCaptureHandle>,
+    client_addr: SocketAddr,
+}
+
+impl UdpRelayPacketTap for UdpRelayCaptureTap {
+    fn tap(&self, direction: UdpRelayDirection, ups: &UpstreamAddr, payload: &[u8]) {
+        if let Some(capture) = self.handle.current.load().as_ref() {
+            capture.sample(direction, self.client_addr, ups, payload);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use ahash::AHashMap;
+
+use g3_io_sys::udp::{UdpIcmpErrorKind, UdpSocketError};
+
+/// the count of port unreachable errors before a destination is considered dead
+const PORT_UNREACHABLE_MAX_COUNT: usize = 3;
+/// the max number of destinations to track in a single task
+const MAX_TRACKED_DESTINATIONS: usize = 1024;
+
+#[derive(Default)]
+pub(crate) struct UdpRelayIcmpErrorSnapshot {
+    pub(crate) port_unreachable: u64,
+    pub(crate) host_unreachable: u64,
+    pub(crate) packet_too_big: u64,
+    pub(crate) time_exceeded: u64,
+    pub(crate) other: u64,
+}
+
+/// escaper level stats for the ICMP errors received on udp relay sockets
+#[derive(Default)]
+pub(crate) struct UdpRelayIcmpErrorStats {
+    port_unreachable: AtomicU64,
+    host_unreachable: AtomicU64,
+    packet_too_big: AtomicU64,
+    time_exceeded: AtomicU64,
+    other: AtomicU64,
+}
+
+impl UdpRelayIcmpErrorStats {
+    fn add_error(&self, kind: UdpIcmpErrorKind) {
+        let counter = match kind {
+            UdpIcmpErrorKind::PortUnreachable => &self.port_unreachable,
+            UdpIcmpErrorKind::HostUnreachable => &self.host_unreachable,
+            UdpIcmpErrorKind::PacketTooBig => &self.packet_too_big,
+            UdpIcmpErrorKind::TimeExceeded => &self.time_exceeded,
+            UdpIcmpErrorKind::Other => &self.other,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> UdpRelayIcmpErrorSnapshot {
+        UdpRelayIcmpErrorSnapshot {
+            port_unreachable: self.port_unreachable.load(Ordering::Relaxed),
+            host_unreachable: self.host_unreachable.load(Ordering::Relaxed),
+            packet_too_big: self.packet_too_big.load(Ordering::Relaxed),
+            time_exceeded: self.time_exceeded.load(Ordering::Relaxed),
+            other: self.other.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Task level tracker for the ICMP errors drained from the socket error queue
+pub(crate) struct UdpRelayIcmpErrorTracker {
+    stats: Arc<UdpRelayIcmpErrorStats>,
+    port_unreachable: AHashMap<SocketAddr, usize>,
+}
+
+impl UdpRelayIcmpErrorTracker {
+    pub(crate) fn new(stats: Arc<UdpRelayIcmpErrorStats>) -> Self {
+        UdpRelayIcmpErrorTracker {
+            stats,
+            port_unreachable: AHashMap::new(),
+        }
+    }
+
+    /// Record the error, and return the destination address if it should be considered dead
+    pub(crate) fn record(&mut self, err: &UdpSocketError) -> Option<SocketAddr> {
+        let kind = err.icmp_kind()?;
+        self.stats.add_error(kind);
+
+        if kind != UdpIcmpErrorKind::PortUnreachable {
+            return None;
+        }
+        let dst = err.dst_addr?;
+        if self.port_unreachable.len() >= MAX_TRACKED_DESTINATIONS
+            && !self.port_unreachable.contains_key(&dst)
+        {
+            self.port_unreachable.clear();
+        }
+        let count = self.port_unreachable.entry(dst).or_insert(0);
+        *count += 1;
+        (*count >= PORT_UNREACHABLE_MAX_COUNT).then_some(dst)
+    }
+
+    /// Reset the error count as the destination is alive
+    pub(crate) fn mark_alive(&mut self, addr: SocketAddr) {
+        if !self.port_unreachable.is_empty() {
+            self.port_unreachable.remove(&addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use g3_io_sys::udp::UdpSocketErrorOrigin;
+
+    fn port_unreachable(dst: SocketAddr) -> UdpSocketError {
+        UdpSocketError {
+            dst_addr: Some(dst),
+            offender: Some(dst.ip()),
+            origin: UdpSocketErrorOrigin::Icmp,
+            errno: 111,
+            icmp_type: 3,
+            icmp_code: 3,
+        }
+    }
+
+    #[test]
+    fn dead_destination() {
+        let stats = Arc::new(UdpRelayIcmpErrorStats::default());
+        let mut tracker = UdpRelayIcmpErrorTracker::new(stats.clone());
+
+        let dst = SocketAddr::from_str("192.0.2.1:53").unwrap();
+        let err = port_unreachable(dst);
+        assert!(tracker.record(&err).is_none());
+        assert!(tracker.record(&err).is_none());
+        tracker.mark_alive(dst);
+        assert!(tracker.record(&err).is_none());
+        assert!(tracker.record(&err).is_none());
+        assert_eq!(tracker.record(&err), Some(dst));
+
+        let mut err = port_unreachable(dst);
+        err.icmp_code = 1;
+        assert!(tracker.record(&err).is_none());
+
+        let snap = stats.snapshot();
+        assert_eq!(snap.port_unreachable, 5);
+        assert_eq!(snap.host_unreachable, 1);
+        assert_eq!(snap.time_exceeded, 0);
+    }
+}
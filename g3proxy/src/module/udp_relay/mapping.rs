@@ -0,0 +1,619 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::task::{Context, Poll, Waker, ready};
+use std::time::Instant;
+
+use g3_io_ext::{AsyncUdpRecv, AsyncUdpSend, UdpRelayRemoteError};
+use g3_types::net::UpstreamAddr;
+
+use crate::config::escaper::direct_fixed::UdpRelayMappingConfig;
+
+#[derive(Default)]
+pub(crate) struct UdpRelayMappingSnapshot {
+    pub(crate) alive: u64,
+    pub(crate) evicted_idle: u64,
+    pub(crate) evicted_overflow: u64,
+    pub(crate) dropped_overflow: u64,
+    pub(crate) dropped_unmatched: u64,
+}
+
+/// escaper level stats for all the udp relay mapping tables
+#[derive(Default)]
+pub(crate) struct UdpRelayMappingStats {
+    alive: AtomicUsize,
+    evicted_idle: AtomicU64,
+    evicted_overflow: AtomicU64,
+    dropped_overflow: AtomicU64,
+    dropped_unmatched: AtomicU64,
+}
+
+impl UdpRelayMappingStats {
+    fn try_add_alive(&self, max: usize) -> bool {
+        self.alive
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                if n < max { Some(n + 1) } else { None }
+            })
+            .is_ok()
+    }
+
+    fn del_alive(&self) {
+        self.alive.fetch_sub(1, Ordering::AcqRel);
+    }
+
+    fn add_evicted_idle(&self) {
+        self.evicted_idle.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn add_evicted_overflow(&self) {
+        self.evicted_overflow.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn add_dropped_overflow(&self) {
+        self.dropped_overflow.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn add_dropped_unmatched(&self) {
+        self.dropped_unmatched.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> UdpRelayMappingSnapshot {
+        UdpRelayMappingSnapshot {
+            alive: self.alive.load(Ordering::Relaxed) as u64,
+            evicted_idle: self.evicted_idle.load(Ordering::Relaxed),
+            evicted_overflow: self.evicted_overflow.load(Ordering::Relaxed),
+            dropped_overflow: self.dropped_overflow.load(Ordering::Relaxed),
+            dropped_unmatched: self.dropped_unmatched.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct UdpRelayMappingKey {
+    pub(crate) client: SocketAddr,
+    pub(crate) remote: SocketAddr,
+}
+
+/// create a new relay socket for the remote address, return `(bind, recv, send)`
+pub(crate) type UdpRelayMappingSocketFactory<R, S> =
+    Box<dyn Fn(SocketAddr) -> io::Result<(SocketAddr, R, S)> + Send>;
+
+struct UdpRelayMappingEntry<R, S> {
+    key: UdpRelayMappingKey,
+    bind: SocketAddr,
+    recv: R,
+    send: S,
+    last_active: Instant,
+}
+
+/// A NAT style `(client, remote) -> relay socket` mapping table for a single udp association.
+///
+/// The send path reuses the socket of an existing entry, or creates a new one if not found.
+/// The recv path polls all the sockets, and tags each returning packet with the mapping key.
+/// Only the mapped remote is allowed to reply on each socket, packets from others will be dropped.
+/// If the total cap is reached and no entry can be evicted, the packet to the new remote will be
+/// dropped, so the association will be kept alive.
+pub(crate) struct UdpRelayMappingTable<R, S> {
+    config: UdpRelayMappingConfig,
+    client: SocketAddr,
+    stats: Arc<UdpRelayMappingStats>,
+    factory: UdpRelayMappingSocketFactory<R, S>,
+    entries: Vec<UdpRelayMappingEntry<R, S>>,
+    recv_next: usize,
+    recv_waker: Option<Waker>,
+}
+
+impl<R, S> Drop for UdpRelayMappingTable<R, S> {
+    fn drop(&mut self) {
+        for _ in 0..self.entries.len() {
+            self.stats.del_alive();
+        }
+    }
+}
+
+impl<R, S> UdpRelayMappingTable<R, S>
+where
+    R: AsyncUdpRecv,
+    S: AsyncUdpSend,
+{
+    pub(crate) fn new(
+        config: UdpRelayMappingConfig,
+        client: SocketAddr,
+        stats: Arc<UdpRelayMappingStats>,
+        factory: UdpRelayMappingSocketFactory<R, S>,
+    ) -> Self {
+        UdpRelayMappingTable {
+            config,
+            client,
+            stats,
+            factory,
+            entries: Vec::with_capacity(config.max_entries.min(16)),
+            recv_next: 0,
+            recv_waker: None,
+        }
+    }
+
+    fn remove_entry(&mut self, index: usize) {
+        self.entries.swap_remove(index);
+        self.stats.del_alive();
+        if self.recv_next >= self.entries.len() {
+            self.recv_next = 0;
+        }
+    }
+
+    /// remove all entries that have been idle for longer than the configured lifetime
+    pub(crate) fn expire_idle(&mut self, now: Instant) {
+        let mut i = 0;
+        while i < self.entries.len() {
+            let idle = now.saturating_duration_since(self.entries[i].last_active);
+            if idle >= self.config.idle_lifetime {
+                self.remove_entry(i);
+                self.stats.add_evicted_idle();
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn evict_oldest(&mut self) -> bool {
+        let Some((index, _)) = self
+            .entries
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, e)| e.last_active)
+        else {
+            return false;
+        };
+        self.remove_entry(index);
+        self.stats.add_evicted_overflow();
+        true
+    }
+
+    /// return `None` if the total cap is reached and there is no entry to evict
+    fn add_entry(
+        &mut self,
+        remote: SocketAddr,
+        now: Instant,
+    ) -> Result<Option<usize>, UdpRelayRemoteError> {
+        if self.entries.len() >= self.config.max_entries {
+            self.evict_oldest();
+        }
+        while !self.stats.try_add_alive(self.config.max_total_entries) {
+            if !self.evict_oldest() {
+                return Ok(None);
+            }
+        }
+
+        let (bind, recv, send) = match (self.factory)(remote) {
+            Ok(v) => v,
+            Err(e) => {
+                self.stats.del_alive();
+                let bind = match remote {
+                    SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+                    SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+                };
+                return Err(UdpRelayRemoteError::SendFailed(bind, remote, e));
+            }
+        };
+        self.entries.push(UdpRelayMappingEntry {
+            key: UdpRelayMappingKey {
+                client: self.client,
+                remote,
+            },
+            bind,
+            recv,
+            send,
+            last_active: now,
+        });
+        // let the recv side poll the new socket
+        if let Some(waker) = self.recv_waker.take() {
+            waker.wake();
+        }
+        Ok(Some(self.entries.len() - 1))
+    }
+
+    pub(crate) fn poll_send_to(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        to: SocketAddr,
+    ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+        let now = Instant::now();
+        self.expire_idle(now);
+
+        let index = match self.entries.iter().position(|e| e.key.remote == to) {
+            Some(index) => index,
+            None => match self.add_entry(to, now)? {
+                Some(index) => index,
+                None => {
+                    // drop this packet only, the client may retry later
+                    self.stats.add_dropped_overflow();
+                    return Poll::Ready(Ok(buf.len()));
+                }
+            },
+        };
+        let entry = &mut self.entries[index];
+        let nw = ready!(entry.send.poll_send_to(cx, buf, to))
+            .map_err(|e| UdpRelayRemoteError::SendFailed(entry.bind, to, e))?;
+        if nw == 0 {
+            return Poll::Ready(Err(UdpRelayRemoteError::SendFailed(
+                entry.bind,
+                to,
+                io::Error::new(io::ErrorKind::WriteZero, "write zero byte into sender"),
+            )));
+        }
+        entry.last_active = now;
+        Poll::Ready(Ok(nw))
+    }
+
+    /// return `(nr, key)`, the source address of the packet is always `key.remote`
+    pub(crate) fn poll_recv_from(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<(usize, UdpRelayMappingKey), UdpRelayRemoteError>> {
+        let now = Instant::now();
+        self.expire_idle(now);
+
+        let total = self.entries.len();
+        for i in 0..total {
+            let index = (self.recv_next + i) % total;
+            let entry = &mut self.entries[index];
+            loop {
+                match entry.recv.poll_recv_from(cx, buf) {
+                    Poll::Ready(Ok((nr, from))) => {
+                        if from != entry.key.remote {
+                            // not a reply to this entry, and should not keep it alive
+                            self.stats.add_dropped_unmatched();
+                            continue;
+                        }
+                        entry.last_active = now;
+                        self.recv_next = (index + 1) % total;
+                        return Poll::Ready(Ok((nr, entry.key)));
+                    }
+                    Poll::Ready(Err(e)) => {
+                        return Poll::Ready(Err(UdpRelayRemoteError::RecvFailed(entry.bind, e)));
+                    }
+                    Poll::Pending => break,
+                }
+            }
+        }
+
+        self.recv_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+struct UdpRelayMappingMark {
+    remote: UpstreamAddr,
+    last_active: Instant,
+}
+
+/// The socket-less variant of [UdpRelayMappingTable] for the escapers that relay all packets
+/// through a single upstream association, only the mapping entries are tracked.
+///
+/// The same per association and total cap will be applied to the send path, packets to new remotes
+/// will be dropped if there is no entry to evict. The returning packets won't be filtered, as the
+/// upstream proxy may reply with the resolved address rather than the domain we sent to.
+pub(crate) struct UdpRelayMappingTracker {
+    config: UdpRelayMappingConfig,
+    stats: Arc<UdpRelayMappingStats>,
+    entries: Vec<UdpRelayMappingMark>,
+}
+
+impl Drop for UdpRelayMappingTracker {
+    fn drop(&mut self) {
+        for _ in 0..self.entries.len() {
+            self.stats.del_alive();
+        }
+    }
+}
+
+impl UdpRelayMappingTracker {
+    pub(crate) fn new(config: UdpRelayMappingConfig, stats: Arc<UdpRelayMappingStats>) -> Self {
+        UdpRelayMappingTracker {
+            config,
+            stats,
+            entries: Vec::with_capacity(config.max_entries.min(16)),
+        }
+    }
+
+    fn expire_idle(&mut self, now: Instant) {
+        let idle_lifetime = self.config.idle_lifetime;
+        let stats = &self.stats;
+        self.entries.retain(|e| {
+            if now.saturating_duration_since(e.last_active) >= idle_lifetime {
+                stats.del_alive();
+                stats.add_evicted_idle();
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    fn evict_oldest(&mut self) -> bool {
+        let Some((index, _)) = self
+            .entries
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, e)| e.last_active)
+        else {
+            return false;
+        };
+        self.entries.swap_remove(index);
+        self.stats.del_alive();
+        self.stats.add_evicted_overflow();
+        true
+    }
+
+    /// return `false` if the packet should be dropped
+    pub(crate) fn check_send(&mut self, to: &UpstreamAddr) -> bool {
+        let now = Instant::now();
+        self.expire_idle(now);
+
+        if let Some(entry) = self.entries.iter_mut().find(|e| &e.remote == to) {
+            entry.last_active = now;
+            return true;
+        }
+
+        if self.entries.len() >= self.config.max_entries {
+            self.evict_oldest();
+        }
+        while !self.stats.try_add_alive(self.config.max_total_entries) {
+            if !self.evict_oldest() {
+                self.stats.add_dropped_overflow();
+                return false;
+            }
+        }
+        self.entries.push(UdpRelayMappingMark {
+            remote: to.clone(),
+            last_active: now,
+        });
+        true
+    }
+
+    /// keep the entry alive if the returning packet is from a known remote
+    pub(crate) fn mark_received(&mut self, from: &UpstreamAddr) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| &e.remote == from) {
+            entry.last_active = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use std::time::Duration;
+
+    use tokio::net::UdpSocket;
+
+    use g3_io_ext::{UdpRecvHalf, UdpSendHalf};
+
+    type TestTable = UdpRelayMappingTable<UdpRecvHalf, UdpSendHalf>;
+
+    const CLIENT_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1080);
+
+    fn new_table(config: UdpRelayMappingConfig, stats: &Arc<UdpRelayMappingStats>) -> TestTable {
+        let factory: UdpRelayMappingSocketFactory<UdpRecvHalf, UdpSendHalf> = Box::new(|_| {
+            let socket = std::net::UdpSocket::bind("127.0.0.1:0")?;
+            socket.set_nonblocking(true)?;
+            let bind = socket.local_addr()?;
+            let (recv, send) = g3_io_ext::split_udp(UdpSocket::from_std(socket)?);
+            Ok((bind, recv, send))
+        });
+        UdpRelayMappingTable::new(config, CLIENT_ADDR, stats.clone(), factory)
+    }
+
+    async fn spawn_echo_server() -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            while let Ok((nr, peer)) = socket.recv_from(&mut buf).await {
+                let _ = socket.send_to(&buf[..nr], peer).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn return_routing() {
+        let stats = Arc::new(UdpRelayMappingStats::default());
+        let mut table = new_table(UdpRelayMappingConfig::default(), &stats);
+
+        let remote_a = spawn_echo_server().await;
+        let remote_b = spawn_echo_server().await;
+
+        poll_fn(|cx| table.poll_send_to(cx, b"to a", remote_a))
+            .await
+            .unwrap();
+        poll_fn(|cx| table.poll_send_to(cx, b"to b", remote_b))
+            .await
+            .unwrap();
+        // existing sockets should be reused
+        poll_fn(|cx| table.poll_send_to(cx, b"to a", remote_a))
+            .await
+            .unwrap();
+        assert_eq!(table.entries.len(), 2);
+        assert_eq!(stats.snapshot().alive, 2);
+
+        let mut buf = [0u8; 64];
+        for _ in 0..3 {
+            let (nr, key) = poll_fn(|cx| table.poll_recv_from(cx, &mut buf))
+                .await
+                .unwrap();
+            assert_eq!(key.client, CLIENT_ADDR);
+            let from = key.remote;
+            if from == remote_a {
+                assert_eq!(&buf[..nr], b"to a");
+            } else {
+                assert_eq!(from, remote_b);
+                assert_eq!(&buf[..nr], b"to b");
+            }
+        }
+
+        drop(table);
+        assert_eq!(stats.snapshot().alive, 0);
+    }
+
+    #[tokio::test]
+    async fn expire_idle() {
+        let stats = Arc::new(UdpRelayMappingStats::default());
+        let config = UdpRelayMappingConfig {
+            idle_lifetime: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let mut table = new_table(config, &stats);
+
+        let remote = spawn_echo_server().await;
+        poll_fn(|cx| table.poll_send_to(cx, b"ping", remote))
+            .await
+            .unwrap();
+
+        table.expire_idle(Instant::now() + Duration::from_secs(5));
+        assert_eq!(table.entries.len(), 1);
+        table.expire_idle(Instant::now() + Duration::from_secs(10));
+        assert_eq!(table.entries.len(), 0);
+
+        let snap = stats.snapshot();
+        assert_eq!(snap.alive, 0);
+        assert_eq!(snap.evicted_idle, 1);
+        assert_eq!(snap.evicted_overflow, 0);
+    }
+
+    #[tokio::test]
+    async fn cap_enforcement() {
+        let stats = Arc::new(UdpRelayMappingStats::default());
+        let config = UdpRelayMappingConfig {
+            max_entries: 2,
+            max_total_entries: 3,
+            ..Default::default()
+        };
+        let mut table1 = new_table(config, &stats);
+        let mut table2 = new_table(config, &stats);
+
+        let remote_a = spawn_echo_server().await;
+        let remote_b = spawn_echo_server().await;
+        let remote_c = spawn_echo_server().await;
+
+        // per association cap, the oldest one should be evicted
+        for remote in [remote_a, remote_b, remote_c] {
+            poll_fn(|cx| table1.poll_send_to(cx, b"ping", remote))
+                .await
+                .unwrap();
+        }
+        assert_eq!(table1.entries.len(), 2);
+        assert!(table1.entries.iter().all(|e| e.key.remote != remote_a));
+        assert_eq!(stats.snapshot().evicted_overflow, 1);
+
+        // total cap, evict from the table itself
+        for remote in [remote_a, remote_b] {
+            poll_fn(|cx| table2.poll_send_to(cx, b"ping", remote))
+                .await
+                .unwrap();
+        }
+        assert_eq!(table2.entries.len(), 1);
+        let snap = stats.snapshot();
+        assert_eq!(snap.alive, 3);
+        assert_eq!(snap.evicted_overflow, 2);
+
+        // total cap, no entry to evict, only the packet should be dropped
+        let mut table3 = new_table(config, &stats);
+        let nw = poll_fn(|cx| table3.poll_send_to(cx, b"ping", remote_a))
+            .await
+            .unwrap();
+        assert_eq!(nw, 4);
+        assert!(table3.entries.is_empty());
+        let snap = stats.snapshot();
+        assert_eq!(snap.alive, 3);
+        assert_eq!(snap.dropped_overflow, 1);
+
+        // and the table should still be usable after some entries are released
+        drop(table1);
+        poll_fn(|cx| table3.poll_send_to(cx, b"ping", remote_a))
+            .await
+            .unwrap();
+        assert_eq!(table3.entries.len(), 1);
+        assert_eq!(stats.snapshot().dropped_overflow, 1);
+    }
+
+    #[tokio::test]
+    async fn drop_unmatched() {
+        let stats = Arc::new(UdpRelayMappingStats::default());
+        let mut table = new_table(UdpRelayMappingConfig::default(), &stats);
+
+        let remote = spawn_echo_server().await;
+        let mut buf = [0u8; 64];
+        poll_fn(|cx| table.poll_send_to(cx, b"ping", remote))
+            .await
+            .unwrap();
+        poll_fn(|cx| table.poll_recv_from(cx, &mut buf))
+            .await
+            .unwrap();
+        let bind = table.entries[0].bind;
+
+        // a packet from some other address should not be routed back
+        let other = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        other.send_to(b"spoof", bind).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        poll_fn(|cx| table.poll_send_to(cx, b"pong", remote))
+            .await
+            .unwrap();
+
+        let (nr, key) = poll_fn(|cx| table.poll_recv_from(cx, &mut buf))
+            .await
+            .unwrap();
+        assert_eq!(key.remote, remote);
+        assert_eq!(&buf[..nr], b"pong");
+        assert_eq!(stats.snapshot().dropped_unmatched, 1);
+    }
+
+    #[test]
+    fn tracker_cap_enforcement() {
+        let stats = Arc::new(UdpRelayMappingStats::default());
+        let config = UdpRelayMappingConfig {
+            idle_lifetime: Duration::from_secs(10),
+            max_entries: 2,
+            max_total_entries: 2,
+        };
+        let remote_a = UpstreamAddr::from_host_str_and_port("a.example.net", 53).unwrap();
+        let remote_b = UpstreamAddr::from_host_str_and_port("b.example.net", 53).unwrap();
+        let remote_c = UpstreamAddr::from_host_str_and_port("c.example.net", 53).unwrap();
+
+        let mut tracker1 = UdpRelayMappingTracker::new(config, stats.clone());
+        assert!(tracker1.check_send(&remote_a));
+        assert!(tracker1.check_send(&remote_b));
+        assert!(tracker1.check_send(&remote_a));
+        assert_eq!(stats.snapshot().alive, 2);
+
+        // total cap, no entry to evict
+        let mut tracker2 = UdpRelayMappingTracker::new(config, stats.clone());
+        assert!(!tracker2.check_send(&remote_c));
+        let snap = stats.snapshot();
+        assert_eq!(snap.alive, 2);
+        assert_eq!(snap.dropped_overflow, 1);
+
+        // per association cap, the oldest one should be evicted
+        assert!(tracker1.check_send(&remote_c));
+        assert!(tracker1.entries.iter().all(|e| e.remote != remote_b));
+        assert_eq!(stats.snapshot().evicted_overflow, 1);
+
+        tracker1.expire_idle(Instant::now() + Duration::from_secs(10));
+        assert!(tracker1.entries.is_empty());
+        let snap = stats.snapshot();
+        assert_eq!(snap.alive, 0);
+        assert_eq!(snap.evicted_idle, 2);
+
+        assert!(tracker2.check_send(&remote_c));
+        drop(tracker2);
+        assert_eq!(stats.snapshot().alive, 0);
+    }
+}
@@ -7,15 +7,28 @@ use slog::Logger;
 
 use g3_io_ext::{UdpRelayRemoteRecv, UdpRelayRemoteSend};
 
+mod capture;
 mod error;
+mod icmp;
+mod mapping;
 mod stats;
 mod task;
+mod verify;
 
+pub(crate) use capture::UdpRelayCaptureHandle;
 pub(crate) use error::UdpRelaySetupError;
+pub(crate) use icmp::{
+    UdpRelayIcmpErrorSnapshot, UdpRelayIcmpErrorStats, UdpRelayIcmpErrorTracker,
+};
+pub(crate) use mapping::{
+    UdpRelayMappingSnapshot, UdpRelayMappingSocketFactory, UdpRelayMappingStats,
+    UdpRelayMappingTable, UdpRelayMappingTracker,
+};
 pub(crate) use stats::{
     ArcUdpRelayTaskRemoteStats, UdpRelayRemoteWrapperStats, UdpRelayTaskRemoteStats,
 };
 pub(crate) use task::{UdpRelayTaskConf, UdpRelayTaskNotes};
+pub(crate) use verify::{UdpDestVerifier, UdpDestVerifyGate, UdpDestVerifyStats};
 
 pub(crate) type UdpRelaySetupResult = Result<
     (
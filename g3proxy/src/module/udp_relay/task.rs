@@ -3,6 +3,8 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::net::SocketAddr;
+
 use chrono::{DateTime, Utc};
 
 use g3_types::metrics::NodeName;
@@ -10,6 +12,7 @@ use g3_types::net::{SocketBufferConfig, UpstreamAddr};
 
 pub(crate) struct UdpRelayTaskConf<'a> {
     pub(crate) initial_peer: &'a UpstreamAddr,
+    pub(crate) client_addr: SocketAddr,
     pub(crate) sock_buf: SocketBufferConfig,
 }
 
@@ -0,0 +1,415 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::VecDeque;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use lru::LruCache;
+use rustc_hash::FxHashMap;
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+
+use g3_types::net::{Host, UpstreamAddr};
+
+use crate::config::server::{UdpDestVerifyConfig, UdpDestVerifyMode};
+
+/// the max number of destinations that can be verified at the same time in a single task
+const MAX_PENDING_DESTINATIONS: usize = 64;
+
+#[async_trait]
+pub(crate) trait UdpDestPtrResolver {
+    async fn lookup_host_name(&self, ip: IpAddr) -> io::Result<String>;
+}
+
+struct SystemPtrResolver;
+
+#[async_trait]
+impl UdpDestPtrResolver for SystemPtrResolver {
+    #[cfg(unix)]
+    async fn lookup_host_name(&self, ip: IpAddr) -> io::Result<String> {
+        tokio::task::spawn_blocking(move || g3_socket::util::lookup_host_name(ip))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    #[cfg(not(unix))]
+    async fn lookup_host_name(&self, _ip: IpAddr) -> io::Result<String> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "reverse lookup is not supported on this platform",
+        ))
+    }
+}
+
+pub(crate) trait UdpDestVerifyStats {
+    fn add_verify_dropped(&self);
+}
+
+pub(crate) type ArcUdpDestVerifyStats = Arc<dyn UdpDestVerifyStats + Send + Sync>;
+
+struct VerifyCacheEntry {
+    allowed: bool,
+    expire: Instant,
+}
+
+/// Server level verifier for new udp relay destinations, the results are shared by all tasks
+pub(crate) struct UdpDestVerifier {
+    config: UdpDestVerifyConfig,
+    ptr_resolver: Arc<dyn UdpDestPtrResolver + Send + Sync>,
+    cache: Mutex<LruCache<UpstreamAddr, VerifyCacheEntry>>,
+}
+
+impl UdpDestVerifier {
+    pub(crate) fn new(config: &UdpDestVerifyConfig) -> Self {
+        UdpDestVerifier::with_ptr_resolver(config, Arc::new(SystemPtrResolver))
+    }
+
+    fn with_ptr_resolver(
+        config: &UdpDestVerifyConfig,
+        ptr_resolver: Arc<dyn UdpDestPtrResolver + Send + Sync>,
+    ) -> Self {
+        UdpDestVerifier {
+            config: config.clone(),
+            ptr_resolver,
+            cache: Mutex::new(LruCache::new(config.cache_size)),
+        }
+    }
+
+    pub(crate) fn match_config(&self, config: &UdpDestVerifyConfig) -> bool {
+        self.config.eq(config)
+    }
+
+    /// Get the cached result, return None if not verified yet or the result has expired
+    fn cached(&self, dest: &UpstreamAddr) -> Option<bool> {
+        let mut cache = self.cache.lock().unwrap();
+        let entry = cache.get(dest)?;
+        if entry.expire > Instant::now() {
+            return Some(entry.allowed);
+        }
+        cache.pop(dest);
+        None
+    }
+
+    fn save(&self, dest: UpstreamAddr, allowed: bool) {
+        // failed destinations are blocked until the cached result expires
+        let ttl = if allowed {
+            self.config.cache_ttl
+        } else {
+            self.config.block_duration
+        };
+        let entry = VerifyCacheEntry {
+            allowed,
+            expire: Instant::now() + ttl,
+        };
+        self.cache.lock().unwrap().put(dest, entry);
+    }
+
+    fn spawn_verify(self: &Arc<Self>, dest: UpstreamAddr) -> oneshot::Receiver<bool> {
+        let (sender, receiver) = oneshot::channel();
+        let verifier = self.clone();
+        tokio::spawn(async move {
+            let allowed = verifier.verify(&dest).await;
+            verifier.save(dest, allowed);
+            let _ = sender.send(allowed);
+        });
+        receiver
+    }
+
+    async fn verify(&self, dest: &UpstreamAddr) -> bool {
+        match self.config.mode {
+            UdpDestVerifyMode::None => true,
+            UdpDestVerifyMode::DnsPtrAllowlist => match dest.host() {
+                Host::Ip(ip) => match self.ptr_resolver.lookup_host_name(*ip).await {
+                    Ok(name) => self.config.ptr_name_allowed(&name),
+                    Err(_) => false,
+                },
+                Host::Domain(domain) => self.config.ptr_name_allowed(domain),
+            },
+            UdpDestVerifyMode::TcpProbe => {
+                let connect = async {
+                    match dest.host() {
+                        Host::Ip(ip) => TcpStream::connect(SocketAddr::new(*ip, dest.port())).await,
+                        Host::Domain(domain) => TcpStream::connect((&**domain, dest.port())).await,
+                    }
+                };
+                matches!(
+                    tokio::time::timeout(self.config.probe_timeout, connect).await,
+                    Ok(Ok(_))
+                )
+            }
+        }
+    }
+}
+
+struct PendingDest {
+    packets: VecDeque<Vec<u8>>,
+    result: oneshot::Receiver<bool>,
+}
+
+/// Task level gate, which holds the packets to new destinations until they are verified
+pub(crate) struct UdpDestVerifyGate {
+    verifier: Arc<UdpDestVerifier>,
+    stats: ArcUdpDestVerifyStats,
+    pending: FxHashMap<UpstreamAddr, PendingDest>,
+    released: VecDeque<(UpstreamAddr, Vec<u8>)>,
+}
+
+impl UdpDestVerifyGate {
+    pub(crate) fn new(verifier: Arc<UdpDestVerifier>, stats: ArcUdpDestVerifyStats) -> Self {
+        UdpDestVerifyGate {
+            verifier,
+            stats,
+            pending: FxHashMap::default(),
+            released: VecDeque::new(),
+        }
+    }
+
+    /// Return true if the packet can be sent out now.
+    ///
+    /// Packets to unverified destinations will be held until the verification is done, and will
+    /// be dropped if the hold queue is full or the destination is blocked.
+    pub(crate) fn check_packet(&mut self, payload: &[u8], dest: &UpstreamAddr) -> bool {
+        let queue_size = self.verifier.config.queue_size;
+        if let Some(p) = self.pending.get_mut(dest) {
+            hold_packet(&mut p.packets, payload, queue_size, &self.stats);
+            return false;
+        }
+
+        match self.verifier.cached(dest) {
+            Some(true) => true,
+            Some(false) => {
+                self.stats.add_verify_dropped();
+                false
+            }
+            None => {
+                if self.pending.len() >= MAX_PENDING_DESTINATIONS {
+                    self.stats.add_verify_dropped();
+                    return false;
+                }
+                let mut packets = VecDeque::new();
+                hold_packet(&mut packets, payload, queue_size, &self.stats);
+                let result = self.verifier.spawn_verify(dest.clone());
+                self.pending
+                    .insert(dest.clone(), PendingDest { packets, result });
+                false
+            }
+        }
+    }
+
+    /// Poll the held packets that can be sent out now, in the order they were received
+    pub(crate) fn poll_release(&mut self, cx: &mut Context<'_>) -> Poll<(UpstreamAddr, Vec<u8>)> {
+        if self.released.is_empty() && !self.pending.is_empty() {
+            let stats = &self.stats;
+            let released = &mut self.released;
+            self.pending
+                .retain(|dest, p| match Pin::new(&mut p.result).poll(cx) {
+                    Poll::Pending => true,
+                    Poll::Ready(Ok(true)) => {
+                        for packet in p.packets.drain(..) {
+                            released.push_back((dest.clone(), packet));
+                        }
+                        false
+                    }
+                    Poll::Ready(_) => {
+                        for _ in p.packets.drain(..) {
+                            stats.add_verify_dropped();
+                        }
+                        false
+                    }
+                });
+        }
+
+        match self.released.pop_front() {
+            Some(r) => Poll::Ready(r),
+            None => Poll::Pending,
+        }
+    }
+}
+
+fn hold_packet(
+    packets: &mut VecDeque<Vec<u8>>,
+    payload: &[u8],
+    queue_size: usize,
+    stats: &ArcUdpDestVerifyStats,
+) {
+    if packets.len() < queue_size {
+        packets.push_back(payload.to_vec());
+    } else {
+        stats.add_verify_dropped();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use std::net::Ipv4Addr;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    use tokio::net::TcpListener;
+
+    struct MockPtrResolver {
+        name: &'static str,
+        queried: AtomicU64,
+    }
+
+    #[async_trait]
+    impl UdpDestPtrResolver for MockPtrResolver {
+        async fn lookup_host_name(&self, _ip: IpAddr) -> io::Result<String> {
+            self.queried.fetch_add(1, Ordering::Relaxed);
+            Ok(self.name.to_string())
+        }
+    }
+
+    #[derive(Default)]
+    struct MockStats {
+        dropped: AtomicU64,
+    }
+
+    impl UdpDestVerifyStats for MockStats {
+        fn add_verify_dropped(&self) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn ptr_verifier(
+        name: &'static str,
+        queue_size: usize,
+    ) -> (Arc<MockPtrResolver>, Arc<UdpDestVerifier>) {
+        let config = UdpDestVerifyConfig {
+            mode: UdpDestVerifyMode::DnsPtrAllowlist,
+            ptr_allowed_suffixes: vec!["example.net".to_string()],
+            queue_size,
+            ..Default::default()
+        };
+        let resolver = Arc::new(MockPtrResolver {
+            name,
+            queried: AtomicU64::new(0),
+        });
+        let verifier = UdpDestVerifier::with_ptr_resolver(&config, resolver.clone());
+        (resolver, Arc::new(verifier))
+    }
+
+    fn new_gate(verifier: &Arc<UdpDestVerifier>) -> (Arc<MockStats>, UdpDestVerifyGate) {
+        let stats = Arc::new(MockStats::default());
+        let gate = UdpDestVerifyGate::new(verifier.clone(), stats.clone());
+        (stats, gate)
+    }
+
+    fn dest(port: u16) -> UpstreamAddr {
+        UpstreamAddr::from_ip_and_port(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+    }
+
+    async fn release(gate: &mut UdpDestVerifyGate) -> Option<(UpstreamAddr, Vec<u8>)> {
+        tokio::time::timeout(
+            Duration::from_millis(100),
+            poll_fn(|cx| gate.poll_release(cx)),
+        )
+        .await
+        .ok()
+    }
+
+    #[tokio::test]
+    async fn ptr_allow() {
+        let (resolver, verifier) = ptr_verifier("dns.example.net", 4);
+        let (stats, mut gate) = new_gate(&verifier);
+        let dest = dest(53);
+
+        assert!(!gate.check_packet(b"1", &dest));
+        assert_eq!(
+            release(&mut gate).await.unwrap(),
+            (dest.clone(), b"1".to_vec())
+        );
+        assert!(release(&mut gate).await.is_none());
+
+        // cache hit in the same task and in other tasks
+        assert!(gate.check_packet(b"2", &dest));
+        let (_, mut gate2) = new_gate(&verifier);
+        assert!(gate2.check_packet(b"3", &dest));
+        assert_eq!(resolver.queried.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.dropped.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn ptr_deny() {
+        let (resolver, verifier) = ptr_verifier("victim.example.com", 4);
+        let (stats, mut gate) = new_gate(&verifier);
+        let dest = dest(53);
+
+        assert!(!gate.check_packet(b"1", &dest));
+        assert!(!gate.check_packet(b"2", &dest));
+        assert!(release(&mut gate).await.is_none());
+        assert_eq!(stats.dropped.load(Ordering::Relaxed), 2);
+
+        // blocked without verifying again
+        assert!(!gate.check_packet(b"3", &dest));
+        assert_eq!(stats.dropped.load(Ordering::Relaxed), 3);
+        assert_eq!(resolver.queried.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn queue_overflow() {
+        let (_, verifier) = ptr_verifier("dns.example.net", 2);
+        let (stats, mut gate) = new_gate(&verifier);
+        let dest1 = dest(53);
+        let dest2 = dest(54);
+
+        for data in [b"1", b"2", b"3", b"4"] {
+            assert!(!gate.check_packet(data, &dest1));
+        }
+        assert!(!gate.check_packet(b"5", &dest2));
+        assert_eq!(stats.dropped.load(Ordering::Relaxed), 2);
+
+        let mut released = Vec::new();
+        while let Some((ups, data)) = release(&mut gate).await {
+            released.push((ups.port(), data));
+        }
+        released.sort();
+        assert_eq!(
+            released,
+            [
+                (53, b"1".to_vec()),
+                (53, b"2".to_vec()),
+                (54, b"5".to_vec())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tcp_probe() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open_port = listener.local_addr().unwrap().port();
+        let closed_port = {
+            let l = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            l.local_addr().unwrap().port()
+        };
+
+        let config = UdpDestVerifyConfig {
+            mode: UdpDestVerifyMode::TcpProbe,
+            ..Default::default()
+        };
+        let verifier = Arc::new(UdpDestVerifier::new(&config));
+        let (stats, mut gate) = new_gate(&verifier);
+
+        assert!(!gate.check_packet(b"1", &dest(open_port)));
+        assert!(!gate.check_packet(b"2", &dest(closed_port)));
+        let (ups, data) = release(&mut gate).await.unwrap();
+        assert_eq!(ups.port(), open_port);
+        assert_eq!(data, b"1");
+        assert!(release(&mut gate).await.is_none());
+        assert_eq!(stats.dropped.load(Ordering::Relaxed), 1);
+
+        assert!(gate.check_packet(b"3", &dest(open_port)));
+        assert!(!gate.check_packet(b"4", &dest(closed_port)));
+        drop(listener);
+    }
+}
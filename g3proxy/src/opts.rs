@@ -18,6 +18,8 @@ const ARGS_COMPLETION: &str = "completion";
 const ARGS_VERSION: &str = "version";
 const ARGS_VERIFY_PANIC: &str = "verify-panic";
 const ARGS_DEP_GRAPH: &str = "dep-graph";
+const ARGS_DRY_RUN: &str = "dry-run";
+const ARGS_DUMP_CONFIG_SCHEMA: &str = "dump-config-schema";
 const ARGS_GROUP_NAME: &str = "group-name";
 const ARGS_CONFIG_FILE: &str = "config-file";
 const ARGS_CONTROL_DIR: &str = "control-dir";
@@ -34,6 +36,8 @@ pub struct ProcArgs {
     pub output_graphviz_graph: bool,
     pub output_mermaid_graph: bool,
     pub output_plantuml_graph: bool,
+    pub dry_run: bool,
+    pub dump_config_schema: bool,
 }
 
 impl Default for ProcArgs {
@@ -43,6 +47,8 @@ impl Default for ProcArgs {
             output_graphviz_graph: false,
             output_mermaid_graph: false,
             output_plantuml_graph: false,
+            dry_run: false,
+            dump_config_schema: false,
         }
     }
 }
@@ -83,6 +89,18 @@ fn build_cli_args() -> Command {
                 .value_parser([DEP_GRAPH_GRAPHVIZ, DEP_GRAPH_MERMAID, DEP_GRAPH_PLANTUML])
                 .default_missing_value(DEP_GRAPH_GRAPHVIZ),
         )
+        .arg(
+            Arg::new(ARGS_DRY_RUN)
+                .help("Check the config file and report the action for each server")
+                .action(ArgAction::SetTrue)
+                .long("dry-run"),
+        )
+        .arg(
+            Arg::new(ARGS_DUMP_CONFIG_SCHEMA)
+                .help("Dump the json schema of the supported config keys")
+                .action(ArgAction::SetTrue)
+                .long("dump-config-schema"),
+        )
         .arg(
             Arg::new(ARGS_GROUP_NAME)
                 .help("Group name")
@@ -110,7 +128,12 @@ fn build_cli_args() -> Command {
                 .value_name("CONFIG FILE")
                 .value_hint(ValueHint::FilePath)
                 .value_parser(value_parser!(PathBuf))
-                .required_unless_present_any([ARGS_COMPLETION, ARGS_VERSION, ARGS_VERIFY_PANIC])
+                .required_unless_present_any([
+                    ARGS_COMPLETION,
+                    ARGS_VERSION,
+                    ARGS_VERIFY_PANIC,
+                    ARGS_DUMP_CONFIG_SCHEMA,
+                ])
                 .short('c')
                 .long("config-file"),
         )
@@ -147,6 +170,13 @@ pub fn parse_clap() -> anyhow::Result<Option<ProcArgs>> {
             }
         }
     }
+    if args.get_flag(ARGS_DRY_RUN) {
+        proc_args.dry_run = true;
+    }
+    if args.get_flag(ARGS_DUMP_CONFIG_SCHEMA) {
+        proc_args.dump_config_schema = true;
+        return Ok(Some(proc_args));
+    }
     if let Some(config_file) = args.get_one::<PathBuf>(ARGS_CONFIG_FILE) {
         g3_daemon::opts::validate_and_set_config_file(config_file, crate::build::PKG_NAME)
             .context(format!(
@@ -15,10 +15,10 @@ use crate::config::resolver::AnyResolverConfig;
 #[macro_use]
 mod handle;
 pub(crate) use handle::{
-    ArcIntegratedResolverHandle, ArriveFirstResolveJob, HappyEyeballsResolveJob,
-    IntegratedResolverHandle,
+    ArcIntegratedResolverHandle, ArriveFirstResolveJob, BoxLoggedResolveJob,
+    HappyEyeballsResolveJob, IntegratedResolverHandle,
 };
-use handle::{BoxLoggedResolveJob, ErrorResolveJob, LoggedResolveJob};
+use handle::{ErrorResolveJob, LoggedResolveJob};
 
 mod stats;
 pub(crate) use stats::ResolverStats;
@@ -0,0 +1,302 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use tokio::io::AsyncWrite;
+
+use g3_daemon::server::ClientConnectionInfo;
+use g3_socks::{SocksVersion, v4a, v5};
+
+use super::ServerTaskError;
+use crate::config::server::{ClientErrorAction, ClientErrorActionMap, ClientErrorClass};
+
+/// Get the client error action for failures after the client stream has been established
+pub(crate) fn established_client_error_action(
+    actions: &ClientErrorActionMap,
+    e: &ServerTaskError,
+) -> Option<ClientErrorAction> {
+    actions.get_established(e.client_error_class()?)
+}
+
+/// Prepare the client connection for the client error action
+///
+/// This should be called before the client stream is dropped.
+pub(crate) fn prepare_client_error_action(
+    action: ClientErrorAction,
+    cc_info: &ClientConnectionInfo,
+) {
+    if action == ClientErrorAction::Reset {
+        cc_info.tcp_sock_reset_on_close();
+    }
+}
+
+/// Reply to the socks client with the client error action set for the failure class,
+/// or with the default reply if not set
+///
+/// The applied client error action will be returned.
+pub(crate) async fn reply_socks_client_error<W>(
+    actions: &ClientErrorActionMap,
+    class: Option<ClientErrorClass>,
+    socks_version: &SocksVersion,
+    v5_reply: v5::Socks5Reply,
+    cc_info: &ClientConnectionInfo,
+    clt_w: &mut W,
+) -> Option<ClientErrorAction>
+where
+    W: AsyncWrite + Unpin,
+{
+    let action = class.and_then(|class| actions.get(class));
+    let v5_reply = match action {
+        Some(ClientErrorAction::SocksReply(code)) => v5::Socks5Reply::failure(code),
+        Some(action) => {
+            prepare_client_error_action(action, cc_info);
+            return Some(action);
+        }
+        None => v5_reply,
+    };
+
+    match socks_version {
+        SocksVersion::V4a => {
+            let _ = v4a::SocksV4Reply::RequestRejectedOrFailed.send(clt_w).await;
+        }
+        SocksVersion::V5 => {
+            let _ = v5_reply.send(clt_w).await;
+        }
+        SocksVersion::V6 => {} // TODO socks v6
+    }
+    action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+    use yaml_rust::{YamlLoader, yaml};
+
+    use g3_socket::RawSocket;
+    use g3_types::net::ConnectError;
+
+    use crate::config::server::socks_proxy::SocksProxyServerConfig;
+    use crate::config::server::tcp_stream::TcpStreamServerConfig;
+    use crate::module::tcp_connect::TcpConnectError;
+    use crate::serve::ServerTaskForbiddenError;
+
+    /// What the client sees after the server side closed the connection
+    #[derive(Debug, PartialEq)]
+    enum ClientSeen {
+        Close,
+        Reset,
+        Data(Vec<u8>),
+    }
+
+    fn load_map(s: &str) -> yaml::Hash {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        yaml[0].as_hash().unwrap().clone()
+    }
+
+    /// Get a connect error by really connecting to a closed port
+    async fn connect_refused() -> ConnectError {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let e = TcpStream::connect(addr).await.unwrap_err();
+        ConnectError::from(e)
+    }
+
+    async fn accept_client() -> (TcpStream, TcpStream, ClientConnectionInfo) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let clt_stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (stream, peer_addr) = listener.accept().await.unwrap();
+        let local_addr: SocketAddr = stream.local_addr().unwrap();
+        let mut cc_info = ClientConnectionInfo::new(peer_addr, local_addr);
+        cc_info.set_tcp_raw_socket(RawSocket::from(&stream));
+        (clt_stream, stream, cc_info)
+    }
+
+    async fn read_client(mut clt_stream: TcpStream) -> ClientSeen {
+        let mut buf = Vec::new();
+        match clt_stream.read_to_end(&mut buf).await {
+            Ok(_) if buf.is_empty() => ClientSeen::Close,
+            Ok(_) => ClientSeen::Data(buf),
+            Err(e) if e.kind() == io::ErrorKind::ConnectionReset => ClientSeen::Reset,
+            Err(e) => panic!("unexpected client read error: {e}"),
+        }
+    }
+
+    /// Fail the client stream the same way as the stream tasks
+    async fn fail_established(
+        actions: &ClientErrorActionMap,
+        e: &ServerTaskError,
+    ) -> (ClientSeen, Option<ClientErrorAction>) {
+        let (clt_stream, stream, cc_info) = accept_client().await;
+        let action = established_client_error_action(actions, e);
+        if let Some(action) = action {
+            prepare_client_error_action(action, &cc_info);
+        }
+        drop(stream);
+        (read_client(clt_stream).await, action)
+    }
+
+    /// Fail the socks request the same way as the socks tcp connect task
+    async fn fail_socks(
+        actions: &ClientErrorActionMap,
+        class: Option<ClientErrorClass>,
+        socks_version: SocksVersion,
+        v5_reply: v5::Socks5Reply,
+    ) -> (ClientSeen, Option<ClientErrorAction>) {
+        let (clt_stream, mut stream, cc_info) = accept_client().await;
+        let action = reply_socks_client_error(
+            actions,
+            class,
+            &socks_version,
+            v5_reply,
+            &cc_info,
+            &mut stream,
+        )
+        .await;
+        drop(stream);
+        (read_client(clt_stream).await, action)
+    }
+
+    async fn check_stream_actions(actions: &ClientErrorActionMap) {
+        let e = ServerTaskError::UpstreamNotConnected(connect_refused().await);
+        assert_eq!(
+            fail_established(actions, &e).await,
+            (ClientSeen::Reset, Some(ClientErrorAction::Reset))
+        );
+
+        let e = ServerTaskError::UpstreamNotConnected(ConnectError::TimedOut);
+        assert_eq!(
+            fail_established(actions, &e).await,
+            (ClientSeen::Close, None)
+        );
+
+        let e = ServerTaskError::Idle(Duration::from_secs(1), 1);
+        assert_eq!(
+            fail_established(actions, &e).await,
+            (ClientSeen::Close, Some(ClientErrorAction::Close))
+        );
+
+        let e = ServerTaskError::CanceledAsServerQuit;
+        assert_eq!(
+            fail_established(actions, &e).await,
+            (ClientSeen::Reset, Some(ClientErrorAction::Reset))
+        );
+    }
+
+    #[tokio::test]
+    async fn tcp_stream() {
+        let map = load_map(
+            "name: test\nescaper: default\nupstream: 127.0.0.1:80\n\
+             client_error_actions:\n  connect_refused: reset\n  idle_killed: close\n  \
+             lifetime_expired: reset\n",
+        );
+        let config = TcpStreamServerConfig::parse(&map, None).unwrap();
+        check_stream_actions(&config.client_error_actions).await;
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd"
+    ))]
+    #[tokio::test]
+    async fn tcp_tproxy() {
+        use crate::config::server::tcp_tproxy::TcpTProxyServerConfig;
+
+        let map = load_map(
+            "name: test\nescaper: default\nlisten: 127.0.0.1:0\n\
+             client_error_actions:\n  connect_refused: reset\n  idle_killed: close\n  \
+             lifetime_expired: reset\n",
+        );
+        let config = TcpTProxyServerConfig::parse(&map, None).unwrap();
+        check_stream_actions(&config.client_error_actions).await;
+    }
+
+    #[tokio::test]
+    async fn socks_proxy() {
+        let map = load_map(
+            "name: test\nescaper: default\n\
+             client_error_actions:\n  ingress_denied: {socks_reply: 2}\n  \
+             connect_refused: reset\n  idle_killed: {socks_reply: 5}\n",
+        );
+        let config = SocksProxyServerConfig::parse(&map, None).unwrap();
+        let actions = &config.client_error_actions;
+
+        let mut expected = Vec::new();
+        v5::Socks5Reply::failure(2)
+            .send(&mut expected)
+            .await
+            .unwrap();
+        let e = ServerTaskError::ForbiddenByRule(ServerTaskForbiddenError::DestDenied);
+        assert_eq!(
+            fail_socks(
+                actions,
+                e.client_error_class(),
+                SocksVersion::V5,
+                v5::Socks5Reply::ForbiddenByRule
+            )
+            .await,
+            (
+                ClientSeen::Data(expected),
+                Some(ClientErrorAction::SocksReply(2))
+            )
+        );
+
+        let mut expected = Vec::new();
+        v4a::SocksV4Reply::RequestRejectedOrFailed
+            .send(&mut expected)
+            .await
+            .unwrap();
+        assert_eq!(
+            fail_socks(
+                actions,
+                e.client_error_class(),
+                SocksVersion::V4a,
+                v5::Socks5Reply::ForbiddenByRule
+            )
+            .await,
+            (
+                ClientSeen::Data(expected),
+                Some(ClientErrorAction::SocksReply(2))
+            )
+        );
+
+        let e = TcpConnectError::ConnectFailed(connect_refused().await);
+        let v5_reply = v5::Socks5Reply::from(&e);
+        let e = ServerTaskError::from(e);
+        assert_eq!(
+            fail_socks(actions, e.client_error_class(), SocksVersion::V5, v5_reply).await,
+            (ClientSeen::Reset, Some(ClientErrorAction::Reset))
+        );
+
+        let mut expected = Vec::new();
+        v5::Socks5Reply::ConnectionTimedOut
+            .send(&mut expected)
+            .await
+            .unwrap();
+        let e = TcpConnectError::ConnectFailed(ConnectError::TimedOut);
+        let v5_reply = v5::Socks5Reply::from(&e);
+        let e = ServerTaskError::from(e);
+        assert_eq!(
+            fail_socks(actions, e.client_error_class(), SocksVersion::V5, v5_reply).await,
+            (ClientSeen::Data(expected), None)
+        );
+
+        // the socks reply has been sent when relaying
+        let e = ServerTaskError::Idle(Duration::from_secs(1), 1);
+        assert_eq!(
+            fail_established(actions, &e).await,
+            (ClientSeen::Close, Some(ClientErrorAction::Close))
+        );
+    }
+}
@@ -3,6 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use anyhow::anyhow;
@@ -13,7 +14,7 @@ use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio_rustls::server::TlsStream;
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats};
+use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ReceiveUdpServer};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
 use g3_openssl::SslStream;
 use g3_types::metrics::NodeName;
@@ -151,6 +152,17 @@ impl AcceptQuicServer for DummyCloseServer {
     async fn run_quic_task(&self, _connection: Connection, _cc_info: ClientConnectionInfo) {}
 }
 
+impl ReceiveUdpServer for DummyCloseServer {
+    fn receive_udp_packet(
+        &self,
+        _packet: &[u8],
+        _client_addr: SocketAddr,
+        _server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) {
+    }
+}
+
 #[async_trait]
 impl Server for DummyCloseServer {
     fn escaper(&self) -> &NodeName {
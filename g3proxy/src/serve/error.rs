@@ -18,13 +18,14 @@ use g3_icap_client::reqmod::imap::ImapAdaptationError;
 use g3_icap_client::reqmod::smtp::SmtpAdaptationError;
 use g3_icap_client::respmod::h1::H1RespmodAdaptationError;
 use g3_io_ext::{
-    IdleForceQuitReason, UdpCopyClientError, UdpCopyError, UdpCopyRemoteError, UdpRelayClientError,
-    UdpRelayError, UdpRelayRemoteError,
+    IdleDirection, IdleForceQuitReason, IdleTracker, UdpCopyClientError, UdpCopyError,
+    UdpCopyRemoteError, UdpRelayClientError, UdpRelayError, UdpRelayRemoteError,
 };
 use g3_resolver::ResolveError;
 use g3_socks::SocksRequestParseError;
 use g3_types::net::ConnectError;
 
+use crate::config::server::ClientErrorClass;
 use crate::inspect::InterceptionError;
 use crate::module::tcp_connect::TcpConnectError;
 
@@ -48,6 +49,8 @@ pub(crate) enum ServerTaskForbiddenError {
     UaBlocked,
     #[error("user blocked")]
     UserBlocked,
+    #[error("udp port not allowed")]
+    UdpPortNotAllowed,
 }
 
 #[derive(Error, Debug)]
@@ -94,6 +97,10 @@ pub(crate) enum ServerTaskError {
     UpstreamReadFailed(io::Error),
     #[error("write to upstream: {0:?}")]
     UpstreamWriteFailed(io::Error),
+    #[error("{0}: read from icap server: {1:?}")]
+    IcapServerReadFailed(&'static str, io::Error),
+    #[error("{0}: write to icap server: {1:?}")]
+    IcapServerWriteFailed(&'static str, io::Error),
     #[error("upstream tls handshake timeout")]
     UpstreamTlsHandshakeTimeout,
     #[error("upstream tls handshake failed: {0:?}")]
@@ -112,12 +119,18 @@ pub(crate) enum ServerTaskError {
     ClosedByClient,
     #[error("closed early by client")]
     ClosedEarlyByClient,
+    #[error("control connection closed by client")]
+    ControlClosed,
     #[error("canceled as user blocked")]
     CanceledAsUserBlocked,
     #[error("canceled as server quit")]
     CanceledAsServerQuit,
+    #[error("killed by operator")]
+    KilledByOperator,
     #[error("idle after {0:?} x {1}")]
     Idle(Duration, usize),
+    #[error("{0} idle after {1:?} x {2}")]
+    DirectionalIdle(IdleDirection, Duration, usize),
     #[error("{0} interception error: {1}")]
     InterceptionError(Protocol, InterceptionError),
     #[error("finished")]
@@ -127,6 +140,66 @@ pub(crate) enum ServerTaskError {
 }
 
 impl ServerTaskError {
+    /// Get the idle error, with the dominant idle side if it has been tracked
+    pub(crate) fn idle(period: Duration, tracker: &IdleTracker) -> Self {
+        match tracker.dominant_direction() {
+            Some(direction) => {
+                ServerTaskError::DirectionalIdle(direction, period, tracker.idle_count())
+            }
+            None => ServerTaskError::Idle(period, tracker.idle_count()),
+        }
+    }
+
+    /// Check if the task failed, the normal close of the client or the upstream is not failure
+    pub(crate) fn is_failure(&self) -> bool {
+        !matches!(
+            self,
+            ServerTaskError::Finished
+                | ServerTaskError::ClosedByClient
+                | ServerTaskError::ClosedByUpstream
+                | ServerTaskError::ControlClosed
+        )
+    }
+
+    /// Get the class of the failure, which decides the client visible behavior
+    pub(crate) fn client_error_class(&self) -> Option<ClientErrorClass> {
+        match self {
+            ServerTaskError::UpstreamNotConnected(e) => match e {
+                ConnectError::ConnectionRefused | ConnectError::ConnectionReset => {
+                    Some(ClientErrorClass::UpstreamConnectRefused)
+                }
+                ConnectError::TimedOut => Some(ClientErrorClass::UpstreamConnectTimeout),
+                _ => None,
+            },
+            ServerTaskError::ForbiddenByRule(_) => Some(ClientErrorClass::IngressDenied),
+            ServerTaskError::InternalAdapterError(_)
+            | ServerTaskError::IcapServerReadFailed(_, _)
+            | ServerTaskError::IcapServerWriteFailed(_, _) => {
+                Some(ClientErrorClass::AdaptationFailed)
+            }
+            ServerTaskError::Idle(_, _) | ServerTaskError::DirectionalIdle(_, _, _) => {
+                Some(ClientErrorClass::IdleKilled)
+            }
+            ServerTaskError::CanceledAsServerQuit => Some(ClientErrorClass::LifetimeExpired),
+            _ => None,
+        }
+    }
+
+    /// Get the kind of the io error if the task ended with one
+    pub(crate) fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            ServerTaskError::ClientTcpReadFailed(e)
+            | ServerTaskError::ClientTcpWriteFailed(e)
+            | ServerTaskError::ClientUdpRecvFailed(e)
+            | ServerTaskError::ClientUdpSendFailed(e)
+            | ServerTaskError::UpstreamReadFailed(e)
+            | ServerTaskError::UpstreamWriteFailed(e)
+            | ServerTaskError::IcapServerReadFailed(_, e)
+            | ServerTaskError::IcapServerWriteFailed(_, e) => Some(e.kind()),
+            _ => None,
+        }
+    }
+
     pub(crate) fn brief(&self) -> &'static str {
         match self {
             ServerTaskError::InternalServerError(_) => "InternalServerError",
@@ -150,6 +223,8 @@ impl ServerTaskError {
             ServerTaskError::InvalidUpstreamProtocol(_) => "InvalidUpstreamProtocol",
             ServerTaskError::UpstreamReadFailed(_) => "UpstreamReadFailed",
             ServerTaskError::UpstreamWriteFailed(_) => "UpstreamWriteFailed",
+            ServerTaskError::IcapServerReadFailed(_, _) => "IcapServerReadFailed",
+            ServerTaskError::IcapServerWriteFailed(_, _) => "IcapServerWriteFailed",
             ServerTaskError::UpstreamTlsHandshakeTimeout => "UpstreamTlsHandshakeTimeout",
             ServerTaskError::UpstreamTlsHandshakeFailed(_) => "UpstreamTlsHandshakeFailed",
             ServerTaskError::UpstreamNotNegotiated(_) => "UpstreamNotNegotiated",
@@ -159,9 +234,17 @@ impl ServerTaskError {
             ServerTaskError::ClosedByUpstream => "ClosedByUpstream",
             ServerTaskError::ClosedByClient => "ClosedByClient",
             ServerTaskError::ClosedEarlyByClient => "ClosedEarlyByClient",
+            ServerTaskError::ControlClosed => "ControlClosed",
             ServerTaskError::CanceledAsUserBlocked => "CanceledAsUserBlocked",
             ServerTaskError::CanceledAsServerQuit => "CanceledAsServerQuit",
+            ServerTaskError::KilledByOperator => "KilledByOperator",
             ServerTaskError::Idle(_, _) => "Idle",
+            ServerTaskError::DirectionalIdle(direction, _, _) => match direction {
+                IdleDirection::ClientRead => "ClientReadIdle",
+                IdleDirection::UpstreamRead => "UpstreamReadIdle",
+                IdleDirection::IcapRead => "IcapReadIdle",
+                IdleDirection::WriteBlocked => "WriteBlockedIdle",
+            },
             ServerTaskError::InterceptionError(_, _) => "InterceptionError",
             ServerTaskError::Finished => "Finished",
             ServerTaskError::UnclassifiedError(_) => "UnclassifiedError",
@@ -222,6 +305,9 @@ impl From<UdpRelayRemoteError> for ServerTaskError {
             UdpRelayRemoteError::RemoteSessionError(_, _, e) => {
                 ServerTaskError::UpstreamReadFailed(e)
             }
+            UdpRelayRemoteError::PortUnreachable(_, _) => ServerTaskError::UpstreamReadFailed(
+                io::Error::from(io::ErrorKind::ConnectionRefused),
+            ),
             UdpRelayRemoteError::InternalServerError(s) => ServerTaskError::InternalServerError(s),
         }
     }
@@ -373,6 +459,12 @@ impl From<H1ReqmodAdaptationError> for ServerTaskError {
                 IdleForceQuitReason::UserBlocked => ServerTaskError::CanceledAsUserBlocked,
                 IdleForceQuitReason::ServerQuit => ServerTaskError::CanceledAsServerQuit,
             },
+            H1ReqmodAdaptationError::IcapServerReadFailed(e) => {
+                ServerTaskError::IcapServerReadFailed("reqmod", e)
+            }
+            H1ReqmodAdaptationError::IcapServerWriteFailed(e) => {
+                ServerTaskError::IcapServerWriteFailed("reqmod", e)
+            }
             e => ServerTaskError::InternalAdapterError(anyhow!("reqmod: {e}")),
         }
     }
@@ -403,6 +495,12 @@ impl From<H1RespmodAdaptationError> for ServerTaskError {
                 IdleForceQuitReason::UserBlocked => ServerTaskError::CanceledAsUserBlocked,
                 IdleForceQuitReason::ServerQuit => ServerTaskError::CanceledAsServerQuit,
             },
+            H1RespmodAdaptationError::IcapServerReadFailed(e) => {
+                ServerTaskError::IcapServerReadFailed("respmod", e)
+            }
+            H1RespmodAdaptationError::IcapServerWriteFailed(e) => {
+                ServerTaskError::IcapServerWriteFailed("respmod", e)
+            }
             e => ServerTaskError::InternalAdapterError(anyhow!("respmod: {e}")),
         }
     }
@@ -456,3 +554,77 @@ impl From<ImapAdaptationError> for ServerTaskError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_error_class() {
+        let e = ServerTaskError::UpstreamNotConnected(ConnectError::ConnectionRefused);
+        assert_eq!(
+            e.client_error_class(),
+            Some(ClientErrorClass::UpstreamConnectRefused)
+        );
+        let e = ServerTaskError::UpstreamNotConnected(ConnectError::TimedOut);
+        assert_eq!(
+            e.client_error_class(),
+            Some(ClientErrorClass::UpstreamConnectTimeout)
+        );
+        let e = ServerTaskError::ForbiddenByRule(ServerTaskForbiddenError::DestDenied);
+        assert_eq!(
+            e.client_error_class(),
+            Some(ClientErrorClass::IngressDenied)
+        );
+        let e = ServerTaskError::Idle(Duration::from_secs(60), 5);
+        assert_eq!(e.client_error_class(), Some(ClientErrorClass::IdleKilled));
+        let e = ServerTaskError::CanceledAsServerQuit;
+        assert_eq!(
+            e.client_error_class(),
+            Some(ClientErrorClass::LifetimeExpired)
+        );
+
+        let e = ServerTaskError::from(H1RespmodAdaptationError::IcapServerReadFailed(
+            io::Error::from(io::ErrorKind::TimedOut),
+        ));
+        assert_eq!(
+            e.client_error_class(),
+            Some(ClientErrorClass::AdaptationFailed)
+        );
+
+        let e = ServerTaskError::UpstreamNotConnected(ConnectError::HostUnreachable);
+        assert!(e.client_error_class().is_none());
+        assert!(
+            ServerTaskError::ClosedByClient
+                .client_error_class()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn adaptation_io_error() {
+        let e = ServerTaskError::from(H1ReqmodAdaptationError::IcapServerWriteFailed(
+            io::Error::from(io::ErrorKind::BrokenPipe),
+        ));
+        assert_eq!(e.brief(), "IcapServerWriteFailed");
+        assert_eq!(e.io_error_kind(), Some(io::ErrorKind::BrokenPipe));
+        assert!(e.to_string().starts_with("reqmod: "));
+
+        let e = ServerTaskError::from(H1RespmodAdaptationError::IcapServerReadFailed(
+            io::Error::from(io::ErrorKind::ConnectionReset),
+        ));
+        assert_eq!(e.brief(), "IcapServerReadFailed");
+        assert_eq!(e.io_error_kind(), Some(io::ErrorKind::ConnectionReset));
+        assert!(e.to_string().starts_with("respmod: "));
+
+        // the peer side of the io error should be kept
+        let e = ServerTaskError::from(H1RespmodAdaptationError::HttpClientWriteFailed(
+            io::Error::from(io::ErrorKind::ConnectionReset),
+        ));
+        assert_eq!(e.brief(), "ClientTcpWriteFailed");
+        assert_eq!(e.io_error_kind(), Some(io::ErrorKind::ConnectionReset));
+
+        let e = ServerTaskError::from(H1ReqmodAdaptationError::NotImplemented("test"));
+        assert!(e.io_error_kind().is_none());
+    }
+}
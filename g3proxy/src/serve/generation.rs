@@ -0,0 +1,361 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde_json::{Value, json};
+
+use g3_types::metrics::NodeName;
+
+use crate::config::ServerCheckAction;
+use crate::config::server::AnyServerConfig;
+
+static CONFIG_GENERATIONS: Mutex<ConfigGenerations> = Mutex::new(ConfigGenerations::new());
+
+struct ServerChange {
+    name: NodeName,
+    r#type: &'static str,
+    action: ServerCheckAction,
+}
+
+impl ServerChange {
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name.as_str(),
+            "type": self.r#type,
+            "action": self.action.as_str(),
+        })
+    }
+}
+
+/// Get the changes of the servers if the target configs is applied to the running ones
+fn plan_changes(
+    running: &BTreeMap<NodeName, AnyServerConfig>,
+    target: &[Arc<AnyServerConfig>],
+) -> Vec<ServerChange> {
+    let mut changes = Vec::with_capacity(target.len());
+    let mut found = HashSet::with_capacity(target.len());
+    for config in target {
+        found.insert(config.name());
+        let action = match running.get(config.name()) {
+            Some(old) => old.diff_action(config).into(),
+            None => ServerCheckAction::SpawnNew,
+        };
+        changes.push(ServerChange {
+            name: config.name().clone(),
+            r#type: config.r#type(),
+            action,
+        });
+    }
+    for (name, old) in running {
+        if found.contains(name) {
+            continue;
+        }
+        changes.push(ServerChange {
+            name: name.clone(),
+            r#type: old.r#type(),
+            action: ServerCheckAction::Remove,
+        });
+    }
+    changes
+}
+
+/// A snapshot of the server configs that has been applied successfully
+struct ConfigGeneration {
+    id: u64,
+    time: DateTime<Utc>,
+    rollback_of: Option<u64>,
+    servers: Vec<Arc<AnyServerConfig>>,
+    changes: Vec<ServerChange>,
+}
+
+impl ConfigGeneration {
+    fn to_json(&self) -> Value {
+        let servers: Vec<&str> = self.servers.iter().map(|c| c.name().as_str()).collect();
+        let changes: Vec<Value> = self
+            .changes
+            .iter()
+            .filter(|c| c.action != ServerCheckAction::NoAction)
+            .map(|c| c.to_json())
+            .collect();
+        let mut value = json!({
+            "generation": self.id,
+            "time": self.time.to_rfc3339(),
+            "servers": servers,
+            "changes": changes,
+            "unchanged": self.changes.len() - changes.len(),
+        });
+        if let Some(id) = self.rollback_of {
+            value["rollback_of"] = json!(id);
+        }
+        value
+    }
+}
+
+struct ConfigGenerations {
+    next_id: u64,
+    inner: VecDeque<ConfigGeneration>,
+}
+
+impl ConfigGenerations {
+    const fn new() -> Self {
+        ConfigGenerations {
+            next_id: 1,
+            inner: VecDeque::new(),
+        }
+    }
+
+    /// Add a new generation and return the evicted ones
+    fn push(
+        &mut self,
+        servers: Vec<Arc<AnyServerConfig>>,
+        changes: Vec<ServerChange>,
+        rollback_of: Option<u64>,
+        retention: usize,
+    ) -> (&ConfigGeneration, Vec<ConfigGeneration>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut evicted = Vec::new();
+        while self.inner.len() >= retention.max(1) {
+            let Some(generation) = self.inner.pop_front() else {
+                break;
+            };
+            evicted.push(generation);
+        }
+        self.inner.push_back(ConfigGeneration {
+            id,
+            time: Utc::now(),
+            rollback_of,
+            servers,
+            changes,
+        });
+        (self.inner.back().unwrap(), evicted)
+    }
+
+    /// Find the server configs of the generation to rollback to.
+    ///
+    /// The one before the current generation will be used if `id` is not set.
+    fn find(&self, id: Option<u64>) -> anyhow::Result<(u64, Vec<Arc<AnyServerConfig>>)> {
+        let Some(oldest) = self.inner.front() else {
+            return Err(anyhow!("no config generation recorded"));
+        };
+        let generation = match id {
+            Some(id) => self.inner.iter().find(|g| g.id == id).ok_or_else(|| {
+                anyhow!(
+                    "no config generation {id} found, the oldest kept one is {}",
+                    oldest.id
+                )
+            })?,
+            None => {
+                let len = self.inner.len();
+                if len < 2 {
+                    return Err(anyhow!("no previous config generation found"));
+                }
+                &self.inner[len - 2]
+            }
+        };
+        Ok((generation.id, generation.servers.clone()))
+    }
+
+    fn to_json(&self) -> Value {
+        let generations: Vec<Value> = self.inner.iter().map(|g| g.to_json()).collect();
+        json!({
+            "current": self.inner.back().map(|g| g.id),
+            "generations": generations,
+        })
+    }
+}
+
+fn spill(dir: &Path, generation: &ConfigGeneration, evicted: &[ConfigGeneration]) {
+    let path = dir.join(format!("generation-{}.json", generation.id));
+    if let Err(e) = std::fs::write(&path, generation.to_json().to_string()) {
+        warn!(
+            "failed to write config generation {} to {}: {e}",
+            generation.id,
+            path.display()
+        );
+    }
+    for generation in evicted {
+        let path = dir.join(format!("generation-{}.json", generation.id));
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Record a new config generation after the target server configs has been applied
+pub(super) fn record(
+    servers: Vec<Arc<AnyServerConfig>>,
+    running: &BTreeMap<NodeName, AnyServerConfig>,
+    rollback_of: Option<u64>,
+) -> u64 {
+    let config = crate::config::history::get();
+    let changes = plan_changes(running, &servers);
+    let mut generations = CONFIG_GENERATIONS.lock().unwrap();
+    let (generation, evicted) = generations.push(servers, changes, rollback_of, config.retention);
+    if let Some(dir) = &config.state_dir {
+        spill(dir, generation, &evicted);
+    }
+    generation.id
+}
+
+pub(super) fn find(id: Option<u64>) -> anyhow::Result<(u64, Vec<Arc<AnyServerConfig>>)> {
+    let generations = CONFIG_GENERATIONS.lock().unwrap();
+    generations.find(id)
+}
+
+pub(crate) fn history() -> Value {
+    let generations = CONFIG_GENERATIONS.lock().unwrap();
+    generations.to_json()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn load_servers(s: &str) -> Vec<Arc<AnyServerConfig>> {
+        let docs = YamlLoader::load_from_str(s).unwrap();
+        docs[0]
+            .as_vec()
+            .unwrap()
+            .iter()
+            .map(|v| {
+                let server =
+                    crate::config::server::load_server(v.as_hash().unwrap(), None).unwrap();
+                Arc::new(server)
+            })
+            .collect()
+    }
+
+    fn apply(servers: &[Arc<AnyServerConfig>]) -> BTreeMap<NodeName, AnyServerConfig> {
+        servers
+            .iter()
+            .map(|c| (c.name().clone(), c.as_ref().clone()))
+            .collect()
+    }
+
+    fn actions(changes: &[ServerChange]) -> Vec<(&str, &str)> {
+        changes
+            .iter()
+            .map(|c| (c.name.as_str(), c.action.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn rollback() {
+        let gen1 = load_servers(
+            r#"
+- name: stream1
+  type: tcp_stream
+  escaper: default
+  listen: 127.0.0.1:8080
+  upstream: 127.0.0.1:80
+- name: close
+  type: dummy_close
+"#,
+        );
+        let gen2 = load_servers(
+            r#"
+- name: stream1
+  type: tcp_stream
+  escaper: default
+  listen: 127.0.0.1:9080
+  upstream: 127.0.0.1:80
+- name: stream2
+  type: tcp_stream
+  escaper: default
+  listen: 127.0.0.1:8081
+  upstream: 127.0.0.1:80
+- name: close
+  type: dummy_close
+"#,
+        );
+
+        let mut generations = ConfigGenerations::new();
+        let mut running = BTreeMap::new();
+
+        let changes = plan_changes(&running, &gen1);
+        generations.push(gen1.clone(), changes, None, 8);
+        running = apply(&gen1);
+
+        let changes = plan_changes(&running, &gen2);
+        assert_eq!(
+            actions(&changes),
+            [
+                ("stream1", "reload_and_respawn"),
+                ("stream2", "spawn_new"),
+                ("close", "no_action"),
+            ]
+        );
+        generations.push(gen2.clone(), changes, None, 8);
+        running = apply(&gen2);
+
+        let (id, servers) = generations.find(None).unwrap();
+        assert_eq!(id, 1);
+        let changes = plan_changes(&running, &servers);
+        assert_eq!(
+            actions(&changes),
+            [
+                ("stream1", "reload_and_respawn"),
+                ("close", "no_action"),
+                ("stream2", "remove"),
+            ]
+        );
+        generations.push(servers.clone(), changes, Some(id), 8);
+        running = apply(&servers);
+
+        // the server set and the listen config should be the same as generation 1
+        let names: Vec<&str> = running.keys().map(|n| n.as_str()).collect();
+        assert_eq!(names, ["close", "stream1"]);
+        let changes = plan_changes(&running, &gen1);
+        assert!(
+            changes
+                .iter()
+                .all(|c| c.action == ServerCheckAction::NoAction)
+        );
+
+        let history = generations.to_json();
+        assert_eq!(history["current"], json!(3));
+        let generation = &history["generations"][2];
+        assert_eq!(generation["rollback_of"], json!(1));
+        assert_eq!(generation["unchanged"], json!(1));
+        assert_eq!(
+            generation["changes"],
+            json!([
+                {"name": "stream1", "type": "TcpStream", "action": "reload_and_respawn"},
+                {"name": "stream2", "type": "TcpStream", "action": "remove"},
+            ])
+        );
+    }
+
+    #[test]
+    fn retention() {
+        let servers = load_servers(
+            r#"
+- name: close
+  type: dummy_close
+"#,
+        );
+
+        let mut generations = ConfigGenerations::new();
+        for _ in 0..3 {
+            let (_, evicted) = generations.push(servers.clone(), Vec::new(), None, 2);
+            assert!(evicted.len() <= 1);
+        }
+        let ids: Vec<u64> = generations.inner.iter().map(|g| g.id).collect();
+        assert_eq!(ids, [2, 3]);
+        assert_eq!(generations.find(None).unwrap().0, 2);
+        assert_eq!(generations.find(Some(3)).unwrap().0, 3);
+        assert!(generations.find(Some(1)).is_err());
+
+        let mut generations = ConfigGenerations::new();
+        generations.push(servers, Vec::new(), None, 2);
+        assert!(generations.find(None).is_err());
+    }
+}
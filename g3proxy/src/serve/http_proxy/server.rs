@@ -19,7 +19,9 @@ use tokio::net::TcpStream;
 use tokio::sync::{broadcast, mpsc};
 use tokio_rustls::{TlsAcceptor, server::TlsStream};
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime, ReceiveUdpServer,
+};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
 use g3_io_ext::{AsyncStream, IdleWheel};
 use g3_openssl::SslStream;
@@ -42,7 +44,7 @@ use crate::config::server::{AnyServerConfig, ServerConfig};
 use crate::escape::ArcEscaper;
 use crate::serve::{
     ArcServer, ArcServerInternal, ArcServerStats, Server, ServerInternal, ServerQuitPolicy,
-    ServerRegistry, ServerStats, WrapArcServer,
+    ServerRegistry, ServerStats, TaskLogSampler, WrapArcServer,
 };
 
 pub(crate) struct HttpProxyServer {
@@ -57,6 +59,7 @@ pub(crate) struct HttpProxyServer {
     dst_host_filter: Option<Arc<AclDstHostRuleSet>>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Option<Logger>,
+    task_log_sampler: Arc<TaskLogSampler>,
 
     escaper: ArcSwap<ArcEscaper>,
     user_group: ArcSwapOption<UserGroup>,
@@ -106,7 +109,11 @@ impl HttpProxyServer {
             .map(|builder| Arc::new(builder.build()));
 
         let task_logger = config.get_task_logger();
-        let idle_wheel = IdleWheel::spawn(config.task_idle_check_duration);
+        let task_log_sampler = Arc::new(TaskLogSampler::new(&config.task_log_sample));
+        let idle_wheel = IdleWheel::spawn_with_jitter(
+            config.task_idle_check_duration,
+            config.task_idle_check_jitter,
+        );
 
         // always update extra metrics tags
         server_stats.set_extra_tags(config.extra_metrics_tags.clone());
@@ -127,6 +134,7 @@ impl HttpProxyServer {
             dst_host_filter,
             reload_sender,
             task_logger,
+            task_log_sampler,
             escaper: ArcSwap::new(escaper),
             user_group: ArcSwapOption::new(user_group),
             audit_handle: ArcSwapOption::new(audit_handle),
@@ -203,6 +211,7 @@ impl HttpProxyServer {
             cc_info,
             tls_client_config: self.tls_client_config.clone(),
             task_logger: self.task_logger.clone(),
+            task_log_sampler: self.task_log_sampler.clone(),
             dst_host_filter: self.dst_host_filter.clone(),
         })
     }
@@ -442,6 +451,17 @@ impl AcceptQuicServer for HttpProxyServer {
     }
 }
 
+impl ReceiveUdpServer for HttpProxyServer {
+    fn receive_udp_packet(
+        &self,
+        _packet: &[u8],
+        _client_addr: SocketAddr,
+        _server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) {
+    }
+}
+
 #[async_trait]
 impl Server for HttpProxyServer {
     fn escaper(&self) -> &NodeName {
@@ -473,6 +493,10 @@ impl Server for HttpProxyServer {
         &self.quit_policy
     }
 
+    fn task_log_sampler(&self) -> Option<&TaskLogSampler> {
+        Some(&self.task_log_sampler)
+    }
+
     async fn run_rustls_task(&self, stream: TlsStream<TcpStream>, cc_info: ClientConnectionInfo) {
         let client_addr = cc_info.client_addr();
         self.server_stats.add_conn(client_addr);
@@ -13,7 +13,8 @@ use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::stats::{StatId, TcpIoSnapshot, TcpIoStats};
 
 use crate::serve::{
-    ServerForbiddenSnapshot, ServerForbiddenStats, ServerPerTaskStats, ServerStats,
+    ServerAdaptationBypassSnapshot, ServerAdaptationBypassStats, ServerForbiddenSnapshot,
+    ServerForbiddenStats, ServerPerTaskStats, ServerStats,
 };
 use crate::stat::types::UntrustedTaskStatsSnapshot;
 
@@ -27,6 +28,7 @@ pub(crate) struct HttpProxyServerStats {
     conn_total: AtomicU64,
 
     pub forbidden: ServerForbiddenStats,
+    pub adaptation_bypass: ServerAdaptationBypassStats,
 
     pub task_http_untrusted: ServerPerTaskStats,
     pub task_http_connect: ServerPerTaskStats,
@@ -47,6 +49,7 @@ impl HttpProxyServerStats {
             online: AtomicIsize::new(0),
             conn_total: AtomicU64::new(0),
             forbidden: Default::default(),
+            adaptation_bypass: Default::default(),
             task_http_untrusted: Default::default(),
             task_http_connect: Default::default(),
             task_http_forward: Default::default(),
@@ -135,4 +138,8 @@ impl ServerStats for HttpProxyServerStats {
             in_bytes: self.io_untrusted.get_in_bytes(),
         })
     }
+
+    fn adaptation_bypass_snapshot(&self) -> Option<ServerAdaptationBypassSnapshot> {
+        Some(self.adaptation_bypass.snapshot())
+    }
 }
@@ -22,7 +22,7 @@ use crate::escape::ArcEscaper;
 use crate::module::http_forward::HttpProxyClientResponse;
 use crate::module::http_header;
 use crate::module::tcp_connect::TcpConnectTaskNotes;
-use crate::serve::{ServerIdleChecker, ServerQuitPolicy, ServerTaskNotes};
+use crate::serve::{ServerIdleChecker, ServerQuitPolicy, ServerTaskNotes, TaskLogSampler};
 
 #[derive(Clone)]
 pub(crate) struct CommonTaskContext {
@@ -34,6 +34,7 @@ pub(crate) struct CommonTaskContext {
     pub(crate) cc_info: ClientConnectionInfo,
     pub(crate) tls_client_config: Arc<OpensslClientConfig>,
     pub(crate) task_logger: Option<Logger>,
+    pub(crate) task_log_sampler: Arc<TaskLogSampler>,
 
     pub(crate) dst_host_filter: Option<Arc<AclDstHostRuleSet>>,
 }
@@ -321,7 +321,7 @@ impl HttpProxyConnectTask {
             .await
         {
             Ok(connection) => {
-                self.task_notes.stage = ServerTaskStage::Connected;
+                self.task_notes.mark_connected(&self.tcp_notes.escaper);
                 self.stream_ups = Some(connection);
                 Ok(())
             }
@@ -379,6 +379,9 @@ impl HttpProxyConnectTask {
                 upstream: &self.upstream,
                 task_notes: &self.task_notes,
                 tcp_notes: &self.tcp_notes,
+                connect_attempts: None,
+                connect_timeout: None,
+                original_dst: None,
                 client_rd_bytes: self.task_stats.clt.read.get_bytes(),
                 client_wr_bytes: self.task_stats.clt.write.get_bytes(),
                 remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
@@ -470,6 +473,7 @@ impl HttpProxyConnectTask {
                             .unwrap_or_else(|| audit_handle.do_task_audit())
                 })
                 .unwrap_or_else(|| audit_handle.do_task_audit());
+            self.task_notes.vars_mut().set_audit_task(audit_task);
 
             if audit_task {
                 let ctx = StreamInspectContext::new(
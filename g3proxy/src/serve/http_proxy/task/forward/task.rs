@@ -63,6 +63,7 @@ pub(crate) struct HttpProxyForwardTask<'a> {
     tcp_notes: TcpConnectTaskNotes,
     task_stats: Arc<HttpForwardTaskStats>,
     max_idle_count: usize,
+    bypass_adaptation: bool,
     started: bool,
 }
 
@@ -111,10 +112,17 @@ impl<'a> HttpProxyForwardTask<'a> {
             tcp_notes: TcpConnectTaskNotes::default(),
             task_stats: Arc::new(HttpForwardTaskStats::default()),
             max_idle_count,
+            bypass_adaptation: false,
             started: false,
         }
     }
 
+    /// Skip the ICAP adaptation as a trusted bypass header is present
+    pub(crate) fn bypass_adaptation(&mut self) {
+        self.bypass_adaptation = true;
+        self.http_notes.icap_bypassed = Some("trusted_header");
+    }
+
     #[inline]
     pub(crate) fn should_close(&self) -> bool {
         self.should_close
@@ -600,6 +608,7 @@ impl<'a> HttpProxyForwardTask<'a> {
                 audit_task = audit_handle.do_task_audit();
             }
         }
+        self.task_notes.vars_mut().set_audit_task(audit_task);
 
         // set client side socket options
         self.ctx
@@ -639,7 +648,7 @@ impl<'a> HttpProxyForwardTask<'a> {
             self.mark_relaying();
 
             let r = self
-                .run_with_connection(fwd_ctx, clt_r, clt_w, connection, audit_task)
+                .run_with_connection(fwd_ctx, clt_r, clt_w, connection)
                 .await;
             match r {
                 Ok(ups_s) => {
@@ -670,7 +679,7 @@ impl<'a> HttpProxyForwardTask<'a> {
 
         let connection = self.get_new_connection(fwd_ctx, clt_w).await?;
         match self
-            .run_with_connection(fwd_ctx, clt_r, clt_w, connection, audit_task)
+            .run_with_connection(fwd_ctx, clt_r, clt_w, connection)
             .await
         {
             Ok(ups_s) => {
@@ -787,7 +796,6 @@ impl<'a> HttpProxyForwardTask<'a> {
         clt_r: &mut Option<HttpClientReader<CDR>>,
         clt_w: &mut HttpClientWriter<CDW>,
         mut ups_c: BoxHttpForwardConnection,
-        audit_task: bool,
     ) -> ServerTaskResult<Option<BoxHttpForwardConnection>>
     where
         CDR: AsyncRead + Send + Unpin,
@@ -806,7 +814,7 @@ impl<'a> HttpProxyForwardTask<'a> {
             }
         }
 
-        if audit_task {
+        if self.task_notes.vars().audit_task().unwrap_or(false) && !self.bypass_adaptation {
             if let Some(audit_handle) = self.audit_ctx.handle() {
                 if let Some(reqmod) = audit_handle.icap_reqmod_client() {
                     match reqmod
@@ -823,9 +831,11 @@ impl<'a> HttpProxyForwardTask<'a> {
                                 self.task_notes.task_created_instant(),
                             );
                             adapter.set_client_addr(self.ctx.client_addr());
+                            adapter.set_task_id(self.task_notes.id);
                             if let Some(name) = self.task_notes.raw_user_name() {
                                 adapter.set_client_username(name.clone());
                             }
+                            let log_icap_timing = audit_handle.log_icap_timing();
                             let r = self
                                 .run_with_adaptation(
                                     clt_r,
@@ -841,6 +851,13 @@ impl<'a> HttpProxyForwardTask<'a> {
                             if let Some(dur) = adaptation_state.dur_ups_send_all {
                                 self.http_notes.dur_req_send_all = dur;
                             }
+                            self.http_notes.icap_reqmod_retried = adaptation_state.icap_retried;
+                            self.http_notes.icap_reqmod_preview_size =
+                                adaptation_state.preview_size;
+                            if log_icap_timing {
+                                self.http_notes.icap_reqmod_timing =
+                                    Some(*adaptation_state.icap_timing());
+                            }
                             return r;
                         }
                         Err(e) => {
@@ -1526,9 +1543,19 @@ impl<'a> HttpProxyForwardTask<'a> {
         self.http_notes.rsp_status = 0;
         self.update_response_header(rsp_header);
 
-        if audit_task {
+        if audit_task && !self.bypass_adaptation {
             if let Some(audit_handle) = self.audit_ctx.handle() {
                 if let Some(respmod) = audit_handle.icap_respmod_client() {
+                    let content_type = rsp_header
+                        .end_to_end_headers
+                        .get(header::CONTENT_TYPE)
+                        .map(|v| v.to_str());
+                    if respmod.transfer_ignored(self.http_notes.uri.path(), content_type) {
+                        self.http_notes.icap_bypassed = Some("transfer_ignore");
+                        return self
+                            .send_response_without_adaptation(clt_w, ups_r, rsp_header)
+                            .await;
+                    }
                     match respmod
                         .h1_adapter(
                             self.ctx.server_config.tcp_copy,
@@ -1543,10 +1570,12 @@ impl<'a> HttpProxyForwardTask<'a> {
                                 self.http_notes.dur_rsp_recv_hdr,
                             );
                             adapter.set_client_addr(self.ctx.client_addr());
+                            adapter.set_task_id(self.task_notes.id);
                             if let Some(name) = self.task_notes.raw_user_name() {
                                 adapter.set_client_username(name.clone());
                             }
                             adapter.set_respond_shared_headers(adaptation_respond_shared_headers);
+                            let log_icap_timing = audit_handle.log_icap_timing();
                             let r = self
                                 .send_response_with_adaptation(
                                     clt_w,
@@ -1556,15 +1585,21 @@ impl<'a> HttpProxyForwardTask<'a> {
                                     &mut adaptation_state,
                                 )
                                 .await;
-                            if !adaptation_state.clt_write_finished
-                                || !adaptation_state.ups_read_finished
+                            if !adaptation_state.clt_write_finished()
+                                || !adaptation_state.ups_read_finished()
                             {
                                 self.should_close = true;
                             }
-                            if let Some(dur) = adaptation_state.dur_ups_recv_all {
+                            if let Some(dur) = adaptation_state.dur_ups_recv_all() {
                                 self.http_notes.dur_rsp_recv_all = dur;
                             }
-                            self.send_error_response = !adaptation_state.clt_write_started;
+                            self.http_notes.icap_respmod_preview_size =
+                                adaptation_state.preview_size();
+                            if log_icap_timing {
+                                self.http_notes.icap_respmod_timing =
+                                    Some(*adaptation_state.icap_timing());
+                            }
+                            self.send_error_response = !adaptation_state.clt_write_started();
                             return r;
                         }
                         Err(e) => {
@@ -22,6 +22,7 @@ use super::{
 };
 use crate::audit::AuditContext;
 use crate::auth::{UserContext, UserGroup, UserRequestStats};
+use crate::config::audit::AdaptationBypassCheck;
 use crate::config::server::ServerConfig;
 use crate::escape::EgressPathSelection;
 use crate::module::http_forward::{BoxHttpForwardContext, HttpProxyClientResponse};
@@ -254,12 +255,13 @@ where
         user_ctx: Option<UserContext>,
     ) -> LoopAction {
         let path_selection = self.get_egress_path_selection(&mut req.inner.end_to_end_headers);
-        let task_notes = ServerTaskNotes::with_path_selection(
+        let mut task_notes = ServerTaskNotes::with_path_selection(
             self.ctx.cc_info.clone(),
             user_ctx,
             req.time_accepted.elapsed(),
             path_selection,
         );
+        task_notes.set_log_sample(self.ctx.task_log_sampler.sample());
 
         let mut audit_ctx = self.audit_ctx.clone();
         let remote_protocol = match req.client_protocol {
@@ -483,6 +485,7 @@ where
             }
             _ => unreachable!(),
         };
+        let bypass_adaptation = self.check_adaptation_bypass(&mut req, &audit_ctx);
 
         match req.body_reader.take() {
             Some(stream_r) => {
@@ -490,6 +493,9 @@ where
                 // we may need to send stream_r back if we have a body
                 let mut forward_task =
                     HttpProxyForwardTask::new(&self.ctx, audit_ctx, &req, is_https, task_notes);
+                if bypass_adaptation {
+                    forward_task.bypass_adaptation();
+                }
                 let mut clt_r = Some(stream_r);
                 forward_task
                     .run(&mut clt_r, clt_w, &mut self.forward_context)
@@ -512,6 +518,9 @@ where
                 // no body, and the connection is expected to keep alive from the client side
                 let mut forward_task =
                     HttpProxyForwardTask::new(&self.ctx, audit_ctx, &req, is_https, task_notes);
+                if bypass_adaptation {
+                    forward_task.bypass_adaptation();
+                }
                 let mut clt_r = None;
                 forward_task
                     .run::<CDR, CDW>(&mut clt_r, clt_w, &mut self.forward_context)
@@ -527,6 +536,43 @@ where
         }
     }
 
+    /// Strip the adaptation bypass header, and check if it is trusted
+    fn check_adaptation_bypass(
+        &self,
+        req: &mut HttpProxyRequest<CDR>,
+        audit_ctx: &AuditContext,
+    ) -> bool {
+        let Some(audit_handle) = audit_ctx.handle() else {
+            return false;
+        };
+        let Some(header) = audit_handle.adaptation_bypass_header() else {
+            return false;
+        };
+        // always strip it, so it won't be forwarded to the upstream
+        let Some(value) = req.inner.end_to_end_headers.remove(header) else {
+            return false;
+        };
+
+        match audit_handle.check_adaptation_bypass(
+            self.ctx.client_addr().ip(),
+            &req.upstream,
+            value.as_bytes(),
+        ) {
+            AdaptationBypassCheck::Trusted => {
+                self.ctx.server_stats.adaptation_bypass.add_trusted();
+                true
+            }
+            AdaptationBypassCheck::Invalid => {
+                self.ctx.server_stats.adaptation_bypass.add_invalid();
+                false
+            }
+            AdaptationBypassCheck::Denied => {
+                self.ctx.server_stats.adaptation_bypass.add_denied();
+                false
+            }
+        }
+    }
+
     async fn run_ftp_over_http(
         &mut self,
         clt_w: &mut HttpClientWriter<CDW>,
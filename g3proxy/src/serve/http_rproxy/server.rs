@@ -20,7 +20,9 @@ use tokio::sync::{broadcast, mpsc};
 use tokio_rustls::LazyConfigAcceptor;
 use tokio_rustls::server::TlsStream;
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime, ReceiveUdpServer,
+};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
 use g3_io_ext::{AsyncStream, IdleWheel};
 use g3_openssl::SslStream;
@@ -43,7 +45,7 @@ use crate::config::server::{AnyServerConfig, ServerConfig};
 use crate::escape::ArcEscaper;
 use crate::serve::{
     ArcServer, ArcServerInternal, ArcServerStats, Server, ServerInternal, ServerQuitPolicy,
-    ServerRegistry, ServerStats, WrapArcServer,
+    ServerRegistry, ServerStats, TaskLogSampler, WrapArcServer,
 };
 
 pub(crate) struct HttpRProxyServer {
@@ -55,6 +57,7 @@ pub(crate) struct HttpRProxyServer {
     ingress_net_filter: Option<AclNetworkRule>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Option<Logger>,
+    task_log_sampler: Arc<TaskLogSampler>,
     hosts: HostMatch<Arc<HttpHost>>,
 
     escaper: ArcSwap<ArcEscaper>,
@@ -94,7 +97,11 @@ impl HttpRProxyServer {
             .map(|builder| builder.build());
 
         let task_logger = config.get_task_logger();
-        let idle_wheel = IdleWheel::spawn(config.task_idle_check_duration);
+        let task_log_sampler = Arc::new(TaskLogSampler::new(&config.task_log_sample));
+        let idle_wheel = IdleWheel::spawn_with_jitter(
+            config.task_idle_check_duration,
+            config.task_idle_check_jitter,
+        );
 
         // always update extra metrics tags
         server_stats.set_extra_tags(config.extra_metrics_tags.clone());
@@ -111,6 +118,7 @@ impl HttpRProxyServer {
             ingress_net_filter,
             reload_sender,
             task_logger,
+            task_log_sampler,
             hosts,
             escaper: ArcSwap::new(escaper),
             user_group: ArcSwapOption::new(user_group),
@@ -200,6 +208,7 @@ impl HttpRProxyServer {
             escaper: self.escaper.load().as_ref().clone(),
             cc_info,
             task_logger: self.task_logger.clone(),
+            task_log_sampler: self.task_log_sampler.clone(),
         })
     }
 
@@ -435,6 +444,17 @@ impl AcceptQuicServer for HttpRProxyServer {
     async fn run_quic_task(&self, _connection: Connection, _cc_info: ClientConnectionInfo) {}
 }
 
+impl ReceiveUdpServer for HttpRProxyServer {
+    fn receive_udp_packet(
+        &self,
+        _packet: &[u8],
+        _client_addr: SocketAddr,
+        _server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) {
+    }
+}
+
 #[async_trait]
 impl Server for HttpRProxyServer {
     fn escaper(&self) -> &NodeName {
@@ -466,6 +486,10 @@ impl Server for HttpRProxyServer {
         &self.quit_policy
     }
 
+    fn task_log_sampler(&self) -> Option<&TaskLogSampler> {
+        Some(&self.task_log_sampler)
+    }
+
     async fn run_rustls_task(&self, stream: TlsStream<TcpStream>, cc_info: ClientConnectionInfo) {
         let client_addr = cc_info.client_addr();
         self.server_stats.add_conn(client_addr);
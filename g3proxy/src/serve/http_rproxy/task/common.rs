@@ -15,7 +15,7 @@ use g3_io_ext::{IdleWheel, OptionalInterval};
 
 use super::{HttpRProxyServerConfig, HttpRProxyServerStats};
 use crate::escape::ArcEscaper;
-use crate::serve::ServerQuitPolicy;
+use crate::serve::{ServerQuitPolicy, TaskLogSampler};
 
 #[derive(Clone)]
 pub(crate) struct CommonTaskContext {
@@ -26,6 +26,7 @@ pub(crate) struct CommonTaskContext {
     pub(crate) escaper: ArcEscaper,
     pub(crate) cc_info: ClientConnectionInfo,
     pub(crate) task_logger: Option<Logger>,
+    pub(crate) task_log_sampler: Arc<TaskLogSampler>,
 }
 
 impl CommonTaskContext {
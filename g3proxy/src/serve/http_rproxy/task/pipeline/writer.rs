@@ -256,11 +256,12 @@ where
         user_ctx: Option<UserContext>,
         host: Arc<HttpHost>,
     ) -> LoopAction {
-        let task_notes = ServerTaskNotes::new(
+        let mut task_notes = ServerTaskNotes::new(
             self.ctx.cc_info.clone(),
             user_ctx,
             req.time_accepted.elapsed(),
         );
+        task_notes.set_log_sample(self.ctx.task_log_sampler.sample());
 
         if let Some(mut stream_w) = self.stream_writer.take() {
             let mut audit_ctx = AuditContext::default();
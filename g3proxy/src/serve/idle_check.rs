@@ -4,11 +4,69 @@
  */
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use g3_io_ext::{IdleCheck, IdleForceQuitReason, IdleInterval, IdleWheel};
 
-use super::ServerQuitPolicy;
+use super::{ServerQuitPolicy, ServerTaskNotes};
 use crate::auth::User;
+use crate::config::server::TaskIdleOverrides;
+
+/// The task idle check values resolved from a matched task idle override
+pub(crate) struct ServerIdleOverride {
+    name: Arc<str>,
+    pub(crate) idle_wheel: Arc<IdleWheel>,
+    pub(crate) max_idle_count: usize,
+}
+
+pub(crate) struct ServerIdleOverrides {
+    config: TaskIdleOverrides,
+    overrides: Vec<Arc<ServerIdleOverride>>,
+}
+
+impl ServerIdleOverrides {
+    /// Build from the config, the server defaults will be used for the values not set
+    pub(crate) fn new(
+        config: &TaskIdleOverrides,
+        idle_wheel: &Arc<IdleWheel>,
+        task_idle_check_duration: Duration,
+        task_idle_check_jitter: u8,
+        task_idle_max_count: usize,
+    ) -> Self {
+        let overrides = config
+            .iter()
+            .map(|o| {
+                let check_duration = o.check_duration(task_idle_check_duration);
+                let idle_wheel = if check_duration == task_idle_check_duration {
+                    idle_wheel.clone()
+                } else {
+                    IdleWheel::spawn_with_jitter(check_duration, task_idle_check_jitter)
+                };
+                Arc::new(ServerIdleOverride {
+                    name: Arc::from(o.name()),
+                    idle_wheel,
+                    max_idle_count: o.max_count(task_idle_max_count),
+                })
+            })
+            .collect();
+        ServerIdleOverrides {
+            config: config.clone(),
+            overrides,
+        }
+    }
+
+    /// Select the override for the task, and record it in the task notes
+    pub(crate) fn select_for_task(
+        &self,
+        task_notes: &mut ServerTaskNotes,
+    ) -> Option<Arc<ServerIdleOverride>> {
+        let user_group = task_notes.user_ctx().map(|c| c.user().group());
+        let i = self.config.select(task_notes.client_ip(), user_group)?;
+        let o = self.overrides.get(i)?.clone();
+        task_notes.vars_mut().set_idle_override(o.name.clone());
+        Some(o)
+    }
+}
 
 pub(crate) struct ServerIdleChecker {
     pub(crate) idle_wheel: Arc<IdleWheel>,
@@ -60,3 +118,100 @@ impl IdleCheck for ServerIdleChecker {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    use tokio::io::AsyncReadExt;
+    use tokio::time::Instant;
+    use yaml_rust::YamlLoader;
+
+    use g3_daemon::server::ClientConnectionInfo;
+
+    fn build_overrides() -> ServerIdleOverrides {
+        let doc = YamlLoader::load_from_str(
+            r#"
+- net: 127.0.0.0/8
+  task_idle_check_duration: 50ms
+  task_idle_max_count: 3
+- net: 127.0.0.2/32
+  task_idle_check_duration: 20ms
+  task_idle_max_count: 1
+"#,
+        )
+        .unwrap();
+        let config = TaskIdleOverrides::parse_yaml(&doc[0]).unwrap();
+        let idle_wheel = IdleWheel::spawn(Duration::from_secs(60));
+        ServerIdleOverrides::new(&config, &idle_wheel, Duration::from_secs(60), 0, 5)
+    }
+
+    fn new_task_notes(client_ip: &str) -> ServerTaskNotes {
+        let client_addr = SocketAddr::new(client_ip.parse().unwrap(), 10000);
+        let server_addr = SocketAddr::from_str("127.0.0.1:1080").unwrap();
+        ServerTaskNotes::new(
+            ClientConnectionInfo::new(client_addr, server_addr),
+            None,
+            Duration::ZERO,
+        )
+    }
+
+    /// Get the time elapsed before the task is killed as idle with a silent upstream
+    async fn idle_quit_time(idle_override: &ServerIdleOverride) -> Duration {
+        let (mut ups_r, _ups_w) = tokio::io::duplex(64);
+        let checker = ServerIdleChecker::new(
+            idle_override.idle_wheel.clone(),
+            None,
+            idle_override.max_idle_count,
+            Arc::new(ServerQuitPolicy::default()),
+        );
+
+        let time_start = Instant::now();
+        let mut idle_interval = checker.interval_timer();
+        let mut idle_count = 0;
+        let mut buf = [0u8; 16];
+        loop {
+            tokio::select! {
+                _ = ups_r.read(&mut buf) => panic!("the upstream should be silent"),
+                n = idle_interval.tick() => {
+                    idle_count += n;
+                    if checker.check_quit(idle_count) {
+                        return time_start.elapsed();
+                    }
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn select_by_client_ip() {
+        let overrides = build_overrides();
+
+        let mut short_notes = new_task_notes("127.0.0.2");
+        let short = overrides.select_for_task(&mut short_notes).unwrap();
+        assert_eq!(
+            short_notes.vars().idle_override().map(|s| s.as_ref()),
+            Some("net:127.0.0.2/32")
+        );
+
+        let mut long_notes = new_task_notes("127.0.0.3");
+        let long = overrides.select_for_task(&mut long_notes).unwrap();
+        assert_eq!(
+            long_notes.vars().idle_override().map(|s| s.as_ref()),
+            Some("net:127.0.0.0/8")
+        );
+
+        let mut other_notes = new_task_notes("192.0.2.1");
+        assert!(overrides.select_for_task(&mut other_notes).is_none());
+        assert!(other_notes.vars().idle_override().is_none());
+
+        let (short_time, long_time) = tokio::join!(idle_quit_time(&short), idle_quit_time(&long));
+        // 2 ticks of 20ms
+        assert!(short_time >= Duration::from_millis(40));
+        assert!(short_time < Duration::from_millis(200));
+        // 4 ticks of 50ms
+        assert!(long_time >= Duration::from_millis(200));
+    }
+}
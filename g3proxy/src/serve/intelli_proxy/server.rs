@@ -15,7 +15,9 @@ use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio_rustls::server::TlsStream;
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime, ReceiveUdpServer,
+};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
 use g3_io_ext::haproxy::{ProxyProtocolV1Reader, ProxyProtocolV2Reader};
 use g3_openssl::SslStream;
@@ -277,6 +279,17 @@ impl AcceptQuicServer for IntelliProxy {
     async fn run_quic_task(&self, _connection: Connection, _cc_info: ClientConnectionInfo) {}
 }
 
+impl ReceiveUdpServer for IntelliProxy {
+    fn receive_udp_packet(
+        &self,
+        _packet: &[u8],
+        _client_addr: SocketAddr,
+        _server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) {
+    }
+}
+
 #[async_trait]
 impl Server for IntelliProxy {
     fn escaper(&self) -> &NodeName {
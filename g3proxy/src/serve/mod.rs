@@ -3,16 +3,20 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 
+use anyhow::anyhow;
 use async_trait::async_trait;
 #[cfg(feature = "quic")]
 use quinn::Connection;
 use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio_rustls::server::TlsStream;
+use uuid::Uuid;
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats};
+use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ReceiveUdpServer};
 use g3_daemon::server::{
     BaseServer, ClientConnectionInfo, ReloadServer, ServerQuitPolicy, ServerReloadCommand,
 };
@@ -23,10 +27,10 @@ use crate::config::server::AnyServerConfig;
 
 mod registry;
 use registry::ServerRegistry;
-pub(crate) use registry::{get_names, get_or_insert_default};
+pub(crate) use registry::{get_config, get_names, get_or_insert_default};
 
 mod idle_check;
-pub(crate) use idle_check::ServerIdleChecker;
+pub(crate) use idle_check::{ServerIdleChecker, ServerIdleOverride, ServerIdleOverrides};
 
 mod dummy_close;
 mod intelli_proxy;
@@ -49,28 +53,53 @@ mod tcp_stream;
 ))]
 mod tcp_tproxy;
 mod tls_stream;
+#[cfg(target_os = "linux")]
+mod udp_tproxy;
 
+mod client_error;
 mod error;
 mod task;
+mod task_log_sample;
+mod task_vars;
 
 pub(crate) use error::{ServerTaskError, ServerTaskForbiddenError, ServerTaskResult};
 pub(crate) use task::{ServerTaskNotes, ServerTaskStage};
+pub(crate) use task_log_sample::{TaskLogSample, TaskLogSampler};
+pub(crate) use task_vars::ServerTaskVars;
+
+mod generation;
+pub(crate) use generation::history as config_history;
 
 mod ops;
 pub(crate) use ops::{
     force_quit_offline_server, force_quit_offline_servers, foreach_server, get_server, reload,
-    stop_all, update_dependency_to_auditor, update_dependency_to_escaper,
+    rollback, stop_all, update_dependency_to_auditor, update_dependency_to_escaper,
     update_dependency_to_user_group, wait_all_tasks,
 };
 pub use ops::{spawn_all, spawn_offline_clean};
 
 mod stats;
 pub(crate) use stats::{
-    ArcServerStats, ServerForbiddenSnapshot, ServerForbiddenStats, ServerPerTaskStats, ServerStats,
+    ArcServerStats, ServerAdaptationBypassSnapshot, ServerAdaptationBypassStats,
+    ServerConnectFallbackSnapshot, ServerConnectFallbackStats, ServerForbiddenSnapshot,
+    ServerForbiddenStats, ServerPerTaskStats, ServerStats, ServerUdpOverheadSnapshot,
+    ServerUdpOverheadStats, ServerUdpOversizeSnapshot, ServerUdpOversizeStats,
+    ServerUdpPortExhaustedStats,
+};
+
+mod check;
+pub(crate) use check::{LOOPBACK_CHECK_TIMEOUT, ServerCheckReport};
+
+mod running_task;
+pub(crate) use running_task::{
+    RunningTask, RunningTaskBatchSize, RunningTaskBytes, RunningTaskGuard, RunningTaskRegistry,
+    RunningTaskStats, TASK_LIST_DEFAULT_LIMIT,
 };
 
 #[async_trait]
-pub(crate) trait Server: BaseServer + AcceptTcpServer + AcceptQuicServer {
+pub(crate) trait Server:
+    BaseServer + AcceptTcpServer + AcceptQuicServer + ReceiveUdpServer
+{
     fn escaper(&self) -> &NodeName;
     fn user_group(&self) -> &NodeName;
     fn auditor(&self) -> &NodeName;
@@ -86,6 +115,42 @@ pub(crate) trait Server: BaseServer + AcceptTcpServer + AcceptQuicServer {
     async fn run_rustls_task(&self, stream: TlsStream<TcpStream>, cc_info: ClientConnectionInfo);
 
     async fn run_openssl_task(&self, stream: SslStream<TcpStream>, cc_info: ClientConnectionInfo);
+
+    /// Connect to the server through the loopback interface and report the latency
+    async fn loopback_check(&self, _sni: Option<&str>) -> anyhow::Result<ServerCheckReport> {
+        let listen_stats = self.get_listen_stats();
+        let (_stream, connect_time) =
+            g3_daemon::listen::loopback_connect(&listen_stats, LOOPBACK_CHECK_TIMEOUT).await?;
+        Ok(ServerCheckReport::new(connect_time))
+    }
+
+    /// Start to capture the sampled udp relay packets, return the path of the pcap file
+    fn start_udp_capture(&self, _ratio: f64, _max_packets: usize) -> anyhow::Result<PathBuf> {
+        Err(anyhow!("udp capture is not supported by this server"))
+    }
+
+    fn stop_udp_capture(&self) -> anyhow::Result<()> {
+        Err(anyhow!("udp capture is not supported by this server"))
+    }
+
+    /// List the running relay tasks, in the order of task id and starting after `after`
+    fn list_tasks(
+        &self,
+        _after: Option<Uuid>,
+        _limit: usize,
+    ) -> anyhow::Result<Vec<Arc<RunningTask>>> {
+        Err(anyhow!("task list is not supported by this server"))
+    }
+
+    /// Kill the running relay task, it will quit at its next idle check
+    fn kill_task(&self, _id: &Uuid) -> anyhow::Result<()> {
+        Err(anyhow!("task kill is not supported by this server"))
+    }
+
+    /// Get the task log sampler, which can be used to adjust the sampling at runtime
+    fn task_log_sampler(&self) -> Option<&TaskLogSampler> {
+        None
+    }
 }
 
 trait ServerInternal: Server {
@@ -157,6 +222,30 @@ impl AcceptQuicServer for WrapArcServer {
     }
 }
 
+impl ReceiveUdpServer for WrapArcServer {
+    fn receive_udp_packet(
+        &self,
+        packet: &[u8],
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+        worker_id: Option<usize>,
+    ) {
+        self.0
+            .receive_udp_packet(packet, client_addr, server_addr, worker_id)
+    }
+
+    fn drain_udp_packet(
+        &self,
+        packet: &[u8],
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+        worker_id: Option<usize>,
+    ) -> bool {
+        self.0
+            .drain_udp_packet(packet, client_addr, server_addr, worker_id)
+    }
+}
+
 fn new_reload_notify_channel() -> broadcast::Sender<ServerReloadCommand> {
     broadcast::Sender::new(16)
 }
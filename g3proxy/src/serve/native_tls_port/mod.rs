@@ -17,10 +17,12 @@ use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio_rustls::server::TlsStream;
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime, ReceiveUdpServer,
+};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
 use g3_io_ext::haproxy::{ProxyProtocolV1Reader, ProxyProtocolV2Reader};
-use g3_openssl::{SslAcceptor, SslStream};
+use g3_openssl::{SslAcceptor, SslHandshakeError, SslStream};
 use g3_types::acl::{AclAction, AclNetworkRule};
 use g3_types::metrics::NodeName;
 use g3_types::net::{OpensslServerConfig, OpensslTicketKey, ProxyProtocolVersion, RollingTicketer};
@@ -212,10 +214,12 @@ impl NativeTlsPort {
             }
             Err(e) => {
                 self.listen_stats.add_failed();
+                let handshake_error = SslHandshakeError::from_io_error(&e);
                 debug!(
-                    "{} - {} tls error: {e:?}",
+                    "{} - {} tls error: {e:?}, classified as {handshake_error}, caused by {}",
                     cc_info.sock_local_addr(),
-                    cc_info.sock_peer_addr()
+                    cc_info.sock_peer_addr(),
+                    handshake_error.fault_side().as_str(),
                 );
                 // TODO record tls failure and add some sec policy
             }
@@ -317,6 +321,17 @@ impl AcceptQuicServer for NativeTlsPort {
     async fn run_quic_task(&self, _connection: Connection, _cc_info: ClientConnectionInfo) {}
 }
 
+impl ReceiveUdpServer for NativeTlsPort {
+    fn receive_udp_packet(
+        &self,
+        _packet: &[u8],
+        _client_addr: SocketAddr,
+        _server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) {
+    }
+}
+
 #[async_trait]
 impl Server for NativeTlsPort {
     fn escaper(&self) -> &NodeName {
@@ -3,7 +3,8 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -12,11 +13,10 @@ use log::{debug, warn};
 use tokio::sync::Mutex;
 
 use g3_types::metrics::NodeName;
-use g3_yaml::YamlDocPosition;
 
 use crate::config::server::{AnyServerConfig, ServerConfigDiffAction};
 
-use super::{ArcServer, ArcServerInternal, Server, registry};
+use super::{ArcServer, ArcServerInternal, Server, generation, registry};
 
 use super::dummy_close::DummyCloseServer;
 use super::intelli_proxy::IntelliProxy;
@@ -39,6 +39,8 @@ use super::tcp_stream::TcpStreamServer;
 ))]
 use super::tcp_tproxy::TcpTProxyServer;
 use super::tls_stream::TlsStreamServer;
+#[cfg(target_os = "linux")]
+use super::udp_tproxy::UdpTProxyServer;
 
 static SERVER_OPS_LOCK: Mutex<()> = Mutex::const_new(());
 
@@ -56,9 +58,43 @@ pub fn spawn_offline_clean() {
 pub async fn spawn_all() -> anyhow::Result<()> {
     let _guard = SERVER_OPS_LOCK.lock().await;
 
+    let all_config = crate::config::server::get_all_sorted()?;
+    let running = get_all_config_unlocked();
+    spawn_all_unlocked(&all_config)?;
+    let id = generation::record(all_config, &running, None);
+    debug!("server config generation {id} recorded");
+    Ok(())
+}
+
+/// Rollback to the server configs of a previous generation, return the new generation id.
+///
+/// The generation before the current one will be used if `id` is not set.
+pub(crate) async fn rollback(id: Option<u64>) -> anyhow::Result<u64> {
+    let _guard = SERVER_OPS_LOCK.lock().await;
+
+    let (rollback_id, all_config) = generation::find(id)?;
+    // restore the loaded config, as what a reload of the old config file would do
+    crate::config::server::replace_all(&all_config);
+    let running = get_all_config_unlocked();
+    spawn_all_unlocked(&all_config)?;
+    let id = generation::record(all_config, &running, Some(rollback_id));
+    debug!("server config generation {id} recorded as rollback of {rollback_id}");
+    Ok(id)
+}
+
+fn get_all_config_unlocked() -> BTreeMap<NodeName, AnyServerConfig> {
+    registry::get_names()
+        .into_iter()
+        .filter_map(|name| {
+            let config = registry::get_config(&name)?;
+            Some((name, config))
+        })
+        .collect()
+}
+
+fn spawn_all_unlocked(all_config: &[Arc<AnyServerConfig>]) -> anyhow::Result<()> {
     let mut new_names = HashSet::<NodeName>::new();
 
-    let all_config = crate::config::server::get_all_sorted()?;
     for config in all_config {
         let name = config.name();
         new_names.insert(name.clone());
@@ -110,10 +146,14 @@ where
     registry::foreach_online(|name, server| f(name, server.as_ref()))
 }
 
+/// Reload the named server only, all other servers will be left untouched.
+///
+/// The config will be loaded from `path` if set, or else from the recorded config position
+/// of the server, or else from the main config file.
 pub(crate) async fn reload(
     name: &NodeName,
-    position: Option<YamlDocPosition>,
-) -> anyhow::Result<()> {
+    path: Option<PathBuf>,
+) -> anyhow::Result<ServerConfigDiffAction> {
     let _guard = SERVER_OPS_LOCK.lock().await;
 
     let old_config = match registry::get_config(name) {
@@ -121,35 +161,47 @@ pub(crate) async fn reload(
         None => return Err(anyhow!("no server with name {name} found")),
     };
 
-    let position = match position {
-        Some(position) => position,
-        None => match old_config.position() {
-            Some(position) => position,
-            None => {
+    let config = match (path, old_config.position()) {
+        (None, Some(position)) => {
+            let position2 = position.clone();
+            let config = tokio::task::spawn_blocking(move || {
+                crate::config::server::load_at_position(&position2)
+            })
+            .await
+            .map_err(|e| anyhow!("unable to join conf load task: {e}"))?
+            .context(format!("unload to load conf at position {position}"))?;
+            if name != config.name() {
                 return Err(anyhow!(
-                    "no config position for server {name} found, reload is not supported"
+                    "server at position {position} has name {}, while we expect {name}",
+                    config.name()
                 ));
             }
-        },
-    };
-
-    let position2 = position.clone();
-    let config =
-        tokio::task::spawn_blocking(move || crate::config::server::load_at_position(&position2))
+            debug!("reloading server {name} from position {position}");
+            config
+        }
+        (path, _) => {
+            let path = match path {
+                Some(path) => path,
+                None => g3_daemon::opts::config_file()
+                    .ok_or_else(|| anyhow!("no config file set"))?
+                    .to_path_buf(),
+            };
+            let path2 = path.clone();
+            let name2 = name.clone();
+            let config = tokio::task::spawn_blocking(move || {
+                crate::config::server::load_named_in_file(&path2, &name2)
+            })
             .await
             .map_err(|e| anyhow!("unable to join conf load task: {e}"))?
-            .context(format!("unload to load conf at position {position}"))?;
-    if name != config.name() {
-        return Err(anyhow!(
-            "server at position {position} has name {}, while we expect {name}",
-            config.name()
-        ));
-    }
+            .context(format!("unable to load conf from file {}", path.display()))?;
+            debug!("reloading server {name} from file {}", path.display());
+            config
+        }
+    };
 
-    debug!("reloading server {name} from position {position}");
-    reload_old_unlocked(old_config, config)?;
+    let action = reload_old_unlocked(old_config, config)?;
     debug!("server {name} reload OK");
-    Ok(())
+    Ok(action)
 }
 
 pub(crate) fn update_dependency_to_server_unlocked(target: &NodeName, status: &str) {
@@ -250,34 +302,36 @@ pub(crate) async fn update_dependency_to_auditor(auditor: &NodeName, status: &st
     }
 }
 
-fn reload_old_unlocked(old: AnyServerConfig, new: AnyServerConfig) -> anyhow::Result<()> {
+fn reload_old_unlocked(
+    old: AnyServerConfig,
+    new: AnyServerConfig,
+) -> anyhow::Result<ServerConfigDiffAction> {
     let name = old.name();
-    match old.diff_action(&new) {
+    let action = old.diff_action(&new);
+    match action {
         ServerConfigDiffAction::NoAction => {
             debug!("server {name} reload: no action is needed");
-            Ok(())
         }
         ServerConfigDiffAction::SpawnNew => {
             debug!("server {name} reload: will create a totally new one");
-            spawn_new_unlocked(new)
+            spawn_new_unlocked(new)?;
         }
         ServerConfigDiffAction::ReloadNoRespawn => {
             debug!("server {name} reload: will reload config without respawn");
             registry::reload_no_respawn(name, new)?;
             update_dependency_to_server_unlocked(name, "reloaded");
-            Ok(())
         }
         ServerConfigDiffAction::ReloadAndRespawn => {
             debug!("server {name} reload: will respawn with old stats");
             registry::reload_and_respawn(name, new)?;
             update_dependency_to_server_unlocked(name, "reloaded");
-            Ok(())
         }
         ServerConfigDiffAction::UpdateInPlace(flags) => {
             debug!("server {name} reload: will update the existed in place");
-            registry::update_config_in_place(name, flags, new)
+            registry::update_config_in_place(name, flags, new)?;
         }
     }
+    Ok(action)
 }
 
 fn delete_existed_unlocked(name: &NodeName) {
@@ -305,6 +359,8 @@ fn spawn_new_unlocked(config: AnyServerConfig) -> anyhow::Result<()> {
         ))]
         AnyServerConfig::TcpTProxy(c) => TcpTProxyServer::prepare_initial(c)?,
         AnyServerConfig::TlsStream(c) => TlsStreamServer::prepare_initial(c)?,
+        #[cfg(target_os = "linux")]
+        AnyServerConfig::UdpTProxy(c) => UdpTProxyServer::prepare_initial(c)?,
         AnyServerConfig::SniProxy(c) => SniProxyServer::prepare_initial(c)?,
         AnyServerConfig::SocksProxy(c) => SocksProxyServer::prepare_initial(c)?,
         AnyServerConfig::HttpProxy(c) => HttpProxyServer::prepare_initial(c)?,
@@ -159,7 +159,7 @@ pub(crate) fn get_names() -> HashSet<NodeName> {
     sr.get_names()
 }
 
-pub(super) fn get_config(name: &NodeName) -> Option<AnyServerConfig> {
+pub(crate) fn get_config(name: &NodeName) -> Option<AnyServerConfig> {
     let sr = RUNTIME_SERVER_REGISTRY.lock().unwrap();
     sr.get_config(name)
 }
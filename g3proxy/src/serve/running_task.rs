@@ -0,0 +1,259 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
+use arc_swap::ArcSwapOption;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use g3_daemon::stat::task::TcpStreamTaskStats;
+use g3_types::net::UpstreamAddr;
+
+use super::ServerTaskNotes;
+
+/// The max number of tasks that can be returned in a single list call
+pub(crate) const TASK_LIST_MAX_LIMIT: usize = 1000;
+pub(crate) const TASK_LIST_DEFAULT_LIMIT: usize = 100;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct RunningTaskBytes {
+    pub(crate) clt_rd: u64,
+    pub(crate) clt_wr: u64,
+    pub(crate) ups_rd: u64,
+    pub(crate) ups_wr: u64,
+}
+
+/// The current recv batch size of udp relay tasks, 0 if not applicable
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct RunningTaskBatchSize {
+    pub(crate) clt_recv: usize,
+    pub(crate) ups_recv: usize,
+}
+
+pub(crate) trait RunningTaskStats {
+    fn io_bytes(&self) -> RunningTaskBytes;
+
+    fn batch_size(&self) -> RunningTaskBatchSize {
+        RunningTaskBatchSize::default()
+    }
+}
+
+impl RunningTaskStats for TcpStreamTaskStats {
+    fn io_bytes(&self) -> RunningTaskBytes {
+        RunningTaskBytes {
+            clt_rd: self.clt.read.get_bytes(),
+            clt_wr: self.clt.write.get_bytes(),
+            ups_rd: self.ups.read.get_bytes(),
+            ups_wr: self.ups.write.get_bytes(),
+        }
+    }
+}
+
+/// A relay task that can be found and killed through the control interface
+pub(crate) struct RunningTask {
+    id: Uuid,
+    client_addr: SocketAddr,
+    start_at: DateTime<Utc>,
+    upstream: ArcSwapOption<UpstreamAddr>,
+    stats: Arc<dyn RunningTaskStats + Send + Sync>,
+    last_active: AtomicI64,
+    killed: AtomicBool,
+}
+
+impl RunningTask {
+    #[inline]
+    pub(crate) fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    #[inline]
+    pub(crate) fn client_addr(&self) -> SocketAddr {
+        self.client_addr
+    }
+
+    #[inline]
+    pub(crate) fn start_at(&self) -> &DateTime<Utc> {
+        &self.start_at
+    }
+
+    pub(crate) fn upstream(&self) -> Option<Arc<UpstreamAddr>> {
+        self.upstream.load_full()
+    }
+
+    pub(crate) fn set_upstream(&self, upstream: &UpstreamAddr) {
+        self.upstream.store(Some(Arc::new(upstream.clone())));
+    }
+
+    pub(crate) fn io_bytes(&self) -> RunningTaskBytes {
+        self.stats.io_bytes()
+    }
+
+    pub(crate) fn batch_size(&self) -> RunningTaskBatchSize {
+        self.stats.batch_size()
+    }
+
+    /// Get the unix timestamp of the last idle check that found the task active
+    pub(crate) fn last_active(&self) -> i64 {
+        self.last_active.load(Ordering::Relaxed)
+    }
+
+    /// Should be called at the idle check if there is any data transferred since the last one
+    pub(crate) fn mark_active(&self) {
+        self.last_active
+            .store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    /// Check if the task has been killed, the task should quit at its next idle check
+    #[inline]
+    pub(crate) fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::Relaxed)
+    }
+
+    fn kill(&self) {
+        self.killed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// All the running relay tasks of a server, it will be shared by the reloaded servers
+#[derive(Default)]
+pub(crate) struct RunningTaskRegistry {
+    tasks: Mutex<BTreeMap<Uuid, Arc<RunningTask>>>,
+}
+
+impl RunningTaskRegistry {
+    pub(crate) fn register(
+        self: &Arc<Self>,
+        task_notes: &ServerTaskNotes,
+        stats: Arc<dyn RunningTaskStats + Send + Sync>,
+    ) -> RunningTaskGuard {
+        let task = Arc::new(RunningTask {
+            id: task_notes.id,
+            client_addr: task_notes.client_addr(),
+            start_at: task_notes.start_at,
+            upstream: ArcSwapOption::empty(),
+            stats,
+            last_active: AtomicI64::new(task_notes.start_at.timestamp()),
+            killed: AtomicBool::new(false),
+        });
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.insert(task.id, task.clone());
+        drop(tasks);
+        RunningTaskGuard {
+            registry: self.clone(),
+            task,
+        }
+    }
+
+    /// List at most `limit` tasks in the order of task id, starting after the one with id `after`
+    pub(crate) fn list(&self, after: Option<Uuid>, limit: usize) -> Vec<Arc<RunningTask>> {
+        let limit = limit.clamp(1, TASK_LIST_MAX_LIMIT);
+        let tasks = self.tasks.lock().unwrap();
+        match after {
+            Some(id) => tasks
+                .range((std::ops::Bound::Excluded(id), std::ops::Bound::Unbounded))
+                .take(limit)
+                .map(|(_, task)| task.clone())
+                .collect(),
+            None => tasks.values().take(limit).cloned().collect(),
+        }
+    }
+
+    pub(crate) fn kill(&self, id: &Uuid) -> anyhow::Result<()> {
+        let tasks = self.tasks.lock().unwrap();
+        let Some(task) = tasks.get(id) else {
+            return Err(anyhow!("no running task with id {id}"));
+        };
+        task.kill();
+        Ok(())
+    }
+}
+
+/// The task will be removed from the registry when this guard is dropped
+pub(crate) struct RunningTaskGuard {
+    registry: Arc<RunningTaskRegistry>,
+    task: Arc<RunningTask>,
+}
+
+impl RunningTaskGuard {
+    #[inline]
+    pub(crate) fn task(&self) -> &RunningTask {
+        &self.task
+    }
+}
+
+impl Drop for RunningTaskGuard {
+    fn drop(&mut self) {
+        let mut tasks = self.registry.tasks.lock().unwrap();
+        tasks.remove(&self.task.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use g3_daemon::server::ClientConnectionInfo;
+
+    fn new_task_notes(client: &str) -> ServerTaskNotes {
+        let client_addr = SocketAddr::from_str(client).unwrap();
+        let server_addr = SocketAddr::from_str("127.0.0.1:1080").unwrap();
+        let cc_info = ClientConnectionInfo::new(client_addr, server_addr);
+        ServerTaskNotes::new(cc_info, None, Duration::ZERO)
+    }
+
+    #[test]
+    fn list_and_kill() {
+        let registry = Arc::new(RunningTaskRegistry::default());
+        let notes1 = new_task_notes("192.0.2.1:10001");
+        let notes2 = new_task_notes("192.0.2.2:10002");
+        let stats1 = Arc::new(TcpStreamTaskStats::default());
+        let guard1 = registry.register(&notes1, stats1.clone());
+        let guard2 = registry.register(&notes2, Arc::new(TcpStreamTaskStats::default()));
+        guard1
+            .task()
+            .set_upstream(&UpstreamAddr::from_str("www.example.net:443").unwrap());
+        stats1.clt.read.add_bytes(10);
+        stats1.ups.write.add_bytes(10);
+        assert_eq!(registry.list(None, TASK_LIST_DEFAULT_LIMIT).len(), 2);
+
+        let task = registry
+            .list(None, TASK_LIST_DEFAULT_LIMIT)
+            .into_iter()
+            .find(|t| t.id() == &notes1.id)
+            .unwrap();
+        assert_eq!(task.client_addr(), notes1.client_addr());
+        assert_eq!(task.upstream().unwrap().to_string(), "www.example.net:443");
+        assert_eq!(task.io_bytes().clt_rd, 10);
+        assert_eq!(task.io_bytes().ups_wr, 10);
+        assert_eq!(task.batch_size(), RunningTaskBatchSize::default());
+        assert_eq!(task.last_active(), notes1.start_at.timestamp());
+
+        // paginated by the task id
+        let page1 = registry.list(None, 1);
+        assert_eq!(page1.len(), 1);
+        let page2 = registry.list(Some(*page1[0].id()), 1);
+        assert_eq!(page2.len(), 1);
+        assert_ne!(page1[0].id(), page2[0].id());
+        assert!(registry.list(Some(*page2[0].id()), 1).is_empty());
+
+        registry.kill(&notes2.id).unwrap();
+        assert!(guard2.task().is_killed());
+        assert!(!guard1.task().is_killed());
+        assert!(registry.kill(&Uuid::nil()).is_err());
+
+        drop(guard2);
+        assert_eq!(registry.list(None, TASK_LIST_DEFAULT_LIMIT).len(), 1);
+        assert!(registry.kill(&notes2.id).is_err());
+        drop(guard1);
+        assert!(registry.list(None, TASK_LIST_DEFAULT_LIMIT).is_empty());
+    }
+}
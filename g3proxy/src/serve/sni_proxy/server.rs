@@ -16,7 +16,9 @@ use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio_rustls::server::TlsStream;
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime, ReceiveUdpServer,
+};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
 use g3_dpi::ProtocolPortMap;
 use g3_io_ext::IdleWheel;
@@ -31,7 +33,7 @@ use crate::config::server::{AnyServerConfig, ServerConfig};
 use crate::escape::ArcEscaper;
 use crate::serve::{
     ArcServer, ArcServerInternal, ArcServerStats, Server, ServerInternal, ServerQuitPolicy,
-    ServerRegistry, ServerStats, WrapArcServer,
+    ServerRegistry, ServerStats, TaskLogSampler, WrapArcServer,
 };
 
 pub(crate) struct SniProxyServer {
@@ -43,6 +45,7 @@ pub(crate) struct SniProxyServer {
     client_tcp_portmap: Arc<ProtocolPortMap>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Option<Logger>,
+    task_log_sampler: Arc<TaskLogSampler>,
 
     escaper: ArcSwap<ArcEscaper>,
     audit_handle: ArcSwapOption<AuditHandle>,
@@ -69,7 +72,11 @@ impl SniProxyServer {
         let client_tcp_portmap = Arc::new(config.client_tcp_portmap.clone());
 
         let task_logger = config.get_task_logger();
-        let idle_wheel = IdleWheel::spawn(config.task_idle_check_duration);
+        let task_log_sampler = Arc::new(TaskLogSampler::new(&config.task_log_sample));
+        let idle_wheel = IdleWheel::spawn_with_jitter(
+            config.task_idle_check_duration,
+            config.task_idle_check_jitter,
+        );
 
         server_stats.set_extra_tags(config.extra_metrics_tags.clone());
 
@@ -85,6 +92,7 @@ impl SniProxyServer {
             client_tcp_portmap,
             reload_sender,
             task_logger,
+            task_log_sampler,
             escaper: ArcSwap::new(escaper),
             audit_handle: ArcSwapOption::new(audit_handle),
             quit_policy: Arc::new(ServerQuitPolicy::default()),
@@ -154,6 +162,7 @@ impl SniProxyServer {
             escaper: self.escaper.load().as_ref().clone(),
             cc_info,
             task_logger: self.task_logger.clone(),
+            task_log_sampler: self.task_log_sampler.clone(),
             server_tcp_portmap: Arc::clone(&self.server_tcp_portmap),
             client_tcp_portmap: Arc::clone(&self.client_tcp_portmap),
         };
@@ -268,6 +277,17 @@ impl AcceptQuicServer for SniProxyServer {
     async fn run_quic_task(&self, _connection: Connection, _cc_info: ClientConnectionInfo) {}
 }
 
+impl ReceiveUdpServer for SniProxyServer {
+    fn receive_udp_packet(
+        &self,
+        _packet: &[u8],
+        _client_addr: SocketAddr,
+        _server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) {
+    }
+}
+
 #[async_trait]
 impl Server for SniProxyServer {
     fn escaper(&self) -> &NodeName {
@@ -299,6 +319,10 @@ impl Server for SniProxyServer {
         &self.quit_policy
     }
 
+    fn task_log_sampler(&self) -> Option<&TaskLogSampler> {
+        Some(&self.task_log_sampler)
+    }
+
     async fn run_rustls_task(&self, _stream: TlsStream<TcpStream>, _cc_info: ClientConnectionInfo) {
     }
 
@@ -15,8 +15,8 @@ use g3_io_ext::IdleWheel;
 
 use crate::config::server::sni_proxy::SniProxyServerConfig;
 use crate::escape::ArcEscaper;
-use crate::serve::ServerQuitPolicy;
 use crate::serve::tcp_stream::TcpStreamServerStats;
+use crate::serve::{ServerQuitPolicy, TaskLogSampler};
 
 pub(crate) struct CommonTaskContext {
     pub(crate) server_config: Arc<SniProxyServerConfig>,
@@ -26,6 +26,7 @@ pub(crate) struct CommonTaskContext {
     pub(crate) escaper: ArcEscaper,
     pub(crate) cc_info: ClientConnectionInfo,
     pub(crate) task_logger: Option<Logger>,
+    pub(crate) task_log_sampler: Arc<TaskLogSampler>,
 
     pub(crate) server_tcp_portmap: Arc<ProtocolPortMap>,
     pub(crate) client_tcp_portmap: Arc<ProtocolPortMap>,
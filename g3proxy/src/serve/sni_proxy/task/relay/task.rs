@@ -46,7 +46,8 @@ impl TcpStreamTask {
         wait_time: Duration,
         pre_handshake_stats: TcpStreamConnectionStats,
     ) -> Self {
-        let task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), None, wait_time);
+        let mut task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), None, wait_time);
+        task_notes.set_log_sample(ctx.task_log_sampler.sample());
         TcpStreamTask {
             ctx,
             upstream,
@@ -68,6 +69,9 @@ impl TcpStreamTask {
                 upstream: &self.upstream,
                 task_notes: &self.task_notes,
                 tcp_notes: &self.tcp_notes,
+                connect_attempts: None,
+                connect_timeout: None,
+                original_dst: None,
                 client_rd_bytes: self.task_stats.clt.read.get_bytes(),
                 client_wr_bytes: self.task_stats.clt.write.get_bytes(),
                 remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
@@ -141,7 +145,7 @@ impl TcpStreamTask {
             )
             .await?;
 
-        self.task_notes.stage = ServerTaskStage::Connected;
+        self.task_notes.mark_connected(&self.tcp_notes.escaper);
         self.run_connected(clt_r, clt_r_buf, clt_w, ups_r, ups_w)
             .await
     }
@@ -4,6 +4,7 @@
  */
 
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::anyhow;
@@ -16,8 +17,12 @@ use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio_rustls::server::TlsStream;
+use uuid::Uuid;
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
+use g3_daemon::ban::ClientViolation;
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime, ReceiveUdpServer,
+};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
 use g3_io_ext::{AsyncStream, IdleWheel};
 use g3_openssl::SslStream;
@@ -32,9 +37,11 @@ use crate::auth::UserGroup;
 use crate::config::server::socks_proxy::SocksProxyServerConfig;
 use crate::config::server::{AnyServerConfig, ServerConfig};
 use crate::escape::ArcEscaper;
+use crate::module::udp_relay::{UdpDestVerifier, UdpRelayCaptureHandle};
 use crate::serve::{
-    ArcServer, ArcServerInternal, ArcServerStats, Server, ServerInternal, ServerQuitPolicy,
-    ServerRegistry, ServerStats, WrapArcServer,
+    ArcServer, ArcServerInternal, ArcServerStats, RunningTask, RunningTaskRegistry, Server,
+    ServerIdleOverrides, ServerInternal, ServerQuitPolicy, ServerRegistry, ServerStats,
+    TaskLogSampler, WrapArcServer,
 };
 
 pub(crate) struct SocksProxyServer {
@@ -45,12 +52,17 @@ pub(crate) struct SocksProxyServer {
     dst_host_filter: Option<Arc<AclDstHostRuleSet>>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Option<Logger>,
+    task_log_sampler: Arc<TaskLogSampler>,
 
     escaper: ArcSwap<ArcEscaper>,
     user_group: ArcSwapOption<UserGroup>,
     audit_handle: ArcSwapOption<AuditHandle>,
     quit_policy: Arc<ServerQuitPolicy>,
     idle_wheel: Arc<IdleWheel>,
+    idle_overrides: Arc<ServerIdleOverrides>,
+    udp_capture: Arc<UdpRelayCaptureHandle>,
+    udp_dest_verifier: Option<Arc<UdpDestVerifier>>,
+    running_tasks: Arc<RunningTaskRegistry>,
     reload_version: usize,
 }
 
@@ -74,13 +86,28 @@ impl SocksProxyServer {
             .map(|builder| Arc::new(builder.build()));
 
         let task_logger = config.get_task_logger();
-        let idle_wheel = IdleWheel::spawn(config.task_idle_check_duration);
+        let task_log_sampler = Arc::new(TaskLogSampler::new(&config.task_log_sample));
+        let idle_wheel = IdleWheel::spawn_with_jitter(
+            config.task_idle_check_duration,
+            config.task_idle_check_jitter,
+        );
+        let idle_overrides = Arc::new(ServerIdleOverrides::new(
+            &config.idle_overrides,
+            &idle_wheel,
+            config.task_idle_check_duration,
+            config.task_idle_check_jitter,
+            config.task_idle_max_count,
+        ));
 
         server_stats.set_extra_tags(config.extra_metrics_tags.clone());
 
         let escaper = Arc::new(crate::escape::get_or_insert_default(config.escaper()));
         let user_group = config.get_user_group();
         let audit_handle = config.get_audit_handle()?;
+        let udp_dest_verifier = config
+            .udp_dest_verify
+            .enabled()
+            .then(|| Arc::new(UdpDestVerifier::new(&config.udp_dest_verify)));
 
         let server = SocksProxyServer {
             config,
@@ -90,11 +117,16 @@ impl SocksProxyServer {
             dst_host_filter,
             reload_sender,
             task_logger,
+            task_log_sampler,
             escaper: ArcSwap::new(escaper),
             user_group: ArcSwapOption::new(user_group),
             audit_handle: ArcSwapOption::new(audit_handle),
             quit_policy: Arc::new(ServerQuitPolicy::default()),
             idle_wheel,
+            idle_overrides,
+            udp_capture: Arc::new(UdpRelayCaptureHandle::default()),
+            udp_dest_verifier,
+            running_tasks: Arc::new(RunningTaskRegistry::default()),
             reload_version: version,
         };
 
@@ -118,8 +150,20 @@ impl SocksProxyServer {
             let server_stats = Arc::clone(&self.server_stats);
             let listen_stats = Arc::clone(&self.listen_stats);
 
-            let server =
+            let mut server =
                 SocksProxyServer::new(config, server_stats, listen_stats, self.reload_version + 1)?;
+            // keep the running capture
+            server.udp_capture = self.udp_capture.clone();
+            // keep the cached verify results
+            if let Some(old) = &self.udp_dest_verifier {
+                if server.udp_dest_verifier.is_some()
+                    && old.match_config(&server.config.udp_dest_verify)
+                {
+                    server.udp_dest_verifier = Some(old.clone());
+                }
+            }
+            // keep the tasks that are still running on the old server visible
+            server.running_tasks = self.running_tasks.clone();
             Ok(server)
         } else {
             Err(anyhow!(
@@ -137,6 +181,7 @@ impl SocksProxyServer {
                 AclAction::Permit | AclAction::PermitAndLog => {}
                 AclAction::Forbid | AclAction::ForbidAndLog => {
                     self.listen_stats.add_dropped();
+                    g3_daemon::ban::report(ClientViolation::AclDenied, client_addr.ip());
                     return true;
                 }
             }
@@ -157,6 +202,10 @@ impl SocksProxyServer {
         S::R: AsyncRead + Send + Sync + Unpin + 'static,
         S::W: AsyncWrite + Send + Sync + Unpin + 'static,
     {
+        if g3_daemon::ban::is_banned(cc_info.client_ip()) {
+            self.listen_stats.add_dropped();
+            return;
+        }
         let client_addr = cc_info.client_addr();
         self.server_stats.add_conn(client_addr);
         if self.drop_early(client_addr) {
@@ -168,11 +217,16 @@ impl SocksProxyServer {
             server_stats: self.server_stats.clone(),
             server_quit_policy: self.quit_policy.clone(),
             idle_wheel: self.idle_wheel.clone(),
+            idle_overrides: self.idle_overrides.clone(),
             escaper: self.escaper.load().as_ref().clone(),
             ingress_net_filter: self.ingress_net_filter.clone(),
             dst_host_filter: self.dst_host_filter.clone(),
             cc_info,
             task_logger: self.task_logger.clone(),
+            task_log_sampler: self.task_log_sampler.clone(),
+            udp_capture: self.udp_capture.clone(),
+            udp_dest_verifier: self.udp_dest_verifier.clone(),
+            running_tasks: self.running_tasks.clone(),
         };
         SocksProxyNegotiationTask::new(ctx, self.audit_context(), self.user_group.load_full())
             .into_running(stream)
@@ -281,6 +335,17 @@ impl AcceptQuicServer for SocksProxyServer {
     async fn run_quic_task(&self, _connection: Connection, _cc_info: ClientConnectionInfo) {}
 }
 
+impl ReceiveUdpServer for SocksProxyServer {
+    fn receive_udp_packet(
+        &self,
+        _packet: &[u8],
+        _client_addr: SocketAddr,
+        _server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) {
+    }
+}
+
 #[async_trait]
 impl Server for SocksProxyServer {
     fn escaper(&self) -> &NodeName {
@@ -312,10 +377,49 @@ impl Server for SocksProxyServer {
         &self.quit_policy
     }
 
+    fn task_log_sampler(&self) -> Option<&TaskLogSampler> {
+        Some(&self.task_log_sampler)
+    }
+
     async fn run_rustls_task(&self, stream: TlsStream<TcpStream>, cc_info: ClientConnectionInfo) {
         self.run_task(stream, cc_info).await
     }
 
+    fn start_udp_capture(&self, ratio: f64, max_packets: usize) -> anyhow::Result<PathBuf> {
+        if !self.config.use_udp_associate {
+            return Err(anyhow!("udp associate is not enabled"));
+        }
+        let Some(dir) = &self.config.udp_capture_dir else {
+            return Err(anyhow!("no udp capture dir configured"));
+        };
+        self.udp_capture.start(
+            dir,
+            self.config.name(),
+            ratio,
+            max_packets,
+            self.config.udp_capture_max_bytes,
+        )
+    }
+
+    fn stop_udp_capture(&self) -> anyhow::Result<()> {
+        self.udp_capture.stop()
+    }
+
+    fn list_tasks(
+        &self,
+        after: Option<Uuid>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Arc<RunningTask>>> {
+        if !self.config.use_udp_associate {
+            return Err(anyhow!("udp associate is not enabled"));
+        }
+        Ok(self.running_tasks.list(after, limit))
+    }
+
+    fn kill_task(&self, id: &Uuid) -> anyhow::Result<()> {
+        self.running_tasks.kill(id)
+    }
+
     async fn run_openssl_task(&self, stream: SslStream<TcpStream>, cc_info: ClientConnectionInfo) {
         self.run_task(stream, cc_info).await
     }
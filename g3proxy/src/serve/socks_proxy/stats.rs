@@ -8,12 +8,15 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicIsize, AtomicU64, Ordering};
 
 use arc_swap::ArcSwapOption;
+use rustc_hash::FxHashMap;
 
 use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::stats::{StatId, TcpIoSnapshot, TcpIoStats, UdpIoSnapshot, UdpIoStats};
 
 use crate::serve::{
     ServerForbiddenSnapshot, ServerForbiddenStats, ServerPerTaskStats, ServerStats,
+    ServerUdpOverheadSnapshot, ServerUdpOverheadStats, ServerUdpOversizeSnapshot,
+    ServerUdpOversizeStats, ServerUdpPortExhaustedStats,
 };
 
 pub(crate) struct SocksProxyServerStats {
@@ -33,6 +36,10 @@ pub(crate) struct SocksProxyServerStats {
 
     pub(crate) io_tcp: TcpIoStats,
     pub(crate) io_udp: UdpIoStats,
+    pub(crate) udp_overhead: ServerUdpOverheadStats,
+
+    pub(crate) udp_port_exhausted: ServerUdpPortExhaustedStats,
+    pub(crate) udp_oversize: ServerUdpOversizeStats,
 }
 
 impl SocksProxyServerStats {
@@ -49,6 +56,9 @@ impl SocksProxyServerStats {
             task_udp_connect: Default::default(),
             io_tcp: TcpIoStats::default(),
             io_udp: UdpIoStats::default(),
+            udp_overhead: Default::default(),
+            udp_port_exhausted: Default::default(),
+            udp_oversize: Default::default(),
         }
     }
 
@@ -124,4 +134,16 @@ impl ServerStats for SocksProxyServerStats {
     fn forbidden_stats(&self) -> ServerForbiddenSnapshot {
         self.forbidden.snapshot()
     }
+
+    fn udp_port_exhausted_snapshot(&self) -> Option<FxHashMap<Arc<str>, u64>> {
+        Some(self.udp_port_exhausted.snapshot())
+    }
+
+    fn udp_oversize_snapshot(&self) -> Option<ServerUdpOversizeSnapshot> {
+        Some(self.udp_oversize.snapshot())
+    }
+
+    fn udp_overhead_snapshot(&self) -> Option<ServerUdpOverheadSnapshot> {
+        Some(self.udp_overhead.snapshot())
+    }
 }
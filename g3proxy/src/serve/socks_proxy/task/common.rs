@@ -3,6 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::io;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::Duration;
@@ -17,9 +18,22 @@ use g3_types::acl::{AclAction, AclNetworkRule};
 use g3_types::acl_set::AclDstHostRuleSet;
 use g3_types::net::UpstreamAddr;
 
+use g3_socks::v5::Socks5Reply;
+use g3_types::net::{PortRange, SocketBufferConfig, UdpMiscSockOpts};
+
 use super::{SocksProxyServerConfig, SocksProxyServerStats};
+use crate::config::server::UdpPortRangeTier;
+use crate::config::server::socks_proxy::SocksProxyUdpClientPortPolicy;
 use crate::escape::ArcEscaper;
-use crate::serve::{ServerQuitPolicy, ServerTaskError, ServerTaskNotes, ServerTaskResult};
+use crate::module::udp_relay::{UdpDestVerifier, UdpRelayCaptureHandle};
+use crate::serve::{
+    RunningTaskRegistry, ServerIdleOverrides, ServerQuitPolicy, ServerTaskError,
+    ServerTaskForbiddenError, ServerTaskNotes, ServerTaskResult, TaskLogSampler,
+};
+
+const UDP_PORT_RANGE_EXHAUSTED: &str = "no udp port available within the port range";
+/// the tier name for the server level udp port range in metrics
+const DEFAULT_UDP_PORT_RANGE_TIER: &str = "default";
 
 #[derive(Clone)]
 pub(crate) struct CommonTaskContext {
@@ -27,11 +41,16 @@ pub(crate) struct CommonTaskContext {
     pub(crate) server_stats: Arc<SocksProxyServerStats>,
     pub(crate) server_quit_policy: Arc<ServerQuitPolicy>,
     pub(crate) idle_wheel: Arc<IdleWheel>,
+    pub(crate) idle_overrides: Arc<ServerIdleOverrides>,
     pub(crate) escaper: ArcEscaper,
     pub(crate) ingress_net_filter: Option<Arc<AclNetworkRule>>,
     pub(crate) dst_host_filter: Option<Arc<AclDstHostRuleSet>>,
     pub(crate) cc_info: ClientConnectionInfo,
     pub(crate) task_logger: Option<Logger>,
+    pub(crate) task_log_sampler: Arc<TaskLogSampler>,
+    pub(crate) udp_capture: Arc<UdpRelayCaptureHandle>,
+    pub(crate) udp_dest_verifier: Option<Arc<UdpDestVerifier>>,
+    pub(crate) running_tasks: Arc<RunningTaskRegistry>,
 }
 
 impl CommonTaskContext {
@@ -112,11 +131,25 @@ impl CommonTaskContext {
         }
     }
 
+    fn select_udp_port_range_tier(
+        &self,
+        task_notes: &ServerTaskNotes,
+    ) -> Option<&UdpPortRangeTier> {
+        let user_ctx = task_notes.user_ctx()?;
+        self.server_config
+            .udp_port_range_map
+            .select(user_ctx.user_name(), user_ctx.user().group())
+    }
+
+    /// Setup the udp relay socket for the client
+    ///
+    /// The returned bool will be set if the client requested port has been tried.
     pub(super) async fn setup_udp_listen(
         &self,
         udp_client_addr: Option<SocketAddr>,
-        task_notes: &ServerTaskNotes,
-    ) -> ServerTaskResult<(SocketAddr, UdpSocket)> {
+        port_policy: SocksProxyUdpClientPortPolicy,
+        task_notes: &mut ServerTaskNotes,
+    ) -> ServerTaskResult<(SocketAddr, UdpSocket, Option<bool>)> {
         let udp_bind_ip = self.select_udp_bind_ip(udp_client_addr)?;
 
         let misc_opts = if let Some(user_ctx) = task_notes.user_ctx() {
@@ -127,38 +160,55 @@ impl CommonTaskContext {
             self.server_config.udp_misc_opts
         };
 
-        let (clt_socket, listen_addr) =
-            if let Some(port_range) = self.server_config.udp_bind_port_range {
-                g3_socket::udp::new_std_in_range_bind_lazy_connect(
-                    udp_bind_ip,
-                    port_range,
-                    self.server_config.udp_socket_buffer,
-                    misc_opts,
-                )
-                .map_err(|_| {
-                    ServerTaskError::InternalServerError(
-                        "setup udp listen socket with ranged port failed",
-                    )
-                })?
-            } else {
-                g3_socket::udp::new_std_bind_lazy_connect(
-                    Some(udp_bind_ip),
-                    self.server_config.udp_socket_buffer,
-                    misc_opts,
-                )
-                .map_err(|_| {
-                    ServerTaskError::InternalServerError(
-                        "setup udp listen socket with random port failed",
-                    )
-                })?
-            };
+        let requested_port = match port_policy {
+            SocksProxyUdpClientPortPolicy::Ignore => None,
+            _ => udp_client_addr
+                .map(|addr| addr.port())
+                .filter(|port| *port != 0),
+        };
+
+        // never fallback to the server level port range if a tier is matched
+        let port_range = match self.select_udp_port_range_tier(task_notes) {
+            Some(tier) => {
+                task_notes
+                    .vars_mut()
+                    .set_udp_port_range_tier(tier.name().clone());
+                Some(tier.port_range())
+            }
+            None => self.server_config.udp_bind_port_range,
+        };
+        if let Some(range) = port_range {
+            task_notes.vars_mut().set_udp_port_range(range);
+        }
+
+        let (clt_socket, listen_addr, port_honored) = bind_udp_relay_socket(
+            udp_bind_ip,
+            port_range,
+            requested_port,
+            port_policy,
+            self.server_config.udp_socket_buffer,
+            misc_opts,
+        )
+        .inspect_err(|e| {
+            if matches!(
+                e,
+                ServerTaskError::InternalServerError(UDP_PORT_RANGE_EXHAUSTED)
+            ) {
+                let tier = task_notes
+                    .vars()
+                    .udp_port_range_tier()
+                    .cloned()
+                    .unwrap_or_else(|| Arc::from(DEFAULT_UDP_PORT_RANGE_TIER));
+                self.server_stats.udp_port_exhausted.add_exhausted(&tier);
+            }
+        })?;
 
         let socket = UdpSocket::from_std(clt_socket).map_err(|_| {
             ServerTaskError::InternalServerError(
                 "failed to convert std udp socket to tokio udp socket",
             )
         })?;
-        Ok((listen_addr, socket))
+        Ok((listen_addr, socket, port_honored))
     }
 
     pub(super) fn log_flush_interval(&self) -> Option<Duration> {
@@ -175,4 +225,277 @@ impl CommonTaskContext {
             })
             .unwrap_or_default()
     }
+
+    pub(super) fn get_idle_echo_interval(&self) -> OptionalInterval {
+        self.server_config
+            .udp_associate_idle_echo
+            .map(|echo_interval| {
+                let echo_interval =
+                    tokio::time::interval_at(Instant::now() + echo_interval, echo_interval);
+                OptionalInterval::with(echo_interval)
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Bind the udp relay socket, with the client requested port handled as specified by the policy
+///
+/// The returned bool will be set to whether the requested port is used if it has been tried.
+fn bind_udp_relay_socket(
+    bind_ip: IpAddr,
+    port_range: Option<PortRange>,
+    requested_port: Option<u16>,
+    port_policy: SocksProxyUdpClientPortPolicy,
+    buf_conf: SocketBufferConfig,
+    misc_opts: UdpMiscSockOpts,
+) -> ServerTaskResult<(std::net::UdpSocket, SocketAddr, Option<bool>)> {
+    if let Some(port) = requested_port {
+        let require = port_policy == SocksProxyUdpClientPortPolicy::Require;
+        if require {
+            if let Some(range) = port_range {
+                if !range.contains(port) {
+                    return Err(ServerTaskError::ForbiddenByRule(
+                        ServerTaskForbiddenError::UdpPortNotAllowed,
+                    ));
+                }
+            }
+        }
+        let (socket, listen_addr) = g3_socket::udp::new_std_in_range_bind_lazy_connect_prefer(
+            bind_ip, port_range, port, !require, buf_conf, misc_opts,
+        )
+        .map_err(|e| {
+            if require {
+                ServerTaskError::InternalServerError("the requested udp port is not available")
+            } else if port_range.is_some() && e.kind() == io::ErrorKind::AddrNotAvailable {
+                ServerTaskError::InternalServerError(UDP_PORT_RANGE_EXHAUSTED)
+            } else {
+                ServerTaskError::InternalServerError(
+                    "setup udp listen socket with preferred port failed",
+                )
+            }
+        })?;
+        return Ok((socket, listen_addr, Some(listen_addr.port() == port)));
+    }
+
+    let (socket, listen_addr) = if let Some(port_range) = port_range {
+        g3_socket::udp::new_std_in_range_bind_lazy_connect(bind_ip, port_range, buf_conf, misc_opts)
+            .map_err(|e| {
+                if e.kind() == io::ErrorKind::AddrNotAvailable {
+                    ServerTaskError::InternalServerError(UDP_PORT_RANGE_EXHAUSTED)
+                } else {
+                    ServerTaskError::InternalServerError(
+                        "setup udp listen socket with ranged port failed",
+                    )
+                }
+            })?
+    } else {
+        g3_socket::udp::new_std_bind_lazy_connect(Some(bind_ip), buf_conf, misc_opts).map_err(
+            |_| {
+                ServerTaskError::InternalServerError(
+                    "setup udp listen socket with random port failed",
+                )
+            },
+        )?
+    };
+    Ok((socket, listen_addr, None))
+}
+
+/// Get the reply to be sent to the client if the udp relay socket setup failed
+pub(super) fn udp_listen_error_reply(e: &ServerTaskError) -> Socks5Reply {
+    match e {
+        ServerTaskError::ForbiddenByRule(_) => Socks5Reply::ForbiddenByRule,
+        _ => Socks5Reply::GeneralServerFailure,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::server::UdpPortRangeMap;
+    use g3_types::metrics::NodeName;
+    use std::net::Ipv4Addr;
+    use yaml_rust::YamlLoader;
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    fn bind(
+        port_range: Option<PortRange>,
+        requested_port: Option<u16>,
+        port_policy: SocksProxyUdpClientPortPolicy,
+    ) -> ServerTaskResult<(std::net::UdpSocket, SocketAddr, Option<bool>)> {
+        bind_udp_relay_socket(
+            LOCALHOST,
+            port_range,
+            requested_port,
+            port_policy,
+            SocketBufferConfig::default(),
+            UdpMiscSockOpts::default(),
+        )
+    }
+
+    fn free_port(port_range: PortRange) -> u16 {
+        let (_socket, listen_addr, _) = bind(
+            Some(port_range),
+            None,
+            SocksProxyUdpClientPortPolicy::Ignore,
+        )
+        .unwrap();
+        listen_addr.port()
+    }
+
+    #[test]
+    fn requested_port_available() {
+        let range = PortRange::new(61000, 65000);
+        for policy in [
+            SocksProxyUdpClientPortPolicy::Try,
+            SocksProxyUdpClientPortPolicy::Require,
+        ] {
+            let port = free_port(range);
+            let (_socket, listen_addr, honored) = bind(Some(range), Some(port), policy).unwrap();
+            assert_eq!(listen_addr.port(), port);
+            assert_eq!(honored, Some(true));
+        }
+
+        let port = free_port(range);
+        let (_socket, listen_addr, honored) =
+            bind(None, Some(port), SocksProxyUdpClientPortPolicy::Require).unwrap();
+        assert_eq!(listen_addr.port(), port);
+        assert_eq!(honored, Some(true));
+    }
+
+    #[test]
+    fn requested_port_conflict_try() {
+        let range = PortRange::new(61000, 65000);
+        let (_used, used_addr, _) =
+            bind(Some(range), None, SocksProxyUdpClientPortPolicy::Ignore).unwrap();
+        let port = used_addr.port();
+
+        let (_socket, listen_addr, honored) =
+            bind(Some(range), Some(port), SocksProxyUdpClientPortPolicy::Try).unwrap();
+        assert_ne!(listen_addr.port(), port);
+        assert!(range.contains(listen_addr.port()));
+        assert_eq!(honored, Some(false));
+
+        // out of range
+        let (_socket, listen_addr, honored) =
+            bind(Some(range), Some(60000), SocksProxyUdpClientPortPolicy::Try).unwrap();
+        assert!(range.contains(listen_addr.port()));
+        assert_eq!(honored, Some(false));
+    }
+
+    #[test]
+    fn requested_port_require_failed() {
+        let range = PortRange::new(61000, 65000);
+        let (_used, used_addr, _) =
+            bind(Some(range), None, SocksProxyUdpClientPortPolicy::Ignore).unwrap();
+
+        let e = bind(
+            Some(range),
+            Some(used_addr.port()),
+            SocksProxyUdpClientPortPolicy::Require,
+        )
+        .unwrap_err();
+        assert!(matches!(e, ServerTaskError::InternalServerError(_)));
+        assert!(matches!(
+            udp_listen_error_reply(&e),
+            Socks5Reply::GeneralServerFailure
+        ));
+
+        let e = bind(
+            Some(range),
+            Some(60000),
+            SocksProxyUdpClientPortPolicy::Require,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            e,
+            ServerTaskError::ForbiddenByRule(ServerTaskForbiddenError::UdpPortNotAllowed)
+        ));
+        assert!(matches!(
+            udp_listen_error_reply(&e),
+            Socks5Reply::ForbiddenByRule
+        ));
+    }
+
+    #[test]
+    fn udp_port_range_tiers() {
+        let range = PortRange::new(61000, 65000);
+        let gold_port = free_port(range);
+        let mut silver_port = free_port(range);
+        while silver_port == gold_port {
+            silver_port = free_port(range);
+        }
+
+        let yaml = YamlLoader::load_from_str(&format!(
+            r#"
+- name: gold
+  user: ^gold-
+  port_range: {gold_port}
+- name: silver
+  user: ^silver-
+  port_range: {silver_port}
+"#
+        ))
+        .unwrap()
+        .pop()
+        .unwrap();
+        let map = UdpPortRangeMap::parse_yaml(&yaml).unwrap();
+        let group = NodeName::default();
+        let gold = map.select("gold-a", &group).unwrap();
+        let silver = map.select("silver-a", &group).unwrap();
+        assert!(map.select("bronze-a", &group).is_none());
+
+        let (_gold_socket, listen_addr, _) = bind(
+            Some(gold.port_range()),
+            None,
+            SocksProxyUdpClientPortPolicy::Ignore,
+        )
+        .unwrap();
+        assert_eq!(listen_addr.port(), gold_port);
+
+        // the gold tier is exhausted, and the silver port should never be used
+        let e = bind(
+            Some(gold.port_range()),
+            None,
+            SocksProxyUdpClientPortPolicy::Ignore,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            e,
+            ServerTaskError::InternalServerError(UDP_PORT_RANGE_EXHAUSTED)
+        ));
+        assert!(matches!(
+            udp_listen_error_reply(&e),
+            Socks5Reply::GeneralServerFailure
+        ));
+        let e = bind(
+            Some(gold.port_range()),
+            Some(silver_port),
+            SocksProxyUdpClientPortPolicy::Try,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            e,
+            ServerTaskError::InternalServerError(UDP_PORT_RANGE_EXHAUSTED)
+        ));
+
+        // the silver tier is not affected
+        let (_silver_socket, listen_addr, _) = bind(
+            Some(silver.port_range()),
+            None,
+            SocksProxyUdpClientPortPolicy::Ignore,
+        )
+        .unwrap();
+        assert_eq!(listen_addr.port(), silver_port);
+        let e = bind(
+            Some(silver.port_range()),
+            None,
+            SocksProxyUdpClientPortPolicy::Ignore,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            e,
+            ServerTaskError::InternalServerError(UDP_PORT_RANGE_EXHAUSTED)
+        ));
+    }
 }
@@ -0,0 +1,97 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::serve::ServerTaskError;
+
+/// Wait until the tcp control connection of the udp associate task is closed.
+///
+/// No data is expected from the client after the negotiation, so this will return at once
+/// if the connection is closed or any error occurred, then the udp relay should be torn down.
+pub(super) async fn wait_tcp_control_closed<R>(clt_tcp_r: &mut R) -> ServerTaskError
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buf: [u8; 4] = [0; 4];
+    match clt_tcp_r.read(&mut buf).await {
+        Ok(0) => ServerTaskError::ControlClosed,
+        Ok(_) => {
+            ServerTaskError::InvalidClientProtocol("unexpected data received from the tcp channel")
+        }
+        Err(e) => ServerTaskError::ClientTcpReadFailed(e),
+    }
+}
+
+/// Close the tcp control connection if the udp relay failed, so the client will know it at once
+pub(super) async fn close_tcp_control<W>(clt_tcp_w: &mut W, e: &ServerTaskError)
+where
+    W: AsyncWrite + Unpin,
+{
+    match e {
+        ServerTaskError::ControlClosed | ServerTaskError::ClientTcpReadFailed(_) => {}
+        _ => {
+            let _ = clt_tcp_w.shutdown().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use tokio::net::{TcpListener, TcpStream, UdpSocket};
+
+    async fn tcp_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, server) = tokio::join!(TcpStream::connect(addr), listener.accept());
+        (client.unwrap(), server.unwrap().0)
+    }
+
+    #[tokio::test]
+    async fn release_on_control_closed() {
+        let (client, server) = tcp_pair().await;
+        let relay_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let relay_addr = relay_socket.local_addr().unwrap();
+
+        let relay = tokio::spawn(async move {
+            let (mut clt_tcp_r, _clt_tcp_w) = server.into_split();
+            let mut buf = [0u8; 16];
+            tokio::select! {
+                e = wait_tcp_control_closed(&mut clt_tcp_r) => e,
+                _ = relay_socket.recv(&mut buf) => {
+                    ServerTaskError::InternalServerError("unexpected udp packet")
+                }
+            }
+        });
+
+        drop(client);
+        let e = tokio::time::timeout(Duration::from_secs(1), relay)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(e, ServerTaskError::ControlClosed));
+        // the relay socket should have been released
+        UdpSocket::bind(relay_addr).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn close_on_relay_error() {
+        let (mut client, server) = tcp_pair().await;
+        let (_clt_tcp_r, mut clt_tcp_w) = server.into_split();
+
+        let e = ServerTaskError::ClosedByUpstream;
+        close_tcp_control(&mut clt_tcp_w, &e).await;
+
+        let mut buf = [0u8; 4];
+        let nr = tokio::time::timeout(Duration::from_secs(1), client.read(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(nr, 0);
+    }
+}
@@ -8,6 +8,10 @@ use crate::config::server::socks_proxy::SocksProxyServerConfig;
 
 mod common;
 pub(super) use common::CommonTaskContext;
+use common::udp_listen_error_reply;
+
+mod control;
+use control::{close_tcp_control, wait_tcp_control_closed};
 
 mod negotiation;
 mod tcp_connect;
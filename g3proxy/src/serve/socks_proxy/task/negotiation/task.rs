@@ -9,6 +9,7 @@ use log::debug;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, BufReader};
 use tokio::time::Instant;
 
+use g3_daemon::ban::ClientViolation;
 use g3_io_ext::{AsyncStream, LimitedReader, LimitedWriter};
 use g3_socks::{SocksAuthMethod, SocksCommand, SocksVersion, v4a, v5};
 
@@ -72,6 +73,9 @@ impl SocksProxyNegotiationTask {
         let client_addr = self.ctx.client_addr();
         if let Err(e) = self.run(BufReader::new(clt_r), clt_w).await {
             debug!("Error handling client {client_addr}: {e}");
+            if matches!(e, ServerTaskError::ClientAuthFailed) {
+                g3_daemon::ban::report(ClientViolation::AuthFailed, client_addr.ip());
+            }
             // TODO handle negotiation error
         }
     }
@@ -150,11 +154,12 @@ impl SocksProxyNegotiationTask {
             user_ctx
         });
 
-        let task_notes = ServerTaskNotes::new(
+        let mut task_notes = ServerTaskNotes::new(
             self.ctx.cc_info.clone(),
             user_ctx,
             self.time_accepted.elapsed(),
         );
+        task_notes.set_log_sample(self.ctx.task_log_sampler.sample());
         match req.command {
             SocksCommand::TcpConnect => {
                 let task = SocksProxyTcpConnectTask::new(
@@ -282,11 +287,12 @@ impl SocksProxyNegotiationTask {
 
         let req = v5::Socks5Request::recv(&mut clt_r).await?;
 
-        let task_notes = ServerTaskNotes::new(
+        let mut task_notes = ServerTaskNotes::new(
             self.ctx.cc_info.clone(),
             user_ctx,
             self.time_accepted.elapsed(),
         );
+        task_notes.set_log_sample(self.ctx.task_log_sampler.sample());
         match req.command {
             SocksCommand::TcpConnect => {
                 let task = SocksProxyTcpConnectTask::new(
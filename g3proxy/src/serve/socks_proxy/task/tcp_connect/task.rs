@@ -20,13 +20,13 @@ use g3_types::net::{ProxyRequestType, UpstreamAddr};
 use super::{CommonTaskContext, TcpConnectTaskCltWrapperStats};
 use crate::audit::AuditContext;
 use crate::auth::User;
-use crate::config::server::ServerConfig;
+use crate::config::server::{ClientErrorAction, ClientErrorClass, ServerConfig};
 use crate::inspect::{StreamInspectContext, StreamTransitTask};
 use crate::log::task::tcp_connect::TaskLogForTcpConnect;
 use crate::module::tcp_connect::{TcpConnectTaskConf, TcpConnectTaskNotes};
 use crate::serve::{
-    ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes, ServerTaskResult,
-    ServerTaskStage,
+    ServerIdleOverride, ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes,
+    ServerTaskResult, ServerTaskStage,
 };
 
 pub(crate) struct SocksProxyTcpConnectTask {
@@ -37,6 +37,7 @@ pub(crate) struct SocksProxyTcpConnectTask {
     tcp_notes: TcpConnectTaskNotes,
     task_stats: Arc<TcpStreamTaskStats>,
     audit_ctx: AuditContext,
+    idle_override: Option<Arc<ServerIdleOverride>>,
     started: bool,
 }
 
@@ -67,6 +68,7 @@ impl SocksProxyTcpConnectTask {
                 site_req_stats.conn_total.add_socks();
             }
         }
+        let idle_override = ctx.idle_overrides.select_for_task(&mut task_notes);
         SocksProxyTcpConnectTask {
             socks_version,
             ctx,
@@ -75,6 +77,7 @@ impl SocksProxyTcpConnectTask {
             tcp_notes: TcpConnectTaskNotes::default(),
             task_stats: Arc::new(TcpStreamTaskStats::default()),
             audit_ctx,
+            idle_override,
             started: false,
         }
     }
@@ -88,6 +91,9 @@ impl SocksProxyTcpConnectTask {
                 upstream: &self.upstream,
                 task_notes: &self.task_notes,
                 tcp_notes: &self.tcp_notes,
+                connect_attempts: None,
+                connect_timeout: None,
+                original_dst: None,
                 client_rd_bytes: self.task_stats.clt.read.get_bytes(),
                 client_wr_bytes: self.task_stats.clt.write.get_bytes(),
                 remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
@@ -144,23 +150,44 @@ impl SocksProxyTcpConnectTask {
         }
     }
 
-    async fn reply_forbidden<W>(&self, clt_w: &mut W)
+    async fn reply_forbidden<W>(&mut self, clt_w: &mut W)
     where
         W: AsyncWrite + Unpin,
     {
-        match self.socks_version {
-            SocksVersion::V4a => {
-                let _ = v4a::SocksV4Reply::RequestRejectedOrFailed.send(clt_w).await;
-            }
-            SocksVersion::V5 => {
-                let _ = v5::Socks5Reply::ForbiddenByRule.send(clt_w).await;
-            }
-            SocksVersion::V6 => {} // TODO socks v6
+        self.reply_client_error(
+            clt_w,
+            Some(ClientErrorClass::IngressDenied),
+            v5::Socks5Reply::ForbiddenByRule,
+        )
+        .await;
+    }
+
+    /// Reply to the client with the configured client error action,
+    /// or the default socks reply if not set
+    async fn reply_client_error<W>(
+        &mut self,
+        clt_w: &mut W,
+        class: Option<ClientErrorClass>,
+        v5_reply: v5::Socks5Reply,
+    ) where
+        W: AsyncWrite + Unpin,
+    {
+        if let Some(action) = crate::serve::client_error::reply_socks_client_error(
+            &self.ctx.server_config.client_error_actions,
+            class,
+            &self.socks_version,
+            v5_reply,
+            &self.ctx.cc_info,
+            clt_w,
+        )
+        .await
+        {
+            self.task_notes.vars_mut().set_client_error_action(action);
         }
     }
 
     async fn handle_server_upstream_acl_action<W>(
-        &self,
+        &mut self,
         action: AclAction,
         clt_w: &mut W,
     ) -> ServerTaskResult<()>
@@ -196,7 +223,7 @@ impl SocksProxyTcpConnectTask {
     }
 
     async fn handle_user_acl_action<W>(
-        &self,
+        &mut self,
         action: AclAction,
         clt_w: &mut W,
         forbidden_error: ServerTaskForbiddenError,
@@ -306,22 +333,15 @@ impl SocksProxyTcpConnectTask {
             .await
         {
             Ok((ups_r, ups_w)) => {
-                self.task_notes.stage = ServerTaskStage::Connected;
+                self.task_notes.mark_connected(&self.tcp_notes.escaper);
                 self.run_connected(clt_r, clt_w, ups_r, ups_w).await
             }
             Err(e) => {
-                match self.socks_version {
-                    SocksVersion::V4a => {
-                        let _ = v4a::SocksV4Reply::RequestRejectedOrFailed
-                            .send(&mut clt_w)
-                            .await;
-                    }
-                    SocksVersion::V5 => {
-                        let _ = v5::Socks5Reply::from(&e).send(&mut clt_w).await;
-                    }
-                    SocksVersion::V6 => {} // TODO socks v6
-                }
-                Err(e.into())
+                let v5_reply = v5::Socks5Reply::from(&e);
+                let e = ServerTaskError::from(e);
+                self.reply_client_error(&mut clt_w, e.client_error_class(), v5_reply)
+                    .await;
+                Err(e)
             }
         }
     }
@@ -408,9 +428,10 @@ impl SocksProxyTcpConnectTask {
                             .unwrap_or_else(|| audit_handle.do_task_audit())
                 })
                 .unwrap_or_else(|| audit_handle.do_task_audit());
+            self.task_notes.vars_mut().set_audit_task(audit_task);
 
             if audit_task {
-                let ctx = StreamInspectContext::new(
+                let mut ctx = StreamInspectContext::new(
                     audit_handle.clone(),
                     self.ctx.server_config.clone(),
                     self.ctx.server_stats.clone(),
@@ -419,6 +440,9 @@ impl SocksProxyTcpConnectTask {
                     &self.task_notes,
                     &self.tcp_notes,
                 );
+                if let Some(idle_override) = &self.idle_override {
+                    ctx.set_idle_override(idle_override);
+                }
                 return crate::inspect::stream::transit_with_inspection(
                     clt_r,
                     clt_w,
@@ -432,7 +456,20 @@ impl SocksProxyTcpConnectTask {
             }
         }
 
-        self.transit_transparent(clt_r, clt_w, ups_r, ups_w).await
+        let r = self.transit_transparent(clt_r, clt_w, ups_r, ups_w).await;
+        if let Err(e) = &r {
+            if let Some(action) = self.established_client_error_action(e) {
+                self.task_notes.vars_mut().set_client_error_action(action);
+            }
+        }
+        r
+    }
+
+    fn established_client_error_action(&self, e: &ServerTaskError) -> Option<ClientErrorAction> {
+        crate::serve::client_error::established_client_error_action(
+            &self.ctx.server_config.client_error_actions,
+            e,
+        )
     }
 
     fn update_clt<CR, CW>(&mut self, clt_r: &mut LimitedReader<CR>, clt_w: &mut LimitedWriter<CW>)
@@ -481,11 +518,17 @@ impl StreamTransitTask for SocksProxyTcpConnectTask {
     }
 
     fn idle_check_interval(&self) -> IdleInterval {
-        self.ctx.idle_wheel.register()
+        match &self.idle_override {
+            Some(idle_override) => idle_override.idle_wheel.register(),
+            None => self.ctx.idle_wheel.register(),
+        }
     }
 
     fn max_idle_count(&self) -> usize {
-        self.ctx.server_config.task_idle_max_count
+        match &self.idle_override {
+            Some(idle_override) => idle_override.max_idle_count,
+            None => self.ctx.server_config.task_idle_max_count,
+        }
     }
 
     fn log_client_shutdown(&self) {
@@ -517,4 +560,10 @@ impl StreamTransitTask for SocksProxyTcpConnectTask {
     fn user(&self) -> Option<&User> {
         self.task_notes.user_ctx().map(|ctx| ctx.user().as_ref())
     }
+
+    fn on_transit_error(&self, e: &ServerTaskError) {
+        if let Some(action) = self.established_client_error_action(e) {
+            crate::serve::client_error::prepare_client_error_action(action, &self.ctx.cc_info);
+        }
+    }
 }
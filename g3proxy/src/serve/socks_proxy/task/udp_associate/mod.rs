@@ -3,7 +3,10 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use super::{CommonTaskContext, SocksProxyServerStats};
+use super::{
+    CommonTaskContext, SocksProxyServerStats, close_tcp_control, udp_listen_error_reply,
+    wait_tcp_control_closed,
+};
 
 mod task;
 pub(super) use task::SocksProxyUdpAssociateTask;
@@ -14,4 +17,96 @@ mod stats;
 
 use recv::Socks5UdpAssociateClientRecv;
 use send::Socks5UdpAssociateClientSend;
-use stats::{UdpAssociateTaskCltWrapperStats, UdpAssociateTaskStats};
+use stats::{
+    UdpAssociateTaskCltWrapperStats, UdpAssociateTaskOversizeWrapperStats, UdpAssociateTaskStats,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use tokio::net::UdpSocket;
+
+    use g3_io_ext::{AsyncUdpRecv, LimitedUdpRecv, LimitedUdpSend, UdpRelayClientSend};
+    use g3_socks::v5::{UdpInput, UdpOutput};
+    use g3_types::metrics::NodeName;
+    use g3_types::net::UpstreamAddr;
+
+    #[tokio::test]
+    async fn overhead_split() {
+        let server_stats = Arc::new(SocksProxyServerStats::new(
+            &NodeName::from_str("socks").unwrap(),
+        ));
+        let task_stats = Arc::new(UdpAssociateTaskStats::default());
+        let wrapper_stats = Arc::new(UdpAssociateTaskCltWrapperStats::new(
+            &server_stats,
+            &task_stats,
+        ));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let client_addr = client.local_addr().unwrap();
+        client.connect(server.local_addr().unwrap()).await.unwrap();
+
+        let (clt_r, clt_w) = g3_io_ext::split_udp(server);
+        let mut clt_r = LimitedUdpRecv::local_limited(clt_r, 0, 0, 0, wrapper_stats.clone());
+        clt_r.set_header_len_fn(UdpInput::calc_header_len);
+        let mut clt_w = LimitedUdpSend::local_limited(clt_w, 0, 0, 0, wrapper_stats);
+        clt_w.set_header_iov(1);
+        let mut clt_w = Socks5UdpAssociateClientSend::new(clt_w, client_addr, None);
+
+        // the header length varies with the peer address in associate mode
+        let peers = [
+            (
+                UpstreamAddr::from_ip_and_port(IpAddr::V4(Ipv4Addr::LOCALHOST), 53),
+                100,
+            ),
+            (
+                UpstreamAddr::from_ip_and_port(IpAddr::V6(Ipv6Addr::LOCALHOST), 53),
+                200,
+            ),
+            (
+                UpstreamAddr::from_host_str_and_port("www.example.net", 53).unwrap(),
+                0,
+            ),
+        ];
+        let mut buf = vec![0u8; 1024];
+
+        for (peer, size) in &peers {
+            let header_len = UdpOutput::calc_header_len(peer);
+            let mut packet = vec![0u8; header_len + size];
+            UdpOutput::generate_header(&mut packet, peer);
+            client.send(&packet).await.unwrap();
+            let nr = poll_fn(|cx| clt_r.poll_recv(cx, &mut buf)).await.unwrap();
+            assert_eq!(nr, packet.len());
+            let (off, ups) = UdpInput::parse_header(&buf[..nr]).unwrap();
+            assert_eq!(off, header_len);
+            assert_eq!(&ups, peer);
+
+            let payload = &packet[header_len..];
+            let nw = poll_fn(|cx| clt_w.poll_send_packet(cx, payload, peer))
+                .await
+                .unwrap();
+            assert_eq!(nw, packet.len());
+            let nr = client.recv(&mut buf).await.unwrap();
+            assert_eq!(nr, packet.len());
+        }
+
+        // 10 + 22 + 22 bytes of headers
+        assert_eq!(task_stats.clt.recv.get_packets(), 3);
+        assert_eq!(task_stats.clt.recv.get_bytes(), 300);
+        assert_eq!(task_stats.clt.recv.get_overhead_bytes(), 54);
+        assert_eq!(task_stats.clt.send.get_packets(), 3);
+        assert_eq!(task_stats.clt.send.get_bytes(), 300);
+        assert_eq!(task_stats.clt.send.get_overhead_bytes(), 54);
+
+        let io = server_stats.io_udp.snapshot();
+        assert_eq!((io.in_bytes, io.out_bytes), (300, 300));
+        let overhead = server_stats.udp_overhead.snapshot();
+        assert_eq!((overhead.in_bytes, overhead.out_bytes), (54, 54));
+    }
+}
@@ -25,12 +25,15 @@ use g3_types::net::UpstreamAddr;
 
 use super::CommonTaskContext;
 use crate::auth::UserContext;
+use crate::module::udp_relay::UdpDestVerifyGate;
 
 pub(super) struct Socks5UdpAssociateClientRecv<T> {
     inner: T,
     client_addr: SocketAddr,
+    local_ip: Option<IpAddr>,
     ctx: Arc<CommonTaskContext>,
     user_ctx: Option<UserContext>,
+    verify_gate: Option<UdpDestVerifyGate>,
 }
 
 impl<T> Socks5UdpAssociateClientRecv<T>
@@ -48,8 +51,22 @@ where
         Socks5UdpAssociateClientRecv {
             inner,
             client_addr,
+            local_ip: None,
             ctx: Arc::clone(ctx),
             user_ctx: user_ctx.cloned(),
+            verify_gate: None,
+        }
+    }
+
+    pub(super) fn set_verify_gate(&mut self, gate: UdpDestVerifyGate) {
+        self.verify_gate = Some(gate);
+    }
+
+    /// Return true if the packet can be sent to the destination now
+    pub(super) fn check_dest_verify(&mut self, payload: &[u8], upstream: &UpstreamAddr) -> bool {
+        match &mut self.verify_gate {
+            Some(gate) => gate.check_packet(payload, upstream),
+            None => true,
         }
     }
 
@@ -61,6 +78,14 @@ where
         &mut self.inner
     }
 
+    /// The local ip of the first packet if the socket is bound to the unspecified address.
+    ///
+    /// The socket should not be connected in this case, as that will fix the local ip to the one
+    /// selected by the kernel, and all replies should be sent from this ip.
+    pub(super) fn local_ip(&self) -> Option<IpAddr> {
+        self.local_ip
+    }
+
     fn handle_user_upstream_acl_action(
         &self,
         action: AclAction,
@@ -130,7 +155,44 @@ where
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<Result<(usize, usize, UpstreamAddr), UdpRelayClientError>> {
-        let nr = ready!(self.inner.poll_recv(cx, buf)).map_err(UdpRelayClientError::RecvFailed)?;
+        if self.verify_gate.is_none() {
+            return self.poll_recv_one(cx, buf);
+        }
+
+        loop {
+            // the released packets should be sent before the new ones
+            if let Some(gate) = &mut self.verify_gate {
+                if let Poll::Ready((upstream, packet)) = gate.poll_release(cx) {
+                    let nr = packet.len();
+                    buf[..nr].copy_from_slice(&packet);
+                    return Poll::Ready(Ok((0, nr, upstream)));
+                }
+            }
+
+            let (off, nr, upstream) = ready!(self.poll_recv_one(cx, buf))?;
+            if self.check_dest_verify(&buf[off..nr], &upstream) {
+                return Poll::Ready(Ok((off, nr, upstream)));
+            }
+        }
+    }
+
+    fn poll_recv_one(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<(usize, usize, UpstreamAddr), UdpRelayClientError>> {
+        let nr = if self.local_ip.is_some() {
+            // the socket is not connected, so drop packets from other peers
+            loop {
+                let (nr, client_addr) = ready!(self.inner.poll_recv_from(cx, buf))
+                    .map_err(UdpRelayClientError::RecvFailed)?;
+                if client_addr == self.client_addr {
+                    break nr;
+                }
+            }
+        } else {
+            ready!(self.inner.poll_recv(cx, buf)).map_err(UdpRelayClientError::RecvFailed)?
+        };
 
         let (off, upstream) = UdpInput::parse_header(buf)
             .map_err(|e| UdpRelayClientError::InvalidPacket(e.to_string()))?;
@@ -149,8 +211,8 @@ where
         let expected_port = self.client_addr.port();
         let set_client = expected_ip.is_unspecified() || expected_port == 0;
 
-        let (nr, client_addr) =
-            ready!(self.inner.poll_recv_from(cx, buf)).map_err(UdpRelayClientError::RecvFailed)?;
+        let (nr, client_addr, local_ip) = ready!(self.inner.poll_recv_from_to(cx, buf))
+            .map_err(UdpRelayClientError::RecvFailed)?;
 
         if set_client {
             if !expected_ip.is_unspecified() && expected_ip != client_addr.ip() {
@@ -181,6 +243,8 @@ where
         }
 
         self.client_addr = client_addr;
+        // only set if IP_PKTINFO / IPV6_PKTINFO is enabled for wildcard sockets
+        self.local_ip = local_ip;
 
         let (off, upstream) = UdpInput::parse_header(buf)
             .map_err(|e| UdpRelayClientError::InvalidPacket(e.to_string()))?;
@@ -241,6 +305,18 @@ where
     ) -> Poll<Result<usize, UdpRelayClientError>> {
         use g3_io_sys::udp::RecvMsgHdr;
 
+        if self.local_ip.is_some() || self.verify_gate.is_some() {
+            // batch recv can not be used as we need to check the peer address or the destination
+            let Some(p) = packets.first_mut() else {
+                return Poll::Ready(Ok(0));
+            };
+            let buf = p.buf_mut();
+            let (off, nr, ups) = ready!(self.poll_recv(cx, buf))?;
+            let iov = std::io::IoSliceMut::new(buf);
+            UdpRelayPacketMeta::new(&iov, off, nr, ups).set_packet(p);
+            return Poll::Ready(Ok(1));
+        }
+
         let mut hdr_v: Vec<RecvMsgHdr<1>> = packets
             .iter_mut()
             .map(|p| RecvMsgHdr::new([std::io::IoSliceMut::new(p.buf_mut())]))
@@ -254,7 +330,7 @@ where
             let iov = &h.iov[0];
             let (off, ups) = UdpInput::parse_header(&iov[0..h.n_recv])
                 .map_err(|e| UdpRelayClientError::InvalidPacket(e.to_string()))?;
-            r.push(UdpRelayPacketMeta::new(iov, off, h.n_recv, ups))
+            r.push(UdpRelayPacketMeta::new(iov, off, h.n_recv, ups).with_truncated(h.truncated()))
         }
         for (m, p) in r.into_iter().zip(packets.iter_mut()) {
             m.set_packet(p);
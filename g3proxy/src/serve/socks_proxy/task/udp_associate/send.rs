@@ -4,7 +4,7 @@
  */
 
 use std::io::{self, IoSlice};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::task::{Context, Poll, ready};
 
 #[cfg(any(
@@ -25,6 +25,7 @@ use g3_types::net::UpstreamAddr;
 pub(super) struct Socks5UdpAssociateClientSend<T> {
     inner: T,
     client: SocketAddr,
+    local_ip: Option<IpAddr>,
     socks_headers: Vec<SocksUdpHeader>,
 }
 
@@ -32,10 +33,12 @@ impl<T> Socks5UdpAssociateClientSend<T>
 where
     T: AsyncUdpSend,
 {
-    pub(super) fn new(inner: T, client: SocketAddr) -> Self {
+    /// `local_ip` should be set if the socket is bound to the unspecified address and not connected
+    pub(super) fn new(inner: T, client: SocketAddr, local_ip: Option<IpAddr>) -> Self {
         Socks5UdpAssociateClientSend {
             inner,
             client,
+            local_ip,
             socks_headers: vec![SocksUdpHeader::default(); 4],
         }
     }
@@ -52,12 +55,15 @@ where
         from: &UpstreamAddr,
     ) -> Poll<Result<usize, UdpRelayClientError>> {
         let socks_header = self.socks_headers.get_mut(0).unwrap();
-        let hdr = SendMsgHdr::new(
+        let mut hdr = SendMsgHdr::new(
             [IoSlice::new(socks_header.encode(from)), IoSlice::new(buf)],
             Some(self.client),
         );
-        let nw =
-            ready!(self.inner.poll_sendmsg(cx, &hdr)).map_err(UdpRelayClientError::SendFailed)?;
+        let nw = match self.local_ip {
+            Some(ip) => ready!(self.inner.poll_sendmsg_from(cx, &mut hdr, ip)),
+            None => ready!(self.inner.poll_sendmsg(cx, &hdr)),
+        }
+        .map_err(UdpRelayClientError::SendFailed)?;
         if nw == 0 {
             Poll::Ready(Err(UdpRelayClientError::SendFailed(io::Error::new(
                 io::ErrorKind::WriteZero,
@@ -84,15 +90,19 @@ where
         if packets.len() > self.socks_headers.len() {
             self.socks_headers.resize(packets.len(), Default::default());
         }
+        // the socket is not connected if local ip is set
+        let client = self.local_ip.map(|_| self.client);
         let mut msgs = Vec::with_capacity(packets.len());
         for (p, h) in packets.iter().zip(self.socks_headers.iter_mut()) {
-            msgs.push(SendMsgHdr::new(
+            let mut hdr = SendMsgHdr::new(
                 [
                     IoSlice::new(h.encode(p.upstream())),
                     IoSlice::new(p.payload()),
                 ],
-                None,
-            ));
+                client,
+            );
+            hdr.set_src_ip(self.local_ip);
+            msgs.push(hdr);
         }
 
         let count = ready!(self.inner.poll_batch_sendmsg(cx, &mut msgs))
@@ -113,6 +123,14 @@ where
         cx: &mut Context<'_>,
         packets: &[UdpRelayPacket],
     ) -> Poll<Result<usize, UdpRelayClientError>> {
+        if self.local_ip.is_some() {
+            // sendmsg_x only works for connected sockets
+            let Some(p) = packets.first() else {
+                return Poll::Ready(Ok(0));
+            };
+            ready!(self.poll_send_packet(cx, p.payload(), p.upstream()))?;
+            return Poll::Ready(Ok(1));
+        }
         if packets.len() > self.socks_headers.len() {
             self.socks_headers.resize(packets.len(), Default::default());
         }
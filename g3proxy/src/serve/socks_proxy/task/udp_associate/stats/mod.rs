@@ -8,5 +8,6 @@ use super::SocksProxyServerStats;
 mod task;
 mod wrapper;
 
+use task::UdpAssociateOversizeStats;
 pub(super) use task::UdpAssociateTaskStats;
-pub(super) use wrapper::UdpAssociateTaskCltWrapperStats;
+pub(super) use wrapper::{UdpAssociateTaskCltWrapperStats, UdpAssociateTaskOversizeWrapperStats};
@@ -3,11 +3,14 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 use g3_daemon::stat::task::UdpConnectHalfConnectionStats;
+use g3_io_ext::{UdpBatchSizeStats, UdpDuplicateStats};
 
-use crate::module::udp_relay::UdpRelayTaskRemoteStats;
+use crate::module::udp_relay::{UdpDestVerifyStats, UdpRelayTaskRemoteStats};
+use crate::serve::{RunningTaskBatchSize, RunningTaskBytes, RunningTaskStats};
 
 #[derive(Default)]
 pub(crate) struct UdpAssociateClientSideStats {
@@ -45,10 +48,79 @@ pub(crate) struct UdpAssociateRemoteSideStats {
     pub(crate) send: UdpAssociateRemoteSideHalfStats,
 }
 
+#[derive(Default)]
+pub(crate) struct UdpAssociateBatchSizeStats {
+    size: AtomicUsize,
+}
+
+impl UdpAssociateBatchSizeStats {
+    pub(crate) fn get(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+}
+
+impl UdpBatchSizeStats for UdpAssociateBatchSizeStats {
+    fn set_batch_size(&self, size: usize) {
+        self.size.store(size, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct UdpAssociateOversizeStats {
+    truncated: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl UdpAssociateOversizeStats {
+    pub(crate) fn get_truncated(&self) -> u64 {
+        self.truncated.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn get_dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn add_truncated(&self) {
+        self.truncated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn add_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct UdpAssociateTaskStats {
     pub(crate) clt: UdpAssociateClientSideStats,
     pub(crate) ups: UdpAssociateRemoteSideStats,
+    pub(crate) clt_recv_batch: Arc<UdpAssociateBatchSizeStats>,
+    pub(crate) ups_recv_batch: Arc<UdpAssociateBatchSizeStats>,
+    pub(crate) clt_oversize: UdpAssociateOversizeStats,
+    pub(crate) ups_oversize: UdpAssociateOversizeStats,
+    clt_dup_dropped: AtomicU64,
+    clt_verify_dropped: AtomicU64,
+}
+
+impl UdpAssociateTaskStats {
+    pub(crate) fn get_clt_dup_dropped(&self) -> u64 {
+        self.clt_dup_dropped.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn get_clt_verify_dropped(&self) -> u64 {
+        self.clt_verify_dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl UdpDuplicateStats for UdpAssociateTaskStats {
+    fn add_duplicate_dropped(&self) {
+        self.clt_dup_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl UdpDestVerifyStats for UdpAssociateTaskStats {
+    fn add_verify_dropped(&self) {
+        self.clt_verify_dropped.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 impl UdpRelayTaskRemoteStats for UdpAssociateTaskStats {
@@ -68,3 +140,21 @@ impl UdpRelayTaskRemoteStats for UdpAssociateTaskStats {
         self.ups.send.add_packets(n);
     }
 }
+
+impl RunningTaskStats for UdpAssociateTaskStats {
+    fn io_bytes(&self) -> RunningTaskBytes {
+        RunningTaskBytes {
+            clt_rd: self.clt.recv.get_bytes(),
+            clt_wr: self.clt.send.get_bytes(),
+            ups_rd: self.ups.recv.get_bytes(),
+            ups_wr: self.ups.send.get_bytes(),
+        }
+    }
+
+    fn batch_size(&self) -> RunningTaskBatchSize {
+        RunningTaskBatchSize {
+            clt_recv: self.clt_recv_batch.get(),
+            ups_recv: self.ups_recv_batch.get(),
+        }
+    }
+}
@@ -4,10 +4,11 @@
  */
 
 use std::sync::Arc;
+use std::time::Duration;
 
-use g3_io_ext::{LimitedRecvStats, LimitedSendStats};
+use g3_io_ext::{LimitedRecvStats, LimitedSendStats, UdpOversizeStats};
 
-use super::{SocksProxyServerStats, UdpAssociateTaskStats};
+use super::{SocksProxyServerStats, UdpAssociateOversizeStats, UdpAssociateTaskStats};
 use crate::auth::UserTrafficStats;
 
 trait UdpAssociateTaskCltStatsWrapper {
@@ -84,6 +85,16 @@ impl LimitedRecvStats for UdpAssociateTaskCltWrapperStats {
         self.task.clt.recv.add_packets(n);
         self.others.iter().for_each(|s| s.add_recv_packets(n));
     }
+
+    fn add_recv_overhead_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.server.udp_overhead.add_in_bytes(size);
+        self.task.clt.recv.add_overhead_bytes(size);
+    }
+
+    fn add_recv_throttled(&self, time: Duration) {
+        self.task.clt.recv.add_throttled(time);
+    }
 }
 
 impl LimitedSendStats for UdpAssociateTaskCltWrapperStats {
@@ -99,4 +110,65 @@ impl LimitedSendStats for UdpAssociateTaskCltWrapperStats {
         self.task.clt.send.add_packets(n);
         self.others.iter().for_each(|s| s.add_send_packets(n));
     }
+
+    fn add_send_overhead_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.server.udp_overhead.add_out_bytes(size);
+        self.task.clt.send.add_overhead_bytes(size);
+    }
+
+    fn add_send_throttled(&self, time: Duration) {
+        self.task.clt.send.add_throttled(time);
+    }
+}
+
+/// Update the oversize counters of the server and the task for one side of the relay
+pub(crate) struct UdpAssociateTaskOversizeWrapperStats {
+    server: Arc<SocksProxyServerStats>,
+    task: Arc<UdpAssociateTaskStats>,
+    client_side: bool,
+}
+
+impl UdpAssociateTaskOversizeWrapperStats {
+    pub(crate) fn new_client_side(
+        server: &Arc<SocksProxyServerStats>,
+        task: &Arc<UdpAssociateTaskStats>,
+    ) -> Self {
+        UdpAssociateTaskOversizeWrapperStats {
+            server: Arc::clone(server),
+            task: Arc::clone(task),
+            client_side: true,
+        }
+    }
+
+    pub(crate) fn new_remote_side(
+        server: &Arc<SocksProxyServerStats>,
+        task: &Arc<UdpAssociateTaskStats>,
+    ) -> Self {
+        UdpAssociateTaskOversizeWrapperStats {
+            server: Arc::clone(server),
+            task: Arc::clone(task),
+            client_side: false,
+        }
+    }
+
+    fn task_side(&self) -> &UdpAssociateOversizeStats {
+        if self.client_side {
+            &self.task.clt_oversize
+        } else {
+            &self.task.ups_oversize
+        }
+    }
+}
+
+impl UdpOversizeStats for UdpAssociateTaskOversizeWrapperStats {
+    fn add_truncated(&self) {
+        self.server.udp_oversize.add_truncated();
+        self.task_side().add_truncated();
+    }
+
+    fn add_oversize_dropped(&self) {
+        self.server.udp_oversize.add_dropped();
+        self.task_side().add_dropped();
+    }
 }
@@ -4,33 +4,34 @@
  */
 
 use std::future::poll_fn;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 
 use slog::Logger;
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::UdpSocket;
 
 use g3_io_ext::{
-    LimitedUdpRecv, LimitedUdpSend, UdpRecvHalf, UdpRelayClientRecv, UdpRelayClientSend,
-    UdpRelayClientToRemote, UdpRelayError, UdpRelayRemoteRecv, UdpRelayRemoteSend,
-    UdpRelayRemoteToClient, UdpSendHalf,
+    IdleWheel, LimitedUdpRecv, LimitedUdpSend, UdpDuplicateFilter, UdpRecvHalf, UdpRelayClientRecv,
+    UdpRelayClientSend, UdpRelayClientToRemote, UdpRelayError, UdpRelayRemoteRecv,
+    UdpRelayRemoteSend, UdpRelayRemoteToClient, UdpSendHalf,
 };
-use g3_socks::v5::Socks5Reply;
+use g3_socks::v5::{Socks5Reply, UdpInput};
 use g3_types::acl::AclAction;
 use g3_types::net::{ProxyRequestType, UpstreamAddr};
 
 use super::{
     CommonTaskContext, Socks5UdpAssociateClientRecv, Socks5UdpAssociateClientSend,
-    UdpAssociateTaskCltWrapperStats, UdpAssociateTaskStats,
+    UdpAssociateTaskCltWrapperStats, UdpAssociateTaskOversizeWrapperStats, UdpAssociateTaskStats,
+    close_tcp_control, wait_tcp_control_closed,
 };
 use crate::config::server::ServerConfig;
 use crate::log::escape::udp_sendto::EscapeLogForUdpRelaySendto;
 use crate::log::task::udp_associate::TaskLogForUdpAssociate;
-use crate::module::udp_relay::{UdpRelayTaskConf, UdpRelayTaskNotes};
+use crate::module::udp_relay::{UdpDestVerifyGate, UdpRelayTaskConf, UdpRelayTaskNotes};
 use crate::serve::{
-    ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes, ServerTaskResult,
-    ServerTaskStage,
+    RunningTaskGuard, ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes,
+    ServerTaskResult, ServerTaskStage,
 };
 
 pub(crate) struct SocksProxyUdpAssociateTask {
@@ -41,7 +42,10 @@ pub(crate) struct SocksProxyUdpAssociateTask {
     task_stats: Arc<UdpAssociateTaskStats>,
     udp_listen_addr: Option<SocketAddr>,
     udp_client_addr: Option<SocketAddr>,
+    udp_port_honored: Option<bool>,
+    idle_wheel: Arc<IdleWheel>,
     max_idle_count: usize,
+    running_task: Option<RunningTaskGuard>,
     started: bool,
 }
 
@@ -57,13 +61,18 @@ impl Drop for SocksProxyUdpAssociateTask {
 impl SocksProxyUdpAssociateTask {
     pub(crate) fn new(
         ctx: CommonTaskContext,
-        notes: ServerTaskNotes,
+        mut notes: ServerTaskNotes,
         udp_client_addr: Option<SocketAddr>,
     ) -> Self {
+        let idle_override = ctx.idle_overrides.select_for_task(&mut notes);
         let max_idle_count = notes
             .user_ctx()
             .and_then(|c| c.user().task_max_idle_count())
+            .or(idle_override.as_ref().map(|o| o.max_idle_count))
             .unwrap_or(ctx.server_config.task_idle_max_count);
+        let idle_wheel = idle_override
+            .map(|o| o.idle_wheel.clone())
+            .unwrap_or_else(|| ctx.idle_wheel.clone());
         SocksProxyUdpAssociateTask {
             ctx: Arc::new(ctx),
             initial_peer: UpstreamAddr::empty(),
@@ -72,7 +81,10 @@ impl SocksProxyUdpAssociateTask {
             task_stats: Arc::new(UdpAssociateTaskStats::default()),
             udp_listen_addr: None,
             udp_client_addr,
+            udp_port_honored: None,
+            idle_wheel,
             max_idle_count,
+            running_task: None,
             started: false,
         }
     }
@@ -88,16 +100,28 @@ impl SocksProxyUdpAssociateTask {
                 tcp_client_addr: self.ctx.client_addr(),
                 udp_listen_addr: self.udp_listen_addr,
                 udp_client_addr: self.udp_client_addr,
+                udp_port_policy: self.ctx.server_config.udp_honor_client_port,
+                udp_port_honored: self.udp_port_honored,
                 initial_peer: &self.initial_peer,
                 udp_notes: &self.udp_notes,
                 client_rd_bytes: self.task_stats.clt.recv.get_bytes(),
                 client_rd_packets: self.task_stats.clt.recv.get_packets(),
+                client_rd_overhead: self.task_stats.clt.recv.get_overhead_bytes(),
                 client_wr_bytes: self.task_stats.clt.send.get_bytes(),
                 client_wr_packets: self.task_stats.clt.send.get_packets(),
+                client_wr_overhead: self.task_stats.clt.send.get_overhead_bytes(),
+                client_dup_dropped: self.task_stats.get_clt_dup_dropped(),
+                client_verify_dropped: self.task_stats.get_clt_verify_dropped(),
+                client_truncated: self.task_stats.clt_oversize.get_truncated(),
+                client_oversize_dropped: self.task_stats.clt_oversize.get_dropped(),
+                client_rd_throttled: self.task_stats.clt.recv.get_throttled(),
+                client_wr_throttled: self.task_stats.clt.send.get_throttled(),
                 remote_rd_bytes: self.task_stats.ups.recv.get_bytes(),
                 remote_rd_packets: self.task_stats.ups.recv.get_packets(),
                 remote_wr_bytes: self.task_stats.ups.send.get_bytes(),
                 remote_wr_packets: self.task_stats.ups.send.get_packets(),
+                remote_truncated: self.task_stats.ups_oversize.get_truncated(),
+                remote_oversize_dropped: self.task_stats.ups_oversize.get_dropped(),
             })
     }
 
@@ -133,6 +157,11 @@ impl SocksProxyUdpAssociateTask {
             }
         }
 
+        self.running_task = Some(
+            self.ctx
+                .running_tasks
+                .register(&self.task_notes, self.task_stats.clone()),
+        );
         self.started = true;
     }
 
@@ -225,12 +254,17 @@ impl SocksProxyUdpAssociateTask {
         self.task_notes.stage = ServerTaskStage::Preparing;
         let clt_socket = match self
             .ctx
-            .setup_udp_listen(self.udp_client_addr, &self.task_notes)
+            .setup_udp_listen(
+                self.udp_client_addr,
+                self.ctx.server_config.udp_honor_client_port,
+                &mut self.task_notes,
+            )
             .await
         {
-            Ok((udp_listen_addr, socket)) => {
+            Ok((udp_listen_addr, socket, port_honored)) => {
                 self.task_notes.stage = ServerTaskStage::Replying;
                 self.udp_listen_addr = Some(udp_listen_addr);
+                self.udp_port_honored = port_honored;
                 let udp_echo_addr = self
                     .ctx
                     .server_config
@@ -242,7 +276,7 @@ impl SocksProxyUdpAssociateTask {
                 socket
             }
             Err(e) => {
-                let _ = Socks5Reply::GeneralServerFailure.send(&mut clt_tcp_w).await;
+                let _ = udp_listen_error_reply(&e).send(&mut clt_tcp_w).await;
                 return Err(e);
             }
         };
@@ -254,15 +288,20 @@ impl SocksProxyUdpAssociateTask {
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             user_ctx.foreach_req_stats(|s| s.req_ready.add_socks_udp_associate());
         }
-        self.run_relay(
-            clt_tcp_r,
-            Box::new(clt_r),
-            Box::new(clt_w),
-            ups_r,
-            ups_w,
-            escape_logger,
-        )
-        .await
+        let r = self
+            .run_relay(
+                clt_tcp_r,
+                Box::new(clt_r),
+                Box::new(clt_w),
+                ups_r,
+                ups_w,
+                escape_logger,
+            )
+            .await;
+        if let Err(e) = &r {
+            close_tcp_control(&mut clt_tcp_w, e).await;
+        }
+        r
     }
 
     async fn run_relay<R>(
@@ -281,27 +320,48 @@ impl SocksProxyUdpAssociateTask {
 
         let mut c_to_r =
             UdpRelayClientToRemote::new(&mut *clt_r, &mut *ups_w, self.ctx.server_config.udp_relay);
+        c_to_r.set_batch_size_stats(self.task_stats.clt_recv_batch.clone());
+        c_to_r.set_oversize_stats(Arc::new(
+            UdpAssociateTaskOversizeWrapperStats::new_client_side(
+                &self.ctx.server_stats,
+                &self.task_stats,
+            ),
+        ));
+        if let Some(config) = self.ctx.server_config.udp_client_duplicate_filter {
+            c_to_r.set_duplicate_filter(
+                UdpDuplicateFilter::new(config).with_stats(self.task_stats.clone()),
+            );
+        }
         let mut r_to_c =
             UdpRelayRemoteToClient::new(&mut *clt_w, &mut *ups_r, self.ctx.server_config.udp_relay);
+        r_to_c.set_batch_size_stats(self.task_stats.ups_recv_batch.clone());
+        r_to_c.set_oversize_stats(Arc::new(
+            UdpAssociateTaskOversizeWrapperStats::new_remote_side(
+                &self.ctx.server_stats,
+                &self.task_stats,
+            ),
+        ));
+        if self.ctx.server_config.udp_capture_dir.is_some() {
+            let client_addr = self
+                .udp_client_addr
+                .unwrap_or_else(|| self.ctx.client_addr());
+            let tap = self.ctx.udp_capture.task_tap(client_addr);
+            c_to_r.set_packet_tap(tap.clone());
+            r_to_c.set_packet_tap(tap);
+        }
 
-        let mut idle_interval = self.ctx.idle_wheel.register();
+        let mut idle_interval = self.idle_wheel.register();
         let mut log_interval = self.ctx.get_log_interval();
         let mut idle_count = 0;
-        let mut buf: [u8; 4] = [0; 4];
+        let mut idle_echo_interval = self.ctx.get_idle_echo_interval();
+        let idle_echo_from = UpstreamAddr::from_ip_and_port(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+        let mut clt_send_packets = 0;
         loop {
             tokio::select! {
                 biased;
 
-                r = clt_tcp_r.read(&mut buf) => {
-                    return match r {
-                        Ok(0) => Ok(()),
-                        Ok(_) => {
-                            Err(ServerTaskError::InvalidClientProtocol(
-                                "unexpected data received from the tcp channel"
-                            ))
-                        }
-                        Err(e) => Err(ServerTaskError::ClientTcpReadFailed(e)),
-                    };
+                e = wait_tcp_control_closed(&mut clt_tcp_r) => {
+                    return Err(e);
                 }
                 r = &mut c_to_r => {
                     return match r {
@@ -342,6 +402,16 @@ impl SocksProxyUdpAssociateTask {
                         log_ctx.log_periodic();
                     }
                 }
+                _ = idle_echo_interval.tick() => {
+                    if self.task_stats.clt.send.get_packets() == clt_send_packets {
+                        // send an empty packet to keep the NAT mappings alive
+                        poll_fn(|cx| {
+                            r_to_c.client_mut().poll_send_packet(cx, &[], &idle_echo_from)
+                        })
+                        .await?;
+                    }
+                    clt_send_packets = self.task_stats.clt.send.get_packets();
+                }
                 n = idle_interval.tick() => {
                     if c_to_r.is_idle() && r_to_c.is_idle() {
                         idle_count += n;
@@ -361,6 +431,9 @@ impl SocksProxyUdpAssociateTask {
 
                         c_to_r.reset_active();
                         r_to_c.reset_active();
+                        if let Some(running_task) = &self.running_task {
+                            running_task.task().mark_active();
+                        }
                     }
 
                     if let Some(user_ctx) = self.task_notes.user_ctx() {
@@ -372,6 +445,12 @@ impl SocksProxyUdpAssociateTask {
                     if self.ctx.server_quit_policy.force_quit() {
                         return Err(ServerTaskError::CanceledAsServerQuit)
                     }
+
+                    if let Some(running_task) = &self.running_task {
+                        if running_task.task().is_killed() {
+                            return Err(ServerTaskError::KilledByOperator);
+                        }
+                    }
                 }
             }
         }
@@ -413,6 +492,14 @@ impl SocksProxyUdpAssociateTask {
             limit_config.max_north_bytes,
             wrapper_stats.clone(),
         );
+        let limit_payload = self
+            .ctx
+            .server_config
+            .udp_sock_speed_limit_bytes
+            .is_payload();
+        // the socks5 udp header is counted as overhead bytes
+        clt_r.set_header_len_fn(UdpInput::calc_header_len);
+        clt_r.set_limit_payload_only(limit_payload);
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if let Some(limiter) = user_ctx.user().udp_all_upload_speed_limit() {
                 clt_r.add_global_limiter(limiter.clone());
@@ -426,6 +513,12 @@ impl SocksProxyUdpAssociateTask {
             &self.ctx,
             self.task_notes.user_ctx(),
         );
+        if let Some(verifier) = &self.ctx.udp_dest_verifier {
+            clt_r.set_verify_gate(UdpDestVerifyGate::new(
+                verifier.clone(),
+                self.task_stats.clone(),
+            ));
+        }
 
         let buf_len = self.ctx.server_config.udp_relay.packet_size();
         let mut buf = vec![0u8; buf_len];
@@ -434,6 +527,9 @@ impl SocksProxyUdpAssociateTask {
             .recv_first_packet(clt_tcp_r, &mut clt_r, &mut buf)
             .await?;
         self.udp_client_addr = Some(udp_client_addr);
+        if let Some(running_task) = &self.running_task {
+            running_task.task().set_upstream(&self.initial_peer);
+        }
 
         if let Some(user_ctx) = self.task_notes.user_ctx_mut() {
             // set user site by using the upstream address of the first packet
@@ -468,14 +564,19 @@ impl SocksProxyUdpAssociateTask {
             clt_w_stats = wrapper_stats;
         }
 
-        clt_r
-            .inner()
-            .inner()
-            .connect(udp_client_addr)
-            .await
-            .map_err(|_| {
-                ServerTaskError::InternalServerError("unable to connect the client side udp socket")
-            })?;
+        let local_ip = clt_r.local_ip();
+        if local_ip.is_none() {
+            clt_r
+                .inner()
+                .inner()
+                .connect(udp_client_addr)
+                .await
+                .map_err(|_| {
+                    ServerTaskError::InternalServerError(
+                        "unable to connect the client side udp socket",
+                    )
+                })?;
+        }
 
         let mut clt_w = LimitedUdpSend::local_limited(
             clt_w,
@@ -484,6 +585,9 @@ impl SocksProxyUdpAssociateTask {
             limit_config.max_south_bytes,
             clt_w_stats,
         );
+        // the socks5 udp header is always sent in the first io slice
+        clt_w.set_header_iov(1);
+        clt_w.set_limit_payload_only(limit_payload);
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if let Some(limiter) = user_ctx.user().udp_all_download_speed_limit() {
                 clt_w.add_global_limiter(limiter.clone());
@@ -494,6 +598,7 @@ impl SocksProxyUdpAssociateTask {
 
         let task_conf = UdpRelayTaskConf {
             initial_peer: &self.initial_peer,
+            client_addr: udp_client_addr,
             sock_buf: self.ctx.server_config.udp_socket_buffer,
         };
         let (ups_r, mut ups_w, logger) = self
@@ -514,9 +619,13 @@ impl SocksProxyUdpAssociateTask {
             }
         }
 
-        poll_fn(|cx| ups_w.poll_send_packet(cx, &buf[buf_off..buf_nr], &self.initial_peer)).await?;
+        // the first packet may be held until the initial peer is verified
+        if clt_r.check_dest_verify(&buf[buf_off..buf_nr], &self.initial_peer) {
+            poll_fn(|cx| ups_w.poll_send_packet(cx, &buf[buf_off..buf_nr], &self.initial_peer))
+                .await?;
+        }
 
-        let clt_w = Socks5UdpAssociateClientSend::new(clt_w, udp_client_addr);
+        let clt_w = Socks5UdpAssociateClientSend::new(clt_w, udp_client_addr, local_ip);
 
         Ok((clt_r, clt_w, ups_r, ups_w, logger))
     }
@@ -534,20 +643,11 @@ impl SocksProxyUdpAssociateTask {
             self.ctx.server_config.timeout.udp_client_initial,
             clt_udp_r.recv_first_packet(buf, &self.ctx.ingress_net_filter, &mut self.initial_peer),
         );
-        let mut buf_tcp: [u8; 4] = [0; 4];
         tokio::select! {
             biased;
 
-            ret = clt_tcp_r.read(&mut buf_tcp) => {
-                match ret {
-                    Ok(0) => Err(ServerTaskError::ClosedByClient),
-                    Ok(_) => {
-                        Err(ServerTaskError::InvalidClientProtocol(
-                            "unexpected data received from the tcp channel"
-                        ))
-                    }
-                    Err(e) => Err(ServerTaskError::ClientTcpReadFailed(e)),
-                }
+            e = wait_tcp_control_closed(clt_tcp_r) => {
+                Err(e)
             }
             ret = udp_fut => {
                 match ret {
@@ -3,7 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use super::{CommonTaskContext, SocksProxyServerStats};
+use super::{CommonTaskContext, SocksProxyServerStats, close_tcp_control, wait_tcp_control_closed};
 
 mod task;
 pub(super) use task::SocksProxyUdpConnectTask;
@@ -15,3 +15,86 @@ mod stats;
 use recv::Socks5UdpConnectClientRecv;
 use send::Socks5UdpConnectClientSend;
 use stats::{UdpConnectTaskCltWrapperStats, UdpConnectTaskStats};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use tokio::net::UdpSocket;
+
+    use g3_io_ext::{LimitedUdpRecv, LimitedUdpSend, UdpCopyClientRecv, UdpCopyClientSend};
+    use g3_socks::v5::{UdpInput, UdpOutput};
+    use g3_types::metrics::NodeName;
+    use g3_types::net::UpstreamAddr;
+
+    #[tokio::test]
+    async fn overhead_split() {
+        let server_stats = Arc::new(SocksProxyServerStats::new(
+            &NodeName::from_str("socks").unwrap(),
+        ));
+        let task_stats = Arc::new(UdpConnectTaskStats::default());
+        let wrapper_stats = Arc::new(UdpConnectTaskCltWrapperStats::new(
+            &server_stats,
+            &task_stats,
+        ));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let client_addr = client.local_addr().unwrap();
+        client.connect(server.local_addr().unwrap()).await.unwrap();
+        server.connect(client_addr).await.unwrap();
+
+        let (clt_r, clt_w) = g3_io_ext::split_udp(server);
+        let mut clt_r = LimitedUdpRecv::local_limited(clt_r, 0, 0, 0, wrapper_stats.clone());
+        clt_r.set_header_len_fn(UdpInput::calc_header_len);
+        let mut clt_r = Socks5UdpConnectClientRecv::new(clt_r, Some(client_addr));
+        let mut clt_w = LimitedUdpSend::local_limited(clt_w, 0, 0, 0, wrapper_stats);
+        clt_w.set_header_iov(1);
+
+        // the header length is fixed for all packets in connect mode
+        let upstream = UpstreamAddr::from_host_str_and_port("www.example.net", 53).unwrap();
+        let header_len = UdpOutput::calc_header_len(&upstream);
+        assert_eq!(header_len, 22);
+        let mut packet = vec![0u8; header_len + 200];
+        UdpOutput::generate_header(&mut packet, &upstream);
+        let mut buf = vec![0u8; 1024];
+
+        client.send(&packet[..header_len + 100]).await.unwrap();
+        let (off, nr, _, ups) = clt_r.recv_first_packet(&mut buf, &None).await.unwrap();
+        assert_eq!((off, nr), (header_len, header_len + 100));
+        assert_eq!(ups, upstream);
+        client.send(&packet).await.unwrap();
+        let (off, nr) = poll_fn(|cx| clt_r.poll_recv_packet(cx, &mut buf))
+            .await
+            .unwrap();
+        assert_eq!((off, nr), (header_len, header_len + 200));
+
+        assert_eq!(task_stats.clt.recv.get_packets(), 2);
+        assert_eq!(task_stats.clt.recv.get_bytes(), 300);
+        assert_eq!(task_stats.clt.recv.get_overhead_bytes(), 44);
+
+        let mut clt_w = Socks5UdpConnectClientSend::new(clt_w, upstream);
+        // the empty one is the same as the idle echo packet
+        for size in [0, 100, 200] {
+            let payload = &packet[header_len..header_len + size];
+            let nw = poll_fn(|cx| clt_w.poll_send_packet(cx, payload))
+                .await
+                .unwrap();
+            assert_eq!(nw, header_len + size);
+            let nr = client.recv(&mut buf).await.unwrap();
+            assert_eq!(nr, header_len + size);
+        }
+
+        assert_eq!(task_stats.clt.send.get_packets(), 3);
+        assert_eq!(task_stats.clt.send.get_bytes(), 300);
+        assert_eq!(task_stats.clt.send.get_overhead_bytes(), 66);
+
+        let io = server_stats.io_udp.snapshot();
+        assert_eq!((io.in_bytes, io.out_bytes), (300, 300));
+        let overhead = server_stats.udp_overhead.snapshot();
+        assert_eq!((overhead.in_bytes, overhead.out_bytes), (44, 66));
+    }
+}
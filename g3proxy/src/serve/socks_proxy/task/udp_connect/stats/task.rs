@@ -3,7 +3,10 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use g3_daemon::stat::task::UdpConnectConnectionStats;
+use g3_io_ext::UdpDuplicateStats;
 
 use crate::module::udp_connect::UdpConnectTaskRemoteStats;
 
@@ -11,6 +14,19 @@ use crate::module::udp_connect::UdpConnectTaskRemoteStats;
 pub(crate) struct UdpConnectTaskStats {
     pub(crate) clt: UdpConnectConnectionStats,
     pub(crate) ups: UdpConnectConnectionStats,
+    clt_dup_dropped: AtomicU64,
+}
+
+impl UdpConnectTaskStats {
+    pub(crate) fn get_clt_dup_dropped(&self) -> u64 {
+        self.clt_dup_dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl UdpDuplicateStats for UdpConnectTaskStats {
+    fn add_duplicate_dropped(&self) {
+        self.clt_dup_dropped.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 impl UdpConnectTaskRemoteStats for UdpConnectTaskStats {
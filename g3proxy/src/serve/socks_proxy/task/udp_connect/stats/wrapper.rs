@@ -84,6 +84,12 @@ impl LimitedRecvStats for UdpConnectTaskCltWrapperStats {
         self.task.clt.recv.add_packets(n);
         self.others.iter().for_each(|s| s.add_recv_packets(n));
     }
+
+    fn add_recv_overhead_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.server.udp_overhead.add_in_bytes(size);
+        self.task.clt.recv.add_overhead_bytes(size);
+    }
 }
 
 impl LimitedSendStats for UdpConnectTaskCltWrapperStats {
@@ -99,4 +105,10 @@ impl LimitedSendStats for UdpConnectTaskCltWrapperStats {
         self.task.clt.send.add_packets(n);
         self.others.iter().for_each(|s| s.add_send_packets(n));
     }
+
+    fn add_send_overhead_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.server.udp_overhead.add_out_bytes(size);
+        self.task.clt.send.add_overhead_bytes(size);
+    }
 }
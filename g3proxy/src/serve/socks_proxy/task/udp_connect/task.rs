@@ -8,23 +8,24 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 
 use slog::Logger;
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::UdpSocket;
 
 use g3_io_ext::{
-    LimitedUdpRecv, LimitedUdpSend, UdpCopyClientRecv, UdpCopyClientSend, UdpCopyClientToRemote,
-    UdpCopyError, UdpCopyRemoteRecv, UdpCopyRemoteSend, UdpCopyRemoteToClient, UdpRecvHalf,
-    UdpSendHalf,
+    IdleWheel, LimitedUdpRecv, LimitedUdpSend, UdpCopyClientRecv, UdpCopyClientSend,
+    UdpCopyClientToRemote, UdpCopyError, UdpCopyRemoteRecv, UdpCopyRemoteSend,
+    UdpCopyRemoteToClient, UdpDuplicateFilter, UdpRecvHalf, UdpSendHalf,
 };
-use g3_socks::v5::Socks5Reply;
+use g3_socks::v5::{Socks5Reply, UdpInput};
 use g3_types::acl::AclAction;
 use g3_types::net::{ProxyRequestType, UpstreamAddr};
 
 use super::{
     CommonTaskContext, Socks5UdpConnectClientRecv, Socks5UdpConnectClientSend,
-    UdpConnectTaskCltWrapperStats, UdpConnectTaskStats,
+    UdpConnectTaskCltWrapperStats, UdpConnectTaskStats, close_tcp_control, wait_tcp_control_closed,
 };
 use crate::config::server::ServerConfig;
+use crate::config::server::socks_proxy::SocksProxyUdpClientPortPolicy;
 use crate::log::escape::udp_sendto::EscapeLogForUdpConnectSendTo;
 use crate::log::task::udp_connect::TaskLogForUdpConnect;
 use crate::module::udp_connect::{UdpConnectTaskConf, UdpConnectTaskNotes};
@@ -41,6 +42,7 @@ pub(crate) struct SocksProxyUdpConnectTask {
     task_stats: Arc<UdpConnectTaskStats>,
     udp_listen_addr: Option<SocketAddr>,
     udp_client_addr: Option<SocketAddr>,
+    idle_wheel: Arc<IdleWheel>,
     max_idle_count: usize,
     started: bool,
 }
@@ -56,13 +58,18 @@ impl Drop for SocksProxyUdpConnectTask {
 impl SocksProxyUdpConnectTask {
     pub(crate) fn new(
         ctx: CommonTaskContext,
-        notes: ServerTaskNotes,
+        mut notes: ServerTaskNotes,
         udp_client_addr: Option<SocketAddr>,
     ) -> Self {
+        let idle_override = ctx.idle_overrides.select_for_task(&mut notes);
         let max_idle_count = notes
             .user_ctx()
             .and_then(|c| c.user().task_max_idle_count())
+            .or(idle_override.as_ref().map(|o| o.max_idle_count))
             .unwrap_or(ctx.server_config.task_idle_max_count);
+        let idle_wheel = idle_override
+            .map(|o| o.idle_wheel.clone())
+            .unwrap_or_else(|| ctx.idle_wheel.clone());
         SocksProxyUdpConnectTask {
             ctx,
             upstream: None,
@@ -71,6 +78,7 @@ impl SocksProxyUdpConnectTask {
             task_stats: Arc::new(UdpConnectTaskStats::default()),
             udp_listen_addr: None,
             udp_client_addr,
+            idle_wheel,
             max_idle_count,
             started: false,
         }
@@ -91,8 +99,11 @@ impl SocksProxyUdpConnectTask {
                 udp_notes: &self.udp_notes,
                 client_rd_bytes: self.task_stats.clt.recv.get_bytes(),
                 client_rd_packets: self.task_stats.clt.recv.get_packets(),
+                client_rd_overhead: self.task_stats.clt.recv.get_overhead_bytes(),
                 client_wr_bytes: self.task_stats.clt.send.get_bytes(),
                 client_wr_packets: self.task_stats.clt.send.get_packets(),
+                client_wr_overhead: self.task_stats.clt.send.get_overhead_bytes(),
+                client_dup_dropped: self.task_stats.get_clt_dup_dropped(),
                 remote_rd_bytes: self.task_stats.ups.recv.get_bytes(),
                 remote_rd_packets: self.task_stats.ups.recv.get_packets(),
                 remote_wr_bytes: self.task_stats.ups.send.get_bytes(),
@@ -274,10 +285,14 @@ impl SocksProxyUdpConnectTask {
         self.task_notes.stage = ServerTaskStage::Preparing;
         let clt_socket = match self
             .ctx
-            .setup_udp_listen(self.udp_client_addr, &self.task_notes)
+            .setup_udp_listen(
+                self.udp_client_addr,
+                SocksProxyUdpClientPortPolicy::Ignore,
+                &mut self.task_notes,
+            )
             .await
         {
-            Ok((udp_listen_addr, socket)) => {
+            Ok((udp_listen_addr, socket, _)) => {
                 self.task_notes.stage = ServerTaskStage::Replying;
                 self.udp_listen_addr = Some(udp_listen_addr);
                 let udp_echo_addr = self
@@ -303,15 +318,20 @@ impl SocksProxyUdpConnectTask {
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             user_ctx.foreach_req_stats(|s| s.req_ready.add_socks_udp_connect());
         }
-        self.run_relay(
-            clt_tcp_r,
-            Box::new(clt_r),
-            Box::new(clt_w),
-            ups_r,
-            ups_w,
-            escape_logger,
-        )
-        .await
+        let r = self
+            .run_relay(
+                clt_tcp_r,
+                Box::new(clt_r),
+                Box::new(clt_w),
+                ups_r,
+                ups_w,
+                escape_logger,
+            )
+            .await;
+        if let Err(e) = &r {
+            close_tcp_control(&mut clt_tcp_w, e).await;
+        }
+        r
     }
 
     async fn run_relay<R>(
@@ -330,27 +350,25 @@ impl SocksProxyUdpConnectTask {
 
         let mut c_to_r =
             UdpCopyClientToRemote::new(&mut *clt_r, &mut *ups_w, self.ctx.server_config.udp_relay);
+        if let Some(config) = self.ctx.server_config.udp_client_duplicate_filter {
+            c_to_r.set_duplicate_filter(
+                UdpDuplicateFilter::new(config).with_stats(self.task_stats.clone()),
+            );
+        }
         let mut r_to_c =
             UdpCopyRemoteToClient::new(&mut *clt_w, &mut *ups_r, self.ctx.server_config.udp_relay);
 
-        let mut idle_interval = self.ctx.idle_wheel.register();
+        let mut idle_interval = self.idle_wheel.register();
         let mut log_interval = self.ctx.get_log_interval();
         let mut idle_count = 0;
-        let mut buf: [u8; 4] = [0; 4];
+        let mut idle_echo_interval = self.ctx.get_idle_echo_interval();
+        let mut clt_send_packets = 0;
         loop {
             tokio::select! {
                 biased;
 
-                r = clt_tcp_r.read(&mut buf) => {
-                    return match r {
-                        Ok(0) => Ok(()),
-                        Ok(_) => {
-                            Err(ServerTaskError::InvalidClientProtocol(
-                                "unexpected data received from the tcp channel"
-                            ))
-                        }
-                        Err(e) => Err(ServerTaskError::ClientTcpReadFailed(e)),
-                    };
+                e = wait_tcp_control_closed(&mut clt_tcp_r) => {
+                    return Err(e);
                 }
                 r = &mut c_to_r => {
                     return match r {
@@ -391,6 +409,13 @@ impl SocksProxyUdpConnectTask {
                         log_ctx.log_periodic();
                     }
                 }
+                _ = idle_echo_interval.tick() => {
+                    if self.task_stats.clt.send.get_packets() == clt_send_packets {
+                        // send an empty packet to keep the NAT mappings alive
+                        poll_fn(|cx| r_to_c.client_mut().poll_send_packet(cx, &[])).await?;
+                    }
+                    clt_send_packets = self.task_stats.clt.send.get_packets();
+                }
                 n = idle_interval.tick() => {
                     if c_to_r.is_idle() && r_to_c.is_idle() {
                         idle_count += n;
@@ -462,6 +487,14 @@ impl SocksProxyUdpConnectTask {
             limit_config.max_north_bytes,
             wrapper_stats.clone(),
         );
+        let limit_payload = self
+            .ctx
+            .server_config
+            .udp_sock_speed_limit_bytes
+            .is_payload();
+        // the socks5 udp header is counted as overhead bytes
+        clt_r.set_header_len_fn(UdpInput::calc_header_len);
+        clt_r.set_limit_payload_only(limit_payload);
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if let Some(limiter) = user_ctx.user().udp_all_upload_speed_limit() {
                 clt_r.add_global_limiter(limiter.clone());
@@ -535,6 +568,9 @@ impl SocksProxyUdpConnectTask {
             limit_config.max_south_bytes,
             clt_w_stats,
         );
+        // the socks5 udp header is always sent in the first io slice
+        clt_w.set_header_iov(1);
+        clt_w.set_limit_payload_only(limit_payload);
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if let Some(limiter) = user_ctx.user().udp_all_download_speed_limit() {
                 clt_w.add_global_limiter(limiter.clone());
@@ -584,20 +620,11 @@ impl SocksProxyUdpConnectTask {
             self.ctx.server_config.timeout.udp_client_initial,
             clt_udp_r.recv_first_packet(buf, &self.ctx.ingress_net_filter),
         );
-        let mut buf_tcp: [u8; 4] = [0; 4];
         tokio::select! {
             biased;
 
-            ret = clt_tcp_r.read(&mut buf_tcp) => {
-                match ret {
-                    Ok(0) => Err(ServerTaskError::ClosedByClient),
-                    Ok(_) => {
-                        Err(ServerTaskError::InvalidClientProtocol(
-                            "unexpected data received from the tcp channel"
-                        ))
-                    }
-                    Err(e) => Err(ServerTaskError::ClientTcpReadFailed(e)),
-                }
+            e = wait_tcp_control_closed(clt_tcp_r) => {
+                Err(e)
             }
             ret = udp_fut => {
                 match ret {
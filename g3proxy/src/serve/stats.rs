@@ -3,11 +3,14 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::sync::Arc;
 use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use arc_swap::ArcSwapOption;
+use rustc_hash::FxHashMap;
 
+use g3_daemon::server::{AcceptRejectSnapshot, TransferStats};
+use g3_histogram::HistogramStats;
 use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::stats::{StatId, TcpIoSnapshot, UdpIoSnapshot};
 
@@ -40,6 +43,46 @@ pub(crate) trait ServerStats {
     fn untrusted_snapshot(&self) -> Option<UntrustedTaskStatsSnapshot> {
         None
     }
+
+    /// histogram stats for the time spent by escapers to connect to the next peer
+    fn connect_duration_stats(&self) -> Option<Arc<HistogramStats>> {
+        None
+    }
+
+    /// count for connections rejected before the task is started
+    fn accept_reject_snapshot(&self) -> Option<AcceptRejectSnapshot> {
+        None
+    }
+
+    /// count for upstream connect retries and fallbacks
+    fn connect_fallback_snapshot(&self) -> Option<ServerConnectFallbackSnapshot> {
+        None
+    }
+
+    /// histogram stats for the size and duration of the relayed streams
+    fn transfer_stats(&self) -> Option<Arc<TransferStats>> {
+        None
+    }
+
+    /// count for udp relay port range exhaustion, per port range tier
+    fn udp_port_exhausted_snapshot(&self) -> Option<FxHashMap<Arc<str>, u64>> {
+        None
+    }
+
+    /// count for the udp relay packets that are larger than the packet size
+    fn udp_oversize_snapshot(&self) -> Option<ServerUdpOversizeSnapshot> {
+        None
+    }
+
+    /// count for the protocol header bytes of the udp relay packets on the client side
+    fn udp_overhead_snapshot(&self) -> Option<ServerUdpOverheadSnapshot> {
+        None
+    }
+
+    /// count for the checked adaptation bypass headers
+    fn adaptation_bypass_snapshot(&self) -> Option<ServerAdaptationBypassSnapshot> {
+        None
+    }
 }
 
 pub(crate) type ArcServerStats = Arc<dyn ServerStats + Send + Sync>;
@@ -80,6 +123,166 @@ impl ServerForbiddenStats {
     }
 }
 
+#[derive(Default)]
+pub(crate) struct ServerConnectFallbackSnapshot {
+    pub(crate) retried: u64,
+    pub(crate) fallback: u64,
+    pub(crate) exhausted: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct ServerConnectFallbackStats {
+    retried: AtomicU64,
+    fallback: AtomicU64,
+    exhausted: AtomicU64,
+}
+
+impl ServerConnectFallbackStats {
+    /// the original destination is retried after a connect failure
+    pub(crate) fn add_retried(&self) {
+        self.retried.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// alternate addresses are tried after the original destination failed
+    pub(crate) fn add_fallback(&self) {
+        self.fallback.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// all addresses have been tried and none of them succeeded
+    pub(crate) fn add_exhausted(&self) {
+        self.exhausted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ServerConnectFallbackSnapshot {
+        ServerConnectFallbackSnapshot {
+            retried: self.retried.load(Ordering::Relaxed),
+            fallback: self.fallback.load(Ordering::Relaxed),
+            exhausted: self.exhausted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ServerUdpPortExhaustedStats {
+    tiers: Mutex<FxHashMap<Arc<str>, u64>>,
+}
+
+impl ServerUdpPortExhaustedStats {
+    /// no port can be selected within the port range of the tier
+    pub(crate) fn add_exhausted(&self, tier: &Arc<str>) {
+        let mut tiers = self.tiers.lock().unwrap();
+        if let Some(v) = tiers.get_mut(tier) {
+            *v += 1;
+        } else {
+            tiers.insert(tier.clone(), 1);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> FxHashMap<Arc<str>, u64> {
+        self.tiers.lock().unwrap().clone()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ServerUdpOversizeSnapshot {
+    pub(crate) truncated: u64,
+    pub(crate) dropped: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct ServerUdpOversizeStats {
+    truncated: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl ServerUdpOversizeStats {
+    /// the received packet is truncated as it is larger than the packet size
+    pub(crate) fn add_truncated(&self) {
+        self.truncated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// the truncated packet is dropped by the oversize policy
+    pub(crate) fn add_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ServerUdpOversizeSnapshot {
+        ServerUdpOversizeSnapshot {
+            truncated: self.truncated.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ServerUdpOverheadSnapshot {
+    pub(crate) in_bytes: u64,
+    pub(crate) out_bytes: u64,
+}
+
+/// The protocol header bytes, which are not counted in the udp io bytes
+#[derive(Default)]
+pub(crate) struct ServerUdpOverheadStats {
+    in_bytes: AtomicU64,
+    out_bytes: AtomicU64,
+}
+
+impl ServerUdpOverheadStats {
+    pub(crate) fn add_in_bytes(&self, size: u64) {
+        self.in_bytes.fetch_add(size, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_out_bytes(&self, size: u64) {
+        self.out_bytes.fetch_add(size, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ServerUdpOverheadSnapshot {
+        ServerUdpOverheadSnapshot {
+            in_bytes: self.in_bytes.load(Ordering::Relaxed),
+            out_bytes: self.out_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ServerAdaptationBypassSnapshot {
+    pub(crate) trusted: u64,
+    pub(crate) invalid: u64,
+    pub(crate) denied: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct ServerAdaptationBypassStats {
+    trusted: AtomicU64,
+    invalid: AtomicU64,
+    denied: AtomicU64,
+}
+
+impl ServerAdaptationBypassStats {
+    /// the ICAP adaptation is bypassed by a valid header
+    pub(crate) fn add_trusted(&self) {
+        self.trusted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// the header is ignored as the signature or the timestamp is invalid
+    pub(crate) fn add_invalid(&self) {
+        self.invalid.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// the header is ignored as the client is not in the allowed source networks
+    pub(crate) fn add_denied(&self) {
+        self.denied.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ServerAdaptationBypassSnapshot {
+        ServerAdaptationBypassSnapshot {
+            trusted: self.trusted.load(Ordering::Relaxed),
+            invalid: self.invalid.load(Ordering::Relaxed),
+            denied: self.denied.load(Ordering::Relaxed),
+        }
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct ServerPerTaskStats {
     task_total: AtomicU64,
@@ -3,19 +3,26 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::io;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
+use tokio::net::TcpStream;
 use tokio::time::Instant;
 use uuid::Uuid;
 
 use g3_daemon::server::ClientConnectionInfo;
+use g3_socket::RawSocket;
+use g3_socket::util::AddressFamily;
 use g3_types::limit::GaugeSemaphorePermit;
+use g3_types::metrics::NodeName;
+use g3_types::net::TcpMiscSockOpts;
 
 use crate::auth::UserContext;
 use crate::escape::EgressPathSelection;
+use crate::serve::{ServerTaskError, ServerTaskVars, TaskLogSample};
 
 #[derive(Clone, Copy)]
 pub(crate) enum ServerTaskStage {
@@ -57,6 +64,13 @@ pub(crate) struct ServerTaskNotes {
     pub(crate) wait_time: Duration,
     pub(crate) ready_time: Duration,
     pub(crate) egress_path_selection: Option<EgressPathSelection>,
+    /// the server side limit of the timeout for each tcp connect attempt in escapers
+    pub(crate) tcp_connect_each_timeout: Option<Duration>,
+    /// the server side socket options for the upstream tcp connections, which will be set
+    /// in escapers after connected
+    pub(crate) tcp_upstream_misc_opts: Option<TcpMiscSockOpts>,
+    vars: ServerTaskVars,
+    log_sample: TaskLogSample,
     /// the following fields should not be cloned
     pub(crate) user_req_alive_permit: Option<GaugeSemaphorePermit>,
 }
@@ -78,6 +92,10 @@ impl ServerTaskNotes {
     ) -> Self {
         let started = Utc::now();
         let uuid = g3_daemon::server::task::generate_uuid(&started);
+        let mut vars = ServerTaskVars::default();
+        if let Some(user) = user_ctx.as_ref().and_then(|c| c.raw_user_name()) {
+            vars.set_user(user.clone());
+        }
         ServerTaskNotes {
             cc_info,
             stage: ServerTaskStage::Created,
@@ -88,10 +106,23 @@ impl ServerTaskNotes {
             wait_time,
             ready_time: Duration::default(),
             egress_path_selection,
+            tcp_connect_each_timeout: None,
+            tcp_upstream_misc_opts: None,
+            vars,
+            log_sample: TaskLogSample::default(),
             user_req_alive_permit: None,
         }
     }
 
+    /// Set the server side socket options on the connected upstream tcp stream
+    pub(crate) fn set_tcp_upstream_misc_opts(&self, stream: &TcpStream) -> io::Result<()> {
+        let Some(misc_opts) = &self.tcp_upstream_misc_opts else {
+            return Ok(());
+        };
+        let peer_addr = stream.peer_addr()?;
+        RawSocket::from(stream).set_tcp_misc_opts(AddressFamily::from(&peer_addr), misc_opts, false)
+    }
+
     #[inline]
     pub(crate) fn client_addr(&self) -> SocketAddr {
         self.cc_info.client_addr()
@@ -122,8 +153,19 @@ impl ServerTaskNotes {
         self.user_ctx.as_mut()
     }
 
+    #[inline]
     pub(crate) fn raw_user_name(&self) -> Option<&Arc<str>> {
-        self.user_ctx.as_ref().and_then(|c| c.raw_user_name())
+        self.vars.user()
+    }
+
+    #[inline]
+    pub(crate) fn vars(&self) -> &ServerTaskVars {
+        &self.vars
+    }
+
+    #[inline]
+    pub(crate) fn vars_mut(&mut self) -> &mut ServerTaskVars {
+        &mut self.vars
     }
 
     pub(crate) fn egress_path(&self) -> Option<&EgressPathSelection> {
@@ -143,6 +185,32 @@ impl ServerTaskNotes {
         self.create_ins.elapsed()
     }
 
+    /// Set the task log sampling decision, which should be done at the creation of the task
+    #[inline]
+    pub(crate) fn set_log_sample(&mut self, sample: TaskLogSample) {
+        self.log_sample = sample;
+    }
+
+    /// Check if the task logs other than the finish one should be skipped
+    #[inline]
+    pub(crate) fn log_sampled_out(&self) -> bool {
+        self.log_sample != TaskLogSample::Full
+    }
+
+    /// Check if the finish task log should be skipped, failed tasks may bypass the sampling
+    pub(crate) fn finish_log_sampled_out(&self, e: &ServerTaskError) -> bool {
+        match self.log_sample {
+            TaskLogSample::Full => false,
+            TaskLogSample::ErrorsOnly => !e.is_failure(),
+            TaskLogSample::Skip => true,
+        }
+    }
+
+    pub(crate) fn mark_connected(&mut self, escaper: &NodeName) {
+        self.stage = ServerTaskStage::Connected;
+        self.vars.set_escaper(escaper.clone());
+    }
+
     pub(crate) fn mark_relaying(&mut self) {
         self.stage = ServerTaskStage::Relaying;
         self.ready_time = self.create_ins.elapsed();
@@ -0,0 +1,145 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::config::server::TaskLogSampleConfig;
+
+/// The task log sampling decision, which is made once at the creation of each task
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum TaskLogSample {
+    /// all task logs will be emitted
+    #[default]
+    Full,
+    /// only the finish log of failed tasks will be emitted
+    ErrorsOnly,
+    /// no task log will be emitted
+    Skip,
+}
+
+/// Per server task log sampler, which can be adjusted at runtime through the control interface.
+///
+/// The runtime changes will be reset when the server config is reloaded.
+pub(crate) struct TaskLogSampler {
+    ratio: AtomicU64,
+    always_log_errors: bool,
+    create_ins: Instant,
+    /// the milliseconds since `create_ins` until which full logging is forced, 0 if not set
+    force_full_until: AtomicU64,
+}
+
+impl TaskLogSampler {
+    pub(crate) fn new(config: &TaskLogSampleConfig) -> Self {
+        TaskLogSampler {
+            ratio: AtomicU64::new(config.ratio().to_bits()),
+            always_log_errors: config.always_log_errors(),
+            create_ins: Instant::now(),
+            force_full_until: AtomicU64::new(0),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn ratio(&self) -> f64 {
+        f64::from_bits(self.ratio.load(Ordering::Relaxed))
+    }
+
+    /// Set the effective sample ratio, which should be in range [0, 1]
+    pub(crate) fn set_ratio(&self, ratio: f64) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&ratio) {
+            return Err(anyhow::anyhow!(
+                "invalid task log sample ratio {ratio}, it should be in range [0, 1]"
+            ));
+        }
+        self.ratio.store(ratio.to_bits(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Force full logging for the new tasks created in the following `duration`
+    pub(crate) fn force_full(&self, duration: Duration) {
+        let until = self.create_ins.elapsed().saturating_add(duration);
+        let until = u64::try_from(until.as_millis()).unwrap_or(u64::MAX).max(1);
+        self.force_full_until.store(until, Ordering::Relaxed);
+    }
+
+    /// Get the remaining time of the forced full logging
+    pub(crate) fn force_full_remaining(&self) -> Option<Duration> {
+        let until = self.force_full_until.load(Ordering::Relaxed);
+        if until == 0 {
+            return None;
+        }
+        Duration::from_millis(until)
+            .checked_sub(self.create_ins.elapsed())
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Make the sampling decision for a new task
+    pub(crate) fn sample(&self) -> TaskLogSample {
+        let ratio = self.ratio();
+        if ratio >= 1.0 || self.force_full_remaining().is_some() {
+            return TaskLogSample::Full;
+        }
+        if ratio > 0.0 && fastrand::f64() < ratio {
+            TaskLogSample::Full
+        } else if self.always_log_errors {
+            TaskLogSample::ErrorsOnly
+        } else {
+            TaskLogSample::Skip
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_sampler(ratio: f64, always_log_errors: bool) -> TaskLogSampler {
+        let mut config = TaskLogSampleConfig::default();
+        config.set_ratio(ratio).unwrap();
+        config.set_always_log_errors(always_log_errors);
+        TaskLogSampler::new(&config)
+    }
+
+    #[test]
+    fn sample_ratio() {
+        let sampler = new_sampler(0.2, true);
+        let full = (0..10000)
+            .filter(|_| sampler.sample() == TaskLogSample::Full)
+            .count();
+        assert!((1500..2500).contains(&full), "full: {full}");
+        assert!(
+            (0..100)
+                .map(|_| sampler.sample())
+                .all(|s| s != TaskLogSample::Skip)
+        );
+
+        let sampler = new_sampler(0.0, false);
+        assert!((0..100).all(|_| sampler.sample() == TaskLogSample::Skip));
+
+        let sampler = new_sampler(1.0, false);
+        assert!((0..100).all(|_| sampler.sample() == TaskLogSample::Full));
+    }
+
+    #[test]
+    fn runtime_change() {
+        let sampler = new_sampler(0.0, true);
+        assert_eq!(sampler.sample(), TaskLogSample::ErrorsOnly);
+
+        sampler.set_ratio(1.0).unwrap();
+        assert_eq!(sampler.sample(), TaskLogSample::Full);
+        assert!(sampler.set_ratio(1.5).is_err());
+        assert_eq!(sampler.ratio(), 1.0);
+
+        sampler.set_ratio(0.0).unwrap();
+        assert!(sampler.force_full_remaining().is_none());
+        sampler.force_full(Duration::from_secs(60));
+        assert!(sampler.force_full_remaining().is_some());
+        assert_eq!(sampler.sample(), TaskLogSample::Full);
+
+        sampler.force_full(Duration::ZERO);
+        assert!(sampler.force_full_remaining().is_none());
+        assert_eq!(sampler.sample(), TaskLogSample::ErrorsOnly);
+    }
+}
@@ -0,0 +1,103 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+//! Typed variables that are bound to a single server task.
+//!
+//! The values are set while negotiating, doing auth or evaluating policies,
+//! and can be read later by the escaper, auditor, inspection and log layers through
+//! [`ServerTaskNotes::vars`](super::ServerTaskNotes::vars), so there is no need
+//! to pass them through every call signature.
+//!
+//! To add a new variable:
+//!
+//!  1. add a new `Option` field to [`ServerTaskVars`]
+//!  2. generate the accessors with the `impl_task_var!` macro
+//!  3. set it where the value is decided, and read it where it is needed
+
+use std::sync::Arc;
+
+use g3_types::metrics::NodeName;
+use g3_types::net::PortRange;
+
+use crate::config::server::ClientErrorAction;
+
+/// generate `name()` and `set_name()` accessors for a variable slot
+macro_rules! impl_task_var {
+    ($field:ident, $set:ident, ref $t:ty) => {
+        #[inline]
+        pub(crate) fn $field(&self) -> Option<&$t> {
+            self.$field.as_ref()
+        }
+
+        impl_task_var!(@set $field, $set, $t);
+    };
+    ($field:ident, $set:ident, copy $t:ty) => {
+        #[inline]
+        pub(crate) fn $field(&self) -> Option<$t> {
+            self.$field
+        }
+
+        impl_task_var!(@set $field, $set, $t);
+    };
+    (@set $field:ident, $set:ident, $t:ty) => {
+        #[inline]
+        pub(crate) fn $set(&mut self, value: $t) {
+            self.$field = Some(value);
+        }
+    };
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct ServerTaskVars {
+    /// the raw user name used in auth
+    user: Option<Arc<str>>,
+    /// the name of the escaper that finally handled the connection
+    escaper: Option<NodeName>,
+    /// whether the task should be audited
+    audit_task: Option<bool>,
+    /// the task idle override that is used by this task
+    idle_override: Option<Arc<str>>,
+    /// the udp port range tier that is used to bind the udp relay socket
+    udp_port_range_tier: Option<Arc<str>>,
+    /// the udp port range that is used to bind the udp relay socket
+    udp_port_range: Option<PortRange>,
+    /// the client error action that is applied to the client connection
+    client_error_action: Option<ClientErrorAction>,
+}
+
+impl ServerTaskVars {
+    impl_task_var!(user, set_user, ref Arc<str>);
+    impl_task_var!(escaper, set_escaper, ref NodeName);
+    impl_task_var!(audit_task, set_audit_task, copy bool);
+    impl_task_var!(idle_override, set_idle_override, ref Arc<str>);
+    impl_task_var!(udp_port_range_tier, set_udp_port_range_tier, ref Arc<str>);
+    impl_task_var!(udp_port_range, set_udp_port_range, copy PortRange);
+    impl_task_var!(client_error_action, set_client_error_action, copy ClientErrorAction);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn set_and_clone() {
+        let mut vars = ServerTaskVars::default();
+        assert!(vars.user().is_none());
+        assert!(vars.audit_task().is_none());
+
+        vars.set_user(Arc::from("foo"));
+        vars.set_escaper(NodeName::from_str("direct").unwrap());
+        vars.set_audit_task(true);
+
+        let copied = vars.clone();
+        vars.set_idle_override(Arc::from("long"));
+        assert_eq!(copied.user().map(|s| s.as_ref()), Some("foo"));
+        assert_eq!(copied.escaper().map(|s| s.as_str()), Some("direct"));
+        assert_eq!(copied.audit_task(), Some(true));
+        assert!(copied.idle_override().is_none());
+        assert_eq!(vars.idle_override().map(|s| s.as_ref()), Some("long"));
+    }
+}
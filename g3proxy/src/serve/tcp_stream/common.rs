@@ -15,7 +15,7 @@ use g3_types::net::OpensslClientConfig;
 use super::stats::TcpStreamServerStats;
 use crate::config::server::tcp_stream::TcpStreamServerConfig;
 use crate::escape::ArcEscaper;
-use crate::serve::ServerQuitPolicy;
+use crate::serve::{ServerQuitPolicy, TaskLogSampler};
 
 pub(super) struct CommonTaskContext {
     pub(super) server_config: Arc<TcpStreamServerConfig>,
@@ -26,6 +26,7 @@ pub(super) struct CommonTaskContext {
     pub(super) cc_info: ClientConnectionInfo,
     pub(super) tls_client_config: Option<Arc<OpensslClientConfig>>,
     pub(super) task_logger: Option<Logger>,
+    pub(super) task_log_sampler: Arc<TaskLogSampler>,
 }
 
 impl CommonTaskContext {
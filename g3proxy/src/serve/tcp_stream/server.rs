@@ -17,7 +17,9 @@ use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio_rustls::server::TlsStream;
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime, ReceiveUdpServer,
+};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerExt, ServerReloadCommand};
 use g3_io_ext::{AsyncStream, IdleWheel};
 use g3_openssl::SslStream;
@@ -35,7 +37,7 @@ use crate::config::server::{AnyServerConfig, ServerConfig};
 use crate::escape::ArcEscaper;
 use crate::serve::{
     ArcServer, ArcServerInternal, ArcServerStats, Server, ServerInternal, ServerQuitPolicy,
-    ServerRegistry, ServerStats, WrapArcServer,
+    ServerRegistry, ServerStats, TaskLogSampler, WrapArcServer,
 };
 
 pub(crate) struct TcpStreamServer {
@@ -47,6 +49,7 @@ pub(crate) struct TcpStreamServer {
     ingress_net_filter: Option<AclNetworkRule>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Option<Logger>,
+    task_log_sampler: Arc<TaskLogSampler>,
 
     escaper: ArcSwap<ArcEscaper>,
     audit_handle: ArcSwapOption<AuditHandle>,
@@ -87,7 +90,11 @@ impl TcpStreamServer {
             .map(|builder| builder.build());
 
         let task_logger = config.get_task_logger();
-        let idle_wheel = IdleWheel::spawn(config.task_idle_check_duration);
+        let task_log_sampler = Arc::new(TaskLogSampler::new(&config.task_log_sample));
+        let idle_wheel = IdleWheel::spawn_with_jitter(
+            config.task_idle_check_duration,
+            config.task_idle_check_jitter,
+        );
 
         server_stats.set_extra_tags(config.extra_metrics_tags.clone());
 
@@ -103,6 +110,7 @@ impl TcpStreamServer {
             ingress_net_filter,
             reload_sender,
             task_logger,
+            task_log_sampler,
             escaper: ArcSwap::new(escaper),
             audit_handle: ArcSwapOption::new(audit_handle),
             quit_policy: Arc::new(ServerQuitPolicy::default()),
@@ -179,6 +187,7 @@ impl TcpStreamServer {
             cc_info,
             tls_client_config: self.tls_client_config.clone(),
             task_logger: self.task_logger.clone(),
+            task_log_sampler: self.task_log_sampler.clone(),
         };
 
         (ctx, upstream.inner())
@@ -346,6 +355,17 @@ impl AcceptQuicServer for TcpStreamServer {
     }
 }
 
+impl ReceiveUdpServer for TcpStreamServer {
+    fn receive_udp_packet(
+        &self,
+        _packet: &[u8],
+        _client_addr: SocketAddr,
+        _server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) {
+    }
+}
+
 #[async_trait]
 impl Server for TcpStreamServer {
     fn escaper(&self) -> &NodeName {
@@ -377,6 +397,10 @@ impl Server for TcpStreamServer {
         &self.quit_policy
     }
 
+    fn task_log_sampler(&self) -> Option<&TaskLogSampler> {
+        Some(&self.task_log_sampler)
+    }
+
     async fn run_rustls_task(&self, stream: TlsStream<TcpStream>, cc_info: ClientConnectionInfo) {
         let client_addr = cc_info.client_addr();
         self.server_stats.add_conn(client_addr);
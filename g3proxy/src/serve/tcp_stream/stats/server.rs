@@ -9,10 +9,15 @@ use std::sync::atomic::{AtomicI32, AtomicIsize, AtomicU64, Ordering};
 
 use arc_swap::ArcSwapOption;
 
+use g3_daemon::server::{AcceptRejectSnapshot, AcceptRejectStats, TransferStats};
+use g3_histogram::HistogramStats;
 use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::stats::{StatId, TcpIoSnapshot, TcpIoStats};
 
-use crate::serve::{ServerForbiddenSnapshot, ServerForbiddenStats, ServerStats};
+use crate::serve::{
+    ServerConnectFallbackSnapshot, ServerConnectFallbackStats, ServerForbiddenSnapshot,
+    ServerForbiddenStats, ServerStats,
+};
 
 pub(crate) struct TcpStreamServerStats {
     name: NodeName,
@@ -28,6 +33,10 @@ pub(crate) struct TcpStreamServerStats {
 
     tcp: TcpIoStats,
     pub(crate) forbidden: ServerForbiddenStats,
+    connect_duration: ArcSwapOption<HistogramStats>,
+    accept_reject: ArcSwapOption<AcceptRejectStats>,
+    connect_fallback: ArcSwapOption<ServerConnectFallbackStats>,
+    transfer: ArcSwapOption<TransferStats>,
 }
 
 impl TcpStreamServerStats {
@@ -42,6 +51,10 @@ impl TcpStreamServerStats {
             task_alive_count: AtomicI32::new(0),
             tcp: Default::default(),
             forbidden: Default::default(),
+            connect_duration: ArcSwapOption::new(None),
+            accept_reject: ArcSwapOption::new(None),
+            connect_fallback: ArcSwapOption::new(None),
+            transfer: ArcSwapOption::new(None),
         }
     }
 
@@ -57,6 +70,40 @@ impl TcpStreamServerStats {
         self.extra_metrics_tags.store(tags);
     }
 
+    pub(crate) fn set_connect_duration_stats(&self, stats: Arc<HistogramStats>) {
+        self.connect_duration.store(Some(stats));
+    }
+
+    pub(crate) fn set_accept_reject_stats(&self, stats: Arc<AcceptRejectStats>) {
+        self.accept_reject.store(Some(stats));
+    }
+
+    pub(crate) fn set_connect_fallback_stats(&self, stats: Arc<ServerConnectFallbackStats>) {
+        self.connect_fallback.store(Some(stats));
+    }
+
+    pub(crate) fn set_transfer_stats(&self, stats: Option<Arc<TransferStats>>) {
+        self.transfer.store(stats);
+    }
+
+    pub(crate) fn add_connect_retried(&self) {
+        if let Some(stats) = self.connect_fallback.load().as_ref() {
+            stats.add_retried();
+        }
+    }
+
+    pub(crate) fn add_connect_fallback(&self) {
+        if let Some(stats) = self.connect_fallback.load().as_ref() {
+            stats.add_fallback();
+        }
+    }
+
+    pub(crate) fn add_connect_exhausted(&self) {
+        if let Some(stats) = self.connect_fallback.load().as_ref() {
+            stats.add_exhausted();
+        }
+    }
+
     pub(crate) fn add_conn(&self, _addr: SocketAddr) {
         self.conn_total.fetch_add(1, Ordering::Relaxed);
     }
@@ -132,4 +179,20 @@ impl ServerStats for TcpStreamServerStats {
     fn forbidden_stats(&self) -> ServerForbiddenSnapshot {
         self.forbidden.snapshot()
     }
+
+    fn connect_duration_stats(&self) -> Option<Arc<HistogramStats>> {
+        self.connect_duration.load_full()
+    }
+
+    fn accept_reject_snapshot(&self) -> Option<AcceptRejectSnapshot> {
+        self.accept_reject.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn connect_fallback_snapshot(&self) -> Option<ServerConnectFallbackSnapshot> {
+        self.connect_fallback.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn transfer_stats(&self) -> Option<Arc<TransferStats>> {
+        self.transfer.load_full()
+    }
 }
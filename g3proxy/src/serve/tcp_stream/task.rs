@@ -17,6 +17,7 @@ use super::common::CommonTaskContext;
 use super::stats::{TcpStreamServerAliveTaskGuard, TcpStreamTaskCltWrapperStats};
 use crate::audit::AuditContext;
 use crate::auth::User;
+use crate::config::server::ClientErrorAction;
 use crate::inspect::{StreamInspectContext, StreamTransitTask};
 use crate::log::task::tcp_connect::TaskLogForTcpConnect;
 use crate::module::tcp_connect::{TcpConnectTaskConf, TcpConnectTaskNotes, TlsConnectTaskConf};
@@ -38,7 +39,8 @@ impl TcpStreamTask {
         upstream: &UpstreamAddr,
         audit_ctx: AuditContext,
     ) -> Self {
-        let task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), None, Duration::ZERO);
+        let mut task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), None, Duration::ZERO);
+        task_notes.set_log_sample(ctx.task_log_sampler.sample());
         TcpStreamTask {
             ctx,
             upstream: upstream.clone(),
@@ -59,6 +61,9 @@ impl TcpStreamTask {
                 upstream: &self.upstream,
                 task_notes: &self.task_notes,
                 tcp_notes: &self.tcp_notes,
+                connect_attempts: None,
+                connect_timeout: None,
+                original_dst: None,
                 client_rd_bytes: self.task_stats.clt.read.get_bytes(),
                 client_wr_bytes: self.task_stats.clt.write.get_bytes(),
                 remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
@@ -106,7 +111,7 @@ impl TcpStreamTask {
             })?;
 
         self.task_notes.stage = ServerTaskStage::Connecting;
-        let (ups_r, ups_w) = if let Some(tls_client_config) = &self.ctx.tls_client_config {
+        let r = if let Some(tls_client_config) = &self.ctx.tls_client_config {
             let tls_name = self
                 .ctx
                 .server_config
@@ -129,7 +134,8 @@ impl TcpStreamTask {
                     self.task_stats.clone(),
                     &mut self.audit_ctx,
                 )
-                .await?
+                .await
+                .map_err(ServerTaskError::from)
         } else {
             let task_conf = TcpConnectTaskConf {
                 upstream: &self.upstream,
@@ -143,10 +149,24 @@ impl TcpStreamTask {
                     self.task_stats.clone(),
                     &mut self.audit_ctx,
                 )
-                .await?
+                .await
+                .map_err(ServerTaskError::from)
+        };
+        let (ups_r, ups_w) = match r {
+            Ok(c) => c,
+            Err(e) => {
+                if let Some(action) = self.established_client_error_action(&e) {
+                    crate::serve::client_error::prepare_client_error_action(
+                        action,
+                        &self.ctx.cc_info,
+                    );
+                    self.task_notes.vars_mut().set_client_error_action(action);
+                }
+                return Err(e);
+            }
         };
 
-        self.task_notes.stage = ServerTaskStage::Connected;
+        self.task_notes.mark_connected(&self.tcp_notes.escaper);
         self.run_connected(clt_r, clt_w, ups_r, ups_w).await
     }
 
@@ -206,10 +226,23 @@ impl TcpStreamTask {
             )
             .await
         } else {
-            self.transit_transparent(clt_r, clt_w, ups_r, ups_w).await
+            let r = self.transit_transparent(clt_r, clt_w, ups_r, ups_w).await;
+            if let Err(e) = &r {
+                if let Some(action) = self.established_client_error_action(e) {
+                    self.task_notes.vars_mut().set_client_error_action(action);
+                }
+            }
+            r
         }
     }
 
+    fn established_client_error_action(&self, e: &ServerTaskError) -> Option<ClientErrorAction> {
+        crate::serve::client_error::established_client_error_action(
+            &self.ctx.server_config.client_error_actions,
+            e,
+        )
+    }
+
     fn setup_limit_and_stats<CR, CW>(
         &self,
         clt_r: CR,
@@ -282,4 +315,10 @@ impl StreamTransitTask for TcpStreamTask {
     fn user(&self) -> Option<&User> {
         None
     }
+
+    fn on_transit_error(&self, e: &ServerTaskError) {
+        if let Some(action) = self.established_client_error_action(e) {
+            crate::serve::client_error::prepare_client_error_action(action, &self.ctx.cc_info);
+        }
+    }
 }
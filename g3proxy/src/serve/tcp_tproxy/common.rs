@@ -3,34 +3,81 @@
  * Copyright 2024-2025 ByteDance and/or its affiliates.
  */
 
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::Duration;
 
 use slog::Logger;
 
-use g3_daemon::server::ClientConnectionInfo;
+use g3_daemon::server::{ClientConnectionInfo, TransferStats};
+use g3_histogram::HistogramRecorder;
 use g3_io_ext::IdleWheel;
 
 use crate::config::server::tcp_tproxy::TcpTProxyServerConfig;
 use crate::escape::ArcEscaper;
-use crate::serve::ServerQuitPolicy;
 use crate::serve::tcp_stream::TcpStreamServerStats;
+use crate::serve::{RunningTaskRegistry, ServerIdleOverrides, ServerQuitPolicy, TaskLogSampler};
+
+/// The original destination address of a transparent proxy connection
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) struct OriginalDst {
+    raw: SocketAddr,
+    target: SocketAddr,
+}
+
+impl OriginalDst {
+    pub(super) fn new(raw: SocketAddr, normalize_mapped: bool) -> Self {
+        let target = if normalize_mapped {
+            normalize_mapped_addr(raw)
+        } else {
+            raw
+        };
+        OriginalDst { raw, target }
+    }
+
+    /// the address got from the socket, which may be an IPv4-mapped IPv6 address
+    #[inline]
+    pub(super) fn raw(&self) -> SocketAddr {
+        self.raw
+    }
+
+    /// the address to be used as the upstream
+    #[inline]
+    pub(super) fn target(&self) -> SocketAddr {
+        self.target
+    }
+}
+
+/// Convert an IPv4-mapped IPv6 address to the IPv4 form
+fn normalize_mapped_addr(addr: SocketAddr) -> SocketAddr {
+    if let SocketAddr::V6(a6) = addr {
+        if let Some(ip4) = a6.ip().to_ipv4_mapped() {
+            return SocketAddr::new(IpAddr::V4(ip4), a6.port());
+        }
+    }
+    addr
+}
 
 pub(super) struct CommonTaskContext {
     pub(super) server_config: Arc<TcpTProxyServerConfig>,
     pub(super) server_stats: Arc<TcpStreamServerStats>,
     pub(super) server_quit_policy: Arc<ServerQuitPolicy>,
     pub(super) idle_wheel: Arc<IdleWheel>,
+    pub(super) idle_overrides: Arc<ServerIdleOverrides>,
     pub(super) escaper: ArcEscaper,
     pub(super) cc_info: ClientConnectionInfo,
+    pub(super) original_dst: OriginalDst,
     pub(super) task_logger: Option<Logger>,
+    pub(super) task_log_sampler: Arc<TaskLogSampler>,
+    pub(super) connect_duration_recorder: HistogramRecorder<u64>,
+    pub(super) transfer_stats: Option<Arc<TransferStats>>,
+    pub(super) running_tasks: Arc<RunningTaskRegistry>,
 }
 
 impl CommonTaskContext {
     #[inline]
     pub(super) fn target_addr(&self) -> SocketAddr {
-        self.cc_info.server_addr()
+        self.original_dst.target()
     }
 
     pub(super) fn log_flush_interval(&self) -> Option<Duration> {
@@ -38,3 +85,28 @@ impl CommonTaskContext {
         self.server_config.task_log_flush_interval
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn normalize_mapped() {
+        let raw = SocketAddr::from_str("[::ffff:192.0.2.1]:443").unwrap();
+        let dst = OriginalDst::new(raw, true);
+        assert_eq!(dst.raw(), raw);
+        assert_eq!(dst.target(), SocketAddr::from_str("192.0.2.1:443").unwrap());
+
+        let dst = OriginalDst::new(raw, false);
+        assert_eq!(dst.target(), raw);
+
+        let raw = SocketAddr::from_str("[2001:db8::1]:443").unwrap();
+        assert_eq!(OriginalDst::new(raw, true).target(), raw);
+        // IPv4-compatible addresses are deprecated and should not be converted
+        let raw = SocketAddr::from_str("[::192.0.2.1]:443").unwrap();
+        assert_eq!(OriginalDst::new(raw, true).target(), raw);
+        let raw = SocketAddr::from_str("192.0.2.1:443").unwrap();
+        assert_eq!(OriginalDst::new(raw, true).target(), raw);
+    }
+}
@@ -0,0 +1,266 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use g3_types::net::ConnectError;
+
+use crate::config::server::tcp_tproxy::TcpTProxyServerConfig;
+use crate::module::tcp_connect::TcpConnectError;
+
+/// The kind of the upstream address to connect to
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum TProxyConnectTarget {
+    /// the original destination of the intercepted connection
+    Original(SocketAddr),
+    /// the original destination again, after a previous connect failure
+    Retry(SocketAddr),
+    /// an alternate address from the upstream fallback rules
+    Fallback(SocketAddr),
+}
+
+impl TProxyConnectTarget {
+    pub(super) fn addr(&self) -> SocketAddr {
+        match self {
+            TProxyConnectTarget::Original(addr)
+            | TProxyConnectTarget::Retry(addr)
+            | TProxyConnectTarget::Fallback(addr) => *addr,
+        }
+    }
+}
+
+/// The upstream addresses to try if the connection to the original destination failed:
+/// the retries of the original destination first, and then the alternate addresses
+pub(super) struct TProxyConnectPlan {
+    original: SocketAddr,
+    retry_left: usize,
+    alternates: VecDeque<SocketAddr>,
+}
+
+impl TProxyConnectPlan {
+    pub(super) fn new(original: SocketAddr, config: &TcpTProxyServerConfig) -> Self {
+        TProxyConnectPlan {
+            original,
+            retry_left: config.connect_retry_count,
+            alternates: config.upstream_fallback.alternates(original).into(),
+        }
+    }
+
+    pub(super) fn next_target(&mut self) -> Option<TProxyConnectTarget> {
+        if self.retry_left > 0 {
+            self.retry_left -= 1;
+            return Some(TProxyConnectTarget::Retry(self.original));
+        }
+        self.alternates
+            .pop_front()
+            .map(TProxyConnectTarget::Fallback)
+    }
+
+    /// Only errors that happened while connecting to the upstream address are worth a retry
+    pub(super) fn should_retry(e: &TcpConnectError) -> bool {
+        matches!(
+            e,
+            TcpConnectError::ConnectFailed(_)
+                | TcpConnectError::TimeoutByRule
+                | TcpConnectError::NoAddressConnected
+        )
+    }
+}
+
+/// The phase in which the upstream connection timed out
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum TProxyConnectTimeout {
+    /// the whole escaper setup, bounded by the server side upstream connect timeout
+    EscaperSetup,
+    /// a single connect attempt inside the escaper
+    ConnectAttempt,
+}
+
+impl TProxyConnectTimeout {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            TProxyConnectTimeout::EscaperSetup => "escaper_setup",
+            TProxyConnectTimeout::ConnectAttempt => "connect_attempt",
+        }
+    }
+
+    fn from_error(e: &TcpConnectError) -> Option<Self> {
+        match e {
+            TcpConnectError::TimeoutByRule
+            | TcpConnectError::ConnectFailed(ConnectError::TimedOut) => {
+                Some(TProxyConnectTimeout::ConnectAttempt)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Run the escaper setup with the server side upstream connect timeout
+///
+/// The timeout phase will be returned along with the error if it's caused by a timeout.
+pub(super) async fn setup_with_timeout<F, T>(
+    timeout: Option<Duration>,
+    setup: F,
+) -> Result<T, (TcpConnectError, Option<TProxyConnectTimeout>)>
+where
+    F: Future<Output = Result<T, TcpConnectError>>,
+{
+    let r = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, setup).await {
+            Ok(r) => r,
+            Err(_) => {
+                return Err((
+                    TcpConnectError::TimeoutByRule,
+                    Some(TProxyConnectTimeout::EscaperSetup),
+                ));
+            }
+        },
+        None => setup.await,
+    };
+    r.map_err(|e| {
+        let phase = TProxyConnectTimeout::from_error(&e);
+        (e, phase)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use tokio::net::{TcpListener, TcpSocket, TcpStream};
+    use tokio::time::Instant;
+    use yaml_rust::YamlLoader;
+
+    fn build_config(yaml: &str) -> TcpTProxyServerConfig {
+        let doc = YamlLoader::load_from_str(yaml).unwrap();
+        TcpTProxyServerConfig::parse(doc[0].as_hash().unwrap(), None).unwrap()
+    }
+
+    #[test]
+    fn plan_order() {
+        let config = build_config(
+            r#"
+name: test
+escaper: default
+listen: 127.0.0.1:10443
+connect_retry_count: 1
+upstream_fallback:
+  - match_net: 127.0.0.1
+    to_port: 8443
+"#,
+        );
+        let dst = SocketAddr::from_str("127.0.0.1:443").unwrap();
+        let mut plan = TProxyConnectPlan::new(dst, &config);
+        assert_eq!(plan.next_target(), Some(TProxyConnectTarget::Retry(dst)));
+        assert_eq!(
+            plan.next_target(),
+            Some(TProxyConnectTarget::Fallback(
+                SocketAddr::from_str("127.0.0.1:8443").unwrap()
+            ))
+        );
+        assert_eq!(plan.next_target(), None);
+    }
+
+    #[tokio::test]
+    async fn fallback_to_listening() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listen_addr = listener.local_addr().unwrap();
+
+        // get a closed port by binding and then dropping the listener
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let config = build_config(&format!(
+            r#"
+name: test
+escaper: default
+listen: 127.0.0.1:10443
+upstream_fallback:
+  match_net: 127.0.0.1
+  match_port: {}
+  to: {listen_addr}
+"#,
+            closed_addr.port()
+        ));
+
+        let mut plan = TProxyConnectPlan::new(closed_addr, &config);
+        let mut target = TProxyConnectTarget::Original(closed_addr);
+        let mut tried = Vec::new();
+        let connected = loop {
+            tried.push(target);
+            if TcpStream::connect(target.addr()).await.is_ok() {
+                break Some(target.addr());
+            }
+            match plan.next_target() {
+                Some(next) => target = next,
+                None => break None,
+            }
+        };
+        assert_eq!(connected, Some(listen_addr));
+        assert_eq!(
+            tried,
+            vec![
+                TProxyConnectTarget::Original(closed_addr),
+                TProxyConnectTarget::Fallback(listen_addr),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn setup_timeout() {
+        let time_start = Instant::now();
+        let r = setup_with_timeout(
+            Some(Duration::from_millis(100)),
+            std::future::pending::<Result<(), TcpConnectError>>(),
+        )
+        .await;
+        let (e, phase) = r.unwrap_err();
+        assert!(matches!(e, TcpConnectError::TimeoutByRule));
+        assert_eq!(phase, Some(TProxyConnectTimeout::EscaperSetup));
+        let elapsed = time_start.elapsed();
+        assert!(elapsed >= Duration::from_millis(100));
+        assert!(elapsed < Duration::from_millis(500));
+
+        let r =
+            setup_with_timeout(None, async { Err::<(), _>(TcpConnectError::TimeoutByRule) }).await;
+        assert_eq!(r.unwrap_err().1, Some(TProxyConnectTimeout::ConnectAttempt));
+
+        let r = setup_with_timeout(None, async {
+            Err::<(), _>(TcpConnectError::ConnectFailed(
+                ConnectError::ConnectionRefused,
+            ))
+        })
+        .await;
+        assert_eq!(r.unwrap_err().1, None);
+    }
+
+    #[tokio::test]
+    async fn setup_timeout_non_routable() {
+        let peer = SocketAddr::from_str("10.255.255.1:80").unwrap();
+        let time_start = Instant::now();
+        let r = setup_with_timeout(Some(Duration::from_millis(200)), async {
+            let sock = TcpSocket::new_v4().map_err(TcpConnectError::SetupSocketFailed)?;
+            sock.connect(peer)
+                .await
+                .map_err(|e| TcpConnectError::ConnectFailed(ConnectError::from(e)))
+        })
+        .await;
+        let elapsed = time_start.elapsed();
+        assert!(elapsed < Duration::from_millis(700));
+        match r {
+            Ok(_) => panic!("connected to non-routable address {peer}"),
+            Err((_, Some(phase))) => {
+                assert_eq!(phase, TProxyConnectTimeout::EscaperSetup);
+                assert!(elapsed >= Duration::from_millis(200));
+            }
+            // fail at once if there is no route in the test environment
+            Err((_, None)) => {}
+        }
+    }
+}
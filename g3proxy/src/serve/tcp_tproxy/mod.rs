@@ -4,6 +4,7 @@
  */
 
 mod common;
+mod fallback;
 mod server;
 mod task;
 
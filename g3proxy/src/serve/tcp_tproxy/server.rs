@@ -15,15 +15,23 @@ use slog::Logger;
 use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio_rustls::server::TlsStream;
+use uuid::Uuid;
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
-use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenConnLimiter, ListenStats, ListenTcpRuntime,
+    ReceiveUdpServer,
+};
+use g3_daemon::server::{
+    AcceptRejectReason, AcceptRejectRecorder, AcceptRejectStats, BaseServer, ClientConnectionInfo,
+    ServerReloadCommand, TransferStats,
+};
+use g3_histogram::HistogramRecorder;
 use g3_io_ext::IdleWheel;
 use g3_openssl::SslStream;
 use g3_types::acl::{AclAction, AclNetworkRule};
 use g3_types::metrics::NodeName;
 
-use super::common::CommonTaskContext;
+use super::common::{CommonTaskContext, OriginalDst};
 use super::task::TProxyStreamTask;
 use crate::audit::{AuditContext, AuditHandle};
 use crate::config::server::tcp_tproxy::TcpTProxyServerConfig;
@@ -31,8 +39,9 @@ use crate::config::server::{AnyServerConfig, ServerConfig};
 use crate::escape::ArcEscaper;
 use crate::serve::tcp_stream::TcpStreamServerStats;
 use crate::serve::{
-    ArcServer, ArcServerInternal, ArcServerStats, Server, ServerInternal, ServerQuitPolicy,
-    ServerRegistry, ServerStats, WrapArcServer,
+    ArcServer, ArcServerInternal, ArcServerStats, RunningTask, RunningTaskRegistry, Server,
+    ServerCheckReport, ServerConnectFallbackStats, ServerIdleOverrides, ServerInternal,
+    ServerQuitPolicy, ServerRegistry, ServerStats, TaskLogSampler, WrapArcServer,
 };
 
 pub(crate) struct TcpTProxyServer {
@@ -40,13 +49,21 @@ pub(crate) struct TcpTProxyServer {
     server_stats: Arc<TcpStreamServerStats>,
     listen_stats: Arc<ListenStats>,
     ingress_net_filter: Option<AclNetworkRule>,
+    conn_limiter: ListenConnLimiter,
+    connect_duration_recorder: HistogramRecorder<u64>,
+    transfer_stats: Option<Arc<TransferStats>>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Option<Logger>,
+    task_log_sampler: Arc<TaskLogSampler>,
+    accept_reject_stats: Arc<AcceptRejectStats>,
+    accept_recorder: AcceptRejectRecorder,
 
     escaper: ArcSwap<ArcEscaper>,
     audit_handle: ArcSwapOption<AuditHandle>,
     quit_policy: Arc<ServerQuitPolicy>,
     idle_wheel: Arc<IdleWheel>,
+    idle_overrides: Arc<ServerIdleOverrides>,
+    running_tasks: Arc<RunningTaskRegistry>,
     reload_version: usize,
 }
 
@@ -55,6 +72,10 @@ impl TcpTProxyServer {
         config: Arc<TcpTProxyServerConfig>,
         server_stats: Arc<TcpStreamServerStats>,
         listen_stats: Arc<ListenStats>,
+        conn_limiter: ListenConnLimiter,
+        connect_duration_recorder: HistogramRecorder<u64>,
+        transfer_stats: Option<Arc<TransferStats>>,
+        accept_reject_stats: Arc<AcceptRejectStats>,
         version: usize,
     ) -> anyhow::Result<Self> {
         let reload_sender = crate::serve::new_reload_notify_channel();
@@ -65,7 +86,23 @@ impl TcpTProxyServer {
             .map(|builder| builder.build());
 
         let task_logger = config.get_task_logger();
-        let idle_wheel = IdleWheel::spawn(config.task_idle_check_duration);
+        let task_log_sampler = Arc::new(TaskLogSampler::new(&config.task_log_sample));
+        let accept_recorder = AcceptRejectRecorder::new(
+            accept_reject_stats.clone(),
+            task_logger.clone(),
+            config.accept_error_log_rate,
+        );
+        let idle_wheel = IdleWheel::spawn_with_jitter(
+            config.task_idle_check_duration,
+            config.task_idle_check_jitter,
+        );
+        let idle_overrides = Arc::new(ServerIdleOverrides::new(
+            &config.idle_overrides,
+            &idle_wheel,
+            config.task_idle_check_duration,
+            config.task_idle_check_jitter,
+            config.task_idle_max_count,
+        ));
 
         server_stats.set_extra_tags(config.extra_metrics_tags.clone());
 
@@ -77,12 +114,20 @@ impl TcpTProxyServer {
             server_stats,
             listen_stats,
             ingress_net_filter,
+            conn_limiter,
+            connect_duration_recorder,
+            transfer_stats,
             reload_sender,
             task_logger,
+            task_log_sampler,
+            accept_reject_stats,
+            accept_recorder,
             escaper: ArcSwap::new(escaper),
             audit_handle: ArcSwapOption::new(audit_handle),
             quit_policy: Arc::new(ServerQuitPolicy::default()),
             idle_wheel,
+            idle_overrides,
+            running_tasks: Arc::new(RunningTaskRegistry::default()),
             reload_version: version,
         };
 
@@ -95,8 +140,27 @@ impl TcpTProxyServer {
         let config = Arc::new(config);
         let server_stats = Arc::new(TcpStreamServerStats::new(config.name()));
         let listen_stats = Arc::new(ListenStats::new(config.name()));
-
-        let server = TcpTProxyServer::new(config, server_stats, listen_stats, 1)?;
+        let conn_limiter = ListenConnLimiter::new(
+            listen_stats.clone(),
+            config.max_connections,
+            config.accept_rate_limit.as_ref(),
+        );
+        let connect_duration_recorder = build_connect_duration_recorder(&config, &server_stats);
+        let transfer_stats = build_transfer_stats(&config, &server_stats);
+        let accept_reject_stats = Arc::new(AcceptRejectStats::default());
+        server_stats.set_accept_reject_stats(accept_reject_stats.clone());
+        server_stats.set_connect_fallback_stats(Arc::new(ServerConnectFallbackStats::default()));
+
+        let server = TcpTProxyServer::new(
+            config,
+            server_stats,
+            listen_stats,
+            conn_limiter,
+            connect_duration_recorder,
+            transfer_stats,
+            accept_reject_stats,
+            1,
+        )?;
         Ok(Arc::new(server))
     }
 
@@ -105,9 +169,34 @@ impl TcpTProxyServer {
             let config = Arc::new(config);
             let server_stats = Arc::clone(&self.server_stats);
             let listen_stats = Arc::clone(&self.listen_stats);
-
-            let server =
-                TcpTProxyServer::new(config, server_stats, listen_stats, self.reload_version + 1)?;
+            let conn_limiter = self
+                .conn_limiter
+                .new_for_reload(config.max_connections, config.accept_rate_limit.as_ref());
+            let connect_duration_recorder =
+                if self.config.connect_duration_stats == config.connect_duration_stats {
+                    self.connect_duration_recorder.clone()
+                } else {
+                    build_connect_duration_recorder(&config, &server_stats)
+                };
+            let transfer_stats =
+                if self.config.detailed_transfer_metrics == config.detailed_transfer_metrics {
+                    self.transfer_stats.clone()
+                } else {
+                    build_transfer_stats(&config, &server_stats)
+                };
+
+            let mut server = TcpTProxyServer::new(
+                config,
+                server_stats,
+                listen_stats,
+                conn_limiter,
+                connect_duration_recorder,
+                transfer_stats,
+                self.accept_reject_stats.clone(),
+                self.reload_version + 1,
+            )?;
+            // keep the tasks that are still running on the old server visible
+            server.running_tasks = self.running_tasks.clone();
             Ok(server)
         } else {
             Err(anyhow!(
@@ -118,36 +207,77 @@ impl TcpTProxyServer {
         }
     }
 
-    fn drop_early(&self, client_addr: SocketAddr) -> bool {
+    fn drop_early(&self, cc_info: &ClientConnectionInfo) -> bool {
         if let Some(ingress_net_filter) = &self.ingress_net_filter {
-            let (_, action) = ingress_net_filter.check(client_addr.ip());
+            let (_, action) = ingress_net_filter.check(cc_info.client_ip());
             match action {
                 AclAction::Permit | AclAction::PermitAndLog => {}
                 AclAction::Forbid | AclAction::ForbidAndLog => {
                     self.listen_stats.add_dropped();
+                    self.accept_recorder
+                        .record(AcceptRejectReason::IngressFiltered, cc_info, None);
                     return true;
                 }
             }
         }
 
-        // TODO add cps limit
-
         false
     }
 
+    fn get_original_dst(&self, cc_info: &ClientConnectionInfo) -> Option<OriginalDst> {
+        #[cfg(target_os = "linux")]
+        let raw = if self.config.redirect_original_dst {
+            match cc_info.tcp_sock_original_dst() {
+                Ok(addr) => addr,
+                Err(_) => {
+                    self.accept_recorder.record(
+                        AcceptRejectReason::OriginalDstUnknown,
+                        cc_info,
+                        None,
+                    );
+                    return None;
+                }
+            }
+        } else {
+            cc_info.server_addr()
+        };
+        #[cfg(not(target_os = "linux"))]
+        let raw = cc_info.server_addr();
+
+        let dst = OriginalDst::new(raw, self.config.normalize_mapped_dst);
+        // the connection is not redirected, so the task would connect to this server itself
+        if target_is_listen_addr(self.config.listen.address(), dst.target()) {
+            self.accept_recorder
+                .record(AcceptRejectReason::TargetIsSelf, cc_info, None);
+            return None;
+        }
+        Some(dst)
+    }
+
     fn audit_context(&self) -> AuditContext {
         AuditContext::new(self.audit_handle.load_full())
     }
 
-    async fn run_task(&self, stream: TcpStream, cc_info: ClientConnectionInfo) {
+    async fn run_task(
+        &self,
+        stream: TcpStream,
+        cc_info: ClientConnectionInfo,
+        original_dst: OriginalDst,
+    ) {
         let ctx = CommonTaskContext {
             server_config: self.config.clone(),
             server_stats: self.server_stats.clone(),
             server_quit_policy: self.quit_policy.clone(),
             idle_wheel: self.idle_wheel.clone(),
+            idle_overrides: self.idle_overrides.clone(),
             escaper: self.escaper.load().as_ref().clone(),
             cc_info,
+            original_dst,
             task_logger: self.task_logger.clone(),
+            task_log_sampler: self.task_log_sampler.clone(),
+            connect_duration_recorder: self.connect_duration_recorder.clone(),
+            transfer_stats: self.transfer_stats.clone(),
+            running_tasks: self.running_tasks.clone(),
         };
 
         TProxyStreamTask::new(ctx, self.audit_context())
@@ -156,6 +286,41 @@ impl TcpTProxyServer {
     }
 }
 
+fn target_is_listen_addr(listen_addr: SocketAddr, target: SocketAddr) -> bool {
+    if target.port() != listen_addr.port() {
+        return false;
+    }
+    let listen_ip = listen_addr.ip();
+    if listen_ip.is_unspecified() {
+        target.ip().is_loopback()
+    } else {
+        target.ip() == listen_ip
+    }
+}
+
+fn build_connect_duration_recorder(
+    config: &TcpTProxyServerConfig,
+    server_stats: &TcpStreamServerStats,
+) -> HistogramRecorder<u64> {
+    let (recorder, stats) = config
+        .connect_duration_stats
+        .build_spawned(g3_daemon::runtime::main_handle().cloned());
+    server_stats.set_connect_duration_stats(stats);
+    recorder
+}
+
+fn build_transfer_stats(
+    config: &TcpTProxyServerConfig,
+    server_stats: &TcpStreamServerStats,
+) -> Option<Arc<TransferStats>> {
+    let stats = config
+        .detailed_transfer_metrics
+        .as_ref()
+        .map(|c| c.build_stats());
+    server_stats.set_transfer_stats(stats.clone());
+    stats
+}
+
 impl ServerInternal for TcpTProxyServer {
     fn _clone_config(&self) -> AnyServerConfig {
         AnyServerConfig::TcpTProxy(self.config.as_ref().clone())
@@ -206,7 +371,8 @@ impl ServerInternal for TcpTProxyServer {
 
     fn _start_runtime(&self, server: ArcServer) -> anyhow::Result<()> {
         let listen_stats = server.get_listen_stats();
-        let runtime = ListenTcpRuntime::new(WrapArcServer(server), listen_stats);
+        let runtime = ListenTcpRuntime::new(WrapArcServer(server), listen_stats)
+            .with_worker_config(&self.config.listen_worker);
         runtime
             .run_all_instances(
                 &self.config.listen,
@@ -242,13 +408,23 @@ impl BaseServer for TcpTProxyServer {
 #[async_trait]
 impl AcceptTcpServer for TcpTProxyServer {
     async fn run_tcp_task(&self, stream: TcpStream, cc_info: ClientConnectionInfo) {
+        if g3_daemon::ban::is_banned(cc_info.client_ip()) {
+            self.listen_stats.add_dropped();
+            return;
+        }
         let client_addr = cc_info.client_addr();
         self.server_stats.add_conn(client_addr);
-        if self.drop_early(client_addr) {
+        if self.drop_early(&cc_info) {
             return;
         }
+        let Some(original_dst) = self.get_original_dst(&cc_info) else {
+            return;
+        };
+        let Ok(_conn_guard) = self.conn_limiter.try_acquire() else {
+            return;
+        };
 
-        self.run_task(stream, cc_info).await
+        self.run_task(stream, cc_info, original_dst).await
     }
 }
 
@@ -258,6 +434,17 @@ impl AcceptQuicServer for TcpTProxyServer {
     async fn run_quic_task(&self, _connection: Connection, _cc_info: ClientConnectionInfo) {}
 }
 
+impl ReceiveUdpServer for TcpTProxyServer {
+    fn receive_udp_packet(
+        &self,
+        _packet: &[u8],
+        _client_addr: SocketAddr,
+        _server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) {
+    }
+}
+
 #[async_trait]
 impl Server for TcpTProxyServer {
     fn escaper(&self) -> &NodeName {
@@ -289,6 +476,10 @@ impl Server for TcpTProxyServer {
         &self.quit_policy
     }
 
+    fn task_log_sampler(&self) -> Option<&TaskLogSampler> {
+        Some(&self.task_log_sampler)
+    }
+
     async fn run_rustls_task(&self, _stream: TlsStream<TcpStream>, _cc_info: ClientConnectionInfo) {
     }
 
@@ -298,4 +489,62 @@ impl Server for TcpTProxyServer {
         _cc_info: ClientConnectionInfo,
     ) {
     }
+
+    async fn loopback_check(&self, _sni: Option<&str>) -> anyhow::Result<ServerCheckReport> {
+        // the local address of a loopback connection is the listen address itself,
+        // so the task would connect to this server again and again
+        Err(anyhow!(
+            "loopback check is not supported on transparent proxy servers"
+        ))
+    }
+    fn list_tasks(
+        &self,
+        after: Option<Uuid>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Arc<RunningTask>>> {
+        Ok(self.running_tasks.list(after, limit))
+    }
+
+    fn kill_task(&self, id: &Uuid) -> anyhow::Result<()> {
+        self.running_tasks.kill(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn target_is_self() {
+        let listen_addr = SocketAddr::from_str("0.0.0.0:8080").unwrap();
+        assert!(target_is_listen_addr(
+            listen_addr,
+            SocketAddr::from_str("127.0.0.1:8080").unwrap()
+        ));
+        assert!(!target_is_listen_addr(
+            listen_addr,
+            SocketAddr::from_str("192.0.2.1:8080").unwrap()
+        ));
+        assert!(!target_is_listen_addr(
+            listen_addr,
+            SocketAddr::from_str("127.0.0.1:80").unwrap()
+        ));
+
+        let mapped = SocketAddr::from_str("[::ffff:127.0.0.1]:8080").unwrap();
+        assert!(target_is_listen_addr(
+            listen_addr,
+            OriginalDst::new(mapped, true).target()
+        ));
+
+        let listen_addr = SocketAddr::from_str("192.0.2.2:8080").unwrap();
+        assert!(target_is_listen_addr(
+            listen_addr,
+            SocketAddr::from_str("192.0.2.2:8080").unwrap()
+        ));
+        assert!(!target_is_listen_addr(
+            listen_addr,
+            SocketAddr::from_str("127.0.0.1:8080").unwrap()
+        ));
+    }
 }
@@ -4,46 +4,66 @@
  */
 
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 
-use g3_daemon::server::ServerQuitPolicy;
+use g3_daemon::server::{ServerQuitPolicy, TransferStats};
 use g3_daemon::stat::task::TcpStreamTaskStats;
 use g3_io_ext::{IdleInterval, LimitedReader, LimitedWriter, StreamCopyConfig};
+use g3_std_ext::time::DurationExt;
 use g3_types::net::UpstreamAddr;
 
 use super::common::CommonTaskContext;
+use super::fallback::{TProxyConnectPlan, TProxyConnectTarget, TProxyConnectTimeout};
 use crate::audit::AuditContext;
 use crate::auth::User;
+use crate::config::server::ClientErrorAction;
 use crate::inspect::{StreamInspectContext, StreamTransitTask};
 use crate::log::task::tcp_connect::TaskLogForTcpConnect;
-use crate::module::tcp_connect::{TcpConnectTaskConf, TcpConnectTaskNotes};
+use crate::module::tcp_connect::{
+    TcpConnectAttempts, TcpConnectTaskConf, TcpConnectTaskNotes, TcpConnection,
+};
 use crate::serve::tcp_stream::{TcpStreamServerAliveTaskGuard, TcpStreamTaskCltWrapperStats};
-use crate::serve::{ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage};
+use crate::serve::{
+    RunningTask, RunningTaskGuard, ServerIdleOverride, ServerTaskError, ServerTaskNotes,
+    ServerTaskResult, ServerTaskStage,
+};
 
 pub(super) struct TProxyStreamTask {
     ctx: CommonTaskContext,
     upstream: UpstreamAddr,
     tcp_notes: TcpConnectTaskNotes,
+    connect_attempts: TcpConnectAttempts,
+    connect_timeout: Option<TProxyConnectTimeout>,
     task_notes: ServerTaskNotes,
     task_stats: Arc<TcpStreamTaskStats>,
     audit_ctx: AuditContext,
+    idle_override: Option<Arc<ServerIdleOverride>>,
+    running_task: Option<RunningTaskGuard>,
     _alive_guard: Option<TcpStreamServerAliveTaskGuard>,
 }
 
 impl TProxyStreamTask {
     pub(super) fn new(ctx: CommonTaskContext, audit_ctx: AuditContext) -> Self {
         let target = ctx.target_addr();
-        let task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), None, Duration::ZERO);
+        let mut task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), None, Duration::ZERO);
+        task_notes.set_log_sample(ctx.task_log_sampler.sample());
+        task_notes.tcp_connect_each_timeout = ctx.server_config.upstream_connect_attempt_timeout;
+        task_notes.tcp_upstream_misc_opts = ctx.server_config.upstream_misc_opts.clone();
+        let idle_override = ctx.idle_overrides.select_for_task(&mut task_notes);
         TProxyStreamTask {
             ctx,
             upstream: UpstreamAddr::from(target),
             tcp_notes: TcpConnectTaskNotes::default(),
+            connect_attempts: TcpConnectAttempts::default(),
+            connect_timeout: None,
             task_notes,
             task_stats: Arc::new(TcpStreamTaskStats::default()),
             audit_ctx,
+            idle_override,
+            running_task: None,
             _alive_guard: None,
         }
     }
@@ -57,6 +77,10 @@ impl TProxyStreamTask {
                 upstream: &self.upstream,
                 task_notes: &self.task_notes,
                 tcp_notes: &self.tcp_notes,
+                connect_attempts: (self.connect_attempts.len() > 1)
+                    .then_some(&self.connect_attempts),
+                connect_timeout: self.connect_timeout.map(|t| t.as_str()),
+                original_dst: Some(self.ctx.original_dst.raw()),
                 client_rd_bytes: self.task_stats.clt.read.get_bytes(),
                 client_wr_bytes: self.task_stats.clt.write.get_bytes(),
                 remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
@@ -77,6 +101,12 @@ impl TProxyStreamTask {
 
     fn pre_start(&mut self) {
         self._alive_guard = Some(self.ctx.server_stats.add_task());
+        let running_task = self
+            .ctx
+            .running_tasks
+            .register(&self.task_notes, self.task_stats.clone());
+        running_task.task().set_upstream(&self.upstream);
+        self.running_task = Some(running_task);
 
         if self.ctx.server_config.flush_task_log_on_created {
             if let Some(log_ctx) = self.get_log_context() {
@@ -89,32 +119,111 @@ impl TProxyStreamTask {
         // set client side socket options
         self.ctx
             .cc_info
-            .tcp_sock_set_raw_opts(&self.ctx.server_config.tcp_misc_opts, true)
+            .tcp_sock_set_raw_opts(&self.ctx.server_config.client_misc_opts, true)
             .map_err(|_| {
                 ServerTaskError::InternalServerError("failed to set client socket options")
             })?;
 
         self.task_notes.stage = ServerTaskStage::Connecting;
 
-        let task_conf = TcpConnectTaskConf {
-            upstream: &self.upstream,
+        let (ups_r, ups_w) = match self.connect_upstream().await {
+            Ok(c) => c,
+            Err(e) => {
+                if let Some(action) = self.established_client_error_action(&e) {
+                    crate::serve::client_error::prepare_client_error_action(
+                        action,
+                        &self.ctx.cc_info,
+                    );
+                    self.task_notes.vars_mut().set_client_error_action(action);
+                } else if self.connect_timeout.is_some()
+                    && self.ctx.server_config.upstream_connect_timeout_reset
+                {
+                    // transparent clients have no other way to know the connect failure
+                    let _ = clt_stream.set_linger(Some(Duration::ZERO));
+                }
+                return Err(e);
+            }
         };
-        let (ups_r, ups_w) = self
+        let _ = self
             .ctx
-            .escaper
-            .tcp_setup_connection(
-                &task_conf,
-                &mut self.tcp_notes,
-                &self.task_notes,
-                self.task_stats.clone(),
-                &mut self.audit_ctx,
-            )
-            .await?;
+            .connect_duration_recorder
+            .record(self.tcp_notes.duration.as_nanos_u64());
 
-        self.task_notes.stage = ServerTaskStage::Connected;
+        if let Some(running_task) = &self.running_task {
+            // the upstream may be changed by retry or fallback
+            running_task.task().set_upstream(&self.upstream);
+        }
+        self.task_notes.mark_connected(&self.tcp_notes.escaper);
         self.run_connected(clt_stream, ups_r, ups_w).await
     }
 
+    async fn connect_upstream(&mut self) -> ServerTaskResult<TcpConnection> {
+        let mut target = TProxyConnectTarget::Original(self.ctx.target_addr());
+        let mut plan = TProxyConnectPlan::new(target.addr(), &self.ctx.server_config);
+        let mut retried = false;
+        let mut fallback = false;
+
+        loop {
+            match target {
+                TProxyConnectTarget::Original(_) => {}
+                TProxyConnectTarget::Retry(_) => {
+                    if !retried {
+                        retried = true;
+                        self.ctx.server_stats.add_connect_retried();
+                    }
+                }
+                TProxyConnectTarget::Fallback(_) => {
+                    if !fallback {
+                        fallback = true;
+                        self.ctx.server_stats.add_connect_fallback();
+                    }
+                }
+            }
+
+            self.upstream = UpstreamAddr::from(target.addr());
+            self.tcp_notes.reset();
+            let task_conf = TcpConnectTaskConf {
+                upstream: &self.upstream,
+            };
+            let time_start = Instant::now();
+            let r = super::fallback::setup_with_timeout(
+                self.ctx.server_config.upstream_connect_timeout,
+                self.ctx.escaper.tcp_setup_connection(
+                    &task_conf,
+                    &mut self.tcp_notes,
+                    &self.task_notes,
+                    self.task_stats.clone(),
+                    &mut self.audit_ctx,
+                ),
+            )
+            .await;
+            let duration = time_start.elapsed();
+            match r {
+                Ok(c) => {
+                    self.connect_timeout = None;
+                    self.connect_attempts.push(target.addr(), None, duration);
+                    return Ok(c);
+                }
+                Err((e, timeout)) => {
+                    self.connect_timeout = timeout;
+                    self.connect_attempts
+                        .push(target.addr(), Some(e.to_string()), duration);
+                    if !TProxyConnectPlan::should_retry(&e) {
+                        return Err(e.into());
+                    }
+                    let Some(next) = plan.next_target() else {
+                        if self.connect_attempts.len() > 1 {
+                            self.ctx.server_stats.add_connect_exhausted();
+                        }
+                        return Err(e.into());
+                    };
+                    target = next;
+                    tokio::time::sleep(self.ctx.server_config.connect_retry_delay).await;
+                }
+            }
+        }
+    }
+
     async fn run_connected<R, W>(
         &mut self,
         clt_stream: TcpStream,
@@ -147,7 +256,7 @@ impl TProxyStreamTask {
         let (clt_r, clt_w) = self.split_clt(clt_stream);
 
         if let Some(audit_handle) = self.audit_ctx.check_take_handle() {
-            let ctx = StreamInspectContext::new(
+            let mut ctx = StreamInspectContext::new(
                 audit_handle,
                 self.ctx.server_config.clone(),
                 self.ctx.server_stats.clone(),
@@ -156,6 +265,9 @@ impl TProxyStreamTask {
                 &self.task_notes,
                 &self.tcp_notes,
             );
+            if let Some(idle_override) = &self.idle_override {
+                ctx.set_idle_override(idle_override);
+            }
             crate::inspect::stream::transit_with_inspection(
                 clt_r,
                 clt_w,
@@ -167,10 +279,23 @@ impl TProxyStreamTask {
             )
             .await
         } else {
-            self.transit_transparent(clt_r, clt_w, ups_r, ups_w).await
+            let r = self.transit_transparent(clt_r, clt_w, ups_r, ups_w).await;
+            if let Err(e) = &r {
+                if let Some(action) = self.established_client_error_action(e) {
+                    self.task_notes.vars_mut().set_client_error_action(action);
+                }
+            }
+            r
         }
     }
 
+    fn established_client_error_action(&self, e: &ServerTaskError) -> Option<ClientErrorAction> {
+        crate::serve::client_error::established_client_error_action(
+            &self.ctx.server_config.client_error_actions,
+            e,
+        )
+    }
+
     fn split_clt(
         &self,
         clt_stream: TcpStream,
@@ -207,11 +332,17 @@ impl StreamTransitTask for TProxyStreamTask {
     }
 
     fn idle_check_interval(&self) -> IdleInterval {
-        self.ctx.idle_wheel.register()
+        match &self.idle_override {
+            Some(idle_override) => idle_override.idle_wheel.register(),
+            None => self.ctx.idle_wheel.register(),
+        }
     }
 
     fn max_idle_count(&self) -> usize {
-        self.ctx.server_config.task_idle_max_count
+        match &self.idle_override {
+            Some(idle_override) => idle_override.max_idle_count,
+            None => self.ctx.server_config.task_idle_max_count,
+        }
     }
 
     fn log_client_shutdown(&self) {
@@ -243,4 +374,18 @@ impl StreamTransitTask for TProxyStreamTask {
     fn user(&self) -> Option<&User> {
         None
     }
+
+    fn transfer_stats(&self) -> Option<&TransferStats> {
+        self.ctx.transfer_stats.as_deref()
+    }
+
+    fn running_task(&self) -> Option<&RunningTask> {
+        self.running_task.as_ref().map(|guard| guard.task())
+    }
+
+    fn on_transit_error(&self, e: &ServerTaskError) {
+        if let Some(action) = self.established_client_error_action(e) {
+            crate::serve::client_error::prepare_client_error_action(action, &self.ctx.cc_info);
+        }
+    }
 }
@@ -14,8 +14,8 @@ use g3_types::net::OpensslClientConfig;
 
 use crate::config::server::tls_stream::TlsStreamServerConfig;
 use crate::escape::ArcEscaper;
-use crate::serve::ServerQuitPolicy;
 use crate::serve::tcp_stream::TcpStreamServerStats;
+use crate::serve::{ServerQuitPolicy, TaskLogSampler};
 
 pub(super) struct CommonTaskContext {
     pub(super) server_config: Arc<TlsStreamServerConfig>,
@@ -26,6 +26,7 @@ pub(super) struct CommonTaskContext {
     pub(super) cc_info: ClientConnectionInfo,
     pub(super) tls_client_config: Option<Arc<OpensslClientConfig>>,
     pub(super) task_logger: Option<Logger>,
+    pub(super) task_log_sampler: Arc<TaskLogSampler>,
 }
 
 impl CommonTaskContext {
@@ -18,7 +18,9 @@ use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio_rustls::{TlsAcceptor, server::TlsStream};
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime, ReceiveUdpServer,
+};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerExt, ServerReloadCommand};
 use g3_io_ext::IdleWheel;
 use g3_openssl::SslStream;
@@ -39,7 +41,7 @@ use crate::escape::ArcEscaper;
 use crate::serve::tcp_stream::TcpStreamServerStats;
 use crate::serve::{
     ArcServer, ArcServerInternal, ArcServerStats, Server, ServerInternal, ServerQuitPolicy,
-    ServerRegistry, ServerStats, WrapArcServer,
+    ServerRegistry, ServerStats, TaskLogSampler, WrapArcServer,
 };
 
 pub(crate) struct TlsStreamServer {
@@ -54,6 +56,7 @@ pub(crate) struct TlsStreamServer {
     ingress_net_filter: Option<AclNetworkRule>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Option<Logger>,
+    task_log_sampler: Arc<TaskLogSampler>,
 
     escaper: ArcSwap<ArcEscaper>,
     audit_handle: ArcSwapOption<AuditHandle>,
@@ -101,7 +104,11 @@ impl TlsStreamServer {
             .map(|builder| builder.build());
 
         let task_logger = config.get_task_logger();
-        let idle_wheel = IdleWheel::spawn(config.task_idle_check_duration);
+        let task_log_sampler = Arc::new(TaskLogSampler::new(&config.task_log_sample));
+        let idle_wheel = IdleWheel::spawn_with_jitter(
+            config.task_idle_check_duration,
+            config.task_idle_check_jitter,
+        );
 
         server_stats.set_extra_tags(config.extra_metrics_tags.clone());
 
@@ -120,6 +127,7 @@ impl TlsStreamServer {
             ingress_net_filter,
             reload_sender,
             task_logger,
+            task_log_sampler,
             escaper: ArcSwap::new(escaper),
             audit_handle: ArcSwapOption::new(audit_handle),
             quit_policy: Arc::new(ServerQuitPolicy::default()),
@@ -219,6 +227,7 @@ impl TlsStreamServer {
             cc_info,
             tls_client_config: self.tls_client_config.clone(),
             task_logger: self.task_logger.clone(),
+            task_log_sampler: self.task_log_sampler.clone(),
         };
 
         TlsStreamTask::new(ctx, upstream.inner(), self.audit_context())
@@ -361,6 +370,17 @@ impl AcceptQuicServer for TlsStreamServer {
     async fn run_quic_task(&self, _connection: Connection, _cc_info: ClientConnectionInfo) {}
 }
 
+impl ReceiveUdpServer for TlsStreamServer {
+    fn receive_udp_packet(
+        &self,
+        _packet: &[u8],
+        _client_addr: SocketAddr,
+        _server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) {
+    }
+}
+
 #[async_trait]
 impl Server for TlsStreamServer {
     fn escaper(&self) -> &NodeName {
@@ -392,6 +412,10 @@ impl Server for TlsStreamServer {
         &self.quit_policy
     }
 
+    fn task_log_sampler(&self) -> Option<&TaskLogSampler> {
+        Some(&self.task_log_sampler)
+    }
+
     async fn run_rustls_task(&self, _stream: TlsStream<TcpStream>, _cc_info: ClientConnectionInfo) {
     }
 
@@ -40,7 +40,8 @@ impl TlsStreamTask {
         upstream: &UpstreamAddr,
         audit_ctx: AuditContext,
     ) -> Self {
-        let task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), None, Duration::ZERO);
+        let mut task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), None, Duration::ZERO);
+        task_notes.set_log_sample(ctx.task_log_sampler.sample());
         TlsStreamTask {
             ctx,
             upstream: upstream.clone(),
@@ -61,6 +62,9 @@ impl TlsStreamTask {
                 upstream: &self.upstream,
                 task_notes: &self.task_notes,
                 tcp_notes: &self.tcp_notes,
+                connect_attempts: None,
+                connect_timeout: None,
+                original_dst: None,
                 client_rd_bytes: self.task_stats.clt.read.get_bytes(),
                 client_wr_bytes: self.task_stats.clt.write.get_bytes(),
                 remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
@@ -139,7 +143,7 @@ impl TlsStreamTask {
                 .await?
         };
 
-        self.task_notes.stage = ServerTaskStage::Connected;
+        self.task_notes.mark_connected(&self.tcp_notes.escaper);
         self.run_connected(clt_stream, ups_r, ups_w).await
     }
 
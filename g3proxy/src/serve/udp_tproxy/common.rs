@@ -0,0 +1,77 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use slog::Logger;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+
+use g3_daemon::server::ClientConnectionInfo;
+use g3_io_ext::{IdleWheel, OptionalInterval};
+
+use super::session::UdpTProxySessionMap;
+use super::stats::UdpTProxyServerStats;
+use crate::config::server::udp_tproxy::UdpTProxyServerConfig;
+use crate::escape::ArcEscaper;
+use crate::serve::{ServerQuitPolicy, ServerTaskError, ServerTaskResult, TaskLogSampler};
+
+pub(super) struct CommonTaskContext {
+    pub(super) server_config: Arc<UdpTProxyServerConfig>,
+    pub(super) server_stats: Arc<UdpTProxyServerStats>,
+    pub(super) server_quit_policy: Arc<ServerQuitPolicy>,
+    pub(super) idle_wheel: Arc<IdleWheel>,
+    pub(super) escaper: ArcEscaper,
+    pub(super) sessions: Arc<UdpTProxySessionMap>,
+    pub(super) cc_info: ClientConnectionInfo,
+    pub(super) task_logger: Option<Logger>,
+    pub(super) task_log_sampler: Arc<TaskLogSampler>,
+}
+
+impl CommonTaskContext {
+    #[inline]
+    pub(super) fn client_addr(&self) -> SocketAddr {
+        self.cc_info.client_addr()
+    }
+
+    #[inline]
+    pub(super) fn target_addr(&self) -> SocketAddr {
+        self.cc_info.server_addr()
+    }
+
+    pub(super) fn setup_reply_socket(&self) -> ServerTaskResult<UdpSocket> {
+        let socket = g3_socket::udp::new_std_transparent_reply(
+            self.target_addr(),
+            self.client_addr(),
+            self.server_config.udp_socket_buffer,
+            self.server_config.udp_misc_opts,
+        )
+        .map_err(|_| {
+            ServerTaskError::InternalServerError("setup udp reply socket to client failed")
+        })?;
+        UdpSocket::from_std(socket).map_err(|_| {
+            ServerTaskError::InternalServerError(
+                "failed to convert std udp socket to tokio udp socket",
+            )
+        })
+    }
+
+    pub(super) fn log_flush_interval(&self) -> Option<Duration> {
+        self.task_logger.as_ref()?;
+        self.server_config.task_log_flush_interval
+    }
+
+    pub(super) fn get_log_interval(&self) -> OptionalInterval {
+        self.log_flush_interval()
+            .map(|log_interval| {
+                let log_interval =
+                    tokio::time::interval_at(Instant::now() + log_interval, log_interval);
+                OptionalInterval::with(log_interval)
+            })
+            .unwrap_or_default()
+    }
+}
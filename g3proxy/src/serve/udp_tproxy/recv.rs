@@ -0,0 +1,112 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io::IoSliceMut;
+use std::task::{Context, Poll, ready};
+
+use tokio::sync::mpsc;
+
+use g3_io_ext::{
+    ArcLimitedRecvStats, AsyncUdpRecv, UdpCopyClientError, UdpCopyClientRecv, UdpCopyPacket,
+    UdpCopyPacketMeta,
+};
+use g3_io_sys::udp::RecvMsgHdr;
+
+/// Receive client packets both from the listen socket, which are dispatched to the queue,
+/// and from the reply socket, which may take over the traffic as it's connected
+pub(super) struct UdpTProxyClientRecv<T> {
+    queue: mpsc::Receiver<Vec<u8>>,
+    inner: T,
+    queue_stats: ArcLimitedRecvStats,
+}
+
+impl<T> UdpTProxyClientRecv<T>
+where
+    T: AsyncUdpRecv,
+{
+    pub(super) fn new(
+        queue: mpsc::Receiver<Vec<u8>>,
+        inner: T,
+        queue_stats: ArcLimitedRecvStats,
+    ) -> Self {
+        UdpTProxyClientRecv {
+            queue,
+            inner,
+            queue_stats,
+        }
+    }
+
+    fn poll_recv_queued(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Option<usize> {
+        match self.queue.poll_recv(cx) {
+            Poll::Ready(Some(packet)) => {
+                let len = packet.len().min(buf.len());
+                buf[..len].copy_from_slice(&packet[..len]);
+                self.queue_stats.add_recv_packet();
+                self.queue_stats.add_recv_bytes(len);
+                Some(len)
+            }
+            Poll::Ready(None) | Poll::Pending => None,
+        }
+    }
+}
+
+impl<T> UdpCopyClientRecv for UdpTProxyClientRecv<T>
+where
+    T: AsyncUdpRecv + Send,
+{
+    fn max_hdr_len(&self) -> usize {
+        0
+    }
+
+    fn poll_recv_packet(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<(usize, usize), UdpCopyClientError>> {
+        if let Some(len) = self.poll_recv_queued(cx, buf) {
+            return Poll::Ready(Ok((0, len)));
+        }
+
+        let nr = ready!(self.inner.poll_recv(cx, buf)).map_err(UdpCopyClientError::RecvFailed)?;
+        Poll::Ready(Ok((0, nr)))
+    }
+
+    fn poll_recv_packets(
+        &mut self,
+        cx: &mut Context<'_>,
+        packets: &mut [UdpCopyPacket],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        let mut count = 0;
+        for p in packets.iter_mut() {
+            let Some(len) = self.poll_recv_queued(cx, p.buf_mut()) else {
+                break;
+            };
+            let m = UdpCopyPacketMeta::new(&IoSliceMut::new(p.buf_mut()), 0, len);
+            m.set_packet(p);
+            count += 1;
+        }
+        if count > 0 {
+            return Poll::Ready(Ok(count));
+        }
+
+        let mut hdr_v: Vec<RecvMsgHdr<1>> = packets
+            .iter_mut()
+            .map(|p| RecvMsgHdr::new([IoSliceMut::new(p.buf_mut())]))
+            .collect();
+
+        let count = ready!(self.inner.poll_batch_recvmsg(cx, &mut hdr_v))
+            .map_err(UdpCopyClientError::RecvFailed)?;
+
+        let mut r = Vec::with_capacity(count);
+        for h in hdr_v.into_iter().take(count) {
+            r.push(UdpCopyPacketMeta::new(&h.iov[0], 0, h.n_recv));
+        }
+        for (m, p) in r.into_iter().zip(packets.iter_mut()) {
+            m.set_packet(p);
+        }
+
+        Poll::Ready(Ok(count))
+    }
+}
@@ -0,0 +1,346 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+#[cfg(feature = "quic")]
+use quinn::Connection;
+use slog::Logger;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio_rustls::server::TlsStream;
+
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenStats, ReceiveUdpRuntime, ReceiveUdpServer,
+};
+use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
+use g3_io_ext::IdleWheel;
+use g3_openssl::SslStream;
+use g3_types::acl::{AclAction, AclNetworkRule};
+use g3_types::metrics::NodeName;
+
+use super::common::CommonTaskContext;
+use super::session::{UdpTProxySessionDispatch, UdpTProxySessionMap};
+use super::stats::UdpTProxyServerStats;
+use super::task::UdpTProxyTask;
+use crate::config::server::udp_tproxy::UdpTProxyServerConfig;
+use crate::config::server::{AnyServerConfig, ServerConfig};
+use crate::escape::ArcEscaper;
+use crate::serve::{
+    ArcServer, ArcServerInternal, ArcServerStats, Server, ServerInternal, ServerQuitPolicy,
+    ServerRegistry, ServerStats, TaskLogSampler, WrapArcServer,
+};
+
+pub(crate) struct UdpTProxyServer {
+    config: Arc<UdpTProxyServerConfig>,
+    server_stats: Arc<UdpTProxyServerStats>,
+    listen_stats: Arc<ListenStats>,
+    ingress_net_filter: Option<AclNetworkRule>,
+    reload_sender: broadcast::Sender<ServerReloadCommand>,
+    task_logger: Option<Logger>,
+    task_log_sampler: Arc<TaskLogSampler>,
+    sessions: Arc<UdpTProxySessionMap>,
+
+    escaper: ArcSwap<ArcEscaper>,
+    quit_policy: Arc<ServerQuitPolicy>,
+    idle_wheel: Arc<IdleWheel>,
+    reload_version: usize,
+}
+
+impl UdpTProxyServer {
+    fn new(
+        config: Arc<UdpTProxyServerConfig>,
+        server_stats: Arc<UdpTProxyServerStats>,
+        listen_stats: Arc<ListenStats>,
+        sessions: Arc<UdpTProxySessionMap>,
+        version: usize,
+    ) -> Self {
+        let reload_sender = crate::serve::new_reload_notify_channel();
+
+        let ingress_net_filter = config
+            .ingress_net_filter
+            .as_ref()
+            .map(|builder| builder.build());
+
+        let task_logger = config.get_task_logger();
+        let task_log_sampler = Arc::new(TaskLogSampler::new(&config.task_log_sample));
+        let idle_wheel = IdleWheel::spawn_with_jitter(
+            config.task_idle_check_duration,
+            config.task_idle_check_jitter,
+        );
+
+        server_stats.set_extra_tags(config.extra_metrics_tags.clone());
+
+        let escaper = Arc::new(crate::escape::get_or_insert_default(config.escaper()));
+
+        UdpTProxyServer {
+            config,
+            server_stats,
+            listen_stats,
+            ingress_net_filter,
+            reload_sender,
+            task_logger,
+            task_log_sampler,
+            sessions,
+            escaper: ArcSwap::new(escaper),
+            quit_policy: Arc::new(ServerQuitPolicy::default()),
+            idle_wheel,
+            reload_version: version,
+        }
+    }
+
+    pub(crate) fn prepare_initial(
+        config: UdpTProxyServerConfig,
+    ) -> anyhow::Result<ArcServerInternal> {
+        let config = Arc::new(config);
+        let server_stats = Arc::new(UdpTProxyServerStats::new(config.name()));
+        let listen_stats = Arc::new(ListenStats::new(config.name()));
+        let sessions = Arc::new(UdpTProxySessionMap::default());
+
+        let server = UdpTProxyServer::new(config, server_stats, listen_stats, sessions, 1);
+        Ok(Arc::new(server))
+    }
+
+    fn prepare_reload(&self, config: AnyServerConfig) -> anyhow::Result<Self> {
+        if let AnyServerConfig::UdpTProxy(config) = config {
+            let config = Arc::new(config);
+            let server_stats = Arc::clone(&self.server_stats);
+            let listen_stats = Arc::clone(&self.listen_stats);
+            let sessions = Arc::clone(&self.sessions);
+
+            let server = UdpTProxyServer::new(
+                config,
+                server_stats,
+                listen_stats,
+                sessions,
+                self.reload_version + 1,
+            );
+            Ok(server)
+        } else {
+            Err(anyhow!(
+                "config type mismatch: expect {}, actual {}",
+                self.config.r#type(),
+                config.r#type()
+            ))
+        }
+    }
+
+    fn drop_early(&self, client_addr: SocketAddr) -> bool {
+        if let Some(ingress_net_filter) = &self.ingress_net_filter {
+            let (_, action) = ingress_net_filter.check(client_addr.ip());
+            match action {
+                AclAction::Permit | AclAction::PermitAndLog => {}
+                AclAction::Forbid | AclAction::ForbidAndLog => {
+                    self.listen_stats.add_dropped();
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+impl ServerInternal for UdpTProxyServer {
+    fn _clone_config(&self) -> AnyServerConfig {
+        AnyServerConfig::UdpTProxy(self.config.as_ref().clone())
+    }
+
+    fn _depend_on_server(&self, _name: &NodeName) -> bool {
+        false
+    }
+
+    fn _reload_config_notify_runtime(&self) {
+        let cmd = ServerReloadCommand::ReloadVersion(self.reload_version);
+        let _ = self.reload_sender.send(cmd);
+    }
+
+    fn _update_next_servers_in_place(&self) {}
+
+    fn _update_escaper_in_place(&self) {
+        let escaper = crate::escape::get_or_insert_default(self.config.escaper());
+        self.escaper.store(Arc::new(escaper));
+    }
+
+    fn _update_user_group_in_place(&self) {}
+
+    fn _update_audit_handle_in_place(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn _reload_with_old_notifier(
+        &self,
+        config: AnyServerConfig,
+        _registry: &mut ServerRegistry,
+    ) -> anyhow::Result<ArcServerInternal> {
+        let mut server = self.prepare_reload(config)?;
+        server.reload_sender = self.reload_sender.clone();
+        Ok(Arc::new(server))
+    }
+
+    fn _reload_with_new_notifier(
+        &self,
+        config: AnyServerConfig,
+        _registry: &mut ServerRegistry,
+    ) -> anyhow::Result<ArcServerInternal> {
+        let server = self.prepare_reload(config)?;
+        Ok(Arc::new(server))
+    }
+
+    fn _start_runtime(&self, server: ArcServer) -> anyhow::Result<()> {
+        let mut runtime = ReceiveUdpRuntime::new(WrapArcServer(server), self.config.listen.clone());
+        runtime.set_listen_stats(self.listen_stats.clone());
+        runtime
+            .run_all_instances(self.config.listen_in_worker, &self.reload_sender)
+            .map(|_| self.server_stats.set_online())
+    }
+
+    fn _abort_runtime(&self) {
+        let _ = self.reload_sender.send(ServerReloadCommand::QuitRuntime);
+        self.server_stats.set_offline();
+    }
+}
+
+impl BaseServer for UdpTProxyServer {
+    #[inline]
+    fn name(&self) -> &NodeName {
+        self.config.name()
+    }
+
+    #[inline]
+    fn r#type(&self) -> &'static str {
+        self.config.r#type()
+    }
+
+    #[inline]
+    fn version(&self) -> usize {
+        self.reload_version
+    }
+}
+
+#[async_trait]
+impl AcceptTcpServer for UdpTProxyServer {
+    async fn run_tcp_task(&self, _stream: TcpStream, _cc_info: ClientConnectionInfo) {}
+}
+
+#[async_trait]
+impl AcceptQuicServer for UdpTProxyServer {
+    #[cfg(feature = "quic")]
+    async fn run_quic_task(&self, _connection: Connection, _cc_info: ClientConnectionInfo) {}
+}
+
+impl ReceiveUdpServer for UdpTProxyServer {
+    fn receive_udp_packet(
+        &self,
+        packet: &[u8],
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+        worker_id: Option<usize>,
+    ) {
+        // ipv4 peers are reported as ipv4-mapped address on dual-stack sockets
+        let client_addr = SocketAddr::new(client_addr.ip().to_canonical(), client_addr.port());
+        let target_addr = SocketAddr::new(server_addr.ip().to_canonical(), server_addr.port());
+        if self.drop_early(client_addr) {
+            return;
+        }
+
+        match self.sessions.dispatch(
+            client_addr,
+            target_addr,
+            packet,
+            self.config.session_queue_size,
+        ) {
+            UdpTProxySessionDispatch::Queued => {}
+            UdpTProxySessionDispatch::Dropped => self.listen_stats.add_dropped(),
+            UdpTProxySessionDispatch::Created(queue) => {
+                self.server_stats.add_conn();
+                self.listen_stats.add_accepted();
+
+                let mut cc_info = ClientConnectionInfo::new(client_addr, target_addr);
+                cc_info.set_worker_id(worker_id);
+                let ctx = CommonTaskContext {
+                    server_config: self.config.clone(),
+                    server_stats: self.server_stats.clone(),
+                    server_quit_policy: self.quit_policy.clone(),
+                    idle_wheel: self.idle_wheel.clone(),
+                    escaper: self.escaper.load().as_ref().clone(),
+                    sessions: self.sessions.clone(),
+                    cc_info,
+                    task_logger: self.task_logger.clone(),
+                    task_log_sampler: self.task_log_sampler.clone(),
+                };
+                UdpTProxyTask::new(ctx).into_running(queue);
+            }
+        }
+    }
+
+    fn drain_udp_packet(
+        &self,
+        packet: &[u8],
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+        _worker_id: Option<usize>,
+    ) -> bool {
+        let client_addr = SocketAddr::new(client_addr.ip().to_canonical(), client_addr.port());
+        let target_addr = SocketAddr::new(server_addr.ip().to_canonical(), server_addr.port());
+        if self.drop_early(client_addr) {
+            return false;
+        }
+
+        // the running sessions will finish the queued packets, but no new session will be created
+        self.sessions
+            .dispatch_existing(client_addr, target_addr, packet)
+    }
+}
+
+#[async_trait]
+impl Server for UdpTProxyServer {
+    fn escaper(&self) -> &NodeName {
+        self.config.escaper()
+    }
+
+    fn user_group(&self) -> &NodeName {
+        Default::default()
+    }
+
+    fn auditor(&self) -> &NodeName {
+        Default::default()
+    }
+
+    fn get_server_stats(&self) -> Option<ArcServerStats> {
+        Some(self.server_stats.clone())
+    }
+
+    fn get_listen_stats(&self) -> Arc<ListenStats> {
+        Arc::clone(&self.listen_stats)
+    }
+
+    fn alive_count(&self) -> i32 {
+        self.server_stats.get_alive_count()
+    }
+
+    #[inline]
+    fn quit_policy(&self) -> &Arc<ServerQuitPolicy> {
+        &self.quit_policy
+    }
+
+    fn task_log_sampler(&self) -> Option<&TaskLogSampler> {
+        Some(&self.task_log_sampler)
+    }
+
+    async fn run_rustls_task(&self, _stream: TlsStream<TcpStream>, _cc_info: ClientConnectionInfo) {
+    }
+
+    async fn run_openssl_task(
+        &self,
+        _stream: SslStream<TcpStream>,
+        _cc_info: ClientConnectionInfo,
+    ) {
+    }
+}
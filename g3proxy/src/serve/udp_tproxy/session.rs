@@ -0,0 +1,181 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+use rustc_hash::FxHashMap;
+use tokio::sync::mpsc;
+
+/// client address and original destination address
+type UdpTProxySessionKey = (SocketAddr, SocketAddr);
+
+pub(super) enum UdpTProxySessionDispatch {
+    Queued,
+    Dropped,
+    Created(mpsc::Receiver<Vec<u8>>),
+}
+
+/// Sessions which will be shared by all versions of the same server
+#[derive(Default)]
+pub(super) struct UdpTProxySessionMap {
+    inner: Mutex<FxHashMap<UdpTProxySessionKey, mpsc::Sender<Vec<u8>>>>,
+}
+
+impl UdpTProxySessionMap {
+    pub(super) fn dispatch(
+        &self,
+        client_addr: SocketAddr,
+        target_addr: SocketAddr,
+        packet: &[u8],
+        queue_size: usize,
+    ) -> UdpTProxySessionDispatch {
+        let key = (client_addr, target_addr);
+        let mut map = self.inner.lock().unwrap();
+        if let Some(sender) = map.get(&key) {
+            match sender.try_send(packet.to_vec()) {
+                Ok(_) => return UdpTProxySessionDispatch::Queued,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    return UdpTProxySessionDispatch::Dropped;
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    // the old session is quiting, start a new one
+                }
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel(queue_size);
+        let _ = sender.try_send(packet.to_vec());
+        map.insert(key, sender);
+        UdpTProxySessionDispatch::Created(receiver)
+    }
+
+    /// Queue the packet to the existing session only, return false if there is no alive session
+    /// or the queue is full
+    pub(super) fn dispatch_existing(
+        &self,
+        client_addr: SocketAddr,
+        target_addr: SocketAddr,
+        packet: &[u8],
+    ) -> bool {
+        let key = (client_addr, target_addr);
+        let map = self.inner.lock().unwrap();
+        map.get(&key)
+            .map(|sender| sender.try_send(packet.to_vec()).is_ok())
+            .unwrap_or(false)
+    }
+
+    /// the receiver should be closed before calling this
+    pub(super) fn remove_closed(&self, client_addr: SocketAddr, target_addr: SocketAddr) {
+        let key = (client_addr, target_addr);
+        let mut map = self.inner.lock().unwrap();
+        if map.get(&key).map(|s| s.is_closed()).unwrap_or(false) {
+            map.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn dispatch_new_and_queued() {
+        let map = UdpTProxySessionMap::default();
+        let client = addr("192.0.2.1:10000");
+        let target = addr("198.51.100.1:53");
+
+        let UdpTProxySessionDispatch::Created(mut receiver) = map.dispatch(client, target, b"1", 4)
+        else {
+            panic!("no new session created");
+        };
+        assert!(matches!(
+            map.dispatch(client, target, b"2", 4),
+            UdpTProxySessionDispatch::Queued
+        ));
+        assert_eq!(receiver.try_recv().unwrap(), b"1");
+        assert_eq!(receiver.try_recv().unwrap(), b"2");
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn dispatch_by_orig_dst() {
+        let map = UdpTProxySessionMap::default();
+        let client = addr("192.0.2.1:10000");
+        let target4 = addr("198.51.100.1:53");
+        let target6 = addr("[2001:db8::1]:53");
+
+        let UdpTProxySessionDispatch::Created(mut receiver4) =
+            map.dispatch(client, target4, b"v4", 4)
+        else {
+            panic!("no new session created");
+        };
+        // the same client with a different original destination should be a new session
+        let UdpTProxySessionDispatch::Created(mut receiver6) =
+            map.dispatch(client, target6, b"v6", 4)
+        else {
+            panic!("no new session created");
+        };
+        assert!(map.dispatch_existing(client, target4, b"v4 again"));
+        assert!(!map.dispatch_existing(client, addr("198.51.100.2:53"), b"none"));
+
+        assert_eq!(receiver4.try_recv().unwrap(), b"v4");
+        assert_eq!(receiver4.try_recv().unwrap(), b"v4 again");
+        assert_eq!(receiver6.try_recv().unwrap(), b"v6");
+        assert!(receiver6.try_recv().is_err());
+    }
+
+    #[test]
+    fn dispatch_queue_full() {
+        let map = UdpTProxySessionMap::default();
+        let client = addr("192.0.2.1:10000");
+        let target = addr("198.51.100.1:53");
+
+        let UdpTProxySessionDispatch::Created(mut receiver) = map.dispatch(client, target, b"1", 1)
+        else {
+            panic!("no new session created");
+        };
+        assert!(matches!(
+            map.dispatch(client, target, b"2", 1),
+            UdpTProxySessionDispatch::Dropped
+        ));
+        assert!(!map.dispatch_existing(client, target, b"3"));
+        assert_eq!(receiver.try_recv().unwrap(), b"1");
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn dispatch_after_close() {
+        let map = UdpTProxySessionMap::default();
+        let client = addr("192.0.2.1:10000");
+        let target = addr("198.51.100.1:53");
+
+        let UdpTProxySessionDispatch::Created(receiver) = map.dispatch(client, target, b"1", 4)
+        else {
+            panic!("no new session created");
+        };
+        drop(receiver);
+        assert!(!map.dispatch_existing(client, target, b"2"));
+
+        // a new session should replace the quiting one
+        let UdpTProxySessionDispatch::Created(mut receiver) = map.dispatch(client, target, b"3", 4)
+        else {
+            panic!("no new session created");
+        };
+        assert_eq!(receiver.try_recv().unwrap(), b"3");
+
+        // the new session should not be removed by the old one
+        map.remove_closed(client, target);
+        assert!(map.dispatch_existing(client, target, b"4"));
+
+        receiver.close();
+        map.remove_closed(client, target);
+        assert!(map.inner.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, AtomicIsize, AtomicU64, Ordering};
+
+use arc_swap::ArcSwapOption;
+
+use g3_daemon::stat::task::UdpConnectConnectionStats;
+use g3_io_ext::{LimitedRecvStats, LimitedSendStats};
+use g3_types::metrics::{MetricTagMap, NodeName};
+use g3_types::stats::{StatId, UdpIoSnapshot, UdpIoStats};
+
+use crate::module::udp_connect::UdpConnectTaskRemoteStats;
+use crate::serve::{ServerForbiddenSnapshot, ServerForbiddenStats, ServerStats};
+
+pub(crate) struct UdpTProxyServerStats {
+    name: NodeName,
+    id: StatId,
+
+    extra_metrics_tags: Arc<ArcSwapOption<MetricTagMap>>,
+
+    online: AtomicIsize,
+    conn_total: AtomicU64,
+
+    task_total: AtomicU64,
+    task_alive_count: AtomicI32,
+
+    udp: UdpIoStats,
+    pub(crate) forbidden: ServerForbiddenStats,
+}
+
+impl UdpTProxyServerStats {
+    pub(crate) fn new(name: &NodeName) -> Self {
+        UdpTProxyServerStats {
+            name: name.clone(),
+            id: StatId::new_unique(),
+            extra_metrics_tags: Arc::new(ArcSwapOption::new(None)),
+            online: AtomicIsize::new(0),
+            conn_total: AtomicU64::new(0),
+            task_total: AtomicU64::new(0),
+            task_alive_count: AtomicI32::new(0),
+            udp: Default::default(),
+            forbidden: Default::default(),
+        }
+    }
+
+    pub(crate) fn set_online(&self) {
+        self.online.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_offline(&self) {
+        self.online.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_extra_tags(&self, tags: Option<Arc<MetricTagMap>>) {
+        self.extra_metrics_tags.store(tags);
+    }
+
+    /// count for new sessions
+    pub(crate) fn add_conn(&self) {
+        self.conn_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub(crate) fn add_task(self: &Arc<Self>) -> UdpTProxyServerAliveTaskGuard {
+        self.task_total.fetch_add(1, Ordering::Relaxed);
+        self.task_alive_count.fetch_add(1, Ordering::Relaxed);
+        UdpTProxyServerAliveTaskGuard(self.clone())
+    }
+}
+
+pub(crate) struct UdpTProxyServerAliveTaskGuard(Arc<UdpTProxyServerStats>);
+
+impl Drop for UdpTProxyServerAliveTaskGuard {
+    fn drop(&mut self) {
+        self.0.task_alive_count.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl ServerStats for UdpTProxyServerStats {
+    #[inline]
+    fn name(&self) -> &NodeName {
+        &self.name
+    }
+
+    #[inline]
+    fn stat_id(&self) -> StatId {
+        self.id
+    }
+
+    #[inline]
+    fn load_extra_tags(&self) -> Option<Arc<MetricTagMap>> {
+        self.extra_metrics_tags.load_full()
+    }
+
+    #[inline]
+    fn share_extra_tags(&self) -> &Arc<ArcSwapOption<MetricTagMap>> {
+        &self.extra_metrics_tags
+    }
+
+    fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed) > 0
+    }
+
+    fn get_conn_total(&self) -> u64 {
+        self.conn_total.load(Ordering::Relaxed)
+    }
+
+    fn get_task_total(&self) -> u64 {
+        self.task_total.load(Ordering::Relaxed)
+    }
+
+    fn get_alive_count(&self) -> i32 {
+        self.task_alive_count.load(Ordering::Relaxed)
+    }
+
+    fn udp_io_snapshot(&self) -> Option<UdpIoSnapshot> {
+        Some(self.udp.snapshot())
+    }
+
+    #[inline]
+    fn forbidden_stats(&self) -> ServerForbiddenSnapshot {
+        self.forbidden.snapshot()
+    }
+}
+
+#[derive(Default)]
+pub(super) struct UdpTProxyTaskStats {
+    pub(super) clt: UdpConnectConnectionStats,
+    pub(super) ups: UdpConnectConnectionStats,
+}
+
+impl UdpConnectTaskRemoteStats for UdpTProxyTaskStats {
+    fn add_recv_bytes(&self, size: u64) {
+        self.ups.recv.add_bytes(size);
+    }
+
+    fn add_recv_packets(&self, n: usize) {
+        self.ups.recv.add_packets(n);
+    }
+
+    fn add_send_bytes(&self, size: u64) {
+        self.ups.send.add_bytes(size);
+    }
+
+    fn add_send_packets(&self, n: usize) {
+        self.ups.send.add_packets(n);
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct UdpTProxyTaskCltWrapperStats {
+    server: Arc<UdpTProxyServerStats>,
+    task: Arc<UdpTProxyTaskStats>,
+}
+
+impl UdpTProxyTaskCltWrapperStats {
+    pub(super) fn new(server: &Arc<UdpTProxyServerStats>, task: &Arc<UdpTProxyTaskStats>) -> Self {
+        UdpTProxyTaskCltWrapperStats {
+            server: Arc::clone(server),
+            task: Arc::clone(task),
+        }
+    }
+}
+
+impl LimitedRecvStats for UdpTProxyTaskCltWrapperStats {
+    fn add_recv_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.server.udp.add_in_bytes(size);
+        self.task.clt.recv.add_bytes(size);
+    }
+
+    fn add_recv_packets(&self, n: usize) {
+        self.server.udp.add_in_packets(n);
+        self.task.clt.recv.add_packets(n);
+    }
+}
+
+impl LimitedSendStats for UdpTProxyTaskCltWrapperStats {
+    fn add_send_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.server.udp.add_out_bytes(size);
+        self.task.clt.send.add_bytes(size);
+    }
+
+    fn add_send_packets(&self, n: usize) {
+        self.server.udp.add_out_packets(n);
+        self.task.clt.send.add_packets(n);
+    }
+}
@@ -0,0 +1,266 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use slog::Logger;
+use tokio::sync::mpsc;
+
+use g3_io_ext::{
+    LimitedUdpRecv, LimitedUdpSend, UdpCopyClientToRemote, UdpCopyError, UdpCopyRemoteRecv,
+    UdpCopyRemoteSend, UdpCopyRemoteToClient, UdpRecvHalf, UdpSendHalf,
+};
+use g3_types::net::UpstreamAddr;
+
+use super::common::CommonTaskContext;
+use super::recv::UdpTProxyClientRecv;
+use super::send::UdpTProxyClientSend;
+use super::stats::{
+    UdpTProxyServerAliveTaskGuard, UdpTProxyTaskCltWrapperStats, UdpTProxyTaskStats,
+};
+use crate::log::escape::udp_sendto::EscapeLogForUdpConnectSendTo;
+use crate::log::task::udp_tproxy::TaskLogForUdpTProxy;
+use crate::module::udp_connect::{UdpConnectTaskConf, UdpConnectTaskNotes};
+use crate::serve::{ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage};
+
+type UdpTProxyTaskClientRecv = UdpTProxyClientRecv<LimitedUdpRecv<UdpRecvHalf>>;
+type UdpTProxyTaskClientSend = UdpTProxyClientSend<LimitedUdpSend<UdpSendHalf>>;
+
+pub(super) struct UdpTProxyTask {
+    ctx: CommonTaskContext,
+    upstream: UpstreamAddr,
+    udp_notes: UdpConnectTaskNotes,
+    task_notes: ServerTaskNotes,
+    task_stats: Arc<UdpTProxyTaskStats>,
+    _alive_guard: Option<UdpTProxyServerAliveTaskGuard>,
+}
+
+impl UdpTProxyTask {
+    pub(super) fn new(ctx: CommonTaskContext) -> Self {
+        let target = ctx.target_addr();
+        let mut task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), None, Duration::ZERO);
+        task_notes.set_log_sample(ctx.task_log_sampler.sample());
+        UdpTProxyTask {
+            ctx,
+            upstream: UpstreamAddr::from(target),
+            udp_notes: UdpConnectTaskNotes::default(),
+            task_notes,
+            task_stats: Arc::new(UdpTProxyTaskStats::default()),
+            _alive_guard: None,
+        }
+    }
+
+    fn get_log_context(&self) -> Option<TaskLogForUdpTProxy<'_>> {
+        self.ctx
+            .task_logger
+            .as_ref()
+            .map(|logger| TaskLogForUdpTProxy {
+                logger,
+                task_notes: &self.task_notes,
+                upstream: &self.upstream,
+                udp_notes: &self.udp_notes,
+                client_rd_bytes: self.task_stats.clt.recv.get_bytes(),
+                client_rd_packets: self.task_stats.clt.recv.get_packets(),
+                client_wr_bytes: self.task_stats.clt.send.get_bytes(),
+                client_wr_packets: self.task_stats.clt.send.get_packets(),
+                remote_rd_bytes: self.task_stats.ups.recv.get_bytes(),
+                remote_rd_packets: self.task_stats.ups.recv.get_packets(),
+                remote_wr_bytes: self.task_stats.ups.send.get_bytes(),
+                remote_wr_packets: self.task_stats.ups.send.get_packets(),
+            })
+    }
+
+    pub(super) fn into_running(mut self, queue: mpsc::Receiver<Vec<u8>>) {
+        tokio::spawn(async move {
+            self.pre_start();
+            let e = match self.run(queue).await {
+                Ok(_) => ServerTaskError::Finished,
+                Err(e) => e,
+            };
+            if let Some(log_ctx) = self.get_log_context() {
+                log_ctx.log(e);
+            }
+        });
+    }
+
+    fn pre_start(&mut self) {
+        self._alive_guard = Some(self.ctx.server_stats.add_task());
+
+        if self.ctx.server_config.flush_task_log_on_created {
+            if let Some(log_ctx) = self.get_log_context() {
+                log_ctx.log_created();
+            }
+        }
+    }
+
+    async fn run(&mut self, queue: mpsc::Receiver<Vec<u8>>) -> ServerTaskResult<()> {
+        let r = match self.split_clt(queue) {
+            Ok((clt_r, clt_w)) => self.run_connect(clt_r, clt_w).await,
+            Err(e) => Err(e),
+        };
+
+        // the queue has been closed, so new packets will be dispatched to a new session
+        self.ctx
+            .sessions
+            .remove_closed(self.ctx.client_addr(), self.ctx.target_addr());
+        r
+    }
+
+    async fn run_connect(
+        &mut self,
+        mut clt_r: UdpTProxyTaskClientRecv,
+        mut clt_w: UdpTProxyTaskClientSend,
+    ) -> ServerTaskResult<()> {
+        self.task_notes.stage = ServerTaskStage::Connecting;
+        let task_conf = UdpConnectTaskConf {
+            upstream: &self.upstream,
+            sock_buf: self.ctx.server_config.udp_socket_buffer,
+        };
+        let (mut ups_r, mut ups_w, escape_logger) = self
+            .ctx
+            .escaper
+            .udp_setup_connection(
+                &task_conf,
+                &mut self.udp_notes,
+                &self.task_notes,
+                self.task_stats.clone(),
+            )
+            .await?;
+        self.task_notes.stage = ServerTaskStage::Connected;
+
+        if self.ctx.server_config.flush_task_log_on_connected {
+            if let Some(log_ctx) = self.get_log_context() {
+                log_ctx.log_connected();
+            }
+        }
+
+        self.task_notes.mark_relaying();
+        self.run_relay(
+            &mut clt_r,
+            &mut clt_w,
+            &mut *ups_r,
+            &mut *ups_w,
+            escape_logger,
+        )
+        .await
+    }
+
+    async fn run_relay(
+        &mut self,
+        clt_r: &mut UdpTProxyTaskClientRecv,
+        clt_w: &mut UdpTProxyTaskClientSend,
+        ups_r: &mut (dyn UdpCopyRemoteRecv + Unpin + Send),
+        ups_w: &mut (dyn UdpCopyRemoteSend + Unpin + Send),
+        escape_logger: Option<Logger>,
+    ) -> ServerTaskResult<()> {
+        let task_id = &self.task_notes.id;
+
+        let mut c_to_r = UdpCopyClientToRemote::new(clt_r, ups_w, self.ctx.server_config.udp_relay);
+        let mut r_to_c = UdpCopyRemoteToClient::new(clt_w, ups_r, self.ctx.server_config.udp_relay);
+
+        let mut idle_interval = self.ctx.idle_wheel.register();
+        let mut log_interval = self.ctx.get_log_interval();
+        let mut idle_count = 0;
+        let max_idle_count = self.ctx.server_config.task_idle_max_count;
+        loop {
+            tokio::select! {
+                biased;
+
+                r = &mut c_to_r => {
+                    return match r {
+                        Ok(_) => Ok(()),
+                        Err(UdpCopyError::RemoteError(e)) => {
+                            if let Some(logger) = escape_logger {
+                                EscapeLogForUdpConnectSendTo {
+                                    task_id,
+                                    upstream: Some(&self.upstream),
+                                    udp_notes: &self.udp_notes,
+                                }
+                                .log(&logger, &e);
+                            }
+                            Err(e.into())
+                        }
+                        Err(UdpCopyError::ClientError(e)) => Err(e.into()),
+                    };
+                }
+                r = &mut r_to_c => {
+                    return match r {
+                        Ok(_) => Ok(()),
+                        Err(UdpCopyError::RemoteError(e)) => {
+                            if let Some(logger) = escape_logger {
+                                EscapeLogForUdpConnectSendTo {
+                                    task_id,
+                                    upstream: Some(&self.upstream),
+                                    udp_notes: &self.udp_notes,
+                                }
+                                .log(&logger, &e);
+                            }
+                            Err(e.into())
+                        }
+                        Err(UdpCopyError::ClientError(e)) => Err(e.into()),
+                    };
+                }
+                _ = log_interval.tick() => {
+                    if let Some(log_ctx) = self.get_log_context() {
+                        log_ctx.log_periodic();
+                    }
+                }
+                n = idle_interval.tick() => {
+                    if c_to_r.is_idle() && r_to_c.is_idle() {
+                        idle_count += n;
+
+                        if idle_count >= max_idle_count {
+                            return Err(ServerTaskError::Idle(idle_interval.period(), idle_count));
+                        }
+                    } else {
+                        idle_count = 0;
+
+                        c_to_r.reset_active();
+                        r_to_c.reset_active();
+                    }
+
+                    if self.ctx.server_quit_policy.force_quit() {
+                        return Err(ServerTaskError::CanceledAsServerQuit)
+                    }
+                }
+            }
+        }
+    }
+
+    fn split_clt(
+        &self,
+        queue: mpsc::Receiver<Vec<u8>>,
+    ) -> ServerTaskResult<(UdpTProxyTaskClientRecv, UdpTProxyTaskClientSend)> {
+        let clt_socket = self.ctx.setup_reply_socket()?;
+        let (clt_r, clt_w) = g3_io_ext::split_udp(clt_socket);
+
+        let wrapper_stats = Arc::new(UdpTProxyTaskCltWrapperStats::new(
+            &self.ctx.server_stats,
+            &self.task_stats,
+        ));
+        let limit_config = &self.ctx.server_config.udp_sock_speed_limit;
+
+        let clt_r = LimitedUdpRecv::local_limited(
+            clt_r,
+            limit_config.shift_millis,
+            limit_config.max_north_packets,
+            limit_config.max_north_bytes,
+            wrapper_stats.clone(),
+        );
+        let clt_w = LimitedUdpSend::local_limited(
+            clt_w,
+            limit_config.shift_millis,
+            limit_config.max_south_packets,
+            limit_config.max_south_bytes,
+            wrapper_stats.clone(),
+        );
+
+        Ok((
+            UdpTProxyClientRecv::new(queue, clt_r, wrapper_stats),
+            UdpTProxyClientSend::new(clt_w),
+        ))
+    }
+}
@@ -38,6 +38,16 @@ async fn do_reload() {
     info!("reload finished");
 }
 
+/// Rollback the servers to a previous config generation
+pub(crate) async fn rollback(generation: Option<u64>) -> anyhow::Result<u64> {
+    let _guard = RELOAD_MUTEX.lock().await;
+    info!("rolling back server config");
+
+    let id = crate::serve::rollback(generation).await?;
+    info!("rollback finished, current server config generation is {id}");
+    Ok(id)
+}
+
 #[derive(Clone, Copy)]
 struct QuitAction {}
 
@@ -17,6 +17,7 @@ use crate::escape::{
     ArcEscaperStats, EscaperForbiddenSnapshot, EscaperTcpConnectSnapshot, EscaperTlsSnapshot,
     RouteEscaperSnapshot, RouteEscaperStats,
 };
+use crate::module::udp_relay::{UdpRelayIcmpErrorSnapshot, UdpRelayMappingSnapshot};
 
 const METRIC_NAME_ESCAPER_TASK_TOTAL: &str = "escaper.task.total";
 const METRIC_NAME_ESCAPER_CONN_ATTEMPT: &str = "escaper.connection.attempt";
@@ -36,6 +37,19 @@ const METRIC_NAME_ESCAPER_IO_IN_PACKETS: &str = "escaper.traffic.in.packets";
 const METRIC_NAME_ESCAPER_IO_OUT_BYTES: &str = "escaper.traffic.out.bytes";
 const METRIC_NAME_ESCAPER_IO_OUT_PACKETS: &str = "escaper.traffic.out.packets";
 const METRIC_NAME_ESCAPER_FORBIDDEN_IP_BLOCKED: &str = "escaper.forbidden.ip_blocked";
+const METRIC_NAME_ESCAPER_UDP_MAPPING_ALIVE: &str = "escaper.udp.mapping.alive";
+const METRIC_NAME_ESCAPER_UDP_MAPPING_EVICT_IDLE: &str = "escaper.udp.mapping.evict.idle";
+const METRIC_NAME_ESCAPER_UDP_MAPPING_EVICT_OVERFLOW: &str = "escaper.udp.mapping.evict.overflow";
+const METRIC_NAME_ESCAPER_UDP_MAPPING_DROP_OVERFLOW: &str = "escaper.udp.mapping.drop.overflow";
+const METRIC_NAME_ESCAPER_UDP_MAPPING_DROP_UNMATCHED: &str = "escaper.udp.mapping.drop.unmatched";
+const METRIC_NAME_ESCAPER_UDP_ICMP_PORT_UNREACHABLE: &str = "escaper.udp.icmp.port_unreachable";
+const METRIC_NAME_ESCAPER_UDP_ICMP_HOST_UNREACHABLE: &str = "escaper.udp.icmp.host_unreachable";
+const METRIC_NAME_ESCAPER_UDP_ICMP_PACKET_TOO_BIG: &str = "escaper.udp.icmp.packet_too_big";
+const METRIC_NAME_ESCAPER_UDP_ICMP_TIME_EXCEEDED: &str = "escaper.udp.icmp.time_exceeded";
+const METRIC_NAME_ESCAPER_UDP_ICMP_OTHER: &str = "escaper.udp.icmp.other";
+const METRIC_NAME_ESCAPER_EGRESS_BIND_SELECTED: &str = "escaper.egress_bind.selected";
+
+const TAG_KEY_RULE_INDEX: &str = "rule_index";
 
 const METRIC_NAME_ROUTE_REQUEST_PASSED: &str = "route.request.passed";
 const METRIC_NAME_ROUTE_REQUEST_FAILED: &str = "route.request.failed";
@@ -70,6 +84,9 @@ struct EscaperSnapshot {
     tcp: TcpIoSnapshot,
     udp: UdpIoSnapshot,
     forbidden: EscaperForbiddenSnapshot,
+    udp_mapping: UdpRelayMappingSnapshot,
+    udp_icmp_error: UdpRelayIcmpErrorSnapshot,
+    egress_bind: Vec<u64>,
 }
 
 pub(in crate::stat) fn sync_stats() {
@@ -161,6 +178,33 @@ fn emit_escaper_stats(
     if let Some(udp_io_stats) = stats.udp_io_snapshot() {
         emit_udp_io_to_statsd(client, udp_io_stats, &mut snap.udp, &common_tags);
     }
+
+    if let Some(udp_mapping_stats) = stats.udp_mapping_snapshot() {
+        emit_udp_mapping_stats(
+            client,
+            udp_mapping_stats,
+            &mut snap.udp_mapping,
+            &common_tags,
+        );
+    }
+
+    if let Some(udp_icmp_error_stats) = stats.udp_icmp_error_snapshot() {
+        emit_udp_icmp_error_stats(
+            client,
+            udp_icmp_error_stats,
+            &mut snap.udp_icmp_error,
+            &common_tags,
+        );
+    }
+
+    if let Some(egress_bind_stats) = stats.egress_bind_snapshot() {
+        emit_egress_bind_stats(
+            client,
+            egress_bind_stats,
+            &mut snap.egress_bind,
+            &common_tags,
+        );
+    }
 }
 
 fn emit_tcp_connect_stats(
@@ -295,6 +339,119 @@ fn emit_udp_io_to_statsd(
     emit_field!(in_bytes, METRIC_NAME_ESCAPER_IO_IN_BYTES);
 }
 
+fn emit_udp_mapping_stats(
+    client: &mut StatsdClient,
+    stats: UdpRelayMappingSnapshot,
+    snap: &mut UdpRelayMappingSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    if stats.alive == 0
+        && stats.evicted_idle == 0
+        && stats.evicted_overflow == 0
+        && stats.dropped_overflow == 0
+        && stats.dropped_unmatched == 0
+    {
+        return;
+    }
+
+    client
+        .gauge_with_tags(
+            METRIC_NAME_ESCAPER_UDP_MAPPING_ALIVE,
+            stats.alive,
+            common_tags,
+        )
+        .send();
+
+    macro_rules! emit_field {
+        ($field:ident, $name:expr) => {
+            let new_value = stats.$field;
+            let diff_value = new_value.wrapping_sub(snap.$field);
+            client
+                .count_with_tags($name, diff_value, common_tags)
+                .send();
+            snap.$field = new_value;
+        };
+    }
+
+    emit_field!(evicted_idle, METRIC_NAME_ESCAPER_UDP_MAPPING_EVICT_IDLE);
+    emit_field!(
+        evicted_overflow,
+        METRIC_NAME_ESCAPER_UDP_MAPPING_EVICT_OVERFLOW
+    );
+    emit_field!(
+        dropped_overflow,
+        METRIC_NAME_ESCAPER_UDP_MAPPING_DROP_OVERFLOW
+    );
+    emit_field!(
+        dropped_unmatched,
+        METRIC_NAME_ESCAPER_UDP_MAPPING_DROP_UNMATCHED
+    );
+}
+
+fn emit_udp_icmp_error_stats(
+    client: &mut StatsdClient,
+    stats: UdpRelayIcmpErrorSnapshot,
+    snap: &mut UdpRelayIcmpErrorSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    macro_rules! emit_field {
+        ($field:ident, $name:expr) => {
+            let new_value = stats.$field;
+            if new_value != 0 || snap.$field != 0 {
+                let diff_value = new_value.wrapping_sub(snap.$field);
+                client
+                    .count_with_tags($name, diff_value, common_tags)
+                    .send();
+                snap.$field = new_value;
+            }
+        };
+    }
+
+    emit_field!(
+        port_unreachable,
+        METRIC_NAME_ESCAPER_UDP_ICMP_PORT_UNREACHABLE
+    );
+    emit_field!(
+        host_unreachable,
+        METRIC_NAME_ESCAPER_UDP_ICMP_HOST_UNREACHABLE
+    );
+    emit_field!(packet_too_big, METRIC_NAME_ESCAPER_UDP_ICMP_PACKET_TOO_BIG);
+    emit_field!(time_exceeded, METRIC_NAME_ESCAPER_UDP_ICMP_TIME_EXCEEDED);
+    emit_field!(other, METRIC_NAME_ESCAPER_UDP_ICMP_OTHER);
+}
+
+fn emit_egress_bind_stats(
+    client: &mut StatsdClient,
+    stats: Vec<u64>,
+    snap: &mut Vec<u64>,
+    common_tags: &StatsdTagGroup,
+) {
+    // the rules may be changed after reload
+    snap.resize(stats.len(), 0);
+
+    let mut buffer = itoa::Buffer::new();
+    for (i, (new_value, old_value)) in stats.into_iter().zip(snap.iter_mut()).enumerate() {
+        if new_value == 0 && *old_value == 0 {
+            continue;
+        }
+        // the counter will be reset if the rule at this index is changed
+        let diff_value = if new_value < *old_value {
+            new_value
+        } else {
+            new_value - *old_value
+        };
+        client
+            .count_with_tags(
+                METRIC_NAME_ESCAPER_EGRESS_BIND_SELECTED,
+                diff_value,
+                common_tags,
+            )
+            .with_tag(TAG_KEY_RULE_INDEX, buffer.format(i))
+            .send();
+        *old_value = new_value;
+    }
+}
+
 fn emit_route_stats(
     client: &mut StatsdClient,
     stats: &Arc<RouteEscaperStats>,
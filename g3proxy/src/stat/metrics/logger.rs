@@ -0,0 +1,66 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use g3_statsd_client::{StatsdClient, StatsdTagGroup};
+
+use crate::log::task::TaskLogPtrCacheStats;
+
+const TAG_KEY_LOGGER: &str = "logger";
+
+const METRIC_NAME_PTR_CACHE_HIT: &str = "logger.ptr_cache.hit";
+const METRIC_NAME_PTR_CACHE_MISS: &str = "logger.ptr_cache.miss";
+const METRIC_NAME_PTR_CACHE_INFLIGHT: &str = "logger.ptr_cache.inflight";
+
+#[derive(Default)]
+struct PtrCacheSnapshot {
+    hit: u64,
+    miss: u64,
+}
+
+static PTR_CACHE_SNAPSHOT_MAP: Mutex<BTreeMap<String, PtrCacheSnapshot>> =
+    Mutex::new(BTreeMap::new());
+
+pub(in crate::stat) fn emit_stats(client: &mut StatsdClient) {
+    let mut snap_map = PTR_CACHE_SNAPSHOT_MAP.lock().unwrap();
+    crate::log::task::foreach_ptr_cache(|name, stats| {
+        let snap = snap_map.entry(name.to_string()).or_default();
+        emit_ptr_cache_stats(client, name, stats, snap);
+    });
+}
+
+fn emit_ptr_cache_stats(
+    client: &mut StatsdClient,
+    logger: &str,
+    stats: &TaskLogPtrCacheStats,
+    snap: &mut PtrCacheSnapshot,
+) {
+    let mut common_tags = StatsdTagGroup::default();
+    common_tags.add_tag(TAG_KEY_LOGGER, logger);
+
+    macro_rules! emit_field {
+        ($field:ident, $name:expr) => {
+            let new_value = stats.$field();
+            let diff_value = new_value.wrapping_sub(snap.$field);
+            client
+                .count_with_tags($name, diff_value, &common_tags)
+                .send();
+            snap.$field = new_value;
+        };
+    }
+
+    emit_field!(hit, METRIC_NAME_PTR_CACHE_HIT);
+    emit_field!(miss, METRIC_NAME_PTR_CACHE_MISS);
+
+    client
+        .gauge_with_tags(
+            METRIC_NAME_PTR_CACHE_INFLIGHT,
+            stats.inflight(),
+            &common_tags,
+        )
+        .send();
+}
@@ -4,6 +4,7 @@
  */
 
 pub(super) mod escaper;
+pub(super) mod logger;
 pub(super) mod resolver;
 pub(super) mod server;
 
@@ -5,14 +5,20 @@
 
 use std::sync::{Arc, Mutex};
 
+use rustc_hash::FxHashMap;
+
 use g3_daemon::listen::{ListenSnapshot, ListenStats};
 use g3_daemon::metrics::{
-    ServerMetricExt, TAG_KEY_TRANSPORT, TRANSPORT_TYPE_TCP, TRANSPORT_TYPE_UDP,
+    ServerMetricExt, TAG_KEY_QUANTILE, TAG_KEY_TRANSPORT, TRANSPORT_TYPE_TCP, TRANSPORT_TYPE_UDP,
 };
+use g3_daemon::server::{AcceptRejectSnapshot, TransferSnapshot};
 use g3_statsd_client::{StatsdClient, StatsdTagGroup};
 use g3_types::stats::{GlobalStatsMap, TcpIoSnapshot, UdpIoSnapshot};
 
-use crate::serve::{ArcServerStats, ServerForbiddenSnapshot};
+use crate::serve::{
+    ArcServerStats, ServerAdaptationBypassSnapshot, ServerConnectFallbackSnapshot,
+    ServerForbiddenSnapshot, ServerUdpOverheadSnapshot, ServerUdpOversizeSnapshot,
+};
 use crate::stat::types::UntrustedTaskStatsSnapshot;
 
 const METRIC_NAME_SERVER_CONN_TOTAL: &str = "server.connection.total";
@@ -25,9 +31,23 @@ const METRIC_NAME_SERVER_IO_IN_BYTES: &str = "server.traffic.in.bytes";
 const METRIC_NAME_SERVER_IO_IN_PACKETS: &str = "server.traffic.in.packets";
 const METRIC_NAME_SERVER_IO_OUT_BYTES: &str = "server.traffic.out.bytes";
 const METRIC_NAME_SERVER_IO_OUT_PACKETS: &str = "server.traffic.out.packets";
+const METRIC_NAME_SERVER_IO_IN_OVERHEAD_BYTES: &str = "server.traffic.in.overhead_bytes";
+const METRIC_NAME_SERVER_IO_OUT_OVERHEAD_BYTES: &str = "server.traffic.out.overhead_bytes";
 const METRIC_NAME_SERVER_UNTRUSTED_TASK_TOTAL: &str = "server.task.untrusted_total";
 const METRIC_NAME_SERVER_UNTRUSTED_TASK_ALIVE: &str = "server.task.untrusted_alive";
 const METRIC_NAME_SERVER_IO_UNTRUSTED_IN_BYTES: &str = "server.traffic.untrusted_in.bytes";
+const METRIC_NAME_SERVER_CONNECT_DURATION: &str = "server.task.connect.duration";
+const METRIC_NAME_SERVER_CONNECT_RETRIED: &str = "server.task.connect.retried";
+const METRIC_NAME_SERVER_CONNECT_FALLBACK: &str = "server.task.connect.fallback";
+const METRIC_NAME_SERVER_CONNECT_EXHAUSTED: &str = "server.task.connect.exhausted";
+const METRIC_NAME_SERVER_UDP_PORT_EXHAUSTED: &str = "server.udp_relay.port_exhausted";
+const METRIC_NAME_SERVER_UDP_TRUNCATED: &str = "server.udp_relay.truncated";
+const METRIC_NAME_SERVER_UDP_OVERSIZE_DROPPED: &str = "server.udp_relay.oversize_dropped";
+const METRIC_NAME_SERVER_ADAPTATION_BYPASS_TRUSTED: &str = "server.adaptation_bypass.trusted";
+const METRIC_NAME_SERVER_ADAPTATION_BYPASS_INVALID: &str = "server.adaptation_bypass.invalid";
+const METRIC_NAME_SERVER_ADAPTATION_BYPASS_DENIED: &str = "server.adaptation_bypass.denied";
+
+const TAG_KEY_PORT_RANGE_TIER: &str = "port_range_tier";
 
 type ServerStatsValue = (ArcServerStats, ServerSnapshot);
 type ListenStatsValue = (Arc<ListenStats>, ListenSnapshot);
@@ -44,7 +64,14 @@ struct ServerSnapshot {
     forbidden: ServerForbiddenSnapshot,
     tcp: TcpIoSnapshot,
     udp: UdpIoSnapshot,
+    udp_overhead: ServerUdpOverheadSnapshot,
     untrusted: UntrustedTaskStatsSnapshot,
+    accept_reject: AcceptRejectSnapshot,
+    connect_fallback: ServerConnectFallbackSnapshot,
+    transfer: TransferSnapshot,
+    udp_port_exhausted: FxHashMap<Arc<str>, u64>,
+    udp_oversize: ServerUdpOversizeSnapshot,
+    adaptation_bypass: ServerAdaptationBypassSnapshot,
 }
 
 pub(in crate::stat) fn sync_stats() {
@@ -126,9 +153,81 @@ fn emit_server_stats(client: &mut StatsdClient, stats: &ArcServerStats, snap: &m
         emit_udp_io_to_statsd(client, udp_io_stats, &mut snap.udp, &common_tags);
     }
 
+    if let Some(udp_overhead_stats) = stats.udp_overhead_snapshot() {
+        emit_udp_overhead_stats(
+            client,
+            udp_overhead_stats,
+            &mut snap.udp_overhead,
+            &common_tags,
+        );
+    }
+
     if let Some(untrusted_stats) = stats.untrusted_snapshot() {
         emit_untrusted_stats(client, untrusted_stats, &mut snap.untrusted, &common_tags);
     }
+
+    if let Some(accept_reject_stats) = stats.accept_reject_snapshot() {
+        g3_daemon::metrics::emit_accept_reject_stats(
+            client,
+            accept_reject_stats,
+            &mut snap.accept_reject,
+            &common_tags,
+        );
+    }
+
+    if let Some(connect_fallback_stats) = stats.connect_fallback_snapshot() {
+        emit_connect_fallback_stats(
+            client,
+            connect_fallback_stats,
+            &mut snap.connect_fallback,
+            &common_tags,
+        );
+    }
+
+    if let Some(connect_duration_stats) = stats.connect_duration_stats() {
+        connect_duration_stats.foreach_stat(|_, quantile, v| {
+            client
+                .gauge_float_with_tags(METRIC_NAME_SERVER_CONNECT_DURATION, v, &common_tags)
+                .with_tag(TAG_KEY_QUANTILE, quantile)
+                .send();
+        });
+    }
+
+    if let Some(transfer_stats) = stats.transfer_stats() {
+        g3_daemon::metrics::emit_transfer_stats(
+            client,
+            &transfer_stats,
+            &mut snap.transfer,
+            &common_tags,
+        );
+    }
+
+    if let Some(udp_port_exhausted_stats) = stats.udp_port_exhausted_snapshot() {
+        emit_udp_port_exhausted_stats(
+            client,
+            udp_port_exhausted_stats,
+            &mut snap.udp_port_exhausted,
+            &common_tags,
+        );
+    }
+
+    if let Some(udp_oversize_stats) = stats.udp_oversize_snapshot() {
+        emit_udp_oversize_stats(
+            client,
+            udp_oversize_stats,
+            &mut snap.udp_oversize,
+            &common_tags,
+        );
+    }
+
+    if let Some(adaptation_bypass_stats) = stats.adaptation_bypass_snapshot() {
+        emit_adaptation_bypass_stats(
+            client,
+            adaptation_bypass_stats,
+            &mut snap.adaptation_bypass,
+            &common_tags,
+        );
+    }
 }
 
 fn emit_forbidden_stats(
@@ -155,6 +254,98 @@ fn emit_forbidden_stats(
     emit_forbid_stats_u64!(user_blocked, METRIC_NAME_SERVER_FORBIDDEN_USER_BLOCKED);
 }
 
+fn emit_connect_fallback_stats(
+    client: &mut StatsdClient,
+    stats: ServerConnectFallbackSnapshot,
+    snap: &mut ServerConnectFallbackSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    macro_rules! emit_field {
+        ($id:ident, $name:expr) => {
+            let new_value = stats.$id;
+            if new_value != 0 || snap.$id != 0 {
+                let diff_value = new_value.wrapping_sub(snap.$id);
+                client
+                    .count_with_tags($name, diff_value, common_tags)
+                    .send();
+                snap.$id = new_value;
+            }
+        };
+    }
+
+    emit_field!(retried, METRIC_NAME_SERVER_CONNECT_RETRIED);
+    emit_field!(fallback, METRIC_NAME_SERVER_CONNECT_FALLBACK);
+    emit_field!(exhausted, METRIC_NAME_SERVER_CONNECT_EXHAUSTED);
+}
+
+fn emit_udp_port_exhausted_stats(
+    client: &mut StatsdClient,
+    stats: FxHashMap<Arc<str>, u64>,
+    snap: &mut FxHashMap<Arc<str>, u64>,
+    common_tags: &StatsdTagGroup,
+) {
+    for (tier, new_value) in stats {
+        let old_value = snap.get(&tier).copied().unwrap_or_default();
+        let diff_value = new_value.wrapping_sub(old_value);
+        client
+            .count_with_tags(
+                METRIC_NAME_SERVER_UDP_PORT_EXHAUSTED,
+                diff_value,
+                common_tags,
+            )
+            .with_tag(TAG_KEY_PORT_RANGE_TIER, &tier)
+            .send();
+        snap.insert(tier, new_value);
+    }
+}
+
+fn emit_udp_oversize_stats(
+    client: &mut StatsdClient,
+    stats: ServerUdpOversizeSnapshot,
+    snap: &mut ServerUdpOversizeSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    macro_rules! emit_field {
+        ($id:ident, $name:expr) => {
+            let new_value = stats.$id;
+            if new_value != 0 || snap.$id != 0 {
+                let diff_value = new_value.wrapping_sub(snap.$id);
+                client
+                    .count_with_tags($name, diff_value, common_tags)
+                    .send();
+                snap.$id = new_value;
+            }
+        };
+    }
+
+    emit_field!(truncated, METRIC_NAME_SERVER_UDP_TRUNCATED);
+    emit_field!(dropped, METRIC_NAME_SERVER_UDP_OVERSIZE_DROPPED);
+}
+
+fn emit_adaptation_bypass_stats(
+    client: &mut StatsdClient,
+    stats: ServerAdaptationBypassSnapshot,
+    snap: &mut ServerAdaptationBypassSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    macro_rules! emit_field {
+        ($id:ident, $name:expr) => {
+            let new_value = stats.$id;
+            if new_value != 0 || snap.$id != 0 {
+                let diff_value = new_value.wrapping_sub(snap.$id);
+                client
+                    .count_with_tags($name, diff_value, common_tags)
+                    .send();
+                snap.$id = new_value;
+            }
+        };
+    }
+
+    emit_field!(trusted, METRIC_NAME_SERVER_ADAPTATION_BYPASS_TRUSTED);
+    emit_field!(invalid, METRIC_NAME_SERVER_ADAPTATION_BYPASS_INVALID);
+    emit_field!(denied, METRIC_NAME_SERVER_ADAPTATION_BYPASS_DENIED);
+}
+
 fn emit_tcp_io_to_statsd(
     client: &mut StatsdClient,
     stats: TcpIoSnapshot,
@@ -209,6 +400,30 @@ fn emit_udp_io_to_statsd(
     emit_field!(out_bytes, METRIC_NAME_SERVER_IO_OUT_BYTES);
 }
 
+fn emit_udp_overhead_stats(
+    client: &mut StatsdClient,
+    stats: ServerUdpOverheadSnapshot,
+    snap: &mut ServerUdpOverheadSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    macro_rules! emit_field {
+        ($id:ident, $name:expr) => {
+            let new_value = stats.$id;
+            if new_value != 0 || snap.$id != 0 {
+                let diff_value = new_value.wrapping_sub(snap.$id);
+                client
+                    .count_with_tags($name, diff_value, common_tags)
+                    .with_tag(TAG_KEY_TRANSPORT, TRANSPORT_TYPE_UDP)
+                    .send();
+                snap.$id = new_value;
+            }
+        };
+    }
+
+    emit_field!(in_bytes, METRIC_NAME_SERVER_IO_IN_OVERHEAD_BYTES);
+    emit_field!(out_bytes, METRIC_NAME_SERVER_IO_OUT_OVERHEAD_BYTES);
+}
+
 fn emit_untrusted_stats(
     client: &mut StatsdClient,
     stats: UntrustedTaskStatsSnapshot,
@@ -49,7 +49,9 @@ fn spawn_main_thread(config: &StatsdClientConfig) -> anyhow::Result<JoinHandle<(
                 metrics::resolver::emit_stats(&mut client);
                 metrics::user::emit_stats(&mut client);
                 g3_daemon::runtime::metrics::emit_stats(&mut client);
+                g3_daemon::ban::metrics::emit_stats(&mut client);
                 g3_daemon::log::metrics::emit_stats(&mut client);
+                metrics::logger::emit_stats(&mut client);
 
                 client.flush_sink();
 
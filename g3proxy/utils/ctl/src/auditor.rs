@@ -0,0 +1,106 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::path::PathBuf;
+
+use clap::{Arg, ArgMatches, Command, value_parser};
+
+use g3_ctl::CommandResult;
+
+use g3proxy_proto::proc_capnp::proc_control;
+
+use crate::common::parse_operation_result;
+
+pub const COMMAND: &str = "auditor";
+
+const COMMAND_ARG_NAME: &str = "name";
+
+const SUBCOMMAND_ICAP_CAPTURE: &str = "icap-capture";
+const SUBCOMMAND_ICAP_CAPTURE_START: &str = "start";
+const SUBCOMMAND_ICAP_CAPTURE_STOP: &str = "stop";
+const SUBCOMMAND_ICAP_CAPTURE_ARG_DIR: &str = "dir";
+const SUBCOMMAND_ICAP_CAPTURE_ARG_RATIO: &str = "ratio";
+const SUBCOMMAND_ICAP_CAPTURE_ARG_MAX_FILE_SIZE: &str = "max-file-size";
+
+pub fn command() -> Command {
+    Command::new(COMMAND)
+        .arg(Arg::new(COMMAND_ARG_NAME).required(true).num_args(1))
+        .subcommand_required(true)
+        .subcommand(
+            Command::new(SUBCOMMAND_ICAP_CAPTURE)
+                .about("Capture sampled ICAP transactions, the changes will be reset by auditor reload")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new(SUBCOMMAND_ICAP_CAPTURE_START)
+                        .arg(
+                            Arg::new(SUBCOMMAND_ICAP_CAPTURE_ARG_DIR)
+                                .help("The absolute path of the directory to store the capture files")
+                                .required(true)
+                                .num_args(1)
+                                .value_parser(value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            Arg::new(SUBCOMMAND_ICAP_CAPTURE_ARG_RATIO)
+                                .help("Sample ratio, should be in [0, 1]")
+                                .long(SUBCOMMAND_ICAP_CAPTURE_ARG_RATIO)
+                                .num_args(1)
+                                .value_parser(value_parser!(f64))
+                                .default_value("1.0"),
+                        )
+                        .arg(
+                            Arg::new(SUBCOMMAND_ICAP_CAPTURE_ARG_MAX_FILE_SIZE)
+                                .help("Max size of each capture file, the server default is 16MiB")
+                                .long(SUBCOMMAND_ICAP_CAPTURE_ARG_MAX_FILE_SIZE)
+                                .num_args(1)
+                                .value_parser(value_parser!(u64)),
+                        ),
+                )
+                .subcommand(Command::new(SUBCOMMAND_ICAP_CAPTURE_STOP)),
+        )
+}
+
+async fn icap_capture(
+    client: &proc_control::Client,
+    name: &str,
+    args: &ArgMatches,
+) -> CommandResult<()> {
+    let (subcommand, args) = args.subcommand().unwrap();
+    match subcommand {
+        SUBCOMMAND_ICAP_CAPTURE_START => {
+            let dir = args
+                .get_one::<PathBuf>(SUBCOMMAND_ICAP_CAPTURE_ARG_DIR)
+                .unwrap();
+            let ratio = args
+                .get_one::<f64>(SUBCOMMAND_ICAP_CAPTURE_ARG_RATIO)
+                .unwrap();
+            let mut req = client.start_icap_debug_capture_request();
+            req.get().set_auditor(name);
+            req.get().set_dir(dir.display().to_string().as_str());
+            req.get().set_sample_ratio(*ratio);
+            if let Some(size) = args.get_one::<u64>(SUBCOMMAND_ICAP_CAPTURE_ARG_MAX_FILE_SIZE) {
+                req.get().set_max_file_size(*size);
+            }
+            let rsp = req.send().promise.await?;
+            parse_operation_result(rsp.get()?.get_result()?)
+        }
+        SUBCOMMAND_ICAP_CAPTURE_STOP => {
+            let mut req = client.stop_icap_debug_capture_request();
+            req.get().set_auditor(name);
+            let rsp = req.send().promise.await?;
+            parse_operation_result(rsp.get()?.get_result()?)
+        }
+        _ => unreachable!(),
+    }
+}
+
+pub async fn run(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let name = args.get_one::<String>(COMMAND_ARG_NAME).unwrap();
+
+    let (subcommand, args) = args.subcommand().unwrap();
+    match subcommand {
+        SUBCOMMAND_ICAP_CAPTURE => icap_capture(client, name, args).await,
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,139 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use clap::{Arg, ArgMatches, Command, ValueHint, value_parser};
+
+use g3_ctl::{CommandError, CommandResult};
+
+use g3proxy_proto::proc_capnp::proc_control;
+
+use crate::common::{parse_fetch_result, parse_operation_result};
+
+pub const COMMAND: &str = "config";
+
+const SUBCOMMAND_CHECK: &str = "check";
+const SUBCOMMAND_CHECK_ARG_PATH: &str = "path";
+const SUBCOMMAND_HISTORY: &str = "history";
+const SUBCOMMAND_ROLLBACK: &str = "rollback";
+const SUBCOMMAND_ROLLBACK_ARG_GENERATION: &str = "generation";
+const SUBCOMMAND_SCHEMA: &str = "schema";
+
+pub fn command() -> Command {
+    Command::new(COMMAND)
+        .subcommand_required(true)
+        .subcommand(
+            Command::new(SUBCOMMAND_CHECK)
+                .about("Check the config file and show the reload action of each server")
+                .arg(
+                    Arg::new(SUBCOMMAND_CHECK_ARG_PATH)
+                        .value_name("CONFIG FILE")
+                        .required(true)
+                        .num_args(1)
+                        .value_hint(ValueHint::FilePath)
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new(SUBCOMMAND_HISTORY)
+                .about("Show the recorded server config generations and the changes of each"),
+        )
+        .subcommand(
+            Command::new(SUBCOMMAND_ROLLBACK)
+                .about("Rollback the servers to a recorded config generation")
+                .arg(
+                    Arg::new(SUBCOMMAND_ROLLBACK_ARG_GENERATION)
+                        .help(
+                            "The generation to rollback to, default to the one before the current",
+                        )
+                        .value_name("GENERATION")
+                        .num_args(1)
+                        .value_parser(value_parser!(u64).range(1..)),
+                ),
+        )
+        .subcommand(
+            Command::new(SUBCOMMAND_SCHEMA)
+                .about("Show the json schema of the supported config keys"),
+        )
+}
+
+fn print_json(data: &str, name: &str) -> CommandResult<()> {
+    let value = serde_json::Value::from_str(data)
+        .map_err(|e| CommandError::Cli(anyhow!("the {name} is not valid json: {e:?}")))?;
+    let content = serde_json::to_string_pretty(&value)
+        .map_err(|e| CommandError::Cli(anyhow!("failed to encode the {name}: {e:?}")))?;
+    println!("{content}");
+    Ok(())
+}
+
+async fn check(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let path = args.get_one::<PathBuf>(SUBCOMMAND_CHECK_ARG_PATH).unwrap();
+    // the path will be opened by the daemon, which may have a different working directory
+    let path = path
+        .canonicalize()
+        .map_err(|e| CommandError::Cli(anyhow!("invalid config file {}: {e:?}", path.display())))?;
+    let path = path
+        .to_str()
+        .ok_or_else(|| CommandError::Cli(anyhow!("the config file path is not valid utf-8")))?;
+
+    let mut req = client.check_config_request();
+    req.get().set_path(path);
+    let rsp = req.send().promise.await?;
+    let report = parse_fetch_result(rsp.get()?.get_result()?)?
+        .to_str()
+        .map_err(|e| CommandError::Utf8 {
+            field: "result",
+            reason: e,
+        })?;
+
+    print_json(report, "check report")
+}
+
+async fn history(client: &proc_control::Client) -> CommandResult<()> {
+    let req = client.config_history_request();
+    let rsp = req.send().promise.await?;
+    let history = parse_fetch_result(rsp.get()?.get_result()?)?
+        .to_str()
+        .map_err(|e| CommandError::Utf8 {
+            field: "result",
+            reason: e,
+        })?;
+    print_json(history, "config history")
+}
+
+async fn rollback(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let mut req = client.config_rollback_request();
+    if let Some(generation) = args.get_one::<u64>(SUBCOMMAND_ROLLBACK_ARG_GENERATION) {
+        req.get().set_generation(*generation);
+    }
+    let rsp = req.send().promise.await?;
+    parse_operation_result(rsp.get()?.get_result()?)
+}
+
+async fn schema(client: &proc_control::Client) -> CommandResult<()> {
+    let req = client.config_schema_request();
+    let rsp = req.send().promise.await?;
+    let schema = parse_fetch_result(rsp.get()?.get_result()?)?
+        .to_str()
+        .map_err(|e| CommandError::Utf8 {
+            field: "result",
+            reason: e,
+        })?;
+    print_json(schema, "config schema")
+}
+
+pub async fn run(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let (subcommand, args) = args.subcommand().unwrap();
+    match subcommand {
+        SUBCOMMAND_CHECK => check(client, args).await,
+        SUBCOMMAND_HISTORY => history(client).await,
+        SUBCOMMAND_ROLLBACK => rollback(client, args).await,
+        SUBCOMMAND_SCHEMA => schema(client).await,
+        _ => unreachable!(),
+    }
+}
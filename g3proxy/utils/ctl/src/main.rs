@@ -13,6 +13,9 @@ use g3proxy_proto::proc_capnp::proc_control;
 mod common;
 mod proc;
 
+mod auditor;
+mod ban;
+mod config;
 mod escaper;
 mod resolver;
 mod server;
@@ -32,6 +35,9 @@ fn build_cli_args() -> Command {
         .subcommand(proc::commands::reload_auditor())
         .subcommand(proc::commands::reload_escaper())
         .subcommand(proc::commands::reload_server())
+        .subcommand(config::command())
+        .subcommand(ban::command())
+        .subcommand(auditor::command())
         .subcommand(user_group::command())
         .subcommand(resolver::command())
         .subcommand(escaper::command())
@@ -74,6 +80,9 @@ async fn main() -> anyhow::Result<()> {
                 proc::COMMAND_RELOAD_AUDITOR => proc::reload_auditor(&proc_control, args).await,
                 proc::COMMAND_RELOAD_ESCAPER => proc::reload_escaper(&proc_control, args).await,
                 proc::COMMAND_RELOAD_SERVER => proc::reload_server(&proc_control, args).await,
+                config::COMMAND => config::run(&proc_control, args).await,
+                ban::COMMAND => ban::run(&proc_control, args).await,
+                auditor::COMMAND => auditor::run(&proc_control, args).await,
                 user_group::COMMAND => user_group::run(&proc_control, args).await,
                 resolver::COMMAND => resolver::run(&proc_control, args).await,
                 escaper::COMMAND => escaper::run(&proc_control, args).await,
@@ -3,9 +3,12 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::path::PathBuf;
+
+use anyhow::anyhow;
 use clap::ArgMatches;
 
-use g3_ctl::CommandResult;
+use g3_ctl::{CommandError, CommandResult};
 
 use g3proxy_proto::escaper_capnp::escaper_control;
 use g3proxy_proto::proc_capnp::proc_control;
@@ -38,10 +41,11 @@ pub const COMMAND_RELOAD_ESCAPER: &str = "reload-escaper";
 pub const COMMAND_RELOAD_SERVER: &str = "reload-server";
 
 const SUBCOMMAND_ARG_NAME: &str = "name";
+const SUBCOMMAND_ARG_PATH: &str = "path";
 
 pub mod commands {
     use super::*;
-    use clap::{Arg, Command};
+    use clap::{Arg, Command, ValueHint, value_parser};
 
     pub fn version() -> Command {
         Command::new(COMMAND_VERSION)
@@ -99,12 +103,30 @@ pub mod commands {
 
     pub fn reload_escaper() -> Command {
         Command::new(COMMAND_RELOAD_ESCAPER)
+            .about("Reload the named escaper only, and show the action taken")
             .arg(Arg::new(SUBCOMMAND_ARG_NAME).required(true).num_args(1))
+            .arg(
+                Arg::new(SUBCOMMAND_ARG_PATH)
+                    .help("The config file to find the escaper in, default to the recorded one")
+                    .value_name("CONFIG FILE")
+                    .num_args(1)
+                    .value_hint(ValueHint::FilePath)
+                    .value_parser(value_parser!(PathBuf)),
+            )
     }
 
     pub fn reload_server() -> Command {
         Command::new(COMMAND_RELOAD_SERVER)
+            .about("Reload the named server only, and show the action taken")
             .arg(Arg::new(SUBCOMMAND_ARG_NAME).required(true).num_args(1))
+            .arg(
+                Arg::new(SUBCOMMAND_ARG_PATH)
+                    .help("The config file to find the server in, default to the recorded one")
+                    .value_name("CONFIG FILE")
+                    .num_args(1)
+                    .value_hint(ValueHint::FilePath)
+                    .value_parser(value_parser!(PathBuf)),
+            )
     }
 }
 
@@ -215,18 +237,40 @@ pub async fn reload_auditor(client: &proc_control::Client, args: &ArgMatches) ->
     parse_operation_result(rsp.get()?.get_result()?)
 }
 
+fn get_path_arg(args: &ArgMatches) -> CommandResult<Option<String>> {
+    let Some(path) = args.get_one::<PathBuf>(SUBCOMMAND_ARG_PATH) else {
+        return Ok(None);
+    };
+    // the path will be opened by the daemon, which may have a different working directory
+    let path = path
+        .canonicalize()
+        .map_err(|e| CommandError::Cli(anyhow!("invalid config file {}: {e:?}", path.display())))?;
+    path.into_os_string()
+        .into_string()
+        .map(Some)
+        .map_err(|_| CommandError::Cli(anyhow!("the config file path is not valid utf-8")))
+}
+
 pub async fn reload_escaper(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
     let name = args.get_one::<String>(SUBCOMMAND_ARG_NAME).unwrap();
+    let path = get_path_arg(args)?;
     let mut req = client.reload_escaper_request();
     req.get().set_name(name);
+    if let Some(path) = &path {
+        req.get().set_path(path);
+    }
     let rsp = req.send().promise.await?;
     parse_operation_result(rsp.get()?.get_result()?)
 }
 
 pub async fn reload_server(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
     let name = args.get_one::<String>(SUBCOMMAND_ARG_NAME).unwrap();
+    let path = get_path_arg(args)?;
     let mut req = client.reload_server_request();
     req.get().set_name(name);
+    if let Some(path) = &path {
+        req.get().set_path(path);
+    }
     let rsp = req.send().promise.await?;
     parse_operation_result(rsp.get()?.get_result()?)
 }
@@ -3,25 +3,128 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use clap::{Arg, ArgMatches, Command};
+use clap::{Arg, ArgMatches, Command, value_parser};
 use futures_util::future::TryFutureExt;
 
-use g3_ctl::CommandResult;
+use g3_ctl::{CommandError, CommandResult};
 
 use g3proxy_proto::proc_capnp::proc_control;
-use g3proxy_proto::server_capnp::server_control;
+use g3proxy_proto::server_capnp::{check_result, server_control, task_list_result};
+
+use crate::common::parse_operation_result;
 
 pub const COMMAND: &str = "server";
 
 const COMMAND_ARG_NAME: &str = "name";
 
 const SUBCOMMAND_STATUS: &str = "status";
+const SUBCOMMAND_CHECK: &str = "check";
+const SUBCOMMAND_CHECK_ARG_SNI: &str = "sni";
+const SUBCOMMAND_UDP_CAPTURE: &str = "udp-capture";
+const SUBCOMMAND_UDP_CAPTURE_START: &str = "start";
+const SUBCOMMAND_UDP_CAPTURE_STOP: &str = "stop";
+const SUBCOMMAND_UDP_CAPTURE_ARG_RATIO: &str = "ratio";
+const SUBCOMMAND_UDP_CAPTURE_ARG_MAX_PACKETS: &str = "max_packets";
+const SUBCOMMAND_TASK: &str = "task";
+const SUBCOMMAND_TASK_LIST: &str = "list";
+const SUBCOMMAND_TASK_LIST_ARG_AFTER: &str = "after";
+const SUBCOMMAND_TASK_LIST_ARG_LIMIT: &str = "limit";
+const SUBCOMMAND_TASK_KILL: &str = "kill";
+const SUBCOMMAND_TASK_KILL_ARG_ID: &str = "id";
+const SUBCOMMAND_TASK_LOG: &str = "task-log";
+const SUBCOMMAND_TASK_LOG_RATIO: &str = "ratio";
+const SUBCOMMAND_TASK_LOG_RATIO_ARG_VALUE: &str = "value";
+const SUBCOMMAND_TASK_LOG_FORCE_FULL: &str = "force-full";
+const SUBCOMMAND_TASK_LOG_FORCE_FULL_ARG_SECONDS: &str = "seconds";
 
 pub fn command() -> Command {
     Command::new(COMMAND)
         .arg(Arg::new(COMMAND_ARG_NAME).required(true).num_args(1))
         .subcommand_required(true)
         .subcommand(Command::new(SUBCOMMAND_STATUS))
+        .subcommand(
+            Command::new(SUBCOMMAND_CHECK).arg(
+                Arg::new(SUBCOMMAND_CHECK_ARG_SNI)
+                    .help("Do a TLS handshake to this server name, for TLS servers only")
+                    .long(SUBCOMMAND_CHECK_ARG_SNI)
+                    .num_args(1),
+            ),
+        )
+        .subcommand(
+            Command::new(SUBCOMMAND_UDP_CAPTURE)
+                .about("Capture sampled udp relay packets to a pcap file")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new(SUBCOMMAND_UDP_CAPTURE_START)
+                        .arg(
+                            Arg::new(SUBCOMMAND_UDP_CAPTURE_ARG_RATIO)
+                                .help("Sample ratio, should be in (0, 1]")
+                                .required(true)
+                                .num_args(1)
+                                .value_parser(value_parser!(f64)),
+                        )
+                        .arg(
+                            Arg::new(SUBCOMMAND_UDP_CAPTURE_ARG_MAX_PACKETS)
+                                .help("Stop the capture after this number of packets captured")
+                                .required(true)
+                                .num_args(1)
+                                .value_parser(value_parser!(u32)),
+                        ),
+                )
+                .subcommand(Command::new(SUBCOMMAND_UDP_CAPTURE_STOP)),
+        )
+        .subcommand(
+            Command::new(SUBCOMMAND_TASK)
+                .about("List or kill the running relay tasks")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new(SUBCOMMAND_TASK_LIST)
+                        .arg(
+                            Arg::new(SUBCOMMAND_TASK_LIST_ARG_AFTER)
+                                .help("List the tasks after the one with this id")
+                                .long(SUBCOMMAND_TASK_LIST_ARG_AFTER)
+                                .num_args(1),
+                        )
+                        .arg(
+                            Arg::new(SUBCOMMAND_TASK_LIST_ARG_LIMIT)
+                                .help("Max number of tasks to list, the server default is 100")
+                                .long(SUBCOMMAND_TASK_LIST_ARG_LIMIT)
+                                .num_args(1)
+                                .value_parser(value_parser!(u32)),
+                        ),
+                )
+                .subcommand(
+                    Command::new(SUBCOMMAND_TASK_KILL).arg(
+                        Arg::new(SUBCOMMAND_TASK_KILL_ARG_ID)
+                            .help("The task id")
+                            .required(true)
+                            .num_args(1),
+                    ),
+                ),
+        )
+        .subcommand(
+            Command::new(SUBCOMMAND_TASK_LOG)
+                .about("Adjust the task log sampling, the changes will be reset by server reload")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new(SUBCOMMAND_TASK_LOG_RATIO).arg(
+                        Arg::new(SUBCOMMAND_TASK_LOG_RATIO_ARG_VALUE)
+                            .help("Sample ratio, should be in [0, 1]")
+                            .required(true)
+                            .num_args(1)
+                            .value_parser(value_parser!(f64)),
+                    ),
+                )
+                .subcommand(
+                    Command::new(SUBCOMMAND_TASK_LOG_FORCE_FULL).arg(
+                        Arg::new(SUBCOMMAND_TASK_LOG_FORCE_FULL_ARG_SECONDS)
+                            .help("Log all new tasks in the following seconds, 0 to stop")
+                            .required(true)
+                            .num_args(1)
+                            .value_parser(value_parser!(u32)),
+                    ),
+                ),
+        )
 }
 
 async fn status(client: &server_control::Client) -> CommandResult<()> {
@@ -32,19 +135,210 @@ async fn status(client: &server_control::Client) -> CommandResult<()> {
     println!("alive tasks: {}", stats.get_alive_task_count());
     println!("total conn: {}", stats.get_total_conn_count());
     println!("total task: {}", stats.get_total_task_count());
+    println!("listening: {}", stats.get_listening());
+    for addr in stats.get_listen_addrs()?.iter() {
+        let addr = addr?.to_str().map_err(|e| CommandError::Utf8 {
+            field: "listen_addrs",
+            reason: e,
+        })?;
+        println!("listen addr: {addr}");
+    }
+    let last_error = stats
+        .get_last_listen_error()?
+        .to_str()
+        .map_err(|e| CommandError::Utf8 {
+            field: "last_listen_error",
+            reason: e,
+        })?;
+    if !last_error.is_empty() {
+        println!("last listen error: {last_error}");
+    }
     Ok(())
 }
 
+async fn check(client: &server_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let mut req = client.check_request();
+    if let Some(sni) = args.get_one::<String>(SUBCOMMAND_CHECK_ARG_SNI) {
+        req.get().set_sni(sni);
+    }
+    let rsp = req.send().promise.await?;
+    let result = rsp.get()?.get_result()?;
+    match result.which().unwrap() {
+        check_result::Which::Report(report) => {
+            let report = report?;
+            println!("connect time: {}us", report.get_connect_time());
+            let handshake_time = report.get_handshake_time();
+            if handshake_time > 0 {
+                println!("handshake time: {handshake_time}us");
+            }
+            Ok(())
+        }
+        check_result::Which::Err(reason) => Err(CommandError::api_error(-1, reason?)),
+    }
+}
+
+async fn udp_capture(client: &server_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let (subcommand, args) = args.subcommand().unwrap();
+    match subcommand {
+        SUBCOMMAND_UDP_CAPTURE_START => {
+            let ratio = args
+                .get_one::<f64>(SUBCOMMAND_UDP_CAPTURE_ARG_RATIO)
+                .unwrap();
+            let max_packets = args
+                .get_one::<u32>(SUBCOMMAND_UDP_CAPTURE_ARG_MAX_PACKETS)
+                .unwrap();
+            let mut req = client.start_udp_capture_request();
+            req.get().set_ratio(*ratio);
+            req.get().set_max_packets(*max_packets);
+            let rsp = req.send().promise.await?;
+            parse_operation_result(rsp.get()?.get_result()?)
+        }
+        SUBCOMMAND_UDP_CAPTURE_STOP => {
+            let req = client.stop_udp_capture_request();
+            let rsp = req.send().promise.await?;
+            parse_operation_result(rsp.get()?.get_result()?)
+        }
+        _ => unreachable!(),
+    }
+}
+
+async fn list_tasks(client: &server_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let mut req = client.list_tasks_request();
+    if let Some(after) = args.get_one::<String>(SUBCOMMAND_TASK_LIST_ARG_AFTER) {
+        req.get().set_after(after);
+    }
+    if let Some(limit) = args.get_one::<u32>(SUBCOMMAND_TASK_LIST_ARG_LIMIT) {
+        req.get().set_limit(*limit);
+    }
+    let rsp = req.send().promise.await?;
+    let result = rsp.get()?.get_result()?;
+    match result.which().unwrap() {
+        task_list_result::Which::Tasks(tasks) => {
+            let tasks = tasks?;
+            let mut last_id = String::new();
+            for task in tasks.iter() {
+                let id = task.get_id()?.to_str().map_err(|e| CommandError::Utf8 {
+                    field: "id",
+                    reason: e,
+                })?;
+                let client_addr = task.get_client_addr()?;
+                let client_addr = client_addr.to_str().map_err(|e| CommandError::Utf8 {
+                    field: "client_addr",
+                    reason: e,
+                })?;
+                let upstream = task.get_upstream_addr()?;
+                let upstream = upstream.to_str().map_err(|e| CommandError::Utf8 {
+                    field: "upstream_addr",
+                    reason: e,
+                })?;
+                println!("{id}:");
+                println!("  client addr: {client_addr}");
+                if !upstream.is_empty() {
+                    println!("  upstream addr: {upstream}");
+                }
+                println!("  start time: {}", task.get_start_time());
+                println!("  last active time: {}", task.get_last_active_time());
+                println!(
+                    "  client rd/wr bytes: {}/{}",
+                    task.get_client_rd_bytes(),
+                    task.get_client_wr_bytes()
+                );
+                println!(
+                    "  remote rd/wr bytes: {}/{}",
+                    task.get_remote_rd_bytes(),
+                    task.get_remote_wr_bytes()
+                );
+                let clt_batch_size = task.get_client_recv_batch_size();
+                let ups_batch_size = task.get_remote_recv_batch_size();
+                if clt_batch_size > 0 || ups_batch_size > 0 {
+                    println!("  client/remote recv batch size: {clt_batch_size}/{ups_batch_size}");
+                }
+                last_id = id.to_string();
+            }
+            if !last_id.is_empty() {
+                println!("next page: --after {last_id}");
+            }
+            Ok(())
+        }
+        task_list_result::Which::Err(err) => {
+            let err = err?;
+            Err(CommandError::api_error(err.get_code(), err.get_reason()?))
+        }
+    }
+}
+
+async fn kill_task(client: &server_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let id = args.get_one::<String>(SUBCOMMAND_TASK_KILL_ARG_ID).unwrap();
+    let mut req = client.kill_task_request();
+    req.get().set_id(id);
+    let rsp = req.send().promise.await?;
+    parse_operation_result(rsp.get()?.get_result()?)
+}
+
+async fn task(client: &server_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let (subcommand, args) = args.subcommand().unwrap();
+    match subcommand {
+        SUBCOMMAND_TASK_LIST => list_tasks(client, args).await,
+        SUBCOMMAND_TASK_KILL => kill_task(client, args).await,
+        _ => unreachable!(),
+    }
+}
+
+async fn task_log(client: &server_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let (subcommand, args) = args.subcommand().unwrap();
+    match subcommand {
+        SUBCOMMAND_TASK_LOG_RATIO => {
+            let ratio = args
+                .get_one::<f64>(SUBCOMMAND_TASK_LOG_RATIO_ARG_VALUE)
+                .unwrap();
+            let mut req = client.set_task_log_sample_ratio_request();
+            req.get().set_ratio(*ratio);
+            let rsp = req.send().promise.await?;
+            parse_operation_result(rsp.get()?.get_result()?)
+        }
+        SUBCOMMAND_TASK_LOG_FORCE_FULL => {
+            let seconds = args
+                .get_one::<u32>(SUBCOMMAND_TASK_LOG_FORCE_FULL_ARG_SECONDS)
+                .unwrap();
+            let mut req = client.force_full_task_log_request();
+            req.get().set_seconds(*seconds);
+            let rsp = req.send().promise.await?;
+            parse_operation_result(rsp.get()?.get_result()?)
+        }
+        _ => unreachable!(),
+    }
+}
+
 pub async fn run(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
     let name = args.get_one::<String>(COMMAND_ARG_NAME).unwrap();
 
-    let (subcommand, _) = args.subcommand().unwrap();
+    let (subcommand, args) = args.subcommand().unwrap();
     match subcommand {
         SUBCOMMAND_STATUS => {
             super::proc::get_server(client, name)
                 .and_then(|server| async move { status(&server).await })
                 .await
         }
+        SUBCOMMAND_CHECK => {
+            super::proc::get_server(client, name)
+                .and_then(|server| async move { check(&server, args).await })
+                .await
+        }
+        SUBCOMMAND_UDP_CAPTURE => {
+            super::proc::get_server(client, name)
+                .and_then(|server| async move { udp_capture(&server, args).await })
+                .await
+        }
+        SUBCOMMAND_TASK => {
+            super::proc::get_server(client, name)
+                .and_then(|server| async move { task(&server, args).await })
+                .await
+        }
+        SUBCOMMAND_TASK_LOG => {
+            super::proc::get_server(client, name)
+                .and_then(|server| async move { task_log(&server, args).await })
+                .await
+        }
         _ => unreachable!(),
     }
 }
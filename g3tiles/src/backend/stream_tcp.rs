@@ -3,22 +3,27 @@
  * Copyright 2024-2025 ByteDance and/or its affiliates.
  */
 
-use std::net::SocketAddr;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, anyhow};
 use arc_swap::ArcSwapOption;
 use async_trait::async_trait;
 use futures_util::future::{AbortHandle, Abortable};
+use tokio::net::{TcpSocket, TcpStream};
 use tokio::time::Instant;
 
+use g3_socket::RawSocket;
+use g3_socket::util::AddressFamily;
 use g3_types::collection::{SelectiveVec, SelectiveVecBuilder, WeightedValue};
 use g3_types::metrics::NodeName;
-use g3_types::net::ConnectError;
+use g3_types::net::{ConnectError, TcpMiscSockOpts};
 
 use super::{ArcBackendInternal, Backend, BackendExt, BackendInternal, BackendRegistry};
 use crate::config::backend::stream_tcp::StreamTcpBackendConfig;
 use crate::config::backend::{AnyBackendConfig, BackendConfig};
+use crate::discover::DiscoverResult;
 use crate::module::stream::{
     StreamBackendDurationRecorder, StreamBackendDurationStats, StreamBackendStats,
     StreamConnectError, StreamConnectResult,
@@ -99,6 +104,54 @@ impl StreamTcpBackend {
     }
 }
 
+/// Update the peer addresses used for new connections, the last known good ones will be kept
+/// if the discover failed or returned nothing
+fn update_peer_addrs(
+    container: &ArcSwapOption<SelectiveVec<WeightedValue<SocketAddr>>>,
+    stats: &StreamBackendStats,
+    r: &DiscoverResult,
+) {
+    let Ok(data) = r else {
+        stats.add_discover_failed();
+        return;
+    };
+    let mut builder = SelectiveVecBuilder::new();
+    for v in data {
+        builder.insert(*v);
+    }
+    match builder.build() {
+        Some(peers) => container.store(Some(Arc::new(peers))),
+        None => stats.add_discover_failed(),
+    }
+}
+
+/// Create the socket for a new backend connection, with the DSCP value set if present
+fn new_backend_socket(peer_ip: IpAddr, dscp: Option<u8>) -> io::Result<TcpSocket> {
+    let mut misc_opts = TcpMiscSockOpts::default();
+    if let Some(dscp) = dscp {
+        misc_opts.set_dscp(dscp);
+    }
+    g3_socket::tcp::new_socket_to(
+        peer_ip,
+        &Default::default(),
+        &Default::default(),
+        &misc_opts,
+        true,
+    )
+}
+
+/// Set the server side socket options on the connected backend stream
+fn set_upstream_misc_opts(
+    stream: &TcpStream,
+    peer_addr: SocketAddr,
+    misc_opts: Option<&TcpMiscSockOpts>,
+) -> io::Result<()> {
+    let Some(misc_opts) = misc_opts else {
+        return Ok(());
+    };
+    RawSocket::from(stream).set_tcp_misc_opts(AddressFamily::from(&peer_addr), misc_opts, false)
+}
+
 impl BackendExt for StreamTcpBackend {}
 
 #[async_trait]
@@ -123,17 +176,12 @@ impl Backend for StreamTcpBackend {
                 ))?;
 
         let peer_addrs_container = self.peer_addrs.clone();
+        let stats = self.stats.clone();
         let (abort_handle, abort_reg) = AbortHandle::new_pair();
         let abort_fut = Abortable::new(
             async move {
                 while discover_receiver.changed().await.is_ok() {
-                    if let Ok(data) = discover_receiver.borrow().as_ref() {
-                        let mut builder = SelectiveVecBuilder::new();
-                        for v in data {
-                            builder.insert(*v);
-                        }
-                        peer_addrs_container.store(builder.build().map(Arc::new));
-                    }
+                    update_peer_addrs(&peer_addrs_container, &stats, &discover_receiver.borrow());
                 }
             },
             abort_reg,
@@ -161,14 +209,8 @@ impl Backend for StreamTcpBackend {
         };
 
         self.stats.add_conn_attempt();
-        let socket = g3_socket::tcp::new_socket_to(
-            next_addr.ip(),
-            &Default::default(),
-            &Default::default(),
-            &Default::default(),
-            true,
-        )
-        .map_err(StreamConnectError::SetupSocketFailed)?;
+        let socket = new_backend_socket(next_addr.ip(), task_notes.backend_dscp)
+            .map_err(StreamConnectError::SetupSocketFailed)?;
 
         let time_now = Instant::now();
         let stream = socket
@@ -178,6 +220,8 @@ impl Backend for StreamTcpBackend {
         let connect_dur = time_now.elapsed();
         self.stats.add_conn_established();
         self.duration_recorder.record_connect_time(connect_dur);
+        set_upstream_misc_opts(&stream, next_addr, task_notes.upstream_misc_opts.as_ref())
+            .map_err(StreamConnectError::SetupSocketFailed)?;
 
         let (ups_r, ups_w) = stream.into_split();
         Ok((Box::new(ups_r), Box::new(ups_w)))
@@ -209,3 +253,112 @@ impl BackendInternal for StreamTcpBackend {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn peers(
+        container: &ArcSwapOption<SelectiveVec<WeightedValue<SocketAddr>>>,
+    ) -> Vec<SocketAddr> {
+        let guard = container.load();
+        let Some(peers) = guard.as_ref() else {
+            return Vec::new();
+        };
+        peers
+            .pick_serial_n(usize::MAX)
+            .into_iter()
+            .map(|v| *v.inner())
+            .collect()
+    }
+
+    #[test]
+    fn keep_last_good() {
+        let container = ArcSwapOption::new(None);
+        let stats = StreamBackendStats::new(&NodeName::from_str("test").unwrap());
+        let addr1 = SocketAddr::from_str("192.0.2.1:443").unwrap();
+        let addr2 = SocketAddr::from_str("192.0.2.2:443").unwrap();
+
+        update_peer_addrs(&container, &stats, &Ok(vec![WeightedValue::new(addr1)]));
+        assert_eq!(peers(&container), vec![addr1]);
+
+        update_peer_addrs(&container, &stats, &Err(anyhow!("server failure")));
+        assert_eq!(peers(&container), vec![addr1]);
+        assert_eq!(stats.discover_failed(), 1);
+
+        update_peer_addrs(&container, &stats, &Ok(Vec::new()));
+        assert_eq!(peers(&container), vec![addr1]);
+        assert_eq!(stats.discover_failed(), 2);
+
+        update_peer_addrs(&container, &stats, &Ok(vec![WeightedValue::new(addr2)]));
+        assert_eq!(peers(&container), vec![addr2]);
+        assert_eq!(stats.discover_failed(), 2);
+    }
+
+    async fn connect_with_dscp(listen: &str, dscp: Option<u8>) -> Option<tokio::net::TcpStream> {
+        // the loopback address may be unavailable, e.g. ipv6 disabled
+        let listener = tokio::net::TcpListener::bind(listen).await.ok()?;
+        let addr = listener.local_addr().unwrap();
+        let socket = new_backend_socket(addr.ip(), dscp).unwrap();
+        let stream = socket.connect(addr).await.unwrap();
+        let _ = listener.accept().await.unwrap();
+        Some(stream)
+    }
+
+    #[tokio::test]
+    async fn backend_dscp_v4() {
+        let stream = connect_with_dscp("127.0.0.1:0", Some(46)).await.unwrap();
+        let tos = socket2::SockRef::from(&stream).tos_v4().unwrap();
+        assert_eq!(tos, 46 << 2);
+
+        let stream = connect_with_dscp("127.0.0.1:0", None).await.unwrap();
+        let tos = socket2::SockRef::from(&stream).tos_v4().unwrap();
+        assert_eq!(tos, 0);
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn backend_dscp_v6() {
+        let Some(stream) = connect_with_dscp("[::1]:0", Some(10)).await else {
+            return;
+        };
+        let tclass = socket2::SockRef::from(&stream).tclass_v6().unwrap();
+        assert_eq!(tclass, 10 << 2);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn per_direction_misc_opts() {
+        use g3_daemon::server::ClientConnectionInfo;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _clt_stream = TcpStream::connect(addr).await.unwrap();
+        let (clt_accepted, clt_addr) = listener.accept().await.unwrap();
+
+        // the client side socket is set when the task starts
+        let mut clt_opts = TcpMiscSockOpts::default();
+        clt_opts.no_delay = Some(true);
+        clt_opts.notsent_lowat = Some(16384);
+        let mut cc_info = ClientConnectionInfo::new(clt_addr, addr);
+        cc_info.set_tcp_raw_socket(RawSocket::from(&clt_accepted));
+        cc_info.tcp_sock_set_raw_opts(&clt_opts, true).unwrap();
+
+        // the backend socket is set after connected
+        let mut ups_opts = TcpMiscSockOpts::default();
+        ups_opts.no_delay = Some(false);
+        ups_opts.notsent_lowat = Some(4096);
+        let socket = new_backend_socket(addr.ip(), None).unwrap();
+        let ups_stream = socket.connect(addr).await.unwrap();
+        let _ = listener.accept().await.unwrap();
+        set_upstream_misc_opts(&ups_stream, addr, Some(&ups_opts)).unwrap();
+
+        assert!(socket2::SockRef::from(&clt_accepted).tcp_nodelay().unwrap());
+        assert!(!socket2::SockRef::from(&ups_stream).tcp_nodelay().unwrap());
+        let lowat = RawSocket::from(&clt_accepted).tcp_notsent_lowat().unwrap();
+        assert_eq!(lowat, 16384);
+        let lowat = RawSocket::from(&ups_stream).tcp_notsent_lowat().unwrap();
+        assert_eq!(lowat, 4096);
+    }
+}
@@ -13,7 +13,7 @@ use g3_histogram::HistogramMetricsConfig;
 use g3_types::metrics::{MetricTagMap, NodeName};
 use g3_types::net::{
     ConnectionPoolConfig, QuinnTransportConfigBuilder, RustlsClientConfigBuilder,
-    SocketBufferConfig,
+    SocketBufferConfig, TlsCertStatus,
 };
 use g3_yaml::YamlDocPosition;
 
@@ -204,4 +204,8 @@ impl BackendConfig for KeylessQuicBackendConfig {
 
         BackendConfigDiffAction::Reload
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        self.tls_client.cert_status()
+    }
 }
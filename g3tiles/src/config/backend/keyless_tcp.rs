@@ -12,7 +12,9 @@ use yaml_rust::{Yaml, yaml};
 
 use g3_histogram::HistogramMetricsConfig;
 use g3_types::metrics::{MetricTagMap, NodeName};
-use g3_types::net::{ConnectionPoolConfig, RustlsClientConfigBuilder, TcpKeepAliveConfig};
+use g3_types::net::{
+    ConnectionPoolConfig, RustlsClientConfigBuilder, TcpKeepAliveConfig, TlsCertStatus,
+};
 use g3_yaml::YamlDocPosition;
 
 use super::{AnyBackendConfig, BackendConfig, BackendConfigDiffAction};
@@ -187,4 +189,11 @@ impl BackendConfig for KeylessTcpBackendConfig {
 
         BackendConfigDiffAction::Reload
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        match &self.tls_client {
+            Some(builder) => builder.cert_status(),
+            None => Ok(Vec::new()),
+        }
+    }
 }
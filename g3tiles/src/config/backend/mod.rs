@@ -10,6 +10,7 @@ use yaml_rust::{Yaml, yaml};
 
 use g3_macros::AnyConfig;
 use g3_types::metrics::NodeName;
+use g3_types::net::TlsCertStatus;
 use g3_yaml::{HybridParser, YamlDocPosition};
 
 pub(crate) mod dummy_close;
@@ -38,6 +39,11 @@ pub(crate) trait BackendConfig {
     fn r#type(&self) -> &'static str;
 
     fn diff_action(&self, new: &AnyBackendConfig) -> BackendConfigDiffAction;
+
+    /// get the status of all the certificates configured in this backend
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        Ok(Vec::new())
+    }
 }
 
 #[derive(Clone, AnyConfig)]
@@ -45,6 +51,7 @@ pub(crate) trait BackendConfig {
 #[def_fn(r#type, &'static str)]
 #[def_fn(position, Option<YamlDocPosition>)]
 #[def_fn(diff_action, &Self, BackendConfigDiffAction)]
+#[def_fn(cert_status, anyhow::Result<Vec<TlsCertStatus>>)]
 pub(crate) enum AnyBackendConfig {
     DummyClose(dummy_close::DummyCloseBackendConfig),
     StreamTcp(stream_tcp::StreamTcpBackendConfig),
@@ -57,6 +64,7 @@ pub(crate) fn load_all(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
     let parser = HybridParser::new(conf_dir, g3_daemon::opts::config_file_extension());
     parser.foreach_map(v, |map, position| {
         let backend = load_backend(map, position)?;
+        check_certs(&backend)?;
         registry::add(backend, false)?;
         Ok(())
     })?;
@@ -67,6 +75,7 @@ pub(crate) fn load_at_position(position: &YamlDocPosition) -> anyhow::Result<Any
     let doc = g3_yaml::load_doc(position)?;
     if let Yaml::Hash(map) = doc {
         let backend = load_backend(&map, Some(position.clone()))?;
+        check_certs(&backend)?;
         registry::add(backend.clone(), true)?;
         Ok(backend)
     } else {
@@ -74,6 +83,15 @@ pub(crate) fn load_at_position(position: &YamlDocPosition) -> anyhow::Result<Any
     }
 }
 
+fn check_certs(backend: &AnyBackendConfig) -> anyhow::Result<()> {
+    let certs = backend
+        .cert_status()
+        .context(format!("invalid certificate in backend {}", backend.name()))?;
+    g3_daemon::tls::check_and_record_certs(format!("backend/{}", backend.name()), certs).context(
+        format!("certificate check failed for backend {}", backend.name()),
+    )
+}
+
 fn load_backend(
     map: &yaml::Hash,
     position: Option<YamlDocPosition>,
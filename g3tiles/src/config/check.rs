@@ -0,0 +1,355 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use serde_json::{Value, json};
+use yaml_rust::{Yaml, YamlLoader, yaml};
+
+use g3_types::metrics::NodeName;
+use g3_yaml::{HybridParser, YamlDocPosition};
+
+use super::server::{AnyServerConfig, ServerConfigDiffAction};
+
+/// The action that would be taken for a server if the candidate config is reloaded
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ServerCheckAction {
+    NoAction,
+    SpawnNew,
+    ReloadNoRespawn,
+    ReloadAndRespawn,
+    UpdateInPlace,
+    Remove,
+    Error,
+}
+
+impl ServerCheckAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ServerCheckAction::NoAction => "no_action",
+            ServerCheckAction::SpawnNew => "spawn_new",
+            ServerCheckAction::ReloadNoRespawn => "reload_no_respawn",
+            ServerCheckAction::ReloadAndRespawn => "reload_and_respawn",
+            ServerCheckAction::UpdateInPlace => "update_in_place",
+            ServerCheckAction::Remove => "remove",
+            ServerCheckAction::Error => "error",
+        }
+    }
+}
+
+impl From<ServerConfigDiffAction> for ServerCheckAction {
+    fn from(value: ServerConfigDiffAction) -> Self {
+        match value {
+            ServerConfigDiffAction::NoAction => ServerCheckAction::NoAction,
+            ServerConfigDiffAction::SpawnNew => ServerCheckAction::SpawnNew,
+            ServerConfigDiffAction::ReloadNoRespawn => ServerCheckAction::ReloadNoRespawn,
+            ServerConfigDiffAction::ReloadAndRespawn => ServerCheckAction::ReloadAndRespawn,
+            ServerConfigDiffAction::UpdateInPlace(_) => ServerCheckAction::UpdateInPlace,
+        }
+    }
+}
+
+struct ServerCheckEntry {
+    name: String,
+    r#type: String,
+    action: ServerCheckAction,
+    error: Option<String>,
+    position: Option<YamlDocPosition>,
+}
+
+impl ServerCheckEntry {
+    fn to_json(&self) -> Value {
+        let mut value = json!({
+            "name": self.name,
+            "type": self.r#type,
+            "action": self.action.as_str(),
+        });
+        if let Some(e) = &self.error {
+            value["error"] = json!(e);
+        }
+        if let Some(position) = &self.position {
+            value["position"] = json!({
+                "path": position.path.display().to_string(),
+                "index": position.index,
+            });
+        }
+        value
+    }
+}
+
+/// The report of a config check, which is built without touching any runtime state
+#[derive(Default)]
+pub(crate) struct ConfigCheckReport {
+    servers: Vec<ServerCheckEntry>,
+    errors: Vec<String>,
+}
+
+impl ConfigCheckReport {
+    pub(crate) fn has_error(&self) -> bool {
+        !self.errors.is_empty()
+            || self
+                .servers
+                .iter()
+                .any(|s| s.action == ServerCheckAction::Error)
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        let servers: Vec<Value> = self.servers.iter().map(|s| s.to_json()).collect();
+        json!({
+            "servers": servers,
+            "errors": self.errors,
+        })
+    }
+}
+
+struct ServerCandidate {
+    name: String,
+    r#type: String,
+    position: YamlDocPosition,
+    config: anyhow::Result<AnyServerConfig>,
+}
+
+impl ServerCandidate {
+    fn load(map: &yaml::Hash, position: Option<YamlDocPosition>, doc: &YamlDocPosition) -> Self {
+        let get_str = |k: &str| {
+            g3_yaml::hash_get_required_str(map, k)
+                .map(|s| s.to_string())
+                .unwrap_or_default()
+        };
+        ServerCandidate {
+            name: get_str(super::server::CONFIG_KEY_SERVER_NAME),
+            r#type: get_str(super::server::CONFIG_KEY_SERVER_TYPE),
+            position: position.clone().unwrap_or_else(|| doc.clone()),
+            config: super::server::load_server(map, position).and_then(|server| {
+                server
+                    .cert_status()
+                    .context(format!("invalid certificate in server {}", server.name()))?;
+                Ok(server)
+            }),
+        }
+    }
+}
+
+/// Check the candidate config file against the running servers
+pub(crate) fn check_file(path: &Path) -> ConfigCheckReport {
+    let running: BTreeMap<NodeName, AnyServerConfig> = crate::serve::get_names()
+        .into_iter()
+        .filter_map(|name| {
+            let config = crate::serve::get_config(&name)?;
+            Some((name, config))
+        })
+        .collect();
+
+    let docs = match load_file(path) {
+        Ok(docs) => docs,
+        Err(e) => {
+            return ConfigCheckReport {
+                servers: Vec::new(),
+                errors: vec![format!("{e:#}")],
+            };
+        }
+    };
+    let conf_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    check_docs(&docs, path, conf_dir, running)
+}
+
+fn load_file(path: &Path) -> anyhow::Result<Vec<Yaml>> {
+    let conf = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read config file {}: {e}", path.display()))?;
+    YamlLoader::load_from_str(&conf)
+        .map_err(|e| anyhow!("invalid yaml file {}: {e}", path.display()))
+}
+
+fn check_docs(
+    docs: &[Yaml],
+    path: &Path,
+    conf_dir: &Path,
+    running: BTreeMap<NodeName, AnyServerConfig>,
+) -> ConfigCheckReport {
+    let mut report = ConfigCheckReport::default();
+
+    let parser = HybridParser::new(conf_dir, g3_daemon::opts::config_file_extension());
+    let candidates = RefCell::new(Vec::new());
+    for (i, doc) in docs.iter().enumerate() {
+        let doc_position = YamlDocPosition {
+            path: PathBuf::from(path),
+            index: i,
+        };
+        let Yaml::Hash(map) = doc else {
+            report
+                .errors
+                .push(format!("yaml doc {doc_position} root should be hash"));
+            continue;
+        };
+        for (k, v) in map.iter() {
+            let Yaml::String(k) = k else {
+                continue;
+            };
+            if g3_yaml::key::normalize(k) != "server" {
+                continue;
+            }
+            // parse each entry on its own, so a bad one won't hide the others
+            let entries: Vec<&Yaml> = match v {
+                Yaml::Array(seq) => seq.iter().collect(),
+                _ => vec![v],
+            };
+            for entry in entries {
+                let entry = Yaml::Array(vec![entry.clone()]);
+                if let Err(e) = parser.foreach_map(&entry, |map, position| {
+                    let candidate = ServerCandidate::load(map, position, &doc_position);
+                    candidates.borrow_mut().push(candidate);
+                    Ok(())
+                }) {
+                    report
+                        .errors
+                        .push(format!("yaml doc {doc_position}: {e:#}"));
+                }
+            }
+        }
+    }
+
+    let mut found = HashSet::new();
+    for candidate in candidates.into_inner() {
+        let first_seen = found.insert(candidate.name.clone());
+        let entry = match candidate.config {
+            Ok(config) if first_seen => {
+                let action = match running.get(config.name()) {
+                    Some(old) => old.diff_action(&config).into(),
+                    None => ServerCheckAction::SpawnNew,
+                };
+                ServerCheckEntry {
+                    name: candidate.name,
+                    r#type: config.r#type().to_string(),
+                    action,
+                    error: None,
+                    position: None,
+                }
+            }
+            r => {
+                let error = match r {
+                    Ok(_) => format!("server with name {} already exists", candidate.name),
+                    Err(e) => format!("{e:#}"),
+                };
+                ServerCheckEntry {
+                    name: candidate.name,
+                    r#type: candidate.r#type,
+                    action: ServerCheckAction::Error,
+                    error: Some(error),
+                    position: Some(candidate.position),
+                }
+            }
+        };
+        report.servers.push(entry);
+    }
+
+    for (name, old) in running {
+        if found.contains(name.as_str()) {
+            continue;
+        }
+        report.servers.push(ServerCheckEntry {
+            name: name.to_string(),
+            r#type: old.r#type().to_string(),
+            action: ServerCheckAction::Remove,
+            error: None,
+            position: None,
+        });
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_running(s: &str) -> BTreeMap<NodeName, AnyServerConfig> {
+        let docs = YamlLoader::load_from_str(s).unwrap();
+        let mut running = BTreeMap::new();
+        for v in docs[0].as_vec().unwrap() {
+            let server = crate::config::server::load_server(v.as_hash().unwrap(), None).unwrap();
+            running.insert(server.name().clone(), server);
+        }
+        running
+    }
+
+    #[test]
+    fn check_servers() {
+        let running = load_running(
+            r#"
+- name: port1
+  type: plain_tcp_port
+  listen: 127.0.0.1:8443
+  server: close
+- name: port2
+  type: plain_tcp_port
+  listen: 127.0.0.1:9443
+  server: close
+- name: port3
+  type: plain_tcp_port
+  listen: 127.0.0.1:10443
+  server: close
+- name: close
+  type: dummy_close
+"#,
+        );
+
+        let docs = YamlLoader::load_from_str(
+            r#"
+server:
+  - name: port1
+    type: plain_tcp_port
+    listen: 127.0.0.1:8444
+    server: close
+  - name: port2
+    type: plain_tcp_port
+    listen_addr: 127.0.0.1:9443
+    server: close
+  - name: close
+    type: dummy_close
+"#,
+        )
+        .unwrap();
+        let path = Path::new("/etc/g3tiles/main.yaml");
+        let report = check_docs(&docs, path, Path::new("/etc/g3tiles"), running);
+        assert!(report.has_error());
+        assert_eq!(
+            report.to_json(),
+            json!({
+                "servers": [
+                    {
+                        "name": "port1",
+                        "type": "PlainTcpPort",
+                        "action": "reload_and_respawn",
+                    },
+                    {
+                        "name": "port2",
+                        "type": "plain_tcp_port",
+                        "action": "error",
+                        "error": "failed to load this PlainTcpPort server: \
+                            failed to parse value of key listen_addr: invalid key listen_addr",
+                        "position": {
+                            "path": "/etc/g3tiles/main.yaml",
+                            "index": 0,
+                        },
+                    },
+                    {
+                        "name": "close",
+                        "type": "DummyClose",
+                        "action": "no_action",
+                    },
+                    {
+                        "name": "port3",
+                        "type": "PlainTcpPort",
+                        "action": "remove",
+                    },
+                ],
+                "errors": [],
+            })
+        );
+    }
+}
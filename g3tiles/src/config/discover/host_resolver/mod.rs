@@ -3,10 +3,13 @@
  * Copyright 2024-2025 ByteDance and/or its affiliates.
  */
 
+use std::time::Duration;
+
 use anyhow::anyhow;
 
 use g3_types::metrics::NodeName;
 use g3_types::net::UpstreamAddr;
+use g3_types::resolve::QueryStrategy;
 use g3_yaml::YamlDocPosition;
 
 use super::{
@@ -18,8 +21,22 @@ mod yaml;
 
 const DISCOVER_CONFIG_TYPE: &str = "HostResolver";
 
+const DEFAULT_RESOLVE_INTERVAL: Duration = Duration::from_secs(60);
+
 pub(crate) struct HostResolverDiscoverInput {
     pub(crate) addr: UpstreamAddr,
+    pub(crate) resolve_strategy: QueryStrategy,
+    pub(crate) resolve_interval: Duration,
+}
+
+impl HostResolverDiscoverInput {
+    fn new(addr: UpstreamAddr) -> Self {
+        HostResolverDiscoverInput {
+            addr,
+            resolve_strategy: QueryStrategy::default(),
+            resolve_interval: DEFAULT_RESOLVE_INTERVAL,
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq)]
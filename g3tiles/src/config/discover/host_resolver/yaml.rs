@@ -3,7 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use anyhow::anyhow;
+use anyhow::{Context, anyhow};
 use yaml_rust::{Yaml, yaml};
 
 use g3_yaml::YamlDocPosition;
@@ -36,7 +36,73 @@ impl HostResolverDiscoverConfig {
         &self,
         input: &Yaml,
     ) -> anyhow::Result<HostResolverDiscoverInput> {
-        let addr = g3_yaml::value::as_upstream_addr(input, 0)?;
-        Ok(HostResolverDiscoverInput { addr })
+        let Yaml::Hash(map) = input else {
+            let addr = g3_yaml::value::as_upstream_addr(input, 0)?;
+            return Ok(HostResolverDiscoverInput::new(addr));
+        };
+
+        let v = g3_yaml::hash_get_required(map, "addr")?;
+        let addr = g3_yaml::value::as_upstream_addr(v, 0)
+            .context("invalid upstream address value for key addr")?;
+        let mut parsed = HostResolverDiscoverInput::new(addr);
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "addr" => Ok(()),
+            "resolve_strategy" => {
+                let strategy = g3_yaml::value::as_resolve_strategy(v)
+                    .context(format!("invalid resolve strategy value for key {k}"))?;
+                parsed.resolve_strategy = strategy.query;
+                Ok(())
+            }
+            "resolve_interval" => {
+                parsed.resolve_interval = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+        if parsed.resolve_interval.is_zero() {
+            return Err(anyhow!("resolve interval should not be zero"));
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use g3_types::resolve::QueryStrategy;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<HostResolverDiscoverInput> {
+        let config = HostResolverDiscoverConfig::new(None);
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        config.parse_yaml_data(&yaml[0])
+    }
+
+    #[test]
+    fn parse_data_ok() {
+        let input = parse("www.example.net:443").unwrap();
+        assert_eq!(input.addr.port(), 443);
+        assert_eq!(input.resolve_strategy, QueryStrategy::Ipv4First);
+        assert_eq!(input.resolve_interval, Duration::from_secs(60));
+
+        let input = parse(
+            "addr: www.example.net:8443\nresolve_strategy: ipv6_only\nresolve_interval: 10s\n",
+        )
+        .unwrap();
+        assert_eq!(input.addr.port(), 8443);
+        assert_eq!(input.resolve_strategy, QueryStrategy::Ipv6Only);
+        assert_eq!(input.resolve_interval, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn parse_data_err() {
+        assert!(parse("resolve_interval: 10s\n").is_err());
+        assert!(parse("addr: www.example.net\n").is_err());
+        assert!(parse("addr: www.example.net:443\nresolve_interval: 0\n").is_err());
+        assert!(parse("addr: www.example.net:443\nresolve_strategy: any\n").is_err());
+        assert!(parse("addr: www.example.net:443\nno_such_key: 1\n").is_err());
     }
 }
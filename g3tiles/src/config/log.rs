@@ -4,10 +4,12 @@
  */
 
 use std::path::Path;
+use std::str::FromStr;
 
 use anyhow::{Context, anyhow};
 use yaml_rust::Yaml;
 
+use g3_daemon::log::task::TaskLogFieldNames;
 use g3_daemon::log::{LogConfig, LogConfigContainer};
 use g3_types::sync::GlobalInit;
 
@@ -47,6 +49,13 @@ pub(crate) fn load(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
                     TASK_DEFAULT_LOG_CONFIG_CONTAINER.with_mut(|l| l.set(config));
                     Ok(())
                 }
+                "task_field_names" => {
+                    let s = g3_yaml::value::as_string(v)?;
+                    let names = TaskLogFieldNames::from_str(&s)
+                        .map_err(|_| anyhow!("invalid task log field names value {s}"))?;
+                    g3_daemon::log::task::set_field_names(names);
+                    Ok(())
+                }
                 _ => Err(anyhow!("invalid key {k}")),
             })?;
         }
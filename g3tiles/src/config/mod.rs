@@ -5,7 +5,7 @@
 
 use std::path::Path;
 
-use anyhow::anyhow;
+use anyhow::{Context, anyhow};
 use yaml_rust::{Yaml, yaml};
 
 pub(crate) mod log;
@@ -14,6 +14,9 @@ pub(crate) mod backend;
 pub(crate) mod discover;
 pub(crate) mod server;
 
+mod check;
+pub(crate) use check::check_file;
+
 pub fn load() -> anyhow::Result<&'static Path> {
     let config_file =
         g3_daemon::opts::config_file().ok_or_else(|| anyhow!("no config file set"))?;
@@ -27,7 +30,42 @@ pub fn load() -> anyhow::Result<&'static Path> {
     Ok(config_file)
 }
 
+/// Check the config file and print the report of all servers, without loading it
+pub fn dry_run() -> anyhow::Result<()> {
+    let config_file =
+        g3_daemon::opts::config_file().ok_or_else(|| anyhow!("no config file set"))?;
+
+    let report = check_file(config_file);
+    let content = serde_json::to_string_pretty(&report.to_json())
+        .context("failed to encode the check report")?;
+    println!("{content}");
+    if report.has_error() {
+        Err(anyhow!(
+            "errors found in config file {}",
+            config_file.display()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Get the json schema of the config keys that have been registered
+pub(crate) fn schema() -> serde_json::Value {
+    serde_json::json!({
+        "server": server::schema(),
+    })
+}
+
+/// Print the json schema of the config keys, without loading any config file
+pub fn dump_schema() -> anyhow::Result<()> {
+    let content =
+        serde_json::to_string_pretty(&schema()).context("failed to encode the config schema")?;
+    println!("{content}");
+    Ok(())
+}
+
 fn clear_all() {
+    g3_daemon::tls::clear_recorded_certs();
     server::clear();
     discover::clear();
     backend::clear();
@@ -55,7 +93,7 @@ fn reload_doc(map: &yaml::Hash) -> anyhow::Result<()> {
     let conf_dir =
         g3_daemon::opts::config_dir().ok_or_else(|| anyhow!("no valid config dir has been set"))?;
     g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
-        "runtime" | "worker" | "log" | "stat" | "controller" => Ok(()),
+        "runtime" | "worker" | "log" | "stat" | "controller" | "client_ban" => Ok(()),
         "server" => server::load_all(v, conf_dir),
         "discover" => discover::load_all(v, conf_dir),
         "backend" => backend::load_all(v, conf_dir),
@@ -73,6 +111,7 @@ fn load_doc(map: &yaml::Hash) -> anyhow::Result<()> {
         "log" => log::load(v, conf_dir),
         "stat" => g3_daemon::stat::config::load(v, crate::build::PKG_NAME),
         "controller" => g3_daemon::control::config::load(v),
+        "client_ban" => g3_daemon::ban::config::load(v),
         "server" => server::load_all(v, conf_dir),
         "discover" => discover::load_all(v, conf_dir),
         "backend" => backend::load_all(v, conf_dir),
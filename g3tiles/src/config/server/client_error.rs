@@ -0,0 +1,158 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::str::FromStr;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+const CLASS_COUNT: usize = 4;
+
+/// The class of task failures whose client visible behavior can be configured
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ClientErrorClass {
+    UpstreamConnectRefused,
+    UpstreamConnectTimeout,
+    IdleKilled,
+    LifetimeExpired,
+}
+
+impl FromStr for ClientErrorClass {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upstream_connect_refused" | "connect_refused" => {
+                Ok(ClientErrorClass::UpstreamConnectRefused)
+            }
+            "upstream_connect_timeout" | "connect_timeout" => {
+                Ok(ClientErrorClass::UpstreamConnectTimeout)
+            }
+            "idle_killed" | "idle" => Ok(ClientErrorClass::IdleKilled),
+            "lifetime_expired" => Ok(ClientErrorClass::LifetimeExpired),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What the client will see when the task failed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ClientErrorAction {
+    /// close the connection without sending anything
+    Close,
+    /// reset the connection
+    Reset,
+    /// send the TLS close_notify alert and then close the connection
+    TlsAlert,
+}
+
+impl ClientErrorAction {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::String(s) = v else {
+            return Err(anyhow!(
+                "yaml value type for client error action should be 'string'"
+            ));
+        };
+        match g3_yaml::key::normalize(s).as_str() {
+            "close" => Ok(ClientErrorAction::Close),
+            "reset" | "rst" => Ok(ClientErrorAction::Reset),
+            "tls_alert" | "close_notify" => Ok(ClientErrorAction::TlsAlert),
+            _ => Err(anyhow!("invalid client error action {s}")),
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ClientErrorAction::Close => "close",
+            ClientErrorAction::Reset => "reset",
+            ClientErrorAction::TlsAlert => "tls_alert",
+        }
+    }
+}
+
+/// The client error actions set for each class of failure
+///
+/// The server default behavior will be used for the classes not set here.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ClientErrorActionMap {
+    actions: [Option<ClientErrorAction>; CLASS_COUNT],
+}
+
+impl ClientErrorActionMap {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for client error actions should be 'map'"
+            ));
+        };
+
+        let mut actions = ClientErrorActionMap::default();
+        g3_yaml::foreach_kv(map, |k, v| {
+            let class = ClientErrorClass::from_str(&g3_yaml::key::normalize(k))
+                .map_err(|_| anyhow!("invalid client error class {k}"))?;
+            let action = ClientErrorAction::parse_yaml(v)
+                .context(format!("invalid client error action value for key {k}"))?;
+            actions.set(class, action);
+            Ok(())
+        })?;
+        Ok(actions)
+    }
+
+    pub(crate) fn set(&mut self, class: ClientErrorClass, action: ClientErrorAction) {
+        self.actions[class as usize] = Some(action);
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, class: ClientErrorClass) -> Option<ClientErrorAction> {
+        self.actions[class as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<ClientErrorActionMap> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        ClientErrorActionMap::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_ok() {
+        let map = parse(
+            "upstream_connect_refused: reset\nconnect_timeout: tls_alert\n\
+             idle-killed: close\n",
+        )
+        .unwrap();
+        assert_eq!(
+            map.get(ClientErrorClass::UpstreamConnectRefused),
+            Some(ClientErrorAction::Reset)
+        );
+        assert_eq!(
+            map.get(ClientErrorClass::UpstreamConnectTimeout),
+            Some(ClientErrorAction::TlsAlert)
+        );
+        assert_eq!(
+            map.get(ClientErrorClass::IdleKilled),
+            Some(ClientErrorAction::Close)
+        );
+        assert!(map.get(ClientErrorClass::LifetimeExpired).is_none());
+
+        let map = parse("lifetime_expired: close_notify\n").unwrap();
+        assert_eq!(
+            map.get(ClientErrorClass::LifetimeExpired).unwrap().as_str(),
+            "tls_alert"
+        );
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("reset").is_err());
+        assert!(parse("ingress_denied: close\n").is_err());
+        assert!(parse("idle_killed: drop\n").is_err());
+        assert!(parse("idle_killed: {socks_reply: 2}\n").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use ip_network::IpNetwork;
+use yaml_rust::Yaml;
+
+use super::IDLE_CHECK_MAXIMUM_DURATION;
+
+/// Alternative task idle check values for tasks from a client network
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct TaskIdleOverride {
+    network: IpNetwork,
+    check_duration: Option<Duration>,
+    max_count: Option<usize>,
+}
+
+impl TaskIdleOverride {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for 'task idle override' should be 'map'"
+            ));
+        };
+
+        let mut network = None;
+        let mut check_duration = None;
+        let mut max_count = None;
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "net" | "network" | "match_net" => {
+                let net = g3_yaml::value::as_ip_network(v)
+                    .context(format!("invalid ip network value for key {k}"))?;
+                network = Some(net);
+                Ok(())
+            }
+            "task_idle_check_duration" | "check_duration" => {
+                let duration = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                check_duration = Some(duration.min(IDLE_CHECK_MAXIMUM_DURATION));
+                Ok(())
+            }
+            "task_idle_max_count" | "max_count" => {
+                let count = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                max_count = Some(count);
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        let Some(network) = network else {
+            return Err(anyhow!("no net is set"));
+        };
+        if check_duration.is_none() && max_count.is_none() {
+            return Err(anyhow!(
+                "neither task_idle_check_duration nor task_idle_max_count is set"
+            ));
+        }
+        Ok(TaskIdleOverride {
+            network,
+            check_duration,
+            max_count,
+        })
+    }
+
+    /// Get the name to be recorded in task logs
+    pub(crate) fn name(&self) -> String {
+        format!("net:{}", self.network)
+    }
+
+    pub(crate) fn check_duration(&self, default: Duration) -> Duration {
+        self.check_duration.unwrap_or(default)
+    }
+
+    pub(crate) fn max_count(&self, default: usize) -> usize {
+        self.max_count.unwrap_or(default)
+    }
+}
+
+/// The task idle overrides of a server, which will be resolved at task start.
+///
+/// The one with the longest network prefix wins if more than one match the client ip.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct TaskIdleOverrides {
+    overrides: Vec<TaskIdleOverride>,
+}
+
+impl TaskIdleOverrides {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let mut overrides = Vec::new();
+        match v {
+            Yaml::Array(seq) => {
+                for (i, v) in seq.iter().enumerate() {
+                    let o = TaskIdleOverride::parse_yaml(v)
+                        .context(format!("invalid task idle override value for #{i}"))?;
+                    overrides.push(o);
+                }
+            }
+            Yaml::Hash(_) => {
+                let o = TaskIdleOverride::parse_yaml(v)?;
+                overrides.push(o);
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for 'task idle overrides' should be 'seq' or 'map'"
+                ));
+            }
+        }
+        Ok(TaskIdleOverrides { overrides })
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &TaskIdleOverride> {
+        self.overrides.iter()
+    }
+
+    /// Get the index of the matched override
+    pub(crate) fn select(&self, client_ip: IpAddr) -> Option<usize> {
+        let mut matched: Option<(usize, u8)> = None;
+        for (i, o) in self.overrides.iter().enumerate() {
+            if !o.network.contains(client_ip) {
+                continue;
+            }
+            let prefix = o.network.netmask();
+            if matched.map(|(_, p)| prefix > p).unwrap_or(true) {
+                matched = Some((i, prefix));
+            }
+        }
+        matched.map(|(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use yaml_rust::YamlLoader;
+
+    fn load_yaml(s: &str) -> Yaml {
+        YamlLoader::load_from_str(s).unwrap().pop().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let overrides = TaskIdleOverrides::parse_yaml(&load_yaml(
+            r#"
+- net: 10.0.0.0/8
+  task_idle_check_duration: 1h
+- net: 192.168.0.0/16
+  max_count: 100
+"#,
+        ))
+        .unwrap();
+        let all: Vec<_> = overrides.iter().collect();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].name(), "net:10.0.0.0/8");
+        assert_eq!(
+            all[0].check_duration(Duration::from_secs(60)),
+            IDLE_CHECK_MAXIMUM_DURATION
+        );
+        assert_eq!(all[0].max_count(5), 5);
+        assert_eq!(all[1].name(), "net:192.168.0.0/16");
+        assert_eq!(all[1].max_count(5), 100);
+
+        assert!(TaskIdleOverrides::parse_yaml(&load_yaml("net: 10.0.0.0/8")).is_err());
+        assert!(TaskIdleOverrides::parse_yaml(&load_yaml("max_count: 1")).is_err());
+        assert!(
+            TaskIdleOverrides::parse_yaml(&load_yaml("{user_group: long, max_count: 1}")).is_err()
+        );
+    }
+
+    #[test]
+    fn select() {
+        let overrides = TaskIdleOverrides::parse_yaml(&load_yaml(
+            r#"
+- net: 10.0.0.0/8
+  max_count: 1
+- net: 10.1.0.0/16
+  max_count: 2
+"#,
+        ))
+        .unwrap();
+
+        let ip = IpAddr::from_str("10.1.0.1").unwrap();
+        assert_eq!(overrides.select(ip), Some(1));
+        let ip = IpAddr::from_str("10.2.0.1").unwrap();
+        assert_eq!(overrides.select(ip), Some(0));
+        let ip = IpAddr::from_str("192.168.0.1").unwrap();
+        assert_eq!(overrides.select(ip), None);
+    }
+}
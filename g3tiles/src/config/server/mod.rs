@@ -15,6 +15,7 @@ use yaml_rust::{Yaml, yaml};
 use g3_daemon::config::TopoMap;
 use g3_macros::AnyConfig;
 use g3_types::metrics::NodeName;
+use g3_types::net::TlsCertStatus;
 use g3_yaml::{HybridParser, YamlDocPosition};
 
 pub(crate) mod dummy_close;
@@ -26,12 +27,26 @@ pub(crate) mod keyless_proxy;
 pub(crate) mod openssl_proxy;
 pub(crate) mod rustls_proxy;
 
+mod client_error;
+pub(crate) use client_error::{ClientErrorAction, ClientErrorActionMap, ClientErrorClass};
+
+mod idle_override;
+pub(crate) use idle_override::TaskIdleOverrides;
+
 mod registry;
 
 pub(crate) use registry::clear;
 
-const CONFIG_KEY_SERVER_TYPE: &str = "type";
-const CONFIG_KEY_SERVER_NAME: &str = "name";
+/// The key schema of the server config types that use a key registry
+const SERVER_CONFIG_SCHEMAS: &[g3_yaml::schema::YamlMapSchema] =
+    &[openssl_proxy::SERVER_CONFIG_SCHEMA];
+
+pub(crate) fn schema() -> Vec<serde_json::Value> {
+    SERVER_CONFIG_SCHEMAS.iter().map(|s| s.to_json()).collect()
+}
+
+pub(super) const CONFIG_KEY_SERVER_TYPE: &str = "type";
+pub(super) const CONFIG_KEY_SERVER_NAME: &str = "name";
 
 const IDLE_CHECK_MAXIMUM_DURATION: Duration = Duration::from_secs(1800);
 const IDLE_CHECK_DEFAULT_DURATION: Duration = Duration::from_secs(60);
@@ -66,6 +81,11 @@ pub(crate) trait ServerConfig {
             crate::log::task::get_logger(self.r#type(), self.name())
         }
     }
+
+    /// get the status of all the certificates configured in this server
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        Ok(Vec::new())
+    }
 }
 
 #[derive(Clone, Debug, AnyConfig)]
@@ -74,6 +94,7 @@ pub(crate) trait ServerConfig {
 #[def_fn(r#type, &'static str)]
 #[def_fn(dependent_server, Option<BTreeSet<NodeName>>)]
 #[def_fn(diff_action, &Self, ServerConfigDiffAction)]
+#[def_fn(cert_status, anyhow::Result<Vec<TlsCertStatus>>)]
 pub(crate) enum AnyServerConfig {
     DummyClose(dummy_close::DummyCloseServerConfig),
     PlainTcpPort(plain_tcp_port::PlainTcpPortConfig),
@@ -88,6 +109,7 @@ pub(crate) fn load_all(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
     let parser = HybridParser::new(conf_dir, g3_daemon::opts::config_file_extension());
     parser.foreach_map(v, |map, position| {
         let server = load_server(map, position)?;
+        check_certs(&server)?;
         if let Some(old_server) = registry::add(server) {
             Err(anyhow!(
                 "server with name {} already exists",
@@ -105,6 +127,7 @@ pub(crate) fn load_at_position(position: &YamlDocPosition) -> anyhow::Result<Any
     let doc = g3_yaml::load_doc(position)?;
     if let Yaml::Hash(map) = doc {
         let server = load_server(&map, Some(position.clone()))?;
+        check_certs(&server)?;
         let old_server = registry::add(server.clone());
         if let Err(e) = build_topology_map() {
             // rollback
@@ -123,7 +146,7 @@ pub(crate) fn load_at_position(position: &YamlDocPosition) -> anyhow::Result<Any
     }
 }
 
-fn load_server(
+pub(super) fn load_server(
     map: &yaml::Hash,
     position: Option<YamlDocPosition>,
 ) -> anyhow::Result<AnyServerConfig> {
@@ -164,6 +187,15 @@ fn load_server(
     }
 }
 
+fn check_certs(server: &AnyServerConfig) -> anyhow::Result<()> {
+    let certs = server
+        .cert_status()
+        .context(format!("invalid certificate in server {}", server.name()))?;
+    g3_daemon::tls::check_and_record_certs(format!("server/{}", server.name()), certs).context(
+        format!("certificate check failed for server {}", server.name()),
+    )
+}
+
 fn build_topology_map() -> anyhow::Result<TopoMap> {
     let mut topo_map = TopoMap::default();
 
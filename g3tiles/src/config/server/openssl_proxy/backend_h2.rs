@@ -0,0 +1,176 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+const MIN_WINDOW_SIZE: u32 = 65535;
+const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+
+/// The protocol used to relay the decrypted traffic to the backend
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum OpensslBackendProtocol {
+    /// relay the decrypted bytes as is
+    #[default]
+    Tcp,
+    /// map the client h2 streams to streams on shared backend h2 connections,
+    /// if h2 is negotiated with the client
+    H2,
+}
+
+impl FromStr for OpensslBackendProtocol {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tcp" | "raw" => Ok(OpensslBackendProtocol::Tcp),
+            "h2" | "http2" => Ok(OpensslBackendProtocol::H2),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The h2 settings used if the backend protocol is h2
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct OpensslBackendH2Config {
+    /// the max concurrent streams advertised to the client
+    pub(crate) max_concurrent_streams: u32,
+    /// the max number of client streams to be mapped onto a single backend connection
+    pub(crate) max_streams_per_connection: usize,
+    stream_window_size: u32,
+    connection_window_size: u32,
+    /// the timeout for the h2 handshake with the client or the backend
+    pub(crate) handshake_timeout: Duration,
+}
+
+impl Default for OpensslBackendH2Config {
+    fn default() -> Self {
+        OpensslBackendH2Config {
+            max_concurrent_streams: 128,
+            max_streams_per_connection: 100,
+            stream_window_size: 1024 * 1024,         // 1MiB
+            connection_window_size: 2 * 1024 * 1024, // 2MiB
+            handshake_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl OpensslBackendH2Config {
+    #[inline]
+    pub(crate) fn stream_window_size(&self) -> u32 {
+        self.stream_window_size
+    }
+
+    #[inline]
+    pub(crate) fn connection_window_size(&self) -> u32 {
+        self.connection_window_size
+    }
+
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for backend h2 config should be 'map'"
+            ));
+        };
+
+        let mut config = OpensslBackendH2Config::default();
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "max_concurrent_streams" => {
+                config.max_concurrent_streams =
+                    g3_yaml::value::as_u32(v).context(format!("invalid u32 value for key {k}"))?;
+                Ok(())
+            }
+            "max_streams_per_connection" | "backend_stream_limit" => {
+                config.max_streams_per_connection = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                Ok(())
+            }
+            "stream_window_size" => {
+                let size = g3_yaml::humanize::as_u32(v)
+                    .context(format!("invalid humanize u32 value for key {k}"))?;
+                config.stream_window_size = size.clamp(MIN_WINDOW_SIZE, MAX_WINDOW_SIZE);
+                Ok(())
+            }
+            "connection_window_size" => {
+                let size = g3_yaml::humanize::as_u32(v)
+                    .context(format!("invalid humanize u32 value for key {k}"))?;
+                config.connection_window_size = size.clamp(MIN_WINDOW_SIZE, MAX_WINDOW_SIZE);
+                Ok(())
+            }
+            "handshake_timeout" => {
+                config.handshake_timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        config.check()?;
+        Ok(config)
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.max_concurrent_streams == 0 {
+            return Err(anyhow!("max concurrent streams should not be zero"));
+        }
+        if self.max_streams_per_connection == 0 {
+            return Err(anyhow!("max streams per connection should not be zero"));
+        }
+        if self.handshake_timeout.is_zero() {
+            return Err(anyhow!("handshake timeout should not be zero"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<OpensslBackendH2Config> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        OpensslBackendH2Config::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_protocol() {
+        assert_eq!(
+            OpensslBackendProtocol::from_str("TCP").unwrap(),
+            OpensslBackendProtocol::Tcp
+        );
+        assert_eq!(
+            OpensslBackendProtocol::from_str("h2").unwrap(),
+            OpensslBackendProtocol::H2
+        );
+        assert!(OpensslBackendProtocol::from_str("h3").is_err());
+    }
+
+    #[test]
+    fn parse_ok() {
+        let config = parse(
+            "max_concurrent_streams: 16\nmax_streams_per_connection: 8\n\
+             stream_window_size: 1000\nconnection_window_size: 4MiB\nhandshake_timeout: 2s\n",
+        )
+        .unwrap();
+        assert_eq!(config.max_concurrent_streams, 16);
+        assert_eq!(config.max_streams_per_connection, 8);
+        assert_eq!(config.stream_window_size(), MIN_WINDOW_SIZE);
+        assert_eq!(config.connection_window_size(), 4 * 1024 * 1024);
+        assert_eq!(config.handshake_timeout, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("true").is_err());
+        assert!(parse("max_concurrent_streams: 0\n").is_err());
+        assert!(parse("max_streams_per_connection: 0\n").is_err());
+        assert!(parse("handshake_timeout: 0\n").is_err());
+        assert!(parse("no_such_key: 1\n").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+const DEFAULT_MAX_IDLE: usize = 32;
+const DEFAULT_MAX_LIFETIME: Duration = Duration::from_secs(60);
+
+/// The backend connection pool config of an openssl proxy host
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct OpensslBackendPoolConfig {
+    /// the min number of idle connections to keep for each backend
+    pub(crate) min_idle: usize,
+    /// the max number of idle connections to keep for each backend
+    pub(crate) max_idle: usize,
+    /// connections older than this will be closed instead of being reused
+    pub(crate) max_lifetime: Duration,
+    /// the time to wait for an idle connection before connecting a new one
+    pub(crate) wait_timeout: Duration,
+}
+
+impl Default for OpensslBackendPoolConfig {
+    fn default() -> Self {
+        OpensslBackendPoolConfig {
+            min_idle: 0,
+            max_idle: DEFAULT_MAX_IDLE,
+            max_lifetime: DEFAULT_MAX_LIFETIME,
+            wait_timeout: Duration::ZERO,
+        }
+    }
+}
+
+impl OpensslBackendPoolConfig {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Option<Self>> {
+        let mut config = OpensslBackendPoolConfig::default();
+        match v {
+            Yaml::Boolean(true) => return Ok(Some(config)),
+            Yaml::Boolean(false) => return Ok(None),
+            Yaml::Hash(map) => {
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "min_idle" | "min_idle_connections" => {
+                        config.min_idle = g3_yaml::value::as_usize(v)
+                            .context(format!("invalid usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "max_idle" | "max_idle_connections" => {
+                        config.max_idle = g3_yaml::value::as_usize(v)
+                            .context(format!("invalid usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "max_lifetime" => {
+                        config.max_lifetime = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "wait_timeout" | "wait_queue_timeout" => {
+                        config.wait_timeout = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for backend pool config should be 'bool' or 'map'"
+                ));
+            }
+        }
+
+        config.check()?;
+        Ok(Some(config))
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.max_idle == 0 {
+            return Err(anyhow!("max idle connections should not be zero"));
+        }
+        if self.min_idle > self.max_idle {
+            return Err(anyhow!(
+                "min idle connections should not be larger than max idle connections"
+            ));
+        }
+        if self.max_lifetime.is_zero() {
+            return Err(anyhow!("max lifetime should not be zero"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<Option<OpensslBackendPoolConfig>> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        OpensslBackendPoolConfig::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_ok() {
+        assert_eq!(
+            parse("true").unwrap(),
+            Some(OpensslBackendPoolConfig::default())
+        );
+        assert!(parse("false").unwrap().is_none());
+
+        let config = parse("min_idle: 2\nmax_idle: 8\nmax_lifetime: 30s\nwait_timeout: 100ms\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.min_idle, 2);
+        assert_eq!(config.max_idle, 8);
+        assert_eq!(config.max_lifetime, Duration::from_secs(30));
+        assert_eq!(config.wait_timeout, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("1").is_err());
+        assert!(parse("max_idle: 0\n").is_err());
+        assert!(parse("min_idle: 4\nmax_idle: 2\n").is_err());
+        assert!(parse("max_lifetime: 0\n").is_err());
+        assert!(parse("no_such_key: 1\n").is_err());
+    }
+}
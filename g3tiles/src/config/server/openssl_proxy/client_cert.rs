@@ -0,0 +1,323 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::IpAddr;
+
+use anyhow::{Context, anyhow};
+use openssl::asn1::Asn1Object;
+use openssl::nid::Nid;
+use openssl::x509::{X509Ref, X509StoreContextRef, X509VerifyResult};
+use yaml_rust::Yaml;
+
+use g3_types::metrics::NodeName;
+
+/// The TLS alert to send when no client cert route rule is matched.
+///
+/// OpenSSL has no public API to send a chosen alert, so the alert is selected by
+/// setting a X509 verify error, which will be mapped to the alert description by libssl.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum OpensslClientCertAlert {
+    #[default]
+    BadCertificate,
+    UnsupportedCertificate,
+    CertificateRevoked,
+    UnknownCa,
+    HandshakeFailure,
+}
+
+impl OpensslClientCertAlert {
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        match g3_yaml::key::normalize(s).as_str() {
+            "bad_certificate" => Ok(OpensslClientCertAlert::BadCertificate),
+            "unsupported_certificate" => Ok(OpensslClientCertAlert::UnsupportedCertificate),
+            "certificate_revoked" => Ok(OpensslClientCertAlert::CertificateRevoked),
+            "unknown_ca" => Ok(OpensslClientCertAlert::UnknownCa),
+            "handshake_failure" => Ok(OpensslClientCertAlert::HandshakeFailure),
+            _ => Err(anyhow!("unsupported tls alert {s}")),
+        }
+    }
+
+    fn verify_result(&self) -> X509VerifyResult {
+        let code = match self {
+            // X509_V_ERR_CERT_REJECTED
+            OpensslClientCertAlert::BadCertificate => 28,
+            // X509_V_ERR_INVALID_PURPOSE
+            OpensslClientCertAlert::UnsupportedCertificate => 26,
+            // X509_V_ERR_CERT_REVOKED
+            OpensslClientCertAlert::CertificateRevoked => 23,
+            // X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT
+            OpensslClientCertAlert::UnknownCa => 2,
+            OpensslClientCertAlert::HandshakeFailure => {
+                return X509VerifyResult::APPLICATION_VERIFICATION;
+            }
+        };
+        unsafe { X509VerifyResult::from_raw(code) }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SubjectAttributeMatch {
+    nid: i32,
+    values: Vec<String>,
+}
+
+impl SubjectAttributeMatch {
+    fn parse(attribute: &str, value: &Yaml) -> anyhow::Result<Self> {
+        let obj = Asn1Object::from_str(attribute)
+            .map_err(|e| anyhow!("unknown subject attribute {attribute}: {e}"))?;
+        let nid = obj.nid();
+        if nid == Nid::UNDEF {
+            return Err(anyhow!("unsupported subject attribute {attribute}"));
+        }
+        let values = g3_yaml::value::as_list(value, g3_yaml::value::as_string)?;
+        Ok(SubjectAttributeMatch {
+            nid: nid.as_raw(),
+            values,
+        })
+    }
+
+    fn matches(&self, cert: &X509Ref) -> bool {
+        cert.subject_name()
+            .entries_by_nid(Nid::from_raw(self.nid))
+            .any(|entry| {
+                // entries that can not be decoded as utf-8 will never match
+                let Ok(data) = entry.data().as_utf8() else {
+                    return false;
+                };
+                self.values.iter().any(|v| v.as_str() == &*data)
+            })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct OpensslClientCertRouteRule {
+    pub(crate) name: String,
+    subject: Vec<SubjectAttributeMatch>,
+    san_dns: Vec<String>,
+    san_email: Vec<String>,
+    san_uri: Vec<String>,
+    san_ip: Vec<IpAddr>,
+    pub(crate) backend: NodeName,
+}
+
+impl OpensslClientCertRouteRule {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for client cert route rule should be 'map'"
+            ));
+        };
+
+        let mut rule = OpensslClientCertRouteRule::default();
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "name" => {
+                rule.name = g3_yaml::value::as_string(v)?;
+                Ok(())
+            }
+            "subject" => {
+                let Yaml::Hash(map) = v else {
+                    return Err(anyhow!("yaml value type for key {k} should be 'map'"));
+                };
+                // the attribute names are case sensitive, so don't normalize them
+                for (attr, value) in map.iter() {
+                    let Yaml::String(attr) = attr else {
+                        return Err(anyhow!("invalid subject attribute name in key {k}"));
+                    };
+                    let m = SubjectAttributeMatch::parse(attr, value)
+                        .context(format!("invalid subject attribute {attr} in key {k}"))?;
+                    rule.subject.push(m);
+                }
+                Ok(())
+            }
+            "san_dns" | "san_dns_name" => {
+                rule.san_dns = g3_yaml::value::as_list(v, g3_yaml::value::as_string)
+                    .context(format!("invalid string list value for key {k}"))?;
+                Ok(())
+            }
+            "san_email" => {
+                rule.san_email = g3_yaml::value::as_list(v, g3_yaml::value::as_string)
+                    .context(format!("invalid string list value for key {k}"))?;
+                Ok(())
+            }
+            "san_uri" => {
+                rule.san_uri = g3_yaml::value::as_list(v, g3_yaml::value::as_string)
+                    .context(format!("invalid string list value for key {k}"))?;
+                Ok(())
+            }
+            "san_ip" | "san_ip_address" => {
+                rule.san_ip = g3_yaml::value::as_list(v, g3_yaml::value::as_ipaddr)
+                    .context(format!("invalid ip address list value for key {k}"))?;
+                Ok(())
+            }
+            "backend" => {
+                rule.backend = g3_yaml::value::as_metric_node_name(v)
+                    .context(format!("invalid metric node name value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        if rule.name.is_empty() {
+            return Err(anyhow!("no name set"));
+        }
+        if rule.backend.is_empty() {
+            return Err(anyhow!("no backend set"));
+        }
+        if rule.subject.is_empty()
+            && rule.san_dns.is_empty()
+            && rule.san_email.is_empty()
+            && rule.san_uri.is_empty()
+            && rule.san_ip.is_empty()
+        {
+            return Err(anyhow!("no match condition set"));
+        }
+        Ok(rule)
+    }
+
+    /// All the configured conditions should be matched,
+    /// and each condition is matched if any of its values is found in the certificate
+    fn matches(&self, cert: &X509Ref) -> bool {
+        if !self.subject.iter().all(|m| m.matches(cert)) {
+            return false;
+        }
+        if self.san_dns.is_empty()
+            && self.san_email.is_empty()
+            && self.san_uri.is_empty()
+            && self.san_ip.is_empty()
+        {
+            return true;
+        }
+
+        let Some(names) = cert.subject_alt_names() else {
+            return false;
+        };
+        if !self.san_dns.is_empty()
+            && !names.iter().any(|n| {
+                n.dnsname()
+                    .map(|d| self.san_dns.iter().any(|v| v.eq_ignore_ascii_case(d)))
+                    .unwrap_or(false)
+            })
+        {
+            return false;
+        }
+        if !self.san_email.is_empty()
+            && !names.iter().any(|n| {
+                n.email()
+                    .map(|e| self.san_email.iter().any(|v| v.eq_ignore_ascii_case(e)))
+                    .unwrap_or(false)
+            })
+        {
+            return false;
+        }
+        if !self.san_uri.is_empty()
+            && !names.iter().any(|n| {
+                n.uri()
+                    .map(|u| self.san_uri.iter().any(|v| v == u))
+                    .unwrap_or(false)
+            })
+        {
+            return false;
+        }
+        if !self.san_ip.is_empty()
+            && !names.iter().any(|n| {
+                n.ipaddress()
+                    .and_then(|b| match b.len() {
+                        4 => <[u8; 4]>::try_from(b).ok().map(IpAddr::from),
+                        16 => <[u8; 16]>::try_from(b).ok().map(IpAddr::from),
+                        _ => None,
+                    })
+                    .map(|ip| self.san_ip.contains(&ip))
+                    .unwrap_or(false)
+            })
+        {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct OpensslClientCertRouterConfig {
+    pub(crate) rules: Vec<OpensslClientCertRouteRule>,
+    pub(crate) require_match: bool,
+    pub(crate) alert: OpensslClientCertAlert,
+}
+
+impl OpensslClientCertRouterConfig {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let mut config = OpensslClientCertRouterConfig::default();
+        match v {
+            Yaml::Hash(map) => {
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "rules" => {
+                        config.rules =
+                            g3_yaml::value::as_list(v, OpensslClientCertRouteRule::parse_yaml)
+                                .context(format!("invalid route rule list value for key {k}"))?;
+                        Ok(())
+                    }
+                    "require_match" => {
+                        config.require_match = g3_yaml::value::as_bool(v)
+                            .context(format!("invalid bool value for key {k}"))?;
+                        Ok(())
+                    }
+                    "alert" | "reject_alert" => {
+                        let s = g3_yaml::value::as_string(v)
+                            .context(format!("invalid string value for key {k}"))?;
+                        config.alert = OpensslClientCertAlert::parse(&s)
+                            .context(format!("invalid tls alert value for key {k}"))?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+            }
+            Yaml::Array(_) => {
+                config.rules = g3_yaml::value::as_list(v, OpensslClientCertRouteRule::parse_yaml)?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for client cert router should be 'map' or 'seq'"
+                ));
+            }
+        }
+
+        if config.rules.is_empty() {
+            return Err(anyhow!("no route rule set"));
+        }
+        for (i, rule) in config.rules.iter().enumerate() {
+            if config.rules[..i].iter().any(|r| r.name == rule.name) {
+                return Err(anyhow!("duplicated route rule name {}", rule.name));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Get the index of the first matched rule
+    pub(crate) fn select(&self, cert: &X509Ref) -> Option<usize> {
+        self.rules.iter().position(|r| r.matches(cert))
+    }
+
+    pub(crate) fn contains_backend(&self, name: &NodeName) -> bool {
+        self.rules.iter().any(|r| r.backend.eq(name))
+    }
+
+    /// Verify callback to reject the peer certificate during handshake when `require_match` is set
+    pub(crate) fn verify_peer_cert(
+        &self,
+        preverify_ok: bool,
+        ctx: &mut X509StoreContextRef,
+    ) -> bool {
+        if !preverify_ok || ctx.error_depth() != 0 {
+            return preverify_ok;
+        }
+        let matched = ctx
+            .current_cert()
+            .map(|cert| self.select(cert).is_some())
+            .unwrap_or(false);
+        if !matched {
+            ctx.set_error(self.alert.verify_result());
+        }
+        matched
+    }
+}
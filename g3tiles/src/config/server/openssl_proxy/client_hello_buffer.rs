@@ -0,0 +1,139 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+const DEFAULT_POOL_SIZE: NonZeroUsize = NonZeroUsize::new(1024).unwrap();
+const DEFAULT_BUFFER_SIZE: usize = 16384;
+const MIN_BUFFER_SIZE: usize = 1024;
+
+/// What to do with the new connections if all the pooled buffers are in use
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum OpensslClientHelloBufferOverflow {
+    /// allocate a transient buffer which will be freed after use
+    #[default]
+    Allocate,
+    /// wait for a buffer to be returned to the pool
+    Wait,
+    /// reject the connection at once
+    Reject,
+}
+
+impl FromStr for OpensslClientHelloBufferOverflow {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "allocate" | "alloc" => Ok(OpensslClientHelloBufferOverflow::Allocate),
+            "wait" => Ok(OpensslClientHelloBufferOverflow::Wait),
+            "reject" => Ok(OpensslClientHelloBufferOverflow::Reject),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The pool of reusable buffers for receiving the ClientHello message
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct OpensslClientHelloBufferPoolConfig {
+    /// the max number of buffers in the pool
+    pub(crate) pool_size: NonZeroUsize,
+    /// the capacity of each buffer
+    pub(crate) buffer_size: usize,
+    pub(crate) overflow: OpensslClientHelloBufferOverflow,
+}
+
+impl Default for OpensslClientHelloBufferPoolConfig {
+    fn default() -> Self {
+        OpensslClientHelloBufferPoolConfig {
+            pool_size: DEFAULT_POOL_SIZE,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            overflow: OpensslClientHelloBufferOverflow::default(),
+        }
+    }
+}
+
+impl OpensslClientHelloBufferPoolConfig {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Option<Self>> {
+        let mut config = OpensslClientHelloBufferPoolConfig::default();
+        match v {
+            Yaml::Boolean(true) => return Ok(Some(config)),
+            Yaml::Boolean(false) => return Ok(None),
+            Yaml::Hash(map) => {
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "pool_size" | "count" => {
+                        config.pool_size = g3_yaml::value::as_nonzero_usize(v)
+                            .context(format!("invalid nonzero usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "buffer_size" | "size" => {
+                        config.buffer_size = g3_yaml::humanize::as_usize(v)
+                            .context(format!("invalid humanize usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "overflow" | "overflow_policy" => {
+                        let s = g3_yaml::value::as_string(v)?;
+                        config.overflow = OpensslClientHelloBufferOverflow::from_str(&s)
+                            .map_err(|_| anyhow!("invalid overflow policy value for key {k}"))?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for client hello buffer pool config should be 'bool' or 'map'"
+                ));
+            }
+        }
+
+        if config.buffer_size < MIN_BUFFER_SIZE {
+            return Err(anyhow!(
+                "buffer size should not be less than {MIN_BUFFER_SIZE}"
+            ));
+        }
+        Ok(Some(config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<Option<OpensslClientHelloBufferPoolConfig>> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        OpensslClientHelloBufferPoolConfig::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_ok() {
+        let config = parse("true").unwrap().unwrap();
+        assert_eq!(config, OpensslClientHelloBufferPoolConfig::default());
+        assert!(parse("false").unwrap().is_none());
+
+        let config = parse("pool_size: 16\nbuffer_size: 64KiB\noverflow: wait\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.pool_size.get(), 16);
+        assert_eq!(config.buffer_size, 65536);
+        assert_eq!(config.overflow, OpensslClientHelloBufferOverflow::Wait);
+
+        let config = parse("overflow: Reject\n").unwrap().unwrap();
+        assert_eq!(config.overflow, OpensslClientHelloBufferOverflow::Reject);
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("1").is_err());
+        assert!(parse("pool_size: 0\n").is_err());
+        assert!(parse("buffer_size: 512\n").is_err());
+        assert!(parse("overflow: drop\n").is_err());
+        assert!(parse("no_such_key: 1\n").is_err());
+    }
+}
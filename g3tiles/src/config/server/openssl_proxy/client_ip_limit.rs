@@ -0,0 +1,188 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+use g3_types::acl::{AclAction, AclNetworkRuleBuilder};
+
+const DEFAULT_TABLE_SIZE: NonZeroUsize = NonZeroUsize::new(16384).unwrap();
+
+/// The accept phase rate limit config keyed by the client ip
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct OpensslClientIpLimitConfig {
+    /// the length of the sliding window
+    pub(crate) window: Duration,
+    /// the max number of new connections from a client ip in the window
+    pub(crate) threshold: usize,
+    /// the max number of ClientHello recv timeouts from a client ip in the window
+    pub(crate) timeout_threshold: usize,
+    /// how long the new connections will be rejected once the client ip is limited
+    pub(crate) penalty: Duration,
+    /// the max number of client ips to track
+    pub(crate) table_size: NonZeroUsize,
+    /// the client networks that will never be limited
+    pub(crate) exempt_networks: AclNetworkRuleBuilder,
+    /// send a fatal TLS alert before closing the rejected connections
+    pub(crate) send_alert: bool,
+}
+
+impl Default for OpensslClientIpLimitConfig {
+    fn default() -> Self {
+        OpensslClientIpLimitConfig {
+            window: Duration::from_secs(10),
+            threshold: 64,
+            timeout_threshold: 8,
+            penalty: Duration::from_secs(60),
+            table_size: DEFAULT_TABLE_SIZE,
+            exempt_networks: AclNetworkRuleBuilder::new(AclAction::Forbid),
+            send_alert: false,
+        }
+    }
+}
+
+impl OpensslClientIpLimitConfig {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Option<Self>> {
+        let mut config = OpensslClientIpLimitConfig::default();
+        match v {
+            Yaml::Boolean(true) => return Ok(Some(config)),
+            Yaml::Boolean(false) => return Ok(None),
+            Yaml::Hash(map) => {
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "window" | "window_size" => {
+                        config.window = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "threshold" | "max_connections" => {
+                        config.threshold = g3_yaml::value::as_usize(v)
+                            .context(format!("invalid usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "timeout_threshold" | "max_client_hello_timeouts" => {
+                        config.timeout_threshold = g3_yaml::value::as_usize(v)
+                            .context(format!("invalid usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "penalty" | "penalty_duration" => {
+                        config.penalty = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "table_size" => {
+                        config.table_size = g3_yaml::value::as_nonzero_usize(v)
+                            .context(format!("invalid nonzero usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "exempt_networks" | "exempt" => {
+                        let mut builder = AclNetworkRuleBuilder::new(AclAction::Forbid);
+                        if let Yaml::Array(seq) = v {
+                            for (i, v) in seq.iter().enumerate() {
+                                let net = g3_yaml::value::as_ip_network(v)
+                                    .context(format!("invalid ip network value for {k}#{i}"))?;
+                                builder.add_network(net, AclAction::Permit);
+                            }
+                        } else {
+                            let net = g3_yaml::value::as_ip_network(v)
+                                .context(format!("invalid ip network value for key {k}"))?;
+                            builder.add_network(net, AclAction::Permit);
+                        }
+                        config.exempt_networks = builder;
+                        Ok(())
+                    }
+                    "send_alert" => {
+                        config.send_alert = g3_yaml::value::as_bool(v)?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for client ip limit config should be 'bool' or 'map'"
+                ));
+            }
+        }
+
+        config.check()?;
+        Ok(Some(config))
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.window.is_zero() {
+            return Err(anyhow!("window should not be zero"));
+        }
+        if self.penalty.is_zero() {
+            return Err(anyhow!("penalty duration should not be zero"));
+        }
+        if self.threshold == 0 {
+            return Err(anyhow!("threshold should not be zero"));
+        }
+        if self.timeout_threshold == 0 {
+            return Err(anyhow!("timeout threshold should not be zero"));
+        }
+        if self.timeout_threshold > self.threshold {
+            return Err(anyhow!(
+                "timeout threshold should not be larger than the threshold"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<Option<OpensslClientIpLimitConfig>> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        OpensslClientIpLimitConfig::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_ok() {
+        let config = parse("true").unwrap().unwrap();
+        assert_eq!(config, OpensslClientIpLimitConfig::default());
+        assert!(parse("false").unwrap().is_none());
+
+        let config = parse(
+            "window: 2s\nthreshold: 10\ntimeout_threshold: 2\npenalty: 30s\n\
+             table_size: 100\nexempt_networks: [10.0.0.0/8, 192.168.1.1]\nsend_alert: true\n",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.window, Duration::from_secs(2));
+        assert_eq!(config.threshold, 10);
+        assert_eq!(config.timeout_threshold, 2);
+        assert_eq!(config.penalty, Duration::from_secs(30));
+        assert_eq!(config.table_size.get(), 100);
+        assert!(config.send_alert);
+
+        let exempt = config.exempt_networks.build();
+        let ip = IpAddr::from_str("10.1.2.3").unwrap();
+        assert_eq!(exempt.check(ip).1, AclAction::Permit);
+        let ip = IpAddr::from_str("192.168.1.2").unwrap();
+        assert_eq!(exempt.check(ip).1, AclAction::Forbid);
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("1").is_err());
+        assert!(parse("window: 0\n").is_err());
+        assert!(parse("penalty: 0\n").is_err());
+        assert!(parse("threshold: 0\n").is_err());
+        assert!(parse("timeout_threshold: 0\n").is_err());
+        assert!(parse("threshold: 4\ntimeout_threshold: 5\n").is_err());
+        assert!(parse("table_size: 0\n").is_err());
+        assert!(parse("exempt_networks: abc\n").is_err());
+        assert!(parse("no_such_key: 1\n").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+const DEFAULT_MAX_SIZE: u32 = 16384;
+/// the default value of recv_max_early_data in OpenSSL, which is the real receive limit
+const MAX_MAX_SIZE: u32 = 16384;
+const DEFAULT_ANTI_REPLAY_WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_ANTI_REPLAY_CACHE_SIZE: usize = 65536;
+
+/// The TLS 1.3 early data (0-RTT) config of an openssl proxy host
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct OpensslEarlyDataConfig {
+    /// the max early data size in bytes the client is allowed to send
+    pub(crate) max_size: u32,
+    /// if the early data can be sent to the backend before the handshake completes
+    pub(crate) idempotent_backend: bool,
+    /// tickets used with early data will be remembered for this time
+    pub(crate) anti_replay_window: Duration,
+    /// the max number of tickets that can be remembered
+    pub(crate) anti_replay_cache_size: usize,
+}
+
+impl Default for OpensslEarlyDataConfig {
+    fn default() -> Self {
+        OpensslEarlyDataConfig {
+            max_size: DEFAULT_MAX_SIZE,
+            idempotent_backend: false,
+            anti_replay_window: DEFAULT_ANTI_REPLAY_WINDOW,
+            anti_replay_cache_size: DEFAULT_ANTI_REPLAY_CACHE_SIZE,
+        }
+    }
+}
+
+impl OpensslEarlyDataConfig {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Option<Self>> {
+        let mut config = OpensslEarlyDataConfig::default();
+        match v {
+            Yaml::Boolean(true) => return Ok(Some(config)),
+            Yaml::Boolean(false) => return Ok(None),
+            Yaml::Hash(map) => {
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "max_size" | "max_early_data" => {
+                        config.max_size = g3_yaml::humanize::as_u32(v)
+                            .context(format!("invalid humanize u32 value for key {k}"))?;
+                        Ok(())
+                    }
+                    "idempotent_backend" | "idempotent" => {
+                        config.idempotent_backend = g3_yaml::value::as_bool(v)
+                            .context(format!("invalid bool value for key {k}"))?;
+                        Ok(())
+                    }
+                    "anti_replay_window" => {
+                        config.anti_replay_window = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "anti_replay_cache_size" => {
+                        config.anti_replay_cache_size = g3_yaml::value::as_usize(v)
+                            .context(format!("invalid usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for early data config should be 'bool' or 'map'"
+                ));
+            }
+        }
+
+        config.check()?;
+        Ok(Some(config))
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.max_size == 0 {
+            return Err(anyhow!("max early data size should not be zero"));
+        }
+        if self.max_size > MAX_MAX_SIZE {
+            return Err(anyhow!(
+                "max early data size should not be larger than {MAX_MAX_SIZE}"
+            ));
+        }
+        if self.anti_replay_window.is_zero() {
+            return Err(anyhow!("anti replay window should not be zero"));
+        }
+        if self.anti_replay_cache_size == 0 {
+            return Err(anyhow!("anti replay cache size should not be zero"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<Option<OpensslEarlyDataConfig>> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        OpensslEarlyDataConfig::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_ok() {
+        assert_eq!(
+            parse("true").unwrap(),
+            Some(OpensslEarlyDataConfig::default())
+        );
+        assert!(parse("false").unwrap().is_none());
+
+        let config = parse(
+            "max_size: 4096\nidempotent_backend: true\nanti_replay_window: 30s\nanti_replay_cache_size: 1024\n",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.max_size, 4096);
+        assert!(config.idempotent_backend);
+        assert_eq!(config.anti_replay_window, Duration::from_secs(30));
+        assert_eq!(config.anti_replay_cache_size, 1024);
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("1").is_err());
+        assert!(parse("max_size: 0\n").is_err());
+        assert!(parse("max_size: 32K\n").is_err());
+        assert!(parse("anti_replay_window: 0\n").is_err());
+        assert!(parse("anti_replay_cache_size: 0\n").is_err());
+        assert!(parse("no_such_key: 1\n").is_err());
+    }
+}
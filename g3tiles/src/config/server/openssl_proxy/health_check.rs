@@ -0,0 +1,234 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+/// The way to probe the backends of a host
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum OpensslHealthProbe {
+    /// a tcp connect to the backend
+    #[default]
+    Tcp,
+    /// a TLS handshake with the backend
+    Tls,
+    /// a HTTP/1.1 GET request, a 2xx or 3xx response is expected
+    Http,
+}
+
+impl FromStr for OpensslHealthProbe {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tcp" | "tcp_connect" => Ok(OpensslHealthProbe::Tcp),
+            "tls" | "tls_handshake" => Ok(OpensslHealthProbe::Tls),
+            "http" => Ok(OpensslHealthProbe::Http),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The action on new client connections when the host is unhealthy
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum OpensslUnhealthyAction {
+    /// send a fatal TLS alert and close the connection before the handshake
+    #[default]
+    Reject,
+    /// finish the TLS handshake, then close the connection with a close_notify alert at once
+    Alert,
+}
+
+impl FromStr for OpensslUnhealthyAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reject" | "reject_before_handshake" => Ok(OpensslUnhealthyAction::Reject),
+            "alert" | "alert_after_handshake" => Ok(OpensslUnhealthyAction::Alert),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The backend health check config of an openssl proxy host
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct OpensslHealthCheckConfig {
+    pub(crate) probe: OpensslHealthProbe,
+    pub(crate) interval: Duration,
+    pub(crate) timeout: Duration,
+    /// the number of consecutive failures to mark the host as unhealthy
+    pub(crate) fall: usize,
+    /// the number of consecutive successful probes to mark the host as healthy again
+    pub(crate) rise: usize,
+    /// the SNI used in the tls probe, no SNI will be sent if not set
+    pub(crate) tls_name: Option<String>,
+    pub(crate) http_path: String,
+    pub(crate) http_host: String,
+    pub(crate) unhealthy_action: OpensslUnhealthyAction,
+}
+
+impl Default for OpensslHealthCheckConfig {
+    fn default() -> Self {
+        OpensslHealthCheckConfig {
+            probe: OpensslHealthProbe::Tcp,
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(4),
+            fall: 3,
+            rise: 2,
+            tls_name: None,
+            http_path: "/".to_string(),
+            http_host: "localhost".to_string(),
+            unhealthy_action: OpensslUnhealthyAction::Reject,
+        }
+    }
+}
+
+impl OpensslHealthCheckConfig {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Option<Self>> {
+        let mut config = OpensslHealthCheckConfig::default();
+        match v {
+            Yaml::Boolean(true) => return Ok(Some(config)),
+            Yaml::Boolean(false) => return Ok(None),
+            Yaml::String(s) => {
+                config.probe = OpensslHealthProbe::from_str(s)
+                    .map_err(|_| anyhow!("invalid health probe type {s}"))?;
+            }
+            Yaml::Hash(map) => {
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "probe" | "probe_type" => {
+                        let s = g3_yaml::value::as_string(v)?;
+                        config.probe = OpensslHealthProbe::from_str(&s)
+                            .map_err(|_| anyhow!("invalid health probe type {s}"))?;
+                        Ok(())
+                    }
+                    "interval" => {
+                        config.interval = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "timeout" => {
+                        config.timeout = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "fall" | "fall_threshold" => {
+                        config.fall = g3_yaml::value::as_usize(v)
+                            .context(format!("invalid usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "rise" | "rise_threshold" => {
+                        config.rise = g3_yaml::value::as_usize(v)
+                            .context(format!("invalid usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "tls_name" | "sni" => {
+                        let name = g3_yaml::value::as_string(v)?;
+                        config.tls_name = Some(name);
+                        Ok(())
+                    }
+                    "http_path" => {
+                        let path = g3_yaml::value::as_string(v)?;
+                        if !path.starts_with('/') {
+                            return Err(anyhow!("the http path should start with '/'"));
+                        }
+                        config.http_path = path;
+                        Ok(())
+                    }
+                    "http_host" => {
+                        config.http_host = g3_yaml::value::as_string(v)?;
+                        Ok(())
+                    }
+                    "unhealthy_action" | "failure_action" => {
+                        let s = g3_yaml::value::as_string(v)?;
+                        config.unhealthy_action = OpensslUnhealthyAction::from_str(&s)
+                            .map_err(|_| anyhow!("invalid unhealthy action {s}"))?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for health check config should be 'bool', 'string' or 'map'"
+                ));
+            }
+        }
+
+        config.check()?;
+        Ok(Some(config))
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.interval.is_zero() {
+            return Err(anyhow!("probe interval should not be zero"));
+        }
+        if self.timeout.is_zero() {
+            return Err(anyhow!("probe timeout should not be zero"));
+        }
+        if self.fall == 0 {
+            return Err(anyhow!("fall threshold should not be zero"));
+        }
+        if self.rise == 0 {
+            return Err(anyhow!("rise threshold should not be zero"));
+        }
+        if self.http_host.is_empty() {
+            return Err(anyhow!("http host should not be empty"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<Option<OpensslHealthCheckConfig>> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        OpensslHealthCheckConfig::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_ok() {
+        let config = parse("true").unwrap().unwrap();
+        assert_eq!(config, OpensslHealthCheckConfig::default());
+        assert!(parse("false").unwrap().is_none());
+
+        let config = parse("tls").unwrap().unwrap();
+        assert_eq!(config.probe, OpensslHealthProbe::Tls);
+
+        let config = parse(
+            "probe: http\ninterval: 2s\ntimeout: 1s\nfall: 2\nrise: 3\n\
+             http_path: /health\nhttp_host: example.net\nunhealthy_action: alert\n",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.probe, OpensslHealthProbe::Http);
+        assert_eq!(config.interval, Duration::from_secs(2));
+        assert_eq!(config.timeout, Duration::from_secs(1));
+        assert_eq!(config.fall, 2);
+        assert_eq!(config.rise, 3);
+        assert_eq!(config.http_path, "/health");
+        assert_eq!(config.http_host, "example.net");
+        assert_eq!(config.unhealthy_action, OpensslUnhealthyAction::Alert);
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("udp").is_err());
+        assert!(parse("1").is_err());
+        assert!(parse("interval: 0\n").is_err());
+        assert!(parse("timeout: 0\n").is_err());
+        assert!(parse("fall: 0\n").is_err());
+        assert!(parse("rise: 0\n").is_err());
+        assert!(parse("http_path: health\n").is_err());
+        assert!(parse("unhealthy_action: drop\n").is_err());
+        assert!(parse("no_such_key: 1\n").is_err());
+    }
+}
@@ -4,15 +4,21 @@
  */
 
 use anyhow::{Context, anyhow};
+use ascii::AsciiString;
+use log::warn;
 use openssl::ex_data::Index;
+use openssl::pkey::Id;
 use openssl::ssl::{
-    SslAcceptor, SslAcceptorBuilder, SslContext, SslContextBuilder, SslOptions,
+    SslAcceptor, SslAcceptorBuilder, SslContext, SslContextBuilder, SslMethod, SslOptions,
     SslSessionCacheMode, SslVerifyMode, TicketKeyStatus,
 };
 use openssl::stack::Stack;
 use openssl::x509::X509;
 use openssl::x509::store::X509StoreBuilder;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use yaml_rust::Yaml;
 
 use g3_types::collection::NamedValue;
@@ -20,7 +26,8 @@ use g3_types::limit::RateLimitQuotaConfig;
 use g3_types::metrics::NodeName;
 use g3_types::net::{
     OpensslCertificatePair, OpensslServerSessionCache, OpensslSessionIdContext, OpensslTicketKey,
-    RollingTicketer, TcpSockSpeedLimitConfig,
+    ProxyProtocolVersion, RollingTicketer, TcpMiscSockOpts, TcpSockSpeedLimitConfig, TlsCertStatus,
+    TlsVersion,
 };
 use g3_types::route::AlpnMatch;
 use g3_yaml::{YamlDocPosition, YamlMapCallback};
@@ -28,22 +35,85 @@ use g3_yaml::{YamlDocPosition, YamlMapCallback};
 #[cfg(feature = "vendored-tongsuo")]
 use g3_types::net::OpensslTlcpCertificatePair;
 
+use super::{
+    OpensslBackendH2Config, OpensslBackendPoolConfig, OpensslBackendProtocol,
+    OpensslClientCertRouterConfig, OpensslEarlyDataConfig, OpensslHealthCheckConfig,
+    OpensslHttpAwareConfig,
+};
+
+const MAX_DSCP_VALUE: u8 = 0b11_1111;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum OpensslCertKeyType {
+    Rsa,
+    Ec,
+    Ed25519,
+    Other,
+}
+
+impl OpensslCertKeyType {
+    pub(crate) fn from_id(id: Id) -> Self {
+        match id {
+            Id::RSA => OpensslCertKeyType::Rsa,
+            Id::EC => OpensslCertKeyType::Ec,
+            Id::ED25519 => OpensslCertKeyType::Ed25519,
+            _ => OpensslCertKeyType::Other,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            OpensslCertKeyType::Rsa => "rsa",
+            OpensslCertKeyType::Ec => "ec",
+            OpensslCertKeyType::Ed25519 => "ed25519",
+            OpensslCertKeyType::Other => "other",
+        }
+    }
+}
+
+/// The file paths of a cert pair, which can be watched for changes
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct OpensslCertPairFiles {
+    pub(crate) certificate: PathBuf,
+    pub(crate) private_key: PathBuf,
+}
+
 #[derive(Clone, Debug, Default, PartialEq)]
 pub(crate) struct OpensslHostConfig {
     name: String,
     cert_pairs: Vec<OpensslCertificatePair>,
+    cert_pair_files: Vec<OpensslCertPairFiles>,
+    watch_cert_files: bool,
     #[cfg(feature = "vendored-tongsuo")]
     tlcp_cert_pairs: Vec<OpensslTlcpCertificatePair>,
+    allow_expired: bool,
     client_auth: bool,
     client_auth_certs: Vec<Vec<u8>>,
     session_id_context: String,
     no_session_ticket: bool,
     no_session_cache: bool,
+    tls_min_version: Option<TlsVersion>,
+    tls_max_version: Option<TlsVersion>,
+    accept_min_version: Option<TlsVersion>,
+    cipher_list: Option<String>,
+    ciphersuites: Option<String>,
     pub(crate) request_alive_max: Option<usize>,
     pub(crate) request_rate_limit: Option<RateLimitQuotaConfig>,
     pub(crate) tcp_sock_speed_limit: Option<TcpSockSpeedLimitConfig>,
+    tcp_misc_opts: Option<TcpMiscSockOpts>,
     pub(crate) task_idle_max_count: Option<usize>,
+    connection_max_lifetime: Option<Duration>,
+    pub(crate) shared_logger: Option<AsciiString>,
     pub(crate) backends: AlpnMatch<NodeName>,
+    pub(crate) backend_dscp: Option<u8>,
+    pub(crate) client_cert_router: Option<Arc<OpensslClientCertRouterConfig>>,
+    pub(crate) early_data: Option<OpensslEarlyDataConfig>,
+    pub(crate) backend_pool: Option<OpensslBackendPoolConfig>,
+    pub(crate) http_aware: Option<OpensslHttpAwareConfig>,
+    pub(crate) upstream_proxy_protocol: Option<ProxyProtocolVersion>,
+    pub(crate) backend_protocol: OpensslBackendProtocol,
+    pub(crate) backend_h2: OpensslBackendH2Config,
+    pub(crate) health_check: Option<OpensslHealthCheckConfig>,
 }
 
 impl NamedValue for OpensslHostConfig {
@@ -60,6 +130,72 @@ impl NamedValue for OpensslHostConfig {
 }
 
 impl OpensslHostConfig {
+    /// The cert pair files to watch, which is empty if not enabled
+    pub(crate) fn watched_cert_files(&self) -> &[OpensslCertPairFiles] {
+        if self.watch_cert_files {
+            &self.cert_pair_files
+        } else {
+            &[]
+        }
+    }
+
+    /// Get the client side socket options, the host values will be merged over the server ones
+    pub(crate) fn tcp_misc_opts(&self, server: &TcpMiscSockOpts) -> TcpMiscSockOpts {
+        match &self.tcp_misc_opts {
+            Some(opts) => opts.merge_over(server),
+            None => server.clone(),
+        }
+    }
+
+    /// Get the max lifetime of the client connections, the host value will override the server
+    /// one, and zero means no limit
+    pub(crate) fn connection_max_lifetime(&self, server: Option<Duration>) -> Option<Duration> {
+        match self.connection_max_lifetime {
+            Some(lifetime) => (!lifetime.is_zero()).then_some(lifetime),
+            None => server,
+        }
+    }
+
+    /// The lowest TLS version allowed for this host, the mozilla intermediate profile allows
+    /// TLS1.2 and above by default
+    pub(crate) fn tls_version_floor(&self) -> TlsVersion {
+        self.tls_min_version.unwrap_or(TlsVersion::TLS1_2)
+    }
+
+    /// Set the loosest TLS version floor of all hosts in the same server
+    pub(crate) fn set_accept_min_version(&mut self, version: TlsVersion) {
+        if version < self.tls_version_floor() {
+            self.accept_min_version = Some(version);
+        } else {
+            self.accept_min_version = None;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn allow_expired(&self) -> bool {
+        self.allow_expired
+    }
+
+    pub(crate) fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::with_capacity(self.cert_pairs.len());
+        for (i, pair) in self.cert_pairs.iter().enumerate() {
+            let status = pair
+                .cert_status()
+                .context(format!("invalid cert pair #{i} in host {}", self.name))?;
+            all.push(status);
+        }
+        #[cfg(feature = "vendored-tongsuo")]
+        for (i, pair) in self.tlcp_cert_pairs.iter().enumerate() {
+            let status = pair
+                .cert_status()
+                .context(format!("invalid tlcp cert pair #{i} in host {}", self.name))?;
+            all.extend(status);
+        }
+        all.iter_mut()
+            .for_each(|s| s.set_allow_expired(self.allow_expired));
+        Ok(all)
+    }
+
     fn set_client_auth_certificates(&mut self, certs: Vec<X509>) -> anyhow::Result<()> {
         for (i, cert) in certs.into_iter().enumerate() {
             let bytes = cert
@@ -76,7 +212,16 @@ impl OpensslHostConfig {
         id_ctx: &mut OpensslSessionIdContext,
     ) -> anyhow::Result<()> {
         if self.client_auth {
-            ssl_builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+            let verify_mode = SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT;
+            match &self.client_cert_router {
+                Some(router) if router.require_match => {
+                    let router = router.clone();
+                    ssl_builder.set_verify_callback(verify_mode, move |preverify_ok, ctx| {
+                        router.verify_peer_cert(preverify_ok, ctx)
+                    });
+                }
+                _ => ssl_builder.set_verify(verify_mode),
+            }
 
             let mut store_builder = X509StoreBuilder::new()
                 .map_err(|e| anyhow!("failed to create ca cert store builder: {e}"))?;
@@ -119,6 +264,52 @@ impl OpensslHostConfig {
         Ok(())
     }
 
+    /// The min protocol version of the context is the loosest floor of all hosts in the server,
+    /// and the floor of this host is checked after the SNI is processed, so clients below it will
+    /// always receive a protocol_version alert, whichever host they are routed to.
+    fn set_tls_params(&self, ssl_builder: &mut SslContextBuilder) -> anyhow::Result<()> {
+        #[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+        let min_version = match self.accept_min_version {
+            Some(accept_min) => {
+                let floor = self.tls_version_floor();
+                g3_openssl::set_protocol_version_floor(ssl_builder, floor.into())
+                    .map_err(|e| anyhow!("failed to set tls version floor to {floor}: {e}"))?;
+                Some(accept_min)
+            }
+            None => self.tls_min_version,
+        };
+        #[cfg(any(feature = "vendored-boringssl", feature = "vendored-aws-lc"))]
+        let min_version = self.tls_min_version;
+        if let Some(version) = min_version {
+            // the mozilla intermediate profile disables TLS1.0 and TLS1.1 by options
+            ssl_builder.clear_options(SslOptions::NO_TLSV1 | SslOptions::NO_TLSV1_1);
+            ssl_builder
+                .set_min_proto_version(Some(version.into()))
+                .map_err(|e| anyhow!("failed to set min tls version to {version}: {e}"))?;
+        }
+        if let Some(version) = self.tls_max_version {
+            ssl_builder
+                .set_max_proto_version(Some(version.into()))
+                .map_err(|e| anyhow!("failed to set max tls version to {version}: {e}"))?;
+        }
+        if let Some(cipher_list) = &self.cipher_list {
+            ssl_builder
+                .set_cipher_list(cipher_list)
+                .map_err(|e| anyhow!("failed to set cipher list: {e}"))?;
+        }
+        if let Some(ciphersuites) = &self.ciphersuites {
+            #[cfg(not(feature = "vendored-boringssl"))]
+            ssl_builder
+                .set_ciphersuites(ciphersuites)
+                .map_err(|e| anyhow!("failed to set ciphersuites: {e}"))?;
+            #[cfg(feature = "vendored-boringssl")]
+            return Err(anyhow!(
+                "boringssl has no support for setting TLS ciphersuites {ciphersuites}"
+            ));
+        }
+        Ok(())
+    }
+
     pub(crate) fn build_ssl_context(
         &self,
         ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
@@ -127,6 +318,32 @@ impl OpensslHostConfig {
             return Ok(None);
         }
 
+        self.build_tls_context(ticketer, &self.cert_pairs).map(Some)
+    }
+
+    /// Build a TLS context that use the settings of this host but a dynamic certificate pair
+    pub(crate) fn build_ssl_context_with_cert(
+        &self,
+        ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+        cert_pair: &OpensslCertificatePair,
+    ) -> anyhow::Result<SslContext> {
+        self.build_tls_context(ticketer, std::slice::from_ref(cert_pair))
+    }
+
+    /// Build a TLS context that use the settings of this host but reloaded cert pairs
+    pub(crate) fn build_ssl_context_with_cert_pairs(
+        &self,
+        ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+        cert_pairs: &[OpensslCertificatePair],
+    ) -> anyhow::Result<SslContext> {
+        self.build_tls_context(ticketer, cert_pairs)
+    }
+
+    fn build_tls_context(
+        &self,
+        ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+        cert_pairs: &[OpensslCertificatePair],
+    ) -> anyhow::Result<SslContext> {
         let mut id_ctx = OpensslSessionIdContext::new()
             .map_err(|e| anyhow!("failed to create session id context builder: {e}"))?;
         if !self.session_id_context.is_empty() {
@@ -157,13 +374,28 @@ impl OpensslHostConfig {
             ssl_builder.set_ex_data(ticket_key_index, ticketer);
             set_ticket_key_callback(&mut ssl_builder, ticket_key_index)?;
         }
+        #[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+        if !self.no_session_ticket {
+            // the SNI of the resumed session will be checked against the new one
+            super::session_sni::set_session_ticket_handler(&mut ssl_builder)?;
+        }
 
         self.set_client_auth(&mut ssl_builder, &mut id_ctx)?;
+        self.set_tls_params(&mut ssl_builder)?;
+        #[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+        if let Some(early_data) = &self.early_data {
+            // Keep using stateless tickets, which will be encrypted by the tls ticketer if set.
+            // The replay check is done by the anti replay cache of the host before handshake.
+            ssl_builder.set_options(SslOptions::NO_ANTI_REPLAY);
+            ssl_builder
+                .set_max_early_data(early_data.max_size)
+                .map_err(|e| anyhow!("failed to set max early data: {e}"))?;
+        }
 
         // ssl_builder.set_mode() // TODO do we need it?
         // ssl_builder.set_options() // TODO do we need it?
 
-        for (i, pair) in self.cert_pairs.iter().enumerate() {
+        for (i, pair) in cert_pairs.iter().enumerate() {
             pair.add_to_server_ssl_context(&mut ssl_builder, &mut id_ctx)
                 .context(format!("failed to add cert pair #{i} to ssl context"))?;
         }
@@ -189,7 +421,7 @@ impl OpensslHostConfig {
 
         let ssl_acceptor = ssl_builder.build();
 
-        Ok(Some(ssl_acceptor.into_context()))
+        Ok(ssl_acceptor.into_context())
     }
 
     #[cfg(feature = "vendored-tongsuo")]
@@ -277,6 +509,51 @@ fn set_ticket_key_callback(
         .map_err(|e| anyhow!("failed to set ticket key callback: {e}"))
 }
 
+/// Get the file paths of all cert pairs, or an empty list if any of them is not set by file path
+fn parse_cert_pair_files(
+    value: &Yaml,
+    lookup_dir: &Path,
+) -> anyhow::Result<Vec<OpensslCertPairFiles>> {
+    fn as_single_file(v: &Yaml, lookup_dir: &Path) -> anyhow::Result<Option<PathBuf>> {
+        match v {
+            Yaml::String(s) if !s.trim_start().starts_with("--") => {
+                g3_yaml::value::as_file_path(v, lookup_dir, false).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    let Yaml::Array(seq) = value else {
+        return Ok(Vec::new());
+    };
+    let mut all_files = Vec::with_capacity(seq.len());
+    for v in seq {
+        let Yaml::Hash(map) = v else {
+            return Ok(Vec::new());
+        };
+        let mut certificate = None;
+        let mut private_key = None;
+        for (k, v) in map.iter() {
+            let Yaml::String(k) = k else {
+                continue;
+            };
+            match g3_yaml::key::normalize(k).as_str() {
+                "certificate" | "cert" => certificate = as_single_file(v, lookup_dir)?,
+                "private_key" | "key" => private_key = as_single_file(v, lookup_dir)?,
+                _ => {}
+            }
+        }
+        let (Some(certificate), Some(private_key)) = (certificate, private_key) else {
+            return Ok(Vec::new());
+        };
+        all_files.push(OpensslCertPairFiles {
+            certificate,
+            private_key,
+        });
+    }
+    Ok(all_files)
+}
+
 impl YamlMapCallback for OpensslHostConfig {
     fn type_name(&self) -> &'static str {
         "OpensslHostConfig"
@@ -301,6 +578,12 @@ impl YamlMapCallback for OpensslHostConfig {
                 .context(format!(
                     "invalid openssl cert pair list value for key {key}"
                 ))?;
+                self.cert_pair_files = parse_cert_pair_files(value, lookup_dir)?;
+                Ok(())
+            }
+            "watch_cert_files" => {
+                self.watch_cert_files = g3_yaml::value::as_bool(value)
+                    .context(format!("invalid bool value for key {key}"))?;
                 Ok(())
             }
             #[cfg(feature = "vendored-tongsuo")]
@@ -314,6 +597,10 @@ impl YamlMapCallback for OpensslHostConfig {
                 ))?;
                 Ok(())
             }
+            "allow_expired" => {
+                self.allow_expired = g3_yaml::value::as_bool(value)?;
+                Ok(())
+            }
             "enable_client_auth" => {
                 self.client_auth = g3_yaml::value::as_bool(value)
                     .context(format!("invalid value for key {key}"))?;
@@ -356,16 +643,139 @@ impl YamlMapCallback for OpensslHostConfig {
                 self.tcp_sock_speed_limit = Some(limit);
                 Ok(())
             }
+            "tcp_misc_opts" => {
+                let opts = g3_yaml::value::as_tcp_misc_sock_opts(value)
+                    .context(format!("invalid tcp misc sock opts value for key {key}"))?;
+                self.tcp_misc_opts = Some(opts);
+                Ok(())
+            }
             "task_idle_max_count" => {
                 let max_count = g3_yaml::value::as_usize(value)
                     .context(format!("invalid usize value for key {key}"))?;
                 self.task_idle_max_count = Some(max_count);
                 Ok(())
             }
+            "connection_max_lifetime" | "max_lifetime" => {
+                let lifetime = g3_yaml::humanize::as_duration(value)
+                    .context(format!("invalid humanize duration value for key {key}"))?;
+                self.connection_max_lifetime = Some(lifetime);
+                Ok(())
+            }
+            "shared_logger" => {
+                let name = g3_yaml::value::as_ascii(value)
+                    .context(format!("invalid ascii string value for key {key}"))?;
+                if name.is_empty() {
+                    return Err(anyhow!("empty shared logger name"));
+                }
+                self.shared_logger = Some(name);
+                Ok(())
+            }
             "backends" => {
                 self.backends = g3_yaml::value::as_alpn_matched_backends(value)?;
                 Ok(())
             }
+            "backend_dscp" => {
+                let dscp = g3_yaml::value::as_u8(value)
+                    .context(format!("invalid u8 value for key {key}"))?;
+                if dscp > MAX_DSCP_VALUE {
+                    return Err(anyhow!(
+                        "dscp value {dscp} for key {key} should be no more than {MAX_DSCP_VALUE}"
+                    ));
+                }
+                self.backend_dscp = Some(dscp);
+                Ok(())
+            }
+            "backend_tos" => {
+                let tos = g3_yaml::value::as_u8(value)
+                    .context(format!("invalid u8 value for key {key}"))?;
+                if tos & 0b11 != 0 {
+                    return Err(anyhow!(
+                        "tos value {tos} for key {key} should not set the ECN bits"
+                    ));
+                }
+                self.backend_dscp = Some(tos >> 2);
+                Ok(())
+            }
+            "client_cert_router" => {
+                let router = OpensslClientCertRouterConfig::parse_yaml(value)
+                    .context(format!("invalid client cert router value for key {key}"))?;
+                self.client_cert_router = Some(Arc::new(router));
+                Ok(())
+            }
+            "tls_min_version" | "min_tls_version" => {
+                let version = g3_yaml::value::as_tls_version(value)
+                    .context(format!("invalid tls version value for key {key}"))?;
+                self.tls_min_version = Some(version);
+                Ok(())
+            }
+            "tls_max_version" | "max_tls_version" => {
+                let version = g3_yaml::value::as_tls_version(value)
+                    .context(format!("invalid tls version value for key {key}"))?;
+                self.tls_max_version = Some(version);
+                Ok(())
+            }
+            "cipher_list" => {
+                let cipher_list = g3_yaml::value::as_string(value)
+                    .context(format!("invalid string value for key {key}"))?;
+                self.cipher_list = Some(cipher_list);
+                Ok(())
+            }
+            "ciphersuites" => {
+                let ciphersuites = g3_yaml::value::as_string(value)
+                    .context(format!("invalid string value for key {key}"))?;
+                self.ciphersuites = Some(ciphersuites);
+                Ok(())
+            }
+            "enable_early_data" | "early_data" => {
+                #[cfg(any(feature = "vendored-boringssl", feature = "vendored-aws-lc"))]
+                return Err(anyhow!(
+                    "early data is not supported with boringssl or aws-lc for key {key}"
+                ));
+                #[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+                {
+                    self.early_data = OpensslEarlyDataConfig::parse_yaml(value)
+                        .context(format!("invalid early data config value for key {key}"))?;
+                    Ok(())
+                }
+            }
+            "backend_pool" => {
+                self.backend_pool = OpensslBackendPoolConfig::parse_yaml(value)
+                    .context(format!("invalid backend pool config value for key {key}"))?;
+                Ok(())
+            }
+            "http_aware" => {
+                self.http_aware = OpensslHttpAwareConfig::parse_yaml(value)
+                    .context(format!("invalid http aware config value for key {key}"))?;
+                Ok(())
+            }
+            "upstream_proxy_protocol" => {
+                let version = g3_yaml::value::as_proxy_protocol_version(value).context(format!(
+                    "invalid proxy protocol version value for key {key}"
+                ))?;
+                if version != ProxyProtocolVersion::V2 {
+                    return Err(anyhow!(
+                        "only proxy protocol v2 is supported for key {key}, as the TLS info is sent in TLVs"
+                    ));
+                }
+                self.upstream_proxy_protocol = Some(version);
+                Ok(())
+            }
+            "backend_protocol" => {
+                let s = g3_yaml::value::as_string(value)?;
+                self.backend_protocol = OpensslBackendProtocol::from_str(&s)
+                    .map_err(|_| anyhow!("invalid backend protocol {s} for key {key}"))?;
+                Ok(())
+            }
+            "backend_h2" => {
+                self.backend_h2 = OpensslBackendH2Config::parse_yaml(value)
+                    .context(format!("invalid backend h2 config value for key {key}"))?;
+                Ok(())
+            }
+            "health_check" => {
+                self.health_check = OpensslHealthCheckConfig::parse_yaml(value)
+                    .context(format!("invalid health check config value for key {key}"))?;
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {key}")),
         }
     }
@@ -385,6 +795,665 @@ impl YamlMapCallback for OpensslHostConfig {
         if self.backends.is_empty() {
             return Err(anyhow!("no backend service set"));
         }
+        if self.client_cert_router.is_some() && !self.client_auth {
+            // never route by attributes of certificates that are not verified
+            return Err(anyhow!(
+                "client auth should be enabled to use client cert router"
+            ));
+        }
+        if self.watch_cert_files {
+            if self.cert_pairs.is_empty() {
+                return Err(anyhow!("no cert pair set to watch"));
+            }
+            if self.cert_pair_files.len() != self.cert_pairs.len() {
+                return Err(anyhow!(
+                    "all cert pairs should be set as single file paths to watch cert files"
+                ));
+            }
+        }
+        if self.early_data.is_some() {
+            if self.no_session_ticket {
+                return Err(anyhow!(
+                    "session ticket should be enabled to use early data"
+                ));
+            }
+            if self.tls_max_version.is_some_and(|v| v < TlsVersion::TLS1_3) {
+                return Err(anyhow!("early data requires tls version 1.3"));
+            }
+        }
+        if self.http_aware.is_some() && self.early_data.is_some() {
+            // the early data is sent to the backend before the requests can be parsed
+            return Err(anyhow!("early data can not be used with http aware relay"));
+        }
+        if self.upstream_proxy_protocol.is_some() && self.backend_pool.is_some() {
+            // the PROXY protocol header can only be sent at the start of a new connection
+            return Err(anyhow!(
+                "upstream proxy protocol can not be used with backend pool"
+            ));
+        }
+        if self.backend_protocol == OpensslBackendProtocol::H2 {
+            // the backend h2 connections are shared by clients
+            if self.upstream_proxy_protocol.is_some() {
+                return Err(anyhow!(
+                    "upstream proxy protocol can not be used with h2 backend protocol"
+                ));
+            }
+            if self.backend_pool.is_some() {
+                return Err(anyhow!(
+                    "backend pool can not be used with h2 backend protocol"
+                ));
+            }
+            if self.early_data.is_some() {
+                return Err(anyhow!(
+                    "early data can not be used with h2 backend protocol"
+                ));
+            }
+        }
+        self.check_tls_params()?;
+        self.check_cert_key_types()
+    }
+}
+
+impl OpensslHostConfig {
+    fn check_tls_params(&self) -> anyhow::Result<()> {
+        if let (Some(min), Some(max)) = (self.tls_min_version, self.tls_max_version) {
+            if min > max {
+                return Err(anyhow!(
+                    "tls min version {min} is greater than tls max version {max}"
+                ));
+            }
+        }
+        if self.cipher_list.is_none() && self.ciphersuites.is_none() {
+            return Ok(());
+        }
+
+        // validate the cipher strings now, so invalid ones can be found at config load time
+        let mut ssl_builder = SslContext::builder(SslMethod::tls_server())
+            .map_err(|e| anyhow!("failed to create ssl context builder: {e}"))?;
+        if let Some(cipher_list) = &self.cipher_list {
+            ssl_builder
+                .set_cipher_list(cipher_list)
+                .map_err(|e| anyhow!("invalid cipher list {cipher_list}: {e}"))?;
+        }
+        if let Some(ciphersuites) = &self.ciphersuites {
+            #[cfg(not(feature = "vendored-boringssl"))]
+            ssl_builder
+                .set_ciphersuites(ciphersuites)
+                .map_err(|e| anyhow!("invalid ciphersuites {ciphersuites}: {e}"))?;
+            #[cfg(feature = "vendored-boringssl")]
+            return Err(anyhow!(
+                "boringssl has no support for setting TLS ciphersuites {ciphersuites}"
+            ));
+        }
+        Ok(())
+    }
+
+    /// OpenSSL selects the certificate by the key type, so only one cert pair is usable for each
+    fn check_cert_key_types(&self) -> anyhow::Result<()> {
+        let mut key_ids = Vec::with_capacity(self.cert_pairs.len());
+        for (i, pair) in self.cert_pairs.iter().enumerate() {
+            let Some(id) = pair.key_id() else {
+                return Err(anyhow!("invalid private key in cert pair #{i}"));
+            };
+            if key_ids.contains(&id) {
+                warn!(
+                    "host {}: cert pair #{i} has the same {} key type as a previous one, \
+                     the previous one will be overridden",
+                    self.name,
+                    OpensslCertKeyType::from_id(id).as_str()
+                );
+            } else {
+                key_ids.push(id);
+            }
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::ssl::{Ssl, SslConnector, SslMethod, SslVersion};
+    use openssl::x509::X509NameBuilder;
+    use yaml_rust::YamlLoader;
+
+    use crate::testing::{
+        TempDir, ca_cert, common_name, ec_key, issued_cert, rsa_key, self_signed_cert, write_cert,
+        write_cert_pair,
+    };
+
+    fn write_host_cert_pair(dir: &Path, name: &str, key: PKey<Private>) {
+        let cert = self_signed_cert(&common_name("test.example.net"), &key, 1);
+        write_cert_pair(dir, name, &cert, &key);
+    }
+
+    fn parse_host_with(
+        dir: &Path,
+        names: &[&str],
+        extra: &str,
+    ) -> anyhow::Result<OpensslHostConfig> {
+        let mut cert_pairs = String::new();
+        for name in names {
+            cert_pairs.push_str(&format!(
+                "  - certificate: {name}.crt\n    private_key: {name}.key\n"
+            ));
+        }
+        let yaml = format!("name: test\ncert_pairs:\n{cert_pairs}backends:\n  - test\n{extra}");
+        let yaml = YamlLoader::load_from_str(&yaml).unwrap();
+        let position = YamlDocPosition {
+            path: dir.join("main.yaml"),
+            index: 0,
+        };
+        let Yaml::Hash(map) = &yaml[0] else {
+            unreachable!()
+        };
+        let mut config = OpensslHostConfig::default();
+        g3_yaml::foreach_kv(map, |k, v| config.parse_kv(k, v, Some(&position)))?;
+        config.check()?;
+        Ok(config)
+    }
+
+    fn parse_host(dir: &Path, names: &[&str]) -> OpensslHostConfig {
+        parse_host_with(dir, names, "").unwrap()
+    }
+
+    fn served_key_type(ssl_context: &SslContext, sigalgs: &str) -> OpensslCertKeyType {
+        let (server_sock, client_sock) = UnixStream::pair().unwrap();
+        let ssl = Ssl::new(ssl_context).unwrap();
+        let server = std::thread::spawn(move || {
+            let stream = ssl.accept(server_sock).unwrap();
+            let key = stream.ssl().certificate().unwrap().public_key().unwrap();
+            OpensslCertKeyType::from_id(key.id())
+        });
+
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        builder
+            .set_max_proto_version(Some(openssl::ssl::SslVersion::TLS1_2))
+            .unwrap();
+        builder.set_sigalgs_list(sigalgs).unwrap();
+        let connector = builder.build();
+        let _stream = connector.connect("test.example.net", client_sock).unwrap();
+
+        server.join().unwrap()
+    }
+
+    #[test]
+    fn multi_key_type_selection() {
+        let temp_dir = TempDir::new("openssl_host_multi_cert");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "rsa", rsa_key());
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let config = parse_host(dir, &["rsa", "ec"]);
+        let ssl_context = config.build_ssl_context(None).unwrap().unwrap();
+
+        assert_eq!(
+            served_key_type(&ssl_context, "ECDSA+SHA256"),
+            OpensslCertKeyType::Ec
+        );
+        assert_eq!(
+            served_key_type(&ssl_context, "RSA+SHA256"),
+            OpensslCertKeyType::Rsa
+        );
+
+        // reload with only one of the pair
+        let config = parse_host(dir, &["ec"]);
+        let ssl_context = config.build_ssl_context(None).unwrap().unwrap();
+        assert_eq!(
+            served_key_type(&ssl_context, "ECDSA+SHA256:RSA+SHA256"),
+            OpensslCertKeyType::Ec
+        );
+    }
+
+    #[test]
+    fn duplicated_key_type() {
+        let temp_dir = TempDir::new("openssl_host_dup_cert");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec1", ec_key());
+        write_host_cert_pair(dir, "ec2", ec_key());
+
+        // only warn for duplicated key types
+        let config = parse_host(dir, &["ec1", "ec2"]);
+        assert!(config.check_cert_key_types().is_ok());
+    }
+
+    #[test]
+    fn watch_cert_files() {
+        let temp_dir = TempDir::new("openssl_host_watch_cert");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let config = parse_host(dir, &["ec"]);
+        assert!(config.watched_cert_files().is_empty());
+
+        let config = parse_host_with(dir, &["ec"], "watch_cert_files: true\n").unwrap();
+        assert_eq!(
+            config.watched_cert_files(),
+            &[OpensslCertPairFiles {
+                certificate: dir.join("ec.crt"),
+                private_key: dir.join("ec.key"),
+            }]
+        );
+
+        // inline certificates can not be watched
+        let cert = fs::read_to_string(dir.join("ec.crt")).unwrap();
+        let cert = cert.trim_end().replace('\n', "\n      ");
+        let yaml = format!(
+            "name: test\ncert_pairs:\n  - certificate: |\n      {cert}\n    private_key: ec.key\n\
+             watch_cert_files: true\nbackends:\n  - test\n"
+        );
+        let yaml = YamlLoader::load_from_str(&yaml).unwrap();
+        let position = YamlDocPosition {
+            path: dir.join("main.yaml"),
+            index: 0,
+        };
+        let Yaml::Hash(map) = &yaml[0] else {
+            unreachable!()
+        };
+        let mut config = OpensslHostConfig::default();
+        g3_yaml::foreach_kv(map, |k, v| config.parse_kv(k, v, Some(&position))).unwrap();
+        assert!(config.check().is_err());
+    }
+
+    fn tls10_handshake(ssl_context: &SslContext) -> bool {
+        let (server_sock, client_sock) = UnixStream::pair().unwrap();
+        let ssl = Ssl::new(ssl_context).unwrap();
+        let server = std::thread::spawn(move || ssl.accept(server_sock).is_ok());
+
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        builder
+            .set_min_proto_version(Some(openssl::ssl::SslVersion::TLS1))
+            .unwrap();
+        builder
+            .set_max_proto_version(Some(openssl::ssl::SslVersion::TLS1))
+            .unwrap();
+        builder.set_cipher_list("DEFAULT:@SECLEVEL=0").unwrap();
+        let connector = builder.build();
+        let client_ok = connector.connect("test.example.net", client_sock).is_ok();
+
+        let server_ok = server.join().unwrap();
+        client_ok && server_ok
+    }
+
+    #[test]
+    fn tls_version_per_host() {
+        let temp_dir = TempDir::new("openssl_host_tls_version");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let legacy = parse_host_with(
+            dir,
+            &["ec"],
+            "tls_min_version: 1.0\ncipher_list: \"DEFAULT:@SECLEVEL=0\"\n",
+        )
+        .unwrap();
+        let ssl_context = legacy.build_ssl_context(None).unwrap().unwrap();
+        assert!(tls10_handshake(&ssl_context));
+
+        let strict = parse_host_with(
+            dir,
+            &["ec"],
+            "tls_min_version: 1.2\nciphersuites: TLS_AES_256_GCM_SHA384\n",
+        )
+        .unwrap();
+        let ssl_context = strict.build_ssl_context(None).unwrap().unwrap();
+        assert!(!tls10_handshake(&ssl_context));
+
+        // the default mozilla intermediate profile rejects TLS1.0 too
+        let ssl_context = parse_host(dir, &["ec"])
+            .build_ssl_context(None)
+            .unwrap()
+            .unwrap();
+        assert!(!tls10_handshake(&ssl_context));
+    }
+
+    fn versioned_handshake(ssl_context: &SslContext, version: SslVersion) -> Result<(), String> {
+        let (server_sock, client_sock) = UnixStream::pair().unwrap();
+        let ssl = Ssl::new(ssl_context).unwrap();
+        let server = std::thread::spawn(move || ssl.accept(server_sock).is_ok());
+
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        builder.set_min_proto_version(Some(version)).unwrap();
+        builder.set_max_proto_version(Some(version)).unwrap();
+        let connector = builder.build();
+        let client_r = connector
+            .connect("test.example.net", client_sock)
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+
+        assert_eq!(server.join().unwrap(), client_r.is_ok());
+        client_r
+    }
+
+    #[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+    #[test]
+    fn tls_version_floor_after_sni() {
+        let temp_dir = TempDir::new("openssl_host_tls_floor");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let mut strict = parse_host_with(dir, &["ec"], "tls_min_version: 1.3\n").unwrap();
+        assert_eq!(strict.tls_version_floor(), TlsVersion::TLS1_3);
+        // another host in the same server allows TLS1.2
+        strict.set_accept_min_version(TlsVersion::TLS1_2);
+        assert_eq!(strict.accept_min_version, Some(TlsVersion::TLS1_2));
+        let ssl_context = strict.build_ssl_context(None).unwrap().unwrap();
+        let e = versioned_handshake(&ssl_context, SslVersion::TLS1_2).unwrap_err();
+        assert!(e.contains("protocol version"), "unexpected error: {e}");
+        assert!(versioned_handshake(&ssl_context, SslVersion::TLS1_3).is_ok());
+
+        let mut loose = parse_host(dir, &["ec"]);
+        loose.set_accept_min_version(TlsVersion::TLS1_2);
+        assert_eq!(loose.accept_min_version, None);
+        let ssl_context = loose.build_ssl_context(None).unwrap().unwrap();
+        assert!(versioned_handshake(&ssl_context, SslVersion::TLS1_2).is_ok());
+        assert!(versioned_handshake(&ssl_context, SslVersion::TLS1_3).is_ok());
+    }
+
+    #[test]
+    fn invalid_tls_params() {
+        let temp_dir = TempDir::new("openssl_host_invalid_tls");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        assert!(parse_host_with(dir, &["ec"], "tls_min_version: 1.4\n").is_err());
+        assert!(parse_host_with(dir, &["ec"], "tls_max_version: ssl3\n").is_err());
+        assert!(
+            parse_host_with(dir, &["ec"], "tls_min_version: 1.3\ntls_max_version: 1.2\n").is_err()
+        );
+        assert!(parse_host_with(dir, &["ec"], "cipher_list: NO-SUCH-CIPHER\n").is_err());
+        assert!(parse_host_with(dir, &["ec"], "ciphersuites: TLS_NO_SUCH_CIPHER\n").is_err());
+    }
+
+    #[test]
+    fn backend_dscp() {
+        let temp_dir = TempDir::new("openssl_host_backend_dscp");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let config = parse_host_with(dir, &["ec"], "backend_dscp: 46\n").unwrap();
+        assert_eq!(config.backend_dscp, Some(46));
+        let config = parse_host_with(dir, &["ec"], "backend_tos: 184\n").unwrap();
+        assert_eq!(config.backend_dscp, Some(46));
+
+        assert!(parse_host_with(dir, &["ec"], "backend_dscp: 64\n").is_err());
+        assert!(parse_host_with(dir, &["ec"], "backend_tos: 185\n").is_err());
+    }
+
+    #[test]
+    fn connection_max_lifetime() {
+        let temp_dir = TempDir::new("openssl_host_connection_max_lifetime");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let server = Some(Duration::from_secs(3600));
+        let config = parse_host(dir, &["ec"]);
+        assert_eq!(config.connection_max_lifetime(server), server);
+        assert_eq!(config.connection_max_lifetime(None), None);
+
+        let config = parse_host_with(dir, &["ec"], "connection_max_lifetime: 10m\n").unwrap();
+        let lifetime = Some(Duration::from_secs(600));
+        assert_eq!(config.connection_max_lifetime(server), lifetime);
+        assert_eq!(config.connection_max_lifetime(None), lifetime);
+
+        // zero will disable the server value
+        let config = parse_host_with(dir, &["ec"], "max_lifetime: 0\n").unwrap();
+        assert_eq!(config.connection_max_lifetime(server), None);
+    }
+
+    #[test]
+    fn shared_logger() {
+        let temp_dir = TempDir::new("openssl_host_shared_logger");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let config = parse_host(dir, &["ec"]);
+        assert!(config.shared_logger.is_none());
+
+        let config = parse_host_with(dir, &["ec"], "shared_logger: tenant-a\n").unwrap();
+        assert_eq!(config.shared_logger.as_ref().unwrap().as_str(), "tenant-a");
+
+        assert!(parse_host_with(dir, &["ec"], "shared_logger: \"\"\n").is_err());
+        assert!(parse_host_with(dir, &["ec"], "shared_logger: \"租户\"\n").is_err());
+    }
+
+    #[test]
+    fn upstream_proxy_protocol() {
+        let temp_dir = TempDir::new("openssl_host_upstream_proxy_protocol");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let config = parse_host(dir, &["ec"]);
+        assert!(config.upstream_proxy_protocol.is_none());
+        let config = parse_host_with(dir, &["ec"], "upstream_proxy_protocol: v2\n").unwrap();
+        assert_eq!(
+            config.upstream_proxy_protocol,
+            Some(ProxyProtocolVersion::V2)
+        );
+
+        assert!(parse_host_with(dir, &["ec"], "upstream_proxy_protocol: v1\n").is_err());
+        assert!(
+            parse_host_with(
+                dir,
+                &["ec"],
+                "upstream_proxy_protocol: 2\nbackend_pool:\n  max_idle: 4\n"
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn backend_protocol() {
+        let temp_dir = TempDir::new("openssl_host_backend_protocol");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let config = parse_host(dir, &["ec"]);
+        assert_eq!(config.backend_protocol, OpensslBackendProtocol::Tcp);
+        let config = parse_host_with(
+            dir,
+            &["ec"],
+            "backend_protocol: h2\nbackend_h2:\n  max_streams_per_connection: 2\n",
+        )
+        .unwrap();
+        assert_eq!(config.backend_protocol, OpensslBackendProtocol::H2);
+        assert_eq!(config.backend_h2.max_streams_per_connection, 2);
+
+        assert!(parse_host_with(dir, &["ec"], "backend_protocol: h3\n").is_err());
+        assert!(
+            parse_host_with(
+                dir,
+                &["ec"],
+                "backend_protocol: h2\nupstream_proxy_protocol: 2\n"
+            )
+            .is_err()
+        );
+        assert!(
+            parse_host_with(dir, &["ec"], "backend_protocol: h2\nbackend_pool: true\n").is_err()
+        );
+    }
+
+    #[test]
+    fn http_aware() {
+        let temp_dir = TempDir::new("openssl_host_http_aware");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let config = parse_host(dir, &["ec"]);
+        assert!(config.http_aware.is_none());
+        let config = parse_host_with(
+            dir,
+            &["ec"],
+            "backend_pool: true\nhttp_aware:\n  max_pipeline_depth: 2\n",
+        )
+        .unwrap();
+        let http_aware = config.http_aware.unwrap();
+        assert_eq!(http_aware.max_pipeline_depth.get(), 2);
+
+        assert!(parse_host_with(dir, &["ec"], "http_aware: 1\n").is_err());
+        assert!(parse_host_with(dir, &["ec"], "http_aware: true\nearly_data: true\n").is_err());
+    }
+
+    #[test]
+    fn tcp_misc_opts_per_host() {
+        let temp_dir = TempDir::new("openssl_host_tcp_misc_opts");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        let server = TcpMiscSockOpts {
+            no_delay: Some(true),
+            time_to_live: Some(64),
+            ..Default::default()
+        };
+
+        let config = parse_host(dir, &["ec"]);
+        assert_eq!(config.tcp_misc_opts(&server), server);
+
+        let config =
+            parse_host_with(dir, &["ec"], "tcp_misc_opts:\n  ttl: 32\n  tos: 32\n").unwrap();
+        let opts = config.tcp_misc_opts(&server);
+        assert_eq!(opts.no_delay, Some(true));
+        assert_eq!(opts.time_to_live, Some(32));
+        assert_eq!(opts.type_of_service, Some(32));
+    }
+
+    fn write_client_ca(dir: &Path) -> (X509, PKey<Private>) {
+        let key = ec_key();
+        let cert = ca_cert(&common_name("test client ca"), &key);
+        write_cert(&dir.join("client_ca.crt"), &cert);
+        (cert, key)
+    }
+
+    fn client_cert(ca: &(X509, PKey<Private>), ou: &str, serial: u32) -> (X509, PKey<Private>) {
+        let mut name_builder = X509NameBuilder::new().unwrap();
+        name_builder
+            .append_entry_by_nid(Nid::ORGANIZATIONALUNITNAME, ou)
+            .unwrap();
+        name_builder
+            .append_entry_by_nid(Nid::COMMONNAME, "client.example.net")
+            .unwrap();
+        let key = ec_key();
+        let cert = issued_cert(&name_builder.build(), &key, (&ca.0, &ca.1), serial);
+        (cert, key)
+    }
+
+    /// Return the backend selected by the client cert router,
+    /// or Err if the handshake is rejected by the server
+    fn routed_backend(
+        config: &OpensslHostConfig,
+        client: &(X509, PKey<Private>),
+    ) -> Result<Option<String>, ()> {
+        let ssl_context = config.build_ssl_context(None).unwrap().unwrap();
+        let (server_sock, client_sock) = UnixStream::pair().unwrap();
+        let ssl = Ssl::new(&ssl_context).unwrap();
+        let server = std::thread::spawn(move || {
+            let stream = ssl.accept(server_sock).map_err(|_| ())?;
+            Ok(stream.ssl().peer_certificate())
+        });
+
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        builder.set_certificate(&client.0).unwrap();
+        builder.set_private_key(&client.1).unwrap();
+        let connector = builder.build();
+        let _stream = connector.connect("test.example.net", client_sock);
+
+        let peer_cert = server.join().unwrap()?;
+        let router = config.client_cert_router.as_ref().unwrap();
+        Ok(peer_cert
+            .and_then(|cert| router.select(&cert))
+            .map(|i| router.rules[i].backend.to_string()))
+    }
+
+    const CLIENT_CERT_ROUTER_YAML: &str = "\
+enable_client_auth: true
+ca_cert: client_ca.crt
+client_cert_router:
+  require_match: {require_match}
+  rules:
+    - name: tenant-a
+      subject:
+        OU: tenant-a
+      backend: backend-a
+    - name: tenant-b
+      subject:
+        OU: [tenant-b, tenant-b-legacy]
+      backend: backend-b
+";
+
+    #[test]
+    fn client_cert_route_by_ou() {
+        let temp_dir = TempDir::new("openssl_host_client_cert_route");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+        let ca = write_client_ca(dir);
+
+        let client_a = client_cert(&ca, "tenant-a", 2);
+        let client_b = client_cert(&ca, "tenant-b", 3);
+        let client_b_legacy = client_cert(&ca, "tenant-b-legacy", 4);
+        let client_other = client_cert(&ca, "tenant-c", 5);
+
+        let extra = CLIENT_CERT_ROUTER_YAML.replace("{require_match}", "false");
+        let config = parse_host_with(dir, &["ec"], &extra).unwrap();
+        assert_eq!(
+            routed_backend(&config, &client_a),
+            Ok(Some("backend-a".to_string()))
+        );
+        assert_eq!(
+            routed_backend(&config, &client_b),
+            Ok(Some("backend-b".to_string()))
+        );
+        assert_eq!(
+            routed_backend(&config, &client_b_legacy),
+            Ok(Some("backend-b".to_string()))
+        );
+        // fall through to the default backend
+        assert_eq!(routed_backend(&config, &client_other), Ok(None));
+
+        let extra = CLIENT_CERT_ROUTER_YAML.replace("{require_match}", "true");
+        let config = parse_host_with(dir, &["ec"], &extra).unwrap();
+        assert_eq!(
+            routed_backend(&config, &client_a),
+            Ok(Some("backend-a".to_string()))
+        );
+        assert!(routed_backend(&config, &client_other).is_err());
+    }
+
+    #[test]
+    fn invalid_client_cert_router() {
+        let temp_dir = TempDir::new("openssl_host_invalid_client_cert_route");
+        let dir = temp_dir.path();
+        write_host_cert_pair(dir, "ec", ec_key());
+
+        // client auth is required
+        let router = "client_cert_router:\n  - name: a\n    subject: {OU: a}\n    backend: a\n";
+        assert!(parse_host_with(dir, &["ec"], router).is_err());
+
+        let rules = [
+            // no match condition
+            "  - name: a\n    backend: a\n",
+            // unknown subject attribute
+            "  - name: a\n    subject: {NO_SUCH_ATTR: a}\n    backend: a\n",
+            // duplicated rule name
+            "  - name: a\n    subject: {OU: a}\n    backend: a\n  - name: a\n    san_dns: a.example.net\n    backend: b\n",
+        ];
+        for rules in rules {
+            let yaml = format!("enable_client_auth: true\nclient_cert_router:\n{rules}");
+            assert!(parse_host_with(dir, &["ec"], &yaml).is_err());
+        }
+
+        let yaml = "enable_client_auth: true\nclient_cert_router:\n  alert: access_denied\n  rules:\n    - name: a\n      subject: {OU: a}\n      backend: a\n";
+        assert!(parse_host_with(dir, &["ec"], yaml).is_err());
+        let yaml = yaml.replace("access_denied", "unknown_ca");
+        assert!(parse_host_with(dir, &["ec"], &yaml).is_ok());
+    }
+}
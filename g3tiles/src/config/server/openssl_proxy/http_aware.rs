@@ -0,0 +1,115 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::num::NonZeroUsize;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+const DEFAULT_MAX_PIPELINE_DEPTH: NonZeroUsize = NonZeroUsize::new(4).unwrap();
+const DEFAULT_MAX_HEADER_SIZE: usize = 64 * 1024;
+const DEFAULT_BODY_LINE_MAX_LENGTH: usize = 8192;
+
+/// The HTTP/1.1 aware relay config of an openssl proxy host
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct OpensslHttpAwareConfig {
+    /// the max number of requests sent to the backend that have not been fully responded
+    pub(crate) max_pipeline_depth: NonZeroUsize,
+    pub(crate) max_header_size: usize,
+    pub(crate) body_line_max_len: usize,
+}
+
+impl Default for OpensslHttpAwareConfig {
+    fn default() -> Self {
+        OpensslHttpAwareConfig {
+            max_pipeline_depth: DEFAULT_MAX_PIPELINE_DEPTH,
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+            body_line_max_len: DEFAULT_BODY_LINE_MAX_LENGTH,
+        }
+    }
+}
+
+impl OpensslHttpAwareConfig {
+    pub(crate) fn parse_yaml(v: &Yaml) -> anyhow::Result<Option<Self>> {
+        let mut config = OpensslHttpAwareConfig::default();
+        match v {
+            Yaml::Boolean(true) => return Ok(Some(config)),
+            Yaml::Boolean(false) => return Ok(None),
+            Yaml::Hash(map) => {
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "max_pipeline_depth" | "pipeline_depth" => {
+                        config.max_pipeline_depth = g3_yaml::value::as_nonzero_usize(v)
+                            .context(format!("invalid nonzero usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "max_header_size" => {
+                        config.max_header_size = g3_yaml::humanize::as_usize(v)
+                            .context(format!("invalid humanize usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    "body_line_max_length" | "body_line_max_len" => {
+                        config.body_line_max_len = g3_yaml::value::as_usize(v)
+                            .context(format!("invalid usize value for key {k}"))?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "yaml value type for http aware config should be 'bool' or 'map'"
+                ));
+            }
+        }
+
+        config.check()?;
+        Ok(Some(config))
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.max_header_size == 0 {
+            return Err(anyhow!("max header size should not be zero"));
+        }
+        if self.body_line_max_len == 0 {
+            return Err(anyhow!("body line max length should not be zero"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<Option<OpensslHttpAwareConfig>> {
+        let yaml = YamlLoader::load_from_str(s).unwrap();
+        OpensslHttpAwareConfig::parse_yaml(&yaml[0])
+    }
+
+    #[test]
+    fn parse_ok() {
+        assert_eq!(
+            parse("true").unwrap(),
+            Some(OpensslHttpAwareConfig::default())
+        );
+        assert!(parse("false").unwrap().is_none());
+
+        let config = parse("max_pipeline_depth: 2\nmax_header_size: 16KiB\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.max_pipeline_depth.get(), 2);
+        assert_eq!(config.max_header_size, 16 * 1024);
+        assert_eq!(config.body_line_max_len, DEFAULT_BODY_LINE_MAX_LENGTH);
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(parse("1").is_err());
+        assert!(parse("max_pipeline_depth: 0\n").is_err());
+        assert!(parse("max_header_size: 0\n").is_err());
+        assert!(parse("no_such_key: 1\n").is_err());
+    }
+}
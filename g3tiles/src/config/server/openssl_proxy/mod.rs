@@ -3,6 +3,8 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::num::NonZeroU32;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -10,25 +12,276 @@ use anyhow::{Context, anyhow};
 use ascii::AsciiString;
 use yaml_rust::{Yaml, yaml};
 
+use g3_daemon::listen::ListenWorkerConfig;
+use g3_daemon::server::TransferMetricsConfig;
 use g3_io_ext::StreamCopyConfig;
 use g3_tls_ticket::TlsTicketConfig;
 use g3_types::acl::AclNetworkRuleBuilder;
+use g3_types::limit::RateLimitQuotaConfig;
 use g3_types::metrics::{MetricTagMap, NodeName};
-use g3_types::net::{TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig};
+use g3_types::net::{
+    ProxyProtocolVersion, TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig, TlsCertStatus,
+};
 use g3_types::route::HostMatch;
 use g3_yaml::YamlDocPosition;
+use g3_yaml::schema::{YamlKeySchema, YamlMapSchema, YamlValueKind};
 
 use super::{
-    IDLE_CHECK_DEFAULT_DURATION, IDLE_CHECK_DEFAULT_MAX_COUNT, IDLE_CHECK_MAXIMUM_DURATION,
-    ServerConfig,
+    ClientErrorActionMap, IDLE_CHECK_DEFAULT_DURATION, IDLE_CHECK_DEFAULT_MAX_COUNT,
+    IDLE_CHECK_MAXIMUM_DURATION, ServerConfig, TaskIdleOverrides,
 };
 use crate::config::server::{AnyServerConfig, ServerConfigDiffAction};
 
+mod client_cert;
+pub(crate) use client_cert::OpensslClientCertRouterConfig;
+
 mod host;
-pub(crate) use host::OpensslHostConfig;
+pub(crate) use host::{OpensslCertKeyType, OpensslCertPairFiles, OpensslHostConfig};
+
+mod early_data;
+pub(crate) use early_data::OpensslEarlyDataConfig;
+
+mod backend_pool;
+pub(crate) use backend_pool::OpensslBackendPoolConfig;
+
+mod http_aware;
+pub(crate) use http_aware::OpensslHttpAwareConfig;
+
+mod backend_h2;
+pub(crate) use backend_h2::{OpensslBackendH2Config, OpensslBackendProtocol};
+
+mod health_check;
+pub(crate) use health_check::{
+    OpensslHealthCheckConfig, OpensslHealthProbe, OpensslUnhealthyAction,
+};
+
+mod client_ip_limit;
+pub(crate) use client_ip_limit::OpensslClientIpLimitConfig;
+
+mod client_hello_buffer;
+pub(crate) use client_hello_buffer::{
+    OpensslClientHelloBufferOverflow, OpensslClientHelloBufferPoolConfig,
+};
+
+mod resolver;
+pub(crate) use resolver::{OpensslCertResolverBackendConfig, OpensslCertResolverConfig};
+
+mod session_sni;
+#[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+pub(crate) use session_sni::OpensslSessionSniCheck;
+pub(crate) use session_sni::SessionSniMismatchPolicy;
+
+mod strict_sni;
+pub(crate) use strict_sni::{SniIpLiteralPolicy, StrictSniAction};
 
 const SERVER_CONFIG_TYPE: &str = "OpensslProxy";
 
+pub(crate) const SERVER_CONFIG_SCHEMA: YamlMapSchema = YamlMapSchema::new("openssl_proxy", KEYS);
+
+const KEYS: &[YamlKeySchema] = &[
+    YamlKeySchema::new(
+        super::CONFIG_KEY_SERVER_TYPE,
+        YamlValueKind::String,
+        "openssl_proxy",
+    ),
+    YamlKeySchema::new(super::CONFIG_KEY_SERVER_NAME, YamlValueKind::String, "tls"),
+    YamlKeySchema::new("shared_logger", YamlValueKind::String, "shared"),
+    YamlKeySchema::new("extra_metrics_tags", YamlValueKind::Map, "{cluster: test}"),
+    YamlKeySchema::new(
+        "listen",
+        YamlValueKind::Object("tcp_listen"),
+        "127.0.0.1:1234",
+    ),
+    YamlKeySchema::new("listen_in_worker", YamlValueKind::Bool, "true").default_value("false"),
+    YamlKeySchema::new("listen_worker_set", YamlValueKind::Seq, "[0, 1]"),
+    YamlKeySchema::new(
+        "ingress_network_filter",
+        YamlValueKind::Object("network_acl_rule"),
+        "{default: allow}",
+    )
+    .aliases(&["ingress_net_filter"]),
+    YamlKeySchema::new(
+        "client_hello_recv_timeout",
+        YamlValueKind::HumanizeDuration,
+        "5s",
+    )
+    .default_value("10s"),
+    YamlKeySchema::new(
+        "client_hello_max_size",
+        YamlValueKind::HumanizeInteger,
+        "32K",
+    )
+    .default_value("16K"),
+    YamlKeySchema::new(
+        "client_hello_buffer_pool",
+        YamlValueKind::Object("openssl_client_hello_buffer_pool"),
+        "{pool_size: 4096, overflow: wait}",
+    ),
+    YamlKeySchema::new("accept_timeout", YamlValueKind::HumanizeDuration, "30s")
+        .aliases(&["handshake_timeout", "negotiation_timeout"])
+        .default_value("60s"),
+    YamlKeySchema::new(
+        "virtual_hosts",
+        YamlValueKind::Object("host_matched_openssl_host"),
+        "[]",
+    )
+    .aliases(&["hosts"]),
+    YamlKeySchema::new(
+        "cert_resolver",
+        YamlValueKind::Object("openssl_cert_resolver"),
+        "{unix_socket: /run/cert-resolver.sock, template_host: default}",
+    ),
+    YamlKeySchema::new(
+        "tcp_sock_speed_limit",
+        YamlValueKind::Object("tcp_sock_speed_limit"),
+        "10M",
+    )
+    .aliases(&["tcp_conn_speed_limit"]),
+    YamlKeySchema::new(
+        "task_idle_check_duration",
+        YamlValueKind::HumanizeDuration,
+        "30s",
+    )
+    .default_value("60s"),
+    YamlKeySchema::new("task_idle_max_count", YamlValueKind::Integer, "10").default_value("5"),
+    YamlKeySchema::new(
+        "idle_overrides",
+        YamlValueKind::Object("task_idle_overrides"),
+        "[{net: 10.0.0.0/8, max_count: 10}]",
+    )
+    .aliases(&["task_idle_overrides"]),
+    YamlKeySchema::new(
+        "connection_max_lifetime",
+        YamlValueKind::HumanizeDuration,
+        "8h",
+    )
+    .aliases(&["max_lifetime"]),
+    YamlKeySchema::new(
+        "connection_lifetime_grace",
+        YamlValueKind::HumanizeDuration,
+        "30s",
+    )
+    .aliases(&["lifetime_grace_period"])
+    .default_value("10s"),
+    YamlKeySchema::new(
+        "client_error_actions",
+        YamlValueKind::Object("client_error_actions"),
+        "{upstream_connect_refused: reset, idle_killed: tls_alert}",
+    ),
+    YamlKeySchema::new("flush_task_log_on_created", YamlValueKind::Bool, "true")
+        .default_value("false"),
+    YamlKeySchema::new("flush_task_log_on_connected", YamlValueKind::Bool, "true")
+        .default_value("false"),
+    YamlKeySchema::new(
+        "task_log_flush_interval",
+        YamlValueKind::HumanizeDuration,
+        "10s",
+    ),
+    YamlKeySchema::new(
+        "tcp_copy_buffer_size",
+        YamlValueKind::HumanizeInteger,
+        "32K",
+    )
+    .default_value("16K"),
+    YamlKeySchema::new("tcp_copy_yield_size", YamlValueKind::HumanizeInteger, "2M")
+        .default_value("1M"),
+    YamlKeySchema::new(
+        "tcp_misc_opts",
+        YamlValueKind::Object("tcp_misc_sock_opts"),
+        "{no_delay: true}",
+    ),
+    YamlKeySchema::new(
+        "client_misc_opts",
+        YamlValueKind::Object("tcp_misc_sock_opts"),
+        "{no_delay: true}",
+    ),
+    YamlKeySchema::new(
+        "upstream_misc_opts",
+        YamlValueKind::Object("tcp_misc_sock_opts"),
+        "{no_delay: false}",
+    ),
+    YamlKeySchema::new(
+        "tls_ticketer",
+        YamlValueKind::Object("tls_ticketer"),
+        "{check_interval: 300s}",
+    ),
+    YamlKeySchema::new(
+        "detailed_transfer_metrics",
+        YamlValueKind::Object("transfer_metrics"),
+        "true",
+    ),
+    YamlKeySchema::new("session_sni_mismatch", YamlValueKind::String, "reject")
+        .aliases(&["session_sni_mismatch_policy"])
+        .default_value("full_handshake"),
+    YamlKeySchema::new("strict_sni", YamlValueKind::String, "reject"),
+    YamlKeySchema::new("sni_ip_literal", YamlValueKind::String, "default").default_value("match"),
+    #[cfg(feature = "openssl-async-job")]
+    YamlKeySchema::new("tls_no_async_mode", YamlValueKind::Bool, "true").default_value("false"),
+    YamlKeySchema::new("spawn_task_unconstrained", YamlValueKind::Bool, "true")
+        .aliases(&["task_unconstrained"])
+        .default_value("false"),
+    YamlKeySchema::new("alert_unrecognized_name", YamlValueKind::Bool, "true")
+        .default_value("false"),
+    YamlKeySchema::new("max_connections", YamlValueKind::Integer, "1000")
+        .aliases(&["max_conn"])
+        .default_value("0"),
+    YamlKeySchema::new(
+        "accept_rate_limit",
+        YamlValueKind::Object("rate_limit_quota"),
+        "100/s",
+    ),
+    YamlKeySchema::new("accept_error_log_rate", YamlValueKind::Integer, "10"),
+    YamlKeySchema::new("alert_conn_limited", YamlValueKind::Bool, "true").default_value("false"),
+    YamlKeySchema::new(
+        "client_ip_limit",
+        YamlValueKind::Object("openssl_client_ip_limit"),
+        "{threshold: 32, timeout_threshold: 4}",
+    ),
+    YamlKeySchema::new("max_concurrent_handshakes", YamlValueKind::Integer, "256"),
+    YamlKeySchema::new("handshake_queue_length", YamlValueKind::Integer, "1024")
+        .default_value("4096"),
+    YamlKeySchema::new("ingress_proxy_protocol", YamlValueKind::String, "auto"),
+    YamlKeySchema::new(
+        "ingress_proxy_protocol_read_timeout",
+        YamlValueKind::HumanizeDuration,
+        "10s",
+    )
+    .default_value("5s"),
+];
+
+const DEFAULT_MAX_CONCURRENT_HANDSHAKES: usize = 1024;
+const DEFAULT_HANDSHAKE_QUEUE_LENGTH: usize = 4096;
+
+/// The PROXY protocol version expected before the TLS ClientHello message
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum IngressProxyProtocol {
+    V1,
+    V2,
+    /// detect the version, or no PROXY protocol header at all, by the first byte
+    Auto,
+}
+
+impl From<ProxyProtocolVersion> for IngressProxyProtocol {
+    fn from(value: ProxyProtocolVersion) -> Self {
+        match value {
+            ProxyProtocolVersion::V1 => IngressProxyProtocol::V1,
+            ProxyProtocolVersion::V2 => IngressProxyProtocol::V2,
+        }
+    }
+}
+
+impl FromStr for IngressProxyProtocol {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            Ok(IngressProxyProtocol::Auto)
+        } else {
+            ProxyProtocolVersion::from_str(s).map(IngressProxyProtocol::from)
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct OpensslProxyServerConfig {
     name: NodeName,
@@ -36,25 +289,46 @@ pub(crate) struct OpensslProxyServerConfig {
     pub(crate) shared_logger: Option<AsciiString>,
     pub(crate) listen: TcpListenConfig,
     pub(crate) listen_in_worker: bool,
+    pub(crate) listen_worker: ListenWorkerConfig,
     pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
     pub(crate) extra_metrics_tags: Option<Arc<MetricTagMap>>,
     pub(crate) client_hello_recv_timeout: Duration,
     pub(crate) client_hello_max_size: u32,
+    pub(crate) client_hello_buffer_pool: Option<OpensslClientHelloBufferPoolConfig>,
     pub(crate) accept_timeout: Duration,
     pub(crate) hosts: HostMatch<Arc<OpensslHostConfig>>,
+    pub(crate) cert_resolver: Option<OpensslCertResolverConfig>,
     pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
     pub(crate) task_idle_check_duration: Duration,
     pub(crate) task_idle_max_count: usize,
+    pub(crate) idle_overrides: TaskIdleOverrides,
+    pub(crate) connection_max_lifetime: Option<Duration>,
+    pub(crate) connection_lifetime_grace: Duration,
+    pub(crate) client_error_actions: ClientErrorActionMap,
     pub(crate) flush_task_log_on_created: bool,
     pub(crate) flush_task_log_on_connected: bool,
     pub(crate) task_log_flush_interval: Option<Duration>,
     pub(crate) tcp_copy: StreamCopyConfig,
-    pub(crate) tcp_misc_opts: TcpMiscSockOpts,
+    pub(crate) client_misc_opts: TcpMiscSockOpts,
+    pub(crate) upstream_misc_opts: Option<TcpMiscSockOpts>,
     pub(crate) tls_ticketer: Option<TlsTicketConfig>,
+    pub(crate) detailed_transfer_metrics: Option<TransferMetricsConfig>,
+    pub(crate) session_sni_mismatch: SessionSniMismatchPolicy,
+    pub(crate) strict_sni: Option<StrictSniAction>,
+    pub(crate) sni_ip_literal: SniIpLiteralPolicy,
     #[cfg(feature = "openssl-async-job")]
     pub(crate) tls_no_async_mode: bool,
     pub(crate) spawn_task_unconstrained: bool,
     pub(crate) alert_unrecognized_name: bool,
+    pub(crate) max_connections: usize,
+    pub(crate) accept_rate_limit: Option<RateLimitQuotaConfig>,
+    pub(crate) alert_conn_limited: bool,
+    pub(crate) client_ip_limit: Option<OpensslClientIpLimitConfig>,
+    max_concurrent_handshakes: Option<usize>,
+    pub(crate) handshake_queue_length: usize,
+    pub(crate) ingress_proxy_protocol: Option<IngressProxyProtocol>,
+    pub(crate) ingress_proxy_protocol_read_timeout: Duration,
+    pub(crate) accept_error_log_rate: Option<NonZeroU32>,
 }
 
 impl OpensslProxyServerConfig {
@@ -65,28 +339,64 @@ impl OpensslProxyServerConfig {
             shared_logger: None,
             listen: TcpListenConfig::default(),
             listen_in_worker: false,
+            listen_worker: ListenWorkerConfig::default(),
             ingress_net_filter: None,
             extra_metrics_tags: None,
             client_hello_recv_timeout: Duration::from_secs(10),
             client_hello_max_size: 16384, // 16K
+            client_hello_buffer_pool: None,
             accept_timeout: Duration::from_secs(60),
             hosts: HostMatch::default(),
+            cert_resolver: None,
             tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
             task_idle_check_duration: IDLE_CHECK_DEFAULT_DURATION,
             task_idle_max_count: IDLE_CHECK_DEFAULT_MAX_COUNT,
+            idle_overrides: TaskIdleOverrides::default(),
+            connection_max_lifetime: None,
+            connection_lifetime_grace: Duration::from_secs(10),
+            client_error_actions: ClientErrorActionMap::default(),
             flush_task_log_on_created: false,
             flush_task_log_on_connected: false,
             task_log_flush_interval: None,
             tcp_copy: Default::default(),
-            tcp_misc_opts: Default::default(),
+            client_misc_opts: Default::default(),
+            upstream_misc_opts: None,
             tls_ticketer: None,
+            detailed_transfer_metrics: None,
+            session_sni_mismatch: SessionSniMismatchPolicy::default(),
+            strict_sni: None,
+            sni_ip_literal: SniIpLiteralPolicy::default(),
             #[cfg(feature = "openssl-async-job")]
             tls_no_async_mode: false,
             spawn_task_unconstrained: false,
             alert_unrecognized_name: false,
+            max_connections: 0,
+            accept_rate_limit: None,
+            alert_conn_limited: false,
+            client_ip_limit: None,
+            max_concurrent_handshakes: None,
+            handshake_queue_length: DEFAULT_HANDSHAKE_QUEUE_LENGTH,
+            ingress_proxy_protocol: None,
+            ingress_proxy_protocol_read_timeout: Duration::from_secs(5),
+            accept_error_log_rate: None,
         }
     }
 
+    /// Get the max number of concurrent TLS handshakes, 0 means no limit.
+    ///
+    /// The handshakes won't block the worker threads in async mode,
+    /// so there will be no limit unless it is explicitly set.
+    pub(crate) fn max_concurrent_handshakes(&self) -> usize {
+        if let Some(max) = self.max_concurrent_handshakes {
+            return max;
+        }
+        #[cfg(feature = "openssl-async-job")]
+        if !self.tls_no_async_mode {
+            return 0;
+        }
+        DEFAULT_MAX_CONCURRENT_HANDSHAKES
+    }
+
     pub(super) fn parse(
         map: &yaml::Hash,
         position: Option<YamlDocPosition>,
@@ -106,14 +416,33 @@ impl OpensslProxyServerConfig {
         if self.hosts.is_empty() {
             return Err(anyhow!("no host config set"));
         }
+        if let Some(resolver) = &self.cert_resolver {
+            if !self.hosts.get_all_values().contains_key(&resolver.host) {
+                return Err(anyhow!(
+                    "template host {} for cert resolver is not found",
+                    resolver.host
+                ));
+            }
+        }
+        if let Some(floor) = self.hosts.values().map(|h| h.tls_version_floor()).min() {
+            // all hosts share the same version negotiation before the host floor is checked
+            self.hosts = self.hosts.try_build_arc(|host| {
+                let mut host = host.as_ref().clone();
+                host.set_accept_min_version(floor);
+                Ok::<_, anyhow::Error>(host)
+            })?;
+        }
         if self.task_idle_check_duration > IDLE_CHECK_MAXIMUM_DURATION {
             self.task_idle_check_duration = IDLE_CHECK_MAXIMUM_DURATION;
         }
+        self.listen_worker
+            .check()
+            .context("invalid listen worker config")?;
         Ok(())
     }
 
     fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
-        match g3_yaml::key::normalize(k).as_str() {
+        match SERVER_CONFIG_SCHEMA.canonical_key(k)? {
             super::CONFIG_KEY_SERVER_TYPE => Ok(()),
             super::CONFIG_KEY_SERVER_NAME => {
                 self.name = g3_yaml::value::as_metric_node_name(v)?;
@@ -139,7 +468,11 @@ impl OpensslProxyServerConfig {
                 self.listen_in_worker = g3_yaml::value::as_bool(v)?;
                 Ok(())
             }
-            "ingress_network_filter" | "ingress_net_filter" => {
+            "listen_worker_set" => self
+                .listen_worker
+                .set_worker_set(v)
+                .context(format!("invalid worker set value for key {k}")),
+            "ingress_network_filter" => {
                 let filter = g3_yaml::value::acl::as_ingress_network_rule_builder(v).context(
                     format!("invalid ingress network acl rule value for key {k}"),
                 )?;
@@ -156,16 +489,29 @@ impl OpensslProxyServerConfig {
                     .context(format!("invalid humanize u32 value for key {k}"))?;
                 Ok(())
             }
-            "accept_timeout" | "handshake_timeout" | "negotiation_timeout" => {
+            "client_hello_buffer_pool" => {
+                let pool = OpensslClientHelloBufferPoolConfig::parse_yaml(v).context(format!(
+                    "invalid client hello buffer pool config value for key {k}"
+                ))?;
+                self.client_hello_buffer_pool = pool;
+                Ok(())
+            }
+            "accept_timeout" => {
                 self.accept_timeout = g3_yaml::humanize::as_duration(v)
                     .context(format!("invalid humanize duration value for key {k}"))?;
                 Ok(())
             }
-            "virtual_hosts" | "hosts" => {
+            "virtual_hosts" => {
                 self.hosts = g3_yaml::value::as_host_matched_obj(v, self.position.as_ref())?;
                 Ok(())
             }
-            "tcp_sock_speed_limit" | "tcp_conn_speed_limit" => {
+            "cert_resolver" => {
+                let resolver = OpensslCertResolverConfig::parse_yaml(v, self.position.as_ref())
+                    .context(format!("invalid openssl cert resolver value for key {k}"))?;
+                self.cert_resolver = Some(resolver);
+                Ok(())
+            }
+            "tcp_sock_speed_limit" => {
                 self.tcp_sock_speed_limit = g3_yaml::value::as_tcp_sock_speed_limit(v)
                     .context(format!("invalid tcp socket speed limit value for key {k}"))?;
                 Ok(())
@@ -180,6 +526,27 @@ impl OpensslProxyServerConfig {
                     .context(format!("invalid usize value for key {k}"))?;
                 Ok(())
             }
+            "idle_overrides" => {
+                self.idle_overrides = TaskIdleOverrides::parse_yaml(v)
+                    .context(format!("invalid task idle overrides value for key {k}"))?;
+                Ok(())
+            }
+            "connection_max_lifetime" => {
+                let lifetime = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                self.connection_max_lifetime = (!lifetime.is_zero()).then_some(lifetime);
+                Ok(())
+            }
+            "connection_lifetime_grace" => {
+                self.connection_lifetime_grace = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "client_error_actions" => {
+                self.client_error_actions = ClientErrorActionMap::parse_yaml(v)
+                    .context(format!("invalid client error actions value for key {k}"))?;
+                Ok(())
+            }
             "flush_task_log_on_created" => {
                 self.flush_task_log_on_created = g3_yaml::value::as_bool(v)?;
                 Ok(())
@@ -207,10 +574,24 @@ impl OpensslProxyServerConfig {
                 Ok(())
             }
             "tcp_misc_opts" => {
-                self.tcp_misc_opts = g3_yaml::value::as_tcp_misc_sock_opts(v)
+                // set for both the client and the backend sockets
+                let opts = g3_yaml::value::as_tcp_misc_sock_opts(v)
+                    .context(format!("invalid tcp misc sock opts value for key {k}"))?;
+                self.upstream_misc_opts = Some(opts.clone());
+                self.client_misc_opts = opts;
+                Ok(())
+            }
+            "client_misc_opts" => {
+                self.client_misc_opts = g3_yaml::value::as_tcp_misc_sock_opts(v)
                     .context(format!("invalid tcp misc sock opts value for key {k}"))?;
                 Ok(())
             }
+            "upstream_misc_opts" => {
+                let opts = g3_yaml::value::as_tcp_misc_sock_opts(v)
+                    .context(format!("invalid tcp misc sock opts value for key {k}"))?;
+                self.upstream_misc_opts = Some(opts);
+                Ok(())
+            }
             "tls_ticketer" => {
                 let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
                 let ticketer = TlsTicketConfig::parse_yaml(v, Some(lookup_dir))
@@ -218,12 +599,37 @@ impl OpensslProxyServerConfig {
                 self.tls_ticketer = Some(ticketer);
                 Ok(())
             }
+            "detailed_transfer_metrics" => {
+                self.detailed_transfer_metrics = TransferMetricsConfig::parse_yaml(v)
+                    .context(format!("invalid transfer metrics config value for key {k}"))?;
+                Ok(())
+            }
+            "session_sni_mismatch" => {
+                let s = g3_yaml::value::as_string(v)?;
+                self.session_sni_mismatch = SessionSniMismatchPolicy::from_str(&s).context(
+                    format!("invalid session sni mismatch policy value for key {k}"),
+                )?;
+                Ok(())
+            }
+            "strict_sni" => {
+                let s = g3_yaml::value::as_string(v)?;
+                let action = StrictSniAction::from_str(&s)
+                    .context(format!("invalid strict sni action value for key {k}"))?;
+                self.strict_sni = Some(action);
+                Ok(())
+            }
+            "sni_ip_literal" => {
+                let s = g3_yaml::value::as_string(v)?;
+                self.sni_ip_literal = SniIpLiteralPolicy::from_str(&s)
+                    .context(format!("invalid sni ip literal policy value for key {k}"))?;
+                Ok(())
+            }
             #[cfg(feature = "openssl-async-job")]
             "tls_no_async_mode" => {
                 self.tls_no_async_mode = g3_yaml::value::as_bool(v)?;
                 Ok(())
             }
-            "spawn_task_unconstrained" | "task_unconstrained" => {
+            "spawn_task_unconstrained" => {
                 self.spawn_task_unconstrained = g3_yaml::value::as_bool(v)?;
                 Ok(())
             }
@@ -232,6 +638,59 @@ impl OpensslProxyServerConfig {
                     .context(format!("invalid bool value for key {k}"))?;
                 Ok(())
             }
+            "max_connections" => {
+                self.max_connections = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                Ok(())
+            }
+            "accept_rate_limit" => {
+                let quota = g3_yaml::value::as_rate_limit_quota(v)
+                    .context(format!("invalid rate limit quota value for key {k}"))?;
+                self.accept_rate_limit = Some(quota);
+                Ok(())
+            }
+            "accept_error_log_rate" => {
+                let rate =
+                    g3_yaml::value::as_u32(v).context(format!("invalid u32 value for key {k}"))?;
+                self.accept_error_log_rate = NonZeroU32::new(rate);
+                Ok(())
+            }
+            "alert_conn_limited" => {
+                self.alert_conn_limited = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                Ok(())
+            }
+            "client_ip_limit" => {
+                self.client_ip_limit = OpensslClientIpLimitConfig::parse_yaml(v)
+                    .context(format!("invalid client ip limit config value for key {k}"))?;
+                Ok(())
+            }
+            "max_concurrent_handshakes" => {
+                let max = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                self.max_concurrent_handshakes = Some(max);
+                Ok(())
+            }
+            "handshake_queue_length" => {
+                self.handshake_queue_length = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                Ok(())
+            }
+            "ingress_proxy_protocol" => {
+                let p = if let Yaml::String(s) = v {
+                    IngressProxyProtocol::from_str(s)
+                } else {
+                    g3_yaml::value::as_proxy_protocol_version(v).map(IngressProxyProtocol::from)
+                }
+                .context(format!("invalid ingress proxy protocol value for key {k}"))?;
+                self.ingress_proxy_protocol = Some(p);
+                Ok(())
+            }
+            "ingress_proxy_protocol_read_timeout" => {
+                self.ingress_proxy_protocol_read_timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
@@ -259,10 +718,81 @@ impl ServerConfig for OpensslProxyServerConfig {
             return ServerConfigDiffAction::NoAction;
         }
 
-        if self.listen != new.listen {
+        if self.listen != new.listen || self.listen_worker != new.listen_worker {
             return ServerConfigDiffAction::ReloadAndRespawn;
         }
 
         ServerConfigDiffAction::ReloadNoRespawn
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::new();
+        for host in self.hosts.get_all_values().values() {
+            all.extend(host.cert_status()?);
+        }
+        Ok(all)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use yaml_rust::YamlLoader;
+
+    #[test]
+    fn schema_keys() {
+        let position = YamlDocPosition {
+            path: PathBuf::from("/etc/g3tiles/server.d/tls.yaml"),
+            index: 0,
+        };
+        for key in SERVER_CONFIG_SCHEMA.keys {
+            let v = &YamlLoader::load_from_str(key.example).unwrap()[0];
+            for name in std::iter::once(&key.name).chain(key.aliases) {
+                let mut config = OpensslProxyServerConfig::new(Some(position.clone()));
+                config
+                    .set(name, v)
+                    .unwrap_or_else(|e| panic!("invalid sample value for key {name}: {e:?}"));
+            }
+        }
+        assert!(SERVER_CONFIG_SCHEMA.canonical_key("no_such_key").is_err());
+    }
+
+    #[test]
+    fn misc_opts_per_direction() {
+        let load = |s: &str| YamlLoader::load_from_str(s).unwrap().remove(0);
+
+        let mut config = OpensslProxyServerConfig::new(None);
+        config
+            .set("tcp_misc_opts", &load("{no_delay: true, ttl: 32}"))
+            .unwrap();
+        assert_eq!(config.client_misc_opts.no_delay, Some(true));
+        let upstream = config.upstream_misc_opts.as_ref().unwrap();
+        assert_eq!(upstream, &config.client_misc_opts);
+
+        config
+            .set("upstream_misc_opts", &load("{no_delay: false}"))
+            .unwrap();
+        assert_eq!(config.client_misc_opts.time_to_live, Some(32));
+        let upstream = config.upstream_misc_opts.as_ref().unwrap();
+        assert_eq!(upstream.no_delay, Some(false));
+        assert_eq!(upstream.time_to_live, None);
+    }
+
+    #[test]
+    fn host_shared_logger_reload() {
+        let build_config = |shared_logger: &str| {
+            let mut host = OpensslHostConfig::default();
+            host.shared_logger = Some(AsciiString::from_ascii(shared_logger).unwrap());
+            let mut config = OpensslProxyServerConfig::new(None);
+            config.hosts.set_default(Arc::new(host));
+            config
+        };
+        let old = build_config("tenant-a");
+        let new = AnyServerConfig::OpensslProxy(build_config("tenant-b"));
+        assert!(matches!(
+            old.diff_action(&new),
+            ServerConfigDiffAction::ReloadNoRespawn
+        ));
+    }
 }
@@ -0,0 +1,109 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+use g3_yaml::YamlDocPosition;
+
+const DEFAULT_CACHE_SIZE: NonZeroUsize = NonZeroUsize::new(1024).unwrap();
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum OpensslCertResolverBackendConfig {
+    /// certificates are stored as `<dir>/<sni>/cert.pem` and `<dir>/<sni>/key.pem`
+    Directory(PathBuf),
+    /// certificates are queried from a unix stream socket
+    UnixSocket(PathBuf),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct OpensslCertResolverConfig {
+    pub(crate) backend: OpensslCertResolverBackendConfig,
+    pub(crate) host: String,
+    pub(crate) cache_size: NonZeroUsize,
+    pub(crate) cache_ttl: Duration,
+    pub(crate) negative_cache_ttl: Duration,
+    pub(crate) query_timeout: Duration,
+}
+
+impl OpensslCertResolverConfig {
+    fn new(backend: OpensslCertResolverBackendConfig) -> Self {
+        OpensslCertResolverConfig {
+            backend,
+            host: String::new(),
+            cache_size: DEFAULT_CACHE_SIZE,
+            cache_ttl: Duration::from_secs(3600),
+            negative_cache_ttl: Duration::from_secs(60),
+            query_timeout: Duration::from_secs(4),
+        }
+    }
+
+    pub(crate) fn parse_yaml(v: &Yaml, position: Option<&YamlDocPosition>) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for openssl cert resolver should be 'map'"
+            ));
+        };
+
+        let mut config = OpensslCertResolverConfig::new(
+            OpensslCertResolverBackendConfig::Directory(PathBuf::new()),
+        );
+        let mut backend_set = false;
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "directory" | "dir" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(position)?;
+                let path = g3_yaml::value::as_dir_path(v, lookup_dir, false)
+                    .context(format!("invalid directory path value for key {k}"))?;
+                config.backend = OpensslCertResolverBackendConfig::Directory(path);
+                backend_set = true;
+                Ok(())
+            }
+            "unix_socket" | "socket" => {
+                let path = g3_yaml::value::as_absolute_path(v)
+                    .context(format!("invalid absolute path value for key {k}"))?;
+                config.backend = OpensslCertResolverBackendConfig::UnixSocket(path);
+                backend_set = true;
+                Ok(())
+            }
+            "host" | "template_host" => {
+                config.host = g3_yaml::value::as_string(v)?;
+                Ok(())
+            }
+            "cache_size" => {
+                config.cache_size = g3_yaml::value::as_nonzero_usize(v)
+                    .context(format!("invalid nonzero usize value for key {k}"))?;
+                Ok(())
+            }
+            "cache_ttl" => {
+                config.cache_ttl = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "negative_cache_ttl" | "negative_ttl" => {
+                config.negative_cache_ttl = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "query_timeout" | "timeout" => {
+                config.query_timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        if !backend_set {
+            return Err(anyhow!("no resolver backend set"));
+        }
+        if config.host.is_empty() {
+            return Err(anyhow!("no template host set"));
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,374 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+/// The action to take if a client resumes a session with a SNI different from the original one
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum SessionSniMismatchPolicy {
+    /// resume the session as usual
+    Allow,
+    /// decline the resumption and do a full handshake
+    #[default]
+    FullHandshake,
+    /// abort the handshake
+    Reject,
+}
+
+impl FromStr for SessionSniMismatchPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match g3_yaml::key::normalize(s).as_str() {
+            "allow" => Ok(SessionSniMismatchPolicy::Allow),
+            "full_handshake" => Ok(SessionSniMismatchPolicy::FullHandshake),
+            "reject" => Ok(SessionSniMismatchPolicy::Reject),
+            _ => Err(anyhow!("unknown session sni mismatch policy {s}")),
+        }
+    }
+}
+
+#[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+mod check {
+    use std::sync::{Arc, OnceLock};
+
+    use anyhow::anyhow;
+    use openssl::ex_data::Index;
+    use openssl::ssl::{NameType, Ssl, SslContextBuilder, SslRef};
+
+    use g3_openssl::{SslSessionTicketHandler, SslTicketAction, SslTicketStatus};
+    use g3_types::net::TlsServerName;
+
+    use super::SessionSniMismatchPolicy;
+    use crate::module::stream::StreamServerStats;
+
+    const TICKET_APP_DATA_VERSION_1: u8 = 1;
+
+    /// Encode the original SNI into the ticket app data.
+    ///
+    /// The layout is `[version: u8][sni length: u16 BE][sni]`, and an empty SNI means no SNI.
+    pub(super) fn encode_ticket_app_data(sni: Option<&str>) -> Vec<u8> {
+        let sni = sni.unwrap_or_default().as_bytes();
+        let len = sni.len().min(u16::MAX as usize);
+        let mut buf = Vec::with_capacity(3 + len);
+        buf.push(TICKET_APP_DATA_VERSION_1);
+        buf.extend_from_slice(&(len as u16).to_be_bytes());
+        buf.extend_from_slice(&sni[..len]);
+        buf
+    }
+
+    /// Get the original SNI from the ticket app data, which will be empty if no SNI was sent.
+    ///
+    /// None will be returned for unknown versions, so the session won't be checked.
+    pub(super) fn decode_ticket_app_data(data: &[u8]) -> Option<&[u8]> {
+        let (&version, left) = data.split_first()?;
+        if version != TICKET_APP_DATA_VERSION_1 || left.len() < 2 {
+            return None;
+        }
+        let len = u16::from_be_bytes([left[0], left[1]]) as usize;
+        left[2..].get(..len)
+    }
+
+    /// The per connection data for the session SNI check
+    pub(crate) struct OpensslSessionSniCheck {
+        sni: Option<TlsServerName>,
+        policy: SessionSniMismatchPolicy,
+        server_stats: Arc<StreamServerStats>,
+    }
+
+    static SNI_CHECK_INDEX: OnceLock<Index<Ssl, OpensslSessionSniCheck>> = OnceLock::new();
+
+    impl OpensslSessionSniCheck {
+        pub(crate) fn new(
+            sni: Option<TlsServerName>,
+            policy: SessionSniMismatchPolicy,
+            server_stats: Arc<StreamServerStats>,
+        ) -> Self {
+            OpensslSessionSniCheck {
+                sni,
+                policy,
+                server_stats,
+            }
+        }
+
+        /// Attach to the SSL instance before the handshake
+        pub(crate) fn set_to_ssl(self, ssl: &mut SslRef) -> anyhow::Result<()> {
+            let index = match SNI_CHECK_INDEX.get() {
+                Some(index) => *index,
+                None => {
+                    let index = Ssl::new_ex_index()
+                        .map_err(|e| anyhow!("failed to create ex index: {e}"))?;
+                    *SNI_CHECK_INDEX.get_or_init(|| index)
+                }
+            };
+            ssl.set_ex_data(index, self);
+            Ok(())
+        }
+
+        fn get_from_ssl(ssl: &SslRef) -> Option<&Self> {
+            let index = SNI_CHECK_INDEX.get()?;
+            ssl.ex_data(*index)
+        }
+
+        fn sni_bytes(&self) -> &[u8] {
+            self.sni
+                .as_ref()
+                .map(|name| name.as_ref().as_bytes())
+                .unwrap_or_default()
+        }
+    }
+
+    struct SessionSniTicketHandler;
+
+    impl SslSessionTicketHandler for SessionSniTicketHandler {
+        fn ticket_app_data(&self, ssl: &mut SslRef) -> Option<Vec<u8>> {
+            let sni = match OpensslSessionSniCheck::get_from_ssl(ssl) {
+                Some(check) => check.sni.as_ref().map(|name| name.as_ref()),
+                None => ssl.servername(NameType::HOST_NAME),
+            };
+            Some(encode_ticket_app_data(sni))
+        }
+
+        fn check_ticket(
+            &self,
+            ssl: &mut SslRef,
+            status: SslTicketStatus,
+            app_data: Option<&[u8]>,
+        ) -> SslTicketAction {
+            let action = status.default_action();
+            if !matches!(action, SslTicketAction::Use | SslTicketAction::UseRenew) {
+                return action;
+            }
+            // tickets issued without the app data are resumed as usual
+            let Some(orig_sni) = app_data.and_then(decode_ticket_app_data) else {
+                return action;
+            };
+            let Some(check) = OpensslSessionSniCheck::get_from_ssl(ssl) else {
+                return action;
+            };
+            if orig_sni.eq_ignore_ascii_case(check.sni_bytes()) {
+                return action;
+            }
+
+            match check.policy {
+                SessionSniMismatchPolicy::Allow => {
+                    check.server_stats.add_session_sni_mismatch_allowed();
+                    action
+                }
+                SessionSniMismatchPolicy::FullHandshake => {
+                    check.server_stats.add_session_sni_mismatch_full_handshake();
+                    SslTicketAction::IgnoreRenew
+                }
+                SessionSniMismatchPolicy::Reject => {
+                    check.server_stats.add_session_sni_mismatch_rejected();
+                    SslTicketAction::Abort
+                }
+            }
+        }
+    }
+
+    /// Store the SNI in new session tickets and check it when the session is resumed
+    pub(crate) fn set_session_ticket_handler(
+        builder: &mut SslContextBuilder,
+    ) -> anyhow::Result<()> {
+        g3_openssl::set_session_ticket_handler(builder, Arc::new(SessionSniTicketHandler))
+            .map_err(|e| anyhow!("failed to set session ticket callback: {e}"))
+    }
+}
+
+#[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+pub(crate) use check::OpensslSessionSniCheck;
+#[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+pub(super) use check::set_session_ticket_handler;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_policy() {
+        assert_eq!(
+            SessionSniMismatchPolicy::from_str("allow").unwrap(),
+            SessionSniMismatchPolicy::Allow
+        );
+        assert_eq!(
+            SessionSniMismatchPolicy::from_str("Full-Handshake").unwrap(),
+            SessionSniMismatchPolicy::FullHandshake
+        );
+        assert_eq!(
+            SessionSniMismatchPolicy::from_str("reject").unwrap(),
+            SessionSniMismatchPolicy::Reject
+        );
+        assert!(SessionSniMismatchPolicy::from_str("deny").is_err());
+    }
+
+    #[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+    mod resumption {
+        use super::super::check::*;
+        use super::*;
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixStream;
+        use std::sync::{Arc, mpsc};
+
+        use openssl::ssl::{
+            Ssl, SslContext, SslMethod, SslSession, SslSessionCacheMode, SslVerifyMode,
+        };
+
+        use g3_types::metrics::NodeName;
+        use g3_types::net::TlsServerName;
+
+        use crate::module::stream::StreamServerStats;
+        use crate::serve::{ServerStats, SessionSniMismatchStats};
+        use crate::testing::server_acceptor_builder;
+
+        #[test]
+        fn ticket_app_data() {
+            let data = encode_ticket_app_data(Some("a.example.net"));
+            assert_eq!(
+                decode_ticket_app_data(&data),
+                Some(b"a.example.net".as_slice())
+            );
+            let data = encode_ticket_app_data(None);
+            assert_eq!(decode_ticket_app_data(&data), Some(b"".as_slice()));
+
+            assert!(decode_ticket_app_data(&[]).is_none());
+            assert!(decode_ticket_app_data(&[1, 0]).is_none());
+            assert!(decode_ticket_app_data(&[1, 0, 4, b'a']).is_none());
+            assert!(decode_ticket_app_data(&[2, 0, 1, b'a']).is_none());
+        }
+
+        fn server_name(host: &str) -> TlsServerName {
+            let name_len = host.len() as u16;
+            let mut buf = Vec::with_capacity(host.len() + 5);
+            buf.extend_from_slice(&(name_len + 3).to_be_bytes());
+            buf.push(0x00);
+            buf.extend_from_slice(&name_len.to_be_bytes());
+            buf.extend_from_slice(host.as_bytes());
+            TlsServerName::from_extension_value(&buf).unwrap()
+        }
+
+        fn server_context() -> SslContext {
+            let mut builder = server_acceptor_builder("example.net");
+            set_session_ticket_handler(&mut builder).unwrap();
+            builder.build().into_context()
+        }
+
+        /// Run the client in a new thread, and return the session ticket received
+        fn spawn_client(
+            sni: &'static str,
+            session: Option<SslSession>,
+            sock: UnixStream,
+        ) -> std::thread::JoinHandle<Option<SslSession>> {
+            std::thread::spawn(move || {
+                let (sender, receiver) = mpsc::channel();
+                let mut builder = SslContext::builder(SslMethod::tls_client()).unwrap();
+                builder.set_verify(SslVerifyMode::NONE);
+                builder.set_session_cache_mode(SslSessionCacheMode::CLIENT);
+                builder.set_new_session_callback(move |_, session| {
+                    let _ = sender.send(session);
+                });
+                let ctx = builder.build();
+
+                let mut ssl = Ssl::new(&ctx).unwrap();
+                ssl.set_hostname(sni).unwrap();
+                if let Some(session) = &session {
+                    unsafe { ssl.set_session(session).unwrap() };
+                }
+                let mut stream = ssl.connect(sock).ok()?;
+                let mut buf = Vec::new();
+                let _ = stream.read_to_end(&mut buf);
+                receiver.try_recv().ok()
+            })
+        }
+
+        /// Return whether the session is resumed, or None if the handshake failed
+        fn run_once(
+            ssl_context: &SslContext,
+            policy: SessionSniMismatchPolicy,
+            server_stats: &Arc<StreamServerStats>,
+            sni: &'static str,
+            session: Option<SslSession>,
+        ) -> (Option<bool>, Option<SslSession>) {
+            let (server_sock, client_sock) = UnixStream::pair().unwrap();
+            let client = spawn_client(sni, session, client_sock);
+
+            let mut ssl = Ssl::new(ssl_context).unwrap();
+            OpensslSessionSniCheck::new(Some(server_name(sni)), policy, server_stats.clone())
+                .set_to_ssl(&mut ssl)
+                .unwrap();
+            let reused = match ssl.accept(server_sock) {
+                Ok(mut stream) => {
+                    let reused = stream.ssl().session_reused();
+                    stream.write_all(b"ok").unwrap();
+                    let _ = stream.shutdown();
+                    Some(reused)
+                }
+                Err(_) => None,
+            };
+            let new_session = client.join().unwrap();
+            (reused, new_session)
+        }
+
+        fn check_policy(
+            policy: SessionSniMismatchPolicy,
+        ) -> (Option<bool>, Arc<StreamServerStats>) {
+            let ssl_context = server_context();
+            let stats = Arc::new(StreamServerStats::new(&NodeName::default()));
+            stats
+                .set_session_sni_mismatch_stats(Some(Arc::new(SessionSniMismatchStats::default())));
+
+            let (reused, session) = run_once(&ssl_context, policy, &stats, "a.example.net", None);
+            assert_eq!(reused, Some(false));
+            let session = session.unwrap();
+
+            // resume with the same SNI
+            let (reused, _) = run_once(
+                &ssl_context,
+                policy,
+                &stats,
+                "A.example.net",
+                Some(session.clone()),
+            );
+            assert_eq!(reused, Some(true));
+
+            // resume with another SNI
+            let (reused, _) =
+                run_once(&ssl_context, policy, &stats, "b.example.net", Some(session));
+            (reused, stats)
+        }
+
+        #[test]
+        fn allow() {
+            let (reused, stats) = check_policy(SessionSniMismatchPolicy::Allow);
+            assert_eq!(reused, Some(true));
+            let snap = stats.session_sni_mismatch_snapshot().unwrap();
+            assert_eq!(snap.allowed, 1);
+            assert_eq!(snap.full_handshake, 0);
+            assert_eq!(snap.rejected, 0);
+        }
+
+        #[test]
+        fn full_handshake() {
+            let (reused, stats) = check_policy(SessionSniMismatchPolicy::FullHandshake);
+            assert_eq!(reused, Some(false));
+            let snap = stats.session_sni_mismatch_snapshot().unwrap();
+            assert_eq!(snap.allowed, 0);
+            assert_eq!(snap.full_handshake, 1);
+            assert_eq!(snap.rejected, 0);
+        }
+
+        #[test]
+        fn reject() {
+            let (reused, stats) = check_policy(SessionSniMismatchPolicy::Reject);
+            assert!(reused.is_none());
+            let snap = stats.session_sni_mismatch_snapshot().unwrap();
+            assert_eq!(snap.allowed, 0);
+            assert_eq!(snap.full_handshake, 0);
+            assert_eq!(snap.rejected, 1);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+/// The action to take if the SNI is not a valid host name
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum StrictSniAction {
+    /// reject the connection
+    Reject,
+    /// select the host as if no SNI was sent
+    Ignore,
+}
+
+impl FromStr for StrictSniAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match g3_yaml::key::normalize(s).as_str() {
+            "reject" => Ok(StrictSniAction::Reject),
+            "ignore" => Ok(StrictSniAction::Ignore),
+            _ => Err(anyhow!("unknown strict sni action {s}")),
+        }
+    }
+}
+
+/// How to select the host if the SNI is an IP literal
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum SniIpLiteralPolicy {
+    /// match the IP against the ip hosts, and fallback to the default host
+    #[default]
+    Match,
+    /// use the default host directly
+    Default,
+    /// reject the connection
+    Reject,
+}
+
+impl FromStr for SniIpLiteralPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match g3_yaml::key::normalize(s).as_str() {
+            "match" => Ok(SniIpLiteralPolicy::Match),
+            "default" => Ok(SniIpLiteralPolicy::Default),
+            "reject" => Ok(SniIpLiteralPolicy::Reject),
+            _ => Err(anyhow!("unknown sni ip literal policy {s}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_action() {
+        assert_eq!(
+            StrictSniAction::from_str("reject").unwrap(),
+            StrictSniAction::Reject
+        );
+        assert_eq!(
+            StrictSniAction::from_str("Ignore").unwrap(),
+            StrictSniAction::Ignore
+        );
+        assert!(StrictSniAction::from_str("allow").is_err());
+    }
+
+    #[test]
+    fn parse_ip_literal_policy() {
+        assert_eq!(
+            SniIpLiteralPolicy::from_str("match").unwrap(),
+            SniIpLiteralPolicy::Match
+        );
+        assert_eq!(
+            SniIpLiteralPolicy::from_str("Default").unwrap(),
+            SniIpLiteralPolicy::Default
+        );
+        assert_eq!(
+            SniIpLiteralPolicy::from_str("reject").unwrap(),
+            SniIpLiteralPolicy::Reject
+        );
+        assert!(SniIpLiteralPolicy::from_str("ignore").is_err());
+    }
+}
@@ -12,7 +12,7 @@ use yaml_rust::{Yaml, yaml};
 use g3_tls_ticket::TlsTicketConfig;
 use g3_types::acl::AclNetworkRuleBuilder;
 use g3_types::metrics::NodeName;
-use g3_types::net::{RustlsServerConfigBuilder, UdpListenConfig};
+use g3_types::net::{RustlsServerConfigBuilder, TlsCertStatus, UdpListenConfig};
 use g3_yaml::YamlDocPosition;
 
 use super::ServerConfig;
@@ -177,4 +177,8 @@ impl ServerConfig for PlainQuicPortConfig {
         set.insert(self.server.clone());
         Some(set)
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        self.tls_server.cert_status()
+    }
 }
@@ -17,7 +17,7 @@ use g3_types::limit::RateLimitQuotaConfig;
 use g3_types::metrics::NodeName;
 use g3_types::net::{
     MultipleCertResolver, OpensslTicketKey, RollingTicketer, RustlsCertificatePair,
-    RustlsServerConfigExt, TcpSockSpeedLimitConfig,
+    RustlsServerConfigExt, TcpSockSpeedLimitConfig, TlsCertStatus,
 };
 use g3_types::route::AlpnMatch;
 use g3_yaml::{YamlDocPosition, YamlMapCallback};
@@ -26,6 +26,7 @@ use g3_yaml::{YamlDocPosition, YamlMapCallback};
 pub(crate) struct RustlsHostConfig {
     name: String,
     cert_pairs: Vec<RustlsCertificatePair>,
+    allow_expired: bool,
     client_auth: bool,
     client_auth_certs: Vec<CertificateDer<'static>>,
     use_session_ticket: bool,
@@ -43,6 +44,7 @@ impl Default for RustlsHostConfig {
         RustlsHostConfig {
             name: String::new(),
             cert_pairs: Vec::with_capacity(1),
+            allow_expired: false,
             client_auth: false,
             client_auth_certs: Vec::new(),
             use_session_ticket: true,
@@ -71,6 +73,18 @@ impl NamedValue for RustlsHostConfig {
 }
 
 impl RustlsHostConfig {
+    pub(crate) fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::with_capacity(self.cert_pairs.len());
+        for (i, pair) in self.cert_pairs.iter().enumerate() {
+            let mut status = pair
+                .cert_status()
+                .context(format!("invalid cert pair #{i} in host {}", self.name))?;
+            status.set_allow_expired(self.allow_expired);
+            all.push(status);
+        }
+        Ok(all)
+    }
+
     pub(crate) fn build_tls_config(
         &self,
         tls_ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
@@ -147,6 +161,10 @@ impl YamlMapCallback for RustlsHostConfig {
                 .context(format!("invalid rustls cert pair list value for key {key}"))?;
                 Ok(())
             }
+            "allow_expired" => {
+                self.allow_expired = g3_yaml::value::as_bool(value)?;
+                Ok(())
+            }
             "enable_client_auth" => {
                 self.client_auth = g3_yaml::value::as_bool(value)?;
                 Ok(())
@@ -14,7 +14,7 @@ use g3_io_ext::StreamCopyConfig;
 use g3_tls_ticket::TlsTicketConfig;
 use g3_types::acl::AclNetworkRuleBuilder;
 use g3_types::metrics::{MetricTagMap, NodeName};
-use g3_types::net::{TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig};
+use g3_types::net::{TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig, TlsCertStatus};
 use g3_types::route::HostMatch;
 use g3_yaml::YamlDocPosition;
 
@@ -236,4 +236,12 @@ impl ServerConfig for RustlsProxyServerConfig {
 
         ServerConfigDiffAction::ReloadNoRespawn
     }
+
+    fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::new();
+        for host in self.hosts.get_all_values().values() {
+            all.extend(host.cert_status()?);
+        }
+        Ok(all)
+    }
 }
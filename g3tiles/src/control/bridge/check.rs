@@ -0,0 +1,17 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+
+pub(in crate::control) async fn check_config(path: String) -> anyhow::Result<String> {
+    let path = PathBuf::from(path);
+    g3_daemon::runtime::main_handle()
+        .ok_or(anyhow!("unable to get main runtime handle"))?
+        .spawn_blocking(move || crate::config::check_file(&path).to_json().to_string())
+        .await
+        .map_err(|e| anyhow!("failed to spawn config check task: {e}"))
+}
@@ -3,5 +3,11 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+mod ban;
+pub(super) use ban::{clear_client_ban, list_client_ban};
+
+mod check;
+pub(super) use check::check_config;
+
 mod reload;
 pub(super) use reload::{reload_backend, reload_discover, reload_server};
@@ -188,6 +188,72 @@ impl proc_control::Server for ProcControlImpl {
         ));
         Promise::ok(())
     }
+
+    fn check_config(
+        &mut self,
+        params: proc_control::CheckConfigParams,
+        mut results: proc_control::CheckConfigResults,
+    ) -> Promise<(), capnp::Error> {
+        let path = pry!(pry!(pry!(params.get()).get_path()).to_string());
+        Promise::from_future(async move {
+            let r = crate::control::bridge::check_config(path).await;
+            let mut builder = results.get().init_result();
+            match r {
+                Ok(report) => builder.set_data(report.as_str().into())?,
+                Err(e) => {
+                    let mut ev = builder.init_err();
+                    ev.set_code(-1);
+                    ev.set_reason(format!("{e:?}").as_str());
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn config_schema(
+        &mut self,
+        _params: proc_control::ConfigSchemaParams,
+        mut results: proc_control::ConfigSchemaResults,
+    ) -> Promise<(), capnp::Error> {
+        let schema = crate::config::schema().to_string();
+        pry!(results.get().init_result().set_data(schema.as_str().into()));
+        Promise::ok(())
+    }
+
+    fn list_client_ban(
+        &mut self,
+        _params: proc_control::ListClientBanParams,
+        mut results: proc_control::ListClientBanResults,
+    ) -> Promise<(), capnp::Error> {
+        let mut builder = results.get().init_result();
+        match crate::control::bridge::list_client_ban() {
+            Ok(bans) => pry!(builder.set_data(bans.to_string().as_str().into())),
+            Err(e) => {
+                let mut ev = builder.init_err();
+                ev.set_code(-1);
+                ev.set_reason(format!("{e:?}").as_str());
+            }
+        }
+        Promise::ok(())
+    }
+
+    fn clear_client_ban(
+        &mut self,
+        params: proc_control::ClearClientBanParams,
+        mut results: proc_control::ClearClientBanResults,
+    ) -> Promise<(), capnp::Error> {
+        let ip = pry!(pry!(pry!(params.get()).get_ip()).to_str());
+        let mut builder = results.get().init_result();
+        match crate::control::bridge::clear_client_ban(ip) {
+            Ok(n) => builder.set_ok(format!("{n} bans cleared").as_str()),
+            Err(e) => {
+                let mut ev = builder.init_err();
+                ev.set_code(-1);
+                ev.set_reason(format!("{e:?}").as_str());
+            }
+        }
+        Promise::ok(())
+    }
 }
 
 fn set_fetch_result<'a, T>(
@@ -4,11 +4,13 @@
  */
 
 use capnp::capability::Promise;
+use capnp_rpc::pry;
 
 use g3_types::metrics::NodeName;
 
 use g3tiles_proto::server_capnp::server_control;
 
+use super::set_operation_result;
 use crate::serve::ArcServer;
 
 pub(super) struct ServerControlImpl {
@@ -29,17 +31,94 @@ impl server_control::Server for ServerControlImpl {
         _params: server_control::StatusParams,
         mut results: server_control::StatusResults,
     ) -> Promise<(), capnp::Error> {
+        let listen_stats = self.server.get_listen_stats();
+        let mut builder = results.get().init_status();
         if let Some(stats) = self.server.get_server_stats() {
-            let mut builder = results.get().init_status();
             builder.set_online(stats.is_online());
-            builder.set_alive_task_count(stats.alive_count());
             builder.set_total_conn_count(stats.conn_total());
             builder.set_total_task_count(stats.task_total());
-            Promise::ok(())
         } else {
-            Promise::err(capnp::Error::failed(
-                "server status is not supported on this server".to_string(),
-            ))
+            // no server stats for port servers, use the listen state instead
+            builder.set_online(listen_stats.is_running());
         }
+        builder.set_alive_task_count(self.server.alive_count());
+        builder.set_listening(listen_stats.is_running());
+        let listen_addrs = listen_stats.listen_addrs();
+        let mut addrs_builder = builder
+            .reborrow()
+            .init_listen_addrs(listen_addrs.len() as u32);
+        for (i, addr) in listen_addrs.iter().enumerate() {
+            addrs_builder.set(i as u32, addr.to_string().as_str());
+        }
+        if let Some(e) = listen_stats.last_error() {
+            builder.set_last_listen_error(e.as_str());
+        }
+        Promise::ok(())
+    }
+
+    fn check(
+        &mut self,
+        params: server_control::CheckParams,
+        mut results: server_control::CheckResults,
+    ) -> Promise<(), capnp::Error> {
+        let sni = pry!(pry!(pry!(params.get()).get_sni()).to_string());
+        let server = self.server.clone();
+        Promise::from_future(async move {
+            let sni = if sni.is_empty() {
+                None
+            } else {
+                Some(sni.as_str())
+            };
+            let r = server.loopback_check(sni).await;
+            let builder = results.get().init_result();
+            match r {
+                Ok(report) => {
+                    let mut report_builder = builder.init_report();
+                    report_builder.set_connect_time(report.connect_time.as_micros() as u64);
+                    if let Some(handshake_time) = report.handshake_time {
+                        report_builder.set_handshake_time(handshake_time.as_micros() as u64);
+                    }
+                }
+                Err(e) => builder.set_err(format!("{e:?}").as_str()),
+            }
+            Ok(())
+        })
+    }
+
+    fn rotate_ticket_key(
+        &mut self,
+        _params: server_control::RotateTicketKeyParams,
+        mut results: server_control::RotateTicketKeyResults,
+    ) -> Promise<(), capnp::Error> {
+        let r = self.server.rotate_tls_ticket_key();
+        set_operation_result(results.get().init_result(), r);
+        Promise::ok(())
+    }
+
+    fn host_health(
+        &mut self,
+        _params: server_control::HostHealthParams,
+        mut results: server_control::HostHealthResults,
+    ) -> Promise<(), capnp::Error> {
+        let builder = results.get().init_result();
+        let Some(hosts) = self
+            .server
+            .get_server_stats()
+            .and_then(|stats| stats.host_health_snapshot())
+        else {
+            builder.set_err("host health check is not supported by this server");
+            return Promise::ok(());
+        };
+
+        let mut hosts = hosts.into_iter().collect::<Vec<_>>();
+        hosts.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut hosts_builder = builder.init_hosts(hosts.len() as u32);
+        for (i, (host, snap)) in hosts.iter().enumerate() {
+            let mut host_builder = hosts_builder.reborrow().get(i as u32);
+            host_builder.set_host(host.as_str());
+            host_builder.set_healthy(snap.healthy);
+            host_builder.set_unhealthy_count(snap.unhealthy_total);
+        }
+        Promise::ok(())
     }
 }
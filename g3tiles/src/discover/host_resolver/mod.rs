@@ -3,18 +3,22 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::io;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
 
-use anyhow::Context;
+use anyhow::{Context, anyhow};
 use tokio::sync::watch;
 use yaml_rust::Yaml;
 
 use g3_types::collection::WeightedValue;
 use g3_types::metrics::NodeName;
+use g3_types::resolve::QueryStrategy;
 
 use super::{ArcDiscoverInternal, Discover, DiscoverInternal, DiscoverResult};
-use crate::config::discover::host_resolver::HostResolverDiscoverConfig;
+use crate::config::discover::host_resolver::{
+    HostResolverDiscoverConfig, HostResolverDiscoverInput,
+};
 use crate::config::discover::{AnyDiscoverConfig, DiscoverConfig};
 
 pub(crate) struct HostResolverDiscover {
@@ -38,22 +42,11 @@ impl Discover for HostResolverDiscover {
             self.config.name()
         ))?;
         let (sender, receiver) = watch::channel(Ok(Vec::new()));
-        tokio::spawn(async move {
-            let addr = input.addr.to_string();
-            loop {
-                let _ = match tokio::net::lookup_host(&addr).await {
-                    Ok(iter) => {
-                        let addrs: Vec<_> = iter.into_iter().map(WeightedValue::new).collect();
-                        sender.send_replace(Ok(addrs))
-                    }
-                    Err(e) => sender.send_replace(Err(anyhow::Error::new(e))),
-                };
-                match tokio::time::timeout(Duration::from_secs(60), sender.closed()).await {
-                    Ok(_) => break,
-                    Err(_) => continue,
-                }
-            }
-        });
+        tokio::spawn(resolve_loop(input, sender, |addr| async move {
+            tokio::net::lookup_host(addr)
+                .await
+                .map(|iter| iter.collect())
+        }));
         Ok(receiver)
     }
 }
@@ -67,3 +60,138 @@ impl DiscoverInternal for HostResolverDiscover {
         Ok(())
     }
 }
+
+/// Resolve the address on each interval, until all the receivers are dropped
+async fn resolve_loop<R, F>(
+    input: HostResolverDiscoverInput,
+    sender: watch::Sender<DiscoverResult>,
+    resolve: R,
+) where
+    R: Fn(String) -> F,
+    F: Future<Output = io::Result<Vec<SocketAddr>>>,
+{
+    let addr = input.addr.to_string();
+    loop {
+        let r = match resolve(addr.clone()).await {
+            Ok(addrs) => {
+                let addrs = sort_addrs(addrs, input.resolve_strategy);
+                if addrs.is_empty() {
+                    // never clear the peers, or all new connections will fail
+                    Err(anyhow!("no usable address resolved for {addr}"))
+                } else {
+                    Ok(addrs.into_iter().map(WeightedValue::new).collect())
+                }
+            }
+            Err(e) => Err(anyhow::Error::new(e)),
+        };
+        sender.send_replace(r);
+        match tokio::time::timeout(input.resolve_interval, sender.closed()).await {
+            Ok(_) => break,
+            Err(_) => continue,
+        }
+    }
+}
+
+fn sort_addrs(mut addrs: Vec<SocketAddr>, strategy: QueryStrategy) -> Vec<SocketAddr> {
+    match strategy {
+        QueryStrategy::Ipv4Only => addrs.retain(|a| a.is_ipv4()),
+        QueryStrategy::Ipv6Only => addrs.retain(|a| a.is_ipv6()),
+        QueryStrategy::Ipv4First => addrs.sort_by_key(|a| a.is_ipv6()),
+        QueryStrategy::Ipv6First => addrs.sort_by_key(|a| a.is_ipv4()),
+    }
+    addrs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use g3_types::net::UpstreamAddr;
+
+    fn addr(s: &str) -> SocketAddr {
+        SocketAddr::from_str(s).unwrap()
+    }
+
+    fn input(resolve_strategy: QueryStrategy) -> HostResolverDiscoverInput {
+        HostResolverDiscoverInput {
+            addr: UpstreamAddr::from_str("backend.example.net:443").unwrap(),
+            resolve_strategy,
+            resolve_interval: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn sort() {
+        let all = vec![addr("[2001:db8::1]:443"), addr("192.0.2.1:443")];
+        assert_eq!(
+            sort_addrs(all.clone(), QueryStrategy::Ipv4First),
+            vec![addr("192.0.2.1:443"), addr("[2001:db8::1]:443")]
+        );
+        assert_eq!(
+            sort_addrs(all.clone(), QueryStrategy::Ipv6First),
+            vec![addr("[2001:db8::1]:443"), addr("192.0.2.1:443")]
+        );
+        assert_eq!(
+            sort_addrs(all.clone(), QueryStrategy::Ipv4Only),
+            vec![addr("192.0.2.1:443")]
+        );
+        assert_eq!(
+            sort_addrs(all, QueryStrategy::Ipv6Only),
+            vec![addr("[2001:db8::1]:443")]
+        );
+    }
+
+    /// Wait until the discovered data matches, the old answers may still be received
+    async fn wait_peers(
+        receiver: &mut watch::Receiver<DiscoverResult>,
+        expected: Option<SocketAddr>,
+    ) {
+        loop {
+            receiver.changed().await.unwrap();
+            let r = receiver.borrow_and_update();
+            match (r.as_ref(), expected) {
+                (Ok(data), Some(addr)) if data == &vec![WeightedValue::new(addr)] => return,
+                (Err(_), None) => return,
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh() {
+        let answer: Arc<Mutex<Option<Vec<SocketAddr>>>> =
+            Arc::new(Mutex::new(Some(vec![addr("192.0.2.1:443")])));
+
+        let (sender, mut receiver) = watch::channel(Ok(Vec::new()));
+        let stub = answer.clone();
+        tokio::spawn(resolve_loop(
+            input(QueryStrategy::Ipv4Only),
+            sender,
+            move |_| {
+                let r = stub
+                    .lock()
+                    .unwrap()
+                    .clone()
+                    .ok_or_else(|| io::Error::other("server failure"));
+                async move { r }
+            },
+        ));
+        wait_peers(&mut receiver, Some(addr("192.0.2.1:443"))).await;
+
+        // only ipv6 addresses resolved, which should be treated as failure
+        *answer.lock().unwrap() = Some(vec![addr("[2001:db8::1]:443")]);
+        wait_peers(&mut receiver, None).await;
+
+        *answer.lock().unwrap() = Some(Vec::new());
+        wait_peers(&mut receiver, None).await;
+
+        *answer.lock().unwrap() = None;
+        wait_peers(&mut receiver, None).await;
+
+        *answer.lock().unwrap() = Some(vec![addr("192.0.2.2:443")]);
+        wait_peers(&mut receiver, Some(addr("192.0.2.2:443"))).await;
+    }
+}
@@ -15,3 +15,6 @@ pub mod stat;
 mod build;
 mod log;
 mod module;
+
+#[cfg(test)]
+mod testing;
@@ -5,7 +5,7 @@
 
 use slog::{Logger, slog_info};
 
-use g3_slog_types::{LtDateTime, LtDuration, LtUuid};
+use g3_daemon::log::task::{TaskLogIoBytes, TaskLogRecord};
 
 use super::TaskEvent;
 use crate::serve::{ServerTaskError, ServerTaskNotes};
@@ -13,76 +13,82 @@ use crate::serve::{ServerTaskError, ServerTaskNotes};
 pub(crate) struct TaskLogForTcpConnect<'a> {
     pub(crate) logger: &'a Logger,
     pub(crate) task_notes: &'a ServerTaskNotes,
+    pub(crate) tls_cert_type: Option<&'static str>,
+    pub(crate) client_cert_rule: Option<&'a str>,
+    pub(crate) early_data: Option<&'static str>,
+    pub(crate) early_data_bytes: Option<u64>,
+    pub(crate) early_data_discarded: Option<u64>,
+    pub(crate) idle_override: Option<&'a str>,
+    pub(crate) client_error_action: Option<&'static str>,
     pub(crate) client_rd_bytes: u64,
     pub(crate) client_wr_bytes: u64,
     pub(crate) remote_rd_bytes: u64,
     pub(crate) remote_wr_bytes: u64,
 }
 
-impl TaskLogForTcpConnect<'_> {
-    pub(crate) fn log_created(&self) {
-        slog_info!(self.logger, "";
-            "task_type" => "TcpConnect",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Created.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "server_addr" => self.task_notes.server_addr(),
-            "client_addr" => self.task_notes.client_addr(),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
+impl<'a> TaskLogForTcpConnect<'a> {
+    fn record(&self, task_event: TaskEvent) -> TaskLogRecord<'a> {
+        TaskLogRecord::new(
+            "TcpConnect",
+            &self.task_notes.id,
+            self.task_notes.stage.brief(),
+            &self.task_notes.start_at,
+        )
+        .task_event(task_event.as_str())
+        .server_addr(self.task_notes.server_addr())
+        .client_addr(self.task_notes.client_addr())
+        .wait_time(self.task_notes.wait_time)
+        .extension("tls_cert_type", self.tls_cert_type)
+        .extension("client_cert_rule", self.client_cert_rule)
+        .extension("early_data", self.early_data)
+        .extension("early_data_bytes", self.early_data_bytes)
+        .extension("early_data_discarded", self.early_data_discarded)
+        .extension("idle_override", self.idle_override)
+        .extension("client_error_action", self.client_error_action)
+        .extension("backend_dscp", self.task_notes.backend_dscp.map(u64::from))
+        .extension(
+            "upstream_proxy_header_bytes",
+            self.task_notes.upstream_proxy_header_bytes,
         )
+        .extension("lifetime_remaining", self.task_notes.lifetime_remaining())
+    }
+
+    fn io_bytes(&self) -> TaskLogIoBytes {
+        TaskLogIoBytes {
+            client_rd: self.client_rd_bytes,
+            client_wr: self.client_wr_bytes,
+            remote_rd: self.remote_rd_bytes,
+            remote_wr: self.remote_wr_bytes,
+        }
+    }
+
+    pub(crate) fn log_created(&self) {
+        slog_info!(self.logger, ""; self.record(TaskEvent::Created))
     }
 
     pub(crate) fn log_connected(&self) {
-        slog_info!(self.logger, "";
-            "task_type" => "TcpConnect",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Connected.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "server_addr" => self.task_notes.server_addr(),
-            "client_addr" => self.task_notes.client_addr(),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-        )
+        let record = self
+            .record(TaskEvent::Connected)
+            .ready_time(self.task_notes.ready_time);
+        slog_info!(self.logger, ""; record)
     }
 
     pub(crate) fn log_periodic(&self) {
-        slog_info!(self.logger, "";
-            "task_type" => "TcpConnect",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Periodic.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "server_addr" => self.task_notes.server_addr(),
-            "client_addr" => self.task_notes.client_addr(),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-            "total_time" => LtDuration(self.task_notes.time_elapsed()),
-            "c_rd_bytes" => self.client_rd_bytes,
-            "c_wr_bytes" => self.client_wr_bytes,
-            "r_rd_bytes" => self.remote_rd_bytes,
-            "r_wr_bytes" => self.remote_wr_bytes,
-        )
+        let record = self
+            .record(TaskEvent::Periodic)
+            .ready_time(self.task_notes.ready_time)
+            .total_time(self.task_notes.time_elapsed())
+            .io_bytes(self.io_bytes());
+        slog_info!(self.logger, ""; record)
     }
 
     fn log_partial_shutdown(&self, task_event: TaskEvent) {
-        slog_info!(self.logger, "";
-            "task_type" => "TcpConnect",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => task_event.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "server_addr" => self.task_notes.server_addr(),
-            "client_addr" => self.task_notes.client_addr(),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-            "total_time" => LtDuration(self.task_notes.time_elapsed()),
-            "c_rd_bytes" => self.client_rd_bytes,
-            "c_wr_bytes" => self.client_wr_bytes,
-            "r_rd_bytes" => self.remote_rd_bytes,
-            "r_wr_bytes" => self.remote_wr_bytes,
-        )
+        let record = self
+            .record(task_event)
+            .ready_time(self.task_notes.ready_time)
+            .total_time(self.task_notes.time_elapsed())
+            .io_bytes(self.io_bytes());
+        slog_info!(self.logger, ""; record)
     }
 
     pub(crate) fn log_client_shutdown(&self) {
@@ -94,22 +100,149 @@ impl TaskLogForTcpConnect<'_> {
     }
 
     pub(crate) fn log(&self, e: ServerTaskError) {
-        slog_info!(self.logger, "{}", e;
-            "task_type" => "TcpConnect",
-            "task_id" => LtUuid(&self.task_notes.id),
-            "task_event" => TaskEvent::Finished.as_str(),
-            "stage" => self.task_notes.stage.brief(),
-            "start_at" => LtDateTime(&self.task_notes.start_at),
-            "server_addr" => self.task_notes.server_addr(),
-            "client_addr" => self.task_notes.client_addr(),
-            "reason" => e.brief(),
-            "wait_time" => LtDuration(self.task_notes.wait_time),
-            "ready_time" => LtDuration(self.task_notes.ready_time),
-            "total_time" => LtDuration(self.task_notes.time_elapsed()),
-            "c_rd_bytes" => self.client_rd_bytes,
-            "c_wr_bytes" => self.client_wr_bytes,
-            "r_rd_bytes" => self.remote_rd_bytes,
-            "r_wr_bytes" => self.remote_wr_bytes,
-        )
+        let record = self
+            .record(TaskEvent::Finished)
+            .reason(e.brief())
+            .ready_time(self.task_notes.ready_time)
+            .total_time(self.task_notes.time_elapsed())
+            .io_bytes(self.io_bytes());
+        slog_info!(self.logger, "{}", e; record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use slog::{Drain, Key, OwnedKVList, Record, Serializer};
+
+    use g3_daemon::log::task::validate_task_log;
+    use g3_daemon::server::ClientConnectionInfo;
+
+    #[derive(Clone, Default)]
+    struct CollectDrain(Arc<Mutex<Vec<HashMap<String, String>>>>);
+
+    struct RecordSerializer(HashMap<String, String>);
+
+    impl Serializer for RecordSerializer {
+        fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+            self.0.insert(key.to_string(), val.to_string());
+            Ok(())
+        }
+    }
+
+    impl Drain for CollectDrain {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(&self, record: &Record, _values: &OwnedKVList) -> Result<(), slog::Never> {
+            let mut serializer = RecordSerializer(HashMap::new());
+            record.kv().serialize(record, &mut serializer).unwrap();
+            self.0.lock().unwrap().push(serializer.0);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn schema() {
+        let client_addr = SocketAddr::from_str("192.0.2.1:10001").unwrap();
+        let server_addr = SocketAddr::from_str("127.0.0.1:443").unwrap();
+        let mut task_notes = ServerTaskNotes::new(
+            ClientConnectionInfo::new(client_addr, server_addr),
+            Duration::from_millis(1),
+        );
+        task_notes.backend_dscp = Some(10);
+        task_notes.upstream_proxy_header_bytes = Some(64);
+
+        let drain = CollectDrain::default();
+        let logger = Logger::root(drain.clone(), slog::o!());
+        let task_log = TaskLogForTcpConnect {
+            logger: &logger,
+            task_notes: &task_notes,
+            tls_cert_type: Some("rsa"),
+            client_cert_rule: None,
+            early_data: None,
+            early_data_bytes: None,
+            early_data_discarded: None,
+            idle_override: Some("net:192.0.2.0/24"),
+            client_error_action: Some("tls_alert"),
+            client_rd_bytes: 1,
+            client_wr_bytes: 2,
+            remote_rd_bytes: 3,
+            remote_wr_bytes: 4,
+        };
+        task_log.log_created();
+        task_log.log_connected();
+        task_log.log_periodic();
+        task_log.log_client_shutdown();
+        task_log.log(ServerTaskError::ClosedByClient);
+
+        let records = drain.0.lock().unwrap();
+        assert_eq!(records.len(), 5);
+        for fields in records.iter() {
+            validate_task_log(fields.iter().map(|(k, v)| (k.as_str(), v.as_str()))).unwrap();
+            assert_eq!(fields["task_type"], "TcpConnect");
+            assert_eq!(fields["client_addr"], "192.0.2.1:10001");
+            assert_eq!(fields["tls_cert_type"], "rsa");
+            assert_eq!(fields["idle_override"], "net:192.0.2.0/24");
+            assert_eq!(fields["client_error_action"], "tls_alert");
+            assert_eq!(fields["backend_dscp"], "10");
+            assert_eq!(fields["upstream_proxy_header_bytes"], "64");
+        }
+        assert_eq!(records[0]["task_event"], "Created");
+        assert!(!records[0].contains_key("c_rd_bytes"));
+        assert_eq!(records[4]["reason"], "ClosedByClient");
+        assert_eq!(records[4]["r_wr_bytes"], "4");
+    }
+
+    #[test]
+    fn lifetime_expired() {
+        let client_addr = SocketAddr::from_str("192.0.2.1:10001").unwrap();
+        let server_addr = SocketAddr::from_str("127.0.0.1:443").unwrap();
+        let mut task_notes = ServerTaskNotes::new(
+            ClientConnectionInfo::new(client_addr, server_addr),
+            Duration::ZERO,
+        );
+        let drain = CollectDrain::default();
+        let logger = Logger::root(drain.clone(), slog::o!());
+        let log = |task_notes: &ServerTaskNotes, e: Option<ServerTaskError>| {
+            let task_log = TaskLogForTcpConnect {
+                logger: &logger,
+                task_notes,
+                tls_cert_type: None,
+                client_cert_rule: None,
+                early_data: None,
+                early_data_bytes: None,
+                early_data_discarded: None,
+                idle_override: None,
+                client_error_action: None,
+                client_rd_bytes: 0,
+                client_wr_bytes: 0,
+                remote_rd_bytes: 0,
+                remote_wr_bytes: 0,
+            };
+            match e {
+                Some(e) => task_log.log(e),
+                None => task_log.log_periodic(),
+            }
+        };
+
+        let now = tokio::time::Instant::now();
+        task_notes.lifetime_deadline = Some(now + Duration::from_secs(3600));
+        log(&task_notes, None);
+        task_notes.lifetime_deadline = Some(now);
+        log(&task_notes, Some(ServerTaskError::LifetimeExpired));
+
+        let records = drain.0.lock().unwrap();
+        let remaining = &records[0]["lifetime_remaining"];
+        assert!(remaining.starts_with("3599.") || remaining.starts_with("3600."));
+        assert_eq!(records[1]["lifetime_remaining"], "None");
+        assert_eq!(records[1]["reason"], "LifetimeExpired");
     }
 }
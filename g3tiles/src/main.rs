@@ -4,7 +4,7 @@
  */
 
 use anyhow::Context;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 
 use g3_daemon::control::{QuitAction, UpgradeAction};
 
@@ -40,6 +40,12 @@ fn main() -> anyhow::Result<()> {
 
     // set up process logger early, only proc args is used inside
     g3_daemon::log::process::setup(&proc_args.daemon_config);
+    if proc_args.dry_run {
+        return g3tiles::config::dry_run();
+    }
+    if proc_args.dump_config_schema {
+        return g3tiles::config::dump_schema();
+    }
     if proc_args.daemon_config.need_daemon_controller() {
         g3tiles::control::UpgradeActor::connect_to_old_daemon();
     }
@@ -115,8 +121,27 @@ fn tokio_run(args: &ProcArgs) -> anyhow::Result<()> {
         g3tiles::signal::register().context("failed to setup signal handler")?;
         g3_daemon::control::panic::set_hook(&args.daemon_config);
 
+        #[cfg(unix)]
+        if let Some(path) = &args.daemon_config.upgrade_from {
+            match g3_daemon::listen::handover::receive_from(path) {
+                Ok(n) => info!("received {n} listen sockets from the old process"),
+                Err(e) => warn!("failed to receive listen sockets from the old process: {e:?}"),
+            }
+        }
+
         match load_and_spawn().await {
-            Ok(_) => g3_daemon::control::upgrade::finish(),
+            Ok(_) => {
+                #[cfg(unix)]
+                {
+                    g3_daemon::listen::handover::clear_adoptable();
+                    if args.daemon_config.need_daemon_controller() {
+                        if let Err(e) = g3tiles::control::UpgradeActor::spawn_handover_server() {
+                            warn!("failed to start listen socket handover server: {e:?}");
+                        }
+                    }
+                }
+                g3_daemon::control::upgrade::finish();
+            }
             Err(e) => {
                 g3_daemon::control::upgrade::cancel_old_shutdown();
                 return Err(e);
@@ -21,6 +21,7 @@ pub(crate) struct StreamBackendStats {
 
     conn_attempt: AtomicU64,
     conn_established: AtomicU64,
+    discover_failed: AtomicU64,
 }
 
 impl StreamBackendStats {
@@ -31,6 +32,7 @@ impl StreamBackendStats {
             extra_metrics_tags: Arc::new(ArcSwapOption::new(None)),
             conn_attempt: AtomicU64::new(0),
             conn_established: AtomicU64::new(0),
+            discover_failed: AtomicU64::new(0),
         }
     }
 
@@ -67,6 +69,14 @@ impl StreamBackendStats {
     pub(crate) fn conn_established(&self) -> u64 {
         self.conn_established.load(Ordering::Relaxed)
     }
+
+    pub(crate) fn add_discover_failed(&self) {
+        self.discover_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn discover_failed(&self) -> u64 {
+        self.discover_failed.load(Ordering::Relaxed)
+    }
 }
 
 pub(crate) struct StreamBackendDurationStats {
@@ -9,10 +9,21 @@ use std::sync::atomic::{AtomicI32, AtomicIsize, AtomicU64, Ordering};
 
 use arc_swap::ArcSwapOption;
 
+use g3_daemon::server::{AcceptRejectSnapshot, AcceptRejectStats, TransferStats};
 use g3_types::metrics::{MetricTagMap, NodeName};
+use g3_types::net::{OpensslTicketKey, RollingTicketer, RollingTicketerSnapshot};
 use g3_types::stats::{StatId, TcpIoSnapshot, TcpIoStats};
 
-use crate::serve::ServerStats;
+use crate::config::server::openssl_proxy::OpensslCertKeyType;
+use crate::serve::{
+    BackendPoolSnapshotMap, BackendPoolStatsMap, CertReloadSnapshot, CertReloadStats,
+    CertResolverSnapshot, CertResolverStats, ClientCertRouteSnapshot, ClientCertRouteStats,
+    ClientHelloBufferSnapshot, ClientHelloBufferStats, ClientIpLimitSnapshot, ClientIpLimitStats,
+    EarlyDataSnapshot, EarlyDataStats, HandshakeLimitSnapshot, HandshakeLimitStats,
+    HostHealthSnapshotMap, HostHealthStatsMap, HttpAwareSnapshot, HttpAwareStats,
+    ServedCertSnapshot, ServedCertStats, ServerStats, SessionSniMismatchSnapshot,
+    SessionSniMismatchStats,
+};
 
 pub(crate) struct StreamServerStats {
     name: NodeName,
@@ -27,6 +38,21 @@ pub(crate) struct StreamServerStats {
     task_alive_count: AtomicI32,
 
     tcp: TcpIoStats,
+    cert_resolver: ArcSwapOption<CertResolverStats>,
+    served_cert: ArcSwapOption<ServedCertStats>,
+    client_cert_route: ArcSwapOption<ClientCertRouteStats>,
+    handshake_limit: ArcSwapOption<HandshakeLimitStats>,
+    client_ip_limit: ArcSwapOption<ClientIpLimitStats>,
+    client_hello_buffer: ArcSwapOption<ClientHelloBufferStats>,
+    early_data: ArcSwapOption<EarlyDataStats>,
+    http_aware: ArcSwapOption<HttpAwareStats>,
+    session_sni_mismatch: ArcSwapOption<SessionSniMismatchStats>,
+    cert_reload: ArcSwapOption<CertReloadStats>,
+    tls_ticketer: ArcSwapOption<RollingTicketer<OpensslTicketKey>>,
+    transfer: ArcSwapOption<TransferStats>,
+    accept_reject: ArcSwapOption<AcceptRejectStats>,
+    backend_pool: ArcSwapOption<BackendPoolStatsMap>,
+    host_health: ArcSwapOption<HostHealthStatsMap>,
     // pub(crate) forbidden: ServerForbiddenStats,
 }
 
@@ -41,6 +67,21 @@ impl StreamServerStats {
             task_total: AtomicU64::new(0),
             task_alive_count: AtomicI32::new(0),
             tcp: Default::default(),
+            cert_resolver: ArcSwapOption::new(None),
+            served_cert: ArcSwapOption::new(None),
+            client_cert_route: ArcSwapOption::new(None),
+            handshake_limit: ArcSwapOption::new(None),
+            client_ip_limit: ArcSwapOption::new(None),
+            client_hello_buffer: ArcSwapOption::new(None),
+            early_data: ArcSwapOption::new(None),
+            http_aware: ArcSwapOption::new(None),
+            session_sni_mismatch: ArcSwapOption::new(None),
+            cert_reload: ArcSwapOption::new(None),
+            tls_ticketer: ArcSwapOption::new(None),
+            transfer: ArcSwapOption::new(None),
+            accept_reject: ArcSwapOption::new(None),
+            backend_pool: ArcSwapOption::new(None),
+            host_health: ArcSwapOption::new(None),
         }
     }
 
@@ -56,6 +97,126 @@ impl StreamServerStats {
         self.extra_metrics_tags.store(tags);
     }
 
+    pub(crate) fn set_cert_resolver_stats(&self, stats: Option<Arc<CertResolverStats>>) {
+        self.cert_resolver.store(stats);
+    }
+
+    pub(crate) fn set_served_cert_stats(&self, stats: Option<Arc<ServedCertStats>>) {
+        self.served_cert.store(stats);
+    }
+
+    pub(crate) fn add_served_cert(&self, key_type: OpensslCertKeyType) {
+        if let Some(stats) = self.served_cert.load().as_ref() {
+            stats.add(key_type);
+        }
+    }
+
+    pub(crate) fn set_client_cert_route_stats(&self, stats: Option<Arc<ClientCertRouteStats>>) {
+        self.client_cert_route.store(stats);
+    }
+
+    pub(crate) fn add_client_cert_route(&self, host: &str, rule: &str) {
+        if let Some(stats) = self.client_cert_route.load().as_ref() {
+            stats.add(host, rule);
+        }
+    }
+
+    pub(crate) fn set_handshake_limit_stats(&self, stats: Option<Arc<HandshakeLimitStats>>) {
+        self.handshake_limit.store(stats);
+    }
+
+    pub(crate) fn set_client_ip_limit_stats(&self, stats: Option<Arc<ClientIpLimitStats>>) {
+        self.client_ip_limit.store(stats);
+    }
+
+    pub(crate) fn set_client_hello_buffer_stats(&self, stats: Option<Arc<ClientHelloBufferStats>>) {
+        self.client_hello_buffer.store(stats);
+    }
+
+    pub(crate) fn set_early_data_stats(&self, stats: Option<Arc<EarlyDataStats>>) {
+        self.early_data.store(stats);
+    }
+
+    pub(crate) fn add_early_data_accepted(&self) {
+        if let Some(stats) = self.early_data.load().as_ref() {
+            stats.add_accepted();
+        }
+    }
+
+    pub(crate) fn add_early_data_rejected(&self) {
+        if let Some(stats) = self.early_data.load().as_ref() {
+            stats.add_rejected();
+        }
+    }
+
+    pub(crate) fn add_early_data_replayed(&self) {
+        if let Some(stats) = self.early_data.load().as_ref() {
+            stats.add_replayed();
+        }
+    }
+
+    pub(crate) fn set_http_aware_stats(&self, stats: Option<Arc<HttpAwareStats>>) {
+        self.http_aware.store(stats);
+    }
+
+    pub(crate) fn add_http_aware_fallback(&self) {
+        if let Some(stats) = self.http_aware.load().as_ref() {
+            stats.add_fallback();
+        }
+    }
+
+    pub(crate) fn set_session_sni_mismatch_stats(
+        &self,
+        stats: Option<Arc<SessionSniMismatchStats>>,
+    ) {
+        self.session_sni_mismatch.store(stats);
+    }
+
+    pub(crate) fn add_session_sni_mismatch_allowed(&self) {
+        if let Some(stats) = self.session_sni_mismatch.load().as_ref() {
+            stats.add_allowed();
+        }
+    }
+
+    pub(crate) fn add_session_sni_mismatch_full_handshake(&self) {
+        if let Some(stats) = self.session_sni_mismatch.load().as_ref() {
+            stats.add_full_handshake();
+        }
+    }
+
+    pub(crate) fn add_session_sni_mismatch_rejected(&self) {
+        if let Some(stats) = self.session_sni_mismatch.load().as_ref() {
+            stats.add_rejected();
+        }
+    }
+
+    pub(crate) fn set_cert_reload_stats(&self, stats: Option<Arc<CertReloadStats>>) {
+        self.cert_reload.store(stats);
+    }
+
+    pub(crate) fn set_tls_ticketer(
+        &self,
+        ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+    ) {
+        self.tls_ticketer.store(ticketer);
+    }
+
+    pub(crate) fn set_transfer_stats(&self, stats: Option<Arc<TransferStats>>) {
+        self.transfer.store(stats);
+    }
+
+    pub(crate) fn set_accept_reject_stats(&self, stats: Option<Arc<AcceptRejectStats>>) {
+        self.accept_reject.store(stats);
+    }
+
+    pub(crate) fn set_backend_pool_stats(&self, stats: Option<Arc<BackendPoolStatsMap>>) {
+        self.backend_pool.store(stats);
+    }
+
+    pub(crate) fn set_host_health_stats(&self, stats: Option<Arc<HostHealthStatsMap>>) {
+        self.host_health.store(stats);
+    }
+
     pub(crate) fn add_conn(&self, _addr: SocketAddr) {
         self.conn_total.fetch_add(1, Ordering::Relaxed);
     }
@@ -121,4 +282,70 @@ impl ServerStats for StreamServerStats {
     fn tcp_io_snapshot(&self) -> Option<TcpIoSnapshot> {
         Some(self.tcp.snapshot())
     }
+
+    fn cert_resolver_snapshot(&self) -> Option<CertResolverSnapshot> {
+        self.cert_resolver.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn served_cert_snapshot(&self) -> Option<ServedCertSnapshot> {
+        self.served_cert.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn client_cert_route_snapshot(&self) -> Option<ClientCertRouteSnapshot> {
+        self.client_cert_route.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn handshake_limit_snapshot(&self) -> Option<HandshakeLimitSnapshot> {
+        self.handshake_limit.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn client_ip_limit_snapshot(&self) -> Option<ClientIpLimitSnapshot> {
+        self.client_ip_limit.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn client_hello_buffer_snapshot(&self) -> Option<ClientHelloBufferSnapshot> {
+        self.client_hello_buffer
+            .load()
+            .as_ref()
+            .map(|s| s.snapshot())
+    }
+
+    fn early_data_snapshot(&self) -> Option<EarlyDataSnapshot> {
+        self.early_data.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn http_aware_snapshot(&self) -> Option<HttpAwareSnapshot> {
+        self.http_aware.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn session_sni_mismatch_snapshot(&self) -> Option<SessionSniMismatchSnapshot> {
+        self.session_sni_mismatch
+            .load()
+            .as_ref()
+            .map(|s| s.snapshot())
+    }
+
+    fn cert_reload_snapshot(&self) -> Option<CertReloadSnapshot> {
+        self.cert_reload.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn tls_ticket_snapshot(&self) -> Option<RollingTicketerSnapshot> {
+        self.tls_ticketer.load().as_ref().map(|t| t.snapshot())
+    }
+
+    fn transfer_stats(&self) -> Option<Arc<TransferStats>> {
+        self.transfer.load_full()
+    }
+
+    fn accept_reject_snapshot(&self) -> Option<AcceptRejectSnapshot> {
+        self.accept_reject.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn backend_pool_snapshot(&self) -> Option<BackendPoolSnapshotMap> {
+        self.backend_pool.load().as_ref().map(|s| s.snapshot())
+    }
+
+    fn host_health_snapshot(&self) -> Option<HostHealthSnapshotMap> {
+        self.host_health.load().as_ref().map(|s| s.snapshot())
+    }
 }
@@ -8,11 +8,37 @@ use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio::time::Instant;
 
-use g3_daemon::server::ServerQuitPolicy;
-use g3_io_ext::{IdleInterval, OptionalInterval, StreamCopy, StreamCopyConfig, StreamCopyError};
+use g3_daemon::server::{ServerQuitPolicy, TransferStats};
+use g3_io_ext::{
+    IdleDirection, IdleInterval, IdleTracker, OptionalInterval, StreamCopy, StreamCopyConfig,
+    StreamCopyError,
+};
 
 use crate::serve::{ServerTaskError, ServerTaskResult};
 
+/// Get the idle side of a copy, `None` will be returned if the copy is active
+fn idle_direction<R, W>(copy: &StreamCopy<'_, R, W>, read: IdleDirection) -> Option<IdleDirection>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    if copy.is_active() {
+        None
+    } else if copy.no_cached_data() {
+        Some(read)
+    } else {
+        Some(IdleDirection::WriteBlocked)
+    }
+}
+
+/// Wait until the max lifetime deadline, or forever if there is no deadline
+async fn lifetime_expire(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
 pub(crate) trait StreamTransitTask {
     fn copy_config(&self) -> StreamCopyConfig;
     fn idle_check_interval(&self) -> IdleInterval;
@@ -22,6 +48,15 @@ pub(crate) trait StreamTransitTask {
     fn log_periodic(&self);
     fn log_flush_interval(&self) -> Option<Duration>;
     fn quit_policy(&self) -> &ServerQuitPolicy;
+    fn transfer_stats(&self) -> Option<&TransferStats> {
+        None
+    }
+    fn lifetime_deadline(&self) -> Option<Instant> {
+        None
+    }
+    fn lifetime_grace(&self) -> Duration {
+        Duration::ZERO
+    }
 
     async fn transit_transparent<CR, CW, UR, UW>(
         &self,
@@ -37,8 +72,12 @@ pub(crate) trait StreamTransitTask {
         UW: AsyncWrite + Unpin,
     {
         let copy_config = self.copy_config();
-        let clt_to_ups = StreamCopy::new(&mut clt_r, &mut ups_w, &copy_config);
-        let ups_to_clt = StreamCopy::new(&mut ups_r, &mut clt_w, &copy_config);
+        let mut clt_to_ups = StreamCopy::new(&mut clt_r, &mut ups_w, &copy_config);
+        let mut ups_to_clt = StreamCopy::new(&mut ups_r, &mut clt_w, &copy_config);
+        if let Some(stats) = self.transfer_stats() {
+            clt_to_ups.set_recorder(stats.upload_recorder());
+            ups_to_clt.set_recorder(stats.download_recorder());
+        }
 
         self.transit_transparent2(clt_to_ups, ups_to_clt).await
     }
@@ -63,8 +102,10 @@ pub(crate) trait StreamTransitTask {
                 OptionalInterval::with(interval)
             })
             .unwrap_or_default();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
         let max_idle_count = self.max_idle_count();
+        let lifetime = lifetime_expire(self.lifetime_deadline());
+        tokio::pin!(lifetime);
         loop {
             tokio::select! {
                 r = &mut clt_to_ups => {
@@ -72,7 +113,7 @@ pub(crate) trait StreamTransitTask {
                         Ok(_) => {
                             let _ = clt_to_ups.writer().shutdown().await;
                             self.log_client_shutdown();
-                            self.transit_south(ups_to_clt, log_interval, idle_interval, idle_count, max_idle_count).await
+                            self.transit_south(ups_to_clt, log_interval, idle_interval, idle_tracker, max_idle_count).await
                         }
                         Err(StreamCopyError::ReadFailed(e)) => Err(ServerTaskError::ClientTcpReadFailed(e)),
                         Err(StreamCopyError::WriteFailed(e)) => {
@@ -86,7 +127,7 @@ pub(crate) trait StreamTransitTask {
                         Ok(_) => {
                             let _ = ups_to_clt.writer().shutdown().await;
                             self.log_upstream_shutdown();
-                            self.transit_north(clt_to_ups, log_interval, idle_interval, idle_count, max_idle_count).await
+                            self.transit_north(clt_to_ups, log_interval, idle_interval, idle_tracker, max_idle_count).await
                         }
                         Err(StreamCopyError::ReadFailed(e)) => Err(ServerTaskError::UpstreamReadFailed(e)),
                         Err(StreamCopyError::WriteFailed(e)) => {
@@ -95,19 +136,120 @@ pub(crate) trait StreamTransitTask {
                         }
                     };
                 }
+                _ = &mut lifetime => {
+                    return Err(self.close_on_lifetime_expired(clt_to_ups, ups_to_clt).await);
+                }
                 _ = log_interval.tick() => {
                     self.log_periodic();
                 }
                 n = idle_interval.tick() => {
-                    if clt_to_ups.is_idle() && ups_to_clt.is_idle() {
-                        idle_count += n;
-
-                        if idle_count >= max_idle_count {
-                            return Err(ServerTaskError::Idle(idle_interval.period(), idle_count));
+                    let clt_idle = idle_direction(&clt_to_ups, IdleDirection::ClientRead);
+                    let ups_idle = idle_direction(&ups_to_clt, IdleDirection::UpstreamRead);
+                    if idle_tracker.record(n, &[clt_idle, ups_idle]) {
+                        if idle_tracker.idle_count() >= max_idle_count {
+                            return Err(ServerTaskError::idle(idle_interval.period(), &idle_tracker));
                         }
                     } else {
-                        idle_count = 0;
+                        clt_to_ups.reset_active();
+                        ups_to_clt.reset_active();
+                    }
 
+                    if self.quit_policy().force_quit() {
+                        return Err(ServerTaskError::CanceledAsServerQuit)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Relay with an upstream connection that may be reused by other clients later.
+    ///
+    /// Return true if the client closed first with nothing left to forward, in which case the
+    /// upstream connection is not shutdown and is clean to be reused.
+    async fn transit_reusable<CR, CW, UR, UW>(
+        &self,
+        mut clt_r: CR,
+        mut clt_w: CW,
+        ups_r: &mut UR,
+        ups_w: &mut UW,
+    ) -> ServerTaskResult<bool>
+    where
+        CR: AsyncRead + Unpin,
+        CW: AsyncWrite + Unpin,
+        UR: AsyncRead + Unpin,
+        UW: AsyncWrite + Unpin,
+    {
+        let copy_config = self.copy_config();
+        let mut clt_to_ups = StreamCopy::new(&mut clt_r, ups_w, &copy_config);
+        let mut ups_to_clt = StreamCopy::new(ups_r, &mut clt_w, &copy_config);
+        if let Some(stats) = self.transfer_stats() {
+            clt_to_ups.set_recorder(stats.upload_recorder());
+            ups_to_clt.set_recorder(stats.download_recorder());
+        }
+
+        let mut idle_interval = self.idle_check_interval();
+        let mut log_interval = self
+            .log_flush_interval()
+            .map(|log_interval| {
+                let interval =
+                    tokio::time::interval_at(Instant::now() + log_interval, log_interval);
+                OptionalInterval::with(interval)
+            })
+            .unwrap_or_default();
+        let mut idle_tracker = IdleTracker::default();
+        let max_idle_count = self.max_idle_count();
+        let lifetime = lifetime_expire(self.lifetime_deadline());
+        tokio::pin!(lifetime);
+        loop {
+            tokio::select! {
+                r = &mut clt_to_ups => {
+                    return match r {
+                        Ok(_) => {
+                            self.log_client_shutdown();
+                            if ups_to_clt.no_cached_data() {
+                                let _ = ups_to_clt.writer().shutdown().await;
+                                return Ok(true);
+                            }
+                            let _ = clt_to_ups.writer().shutdown().await;
+                            self.transit_south(ups_to_clt, log_interval, idle_interval, idle_tracker, max_idle_count).await?;
+                            Ok(false)
+                        }
+                        Err(StreamCopyError::ReadFailed(e)) => Err(ServerTaskError::ClientTcpReadFailed(e)),
+                        Err(StreamCopyError::WriteFailed(e)) => {
+                            let _ = ups_to_clt.write_flush().await;
+                            Err(ServerTaskError::UpstreamWriteFailed(e))
+                        }
+                    };
+                }
+                r = &mut ups_to_clt => {
+                    return match r {
+                        Ok(_) => {
+                            let _ = ups_to_clt.writer().shutdown().await;
+                            self.log_upstream_shutdown();
+                            self.transit_north(clt_to_ups, log_interval, idle_interval, idle_tracker, max_idle_count).await?;
+                            Ok(false)
+                        }
+                        Err(StreamCopyError::ReadFailed(e)) => Err(ServerTaskError::UpstreamReadFailed(e)),
+                        Err(StreamCopyError::WriteFailed(e)) => {
+                            let _ = clt_to_ups.write_flush().await;
+                            Err(ServerTaskError::ClientTcpWriteFailed(e))
+                        }
+                    };
+                }
+                _ = &mut lifetime => {
+                    return Err(self.close_on_lifetime_expired(clt_to_ups, ups_to_clt).await);
+                }
+                _ = log_interval.tick() => {
+                    self.log_periodic();
+                }
+                n = idle_interval.tick() => {
+                    let clt_idle = idle_direction(&clt_to_ups, IdleDirection::ClientRead);
+                    let ups_idle = idle_direction(&ups_to_clt, IdleDirection::UpstreamRead);
+                    if idle_tracker.record(n, &[clt_idle, ups_idle]) {
+                        if idle_tracker.idle_count() >= max_idle_count {
+                            return Err(ServerTaskError::idle(idle_interval.period(), &idle_tracker));
+                        }
+                    } else {
                         clt_to_ups.reset_active();
                         ups_to_clt.reset_active();
                     }
@@ -120,18 +262,49 @@ pub(crate) trait StreamTransitTask {
         }
     }
 
+    /// Close the connection after the max lifetime expired.
+    ///
+    /// No more data will be read from the client. The cached client data and the in-flight
+    /// upstream data will be relayed in the grace period, and then the client side will be
+    /// shutdown, which will send the TLS close_notify alert. The connection will be aborted
+    /// if the grace period passed.
+    async fn close_on_lifetime_expired<CR, CW, UR, UW>(
+        &self,
+        mut clt_to_ups: StreamCopy<'_, CR, UW>,
+        mut ups_to_clt: StreamCopy<'_, UR, CW>,
+    ) -> ServerTaskError
+    where
+        CR: AsyncRead + Unpin,
+        CW: AsyncWrite + Unpin,
+        UR: AsyncRead + Unpin,
+        UW: AsyncWrite + Unpin,
+    {
+        let drain = async {
+            if clt_to_ups.write_flush().await.is_ok() {
+                let _ = clt_to_ups.writer().shutdown().await;
+            }
+            if (&mut ups_to_clt).await.is_ok() {
+                let _ = ups_to_clt.writer().shutdown().await;
+            }
+        };
+        let _ = tokio::time::timeout(self.lifetime_grace(), drain).await;
+        ServerTaskError::LifetimeExpired
+    }
+
     async fn transit_north<CR, UW>(
         &self,
         mut clt_to_ups: StreamCopy<'_, CR, UW>,
         mut log_interval: OptionalInterval,
         mut idle_interval: IdleInterval,
-        mut idle_count: usize,
+        mut idle_tracker: IdleTracker,
         max_idle_count: usize,
     ) -> ServerTaskResult<()>
     where
         CR: AsyncRead + Unpin,
         UW: AsyncWrite + Unpin,
     {
+        let lifetime = lifetime_expire(self.lifetime_deadline());
+        tokio::pin!(lifetime);
         loop {
             tokio::select! {
                 r = &mut clt_to_ups => {
@@ -144,19 +317,26 @@ pub(crate) trait StreamTransitTask {
                         Err(StreamCopyError::WriteFailed(e)) => Err(ServerTaskError::UpstreamWriteFailed(e)),
                     };
                 }
+                _ = &mut lifetime => {
+                    // the upstream has been shutdown, only the cached client data need to be sent
+                    let drain = async {
+                        if clt_to_ups.write_flush().await.is_ok() {
+                            let _ = clt_to_ups.writer().shutdown().await;
+                        }
+                    };
+                    let _ = tokio::time::timeout(self.lifetime_grace(), drain).await;
+                    return Err(ServerTaskError::LifetimeExpired);
+                }
                 _ = log_interval.tick() => {
                     self.log_periodic();
                 }
                 n = idle_interval.tick() => {
-                    if clt_to_ups.is_idle() {
-                        idle_count += n;
-
-                        if idle_count >= max_idle_count {
-                            return Err(ServerTaskError::Idle(idle_interval.period(), idle_count));
+                    let idle = idle_direction(&clt_to_ups, IdleDirection::ClientRead);
+                    if idle_tracker.record(n, &[idle]) {
+                        if idle_tracker.idle_count() >= max_idle_count {
+                            return Err(ServerTaskError::idle(idle_interval.period(), &idle_tracker));
                         }
                     } else {
-                        idle_count = 0;
-
                         clt_to_ups.reset_active();
                     }
 
@@ -173,13 +353,15 @@ pub(crate) trait StreamTransitTask {
         mut ups_to_clt: StreamCopy<'_, UR, CW>,
         mut log_interval: OptionalInterval,
         mut idle_interval: IdleInterval,
-        mut idle_count: usize,
+        mut idle_tracker: IdleTracker,
         max_idle_count: usize,
     ) -> ServerTaskResult<()>
     where
         CW: AsyncWrite + Unpin,
         UR: AsyncRead + Unpin,
     {
+        let lifetime = lifetime_expire(self.lifetime_deadline());
+        tokio::pin!(lifetime);
         loop {
             tokio::select! {
                 r = &mut ups_to_clt => {
@@ -192,19 +374,26 @@ pub(crate) trait StreamTransitTask {
                         Err(StreamCopyError::WriteFailed(e)) => Err(ServerTaskError::ClientTcpWriteFailed(e)),
                     };
                 }
+                _ = &mut lifetime => {
+                    // the client has been shutdown, relay the in-flight upstream data
+                    let drain = async {
+                        if (&mut ups_to_clt).await.is_ok() {
+                            let _ = ups_to_clt.writer().shutdown().await;
+                        }
+                    };
+                    let _ = tokio::time::timeout(self.lifetime_grace(), drain).await;
+                    return Err(ServerTaskError::LifetimeExpired);
+                }
                 _ = log_interval.tick() => {
                     self.log_periodic();
                 }
                 n = idle_interval.tick() => {
-                    if ups_to_clt.is_idle() {
-                        idle_count += n;
-
-                        if idle_count >= max_idle_count {
-                            return Err(ServerTaskError::Idle(idle_interval.period(), idle_count));
+                    let idle = idle_direction(&ups_to_clt, IdleDirection::UpstreamRead);
+                    if idle_tracker.record(n, &[idle]) {
+                        if idle_tracker.idle_count() >= max_idle_count {
+                            return Err(ServerTaskError::idle(idle_interval.period(), &idle_tracker));
                         }
                     } else {
-                        idle_count = 0;
-
                         ups_to_clt.reset_active();
                     }
 
@@ -216,3 +405,221 @@ pub(crate) trait StreamTransitTask {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use tokio::io::AsyncReadExt;
+
+    use g3_io_ext::IdleWheel;
+
+    struct MockTransitTask {
+        idle_wheel: Arc<IdleWheel>,
+        quit_policy: ServerQuitPolicy,
+        lifetime_deadline: Option<Instant>,
+        lifetime_grace: Duration,
+    }
+
+    impl MockTransitTask {
+        fn new(idle_check: Duration) -> Self {
+            MockTransitTask {
+                idle_wheel: IdleWheel::spawn(idle_check),
+                quit_policy: ServerQuitPolicy::default(),
+                lifetime_deadline: None,
+                lifetime_grace: Duration::ZERO,
+            }
+        }
+
+        fn with_lifetime(idle_check: Duration, lifetime: Duration, grace: Duration) -> Self {
+            let mut task = MockTransitTask::new(idle_check);
+            task.lifetime_deadline = Some(Instant::now() + lifetime);
+            task.lifetime_grace = grace;
+            task
+        }
+    }
+
+    impl StreamTransitTask for MockTransitTask {
+        fn copy_config(&self) -> StreamCopyConfig {
+            StreamCopyConfig::default()
+        }
+
+        fn idle_check_interval(&self) -> IdleInterval {
+            self.idle_wheel.register()
+        }
+
+        fn max_idle_count(&self) -> usize {
+            2
+        }
+
+        fn log_client_shutdown(&self) {}
+
+        fn log_upstream_shutdown(&self) {}
+
+        fn log_periodic(&self) {}
+
+        fn log_flush_interval(&self) -> Option<Duration> {
+            None
+        }
+
+        fn quit_policy(&self) -> &ServerQuitPolicy {
+            &self.quit_policy
+        }
+
+        fn lifetime_deadline(&self) -> Option<Instant> {
+            self.lifetime_deadline
+        }
+
+        fn lifetime_grace(&self) -> Duration {
+            self.lifetime_grace
+        }
+    }
+
+    /// Keep one side sending data for a while, and get the idle reason after all sides stopped
+    async fn idle_reason(client_active: bool) -> &'static str {
+        let task = MockTransitTask::new(Duration::from_millis(40));
+
+        let (mut clt_peer, clt_io) = tokio::io::duplex(1024);
+        let (mut ups_peer, ups_io) = tokio::io::duplex(1024);
+        let (clt_r, clt_w) = tokio::io::split(clt_io);
+        let (ups_r, ups_w) = tokio::io::split(ups_io);
+
+        let sender = tokio::spawn(async move {
+            for _ in 0..10 {
+                if client_active {
+                    clt_peer.write_all(b"a").await.unwrap();
+                } else {
+                    ups_peer.write_all(b"a").await.unwrap();
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            // keep the peers open
+            (clt_peer, ups_peer)
+        });
+
+        let e = task
+            .transit_transparent(clt_r, clt_w, ups_r, ups_w)
+            .await
+            .unwrap_err();
+        let _peers = sender.await.unwrap();
+        e.brief()
+    }
+
+    #[tokio::test]
+    async fn idle_direction_reason() {
+        assert_eq!(idle_reason(true).await, "UpstreamReadIdle");
+        assert_eq!(idle_reason(false).await, "ClientReadIdle");
+    }
+
+    #[tokio::test]
+    async fn lifetime_expired_drain() {
+        let task = MockTransitTask::with_lifetime(
+            Duration::from_secs(10),
+            Duration::from_millis(100),
+            Duration::from_secs(5),
+        );
+
+        let (mut clt_peer, clt_io) = tokio::io::duplex(1024);
+        let (mut ups_peer, ups_io) = tokio::io::duplex(1024);
+        let (clt_r, clt_w) = tokio::io::split(clt_io);
+        let (ups_r, ups_w) = tokio::io::split(ups_io);
+
+        // the upstream echo back all data, and close after the client side closed
+        let upstream = tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            let mut total = 0;
+            loop {
+                let n = ups_peer.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                total += n;
+                ups_peer.write_all(&buf[..n]).await.unwrap();
+            }
+            ups_peer.write_all(b"bye").await.unwrap();
+            ups_peer.shutdown().await.unwrap();
+            total
+        });
+        // a long-running client transfer, which will not stop by itself
+        let client = tokio::spawn(async move {
+            let (mut clt_peer_r, mut clt_peer_w) = tokio::io::split(&mut clt_peer);
+            // the write will only fail after the task has finished
+            let write = async {
+                while clt_peer_w.write_all(b"a").await.is_ok() {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            };
+            let read = async {
+                let mut received = Vec::new();
+                clt_peer_r.read_to_end(&mut received).await.unwrap();
+                received
+            };
+            let (_, received) = tokio::join!(write, read);
+            received
+        });
+
+        let time_start = Instant::now();
+        let e = task
+            .transit_transparent(clt_r, clt_w, ups_r, ups_w)
+            .await
+            .unwrap_err();
+        assert_eq!(e.brief(), "LifetimeExpired");
+        assert!(time_start.elapsed() < Duration::from_secs(5));
+
+        // all data received by the upstream have been echoed to the client before the close
+        let upstream_received = upstream.await.unwrap();
+        let client_received = client.await.unwrap();
+        assert!(upstream_received > 0);
+        assert_eq!(client_received.len(), upstream_received + 3);
+        assert!(client_received.ends_with(b"bye"));
+    }
+
+    #[tokio::test]
+    async fn lifetime_expired_abort() {
+        let task = MockTransitTask::with_lifetime(
+            Duration::from_secs(10),
+            Duration::from_millis(50),
+            Duration::from_millis(100),
+        );
+
+        let (clt_peer, clt_io) = tokio::io::duplex(1024);
+        let (ups_peer, ups_io) = tokio::io::duplex(1024);
+        let (clt_r, clt_w) = tokio::io::split(clt_io);
+        let (ups_r, ups_w) = tokio::io::split(ups_io);
+
+        // the upstream never closes, so the connection will be aborted after the grace period
+        let time_start = Instant::now();
+        let e = task
+            .transit_transparent(clt_r, clt_w, ups_r, ups_w)
+            .await
+            .unwrap_err();
+        assert_eq!(e.brief(), "LifetimeExpired");
+        assert!(time_start.elapsed() >= Duration::from_millis(100));
+        drop((clt_peer, ups_peer));
+    }
+
+    #[tokio::test]
+    async fn idle_before_lifetime_expired() {
+        let task = MockTransitTask::with_lifetime(
+            Duration::from_millis(20),
+            Duration::from_secs(10),
+            Duration::ZERO,
+        );
+
+        let (clt_peer, clt_io) = tokio::io::duplex(1024);
+        let (ups_peer, ups_io) = tokio::io::duplex(1024);
+        let (clt_r, clt_w) = tokio::io::split(clt_io);
+        let (ups_r, ups_w) = tokio::io::split(ups_io);
+
+        let e = task
+            .transit_transparent(clt_r, clt_w, ups_r, ups_w)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            e,
+            ServerTaskError::Idle(..) | ServerTaskError::DirectionalIdle(..)
+        ));
+        drop((clt_peer, ups_peer));
+    }
+}
@@ -16,6 +16,8 @@ use g3_daemon::opts::{DaemonArgs, DaemonArgsExt};
 
 const ARGS_COMPLETION: &str = "completion";
 const ARGS_VERSION: &str = "version";
+const ARGS_DRY_RUN: &str = "dry-run";
+const ARGS_DUMP_CONFIG_SCHEMA: &str = "dump-config-schema";
 const ARGS_GROUP_NAME: &str = "group-name";
 const ARGS_CONFIG_FILE: &str = "config-file";
 const ARGS_CONTROL_DIR: &str = "control-dir";
@@ -25,12 +27,16 @@ static DAEMON_GROUP: OnceLock<String> = OnceLock::new();
 #[derive(Debug)]
 pub struct ProcArgs {
     pub daemon_config: DaemonArgs,
+    pub dry_run: bool,
+    pub dump_config_schema: bool,
 }
 
 impl Default for ProcArgs {
     fn default() -> Self {
         ProcArgs {
             daemon_config: DaemonArgs::new(crate::build::PKG_NAME),
+            dry_run: false,
+            dump_config_schema: false,
         }
     }
 }
@@ -54,6 +60,18 @@ fn build_cli_args() -> Command {
                 .short('V')
                 .long("version"),
         )
+        .arg(
+            Arg::new(ARGS_DRY_RUN)
+                .help("Check the config file and report the action for each server")
+                .action(ArgAction::SetTrue)
+                .long("dry-run"),
+        )
+        .arg(
+            Arg::new(ARGS_DUMP_CONFIG_SCHEMA)
+                .help("Dump the json schema of the supported config keys")
+                .action(ArgAction::SetTrue)
+                .long("dump-config-schema"),
+        )
         .arg(
             Arg::new(ARGS_GROUP_NAME)
                 .help("Group name")
@@ -81,7 +99,11 @@ fn build_cli_args() -> Command {
                 .value_name("CONFIG FILE")
                 .value_hint(ValueHint::FilePath)
                 .value_parser(value_parser!(PathBuf))
-                .required_unless_present_any([ARGS_COMPLETION, ARGS_VERSION])
+                .required_unless_present_any([
+                    ARGS_COMPLETION,
+                    ARGS_VERSION,
+                    ARGS_DUMP_CONFIG_SCHEMA,
+                ])
                 .short('c')
                 .long("config-file"),
         )
@@ -105,6 +127,13 @@ pub fn parse_clap() -> anyhow::Result<Option<ProcArgs>> {
         crate::build::print_version(proc_args.daemon_config.verbose_level);
         return Ok(None);
     }
+    if args.get_flag(ARGS_DRY_RUN) {
+        proc_args.dry_run = true;
+    }
+    if args.get_flag(ARGS_DUMP_CONFIG_SCHEMA) {
+        proc_args.dump_config_schema = true;
+        return Ok(Some(proc_args));
+    }
     if let Some(config_file) = args.get_one::<PathBuf>(ARGS_CONFIG_FILE) {
         g3_daemon::opts::validate_and_set_config_file(config_file, crate::build::PKG_NAME)
             .context(format!(
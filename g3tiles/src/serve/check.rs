@@ -0,0 +1,24 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::time::Duration;
+
+/// The timeout for each step of the loopback self check
+pub(crate) const LOOPBACK_CHECK_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// The latency report of a loopback self check
+pub(crate) struct ServerCheckReport {
+    pub(crate) connect_time: Duration,
+    pub(crate) handshake_time: Option<Duration>,
+}
+
+impl ServerCheckReport {
+    pub(crate) fn new(connect_time: Duration) -> Self {
+        ServerCheckReport {
+            connect_time,
+            handshake_time: None,
+        }
+    }
+}
@@ -8,8 +8,10 @@ use std::time::Duration;
 
 use thiserror::Error;
 
+use g3_io_ext::{IdleDirection, IdleTracker};
 use g3_types::net::ConnectError;
 
+use crate::config::server::ClientErrorClass;
 use crate::module::stream::StreamConnectError;
 
 #[derive(Error, Debug)]
@@ -22,6 +24,8 @@ pub(crate) enum ServerTaskError {
     ClientTcpWriteFailed(io::Error),
     #[error("invalid client protocol: {0}")]
     InvalidClientProtocol(&'static str),
+    #[error("client tls handshake failed: {0:?}")]
+    ClientTlsHandshakeFailed(io::Error),
     #[error("upstream not resolved")]
     UpstreamNotResolved,
     #[error("upstream not connected: {0}")]
@@ -36,6 +40,10 @@ pub(crate) enum ServerTaskError {
     CanceledAsServerQuit,
     #[error("idle after {0:?} x {1}")]
     Idle(Duration, usize),
+    #[error("{0} idle after {1:?} x {2}")]
+    DirectionalIdle(IdleDirection, Duration, usize),
+    #[error("max connection lifetime expired")]
+    LifetimeExpired,
     #[allow(unused)]
     #[error("finished")]
     Finished, // this isn't an error, for log only
@@ -44,12 +52,43 @@ pub(crate) enum ServerTaskError {
 }
 
 impl ServerTaskError {
+    /// Get the idle error, with the dominant idle side if it has been tracked
+    pub(crate) fn idle(period: Duration, tracker: &IdleTracker) -> Self {
+        match tracker.dominant_direction() {
+            Some(direction) => {
+                ServerTaskError::DirectionalIdle(direction, period, tracker.idle_count())
+            }
+            None => ServerTaskError::Idle(period, tracker.idle_count()),
+        }
+    }
+
+    /// Get the class of the failure, which decides the client visible behavior
+    pub(crate) fn client_error_class(&self) -> Option<ClientErrorClass> {
+        match self {
+            ServerTaskError::UpstreamNotConnected(e) => match e {
+                ConnectError::ConnectionRefused | ConnectError::ConnectionReset => {
+                    Some(ClientErrorClass::UpstreamConnectRefused)
+                }
+                ConnectError::TimedOut => Some(ClientErrorClass::UpstreamConnectTimeout),
+                _ => None,
+            },
+            ServerTaskError::Idle(_, _) | ServerTaskError::DirectionalIdle(_, _, _) => {
+                Some(ClientErrorClass::IdleKilled)
+            }
+            ServerTaskError::LifetimeExpired | ServerTaskError::CanceledAsServerQuit => {
+                Some(ClientErrorClass::LifetimeExpired)
+            }
+            _ => None,
+        }
+    }
+
     pub(crate) fn brief(&self) -> &'static str {
         match self {
             ServerTaskError::InternalServerError(_) => "InternalServerError",
             ServerTaskError::ClientTcpReadFailed(_) => "ClientTcpReadFailed",
             ServerTaskError::ClientTcpWriteFailed(_) => "ClientTcpWriteFailed",
             ServerTaskError::InvalidClientProtocol(_) => "InvalidClientProtocol",
+            ServerTaskError::ClientTlsHandshakeFailed(_) => "ClientTlsHandshakeFailed",
             ServerTaskError::UpstreamNotResolved => "UpstreamNotResolved",
             ServerTaskError::UpstreamNotConnected(_) => "UpstreamNotConnected",
             ServerTaskError::UpstreamReadFailed(_) => "UpstreamReadFailed",
@@ -57,6 +96,13 @@ impl ServerTaskError {
             ServerTaskError::ClosedByClient => "ClosedByClient",
             ServerTaskError::CanceledAsServerQuit => "CanceledAsServerQuit",
             ServerTaskError::Idle(_, _) => "Idle",
+            ServerTaskError::DirectionalIdle(direction, _, _) => match direction {
+                IdleDirection::ClientRead => "ClientReadIdle",
+                IdleDirection::UpstreamRead => "UpstreamReadIdle",
+                IdleDirection::IcapRead => "IcapReadIdle",
+                IdleDirection::WriteBlocked => "WriteBlockedIdle",
+            },
+            ServerTaskError::LifetimeExpired => "LifetimeExpired",
             ServerTaskError::Finished => "Finished",
             ServerTaskError::UnclassifiedError(_) => "UnclassifiedError",
         }
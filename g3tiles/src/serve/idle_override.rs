@@ -0,0 +1,124 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use g3_io_ext::IdleWheel;
+
+use crate::config::server::TaskIdleOverrides;
+
+/// The task idle check values resolved from a matched task idle override
+pub(crate) struct ServerIdleOverride {
+    name: Arc<str>,
+    pub(crate) idle_wheel: Arc<IdleWheel>,
+    pub(crate) max_idle_count: usize,
+}
+
+impl ServerIdleOverride {
+    pub(crate) fn name(&self) -> &Arc<str> {
+        &self.name
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ServerIdleOverrides {
+    config: TaskIdleOverrides,
+    overrides: Vec<Arc<ServerIdleOverride>>,
+}
+
+impl ServerIdleOverrides {
+    /// Build from the config, the server defaults will be used for the values not set
+    pub(crate) fn new(
+        config: &TaskIdleOverrides,
+        idle_wheel: &Arc<IdleWheel>,
+        task_idle_check_duration: Duration,
+        task_idle_max_count: usize,
+    ) -> Self {
+        let overrides = config
+            .iter()
+            .map(|o| {
+                let check_duration = o.check_duration(task_idle_check_duration);
+                let idle_wheel = if check_duration == task_idle_check_duration {
+                    idle_wheel.clone()
+                } else {
+                    IdleWheel::spawn(check_duration)
+                };
+                Arc::new(ServerIdleOverride {
+                    name: Arc::from(o.name()),
+                    idle_wheel,
+                    max_idle_count: o.max_count(task_idle_max_count),
+                })
+            })
+            .collect();
+        ServerIdleOverrides {
+            config: config.clone(),
+            overrides,
+        }
+    }
+
+    /// Select the override for the task from the client ip
+    pub(crate) fn select(&self, client_ip: IpAddr) -> Option<Arc<ServerIdleOverride>> {
+        let i = self.config.select(client_ip)?;
+        self.overrides.get(i).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::time::Instant;
+    use yaml_rust::YamlLoader;
+
+    fn build_overrides() -> ServerIdleOverrides {
+        let doc = YamlLoader::load_from_str(
+            r#"
+- net: 127.0.0.0/8
+  task_idle_check_duration: 50ms
+  task_idle_max_count: 3
+- net: 127.0.0.2/32
+  task_idle_check_duration: 20ms
+  task_idle_max_count: 1
+"#,
+        )
+        .unwrap();
+        let config = TaskIdleOverrides::parse_yaml(&doc[0]).unwrap();
+        let idle_wheel = IdleWheel::spawn(Duration::from_secs(60));
+        ServerIdleOverrides::new(&config, &idle_wheel, Duration::from_secs(60), 5)
+    }
+
+    /// Get the time elapsed before an idle task is killed, the same way as the stream transit
+    async fn idle_quit_time(idle_override: &ServerIdleOverride) -> Duration {
+        let time_start = Instant::now();
+        let mut idle_interval = idle_override.idle_wheel.register();
+        let mut idle_count = 0;
+        loop {
+            idle_count += idle_interval.tick().await;
+            if idle_count >= idle_override.max_idle_count {
+                return time_start.elapsed();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn select_by_client_ip() {
+        let overrides = build_overrides();
+
+        let short = overrides.select("127.0.0.2".parse().unwrap()).unwrap();
+        assert_eq!(short.name().as_ref(), "net:127.0.0.2/32");
+        let long = overrides.select("127.0.0.3".parse().unwrap()).unwrap();
+        assert_eq!(long.name().as_ref(), "net:127.0.0.0/8");
+        assert!(overrides.select("192.0.2.1".parse().unwrap()).is_none());
+
+        let (short_time, long_time) = tokio::join!(idle_quit_time(&short), idle_quit_time(&long));
+        // 1 tick of 20ms
+        assert!(short_time >= Duration::from_millis(20));
+        assert!(short_time < Duration::from_millis(150));
+        // 3 ticks of 50ms
+        assert!(long_time >= Duration::from_millis(150));
+    }
+}
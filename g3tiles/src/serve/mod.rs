@@ -5,6 +5,7 @@
 
 use std::sync::Arc;
 
+use anyhow::anyhow;
 use async_trait::async_trait;
 #[cfg(feature = "quic")]
 use quinn::Connection;
@@ -21,7 +22,7 @@ use crate::config::server::AnyServerConfig;
 
 mod registry;
 use registry::ServerRegistry;
-pub(crate) use registry::{get_names, get_or_insert_default};
+pub(crate) use registry::{get_config, get_names, get_or_insert_default};
 
 mod error;
 pub(crate) use error::{ServerTaskError, ServerTaskResult};
@@ -45,8 +46,22 @@ pub use ops::{spawn_all, spawn_offline_clean};
 mod task;
 pub(crate) use task::{ServerTaskNotes, ServerTaskStage};
 
+mod idle_override;
+pub(crate) use idle_override::{ServerIdleOverride, ServerIdleOverrides};
+
+mod check;
+pub(crate) use check::{LOOPBACK_CHECK_TIMEOUT, ServerCheckReport};
+
 mod stats;
-pub(crate) use stats::{ArcServerStats, ServerStats};
+pub(crate) use stats::{
+    ArcServerStats, BackendPoolSnapshotMap, BackendPoolStats, BackendPoolStatsMap,
+    CertReloadSnapshot, CertReloadStats, CertResolverSnapshot, CertResolverStats,
+    ClientCertRouteSnapshot, ClientCertRouteStats, ClientHelloBufferSnapshot,
+    ClientHelloBufferStats, ClientIpLimitSnapshot, ClientIpLimitStats, EarlyDataSnapshot,
+    EarlyDataStats, HandshakeLimitSnapshot, HandshakeLimitStats, HostHealthSnapshotMap,
+    HostHealthStats, HostHealthStatsMap, HttpAwareSnapshot, HttpAwareStats, ServedCertSnapshot,
+    ServedCertStats, ServerStats, SessionSniMismatchSnapshot, SessionSniMismatchStats,
+};
 
 #[async_trait]
 pub(crate) trait Server: BaseServer + AcceptTcpServer + AcceptQuicServer {
@@ -59,6 +74,23 @@ pub(crate) trait Server: BaseServer + AcceptTcpServer + AcceptQuicServer {
     fn quit_policy(&self) -> &Arc<ServerQuitPolicy>;
 
     fn update_backend(&self, name: &NodeName);
+
+    /// Connect to the server through the loopback interface and report the latency
+    ///
+    /// The TLS handshake to `sni` will only be done by servers that support it.
+    async fn loopback_check(&self, _sni: Option<&str>) -> anyhow::Result<ServerCheckReport> {
+        let listen_stats = self.get_listen_stats();
+        let (_stream, connect_time) =
+            g3_daemon::listen::loopback_connect(&listen_stats, LOOPBACK_CHECK_TIMEOUT).await?;
+        Ok(ServerCheckReport::new(connect_time))
+    }
+
+    /// Replace the TLS ticket encrypt key right now, the old key is still valid for decryption
+    fn rotate_tls_ticket_key(&self) -> anyhow::Result<()> {
+        Err(anyhow!(
+            "tls ticket key rotation is not supported by this server"
+        ))
+    }
 }
 
 trait ServerInternal: Server {
@@ -0,0 +1,359 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use ahash::AHashMap;
+use anyhow::anyhow;
+use bytes::Bytes;
+use h2::client::SendRequest;
+use h2::server::SendResponse;
+use h2::{Reason, RecvStream};
+use http::{Request, Response};
+use tokio::sync::Mutex;
+
+use g3_h2::H2BodyTransfer;
+use g3_types::metrics::NodeName;
+
+use crate::backend::ArcBackend;
+use crate::config::server::openssl_proxy::OpensslBackendH2Config;
+use crate::serve::{ServerTaskNotes, ServerTaskResult};
+
+struct H2BackendConnection {
+    send_request: SendRequest<Bytes>,
+    streams: Arc<AtomicUsize>,
+    closed: Arc<AtomicBool>,
+}
+
+impl H2BackendConnection {
+    fn new_stream(&self) -> H2BackendStream {
+        self.streams.fetch_add(1, Ordering::AcqRel);
+        H2BackendStream {
+            send_request: self.send_request.clone(),
+            streams: self.streams.clone(),
+        }
+    }
+}
+
+/// A stream slot on a shared backend h2 connection, which will be released on drop
+pub(crate) struct H2BackendStream {
+    send_request: SendRequest<Bytes>,
+    streams: Arc<AtomicUsize>,
+}
+
+impl Drop for H2BackendStream {
+    fn drop(&mut self) {
+        self.streams.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// The shared backend h2 connections of a single host, the connections are kept for each backend.
+pub(crate) struct OpensslH2BackendPool {
+    config: OpensslBackendH2Config,
+    connections: Mutex<AHashMap<NodeName, Vec<H2BackendConnection>>>,
+}
+
+impl OpensslH2BackendPool {
+    pub(super) fn new(config: &OpensslBackendH2Config) -> Self {
+        OpensslH2BackendPool {
+            config: config.clone(),
+            connections: Mutex::new(AHashMap::new()),
+        }
+    }
+
+    pub(super) fn match_config(&self, config: &OpensslBackendH2Config) -> bool {
+        self.config.eq(config)
+    }
+
+    #[inline]
+    pub(super) fn config(&self) -> &OpensslBackendH2Config {
+        &self.config
+    }
+
+    /// Get a stream slot on a backend connection that has not reached the stream limit,
+    /// a new connection will be created if there is none.
+    pub(super) async fn get_stream(
+        &self,
+        backend: &ArcBackend,
+        task_notes: &ServerTaskNotes,
+    ) -> ServerTaskResult<H2BackendStream> {
+        // keep the lock while connecting, so the concurrent streams will share the new connection
+        let mut connections = self.connections.lock().await;
+        let list = connections.entry(backend.name().clone()).or_default();
+        list.retain(|c| !c.closed.load(Ordering::Acquire));
+        if let Some(c) = list
+            .iter()
+            .find(|c| c.streams.load(Ordering::Acquire) < self.config.max_streams_per_connection)
+        {
+            return Ok(c.new_stream());
+        }
+
+        let c = self.connect(backend, task_notes).await?;
+        let stream = c.new_stream();
+        list.push(c);
+        Ok(stream)
+    }
+
+    async fn connect(
+        &self,
+        backend: &ArcBackend,
+        task_notes: &ServerTaskNotes,
+    ) -> ServerTaskResult<H2BackendConnection> {
+        let (ups_r, ups_w) = backend.stream_connect(task_notes).await?;
+
+        let mut client_builder = h2::client::Builder::new();
+        client_builder
+            .enable_push(false)
+            .initial_window_size(self.config.stream_window_size())
+            .initial_connection_window_size(self.config.connection_window_size());
+        let (send_request, connection) = match tokio::time::timeout(
+            self.config.handshake_timeout,
+            client_builder.handshake(tokio::io::join(ups_r, ups_w)),
+        )
+        .await
+        {
+            Ok(Ok(d)) => d,
+            Ok(Err(e)) => return Err(anyhow!("backend h2 handshake failed: {e}").into()),
+            Err(_) => return Err(anyhow!("backend h2 handshake timed out").into()),
+        };
+
+        let closed = Arc::new(AtomicBool::new(false));
+        let closed_flag = closed.clone();
+        tokio::spawn(async move {
+            let _ = connection.await;
+            closed_flag.store(true, Ordering::Release);
+        });
+
+        Ok(H2BackendConnection {
+            send_request,
+            streams: Arc::new(AtomicUsize::new(0)),
+            closed,
+        })
+    }
+}
+
+/// Map a client h2 stream onto a backend h2 stream.
+///
+/// The stream will be reset if the backend stream can not be opened or the response head is not
+/// received, errors after the response head has been sent will be left to h2 to reset the stream.
+pub(super) async fn transfer_stream(
+    clt_req: Request<RecvStream>,
+    mut clt_send_rsp: SendResponse<Bytes>,
+    ups: H2BackendStream,
+    yield_size: usize,
+) {
+    let mut ups_send_req = match ups.send_request.clone().ready().await {
+        Ok(s) => s,
+        Err(e) => {
+            clt_send_rsp.send_reset(e.reason().unwrap_or(Reason::REFUSED_STREAM));
+            return;
+        }
+    };
+
+    let (parts, clt_body) = clt_req.into_parts();
+    let no_req_body = clt_body.is_end_stream();
+    let (ups_rsp_fut, ups_send_stream) =
+        match ups_send_req.send_request(Request::from_parts(parts, ()), no_req_body) {
+            Ok(d) => d,
+            Err(e) => {
+                clt_send_rsp.send_reset(e.reason().unwrap_or(Reason::REFUSED_STREAM));
+                return;
+            }
+        };
+
+    let req_body = async {
+        if !no_req_body {
+            let _ = H2BodyTransfer::new(clt_body, ups_send_stream, yield_size).await;
+        }
+    };
+    let rsp = async {
+        let ups_rsp = match ups_rsp_fut.await {
+            Ok(rsp) => rsp,
+            Err(e) => {
+                clt_send_rsp.send_reset(e.reason().unwrap_or(Reason::INTERNAL_ERROR));
+                return;
+            }
+        };
+        let (parts, ups_body) = ups_rsp.into_parts();
+        let no_rsp_body = ups_body.is_end_stream();
+        let Ok(clt_send_stream) =
+            clt_send_rsp.send_response(Response::from_parts(parts, ()), no_rsp_body)
+        else {
+            return;
+        };
+        if !no_rsp_body {
+            let _ = H2BodyTransfer::new(ups_body, clt_send_stream, yield_size).await;
+        }
+    };
+    tokio::join!(req_body, rsp);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::Barrier;
+
+    use g3_daemon::server::ClientConnectionInfo;
+
+    use crate::backend::Backend;
+    use crate::module::stream::{StreamConnectError, StreamConnectResult};
+
+    struct MockBackend {
+        name: NodeName,
+        addr: SocketAddr,
+        connected: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Backend for MockBackend {
+        fn name(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn discover(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn update_discover(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn alive_connection(&self) -> u64 {
+            0
+        }
+
+        async fn stream_connect(&self, _task_notes: &ServerTaskNotes) -> StreamConnectResult {
+            let stream = TcpStream::connect(self.addr)
+                .await
+                .map_err(StreamConnectError::SetupSocketFailed)?;
+            self.connected.fetch_add(1, Ordering::Relaxed);
+            let (r, w) = stream.into_split();
+            Ok((Box::new(r), Box::new(w)))
+        }
+    }
+
+    /// Start a h2 backend, which waits for 2 concurrent requests on the same connection before
+    /// sending the responses, and replies the request path as the response body
+    async fn start_backend(barrier: Arc<Barrier>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let barrier = barrier.clone();
+                tokio::spawn(async move {
+                    let mut h2c = h2::server::handshake(stream).await.unwrap();
+                    while let Some(Ok((req, mut send_rsp))) = h2c.accept().await {
+                        let barrier = barrier.clone();
+                        tokio::spawn(async move {
+                            barrier.wait().await;
+                            let body = Bytes::from(req.uri().path().to_string());
+                            let mut send_stream =
+                                send_rsp.send_response(Response::new(()), false).unwrap();
+                            send_stream.send_data(body, true).unwrap();
+                        });
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    fn new_backend(addr: SocketAddr) -> (Arc<MockBackend>, ArcBackend) {
+        let mock = Arc::new(MockBackend {
+            name: NodeName::from_str("mock").unwrap(),
+            addr,
+            connected: AtomicUsize::new(0),
+        });
+        let backend: ArcBackend = mock.clone();
+        (mock, backend)
+    }
+
+    fn task_notes() -> ServerTaskNotes {
+        let cc_info = ClientConnectionInfo::new(
+            SocketAddr::from(([127, 0, 0, 1], 12345)),
+            SocketAddr::from(([127, 0, 0, 1], 443)),
+        );
+        ServerTaskNotes::new(cc_info, Duration::ZERO)
+    }
+
+    /// Serve a client h2 connection by mapping all streams to the backend
+    async fn serve_client(
+        stream: tokio::io::DuplexStream,
+        pool: Arc<OpensslH2BackendPool>,
+        backend: ArcBackend,
+    ) {
+        let mut h2c = h2::server::handshake(stream).await.unwrap();
+        while let Some(Ok((req, send_rsp))) = h2c.accept().await {
+            let ups = pool.get_stream(&backend, &task_notes()).await.unwrap();
+            tokio::spawn(transfer_stream(req, send_rsp, ups, 16 * 1024));
+        }
+    }
+
+    async fn send_get(mut send_req: SendRequest<Bytes>, path: &str) -> Bytes {
+        let req = Request::get(format!("https://example.net{path}"))
+            .body(())
+            .unwrap();
+        send_req = send_req.ready().await.unwrap();
+        let (rsp_fut, _) = send_req.send_request(req, true).unwrap();
+        let rsp = rsp_fut.await.unwrap();
+        assert_eq!(rsp.status(), 200);
+        let mut body = rsp.into_body();
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.unwrap();
+            let _ = body.flow_control().release_capacity(chunk.len());
+            data.extend_from_slice(&chunk);
+        }
+        Bytes::from(data)
+    }
+
+    #[tokio::test]
+    async fn multiplex_streams() {
+        let addr = start_backend(Arc::new(Barrier::new(2))).await;
+        let (mock, backend) = new_backend(addr);
+        let pool = Arc::new(OpensslH2BackendPool::new(&OpensslBackendH2Config::default()));
+
+        let (clt_io, proxy_io) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(serve_client(proxy_io, pool, backend));
+        let (send_req, connection) = h2::client::handshake(clt_io).await.unwrap();
+        tokio::spawn(connection);
+
+        // the backend replies only after both requests are received
+        let (body1, body2) =
+            tokio::join!(send_get(send_req.clone(), "/a"), send_get(send_req, "/b"));
+        assert_eq!(body1.as_ref(), b"/a");
+        assert_eq!(body2.as_ref(), b"/b");
+        assert_eq!(mock.connected.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn stream_limit() {
+        let addr = start_backend(Arc::new(Barrier::new(1))).await;
+        let (mock, backend) = new_backend(addr);
+        let config = OpensslBackendH2Config {
+            max_streams_per_connection: 1,
+            ..Default::default()
+        };
+        let pool = OpensslH2BackendPool::new(&config);
+        let task_notes = task_notes();
+
+        let s1 = pool.get_stream(&backend, &task_notes).await.unwrap();
+        let s2 = pool.get_stream(&backend, &task_notes).await.unwrap();
+        assert_eq!(mock.connected.load(Ordering::Relaxed), 2);
+
+        // the released slot should be reused
+        drop(s1);
+        let _s3 = pool.get_stream(&backend, &task_notes).await.unwrap();
+        assert_eq!(mock.connected.load(Ordering::Relaxed), 2);
+        drop(s2);
+    }
+}
@@ -0,0 +1,392 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use ahash::AHashMap;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use g3_daemon::server::ClientConnectionInfo;
+use g3_types::metrics::NodeName;
+
+use crate::backend::ArcBackend;
+use crate::config::server::openssl_proxy::OpensslBackendPoolConfig;
+use crate::module::stream::StreamConnectError;
+use crate::serve::{BackendPoolStats, ServerTaskNotes};
+
+/// A backend connection that can be put back to the pool after use
+pub(crate) struct PooledStream {
+    pub(crate) reader: Box<dyn AsyncRead + Unpin + Send + Sync>,
+    pub(crate) writer: Box<dyn AsyncWrite + Unpin + Send + Sync>,
+    created: Instant,
+}
+
+impl PooledStream {
+    fn new(
+        reader: Box<dyn AsyncRead + Unpin + Send + Sync>,
+        writer: Box<dyn AsyncWrite + Unpin + Send + Sync>,
+    ) -> Self {
+        PooledStream {
+            reader,
+            writer,
+            created: Instant::now(),
+        }
+    }
+
+    fn is_expired(&self, max_lifetime: Duration) -> bool {
+        self.created.elapsed() >= max_lifetime
+    }
+
+    /// Check if the idle connection is still usable without waiting.
+    ///
+    /// A clean idle connection should have nothing to read, a zero-byte read means the backend
+    /// has closed it, and any data read means it's polluted by the previous client.
+    fn is_clean(&mut self) -> bool {
+        let mut buf = [0u8; 1];
+        let mut read_buf = ReadBuf::new(&mut buf);
+        let mut cx = Context::from_waker(Waker::noop());
+        matches!(
+            Pin::new(&mut self.reader).poll_read(&mut cx, &mut read_buf),
+            Poll::Pending
+        )
+    }
+}
+
+/// The backend connection pool of a single host, the connections are kept for each backend.
+pub(crate) struct OpensslBackendPool {
+    config: OpensslBackendPoolConfig,
+    stats: Arc<BackendPoolStats>,
+    idle: Mutex<AHashMap<NodeName, VecDeque<PooledStream>>>,
+    returned: Notify,
+    refilling: AtomicBool,
+}
+
+impl OpensslBackendPool {
+    pub(super) fn new(config: &OpensslBackendPoolConfig, stats: Arc<BackendPoolStats>) -> Self {
+        OpensslBackendPool {
+            config: config.clone(),
+            stats,
+            idle: Mutex::new(AHashMap::new()),
+            returned: Notify::new(),
+            refilling: AtomicBool::new(false),
+        }
+    }
+
+    pub(super) fn match_config(&self, config: &OpensslBackendPoolConfig) -> bool {
+        self.config.eq(config)
+    }
+
+    fn idle_count(&self, backend: &NodeName) -> usize {
+        let idle = self.idle.lock().unwrap();
+        idle.get(backend).map(|q| q.len()).unwrap_or_default()
+    }
+
+    fn take_idle(&self, backend: &NodeName) -> Option<PooledStream> {
+        let mut idle = self.idle.lock().unwrap();
+        let queue = idle.get_mut(backend)?;
+        // use the most recently returned one first
+        while let Some(mut stream) = queue.pop_back() {
+            self.stats.dec_idle(1);
+            if !stream.is_expired(self.config.max_lifetime) && stream.is_clean() {
+                return Some(stream);
+            }
+        }
+        None
+    }
+
+    /// Get a connection to the backend, the idle ones in the pool will be used first
+    pub(super) async fn get(
+        self: &Arc<Self>,
+        backend: &ArcBackend,
+        task_notes: &ServerTaskNotes,
+        cc_info: &ClientConnectionInfo,
+    ) -> Result<PooledStream, StreamConnectError> {
+        let r = self.get_idle(backend.name()).await;
+        self.spawn_refill(backend, task_notes, cc_info);
+        if let Some(stream) = r {
+            self.stats.add_reused();
+            return Ok(stream);
+        }
+
+        let (reader, writer) = backend.stream_connect(task_notes).await?;
+        self.stats.add_created();
+        Ok(PooledStream::new(reader, writer))
+    }
+
+    async fn get_idle(&self, backend: &NodeName) -> Option<PooledStream> {
+        if self.config.wait_timeout.is_zero() {
+            return self.take_idle(backend);
+        }
+
+        let time_start = Instant::now();
+        let deadline = time_start + self.config.wait_timeout;
+        let r = loop {
+            let notified = self.returned.notified();
+            tokio::pin!(notified);
+            // register before checking, so we won't miss the returned connections
+            notified.as_mut().enable();
+
+            if let Some(stream) = self.take_idle(backend) {
+                break Some(stream);
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                break None;
+            }
+        };
+        self.stats.record_wait_time(time_start.elapsed());
+        r
+    }
+
+    /// Put back a clean connection, it will be dropped if the pool is full or it's expired
+    pub(super) fn put(&self, backend: &NodeName, stream: PooledStream) {
+        if stream.is_expired(self.config.max_lifetime) {
+            return;
+        }
+
+        let mut idle = self.idle.lock().unwrap();
+        let queue = idle.entry(backend.clone()).or_default();
+        // the oldest ones are at the front
+        let mut expired = 0;
+        while queue
+            .front()
+            .map(|s| s.is_expired(self.config.max_lifetime))
+            .unwrap_or(false)
+        {
+            queue.pop_front();
+            expired += 1;
+        }
+        self.stats.dec_idle(expired);
+        if queue.len() >= self.config.max_idle {
+            return;
+        }
+        queue.push_back(stream);
+        drop(idle);
+
+        self.stats.inc_idle();
+        self.returned.notify_one();
+    }
+
+    fn spawn_refill(
+        self: &Arc<Self>,
+        backend: &ArcBackend,
+        task_notes: &ServerTaskNotes,
+        cc_info: &ClientConnectionInfo,
+    ) {
+        if self.idle_count(backend.name()) >= self.config.min_idle {
+            return;
+        }
+        if self.refilling.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let pool = self.clone();
+        let backend = backend.clone();
+        let backend_dscp = task_notes.backend_dscp;
+        let mut task_notes = ServerTaskNotes::new(cc_info.clone(), Duration::ZERO);
+        task_notes.backend_dscp = backend_dscp;
+        tokio::spawn(async move {
+            while pool.idle_count(backend.name()) < pool.config.min_idle {
+                let Ok((reader, writer)) = backend.stream_connect(&task_notes).await else {
+                    break;
+                };
+                pool.stats.add_created();
+                pool.put(backend.name(), PooledStream::new(reader, writer));
+            }
+            pool.refilling.store(false, Ordering::Release);
+        });
+    }
+}
+
+impl Drop for OpensslBackendPool {
+    fn drop(&mut self) {
+        let idle = self.idle.lock().unwrap();
+        let count = idle.values().map(|q| q.len()).sum();
+        self.stats.dec_idle(count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use std::sync::atomic::AtomicU64;
+
+    use async_trait::async_trait;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+
+    use crate::backend::Backend;
+    use crate::module::stream::StreamConnectResult;
+    use crate::serve::BackendPoolStatsMap;
+
+    struct MockBackend {
+        name: NodeName,
+        addr: SocketAddr,
+        connected: AtomicU64,
+    }
+
+    #[async_trait]
+    impl Backend for MockBackend {
+        fn name(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn discover(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn update_discover(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn alive_connection(&self) -> u64 {
+            0
+        }
+
+        async fn stream_connect(&self, _task_notes: &ServerTaskNotes) -> StreamConnectResult {
+            let stream = TcpStream::connect(self.addr)
+                .await
+                .map_err(StreamConnectError::SetupSocketFailed)?;
+            self.connected.fetch_add(1, Ordering::Relaxed);
+            let (r, w) = stream.into_split();
+            Ok((Box::new(r), Box::new(w)))
+        }
+    }
+
+    /// Start a backend server, and send out the accepted connections
+    async fn start_backend() -> (SocketAddr, mpsc::UnboundedReceiver<TcpStream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                if sender.send(stream).is_err() {
+                    break;
+                }
+            }
+        });
+        (addr, receiver)
+    }
+
+    fn new_pool(config: OpensslBackendPoolConfig) -> Arc<OpensslBackendPool> {
+        let stats = BackendPoolStatsMap::default().get_or_insert("test");
+        Arc::new(OpensslBackendPool::new(&config, stats))
+    }
+
+    fn cc_info() -> ClientConnectionInfo {
+        ClientConnectionInfo::new(
+            SocketAddr::from(([127, 0, 0, 1], 12345)),
+            SocketAddr::from(([127, 0, 0, 1], 443)),
+        )
+    }
+
+    #[tokio::test]
+    async fn reuse_sequential() {
+        let (addr, mut accepted) = start_backend().await;
+        let mock = Arc::new(MockBackend {
+            name: NodeName::from_str("mock").unwrap(),
+            addr,
+            connected: AtomicU64::new(0),
+        });
+        let backend: ArcBackend = mock.clone();
+        let pool = new_pool(OpensslBackendPoolConfig::default());
+        let cc_info = cc_info();
+        let task_notes = ServerTaskNotes::new(cc_info.clone(), Duration::ZERO);
+
+        let mut backend_streams = Vec::new();
+        for _ in 0..5 {
+            let stream = pool.get(&backend, &task_notes, &cc_info).await.unwrap();
+            pool.put(backend.name(), stream);
+            if let Ok(s) = accepted.try_recv() {
+                backend_streams.push(s);
+            }
+        }
+        assert_eq!(mock.connected.load(Ordering::Relaxed), 1);
+        let snap = pool.stats.snapshot();
+        assert_eq!(snap.created, 1);
+        assert_eq!(snap.reused, 4);
+        assert_eq!(snap.idle, 1);
+
+        // the backend closes the idle connection
+        let backend_stream = match backend_streams.pop() {
+            Some(s) => s,
+            None => accepted.recv().await.unwrap(),
+        };
+        drop(backend_stream);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let stream = pool.get(&backend, &task_notes, &cc_info).await.unwrap();
+        assert_eq!(mock.connected.load(Ordering::Relaxed), 2);
+        drop(stream);
+        let snap = pool.stats.snapshot();
+        assert_eq!(snap.created, 2);
+        assert_eq!(snap.idle, 0);
+    }
+
+    #[tokio::test]
+    async fn wait_returned() {
+        let (addr, _accepted) = start_backend().await;
+        let mock = Arc::new(MockBackend {
+            name: NodeName::from_str("mock").unwrap(),
+            addr,
+            connected: AtomicU64::new(0),
+        });
+        let backend: ArcBackend = mock.clone();
+        let config = OpensslBackendPoolConfig {
+            wait_timeout: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let pool = new_pool(config);
+        let cc_info = cc_info();
+        let task_notes = ServerTaskNotes::new(cc_info.clone(), Duration::ZERO);
+
+        let stream = pool.get(&backend, &task_notes, &cc_info).await.unwrap();
+        let pool2 = pool.clone();
+        let name = backend.name().clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            pool2.put(&name, stream);
+        });
+
+        let _stream = pool.get(&backend, &task_notes, &cc_info).await.unwrap();
+        assert_eq!(mock.connected.load(Ordering::Relaxed), 1);
+        assert_eq!(pool.stats.snapshot().reused, 1);
+    }
+
+    #[tokio::test]
+    async fn drop_polluted() {
+        let (addr, mut accepted) = start_backend().await;
+        let mock = Arc::new(MockBackend {
+            name: NodeName::from_str("mock").unwrap(),
+            addr,
+            connected: AtomicU64::new(0),
+        });
+        let backend: ArcBackend = mock.clone();
+        let pool = new_pool(OpensslBackendPoolConfig::default());
+        let cc_info = cc_info();
+        let task_notes = ServerTaskNotes::new(cc_info.clone(), Duration::ZERO);
+
+        let stream = pool.get(&backend, &task_notes, &cc_info).await.unwrap();
+        pool.put(backend.name(), stream);
+
+        // late data from the backend for the previous client
+        let backend_stream = accepted.recv().await.unwrap();
+        backend_stream.writable().await.unwrap();
+        backend_stream.try_write(b"late").unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let _stream = pool.get(&backend, &task_notes, &cc_info).await.unwrap();
+        assert_eq!(mock.connected.load(Ordering::Relaxed), 2);
+        assert_eq!(pool.stats.snapshot().reused, 0);
+    }
+}
@@ -0,0 +1,441 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, anyhow};
+use arc_swap::ArcSwapOption;
+use log::{info, warn};
+use openssl::pkey::PKey;
+use openssl::ssl::SslContext;
+use openssl::x509::X509;
+use tokio::sync::oneshot;
+
+use g3_types::net::{OpensslCertificatePair, OpensslTicketKey, RollingTicketer, TlsCertStatus};
+
+use crate::config::server::openssl_proxy::{OpensslCertPairFiles, OpensslHostConfig};
+use crate::serve::CertReloadStats;
+
+/// Changes within this duration will be merged, as the cert and key files are often written in turn
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Watch the cert pair files of a host, and reload the TLS context when they are changed.
+///
+/// The watch task will quit when this is dropped.
+pub(super) struct OpensslCertWatcher {
+    _quit_sender: oneshot::Sender<()>,
+}
+
+impl OpensslCertWatcher {
+    pub(super) fn spawn(
+        config: Arc<OpensslHostConfig>,
+        ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+        ssl_context: Arc<ArcSwapOption<SslContext>>,
+        stats: Arc<CertReloadStats>,
+    ) -> Self {
+        // start watching at once, so no changes after the initial load will be lost
+        let events = CertFileEvents::new(config.watched_cert_files());
+        let (quit_sender, quit_receiver) = oneshot::channel();
+        let reloader = CertReloader {
+            config,
+            ticketer,
+            ssl_context,
+            stats,
+        };
+        tokio::spawn(reloader.into_running(events, quit_receiver));
+        OpensslCertWatcher {
+            _quit_sender: quit_sender,
+        }
+    }
+}
+
+struct LoadedCertInfo {
+    subject: String,
+    serial: String,
+    not_after: String,
+}
+
+struct CertReloader {
+    config: Arc<OpensslHostConfig>,
+    ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+    ssl_context: Arc<ArcSwapOption<SslContext>>,
+    stats: Arc<CertReloadStats>,
+}
+
+impl CertReloader {
+    async fn into_running(
+        self,
+        mut events: CertFileEvents,
+        mut quit_receiver: oneshot::Receiver<()>,
+    ) {
+        loop {
+            tokio::select! {
+                biased;
+
+                _ = &mut quit_receiver => break,
+                _ = events.next_change() => self.reload().await,
+            }
+        }
+    }
+
+    async fn reload(&self) {
+        let host = self.config.name();
+        match self.load_ssl_context().await {
+            Ok((ssl_context, infos)) => {
+                self.ssl_context.store(Some(Arc::new(ssl_context)));
+                self.stats.add_success();
+                for info in infos {
+                    info!(
+                        "host {host}: reloaded certificate {} with serial {}, not after {}",
+                        info.subject, info.serial, info.not_after
+                    );
+                }
+            }
+            Err(e) => {
+                self.stats.add_failed();
+                warn!("host {host}: failed to reload cert files, the old ones are kept: {e:?}");
+            }
+        }
+    }
+
+    async fn load_ssl_context(&self) -> anyhow::Result<(SslContext, Vec<LoadedCertInfo>)> {
+        let files = self.config.watched_cert_files().to_vec();
+        let allow_expired = self.config.allow_expired();
+        let (cert_pairs, infos) =
+            tokio::task::spawn_blocking(move || load_cert_pairs(&files, allow_expired))
+                .await
+                .map_err(|e| anyhow!("failed to join the load task: {e}"))??;
+        let ssl_context = self
+            .config
+            .build_ssl_context_with_cert_pairs(self.ticketer.clone(), &cert_pairs)?;
+        Ok((ssl_context, infos))
+    }
+}
+
+fn load_cert_pairs(
+    files: &[OpensslCertPairFiles],
+    allow_expired: bool,
+) -> anyhow::Result<(Vec<OpensslCertificatePair>, Vec<LoadedCertInfo>)> {
+    let mut cert_pairs = Vec::with_capacity(files.len());
+    let mut infos = Vec::with_capacity(files.len());
+    for (i, f) in files.iter().enumerate() {
+        let (pair, info) =
+            load_cert_pair(f, allow_expired).context(format!("failed to load cert pair #{i}"))?;
+        cert_pairs.push(pair);
+        infos.push(info);
+    }
+    Ok((cert_pairs, infos))
+}
+
+fn load_cert_pair(
+    files: &OpensslCertPairFiles,
+    allow_expired: bool,
+) -> anyhow::Result<(OpensslCertificatePair, LoadedCertInfo)> {
+    let cert_path = &files.certificate;
+    let key_path = &files.private_key;
+
+    let content = std::fs::read(cert_path)
+        .map_err(|e| anyhow!("failed to read file {}: {e}", cert_path.display()))?;
+    let certs = X509::stack_from_pem(&content)
+        .map_err(|e| anyhow!("invalid certificate file {}: {e}", cert_path.display()))?;
+    let content = std::fs::read(key_path)
+        .map_err(|e| anyhow!("failed to read file {}: {e}", key_path.display()))?;
+    let key = PKey::private_key_from_pem(&content)
+        .map_err(|e| anyhow!("invalid private key file {}: {e}", key_path.display()))?;
+
+    let status = TlsCertStatus::check_openssl(&certs, &key)?;
+    if !allow_expired {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        if status.not_after() < now {
+            return Err(anyhow!("the certificate {} is expired", status.subject()));
+        }
+    }
+
+    let leaf = &certs[0];
+    let serial = leaf
+        .serial_number()
+        .to_bn()
+        .and_then(|bn| bn.to_hex_str().map(|s| s.to_string()))
+        .map_err(|e| anyhow!("invalid serial number in certificate: {e}"))?;
+    let info = LoadedCertInfo {
+        subject: status.subject().to_string(),
+        serial,
+        not_after: leaf.not_after().to_string(),
+    };
+
+    let mut pair = OpensslCertificatePair::default();
+    pair.set_certificates(certs)?;
+    pair.set_private_key(key)?;
+    Ok((pair, info))
+}
+
+enum CertFileEvents {
+    #[cfg(target_os = "linux")]
+    Inotify(InotifyEvents),
+    Poll(PollEvents),
+}
+
+impl CertFileEvents {
+    fn new(files: &[OpensslCertPairFiles]) -> Self {
+        let paths: Vec<PathBuf> = files
+            .iter()
+            .flat_map(|f| [f.certificate.clone(), f.private_key.clone()])
+            .collect();
+
+        #[cfg(target_os = "linux")]
+        match InotifyEvents::new(&paths) {
+            Ok(events) => return CertFileEvents::Inotify(events),
+            Err(e) => warn!("failed to watch cert files by inotify, fallback to polling: {e:?}"),
+        }
+
+        CertFileEvents::Poll(PollEvents::new(paths))
+    }
+
+    /// Wait until the files are changed and no more changes in the debounce duration
+    async fn next_change(&mut self) {
+        match self {
+            #[cfg(target_os = "linux")]
+            CertFileEvents::Inotify(events) => {
+                if let Err(e) = events.next_change().await {
+                    warn!("inotify watch of cert files failed, fallback to polling: {e}");
+                    // changes may be lost, so return to do a reload at once
+                    let paths = events.paths.clone();
+                    *self = CertFileEvents::Poll(PollEvents::new(paths));
+                }
+            }
+            CertFileEvents::Poll(events) => events.next_change().await,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct InotifyEvents {
+    paths: Vec<PathBuf>,
+    file_names: Vec<std::ffi::OsString>,
+    stream: inotify::EventStream<[u8; 4096]>,
+}
+
+#[cfg(target_os = "linux")]
+impl InotifyEvents {
+    fn new(paths: &[PathBuf]) -> anyhow::Result<Self> {
+        use inotify::{Inotify, WatchMask};
+
+        let inotify =
+            Inotify::init().map_err(|e| anyhow!("failed to init inotify instance: {e}"))?;
+        // watch the parent dirs, so files replaced by rename can also be detected
+        let mut dirs: Vec<&std::path::Path> = Vec::with_capacity(paths.len());
+        let mut file_names = Vec::with_capacity(paths.len());
+        for path in paths {
+            let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+                return Err(anyhow!("invalid file path {}", path.display()));
+            };
+            if !dirs.contains(&dir) {
+                inotify
+                    .watches()
+                    .add(
+                        dir,
+                        WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE,
+                    )
+                    .map_err(|e| anyhow!("failed to watch dir {}: {e}", dir.display()))?;
+                dirs.push(dir);
+            }
+            file_names.push(name.to_os_string());
+        }
+        let stream = inotify
+            .into_event_stream([0u8; 4096])
+            .map_err(|e| anyhow!("failed to create inotify event stream: {e}"))?;
+        Ok(InotifyEvents {
+            paths: paths.to_vec(),
+            file_names,
+            stream,
+        })
+    }
+
+    async fn next_event(&mut self) -> anyhow::Result<()> {
+        use std::future::poll_fn;
+
+        use futures_util::StreamExt;
+
+        loop {
+            match poll_fn(|cx| self.stream.poll_next_unpin(cx)).await {
+                Some(Ok(event)) => {
+                    if let Some(name) = event.name {
+                        if self.file_names.contains(&name) {
+                            return Ok(());
+                        }
+                    }
+                }
+                Some(Err(e)) => return Err(anyhow!("failed to read inotify event: {e}")),
+                None => return Err(anyhow!("inotify event stream ended unexpectedly")),
+            }
+        }
+    }
+
+    async fn next_change(&mut self) -> anyhow::Result<()> {
+        self.next_event().await?;
+        while let Ok(r) = tokio::time::timeout(DEBOUNCE_DURATION, self.next_event()).await {
+            r?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+struct PollEvents {
+    paths: Vec<PathBuf>,
+    stamps: Vec<Option<FileStamp>>,
+}
+
+impl PollEvents {
+    fn new(paths: Vec<PathBuf>) -> Self {
+        let stamps = load_file_stamps(&paths);
+        PollEvents { paths, stamps }
+    }
+
+    fn check_changed(&mut self) -> bool {
+        let stamps = load_file_stamps(&self.paths);
+        if stamps == self.stamps {
+            false
+        } else {
+            self.stamps = stamps;
+            true
+        }
+    }
+
+    async fn next_change(&mut self) {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            if self.check_changed() {
+                break;
+            }
+        }
+        loop {
+            tokio::time::sleep(DEBOUNCE_DURATION).await;
+            if !self.check_changed() {
+                break;
+            }
+        }
+    }
+}
+
+fn load_file_stamps(paths: &[PathBuf]) -> Vec<Option<FileStamp>> {
+    paths
+        .iter()
+        .map(|p| {
+            std::fs::metadata(p).ok().map(|m| FileStamp {
+                modified: m.modified().ok(),
+                len: m.len(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+
+    use openssl::pkey::Private;
+    use openssl::ssl::{Ssl, SslConnector, SslMethod, SslVerifyMode};
+    use yaml_rust::{Yaml, YamlLoader};
+
+    use g3_yaml::{YamlDocPosition, YamlMapCallback};
+
+    use crate::serve::openssl_proxy::OpensslHost;
+    use crate::testing::{TempDir, common_name, ec_key, self_signed_cert, write_cert, write_key};
+
+    fn write_test_cert(dir: &Path, serial: u32, key: &PKey<Private>) {
+        let cert = self_signed_cert(&common_name("test.example.net"), key, serial);
+        write_cert(&dir.join("test.crt"), &cert);
+    }
+
+    fn parse_host(dir: &Path) -> OpensslHostConfig {
+        let yaml = "name: test\n\
+                    cert_pairs:\n  - certificate: test.crt\n    private_key: test.key\n\
+                    watch_cert_files: true\n\
+                    backends:\n  - test\n";
+        let yaml = YamlLoader::load_from_str(yaml).unwrap();
+        let position = YamlDocPosition {
+            path: dir.join("main.yaml"),
+            index: 0,
+        };
+        let Yaml::Hash(map) = &yaml[0] else {
+            unreachable!()
+        };
+        let mut config = OpensslHostConfig::default();
+        g3_yaml::foreach_kv(map, |k, v| config.parse_kv(k, v, Some(&position))).unwrap();
+        config.check().unwrap();
+        config
+    }
+
+    fn served_serial(ssl_context: &SslContext) -> u32 {
+        let (server_sock, client_sock) = UnixStream::pair().unwrap();
+        let ssl = Ssl::new(ssl_context).unwrap();
+        let server = std::thread::spawn(move || ssl.accept(server_sock).map(|_| ()));
+
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        let connector = builder.build();
+        let stream = connector.connect("test.example.net", client_sock).unwrap();
+        server.join().unwrap().unwrap();
+
+        let cert = stream.ssl().peer_certificate().unwrap();
+        let serial = cert.serial_number().to_bn().unwrap();
+        serial.to_dec_str().unwrap().parse().unwrap()
+    }
+
+    async fn wait_reload(stats: &CertReloadStats, success: u64, failed: u64) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let snap = stats.snapshot();
+                if snap.success == success && snap.failed == failed {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reload_on_change() {
+        let temp_dir = TempDir::new("openssl_cert_watch");
+        let dir = temp_dir.path();
+        let key = ec_key();
+        write_test_cert(dir, 1, &key);
+        write_key(&dir.join("test.key"), &key);
+
+        let config = Arc::new(parse_host(dir));
+        let stats = Arc::new(CertReloadStats::default());
+        let host = OpensslHost::try_build(&config, &None, &stats, &Arc::default(), &Arc::default())
+            .unwrap();
+        assert_eq!(served_serial(&host.ssl_context().unwrap()), 1);
+
+        // replace both the cert and the key, which should be merged into one reload
+        let key = ec_key();
+        write_test_cert(dir, 2, &key);
+        write_key(&dir.join("test.key"), &key);
+        wait_reload(&stats, 1, 0).await;
+        assert_eq!(served_serial(&host.ssl_context().unwrap()), 2);
+
+        // a cert that does not match the key should be rejected
+        write_test_cert(dir, 3, &ec_key());
+        wait_reload(&stats, 1, 1).await;
+        assert_eq!(served_serial(&host.ssl_context().unwrap()), 2);
+    }
+}
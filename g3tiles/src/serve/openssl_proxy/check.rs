@@ -0,0 +1,49 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+
+use crate::config::server::openssl_proxy::IngressProxyProtocol;
+
+/// Do a TLS handshake to `sni` on the loopback connection and return the handshake latency
+///
+/// The server certificate is not verified, as only the availability of the server is checked.
+pub(super) async fn loopback_tls_handshake(
+    mut stream: TcpStream,
+    ingress_proxy_protocol: Option<IngressProxyProtocol>,
+    sni: &str,
+    timeout: Duration,
+) -> anyhow::Result<Duration> {
+    let mut builder = SslConnector::builder(SslMethod::tls_client())
+        .map_err(|e| anyhow!("failed to create ssl connector builder: {e}"))?;
+    builder.set_verify(SslVerifyMode::NONE);
+    let ssl = builder
+        .build()
+        .configure()
+        .and_then(|c| c.verify_hostname(false).into_ssl(sni))
+        .map_err(|e| anyhow!("failed to create ssl for {sni}: {e}"))?;
+
+    if let Some(protocol) = ingress_proxy_protocol {
+        stream
+            .write_all(super::ingress_local_header(protocol))
+            .await
+            .context("failed to send PROXY protocol header")?;
+    }
+
+    let time_start = Instant::now();
+    let connector = g3_openssl::SslConnector::new(ssl, stream)
+        .map_err(|e| anyhow!("failed to create ssl connector: {e}"))?;
+    match tokio::time::timeout(timeout, connector.connect()).await {
+        Ok(Ok(_stream)) => Ok(time_start.elapsed()),
+        Ok(Err(e)) => Err(anyhow!("tls handshake failed: {e}")),
+        Err(_) => Err(anyhow!("tls handshake timed out")),
+    }
+}
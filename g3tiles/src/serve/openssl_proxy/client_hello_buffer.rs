@@ -0,0 +1,278 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::{Bytes, BytesMut};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
+
+use crate::config::server::openssl_proxy::{
+    OpensslClientHelloBufferOverflow, OpensslClientHelloBufferPoolConfig,
+};
+use crate::serve::ClientHelloBufferStats;
+
+/// A server owned pool of reusable buffers for receiving the ClientHello message
+///
+/// The buffers are allocated lazily, and at most `pool_size` of them can be in use at the same time.
+/// What to do if all of them are in use is decided by the overflow policy.
+pub(crate) struct OpensslClientHelloBufferPool {
+    config: OpensslClientHelloBufferPoolConfig,
+    semaphore: Arc<Semaphore>,
+    idle: Mutex<Vec<BytesMut>>,
+    stats: Arc<ClientHelloBufferStats>,
+}
+
+impl OpensslClientHelloBufferPool {
+    pub(crate) fn new(
+        config: &OpensslClientHelloBufferPoolConfig,
+        stats: Arc<ClientHelloBufferStats>,
+    ) -> Self {
+        OpensslClientHelloBufferPool {
+            config: config.clone(),
+            semaphore: Arc::new(Semaphore::new(config.pool_size.get())),
+            idle: Mutex::new(Vec::with_capacity(config.pool_size.get())),
+            stats,
+        }
+    }
+
+    pub(crate) fn new_for_reload(
+        self: &Arc<Self>,
+        config: &OpensslClientHelloBufferPoolConfig,
+    ) -> Arc<Self> {
+        if self.config.eq(config) {
+            // keep the allocated buffers
+            self.clone()
+        } else {
+            Arc::new(OpensslClientHelloBufferPool::new(
+                config,
+                self.stats.clone(),
+            ))
+        }
+    }
+
+    /// Get a buffer from the pool, `None` will be returned if rejected by the overflow policy
+    ///
+    /// The wait for a returned buffer will be limited by `wait_timeout`.
+    pub(super) async fn acquire(
+        self: &Arc<Self>,
+        wait_timeout: Duration,
+    ) -> Option<ClientHelloBuffer> {
+        let permit = match self.semaphore.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(TryAcquireError::NoPermits) => match self.config.overflow {
+                OpensslClientHelloBufferOverflow::Allocate => {
+                    self.stats.add_overflow_allocated();
+                    return Some(ClientHelloBuffer::transient(self.config.buffer_size));
+                }
+                OpensslClientHelloBufferOverflow::Wait => {
+                    self.stats.add_overflow_waited();
+                    let acquire = self.semaphore.clone().acquire_owned();
+                    match tokio::time::timeout(wait_timeout, acquire).await {
+                        Ok(Ok(permit)) => permit,
+                        Ok(Err(_)) => return None,
+                        Err(_) => {
+                            self.stats.add_overflow_rejected();
+                            return None;
+                        }
+                    }
+                }
+                OpensslClientHelloBufferOverflow::Reject => {
+                    self.stats.add_overflow_rejected();
+                    return None;
+                }
+            },
+            Err(TryAcquireError::Closed) => return None,
+        };
+
+        let buf = self
+            .idle
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(|| BytesMut::with_capacity(self.config.buffer_size));
+        self.stats.add_in_use();
+        Some(ClientHelloBuffer {
+            buf,
+            pooled: Some(PooledSlot {
+                pool: self.clone(),
+                _permit: permit,
+            }),
+        })
+    }
+
+    fn put_back(&self, mut buf: BytesMut) {
+        self.stats.del_in_use();
+        // the buffer may have grown, or been split, while receiving a large ClientHello message
+        if buf.capacity() != self.config.buffer_size {
+            return;
+        }
+        // only the data in the range of the length can be read out,
+        // so there is no need to zero the whole buffer
+        buf.clear();
+        self.idle.lock().unwrap().push(buf);
+    }
+}
+
+struct PooledSlot {
+    pool: Arc<OpensslClientHelloBufferPool>,
+    _permit: OwnedSemaphorePermit,
+}
+
+/// The buffer used for receiving the ClientHello message,
+/// which will be returned to the pool on drop if it comes from a pool
+pub(crate) struct ClientHelloBuffer {
+    buf: BytesMut,
+    pooled: Option<PooledSlot>,
+}
+
+impl ClientHelloBuffer {
+    pub(super) fn transient(capacity: usize) -> Self {
+        ClientHelloBuffer {
+            buf: BytesMut::with_capacity(capacity),
+            pooled: None,
+        }
+    }
+
+    #[inline]
+    pub(super) fn buf_mut(&mut self) -> &mut BytesMut {
+        &mut self.buf
+    }
+
+    /// Take out the received data for the handshake handoff
+    ///
+    /// The data will be copied out if the buffer comes from a pool,
+    /// so the buffer can be returned to the pool at once.
+    pub(super) fn take_data(mut self) -> Bytes {
+        if self.pooled.is_some() {
+            Bytes::copy_from_slice(&self.buf)
+        } else {
+            self.buf.split().freeze()
+        }
+    }
+}
+
+impl Drop for ClientHelloBuffer {
+    fn drop(&mut self) {
+        if let Some(slot) = self.pooled.take() {
+            // return the buffer before the permit is released
+            slot.pool.put_back(std::mem::take(&mut self.buf));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroUsize;
+
+    use bytes::BufMut;
+
+    const WAIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+    fn new_pool(
+        pool_size: usize,
+        overflow: OpensslClientHelloBufferOverflow,
+    ) -> Arc<OpensslClientHelloBufferPool> {
+        let config = OpensslClientHelloBufferPoolConfig {
+            pool_size: NonZeroUsize::new(pool_size).unwrap(),
+            buffer_size: 1024,
+            overflow,
+        };
+        Arc::new(OpensslClientHelloBufferPool::new(
+            &config,
+            Arc::new(ClientHelloBufferStats::default()),
+        ))
+    }
+
+    #[tokio::test]
+    async fn reuse_without_leak() {
+        let pool = new_pool(1, OpensslClientHelloBufferOverflow::Reject);
+
+        let mut buffer = pool.acquire(WAIT_TIMEOUT).await.unwrap();
+        let ptr = buffer.buf_mut().as_ptr();
+        buffer.buf_mut().put_slice(b"secret client hello");
+        assert_eq!(pool.stats.snapshot().in_use, 1);
+        let data = buffer.take_data();
+        assert_eq!(data.as_ref(), b"secret client hello");
+        assert_eq!(pool.stats.snapshot().in_use, 0);
+
+        let mut buffer = pool.acquire(WAIT_TIMEOUT).await.unwrap();
+        assert_eq!(buffer.buf_mut().as_ptr(), ptr);
+        assert!(buffer.buf_mut().is_empty());
+        assert_eq!(buffer.buf_mut().capacity(), 1024);
+
+        // grown buffers will not be returned to the pool
+        buffer.buf_mut().put_slice(&[0u8; 2048]);
+        drop(buffer);
+        let mut buffer = pool.acquire(WAIT_TIMEOUT).await.unwrap();
+        assert!(buffer.buf_mut().is_empty());
+        assert_eq!(buffer.buf_mut().capacity(), 1024);
+    }
+
+    #[tokio::test]
+    async fn overflow_allocate() {
+        let pool = new_pool(2, OpensslClientHelloBufferOverflow::Allocate);
+
+        let mut buffers = Vec::new();
+        for _ in 0..5 {
+            buffers.push(pool.acquire(WAIT_TIMEOUT).await.unwrap());
+        }
+        let pooled = buffers.iter().filter(|b| b.pooled.is_some()).count();
+        assert_eq!(pooled, 2);
+        let snap = pool.stats.snapshot();
+        assert_eq!(snap.in_use, 2);
+        assert_eq!(snap.overflow_allocated, 3);
+
+        drop(buffers);
+        assert_eq!(pool.stats.snapshot().in_use, 0);
+        assert_eq!(pool.idle.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn overflow_wait() {
+        let pool = new_pool(1, OpensslClientHelloBufferOverflow::Wait);
+
+        let buffer = pool.acquire(WAIT_TIMEOUT).await.unwrap();
+        let pool2 = pool.clone();
+        let waiting = tokio::spawn(async move {
+            let buffer = pool2.acquire(WAIT_TIMEOUT).await;
+            buffer.is_some_and(|b| b.pooled.is_some())
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiting.is_finished());
+        assert_eq!(pool.stats.snapshot().overflow_waited, 1);
+
+        drop(buffer);
+        assert!(waiting.await.unwrap());
+
+        // timed out
+        let _buffer = pool.acquire(WAIT_TIMEOUT).await.unwrap();
+        assert!(pool.acquire(Duration::from_millis(10)).await.is_none());
+        let snap = pool.stats.snapshot();
+        assert_eq!(snap.overflow_waited, 2);
+        assert_eq!(snap.overflow_rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn overflow_reject() {
+        let pool = new_pool(2, OpensslClientHelloBufferOverflow::Reject);
+
+        let mut buffers = Vec::new();
+        let mut rejected = 0;
+        for _ in 0..5 {
+            match pool.acquire(WAIT_TIMEOUT).await {
+                Some(b) => buffers.push(b),
+                None => rejected += 1,
+            }
+        }
+        assert_eq!(buffers.len(), 2);
+        assert_eq!(rejected, 3);
+        assert_eq!(pool.stats.snapshot().overflow_rejected, 3);
+
+        buffers.pop();
+        assert!(pool.acquire(WAIT_TIMEOUT).await.is_some());
+    }
+}
@@ -0,0 +1,277 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use lru::LruCache;
+use tokio::time::Instant;
+
+use g3_types::acl::{AclAction, AclNetworkRule};
+
+use crate::config::server::openssl_proxy::OpensslClientIpLimitConfig;
+use crate::serve::ClientIpLimitStats;
+
+/// Sliding window counter, which weights the count of the previous window
+/// by the part of it that still overlaps with the sliding window
+#[derive(Default)]
+struct SlidingCount {
+    previous: usize,
+    current: usize,
+}
+
+impl SlidingCount {
+    fn estimate(&self, overlap: f64) -> f64 {
+        self.previous as f64 * overlap + self.current as f64
+    }
+}
+
+struct ClientIpEntry {
+    window_start: Instant,
+    connections: SlidingCount,
+    timeouts: SlidingCount,
+    limited_until: Option<Instant>,
+}
+
+impl ClientIpEntry {
+    fn new(now: Instant) -> Self {
+        ClientIpEntry {
+            window_start: now,
+            connections: SlidingCount::default(),
+            timeouts: SlidingCount::default(),
+            limited_until: None,
+        }
+    }
+
+    /// Move to the window that contains `now`, and return the overlap ratio of the previous one
+    fn slide(&mut self, now: Instant, window: Duration) -> f64 {
+        let mut elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= window * 2 {
+            self.window_start = now;
+            self.connections = SlidingCount::default();
+            self.timeouts = SlidingCount::default();
+            elapsed = Duration::ZERO;
+        } else if elapsed >= window {
+            self.window_start += window;
+            self.connections.previous = self.connections.current;
+            self.connections.current = 0;
+            self.timeouts.previous = self.timeouts.current;
+            self.timeouts.current = 0;
+            elapsed -= window;
+        }
+        1.0 - elapsed.as_secs_f64() / window.as_secs_f64()
+    }
+}
+
+/// Limit the rate of new connections from each client ip in the accept phase
+///
+/// The client ip will be limited for a penalty duration if there are too many new connections,
+/// or too many ClientHello recv timeouts, in the sliding window.
+pub(crate) struct OpensslClientIpLimiter {
+    config: OpensslClientIpLimitConfig,
+    exempt_networks: AclNetworkRule,
+    table: Mutex<LruCache<IpAddr, ClientIpEntry>>,
+    stats: Arc<ClientIpLimitStats>,
+}
+
+impl OpensslClientIpLimiter {
+    pub(crate) fn new(config: &OpensslClientIpLimitConfig, stats: Arc<ClientIpLimitStats>) -> Self {
+        OpensslClientIpLimiter {
+            config: config.clone(),
+            exempt_networks: config.exempt_networks.build(),
+            table: Mutex::new(LruCache::new(config.table_size)),
+            stats,
+        }
+    }
+
+    pub(crate) fn new_for_reload(
+        self: &Arc<Self>,
+        config: &OpensslClientIpLimitConfig,
+    ) -> Arc<Self> {
+        if self.config.eq(config) {
+            // keep the client ips that are limited
+            self.clone()
+        } else {
+            Arc::new(OpensslClientIpLimiter::new(config, self.stats.clone()))
+        }
+    }
+
+    #[inline]
+    pub(super) fn send_alert(&self) -> bool {
+        self.config.send_alert
+    }
+
+    fn is_exempt(&self, ip: IpAddr) -> bool {
+        let (_, action) = self.exempt_networks.check(ip);
+        action == AclAction::Permit
+    }
+
+    /// Check if a new connection from the client ip should be accepted
+    pub(super) fn check_new_connection(&self, ip: IpAddr) -> bool {
+        self.check_new_connection_at(ip, Instant::now())
+    }
+
+    fn check_new_connection_at(&self, ip: IpAddr, now: Instant) -> bool {
+        if self.is_exempt(ip) {
+            return true;
+        }
+
+        let mut table = self.table.lock().unwrap();
+        let entry = table.get_or_insert_mut(ip, || ClientIpEntry::new(now));
+        if let Some(until) = entry.limited_until {
+            if now < until {
+                self.stats.add_rejected();
+                return false;
+            }
+            entry.limited_until = None;
+        }
+
+        let overlap = entry.slide(now, self.config.window);
+        entry.connections.current += 1;
+        if entry.connections.estimate(overlap) > self.config.threshold as f64 {
+            self.limit(entry, now);
+            self.stats.add_rejected();
+            return false;
+        }
+        true
+    }
+
+    /// Record a ClientHello recv timeout of the client ip
+    pub(super) fn add_client_hello_timeout(&self, ip: IpAddr) {
+        self.add_client_hello_timeout_at(ip, Instant::now())
+    }
+
+    fn add_client_hello_timeout_at(&self, ip: IpAddr, now: Instant) {
+        if self.is_exempt(ip) {
+            return;
+        }
+
+        let mut table = self.table.lock().unwrap();
+        let entry = table.get_or_insert_mut(ip, || ClientIpEntry::new(now));
+        if entry.limited_until.is_some_and(|until| now < until) {
+            return;
+        }
+
+        let overlap = entry.slide(now, self.config.window);
+        entry.timeouts.current += 1;
+        if entry.timeouts.estimate(overlap) >= self.config.timeout_threshold as f64 {
+            self.limit(entry, now);
+        }
+    }
+
+    fn limit(&self, entry: &mut ClientIpEntry, now: Instant) {
+        entry.limited_until = Some(now + self.config.penalty);
+        entry.window_start = now;
+        entry.connections = SlidingCount::default();
+        entry.timeouts = SlidingCount::default();
+        self.stats.add_limited();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use yaml_rust::YamlLoader;
+
+    fn new_limiter(exempt: &str) -> OpensslClientIpLimiter {
+        let yaml = YamlLoader::load_from_str(&format!(
+            "window: 10s\nthreshold: 5\ntimeout_threshold: 2\npenalty: 30s\n\
+             exempt_networks: {exempt}\n"
+        ))
+        .unwrap();
+        let config = OpensslClientIpLimitConfig::parse_yaml(&yaml[0])
+            .unwrap()
+            .unwrap();
+        OpensslClientIpLimiter::new(&config, Arc::new(ClientIpLimitStats::default()))
+    }
+
+    #[test]
+    fn bursty_ip() {
+        let limiter = new_limiter("192.168.0.0/16");
+        let bursty = IpAddr::from_str("10.0.0.1").unwrap();
+        let exempt = IpAddr::from_str("192.168.1.1").unwrap();
+        let other = IpAddr::from_str("10.0.0.2").unwrap();
+
+        let start = Instant::now();
+        for i in 0..20 {
+            let now = start + Duration::from_millis(i * 10);
+            let accepted = limiter.check_new_connection_at(bursty, now);
+            assert_eq!(accepted, i < 5);
+            assert!(limiter.check_new_connection_at(exempt, now));
+        }
+        assert!(limiter.check_new_connection_at(other, start));
+        let snap = limiter.stats.snapshot();
+        assert_eq!(snap.limited, 1);
+        assert_eq!(snap.rejected, 15);
+
+        // still limited in the penalty duration, even if the window has passed
+        let now = start + Duration::from_secs(20);
+        assert!(!limiter.check_new_connection_at(bursty, now));
+        // accepted again after the penalty
+        let now = start + Duration::from_secs(31);
+        assert!(limiter.check_new_connection_at(bursty, now));
+    }
+
+    #[test]
+    fn sliding_window() {
+        let limiter = new_limiter("192.168.0.0/16");
+        let ip = IpAddr::from_str("10.0.0.1").unwrap();
+
+        let start = Instant::now();
+        for _ in 0..5 {
+            assert!(limiter.check_new_connection_at(ip, start));
+        }
+        // half of the previous window is still counted
+        let now = start + Duration::from_secs(15);
+        assert!(limiter.check_new_connection_at(ip, now));
+        assert!(limiter.check_new_connection_at(ip, now));
+        assert!(!limiter.check_new_connection_at(ip, now));
+    }
+
+    #[test]
+    fn client_hello_timeout() {
+        let limiter = new_limiter("192.168.0.0/16");
+        let ip = IpAddr::from_str("10.0.0.1").unwrap();
+        let exempt = IpAddr::from_str("192.168.1.1").unwrap();
+
+        let start = Instant::now();
+        assert!(limiter.check_new_connection_at(ip, start));
+        limiter.add_client_hello_timeout_at(ip, start);
+        assert!(limiter.check_new_connection_at(ip, start));
+        limiter.add_client_hello_timeout_at(ip, start);
+        assert!(!limiter.check_new_connection_at(ip, start));
+
+        for _ in 0..10 {
+            limiter.add_client_hello_timeout_at(exempt, start);
+        }
+        assert!(limiter.check_new_connection_at(exempt, start));
+
+        let snap = limiter.stats.snapshot();
+        assert_eq!(snap.limited, 1);
+        assert_eq!(snap.rejected, 1);
+    }
+
+    #[test]
+    fn table_size() {
+        let config = OpensslClientIpLimitConfig {
+            threshold: 1,
+            timeout_threshold: 1,
+            table_size: std::num::NonZeroUsize::new(1).unwrap(),
+            ..Default::default()
+        };
+        let limiter = OpensslClientIpLimiter::new(&config, Arc::new(ClientIpLimitStats::default()));
+        let ip1 = IpAddr::from_str("10.0.0.1").unwrap();
+        let ip2 = IpAddr::from_str("10.0.0.2").unwrap();
+
+        let start = Instant::now();
+        assert!(limiter.check_new_connection_at(ip1, start));
+        assert!(!limiter.check_new_connection_at(ip1, start));
+        // the entry of ip1 is evicted
+        assert!(limiter.check_new_connection_at(ip2, start));
+        assert!(limiter.check_new_connection_at(ip1, start));
+    }
+}
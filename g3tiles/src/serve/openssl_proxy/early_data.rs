@@ -0,0 +1,315 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::VecDeque;
+use std::hash::BuildHasher;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ahash::{AHashSet, RandomState};
+use tokio::time::Instant;
+
+use crate::config::server::openssl_proxy::OpensslEarlyDataConfig;
+
+/// Remember the tickets that have been used with early data in a time window
+///
+/// Tickets are stateless and can be used more than once, so the early data sent along with
+/// a reused ticket should be rejected, or it may be a replay of a captured 0-RTT request.
+/// The cache is bounded, and all early data will be rejected if it is full.
+pub(crate) struct OpensslEarlyDataReplayCache {
+    window: Duration,
+    max_size: usize,
+    hash_state: RandomState,
+    inner: Mutex<ReplayCacheInner>,
+}
+
+#[derive(Default)]
+struct ReplayCacheInner {
+    seen: AHashSet<u64>,
+    queue: VecDeque<(Instant, u64)>,
+}
+
+impl OpensslEarlyDataReplayCache {
+    pub(crate) fn new(config: &OpensslEarlyDataConfig) -> Self {
+        OpensslEarlyDataReplayCache {
+            window: config.anti_replay_window,
+            max_size: config.anti_replay_cache_size,
+            hash_state: RandomState::new(),
+            inner: Mutex::new(ReplayCacheInner::default()),
+        }
+    }
+
+    pub(crate) fn match_config(&self, config: &OpensslEarlyDataConfig) -> bool {
+        self.window == config.anti_replay_window && self.max_size == config.anti_replay_cache_size
+    }
+
+    /// Record the ticket and return true if it has not been seen in the window
+    pub(crate) fn check_fresh(&self, ticket: &[u8]) -> bool {
+        let key = self.hash_state.hash_one(ticket);
+        let now = Instant::now();
+
+        let mut inner = self.inner.lock().unwrap();
+        while let Some((time, old)) = inner.queue.front().copied() {
+            if now.duration_since(time) < self.window {
+                break;
+            }
+            inner.queue.pop_front();
+            inner.seen.remove(&old);
+        }
+
+        if inner.seen.contains(&key) || inner.queue.len() >= self.max_size {
+            return false;
+        }
+        inner.seen.insert(key);
+        inner.queue.push_back((now, key));
+        true
+    }
+}
+
+/// Get the first identity in the pre_shared_key extension of the client hello message,
+/// which is the only one that can be used with early data
+pub(crate) fn first_psk_identity(ext: &[u8]) -> Option<&[u8]> {
+    if ext.len() < 4 {
+        return None;
+    }
+    let identities_len = u16::from_be_bytes([ext[0], ext[1]]) as usize;
+    let identities = ext.get(2..2 + identities_len)?;
+    if identities.len() < 2 {
+        return None;
+    }
+    let identity_len = u16::from_be_bytes([identities[0], identities[1]]) as usize;
+    let identity = identities.get(2..2 + identity_len)?;
+    if identity.is_empty() {
+        None
+    } else {
+        Some(identity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_cache(window: Duration, max_size: usize) -> OpensslEarlyDataReplayCache {
+        let config = OpensslEarlyDataConfig {
+            anti_replay_window: window,
+            anti_replay_cache_size: max_size,
+            ..Default::default()
+        };
+        OpensslEarlyDataReplayCache::new(&config)
+    }
+
+    #[test]
+    fn replay_in_window() {
+        let cache = new_cache(Duration::from_secs(60), 16);
+        assert!(cache.check_fresh(b"ticket-1"));
+        assert!(cache.check_fresh(b"ticket-2"));
+        assert!(!cache.check_fresh(b"ticket-1"));
+        assert!(!cache.check_fresh(b"ticket-2"));
+    }
+
+    #[tokio::test]
+    async fn replay_after_window() {
+        let cache = new_cache(Duration::from_millis(20), 16);
+        assert!(cache.check_fresh(b"ticket-1"));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(cache.check_fresh(b"ticket-1"));
+        assert!(!cache.check_fresh(b"ticket-1"));
+    }
+
+    #[test]
+    fn full() {
+        let cache = new_cache(Duration::from_secs(60), 2);
+        assert!(cache.check_fresh(b"ticket-1"));
+        assert!(cache.check_fresh(b"ticket-2"));
+        // never evict tickets in the window
+        assert!(!cache.check_fresh(b"ticket-3"));
+        assert!(!cache.check_fresh(b"ticket-1"));
+    }
+
+    #[test]
+    fn psk_identity() {
+        let ext = [
+            0x00, 0x0b, // identities
+            0x00, 0x05, b't', b'i', b'c', b'k', b't', // identity
+            0x00, 0x00, 0x00, 0x01, // obfuscated ticket age
+            0x00, 0x00, // binders, not checked
+        ];
+        assert_eq!(first_psk_identity(&ext), Some(b"tickt".as_slice()));
+
+        assert!(first_psk_identity(&ext[..6]).is_none());
+        assert!(first_psk_identity(&[0x00, 0x02, 0x00, 0x00]).is_none());
+    }
+
+    #[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+    mod zero_rtt {
+        use super::*;
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixStream;
+        use std::sync::mpsc;
+
+        use bytes::BytesMut;
+        use openssl::ssl::{
+            self, Ssl, SslContext, SslMethod, SslOptions, SslSession, SslSessionCacheMode,
+            SslVerifyMode,
+        };
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        use g3_dpi::parser::tls::{ExtensionType, HandshakeCoalescer, Record, RecordParseError};
+        use g3_io_ext::OnceBufReader;
+
+        use crate::testing::server_acceptor_builder;
+
+        const REQUEST: &[u8] = b"GET / HTTP/1.1\r\nHost: test.example.net\r\n\r\n";
+
+        fn server_context() -> SslContext {
+            // the same as the host ssl context with early data enabled
+            let mut builder = server_acceptor_builder("test.example.net");
+            builder.set_options(SslOptions::NO_ANTI_REPLAY);
+            builder.set_max_early_data(16384).unwrap();
+            builder.build().into_context()
+        }
+
+        /// Run the client in a new thread, and return the session ticket received
+        fn spawn_client(
+            session: Option<SslSession>,
+            sock: UnixStream,
+        ) -> std::thread::JoinHandle<Option<SslSession>> {
+            std::thread::spawn(move || {
+                let (sender, receiver) = mpsc::channel();
+                let mut builder = SslContext::builder(SslMethod::tls_client()).unwrap();
+                builder.set_verify(SslVerifyMode::NONE);
+                builder.set_session_cache_mode(SslSessionCacheMode::CLIENT);
+                builder.set_new_session_callback(move |_, session| {
+                    let _ = sender.send(session);
+                });
+                let ctx = builder.build();
+
+                let mut ssl = Ssl::new(&ctx).unwrap();
+                ssl.set_hostname("test.example.net").unwrap();
+                ssl.set_connect_state();
+                let mut stream = ssl::SslStream::new(ssl, sock).unwrap();
+                if let Some(session) = &session {
+                    unsafe { stream.ssl_mut().set_session(session).unwrap() };
+                    stream.write_early_data(REQUEST).unwrap();
+                }
+                stream.connect().unwrap();
+                if session.is_none() {
+                    stream.write_all(REQUEST).unwrap();
+                }
+                let mut buf = Vec::new();
+                let _ = stream.read_to_end(&mut buf);
+                receiver.try_recv().ok()
+            })
+        }
+
+        /// Return whether the ticket is fresh, and the early data accepted
+        async fn accept_once(
+            ssl_context: &SslContext,
+            cache: &OpensslEarlyDataReplayCache,
+            sock: UnixStream,
+        ) -> (Option<bool>, Vec<u8>) {
+            sock.set_nonblocking(true).unwrap();
+            let mut stream = tokio::net::UnixStream::from_std(sock).unwrap();
+
+            let mut buf = BytesMut::with_capacity(4096);
+            let ticket = loop {
+                let mut record = match Record::parse(&buf) {
+                    Ok(r) => r,
+                    Err(RecordParseError::NeedMoreData(_)) => {
+                        assert_ne!(stream.read_buf(&mut buf).await.unwrap(), 0);
+                        continue;
+                    }
+                    Err(e) => panic!("invalid record: {e:?}"),
+                };
+                let mut coalescer = HandshakeCoalescer::new(1 << 16);
+                let ch = record
+                    .consume_handshake(&mut coalescer)
+                    .unwrap()
+                    .unwrap()
+                    .parse_client_hello()
+                    .unwrap();
+                if ch.get_ext(ExtensionType::EarlyData).unwrap().is_none() {
+                    break None;
+                }
+                let psk = ch.get_ext(ExtensionType::PreSharedKey).unwrap().unwrap();
+                break first_psk_identity(psk).map(|v| v.to_vec());
+            };
+
+            let mut ssl = Ssl::new(ssl_context).unwrap();
+            let fresh = ticket.map(|ticket| cache.check_fresh(&ticket));
+            if fresh == Some(false) {
+                ssl.set_max_early_data(0).unwrap();
+            }
+            let mut acceptor = g3_openssl::SslAcceptor::new(
+                ssl,
+                OnceBufReader::new(stream, buf),
+                Duration::from_secs(4),
+            )
+            .unwrap();
+
+            let mut early_data = Vec::new();
+            if fresh.is_some() {
+                let mut buf = [0u8; 1024];
+                loop {
+                    let nr = acceptor.read_early_data(&mut buf).await.unwrap();
+                    if nr == 0 {
+                        break;
+                    }
+                    early_data.extend_from_slice(&buf[..nr]);
+                }
+                assert_eq!(acceptor.early_data_accepted(), !early_data.is_empty());
+            }
+
+            let mut ssl_stream = acceptor.accept().await.unwrap();
+            if fresh.is_none() {
+                // no early data for the full handshake
+                let mut buf = vec![0u8; REQUEST.len()];
+                ssl_stream.read_exact(&mut buf).await.unwrap();
+                assert_eq!(buf, REQUEST);
+            }
+            ssl_stream
+                .write_all(b"HTTP/1.1 200 OK\r\n\r\n")
+                .await
+                .unwrap();
+            ssl_stream.shutdown().await.unwrap();
+            (fresh, early_data)
+        }
+
+        async fn run_once(
+            ssl_context: &SslContext,
+            cache: &OpensslEarlyDataReplayCache,
+            session: Option<SslSession>,
+        ) -> (Option<bool>, Vec<u8>, Option<SslSession>) {
+            let (server_sock, client_sock) = UnixStream::pair().unwrap();
+            let client = spawn_client(session, client_sock);
+            let (fresh, early_data) = accept_once(ssl_context, cache, server_sock).await;
+            let new_session = client.join().unwrap();
+            (fresh, early_data, new_session)
+        }
+
+        #[tokio::test]
+        async fn replay_ticket() {
+            let ssl_context = server_context();
+            let cache = new_cache(Duration::from_secs(60), 16);
+
+            let (fresh, early_data, session) = run_once(&ssl_context, &cache, None).await;
+            assert!(fresh.is_none());
+            assert!(early_data.is_empty());
+            let session = session.unwrap();
+
+            // the first use of the ticket
+            let (fresh, early_data, _) =
+                run_once(&ssl_context, &cache, Some(session.clone())).await;
+            assert_eq!(fresh, Some(true));
+            assert_eq!(early_data, REQUEST);
+
+            // replay the same ticket
+            let (fresh, early_data, _) = run_once(&ssl_context, &cache, Some(session)).await;
+            assert_eq!(fresh, Some(false));
+            assert!(early_data.is_empty());
+        }
+    }
+}
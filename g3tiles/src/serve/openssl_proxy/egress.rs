@@ -0,0 +1,138 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::SocketAddr;
+
+use openssl::ssl::{NameType, SslRef};
+use openssl::x509::X509VerifyResult;
+
+use g3_types::net::{ProxyProtocolEncodeError, ProxyProtocolV2Encoder};
+
+/// Build the PROXY protocol v2 header to send to the backend before any client payload
+///
+/// The negotiated TLS version and cipher are sent in the PP2_TYPE_SSL TLV, the SNI in the
+/// PP2_TYPE_AUTHORITY TLV, and the selected ALPN protocol in the PP2_TYPE_ALPN TLV.
+pub(super) fn upstream_proxy_header(
+    ssl: &SslRef,
+    client_addr: SocketAddr,
+    server_addr: SocketAddr,
+) -> Result<ProxyProtocolV2Encoder, ProxyProtocolEncodeError> {
+    let mut encoder = ProxyProtocolV2Encoder::new_tcp(client_addr, server_addr)?;
+    if let Some(alpn) = ssl.selected_alpn_protocol() {
+        encoder.push_alpn(alpn)?;
+    }
+    if let Some(sni) = ssl.servername(NameType::HOST_NAME) {
+        encoder.push_authority(sni)?;
+    }
+    let cert_verified = ssl
+        .peer_certificate()
+        .map(|_| ssl.verify_result() == X509VerifyResult::OK);
+    let cipher = ssl.current_cipher().map(|c| c.name()).unwrap_or_default();
+    encoder.push_ssl(cert_verified, ssl.version_str(), cipher)?;
+    Ok(encoder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use openssl::ssl::{
+        AlpnError, Ssl, SslAcceptor, SslConnector, SslMethod, SslVerifyMode, SslVersion,
+        select_next_proto,
+    };
+
+    use g3_io_ext::haproxy::{PP2_TYPE_ALPN, PP2_TYPE_AUTHORITY, ProxyProtocolV2Reader};
+
+    use crate::testing::server_acceptor_builder;
+
+    const PP2_TYPE_SSL: u8 = 0x20;
+    const PP2_SUBTYPE_SSL_VERSION: u8 = 0x21;
+    const PP2_SUBTYPE_SSL_CIPHER: u8 = 0x23;
+    const PP2_CLIENT_SSL: u8 = 0x01;
+
+    fn server_acceptor() -> SslAcceptor {
+        let mut builder = server_acceptor_builder("test.example.net");
+        builder.set_alpn_select_callback(|_, client| {
+            select_next_proto(b"\x02h2\x08http/1.1", client).ok_or(AlpnError::NOACK)
+        });
+        builder.build()
+    }
+
+    /// Parse the sub TLVs in the value of the PP2_TYPE_SSL TLV
+    fn ssl_sub_tlv(value: &[u8], tlv_type: u8) -> Option<&[u8]> {
+        let mut left = &value[5..];
+        while left.len() >= 3 {
+            let len = u16::from_be_bytes([left[1], left[2]]) as usize;
+            let (tlv, next) = left[3..].split_at(len);
+            if left[0] == tlv_type {
+                return Some(tlv);
+            }
+            left = next;
+        }
+        None
+    }
+
+    #[tokio::test]
+    async fn tls13_h2() {
+        let acceptor = server_acceptor();
+        let (server_sock, client_sock) = UnixStream::pair().unwrap();
+        let server = std::thread::spawn(move || {
+            let ssl = Ssl::new(acceptor.context()).unwrap();
+            let stream = ssl.accept(server_sock).unwrap();
+            let client = SocketAddr::from_str("192.0.2.1:56324").unwrap();
+            let server = SocketAddr::from_str("192.0.2.11:443").unwrap();
+            let mut encoder = upstream_proxy_header(stream.ssl(), client, server).unwrap();
+            encoder.finalize().to_vec()
+        });
+
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        builder
+            .set_min_proto_version(Some(SslVersion::TLS1_3))
+            .unwrap();
+        builder.set_alpn_protos(b"\x02h2").unwrap();
+        let connector = builder.build();
+        let client = std::thread::spawn(move || {
+            // keep the stream open until the server side handshake is finished
+            connector.connect("test.example.net", client_sock).unwrap()
+        });
+        let header = server.join().unwrap();
+        let _client_stream = client.join().unwrap();
+
+        let mut reader = ProxyProtocolV2Reader::new(Duration::from_secs(1));
+        let addr = reader
+            .read_proxy_protocol_v2_for_tcp(&mut header.as_slice())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            addr.src_addr,
+            SocketAddr::from_str("192.0.2.1:56324").unwrap()
+        );
+        assert_eq!(
+            addr.dst_addr,
+            SocketAddr::from_str("192.0.2.11:443").unwrap()
+        );
+        assert_eq!(reader.tlv(PP2_TYPE_ALPN), Some(b"h2".as_slice()));
+        assert_eq!(
+            reader.tlv(PP2_TYPE_AUTHORITY),
+            Some(b"test.example.net".as_slice())
+        );
+
+        let ssl = reader.tlv(PP2_TYPE_SSL).unwrap();
+        // no client certificate is presented
+        assert_eq!(ssl[0], PP2_CLIENT_SSL);
+        assert_ne!(&ssl[1..5], &[0, 0, 0, 0]);
+        assert_eq!(
+            ssl_sub_tlv(ssl, PP2_SUBTYPE_SSL_VERSION),
+            Some(b"TLSv1.3".as_slice())
+        );
+        let cipher = ssl_sub_tlv(ssl, PP2_SUBTYPE_SSL_CIPHER).unwrap();
+        assert!(cipher.starts_with(b"TLS_"));
+    }
+}
@@ -0,0 +1,222 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
+
+use crate::serve::HandshakeLimitStats;
+
+/// Limit the number of concurrent TLS handshakes of a server
+///
+/// New handshakes will wait in a queue if the limit is reached,
+/// and will be rejected at once if the queue is also full.
+pub(crate) struct OpensslHandshakeLimiter {
+    max_concurrent: usize,
+    max_queued: usize,
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+    stats: Arc<HandshakeLimitStats>,
+}
+
+impl OpensslHandshakeLimiter {
+    pub(crate) fn new(
+        max_concurrent: usize,
+        max_queued: usize,
+        stats: Arc<HandshakeLimitStats>,
+    ) -> Self {
+        OpensslHandshakeLimiter {
+            max_concurrent,
+            max_queued,
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            queued: AtomicUsize::new(0),
+            stats,
+        }
+    }
+
+    pub(crate) fn new_for_reload(
+        self: &Arc<Self>,
+        max_concurrent: usize,
+        max_queued: usize,
+    ) -> Arc<Self> {
+        if self.max_concurrent == max_concurrent && self.max_queued == max_queued {
+            // keep the running handshakes in count
+            self.clone()
+        } else {
+            Arc::new(OpensslHandshakeLimiter::new(
+                max_concurrent,
+                max_queued,
+                self.stats.clone(),
+            ))
+        }
+    }
+
+    /// Acquire a permit to start the handshake, `None` will be returned if rejected
+    ///
+    /// The permit should be held until the handshake completes or times out.
+    pub(crate) async fn acquire(&self) -> Option<OpensslHandshakePermit> {
+        let permit = match self.semaphore.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(TryAcquireError::NoPermits) => {
+                let _queued = self.enter_queue()?;
+                self.semaphore.clone().acquire_owned().await.ok()?
+            }
+            Err(TryAcquireError::Closed) => return None,
+        };
+        self.stats.add_in_flight();
+        Some(OpensslHandshakePermit {
+            _permit: permit,
+            stats: self.stats.clone(),
+        })
+    }
+
+    fn enter_queue(&self) -> Option<QueuedGuard<'_>> {
+        let queued = self.queued.fetch_add(1, Ordering::Relaxed);
+        if queued >= self.max_queued {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            self.stats.add_rejected();
+            return None;
+        }
+        self.stats.add_queued();
+        Some(QueuedGuard(self))
+    }
+}
+
+/// Leave the queue on drop, even if the waiting task is cancelled
+struct QueuedGuard<'a>(&'a OpensslHandshakeLimiter);
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.queued.fetch_sub(1, Ordering::Relaxed);
+        self.0.stats.del_queued();
+    }
+}
+
+pub(crate) struct OpensslHandshakePermit {
+    _permit: OwnedSemaphorePermit,
+    stats: Arc<HandshakeLimitStats>,
+}
+
+impl Drop for OpensslHandshakePermit {
+    fn drop(&mut self) {
+        self.stats.del_in_flight();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use tokio::sync::oneshot;
+
+    /// Run a handshake that will only complete when the returned sender is used or dropped,
+    /// just like a slow private key operation
+    fn spawn_slow_handshake(
+        limiter: &Arc<OpensslHandshakeLimiter>,
+    ) -> (oneshot::Sender<()>, tokio::task::JoinHandle<bool>) {
+        let (sender, receiver) = oneshot::channel();
+        let limiter = limiter.clone();
+        let handle = tokio::spawn(async move {
+            let Some(_permit) = limiter.acquire().await else {
+                return false;
+            };
+            let _ = receiver.await;
+            true
+        });
+        (sender, handle)
+    }
+
+    async fn wait_stats(stats: &HandshakeLimitStats, in_flight: u64, queued: u64) {
+        tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                let snap = stats.snapshot();
+                if snap.in_flight == in_flight && snap.queued == queued {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn queue_and_reject() {
+        let stats = Arc::new(HandshakeLimitStats::default());
+        let limiter = Arc::new(OpensslHandshakeLimiter::new(2, 1, stats.clone()));
+
+        let (s1, h1) = spawn_slow_handshake(&limiter);
+        let (s2, h2) = spawn_slow_handshake(&limiter);
+        wait_stats(&stats, 2, 0).await;
+
+        let (s3, h3) = spawn_slow_handshake(&limiter);
+        wait_stats(&stats, 2, 1).await;
+
+        // the queue is full
+        let (_s4, h4) = spawn_slow_handshake(&limiter);
+        assert!(!h4.await.unwrap());
+        assert_eq!(stats.snapshot().rejected, 1);
+
+        // the queued one will start after a running one completes
+        drop(s1);
+        assert!(h1.await.unwrap());
+        wait_stats(&stats, 2, 0).await;
+
+        drop(s2);
+        drop(s3);
+        assert!(h2.await.unwrap());
+        assert!(h3.await.unwrap());
+        wait_stats(&stats, 0, 0).await;
+        assert_eq!(stats.snapshot().rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn cancel_queued() {
+        let stats = Arc::new(HandshakeLimitStats::default());
+        let limiter = Arc::new(OpensslHandshakeLimiter::new(1, 1, stats.clone()));
+
+        let (s1, h1) = spawn_slow_handshake(&limiter);
+        wait_stats(&stats, 1, 0).await;
+        let (_s2, h2) = spawn_slow_handshake(&limiter);
+        wait_stats(&stats, 1, 1).await;
+
+        // the queue slot should be released if the waiting task is aborted
+        h2.abort();
+        wait_stats(&stats, 1, 0).await;
+        let (s3, h3) = spawn_slow_handshake(&limiter);
+        wait_stats(&stats, 1, 1).await;
+
+        drop(s1);
+        drop(s3);
+        assert!(h1.await.unwrap());
+        assert!(h3.await.unwrap());
+        assert_eq!(stats.snapshot().rejected, 0);
+    }
+
+    #[tokio::test]
+    async fn reload() {
+        let stats = Arc::new(HandshakeLimitStats::default());
+        let limiter = Arc::new(OpensslHandshakeLimiter::new(1, 0, stats.clone()));
+
+        let (s1, h1) = spawn_slow_handshake(&limiter);
+        wait_stats(&stats, 1, 0).await;
+
+        let same = limiter.new_for_reload(1, 0);
+        let (_s2, h2) = spawn_slow_handshake(&same);
+        assert!(!h2.await.unwrap());
+
+        let larger = limiter.new_for_reload(2, 0);
+        let (s3, h3) = spawn_slow_handshake(&larger);
+        wait_stats(&stats, 2, 0).await;
+
+        drop(s1);
+        drop(s3);
+        assert!(h1.await.unwrap());
+        assert!(h3.await.unwrap());
+        wait_stats(&stats, 0, 0).await;
+    }
+}
@@ -0,0 +1,503 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use arc_swap::ArcSwap;
+use log::{debug, info, warn};
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use g3_daemon::server::ClientConnectionInfo;
+use g3_types::metrics::NodeName;
+use g3_types::route::AlpnMatch;
+
+use crate::backend::ArcBackend;
+use crate::config::server::openssl_proxy::{
+    IngressProxyProtocol, OpensslHealthCheckConfig, OpensslHealthProbe, OpensslHostConfig,
+    OpensslUnhealthyAction,
+};
+use crate::serve::{HostHealthStats, ServerTaskNotes};
+
+const HTTP_RESPONSE_HEAD_MAX_SIZE: usize = 4096;
+
+#[derive(Default)]
+struct HealthState {
+    /// consecutive failures while healthy
+    failures: usize,
+    /// consecutive successful probes while unhealthy
+    successes: usize,
+}
+
+/// The health state of the backends of a host.
+///
+/// The host will be marked as unhealthy after `fall` consecutive failures, either from the active
+/// probes or from the backend connections of the real tasks, and only `rise` consecutive
+/// successful probes will mark it as healthy again.
+/// The probe task will quit when this is dropped.
+pub(super) struct OpensslHostHealth {
+    host: String,
+    config: OpensslHealthCheckConfig,
+    backend_dscp: Option<u8>,
+    send_proxy_header: bool,
+    healthy: AtomicBool,
+    state: Mutex<HealthState>,
+    stats: Arc<HostHealthStats>,
+}
+
+impl OpensslHostHealth {
+    fn new(
+        host_config: &OpensslHostConfig,
+        config: &OpensslHealthCheckConfig,
+        stats: Arc<HostHealthStats>,
+    ) -> Self {
+        stats.set_healthy();
+        OpensslHostHealth {
+            host: host_config.name().to_string(),
+            config: config.clone(),
+            backend_dscp: host_config.backend_dscp,
+            send_proxy_header: host_config.upstream_proxy_protocol.is_some(),
+            healthy: AtomicBool::new(true),
+            state: Mutex::new(HealthState::default()),
+            stats,
+        }
+    }
+
+    pub(super) fn spawn(
+        host_config: &OpensslHostConfig,
+        config: &OpensslHealthCheckConfig,
+        backends: Arc<ArcSwap<AlpnMatch<ArcBackend>>>,
+        stats: Arc<HostHealthStats>,
+    ) -> Arc<Self> {
+        let health = Arc::new(OpensslHostHealth::new(host_config, config, stats));
+        tokio::spawn(run_probe(
+            Arc::downgrade(&health),
+            config.interval,
+            backends,
+        ));
+        health
+    }
+
+    pub(super) fn match_config(&self, host_config: &OpensslHostConfig) -> bool {
+        let Some(config) = &host_config.health_check else {
+            return false;
+        };
+        self.config.eq(config)
+            && self.backend_dscp == host_config.backend_dscp
+            && self.send_proxy_header == host_config.upstream_proxy_protocol.is_some()
+    }
+
+    #[inline]
+    pub(super) fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Acquire)
+    }
+
+    /// Get the action on new client connections, `None` if the host is healthy
+    pub(super) fn unhealthy_action(&self) -> Option<OpensslUnhealthyAction> {
+        if self.is_healthy() {
+            None
+        } else {
+            Some(self.config.unhealthy_action)
+        }
+    }
+
+    /// Record the result of a backend connect of a real task.
+    ///
+    /// Only failures while healthy will be counted, the recovery is left to the active probes.
+    pub(super) fn record_connect(&self, ok: bool) {
+        if self.is_healthy() {
+            self.record(ok);
+        }
+    }
+
+    fn record_probe(&self, ok: bool) {
+        self.record(ok);
+    }
+
+    fn record(&self, ok: bool) {
+        let mut state = self.state.lock().unwrap();
+        if self.is_healthy() {
+            if ok {
+                state.failures = 0;
+            } else {
+                state.failures += 1;
+                if state.failures >= self.config.fall {
+                    state.failures = 0;
+                    state.successes = 0;
+                    self.healthy.store(false, Ordering::Release);
+                    self.stats.set_unhealthy();
+                    warn!("host {} is marked as unhealthy", self.host);
+                }
+            }
+        } else if ok {
+            state.successes += 1;
+            if state.successes >= self.config.rise {
+                state.failures = 0;
+                state.successes = 0;
+                self.healthy.store(true, Ordering::Release);
+                self.stats.set_healthy();
+                info!("host {} is marked as healthy again", self.host);
+            }
+        } else {
+            state.successes = 0;
+        }
+    }
+
+    /// Probe all the backends of the host, the probe succeeds if any backend is available
+    async fn probe(&self, backends: &AlpnMatch<ArcBackend>) -> bool {
+        let mut probed: Vec<&NodeName> = Vec::new();
+        let all = backends
+            .protocols()
+            .iter()
+            .filter_map(|p| backends.get(p))
+            .chain(backends.get_default());
+        for backend in all {
+            if probed.contains(&backend.name()) {
+                continue;
+            }
+            probed.push(backend.name());
+
+            match tokio::time::timeout(self.config.timeout, self.probe_backend(backend)).await {
+                Ok(Ok(_)) => return true,
+                Ok(Err(e)) => {
+                    debug!(
+                        "health probe to backend {} of host {} failed: {e:?}",
+                        backend.name(),
+                        self.host
+                    );
+                }
+                Err(_) => {
+                    debug!(
+                        "health probe to backend {} of host {} timed out",
+                        backend.name(),
+                        self.host
+                    );
+                }
+            }
+        }
+        false
+    }
+
+    async fn probe_backend(&self, backend: &ArcBackend) -> anyhow::Result<()> {
+        let cc_info = ClientConnectionInfo::new(
+            SocketAddr::from(([0, 0, 0, 0], 0)),
+            SocketAddr::from(([0, 0, 0, 0], 0)),
+        );
+        let mut task_notes = ServerTaskNotes::new(cc_info, Duration::ZERO);
+        task_notes.backend_dscp = self.backend_dscp;
+        let (ups_r, mut ups_w) = backend
+            .stream_connect(&task_notes)
+            .await
+            .map_err(|e| anyhow!("connect failed: {e}"))?;
+        if self.config.probe == OpensslHealthProbe::Tcp {
+            return Ok(());
+        }
+
+        // the backend expects a PROXY protocol header, send a LOCAL one as it is a local probe
+        if self.send_proxy_header {
+            ups_w
+                .write_all(super::ingress_local_header(IngressProxyProtocol::V2))
+                .await
+                .map_err(|e| anyhow!("failed to send PROXY protocol header: {e}"))?;
+        }
+
+        let stream = tokio::io::join(ups_r, ups_w);
+        if self.config.probe == OpensslHealthProbe::Tls {
+            let _ssl_stream = self.tls_handshake(stream).await?;
+            Ok(())
+        } else {
+            self.http_get(stream).await
+        }
+    }
+
+    async fn tls_handshake<S>(&self, stream: S) -> anyhow::Result<g3_openssl::SslStream<S>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut builder = SslConnector::builder(SslMethod::tls_client())
+            .map_err(|e| anyhow!("failed to create ssl connector builder: {e}"))?;
+        builder.set_verify(SslVerifyMode::NONE);
+        let ssl = builder
+            .build()
+            .configure()
+            .and_then(|c| match &self.config.tls_name {
+                Some(name) => c.verify_hostname(false).into_ssl(name),
+                None => c
+                    .verify_hostname(false)
+                    .use_server_name_indication(false)
+                    .into_ssl(""),
+            })
+            .map_err(|e| anyhow!("failed to create ssl: {e}"))?;
+        let connector = g3_openssl::SslConnector::new(ssl, stream)
+            .map_err(|e| anyhow!("failed to create ssl connector: {e}"))?;
+        connector
+            .connect()
+            .await
+            .map_err(|e| anyhow!("tls handshake failed: {e}"))
+    }
+
+    async fn http_get<S>(&self, mut stream: S) -> anyhow::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let req = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            self.config.http_path, self.config.http_host
+        );
+        stream
+            .write_all(req.as_bytes())
+            .await
+            .map_err(|e| anyhow!("failed to send request: {e}"))?;
+        stream
+            .flush()
+            .await
+            .map_err(|e| anyhow!("failed to send request: {e}"))?;
+
+        let mut buf = Vec::with_capacity(256);
+        let line_end = loop {
+            if let Some(p) = buf.iter().position(|b| *b == b'\n') {
+                break p;
+            }
+            if buf.len() >= HTTP_RESPONSE_HEAD_MAX_SIZE {
+                return Err(anyhow!("too long response status line"));
+            }
+            let nr = (&mut stream)
+                .take((HTTP_RESPONSE_HEAD_MAX_SIZE - buf.len()) as u64)
+                .read_buf(&mut buf)
+                .await
+                .map_err(|e| anyhow!("failed to read response: {e}"))?;
+            if nr == 0 {
+                return Err(anyhow!("connection closed before the response"));
+            }
+        };
+
+        let status = parse_status_code(&buf[..line_end])?;
+        if (200..400).contains(&status) {
+            Ok(())
+        } else {
+            Err(anyhow!("unexpected response status code {status}"))
+        }
+    }
+}
+
+fn parse_status_code(line: &[u8]) -> anyhow::Result<u16> {
+    let line = std::str::from_utf8(line).map_err(|_| anyhow!("invalid response status line"))?;
+    let mut parts = line.split_ascii_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some(version), Some(code)) if version.starts_with("HTTP/1.") => code
+            .parse::<u16>()
+            .map_err(|_| anyhow!("invalid response status code {code}")),
+        _ => Err(anyhow!("invalid response status line")),
+    }
+}
+
+async fn run_probe(
+    health: Weak<OpensslHostHealth>,
+    interval: Duration,
+    backends: Arc<ArcSwap<AlpnMatch<ArcBackend>>>,
+) {
+    let mut interval = tokio::time::interval(interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let Some(health) = health.upgrade() else {
+            break;
+        };
+        let ok = health.probe(&backends.load()).await;
+        health.record_probe(ok);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use async_trait::async_trait;
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::backend::Backend;
+    use crate::module::stream::{StreamConnectError, StreamConnectResult};
+    use crate::serve::HostHealthStatsMap;
+
+    /// A backend that can be turned up and down
+    struct MockBackend {
+        name: NodeName,
+        addr: SocketAddr,
+        available: AtomicBool,
+    }
+
+    #[async_trait]
+    impl Backend for MockBackend {
+        fn name(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn discover(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn update_discover(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn alive_connection(&self) -> u64 {
+            0
+        }
+
+        async fn stream_connect(&self, _task_notes: &ServerTaskNotes) -> StreamConnectResult {
+            if !self.available.load(Ordering::Relaxed) {
+                return Err(StreamConnectError::SetupSocketFailed(
+                    std::io::Error::other("backend down"),
+                ));
+            }
+            let stream = TcpStream::connect(self.addr)
+                .await
+                .map_err(StreamConnectError::SetupSocketFailed)?;
+            let (r, w) = stream.into_split();
+            Ok((Box::new(r), Box::new(w)))
+        }
+    }
+
+    /// Start a backend that replies the same HTTP response on each connection
+    async fn start_backend(response: &'static [u8]) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream.write_all(response).await;
+                });
+            }
+        });
+        addr
+    }
+
+    fn new_backends(addr: SocketAddr) -> (Arc<MockBackend>, AlpnMatch<ArcBackend>) {
+        let mock = Arc::new(MockBackend {
+            name: NodeName::from_str("mock").unwrap(),
+            addr,
+            available: AtomicBool::new(true),
+        });
+        let mut backends = AlpnMatch::default();
+        backends.set_default(mock.clone() as ArcBackend);
+        (mock, backends)
+    }
+
+    fn new_health(config: OpensslHealthCheckConfig) -> (OpensslHostHealth, Arc<HostHealthStats>) {
+        let stats = HostHealthStatsMap::default().get_or_insert("test");
+        let host_config = OpensslHostConfig::default();
+        let health = OpensslHostHealth::new(&host_config, &config, stats.clone());
+        (health, stats)
+    }
+
+    #[tokio::test]
+    async fn fast_fail_and_recover() {
+        let addr = start_backend(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+        let (mock, backends) = new_backends(addr);
+        let config = OpensslHealthCheckConfig {
+            fall: 2,
+            rise: 2,
+            unhealthy_action: OpensslUnhealthyAction::Alert,
+            ..Default::default()
+        };
+        let (health, stats) = new_health(config);
+
+        assert!(health.probe(&backends).await);
+        assert!(health.unhealthy_action().is_none());
+
+        // the backend goes down
+        mock.available.store(false, Ordering::Relaxed);
+        for _ in 0..2 {
+            let ok = health.probe(&backends).await;
+            health.record_probe(ok);
+        }
+        assert!(!health.is_healthy());
+        assert_eq!(
+            health.unhealthy_action(),
+            Some(OpensslUnhealthyAction::Alert)
+        );
+        let snap = stats.snapshot();
+        assert!(!snap.healthy);
+        assert_eq!(snap.unhealthy_total, 1);
+
+        // the backend is up again, the passive results should not make it recover
+        mock.available.store(true, Ordering::Relaxed);
+        health.record_connect(true);
+        health.record_connect(true);
+        assert!(!health.is_healthy());
+
+        let ok = health.probe(&backends).await;
+        health.record_probe(ok);
+        assert!(!health.is_healthy());
+        let ok = health.probe(&backends).await;
+        health.record_probe(ok);
+        assert!(health.is_healthy());
+        assert!(stats.snapshot().healthy);
+    }
+
+    #[tokio::test]
+    async fn passive_failures() {
+        let config = OpensslHealthCheckConfig {
+            fall: 3,
+            ..Default::default()
+        };
+        let (health, stats) = new_health(config);
+
+        health.record_connect(false);
+        health.record_connect(false);
+        health.record_connect(true);
+        health.record_connect(false);
+        health.record_connect(false);
+        assert!(health.is_healthy());
+        health.record_connect(false);
+        assert!(!health.is_healthy());
+        assert_eq!(
+            health.unhealthy_action(),
+            Some(OpensslUnhealthyAction::Reject)
+        );
+
+        // a failed probe resets the rise count
+        health.record_probe(true);
+        health.record_probe(false);
+        health.record_probe(true);
+        assert!(!health.is_healthy());
+        health.record_probe(true);
+        assert!(health.is_healthy());
+        assert_eq!(stats.snapshot().unhealthy_total, 1);
+    }
+
+    #[tokio::test]
+    async fn http_probe() {
+        let config = OpensslHealthCheckConfig {
+            probe: OpensslHealthProbe::Http,
+            ..Default::default()
+        };
+
+        let addr = start_backend(b"HTTP/1.1 204 No Content\r\n\r\n").await;
+        let (_mock, backends) = new_backends(addr);
+        let (health, _) = new_health(config.clone());
+        assert!(health.probe(&backends).await);
+
+        let addr = start_backend(b"HTTP/1.1 503 Service Unavailable\r\n\r\n").await;
+        let (_mock, backends) = new_backends(addr);
+        let (health, _) = new_health(config);
+        assert!(!health.probe(&backends).await);
+    }
+
+    #[test]
+    fn status_line() {
+        assert_eq!(parse_status_code(b"HTTP/1.1 200 OK\r").unwrap(), 200);
+        assert_eq!(parse_status_code(b"HTTP/1.0 302").unwrap(), 302);
+        assert!(parse_status_code(b"SSH-2.0-OpenSSH").is_err());
+        assert!(parse_status_code(b"HTTP/1.1 abc").is_err());
+    }
+}
@@ -3,11 +3,13 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use governor::{RateLimiter, clock::DefaultClock, state::InMemoryState, state::NotKeyed};
 use openssl::ssl::SslContext;
+use openssl::x509::X509Ref;
+use slog::Logger;
 
 use g3_types::collection::NamedValue;
 use g3_types::limit::{GaugeSemaphore, GaugeSemaphorePermit};
@@ -15,44 +17,92 @@ use g3_types::metrics::NodeName;
 use g3_types::net::{OpensslTicketKey, RollingTicketer};
 use g3_types::route::AlpnMatch;
 
+use super::{
+    OpensslBackendPool, OpensslCertWatcher, OpensslEarlyDataReplayCache, OpensslH2BackendPool,
+    OpensslHostHealth,
+};
 use crate::backend::ArcBackend;
-use crate::config::server::openssl_proxy::OpensslHostConfig;
+use crate::config::server::ServerConfig;
+use crate::config::server::openssl_proxy::{
+    OpensslBackendProtocol, OpensslHostConfig, OpensslProxyServerConfig, OpensslUnhealthyAction,
+};
+use crate::serve::{BackendPoolStatsMap, CertReloadStats, HostHealthStatsMap};
 
 pub(crate) struct OpensslHost {
     pub(super) config: Arc<OpensslHostConfig>,
-    pub(super) ssl_context: Option<SslContext>,
+    ssl_context: Arc<ArcSwapOption<SslContext>>,
+    _cert_watcher: Option<OpensslCertWatcher>,
     #[cfg(feature = "vendored-tongsuo")]
     pub(super) tlcp_context: Option<SslContext>,
     req_alive_sem: Option<GaugeSemaphore>,
     request_rate_limit: Option<Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock>>>,
+    early_data_replay_cache: Option<Arc<OpensslEarlyDataReplayCache>>,
+    backend_pool: Option<Arc<OpensslBackendPool>>,
+    h2_backend_pool: Option<Arc<OpensslH2BackendPool>>,
+    health: Option<Arc<OpensslHostHealth>>,
     pub(crate) backends: Arc<ArcSwap<AlpnMatch<ArcBackend>>>,
+    pub(super) client_cert_backends: Arc<ArcSwap<Vec<ArcBackend>>>,
+    pub(super) shared_logger: OnceLock<Option<Logger>>,
 }
 
 impl OpensslHost {
     pub(super) fn try_build(
         config: &Arc<OpensslHostConfig>,
         tls_ticketer: &Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+        cert_reload_stats: &Arc<CertReloadStats>,
+        backend_pool_stats: &Arc<BackendPoolStatsMap>,
+        host_health_stats: &Arc<HostHealthStatsMap>,
     ) -> anyhow::Result<Self> {
         let ssl_context = config.build_ssl_context(tls_ticketer.clone())?;
+        let ssl_context = Arc::new(ArcSwapOption::new(ssl_context.map(Arc::new)));
+        let cert_watcher =
+            spawn_cert_watcher(config, tls_ticketer, &ssl_context, cert_reload_stats);
         #[cfg(feature = "vendored-tongsuo")]
         let tlcp_context = config.build_tlcp_context(tls_ticketer.clone())?;
 
         let backends = config.backends.build(crate::backend::get_or_insert_default);
+        let client_cert_backends = build_client_cert_backends(config);
 
         let request_rate_limit = config
             .request_rate_limit
             .as_ref()
             .map(|quota| Arc::new(RateLimiter::direct(quota.get_inner())));
         let req_alive_sem = config.request_alive_max.map(GaugeSemaphore::new);
+        let early_data_replay_cache = config
+            .early_data
+            .as_ref()
+            .map(|c| Arc::new(OpensslEarlyDataReplayCache::new(c)));
+        let backend_pool = config.backend_pool.as_ref().map(|c| {
+            let stats = backend_pool_stats.get_or_insert(config.name());
+            Arc::new(OpensslBackendPool::new(c, stats))
+        });
+        let h2_backend_pool = match config.backend_protocol {
+            OpensslBackendProtocol::Tcp => None,
+            OpensslBackendProtocol::H2 => {
+                Some(Arc::new(OpensslH2BackendPool::new(&config.backend_h2)))
+            }
+        };
+        let backends = Arc::new(ArcSwap::from_pointee(backends));
+        let health = config.health_check.as_ref().map(|c| {
+            let stats = host_health_stats.get_or_insert(config.name());
+            OpensslHostHealth::spawn(config, c, backends.clone(), stats)
+        });
 
         Ok(OpensslHost {
             config: config.clone(),
             ssl_context,
+            _cert_watcher: cert_watcher,
             #[cfg(feature = "vendored-tongsuo")]
             tlcp_context,
             req_alive_sem,
             request_rate_limit,
-            backends: Arc::new(ArcSwap::from_pointee(backends)),
+            early_data_replay_cache,
+            backend_pool,
+            h2_backend_pool,
+            health,
+            backends,
+            client_cert_backends: Arc::new(ArcSwap::from_pointee(client_cert_backends)),
+            shared_logger: OnceLock::new(),
         })
     }
 
@@ -60,8 +110,15 @@ impl OpensslHost {
         &self,
         config: Arc<OpensslHostConfig>,
         tls_ticketer: &Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+        cert_reload_stats: &Arc<CertReloadStats>,
+        backend_pool_stats: &Arc<BackendPoolStatsMap>,
+        host_health_stats: &Arc<HostHealthStatsMap>,
     ) -> anyhow::Result<Self> {
+        // the cert files are loaded again, and the old watcher will quit along with the old host
         let ssl_context = config.build_ssl_context(tls_ticketer.clone())?;
+        let ssl_context = Arc::new(ArcSwapOption::new(ssl_context.map(Arc::new)));
+        let cert_watcher =
+            spawn_cert_watcher(&config, tls_ticketer, &ssl_context, cert_reload_stats);
         #[cfg(feature = "vendored-tongsuo")]
         let tlcp_context = config.build_tlcp_context(tls_ticketer.clone())?;
 
@@ -93,20 +150,92 @@ impl OpensslHost {
         } else {
             None
         };
+        let early_data_replay_cache = config.early_data.as_ref().map(|c| {
+            match &self.early_data_replay_cache {
+                // keep the seen tickets, or they may be replayed after reload
+                Some(old) if old.match_config(c) => old.clone(),
+                _ => Arc::new(OpensslEarlyDataReplayCache::new(c)),
+            }
+        });
+        let backend_pool = config.backend_pool.as_ref().map(|c| {
+            match &self.backend_pool {
+                // keep the idle connections
+                Some(old) if old.match_config(c) => old.clone(),
+                _ => {
+                    let stats = backend_pool_stats.get_or_insert(config.name());
+                    Arc::new(OpensslBackendPool::new(c, stats))
+                }
+            }
+        });
+        let h2_backend_pool = match config.backend_protocol {
+            OpensslBackendProtocol::Tcp => None,
+            OpensslBackendProtocol::H2 => match &self.h2_backend_pool {
+                // keep the established backend connections
+                Some(old) if old.match_config(&config.backend_h2) => Some(old.clone()),
+                _ => Some(Arc::new(OpensslH2BackendPool::new(&config.backend_h2))),
+            },
+        };
+        let health = match &config.health_check {
+            Some(c) => match &self.health {
+                // keep the health state, or an unhealthy host will be served again
+                Some(old) if old.match_config(&config) => Some(old.clone()),
+                _ => {
+                    let stats = host_health_stats.get_or_insert(config.name());
+                    let backends = self.backends.clone();
+                    Some(OpensslHostHealth::spawn(&config, c, backends, stats))
+                }
+            },
+            None => {
+                host_health_stats.remove(config.name());
+                None
+            }
+        };
 
         let new_host = OpensslHost {
             config,
             ssl_context,
+            _cert_watcher: cert_watcher,
             #[cfg(feature = "vendored-tongsuo")]
             tlcp_context,
             req_alive_sem,
             request_rate_limit,
+            early_data_replay_cache,
+            backend_pool,
+            h2_backend_pool,
+            health,
             backends: self.backends.clone(), // use the old container
+            client_cert_backends: self.client_cert_backends.clone(),
+            shared_logger: OnceLock::new(),
         };
         new_host.update_backends(); // update backends using the new config
         Ok(new_host)
     }
 
+    pub(super) fn ssl_context(&self) -> Option<Arc<SslContext>> {
+        self.ssl_context.load_full()
+    }
+
+    /// Get the task logger for the tasks of this host, the server one will be used if no shared
+    /// logger is set for this host
+    pub(super) fn task_logger(
+        &self,
+        server_config: &OpensslProxyServerConfig,
+        server_logger: Option<Logger>,
+    ) -> Option<Logger> {
+        let Some(name) = &self.config.shared_logger else {
+            return server_logger;
+        };
+        self.shared_logger
+            .get_or_init(|| {
+                crate::log::task::get_shared_logger(
+                    name.as_str(),
+                    server_config.r#type(),
+                    server_config.name(),
+                )
+            })
+            .clone()
+    }
+
     pub(super) fn check_rate_limit(&self) -> Result<(), ()> {
         if let Some(limit) = &self.request_rate_limit {
             if limit.check().is_err() {
@@ -124,6 +253,33 @@ impl OpensslHost {
             .transpose()
     }
 
+    /// Return true if the early data sent along with this ticket is not a replay
+    pub(super) fn check_early_data_ticket(&self, ticket: &[u8]) -> bool {
+        self.early_data_replay_cache
+            .as_ref()
+            .map(|cache| cache.check_fresh(ticket))
+            .unwrap_or(false)
+    }
+
+    pub(super) fn backend_pool(&self) -> Option<&Arc<OpensslBackendPool>> {
+        self.backend_pool.as_ref()
+    }
+
+    pub(super) fn h2_backend_pool(&self) -> Option<&Arc<OpensslH2BackendPool>> {
+        self.h2_backend_pool.as_ref()
+    }
+
+    /// Get the action on new client connections, `None` if the host is healthy or not checked
+    pub(super) fn unhealthy_action(&self) -> Option<OpensslUnhealthyAction> {
+        self.health.as_ref().and_then(|h| h.unhealthy_action())
+    }
+
+    pub(super) fn record_backend_connect(&self, ok: bool) {
+        if let Some(health) = &self.health {
+            health.record_connect(ok);
+        }
+    }
+
     pub(super) fn get_backend(&self, protocol: &str) -> Option<ArcBackend> {
         self.backends.load().get(protocol).cloned()
     }
@@ -132,8 +288,26 @@ impl OpensslHost {
         self.backends.load().get_default().cloned()
     }
 
+    /// Select the backend by the first client cert route rule matched by the peer certificate
+    pub(super) fn select_client_cert_backend(
+        &self,
+        cert: &X509Ref,
+    ) -> Option<(String, ArcBackend)> {
+        let router = self.config.client_cert_router.as_ref()?;
+        let index = router.select(cert)?;
+        let backend = self.client_cert_backends.load().get(index)?.clone();
+        Some((router.rules[index].name.clone(), backend))
+    }
+
     pub(super) fn use_backend(&self, name: &NodeName) -> bool {
-        self.config.backends.contains_value(name)
+        if self.config.backends.contains_value(name) {
+            return true;
+        }
+        self.config
+            .client_cert_router
+            .as_ref()
+            .map(|router| router.contains_backend(name))
+            .unwrap_or(false)
     }
 
     pub(super) fn update_backends(&self) {
@@ -142,9 +316,39 @@ impl OpensslHost {
             .backends
             .build(crate::backend::get_or_insert_default);
         self.backends.store(Arc::new(backends));
+        self.client_cert_backends
+            .store(Arc::new(build_client_cert_backends(&self.config)));
     }
 }
 
+fn spawn_cert_watcher(
+    config: &Arc<OpensslHostConfig>,
+    tls_ticketer: &Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+    ssl_context: &Arc<ArcSwapOption<SslContext>>,
+    cert_reload_stats: &Arc<CertReloadStats>,
+) -> Option<OpensslCertWatcher> {
+    if config.watched_cert_files().is_empty() {
+        return None;
+    }
+    Some(OpensslCertWatcher::spawn(
+        config.clone(),
+        tls_ticketer.clone(),
+        ssl_context.clone(),
+        cert_reload_stats.clone(),
+    ))
+}
+
+fn build_client_cert_backends(config: &OpensslHostConfig) -> Vec<ArcBackend> {
+    let Some(router) = &config.client_cert_router else {
+        return Vec::new();
+    };
+    router
+        .rules
+        .iter()
+        .map(|rule| crate::backend::get_or_insert_default(&rule.backend))
+        .collect()
+}
+
 impl NamedValue for OpensslHost {
     type Name = str;
     type NameOwned = String;
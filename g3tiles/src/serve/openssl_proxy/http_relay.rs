@@ -0,0 +1,544 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll, ready};
+
+use http::Method;
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadBuf};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, mpsc};
+
+use g3_http::client::{HttpResponseParseError, HttpTransparentResponse};
+use g3_http::server::{HttpRequestParseError, HttpTransparentRequest};
+use g3_http::{HttpBodyReader, HttpBodyType};
+use g3_io_ext::{StreamCopy, StreamCopyConfig, StreamCopyError};
+
+use crate::config::server::openssl_proxy::OpensslHttpAwareConfig;
+use crate::serve::{ServerTaskError, ServerTaskResult};
+
+/// A buffered reader that is able to record the consumed bytes, so the raw data can still be
+/// relayed if it can not be parsed as HTTP/1.1 messages.
+struct RecordBufReader<R> {
+    inner: BufReader<R>,
+    record: Option<Vec<u8>>,
+}
+
+impl<R: AsyncRead> RecordBufReader<R> {
+    fn new(inner: R) -> Self {
+        RecordBufReader {
+            inner: BufReader::new(inner),
+            record: None,
+        }
+    }
+
+    fn start_record(&mut self) {
+        self.record = Some(Vec::new());
+    }
+
+    fn stop_record(&mut self) {
+        self.record = None;
+    }
+
+    fn take_record(&mut self) -> Vec<u8> {
+        self.record.take().unwrap_or_default()
+    }
+
+    fn record_is_empty(&self) -> bool {
+        self.record.as_ref().map(|r| r.is_empty()).unwrap_or(true)
+    }
+
+    fn no_cached_data(&self) -> bool {
+        self.inner.buffer().is_empty()
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for RecordBufReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let offset = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        if let Some(record) = &mut this.record {
+            record.extend_from_slice(&buf.filled()[offset..]);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncBufRead for RecordBufReader<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        Pin::new(&mut self.get_mut().inner).poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        if let Some(record) = &mut this.record {
+            let buf = this.inner.buffer();
+            record.extend_from_slice(&buf[..amt.min(buf.len())]);
+        }
+        Pin::new(&mut this.inner).consume(amt);
+    }
+}
+
+/// A request that has been sent to the backend, and is waiting for the response.
+///
+/// The pipeline slot will be released when dropped.
+struct PendingResponse {
+    method: Method,
+    keep_alive: bool,
+    _permit: OwnedSemaphorePermit,
+}
+
+struct HttpRelayState<'a, F> {
+    config: &'a OpensslHttpAwareConfig,
+    copy_config: StreamCopyConfig,
+    pipeline: Arc<Semaphore>,
+    // set if the client side is no longer relayed as HTTP messages
+    north_raw: AtomicBool,
+    fallback: AtomicBool,
+    on_fallback: &'a F,
+}
+
+impl<F: Fn()> HttpRelayState<'_, F> {
+    fn mark_fallback(&self) {
+        if !self.fallback.swap(true, Ordering::Relaxed) {
+            (self.on_fallback)();
+        }
+    }
+}
+
+/// Relay the connection as HTTP/1.1 messages, and the requests will be pipelined to the backend
+/// with a limited depth. The connection will be relayed as raw bytes once a message can not be
+/// parsed, or the protocol is switched.
+///
+/// Return true if the backend connection is clean to be reused.
+pub(super) async fn relay_http<CR, CW, UR, UW, F>(
+    config: &OpensslHttpAwareConfig,
+    copy_config: StreamCopyConfig,
+    clt_r: CR,
+    clt_w: &mut CW,
+    ups_r: &mut UR,
+    ups_w: &mut UW,
+    on_fallback: &F,
+) -> ServerTaskResult<bool>
+where
+    CR: AsyncRead + Unpin,
+    CW: AsyncWrite + Unpin,
+    UR: AsyncRead + Unpin,
+    UW: AsyncWrite + Unpin,
+    F: Fn(),
+{
+    let state = HttpRelayState {
+        config,
+        copy_config,
+        pipeline: Arc::new(Semaphore::new(config.max_pipeline_depth.get())),
+        north_raw: AtomicBool::new(false),
+        fallback: AtomicBool::new(false),
+        on_fallback,
+    };
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    let north = forward_requests(&state, RecordBufReader::new(clt_r), ups_w, sender);
+    let south = forward_responses(&state, RecordBufReader::new(ups_r), clt_w, receiver);
+    tokio::pin!(north);
+    tokio::pin!(south);
+
+    tokio::select! {
+        r = &mut north => {
+            r?;
+            south.await
+        }
+        r = &mut south => {
+            // the backend has closed the connection, so it's useless to forward more requests
+            r?;
+            Ok(false)
+        }
+    }
+}
+
+async fn forward_requests<CR, UW, F>(
+    state: &HttpRelayState<'_, F>,
+    mut clt_r: RecordBufReader<CR>,
+    ups_w: &mut UW,
+    sender: mpsc::UnboundedSender<PendingResponse>,
+) -> ServerTaskResult<()>
+where
+    CR: AsyncRead + Unpin,
+    UW: AsyncWrite + Unpin,
+    F: Fn(),
+{
+    loop {
+        let Ok(permit) = state.pipeline.clone().acquire_owned().await else {
+            // the response side is no longer relayed as HTTP messages
+            break;
+        };
+
+        clt_r.start_record();
+        let (req, head) =
+            match HttpTransparentRequest::parse(&mut clt_r, state.config.max_header_size, false)
+                .await
+            {
+                Ok(d) => d,
+                Err(HttpRequestParseError::ClientClosed) if clt_r.record_is_empty() => {
+                    let _ = ups_w.flush().await;
+                    return Ok(());
+                }
+                Err(HttpRequestParseError::IoFailed(e)) => {
+                    return Err(ServerTaskError::ClientTcpReadFailed(e));
+                }
+                Err(_) => {
+                    let record = clt_r.take_record();
+                    state.north_raw.store(true, Ordering::Relaxed);
+                    state.mark_fallback();
+                    drop(sender);
+                    ups_w
+                        .write_all(&record)
+                        .await
+                        .map_err(ServerTaskError::UpstreamWriteFailed)?;
+                    return relay_raw_north(state, clt_r, ups_w).await;
+                }
+            };
+        clt_r.stop_record();
+
+        ups_w
+            .write_all(&head)
+            .await
+            .map_err(ServerTaskError::UpstreamWriteFailed)?;
+        if let Some(body_type) = req.body_type() {
+            let mut body_reader =
+                HttpBodyReader::new(&mut clt_r, body_type, state.config.body_line_max_len);
+            StreamCopy::new(&mut body_reader, ups_w, &state.copy_config)
+                .await
+                .map_err(|e| match e {
+                    StreamCopyError::ReadFailed(e) => ServerTaskError::ClientTcpReadFailed(e),
+                    StreamCopyError::WriteFailed(e) => ServerTaskError::UpstreamWriteFailed(e),
+                })?;
+        } else {
+            ups_w
+                .flush()
+                .await
+                .map_err(ServerTaskError::UpstreamWriteFailed)?;
+        }
+
+        let switch_protocol = req.upgrade || req.method == Method::CONNECT;
+        let keep_alive = req.keep_alive();
+        let pending = PendingResponse {
+            method: req.method,
+            keep_alive,
+            _permit: permit,
+        };
+        if sender.send(pending).is_err() || switch_protocol || !keep_alive {
+            // no more requests should be parsed on this connection
+            break;
+        }
+    }
+
+    state.north_raw.store(true, Ordering::Relaxed);
+    drop(sender);
+    relay_raw_north(state, clt_r, ups_w).await
+}
+
+async fn relay_raw_north<CR, UW, F>(
+    state: &HttpRelayState<'_, F>,
+    mut clt_r: RecordBufReader<CR>,
+    ups_w: &mut UW,
+) -> ServerTaskResult<()>
+where
+    CR: AsyncRead + Unpin,
+    UW: AsyncWrite + Unpin,
+{
+    StreamCopy::new(&mut clt_r, ups_w, &state.copy_config)
+        .await
+        .map_err(|e| match e {
+            StreamCopyError::ReadFailed(e) => ServerTaskError::ClientTcpReadFailed(e),
+            StreamCopyError::WriteFailed(e) => ServerTaskError::UpstreamWriteFailed(e),
+        })?;
+    let _ = ups_w.shutdown().await;
+    Ok(())
+}
+
+async fn forward_responses<UR, CW, F>(
+    state: &HttpRelayState<'_, F>,
+    mut ups_r: RecordBufReader<UR>,
+    clt_w: &mut CW,
+    mut receiver: mpsc::UnboundedReceiver<PendingResponse>,
+) -> ServerTaskResult<bool>
+where
+    UR: AsyncRead + Unpin,
+    CW: AsyncWrite + Unpin,
+    F: Fn(),
+{
+    let mut keep_alive = true;
+    while let Some(pending) = receiver.recv().await {
+        let rsp = loop {
+            ups_r.start_record();
+            let (rsp, head) = match HttpTransparentResponse::parse(
+                &mut ups_r,
+                &pending.method,
+                pending.keep_alive,
+                state.config.max_header_size,
+            )
+            .await
+            {
+                Ok(d) => d,
+                Err(HttpResponseParseError::RemoteClosed) if ups_r.record_is_empty() => {
+                    let _ = clt_w.shutdown().await;
+                    return Ok(false);
+                }
+                Err(HttpResponseParseError::IoFailed(e)) => {
+                    return Err(ServerTaskError::UpstreamReadFailed(e));
+                }
+                Err(_) => {
+                    let record = ups_r.take_record();
+                    state.pipeline.close();
+                    receiver.close();
+                    state.mark_fallback();
+                    clt_w
+                        .write_all(&record)
+                        .await
+                        .map_err(ServerTaskError::ClientTcpWriteFailed)?;
+                    return relay_raw_south(state, ups_r, clt_w).await;
+                }
+            };
+            ups_r.stop_record();
+
+            clt_w
+                .write_all(&head)
+                .await
+                .map_err(ServerTaskError::ClientTcpWriteFailed)?;
+            if rsp.code == 101 || (pending.method == Method::CONNECT && rsp.code / 100 == 2) {
+                state.pipeline.close();
+                receiver.close();
+                return relay_raw_south(state, ups_r, clt_w).await;
+            }
+            if rsp.code >= 200 {
+                break rsp;
+            }
+            // forward the interim response, and wait for the final one
+            clt_w
+                .flush()
+                .await
+                .map_err(ServerTaskError::ClientTcpWriteFailed)?;
+        };
+
+        let body_type = rsp.body_type(&pending.method);
+        if let Some(body_type) = body_type {
+            let mut body_reader =
+                HttpBodyReader::new(&mut ups_r, body_type, state.config.body_line_max_len);
+            StreamCopy::new(&mut body_reader, clt_w, &state.copy_config)
+                .await
+                .map_err(|e| match e {
+                    StreamCopyError::ReadFailed(e) => ServerTaskError::UpstreamReadFailed(e),
+                    StreamCopyError::WriteFailed(e) => ServerTaskError::ClientTcpWriteFailed(e),
+                })?;
+        } else {
+            clt_w
+                .flush()
+                .await
+                .map_err(ServerTaskError::ClientTcpWriteFailed)?;
+        }
+
+        if !rsp.keep_alive() || matches!(body_type, Some(HttpBodyType::ReadUntilEnd)) {
+            let _ = clt_w.shutdown().await;
+            return Ok(false);
+        }
+        keep_alive = rsp.keep_alive();
+        // release the pipeline slot
+        drop(pending);
+    }
+
+    if state.north_raw.load(Ordering::Relaxed) {
+        relay_raw_south(state, ups_r, clt_w).await
+    } else {
+        let _ = clt_w.shutdown().await;
+        Ok(keep_alive && ups_r.no_cached_data())
+    }
+}
+
+async fn relay_raw_south<UR, CW, F>(
+    state: &HttpRelayState<'_, F>,
+    mut ups_r: RecordBufReader<UR>,
+    clt_w: &mut CW,
+) -> ServerTaskResult<bool>
+where
+    UR: AsyncRead + Unpin,
+    CW: AsyncWrite + Unpin,
+{
+    StreamCopy::new(&mut ups_r, clt_w, &state.copy_config)
+        .await
+        .map_err(|e| match e {
+            StreamCopyError::ReadFailed(e) => ServerTaskError::UpstreamReadFailed(e),
+            StreamCopyError::WriteFailed(e) => ServerTaskError::ClientTcpWriteFailed(e),
+        })?;
+    let _ = clt_w.shutdown().await;
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroUsize;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, BufReader as TokioBufReader};
+
+    fn http_aware_config(depth: usize) -> OpensslHttpAwareConfig {
+        OpensslHttpAwareConfig {
+            max_pipeline_depth: NonZeroUsize::new(depth).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    async fn read_request_heads<R>(reader: &mut R, count: usize) -> Vec<String>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut heads = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (req, _) = HttpTransparentRequest::parse(reader, 4096, false)
+                .await
+                .unwrap();
+            heads.push(req.uri.to_string());
+        }
+        heads
+    }
+
+    #[tokio::test]
+    async fn pipeline_depth() {
+        let config = http_aware_config(2);
+        let (mut client, clt_side) = tokio::io::duplex(1024);
+        let (ups_side, backend) = tokio::io::duplex(1024);
+        let (clt_r, mut clt_w) = tokio::io::split(clt_side);
+        let (mut ups_r, mut ups_w) = tokio::io::split(ups_side);
+        let (backend_r, mut backend_w) = tokio::io::split(backend);
+
+        let received = Arc::new(AtomicUsize::new(0));
+        let backend_received = received.clone();
+        let backend_task = tokio::spawn(async move {
+            let mut backend_r = TokioBufReader::new(backend_r);
+            let mut uris = read_request_heads(&mut backend_r, 1).await;
+            backend_received.fetch_add(1, Ordering::Relaxed);
+
+            // the large body will not be fully sent until the client reads it
+            backend_w
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 8192\r\n\r\n")
+                .await
+                .unwrap();
+            let body = vec![b'a'; 8192];
+            let read_second = async {
+                let uris = read_request_heads(&mut backend_r, 1).await;
+                backend_received.fetch_add(1, Ordering::Relaxed);
+                uris
+            };
+            let (r, second) = tokio::join!(backend_w.write_all(&body), read_second);
+            r.unwrap();
+            uris.extend(second);
+
+            backend_w
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            uris.extend(read_request_heads(&mut backend_r, 1).await);
+            backend_received.fetch_add(1, Ordering::Relaxed);
+            backend_w
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            uris
+        });
+
+        let relay_task = tokio::spawn(async move {
+            relay_http(
+                &config,
+                StreamCopyConfig::default(),
+                clt_r,
+                &mut clt_w,
+                &mut ups_r,
+                &mut ups_w,
+                &|| {},
+            )
+            .await
+        });
+
+        client
+            .write_all(b"GET /1 HTTP/1.1\r\nHost: a\r\n\r\nGET /2 HTTP/1.1\r\nHost: a\r\n\r\nGET /3 HTTP/1.1\r\nHost: a\r\n\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        // the first response is blocked, so only 2 requests should be in flight
+        assert_eq!(received.load(Ordering::Relaxed), 2);
+
+        let mut client = TokioBufReader::new(client);
+        let (rsp, _) = HttpTransparentResponse::parse(&mut client, &Method::GET, true, 4096)
+            .await
+            .unwrap();
+        assert_eq!(rsp.code, 200);
+        let mut body = vec![0u8; 8192];
+        client.read_exact(&mut body).await.unwrap();
+        for _ in 0..2 {
+            let (rsp, _) = HttpTransparentResponse::parse(&mut client, &Method::GET, true, 4096)
+                .await
+                .unwrap();
+            assert_eq!(rsp.code, 204);
+        }
+
+        let uris = backend_task.await.unwrap();
+        assert_eq!(uris, ["/1", "/2", "/3"]);
+        assert_eq!(received.load(Ordering::Relaxed), 3);
+
+        client.get_mut().shutdown().await.unwrap();
+        let reusable = relay_task.await.unwrap().unwrap();
+        assert!(reusable);
+    }
+
+    #[tokio::test]
+    async fn fallback_raw() {
+        let config = http_aware_config(4);
+        let (mut client, clt_side) = tokio::io::duplex(1024);
+        let (ups_side, mut backend) = tokio::io::duplex(1024);
+        let (clt_r, mut clt_w) = tokio::io::split(clt_side);
+        let (mut ups_r, mut ups_w) = tokio::io::split(ups_side);
+
+        let fallback = Arc::new(AtomicUsize::new(0));
+        let fallback_count = fallback.clone();
+        let relay_task = tokio::spawn(async move {
+            relay_http(
+                &config,
+                StreamCopyConfig::default(),
+                clt_r,
+                &mut clt_w,
+                &mut ups_r,
+                &mut ups_w,
+                &|| {
+                    fallback_count.fetch_add(1, Ordering::Relaxed);
+                },
+            )
+            .await
+        });
+
+        client.write_all(b"\x16\x03\x01 not http\n").await.unwrap();
+        client.shutdown().await.unwrap();
+        let mut buf = Vec::new();
+        backend.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf.as_slice(), b"\x16\x03\x01 not http\n");
+
+        backend.write_all(b"raw reply").await.unwrap();
+        backend.shutdown().await.unwrap();
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf.as_slice(), b"raw reply");
+
+        let reusable = relay_task.await.unwrap().unwrap();
+        assert!(!reusable);
+        assert_eq!(fallback.load(Ordering::Relaxed), 1);
+    }
+}
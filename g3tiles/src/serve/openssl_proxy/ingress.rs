@@ -0,0 +1,221 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+
+use g3_io_ext::haproxy::{
+    PP2_TYPE_ALPN, PP2_TYPE_AUTHORITY, ProxyAddr, ProxyProtocolReadError, ProxyProtocolV1Reader,
+    ProxyProtocolV2Reader,
+};
+
+use crate::config::server::openssl_proxy::IngressProxyProtocol;
+
+const V1_FIRST_BYTE: u8 = b'P';
+const V2_FIRST_BYTE: u8 = 0x0D;
+
+const V1_LOCAL_HEADER: &[u8] = b"PROXY UNKNOWN\r\n";
+const V2_LOCAL_HEADER: &[u8] = b"\x0d\x0a\x0d\x0a\x00\x0d\x0a\x51\x55\x49\x54\x0a\x20\x00\x00\x00";
+
+/// The extra info conveyed by the PROXY protocol v2 TLVs
+#[derive(Default)]
+pub(crate) struct IngressProxyTlvs {
+    pub(crate) authority: Option<String>,
+    pub(crate) alpn: Option<String>,
+}
+
+impl IngressProxyTlvs {
+    fn from_v2_reader(reader: &ProxyProtocolV2Reader) -> Self {
+        let get_str = |tlv_type| {
+            reader
+                .tlv(tlv_type)
+                .and_then(|v| std::str::from_utf8(v).ok())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+        };
+        IngressProxyTlvs {
+            authority: get_str(PP2_TYPE_AUTHORITY),
+            alpn: get_str(PP2_TYPE_ALPN),
+        }
+    }
+}
+
+/// The PROXY protocol header for local connections that are not relayed, like the loopback check
+pub(super) fn ingress_local_header(protocol: IngressProxyProtocol) -> &'static [u8] {
+    match protocol {
+        IngressProxyProtocol::V1 | IngressProxyProtocol::Auto => V1_LOCAL_HEADER,
+        IngressProxyProtocol::V2 => V2_LOCAL_HEADER,
+    }
+}
+
+/// Read and strip the PROXY protocol header before the TLS ClientHello message
+///
+/// The header size is bounded by the readers, and the whole read is bounded by `timeout`.
+pub(super) async fn read_ingress_proxy_protocol(
+    stream: &mut TcpStream,
+    protocol: IngressProxyProtocol,
+    timeout: Duration,
+) -> Result<(Option<ProxyAddr>, IngressProxyTlvs), ProxyProtocolReadError> {
+    match protocol {
+        IngressProxyProtocol::V1 => read_v1(stream, timeout).await,
+        IngressProxyProtocol::V2 => read_v2(stream, timeout).await,
+        IngressProxyProtocol::Auto => {
+            let time_start = Instant::now();
+            let mut buf = [0u8; 1];
+            match tokio::time::timeout(timeout, stream.peek(&mut buf)).await {
+                Ok(Ok(0)) => return Err(ProxyProtocolReadError::ClosedUnexpected),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => return Err(ProxyProtocolReadError::ReadFailed(e)),
+                Err(_) => return Err(ProxyProtocolReadError::ReadTimeout),
+            }
+            let timeout = timeout.saturating_sub(time_start.elapsed());
+            match buf[0] {
+                V1_FIRST_BYTE => read_v1(stream, timeout).await,
+                V2_FIRST_BYTE => read_v2(stream, timeout).await,
+                _ => Ok((None, IngressProxyTlvs::default())),
+            }
+        }
+    }
+}
+
+async fn read_v1(
+    stream: &mut TcpStream,
+    timeout: Duration,
+) -> Result<(Option<ProxyAddr>, IngressProxyTlvs), ProxyProtocolReadError> {
+    let mut reader = ProxyProtocolV1Reader::new(timeout);
+    let addr = reader.read_proxy_protocol_v1_for_tcp(stream).await?;
+    Ok((addr, IngressProxyTlvs::default()))
+}
+
+async fn read_v2(
+    stream: &mut TcpStream,
+    timeout: Duration,
+) -> Result<(Option<ProxyAddr>, IngressProxyTlvs), ProxyProtocolReadError> {
+    let mut reader = ProxyProtocolV2Reader::new(timeout);
+    let addr = reader.read_proxy_protocol_v2_for_tcp(stream).await?;
+    Ok((addr, IngressProxyTlvs::from_v2_reader(&reader)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use g3_types::net::ProxyProtocolV2Encoder;
+
+    const CLIENT_HELLO_START: &[u8] = &[0x16, 0x03, 0x01];
+
+    async fn run_read(
+        protocol: IngressProxyProtocol,
+        data: Vec<u8>,
+    ) -> Result<(Option<ProxyAddr>, IngressProxyTlvs), ProxyProtocolReadError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&data).await.unwrap();
+            stream.write_all(CLIENT_HELLO_START).await.unwrap();
+            // keep the connection open until the server side closes it
+            let mut buf = [0u8; 1];
+            let _ = stream.read(&mut buf).await;
+        });
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let r = read_ingress_proxy_protocol(&mut stream, protocol, Duration::from_secs(1)).await;
+        if r.is_ok() {
+            // the ClientHello message should be kept untouched
+            let mut buf = [0u8; 3];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, CLIENT_HELLO_START);
+        }
+        drop(stream);
+        client.await.unwrap();
+        r
+    }
+
+    #[tokio::test]
+    async fn v1() {
+        let data = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\n".to_vec();
+        let (addr, tlvs) = run_read(IngressProxyProtocol::V1, data.clone())
+            .await
+            .unwrap();
+        let addr = addr.unwrap();
+        assert_eq!(
+            addr.src_addr,
+            SocketAddr::from_str("192.168.0.1:56324").unwrap()
+        );
+        assert_eq!(
+            addr.dst_addr,
+            SocketAddr::from_str("192.168.0.11:443").unwrap()
+        );
+        assert!(tlvs.authority.is_none());
+
+        let (addr, _) = run_read(IngressProxyProtocol::Auto, data).await.unwrap();
+        assert!(addr.is_some());
+    }
+
+    #[tokio::test]
+    async fn v2_tlv() {
+        let client = SocketAddr::from_str("[2001:db8::1]:56324").unwrap();
+        let server = SocketAddr::from_str("[2001:db8::11]:443").unwrap();
+        let mut encoder = ProxyProtocolV2Encoder::new_tcp(client, server).unwrap();
+        encoder
+            .push_tlv(PP2_TYPE_AUTHORITY, b"www.example.net")
+            .unwrap();
+        encoder.push_tlv(PP2_TYPE_ALPN, b"h2").unwrap();
+        let data = encoder.finalize().to_vec();
+
+        for protocol in [IngressProxyProtocol::V2, IngressProxyProtocol::Auto] {
+            let (addr, tlvs) = run_read(protocol, data.clone()).await.unwrap();
+            let addr = addr.unwrap();
+            assert_eq!(addr.src_addr, client);
+            assert_eq!(addr.dst_addr, server);
+            assert_eq!(tlvs.authority.as_deref(), Some("www.example.net"));
+            assert_eq!(tlvs.alpn.as_deref(), Some("h2"));
+        }
+    }
+
+    #[tokio::test]
+    async fn auto_no_proxy() {
+        let (addr, tlvs) = run_read(IngressProxyProtocol::Auto, Vec::new())
+            .await
+            .unwrap();
+        assert!(addr.is_none());
+        assert!(tlvs.authority.is_none());
+        assert!(tlvs.alpn.is_none());
+    }
+
+    #[tokio::test]
+    async fn local() {
+        for protocol in [
+            IngressProxyProtocol::V1,
+            IngressProxyProtocol::V2,
+            IngressProxyProtocol::Auto,
+        ] {
+            let data = ingress_local_header(protocol).to_vec();
+            let (addr, tlvs) = run_read(protocol, data).await.unwrap();
+            assert!(addr.is_none());
+            assert!(tlvs.authority.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed() {
+        let data = b"PROXY TCP4 192.168.0.1\r\n".to_vec();
+        let r = run_read(IngressProxyProtocol::V1, data).await;
+        assert!(matches!(r, Err(ProxyProtocolReadError::InvalidDstAddr)));
+
+        // the last byte of the magic header is wrong
+        let data = b"\x0d\x0a\x0d\x0a\x00\x0d\x0a\x51\x55\x49\x54\x0b\x21\x11\x00\x00".to_vec();
+        let r = run_read(IngressProxyProtocol::V2, data).await;
+        assert!(matches!(r, Err(ProxyProtocolReadError::InvalidMagicHeader)));
+    }
+}
@@ -5,9 +5,49 @@
 
 mod server;
 pub(super) use server::OpensslProxyServer;
+use server::TLS_ALERT_INTERNAL_ERROR;
 
 mod task;
 use task::{CommonTaskContext, OpensslAcceptTask};
 
 mod host;
 use host::OpensslHost;
+
+mod cert_watch;
+use cert_watch::OpensslCertWatcher;
+
+mod resolver;
+use resolver::OpensslCertResolver;
+
+mod handshake;
+use handshake::{OpensslHandshakeLimiter, OpensslHandshakePermit};
+
+mod client_ip_limit;
+use client_ip_limit::OpensslClientIpLimiter;
+
+mod client_hello_buffer;
+use client_hello_buffer::{ClientHelloBuffer, OpensslClientHelloBufferPool};
+
+mod early_data;
+use early_data::{OpensslEarlyDataReplayCache, first_psk_identity};
+
+mod ingress;
+use ingress::{IngressProxyTlvs, ingress_local_header, read_ingress_proxy_protocol};
+
+mod egress;
+use egress::upstream_proxy_header;
+
+mod check;
+use check::loopback_tls_handshake;
+
+mod backend_pool;
+use backend_pool::OpensslBackendPool;
+
+mod backend_h2;
+use backend_h2::{OpensslH2BackendPool, transfer_stream};
+
+mod http_relay;
+use http_relay::relay_http;
+
+mod health;
+use health::OpensslHostHealth;
@@ -0,0 +1,60 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+
+use g3_types::net::OpensslCertificatePair;
+
+use super::CertResolveBackend;
+
+const CERT_FILE_NAME: &str = "cert.pem";
+const KEY_FILE_NAME: &str = "key.pem";
+
+pub(super) struct DirectoryCertResolveBackend {
+    dir: PathBuf,
+}
+
+impl DirectoryCertResolveBackend {
+    pub(super) fn new(dir: PathBuf) -> Self {
+        DirectoryCertResolveBackend { dir }
+    }
+}
+
+#[async_trait]
+impl CertResolveBackend for DirectoryCertResolveBackend {
+    async fn query(&self, name: &str) -> anyhow::Result<Option<OpensslCertificatePair>> {
+        // the server name will be used as a path component, so never allow path separators
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Ok(None);
+        }
+
+        let host_dir = self.dir.join(name);
+        tokio::task::spawn_blocking(move || load_cert_pair(&host_dir))
+            .await
+            .map_err(|e| anyhow!("failed to join the blocking load task: {e}"))?
+    }
+}
+
+fn read_pem_file(path: &Path) -> anyhow::Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(anyhow!("failed to read file {}: {e}", path.display())),
+    }
+}
+
+fn load_cert_pair(host_dir: &Path) -> anyhow::Result<Option<OpensslCertificatePair>> {
+    let Some(cert_pem) = read_pem_file(&host_dir.join(CERT_FILE_NAME))? else {
+        return Ok(None);
+    };
+    let Some(key_pem) = read_pem_file(&host_dir.join(KEY_FILE_NAME))? else {
+        return Ok(None);
+    };
+    super::parse_cert_pair(&cert_pem, &key_pem).map(Some)
+}
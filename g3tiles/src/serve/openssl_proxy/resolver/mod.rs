@@ -0,0 +1,382 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use log::debug;
+use lru::LruCache;
+use openssl::pkey::PKey;
+use openssl::ssl::SslContext;
+use openssl::x509::X509;
+use tokio::time::Instant;
+
+use g3_types::net::{OpensslCertificatePair, OpensslTicketKey, RollingTicketer};
+
+use super::OpensslHost;
+use crate::config::server::openssl_proxy::{
+    OpensslCertResolverBackendConfig, OpensslCertResolverConfig,
+};
+use crate::serve::CertResolverStats;
+
+mod directory;
+use directory::DirectoryCertResolveBackend;
+
+mod unix_socket;
+use unix_socket::UnixSocketCertResolveBackend;
+
+#[async_trait]
+pub(crate) trait CertResolveBackend {
+    /// Query the certificate pair for the server name, return `None` if not found
+    async fn query(&self, name: &str) -> anyhow::Result<Option<OpensslCertificatePair>>;
+}
+
+type ArcCertResolveBackend = Arc<dyn CertResolveBackend + Send + Sync>;
+
+struct CachedSslContext {
+    ssl_context: Option<SslContext>,
+    expire: Instant,
+}
+
+pub(crate) struct OpensslCertResolver {
+    config: OpensslCertResolverConfig,
+    backend: ArcCertResolveBackend,
+    host: Arc<OpensslHost>,
+    tls_ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+    cache: Mutex<LruCache<String, CachedSslContext>>,
+    stats: Arc<CertResolverStats>,
+}
+
+impl OpensslCertResolver {
+    pub(super) fn new(
+        config: &OpensslCertResolverConfig,
+        host: Arc<OpensslHost>,
+        tls_ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+        stats: Arc<CertResolverStats>,
+    ) -> Self {
+        let backend: ArcCertResolveBackend = match &config.backend {
+            OpensslCertResolverBackendConfig::Directory(path) => {
+                Arc::new(DirectoryCertResolveBackend::new(path.clone()))
+            }
+            OpensslCertResolverBackendConfig::UnixSocket(path) => {
+                Arc::new(UnixSocketCertResolveBackend::new(path.clone()))
+            }
+        };
+
+        OpensslCertResolver {
+            config: config.clone(),
+            backend,
+            host,
+            tls_ticketer,
+            cache: Mutex::new(LruCache::new(config.cache_size)),
+            stats,
+        }
+    }
+
+    #[inline]
+    pub(super) fn host(&self) -> &Arc<OpensslHost> {
+        &self.host
+    }
+
+    /// Resolve the TLS context for the server name, return `None` if not available in time
+    pub(super) async fn resolve(&self, name: &str, timeout: Duration) -> Option<SslContext> {
+        if let Some(ssl_context) = self.get_cached(name) {
+            self.stats.add_hit();
+            return ssl_context;
+        }
+        self.stats.add_miss();
+
+        let timeout = timeout.min(self.config.query_timeout);
+        match tokio::time::timeout(timeout, self.backend.query(name)).await {
+            Ok(Ok(Some(cert_pair))) => {
+                match self
+                    .host
+                    .config
+                    .build_ssl_context_with_cert(self.tls_ticketer.clone(), &cert_pair)
+                {
+                    Ok(ssl_context) => {
+                        self.add_cached(name, Some(ssl_context.clone()), self.config.cache_ttl);
+                        Some(ssl_context)
+                    }
+                    Err(e) => {
+                        debug!("failed to build ssl context for server name {name}: {e:?}");
+                        self.stats.add_error();
+                        self.add_cached(name, None, self.config.negative_cache_ttl);
+                        None
+                    }
+                }
+            }
+            Ok(Ok(None)) => {
+                self.add_cached(name, None, self.config.negative_cache_ttl);
+                None
+            }
+            Ok(Err(e)) => {
+                debug!("failed to query cert for server name {name}: {e:?}");
+                self.stats.add_error();
+                self.add_cached(name, None, self.config.negative_cache_ttl);
+                None
+            }
+            Err(_) => {
+                self.stats.add_timeout();
+                None
+            }
+        }
+    }
+
+    fn get_cached(&self, name: &str) -> Option<Option<SslContext>> {
+        let mut cache = self.cache.lock().unwrap();
+        let v = cache.get(name)?;
+        if v.expire > Instant::now() {
+            Some(v.ssl_context.clone())
+        } else {
+            cache.pop(name);
+            None
+        }
+    }
+
+    fn add_cached(&self, name: &str, ssl_context: Option<SslContext>, ttl: Duration) {
+        let v = CachedSslContext {
+            ssl_context,
+            expire: Instant::now() + ttl,
+        };
+        let mut cache = self.cache.lock().unwrap();
+        cache.put(name.to_string(), v);
+    }
+}
+
+fn parse_cert_pair(cert_pem: &[u8], key_pem: &[u8]) -> anyhow::Result<OpensslCertificatePair> {
+    let certs =
+        X509::stack_from_pem(cert_pem).map_err(|e| anyhow!("invalid certificate pem: {e}"))?;
+    let key =
+        PKey::private_key_from_pem(key_pem).map_err(|e| anyhow!("invalid private key pem: {e}"))?;
+
+    let mut cert_pair = OpensslCertificatePair::default();
+    cert_pair.set_certificates(certs)?;
+    cert_pair.set_private_key(key)?;
+    Ok(cert_pair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    use openssl::nid::Nid;
+    use tokio::net::UnixListener;
+    use yaml_rust::YamlLoader;
+
+    use g3_yaml::YamlDocPosition;
+
+    use crate::config::server::openssl_proxy::OpensslHostConfig;
+    use crate::testing::{TempDir, common_name, ec_key, self_signed_cert, write_cert, write_key};
+
+    fn write_cert_pair(dir: &Path, name: &str) {
+        let key = ec_key();
+        let cert = self_signed_cert(&common_name(name), &key, 1);
+
+        let host_dir = dir.join(name);
+        fs::create_dir_all(&host_dir).unwrap();
+        write_cert(&host_dir.join("cert.pem"), &cert);
+        write_key(&host_dir.join("key.pem"), &key);
+    }
+
+    fn ssl_context_common_name(ssl_context: &SslContext) -> String {
+        let cert = ssl_context.certificate().unwrap();
+        let entry = cert
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .unwrap();
+        entry.data().as_utf8().unwrap().to_string()
+    }
+
+    fn build_resolver(conf_dir: &Path, resolver_yaml: &str) -> OpensslCertResolver {
+        write_cert_pair(conf_dir, "template");
+        let position = YamlDocPosition {
+            path: conf_dir.join("main.yaml"),
+            index: 0,
+        };
+
+        let host_yaml = YamlLoader::load_from_str(
+            r#"
+                name: template
+                cert_pairs:
+                  certificate: template/cert.pem
+                  private_key: template/key.pem
+                backends:
+                  - test
+            "#,
+        )
+        .unwrap();
+        let hosts = g3_yaml::value::as_host_matched_obj::<OpensslHostConfig>(
+            &host_yaml[0],
+            Some(&position),
+        )
+        .unwrap();
+        let host_config = hosts.get_default().unwrap();
+        let host = OpensslHost::try_build(
+            host_config,
+            &None,
+            &Arc::default(),
+            &Arc::default(),
+            &Arc::default(),
+        )
+        .unwrap();
+
+        let resolver_yaml = YamlLoader::load_from_str(resolver_yaml).unwrap();
+        let config =
+            OpensslCertResolverConfig::parse_yaml(&resolver_yaml[0], Some(&position)).unwrap();
+
+        OpensslCertResolver::new(
+            &config,
+            Arc::new(host),
+            None,
+            Arc::new(CertResolverStats::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn directory_two_names() {
+        let temp_dir = TempDir::new("cert_resolver_dir");
+        let conf_dir = temp_dir.path();
+        let cert_dir = conf_dir.join("certs");
+        write_cert_pair(&cert_dir, "a.example.net");
+        write_cert_pair(&cert_dir, "b.example.net");
+
+        let resolver = build_resolver(
+            conf_dir,
+            r#"
+                directory: certs
+                host: template
+            "#,
+        );
+        let timeout = Duration::from_secs(4);
+
+        let ctx = resolver.resolve("a.example.net", timeout).await.unwrap();
+        assert_eq!(ssl_context_common_name(&ctx), "a.example.net");
+        let ctx = resolver.resolve("b.example.net", timeout).await.unwrap();
+        assert_eq!(ssl_context_common_name(&ctx), "b.example.net");
+        assert!(resolver.resolve("c.example.net", timeout).await.is_none());
+        let snap = resolver.stats.snapshot();
+        assert_eq!(snap.hit, 0);
+        assert_eq!(snap.miss, 3);
+
+        // the cached results should be used even if the files are removed
+        fs::remove_dir_all(&cert_dir).unwrap();
+        let ctx = resolver.resolve("a.example.net", timeout).await.unwrap();
+        assert_eq!(ssl_context_common_name(&ctx), "a.example.net");
+        let ctx = resolver.resolve("b.example.net", timeout).await.unwrap();
+        assert_eq!(ssl_context_common_name(&ctx), "b.example.net");
+
+        // the negative result should be cached
+        write_cert_pair(&cert_dir, "c.example.net");
+        assert!(resolver.resolve("c.example.net", timeout).await.is_none());
+
+        let snap = resolver.stats.snapshot();
+        assert_eq!(snap.hit, 3);
+        assert_eq!(snap.miss, 3);
+        assert_eq!(snap.timeout, 0);
+        assert_eq!(snap.error, 0);
+    }
+
+    #[tokio::test]
+    async fn directory_invalid_name() {
+        let temp_dir = TempDir::new("cert_resolver_invalid");
+        let conf_dir = temp_dir.path();
+        fs::create_dir_all(conf_dir.join("certs")).unwrap();
+
+        let resolver = build_resolver(
+            conf_dir,
+            r#"
+                directory: certs
+                host: template
+            "#,
+        );
+        let timeout = Duration::from_secs(4);
+
+        assert!(resolver.resolve("../template", timeout).await.is_none());
+        assert!(resolver.resolve("..", timeout).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn directory_backend_error() {
+        let temp_dir = TempDir::new("cert_resolver_error");
+        let conf_dir = temp_dir.path();
+        let host_dir = conf_dir.join("certs").join("a.example.net");
+        fs::create_dir_all(&host_dir).unwrap();
+        fs::write(host_dir.join("cert.pem"), b"invalid").unwrap();
+        fs::write(host_dir.join("key.pem"), b"invalid").unwrap();
+
+        let resolver = build_resolver(
+            conf_dir,
+            r#"
+                directory: certs
+                host: template
+            "#,
+        );
+        let timeout = Duration::from_secs(4);
+
+        assert!(resolver.resolve("a.example.net", timeout).await.is_none());
+        // the failed result should be negative cached
+        write_cert_pair(&conf_dir.join("certs"), "a.example.net");
+        assert!(resolver.resolve("a.example.net", timeout).await.is_none());
+
+        let snap = resolver.stats.snapshot();
+        assert_eq!(snap.hit, 1);
+        assert_eq!(snap.miss, 1);
+        assert_eq!(snap.timeout, 0);
+        assert_eq!(snap.error, 1);
+    }
+
+    #[tokio::test]
+    async fn unix_socket_timeout() {
+        let temp_dir = TempDir::new("cert_resolver_unix");
+        let conf_dir = temp_dir.path();
+        let socket_path = conf_dir.join("resolver.sock");
+        // accept but never respond
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        tokio::spawn(async move {
+            let mut streams = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+
+        let resolver = build_resolver(
+            conf_dir,
+            &format!(
+                r#"
+                    unix_socket: {}
+                    host: template
+                    query_timeout: 100ms
+                "#,
+                socket_path.display()
+            ),
+        );
+
+        assert!(
+            resolver
+                .resolve("a.example.net", Duration::from_secs(4))
+                .await
+                .is_none()
+        );
+        // timed out results should not be cached
+        assert!(
+            resolver
+                .resolve("a.example.net", Duration::from_millis(50))
+                .await
+                .is_none()
+        );
+
+        let snap = resolver.stats.snapshot();
+        assert_eq!(snap.hit, 0);
+        assert_eq!(snap.miss, 2);
+        assert_eq!(snap.timeout, 2);
+        assert_eq!(snap.error, 0);
+    }
+}
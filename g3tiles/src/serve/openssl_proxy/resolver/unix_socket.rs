@@ -0,0 +1,81 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::path::PathBuf;
+
+use anyhow::{Context, anyhow};
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+use g3_types::net::OpensslCertificatePair;
+
+use super::CertResolveBackend;
+
+const RESPONSE_MAX_SIZE: u64 = 1 << 20;
+
+/// Query the cert pair from a unix stream socket, one request per connection.
+///
+/// The request is a json map `{"host": "<sni>"}`, and the write half will be shutdown after sent.
+/// The response should be a json map `{"cert": "<pem>", "key": "<pem>"}`, or an empty map if not
+/// found.
+pub(super) struct UnixSocketCertResolveBackend {
+    path: PathBuf,
+}
+
+impl UnixSocketCertResolveBackend {
+    pub(super) fn new(path: PathBuf) -> Self {
+        UnixSocketCertResolveBackend { path }
+    }
+}
+
+#[async_trait]
+impl CertResolveBackend for UnixSocketCertResolveBackend {
+    async fn query(&self, name: &str) -> anyhow::Result<Option<OpensslCertificatePair>> {
+        let mut stream = UnixStream::connect(&self.path)
+            .await
+            .map_err(|e| anyhow!("failed to connect to {}: {e}", self.path.display()))?;
+
+        let req = serde_json::json!({"host": name});
+        let req = serde_json::to_vec(&req).map_err(|e| anyhow!("failed to encode request: {e}"))?;
+        stream
+            .write_all(&req)
+            .await
+            .map_err(|e| anyhow!("failed to send request: {e}"))?;
+        stream
+            .shutdown()
+            .await
+            .map_err(|e| anyhow!("failed to shutdown write: {e}"))?;
+
+        let mut rsp = Vec::with_capacity(8192);
+        stream
+            .take(RESPONSE_MAX_SIZE)
+            .read_to_end(&mut rsp)
+            .await
+            .map_err(|e| anyhow!("failed to read response: {e}"))?;
+
+        parse_response(&rsp).context("invalid response")
+    }
+}
+
+fn parse_response(rsp: &[u8]) -> anyhow::Result<Option<OpensslCertificatePair>> {
+    let Value::Object(map) =
+        serde_json::from_slice(rsp).map_err(|e| anyhow!("invalid json data: {e}"))?
+    else {
+        return Err(anyhow!("the response should be a json map"));
+    };
+
+    let cert = match map.get("cert") {
+        Some(Value::String(s)) => s,
+        Some(Value::Null) | None => return Ok(None),
+        Some(_) => return Err(anyhow!("invalid value type for key cert")),
+    };
+    let Some(Value::String(key)) = map.get("key") else {
+        return Err(anyhow!("no valid value found for key key"));
+    };
+
+    super::parse_cert_pair(cert.as_bytes(), key.as_bytes()).map(Some)
+}
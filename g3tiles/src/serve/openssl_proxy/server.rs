@@ -3,7 +3,6 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::net::SocketAddr;
 use std::sync::Arc;
 
 use ahash::AHashMap;
@@ -15,45 +14,92 @@ use slog::Logger;
 use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 
-use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
-use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenConnLimiter, ListenStats, ListenTcpRuntime,
+    loopback_connect,
+};
+use g3_daemon::server::{
+    AcceptRejectReason, AcceptRejectRecorder, AcceptRejectStats, BaseServer, ClientConnectionInfo,
+    ServerReloadCommand, TransferStats,
+};
 use g3_io_ext::IdleWheel;
 use g3_types::acl::{AclAction, AclNetworkRule};
 use g3_types::metrics::NodeName;
 use g3_types::net::{OpensslTicketKey, RollingTicketer};
 use g3_types::route::HostMatch;
 
-use super::{CommonTaskContext, OpensslAcceptTask, OpensslHost};
+use super::{
+    CommonTaskContext, IngressProxyTlvs, OpensslAcceptTask, OpensslCertResolver,
+    OpensslClientHelloBufferPool, OpensslClientIpLimiter, OpensslHandshakeLimiter, OpensslHost,
+    loopback_tls_handshake,
+};
 use crate::config::server::openssl_proxy::OpensslProxyServerConfig;
 use crate::config::server::{AnyServerConfig, ServerConfig};
 use crate::module::stream::StreamServerStats;
 use crate::serve::{
-    ArcServer, ArcServerInternal, ArcServerStats, Server, ServerInternal, ServerQuitPolicy,
-    ServerRegistry, ServerStats, WrapArcServer,
+    ArcServer, ArcServerInternal, ArcServerStats, BackendPoolStatsMap, CertReloadStats,
+    CertResolverStats, ClientCertRouteStats, ClientHelloBufferStats, ClientIpLimitStats,
+    EarlyDataStats, HandshakeLimitStats, HostHealthStatsMap, HttpAwareStats,
+    LOOPBACK_CHECK_TIMEOUT, ServedCertStats, Server, ServerCheckReport, ServerIdleOverrides,
+    ServerInternal, ServerQuitPolicy, ServerRegistry, ServerStats, SessionSniMismatchStats,
+    WrapArcServer,
 };
 
+/// A fatal internal_error alert record, with the TLS 1.0 record version that all clients accept
+pub(super) const TLS_ALERT_INTERNAL_ERROR: &[u8] = &[0x15, 0x03, 0x01, 0x00, 0x02, 0x02, 0x50];
+
 pub(crate) struct OpensslProxyServer {
     config: Arc<OpensslProxyServerConfig>,
     server_stats: Arc<StreamServerStats>,
     listen_stats: Arc<ListenStats>,
     ingress_net_filter: Option<AclNetworkRule>,
+    conn_limiter: ListenConnLimiter,
     tls_rolling_ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Option<Logger>,
     hosts: Arc<HostMatch<Arc<OpensslHost>>>,
+    cert_resolver: Option<Arc<OpensslCertResolver>>,
+    cert_resolver_stats: Arc<CertResolverStats>,
+    handshake_limiter: Option<Arc<OpensslHandshakeLimiter>>,
+    handshake_limit_stats: Arc<HandshakeLimitStats>,
+    client_ip_limiter: Option<Arc<OpensslClientIpLimiter>>,
+    client_ip_limit_stats: Arc<ClientIpLimitStats>,
+    client_hello_buffer_pool: Option<Arc<OpensslClientHelloBufferPool>>,
+    client_hello_buffer_stats: Arc<ClientHelloBufferStats>,
+    cert_reload_stats: Arc<CertReloadStats>,
+    backend_pool_stats: Arc<BackendPoolStatsMap>,
+    host_health_stats: Arc<HostHealthStatsMap>,
+    accept_reject_stats: Arc<AcceptRejectStats>,
+    accept_recorder: Arc<AcceptRejectRecorder>,
+    transfer_stats: Option<Arc<TransferStats>>,
 
     quit_policy: Arc<ServerQuitPolicy>,
     idle_wheel: Arc<IdleWheel>,
+    idle_overrides: Arc<ServerIdleOverrides>,
     reload_version: usize,
 }
 
 impl OpensslProxyServer {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         config: Arc<OpensslProxyServerConfig>,
         server_stats: Arc<StreamServerStats>,
         listen_stats: Arc<ListenStats>,
+        conn_limiter: ListenConnLimiter,
         hosts: Arc<HostMatch<Arc<OpensslHost>>>,
         tls_rolling_ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+        cert_resolver_stats: Arc<CertResolverStats>,
+        handshake_limiter: Option<Arc<OpensslHandshakeLimiter>>,
+        handshake_limit_stats: Arc<HandshakeLimitStats>,
+        client_ip_limiter: Option<Arc<OpensslClientIpLimiter>>,
+        client_ip_limit_stats: Arc<ClientIpLimitStats>,
+        client_hello_buffer_pool: Option<Arc<OpensslClientHelloBufferPool>>,
+        client_hello_buffer_stats: Arc<ClientHelloBufferStats>,
+        cert_reload_stats: Arc<CertReloadStats>,
+        backend_pool_stats: Arc<BackendPoolStatsMap>,
+        host_health_stats: Arc<HostHealthStatsMap>,
+        accept_reject_stats: Arc<AcceptRejectStats>,
+        transfer_stats: Option<Arc<TransferStats>>,
         version: usize,
     ) -> anyhow::Result<Self> {
         let reload_sender = crate::serve::new_reload_notify_channel();
@@ -64,22 +110,89 @@ impl OpensslProxyServer {
             .map(|builder| builder.build());
 
         let task_logger = config.get_task_logger();
+        let accept_recorder = Arc::new(AcceptRejectRecorder::new(
+            accept_reject_stats.clone(),
+            task_logger.clone(),
+            config.accept_error_log_rate,
+        ));
         let idle_wheel = IdleWheel::spawn(config.task_idle_check_duration);
+        let idle_overrides = Arc::new(ServerIdleOverrides::new(
+            &config.idle_overrides,
+            &idle_wheel,
+            config.task_idle_check_duration,
+            config.task_idle_max_count,
+        ));
 
         // always update extra metrics tags
         server_stats.set_extra_tags(config.extra_metrics_tags.clone());
+        server_stats.set_tls_ticketer(tls_rolling_ticketer.clone());
+        server_stats.set_transfer_stats(transfer_stats.clone());
+
+        let cert_resolver = if let Some(c) = &config.cert_resolver {
+            let Some(host) = hosts.get_all_values().get(c.host.as_str()).cloned() else {
+                return Err(anyhow!(
+                    "template host {} for cert resolver is not found",
+                    c.host
+                ));
+            };
+            let resolver = OpensslCertResolver::new(
+                c,
+                host,
+                tls_rolling_ticketer.clone(),
+                cert_resolver_stats.clone(),
+            );
+            server_stats.set_cert_resolver_stats(Some(cert_resolver_stats.clone()));
+            Some(Arc::new(resolver))
+        } else {
+            server_stats.set_cert_resolver_stats(None);
+            None
+        };
+
+        if handshake_limiter.is_some() {
+            server_stats.set_handshake_limit_stats(Some(handshake_limit_stats.clone()));
+        } else {
+            server_stats.set_handshake_limit_stats(None);
+        }
+
+        if client_ip_limiter.is_some() {
+            server_stats.set_client_ip_limit_stats(Some(client_ip_limit_stats.clone()));
+        } else {
+            server_stats.set_client_ip_limit_stats(None);
+        }
+
+        if client_hello_buffer_pool.is_some() {
+            server_stats.set_client_hello_buffer_stats(Some(client_hello_buffer_stats.clone()));
+        } else {
+            server_stats.set_client_hello_buffer_stats(None);
+        }
 
         Ok(OpensslProxyServer {
             config,
             server_stats,
             listen_stats,
             ingress_net_filter,
+            conn_limiter,
             tls_rolling_ticketer,
             reload_sender,
             task_logger,
             hosts,
+            cert_resolver,
+            cert_resolver_stats,
+            handshake_limiter,
+            handshake_limit_stats,
+            client_ip_limiter,
+            client_ip_limit_stats,
+            client_hello_buffer_pool,
+            client_hello_buffer_stats,
+            cert_reload_stats,
+            backend_pool_stats,
+            host_health_stats,
+            accept_reject_stats,
+            accept_recorder,
+            transfer_stats,
             quit_policy: Arc::new(ServerQuitPolicy::default()),
             idle_wheel,
+            idle_overrides,
             reload_version: version,
         })
     }
@@ -89,7 +202,26 @@ impl OpensslProxyServer {
     ) -> anyhow::Result<ArcServerInternal> {
         let config = Arc::new(config);
         let server_stats = Arc::new(StreamServerStats::new(config.name()));
+        server_stats.set_served_cert_stats(Some(Arc::new(ServedCertStats::default())));
+        server_stats.set_client_cert_route_stats(Some(Arc::new(ClientCertRouteStats::default())));
+        server_stats.set_early_data_stats(Some(Arc::new(EarlyDataStats::default())));
+        server_stats.set_http_aware_stats(Some(Arc::new(HttpAwareStats::default())));
+        server_stats
+            .set_session_sni_mismatch_stats(Some(Arc::new(SessionSniMismatchStats::default())));
+        let cert_reload_stats = Arc::new(CertReloadStats::default());
+        server_stats.set_cert_reload_stats(Some(cert_reload_stats.clone()));
+        let backend_pool_stats = Arc::new(BackendPoolStatsMap::default());
+        server_stats.set_backend_pool_stats(Some(backend_pool_stats.clone()));
+        let host_health_stats = Arc::new(HostHealthStatsMap::default());
+        server_stats.set_host_health_stats(Some(host_health_stats.clone()));
+        let accept_reject_stats = Arc::new(AcceptRejectStats::default());
+        server_stats.set_accept_reject_stats(Some(accept_reject_stats.clone()));
         let listen_stats = Arc::new(ListenStats::new(config.name()));
+        let conn_limiter = ListenConnLimiter::new(
+            listen_stats.clone(),
+            config.max_connections,
+            config.accept_rate_limit.as_ref(),
+        );
 
         let tls_rolling_ticketer = if let Some(c) = &config.tls_ticketer {
             let ticketer = c
@@ -100,16 +232,47 @@ impl OpensslProxyServer {
             None
         };
 
-        let hosts = config
-            .hosts
-            .try_build_arc(|c| OpensslHost::try_build(c, &tls_rolling_ticketer))?;
+        let hosts = config.hosts.try_build_arc(|c| {
+            OpensslHost::try_build(
+                c,
+                &tls_rolling_ticketer,
+                &cert_reload_stats,
+                &backend_pool_stats,
+                &host_health_stats,
+            )
+        })?;
+
+        let handshake_limit_stats = Arc::new(HandshakeLimitStats::default());
+        let handshake_limiter = build_handshake_limiter(&config, None, &handshake_limit_stats);
+        let client_ip_limit_stats = Arc::new(ClientIpLimitStats::default());
+        let client_ip_limiter = build_client_ip_limiter(&config, None, &client_ip_limit_stats);
+        let client_hello_buffer_stats = Arc::new(ClientHelloBufferStats::default());
+        let client_hello_buffer_pool =
+            build_client_hello_buffer_pool(&config, None, &client_hello_buffer_stats);
+        let transfer_stats = config
+            .detailed_transfer_metrics
+            .as_ref()
+            .map(|c| c.build_stats());
 
         let server = OpensslProxyServer::new(
             config,
             server_stats,
             listen_stats,
+            conn_limiter,
             Arc::new(hosts),
             tls_rolling_ticketer,
+            Arc::new(CertResolverStats::default()),
+            handshake_limiter,
+            handshake_limit_stats,
+            client_ip_limiter,
+            client_ip_limit_stats,
+            client_hello_buffer_pool,
+            client_hello_buffer_stats,
+            cert_reload_stats,
+            backend_pool_stats,
+            host_health_stats,
+            accept_reject_stats,
+            transfer_stats,
             1,
         )?;
         Ok(Arc::new(server))
@@ -120,6 +283,9 @@ impl OpensslProxyServer {
             let config = Arc::new(config);
             let server_stats = Arc::clone(&self.server_stats);
             let listen_stats = Arc::clone(&self.listen_stats);
+            let conn_limiter = self
+                .conn_limiter
+                .new_for_reload(config.max_connections, config.accept_rate_limit.as_ref());
 
             let tls_rolling_ticketer = if self.config.tls_ticketer.eq(&config.tls_ticketer) {
                 self.tls_rolling_ticketer.clone()
@@ -137,21 +303,72 @@ impl OpensslProxyServer {
             let mut new_hosts_map = AHashMap::with_capacity(new_conf_map.len());
             for (name, conf) in new_conf_map {
                 let host = if let Some(old_host) = old_hosts_map.get(&name) {
-                    old_host.new_for_reload(conf, &tls_rolling_ticketer)?
+                    old_host.new_for_reload(
+                        conf,
+                        &tls_rolling_ticketer,
+                        &self.cert_reload_stats,
+                        &self.backend_pool_stats,
+                        &self.host_health_stats,
+                    )?
                 } else {
-                    OpensslHost::try_build(&conf, &tls_rolling_ticketer)?
+                    OpensslHost::try_build(
+                        &conf,
+                        &tls_rolling_ticketer,
+                        &self.cert_reload_stats,
+                        &self.backend_pool_stats,
+                        &self.host_health_stats,
+                    )?
                 };
                 new_hosts_map.insert(name, Arc::new(host));
             }
 
             let hosts = config.hosts.build_from(new_hosts_map);
 
+            let handshake_limiter = build_handshake_limiter(
+                &config,
+                self.handshake_limiter.as_ref(),
+                &self.handshake_limit_stats,
+            );
+            let client_ip_limiter = build_client_ip_limiter(
+                &config,
+                self.client_ip_limiter.as_ref(),
+                &self.client_ip_limit_stats,
+            );
+            let client_hello_buffer_pool = build_client_hello_buffer_pool(
+                &config,
+                self.client_hello_buffer_pool.as_ref(),
+                &self.client_hello_buffer_stats,
+            );
+
+            let transfer_stats =
+                if self.config.detailed_transfer_metrics == config.detailed_transfer_metrics {
+                    self.transfer_stats.clone()
+                } else {
+                    config
+                        .detailed_transfer_metrics
+                        .as_ref()
+                        .map(|c| c.build_stats())
+                };
+
             OpensslProxyServer::new(
                 config,
                 server_stats,
                 listen_stats,
+                conn_limiter,
                 Arc::new(hosts),
                 tls_rolling_ticketer,
+                self.cert_resolver_stats.clone(),
+                handshake_limiter,
+                self.handshake_limit_stats.clone(),
+                client_ip_limiter,
+                self.client_ip_limit_stats.clone(),
+                client_hello_buffer_pool,
+                self.client_hello_buffer_stats.clone(),
+                self.cert_reload_stats.clone(),
+                self.backend_pool_stats.clone(),
+                self.host_health_stats.clone(),
+                self.accept_reject_stats.clone(),
+                transfer_stats,
                 self.reload_version + 1,
             )
         } else {
@@ -163,46 +380,120 @@ impl OpensslProxyServer {
         }
     }
 
-    fn drop_early(&self, client_addr: SocketAddr) -> bool {
+    fn drop_early(&self, cc_info: &ClientConnectionInfo) -> bool {
         if let Some(ingress_net_filter) = &self.ingress_net_filter {
-            let (_, action) = ingress_net_filter.check(client_addr.ip());
+            let (_, action) = ingress_net_filter.check(cc_info.client_ip());
             match action {
                 AclAction::Permit | AclAction::PermitAndLog => {}
                 AclAction::Forbid | AclAction::ForbidAndLog => {
                     self.listen_stats.add_dropped();
+                    self.accept_recorder
+                        .record(AcceptRejectReason::IngressFiltered, cc_info, None);
                     return true;
                 }
             }
         }
 
-        // TODO add cps limit
-
         false
     }
 
-    async fn run_task(&self, stream: TcpStream, cc_info: ClientConnectionInfo) {
+    async fn run_task(
+        &self,
+        stream: TcpStream,
+        cc_info: ClientConnectionInfo,
+        ingress_proxy_tlvs: IngressProxyTlvs,
+    ) {
         let ctx = CommonTaskContext {
             server_config: self.config.clone(),
             server_stats: self.server_stats.clone(),
             server_quit_policy: self.quit_policy.clone(),
             idle_wheel: self.idle_wheel.clone(),
+            idle_overrides: self.idle_overrides.clone(),
             cc_info,
             task_logger: self.task_logger.clone(),
+            accept_recorder: self.accept_recorder.clone(),
+            ingress_proxy_tlvs,
+            transfer_stats: self.transfer_stats.clone(),
         };
 
         if self.config.spawn_task_unconstrained {
             tokio::task::unconstrained(
-                OpensslAcceptTask::new(ctx, self.hosts.clone()).into_running(stream),
+                OpensslAcceptTask::new(
+                    ctx,
+                    self.hosts.clone(),
+                    self.cert_resolver.clone(),
+                    self.handshake_limiter.clone(),
+                    self.client_ip_limiter.clone(),
+                    self.client_hello_buffer_pool.clone(),
+                )
+                .into_running(stream),
             )
             .await
         } else {
-            OpensslAcceptTask::new(ctx, self.hosts.clone())
-                .into_running(stream)
-                .await;
+            OpensslAcceptTask::new(
+                ctx,
+                self.hosts.clone(),
+                self.cert_resolver.clone(),
+                self.handshake_limiter.clone(),
+                self.client_ip_limiter.clone(),
+                self.client_hello_buffer_pool.clone(),
+            )
+            .into_running(stream)
+            .await;
         }
     }
 }
 
+fn build_handshake_limiter(
+    config: &OpensslProxyServerConfig,
+    old: Option<&Arc<OpensslHandshakeLimiter>>,
+    stats: &Arc<HandshakeLimitStats>,
+) -> Option<Arc<OpensslHandshakeLimiter>> {
+    let max_concurrent = config.max_concurrent_handshakes();
+    if max_concurrent == 0 {
+        return None;
+    }
+    let max_queued = config.handshake_queue_length;
+    let limiter = match old {
+        Some(old) => old.new_for_reload(max_concurrent, max_queued),
+        None => Arc::new(OpensslHandshakeLimiter::new(
+            max_concurrent,
+            max_queued,
+            stats.clone(),
+        )),
+    };
+    Some(limiter)
+}
+
+fn build_client_ip_limiter(
+    config: &OpensslProxyServerConfig,
+    old: Option<&Arc<OpensslClientIpLimiter>>,
+    stats: &Arc<ClientIpLimitStats>,
+) -> Option<Arc<OpensslClientIpLimiter>> {
+    let limit_config = config.client_ip_limit.as_ref()?;
+    let limiter = match old {
+        Some(old) => old.new_for_reload(limit_config),
+        None => Arc::new(OpensslClientIpLimiter::new(limit_config, stats.clone())),
+    };
+    Some(limiter)
+}
+
+fn build_client_hello_buffer_pool(
+    config: &OpensslProxyServerConfig,
+    old: Option<&Arc<OpensslClientHelloBufferPool>>,
+    stats: &Arc<ClientHelloBufferStats>,
+) -> Option<Arc<OpensslClientHelloBufferPool>> {
+    let pool_config = config.client_hello_buffer_pool.as_ref()?;
+    let pool = match old {
+        Some(old) => old.new_for_reload(pool_config),
+        None => Arc::new(OpensslClientHelloBufferPool::new(
+            pool_config,
+            stats.clone(),
+        )),
+    };
+    Some(pool)
+}
+
 impl ServerInternal for OpensslProxyServer {
     fn _clone_config(&self) -> AnyServerConfig {
         AnyServerConfig::OpensslProxy(self.config.as_ref().clone())
@@ -240,7 +531,8 @@ impl ServerInternal for OpensslProxyServer {
 
     fn _start_runtime(&self, server: ArcServer) -> anyhow::Result<()> {
         let listen_stats = server.get_listen_stats();
-        let runtime = ListenTcpRuntime::new(WrapArcServer(server), listen_stats);
+        let runtime = ListenTcpRuntime::new(WrapArcServer(server), listen_stats)
+            .with_worker_config(&self.config.listen_worker);
         runtime
             .run_all_instances(
                 &self.config.listen,
@@ -275,14 +567,57 @@ impl BaseServer for OpensslProxyServer {
 
 #[async_trait]
 impl AcceptTcpServer for OpensslProxyServer {
-    async fn run_tcp_task(&self, stream: TcpStream, cc_info: ClientConnectionInfo) {
+    async fn run_tcp_task(&self, mut stream: TcpStream, mut cc_info: ClientConnectionInfo) {
+        let mut ingress_proxy_tlvs = IngressProxyTlvs::default();
+        if let Some(protocol) = self.config.ingress_proxy_protocol {
+            match super::read_ingress_proxy_protocol(
+                &mut stream,
+                protocol,
+                self.config.ingress_proxy_protocol_read_timeout,
+            )
+            .await
+            {
+                Ok((addr, tlvs)) => {
+                    if let Some(addr) = addr {
+                        cc_info.set_proxy_addr(addr);
+                    }
+                    ingress_proxy_tlvs = tlvs;
+                }
+                Err(e) => {
+                    self.listen_stats.add_by_proxy_protocol_error(e);
+                    return;
+                }
+            }
+        }
+
+        // the real client address is only known after the PROXY protocol header is read
+        if g3_daemon::ban::is_banned(cc_info.client_ip()) {
+            self.listen_stats.add_dropped();
+            return;
+        }
         let client_addr = cc_info.client_addr();
         self.server_stats.add_conn(client_addr);
-        if self.drop_early(client_addr) {
+        if self.drop_early(&cc_info) {
             return;
         }
+        if let Some(limiter) = &self.client_ip_limiter {
+            // reject before allocating any buffer for the ClientHello message
+            if !limiter.check_new_connection(cc_info.client_ip()) {
+                if limiter.send_alert() {
+                    let _ = stream.try_write(TLS_ALERT_INTERNAL_ERROR);
+                }
+                return;
+            }
+        }
+        let Ok(_conn_guard) = self.conn_limiter.try_acquire() else {
+            if self.config.alert_conn_limited {
+                // the alert is sent only if it can be written out at once
+                let _ = stream.try_write(TLS_ALERT_INTERNAL_ERROR);
+            }
+            return;
+        };
 
-        self.run_task(stream, cc_info).await
+        self.run_task(stream, cc_info, ingress_proxy_tlvs).await
     }
 }
 
@@ -319,4 +654,28 @@ impl Server for OpensslProxyServer {
             }
         }
     }
+
+    async fn loopback_check(&self, sni: Option<&str>) -> anyhow::Result<ServerCheckReport> {
+        let (stream, connect_time) =
+            loopback_connect(&self.listen_stats, LOOPBACK_CHECK_TIMEOUT).await?;
+        let mut report = ServerCheckReport::new(connect_time);
+        if let Some(sni) = sni {
+            let handshake_time = loopback_tls_handshake(
+                stream,
+                self.config.ingress_proxy_protocol,
+                sni,
+                LOOPBACK_CHECK_TIMEOUT,
+            )
+            .await?;
+            report.handshake_time = Some(handshake_time);
+        }
+        Ok(report)
+    }
+
+    fn rotate_tls_ticket_key(&self) -> anyhow::Result<()> {
+        let Some(ticketer) = &self.tls_rolling_ticketer else {
+            return Err(anyhow!("tls ticketer is not enabled for this server"));
+        };
+        ticketer.force_rotate()
+    }
 }
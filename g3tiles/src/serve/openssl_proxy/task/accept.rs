@@ -3,48 +3,134 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::io;
+use std::net::IpAddr;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use anyhow::anyhow;
 use bytes::BytesMut;
 use log::debug;
 use openssl::error::ErrorStack;
-use openssl::ssl::{Ssl, SslContext};
+use openssl::ssl::{Ssl, SslContext, SslRef};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::time::Instant;
 
+use g3_daemon::server::AcceptRejectReason;
 use g3_daemon::stat::task::TcpStreamConnectionStats;
 use g3_dpi::parser::tls::{
-    ClientHello, ExtensionType, HandshakeCoalescer, RawVersion, Record, RecordParseError,
+    ClientHello, ExtensionType, HandshakeCoalesceError, HandshakeCollectError, HandshakeCollector,
+    PartialClientHelloExt, RawVersion,
 };
 use g3_io_ext::{LimitedStream, OnceBufReader};
-use g3_openssl::{SslAcceptor, SslStream};
+use g3_openssl::{SslAcceptor, SslHandshakeError};
+use g3_types::collection::NamedValue;
 use g3_types::limit::GaugeSemaphorePermit;
 use g3_types::net::{Host, TlsServerName};
 use g3_types::route::HostMatch;
 
-use super::{CommonTaskContext, OpensslRelayTask};
+use super::{CommonTaskContext, OpensslEarlyData, OpensslEarlyDataStatus, OpensslRelayTask};
+use crate::backend::ArcBackend;
+#[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+use crate::config::server::openssl_proxy::OpensslSessionSniCheck;
+use crate::config::server::openssl_proxy::{
+    OpensslCertKeyType, OpensslUnhealthyAction, SniIpLiteralPolicy, StrictSniAction,
+};
 use crate::module::stream::StreamAcceptTaskCltWrapperStats;
-use crate::serve::openssl_proxy::OpensslHost;
+use crate::serve::openssl_proxy::{
+    ClientHelloBuffer, OpensslCertResolver, OpensslClientHelloBufferPool, OpensslClientIpLimiter,
+    OpensslHandshakeLimiter, OpensslHandshakePermit, OpensslHost, TLS_ALERT_INTERNAL_ERROR,
+    first_psk_identity,
+};
+
+type SelectedHost = (Arc<OpensslHost>, Option<SslContext>);
+
+struct ClientHelloInfo {
+    legacy_version: RawVersion,
+    sni: Option<TlsServerName>,
+    /// the ticket used to resume the session, only set if early data is sent along with it
+    early_data_ticket: Option<Vec<u8>>,
+    /// the result of the host selection that is done once the server name is received
+    selected_host: Option<Result<SelectedHost, AcceptError>>,
+}
+
+/// The error that makes the connection be rejected in the accept phase
+struct AcceptError {
+    reason: AcceptRejectReason,
+    error: anyhow::Error,
+    /// the classification of the failed TLS handshake
+    handshake_error: Option<SslHandshakeError>,
+}
+
+impl AcceptError {
+    fn new(reason: AcceptRejectReason, error: anyhow::Error) -> Self {
+        AcceptError {
+            reason,
+            error,
+            handshake_error: None,
+        }
+    }
+
+    fn client_hello_invalid(error: anyhow::Error) -> Self {
+        AcceptError::new(AcceptRejectReason::ClientHelloInvalid, error)
+    }
+
+    fn handshake_failed(error: anyhow::Error) -> Self {
+        AcceptError::new(AcceptRejectReason::HandshakeFailed, error)
+    }
+
+    fn host_unhealthy(host: &OpensslHost) -> Self {
+        AcceptError::new(
+            AcceptRejectReason::HostUnhealthy,
+            anyhow!("host {} is unhealthy", host.name()),
+        )
+    }
+
+    fn handshake_error(e: io::Error) -> Self {
+        let handshake_error = SslHandshakeError::from_io_error(&e);
+        AcceptError {
+            reason: AcceptRejectReason::HandshakeFailed,
+            error: anyhow!("failed to accept ssl handshake: {e}"),
+            handshake_error: Some(handshake_error),
+        }
+    }
+}
 
 pub(crate) struct OpensslAcceptTask {
     ctx: CommonTaskContext,
     hosts: Arc<HostMatch<Arc<OpensslHost>>>,
+    cert_resolver: Option<Arc<OpensslCertResolver>>,
+    handshake_limiter: Option<Arc<OpensslHandshakeLimiter>>,
+    client_ip_limiter: Option<Arc<OpensslClientIpLimiter>>,
+    client_hello_buffer_pool: Option<Arc<OpensslClientHelloBufferPool>>,
     alive_permit: Option<GaugeSemaphorePermit>,
+    time_accepted: Instant,
 }
 
 impl OpensslAcceptTask {
-    pub(crate) fn new(ctx: CommonTaskContext, hosts: Arc<HostMatch<Arc<OpensslHost>>>) -> Self {
+    pub(crate) fn new(
+        ctx: CommonTaskContext,
+        hosts: Arc<HostMatch<Arc<OpensslHost>>>,
+        cert_resolver: Option<Arc<OpensslCertResolver>>,
+        handshake_limiter: Option<Arc<OpensslHandshakeLimiter>>,
+        client_ip_limiter: Option<Arc<OpensslClientIpLimiter>>,
+        client_hello_buffer_pool: Option<Arc<OpensslClientHelloBufferPool>>,
+    ) -> Self {
         OpensslAcceptTask {
             ctx,
             hosts,
+            cert_resolver,
+            handshake_limiter,
+            client_ip_limiter,
+            client_hello_buffer_pool,
             alive_permit: None,
+            time_accepted: Instant::now(),
         }
     }
 
     pub(crate) async fn into_running(mut self, stream: TcpStream) {
-        let time_accepted = Instant::now();
+        let time_accepted = self.time_accepted;
 
         let pre_handshake_stats = Arc::new(TcpStreamConnectionStats::default());
         let wrapper_stats =
@@ -59,32 +145,142 @@ impl OpensslAcceptTask {
             Arc::new(wrapper_stats),
         );
 
-        let mut clt_r_buf = BytesMut::with_capacity(2048);
-        match self.read_client_hello(&mut stream, &mut clt_r_buf).await {
-            Ok((legacy_version, host)) => {
-                let mut ssl_stream = match self
-                    .handshake(&host, legacy_version, OnceBufReader::new(stream, clt_r_buf))
+        let Some(mut clt_r_buf) = self.get_client_hello_buffer().await else {
+            self.reject(
+                AcceptError::new(
+                    AcceptRejectReason::HandshakeLimited,
+                    anyhow!("no free buffer to recv client hello message"),
+                ),
+                None,
+            );
+            return;
+        };
+        match self
+            .read_client_hello(&mut stream, clt_r_buf.buf_mut())
+            .await
+        {
+            Ok(mut ch_info) => {
+                let selected_host = match ch_info.selected_host.take() {
+                    Some(r) => r,
+                    None => self.select_host(ch_info.sni.as_ref()).await,
+                };
+                let sni = ch_info.sni.as_ref();
+                let (host, resolved_context) = match selected_host {
+                    Ok(v) => v,
+                    Err(e) => {
+                        self.reject(e, sni);
+                        return;
+                    }
+                };
+
+                let unhealthy_action = host.unhealthy_action();
+                if unhealthy_action == Some(OpensslUnhealthyAction::Reject) {
+                    self.reject(AcceptError::host_unhealthy(&host), sni);
+                    let _ = stream.write_all(TLS_ALERT_INTERNAL_ERROR).await;
+                    return;
+                }
+
+                let (acceptor, early_data, handshake_permit) = match self
+                    .start_handshake(
+                        &host,
+                        sni,
+                        ch_info.legacy_version,
+                        ch_info.early_data_ticket,
+                        resolved_context,
+                        OnceBufReader::with_bytes(stream, clt_r_buf.take_data()),
+                    )
                     .await
                 {
+                    Ok(v) => v,
+                    Err(e) => {
+                        self.reject(e, sni);
+                        return;
+                    }
+                };
+
+                if unhealthy_action.is_none()
+                    && self.release_early_data_in_handshake(&host, &early_data)
+                {
+                    if let Some(backend) = self.select_backend(&host, acceptor.ssl()) {
+                        let served_cert = self.check_served_ssl(acceptor.ssl());
+                        OpensslRelayTask::new(
+                            self.ctx,
+                            host,
+                            backend,
+                            served_cert,
+                            None,
+                            early_data,
+                            time_accepted.elapsed(),
+                            pre_handshake_stats,
+                            self.alive_permit,
+                        )
+                        .into_running_with_early_data(acceptor, handshake_permit)
+                        .await;
+                        return;
+                    }
+                }
+
+                let accept_result = acceptor.accept().await;
+                drop(handshake_permit);
+                let mut ssl_stream = match accept_result {
                     Ok(stream) => stream,
                     Err(e) => {
-                        debug!("handshake with client failed: {e}");
+                        self.reject(AcceptError::handshake_error(e), sni);
                         return;
                     }
                 };
 
-                if ssl_stream.ssl().session_reused() {
-                    // Quick ACK is needed with session resumption
-                    self.ctx.cc_info.tcp_sock_try_quick_ack();
+                if unhealthy_action.is_some() {
+                    self.reject(AcceptError::host_unhealthy(&host), sni);
+                    // the close_notify alert will be sent right after the handshake
+                    let _ = ssl_stream.shutdown().await;
+                    return;
                 }
 
-                let backend = if let Some(alpn) = ssl_stream.ssl().selected_alpn_protocol() {
-                    let protocol = unsafe { std::str::from_utf8_unchecked(alpn) };
-                    host.get_backend(protocol)
-                } else {
-                    host.get_default_backend()
+                let served_cert = self.check_served_ssl(ssl_stream.ssl());
+
+                let mut client_cert_rule = None;
+                let mut backend = None;
+                if let Some(router) = &host.config.client_cert_router {
+                    let selected = ssl_stream
+                        .ssl()
+                        .peer_certificate()
+                        .and_then(|cert| host.select_client_cert_backend(&cert));
+                    if let Some((rule, b)) = selected {
+                        self.ctx
+                            .server_stats
+                            .add_client_cert_route(host.name(), &rule);
+                        client_cert_rule = Some(rule);
+                        backend = Some(b);
+                    } else if router.require_match {
+                        // resumed sessions skip the certificate verify callback
+                        self.reject(
+                            AcceptError::new(
+                                AcceptRejectReason::NoBackend,
+                                anyhow!(
+                                    "no client cert route rule matched for host {}",
+                                    host.name()
+                                ),
+                            ),
+                            sni,
+                        );
+                        let _ = ssl_stream.shutdown().await;
+                        return;
+                    }
+                }
+
+                let backend = match backend {
+                    Some(backend) => Some(backend),
+                    None => self.select_backend(&host, ssl_stream.ssl()),
                 };
                 let Some(backend) = backend else {
+                    self.reject(
+                        AcceptError::new(
+                            AcceptRejectReason::NoBackend,
+                            anyhow!("no backend found for host {}", host.name()),
+                        ),
+                        sni,
+                    );
                     let _ = ssl_stream.shutdown().await;
                     return;
                 };
@@ -93,6 +289,9 @@ impl OpensslAcceptTask {
                     self.ctx,
                     host,
                     backend,
+                    served_cert,
+                    client_cert_rule,
+                    early_data,
                     time_accepted.elapsed(),
                     pre_handshake_stats,
                     self.alive_permit,
@@ -101,137 +300,487 @@ impl OpensslAcceptTask {
                 .await;
             }
             Err(e) => {
-                debug!("dropped connection: {e}")
+                if e.reason == AcceptRejectReason::ClientHelloTimeout {
+                    if let Some(limiter) = &self.client_ip_limiter {
+                        limiter.add_client_hello_timeout(self.ctx.cc_info.client_ip());
+                    }
+                }
+                self.reject(e, None)
             }
         };
     }
 
-    async fn read_client_hello<R>(
-        &mut self,
-        clt_r: &mut R,
-        clt_r_buf: &mut BytesMut,
-    ) -> anyhow::Result<(RawVersion, Arc<OpensslHost>)>
-    where
-        R: AsyncRead + Unpin,
-    {
-        tokio::time::timeout(
-            self.ctx.server_config.client_hello_recv_timeout,
-            self.do_read_client_hello(clt_r, clt_r_buf),
-        )
-        .await
-        .map_err(|_| anyhow!("timed out to recv client hello message"))?
+    fn reject(&self, e: AcceptError, sni: Option<&TlsServerName>) {
+        debug!("dropped connection: {}: {}", e.reason.as_str(), e.error);
+        let sni = sni.map(|name| name.as_ref());
+        match &e.handshake_error {
+            Some(handshake_error) => self.ctx.accept_recorder.record_handshake_error(
+                handshake_error,
+                &self.ctx.cc_info,
+                sni,
+            ),
+            None => self
+                .ctx
+                .accept_recorder
+                .record(e.reason, &self.ctx.cc_info, sni),
+        }
+    }
+
+    /// Count the served certificate and get the key type of it
+    fn check_served_ssl(&self, ssl: &SslRef) -> Option<OpensslCertKeyType> {
+        let served_cert = ssl
+            .certificate()
+            .and_then(|cert| cert.public_key().ok())
+            .map(|key| OpensslCertKeyType::from_id(key.id()));
+        if let Some(key_type) = served_cert {
+            self.ctx.server_stats.add_served_cert(key_type);
+        }
+
+        if ssl.session_reused() {
+            // Quick ACK is needed with session resumption
+            self.ctx.cc_info.tcp_sock_try_quick_ack();
+        }
+        served_cert
+    }
+
+    fn select_backend(&self, host: &OpensslHost, ssl: &SslRef) -> Option<ArcBackend> {
+        if let Some(alpn) = ssl.selected_alpn_protocol() {
+            let protocol = unsafe { std::str::from_utf8_unchecked(alpn) };
+            host.get_backend(protocol)
+        } else if let Some(protocol) = &self.ctx.ingress_proxy_tlvs.alpn {
+            host.get_backend(protocol)
+        } else {
+            host.get_default_backend()
+        }
+    }
+
+    /// The accepted early data can be sent to the backend before the handshake completes
+    /// only if the backend is idempotent, and the backend is not selected by the client cert.
+    fn release_early_data_in_handshake(
+        &self,
+        host: &OpensslHost,
+        early_data: &Option<OpensslEarlyData>,
+    ) -> bool {
+        let Some(early_data) = early_data else {
+            return false;
+        };
+        if early_data.status() != OpensslEarlyDataStatus::Accepted {
+            return false;
+        }
+        if host.config.client_cert_router.is_some() {
+            return false;
+        }
+        host.config
+            .early_data
+            .as_ref()
+            .map(|c| c.idempotent_backend)
+            .unwrap_or(false)
+    }
+
+    async fn get_client_hello_buffer(&self) -> Option<ClientHelloBuffer> {
+        match &self.client_hello_buffer_pool {
+            Some(pool) => {
+                pool.acquire(self.ctx.server_config.client_hello_recv_timeout)
+                    .await
+            }
+            None => Some(ClientHelloBuffer::transient(2048)),
+        }
     }
 
-    async fn do_read_client_hello<R>(
+    async fn read_client_hello<R>(
         &mut self,
         clt_r: &mut R,
         clt_r_buf: &mut BytesMut,
-    ) -> anyhow::Result<(RawVersion, Arc<OpensslHost>)>
+    ) -> Result<ClientHelloInfo, AcceptError>
     where
         R: AsyncRead + Unpin,
     {
-        let mut handshake_coalescer =
-            HandshakeCoalescer::new(self.ctx.server_config.client_hello_max_size);
-        let mut record_offset = 0;
+        let mut collector = HandshakeCollector::new(self.ctx.server_config.client_hello_max_size);
+        let mut deadline = Instant::now() + self.ctx.server_config.client_hello_recv_timeout;
+        let mut selected_host = None;
         loop {
-            let mut record = match Record::parse(&clt_r_buf[record_offset..]) {
-                Ok(r) => r,
-                Err(RecordParseError::NeedMoreData(_)) => match clt_r.read_buf(clt_r_buf).await {
-                    Ok(0) => return Err(anyhow!("connection closed by client")),
-                    Ok(_) => continue,
-                    Err(e) => return Err(anyhow!("client read error: {e}")),
-                },
+            // The Client Hello Message MUST be the first Handshake message
+            match collector.feed(clt_r_buf) {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(HandshakeCollectError::CoalesceFailed(
+                    HandshakeCoalesceError::TooLargeMessageSize(size),
+                )) => {
+                    return Err(AcceptError::new(
+                        AcceptRejectReason::ClientHelloTooLarge,
+                        anyhow!("too large tls client hello message size {size}"),
+                    ));
+                }
                 Err(_) => {
-                    return Err(anyhow!("invalid tls client hello request"));
+                    return Err(AcceptError::client_hello_invalid(anyhow!(
+                        "invalid tls client hello request"
+                    )));
                 }
-            };
-            record_offset += record.encoded_len();
+            }
 
-            // The Client Hello Message MUST be the first Handshake message
-            match record.consume_handshake(&mut handshake_coalescer) {
-                Ok(Some(handshake_msg)) => {
-                    let ch = handshake_msg
-                        .parse_client_hello()
-                        .map_err(|_| anyhow!("invalid tls client hello request"))?;
-                    return self.parse_sni(ch);
-                }
-                Ok(None) => match handshake_coalescer.parse_client_hello() {
-                    Ok(Some(ch)) => return self.parse_sni(ch),
-                    Ok(None) => {
-                        if !record.consume_done() {
-                            return Err(anyhow!("partial fragmented tls client hello request"));
-                        }
+            if selected_host.is_none() {
+                match collector
+                    .coalescer()
+                    .peek_client_hello_ext(ExtensionType::ServerName)
+                {
+                    Ok(PartialClientHelloExt::Found(data)) => {
+                        let sni = TlsServerName::from_extension_value(data).map_err(|_| {
+                            AcceptError::client_hello_invalid(anyhow!(
+                                "invalid server name in tls client hello message"
+                            ))
+                        })?;
+                        let sni = self.check_sni(sni)?;
+                        // select the host while the rest of the message is still on the way,
+                        // the time spent on it should not be counted in the recv timeout
+                        let select_start = Instant::now();
+                        selected_host = Some(self.select_host(sni.as_ref()).await);
+                        deadline += select_start.elapsed();
                     }
+                    Ok(_) => {}
                     Err(_) => {
-                        return Err(anyhow!("invalid fragmented tls client hello request"));
+                        return Err(AcceptError::client_hello_invalid(anyhow!(
+                            "invalid tls client hello request"
+                        )));
                     }
-                },
+                }
+            }
+
+            match tokio::time::timeout_at(deadline, clt_r.read_buf(clt_r_buf)).await {
+                Ok(Ok(0)) => {
+                    return Err(AcceptError::new(
+                        AcceptRejectReason::ClientClosed,
+                        anyhow!("connection closed by client"),
+                    ));
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => {
+                    return Err(AcceptError::new(
+                        AcceptRejectReason::ClientClosed,
+                        anyhow!("client read error: {e}"),
+                    ));
+                }
                 Err(_) => {
-                    return Err(anyhow!("invalid tls client hello request"));
+                    return Err(AcceptError::new(
+                        AcceptRejectReason::ClientHelloTimeout,
+                        anyhow!("timed out to recv client hello message"),
+                    ));
                 }
             }
         }
+
+        let ch = match collector.coalescer().parse_client_hello() {
+            Ok(Some(ch)) => ch,
+            _ => {
+                return Err(AcceptError::client_hello_invalid(anyhow!(
+                    "invalid tls client hello request"
+                )));
+            }
+        };
+        let mut ch_info = self
+            .parse_client_hello(ch)
+            .map_err(AcceptError::client_hello_invalid)?;
+        if let Some(sni) = ch_info.sni.take() {
+            ch_info.sni = self.check_sni(sni)?;
+        }
+        ch_info.selected_host = selected_host;
+        Ok(ch_info)
+    }
+
+    /// Validate and normalize the server name if strict SNI is enabled.
+    ///
+    /// None will be returned if the invalid server name should be ignored.
+    fn check_sni(&self, sni: TlsServerName) -> Result<Option<TlsServerName>, AcceptError> {
+        let Some(action) = self.ctx.server_config.strict_sni else {
+            return Ok(Some(sni));
+        };
+        match sni.normalize() {
+            Ok(name) => Ok(Some(name)),
+            Err(e) => match action {
+                StrictSniAction::Reject => Err(AcceptError::client_hello_invalid(anyhow!(
+                    "invalid server name {:?} in tls client hello message: {e}",
+                    sni.as_ref()
+                ))),
+                StrictSniAction::Ignore => {
+                    debug!("ignored invalid server name {:?}: {e}", sni.as_ref());
+                    Ok(None)
+                }
+            },
+        }
     }
 
-    fn parse_sni(&mut self, ch: ClientHello<'_>) -> anyhow::Result<(RawVersion, Arc<OpensslHost>)> {
-        match ch.get_ext(ExtensionType::ServerName) {
+    fn select_ip_literal_host(&self, ip: IpAddr) -> Result<SelectedHost, AcceptError> {
+        if self.ctx.server_config.sni_ip_literal == SniIpLiteralPolicy::Match {
+            if let Some(matched) = self.hosts.get_matched(&Host::Ip(ip)) {
+                return Ok((matched.clone(), None));
+            }
+        }
+        if self.ctx.server_config.sni_ip_literal != SniIpLiteralPolicy::Reject {
+            if let Some(default) = self.hosts.get_default() {
+                return Ok((default.clone(), None));
+            }
+        }
+        Err(AcceptError::new(
+            AcceptRejectReason::NoHostMatched,
+            anyhow!("no host found for ip literal server name {ip}"),
+        ))
+    }
+
+    fn parse_client_hello(&mut self, ch: ClientHello<'_>) -> anyhow::Result<ClientHelloInfo> {
+        let sni = match ch.get_ext(ExtensionType::ServerName) {
             Ok(Some(data)) => {
                 let sni = TlsServerName::from_extension_value(data)
                     .map_err(|_| anyhow!("invalid server name in tls client hello message"))?;
-                let host = Host::from(sni);
-                let Some(host) = self.hosts.get(&host) else {
-                    return Err(anyhow!("no tls config found for server named {host}"));
-                };
-                Ok((ch.legacy_version, host.clone()))
+                Some(sni)
+            }
+            Ok(None) => None,
+            Err(_) => return Err(anyhow!("invalid extension in tls client hello request")),
+        };
+
+        let mut early_data_ticket = None;
+        if let Ok(Some(_)) = ch.get_ext(ExtensionType::EarlyData) {
+            if let Ok(Some(data)) = ch.get_ext(ExtensionType::PreSharedKey) {
+                early_data_ticket = first_psk_identity(data).map(|v| v.to_vec());
+            }
+        }
+
+        Ok(ClientHelloInfo {
+            legacy_version: ch.legacy_version,
+            sni,
+            early_data_ticket,
+            selected_host: None,
+        })
+    }
+
+    async fn select_host(&self, sni: Option<&TlsServerName>) -> Result<SelectedHost, AcceptError> {
+        let Some(sni) = sni else {
+            // use the authority conveyed by the PROXY protocol if no SNI
+            if let Some(authority) = &self.ctx.ingress_proxy_tlvs.authority {
+                if let Ok(host) = Host::from_str(authority) {
+                    if let Some(matched) = self.hosts.get_matched(&host) {
+                        return Ok((matched.clone(), None));
+                    }
+                }
+            }
+            return match self.hosts.get_default() {
+                Some(host) => Ok((host.clone(), None)),
+                None => Err(AcceptError::new(
+                    AcceptRejectReason::NoHostMatched,
+                    anyhow!("no server name in client hello message"),
+                )),
+            };
+        };
+
+        if self.ctx.server_config.strict_sni.is_some() {
+            if let Some(ip) = sni.ip_literal() {
+                return self.select_ip_literal_host(ip);
             }
-            Ok(None) => match self.hosts.get_default() {
-                Some(host) => Ok((ch.legacy_version, host.clone())),
-                None => Err(anyhow!("no server name in client hello message")),
-            },
-            Err(_) => Err(anyhow!("invalid extension in tls client hello request")),
+        }
+
+        let host = Host::from(sni);
+        if let Some(matched) = self.hosts.get_matched(&host) {
+            return Ok((matched.clone(), None));
+        }
+
+        if let Some(resolver) = &self.cert_resolver {
+            // only wait for the time left in the accept timeout
+            let timeout = self
+                .ctx
+                .server_config
+                .accept_timeout
+                .saturating_sub(self.time_accepted.elapsed());
+            if let Some(ssl_context) = resolver.resolve(sni.as_ref(), timeout).await {
+                return Ok((resolver.host().clone(), Some(ssl_context)));
+            }
+        }
+
+        match self.hosts.get_default() {
+            Some(default) => Ok((default.clone(), None)),
+            None => Err(AcceptError::new(
+                AcceptRejectReason::NoHostMatched,
+                anyhow!("no tls config found for server named {host}"),
+            )),
         }
     }
 
-    async fn handshake<S>(
+    /// Start the handshake, and read the early data if it's sent by the client.
+    ///
+    /// The handshake permit should be held until the handshake completes.
+    async fn start_handshake<S>(
         &mut self,
         host: &OpensslHost,
+        #[cfg_attr(
+            any(feature = "vendored-boringssl", feature = "vendored-aws-lc"),
+            allow(unused_variables)
+        )]
+        sni: Option<&TlsServerName>,
         legacy_version: RawVersion,
+        early_data_ticket: Option<Vec<u8>>,
+        resolved_context: Option<SslContext>,
         stream: S,
-    ) -> anyhow::Result<SslStream<S>>
+    ) -> Result<
+        (
+            SslAcceptor<S>,
+            Option<OpensslEarlyData>,
+            Option<OpensslHandshakePermit>,
+        ),
+        AcceptError,
+    >
     where
         S: AsyncRead + AsyncWrite + Unpin,
     {
-        host.check_rate_limit()
-            .map_err(|_| anyhow!("host level rate limit reached"))?;
-        self.alive_permit = host
-            .acquire_request_semaphore()
-            .map_err(|_| anyhow!("host level alive limit reached"))?;
+        host.check_rate_limit().map_err(|_| {
+            AcceptError::new(
+                AcceptRejectReason::HostLimited,
+                anyhow!("host level rate limit reached"),
+            )
+        })?;
+        self.alive_permit = host.acquire_request_semaphore().map_err(|_| {
+            AcceptError::new(
+                AcceptRejectReason::HostLimited,
+                anyhow!("host level alive limit reached"),
+            )
+        })?;
 
+        let host_context;
         let ssl_context = if legacy_version.is_tlcp() {
             #[cfg(not(feature = "vendored-tongsuo"))]
-            return Err(anyhow!("tlcp protocol is not supported"));
+            return Err(AcceptError::handshake_failed(anyhow!(
+                "tlcp protocol is not supported"
+            )));
             #[cfg(feature = "vendored-tongsuo")]
             host.tlcp_context.as_ref()
+        } else if resolved_context.is_some() {
+            resolved_context.as_ref()
         } else {
-            host.ssl_context.as_ref()
+            // the context may be replaced when the cert files are reloaded
+            host_context = host.ssl_context();
+            host_context.as_deref()
         };
         let Some(ssl_context) = ssl_context else {
-            return Err(anyhow!(
+            return Err(AcceptError::handshake_failed(anyhow!(
                 "no supported tls context for legacy protocol {:?}",
                 legacy_version
-            ));
+            )));
         };
 
-        let ssl = self
-            .build_ssl(ssl_context)
-            .map_err(|e| anyhow!("failed to create SSL instance: {e}"))?;
-        let acceptor = SslAcceptor::new(ssl, stream, self.ctx.server_config.accept_timeout)
-            .map_err(|e| anyhow!("failed to create new ssl acceptor: {e}"))?;
+        #[cfg_attr(
+            any(feature = "vendored-boringssl", feature = "vendored-aws-lc"),
+            allow(unused_mut)
+        )]
+        let mut ssl = self.build_ssl(ssl_context).map_err(|e| {
+            AcceptError::handshake_failed(anyhow!("failed to create SSL instance: {e}"))
+        })?;
+        #[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+        OpensslSessionSniCheck::new(
+            sni.cloned(),
+            self.ctx.server_config.session_sni_mismatch,
+            self.ctx.server_stats.clone(),
+        )
+        .set_to_ssl(&mut ssl)
+        .map_err(AcceptError::handshake_failed)?;
+        #[cfg_attr(
+            any(feature = "vendored-boringssl", feature = "vendored-aws-lc"),
+            allow(unused_mut)
+        )]
+        let mut acceptor = SslAcceptor::new(ssl, stream, self.ctx.server_config.accept_timeout)
+            .map_err(|e| {
+                AcceptError::handshake_failed(anyhow!("failed to create new ssl acceptor: {e}"))
+            })?;
 
-        acceptor
-            .accept()
-            .await
-            .map_err(|e| anyhow!("failed to accept ssl handshake: {e}"))
+        let handshake_permit = match &self.handshake_limiter {
+            Some(limiter) => {
+                let permit =
+                    tokio::time::timeout(self.ctx.server_config.accept_timeout, limiter.acquire())
+                        .await
+                        .map_err(|_| {
+                            AcceptError::new(
+                                AcceptRejectReason::HandshakeLimited,
+                                anyhow!("timed out to wait for handshake permit"),
+                            )
+                        })?
+                        .ok_or_else(|| {
+                            AcceptError::new(
+                                AcceptRejectReason::HandshakeLimited,
+                                anyhow!("server level handshake limit reached"),
+                            )
+                        })?;
+                Some(permit)
+            }
+            None => None,
+        };
+
+        // only the hosts with early data enabled will accept it
+        let early_data = match early_data_ticket {
+            #[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+            Some(ticket) if host.config.early_data.is_some() => {
+                let early_data = self
+                    .read_early_data(host, &mut acceptor, &ticket)
+                    .await
+                    .map_err(AcceptError::handshake_failed)?;
+                Some(early_data)
+            }
+            _ => None,
+        };
+
+        Ok((acceptor, early_data, handshake_permit))
+    }
+
+    #[cfg(not(any(feature = "vendored-boringssl", feature = "vendored-aws-lc")))]
+    async fn read_early_data<S>(
+        &self,
+        host: &OpensslHost,
+        acceptor: &mut SslAcceptor<S>,
+        ticket: &[u8],
+    ) -> anyhow::Result<OpensslEarlyData>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        if !host.check_early_data_ticket(ticket) {
+            // reject it, and the client will send the data again after the handshake
+            acceptor
+                .ssl_mut()
+                .set_max_early_data(0)
+                .map_err(|e| anyhow!("failed to reject early data: {e}"))?;
+            self.ctx.server_stats.add_early_data_replayed();
+            return Ok(OpensslEarlyData::new(
+                OpensslEarlyDataStatus::Replayed,
+                Vec::new(),
+            ));
+        }
+
+        let max_size = host
+            .config
+            .early_data
+            .as_ref()
+            .map(|c| c.max_size as usize)
+            .unwrap_or_default();
+        let mut buf = vec![0u8; max_size + 1];
+        let mut len = 0;
+        loop {
+            let nr = acceptor
+                .read_early_data(&mut buf[len..])
+                .await
+                .map_err(|e| anyhow!("failed to read early data: {e}"))?;
+            if nr == 0 {
+                break;
+            }
+            len += nr;
+            if len > max_size {
+                return Err(anyhow!("too much early data received"));
+            }
+        }
+        buf.truncate(len);
+
+        if acceptor.early_data_accepted() {
+            self.ctx.server_stats.add_early_data_accepted();
+            Ok(OpensslEarlyData::new(OpensslEarlyDataStatus::Accepted, buf))
+        } else {
+            self.ctx.server_stats.add_early_data_rejected();
+            Ok(OpensslEarlyData::new(
+                OpensslEarlyDataStatus::Rejected,
+                Vec::new(),
+            ))
+        }
     }
 
     #[cfg(not(feature = "openssl-async-job"))]
@@ -254,3 +803,1141 @@ impl OpensslAcceptTask {
         Ok(ssl)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt;
+    use std::io::{Read, Write};
+    use std::net::SocketAddr;
+    use std::pin::Pin;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use ascii::AsciiString;
+    use async_trait::async_trait;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::ssl::{ErrorCode, SslConnector, SslMethod, SslVerifyMode};
+    use openssl::x509::{X509, X509NameBuilder};
+    use slog::{Drain, Key, Logger, OwnedKVList, Record, Serializer, slog_info, slog_o};
+    use tokio::io::ReadBuf;
+    use tokio::net::TcpListener;
+    use yaml_rust::{Yaml, YamlLoader};
+
+    use g3_daemon::server::{AcceptRejectRecorder, AcceptRejectStats, ClientConnectionInfo};
+    use g3_io_ext::IdleWheel;
+    use g3_openssl::SslHandshakeErrorReason;
+    use g3_socket::RawSocket;
+    use g3_types::metrics::NodeName;
+    use g3_types::net::ConnectError;
+    use g3_types::route::AlpnMatch;
+    use g3_yaml::{YamlDocPosition, YamlMapCallback};
+
+    use crate::backend::Backend;
+    use crate::config::server::openssl_proxy::{
+        OpensslCertResolverConfig, OpensslClientHelloBufferOverflow,
+        OpensslClientHelloBufferPoolConfig, OpensslHealthCheckConfig, OpensslHostConfig,
+        OpensslProxyServerConfig,
+    };
+    use crate::config::server::{ClientErrorActionMap, ServerConfig};
+    use crate::module::stream::{StreamConnectResult, StreamServerStats};
+    use crate::serve::openssl_proxy::IngressProxyTlvs;
+    use crate::serve::{
+        BackendPoolStatsMap, CertReloadStats, CertResolverStats, ClientHelloBufferStats,
+        HostHealthStatsMap, ServerIdleOverrides, ServerQuitPolicy, ServerTaskNotes,
+    };
+    use crate::testing::{
+        TempDir, ca_cert, common_name, ec_key, issued_cert, self_signed_cert, write_cert,
+        write_cert_pair, write_key,
+    };
+
+    fn new_task(
+        config: OpensslProxyServerConfig,
+        stats: &Arc<AcceptRejectStats>,
+    ) -> OpensslAcceptTask {
+        new_task_with_hosts(config, stats, Arc::new(HostMatch::default()))
+    }
+
+    fn new_task_with_hosts(
+        config: OpensslProxyServerConfig,
+        stats: &Arc<AcceptRejectStats>,
+        hosts: Arc<HostMatch<Arc<OpensslHost>>>,
+    ) -> OpensslAcceptTask {
+        let server_stats = Arc::new(StreamServerStats::new(config.name()));
+        let ctx = CommonTaskContext {
+            server_config: Arc::new(config),
+            server_stats,
+            server_quit_policy: Arc::new(ServerQuitPolicy::default()),
+            idle_wheel: IdleWheel::spawn(Duration::from_secs(1)),
+            idle_overrides: Arc::new(ServerIdleOverrides::default()),
+            cc_info: ClientConnectionInfo::new(
+                SocketAddr::from(([192, 0, 2, 1], 12345)),
+                SocketAddr::from(([192, 0, 2, 2], 443)),
+            ),
+            task_logger: None,
+            accept_recorder: Arc::new(AcceptRejectRecorder::new(stats.clone(), None, None)),
+            ingress_proxy_tlvs: IngressProxyTlvs::default(),
+            transfer_stats: None,
+        };
+        OpensslAcceptTask::new(ctx, hosts, None, None, None, None)
+    }
+
+    /// Return only one byte for each read
+    struct OneByteReader<'a>(&'a [u8]);
+
+    impl AsyncRead for OneByteReader<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            if let Some((b, left)) = self.0.split_first() {
+                buf.put_slice(&[*b]);
+                self.0 = left;
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Build a ClientHello message with a padding extension after the server name extension,
+    /// and split it into TLS records with at most `record_size` bytes of fragment data
+    fn build_client_hello_records(sni: &str, padding: usize, record_size: usize) -> Vec<u8> {
+        let mut sni_ext = vec![0x00, 0x00]; // Server Name List Length
+        sni_ext.push(0x00); // Server Name Type - Domain
+        sni_ext.extend_from_slice(&(sni.len() as u16).to_be_bytes());
+        sni_ext.extend_from_slice(sni.as_bytes());
+        let list_len = (sni_ext.len() - 2) as u16;
+        sni_ext[..2].copy_from_slice(&list_len.to_be_bytes());
+
+        let mut extensions = vec![0x00, 0x00]; // Extension Type - Server Name
+        extensions.extend_from_slice(&(sni_ext.len() as u16).to_be_bytes());
+        extensions.extend_from_slice(&sni_ext);
+        extensions.extend_from_slice(&[0x00, 0x15]); // Extension Type - Padding
+        extensions.extend_from_slice(&(padding as u16).to_be_bytes());
+        extensions.resize(extensions.len() + padding, 0);
+
+        let mut body = vec![0x03, 0x03]; // TLS 1.2
+        body.resize(body.len() + 32, 0x5a); // Random data
+        body.push(0x00); // Session ID Length
+        body.extend_from_slice(&[0x00, 0x02, 0x13, 0x01]); // Cipher Suites
+        body.extend_from_slice(&[0x01, 0x00]); // Compression Methods
+        body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        body.extend_from_slice(&extensions);
+
+        let mut msg = vec![0x01]; // Handshake Type - ClientHello
+        msg.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        msg.extend_from_slice(&body);
+
+        let mut data = Vec::new();
+        for chunk in msg.chunks(record_size) {
+            data.extend_from_slice(&[0x16, 0x03, 0x01]);
+            data.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+            data.extend_from_slice(chunk);
+        }
+        data
+    }
+
+    async fn read_reject_reason(
+        config: OpensslProxyServerConfig,
+        mut data: &[u8],
+    ) -> AcceptRejectReason {
+        let stats = Arc::new(AcceptRejectStats::default());
+        let mut task = new_task(config, &stats);
+        let mut buf = BytesMut::new();
+        let Err(e) = task.read_client_hello(&mut data, &mut buf).await else {
+            panic!("client hello should not be accepted");
+        };
+        let reason = e.reason;
+        task.reject(e, None);
+        assert_eq!(stats.get(reason), 1);
+        reason
+    }
+
+    #[tokio::test]
+    async fn client_closed() {
+        let config = OpensslProxyServerConfig::new(None);
+        let reason = read_reject_reason(config, &[0x16, 0x03, 0x01]).await;
+        assert_eq!(reason, AcceptRejectReason::ClientClosed);
+    }
+
+    #[tokio::test]
+    async fn client_hello_invalid() {
+        let config = OpensslProxyServerConfig::new(None);
+        // an alert record instead of a handshake record
+        let data = [0x15, 0x03, 0x01, 0x00, 0x02, 0x02, 0x50];
+        let reason = read_reject_reason(config, &data).await;
+        assert_eq!(reason, AcceptRejectReason::ClientHelloInvalid);
+    }
+
+    #[tokio::test]
+    async fn client_hello_too_large() {
+        let mut config = OpensslProxyServerConfig::new(None);
+        config.client_hello_max_size = 64;
+        // the first fragment of a 4096 bytes ClientHello message
+        let data = [0x16, 0x03, 0x01, 0x00, 0x04, 0x01, 0x00, 0x10, 0x00];
+        let reason = read_reject_reason(config, &data).await;
+        assert_eq!(reason, AcceptRejectReason::ClientHelloTooLarge);
+    }
+
+    #[tokio::test]
+    async fn client_hello_timeout() {
+        let mut config = OpensslProxyServerConfig::new(None);
+        config.client_hello_recv_timeout = Duration::from_millis(10);
+
+        let stats = Arc::new(AcceptRejectStats::default());
+        let mut task = new_task(config, &stats);
+        let (mut clt_r, _clt_w) = tokio::io::duplex(64);
+        let mut buf = BytesMut::new();
+        let Err(e) = task.read_client_hello(&mut clt_r, &mut buf).await else {
+            panic!("client hello should not be received");
+        };
+        assert_eq!(e.reason, AcceptRejectReason::ClientHelloTimeout);
+        task.reject(e, None);
+        assert_eq!(stats.get(AcceptRejectReason::ClientHelloTimeout), 1);
+    }
+
+    #[tokio::test]
+    async fn client_hello_buffer_pool() {
+        let pool_config = OpensslClientHelloBufferPoolConfig {
+            pool_size: std::num::NonZeroUsize::new(1).unwrap(),
+            overflow: OpensslClientHelloBufferOverflow::Reject,
+            ..Default::default()
+        };
+        let pool = Arc::new(OpensslClientHelloBufferPool::new(
+            &pool_config,
+            Arc::new(ClientHelloBufferStats::default()),
+        ));
+        let stats = Arc::new(AcceptRejectStats::default());
+        let mut task = new_task(OpensslProxyServerConfig::new(None), &stats);
+        task.client_hello_buffer_pool = Some(pool);
+
+        let data = build_client_hello_records("www.example.net", 0, 1 << 14);
+        let mut buffer = task.get_client_hello_buffer().await.unwrap();
+        assert!(task.get_client_hello_buffer().await.is_none());
+        let mut clt_r = data.as_slice();
+        assert!(
+            task.read_client_hello(&mut clt_r, buffer.buf_mut())
+                .await
+                .is_ok()
+        );
+        assert_eq!(buffer.take_data().as_ref(), data.as_slice());
+
+        // the buffer is returned to the pool after the handoff
+        let mut buffer = task.get_client_hello_buffer().await.unwrap();
+        assert!(buffer.buf_mut().is_empty());
+    }
+
+    #[tokio::test]
+    async fn no_host_matched() {
+        let stats = Arc::new(AcceptRejectStats::default());
+        let task = new_task(OpensslProxyServerConfig::new(None), &stats);
+
+        let mut ext_value = vec![0x00, 0x0e, 0x00, 0x00, 0x0b];
+        ext_value.extend_from_slice(b"example.net");
+        let sni = TlsServerName::from_extension_value(&ext_value).unwrap();
+        let Err(e) = task.select_host(Some(&sni)).await else {
+            panic!("no host should be matched");
+        };
+        assert_eq!(e.reason, AcceptRejectReason::NoHostMatched);
+        task.reject(e, Some(&sni));
+
+        let Err(e) = task.select_host(None).await else {
+            panic!("no host should be matched");
+        };
+        assert_eq!(e.reason, AcceptRejectReason::NoHostMatched);
+        task.reject(e, None);
+
+        assert_eq!(stats.get(AcceptRejectReason::NoHostMatched), 2);
+    }
+
+    #[tokio::test]
+    async fn handshake_error() {
+        let stats = Arc::new(AcceptRejectStats::default());
+        let task = new_task(OpensslProxyServerConfig::new(None), &stats);
+
+        let e = AcceptError::handshake_error(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "ssl accept timed out",
+        ));
+        assert_eq!(e.reason, AcceptRejectReason::HandshakeFailed);
+        task.reject(e, None);
+
+        assert_eq!(stats.get(AcceptRejectReason::HandshakeFailed), 1);
+        assert_eq!(
+            stats.get_handshake_error(SslHandshakeErrorReason::TimedOut),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn fragmented_client_hello() {
+        let build_host = || {
+            let host = OpensslHost::try_build(
+                &Arc::new(OpensslHostConfig::default()),
+                &None,
+                &Arc::new(CertReloadStats::default()),
+                &Arc::new(BackendPoolStatsMap::default()),
+                &Arc::new(HostHealthStatsMap::default()),
+            )
+            .unwrap();
+            Arc::new(host)
+        };
+        let matched_host = build_host();
+        let default_host = build_host();
+        let mut hosts = HostMatch::default();
+        hosts.add_exact_domain(Arc::from("www.example.net"), matched_host.clone());
+        hosts.set_default(default_host.clone());
+        let hosts = Arc::new(hosts);
+
+        for (sni, expected_host) in [
+            ("www.example.net", &matched_host),
+            ("www.example.org", &default_host),
+        ] {
+            let stats = Arc::new(AcceptRejectStats::default());
+            let mut task =
+                new_task_with_hosts(OpensslProxyServerConfig::new(None), &stats, hosts.clone());
+
+            let data = build_client_hello_records(sni, 1024, 64);
+            let mut clt_r = OneByteReader(&data);
+            let mut buf = BytesMut::new();
+            let Ok(mut ch_info) = task.read_client_hello(&mut clt_r, &mut buf).await else {
+                panic!("client hello should be accepted");
+            };
+            assert_eq!(ch_info.sni.as_ref().unwrap().as_ref(), sni);
+            // the host is selected before the padding extension is received
+            let Some(Ok((host, _))) = ch_info.selected_host.take() else {
+                panic!("host should be selected early");
+            };
+            assert!(Arc::ptr_eq(&host, expected_host));
+            // all the records should be kept for the handshake
+            assert_eq!(buf.as_ref(), data.as_slice());
+        }
+    }
+
+    #[tokio::test]
+    async fn host_unhealthy() {
+        let mut config = OpensslHostConfig::default();
+        config.health_check = Some(OpensslHealthCheckConfig {
+            fall: 1,
+            ..Default::default()
+        });
+        let host = OpensslHost::try_build(
+            &Arc::new(config),
+            &None,
+            &Arc::new(CertReloadStats::default()),
+            &Arc::new(BackendPoolStatsMap::default()),
+            &Arc::new(HostHealthStatsMap::default()),
+        )
+        .unwrap();
+
+        // a backend connect failure makes the host unhealthy at once
+        host.record_backend_connect(false);
+        assert_eq!(
+            host.unhealthy_action(),
+            Some(OpensslUnhealthyAction::Reject)
+        );
+
+        let stats = Arc::new(AcceptRejectStats::default());
+        let task = new_task(OpensslProxyServerConfig::new(None), &stats);
+        let e = AcceptError::host_unhealthy(&host);
+        assert_eq!(e.reason, AcceptRejectReason::HostUnhealthy);
+        task.reject(e, None);
+        assert_eq!(stats.get(AcceptRejectReason::HostUnhealthy), 1);
+    }
+
+    #[derive(Clone, Default)]
+    struct CountDrain(Arc<AtomicUsize>);
+
+    impl CountDrain {
+        fn logger(&self) -> Logger {
+            Logger::root(self.clone(), slog_o!())
+        }
+
+        fn count(&self) -> usize {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    impl Drain for CountDrain {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(&self, _record: &Record, _values: &OwnedKVList) -> Result<(), slog::Never> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    fn server_name(name: &str) -> TlsServerName {
+        let mut ext_value = Vec::with_capacity(name.len() + 5);
+        ext_value.extend_from_slice(&((name.len() + 3) as u16).to_be_bytes());
+        ext_value.push(0x00);
+        ext_value.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        ext_value.extend_from_slice(name.as_bytes());
+        TlsServerName::from_extension_value(&ext_value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn host_task_logger() {
+        let build_host = |shared_logger: Option<&str>, sink: &CountDrain| {
+            let mut config = OpensslHostConfig::default();
+            config.shared_logger = shared_logger.map(|s| AsciiString::from_ascii(s).unwrap());
+            let host = OpensslHost::try_build(
+                &Arc::new(config),
+                &None,
+                &Arc::new(CertReloadStats::default()),
+                &Arc::new(BackendPoolStatsMap::default()),
+                &Arc::new(HostHealthStatsMap::default()),
+            )
+            .unwrap();
+            // use the in-memory sink instead of the real shared logger
+            host.shared_logger.set(Some(sink.logger())).unwrap();
+            Arc::new(host)
+        };
+        let server_sink = CountDrain::default();
+        let sink_a = CountDrain::default();
+        let sink_b = CountDrain::default();
+        let unused_sink = CountDrain::default();
+
+        let mut hosts = HostMatch::default();
+        let host_a = build_host(Some("tenant-a"), &sink_a);
+        hosts.add_exact_domain(Arc::from("a.example.net"), host_a);
+        let host_b = build_host(Some("tenant-b"), &sink_b);
+        hosts.add_exact_domain(Arc::from("b.example.net"), host_b);
+        let host_c = build_host(None, &unused_sink);
+        hosts.add_exact_domain(Arc::from("c.example.net"), host_c);
+        let hosts = Arc::new(hosts);
+
+        let stats = Arc::new(AcceptRejectStats::default());
+        let mut task = new_task_with_hosts(OpensslProxyServerConfig::new(None), &stats, hosts);
+        task.ctx.task_logger = Some(server_sink.logger());
+
+        for sni in [
+            "a.example.net",
+            "b.example.net",
+            "b.example.net",
+            "c.example.net",
+        ] {
+            let sni = server_name(sni);
+            let Ok((host, _)) = task.select_host(Some(&sni)).await else {
+                panic!("host should be matched");
+            };
+            let logger = host
+                .task_logger(&task.ctx.server_config, task.ctx.task_logger.clone())
+                .unwrap();
+            slog_info!(logger, "");
+        }
+        assert_eq!(sink_a.count(), 1);
+        assert_eq!(sink_b.count(), 2);
+        assert_eq!(server_sink.count(), 1);
+        assert_eq!(unused_sink.count(), 0);
+
+        // rejected before host selection, the server logger should be kept
+        let sni = server_name("d.example.net");
+        let Err(e) = task.select_host(Some(&sni)).await else {
+            panic!("no host should be matched");
+        };
+        task.reject(e, Some(&sni));
+        slog_info!(task.ctx.task_logger.as_ref().unwrap(), "");
+        assert_eq!(server_sink.count(), 2);
+        assert_eq!(sink_a.count() + sink_b.count(), 3);
+    }
+
+    #[tokio::test]
+    async fn strict_sni() {
+        let build_host = || {
+            let host = OpensslHost::try_build(
+                &Arc::new(OpensslHostConfig::default()),
+                &None,
+                &Arc::new(CertReloadStats::default()),
+                &Arc::new(BackendPoolStatsMap::default()),
+                &Arc::new(HostHealthStatsMap::default()),
+            )
+            .unwrap();
+            Arc::new(host)
+        };
+        let domain_host = build_host();
+        let ip_host = build_host();
+        let default_host = build_host();
+        let mut hosts = HostMatch::default();
+        hosts.add_exact_domain(Arc::from("www.example.net"), domain_host.clone());
+        hosts.add_exact_ip(IpAddr::from([192, 0, 2, 10]), ip_host.clone());
+        hosts.set_default(default_host.clone());
+        let hosts = Arc::new(hosts);
+
+        let new_strict_task = |action: Option<StrictSniAction>, policy: SniIpLiteralPolicy| {
+            let mut config = OpensslProxyServerConfig::new(None);
+            config.strict_sni = action;
+            config.sni_ip_literal = policy;
+            let stats = Arc::new(AcceptRejectStats::default());
+            new_task_with_hosts(config, &stats, hosts.clone())
+        };
+
+        // the lookup key of the sni, None if it's invalid
+        for (name, key) in [
+            ("www.example.net", Some("www.example.net")),
+            ("WWW.Example.NET", Some("www.example.net")),
+            ("www.example.net.", Some("www.example.net")),
+            ("192.0.2.10.", Some("192.0.2.10")),
+            ("2001:DB8::1", Some("2001:db8::1")),
+            ("", None),
+            ("www.example.net..", None),
+            ("www.example.net\0", None),
+            ("www\r\n.example.net", None),
+            ("www.exa mple.net", None),
+            ("*.example.net", None),
+            ("www.example.net:443", None),
+        ] {
+            let task = new_strict_task(Some(StrictSniAction::Ignore), SniIpLiteralPolicy::Match);
+            let Ok(sni) = task.check_sni(server_name(name)) else {
+                panic!("sni {name:?} should be ignored if invalid");
+            };
+            assert_eq!(sni.as_ref().map(|s| s.as_ref()), key, "sni {name:?}");
+
+            let task = new_strict_task(Some(StrictSniAction::Reject), SniIpLiteralPolicy::Match);
+            match (task.check_sni(server_name(name)), key) {
+                (Ok(Some(sni)), Some(key)) => assert_eq!(sni.as_ref(), key),
+                (Err(e), None) => {
+                    assert_eq!(e.reason, AcceptRejectReason::ClientHelloInvalid)
+                }
+                _ => panic!("unexpected check result for sni {name:?}"),
+            }
+
+            // nothing is changed if strict sni is not enabled
+            let task = new_strict_task(None, SniIpLiteralPolicy::Reject);
+            let Ok(Some(sni)) = task.check_sni(server_name(name)) else {
+                panic!("sni {name:?} should be kept");
+            };
+            assert_eq!(sni.as_ref(), name);
+        }
+
+        // the host selected for each policy, None if rejected
+        for (action, policy, name, expected_host) in [
+            (
+                None,
+                SniIpLiteralPolicy::Reject,
+                "www.example.net",
+                Some(&domain_host),
+            ),
+            (
+                None,
+                SniIpLiteralPolicy::Reject,
+                "192.0.2.10",
+                Some(&default_host),
+            ),
+            (
+                Some(StrictSniAction::Reject),
+                SniIpLiteralPolicy::Match,
+                "WWW.example.net.",
+                Some(&domain_host),
+            ),
+            (
+                Some(StrictSniAction::Reject),
+                SniIpLiteralPolicy::Match,
+                "www.example.org",
+                Some(&default_host),
+            ),
+            (
+                Some(StrictSniAction::Ignore),
+                SniIpLiteralPolicy::Match,
+                "www.example.net\0",
+                Some(&default_host),
+            ),
+            (
+                Some(StrictSniAction::Reject),
+                SniIpLiteralPolicy::Match,
+                "www.example.net\0",
+                None,
+            ),
+            (
+                Some(StrictSniAction::Reject),
+                SniIpLiteralPolicy::Match,
+                "192.0.2.10",
+                Some(&ip_host),
+            ),
+            (
+                Some(StrictSniAction::Reject),
+                SniIpLiteralPolicy::Match,
+                "192.0.2.11",
+                Some(&default_host),
+            ),
+            (
+                Some(StrictSniAction::Reject),
+                SniIpLiteralPolicy::Default,
+                "192.0.2.10",
+                Some(&default_host),
+            ),
+            (
+                Some(StrictSniAction::Reject),
+                SniIpLiteralPolicy::Reject,
+                "192.0.2.10",
+                None,
+            ),
+            (
+                Some(StrictSniAction::Reject),
+                SniIpLiteralPolicy::Reject,
+                "www.example.net",
+                Some(&domain_host),
+            ),
+        ] {
+            let task = new_strict_task(action, policy);
+            let selected = match task.check_sni(server_name(name)) {
+                Ok(sni) => task.select_host(sni.as_ref()).await,
+                Err(e) => Err(e),
+            };
+            match (selected, expected_host) {
+                (Ok((host, _)), Some(expected)) => {
+                    assert!(Arc::ptr_eq(&host, expected), "sni {name:?}")
+                }
+                (Err(_), None) => {}
+                _ => panic!("unexpected host selection result for sni {name:?}"),
+            }
+        }
+    }
+
+    /// A backend that sends its name to each new connection
+    struct NameEchoBackend {
+        name: NodeName,
+        addr: SocketAddr,
+    }
+
+    impl NameEchoBackend {
+        async fn spawn(name: &str) -> ArcBackend {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let reply = name.to_string();
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let _ = stream.write_all(reply.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                }
+            });
+            Arc::new(NameEchoBackend {
+                name: NodeName::from_str(name).unwrap(),
+                addr,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Backend for NameEchoBackend {
+        fn name(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn discover(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn update_discover(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn alive_connection(&self) -> u64 {
+            0
+        }
+
+        async fn stream_connect(&self, _task_notes: &ServerTaskNotes) -> StreamConnectResult {
+            let stream = TcpStream::connect(self.addr)
+                .await
+                .map_err(ConnectError::from)?;
+            let (ups_r, ups_w) = stream.into_split();
+            Ok((Box::new(ups_r), Box::new(ups_w)))
+        }
+    }
+
+    fn client_cert_host_config(dir: &std::path::Path) -> OpensslHostConfig {
+        let yaml = "\
+name: test
+cert_pairs:
+  - certificate: server.crt
+    private_key: server.key
+backends:
+  - test
+enable_client_auth: true
+ca_cert: client_ca.crt
+client_cert_router:
+  require_match: true
+  rules:
+    - name: tenant-a
+      subject:
+        OU: tenant-a
+      backend: backend-a
+    - name: tenant-b
+      subject:
+        OU: tenant-b
+      backend: backend-b
+";
+        let yaml = YamlLoader::load_from_str(yaml).unwrap();
+        let position = YamlDocPosition {
+            path: dir.join("main.yaml"),
+            index: 0,
+        };
+        let Yaml::Hash(map) = &yaml[0] else {
+            unreachable!()
+        };
+        let mut config = OpensslHostConfig::default();
+        g3_yaml::foreach_kv(map, |k, v| config.parse_kv(k, v, Some(&position))).unwrap();
+        config.check().unwrap();
+        config
+    }
+
+    fn tenant_cert(ca: &(X509, PKey<Private>), ou: &str, serial: u32) -> (X509, PKey<Private>) {
+        let mut name_builder = X509NameBuilder::new().unwrap();
+        name_builder
+            .append_entry_by_nid(Nid::ORGANIZATIONALUNITNAME, ou)
+            .unwrap();
+        name_builder
+            .append_entry_by_nid(Nid::COMMONNAME, "client.example.net")
+            .unwrap();
+        let key = ec_key();
+        let cert = issued_cert(&name_builder.build(), &key, (&ca.0, &ca.1), serial);
+        (cert, key)
+    }
+
+    /// Connect to the server with the client cert, and return the data sent by the backend
+    async fn relay_with_client_cert(
+        hosts: &Arc<HostMatch<Arc<OpensslHost>>>,
+        client: (X509, PKey<Private>),
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let client_thread = std::thread::spawn(move || {
+            let stream = std::net::TcpStream::connect(server_addr).unwrap();
+            let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+            builder.set_verify(SslVerifyMode::NONE);
+            builder.set_certificate(&client.0).unwrap();
+            builder.set_private_key(&client.1).unwrap();
+            let connector = builder.build();
+            let mut ssl_stream = connector.connect("test.example.net", stream).unwrap();
+            let mut buf = [0u8; 9];
+            ssl_stream.read_exact(&mut buf).unwrap();
+            let _ = ssl_stream.write_all(b"done");
+            String::from_utf8(buf.to_vec()).unwrap()
+        });
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let stats = Arc::new(AcceptRejectStats::default());
+        let task = new_task_with_hosts(OpensslProxyServerConfig::new(None), &stats, hosts.clone());
+        tokio::spawn(task.into_running(stream));
+
+        tokio::task::spawn_blocking(move || client_thread.join().unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn client_cert_route_relay() {
+        let temp_dir = TempDir::new("openssl_accept_client_cert_route");
+        let dir = temp_dir.path();
+        let server_key = ec_key();
+        let server_cert = self_signed_cert(&common_name("test.example.net"), &server_key, 1);
+        write_cert_pair(dir, "server", &server_cert, &server_key);
+        let ca_key = ec_key();
+        let ca = (ca_cert(&common_name("test client ca"), &ca_key), ca_key);
+        write_cert(&dir.join("client_ca.crt"), &ca.0);
+
+        let host = OpensslHost::try_build(
+            &Arc::new(client_cert_host_config(dir)),
+            &None,
+            &Arc::new(CertReloadStats::default()),
+            &Arc::new(BackendPoolStatsMap::default()),
+            &Arc::new(HostHealthStatsMap::default()),
+        )
+        .unwrap();
+        // use the local mock backends instead of the ones in the registry
+        host.client_cert_backends.store(Arc::new(vec![
+            NameEchoBackend::spawn("backend-a").await,
+            NameEchoBackend::spawn("backend-b").await,
+        ]));
+        let mut hosts = HostMatch::default();
+        hosts.set_default(Arc::new(host));
+        let hosts = Arc::new(hosts);
+
+        for (ou, serial, expected) in [
+            ("tenant-a", 2, "backend-a"),
+            ("tenant-b", 3, "backend-b"),
+            ("tenant-a", 4, "backend-a"),
+        ] {
+            let client = tenant_cert(&ca, ou, serial);
+            let received = relay_with_client_cert(&hosts, client).await;
+            assert_eq!(received, expected, "client cert with OU {ou}");
+        }
+    }
+
+    /// The host config using the *server* cert pair in the dir
+    fn server_host_config(dir: &std::path::Path) -> Arc<OpensslHostConfig> {
+        let yaml = "\
+name: test
+cert_pairs:
+  - certificate: server.crt
+    private_key: server.key
+backends:
+  - test
+";
+        let yaml = YamlLoader::load_from_str(yaml).unwrap();
+        let position = YamlDocPosition {
+            path: dir.join("main.yaml"),
+            index: 0,
+        };
+        let Yaml::Hash(map) = &yaml[0] else {
+            unreachable!()
+        };
+        let mut config = OpensslHostConfig::default();
+        g3_yaml::foreach_kv(map, |k, v| config.parse_kv(k, v, Some(&position))).unwrap();
+        config.check().unwrap();
+        Arc::new(config)
+    }
+
+    /// A backend that connects to the address, or fails as timed out if no address is set
+    struct AddrBackend {
+        name: NodeName,
+        addr: Option<SocketAddr>,
+    }
+
+    #[async_trait]
+    impl Backend for AddrBackend {
+        fn name(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn discover(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn update_discover(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn alive_connection(&self) -> u64 {
+            0
+        }
+
+        async fn stream_connect(&self, _task_notes: &ServerTaskNotes) -> StreamConnectResult {
+            let Some(addr) = self.addr else {
+                return Err(ConnectError::TimedOut.into());
+            };
+            let stream = TcpStream::connect(addr).await.map_err(ConnectError::from)?;
+            let (ups_r, ups_w) = stream.into_split();
+            Ok((Box::new(ups_r), Box::new(ups_w)))
+        }
+    }
+
+    /// Keep the client_error_action field of the last task log
+    #[derive(Clone, Default)]
+    struct ClientErrorActionDrain(Arc<Mutex<String>>);
+
+    struct ClientErrorActionSerializer<'a>(&'a Mutex<String>);
+
+    impl Serializer for ClientErrorActionSerializer<'_> {
+        fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+            if key == "client_error_action" {
+                *self.0.lock().unwrap() = val.to_string();
+            }
+            Ok(())
+        }
+    }
+
+    impl Drain for ClientErrorActionDrain {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(&self, record: &Record, _values: &OwnedKVList) -> Result<(), slog::Never> {
+            let mut serializer = ClientErrorActionSerializer(&self.0);
+            record.kv().serialize(record, &mut serializer).unwrap();
+            Ok(())
+        }
+    }
+
+    /// Get what the client sees after the handshake, as nothing will be sent by the backends
+    fn read_client_error(server_addr: SocketAddr) -> &'static str {
+        let stream = std::net::TcpStream::connect(server_addr).unwrap();
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        let connector = builder.build();
+        let mut ssl_stream = connector.connect("test.example.net", stream).unwrap();
+        let mut buf = [0u8; 16];
+        let e = ssl_stream.ssl_read(&mut buf).unwrap_err();
+        if e.code() == ErrorCode::ZERO_RETURN {
+            return "tls_alert";
+        }
+        match e.io_error() {
+            Some(io_e) if io_e.kind() == io::ErrorKind::ConnectionReset => "reset",
+            Some(io_e) => panic!("unexpected client io error: {io_e}"),
+            None => "close",
+        }
+    }
+
+    /// Run a task with the client error actions, and return what the client sees and the
+    /// client error action in the task log
+    async fn run_with_client_error_actions(
+        hosts: &Arc<HostMatch<Arc<OpensslHost>>>,
+        actions: &str,
+    ) -> (&'static str, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let client_thread = std::thread::spawn(move || read_client_error(server_addr));
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut config = OpensslProxyServerConfig::new(None);
+        config.task_idle_max_count = 1;
+        let yaml = YamlLoader::load_from_str(actions).unwrap();
+        config.client_error_actions = ClientErrorActionMap::parse_yaml(&yaml[0]).unwrap();
+        let stats = Arc::new(AcceptRejectStats::default());
+        let mut task = new_task_with_hosts(config, &stats, hosts.clone());
+        task.ctx
+            .cc_info
+            .set_tcp_raw_socket(RawSocket::from(&stream));
+        let drain = ClientErrorActionDrain::default();
+        task.ctx.task_logger = Some(Logger::root(drain.clone(), slog_o!()));
+        task.into_running(stream).await;
+
+        let seen = tokio::task::spawn_blocking(move || client_thread.join().unwrap())
+            .await
+            .unwrap();
+        let logged = drain.0.lock().unwrap().clone();
+        (seen, logged)
+    }
+
+    #[tokio::test]
+    async fn client_error_actions() {
+        let temp_dir = TempDir::new("openssl_accept_client_error_actions");
+        let dir = temp_dir.path();
+        let server_key = ec_key();
+        let server_cert = self_signed_cert(&common_name("test.example.net"), &server_key, 1);
+        write_cert_pair(dir, "server", &server_cert, &server_key);
+
+        let host_config = server_host_config(dir);
+
+        // get a closed port by binding and then dropping the listener
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let refused_addr = closed.local_addr().unwrap();
+        drop(closed);
+        // a backend that accepts the connections but never sends anything
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_addr = silent.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut accepted = Vec::new();
+            while let Ok((stream, _)) = silent.accept().await {
+                accepted.push(stream);
+            }
+        });
+
+        for (backend_addr, actions, expected, logged) in [
+            // connection refused
+            (Some(refused_addr), "{}", "close", "None"),
+            (
+                Some(refused_addr),
+                "{upstream_connect_refused: reset}",
+                "reset",
+                "reset",
+            ),
+            (
+                Some(refused_addr),
+                "{upstream_connect_refused: tls_alert}",
+                "tls_alert",
+                "tls_alert",
+            ),
+            // connect timed out
+            (None, "{}", "close", "None"),
+            (None, "{upstream_connect_timeout: reset}", "reset", "reset"),
+            (
+                None,
+                "{upstream_connect_timeout: tls_alert, upstream_connect_refused: reset}",
+                "tls_alert",
+                "tls_alert",
+            ),
+            // killed as idle
+            (Some(silent_addr), "{}", "close", "None"),
+            (Some(silent_addr), "{idle_killed: reset}", "reset", "reset"),
+            (
+                Some(silent_addr),
+                "{idle_killed: tls_alert}",
+                "tls_alert",
+                "tls_alert",
+            ),
+        ] {
+            let host = OpensslHost::try_build(
+                &host_config,
+                &None,
+                &Arc::new(CertReloadStats::default()),
+                &Arc::new(BackendPoolStatsMap::default()),
+                &Arc::new(HostHealthStatsMap::default()),
+            )
+            .unwrap();
+            let backend: ArcBackend = Arc::new(AddrBackend {
+                name: NodeName::from_str("test").unwrap(),
+                addr: backend_addr,
+            });
+            let mut backends = AlpnMatch::default();
+            backends.set_default(backend);
+            host.backends.store(Arc::new(backends));
+            let mut hosts = HostMatch::default();
+            hosts.set_default(Arc::new(host));
+
+            let (seen, action) = run_with_client_error_actions(&Arc::new(hosts), actions).await;
+            assert_eq!(seen, expected, "client error actions {actions}");
+            assert_eq!(action, logged, "client error actions {actions}");
+        }
+    }
+
+    /// Build a cert resolver using the *test* host as the template, with the backend named *test*
+    async fn build_cert_resolver(
+        dir: &std::path::Path,
+        resolver_yaml: &str,
+        stats: &Arc<CertResolverStats>,
+    ) -> Arc<OpensslCertResolver> {
+        let host = OpensslHost::try_build(
+            &server_host_config(dir),
+            &None,
+            &Arc::new(CertReloadStats::default()),
+            &Arc::new(BackendPoolStatsMap::default()),
+            &Arc::new(HostHealthStatsMap::default()),
+        )
+        .unwrap();
+        let mut backends = AlpnMatch::default();
+        backends.set_default(NameEchoBackend::spawn("test").await);
+        host.backends.store(Arc::new(backends));
+
+        let position = YamlDocPosition {
+            path: dir.join("main.yaml"),
+            index: 0,
+        };
+        let yaml = YamlLoader::load_from_str(resolver_yaml).unwrap();
+        let config = OpensslCertResolverConfig::parse_yaml(&yaml[0], Some(&position)).unwrap();
+        Arc::new(OpensslCertResolver::new(
+            &config,
+            Arc::new(host),
+            None,
+            stats.clone(),
+        ))
+    }
+
+    /// Connect to the server with the server name, and return the common name in the server
+    /// certificate and the data sent by the backend, or `None` if the handshake failed
+    async fn relay_with_server_name(
+        config: OpensslProxyServerConfig,
+        resolver: &Arc<OpensslCertResolver>,
+        server_name: &'static str,
+    ) -> Option<(String, String)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let client_thread = std::thread::spawn(move || {
+            let stream = std::net::TcpStream::connect(server_addr).unwrap();
+            let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+            builder.set_verify(SslVerifyMode::NONE);
+            let connector = builder.build();
+            let mut ssl_stream = connector.connect(server_name, stream).ok()?;
+            let cert = ssl_stream.ssl().peer_certificate().unwrap();
+            let entry = cert
+                .subject_name()
+                .entries_by_nid(Nid::COMMONNAME)
+                .next()
+                .unwrap();
+            let common_name = entry.data().as_utf8().unwrap().to_string();
+            let mut buf = [0u8; 4];
+            ssl_stream.read_exact(&mut buf).unwrap();
+            let _ = ssl_stream.write_all(b"done");
+            Some((common_name, String::from_utf8(buf.to_vec()).unwrap()))
+        });
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let stats = Arc::new(AcceptRejectStats::default());
+        // no default host, so the server names not resolved will be rejected
+        let mut task = new_task(config, &stats);
+        task.cert_resolver = Some(resolver.clone());
+        tokio::spawn(task.into_running(stream));
+
+        tokio::task::spawn_blocking(move || client_thread.join().unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn cert_resolver_relay() {
+        let temp_dir = TempDir::new("openssl_accept_cert_resolver");
+        let dir = temp_dir.path();
+        let server_key = ec_key();
+        let server_cert = self_signed_cert(&common_name("test.example.net"), &server_key, 1);
+        write_cert_pair(dir, "server", &server_cert, &server_key);
+        for (name, serial) in [("a.example.net", 2), ("b.example.net", 3)] {
+            let key = ec_key();
+            let cert = self_signed_cert(&common_name(name), &key, serial);
+            let host_dir = dir.join("certs").join(name);
+            std::fs::create_dir_all(&host_dir).unwrap();
+            write_cert(&host_dir.join("cert.pem"), &cert);
+            write_key(&host_dir.join("key.pem"), &key);
+        }
+
+        let stats = Arc::new(CertResolverStats::default());
+        let resolver = build_cert_resolver(dir, "{directory: certs, host: test}", &stats).await;
+
+        for name in ["a.example.net", "b.example.net", "a.example.net"] {
+            let config = OpensslProxyServerConfig::new(None);
+            let (served, received) = relay_with_server_name(config, &resolver, name)
+                .await
+                .unwrap();
+            assert_eq!(served, name);
+            assert_eq!(received, "test");
+        }
+        let config = OpensslProxyServerConfig::new(None);
+        assert!(
+            relay_with_server_name(config, &resolver, "c.example.net")
+                .await
+                .is_none()
+        );
+
+        let snap = stats.snapshot();
+        assert_eq!(snap.hit, 1);
+        assert_eq!(snap.miss, 3);
+        assert_eq!(snap.timeout, 0);
+        assert_eq!(snap.error, 0);
+    }
+
+    #[tokio::test]
+    async fn cert_resolver_accept_timeout() {
+        let temp_dir = TempDir::new("openssl_accept_cert_resolver_timeout");
+        let dir = temp_dir.path();
+        let server_key = ec_key();
+        let server_cert = self_signed_cert(&common_name("test.example.net"), &server_key, 1);
+        write_cert_pair(dir, "server", &server_cert, &server_key);
+        let socket_path = dir.join("resolver.sock");
+        // accept but never respond
+        let listener = tokio::net::UnixListener::bind(&socket_path).unwrap();
+        tokio::spawn(async move {
+            let mut streams = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+
+        let stats = Arc::new(CertResolverStats::default());
+        let resolver = build_cert_resolver(
+            dir,
+            &format!(
+                "{{unix_socket: {}, host: test, query_timeout: 30s}}",
+                socket_path.display()
+            ),
+            &stats,
+        )
+        .await;
+
+        // the query should only wait for the time left in the accept timeout
+        let mut config = OpensslProxyServerConfig::new(None);
+        config.accept_timeout = Duration::from_millis(500);
+        let time_start = Instant::now();
+        assert!(
+            relay_with_server_name(config, &resolver, "a.example.net")
+                .await
+                .is_none()
+        );
+        assert!(time_start.elapsed() < Duration::from_secs(10));
+
+        let snap = stats.snapshot();
+        assert_eq!(snap.miss, 1);
+        assert_eq!(snap.timeout, 1);
+    }
+}
@@ -8,20 +8,25 @@ use std::time::Duration;
 
 use slog::Logger;
 
-use g3_daemon::server::ClientConnectionInfo;
+use g3_daemon::server::{AcceptRejectRecorder, ClientConnectionInfo, TransferStats};
 use g3_io_ext::IdleWheel;
 
 use crate::config::server::openssl_proxy::OpensslProxyServerConfig;
 use crate::module::stream::StreamServerStats;
-use crate::serve::ServerQuitPolicy;
+use crate::serve::openssl_proxy::IngressProxyTlvs;
+use crate::serve::{ServerIdleOverrides, ServerQuitPolicy};
 
 pub(crate) struct CommonTaskContext {
     pub server_config: Arc<OpensslProxyServerConfig>,
     pub server_stats: Arc<StreamServerStats>,
     pub server_quit_policy: Arc<ServerQuitPolicy>,
     pub idle_wheel: Arc<IdleWheel>,
+    pub idle_overrides: Arc<ServerIdleOverrides>,
     pub cc_info: ClientConnectionInfo,
     pub task_logger: Option<Logger>,
+    pub accept_recorder: Arc<AcceptRejectRecorder>,
+    pub ingress_proxy_tlvs: IngressProxyTlvs,
+    pub transfer_stats: Option<Arc<TransferStats>>,
 }
 
 impl CommonTaskContext {
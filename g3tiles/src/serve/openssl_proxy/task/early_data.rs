@@ -0,0 +1,66 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum OpensslEarlyDataStatus {
+    Accepted,
+    /// rejected by the TLS library, like for an invalid ticket
+    Rejected,
+    /// rejected as the ticket has been used with early data in the anti replay window
+    Replayed,
+}
+
+impl OpensslEarlyDataStatus {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            OpensslEarlyDataStatus::Accepted => "accepted",
+            OpensslEarlyDataStatus::Rejected => "rejected",
+            OpensslEarlyDataStatus::Replayed => "replayed",
+        }
+    }
+}
+
+/// The early data sent by the client, which should be released to the backend at most once
+pub(crate) struct OpensslEarlyData {
+    status: OpensslEarlyDataStatus,
+    data: Vec<u8>,
+    released: bool,
+}
+
+impl OpensslEarlyData {
+    pub(crate) fn new(status: OpensslEarlyDataStatus, data: Vec<u8>) -> Self {
+        OpensslEarlyData {
+            status,
+            data,
+            released: false,
+        }
+    }
+
+    pub(crate) fn status(&self) -> OpensslEarlyDataStatus {
+        self.status
+    }
+
+    /// Get the data to send to the backend, it will be empty if already released
+    pub(crate) fn pending_data(&self) -> &[u8] {
+        if self.released { &[] } else { &self.data }
+    }
+
+    pub(crate) fn mark_released(&mut self) {
+        self.released = true;
+    }
+
+    pub(crate) fn accepted_bytes(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    /// Accepted but not released to the backend
+    pub(crate) fn discarded_bytes(&self) -> u64 {
+        if self.released {
+            0
+        } else {
+            self.data.len() as u64
+        }
+    }
+}
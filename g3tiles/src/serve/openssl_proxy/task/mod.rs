@@ -11,3 +11,6 @@ pub(super) use accept::OpensslAcceptTask;
 
 mod relay;
 use relay::OpensslRelayTask;
+
+mod early_data;
+use early_data::{OpensslEarlyData, OpensslEarlyDataStatus};
@@ -4,29 +4,50 @@
  */
 
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
-use tokio::io::{AsyncRead, AsyncWrite};
+use anyhow::anyhow;
+use bytes::Bytes;
+use h2::Reason;
+use openssl::ssl::SslRef;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::time::Instant;
 
-use g3_daemon::server::ServerQuitPolicy;
+use g3_daemon::server::{ServerQuitPolicy, TransferStats};
 use g3_daemon::stat::task::{TcpStreamConnectionStats, TcpStreamTaskStats};
-use g3_io_ext::{AsyncStream, IdleInterval, LimitedStream, OnceBufReader, StreamCopyConfig};
-use g3_openssl::SslStream;
+use g3_io_ext::{
+    AsyncStream, IdleInterval, LimitedStream, OnceBufReader, OptionalInterval, StreamCopyConfig,
+};
+use g3_openssl::{SslAcceptor, SslStream};
 use g3_types::limit::GaugeSemaphorePermit;
+use g3_types::net::ProxyProtocolV2Encoder;
 
-use super::CommonTaskContext;
+use super::{CommonTaskContext, OpensslEarlyData};
 use crate::backend::ArcBackend;
+use crate::config::server::ClientErrorAction;
+use crate::config::server::openssl_proxy::{OpensslCertKeyType, OpensslHttpAwareConfig};
 use crate::log::task::tcp_connect::TaskLogForTcpConnect;
 use crate::module::stream::{
     StreamRelayTaskCltWrapperStats, StreamServerAliveTaskGuard, StreamTransitTask,
 };
-use crate::serve::openssl_proxy::OpensslHost;
-use crate::serve::{ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage};
+use crate::serve::openssl_proxy::{
+    OpensslBackendPool, OpensslH2BackendPool, OpensslHandshakePermit, OpensslHost, relay_http,
+    transfer_stream, upstream_proxy_header,
+};
+use crate::serve::{
+    ServerIdleOverride, ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage,
+};
 
 pub(crate) struct OpensslRelayTask {
     ctx: CommonTaskContext,
     host: Arc<OpensslHost>,
     backend: ArcBackend,
+    served_cert: Option<OpensslCertKeyType>,
+    client_cert_rule: Option<String>,
+    early_data: Option<OpensslEarlyData>,
+    idle_override: Option<Arc<ServerIdleOverride>>,
+    client_error_action: Option<ClientErrorAction>,
     task_notes: ServerTaskNotes,
     task_stats: Arc<TcpStreamTaskStats>,
     _alive_permit: Option<GaugeSemaphorePermit>,
@@ -35,18 +56,31 @@ pub(crate) struct OpensslRelayTask {
 
 impl OpensslRelayTask {
     pub(crate) fn new(
-        ctx: CommonTaskContext,
+        mut ctx: CommonTaskContext,
         host: Arc<OpensslHost>,
         backend: ArcBackend,
+        served_cert: Option<OpensslCertKeyType>,
+        client_cert_rule: Option<String>,
+        early_data: Option<OpensslEarlyData>,
         wait_time: Duration,
         pre_handshake_stats: Arc<TcpStreamConnectionStats>,
         alive_permit: Option<GaugeSemaphorePermit>,
     ) -> Self {
-        let task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), wait_time);
+        // the host is selected now, so use its own task logger if set
+        ctx.task_logger = host.task_logger(&ctx.server_config, ctx.task_logger.take());
+        let mut task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), wait_time);
+        task_notes.backend_dscp = host.config.backend_dscp;
+        task_notes.upstream_misc_opts = ctx.server_config.upstream_misc_opts.clone();
+        let idle_override = ctx.idle_overrides.select(ctx.cc_info.client_ip());
         OpensslRelayTask {
             ctx,
             host,
             backend,
+            served_cert,
+            client_cert_rule,
+            early_data,
+            idle_override,
+            client_error_action: None,
             task_notes,
             task_stats: Arc::new(TcpStreamTaskStats::with_clt_stats(
                 pre_handshake_stats.as_ref().clone(),
@@ -63,6 +97,13 @@ impl OpensslRelayTask {
             .map(|logger| TaskLogForTcpConnect {
                 logger,
                 task_notes: &self.task_notes,
+                tls_cert_type: self.served_cert.map(|t| t.as_str()),
+                client_cert_rule: self.client_cert_rule.as_deref(),
+                early_data: self.early_data.as_ref().map(|d| d.status().as_str()),
+                early_data_bytes: self.early_data.as_ref().map(|d| d.accepted_bytes()),
+                early_data_discarded: self.early_data.as_ref().map(|d| d.discarded_bytes()),
+                idle_override: self.idle_override.as_ref().map(|o| o.name().as_ref()),
+                client_error_action: self.client_error_action.map(|a| a.as_str()),
                 client_rd_bytes: self.task_stats.clt.read.get_bytes(),
                 client_wr_bytes: self.task_stats.clt.write.get_bytes(),
                 remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
@@ -84,6 +125,25 @@ impl OpensslRelayTask {
         }
     }
 
+    /// Run the task with a handshake that is still in progress, the early data will be sent to
+    /// the backend as soon as it is connected, without waiting for the handshake to complete.
+    ///
+    /// This should only be used if the backend is safe to handle replayed requests.
+    pub(crate) async fn into_running_with_early_data<S>(
+        mut self,
+        acceptor: SslAcceptor<OnceBufReader<LimitedStream<S>>>,
+        handshake_permit: Option<OpensslHandshakePermit>,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.pre_start();
+        if let Err(e) = self.run_with_early_data(acceptor, handshake_permit).await {
+            if let Some(log_ctx) = self.get_log_context() {
+                log_ctx.log(e);
+            }
+        }
+    }
+
     fn pre_start(&mut self) {
         self._alive_guard = Some(self.ctx.server_stats.add_task());
 
@@ -96,30 +156,342 @@ impl OpensslRelayTask {
 
     async fn run<S>(
         &mut self,
-        ssl_stream: SslStream<OnceBufReader<LimitedStream<S>>>,
+        mut ssl_stream: SslStream<OnceBufReader<LimitedStream<S>>>,
     ) -> ServerTaskResult<()>
     where
         S: AsyncRead + AsyncWrite + Unpin,
     {
+        self.start_lifetime();
         self.task_notes.stage = ServerTaskStage::Preparing;
+        self.set_clt_sock_opts()?;
 
-        // set client side socket options
-        self.ctx
-            .cc_info
-            .tcp_sock_set_raw_opts(&self.ctx.server_config.tcp_misc_opts, true)
-            .map_err(|_| {
-                ServerTaskError::InternalServerError("failed to set client socket options")
-            })?;
+        self.task_notes.stage = ServerTaskStage::Connecting;
+
+        if let Some(pool) = self.host.h2_backend_pool().cloned() {
+            if ssl_stream.ssl().selected_alpn_protocol() == Some(b"h2".as_slice()) {
+                return self.run_h2(ssl_stream, pool).await;
+            }
+        }
+
+        if let Some(pool) = self.host.backend_pool().cloned() {
+            return self.run_pooled(ssl_stream, pool).await;
+        }
+
+        let mut proxy_header = self.upstream_proxy_header(ssl_stream.ssl())?;
+
+        let r = self.backend.stream_connect(&self.task_notes).await;
+        self.host.record_backend_connect(r.is_ok());
+        let (ups_r, mut ups_w) = match r {
+            Ok(c) => c,
+            Err(e) => {
+                let e = ServerTaskError::from(e);
+                self.apply_client_error_action(&mut ssl_stream, &e).await;
+                return Err(e);
+            }
+        };
+
+        self.task_notes.stage = ServerTaskStage::Connected;
+
+        if let Some(encoder) = &mut proxy_header {
+            let header = encoder.finalize();
+            ups_w
+                .write_all(header)
+                .await
+                .map_err(ServerTaskError::UpstreamWriteFailed)?;
+            self.task_notes.upstream_proxy_header_bytes = Some(header.len() as u64);
+        }
+
+        // the handshake has completed, so it's safe to release the early data now
+        self.send_early_data(&mut ups_w).await?;
+
+        self.run_connected(ssl_stream, ups_r, ups_w).await
+    }
+
+    async fn run_pooled<S>(
+        &mut self,
+        mut ssl_stream: SslStream<OnceBufReader<LimitedStream<S>>>,
+        pool: Arc<OpensslBackendPool>,
+    ) -> ServerTaskResult<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let r = pool
+            .get(&self.backend, &self.task_notes, &self.ctx.cc_info)
+            .await;
+        self.host.record_backend_connect(r.is_ok());
+        let mut ups = match r {
+            Ok(ups) => ups,
+            Err(e) => {
+                let e = ServerTaskError::from(e);
+                self.apply_client_error_action(&mut ssl_stream, &e).await;
+                return Err(e);
+            }
+        };
+
+        self.task_notes.stage = ServerTaskStage::Connected;
+
+        self.send_early_data(&mut ups.writer).await?;
+
+        self.pre_relay();
+        self.reset_clt_limit_and_stats(&mut ssl_stream);
+        let (mut clt_r, mut clt_w) = ssl_stream.into_split();
+
+        let r = match &self.host.config.http_aware {
+            Some(config) => {
+                self.transit_http_aware(
+                    config,
+                    &mut clt_r,
+                    &mut clt_w,
+                    &mut ups.reader,
+                    &mut ups.writer,
+                )
+                .await
+            }
+            None => {
+                self.transit_reusable(&mut clt_r, &mut clt_w, &mut ups.reader, &mut ups.writer)
+                    .await
+            }
+        };
+        let reusable = match r {
+            Ok(reusable) => reusable,
+            Err(e) => {
+                let mut ssl_stream = clt_r.unsplit(clt_w);
+                self.apply_client_error_action(&mut ssl_stream, &e).await;
+                return Err(e);
+            }
+        };
+        if reusable {
+            pool.put(self.backend.name(), ups);
+        }
+        Ok(())
+    }
+
+    /// Map the client h2 streams onto the shared backend h2 connections.
+    ///
+    /// The backend connections are established on demand, and only the client side bytes are
+    /// counted in this task.
+    async fn run_h2<S>(
+        &mut self,
+        mut ssl_stream: SslStream<OnceBufReader<LimitedStream<S>>>,
+        pool: Arc<OpensslH2BackendPool>,
+    ) -> ServerTaskResult<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.task_notes.stage = ServerTaskStage::Connected;
+
+        self.pre_relay();
+        self.reset_clt_limit_and_stats(&mut ssl_stream);
+
+        let config = pool.config();
+        let mut server_builder = h2::server::Builder::new();
+        server_builder
+            .max_concurrent_streams(config.max_concurrent_streams)
+            .initial_window_size(config.stream_window_size())
+            .initial_connection_window_size(config.connection_window_size());
+        let mut h2_conn = match tokio::time::timeout(
+            config.handshake_timeout,
+            server_builder.handshake::<_, Bytes>(ssl_stream),
+        )
+        .await
+        {
+            Ok(Ok(c)) => c,
+            Ok(Err(e)) => return Err(anyhow!("client h2 handshake failed: {e}").into()),
+            Err(_) => return Err(anyhow!("client h2 handshake timed out").into()),
+        };
+
+        let yield_size = self.ctx.server_config.tcp_copy.yield_size();
+        let alive_streams = Arc::new(AtomicUsize::new(0));
+        let mut idle_interval = self.idle_check_interval();
+        let mut idle_count = 0;
+        let max_idle_count = self.max_idle_count();
+        let deadline = self.lifetime_deadline();
+        let lifetime = async move {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(lifetime);
+        loop {
+            tokio::select! {
+                r = h2_conn.accept() => {
+                    let (clt_req, mut clt_send_rsp) = match r {
+                        Some(Ok(d)) => d,
+                        Some(Err(e)) => {
+                            return Err(anyhow!("client h2 connection error: {e}").into());
+                        }
+                        None => {
+                            self.log_client_shutdown();
+                            return Ok(());
+                        }
+                    };
+
+                    let pool = pool.clone();
+                    let host = self.host.clone();
+                    let backend = self.backend.clone();
+                    let mut task_notes =
+                        ServerTaskNotes::new(self.ctx.cc_info.clone(), Duration::ZERO);
+                    task_notes.backend_dscp = self.task_notes.backend_dscp;
+                    let alive_streams = alive_streams.clone();
+                    alive_streams.fetch_add(1, Ordering::Relaxed);
+                    tokio::spawn(async move {
+                        let r = pool.get_stream(&backend, &task_notes).await;
+                        host.record_backend_connect(r.is_ok());
+                        match r {
+                            Ok(ups) => {
+                                transfer_stream(clt_req, clt_send_rsp, ups, yield_size).await
+                            }
+                            Err(_) => clt_send_rsp.send_reset(Reason::REFUSED_STREAM),
+                        }
+                        alive_streams.fetch_sub(1, Ordering::Relaxed);
+                    });
+                }
+                _ = &mut lifetime => {
+                    // send GOAWAY and wait for the in-flight streams in the grace period
+                    h2_conn.graceful_shutdown();
+                    let drain = async {
+                        while let Some(Ok(_)) = h2_conn.accept().await {}
+                    };
+                    let _ = tokio::time::timeout(self.lifetime_grace(), drain).await;
+                    return Err(ServerTaskError::LifetimeExpired);
+                }
+                n = idle_interval.tick() => {
+                    if alive_streams.load(Ordering::Relaxed) == 0 {
+                        idle_count += n;
+
+                        if idle_count >= max_idle_count {
+                            return Err(ServerTaskError::Idle(idle_interval.period(), idle_count));
+                        }
+                    } else {
+                        idle_count = 0;
+                    }
+
+                    if self.quit_policy().force_quit() {
+                        return Err(ServerTaskError::CanceledAsServerQuit)
+                    }
+                }
+            }
+        }
+    }
+
+    async fn run_with_early_data<S>(
+        &mut self,
+        acceptor: SslAcceptor<OnceBufReader<LimitedStream<S>>>,
+        handshake_permit: Option<OpensslHandshakePermit>,
+    ) -> ServerTaskResult<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.task_notes.stage = ServerTaskStage::Preparing;
+        self.set_clt_sock_opts()?;
 
         self.task_notes.stage = ServerTaskStage::Connecting;
 
-        let (ups_r, ups_w) = self.backend.stream_connect(&self.task_notes).await?;
+        // the TLS params are already negotiated when the early data is accepted
+        let mut proxy_header = self.upstream_proxy_header(acceptor.ssl())?;
+        let proxy_header = proxy_header.as_mut().map(|encoder| encoder.finalize());
+
+        let handshake = async move {
+            let r = acceptor.accept().await;
+            drop(handshake_permit);
+            r
+        };
+        let connect = async {
+            let r = self.backend.stream_connect(&self.task_notes).await;
+            self.host.record_backend_connect(r.is_ok());
+            let (ups_r, mut ups_w) = r?;
+            if let Some(header) = proxy_header {
+                ups_w
+                    .write_all(header)
+                    .await
+                    .map_err(ServerTaskError::UpstreamWriteFailed)?;
+            }
+            if let Some(early_data) = &self.early_data {
+                ups_w
+                    .write_all(early_data.pending_data())
+                    .await
+                    .map_err(ServerTaskError::UpstreamWriteFailed)?;
+            }
+            Ok::<_, ServerTaskError>((ups_r, ups_w))
+        };
+        let (handshake_r, connect_r) = tokio::join!(handshake, connect);
+        let (ups_r, ups_w) = match connect_r {
+            Ok(c) => c,
+            Err(e) => {
+                if let Ok(mut ssl_stream) = handshake_r {
+                    self.apply_client_error_action(&mut ssl_stream, &e).await;
+                }
+                return Err(e);
+            }
+        };
+        self.task_notes.upstream_proxy_header_bytes = proxy_header.map(|h| h.len() as u64);
+        if let Some(early_data) = &mut self.early_data {
+            early_data.mark_released();
+        }
+        let ssl_stream = handshake_r.map_err(ServerTaskError::ClientTlsHandshakeFailed)?;
+        self.start_lifetime();
 
         self.task_notes.stage = ServerTaskStage::Connected;
 
         self.run_connected(ssl_stream, ups_r, ups_w).await
     }
 
+    /// Start the max lifetime timer of the client connection, should be called once the client
+    /// handshake has completed
+    fn start_lifetime(&mut self) {
+        let lifetime = self
+            .host
+            .config
+            .connection_max_lifetime(self.ctx.server_config.connection_max_lifetime);
+        self.task_notes.lifetime_deadline = lifetime.map(|d| Instant::now() + d);
+    }
+
+    fn set_clt_sock_opts(&self) -> ServerTaskResult<()> {
+        // set client side socket options
+        let tcp_misc_opts = self
+            .host
+            .config
+            .tcp_misc_opts(&self.ctx.server_config.client_misc_opts);
+        self.ctx
+            .cc_info
+            .tcp_sock_set_raw_opts(&tcp_misc_opts, true)
+            .map_err(|_| {
+                ServerTaskError::InternalServerError("failed to set client socket options")
+            })
+    }
+
+    fn upstream_proxy_header(
+        &self,
+        ssl: &SslRef,
+    ) -> ServerTaskResult<Option<ProxyProtocolV2Encoder>> {
+        if self.host.config.upstream_proxy_protocol.is_none() {
+            return Ok(None);
+        }
+        let encoder = upstream_proxy_header(
+            ssl,
+            self.task_notes.client_addr(),
+            self.task_notes.server_addr(),
+        )
+        .map_err(|e| anyhow!("failed to encode upstream PROXY protocol header: {e}"))?;
+        Ok(Some(encoder))
+    }
+
+    async fn send_early_data<W>(&mut self, ups_w: &mut W) -> ServerTaskResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let Some(early_data) = &mut self.early_data else {
+            return Ok(());
+        };
+        ups_w
+            .write_all(early_data.pending_data())
+            .await
+            .map_err(ServerTaskError::UpstreamWriteFailed)?;
+        early_data.mark_released();
+        Ok(())
+    }
+
     async fn run_connected<S, UR, UW>(
         &mut self,
         ssl_stream: SslStream<OnceBufReader<LimitedStream<S>>>,
@@ -131,6 +503,11 @@ impl OpensslRelayTask {
         UR: AsyncRead + Unpin,
         UW: AsyncWrite + Unpin,
     {
+        self.pre_relay();
+        self.relay(ssl_stream, ups_r, ups_w).await
+    }
+
+    fn pre_relay(&mut self) {
         if self.ctx.server_config.flush_task_log_on_connected {
             if let Some(log_ctx) = self.get_log_context() {
                 log_ctx.log_connected();
@@ -138,14 +515,13 @@ impl OpensslRelayTask {
         }
 
         self.task_notes.mark_relaying();
-        self.relay(ssl_stream, ups_r, ups_w).await
     }
 
     async fn relay<S, UR, UW>(
         &mut self,
         mut ssl_stream: SslStream<OnceBufReader<LimitedStream<S>>>,
-        ups_r: UR,
-        ups_w: UW,
+        mut ups_r: UR,
+        mut ups_w: UW,
     ) -> ServerTaskResult<()>
     where
         S: AsyncRead + AsyncWrite + Unpin,
@@ -153,9 +529,132 @@ impl OpensslRelayTask {
         UW: AsyncWrite + Unpin,
     {
         self.reset_clt_limit_and_stats(&mut ssl_stream);
-        let (clt_r, clt_w) = ssl_stream.into_split();
+        let (mut clt_r, mut clt_w) = ssl_stream.into_split();
 
-        self.transit_transparent(clt_r, clt_w, ups_r, ups_w).await
+        let r = match &self.host.config.http_aware {
+            Some(config) => self
+                .transit_http_aware(config, &mut clt_r, &mut clt_w, &mut ups_r, &mut ups_w)
+                .await
+                .map(|_| ()),
+            None => {
+                self.transit_transparent(&mut clt_r, &mut clt_w, ups_r, ups_w)
+                    .await
+            }
+        };
+        if let Err(e) = &r {
+            let mut ssl_stream = clt_r.unsplit(clt_w);
+            self.apply_client_error_action(&mut ssl_stream, e).await;
+        }
+        r
+    }
+
+    /// Apply the configured client error action before the client connection is closed
+    async fn apply_client_error_action<S>(
+        &mut self,
+        ssl_stream: &mut SslStream<S>,
+        e: &ServerTaskError,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let Some(action) = e
+            .client_error_class()
+            .and_then(|class| self.ctx.server_config.client_error_actions.get(class))
+        else {
+            return;
+        };
+        match action {
+            ClientErrorAction::Close => {}
+            ClientErrorAction::Reset => self.ctx.cc_info.tcp_sock_reset_on_close(),
+            ClientErrorAction::TlsAlert => {
+                // it may have been sent when closing on lifetime expired
+                if !ssl_stream.close_notify_sent() {
+                    let _ = ssl_stream.shutdown().await;
+                }
+            }
+        }
+        self.client_error_action = Some(action);
+    }
+
+    /// Relay the client connection as HTTP/1.1 messages, see `relay_http` for the details.
+    ///
+    /// Return true if the backend connection is clean to be reused.
+    async fn transit_http_aware<CR, CW, UR, UW>(
+        &self,
+        config: &OpensslHttpAwareConfig,
+        clt_r: CR,
+        mut clt_w: CW,
+        ups_r: &mut UR,
+        ups_w: &mut UW,
+    ) -> ServerTaskResult<bool>
+    where
+        CR: AsyncRead + Unpin,
+        CW: AsyncWrite + Unpin,
+        UR: AsyncRead + Unpin,
+        UW: AsyncWrite + Unpin,
+    {
+        let on_fallback = || self.ctx.server_stats.add_http_aware_fallback();
+        let relay = relay_http(
+            config,
+            self.copy_config(),
+            clt_r,
+            &mut clt_w,
+            ups_r,
+            ups_w,
+            &on_fallback,
+        );
+        tokio::pin!(relay);
+
+        let mut idle_interval = self.idle_check_interval();
+        let mut log_interval = self
+            .log_flush_interval()
+            .map(|log_interval| {
+                let interval =
+                    tokio::time::interval_at(Instant::now() + log_interval, log_interval);
+                OptionalInterval::with(interval)
+            })
+            .unwrap_or_default();
+        let mut idle_count = 0;
+        let max_idle_count = self.max_idle_count();
+        // the messages are relayed in turn, so just check if there is any client traffic
+        let mut relayed_bytes = self.client_relayed_bytes();
+        let deadline = self.lifetime_deadline();
+        let lifetime = async move {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(lifetime);
+        loop {
+            tokio::select! {
+                r = &mut relay => return r,
+                _ = &mut lifetime => return Err(ServerTaskError::LifetimeExpired),
+                _ = log_interval.tick() => {
+                    self.log_periodic();
+                }
+                n = idle_interval.tick() => {
+                    let bytes = self.client_relayed_bytes();
+                    if bytes == relayed_bytes {
+                        idle_count += n;
+
+                        if idle_count >= max_idle_count {
+                            return Err(ServerTaskError::Idle(idle_interval.period(), idle_count));
+                        }
+                    } else {
+                        idle_count = 0;
+                        relayed_bytes = bytes;
+                    }
+
+                    if self.quit_policy().force_quit() {
+                        return Err(ServerTaskError::CanceledAsServerQuit)
+                    }
+                }
+            }
+        }
+    }
+
+    fn client_relayed_bytes(&self) -> u64 {
+        self.task_stats.clt.read.get_bytes() + self.task_stats.clt.write.get_bytes()
     }
 
     fn reset_clt_limit_and_stats<S>(
@@ -195,14 +694,19 @@ impl StreamTransitTask for OpensslRelayTask {
     }
 
     fn idle_check_interval(&self) -> IdleInterval {
-        self.ctx.idle_wheel.register()
+        match &self.idle_override {
+            Some(o) => o.idle_wheel.register(),
+            None => self.ctx.idle_wheel.register(),
+        }
     }
 
     fn max_idle_count(&self) -> usize {
-        self.host
-            .config
-            .task_idle_max_count
-            .unwrap_or(self.ctx.server_config.task_idle_max_count)
+        self.host.config.task_idle_max_count.unwrap_or_else(|| {
+            self.idle_override
+                .as_ref()
+                .map(|o| o.max_idle_count)
+                .unwrap_or(self.ctx.server_config.task_idle_max_count)
+        })
     }
 
     fn log_client_shutdown(&self) {
@@ -230,4 +734,16 @@ impl StreamTransitTask for OpensslRelayTask {
     fn quit_policy(&self) -> &ServerQuitPolicy {
         self.ctx.server_quit_policy.as_ref()
     }
+
+    fn transfer_stats(&self) -> Option<&TransferStats> {
+        self.ctx.transfer_stats.as_deref()
+    }
+
+    fn lifetime_deadline(&self) -> Option<Instant> {
+        self.task_notes.lifetime_deadline
+    }
+
+    fn lifetime_grace(&self) -> Duration {
+        self.ctx.server_config.connection_lifetime_grace
+    }
 }
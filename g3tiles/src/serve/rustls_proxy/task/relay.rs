@@ -63,6 +63,13 @@ impl RustlsRelayTask {
             .map(|logger| TaskLogForTcpConnect {
                 logger,
                 task_notes: &self.task_notes,
+                tls_cert_type: None,
+                client_cert_rule: None,
+                early_data: None,
+                early_data_bytes: None,
+                early_data_discarded: None,
+                idle_override: None,
+                client_error_action: None,
                 client_rd_bytes: self.task_stats.clt.read.get_bytes(),
                 client_wr_bytes: self.task_stats.clt.write.get_bytes(),
                 remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
@@ -3,11 +3,21 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use ahash::AHashMap;
+
+use g3_daemon::server::{AcceptRejectSnapshot, TransferStats};
+use g3_histogram::{HistogramMetricsConfig, HistogramRecorder, HistogramStats};
+use g3_std_ext::time::DurationExt;
 use g3_types::metrics::{MetricTagMap, NodeName};
+use g3_types::net::RollingTicketerSnapshot;
 use g3_types::stats::{StatId, TcpIoSnapshot, UdpIoSnapshot};
 
+use crate::config::server::openssl_proxy::OpensslCertKeyType;
+
 pub(crate) trait ServerStats {
     fn name(&self) -> &NodeName;
     fn stat_id(&self) -> StatId;
@@ -28,6 +38,555 @@ pub(crate) trait ServerStats {
     fn udp_io_snapshot(&self) -> Option<UdpIoSnapshot> {
         None
     }
+    fn cert_resolver_snapshot(&self) -> Option<CertResolverSnapshot> {
+        None
+    }
+    fn served_cert_snapshot(&self) -> Option<ServedCertSnapshot> {
+        None
+    }
+    fn client_cert_route_snapshot(&self) -> Option<ClientCertRouteSnapshot> {
+        None
+    }
+    fn handshake_limit_snapshot(&self) -> Option<HandshakeLimitSnapshot> {
+        None
+    }
+    fn client_ip_limit_snapshot(&self) -> Option<ClientIpLimitSnapshot> {
+        None
+    }
+    fn client_hello_buffer_snapshot(&self) -> Option<ClientHelloBufferSnapshot> {
+        None
+    }
+    fn early_data_snapshot(&self) -> Option<EarlyDataSnapshot> {
+        None
+    }
+    fn http_aware_snapshot(&self) -> Option<HttpAwareSnapshot> {
+        None
+    }
+    fn session_sni_mismatch_snapshot(&self) -> Option<SessionSniMismatchSnapshot> {
+        None
+    }
+    fn cert_reload_snapshot(&self) -> Option<CertReloadSnapshot> {
+        None
+    }
+    fn tls_ticket_snapshot(&self) -> Option<RollingTicketerSnapshot> {
+        None
+    }
+    fn accept_reject_snapshot(&self) -> Option<AcceptRejectSnapshot> {
+        None
+    }
+    fn backend_pool_snapshot(&self) -> Option<BackendPoolSnapshotMap> {
+        None
+    }
+    fn host_health_snapshot(&self) -> Option<HostHealthSnapshotMap> {
+        None
+    }
+    fn transfer_stats(&self) -> Option<Arc<TransferStats>> {
+        None
+    }
 }
 
 pub(crate) type ArcServerStats = Arc<dyn ServerStats + Send + Sync>;
+
+#[derive(Default)]
+pub(crate) struct CertResolverStats {
+    hit: AtomicU64,
+    miss: AtomicU64,
+    timeout: AtomicU64,
+    error: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct CertResolverSnapshot {
+    pub(crate) hit: u64,
+    pub(crate) miss: u64,
+    pub(crate) timeout: u64,
+    pub(crate) error: u64,
+}
+
+impl CertResolverStats {
+    pub(crate) fn add_hit(&self) {
+        self.hit.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_miss(&self) {
+        self.miss.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_timeout(&self) {
+        self.timeout.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_error(&self) {
+        self.error.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> CertResolverSnapshot {
+        CertResolverSnapshot {
+            hit: self.hit.load(Ordering::Relaxed),
+            miss: self.miss.load(Ordering::Relaxed),
+            timeout: self.timeout.load(Ordering::Relaxed),
+            error: self.error.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct HandshakeLimitStats {
+    in_flight: AtomicU64,
+    queued: AtomicU64,
+    rejected: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct HandshakeLimitSnapshot {
+    pub(crate) in_flight: u64,
+    pub(crate) queued: u64,
+    pub(crate) rejected: u64,
+}
+
+impl HandshakeLimitStats {
+    pub(crate) fn add_in_flight(&self) {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn del_in_flight(&self) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_queued(&self) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn del_queued(&self) {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> HandshakeLimitSnapshot {
+        HandshakeLimitSnapshot {
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Client ips limited by the accept phase rate limit, and the connections rejected from them
+#[derive(Default)]
+pub(crate) struct ClientIpLimitStats {
+    limited: AtomicU64,
+    rejected: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct ClientIpLimitSnapshot {
+    pub(crate) limited: u64,
+    pub(crate) rejected: u64,
+}
+
+impl ClientIpLimitStats {
+    pub(crate) fn add_limited(&self) {
+        self.limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ClientIpLimitSnapshot {
+        ClientIpLimitSnapshot {
+            limited: self.limited.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The usage of the pooled ClientHello buffers, and the overflow counts when the pool is empty
+#[derive(Default)]
+pub(crate) struct ClientHelloBufferStats {
+    in_use: AtomicU64,
+    overflow_allocated: AtomicU64,
+    overflow_waited: AtomicU64,
+    overflow_rejected: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct ClientHelloBufferSnapshot {
+    pub(crate) in_use: u64,
+    pub(crate) overflow_allocated: u64,
+    pub(crate) overflow_waited: u64,
+    pub(crate) overflow_rejected: u64,
+}
+
+impl ClientHelloBufferStats {
+    pub(crate) fn add_in_use(&self) {
+        self.in_use.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn del_in_use(&self) {
+        self.in_use.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_overflow_allocated(&self) {
+        self.overflow_allocated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_overflow_waited(&self) {
+        self.overflow_waited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_overflow_rejected(&self) {
+        self.overflow_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ClientHelloBufferSnapshot {
+        ClientHelloBufferSnapshot {
+            in_use: self.in_use.load(Ordering::Relaxed),
+            overflow_allocated: self.overflow_allocated.load(Ordering::Relaxed),
+            overflow_waited: self.overflow_waited.load(Ordering::Relaxed),
+            overflow_rejected: self.overflow_rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct EarlyDataStats {
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    replayed: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct EarlyDataSnapshot {
+    pub(crate) accepted: u64,
+    pub(crate) rejected: u64,
+    pub(crate) replayed: u64,
+}
+
+impl EarlyDataStats {
+    pub(crate) fn add_accepted(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_replayed(&self) {
+        self.replayed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> EarlyDataSnapshot {
+        EarlyDataSnapshot {
+            accepted: self.accepted.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            replayed: self.replayed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct HttpAwareStats {
+    fallback: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct HttpAwareSnapshot {
+    pub(crate) fallback: u64,
+}
+
+impl HttpAwareStats {
+    pub(crate) fn add_fallback(&self) {
+        self.fallback.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> HttpAwareSnapshot {
+        HttpAwareSnapshot {
+            fallback: self.fallback.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Resumed sessions whose original SNI differs from the new one, by the action taken
+#[derive(Default)]
+pub(crate) struct SessionSniMismatchStats {
+    allowed: AtomicU64,
+    full_handshake: AtomicU64,
+    rejected: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct SessionSniMismatchSnapshot {
+    pub(crate) allowed: u64,
+    pub(crate) full_handshake: u64,
+    pub(crate) rejected: u64,
+}
+
+impl SessionSniMismatchStats {
+    pub(crate) fn add_allowed(&self) {
+        self.allowed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_full_handshake(&self) {
+        self.full_handshake.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> SessionSniMismatchSnapshot {
+        SessionSniMismatchSnapshot {
+            allowed: self.allowed.load(Ordering::Relaxed),
+            full_handshake: self.full_handshake.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct CertReloadStats {
+    success: AtomicU64,
+    failed: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct CertReloadSnapshot {
+    pub(crate) success: u64,
+    pub(crate) failed: u64,
+}
+
+impl CertReloadStats {
+    pub(crate) fn add_success(&self) {
+        self.success.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_failed(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> CertReloadSnapshot {
+        CertReloadSnapshot {
+            success: self.success.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ServedCertStats {
+    rsa: AtomicU64,
+    ec: AtomicU64,
+    ed25519: AtomicU64,
+    other: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct ServedCertSnapshot {
+    pub(crate) rsa: u64,
+    pub(crate) ec: u64,
+    pub(crate) ed25519: u64,
+    pub(crate) other: u64,
+}
+
+impl ServedCertStats {
+    pub(crate) fn add(&self, key_type: OpensslCertKeyType) {
+        let counter = match key_type {
+            OpensslCertKeyType::Rsa => &self.rsa,
+            OpensslCertKeyType::Ec => &self.ec,
+            OpensslCertKeyType::Ed25519 => &self.ed25519,
+            OpensslCertKeyType::Other => &self.other,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ServedCertSnapshot {
+        ServedCertSnapshot {
+            rsa: self.rsa.load(Ordering::Relaxed),
+            ec: self.ec.load(Ordering::Relaxed),
+            ed25519: self.ed25519.load(Ordering::Relaxed),
+            other: self.other.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// host name and rule name
+pub(crate) type ClientCertRouteKey = (String, String);
+pub(crate) type ClientCertRouteSnapshot = AHashMap<ClientCertRouteKey, u64>;
+
+#[derive(Default)]
+pub(crate) struct ClientCertRouteStats {
+    rules: Mutex<AHashMap<ClientCertRouteKey, u64>>,
+}
+
+impl ClientCertRouteStats {
+    pub(crate) fn add(&self, host: &str, rule: &str) {
+        let mut rules = self.rules.lock().unwrap();
+        *rules
+            .entry((host.to_string(), rule.to_string()))
+            .or_insert(0) += 1;
+    }
+
+    pub(crate) fn snapshot(&self) -> ClientCertRouteSnapshot {
+        self.rules.lock().unwrap().clone()
+    }
+}
+
+pub(crate) struct BackendPoolStats {
+    idle: AtomicIsize,
+    reused: AtomicU64,
+    created: AtomicU64,
+    wait_time_recorder: HistogramRecorder<u64>,
+    wait_time: Arc<HistogramStats>,
+}
+
+#[derive(Clone)]
+pub(crate) struct BackendPoolSnapshot {
+    pub(crate) idle: isize,
+    pub(crate) reused: u64,
+    pub(crate) created: u64,
+    pub(crate) wait_time: Arc<HistogramStats>,
+}
+
+impl BackendPoolStats {
+    fn new() -> Self {
+        let (wait_time_recorder, wait_time) = HistogramMetricsConfig::default()
+            .build_spawned(g3_daemon::runtime::main_handle().cloned());
+        BackendPoolStats {
+            idle: AtomicIsize::new(0),
+            reused: AtomicU64::new(0),
+            created: AtomicU64::new(0),
+            wait_time_recorder,
+            wait_time,
+        }
+    }
+
+    pub(crate) fn inc_idle(&self) {
+        self.idle.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn dec_idle(&self, count: usize) {
+        self.idle.fetch_sub(count as isize, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_reused(&self) {
+        self.reused.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_created(&self) {
+        self.created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_wait_time(&self, dur: Duration) {
+        let _ = self.wait_time_recorder.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn snapshot(&self) -> BackendPoolSnapshot {
+        BackendPoolSnapshot {
+            idle: self.idle.load(Ordering::Relaxed),
+            reused: self.reused.load(Ordering::Relaxed),
+            created: self.created.load(Ordering::Relaxed),
+            wait_time: self.wait_time.clone(),
+        }
+    }
+}
+
+/// host name as the key
+pub(crate) type BackendPoolSnapshotMap = AHashMap<String, BackendPoolSnapshot>;
+
+/// The backend pool stats of all hosts, which will be kept across reloads
+#[derive(Default)]
+pub(crate) struct BackendPoolStatsMap {
+    hosts: Mutex<AHashMap<String, Arc<BackendPoolStats>>>,
+}
+
+impl BackendPoolStatsMap {
+    pub(crate) fn get_or_insert(&self, host: &str) -> Arc<BackendPoolStats> {
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(BackendPoolStats::new()))
+            .clone()
+    }
+
+    pub(crate) fn snapshot(&self) -> BackendPoolSnapshotMap {
+        let hosts = self.hosts.lock().unwrap();
+        hosts
+            .iter()
+            .map(|(host, stats)| (host.clone(), stats.snapshot()))
+            .collect()
+    }
+}
+
+pub(crate) struct HostHealthStats {
+    healthy: AtomicBool,
+    unhealthy_total: AtomicU64,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct HostHealthSnapshot {
+    pub(crate) healthy: bool,
+    /// the number of times the host has been marked as unhealthy
+    pub(crate) unhealthy_total: u64,
+}
+
+impl HostHealthStats {
+    fn new() -> Self {
+        HostHealthStats {
+            healthy: AtomicBool::new(true),
+            unhealthy_total: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn set_healthy(&self) {
+        self.healthy.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_unhealthy(&self) {
+        self.healthy.store(false, Ordering::Relaxed);
+        self.unhealthy_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> HostHealthSnapshot {
+        HostHealthSnapshot {
+            healthy: self.healthy.load(Ordering::Relaxed),
+            unhealthy_total: self.unhealthy_total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// host name as the key
+pub(crate) type HostHealthSnapshotMap = AHashMap<String, HostHealthSnapshot>;
+
+/// The health check stats of all hosts, which will be kept across reloads
+#[derive(Default)]
+pub(crate) struct HostHealthStatsMap {
+    hosts: Mutex<AHashMap<String, Arc<HostHealthStats>>>,
+}
+
+impl HostHealthStatsMap {
+    pub(crate) fn get_or_insert(&self, host: &str) -> Arc<HostHealthStats> {
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(HostHealthStats::new()))
+            .clone()
+    }
+
+    /// Remove the stats of the host, which should be called if the health check is disabled
+    pub(crate) fn remove(&self, host: &str) {
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts.remove(host);
+    }
+
+    pub(crate) fn snapshot(&self) -> HostHealthSnapshotMap {
+        let hosts = self.hosts.lock().unwrap();
+        hosts
+            .iter()
+            .map(|(host, stats)| (host.clone(), stats.snapshot()))
+            .collect()
+    }
+}
@@ -11,6 +11,7 @@ use tokio::time::Instant;
 use uuid::Uuid;
 
 use g3_daemon::server::ClientConnectionInfo;
+use g3_types::net::TcpMiscSockOpts;
 
 #[derive(Clone)]
 pub(crate) enum ServerTaskStage {
@@ -50,6 +51,14 @@ pub(crate) struct ServerTaskNotes {
     pub(crate) id: Uuid,
     pub(crate) wait_time: Duration,
     pub(crate) ready_time: Duration,
+    /// The DSCP value to set on the backend connections
+    pub(crate) backend_dscp: Option<u8>,
+    /// The socket options to set on the backend connections after connected
+    pub(crate) upstream_misc_opts: Option<TcpMiscSockOpts>,
+    /// The size of the PROXY protocol header sent to the backend, not counted as relayed bytes
+    pub(crate) upstream_proxy_header_bytes: Option<u64>,
+    /// The time when the client connection reaches its max lifetime
+    pub(crate) lifetime_deadline: Option<Instant>,
 }
 
 impl ServerTaskNotes {
@@ -64,6 +73,10 @@ impl ServerTaskNotes {
             id: uuid,
             wait_time,
             ready_time: Duration::default(),
+            backend_dscp: None,
+            upstream_misc_opts: None,
+            upstream_proxy_header_bytes: None,
+            lifetime_deadline: None,
         }
     }
 
@@ -87,6 +100,12 @@ impl ServerTaskNotes {
         self.create_ins.elapsed()
     }
 
+    /// Get the remaining lifetime of the client connection, if a max lifetime has been set
+    pub(crate) fn lifetime_remaining(&self) -> Option<Duration> {
+        self.lifetime_deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
     pub(crate) fn mark_relaying(&mut self) {
         self.stage = ServerTaskStage::Relaying;
         self.ready_time = self.create_ins.elapsed();
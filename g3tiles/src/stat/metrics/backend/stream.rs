@@ -15,6 +15,7 @@ use crate::module::stream::{StreamBackendDurationStats, StreamBackendStats};
 
 const METRIC_NAME_STREAM_CONN_ATTEMPT: &str = "backend.stream.connection.attempt";
 const METRIC_NAME_STREAM_CONN_ESTABLISHED: &str = "backend.stream.connection.established";
+const METRIC_NAME_STREAM_DISCOVER_FAILED: &str = "backend.stream.discover.failed";
 
 const METRIC_NAME_STREAM_CONNECT_DURATION: &str = "backend.stream.connect.duration";
 
@@ -33,6 +34,7 @@ static STREAM_DURATION_STATS_MAP: Mutex<GlobalStatsMap<Arc<StreamBackendDuration
 struct StreamBackendSnapshot {
     conn_attempt: u64,
     conn_established: u64,
+    discover_failed: u64,
 }
 
 pub(crate) fn push_stream_stats(stats: Arc<StreamBackendStats>) {
@@ -96,6 +98,7 @@ fn emit_stream_stats(
 
     emit_count!(conn_attempt, METRIC_NAME_STREAM_CONN_ATTEMPT);
     emit_count!(conn_established, METRIC_NAME_STREAM_CONN_ESTABLISHED);
+    emit_count!(discover_failed, METRIC_NAME_STREAM_DISCOVER_FAILED);
 }
 
 fn emit_stream_duration_stats(client: &mut StatsdClient, stats: &Arc<StreamBackendDurationStats>) {
@@ -10,12 +10,20 @@ use foldhash::fast::FixedState;
 
 use g3_daemon::listen::{ListenSnapshot, ListenStats};
 use g3_daemon::metrics::{
-    ServerMetricExt, TAG_KEY_TRANSPORT, TRANSPORT_TYPE_TCP, TRANSPORT_TYPE_UDP,
+    ServerMetricExt, TAG_KEY_QUANTILE, TAG_KEY_TRANSPORT, TRANSPORT_TYPE_TCP, TRANSPORT_TYPE_UDP,
 };
+use g3_daemon::server::{AcceptRejectSnapshot, TransferSnapshot};
 use g3_statsd_client::{StatsdClient, StatsdTagGroup};
+use g3_types::net::RollingTicketerSnapshot;
 use g3_types::stats::{StatId, TcpIoSnapshot, UdpIoSnapshot};
 
-use crate::serve::ArcServerStats;
+use crate::config::server::openssl_proxy::OpensslCertKeyType;
+use crate::serve::{
+    ArcServerStats, BackendPoolSnapshotMap, CertReloadSnapshot, CertResolverSnapshot,
+    ClientCertRouteSnapshot, ClientHelloBufferSnapshot, ClientIpLimitSnapshot, EarlyDataSnapshot,
+    HandshakeLimitSnapshot, HostHealthSnapshotMap, HttpAwareSnapshot, ServedCertSnapshot,
+    SessionSniMismatchSnapshot,
+};
 
 const METRIC_NAME_SERVER_CONN_TOTAL: &str = "server.connection.total";
 const METRIC_NAME_SERVER_TASK_TOTAL: &str = "server.task.total";
@@ -24,6 +32,52 @@ const METRIC_NAME_SERVER_IO_IN_BYTES: &str = "server.traffic.in.bytes";
 const METRIC_NAME_SERVER_IO_IN_PACKETS: &str = "server.traffic.in.packets";
 const METRIC_NAME_SERVER_IO_OUT_BYTES: &str = "server.traffic.out.bytes";
 const METRIC_NAME_SERVER_IO_OUT_PACKETS: &str = "server.traffic.out.packets";
+const METRIC_NAME_SERVER_CERT_RESOLVER_HIT: &str = "server.cert_resolver.hit";
+const METRIC_NAME_SERVER_CERT_RESOLVER_MISS: &str = "server.cert_resolver.miss";
+const METRIC_NAME_SERVER_CERT_RESOLVER_TIMEOUT: &str = "server.cert_resolver.timeout";
+const METRIC_NAME_SERVER_CERT_RESOLVER_ERROR: &str = "server.cert_resolver.error";
+const METRIC_NAME_SERVER_TLS_SERVED_CERT: &str = "server.tls.served_cert";
+const METRIC_NAME_SERVER_TLS_CLIENT_CERT_ROUTE: &str = "server.tls.client_cert_route";
+const METRIC_NAME_SERVER_TLS_HANDSHAKE_IN_FLIGHT: &str = "server.tls.handshake.in_flight";
+const METRIC_NAME_SERVER_TLS_HANDSHAKE_QUEUED: &str = "server.tls.handshake.queued";
+const METRIC_NAME_SERVER_TLS_HANDSHAKE_REJECTED: &str = "server.tls.handshake.rejected";
+const METRIC_NAME_SERVER_TLS_CLIENT_IP_LIMITED: &str = "server.tls.client_ip.limited";
+const METRIC_NAME_SERVER_TLS_CLIENT_IP_REJECTED: &str = "server.tls.client_ip.rejected";
+const METRIC_NAME_SERVER_TLS_CLIENT_HELLO_BUFFER_IN_USE: &str =
+    "server.tls.client_hello_buffer.in_use";
+const METRIC_NAME_SERVER_TLS_CLIENT_HELLO_BUFFER_ALLOCATED: &str =
+    "server.tls.client_hello_buffer.overflow_allocated";
+const METRIC_NAME_SERVER_TLS_CLIENT_HELLO_BUFFER_WAITED: &str =
+    "server.tls.client_hello_buffer.overflow_waited";
+const METRIC_NAME_SERVER_TLS_CLIENT_HELLO_BUFFER_REJECTED: &str =
+    "server.tls.client_hello_buffer.overflow_rejected";
+const METRIC_NAME_SERVER_TLS_EARLY_DATA_ACCEPTED: &str = "server.tls.early_data.accepted";
+const METRIC_NAME_SERVER_TLS_EARLY_DATA_REJECTED: &str = "server.tls.early_data.rejected";
+const METRIC_NAME_SERVER_TLS_EARLY_DATA_REPLAYED: &str = "server.tls.early_data.replayed";
+const METRIC_NAME_SERVER_HTTP_AWARE_FALLBACK: &str = "server.http_aware.fallback";
+const METRIC_NAME_SERVER_TLS_SESSION_SNI_MISMATCH_ALLOWED: &str =
+    "server.tls.session_sni_mismatch.allowed";
+const METRIC_NAME_SERVER_TLS_SESSION_SNI_MISMATCH_FULL_HANDSHAKE: &str =
+    "server.tls.session_sni_mismatch.full_handshake";
+const METRIC_NAME_SERVER_TLS_SESSION_SNI_MISMATCH_REJECTED: &str =
+    "server.tls.session_sni_mismatch.rejected";
+const METRIC_NAME_SERVER_TLS_CERT_RELOAD_SUCCESS: &str = "server.tls.cert_reload.success";
+const METRIC_NAME_SERVER_TLS_CERT_RELOAD_FAILED: &str = "server.tls.cert_reload.failed";
+const METRIC_NAME_SERVER_TLS_TICKET_ISSUED: &str = "server.tls.ticket.issued";
+const METRIC_NAME_SERVER_TLS_TICKET_RESUMED: &str = "server.tls.ticket.resumed";
+const METRIC_NAME_SERVER_TLS_TICKET_REJECTED: &str = "server.tls.ticket.rejected";
+const METRIC_NAME_SERVER_TLS_TICKET_KEY_AGE: &str = "server.tls.ticket.key_age";
+const METRIC_NAME_SERVER_BACKEND_POOL_IDLE: &str = "server.backend_pool.idle";
+const METRIC_NAME_SERVER_BACKEND_POOL_REUSED: &str = "server.backend_pool.reused";
+const METRIC_NAME_SERVER_BACKEND_POOL_CREATED: &str = "server.backend_pool.created";
+const METRIC_NAME_SERVER_BACKEND_POOL_WAIT_TIME: &str = "server.backend_pool.wait_time";
+const METRIC_NAME_SERVER_HOST_HEALTHY: &str = "server.host.healthy";
+const METRIC_NAME_SERVER_HOST_UNHEALTHY: &str = "server.host.unhealthy";
+
+const TAG_KEY_KEY_TYPE: &str = "key_type";
+const TAG_KEY_HOST: &str = "host";
+const TAG_KEY_KEY_GENERATION: &str = "key_generation";
+const TAG_KEY_RULE: &str = "rule";
 
 type ServerStatsValue = (ArcServerStats, ServerSnapshot);
 type ListenStatsValue = (Arc<ListenStats>, ListenSnapshot);
@@ -39,6 +93,21 @@ struct ServerSnapshot {
     task_total: u64,
     tcp: TcpIoSnapshot,
     udp: UdpIoSnapshot,
+    cert_resolver: CertResolverSnapshot,
+    served_cert: ServedCertSnapshot,
+    client_cert_route: ClientCertRouteSnapshot,
+    handshake_limit: HandshakeLimitSnapshot,
+    client_ip_limit: ClientIpLimitSnapshot,
+    client_hello_buffer: ClientHelloBufferSnapshot,
+    early_data: EarlyDataSnapshot,
+    http_aware: HttpAwareSnapshot,
+    session_sni_mismatch: SessionSniMismatchSnapshot,
+    cert_reload: CertReloadSnapshot,
+    tls_ticket: RollingTicketerSnapshot,
+    accept_reject: AcceptRejectSnapshot,
+    backend_pool: BackendPoolSnapshotMap,
+    host_health: HostHealthSnapshotMap,
+    transfer: TransferSnapshot,
 }
 
 pub(in crate::stat) fn sync_stats() {
@@ -117,6 +186,125 @@ fn emit_server_stats(client: &mut StatsdClient, stats: &ArcServerStats, snap: &m
     if let Some(udp_io_stats) = stats.udp_io_snapshot() {
         emit_udp_io_to_statsd(client, udp_io_stats, &mut snap.udp, &common_tags);
     }
+
+    if let Some(resolver_stats) = stats.cert_resolver_snapshot() {
+        emit_cert_resolver_to_statsd(
+            client,
+            resolver_stats,
+            &mut snap.cert_resolver,
+            &common_tags,
+        );
+    }
+
+    if let Some(served_cert_stats) = stats.served_cert_snapshot() {
+        emit_served_cert_to_statsd(
+            client,
+            served_cert_stats,
+            &mut snap.served_cert,
+            &common_tags,
+        );
+    }
+
+    if let Some(route_stats) = stats.client_cert_route_snapshot() {
+        emit_client_cert_route_to_statsd(
+            client,
+            route_stats,
+            &mut snap.client_cert_route,
+            &common_tags,
+        );
+    }
+
+    if let Some(limit_stats) = stats.handshake_limit_snapshot() {
+        emit_handshake_limit_to_statsd(
+            client,
+            limit_stats,
+            &mut snap.handshake_limit,
+            &common_tags,
+        );
+    }
+
+    if let Some(limit_stats) = stats.client_ip_limit_snapshot() {
+        emit_client_ip_limit_to_statsd(
+            client,
+            limit_stats,
+            &mut snap.client_ip_limit,
+            &common_tags,
+        );
+    }
+
+    if let Some(buffer_stats) = stats.client_hello_buffer_snapshot() {
+        emit_client_hello_buffer_to_statsd(
+            client,
+            buffer_stats,
+            &mut snap.client_hello_buffer,
+            &common_tags,
+        );
+    }
+
+    if let Some(early_data_stats) = stats.early_data_snapshot() {
+        emit_early_data_to_statsd(client, early_data_stats, &mut snap.early_data, &common_tags);
+    }
+
+    if let Some(http_aware_stats) = stats.http_aware_snapshot() {
+        let new_value = http_aware_stats.fallback;
+        let diff_value = new_value.wrapping_sub(snap.http_aware.fallback);
+        client
+            .count_with_tags(
+                METRIC_NAME_SERVER_HTTP_AWARE_FALLBACK,
+                diff_value,
+                &common_tags,
+            )
+            .send();
+        snap.http_aware.fallback = new_value;
+    }
+
+    if let Some(mismatch_stats) = stats.session_sni_mismatch_snapshot() {
+        emit_session_sni_mismatch_to_statsd(
+            client,
+            mismatch_stats,
+            &mut snap.session_sni_mismatch,
+            &common_tags,
+        );
+    }
+
+    if let Some(cert_reload_stats) = stats.cert_reload_snapshot() {
+        emit_cert_reload_to_statsd(
+            client,
+            cert_reload_stats,
+            &mut snap.cert_reload,
+            &common_tags,
+        );
+    }
+
+    if let Some(ticket_stats) = stats.tls_ticket_snapshot() {
+        emit_tls_ticket_to_statsd(client, ticket_stats, &mut snap.tls_ticket, &common_tags);
+    }
+
+    if let Some(accept_reject_stats) = stats.accept_reject_snapshot() {
+        g3_daemon::metrics::emit_accept_reject_stats(
+            client,
+            accept_reject_stats,
+            &mut snap.accept_reject,
+            &common_tags,
+        );
+    }
+
+    if let Some(pool_stats) = stats.backend_pool_snapshot() {
+        emit_backend_pool_to_statsd(client, pool_stats, &mut snap.backend_pool, &common_tags);
+    }
+
+    if let Some(health_stats) = stats.host_health_snapshot() {
+        emit_host_health_to_statsd(client, health_stats, &mut snap.host_health, &common_tags);
+    }
+
+    if let Some(transfer_stats) = stats.transfer_stats() {
+        g3_daemon::metrics::emit_transfer_stats(
+            client,
+            &transfer_stats,
+            &mut snap.transfer,
+            &common_tags,
+        );
+    }
 }
 
 fn emit_tcp_io_to_statsd(
@@ -172,3 +360,390 @@ fn emit_udp_io_to_statsd(
     emit_field!(out_packets, METRIC_NAME_SERVER_IO_OUT_PACKETS);
     emit_field!(out_bytes, METRIC_NAME_SERVER_IO_OUT_BYTES);
 }
+
+fn emit_cert_resolver_to_statsd(
+    client: &mut StatsdClient,
+    stats: CertResolverSnapshot,
+    snap: &mut CertResolverSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    macro_rules! emit_field {
+        ($field:ident, $name:expr) => {
+            let new_value = stats.$field;
+            let diff_value = new_value.wrapping_sub(snap.$field);
+            client
+                .count_with_tags($name, diff_value, common_tags)
+                .send();
+            snap.$field = new_value;
+        };
+    }
+
+    emit_field!(hit, METRIC_NAME_SERVER_CERT_RESOLVER_HIT);
+    emit_field!(miss, METRIC_NAME_SERVER_CERT_RESOLVER_MISS);
+    emit_field!(timeout, METRIC_NAME_SERVER_CERT_RESOLVER_TIMEOUT);
+    emit_field!(error, METRIC_NAME_SERVER_CERT_RESOLVER_ERROR);
+}
+
+fn emit_served_cert_to_statsd(
+    client: &mut StatsdClient,
+    stats: ServedCertSnapshot,
+    snap: &mut ServedCertSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    macro_rules! emit_field {
+        ($field:ident, $key_type:expr) => {
+            let new_value = stats.$field;
+            let diff_value = new_value.wrapping_sub(snap.$field);
+            client
+                .count_with_tags(METRIC_NAME_SERVER_TLS_SERVED_CERT, diff_value, common_tags)
+                .with_tag(TAG_KEY_KEY_TYPE, $key_type.as_str())
+                .send();
+            snap.$field = new_value;
+        };
+    }
+
+    emit_field!(rsa, OpensslCertKeyType::Rsa);
+    emit_field!(ec, OpensslCertKeyType::Ec);
+    emit_field!(ed25519, OpensslCertKeyType::Ed25519);
+    emit_field!(other, OpensslCertKeyType::Other);
+}
+
+fn emit_client_cert_route_to_statsd(
+    client: &mut StatsdClient,
+    stats: ClientCertRouteSnapshot,
+    snap: &mut ClientCertRouteSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    for (key, new_value) in &stats {
+        let (host, rule) = key;
+        let old_value = snap.get(key).copied().unwrap_or_default();
+        let diff_value = new_value.wrapping_sub(old_value);
+        client
+            .count_with_tags(
+                METRIC_NAME_SERVER_TLS_CLIENT_CERT_ROUTE,
+                diff_value,
+                common_tags,
+            )
+            .with_tag(TAG_KEY_HOST, host)
+            .with_tag(TAG_KEY_RULE, rule)
+            .send();
+    }
+    *snap = stats;
+}
+
+fn emit_handshake_limit_to_statsd(
+    client: &mut StatsdClient,
+    stats: HandshakeLimitSnapshot,
+    snap: &mut HandshakeLimitSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    client
+        .gauge_with_tags(
+            METRIC_NAME_SERVER_TLS_HANDSHAKE_IN_FLIGHT,
+            stats.in_flight,
+            common_tags,
+        )
+        .send();
+    client
+        .gauge_with_tags(
+            METRIC_NAME_SERVER_TLS_HANDSHAKE_QUEUED,
+            stats.queued,
+            common_tags,
+        )
+        .send();
+
+    let diff_value = stats.rejected.wrapping_sub(snap.rejected);
+    client
+        .count_with_tags(
+            METRIC_NAME_SERVER_TLS_HANDSHAKE_REJECTED,
+            diff_value,
+            common_tags,
+        )
+        .send();
+    snap.rejected = stats.rejected;
+}
+
+fn emit_client_ip_limit_to_statsd(
+    client: &mut StatsdClient,
+    stats: ClientIpLimitSnapshot,
+    snap: &mut ClientIpLimitSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    let diff_value = stats.limited.wrapping_sub(snap.limited);
+    client
+        .count_with_tags(
+            METRIC_NAME_SERVER_TLS_CLIENT_IP_LIMITED,
+            diff_value,
+            common_tags,
+        )
+        .send();
+    snap.limited = stats.limited;
+
+    let diff_value = stats.rejected.wrapping_sub(snap.rejected);
+    client
+        .count_with_tags(
+            METRIC_NAME_SERVER_TLS_CLIENT_IP_REJECTED,
+            diff_value,
+            common_tags,
+        )
+        .send();
+    snap.rejected = stats.rejected;
+}
+
+fn emit_client_hello_buffer_to_statsd(
+    client: &mut StatsdClient,
+    stats: ClientHelloBufferSnapshot,
+    snap: &mut ClientHelloBufferSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    client
+        .gauge_with_tags(
+            METRIC_NAME_SERVER_TLS_CLIENT_HELLO_BUFFER_IN_USE,
+            stats.in_use,
+            common_tags,
+        )
+        .send();
+
+    macro_rules! emit_field {
+        ($field:ident, $name:expr) => {
+            let new_value = stats.$field;
+            let diff_value = new_value.wrapping_sub(snap.$field);
+            client
+                .count_with_tags($name, diff_value, common_tags)
+                .send();
+            snap.$field = new_value;
+        };
+    }
+
+    emit_field!(
+        overflow_allocated,
+        METRIC_NAME_SERVER_TLS_CLIENT_HELLO_BUFFER_ALLOCATED
+    );
+    emit_field!(
+        overflow_waited,
+        METRIC_NAME_SERVER_TLS_CLIENT_HELLO_BUFFER_WAITED
+    );
+    emit_field!(
+        overflow_rejected,
+        METRIC_NAME_SERVER_TLS_CLIENT_HELLO_BUFFER_REJECTED
+    );
+}
+
+fn emit_early_data_to_statsd(
+    client: &mut StatsdClient,
+    stats: EarlyDataSnapshot,
+    snap: &mut EarlyDataSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    macro_rules! emit_field {
+        ($field:ident, $name:expr) => {
+            let new_value = stats.$field;
+            let diff_value = new_value.wrapping_sub(snap.$field);
+            client
+                .count_with_tags($name, diff_value, common_tags)
+                .send();
+            snap.$field = new_value;
+        };
+    }
+
+    emit_field!(accepted, METRIC_NAME_SERVER_TLS_EARLY_DATA_ACCEPTED);
+    emit_field!(rejected, METRIC_NAME_SERVER_TLS_EARLY_DATA_REJECTED);
+    emit_field!(replayed, METRIC_NAME_SERVER_TLS_EARLY_DATA_REPLAYED);
+}
+
+fn emit_session_sni_mismatch_to_statsd(
+    client: &mut StatsdClient,
+    stats: SessionSniMismatchSnapshot,
+    snap: &mut SessionSniMismatchSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    macro_rules! emit_field {
+        ($field:ident, $name:expr) => {
+            let new_value = stats.$field;
+            let diff_value = new_value.wrapping_sub(snap.$field);
+            client
+                .count_with_tags($name, diff_value, common_tags)
+                .send();
+            snap.$field = new_value;
+        };
+    }
+
+    emit_field!(allowed, METRIC_NAME_SERVER_TLS_SESSION_SNI_MISMATCH_ALLOWED);
+    emit_field!(
+        full_handshake,
+        METRIC_NAME_SERVER_TLS_SESSION_SNI_MISMATCH_FULL_HANDSHAKE
+    );
+    emit_field!(
+        rejected,
+        METRIC_NAME_SERVER_TLS_SESSION_SNI_MISMATCH_REJECTED
+    );
+}
+
+fn emit_cert_reload_to_statsd(
+    client: &mut StatsdClient,
+    stats: CertReloadSnapshot,
+    snap: &mut CertReloadSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    macro_rules! emit_field {
+        ($field:ident, $name:expr) => {
+            let new_value = stats.$field;
+            let diff_value = new_value.wrapping_sub(snap.$field);
+            client
+                .count_with_tags($name, diff_value, common_tags)
+                .send();
+            snap.$field = new_value;
+        };
+    }
+
+    emit_field!(success, METRIC_NAME_SERVER_TLS_CERT_RELOAD_SUCCESS);
+    emit_field!(failed, METRIC_NAME_SERVER_TLS_CERT_RELOAD_FAILED);
+}
+
+fn emit_tls_ticket_to_statsd(
+    client: &mut StatsdClient,
+    stats: RollingTicketerSnapshot,
+    snap: &mut RollingTicketerSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    // the ticketer will be recreated if the ticket config changed on reload
+    if stats.issued < snap.issued
+        || stats.resumed_current < snap.resumed_current
+        || stats.resumed_previous < snap.resumed_previous
+        || stats.rejected_unknown_key < snap.rejected_unknown_key
+    {
+        *snap = RollingTicketerSnapshot::default();
+    }
+
+    let new_value = stats.issued;
+    client
+        .count_with_tags(
+            METRIC_NAME_SERVER_TLS_TICKET_ISSUED,
+            new_value - snap.issued,
+            common_tags,
+        )
+        .send();
+    snap.issued = new_value;
+
+    macro_rules! emit_resumed {
+        ($field:ident, $generation:literal) => {
+            let new_value = stats.$field;
+            client
+                .count_with_tags(
+                    METRIC_NAME_SERVER_TLS_TICKET_RESUMED,
+                    new_value - snap.$field,
+                    common_tags,
+                )
+                .with_tag(TAG_KEY_KEY_GENERATION, $generation)
+                .send();
+            snap.$field = new_value;
+        };
+    }
+
+    emit_resumed!(resumed_current, "current");
+    emit_resumed!(resumed_previous, "previous");
+
+    let new_value = stats.rejected_unknown_key;
+    client
+        .count_with_tags(
+            METRIC_NAME_SERVER_TLS_TICKET_REJECTED,
+            new_value - snap.rejected_unknown_key,
+            common_tags,
+        )
+        .send();
+    snap.rejected_unknown_key = new_value;
+
+    client
+        .gauge_with_tags(
+            METRIC_NAME_SERVER_TLS_TICKET_KEY_AGE,
+            stats.encrypt_key_age.as_secs(),
+            common_tags,
+        )
+        .send();
+}
+
+fn emit_backend_pool_to_statsd(
+    client: &mut StatsdClient,
+    stats: BackendPoolSnapshotMap,
+    snap: &mut BackendPoolSnapshotMap,
+    common_tags: &StatsdTagGroup,
+) {
+    for (host, new_value) in &stats {
+        client
+            .gauge_with_tags(
+                METRIC_NAME_SERVER_BACKEND_POOL_IDLE,
+                new_value.idle,
+                common_tags,
+            )
+            .with_tag(TAG_KEY_HOST, host)
+            .send();
+
+        let (old_reused, old_created) = snap
+            .get(host)
+            .map(|s| (s.reused, s.created))
+            .unwrap_or_default();
+        client
+            .count_with_tags(
+                METRIC_NAME_SERVER_BACKEND_POOL_REUSED,
+                new_value.reused.wrapping_sub(old_reused),
+                common_tags,
+            )
+            .with_tag(TAG_KEY_HOST, host)
+            .send();
+        client
+            .count_with_tags(
+                METRIC_NAME_SERVER_BACKEND_POOL_CREATED,
+                new_value.created.wrapping_sub(old_created),
+                common_tags,
+            )
+            .with_tag(TAG_KEY_HOST, host)
+            .send();
+
+        new_value.wait_time.foreach_stat(|_, qs, v| {
+            if v > 0_f64 {
+                client
+                    .gauge_float_with_tags(
+                        METRIC_NAME_SERVER_BACKEND_POOL_WAIT_TIME,
+                        v,
+                        common_tags,
+                    )
+                    .with_tag(TAG_KEY_HOST, host)
+                    .with_tag(TAG_KEY_QUANTILE, qs)
+                    .send();
+            }
+        });
+    }
+    *snap = stats;
+}
+
+fn emit_host_health_to_statsd(
+    client: &mut StatsdClient,
+    stats: HostHealthSnapshotMap,
+    snap: &mut HostHealthSnapshotMap,
+    common_tags: &StatsdTagGroup,
+) {
+    for (host, new_value) in &stats {
+        client
+            .gauge_with_tags(
+                METRIC_NAME_SERVER_HOST_HEALTHY,
+                u8::from(new_value.healthy),
+                common_tags,
+            )
+            .with_tag(TAG_KEY_HOST, host)
+            .send();
+
+        let old_unhealthy = snap
+            .get(host)
+            .map(|s| s.unhealthy_total)
+            .unwrap_or_default();
+        client
+            .count_with_tags(
+                METRIC_NAME_SERVER_HOST_UNHEALTHY,
+                new_value.unhealthy_total.wrapping_sub(old_unhealthy),
+                common_tags,
+            )
+            .with_tag(TAG_KEY_HOST, host)
+            .send();
+    }
+    *snap = stats;
+}
@@ -0,0 +1,136 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+//! Fixtures shared by the unit tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use openssl::asn1::{Asn1Integer, Asn1Time};
+use openssl::bn::BigNum;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslMethod};
+use openssl::x509::extension::BasicConstraints;
+use openssl::x509::{X509, X509Builder, X509Name, X509NameBuilder};
+
+static TEST_DIR_ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A temp directory that will be removed on drop
+pub(crate) struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub(crate) fn new(prefix: &str) -> Self {
+        let id = TEST_DIR_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let path =
+            std::env::temp_dir().join(format!("g3tiles_{prefix}_{}_{id}", std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+pub(crate) fn ec_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+pub(crate) fn rsa_key() -> PKey<Private> {
+    PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap()
+}
+
+pub(crate) fn common_name(name: &str) -> X509Name {
+    let mut name_builder = X509NameBuilder::new().unwrap();
+    name_builder
+        .append_entry_by_nid(Nid::COMMONNAME, name)
+        .unwrap();
+    name_builder.build()
+}
+
+fn cert_builder(subject: &X509Name, key: &PKey<Private>, serial: u32) -> X509Builder {
+    let mut builder = X509Builder::new().unwrap();
+    builder.set_version(2).unwrap();
+    let serial = Asn1Integer::from_bn(&BigNum::from_u32(serial).unwrap()).unwrap();
+    builder.set_serial_number(&serial).unwrap();
+    builder.set_subject_name(subject).unwrap();
+    builder.set_pubkey(key).unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    builder
+}
+
+/// Build a self-signed leaf certificate which is valid for one day
+pub(crate) fn self_signed_cert(subject: &X509Name, key: &PKey<Private>, serial: u32) -> X509 {
+    let mut builder = cert_builder(subject, key, serial);
+    builder.set_issuer_name(subject).unwrap();
+    builder.sign(key, MessageDigest::sha256()).unwrap();
+    builder.build()
+}
+
+/// Build a self-signed CA certificate which is valid for one day
+pub(crate) fn ca_cert(subject: &X509Name, key: &PKey<Private>) -> X509 {
+    let mut builder = cert_builder(subject, key, 1);
+    builder.set_issuer_name(subject).unwrap();
+    let ca = BasicConstraints::new().critical().ca().build().unwrap();
+    builder.append_extension(ca).unwrap();
+    builder.sign(key, MessageDigest::sha256()).unwrap();
+    builder.build()
+}
+
+/// Build a certificate signed by the CA which is valid for one day
+pub(crate) fn issued_cert(
+    subject: &X509Name,
+    key: &PKey<Private>,
+    ca: (&X509, &PKey<Private>),
+    serial: u32,
+) -> X509 {
+    let mut builder = cert_builder(subject, key, serial);
+    builder.set_issuer_name(ca.0.subject_name()).unwrap();
+    builder.sign(ca.1, MessageDigest::sha256()).unwrap();
+    builder.build()
+}
+
+/// Write the certificate and the private key to `<name>.crt` and `<name>.key` in PEM format
+pub(crate) fn write_cert_pair(dir: &Path, name: &str, cert: &X509, key: &PKey<Private>) {
+    write_cert(&dir.join(format!("{name}.crt")), cert);
+    write_key(&dir.join(format!("{name}.key")), key);
+}
+
+pub(crate) fn write_cert(path: &Path, cert: &X509) {
+    fs::write(path, cert.to_pem().unwrap()).unwrap();
+}
+
+pub(crate) fn write_key(path: &Path, key: &PKey<Private>) {
+    fs::write(path, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+}
+
+/// Get a TLS server acceptor builder with a new self-signed EC certificate
+pub(crate) fn server_acceptor_builder(server_name: &str) -> SslAcceptorBuilder {
+    let key = ec_key();
+    let cert = self_signed_cert(&common_name(server_name), &key, 1);
+    let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server()).unwrap();
+    builder.set_certificate(&cert).unwrap();
+    builder.set_private_key(&key).unwrap();
+    builder
+}
@@ -0,0 +1,74 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use clap::{Arg, ArgMatches, Command, value_parser};
+
+use g3_ctl::{CommandError, CommandResult};
+
+use g3tiles_proto::proc_capnp::proc_control;
+
+use crate::common::{parse_fetch_result, parse_operation_result};
+
+pub const COMMAND: &str = "ban";
+
+const SUBCOMMAND_LIST: &str = "list";
+const SUBCOMMAND_CLEAR: &str = "clear";
+const SUBCOMMAND_CLEAR_ARG_IP: &str = "ip";
+
+pub fn command() -> Command {
+    Command::new(COMMAND)
+        .about("Manage the temporary client ban list")
+        .subcommand_required(true)
+        .subcommand(Command::new(SUBCOMMAND_LIST).about("Show the active client bans"))
+        .subcommand(
+            Command::new(SUBCOMMAND_CLEAR)
+                .about("Clear the ban of the client ip, or all the bans if no ip specified")
+                .arg(
+                    Arg::new(SUBCOMMAND_CLEAR_ARG_IP)
+                        .value_name("IP ADDRESS")
+                        .num_args(1)
+                        .value_parser(value_parser!(IpAddr)),
+                ),
+        )
+}
+
+async fn list(client: &proc_control::Client) -> CommandResult<()> {
+    let req = client.list_client_ban_request();
+    let rsp = req.send().promise.await?;
+    let bans = parse_fetch_result(rsp.get()?.get_result()?)?
+        .to_str()
+        .map_err(|e| CommandError::Utf8 {
+            field: "result",
+            reason: e,
+        })?;
+    let value = serde_json::Value::from_str(bans)
+        .map_err(|e| CommandError::Cli(anyhow!("the ban list is not valid json: {e:?}")))?;
+    let content = serde_json::to_string_pretty(&value)
+        .map_err(|e| CommandError::Cli(anyhow!("failed to encode the ban list: {e:?}")))?;
+    println!("{content}");
+    Ok(())
+}
+
+async fn clear(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let mut req = client.clear_client_ban_request();
+    if let Some(ip) = args.get_one::<IpAddr>(SUBCOMMAND_CLEAR_ARG_IP) {
+        req.get().set_ip(ip.to_string().as_str());
+    }
+    let rsp = req.send().promise.await?;
+    parse_operation_result(rsp.get()?.get_result()?)
+}
+
+pub async fn run(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let (subcommand, args) = args.subcommand().unwrap();
+    match subcommand {
+        SUBCOMMAND_LIST => list(client).await,
+        SUBCOMMAND_CLEAR => clear(client, args).await,
+        _ => unreachable!(),
+    }
+}
@@ -14,6 +14,8 @@ mod common;
 mod proc;
 
 mod backend;
+mod ban;
+mod config;
 mod server;
 
 fn build_cli_args() -> Command {
@@ -28,6 +30,8 @@ fn build_cli_args() -> Command {
         .subcommand(proc::commands::reload_server())
         .subcommand(proc::commands::reload_discover())
         .subcommand(proc::commands::reload_backend())
+        .subcommand(config::command())
+        .subcommand(ban::command())
         .subcommand(server::command())
         .subcommand(backend::command())
 }
@@ -64,6 +68,8 @@ async fn main() -> anyhow::Result<()> {
                 proc::COMMAND_RELOAD_SERVER => proc::reload_server(&proc_control, args).await,
                 proc::COMMAND_RELOAD_DISCOVER => proc::reload_discover(&proc_control, args).await,
                 proc::COMMAND_RELOAD_BACKEND => proc::reload_backend(&proc_control, args).await,
+                config::COMMAND => config::run(&proc_control, args).await,
+                ban::COMMAND => ban::run(&proc_control, args).await,
                 server::COMMAND => server::run(&proc_control, args).await,
                 backend::COMMAND => backend::run(&proc_control, args).await,
                 _ => Err(CommandError::Cli(anyhow!(
@@ -6,22 +6,44 @@
 use clap::{Arg, ArgMatches, Command};
 use futures_util::future::TryFutureExt;
 
-use g3_ctl::CommandResult;
+use g3_ctl::{CommandError, CommandResult};
 
 use g3tiles_proto::proc_capnp::proc_control;
-use g3tiles_proto::server_capnp::server_control;
+use g3tiles_proto::server_capnp::{check_result, host_health_result, server_control};
+
+use crate::common::parse_operation_result;
 
 pub const COMMAND: &str = "server";
 
 const COMMAND_ARG_NAME: &str = "name";
 
 const SUBCOMMAND_STATUS: &str = "status";
+const SUBCOMMAND_CHECK: &str = "check";
+const SUBCOMMAND_CHECK_ARG_SNI: &str = "sni";
+const SUBCOMMAND_ROTATE_TICKET_KEY: &str = "rotate-ticket-key";
+const SUBCOMMAND_HOST_HEALTH: &str = "host-health";
 
 pub fn command() -> Command {
     Command::new(COMMAND)
         .arg(Arg::new(COMMAND_ARG_NAME).required(true).num_args(1))
         .subcommand_required(true)
         .subcommand(Command::new(SUBCOMMAND_STATUS))
+        .subcommand(
+            Command::new(SUBCOMMAND_CHECK).arg(
+                Arg::new(SUBCOMMAND_CHECK_ARG_SNI)
+                    .help("Do a TLS handshake to this server name, for TLS servers only")
+                    .long(SUBCOMMAND_CHECK_ARG_SNI)
+                    .num_args(1),
+            ),
+        )
+        .subcommand(
+            Command::new(SUBCOMMAND_ROTATE_TICKET_KEY)
+                .about("Replace the TLS ticket encrypt key right now"),
+        )
+        .subcommand(
+            Command::new(SUBCOMMAND_HOST_HEALTH)
+                .about("Show the health state of the hosts with health check enabled"),
+        )
 }
 
 async fn status(client: &server_control::Client) -> CommandResult<()> {
@@ -32,19 +54,106 @@ async fn status(client: &server_control::Client) -> CommandResult<()> {
     println!("alive tasks: {}", stats.get_alive_task_count());
     println!("total conn: {}", stats.get_total_conn_count());
     println!("total task: {}", stats.get_total_task_count());
+    println!("listening: {}", stats.get_listening());
+    for addr in stats.get_listen_addrs()?.iter() {
+        let addr = addr?.to_str().map_err(|e| CommandError::Utf8 {
+            field: "listen_addrs",
+            reason: e,
+        })?;
+        println!("listen addr: {addr}");
+    }
+    let last_error = stats
+        .get_last_listen_error()?
+        .to_str()
+        .map_err(|e| CommandError::Utf8 {
+            field: "last_listen_error",
+            reason: e,
+        })?;
+    if !last_error.is_empty() {
+        println!("last listen error: {last_error}");
+    }
     Ok(())
 }
 
+async fn check(client: &server_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let mut req = client.check_request();
+    if let Some(sni) = args.get_one::<String>(SUBCOMMAND_CHECK_ARG_SNI) {
+        req.get().set_sni(sni);
+    }
+    let rsp = req.send().promise.await?;
+    let result = rsp.get()?.get_result()?;
+    match result.which().unwrap() {
+        check_result::Which::Report(report) => {
+            let report = report?;
+            println!("connect time: {}us", report.get_connect_time());
+            let handshake_time = report.get_handshake_time();
+            if handshake_time > 0 {
+                println!("handshake time: {handshake_time}us");
+            }
+            Ok(())
+        }
+        check_result::Which::Err(reason) => Err(CommandError::api_error(-1, reason?)),
+    }
+}
+
+async fn rotate_ticket_key(client: &server_control::Client) -> CommandResult<()> {
+    let req = client.rotate_ticket_key_request();
+    let rsp = req.send().promise.await?;
+    parse_operation_result(rsp.get()?.get_result()?)
+}
+
+async fn host_health(client: &server_control::Client) -> CommandResult<()> {
+    let req = client.host_health_request();
+    let rsp = req.send().promise.await?;
+    let result = rsp.get()?.get_result()?;
+    match result.which().unwrap() {
+        host_health_result::Which::Hosts(hosts) => {
+            for host in hosts?.iter() {
+                let name = host.get_host()?.to_str().map_err(|e| CommandError::Utf8 {
+                    field: "host",
+                    reason: e,
+                })?;
+                let state = if host.get_healthy() {
+                    "healthy"
+                } else {
+                    "unhealthy"
+                };
+                println!(
+                    "{name}: {state}, marked unhealthy {} times",
+                    host.get_unhealthy_count()
+                );
+            }
+            Ok(())
+        }
+        host_health_result::Which::Err(reason) => Err(CommandError::api_error(-1, reason?)),
+    }
+}
+
 pub async fn run(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
     let name = args.get_one::<String>(COMMAND_ARG_NAME).unwrap();
 
-    let (subcommand, _) = args.subcommand().unwrap();
+    let (subcommand, args) = args.subcommand().unwrap();
     match subcommand {
         SUBCOMMAND_STATUS => {
             super::proc::get_server(client, name)
                 .and_then(|server| async move { status(&server).await })
                 .await
         }
+        SUBCOMMAND_CHECK => {
+            super::proc::get_server(client, name)
+                .and_then(|server| async move { check(&server, args).await })
+                .await
+        }
+        SUBCOMMAND_ROTATE_TICKET_KEY => {
+            super::proc::get_server(client, name)
+                .and_then(|server| async move { rotate_ticket_key(&server).await })
+                .await
+        }
+        SUBCOMMAND_HOST_HEALTH => {
+            super::proc::get_server(client, name)
+                .and_then(|server| async move { host_health(&server).await })
+                .await
+        }
         _ => unreachable!(),
     }
 }
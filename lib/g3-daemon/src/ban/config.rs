@@ -0,0 +1,248 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use ip_network::IpNetwork;
+use yaml_rust::Yaml;
+
+use super::ClientViolation;
+
+const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_THRESHOLD: u32 = 20;
+const DEFAULT_BAN_DURATION: Duration = Duration::from_secs(300);
+const DEFAULT_MAX_BAN_DURATION: Duration = Duration::from_secs(86400);
+const DEFAULT_OFFENSE_RESET: Duration = Duration::from_secs(86400);
+
+#[derive(Clone, Debug)]
+pub struct ClientBanConfig {
+    pub(super) window: Duration,
+    pub(super) threshold: u32,
+    pub(super) ban_duration: Duration,
+    pub(super) max_ban_duration: Duration,
+    pub(super) offense_reset: Duration,
+    weights: [u32; ClientViolation::ALL.len()],
+    exempt: Vec<IpNetwork>,
+}
+
+impl Default for ClientBanConfig {
+    fn default() -> Self {
+        ClientBanConfig {
+            window: DEFAULT_WINDOW,
+            threshold: DEFAULT_THRESHOLD,
+            ban_duration: DEFAULT_BAN_DURATION,
+            max_ban_duration: DEFAULT_MAX_BAN_DURATION,
+            offense_reset: DEFAULT_OFFENSE_RESET,
+            weights: [1; ClientViolation::ALL.len()],
+            exempt: Vec::new(),
+        }
+    }
+}
+
+impl ClientBanConfig {
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    pub fn set_threshold(&mut self, threshold: u32) {
+        self.threshold = threshold;
+    }
+
+    pub fn set_ban_duration(&mut self, duration: Duration) {
+        self.ban_duration = duration;
+    }
+
+    pub fn set_max_ban_duration(&mut self, duration: Duration) {
+        self.max_ban_duration = duration;
+    }
+
+    pub fn set_offense_reset(&mut self, duration: Duration) {
+        self.offense_reset = duration;
+    }
+
+    pub fn set_weight(&mut self, violation: ClientViolation, weight: u32) {
+        self.weights[violation.index()] = weight;
+    }
+
+    pub fn add_exempt(&mut self, network: IpNetwork) {
+        self.exempt.push(network);
+    }
+
+    pub(super) fn weight(&self, violation: ClientViolation) -> u32 {
+        self.weights[violation.index()]
+    }
+
+    pub(super) fn is_exempt(&self, ip: IpAddr) -> bool {
+        self.exempt.iter().any(|net| net.contains(ip))
+    }
+
+    /// Get the ban duration for the client that has already been banned `level` times,
+    /// which will be doubled for each repeated ban, up to the max ban duration
+    pub(super) fn ban_duration_of(&self, level: u32) -> Duration {
+        let factor = 1u32.checked_shl(level).unwrap_or(u32::MAX);
+        self.ban_duration
+            .saturating_mul(factor)
+            .min(self.max_ban_duration)
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.window.is_zero() {
+            return Err(anyhow!("the scoring window should not be zero"));
+        }
+        if self.threshold == 0 {
+            return Err(anyhow!("the ban threshold should not be zero"));
+        }
+        if self.ban_duration.is_zero() {
+            return Err(anyhow!("the ban duration should not be zero"));
+        }
+        if self.max_ban_duration < self.ban_duration {
+            return Err(anyhow!(
+                "the max ban duration {:?} should not be less than the ban duration {:?}",
+                self.max_ban_duration,
+                self.ban_duration
+            ));
+        }
+        Ok(())
+    }
+
+    fn set_weights_by_yaml(&mut self, v: &Yaml) -> anyhow::Result<()> {
+        match v {
+            Yaml::Hash(map) => g3_yaml::foreach_kv(map, |k, v| {
+                let violation = ClientViolation::from_key(k)
+                    .ok_or_else(|| anyhow!("invalid violation type {k}"))?;
+                let weight =
+                    g3_yaml::value::as_u32(v).context(format!("invalid u32 value for key {k}"))?;
+                self.set_weight(violation, weight);
+                Ok(())
+            }),
+            Yaml::Integer(_) => {
+                let weight = g3_yaml::value::as_u32(v)?;
+                self.weights = [weight; ClientViolation::ALL.len()];
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid yaml value type, expect map or integer")),
+        }
+    }
+
+    fn set_exempt_by_yaml(&mut self, v: &Yaml) -> anyhow::Result<()> {
+        match v {
+            Yaml::Array(seq) => {
+                for (i, v) in seq.iter().enumerate() {
+                    let net = g3_yaml::value::as_ip_network(v)
+                        .context(format!("invalid ip network value for #{i}"))?;
+                    self.add_exempt(net);
+                }
+                Ok(())
+            }
+            _ => {
+                let net = g3_yaml::value::as_ip_network(v)?;
+                self.add_exempt(net);
+                Ok(())
+            }
+        }
+    }
+
+    fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            "window" | "scoring_window" => {
+                self.window = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "threshold" => {
+                self.threshold =
+                    g3_yaml::value::as_u32(v).context(format!("invalid u32 value for key {k}"))?;
+                Ok(())
+            }
+            "ban_duration" | "duration" => {
+                self.ban_duration = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "max_ban_duration" | "max_duration" => {
+                self.max_ban_duration = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "offense_reset" => {
+                self.offense_reset = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "weight" | "weights" => self
+                .set_weights_by_yaml(v)
+                .context(format!("invalid violation weight value for key {k}")),
+            "exempt" | "exempt_networks" => self
+                .set_exempt_by_yaml(v)
+                .context(format!("invalid exempt networks value for key {k}")),
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    pub fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let mut config = ClientBanConfig::default();
+        match v {
+            Yaml::Hash(map) => g3_yaml::foreach_kv(map, |k, v| config.set(k, v))?,
+            Yaml::Null => {}
+            _ => return Err(anyhow!("root value type should be hash")),
+        }
+        config.check()?;
+        Ok(config)
+    }
+}
+
+pub fn load(v: &Yaml) -> anyhow::Result<()> {
+    let config = ClientBanConfig::parse_yaml(v)?;
+    super::init_global(config);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn parse_yaml() {
+        let doc = yaml_rust::YamlLoader::load_from_str(
+            r#"
+            window: 30s
+            threshold: 5
+            ban_duration: 1m
+            max_ban_duration: 10m
+            weight:
+              auth_failed: 2
+              acl_denied: 0
+            exempt:
+              - 10.0.0.0/8
+              - 192.0.2.1
+            "#,
+        )
+        .unwrap();
+        let config = ClientBanConfig::parse_yaml(&doc[0]).unwrap();
+        assert_eq!(config.window, Duration::from_secs(30));
+        assert_eq!(config.threshold, 5);
+        assert_eq!(config.weight(ClientViolation::AuthFailed), 2);
+        assert_eq!(config.weight(ClientViolation::AclDenied), 0);
+        assert_eq!(config.weight(ClientViolation::HandshakeFailed), 1);
+        assert!(config.is_exempt(IpAddr::from_str("10.1.2.3").unwrap()));
+        assert!(config.is_exempt(IpAddr::from_str("192.0.2.1").unwrap()));
+        assert!(!config.is_exempt(IpAddr::from_str("192.0.2.2").unwrap()));
+
+        assert_eq!(config.ban_duration_of(0), Duration::from_secs(60));
+        assert_eq!(config.ban_duration_of(2), Duration::from_secs(240));
+        assert_eq!(config.ban_duration_of(4), Duration::from_secs(600));
+        assert_eq!(config.ban_duration_of(40), Duration::from_secs(600));
+
+        let doc =
+            yaml_rust::YamlLoader::load_from_str("ban_duration: 1h\nmax_ban_duration: 1m").unwrap();
+        assert!(ClientBanConfig::parse_yaml(&doc[0]).is_err());
+
+        let doc = yaml_rust::YamlLoader::load_from_str("threshold: 0").unwrap();
+        assert!(ClientBanConfig::parse_yaml(&doc[0]).is_err());
+    }
+}
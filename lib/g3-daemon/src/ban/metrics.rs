@@ -0,0 +1,55 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Mutex;
+
+use g3_statsd_client::{StatsdClient, StatsdTagGroup};
+
+const METRIC_NAME_CLIENT_BAN_ACTIVE: &str = "client_ban.active";
+const METRIC_NAME_CLIENT_BAN_CREATED: &str = "client_ban.created";
+const METRIC_NAME_CLIENT_BAN_REJECTED: &str = "client_ban.rejected";
+
+static CLIENT_BAN_SNAPSHOT: Mutex<ClientBanSnapshot> = Mutex::new(ClientBanSnapshot {
+    created: 0,
+    rejected: 0,
+});
+
+struct ClientBanSnapshot {
+    created: u64,
+    rejected: u64,
+}
+
+pub fn emit_stats(client: &mut StatsdClient) {
+    let Some(list) = super::global() else {
+        return;
+    };
+    let stats = list.stats();
+    let common_tags = StatsdTagGroup::default();
+
+    client
+        .gauge_with_tags(
+            METRIC_NAME_CLIENT_BAN_ACTIVE,
+            list.prune_expired(),
+            &common_tags,
+        )
+        .send();
+
+    let mut snap = CLIENT_BAN_SNAPSHOT.lock().unwrap();
+    macro_rules! emit_field {
+        ($field:ident, $get:ident, $name:expr) => {
+            let new_value = stats.$get();
+            if new_value != 0 || snap.$field != 0 {
+                let diff_value = new_value.wrapping_sub(snap.$field);
+                client
+                    .count_with_tags($name, diff_value, &common_tags)
+                    .send();
+                snap.$field = new_value;
+            }
+        };
+    }
+
+    emit_field!(created, get_created, METRIC_NAME_CLIENT_BAN_CREATED);
+    emit_field!(rejected, get_rejected, METRIC_NAME_CLIENT_BAN_REJECTED);
+}
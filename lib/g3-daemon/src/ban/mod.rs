@@ -0,0 +1,496 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+use log::{info, warn};
+use rustc_hash::FxHashMap;
+
+pub mod config;
+pub use config::ClientBanConfig;
+
+pub mod metrics;
+
+static CLIENT_BAN_LIST: OnceLock<ClientBanList> = OnceLock::new();
+
+const MIN_PRUNE_CLIENTS: usize = 1024;
+
+/// The type of the client misbehavior that will be scored for the ban
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClientViolation {
+    /// the TLS handshake failed or the TLS ClientHello message is invalid
+    HandshakeFailed,
+    /// denied by the ingress network ACL
+    AclDenied,
+    /// the client failed to authenticate
+    AuthFailed,
+}
+
+impl ClientViolation {
+    pub const ALL: [ClientViolation; 3] = [
+        ClientViolation::HandshakeFailed,
+        ClientViolation::AclDenied,
+        ClientViolation::AuthFailed,
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ClientViolation::HandshakeFailed => "handshake_failed",
+            ClientViolation::AclDenied => "acl_denied",
+            ClientViolation::AuthFailed => "auth_failed",
+        }
+    }
+
+    const fn index(&self) -> usize {
+        *self as usize
+    }
+
+    fn from_key(k: &str) -> Option<Self> {
+        match g3_yaml::key::normalize(k).as_str() {
+            "handshake_failed" | "handshake" => Some(ClientViolation::HandshakeFailed),
+            "acl_denied" | "acl" => Some(ClientViolation::AclDenied),
+            "auth_failed" | "auth" => Some(ClientViolation::AuthFailed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ClientBanStats {
+    created: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl ClientBanStats {
+    pub fn get_created(&self) -> u64 {
+        self.created.load(Ordering::Relaxed)
+    }
+
+    pub fn get_rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct BanEntry {
+    created: Instant,
+    expire: Instant,
+    level: u32,
+}
+
+struct ClientScore {
+    window_start: Instant,
+    score: u64,
+    /// how many times the client has been banned
+    level: u32,
+    last_ban_expire: Option<Instant>,
+}
+
+impl ClientScore {
+    fn new(now: Instant) -> Self {
+        ClientScore {
+            window_start: now,
+            score: 0,
+            level: 0,
+            last_ban_expire: None,
+        }
+    }
+
+    fn is_idle(&self, now: Instant, config: &ClientBanConfig) -> bool {
+        if now.duration_since(self.window_start) < config.window {
+            return false;
+        }
+        match self.last_ban_expire {
+            Some(expire) => now.saturating_duration_since(expire) >= config.offense_reset,
+            None => true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ScoreTable {
+    clients: FxHashMap<IpAddr, ClientScore>,
+    prune_len: usize,
+}
+
+impl ScoreTable {
+    fn prune(&mut self, now: Instant, config: &ClientBanConfig) {
+        if self.clients.len() < self.prune_len.max(MIN_PRUNE_CLIENTS) {
+            return;
+        }
+        self.clients.retain(|_, score| !score.is_idle(now, config));
+        self.prune_len = self.clients.len() * 2;
+    }
+}
+
+/// Info of an active ban
+pub struct ClientBanInfo {
+    pub ip: IpAddr,
+    /// how many times the client has been banned, including this one
+    pub level: u32,
+    pub duration: Duration,
+    pub remaining: Duration,
+}
+
+/// A daemon wide temporary ban list of client ips.
+///
+/// The ban state is checked in the accept path of the servers, so it's kept in a read-only map
+/// which will be replaced as a whole when changed. The scores are only updated when violations
+/// are reported, and are protected by a mutex.
+pub struct ClientBanList {
+    config: ClientBanConfig,
+    bans: ArcSwap<FxHashMap<IpAddr, BanEntry>>,
+    scores: Mutex<ScoreTable>,
+    stats: ClientBanStats,
+}
+
+impl ClientBanList {
+    pub fn new(config: ClientBanConfig) -> Self {
+        ClientBanList {
+            config,
+            bans: ArcSwap::from_pointee(FxHashMap::default()),
+            scores: Mutex::new(ScoreTable::default()),
+            stats: ClientBanStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &ClientBanStats {
+        &self.stats
+    }
+
+    /// Check if the client ip is banned, and count the rejection if it is
+    pub fn check(&self, ip: IpAddr) -> bool {
+        let bans = self.bans.load();
+        if bans.is_empty() {
+            return false;
+        }
+        self.check_in(&bans, ip, Instant::now())
+    }
+
+    fn check_in(&self, bans: &FxHashMap<IpAddr, BanEntry>, ip: IpAddr, now: Instant) -> bool {
+        match bans.get(&ip) {
+            Some(entry) if entry.expire > now => {
+                self.stats.rejected.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Report a violation of the client, and ban it if the score reached the threshold
+    pub fn report(&self, violation: ClientViolation, ip: IpAddr) {
+        self.report_at(violation, ip, Instant::now());
+    }
+
+    fn report_at(&self, violation: ClientViolation, ip: IpAddr, now: Instant) {
+        let weight = self.config.weight(violation);
+        if weight == 0 || self.config.is_exempt(ip) {
+            return;
+        }
+
+        let mut table = self.scores.lock().unwrap();
+        if let Some(entry) = self.bans.load().get(&ip) {
+            if entry.expire > now {
+                // the connections will be rejected before any further violation
+                return;
+            }
+        }
+
+        let client = table
+            .clients
+            .entry(ip)
+            .or_insert_with(|| ClientScore::new(now));
+        if now.duration_since(client.window_start) >= self.config.window {
+            client.window_start = now;
+            client.score = 0;
+        }
+        if let Some(expire) = client.last_ban_expire {
+            if now.saturating_duration_since(expire) >= self.config.offense_reset {
+                client.level = 0;
+                client.last_ban_expire = None;
+            }
+        }
+
+        client.score += weight as u64;
+        if client.score < self.config.threshold as u64 {
+            table.prune(now, &self.config);
+            return;
+        }
+
+        let duration = self.config.ban_duration_of(client.level);
+        let entry = BanEntry {
+            created: now,
+            expire: now + duration,
+            level: client.level.saturating_add(1),
+        };
+        client.window_start = now;
+        client.score = 0;
+        client.level = entry.level;
+        client.last_ban_expire = Some(entry.expire);
+
+        let mut bans = FxHashMap::clone(&self.bans.load());
+        bans.retain(|_, entry| entry.expire > now);
+        bans.insert(ip, entry);
+        self.bans.store(Arc::new(bans));
+        self.stats.created.fetch_add(1, Ordering::Relaxed);
+        info!(
+            "client {ip} banned for {duration:?} after {} violation, ban level {}",
+            violation.as_str(),
+            entry.level
+        );
+
+        table.prune(now, &self.config);
+    }
+
+    /// Get all the active bans
+    pub fn list(&self) -> Vec<ClientBanInfo> {
+        let now = Instant::now();
+        self.bans
+            .load()
+            .iter()
+            .filter(|(_, entry)| entry.expire > now)
+            .map(|(ip, entry)| ClientBanInfo {
+                ip: *ip,
+                level: entry.level,
+                duration: entry.expire.duration_since(entry.created),
+                remaining: entry.expire.duration_since(now),
+            })
+            .collect()
+    }
+
+    /// Clear the ban of the specified client, or all if not specified.
+    ///
+    /// The score and the repeat offense level of the cleared clients will also be reset.
+    /// The number of the removed active bans will be returned.
+    pub fn clear(&self, ip: Option<IpAddr>) -> usize {
+        let now = Instant::now();
+        let mut table = self.scores.lock().unwrap();
+        let old = self.bans.load_full();
+        let removed = match ip {
+            Some(ip) => {
+                table.clients.remove(&ip);
+                let Some(entry) = old.get(&ip) else {
+                    return 0;
+                };
+                let mut bans = FxHashMap::clone(&old);
+                bans.remove(&ip);
+                self.bans.store(Arc::new(bans));
+                usize::from(entry.expire > now)
+            }
+            None => {
+                table.clients.clear();
+                self.bans.store(Arc::new(FxHashMap::default()));
+                old.values().filter(|entry| entry.expire > now).count()
+            }
+        };
+        if removed > 0 {
+            info!("{removed} client bans cleared");
+        }
+        removed
+    }
+
+    /// Remove the expired bans, and return the count of the active bans
+    pub fn prune_expired(&self) -> usize {
+        let now = Instant::now();
+        let bans = self.bans.load();
+        let active = bans.values().filter(|entry| entry.expire > now).count();
+        if active < bans.len() {
+            let _table = self.scores.lock().unwrap();
+            let mut bans = FxHashMap::clone(&self.bans.load());
+            bans.retain(|_, entry| entry.expire > now);
+            self.bans.store(Arc::new(bans));
+        }
+        active
+    }
+}
+
+fn init_global(config: ClientBanConfig) {
+    if CLIENT_BAN_LIST.set(ClientBanList::new(config)).is_err() {
+        warn!("Global client ban list has already been set");
+    }
+}
+
+/// Get the global client ban list, which will be present only if configured
+pub fn global() -> Option<&'static ClientBanList> {
+    CLIENT_BAN_LIST.get()
+}
+
+/// Check if the client ip is banned by the global client ban list
+#[inline]
+pub fn is_banned(ip: IpAddr) -> bool {
+    CLIENT_BAN_LIST
+        .get()
+        .map(|list| list.check(ip))
+        .unwrap_or(false)
+}
+
+/// Report a client violation to the global client ban list
+pub fn report(violation: ClientViolation, ip: IpAddr) {
+    if let Some(list) = CLIENT_BAN_LIST.get() {
+        list.report(violation, ip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    use g3_openssl::SslHandshakeError;
+
+    use crate::server::{
+        AcceptRejectReason, AcceptRejectRecorder, AcceptRejectStats, ClientConnectionInfo,
+    };
+
+    fn test_config() -> ClientBanConfig {
+        let mut config = ClientBanConfig::default();
+        config.set_window(Duration::from_secs(10));
+        config.set_threshold(4);
+        config.set_ban_duration(Duration::from_secs(60));
+        config.set_max_ban_duration(Duration::from_secs(300));
+        config.set_offense_reset(Duration::from_secs(600));
+        config.set_weight(ClientViolation::AuthFailed, 2);
+        config
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        IpAddr::from_str(s).unwrap()
+    }
+
+    fn is_banned_at(list: &ClientBanList, ip: IpAddr, now: Instant) -> bool {
+        list.check_in(&list.bans.load(), ip, now)
+    }
+
+    #[test]
+    fn score_window() {
+        let list = ClientBanList::new(test_config());
+        let client = ip("192.0.2.1");
+        let now = Instant::now();
+
+        // the score is reset in each new window
+        for i in 0..6 {
+            let now = now + Duration::from_secs(11 * i);
+            list.report_at(ClientViolation::HandshakeFailed, client, now);
+            assert!(!is_banned_at(&list, client, now));
+        }
+        assert_eq!(list.stats().get_created(), 0);
+
+        let now = now + Duration::from_secs(100);
+        list.report_at(ClientViolation::AuthFailed, client, now);
+        list.report_at(ClientViolation::AclDenied, client, now);
+        assert!(!is_banned_at(&list, client, now));
+        list.report_at(ClientViolation::AclDenied, client, now);
+        assert!(is_banned_at(&list, client, now));
+        assert!(!is_banned_at(&list, ip("192.0.2.2"), now));
+        assert_eq!(list.stats().get_created(), 1);
+        assert_eq!(list.stats().get_rejected(), 1);
+
+        assert!(is_banned_at(&list, client, now + Duration::from_secs(59)));
+        assert!(!is_banned_at(&list, client, now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn repeat_offender() {
+        let list = ClientBanList::new(test_config());
+        let client = ip("2001:db8::1");
+        let mut now = Instant::now();
+
+        for expected in [60, 120, 240, 300, 300] {
+            list.report_at(ClientViolation::AuthFailed, client, now);
+            list.report_at(ClientViolation::AuthFailed, client, now);
+            let entry = *list.bans.load().get(&client).unwrap();
+            assert_eq!(
+                entry.expire.duration_since(entry.created),
+                Duration::from_secs(expected)
+            );
+            now = entry.expire;
+        }
+
+        // the offense level is reset after a long time
+        now += Duration::from_secs(600);
+        list.report_at(ClientViolation::AuthFailed, client, now);
+        list.report_at(ClientViolation::AuthFailed, client, now);
+        let entry = *list.bans.load().get(&client).unwrap();
+        assert_eq!(entry.level, 1);
+        assert_eq!(
+            entry.expire.duration_since(entry.created),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn exempt_and_clear() {
+        let mut config = test_config();
+        config.add_exempt(ip_network::IpNetwork::from_str("10.0.0.0/8").unwrap());
+        let list = ClientBanList::new(config);
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            list.report_at(ClientViolation::AuthFailed, ip("10.1.1.1"), now);
+            list.report_at(ClientViolation::AuthFailed, ip("192.0.2.1"), now);
+            list.report_at(ClientViolation::AuthFailed, ip("192.0.2.2"), now);
+        }
+        assert!(!is_banned_at(&list, ip("10.1.1.1"), now));
+        assert!(list.check(ip("192.0.2.1")));
+        assert!(list.check(ip("192.0.2.2")));
+        assert_eq!(list.list().len(), 2);
+
+        assert_eq!(list.clear(Some(ip("192.0.2.1"))), 1);
+        assert_eq!(list.clear(Some(ip("192.0.2.1"))), 0);
+        assert!(!list.check(ip("192.0.2.1")));
+        assert!(list.check(ip("192.0.2.2")));
+        assert_eq!(list.prune_expired(), 1);
+
+        assert_eq!(list.clear(None), 1);
+        assert!(list.list().is_empty());
+        assert!(!list.check(ip("192.0.2.2")));
+        assert_eq!(list.stats().get_created(), 2);
+    }
+
+    #[test]
+    fn shared_between_servers() {
+        let mut config = test_config();
+        config.set_weight(ClientViolation::HandshakeFailed, 1);
+        init_global(config);
+
+        let client = SocketAddr::from_str("192.0.2.100:40000").unwrap();
+        // a tls server and a socks server of the same daemon
+        let tls_cc_info =
+            ClientConnectionInfo::new(client, SocketAddr::from_str("192.0.2.2:443").unwrap());
+        let socks_cc_info =
+            ClientConnectionInfo::new(client, SocketAddr::from_str("192.0.2.2:1080").unwrap());
+
+        let recorder =
+            AcceptRejectRecorder::new(Arc::new(AcceptRejectStats::default()), None, None);
+        let handshake_error = SslHandshakeError::from_io_error(&std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid client hello",
+        ));
+        recorder.record_handshake_error(&handshake_error, &tls_cc_info, None);
+        recorder.record(AcceptRejectReason::ClientHelloInvalid, &tls_cc_info, None);
+        assert!(!is_banned(tls_cc_info.client_ip()));
+        assert!(!is_banned(socks_cc_info.client_ip()));
+
+        report(ClientViolation::AuthFailed, socks_cc_info.client_ip());
+        assert!(is_banned(tls_cc_info.client_ip()));
+        assert!(is_banned(socks_cc_info.client_ip()));
+        assert!(!is_banned(ip("192.0.2.101")));
+
+        // other tests may also report to the global ban list
+        let list = global().unwrap();
+        let bans = list.list();
+        let ban = bans.iter().find(|ban| ban.ip == client.ip()).unwrap();
+        assert_eq!(ban.level, 1);
+        assert_eq!(ban.duration, Duration::from_secs(60));
+        assert_eq!(list.stats().get_rejected(), 2);
+        assert_eq!(list.clear(Some(client.ip())), 1);
+        assert!(!is_banned(tls_cc_info.client_ip()));
+    }
+}
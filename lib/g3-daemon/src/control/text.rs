@@ -127,6 +127,7 @@ where
             }
             Some("set") => self.set(iter),
             Some("pid") => Ok(std::process::id().to_string()),
+            Some("tls") => self.tls(iter),
             Some(k) => Err(anyhow!("unknown command {k}")),
             None => Ok(String::new()),
         };
@@ -136,6 +137,14 @@ where
         }
     }
 
+    fn tls(&mut self, mut iter: SplitWhitespace) -> anyhow::Result<String> {
+        match iter.next() {
+            Some("cert-status") => Ok(crate::tls::cert_status_text()),
+            Some(k) => Err(anyhow!("unknown tls subcommand {k}")),
+            None => Err(anyhow!("no tls subcommand found")),
+        }
+    }
+
     fn set(&mut self, mut iter: SplitWhitespace) -> anyhow::Result<String> {
         if let Some(key) = iter.next() {
             if let Some(value) = iter.next() {
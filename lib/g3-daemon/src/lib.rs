@@ -3,6 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+pub mod ban;
 pub mod config;
 pub mod control;
 pub mod listen;
@@ -13,6 +14,7 @@ pub mod runtime;
 pub mod server;
 pub mod signal;
 pub mod stat;
+pub mod tls;
 
 #[cfg(unix)]
 pub mod daemonize;
@@ -0,0 +1,176 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+
+use super::ListenStats;
+
+/// Connect to one of the running listen sockets through the loopback interface
+///
+/// The connect latency will be returned along with the connected stream.
+pub async fn loopback_connect(
+    stats: &ListenStats,
+    timeout: Duration,
+) -> anyhow::Result<(TcpStream, Duration)> {
+    let Some(listen_addr) = stats.listen_addrs().first().copied() else {
+        return match stats.last_error() {
+            Some(e) => Err(anyhow!("not listening, last error: {e}")),
+            None => Err(anyhow!("not listening")),
+        };
+    };
+    let peer_addr = loopback_addr(listen_addr);
+
+    let time_start = Instant::now();
+    match tokio::time::timeout(timeout, TcpStream::connect(peer_addr)).await {
+        Ok(Ok(stream)) => Ok((stream, time_start.elapsed())),
+        Ok(Err(e)) => Err(anyhow!("failed to connect to {peer_addr}: {e}")),
+        Err(_) => Err(anyhow!("timed out to connect to {peer_addr}")),
+    }
+}
+
+fn loopback_addr(listen_addr: SocketAddr) -> SocketAddr {
+    match listen_addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => {
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), listen_addr.port())
+        }
+        IpAddr::V6(ip) if ip.is_unspecified() => {
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), listen_addr.port())
+        }
+        _ => listen_addr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use tokio::sync::broadcast;
+
+    use g3_types::metrics::NodeName;
+    use g3_types::net::TcpListenConfig;
+
+    use crate::listen::{AcceptTcpServer, ListenTcpRuntime};
+    use crate::server::{BaseServer, ClientConnectionInfo, ReloadServer, ServerReloadCommand};
+
+    #[derive(Clone)]
+    struct TestServer {
+        name: NodeName,
+    }
+
+    impl BaseServer for TestServer {
+        fn name(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn r#type(&self) -> &'static str {
+            "Test"
+        }
+
+        fn version(&self) -> usize {
+            0
+        }
+    }
+
+    impl ReloadServer for TestServer {
+        fn reload(&self) -> Self {
+            self.clone()
+        }
+    }
+
+    #[async_trait]
+    impl AcceptTcpServer for TestServer {
+        async fn run_tcp_task(&self, _stream: TcpStream, _cc_info: ClientConnectionInfo) {}
+    }
+
+    fn new_runtime(name: &str) -> (ListenTcpRuntime<TestServer>, Arc<ListenStats>) {
+        let name = NodeName::from_str(name).unwrap();
+        let stats = Arc::new(ListenStats::new(&name));
+        let runtime = ListenTcpRuntime::new(TestServer { name }, stats.clone());
+        (runtime, stats)
+    }
+
+    async fn wait_running(stats: &ListenStats, running: bool) {
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while stats.is_running() != running {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn loopback() {
+        assert_eq!(
+            loopback_addr(SocketAddr::from_str("0.0.0.0:443").unwrap()),
+            SocketAddr::from_str("127.0.0.1:443").unwrap()
+        );
+        assert_eq!(
+            loopback_addr(SocketAddr::from_str("[::]:443").unwrap()),
+            SocketAddr::from_str("[::1]:443").unwrap()
+        );
+        assert_eq!(
+            loopback_addr(SocketAddr::from_str("192.168.1.1:443").unwrap()),
+            SocketAddr::from_str("192.168.1.1:443").unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn healthy() {
+        let (reload_sender, _) = broadcast::channel(4);
+        let (runtime, stats) = new_runtime("healthy");
+        let config = TcpListenConfig::new(SocketAddr::from_str("127.0.0.1:0").unwrap());
+        runtime
+            .run_all_instances(&config, false, &reload_sender)
+            .unwrap();
+        wait_running(&stats, true).await;
+
+        let listen_addrs = stats.listen_addrs();
+        assert_eq!(listen_addrs.len(), 1);
+        assert_ne!(listen_addrs[0].port(), 0);
+        let (stream, _) = loopback_connect(&stats, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), listen_addrs[0]);
+        assert!(stats.last_error().is_none());
+
+        let _ = reload_sender.send(ServerReloadCommand::QuitRuntime);
+        wait_running(&stats, false).await;
+        assert!(stats.listen_addrs().is_empty());
+    }
+
+    #[tokio::test]
+    async fn port_conflicted() {
+        let occupied = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let listen_addr = occupied.local_addr().unwrap();
+
+        let (reload_sender, _) = broadcast::channel(4);
+        let (runtime, stats) = new_runtime("conflicted");
+        let config = TcpListenConfig::new(listen_addr);
+        assert!(
+            runtime
+                .run_all_instances(&config, false, &reload_sender)
+                .is_err()
+        );
+
+        assert!(!stats.is_running());
+        assert!(stats.listen_addrs().is_empty());
+        let e = stats.last_error().unwrap();
+        assert!(e.starts_with(&format!("bind {listen_addr}: ")));
+        assert!(
+            loopback_connect(&stats, Duration::from_secs(1))
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,493 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+//! Pass listen sockets from the old process to the new one during binary upgrade.
+//!
+//! The old process exports all of its listen sockets, and will send them to the first peer,
+//! which should be running as the same user, that connects to the handover socket.
+//! The manifest (socket type, server name and listen address) is sent as the data part,
+//! and the fds are sent in the same order via SCM_RIGHTS.
+
+use std::collections::HashMap;
+use std::io::{self, IoSlice, IoSliceMut, Read};
+use std::mem::MaybeUninit;
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{Context, anyhow};
+use log::{debug, info, warn};
+use rustix::net::{
+    RecvAncillaryBuffer, RecvAncillaryMessage, RecvFlags, SendAncillaryBuffer,
+    SendAncillaryMessage, SendFlags, SocketType,
+};
+
+/// the max number of fds that can be sent in a single message, see SCM_MAX_FD in linux
+const MAX_HANDOVER_FDS: usize = 253;
+const MAX_MANIFEST_SIZE: usize = 1 << 20;
+
+static EXPORT_ID: AtomicU64 = AtomicU64::new(0);
+static EXPORTED: Mutex<Option<HashMap<(String, usize), ExportedSocket>>> = Mutex::new(None);
+static ADOPTABLE: Mutex<Vec<HandoverSocket>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum HandoverSocketKind {
+    Tcp,
+    Udp,
+}
+
+impl HandoverSocketKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HandoverSocketKind::Tcp => "tcp",
+            HandoverSocketKind::Udp => "udp",
+        }
+    }
+
+    fn socket_type(&self) -> SocketType {
+        match self {
+            HandoverSocketKind::Tcp => SocketType::STREAM,
+            HandoverSocketKind::Udp => SocketType::DGRAM,
+        }
+    }
+}
+
+impl FromStr for HandoverSocketKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tcp" => Ok(HandoverSocketKind::Tcp),
+            "udp" => Ok(HandoverSocketKind::Udp),
+            _ => Err(()),
+        }
+    }
+}
+
+struct ExportedSocket {
+    id: u64,
+    kind: HandoverSocketKind,
+    addr: SocketAddr,
+    fd: OwnedFd,
+}
+
+struct HandoverSocket {
+    kind: HandoverSocketKind,
+    server: String,
+    addr: SocketAddr,
+    fd: OwnedFd,
+}
+
+/// Remove the exported socket when the listen runtime instance stopped
+pub struct HandoverExportGuard {
+    server: String,
+    instance: usize,
+    id: u64,
+}
+
+impl Drop for HandoverExportGuard {
+    fn drop(&mut self) {
+        let mut exported = EXPORTED.lock().unwrap();
+        if let Some(map) = exported.as_mut() {
+            let key = (std::mem::take(&mut self.server), self.instance);
+            if map.get(&key).map(|s| s.id == self.id).unwrap_or(false) {
+                map.remove(&key);
+            }
+        }
+    }
+}
+
+fn export_socket(
+    kind: HandoverSocketKind,
+    server: &str,
+    instance: usize,
+    addr: SocketAddr,
+    fd: BorrowedFd<'_>,
+) -> Option<HandoverExportGuard> {
+    let fd = match fd.try_clone_to_owned() {
+        Ok(fd) => fd,
+        Err(e) => {
+            warn!(
+                "failed to dup {} listen socket of server {server} for handover: {e}",
+                kind.as_str()
+            );
+            return None;
+        }
+    };
+    let id = EXPORT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut exported = EXPORTED.lock().unwrap();
+    let map = exported.get_or_insert_with(HashMap::new);
+    map.insert(
+        (server.to_string(), instance),
+        ExportedSocket { id, kind, addr, fd },
+    );
+    Some(HandoverExportGuard {
+        server: server.to_string(),
+        instance,
+        id,
+    })
+}
+
+pub(crate) fn export_tcp(
+    server: &str,
+    instance: usize,
+    listener: &TcpListener,
+) -> Option<HandoverExportGuard> {
+    let addr = listener.local_addr().ok()?;
+    export_socket(
+        HandoverSocketKind::Tcp,
+        server,
+        instance,
+        addr,
+        listener.as_fd(),
+    )
+}
+
+pub(crate) fn export_udp(
+    server: &str,
+    instance: usize,
+    socket: &UdpSocket,
+) -> Option<HandoverExportGuard> {
+    let addr = socket.local_addr().ok()?;
+    export_socket(
+        HandoverSocketKind::Udp,
+        server,
+        instance,
+        addr,
+        socket.as_fd(),
+    )
+}
+
+fn take_socket(kind: HandoverSocketKind, server: &str, addr: SocketAddr) -> Option<OwnedFd> {
+    let mut adoptable = ADOPTABLE.lock().unwrap();
+    if adoptable.is_empty() {
+        return None;
+    }
+
+    let index = adoptable
+        .iter()
+        .position(|s| s.kind == kind && s.server == server && s.addr == addr)?;
+    let s = adoptable.swap_remove(index);
+    match rustix::net::sockopt::socket_type(&s.fd) {
+        Ok(t) if t == kind.socket_type() => Some(s.fd),
+        Ok(_) => {
+            warn!(
+                "handover socket for server {server} is not a {} socket",
+                kind.as_str()
+            );
+            None
+        }
+        Err(e) => {
+            warn!("failed to get type of handover socket for server {server}: {e}");
+            None
+        }
+    }
+}
+
+/// Take the tcp listen socket passed from the old process
+pub fn take_tcp_listener(server: &str, addr: SocketAddr) -> Option<TcpListener> {
+    let fd = take_socket(HandoverSocketKind::Tcp, server, addr)?;
+    let listener = TcpListener::from(fd);
+    // the real address of the socket should match the config
+    match listener.local_addr() {
+        Ok(local) if local == addr => {}
+        Ok(local) => {
+            warn!("handover tcp socket for server {server} is bound to {local}, expected {addr}");
+            return None;
+        }
+        Err(e) => {
+            warn!("failed to get local address of handover tcp socket for server {server}: {e}");
+            return None;
+        }
+    }
+    if let Err(e) = listener.set_nonblocking(true) {
+        warn!("failed to set handover tcp socket for server {server} to nonblocking: {e}");
+        return None;
+    }
+    debug!("adopted tcp listen socket {addr} for server {server}");
+    Some(listener)
+}
+
+/// Take the udp listen socket passed from the old process
+pub fn take_udp_socket(server: &str, addr: SocketAddr) -> Option<UdpSocket> {
+    let fd = take_socket(HandoverSocketKind::Udp, server, addr)?;
+    let socket = UdpSocket::from(fd);
+    match socket.local_addr() {
+        Ok(local) if local == addr => {}
+        Ok(local) => {
+            warn!("handover udp socket for server {server} is bound to {local}, expected {addr}");
+            return None;
+        }
+        Err(e) => {
+            warn!("failed to get local address of handover udp socket for server {server}: {e}");
+            return None;
+        }
+    }
+    if let Err(e) = socket.set_nonblocking(true) {
+        warn!("failed to set handover udp socket for server {server} to nonblocking: {e}");
+        return None;
+    }
+    debug!("adopted udp listen socket {addr} for server {server}");
+    Some(socket)
+}
+
+/// Drop all the sockets passed from the old process that are not adopted
+pub fn clear_adoptable() {
+    let mut adoptable = ADOPTABLE.lock().unwrap();
+    if !adoptable.is_empty() {
+        info!("{} handover listen sockets not adopted", adoptable.len());
+        adoptable.clear();
+    }
+}
+
+pub fn default_socket_path(daemon_group: &str) -> PathBuf {
+    let socket_name = if daemon_group.is_empty() {
+        "_.handover.sock".to_string()
+    } else {
+        format!("{daemon_group}.handover.sock")
+    };
+    let mut path = crate::opts::control_dir();
+    path.push(Path::new(&socket_name));
+    path
+}
+
+fn send_exported(stream: &UnixStream) -> io::Result<usize> {
+    let exported = EXPORTED.lock().unwrap();
+    let Some(map) = exported.as_ref() else {
+        return Ok(0);
+    };
+
+    let mut manifest = String::new();
+    let mut fds = Vec::with_capacity(map.len());
+    for ((server, _), s) in map.iter() {
+        if fds.len() >= MAX_HANDOVER_FDS {
+            warn!("too many listen sockets, the left ones won't be passed to the new process");
+            break;
+        }
+        manifest.push_str(&format!("{} {server} {}\n", s.kind.as_str(), s.addr));
+        fds.push(s.fd.as_fd());
+    }
+    if fds.is_empty() {
+        return Ok(0);
+    }
+
+    let mut space = [MaybeUninit::uninit(); rustix::cmsg_space!(ScmRights(MAX_HANDOVER_FDS))];
+    let mut cmsg_buffer = SendAncillaryBuffer::new(&mut space);
+    if !cmsg_buffer.push(SendAncillaryMessage::ScmRights(&fds)) {
+        return Err(io::Error::other("no enough space for ancillary data"));
+    }
+
+    let data = manifest.as_bytes();
+    let nw = rustix::net::sendmsg(
+        stream,
+        &[IoSlice::new(data)],
+        &mut cmsg_buffer,
+        SendFlags::empty(),
+    )?;
+    if nw < data.len() {
+        // the fds have already been sent along with the first part
+        let mut stream = stream;
+        io::Write::write_all(&mut stream, &data[nw..])?;
+    }
+    Ok(fds.len())
+}
+
+#[cfg(target_os = "linux")]
+fn check_peer(stream: &UnixStream) -> anyhow::Result<()> {
+    let cred = rustix::net::sockopt::socket_peercred(stream)
+        .map_err(|e| anyhow!("failed to get peer credentials: {e}"))?;
+    let uid = rustix::process::geteuid();
+    if cred.uid != uid {
+        return Err(anyhow!(
+            "peer is running as uid {}, expected {}",
+            cred.uid.as_raw(),
+            uid.as_raw()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_peer(_stream: &UnixStream) -> anyhow::Result<()> {
+    // only the owner has access to the socket file
+    Ok(())
+}
+
+/// Serve the handover socket in the old process
+///
+/// The server will stop after the listen sockets have been passed to one new process.
+pub fn spawn_server(path: PathBuf) -> anyhow::Result<()> {
+    if path.exists() {
+        std::fs::remove_file(&path)
+            .map_err(|e| anyhow!("failed to remove old {}: {e}", path.display()))?;
+    }
+    let listener = UnixListener::bind(&path)
+        .map_err(|e| anyhow!("failed to bind to {}: {e}", path.display()))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| anyhow!("failed to set permissions of {}: {e}", path.display()))?;
+    debug!(
+        "listen socket handover server started at {}",
+        path.display()
+    );
+
+    std::thread::Builder::new()
+        .name("handover".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("handover server accept: {e}");
+                        continue;
+                    }
+                };
+                if let Err(e) = check_peer(&stream) {
+                    warn!("handover peer rejected: {e}");
+                    continue;
+                }
+                match send_exported(&stream) {
+                    Ok(n) => {
+                        info!("passed {n} listen sockets to the new process");
+                        break;
+                    }
+                    Err(e) => warn!("failed to pass listen sockets: {e}"),
+                }
+            }
+            debug!("listen socket handover server stopped");
+        })
+        .map_err(|e| anyhow!("failed to spawn handover thread: {e}"))?;
+    Ok(())
+}
+
+fn parse_manifest(data: &[u8], fds: Vec<OwnedFd>) -> anyhow::Result<Vec<HandoverSocket>> {
+    let data = std::str::from_utf8(data).map_err(|_| anyhow!("invalid utf-8 manifest"))?;
+    let lines: Vec<&str> = data.lines().filter(|l| !l.is_empty()).collect();
+    if lines.len() != fds.len() {
+        return Err(anyhow!(
+            "manifest contains {} entries but {} fds received",
+            lines.len(),
+            fds.len()
+        ));
+    }
+
+    let mut sockets = Vec::with_capacity(fds.len());
+    for (line, fd) in lines.into_iter().zip(fds) {
+        let mut iter = line.split(' ');
+        let (Some(kind), Some(server), Some(addr), None) =
+            (iter.next(), iter.next(), iter.next(), iter.next())
+        else {
+            return Err(anyhow!("invalid manifest line: {line}"));
+        };
+        let kind = HandoverSocketKind::from_str(kind)
+            .map_err(|_| anyhow!("invalid socket type in manifest line: {line}"))?;
+        let addr = SocketAddr::from_str(addr)
+            .map_err(|e| anyhow!("invalid socket address in manifest line {line}: {e}"))?;
+        sockets.push(HandoverSocket {
+            kind,
+            server: server.to_string(),
+            addr,
+            fd,
+        });
+    }
+    Ok(sockets)
+}
+
+/// Receive listen sockets from the old process
+pub fn receive_from(path: &Path) -> anyhow::Result<usize> {
+    let stream = UnixStream::connect(path)
+        .map_err(|e| anyhow!("failed to connect to {}: {e}", path.display()))?;
+
+    let mut data = vec![0u8; 16384];
+    let mut space = [MaybeUninit::uninit(); rustix::cmsg_space!(ScmRights(MAX_HANDOVER_FDS))];
+    let mut cmsg_buffer = RecvAncillaryBuffer::new(&mut space);
+    let msg = rustix::net::recvmsg(
+        &stream,
+        &mut [IoSliceMut::new(&mut data)],
+        &mut cmsg_buffer,
+        RecvFlags::empty(),
+    )
+    .context("failed to recv handover message")?;
+
+    let mut fds = Vec::new();
+    for m in cmsg_buffer.drain() {
+        if let RecvAncillaryMessage::ScmRights(rights) = m {
+            fds.extend(rights);
+        }
+    }
+    for fd in &fds {
+        rustix::io::fcntl_setfd(fd, rustix::io::FdFlags::CLOEXEC)
+            .context("failed to set close-on-exec flag")?;
+    }
+
+    data.truncate(msg.bytes);
+    (&stream)
+        .take(MAX_MANIFEST_SIZE as u64)
+        .read_to_end(&mut data)
+        .context("failed to read the left manifest data")?;
+
+    let sockets = parse_manifest(&data, fds)?;
+    let count = sockets.len();
+    *ADOPTABLE.lock().unwrap() = sockets;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    #[test]
+    fn handover_tcp() {
+        let old_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = old_listener.local_addr().unwrap();
+        let guard = export_tcp("test", 0, &old_listener).unwrap();
+
+        // a client connection that is still in the accept queue
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"hello").unwrap();
+
+        let path = std::env::temp_dir().join(format!("g3-handover-{}.sock", std::process::id()));
+        spawn_server(path.clone()).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(receive_from(&path).unwrap(), 1);
+
+        // the server should stop after the first handover
+        let mut stopped = false;
+        for _ in 0..100 {
+            if UnixStream::connect(&path).is_err() {
+                stopped = true;
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(stopped);
+        let _ = std::fs::remove_file(&path);
+
+        // the old process quit
+        drop(guard);
+        drop(old_listener);
+
+        assert!(take_tcp_listener("other", addr).is_none());
+        let new_listener = take_tcp_listener("test", addr).unwrap();
+        new_listener.set_nonblocking(false).unwrap();
+        let (mut stream, _) = new_listener.accept().unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    fn manifest_mismatch() {
+        assert!(parse_manifest(b"tcp test 127.0.0.1:80\n", Vec::new()).is_err());
+        assert!(parse_manifest(b"", Vec::new()).unwrap().is_empty());
+    }
+}
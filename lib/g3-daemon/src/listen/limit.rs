@@ -0,0 +1,162 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+
+use governor::{RateLimiter, clock::DefaultClock, state::InMemoryState, state::NotKeyed};
+
+use g3_types::limit::RateLimitQuotaConfig;
+
+use super::{ListenConnAliveGuard, ListenStats};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ListenConnLimitError {
+    MaxConnections,
+    AcceptRate,
+}
+
+/// Limit the alive connections and the accept rate of a server
+///
+/// The alive connection count is kept in the listen stats, so it will be kept across reloads.
+pub struct ListenConnLimiter {
+    stats: Arc<ListenStats>,
+    max_connections: usize,
+    accept_rate_quota: Option<RateLimitQuotaConfig>,
+    accept_rate_limiter: Option<Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock>>>,
+}
+
+impl ListenConnLimiter {
+    pub fn new(
+        stats: Arc<ListenStats>,
+        max_connections: usize,
+        accept_rate_limit: Option<&RateLimitQuotaConfig>,
+    ) -> Self {
+        ListenConnLimiter {
+            stats,
+            max_connections,
+            accept_rate_quota: accept_rate_limit.cloned(),
+            accept_rate_limiter: accept_rate_limit
+                .map(|quota| Arc::new(RateLimiter::direct(quota.get_inner()))),
+        }
+    }
+
+    pub fn new_for_reload(
+        &self,
+        max_connections: usize,
+        accept_rate_limit: Option<&RateLimitQuotaConfig>,
+    ) -> Self {
+        if self.accept_rate_quota.as_ref() == accept_rate_limit {
+            // always use the old rate limiter when possible
+            ListenConnLimiter {
+                stats: self.stats.clone(),
+                max_connections,
+                accept_rate_quota: self.accept_rate_quota.clone(),
+                accept_rate_limiter: self.accept_rate_limiter.clone(),
+            }
+        } else {
+            ListenConnLimiter::new(self.stats.clone(), max_connections, accept_rate_limit)
+        }
+    }
+
+    #[inline]
+    pub fn max_connections(&self) -> usize {
+        self.max_connections
+    }
+
+    /// Check the limits for a new accepted connection
+    ///
+    /// The connection should be closed at once if error is returned,
+    /// otherwise the returned guard should be held until the connection is closed.
+    pub fn try_acquire(&self) -> Result<ListenConnAliveGuard, ListenConnLimitError> {
+        let Some(guard) = self.stats.try_add_alive_conn(self.max_connections) else {
+            self.stats.add_conn_limited();
+            return Err(ListenConnLimitError::MaxConnections);
+        };
+        if let Some(limiter) = &self.accept_rate_limiter {
+            if limiter.check().is_err() {
+                self.stats.add_rate_limited();
+                return Err(ListenConnLimitError::AcceptRate);
+            }
+        }
+        Ok(guard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroU32;
+
+    use g3_types::metrics::NodeName;
+
+    fn new_stats() -> Arc<ListenStats> {
+        Arc::new(ListenStats::new(&NodeName::default()))
+    }
+
+    #[test]
+    fn max_connections() {
+        let stats = new_stats();
+        let limiter = ListenConnLimiter::new(stats.clone(), 4, None);
+
+        let alive: Vec<_> = (0..4).map(|_| limiter.try_acquire().unwrap()).collect();
+        for _ in 0..3 {
+            assert_eq!(
+                limiter.try_acquire().err(),
+                Some(ListenConnLimitError::MaxConnections)
+            );
+        }
+        // existing connections are not affected
+        assert_eq!(alive.len(), 4);
+        assert_eq!(stats.alive_conn(), 4);
+        assert_eq!(stats.conn_limited(), 3);
+
+        drop(alive);
+        assert_eq!(stats.alive_conn(), 0);
+        let _conn = limiter.try_acquire().unwrap();
+        assert_eq!(stats.alive_conn(), 1);
+    }
+
+    #[test]
+    fn reload_max_connections() {
+        let stats = new_stats();
+        let limiter = ListenConnLimiter::new(stats.clone(), 4, None);
+        let alive: Vec<_> = (0..4).map(|_| limiter.try_acquire().unwrap()).collect();
+
+        let limiter = limiter.new_for_reload(2, None);
+        assert!(limiter.try_acquire().is_err());
+        drop(alive);
+        let _c1 = limiter.try_acquire().unwrap();
+        let _c2 = limiter.try_acquire().unwrap();
+        assert!(limiter.try_acquire().is_err());
+
+        let limiter = limiter.new_for_reload(0, None);
+        let alive: Vec<_> = (0..16).map(|_| limiter.try_acquire().unwrap()).collect();
+        assert_eq!(stats.alive_conn(), 18);
+        drop(alive);
+        assert_eq!(stats.alive_conn(), 2);
+    }
+
+    #[test]
+    fn accept_rate() {
+        let stats = new_stats();
+        let mut quota = RateLimitQuotaConfig::per_second(NonZeroU32::new(1).unwrap());
+        quota.allow_burst(NonZeroU32::new(3).unwrap());
+        let limiter = ListenConnLimiter::new(stats.clone(), 0, Some(&quota));
+
+        let alive: Vec<_> = (0..3).map(|_| limiter.try_acquire().unwrap()).collect();
+        assert_eq!(
+            limiter.try_acquire().err(),
+            Some(ListenConnLimitError::AcceptRate)
+        );
+        assert_eq!(stats.alive_conn(), 3);
+        assert_eq!(stats.rate_limited(), 1);
+
+        // the rate limiter state should be kept if the quota is not changed
+        let limiter = limiter.new_for_reload(0, Some(&quota));
+        assert!(limiter.try_acquire().is_err());
+        drop(alive);
+        assert_eq!(stats.alive_conn(), 0);
+    }
+}
@@ -4,11 +4,22 @@
  */
 
 mod stats;
-pub use stats::{ListenAliveGuard, ListenSnapshot, ListenStats};
+pub use stats::{
+    ListenAddrGuard, ListenAliveGuard, ListenConnAliveGuard, ListenSnapshot, ListenStats,
+};
+
+mod limit;
+pub use limit::{ListenConnLimitError, ListenConnLimiter};
 
 mod tcp;
 pub use tcp::{AcceptTcpServer, ListenTcpRuntime};
 
+mod worker;
+pub use worker::ListenWorkerConfig;
+
+mod check;
+pub use check::loopback_connect;
+
 mod udp;
 pub use udp::{ReceiveUdpRuntime, ReceiveUdpServer};
 
@@ -17,6 +28,11 @@ pub use udp::{ReceiveUdpRuntime, ReceiveUdpServer};
 mod quic;
 pub use quic::{AcceptQuicServer, ListenQuicConf, ListenQuicRuntime};
 
+#[cfg(unix)]
+pub mod handover;
+#[cfg(unix)]
+pub use handover::HandoverExportGuard;
+
 #[cfg(unix)]
 mod unix;
 #[cfg(unix)]
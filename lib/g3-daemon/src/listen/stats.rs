@@ -3,8 +3,10 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::sync::Arc;
-use std::sync::atomic::{AtomicIsize, AtomicU64, Ordering};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicIsize, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 use g3_io_ext::haproxy::ProxyProtocolReadError;
 use g3_types::metrics::NodeName;
@@ -16,6 +18,12 @@ pub struct ListenSnapshot {
     pub dropped: u64,
     pub timeout: u64,
     pub failed: u64,
+    pub conn_limited: u64,
+    pub rate_limited: u64,
+    pub proxy_protocol_invalid: u64,
+    pub shutdown_drained: u64,
+    pub shutdown_dropped: u64,
+    pub worker_accepted: BTreeMap<usize, u64>,
 }
 
 #[derive(Debug)]
@@ -28,6 +36,16 @@ pub struct ListenStats {
     dropped: AtomicU64,
     timeout: AtomicU64,
     failed: AtomicU64,
+    alive_conn: AtomicUsize,
+    conn_limited: AtomicU64,
+    rate_limited: AtomicU64,
+    proxy_protocol_invalid: AtomicU64,
+    shutdown_drained: AtomicU64,
+    shutdown_dropped: AtomicU64,
+    worker_accepted: Mutex<Vec<(usize, Arc<AtomicU64>)>>,
+
+    listen_addrs: Mutex<Vec<SocketAddr>>,
+    last_error: Mutex<Option<String>>,
 }
 
 impl ListenStats {
@@ -40,6 +58,15 @@ impl ListenStats {
             dropped: AtomicU64::new(0),
             timeout: AtomicU64::new(0),
             failed: AtomicU64::new(0),
+            alive_conn: AtomicUsize::new(0),
+            conn_limited: AtomicU64::new(0),
+            rate_limited: AtomicU64::new(0),
+            proxy_protocol_invalid: AtomicU64::new(0),
+            shutdown_drained: AtomicU64::new(0),
+            shutdown_dropped: AtomicU64::new(0),
+            worker_accepted: Mutex::new(Vec::new()),
+            listen_addrs: Mutex::new(Vec::new()),
+            last_error: Mutex::new(None),
         }
     }
 
@@ -67,6 +94,32 @@ impl ListenStats {
         self.running_runtime_count() > 0
     }
 
+    /// Record the local address of a running listen socket, until the returned guard is dropped
+    #[must_use]
+    pub fn add_listen_addr(self: &Arc<Self>, addr: SocketAddr) -> ListenAddrGuard {
+        self.listen_addrs.lock().unwrap().push(addr);
+        ListenAddrGuard {
+            stats: self.clone(),
+            addr,
+        }
+    }
+
+    /// Get the local addresses of all running listen sockets, without duplicates
+    pub fn listen_addrs(&self) -> Vec<SocketAddr> {
+        let mut addrs = self.listen_addrs.lock().unwrap().clone();
+        addrs.sort();
+        addrs.dedup();
+        addrs
+    }
+
+    /// Set the last bind or accept error, which will be kept until the next one
+    pub fn set_last_error(&self, e: String) {
+        *self.last_error.lock().unwrap() = Some(e);
+    }
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
     pub fn add_accepted(&self) {
         self.accepted.fetch_add(1, Ordering::Relaxed);
     }
@@ -74,6 +127,30 @@ impl ListenStats {
         self.accepted.load(Ordering::Relaxed)
     }
 
+    /// Get the accepted counter for listen instances running in the worker `worker_id`,
+    /// which will be kept across respawns
+    pub(crate) fn worker_accepted_counter(&self, worker_id: usize) -> Arc<AtomicU64> {
+        let mut counters = self.worker_accepted.lock().unwrap();
+        if let Some((_, counter)) = counters.iter().find(|(id, _)| *id == worker_id) {
+            return counter.clone();
+        }
+        let counter = Arc::new(AtomicU64::new(0));
+        counters.push((worker_id, counter.clone()));
+        counter
+    }
+    /// Get the accepted count of each worker, only for listen instances running in workers
+    pub fn worker_accepted(&self) -> Vec<(usize, u64)> {
+        let mut values: Vec<(usize, u64)> = self
+            .worker_accepted
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, counter)| (*id, counter.load(Ordering::Relaxed)))
+            .collect();
+        values.sort_unstable();
+        values
+    }
+
     pub fn add_dropped(&self) {
         self.dropped.fetch_add(1, Ordering::Relaxed);
     }
@@ -95,6 +172,73 @@ impl ListenStats {
         self.failed.load(Ordering::Relaxed)
     }
 
+    /// Add an alive connection, or return `None` if there are already `max` alive connections.
+    ///
+    /// Set `max` to 0 to disable the limit.
+    #[must_use]
+    pub fn try_add_alive_conn(self: &Arc<Self>, max: usize) -> Option<ListenConnAliveGuard> {
+        if max == 0 {
+            self.alive_conn.fetch_add(1, Ordering::AcqRel);
+            return Some(ListenConnAliveGuard(self.clone()));
+        }
+
+        let mut curr = self.alive_conn.load(Ordering::Acquire);
+        loop {
+            if curr >= max {
+                return None;
+            }
+            match self.alive_conn.compare_exchange(
+                curr,
+                curr + 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(ListenConnAliveGuard(self.clone())),
+                Err(actual) => curr = actual,
+            }
+        }
+    }
+    pub fn alive_conn(&self) -> usize {
+        self.alive_conn.load(Ordering::Acquire)
+    }
+
+    pub fn add_conn_limited(&self) {
+        self.conn_limited.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn conn_limited(&self) -> u64 {
+        self.conn_limited.load(Ordering::Relaxed)
+    }
+
+    pub fn add_rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn rate_limited(&self) -> u64 {
+        self.rate_limited.load(Ordering::Relaxed)
+    }
+
+    pub fn add_proxy_protocol_invalid(&self) {
+        self.proxy_protocol_invalid.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn proxy_protocol_invalid(&self) -> u64 {
+        self.proxy_protocol_invalid.load(Ordering::Relaxed)
+    }
+
+    /// Add the count of datagrams that have been drained from the socket buffer on shutdown
+    pub fn add_shutdown_drained(&self, count: u64) {
+        self.shutdown_drained.fetch_add(count, Ordering::Relaxed);
+    }
+    pub fn shutdown_drained(&self) -> u64 {
+        self.shutdown_drained.load(Ordering::Relaxed)
+    }
+
+    /// Add the count of datagrams that have been dropped as the drain timed out on shutdown
+    pub fn add_shutdown_dropped(&self, count: u64) {
+        self.shutdown_dropped.fetch_add(count, Ordering::Relaxed);
+    }
+    pub fn shutdown_dropped(&self) -> u64 {
+        self.shutdown_dropped.load(Ordering::Relaxed)
+    }
+
     pub fn add_by_proxy_protocol_error(&self, e: ProxyProtocolReadError) {
         match e {
             ProxyProtocolReadError::ReadTimeout => self.add_timeout(),
@@ -108,7 +252,7 @@ impl ListenStats {
             | ProxyProtocolReadError::InvalidFamily(_)
             | ProxyProtocolReadError::InvalidProtocol(_)
             | ProxyProtocolReadError::InvalidSrcAddr
-            | ProxyProtocolReadError::InvalidDstAddr => self.add_dropped(),
+            | ProxyProtocolReadError::InvalidDstAddr => self.add_proxy_protocol_invalid(),
         }
     }
 }
@@ -120,3 +264,25 @@ impl Drop for ListenAliveGuard {
         self.0.runtime_count.fetch_sub(1, Ordering::Relaxed);
     }
 }
+
+pub struct ListenAddrGuard {
+    stats: Arc<ListenStats>,
+    addr: SocketAddr,
+}
+
+impl Drop for ListenAddrGuard {
+    fn drop(&mut self) {
+        let mut addrs = self.stats.listen_addrs.lock().unwrap();
+        if let Some(i) = addrs.iter().position(|v| *v == self.addr) {
+            addrs.swap_remove(i);
+        }
+    }
+}
+
+pub struct ListenConnAliveGuard(Arc<ListenStats>);
+
+impl Drop for ListenConnAliveGuard {
+    fn drop(&mut self) {
+        self.0.alive_conn.fetch_sub(1, Ordering::AcqRel);
+    }
+}
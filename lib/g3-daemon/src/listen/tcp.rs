@@ -5,6 +5,7 @@
 
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use async_trait::async_trait;
 use log::{info, warn};
@@ -18,7 +19,8 @@ use g3_socket::RawSocket;
 use g3_std_ext::net::SocketAddrExt;
 use g3_types::net::TcpListenConfig;
 
-use crate::listen::{ListenAliveGuard, ListenStats};
+use crate::listen::{ListenAddrGuard, ListenAliveGuard, ListenStats, ListenWorkerConfig};
+use crate::runtime::worker::WorkerHandle;
 use crate::server::{BaseServer, ClientConnectionInfo, ReloadServer, ServerReloadCommand};
 
 #[async_trait]
@@ -30,6 +32,7 @@ pub trait AcceptTcpServer: BaseServer {
 pub struct ListenTcpRuntime<S> {
     server: S,
     listen_stats: Arc<ListenStats>,
+    worker_config: ListenWorkerConfig,
 }
 
 impl<S> ListenTcpRuntime<S>
@@ -40,9 +43,16 @@ where
         ListenTcpRuntime {
             server,
             listen_stats,
+            worker_config: ListenWorkerConfig::default(),
         }
     }
 
+    /// Set the workers to run the listen instances on, if `listen_in_worker` is enabled
+    pub fn with_worker_config(mut self, config: &ListenWorkerConfig) -> Self {
+        self.worker_config = config.clone();
+        self
+    }
+
     fn create_instance(&self) -> ListenTcpRuntimeInstance<S> {
         let server_type = self.server.r#type();
         let server_version = self.server.version();
@@ -54,9 +64,27 @@ where
             #[cfg(target_os = "linux")]
             follow_incoming_cpu: false,
             listen_stats: self.listen_stats.clone(),
+            worker_accepted: None,
             instance_id: 0,
             _alive_guard: None,
+            _addr_guard: None,
+            #[cfg(unix)]
+            _handover_guard: None,
+        }
+    }
+
+    fn new_std_listener(
+        &self,
+        listen_config: &TcpListenConfig,
+    ) -> std::io::Result<std::net::TcpListener> {
+        #[cfg(unix)]
+        if let Some(listener) = crate::listen::handover::take_tcp_listener(
+            self.server.name().as_str(),
+            listen_config.address(),
+        ) {
+            return Ok(listener);
         }
+        g3_socket::tcp::new_std_listener(listen_config)
     }
 
     pub fn run_all_instances(
@@ -66,10 +94,12 @@ where
         server_reload_sender: &broadcast::Sender<ServerReloadCommand>,
     ) -> anyhow::Result<()> {
         let mut instance_count = listen_config.instance();
+        let mut listen_workers = Vec::new();
         if listen_in_worker {
             let worker_count = crate::runtime::worker::worker_count();
             if worker_count > 0 {
-                instance_count = worker_count;
+                listen_workers = self.worker_config.listen_workers(worker_count);
+                instance_count = listen_workers.len();
             }
         }
 
@@ -77,10 +107,26 @@ where
             let mut runtime = self.create_instance();
             runtime.instance_id = i;
 
-            let listener = g3_socket::tcp::new_std_listener(listen_config)?;
+            let worker = listen_workers
+                .get(i)
+                .and_then(|id| crate::runtime::worker::get_handle(*id));
+
+            let listener = match self.new_std_listener(listen_config) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    self.listen_stats
+                        .set_last_error(format!("bind {}: {e}", listen_config.address()));
+                    return Err(e.into());
+                }
+            };
+            #[cfg(unix)]
+            {
+                runtime._handover_guard =
+                    crate::listen::handover::export_tcp(self.server.name().as_str(), i, &listener);
+            }
             runtime.into_running(
                 listener,
-                listen_in_worker,
+                worker,
                 listen_config.follow_cpu_affinity(),
                 server_reload_sender.subscribe(),
             );
@@ -97,8 +143,12 @@ pub struct ListenTcpRuntimeInstance<S> {
     #[cfg(target_os = "linux")]
     follow_incoming_cpu: bool,
     listen_stats: Arc<ListenStats>,
+    worker_accepted: Option<Arc<AtomicU64>>,
     instance_id: usize,
     _alive_guard: Option<ListenAliveGuard>,
+    _addr_guard: Option<ListenAddrGuard>,
+    #[cfg(unix)]
+    _handover_guard: Option<crate::listen::HandoverExportGuard>,
 }
 
 impl<S> ListenTcpRuntimeInstance<S>
@@ -183,6 +233,14 @@ where
                         match result {
                             Ok(Some((stream, peer_addr, local_addr))) => {
                                 self.listen_stats.add_accepted();
+                                if let Some(worker_accepted) = &self.worker_accepted {
+                                    worker_accepted.fetch_add(1, Ordering::Relaxed);
+                                }
+                                if !g3_io_ext::global_copy_memory().check_accept() {
+                                    // the copy memory budget is exhausted
+                                    self.listen_stats.add_dropped();
+                                    return Ok(());
+                                }
                                 self.run_task(
                                     stream,
                                     peer_addr.to_canonical(),
@@ -197,6 +255,7 @@ where
                             }
                             Err(e) => {
                                 self.listen_stats.add_failed();
+                                self.listen_stats.set_last_error(format!("accept: {e}"));
                                 warn!("SRT[{}_v{}#{}] accept: {e:?}",
                                     self.server.name(), self.server_version, self.instance_id);
                                 Ok(())
@@ -247,12 +306,11 @@ where
         }
     }
 
-    fn get_rt_handle(&mut self, listen_in_worker: bool) -> (Handle, Option<CpuAffinity>) {
-        if listen_in_worker {
-            if let Some(rt) = crate::runtime::worker::select_listen_handle() {
-                self.worker_id = Some(rt.id);
-                return (rt.handle, rt.cpu_affinity);
-            }
+    fn get_rt_handle(&mut self, worker: Option<WorkerHandle>) -> (Handle, Option<CpuAffinity>) {
+        if let Some(rt) = worker {
+            self.worker_id = Some(rt.id);
+            self.worker_accepted = Some(self.listen_stats.worker_accepted_counter(rt.id));
+            return (rt.handle, rt.cpu_affinity);
         }
         (Handle::current(), None)
     }
@@ -260,11 +318,11 @@ where
     fn into_running(
         mut self,
         listener: std::net::TcpListener,
-        listen_in_worker: bool,
+        worker: Option<WorkerHandle>,
         follow_cpu_affinity: bool,
         server_reload_channel: broadcast::Receiver<ServerReloadCommand>,
     ) {
-        let (handle, cpu_affinity) = self.get_rt_handle(listen_in_worker);
+        let (handle, cpu_affinity) = self.get_rt_handle(worker);
         handle.spawn(async move {
             if follow_cpu_affinity {
                 #[cfg(target_os = "linux")]
@@ -288,11 +346,16 @@ where
             // make sure the listen socket associated with the correct reactor
             match tokio::net::TcpListener::from_std(listener) {
                 Ok(listener) => {
+                    if let Ok(addr) = listener.local_addr() {
+                        self._addr_guard = Some(self.listen_stats.add_listen_addr(addr));
+                    }
                     self.pre_start();
                     self.run(LimitedTcpListener::new(listener), server_reload_channel)
                         .await;
                 }
                 Err(e) => {
+                    self.listen_stats
+                        .set_last_error(format!("listen async: {e}"));
                     warn!(
                         "SRT[{}_v{}#{}] listen async: {e:?}",
                         self.server.name(),
@@ -6,6 +6,9 @@
 use std::future::poll_fn;
 use std::io::{self, IoSliceMut};
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Instant;
 
 use log::{info, warn};
 use tokio::net::UdpSocket;
@@ -16,8 +19,15 @@ use g3_io_ext::UdpSocketExt;
 use g3_io_sys::udp::RecvMsgHdr;
 use g3_types::net::UdpListenConfig;
 
+use super::ListenStats;
 use crate::server::{BaseServer, ReloadServer, ServerReloadCommand};
 
+/// Yield to the runtime after draining this count of packets
+const DRAIN_YIELD_COUNT: u64 = 64;
+/// The max count of packets to discard after the drain timed out, the packets after this
+/// will be dropped along with the socket and will not be counted
+const DRAIN_MAX_DISCARD_COUNT: u64 = 65536;
+
 pub trait ReceiveUdpServer: BaseServer {
     fn receive_udp_packet(
         &self,
@@ -26,6 +36,21 @@ pub trait ReceiveUdpServer: BaseServer {
         server_addr: SocketAddr,
         worker_id: Option<usize>,
     );
+
+    /// Receive a packet that was buffered in the socket when the runtime is going offline.
+    ///
+    /// No new association should be created for these packets.
+    /// Return false if the packet is dropped.
+    fn drain_udp_packet(
+        &self,
+        packet: &[u8],
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+        worker_id: Option<usize>,
+    ) -> bool {
+        self.receive_udp_packet(packet, client_addr, server_addr, worker_id);
+        true
+    }
 }
 
 #[derive(Clone)]
@@ -35,8 +60,10 @@ pub struct ReceiveUdpRuntime<S> {
     server_version: usize,
     worker_id: Option<usize>,
     listen_config: UdpListenConfig,
-    //listen_stats: Arc<ListenStats>,
+    listen_stats: Option<Arc<ListenStats>>,
     instance_id: usize,
+    #[cfg(unix)]
+    _handover_guard: Option<Arc<crate::listen::HandoverExportGuard>>,
 }
 
 impl<S> ReceiveUdpRuntime<S>
@@ -52,8 +79,28 @@ where
             server_version,
             worker_id: None,
             listen_config,
+            listen_stats: None,
             instance_id: 0,
+            #[cfg(unix)]
+            _handover_guard: None,
+        }
+    }
+
+    /// Set the listen stats, which will be used to record the drained and dropped packets on
+    /// shutdown
+    pub fn set_listen_stats(&mut self, listen_stats: Arc<ListenStats>) {
+        self.listen_stats = Some(listen_stats);
+    }
+
+    fn new_std_socket(&self) -> io::Result<std::net::UdpSocket> {
+        #[cfg(unix)]
+        if let Some(socket) = crate::listen::handover::take_udp_socket(
+            self.server.name().as_str(),
+            self.listen_config.address(),
+        ) {
+            return Ok(socket);
         }
+        g3_socket::udp::new_std_bind_listen(&self.listen_config)
     }
 
     fn pre_start(&self) {
@@ -123,6 +170,7 @@ where
                     info!("SRT[{}_v{}#{}] will go offline",
                         self.server.name(), self.server_version, self.instance_id);
                     self.pre_stop();
+                    self.drain(&socket, listen_addr, &mut buf).await;
                     break;
                 }
                 r = self.recv_packet(&socket, listen_addr, &mut buf) => {
@@ -143,6 +191,96 @@ where
         self.post_stop();
     }
 
+    /// Process the packets buffered in the socket before going offline.
+    ///
+    /// The drain will stop once there is no more buffered packet, and the packets received
+    /// after the drain timeout will be dropped.
+    async fn drain(&self, socket: &UdpSocket, listen_addr: SocketAddr, buf: &mut [u8]) {
+        let timeout = self.listen_config.drain_timeout();
+        if timeout.is_zero() {
+            return;
+        }
+
+        let deadline = Instant::now() + timeout;
+        let mut drained = 0u64;
+        let mut dropped = 0u64;
+        let mut retried = false;
+        loop {
+            let Some(r) = self.try_recv_packet(socket, listen_addr, buf).await else {
+                if retried {
+                    break;
+                }
+                // let the io driver update the readiness of the socket, and try again
+                retried = true;
+                tokio::task::yield_now().await;
+                continue;
+            };
+            retried = false;
+
+            match r {
+                Ok((len, peer_addr, local_addr)) => {
+                    if Instant::now() >= deadline {
+                        dropped += 1;
+                        if dropped >= DRAIN_MAX_DISCARD_COUNT {
+                            break;
+                        }
+                    } else if self.server.drain_udp_packet(
+                        &buf[..len],
+                        peer_addr,
+                        local_addr,
+                        self.worker_id,
+                    ) {
+                        drained += 1;
+                    } else {
+                        dropped += 1;
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        "SRT[{}_v{}#{}] error draining data from socket, error: {e}",
+                        self.server.name(),
+                        self.server_version,
+                        self.instance_id
+                    );
+                    break;
+                }
+            }
+
+            if (drained + dropped) % DRAIN_YIELD_COUNT == 0 {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        info!(
+            "SRT[{}_v{}#{}] drained {drained} packets, dropped {dropped} packets",
+            self.server.name(),
+            self.server_version,
+            self.instance_id
+        );
+        if let Some(stats) = &self.listen_stats {
+            stats.add_shutdown_drained(drained);
+            stats.add_shutdown_dropped(dropped);
+        }
+    }
+
+    /// Receive a packet that is already buffered in the socket, without waiting for new ones
+    async fn try_recv_packet(
+        &self,
+        socket: &UdpSocket,
+        listen_addr: SocketAddr,
+        buf: &mut [u8],
+    ) -> Option<io::Result<(usize, SocketAddr, SocketAddr)>> {
+        let mut hdr = RecvMsgHdr::new([IoSliceMut::new(buf)]);
+
+        let r = poll_fn(|cx| match socket.poll_recvmsg(cx, &mut hdr) {
+            Poll::Ready(r) => Poll::Ready(Some(r)),
+            Poll::Pending => Poll::Ready(None),
+        })
+        .await?;
+
+        Some(r.and_then(|_| Self::parse_recv_hdr(&hdr, listen_addr)))
+    }
+
     async fn recv_packet(
         &self,
         socket: &UdpSocket,
@@ -153,10 +291,20 @@ where
 
         poll_fn(|cx| socket.poll_recvmsg(cx, &mut hdr)).await?;
 
+        Self::parse_recv_hdr(&hdr, listen_addr)
+    }
+
+    fn parse_recv_hdr<const C: usize>(
+        hdr: &RecvMsgHdr<'_, C>,
+        listen_addr: SocketAddr,
+    ) -> io::Result<(usize, SocketAddr, SocketAddr)> {
         let peer_addr = hdr
             .src_addr()
             .ok_or_else(|| io::Error::other("unable to get peer address"))?;
-        let local_addr = hdr.dst_addr(listen_addr);
+        // the original destination address is available only on transparent sockets
+        let local_addr = hdr
+            .orig_dst_addr()
+            .unwrap_or_else(|| hdr.dst_addr(listen_addr));
 
         Ok((hdr.n_recv, peer_addr, local_addr))
     }
@@ -215,8 +363,14 @@ where
             let mut runtime = self.clone();
             runtime.instance_id = i;
 
-            let socket = g3_socket::udp::new_std_bind_listen(&self.listen_config)?;
+            let socket = self.new_std_socket()?;
             let listen_addr = socket.local_addr()?;
+            #[cfg(unix)]
+            {
+                runtime._handover_guard =
+                    crate::listen::handover::export_udp(self.server.name().as_str(), i, &socket)
+                        .map(Arc::new);
+            }
             runtime.into_running(
                 socket,
                 listen_addr,
@@ -227,3 +381,103 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use g3_types::metrics::NodeName;
+
+    use crate::listen::ListenSnapshot;
+
+    #[derive(Clone)]
+    struct MockServer {
+        name: NodeName,
+        received: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl BaseServer for MockServer {
+        fn name(&self) -> &NodeName {
+            &self.name
+        }
+
+        fn r#type(&self) -> &'static str {
+            "mock"
+        }
+
+        fn version(&self) -> usize {
+            1
+        }
+    }
+
+    impl ReloadServer for MockServer {
+        fn reload(&self) -> Self {
+            self.clone()
+        }
+    }
+
+    impl ReceiveUdpServer for MockServer {
+        fn receive_udp_packet(
+            &self,
+            packet: &[u8],
+            _client_addr: SocketAddr,
+            _server_addr: SocketAddr,
+            _worker_id: Option<usize>,
+        ) {
+            self.received.lock().unwrap().push(packet.to_vec());
+        }
+    }
+
+    /// Queue some packets in the socket, and then shutdown the runtime before receiving them
+    async fn shutdown_with_queued(drain_timeout: Duration) -> (usize, ListenSnapshot) {
+        let server = MockServer {
+            name: NodeName::from_str("mock").unwrap(),
+            received: Arc::new(Mutex::new(Vec::new())),
+        };
+        let mut listen_config = UdpListenConfig::new("127.0.0.1:0".parse().unwrap());
+        listen_config.set_drain_timeout(drain_timeout);
+        let listen_stats = Arc::new(ListenStats::new(&server.name));
+        let mut runtime = ReceiveUdpRuntime::new(server.clone(), listen_config);
+        runtime.set_listen_stats(listen_stats.clone());
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let listen_addr = socket.local_addr().unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        for i in 0..10u8 {
+            client.send_to(&[i], listen_addr).await.unwrap();
+        }
+        // make sure all packets have been queued
+        std::thread::sleep(Duration::from_millis(50));
+
+        let (sender, receiver) = broadcast::channel(1);
+        let _ = sender.send(ServerReloadCommand::QuitRuntime);
+        runtime.run(socket, listen_addr, receiver).await;
+
+        let received = server.received.lock().unwrap().len();
+        let snapshot = ListenSnapshot {
+            shutdown_drained: listen_stats.shutdown_drained(),
+            shutdown_dropped: listen_stats.shutdown_dropped(),
+            ..Default::default()
+        };
+        (received, snapshot)
+    }
+
+    #[tokio::test]
+    async fn drain_on_shutdown() {
+        let (received, snapshot) = shutdown_with_queued(Duration::from_secs(1)).await;
+        assert_eq!(received, 10);
+        assert_eq!(snapshot.shutdown_drained, 10);
+        assert_eq!(snapshot.shutdown_dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn no_drain_on_shutdown() {
+        let (received, snapshot) = shutdown_with_queued(Duration::ZERO).await;
+        assert_eq!(received, 0);
+        assert_eq!(snapshot.shutdown_drained, 0);
+        assert_eq!(snapshot.shutdown_dropped, 0);
+    }
+}
@@ -0,0 +1,102 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::BTreeSet;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+/// Select the workers to run the listen instances on, for servers that set `listen_in_worker`.
+///
+/// The CPU affinity of the listen instances follows the worker runtime they run in, which is set
+/// by `sched_affinity` in the worker runtime config and exposed as `WorkerHandle::cpu_affinity`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListenWorkerConfig {
+    worker_set: BTreeSet<usize>,
+}
+
+impl ListenWorkerConfig {
+    pub fn set_worker_set(&mut self, v: &Yaml) -> anyhow::Result<()> {
+        self.worker_set.clear();
+        match v {
+            Yaml::Array(seq) => {
+                for (i, v) in seq.iter().enumerate() {
+                    let id = g3_yaml::value::as_usize(v)
+                        .context(format!("invalid worker index value for #{i}"))?;
+                    self.worker_set.insert(id);
+                }
+            }
+            _ => {
+                let id = g3_yaml::value::as_usize(v).context("invalid worker index value")?;
+                self.worker_set.insert(id);
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.worker_set.is_empty()
+    }
+
+    /// Check the worker indices against the worker runtime config
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let Some(config) = crate::runtime::config::get_worker_config() else {
+            return Err(anyhow!("worker runtime is not configured"));
+        };
+        self.check_worker_count(config.worker_count())
+    }
+
+    fn check_worker_count(&self, worker_count: usize) -> anyhow::Result<()> {
+        for id in &self.worker_set {
+            if *id >= worker_count {
+                return Err(anyhow!(
+                    "worker index {id} is out of range, the worker count is {worker_count}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the workers to run the listen instances on, one instance for each worker
+    pub fn listen_workers(&self, worker_count: usize) -> Vec<usize> {
+        if self.worker_set.is_empty() {
+            (0..worker_count).collect()
+        } else {
+            self.worker_set
+                .iter()
+                .filter(|id| **id < worker_count)
+                .copied()
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn load_yaml(s: &str) -> Yaml {
+        YamlLoader::load_from_str(s).unwrap().pop().unwrap()
+    }
+
+    #[test]
+    fn listen_workers() {
+        let mut config = ListenWorkerConfig::default();
+        assert_eq!(config.listen_workers(4), vec![0, 1, 2, 3]);
+
+        config.set_worker_set(&load_yaml("[3, 1, 1]")).unwrap();
+        assert_eq!(config.listen_workers(4), vec![1, 3]);
+        assert!(config.check_worker_count(4).is_ok());
+        assert!(config.check_worker_count(3).is_err());
+
+        config.set_worker_set(&load_yaml("2")).unwrap();
+        assert_eq!(config.listen_workers(4), vec![2]);
+    }
+}
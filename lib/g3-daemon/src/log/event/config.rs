@@ -10,6 +10,7 @@ use anyhow::{Context, anyhow};
 use slog::{Logger, OwnedKV, SendSyncRefUnwindSafeKV, slog_o};
 use yaml_rust::Yaml;
 
+use g3_filelog::FileLogConfig;
 use g3_fluentd::FluentdClientConfig;
 #[cfg(target_os = "linux")]
 use g3_journal::JournalConfig;
@@ -29,6 +30,7 @@ pub enum LogConfigDriver {
     Journal(JournalConfig),
     Syslog(SyslogBuilder),
     Fluentd(Arc<FluentdClientConfig>),
+    CompressedFile(Arc<FileLogConfig>),
     Stdout,
 }
 
@@ -130,6 +132,12 @@ impl LogConfig {
                         config.driver = LogConfigDriver::Fluentd(Arc::new(client));
                         Ok(())
                     }
+                    "compressed_file" => {
+                        let file_config = FileLogConfig::parse_yaml(v, conf_dir)
+                            .context("invalid compressed file config")?;
+                        config.driver = LogConfigDriver::CompressedFile(Arc::new(file_config));
+                        Ok(())
+                    }
                     "async_channel_size" | "channel_size" => {
                         let channel_size = g3_yaml::value::as_usize(v)
                             .context(format!("invalid usize value for key {k}"))?;
@@ -240,6 +248,13 @@ impl LogConfig {
                 let drain = ReportLogIoError::new(drain, &logger_name, self.io_err_sampling_mask);
                 Some(Logger::root(drain, common_values))
             }
+            LogConfigDriver::CompressedFile(file_conf) => {
+                let drain = g3_filelog::new_async_logger(&async_conf, &file_conf, &logger_name);
+                let logger_stats = LoggerStats::new(&logger_name, drain.get_stats());
+                super::registry::add(logger_name.clone(), Arc::new(logger_stats));
+                let drain = ReportLogIoError::new(drain, &logger_name, self.io_err_sampling_mask);
+                Some(Logger::root(drain, common_values))
+            }
             LogConfigDriver::Stdout => {
                 let drain = g3_stdlog::new_async_logger(&async_conf, false, true);
                 let logger_stats = LoggerStats::new(&logger_name, drain.get_stats());
@@ -4,6 +4,7 @@
  */
 
 pub mod process;
+pub mod task;
 
 #[cfg(feature = "event-log")]
 mod event;
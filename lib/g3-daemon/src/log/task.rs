@@ -0,0 +1,558 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use slog::{KV, Record, Serializer, Value};
+use uuid::Uuid;
+
+use g3_slog_types::{LtDateTime, LtDuration, LtIpAddr, LtUpstreamAddr, LtUuid};
+use g3_types::net::UpstreamAddr;
+
+static UNIFIED_FIELD_NAMES: AtomicBool = AtomicBool::new(false);
+
+/// The naming of the task log fields that have been renamed in the unified schema
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TaskLogFieldNames {
+    /// Keep the old names used by each task type
+    #[default]
+    Legacy,
+    /// Use the names defined in the unified schema
+    Unified,
+}
+
+impl TaskLogFieldNames {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskLogFieldNames::Legacy => "legacy",
+            TaskLogFieldNames::Unified => "unified",
+        }
+    }
+}
+
+impl FromStr for TaskLogFieldNames {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "legacy" => Ok(TaskLogFieldNames::Legacy),
+            "unified" => Ok(TaskLogFieldNames::Unified),
+            _ => Err(()),
+        }
+    }
+}
+
+pub fn set_field_names(names: TaskLogFieldNames) {
+    UNIFIED_FIELD_NAMES.store(names == TaskLogFieldNames::Unified, Ordering::Relaxed);
+}
+
+pub fn field_names() -> TaskLogFieldNames {
+    if UNIFIED_FIELD_NAMES.load(Ordering::Relaxed) {
+        TaskLogFieldNames::Unified
+    } else {
+        TaskLogFieldNames::Legacy
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TaskLogFieldType {
+    Str,
+    Uuid,
+    DateTime,
+    SocketAddr,
+    Upstream,
+    Duration,
+    U64,
+    Bool,
+}
+
+impl TaskLogFieldType {
+    fn check(&self, value: &str) -> bool {
+        match self {
+            TaskLogFieldType::Str => true,
+            TaskLogFieldType::Uuid => Uuid::try_parse(value).is_ok(),
+            TaskLogFieldType::DateTime => DateTime::parse_from_rfc3339(value).is_ok(),
+            TaskLogFieldType::SocketAddr => SocketAddr::from_str(value).is_ok(),
+            TaskLogFieldType::Upstream => UpstreamAddr::from_str(value).is_ok(),
+            TaskLogFieldType::Duration => {
+                let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == 'µ');
+                number.len() < value.len() && f64::from_str(number).is_ok()
+            }
+            TaskLogFieldType::U64 => u64::from_str(value).is_ok(),
+            TaskLogFieldType::Bool => bool::from_str(value).is_ok(),
+        }
+    }
+}
+
+/// The common fields in the unified task log schema, and whether they are required
+///
+/// The server_type and server_name fields are set in the logger by the daemon.
+pub const TASK_LOG_COMMON_FIELDS: &[(&str, TaskLogFieldType, bool)] = &[
+    ("task_type", TaskLogFieldType::Str, true),
+    ("task_id", TaskLogFieldType::Uuid, true),
+    ("task_event", TaskLogFieldType::Str, false),
+    ("stage", TaskLogFieldType::Str, true),
+    ("start_at", TaskLogFieldType::DateTime, true),
+    ("user", TaskLogFieldType::Str, false),
+    ("server_addr", TaskLogFieldType::SocketAddr, false),
+    ("client_addr", TaskLogFieldType::SocketAddr, false),
+    ("upstream", TaskLogFieldType::Upstream, false),
+    ("reason", TaskLogFieldType::Str, false),
+    ("wait_time", TaskLogFieldType::Duration, false),
+    ("ready_time", TaskLogFieldType::Duration, false),
+    ("total_time", TaskLogFieldType::Duration, false),
+    ("c_rd_bytes", TaskLogFieldType::U64, false),
+    ("c_wr_bytes", TaskLogFieldType::U64, false),
+    ("r_rd_bytes", TaskLogFieldType::U64, false),
+    ("r_wr_bytes", TaskLogFieldType::U64, false),
+];
+
+/// Validate the serialized fields of a task log against the unified schema
+///
+/// Empty values and the `None` placeholder are accepted for optional fields.
+/// Extension fields are not checked.
+pub fn validate_task_log<'a, I>(fields: I) -> anyhow::Result<()>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)> + Clone,
+{
+    for (name, field_type, required) in TASK_LOG_COMMON_FIELDS {
+        let Some((_, value)) = fields.clone().into_iter().find(|(k, _)| k == name) else {
+            if *required {
+                return Err(anyhow!("required field {name} is missing"));
+            }
+            continue;
+        };
+        if value.is_empty() || value == "None" {
+            if *required {
+                return Err(anyhow!("required field {name} is empty"));
+            }
+            continue;
+        }
+        if !field_type.check(value) {
+            return Err(anyhow!(
+                "invalid {field_type:?} value {value} for field {name}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TaskLogIoBytes {
+    pub client_rd: u64,
+    pub client_wr: u64,
+    pub remote_rd: u64,
+    pub remote_wr: u64,
+}
+
+/// The value of a server specific task log field
+pub enum TaskLogValue<'a> {
+    None,
+    Str(&'a str),
+    String(String),
+    U64(u64),
+    Bool(bool),
+    IpAddr(IpAddr),
+    SocketAddr(SocketAddr),
+    Upstream(&'a UpstreamAddr),
+    Duration(Duration),
+    DateTime(&'a DateTime<Utc>),
+}
+
+macro_rules! impl_from_for_value {
+    ($t:ty, $v:ident) => {
+        impl<'a> From<$t> for TaskLogValue<'a> {
+            fn from(v: $t) -> Self {
+                TaskLogValue::$v(v)
+            }
+        }
+
+        impl<'a> From<Option<$t>> for TaskLogValue<'a> {
+            fn from(v: Option<$t>) -> Self {
+                v.map(TaskLogValue::$v).unwrap_or(TaskLogValue::None)
+            }
+        }
+    };
+}
+
+impl_from_for_value!(&'a str, Str);
+impl_from_for_value!(String, String);
+impl_from_for_value!(u64, U64);
+impl_from_for_value!(bool, Bool);
+impl_from_for_value!(IpAddr, IpAddr);
+impl_from_for_value!(SocketAddr, SocketAddr);
+impl_from_for_value!(&'a UpstreamAddr, Upstream);
+impl_from_for_value!(Duration, Duration);
+impl_from_for_value!(&'a DateTime<Utc>, DateTime);
+
+impl<'a> From<usize> for TaskLogValue<'a> {
+    fn from(v: usize) -> Self {
+        TaskLogValue::U64(v as u64)
+    }
+}
+
+impl Value for TaskLogValue<'_> {
+    fn serialize(
+        &self,
+        record: &Record,
+        key: slog::Key,
+        serializer: &mut dyn Serializer,
+    ) -> slog::Result {
+        match self {
+            TaskLogValue::None => serializer.emit_none(key),
+            TaskLogValue::Str(s) => serializer.emit_str(key, s),
+            TaskLogValue::String(s) => serializer.emit_str(key, s),
+            TaskLogValue::U64(v) => serializer.emit_u64(key, *v),
+            TaskLogValue::Bool(v) => serializer.emit_bool(key, *v),
+            TaskLogValue::IpAddr(ip) => LtIpAddr(*ip).serialize(record, key, serializer),
+            TaskLogValue::SocketAddr(addr) => addr.serialize(record, key, serializer),
+            TaskLogValue::Upstream(ups) => LtUpstreamAddr(ups).serialize(record, key, serializer),
+            TaskLogValue::Duration(d) => LtDuration(*d).serialize(record, key, serializer),
+            TaskLogValue::DateTime(t) => LtDateTime(t).serialize(record, key, serializer),
+        }
+    }
+}
+
+/// A task log record with typed common fields
+///
+/// Only the common fields that have been set will be present, with the names in the unified
+/// schema, unless a legacy name is given and the legacy field names are in use. The extension
+/// fields are serialized after them in the order they are added.
+pub struct TaskLogRecord<'a> {
+    task_type: &'static str,
+    task_id: &'a Uuid,
+    task_event: Option<&'static str>,
+    stage: &'static str,
+    start_at: &'a DateTime<Utc>,
+    user: Option<Option<&'a str>>,
+    server_addr: Option<(&'static str, SocketAddr)>,
+    client_addr: Option<(&'static str, SocketAddr)>,
+    upstream: Option<(&'static str, &'a UpstreamAddr)>,
+    reason: Option<&'a str>,
+    wait_time: Option<Duration>,
+    ready_time: Option<Duration>,
+    total_time: Option<Duration>,
+    io_bytes: Option<TaskLogIoBytes>,
+    extensions: Vec<(&'static str, TaskLogValue<'a>)>,
+    field_names: TaskLogFieldNames,
+}
+
+impl<'a> TaskLogRecord<'a> {
+    pub fn new(
+        task_type: &'static str,
+        task_id: &'a Uuid,
+        stage: &'static str,
+        start_at: &'a DateTime<Utc>,
+    ) -> Self {
+        TaskLogRecord {
+            task_type,
+            task_id,
+            task_event: None,
+            stage,
+            start_at,
+            user: None,
+            server_addr: None,
+            client_addr: None,
+            upstream: None,
+            reason: None,
+            wait_time: None,
+            ready_time: None,
+            total_time: None,
+            io_bytes: None,
+            extensions: Vec::new(),
+            field_names: field_names(),
+        }
+    }
+
+    pub fn task_event(mut self, event: &'static str) -> Self {
+        self.task_event = Some(event);
+        self
+    }
+
+    pub fn user(mut self, user: Option<&'a str>) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    pub fn server_addr(mut self, addr: SocketAddr) -> Self {
+        self.server_addr = Some(("server_addr", addr));
+        self
+    }
+
+    /// Set the server address, which will be logged as `legacy_name` if not using the unified names
+    pub fn server_addr_renamed(mut self, legacy_name: &'static str, addr: SocketAddr) -> Self {
+        self.server_addr = Some((self.select_name("server_addr", legacy_name), addr));
+        self
+    }
+
+    pub fn client_addr(mut self, addr: SocketAddr) -> Self {
+        self.client_addr = Some(("client_addr", addr));
+        self
+    }
+
+    /// Set the client address, which will be logged as `legacy_name` if not using the unified names
+    pub fn client_addr_renamed(mut self, legacy_name: &'static str, addr: SocketAddr) -> Self {
+        self.client_addr = Some((self.select_name("client_addr", legacy_name), addr));
+        self
+    }
+
+    pub fn upstream(mut self, upstream: &'a UpstreamAddr) -> Self {
+        self.upstream = Some(("upstream", upstream));
+        self
+    }
+
+    /// Set the upstream address, which will be logged as `legacy_name` if not using the unified names
+    pub fn upstream_renamed(
+        mut self,
+        legacy_name: &'static str,
+        upstream: &'a UpstreamAddr,
+    ) -> Self {
+        self.upstream = Some((self.select_name("upstream", legacy_name), upstream));
+        self
+    }
+
+    pub fn reason(mut self, reason: &'a str) -> Self {
+        self.reason = Some(reason);
+        self
+    }
+
+    pub fn wait_time(mut self, time: Duration) -> Self {
+        self.wait_time = Some(time);
+        self
+    }
+
+    pub fn ready_time(mut self, time: Duration) -> Self {
+        self.ready_time = Some(time);
+        self
+    }
+
+    pub fn total_time(mut self, time: Duration) -> Self {
+        self.total_time = Some(time);
+        self
+    }
+
+    pub fn io_bytes(mut self, io_bytes: TaskLogIoBytes) -> Self {
+        self.io_bytes = Some(io_bytes);
+        self
+    }
+
+    /// Add a server specific field, which should not use the name of any common field
+    pub fn extension<V>(mut self, name: &'static str, value: V) -> Self
+    where
+        V: Into<TaskLogValue<'a>>,
+    {
+        self.extensions.push((name, value.into()));
+        self
+    }
+
+    fn select_name(&self, unified: &'static str, legacy: &'static str) -> &'static str {
+        match self.field_names {
+            TaskLogFieldNames::Legacy => legacy,
+            TaskLogFieldNames::Unified => unified,
+        }
+    }
+}
+
+impl KV for TaskLogRecord<'_> {
+    fn serialize(&self, record: &Record, serializer: &mut dyn Serializer) -> slog::Result {
+        // the slog serializers emit the KVs in reverse order, so do it reversely here
+        for (name, value) in self.extensions.iter().rev() {
+            value.serialize(record, (*name).into(), serializer)?;
+        }
+        if let Some(io) = &self.io_bytes {
+            serializer.emit_u64("r_wr_bytes".into(), io.remote_wr)?;
+            serializer.emit_u64("r_rd_bytes".into(), io.remote_rd)?;
+            serializer.emit_u64("c_wr_bytes".into(), io.client_wr)?;
+            serializer.emit_u64("c_rd_bytes".into(), io.client_rd)?;
+        }
+        if let Some(time) = self.total_time {
+            LtDuration(time).serialize(record, "total_time".into(), serializer)?;
+        }
+        if let Some(time) = self.ready_time {
+            LtDuration(time).serialize(record, "ready_time".into(), serializer)?;
+        }
+        if let Some(time) = self.wait_time {
+            LtDuration(time).serialize(record, "wait_time".into(), serializer)?;
+        }
+        if let Some(reason) = self.reason {
+            serializer.emit_str("reason".into(), reason)?;
+        }
+        if let Some((name, upstream)) = self.upstream {
+            LtUpstreamAddr(upstream).serialize(record, name.into(), serializer)?;
+        }
+        if let Some((name, addr)) = self.client_addr {
+            addr.serialize(record, name.into(), serializer)?;
+        }
+        if let Some((name, addr)) = self.server_addr {
+            addr.serialize(record, name.into(), serializer)?;
+        }
+        if let Some(user) = self.user {
+            user.serialize(record, "user".into(), serializer)?;
+        }
+        LtDateTime(self.start_at).serialize(record, "start_at".into(), serializer)?;
+        serializer.emit_str("stage".into(), self.stage)?;
+        if let Some(event) = self.task_event {
+            serializer.emit_str("task_event".into(), event)?;
+        }
+        LtUuid(self.task_id).serialize(record, "task_id".into(), serializer)?;
+        serializer.emit_str("task_type".into(), self.task_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+
+    use slog::{Drain, Key, Logger, OwnedKVList, slog_info};
+
+    use crate::server::task::generate_uuid;
+
+    #[derive(Clone, Default)]
+    struct CollectDrain(Arc<Mutex<Vec<(String, String)>>>);
+
+    impl Serializer for CollectDrain {
+        fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+            let mut fields = self.0.lock().unwrap();
+            fields.push((key.to_string(), val.to_string()));
+            Ok(())
+        }
+    }
+
+    impl Drain for CollectDrain {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(&self, record: &Record, _values: &OwnedKVList) -> Result<(), slog::Never> {
+            let mut serializer = self.clone();
+            record.kv().serialize(record, &mut serializer).unwrap();
+            Ok(())
+        }
+    }
+
+    fn log_record(names: TaskLogFieldNames) -> Vec<(String, String)> {
+        let drain = CollectDrain::default();
+        let logger = Logger::root(drain.clone(), slog::o!());
+
+        let start_at = Utc::now();
+        let task_id = generate_uuid(&start_at);
+        let upstream = UpstreamAddr::from_str("www.example.net:443").unwrap();
+        let mut record = TaskLogRecord::new("TcpConnect", &task_id, "Relaying", &start_at);
+        record.field_names = names;
+        let record = record
+            .task_event("Finished")
+            .user(None)
+            .server_addr_renamed(
+                "tcp_server_addr",
+                SocketAddr::from_str("127.0.0.1:1080").unwrap(),
+            )
+            .client_addr(SocketAddr::from_str("192.0.2.1:10001").unwrap())
+            .upstream_renamed("initial_peer", &upstream)
+            .reason("ClosedByClient")
+            .wait_time(Duration::from_millis(1))
+            .ready_time(Duration::from_millis(2))
+            .total_time(Duration::from_secs(10))
+            .io_bytes(TaskLogIoBytes {
+                client_rd: 1,
+                client_wr: 2,
+                remote_rd: 3,
+                remote_wr: 4,
+            })
+            .extension("escaper", "direct")
+            .extension("next_expire", None::<&DateTime<Utc>>)
+            .extension("tcp_connect_tries", 2usize);
+        slog_info!(logger, "closed"; record);
+
+        let mut fields = drain.0.lock().unwrap().clone();
+        fields.reverse();
+        fields
+    }
+
+    #[test]
+    fn serialize_legacy() {
+        let fields = log_record(TaskLogFieldNames::Legacy);
+        let names = fields.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "task_type",
+                "task_id",
+                "task_event",
+                "stage",
+                "start_at",
+                "user",
+                "tcp_server_addr",
+                "client_addr",
+                "initial_peer",
+                "reason",
+                "wait_time",
+                "ready_time",
+                "total_time",
+                "c_rd_bytes",
+                "c_wr_bytes",
+                "r_rd_bytes",
+                "r_wr_bytes",
+                "escaper",
+                "next_expire",
+                "tcp_connect_tries",
+            ]
+        );
+        validate_task_log(fields.iter().map(|(k, v)| (k.as_str(), v.as_str()))).unwrap();
+    }
+
+    #[test]
+    fn serialize_unified() {
+        let fields = log_record(TaskLogFieldNames::Unified);
+        let map = fields.into_iter().collect::<BTreeMap<_, _>>();
+        assert_eq!(map["server_addr"], "127.0.0.1:1080");
+        assert_eq!(map["upstream"], "www.example.net:443");
+        // the user field is kept for anonymous tasks, and none is emitted as empty by default
+        assert_eq!(map["user"], "");
+        assert_eq!(map["r_wr_bytes"], "4");
+        assert_eq!(map["tcp_connect_tries"], "2");
+        assert!(!map.contains_key("tcp_server_addr"));
+        validate_task_log(map.iter().map(|(k, v)| (k.as_str(), v.as_str()))).unwrap();
+    }
+
+    #[test]
+    fn validate() {
+        let fields = [
+            ("task_type", "TcpConnect"),
+            ("task_id", "c0e2a6b2a3f44c1f9b0e4c5d6e7f8a9b"),
+            ("stage", "Created"),
+            ("start_at", "2025-01-01T00:00:00.000000Z"),
+            ("wait_time", "1.234ms"),
+            ("ready_time", "None"),
+        ];
+        validate_task_log(fields).unwrap();
+
+        assert!(validate_task_log(fields[1..].iter().copied()).is_err());
+
+        let mut invalid = fields;
+        invalid[4] = ("wait_time", "1234");
+        assert!(validate_task_log(invalid).is_err());
+
+        let mut invalid = fields;
+        invalid[1] = ("task_id", "abc");
+        assert!(validate_task_log(invalid).is_err());
+    }
+
+    #[test]
+    fn field_names_config() {
+        assert_eq!(
+            TaskLogFieldNames::from_str("Unified").unwrap(),
+            TaskLogFieldNames::Unified
+        );
+        assert!(TaskLogFieldNames::from_str("new").is_err());
+        assert_eq!(TaskLogFieldNames::default().as_str(), "legacy");
+    }
+}
@@ -15,6 +15,15 @@ const METRIC_NAME_LISTEN_ACCEPTED: &str = "listen.accepted";
 const METRIC_NAME_LISTEN_DROPPED: &str = "listen.dropped";
 const METRIC_NAME_LISTEN_TIMEOUT: &str = "listen.timeout";
 const METRIC_NAME_LISTEN_FAILED: &str = "listen.failed";
+const METRIC_NAME_LISTEN_CONN_ALIVE: &str = "listen.connection.alive";
+const METRIC_NAME_LISTEN_CONN_LIMITED: &str = "listen.conn_limited";
+const METRIC_NAME_LISTEN_RATE_LIMITED: &str = "listen.rate_limited";
+const METRIC_NAME_LISTEN_PROXY_PROTOCOL_INVALID: &str = "listen.proxy_protocol_invalid";
+const METRIC_NAME_LISTEN_SHUTDOWN_DRAINED: &str = "listen.shutdown.drained";
+const METRIC_NAME_LISTEN_SHUTDOWN_DROPPED: &str = "listen.shutdown.dropped";
+const METRIC_NAME_LISTEN_WORKER_ACCEPTED: &str = "listen.worker.accepted";
+
+const TAG_KEY_WORKER_ID: &str = "worker_id";
 
 pub fn emit_listen_stats(
     client: &mut StatsdClient,
@@ -31,6 +40,13 @@ pub fn emit_listen_stats(
             &common_tags,
         )
         .send();
+    client
+        .gauge_with_tags(
+            METRIC_NAME_LISTEN_CONN_ALIVE,
+            stats.alive_conn(),
+            &common_tags,
+        )
+        .send();
 
     macro_rules! emit_field {
         ($field:ident, $name:expr) => {
@@ -49,4 +65,26 @@ pub fn emit_listen_stats(
     emit_field!(dropped, METRIC_NAME_LISTEN_DROPPED);
     emit_field!(timeout, METRIC_NAME_LISTEN_TIMEOUT);
     emit_field!(failed, METRIC_NAME_LISTEN_FAILED);
+    emit_field!(conn_limited, METRIC_NAME_LISTEN_CONN_LIMITED);
+    emit_field!(rate_limited, METRIC_NAME_LISTEN_RATE_LIMITED);
+    emit_field!(
+        proxy_protocol_invalid,
+        METRIC_NAME_LISTEN_PROXY_PROTOCOL_INVALID
+    );
+    emit_field!(shutdown_drained, METRIC_NAME_LISTEN_SHUTDOWN_DRAINED);
+    emit_field!(shutdown_dropped, METRIC_NAME_LISTEN_SHUTDOWN_DROPPED);
+
+    let mut buffer = itoa::Buffer::new();
+    for (worker_id, new_value) in stats.worker_accepted() {
+        let old_value = snap.worker_accepted.entry(worker_id).or_default();
+        if new_value == 0 && *old_value == 0 {
+            continue;
+        }
+        let diff_value = new_value.wrapping_sub(*old_value);
+        client
+            .count_with_tags(METRIC_NAME_LISTEN_WORKER_ACCEPTED, diff_value, &common_tags)
+            .with_tag(TAG_KEY_WORKER_ID, buffer.format(worker_id))
+            .send();
+        *old_value = new_value;
+    }
 }
@@ -12,7 +12,9 @@ mod log;
 pub(crate) use log::{LoggerMetricExt, emit_log_drop_stats, emit_log_io_stats};
 
 mod server;
-pub use server::{ServerMetricExt, TAG_KEY_ONLINE, TAG_KEY_SERVER};
+pub use server::{
+    ServerMetricExt, TAG_KEY_ONLINE, TAG_KEY_SERVER, emit_accept_reject_stats, emit_transfer_stats,
+};
 
 pub mod helper;
 
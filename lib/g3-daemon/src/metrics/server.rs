@@ -3,14 +3,29 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use g3_statsd_client::StatsdTagGroup;
+use g3_histogram::BucketHistogramSnapshot;
+use g3_openssl::SslHandshakeErrorReason;
+use g3_statsd_client::{StatsdClient, StatsdTagGroup};
 use g3_types::metrics::NodeName;
 use g3_types::stats::StatId;
 
-use super::TAG_KEY_STAT_ID;
+use super::{TAG_KEY_QUANTILE, TAG_KEY_STAT_ID};
+use crate::server::{
+    AcceptRejectReason, AcceptRejectSnapshot, TransferDirectionStats, TransferSnapshot,
+    TransferStats,
+};
 
 pub const TAG_KEY_SERVER: &str = "server";
 pub const TAG_KEY_ONLINE: &str = "online";
+const TAG_KEY_REASON: &str = "reason";
+const TAG_KEY_DIRECTION: &str = "direction";
+const TAG_KEY_BUCKET: &str = "le";
+
+const METRIC_NAME_SERVER_ACCEPT_REJECTED: &str = "server.accept.rejected";
+const METRIC_NAME_SERVER_ACCEPT_HANDSHAKE_ERROR: &str = "server.accept.handshake_error";
+const METRIC_NAME_SERVER_TRANSFER_SIZE: &str = "server.transfer.size";
+const METRIC_NAME_SERVER_TRANSFER_DURATION: &str = "server.transfer.duration";
+const METRIC_NAME_SERVER_TRANSFER_FIRST_BYTE: &str = "server.transfer.first_byte";
 
 pub trait ServerMetricExt {
     fn add_server_tags(&mut self, server: &NodeName, online: bool, stat_id: StatId);
@@ -28,3 +43,118 @@ impl ServerMetricExt for StatsdTagGroup {
         self.add_tag(TAG_KEY_STAT_ID, stat_id);
     }
 }
+
+pub fn emit_accept_reject_stats(
+    client: &mut StatsdClient,
+    stats: AcceptRejectSnapshot,
+    snap: &mut AcceptRejectSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    for reason in AcceptRejectReason::ALL {
+        let new_value = stats.get(reason);
+        let old_value = snap.get(reason);
+        if new_value == 0 && old_value == 0 {
+            continue;
+        }
+        client
+            .count_with_tags(
+                METRIC_NAME_SERVER_ACCEPT_REJECTED,
+                new_value.wrapping_sub(old_value),
+                common_tags,
+            )
+            .with_tag(TAG_KEY_REASON, reason.as_str())
+            .send();
+    }
+    for reason in SslHandshakeErrorReason::ALL {
+        let new_value = stats.get_handshake_error(reason);
+        let old_value = snap.get_handshake_error(reason);
+        if new_value == 0 && old_value == 0 {
+            continue;
+        }
+        client
+            .count_with_tags(
+                METRIC_NAME_SERVER_ACCEPT_HANDSHAKE_ERROR,
+                new_value.wrapping_sub(old_value),
+                common_tags,
+            )
+            .with_tag(TAG_KEY_REASON, reason.as_str())
+            .send();
+    }
+    *snap = stats;
+}
+
+pub fn emit_transfer_stats(
+    client: &mut StatsdClient,
+    stats: &TransferStats,
+    snap: &mut TransferSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    emit_transfer_direction_stats(
+        client,
+        stats.upload(),
+        &mut snap.upload,
+        "upload",
+        common_tags,
+    );
+    emit_transfer_direction_stats(
+        client,
+        stats.download(),
+        &mut snap.download,
+        "download",
+        common_tags,
+    );
+}
+
+fn emit_transfer_direction_stats(
+    client: &mut StatsdClient,
+    stats: &TransferDirectionStats,
+    snap: &mut BucketHistogramSnapshot,
+    direction: &str,
+    common_tags: &StatsdTagGroup,
+) {
+    let size = stats.size_snapshot();
+    // the old counts are not comparable if the buckets changed
+    let old_counts = if size.bounds() == snap.bounds() {
+        snap.counts()
+    } else {
+        &[]
+    };
+    let mut buffer = itoa::Buffer::new();
+    let mut i = 0;
+    size.foreach_bucket(|bound, new_value| {
+        let old_value = old_counts.get(i).copied().unwrap_or_default();
+        i += 1;
+        if new_value == 0 && old_value == 0 {
+            return;
+        }
+        let bucket = match bound {
+            Some(bound) => buffer.format(bound),
+            None => "inf",
+        };
+        client
+            .count_with_tags(
+                METRIC_NAME_SERVER_TRANSFER_SIZE,
+                new_value.wrapping_sub(old_value),
+                common_tags,
+            )
+            .with_tag(TAG_KEY_DIRECTION, direction)
+            .with_tag(TAG_KEY_BUCKET, bucket)
+            .send();
+    });
+    *snap = size;
+
+    stats.duration_stats().foreach_stat(|_, quantile, v| {
+        client
+            .gauge_float_with_tags(METRIC_NAME_SERVER_TRANSFER_DURATION, v, common_tags)
+            .with_tag(TAG_KEY_DIRECTION, direction)
+            .with_tag(TAG_KEY_QUANTILE, quantile)
+            .send();
+    });
+    stats.first_byte_stats().foreach_stat(|_, quantile, v| {
+        client
+            .gauge_float_with_tags(METRIC_NAME_SERVER_TRANSFER_FIRST_BYTE, v, common_tags)
+            .with_tag(TAG_KEY_DIRECTION, direction)
+            .with_tag(TAG_KEY_QUANTILE, quantile)
+            .send();
+    });
+}
@@ -14,6 +14,8 @@ const ARGS_MONITORED: &str = "monitored";
 const ARGS_PID_FILE: &str = "pid-file";
 const ARGS_TEST_CONFIG: &str = "test-config";
 const ARGS_PANIC_QUIT: &str = "panic-quit";
+#[cfg(unix)]
+const ARGS_UPGRADE_FROM: &str = "upgrade-from";
 
 pub trait DaemonArgsExt {
     fn append_daemon_args(self) -> Self;
@@ -29,6 +31,8 @@ pub struct DaemonArgs {
     pub pid_file: Option<PathBuf>,
     pub test_config: bool,
     pub(crate) panic_quit: bool,
+    #[cfg(unix)]
+    pub upgrade_from: Option<PathBuf>,
 }
 
 impl DaemonArgs {
@@ -42,6 +46,8 @@ impl DaemonArgs {
             pid_file: None,
             test_config: false,
             panic_quit: false,
+            #[cfg(unix)]
+            upgrade_from: None,
         }
     }
 
@@ -91,64 +97,80 @@ impl DaemonArgs {
         if args.get_flag(ARGS_PANIC_QUIT) {
             self.panic_quit = true;
         }
+        #[cfg(unix)]
+        if let Some(path) = args.get_one::<PathBuf>(ARGS_UPGRADE_FROM) {
+            self.upgrade_from = Some(path.to_path_buf());
+        }
         Ok(())
     }
 }
 
 impl DaemonArgsExt for Command {
     fn append_daemon_args(self) -> Self {
-        self.arg(
-            Arg::new(ARGS_VERBOSE)
-                .help("Show verbose output")
-                .num_args(0)
-                .action(ArgAction::Count)
-                .short('v')
-                .long(ARGS_VERBOSE),
-        )
-        .arg(
-            Arg::new(ARGS_DAEMON)
-                .help("Run in daemon mode")
-                .action(ArgAction::SetTrue)
-                .requires(ARGS_PID_FILE)
-                .short('d')
-                .long(ARGS_DAEMON),
-        )
-        .arg(
-            Arg::new(ARGS_SYSTEMD)
-                .help("Run with systemd")
-                .action(ArgAction::SetTrue)
-                .short('s')
-                .long(ARGS_SYSTEMD),
-        )
-        .arg(
-            Arg::new(ARGS_MONITORED)
-                .help("Run in monitored mode")
-                .action(ArgAction::SetTrue)
-                .short('m')
-                .long(ARGS_MONITORED),
-        )
-        .arg(
-            Arg::new(ARGS_PID_FILE)
-                .help("Pid file for daemon mode")
+        let cmd = self
+            .arg(
+                Arg::new(ARGS_VERBOSE)
+                    .help("Show verbose output")
+                    .num_args(0)
+                    .action(ArgAction::Count)
+                    .short('v')
+                    .long(ARGS_VERBOSE),
+            )
+            .arg(
+                Arg::new(ARGS_DAEMON)
+                    .help("Run in daemon mode")
+                    .action(ArgAction::SetTrue)
+                    .requires(ARGS_PID_FILE)
+                    .short('d')
+                    .long(ARGS_DAEMON),
+            )
+            .arg(
+                Arg::new(ARGS_SYSTEMD)
+                    .help("Run with systemd")
+                    .action(ArgAction::SetTrue)
+                    .short('s')
+                    .long(ARGS_SYSTEMD),
+            )
+            .arg(
+                Arg::new(ARGS_MONITORED)
+                    .help("Run in monitored mode")
+                    .action(ArgAction::SetTrue)
+                    .short('m')
+                    .long(ARGS_MONITORED),
+            )
+            .arg(
+                Arg::new(ARGS_PID_FILE)
+                    .help("Pid file for daemon mode")
+                    .num_args(1)
+                    .value_name("PID FILE")
+                    .value_hint(ValueHint::FilePath)
+                    .value_parser(value_parser!(PathBuf))
+                    .short('p')
+                    .long(ARGS_PID_FILE),
+            )
+            .arg(
+                Arg::new(ARGS_TEST_CONFIG)
+                    .help("Test the format of config file and exit")
+                    .action(ArgAction::SetTrue)
+                    .short('t')
+                    .long(ARGS_TEST_CONFIG),
+            )
+            .arg(
+                Arg::new(ARGS_PANIC_QUIT)
+                    .help("Quit the process if panic")
+                    .action(ArgAction::SetTrue)
+                    .long(ARGS_PANIC_QUIT),
+            );
+        #[cfg(unix)]
+        let cmd = cmd.arg(
+            Arg::new(ARGS_UPGRADE_FROM)
+                .help("Adopt listen sockets from the old process via this handover socket")
                 .num_args(1)
-                .value_name("PID FILE")
+                .value_name("HANDOVER SOCKET")
                 .value_hint(ValueHint::FilePath)
                 .value_parser(value_parser!(PathBuf))
-                .short('p')
-                .long(ARGS_PID_FILE),
-        )
-        .arg(
-            Arg::new(ARGS_TEST_CONFIG)
-                .help("Test the format of config file and exit")
-                .action(ArgAction::SetTrue)
-                .short('t')
-                .long(ARGS_TEST_CONFIG),
-        )
-        .arg(
-            Arg::new(ARGS_PANIC_QUIT)
-                .help("Quit the process if panic")
-                .action(ArgAction::SetTrue)
-                .long(ARGS_PANIC_QUIT),
-        )
+                .long(ARGS_UPGRADE_FROM),
+        );
+        cmd
     }
 }
@@ -8,6 +8,7 @@ use std::time::Duration;
 use anyhow::{Context, anyhow};
 use yaml_rust::Yaml;
 
+use g3_io_ext::CopyMemoryExhaustedPolicy;
 use g3_runtime::blended::BlendedRuntimeConfig;
 use g3_runtime::unaided::UnaidedRuntimeConfig;
 use g3_types::sync::GlobalInit;
@@ -110,6 +111,44 @@ fn set_global_config(k: &str, v: &Yaml) -> anyhow::Result<()> {
             GRACEFUL_WAIT_CONFIG.with_mut(|config| config.task_quit_timeout = value);
             Ok(())
         }
+        "tls_cert_expire_warning" | "cert_expire_warning" => {
+            let value = g3_yaml::humanize::as_duration(v)
+                .context(format!("invalid humanize duration value for key {k}"))?;
+            crate::tls::set_expire_warning_window(value);
+            Ok(())
+        }
+        "max_total_copy_memory" => {
+            let value = g3_yaml::humanize::as_usize(v)
+                .context(format!("invalid humanize usize value for key {k}"))?;
+            g3_io_ext::global_copy_memory().set_max_total(value);
+            Ok(())
+        }
+        "copy_memory_min_buffer_size" => {
+            let value = g3_yaml::humanize::as_usize(v)
+                .context(format!("invalid humanize usize value for key {k}"))?;
+            if value == 0 {
+                return Err(anyhow!("the value for key {k} should not be zero"));
+            }
+            g3_io_ext::global_copy_memory().set_min_buffer_size(value);
+            Ok(())
+        }
+        "copy_memory_exhausted_policy" => {
+            let s = g3_yaml::value::as_string(v)
+                .context(format!("invalid string value for key {k}"))?;
+            let policy = match g3_yaml::key::normalize(&s).as_str() {
+                "downgrade" => CopyMemoryExhaustedPolicy::Downgrade,
+                "reject" => CopyMemoryExhaustedPolicy::Reject,
+                _ => return Err(anyhow!("invalid copy memory exhausted policy {s}")),
+            };
+            g3_io_ext::global_copy_memory().set_exhausted_policy(policy);
+            Ok(())
+        }
+        "io_stats_flush_interval" => {
+            let value = g3_yaml::humanize::as_duration(v)
+                .context(format!("invalid humanize duration value for key {k}"))?;
+            g3_io_ext::set_io_stats_flush_interval(value);
+            Ok(())
+        }
         _ => RUNTIME_CONFIG.with_mut(|config| config.parse_by_yaml_kv(k, v)),
     }
 }
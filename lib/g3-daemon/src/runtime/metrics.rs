@@ -16,8 +16,20 @@ const TAG_KEY_RUNTIME_ID: &str = "runtime_id";
 
 const METRIC_NAME_RUNTIME_TOKIO_ALIVE_TASKS: &str = "runtime.tokio.alive_tasks";
 const METRIC_NAME_RUNTIME_TOKIO_GLOBAL_QUEUE_DEPTH: &str = "runtime.tokio.global_queue_depth";
+const METRIC_NAME_RUNTIME_COPY_MEMORY_USED: &str = "runtime.copy_memory.used";
+const METRIC_NAME_RUNTIME_COPY_MEMORY_DOWNGRADED: &str = "runtime.copy_memory.downgraded";
+const METRIC_NAME_RUNTIME_COPY_MEMORY_REJECTED: &str = "runtime.copy_memory.rejected";
 
 static TOKIO_STATS_VEC: Mutex<Vec<TokioStatsValue>> = Mutex::new(Vec::new());
+static COPY_MEMORY_SNAPSHOT: Mutex<CopyMemorySnapshot> = Mutex::new(CopyMemorySnapshot {
+    downgraded: 0,
+    rejected: 0,
+});
+
+struct CopyMemorySnapshot {
+    downgraded: u64,
+    rejected: u64,
+}
 
 struct TokioStatsValue {
     stat_id: StatId,
@@ -40,6 +52,38 @@ pub fn emit_stats(client: &mut StatsdClient) {
     for v in tokio_stats_vec.iter_mut() {
         emit_tokio_stats(client, v);
     }
+
+    let mut snap = COPY_MEMORY_SNAPSHOT.lock().unwrap();
+    emit_copy_memory_stats(client, &mut snap);
+}
+
+fn emit_copy_memory_stats(client: &mut StatsdClient, snap: &mut CopyMemorySnapshot) {
+    let stats = g3_io_ext::global_copy_memory();
+    let common_tags = StatsdTagGroup::default();
+
+    client
+        .gauge_with_tags(
+            METRIC_NAME_RUNTIME_COPY_MEMORY_USED,
+            stats.used(),
+            &common_tags,
+        )
+        .send();
+
+    macro_rules! emit_field {
+        ($field:ident, $name:expr) => {
+            let new_value = stats.$field();
+            if new_value != 0 || snap.$field != 0 {
+                let diff_value = new_value.wrapping_sub(snap.$field);
+                client
+                    .count_with_tags($name, diff_value, &common_tags)
+                    .send();
+                snap.$field = new_value;
+            }
+        };
+    }
+
+    emit_field!(downgraded, METRIC_NAME_RUNTIME_COPY_MEMORY_DOWNGRADED);
+    emit_field!(rejected, METRIC_NAME_RUNTIME_COPY_MEMORY_REJECTED);
 }
 
 fn emit_tokio_stats(client: &mut StatsdClient, v: &mut TokioStatsValue) {
@@ -87,6 +87,10 @@ pub fn select_handle() -> Option<WorkerHandle> {
     }
 }
 
+pub fn get_handle(id: usize) -> Option<WorkerHandle> {
+    handles().iter().find(|h| h.id == id).cloned()
+}
+
 pub fn select_handle_by_cpu_id(cpu_id: usize) -> Option<WorkerHandle> {
     CPU_CORE_WORKER_MAP
         .get()
@@ -106,6 +106,15 @@ impl ClientConnectionInfo {
         }
     }
 
+    /// Let the client connection be reset when it's closed
+    ///
+    /// This should be called before the client connection is closed.
+    pub fn tcp_sock_reset_on_close(&self) {
+        if let Some(raw_socket) = &self.tcp_raw_socket {
+            let _ = raw_socket.set_tcp_reset_on_close();
+        }
+    }
+
     #[cfg(any(target_os = "linux", target_os = "android"))]
     pub fn tcp_sock_try_quick_ack(&self) {
         if let Some(raw_socket) = &self.tcp_raw_socket {
@@ -130,4 +139,14 @@ impl ClientConnectionInfo {
             None
         }
     }
+
+    /// Get the original destination address of a connection redirected by netfilter NAT
+    #[cfg(target_os = "linux")]
+    pub fn tcp_sock_original_dst(&self) -> io::Result<SocketAddr> {
+        if let Some(raw_socket) = &self.tcp_raw_socket {
+            raw_socket.tcp_original_dst()
+        } else {
+            Err(io::Error::other("no raw socket found"))
+        }
+    }
 }
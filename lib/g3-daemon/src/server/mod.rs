@@ -11,5 +11,15 @@ pub mod task;
 mod connection;
 pub use connection::ClientConnectionInfo;
 
+mod reject;
+pub use reject::{
+    AcceptRejectReason, AcceptRejectRecorder, AcceptRejectSnapshot, AcceptRejectStats,
+};
+
+mod transfer;
+pub use transfer::{
+    TransferDirectionStats, TransferMetricsConfig, TransferSnapshot, TransferStats,
+};
+
 mod runtime;
 pub use runtime::{BaseServer, ReloadServer, ServerExt, ServerReloadCommand};
@@ -0,0 +1,392 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use governor::{Quota, RateLimiter, clock::DefaultClock, state::InMemoryState, state::NotKeyed};
+use slog::{Logger, slog_info};
+
+use g3_openssl::{SslHandshakeError, SslHandshakeErrorReason, SslHandshakeFaultSide};
+
+use super::ClientConnectionInfo;
+use crate::ban::ClientViolation;
+
+/// The reason why a connection is rejected before the task is started
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AcceptRejectReason {
+    /// blocked by the ingress network filter
+    IngressFiltered,
+    /// timed out to receive the TLS ClientHello message
+    ClientHelloTimeout,
+    /// the TLS ClientHello message is larger than the max allowed size
+    ClientHelloTooLarge,
+    /// the TLS ClientHello message is invalid
+    ClientHelloInvalid,
+    /// the client closed the connection or read failed before the TLS ClientHello received
+    ClientClosed,
+    /// no TLS host matched the server name
+    NoHostMatched,
+    /// the host level request rate limit or alive limit reached
+    HostLimited,
+    /// the server level handshake limit reached
+    HandshakeLimited,
+    /// the TLS handshake failed
+    HandshakeFailed,
+    /// no backend is selected after the handshake
+    NoBackend,
+    /// the target address of a transparent proxy connection is the listen address itself
+    TargetIsSelf,
+    /// failed to get the original destination address of a redirected connection
+    OriginalDstUnknown,
+    /// the backends of the host are marked as unhealthy by the health check
+    HostUnhealthy,
+}
+
+impl AcceptRejectReason {
+    pub const ALL: [AcceptRejectReason; 13] = [
+        AcceptRejectReason::IngressFiltered,
+        AcceptRejectReason::ClientHelloTimeout,
+        AcceptRejectReason::ClientHelloTooLarge,
+        AcceptRejectReason::ClientHelloInvalid,
+        AcceptRejectReason::ClientClosed,
+        AcceptRejectReason::NoHostMatched,
+        AcceptRejectReason::HostLimited,
+        AcceptRejectReason::HandshakeLimited,
+        AcceptRejectReason::HandshakeFailed,
+        AcceptRejectReason::NoBackend,
+        AcceptRejectReason::TargetIsSelf,
+        AcceptRejectReason::OriginalDstUnknown,
+        AcceptRejectReason::HostUnhealthy,
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            AcceptRejectReason::IngressFiltered => "ingress_filtered",
+            AcceptRejectReason::ClientHelloTimeout => "client_hello_timeout",
+            AcceptRejectReason::ClientHelloTooLarge => "client_hello_too_large",
+            AcceptRejectReason::ClientHelloInvalid => "client_hello_invalid",
+            AcceptRejectReason::ClientClosed => "client_closed",
+            AcceptRejectReason::NoHostMatched => "no_host_matched",
+            AcceptRejectReason::HostLimited => "host_limited",
+            AcceptRejectReason::HandshakeLimited => "handshake_limited",
+            AcceptRejectReason::HandshakeFailed => "handshake_failed",
+            AcceptRejectReason::NoBackend => "no_backend",
+            AcceptRejectReason::TargetIsSelf => "target_is_self",
+            AcceptRejectReason::OriginalDstUnknown => "original_dst_unknown",
+            AcceptRejectReason::HostUnhealthy => "host_unhealthy",
+        }
+    }
+
+    const fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// The client violation that should be reported to the client ban list
+    const fn violation(&self) -> Option<ClientViolation> {
+        match self {
+            AcceptRejectReason::IngressFiltered => Some(ClientViolation::AclDenied),
+            AcceptRejectReason::ClientHelloTooLarge
+            | AcceptRejectReason::ClientHelloInvalid
+            | AcceptRejectReason::HandshakeFailed => Some(ClientViolation::HandshakeFailed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AcceptRejectStats {
+    counts: [AtomicU64; AcceptRejectReason::ALL.len()],
+    handshake_errors: [AtomicU64; SslHandshakeErrorReason::ALL.len()],
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct AcceptRejectSnapshot {
+    counts: [u64; AcceptRejectReason::ALL.len()],
+    handshake_errors: [u64; SslHandshakeErrorReason::ALL.len()],
+}
+
+impl AcceptRejectSnapshot {
+    pub fn get(&self, reason: AcceptRejectReason) -> u64 {
+        self.counts[reason.index()]
+    }
+
+    pub fn get_handshake_error(&self, reason: SslHandshakeErrorReason) -> u64 {
+        self.handshake_errors[reason.index()]
+    }
+}
+
+impl AcceptRejectStats {
+    pub fn add(&self, reason: AcceptRejectReason) {
+        self.counts[reason.index()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, reason: AcceptRejectReason) -> u64 {
+        self.counts[reason.index()].load(Ordering::Relaxed)
+    }
+
+    pub fn add_handshake_error(&self, reason: SslHandshakeErrorReason) {
+        self.handshake_errors[reason.index()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get_handshake_error(&self, reason: SslHandshakeErrorReason) -> u64 {
+        self.handshake_errors[reason.index()].load(Ordering::Relaxed)
+    }
+
+    pub fn snapshot(&self) -> AcceptRejectSnapshot {
+        let mut snap = AcceptRejectSnapshot::default();
+        for reason in AcceptRejectReason::ALL {
+            snap.counts[reason.index()] = self.get(reason);
+        }
+        for reason in SslHandshakeErrorReason::ALL {
+            snap.handshake_errors[reason.index()] = self.get_handshake_error(reason);
+        }
+        snap
+    }
+}
+
+/// Record the rejections in the accept phase, and log them with a rate limit.
+///
+/// The rejections caused by the client will also be reported to the global client ban list.
+pub struct AcceptRejectRecorder {
+    stats: Arc<AcceptRejectStats>,
+    logger: Option<(Logger, RateLimiter<NotKeyed, InMemoryState, DefaultClock>)>,
+}
+
+impl AcceptRejectRecorder {
+    /// The log will be enabled only if both the logger and the max log rate per second are set
+    pub fn new(
+        stats: Arc<AcceptRejectStats>,
+        logger: Option<Logger>,
+        log_rate: Option<NonZeroU32>,
+    ) -> Self {
+        let logger = logger
+            .zip(log_rate)
+            .map(|(logger, rate)| (logger, RateLimiter::direct(Quota::per_second(rate))));
+        AcceptRejectRecorder { stats, logger }
+    }
+
+    pub fn record(
+        &self,
+        reason: AcceptRejectReason,
+        cc_info: &ClientConnectionInfo,
+        sni: Option<&str>,
+    ) {
+        self.stats.add(reason);
+        if let Some(violation) = reason.violation() {
+            crate::ban::report(violation, cc_info.client_ip());
+        }
+
+        let Some(logger) = self.sampled_logger() else {
+            return;
+        };
+        slog_info!(logger, "";
+            "task_type" => "AcceptError",
+            "server_addr" => cc_info.server_addr(),
+            "client_addr" => cc_info.client_addr(),
+            "sni" => sni,
+            "reason" => reason.as_str(),
+        );
+    }
+
+    /// Record a failed TLS handshake, with the classified reason counted and logged
+    pub fn record_handshake_error(
+        &self,
+        error: &SslHandshakeError,
+        cc_info: &ClientConnectionInfo,
+        sni: Option<&str>,
+    ) {
+        self.stats.add(AcceptRejectReason::HandshakeFailed);
+        self.stats.add_handshake_error(error.reason());
+        if error.fault_side() != SslHandshakeFaultSide::Server {
+            crate::ban::report(ClientViolation::HandshakeFailed, cc_info.client_ip());
+        }
+
+        let Some(logger) = self.sampled_logger() else {
+            return;
+        };
+        let alert = error.alert();
+        slog_info!(logger, "";
+            "task_type" => "AcceptError",
+            "server_addr" => cc_info.server_addr(),
+            "client_addr" => cc_info.client_addr(),
+            "sni" => sni,
+            "reason" => AcceptRejectReason::HandshakeFailed.as_str(),
+            "handshake_error" => error.reason().as_str(),
+            "handshake_phase" => error.phase(),
+            "tls_alert" => alert.map(|a| a.name()),
+            "tls_alert_sent" => alert.map(|a| a.is_sent()),
+            "fault_side" => error.fault_side().as_str(),
+            "retryable" => error.retryable(),
+            "raw_error" => error.raw_error(),
+        );
+    }
+
+    fn sampled_logger(&self) -> Option<&Logger> {
+        let (logger, limiter) = self.logger.as_ref()?;
+        limiter.check().ok().map(|_| logger)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use std::sync::Mutex;
+
+    use slog::{Drain, KV, OwnedKVList, Record, Serializer, o};
+
+    #[derive(Default)]
+    struct LoggedEntry {
+        reason: String,
+        sni: Option<String>,
+        handshake_error: Option<String>,
+        raw_error: Option<String>,
+    }
+
+    impl Serializer for LoggedEntry {
+        fn emit_arguments(&mut self, key: slog::Key, val: &fmt::Arguments<'_>) -> slog::Result {
+            match key.as_ref() {
+                "reason" => self.reason = val.to_string(),
+                "sni" => self.sni = Some(val.to_string()),
+                "handshake_error" => self.handshake_error = Some(val.to_string()),
+                "raw_error" => self.raw_error = Some(val.to_string()),
+                _ => {}
+            }
+            Ok(())
+        }
+
+        fn emit_none(&mut self, _key: slog::Key) -> slog::Result {
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct CaptureDrain {
+        entries: Arc<Mutex<Vec<LoggedEntry>>>,
+    }
+
+    impl Drain for CaptureDrain {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(&self, record: &Record<'_>, _values: &OwnedKVList) -> Result<(), slog::Never> {
+            let mut entry = LoggedEntry::default();
+            record.kv().serialize(record, &mut entry).unwrap();
+            self.entries.lock().unwrap().push(entry);
+            Ok(())
+        }
+    }
+
+    fn cc_info() -> ClientConnectionInfo {
+        ClientConnectionInfo::new(
+            SocketAddr::from_str("192.0.2.1:12345").unwrap(),
+            SocketAddr::from_str("192.0.2.2:443").unwrap(),
+        )
+    }
+
+    #[test]
+    fn record_all() {
+        let drain = CaptureDrain::default();
+        let stats = Arc::new(AcceptRejectStats::default());
+        let recorder = AcceptRejectRecorder::new(
+            stats.clone(),
+            Some(Logger::root(drain.clone(), o!())),
+            NonZeroU32::new(100),
+        );
+
+        let cc_info = cc_info();
+        for reason in AcceptRejectReason::ALL {
+            recorder.record(reason, &cc_info, Some("example.net"));
+        }
+
+        let snap = stats.snapshot();
+        let entries = drain.entries.lock().unwrap();
+        assert_eq!(entries.len(), AcceptRejectReason::ALL.len());
+        for (reason, entry) in AcceptRejectReason::ALL.iter().zip(entries.iter()) {
+            assert_eq!(snap.get(*reason), 1);
+            assert_eq!(entry.reason, reason.as_str());
+            assert_eq!(entry.sni.as_deref(), Some("example.net"));
+        }
+    }
+
+    #[test]
+    fn log_sampled() {
+        let drain = CaptureDrain::default();
+        let stats = Arc::new(AcceptRejectStats::default());
+        let recorder = AcceptRejectRecorder::new(
+            stats.clone(),
+            Some(Logger::root(drain.clone(), o!())),
+            NonZeroU32::new(2),
+        );
+
+        let cc_info = cc_info();
+        for _ in 0..10 {
+            recorder.record(AcceptRejectReason::HandshakeFailed, &cc_info, None);
+        }
+        assert_eq!(stats.get(AcceptRejectReason::HandshakeFailed), 10);
+        let entries = drain.entries.lock().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].sni.is_none());
+    }
+
+    #[test]
+    fn record_handshake_error() {
+        let drain = CaptureDrain::default();
+        let stats = Arc::new(AcceptRejectStats::default());
+        let recorder = AcceptRejectRecorder::new(
+            stats.clone(),
+            Some(Logger::root(drain.clone(), o!())),
+            NonZeroU32::new(100),
+        );
+
+        let cc_info = cc_info();
+        let timed_out = SslHandshakeError::from_io_error(&std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "ssl accept timed out",
+        ));
+        recorder.record_handshake_error(&timed_out, &cc_info, Some("example.net"));
+        let io_failed = SslHandshakeError::from_io_error(&std::io::Error::other("io failed"));
+        recorder.record_handshake_error(&io_failed, &cc_info, None);
+
+        assert_eq!(stats.get(AcceptRejectReason::HandshakeFailed), 2);
+        let snap = stats.snapshot();
+        assert_eq!(
+            snap.get_handshake_error(SslHandshakeErrorReason::TimedOut),
+            1
+        );
+        assert_eq!(
+            snap.get_handshake_error(SslHandshakeErrorReason::IoFailed),
+            1
+        );
+        assert_eq!(
+            snap.get_handshake_error(SslHandshakeErrorReason::Unknown),
+            0
+        );
+
+        let entries = drain.entries.lock().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].reason, "handshake_failed");
+        assert_eq!(entries[0].handshake_error.as_deref(), Some("timed_out"));
+        assert!(entries[0].raw_error.is_none());
+        assert_eq!(entries[1].handshake_error.as_deref(), Some("io_failed"));
+        assert_eq!(entries[1].raw_error.as_deref(), Some("io failed"));
+    }
+
+    #[test]
+    fn log_disabled() {
+        let drain = CaptureDrain::default();
+        let stats = Arc::new(AcceptRejectStats::default());
+        let recorder =
+            AcceptRejectRecorder::new(stats.clone(), Some(Logger::root(drain.clone(), o!())), None);
+
+        recorder.record(AcceptRejectReason::IngressFiltered, &cc_info(), None);
+        assert_eq!(stats.get(AcceptRejectReason::IngressFiltered), 1);
+        assert!(drain.entries.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use yaml_rust::Yaml;
+
+use g3_histogram::{
+    BucketHistogram, BucketHistogramConfig, BucketHistogramSnapshot, HistogramMetricsConfig,
+    HistogramRecorder, HistogramStats,
+};
+use g3_io_ext::{ArcStreamCopyRecorder, StreamCopyRecorder};
+use g3_std_ext::time::DurationExt;
+
+/// Config for the size and duration histograms of the relayed streams
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransferMetricsConfig {
+    size_buckets: BucketHistogramConfig,
+    duration_stats: HistogramMetricsConfig,
+}
+
+impl TransferMetricsConfig {
+    /// Parse the config, None will be returned if it's disabled
+    pub fn parse_yaml(v: &Yaml) -> anyhow::Result<Option<Self>> {
+        match v {
+            Yaml::Boolean(true) => Ok(Some(TransferMetricsConfig::default())),
+            Yaml::Boolean(false) => Ok(None),
+            Yaml::Hash(map) => {
+                let mut config = TransferMetricsConfig::default();
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "size_buckets" => {
+                        config.size_buckets = g3_yaml::value::as_bucket_histogram_config(v)
+                            .context(format!(
+                                "invalid bucket histogram config value for key {k}"
+                            ))?;
+                        Ok(())
+                    }
+                    "duration_stats" | "duration_metrics" => {
+                        config.duration_stats = g3_yaml::value::as_histogram_metrics_config(v)
+                            .context(format!(
+                                "invalid histogram metrics config value for key {k}"
+                            ))?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+                Ok(Some(config))
+            }
+            _ => Err(anyhow!(
+                "yaml value type for 'transfer metrics config' should be 'bool' or 'map'"
+            )),
+        }
+    }
+
+    pub fn build_stats(&self) -> Arc<TransferStats> {
+        Arc::new(TransferStats {
+            upload: Arc::new(TransferDirectionStats::new(self)),
+            download: Arc::new(TransferDirectionStats::new(self)),
+        })
+    }
+}
+
+/// Histograms of a single direction, which will be shared by all tasks of the server
+pub struct TransferDirectionStats {
+    size: BucketHistogram,
+    duration_recorder: HistogramRecorder<u64>,
+    duration: Arc<HistogramStats>,
+    first_byte_recorder: HistogramRecorder<u64>,
+    first_byte: Arc<HistogramStats>,
+}
+
+impl TransferDirectionStats {
+    fn new(config: &TransferMetricsConfig) -> Self {
+        let handle = crate::runtime::main_handle().cloned();
+        let (duration_recorder, duration) = config.duration_stats.build_spawned(handle.clone());
+        let (first_byte_recorder, first_byte) = config.duration_stats.build_spawned(handle);
+        TransferDirectionStats {
+            size: BucketHistogram::new(&config.size_buckets),
+            duration_recorder,
+            duration,
+            first_byte_recorder,
+            first_byte,
+        }
+    }
+
+    pub fn size_snapshot(&self) -> BucketHistogramSnapshot {
+        self.size.snapshot()
+    }
+
+    #[inline]
+    pub fn duration_stats(&self) -> &HistogramStats {
+        &self.duration
+    }
+
+    #[inline]
+    pub fn first_byte_stats(&self) -> &HistogramStats {
+        &self.first_byte
+    }
+}
+
+impl StreamCopyRecorder for TransferDirectionStats {
+    fn record_transfer(&self, size: u64, first_byte: Option<Duration>, total: Duration) {
+        self.size.record(size);
+        let _ = self.duration_recorder.record(total.as_nanos_u64());
+        if let Some(first_byte) = first_byte {
+            let _ = self.first_byte_recorder.record(first_byte.as_nanos_u64());
+        }
+    }
+}
+
+pub struct TransferStats {
+    upload: Arc<TransferDirectionStats>,
+    download: Arc<TransferDirectionStats>,
+}
+
+impl TransferStats {
+    /// Get the stats for data from the client to the remote peer
+    #[inline]
+    pub fn upload(&self) -> &TransferDirectionStats {
+        &self.upload
+    }
+
+    /// Get the stats for data from the remote peer to the client
+    #[inline]
+    pub fn download(&self) -> &TransferDirectionStats {
+        &self.download
+    }
+
+    pub fn upload_recorder(&self) -> ArcStreamCopyRecorder {
+        self.upload.clone()
+    }
+
+    pub fn download_recorder(&self) -> ArcStreamCopyRecorder {
+        self.download.clone()
+    }
+}
+
+#[derive(Default)]
+pub struct TransferSnapshot {
+    pub upload: BucketHistogramSnapshot,
+    pub download: BucketHistogramSnapshot,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn load_yaml(s: &str) -> Yaml {
+        YamlLoader::load_from_str(s).unwrap().pop().unwrap()
+    }
+
+    #[test]
+    fn parse_yaml() {
+        assert!(
+            TransferMetricsConfig::parse_yaml(&load_yaml("false"))
+                .unwrap()
+                .is_none()
+        );
+        let config = TransferMetricsConfig::parse_yaml(&load_yaml("true"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(config, TransferMetricsConfig::default());
+
+        let config = TransferMetricsConfig::parse_yaml(&load_yaml("size_buckets: [16, 1024]"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.size_buckets.bounds(), &[16, 1024]);
+
+        assert!(TransferMetricsConfig::parse_yaml(&load_yaml("1")).is_err());
+        assert!(TransferMetricsConfig::parse_yaml(&load_yaml("buckets: [16]")).is_err());
+    }
+
+    #[tokio::test]
+    async fn record_transfer() {
+        let config = TransferMetricsConfig::parse_yaml(&load_yaml("size_buckets: [16, 1024]"))
+            .unwrap()
+            .unwrap();
+        let stats = config.build_stats();
+
+        let recorder = stats.upload_recorder();
+        for size in [10, 100, 1000, 10000] {
+            recorder.record_transfer(size, Some(Duration::from_millis(1)), Duration::from_secs(1));
+        }
+        stats
+            .download_recorder()
+            .record_transfer(0, None, Duration::from_secs(1));
+
+        let snapshot = stats.upload().size_snapshot();
+        assert_eq!(snapshot.counts(), &[1, 2, 1]);
+        assert_eq!(snapshot.sum(), 11110);
+        let snapshot = stats.download().size_snapshot();
+        assert_eq!(snapshot.counts(), &[1, 0, 0]);
+    }
+}
@@ -4,11 +4,14 @@
  */
 
 use std::cell::UnsafeCell;
+use std::time::Duration;
 
 #[derive(Default)]
 pub struct UdpConnectHalfConnectionStats {
     bytes: UnsafeCell<u64>,
     packets: UnsafeCell<u64>,
+    overhead_bytes: UnsafeCell<u64>,
+    throttled: UnsafeCell<Duration>,
 }
 
 unsafe impl Sync for UdpConnectHalfConnectionStats {}
@@ -24,6 +27,18 @@ impl UdpConnectHalfConnectionStats {
         *r
     }
 
+    /// Get the protocol header bytes, which are not included in the bytes
+    pub fn get_overhead_bytes(&self) -> u64 {
+        let r = unsafe { &*self.overhead_bytes.get() };
+        *r
+    }
+
+    /// Get the total time delayed by the speed limit
+    pub fn get_throttled(&self) -> Duration {
+        let r = unsafe { &*self.throttled.get() };
+        *r
+    }
+
     pub fn add_bytes(&self, size: u64) {
         let r = unsafe { &mut *self.bytes.get() };
         *r += size;
@@ -37,6 +52,16 @@ impl UdpConnectHalfConnectionStats {
         let r = unsafe { &mut *self.packets.get() };
         *r += n as u64;
     }
+
+    pub fn add_overhead_bytes(&self, size: u64) {
+        let r = unsafe { &mut *self.overhead_bytes.get() };
+        *r += size;
+    }
+
+    pub fn add_throttled(&self, time: Duration) {
+        let r = unsafe { &mut *self.throttled.get() };
+        *r += time;
+    }
 }
 
 #[derive(Default)]
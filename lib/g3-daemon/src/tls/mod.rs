@@ -0,0 +1,134 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use chrono::DateTime;
+use log::warn;
+
+use g3_types::net::TlsCertStatus;
+use g3_types::sync::GlobalInit;
+
+const DEFAULT_EXPIRE_WARNING_WINDOW: Duration = Duration::from_secs(30 * 86400);
+
+static EXPIRE_WARNING_WINDOW: GlobalInit<Duration> = GlobalInit::new(DEFAULT_EXPIRE_WARNING_WINDOW);
+
+/// all loaded certificates, keyed by the config object that references them
+static LOADED_CERTS: Mutex<BTreeMap<String, Vec<TlsCertStatus>>> = Mutex::new(BTreeMap::new());
+
+pub(crate) fn set_expire_warning_window(window: Duration) {
+    EXPIRE_WARNING_WINDOW.set(window);
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn check_expiry(object: &str, cert: &TlsCertStatus, now: i64) -> anyhow::Result<()> {
+    let left = cert.not_after() - now;
+    if left <= 0 {
+        if !cert.allow_expired() {
+            return Err(anyhow!(
+                "certificate {} has expired {} days ago",
+                cert.subject(),
+                -left / 86400
+            ));
+        }
+        warn!(
+            "{object}: certificate {} has expired {} days ago",
+            cert.subject(),
+            -left / 86400
+        );
+    } else if left < EXPIRE_WARNING_WINDOW.as_ref().as_secs() as i64 {
+        warn!(
+            "{object}: certificate {} will expire in {} days",
+            cert.subject(),
+            left / 86400
+        );
+    }
+    Ok(())
+}
+
+/// Check the expiry of the certificates referenced by the config object,
+/// and record them for the `tls cert-status` control command.
+///
+/// Expired certificates will be treated as errors unless `allow_expired` is set,
+/// and warnings will be emitted for certificates that will expire soon.
+pub fn check_and_record_certs(object: String, certs: Vec<TlsCertStatus>) -> anyhow::Result<()> {
+    let now = unix_now();
+    for cert in &certs {
+        check_expiry(&object, cert, now)?;
+    }
+
+    let mut loaded = LOADED_CERTS.lock().unwrap();
+    if certs.is_empty() {
+        loaded.remove(&object);
+    } else {
+        loaded.insert(object, certs);
+    }
+    Ok(())
+}
+
+pub fn clear_recorded_certs() {
+    LOADED_CERTS.lock().unwrap().clear();
+}
+
+pub(crate) fn cert_status_text() -> String {
+    let now = unix_now();
+    let loaded = LOADED_CERTS.lock().unwrap();
+    let mut s = String::new();
+    for (object, certs) in loaded.iter() {
+        for cert in certs {
+            let not_after = DateTime::from_timestamp(cert.not_after(), 0)
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_else(|| cert.not_after().to_string());
+            let _ = writeln!(
+                s,
+                "{object}\t{}\tnot_after={not_after}\texpire_in={}d",
+                cert.subject(),
+                (cert.not_after() - now) / 86400
+            );
+        }
+    }
+    s.pop();
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_and_record() {
+        let now = unix_now();
+
+        let valid = TlsCertStatus::new("valid".to_string(), now + 365 * 86400);
+        let expiring = TlsCertStatus::new("expiring".to_string(), now + 86400);
+        check_and_record_certs("server/a".to_string(), vec![valid, expiring]).unwrap();
+
+        let mut expired = TlsCertStatus::new("expired".to_string(), now - 86400);
+        assert!(check_and_record_certs("server/b".to_string(), vec![expired.clone()]).is_err());
+        expired.set_allow_expired(true);
+        check_and_record_certs("server/b".to_string(), vec![expired]).unwrap();
+
+        let text = cert_status_text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("server/a\tvalid\t"));
+        assert!(lines[2].starts_with("server/b\texpired\t"));
+
+        check_and_record_certs("server/b".to_string(), Vec::new()).unwrap();
+        assert_eq!(cert_status_text().lines().count(), 2);
+        clear_recorded_certs();
+        assert!(cert_status_text().is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use thiserror::Error;
+
+use super::{
+    ContentType, HandshakeCoalesceError, HandshakeCoalescer, RecordHeader, RecordParseError,
+};
+
+#[derive(Debug, Error)]
+pub enum HandshakeCollectError {
+    #[error("invalid record: {0}")]
+    InvalidRecord(#[from] RecordParseError),
+    #[error("coalesce failed: {0}")]
+    CoalesceFailed(#[from] HandshakeCoalesceError),
+}
+
+/// Collect a Handshake message from TLS records, which may arrive in pieces of any size.
+///
+/// Unlike [`super::Record`], the fragment data will be consumed before the whole record is received.
+pub struct HandshakeCollector {
+    coalescer: HandshakeCoalescer,
+    offset: usize,
+    record_left: usize,
+}
+
+impl HandshakeCollector {
+    pub fn new(max_message_size: u32) -> Self {
+        HandshakeCollector {
+            coalescer: HandshakeCoalescer::new(max_message_size),
+            offset: 0,
+            record_left: 0,
+        }
+    }
+
+    /// Get the length of the data that has been consumed
+    #[inline]
+    pub fn consumed(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    pub fn coalescer(&self) -> &HandshakeCoalescer {
+        &self.coalescer
+    }
+
+    /// Feed all the data received so far, return true if the whole message has been collected.
+    ///
+    /// The data should be the same buffer for each call, with new data appended to the end.
+    pub fn feed(&mut self, data: &[u8]) -> Result<bool, HandshakeCollectError> {
+        loop {
+            if self.coalescer.is_complete() {
+                return Ok(true);
+            }
+
+            let left = &data[self.offset..];
+            if self.record_left == 0 {
+                if left.len() < RecordHeader::SIZE {
+                    return Ok(false);
+                }
+                let header = RecordHeader::parse(left)?;
+                if header.content_type != ContentType::Handshake {
+                    return Err(HandshakeCoalesceError::InvalidContentType(
+                        header.content_type as u8,
+                    )
+                    .into());
+                }
+                if header.fragment_size > 1 << 14 {
+                    // The length MUST NOT exceed 2^14 bytes.
+                    return Err(RecordParseError::FragmentLengthExceeded.into());
+                }
+                self.offset += RecordHeader::SIZE;
+                self.record_left = header.fragment_size as usize;
+                continue;
+            }
+
+            let len = left.len().min(self.record_left);
+            if len == 0 {
+                return Ok(false);
+            }
+            let consumed = self.coalescer.coalesce_fragment(&left[..len])?;
+            self.offset += consumed;
+            self.record_left -= consumed;
+            if consumed < len {
+                // the message ends in the middle of this record
+                return Ok(true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tls::{ExtensionType, PartialClientHelloExt};
+
+    #[test]
+    fn empty_records() {
+        let mut collector = HandshakeCollector::new(1 << 14);
+        let data = [0x16, 0x03, 0x01, 0x00, 0x00, 0x16, 0x03, 0x01];
+        assert!(!collector.feed(&data).unwrap());
+        assert_eq!(collector.consumed(), 5);
+        assert_eq!(
+            collector
+                .coalescer()
+                .peek_client_hello_ext(ExtensionType::ServerName)
+                .unwrap(),
+            PartialClientHelloExt::Incomplete
+        );
+    }
+
+    #[test]
+    fn invalid_content_type() {
+        let mut collector = HandshakeCollector::new(1 << 14);
+        let data = [0x15, 0x03, 0x01, 0x00, 0x02, 0x02, 0x50];
+        assert!(matches!(
+            collector.feed(&data),
+            Err(HandshakeCollectError::CoalesceFailed(
+                HandshakeCoalesceError::InvalidContentType(21)
+            ))
+        ));
+    }
+}
@@ -25,6 +25,17 @@ pub enum ClientHelloParseError {
     UnsupportedVersion(RawVersion),
 }
 
+/// The result of looking for an extension in a partially received ClientHello message
+#[derive(Debug, PartialEq, Eq)]
+pub enum PartialClientHelloExt<'a> {
+    /// The extension is complete in the data received so far
+    Found(&'a [u8]),
+    /// The extension is not present in the message
+    Absent,
+    /// More data is needed to tell
+    Incomplete,
+}
+
 pub struct ClientHello<'a> {
     pub legacy_version: RawVersion,
     pub cipher_suites: &'a [u8],
@@ -158,6 +169,88 @@ impl<'a> ClientHello<'a> {
         })
     }
 
+    /// Look for an extension in the ClientHello message data received so far,
+    /// without the Handshake message header.
+    ///
+    /// The `msg_len` should be the message length in the Handshake message header.
+    pub(crate) fn peek_partial_ext(
+        data: &'a [u8],
+        msg_len: usize,
+        ext_type: ExtensionType,
+    ) -> Result<PartialClientHelloExt<'a>, ClientHelloParseError> {
+        const RANDOM_FIELD_SIZE: usize = 32;
+
+        if data.len() > msg_len {
+            return Err(ClientHelloParseError::InvalidMessageLength);
+        }
+
+        macro_rules! need {
+            ($len:expr) => {
+                if data.len() < $len {
+                    return if $len > msg_len {
+                        Err(ClientHelloParseError::InvalidMessageLength)
+                    } else {
+                        Ok(PartialClientHelloExt::Incomplete)
+                    };
+                }
+            };
+        }
+
+        need!(2);
+        match (data[0], data[1]) {
+            (1, 1) | (3, 0) | (3, 1) | (3, 2) | (3, 3) => {}
+            _ => {
+                return Err(ClientHelloParseError::UnsupportedVersion(RawVersion {
+                    major: data[0],
+                    minor: data[1],
+                }));
+            }
+        }
+        let mut offset = 2 + RANDOM_FIELD_SIZE;
+
+        // Session ID
+        need!(offset + 1);
+        offset += 1 + data[offset] as usize;
+
+        // Cipher Suites
+        need!(offset + 2);
+        let cipher_suites_len = u16::from_be_bytes([data[offset], data[offset + 1]]) as usize;
+        if cipher_suites_len == 0 || cipher_suites_len & 0x01 != 0 {
+            return Err(ClientHelloParseError::InvalidCipherSuitesLength);
+        }
+        offset += 2 + cipher_suites_len;
+
+        // Compression Methods
+        need!(offset + 1);
+        offset += 1 + data[offset] as usize;
+        if offset == msg_len {
+            // No Extensions
+            return Ok(PartialClientHelloExt::Absent);
+        }
+
+        // Extensions
+        need!(offset + 2);
+        let extensions_len = u16::from_be_bytes([data[offset], data[offset + 1]]) as usize;
+        offset += 2;
+        let extensions_end = offset + extensions_len;
+        if extensions_end != msg_len {
+            return Err(ClientHelloParseError::InvalidMessageLength);
+        }
+        while offset < extensions_end {
+            need!(offset + 4);
+            let t = u16::from_be_bytes([data[offset], data[offset + 1]]);
+            let len = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+            let start = offset + 4;
+            let end = start + len;
+            need!(end);
+            if ExtensionType::from(t) == ext_type {
+                return Ok(PartialClientHelloExt::Found(&data[start..end]));
+            }
+            offset = end;
+        }
+        Ok(PartialClientHelloExt::Absent)
+    }
+
     /// Get the raw extension value
     pub fn get_ext(&self, ext_type: ExtensionType) -> Result<Option<&[u8]>, ExtensionParseError> {
         let Some(data) = self.extensions else {
@@ -240,4 +333,44 @@ mod tests {
         let handshake_msg = HandshakeMessage::try_parse_fragment(data).unwrap();
         assert!(handshake_msg.parse_client_hello().is_err());
     }
+
+    #[test]
+    fn peek_partial_ext() {
+        let data: &[u8] = &[
+            0x03, 0x03, // TLS 1.2
+            0x74, 0x90, 0x65, 0xea, 0xbb, 0x00, 0x5d, 0xf8, 0xdf, 0xd6, 0xde, 0x04, 0xf8, 0xd3,
+            0x69, 0x02, 0xf5, 0x8c, 0x82, 0x50, 0x7a, 0x40, 0xf6, 0xf3, 0xbb, 0x18, 0xc0, 0xac,
+            0x4f, 0x55, 0x9a, 0xda, // Random data, 32 bytes
+            0x00, // Session ID Length
+            0x00, 0x04, // Cipher Suites Length
+            0x13, 0x02, 0x13, 0x01, // Cipher Suites
+            0x01, // Compression Methods Length
+            0x00, // Compression Methods
+            0x00, 0x14, // Extensions Length, 20
+            0x00, 0x00, // Extension Type - Server Name
+            0x00, 0x10, // Extension Length, 16
+            0x00, 0x0e, // Server Name List Length, 14
+            0x00, // Server Name Type - Domain
+            0x00, 0x0b, // Server Name Length, 11
+            b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'n', b'e', b't',
+        ];
+        let msg_len = data.len();
+
+        for i in 0..msg_len {
+            let r = ClientHello::peek_partial_ext(&data[..i], msg_len, ExtensionType::ServerName)
+                .unwrap();
+            assert_eq!(r, PartialClientHelloExt::Incomplete);
+        }
+        let r = ClientHello::peek_partial_ext(data, msg_len, ExtensionType::ServerName).unwrap();
+        assert_eq!(r, PartialClientHelloExt::Found(&data[msg_len - 16..]));
+
+        // the server name extension is complete, so we know there is no early data extension
+        let r = ClientHello::peek_partial_ext(data, msg_len, ExtensionType::EarlyData).unwrap();
+        assert_eq!(r, PartialClientHelloExt::Absent);
+
+        assert!(ClientHello::peek_partial_ext(&data[..60], 59, ExtensionType::ServerName).is_err());
+        assert!(
+            ClientHello::peek_partial_ext(data, msg_len + 1, ExtensionType::ServerName).is_err()
+        );
+    }
 }
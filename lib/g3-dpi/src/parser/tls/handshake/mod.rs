@@ -6,7 +6,9 @@
 use thiserror::Error;
 
 mod client_hello;
-pub use client_hello::{ClientHello, ClientHelloParseError};
+pub use client_hello::{ClientHello, ClientHelloParseError, PartialClientHelloExt};
+
+use super::ExtensionType;
 
 #[allow(dead_code)]
 #[repr(u8)]
@@ -161,8 +163,8 @@ impl HandshakeCoalescer {
                             self.buf.reserve(cap - self.buf.len());
                             Ok(data.len())
                         } else {
-                            self.buf.resize(cap, 0);
                             let consumed = data.len() - (self.buf.len() - cap);
+                            self.buf.truncate(cap);
                             Ok(consumed)
                         }
                     }
@@ -176,6 +178,14 @@ impl HandshakeCoalescer {
         self.buf.is_empty()
     }
 
+    /// Check if the whole message has been received
+    pub fn is_complete(&self) -> bool {
+        self.header
+            .as_ref()
+            .map(|hdr| hdr.encoded_cap() == self.buf.len())
+            .unwrap_or(false)
+    }
+
     /// Parse this message as a ClientHello message
     pub fn parse_client_hello(&self) -> Result<Option<ClientHello<'_>>, ClientHelloParseError> {
         let Some(hdr) = &self.header else {
@@ -191,4 +201,22 @@ impl HandshakeCoalescer {
             Ok(None)
         }
     }
+
+    /// Look for an extension in the ClientHello message received so far
+    pub fn peek_client_hello_ext(
+        &self,
+        ext_type: ExtensionType,
+    ) -> Result<PartialClientHelloExt<'_>, ClientHelloParseError> {
+        let Some(hdr) = &self.header else {
+            return Ok(PartialClientHelloExt::Incomplete);
+        };
+        if hdr.msg_type != HandshakeType::ClientHello as u8 {
+            return Err(ClientHelloParseError::InvalidMessageType(hdr.msg_type));
+        }
+        ClientHello::peek_partial_ext(
+            &self.buf[HandshakeHeader::SIZE..],
+            hdr.msg_length as usize,
+            ext_type,
+        )
+    }
 }
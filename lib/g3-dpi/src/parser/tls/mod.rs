@@ -23,7 +23,13 @@ mod handshake;
 #[cfg(feature = "quic")]
 pub(crate) use handshake::HandshakeHeader;
 pub(crate) use handshake::HandshakeType;
-pub use handshake::{ClientHello, ClientHelloParseError, HandshakeCoalescer, HandshakeMessage};
+pub use handshake::{
+    ClientHello, ClientHelloParseError, HandshakeCoalesceError, HandshakeCoalescer,
+    HandshakeMessage, PartialClientHelloExt,
+};
+
+mod collect;
+pub use collect::{HandshakeCollectError, HandshakeCollector};
 
 mod extension;
 pub use extension::{ExtensionList, ExtensionParseError, ExtensionType};
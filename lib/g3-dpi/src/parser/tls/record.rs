@@ -55,7 +55,7 @@ pub struct RecordHeader {
 impl RecordHeader {
     pub const SIZE: usize = 5;
 
-    fn parse(data: &[u8]) -> Result<Self, RecordParseError> {
+    pub(super) fn parse(data: &[u8]) -> Result<Self, RecordParseError> {
         let Ok(content_type) = ContentType::try_from(data[0]) else {
             return Err(RecordParseError::InvalidContentType(data[0]));
         };
@@ -5,7 +5,9 @@
 
 use g3_types::net::TlsServerName;
 
-use crate::parser::tls::{ExtensionType, HandshakeCoalescer, Record};
+use crate::parser::tls::{
+    ExtensionType, HandshakeCoalescer, HandshakeCollector, PartialClientHelloExt, Record,
+};
 
 const RECORD_1_BYTES: &[u8] = &[
     0x16, 0x03, 0x01, 0x00, 0x64, 0x01, 0x00, 0x01, 0x8a, 0x03, 0x03, 0x02, 0x86, 0x70, 0x33, 0x46,
@@ -82,3 +84,54 @@ fn sni() {
     let sni = TlsServerName::from_extension_value(sni_bytes).unwrap();
     assert_eq!(sni.as_ref(), "www.google.com");
 }
+
+#[test]
+fn collect_byte_by_byte() {
+    let mut data = Vec::new();
+    for record in [
+        RECORD_1_BYTES,
+        RECORD_2_BYTES,
+        RECORD_3_BYTES,
+        RECORD_4_BYTES,
+    ] {
+        data.extend_from_slice(record);
+    }
+    data.extend_from_slice(&[0x14, 0x03, 0x03, 0x00, 0x01, 0x01]);
+
+    let mut collector = HandshakeCollector::new(1 << 14);
+    let mut sni_at = None;
+    let mut done_at = None;
+    for i in 1..=data.len() {
+        if collector.feed(&data[..i]).unwrap() {
+            done_at = Some(i);
+            break;
+        }
+        if sni_at.is_none() {
+            if let PartialClientHelloExt::Found(v) = collector
+                .coalescer()
+                .peek_client_hello_ext(ExtensionType::ServerName)
+                .unwrap()
+            {
+                let sni = TlsServerName::from_extension_value(v).unwrap();
+                assert_eq!(sni.as_ref(), "www.google.com");
+                sni_at = Some(i);
+            }
+        }
+    }
+
+    let sni_at = sni_at.unwrap();
+    let done_at = done_at.unwrap();
+    // the server name extension is in the third record
+    let third_end = RECORD_1_BYTES.len() + RECORD_2_BYTES.len() + RECORD_3_BYTES.len();
+    assert!(sni_at < third_end);
+    assert_eq!(done_at, data.len() - 6);
+    assert_eq!(collector.consumed(), done_at);
+
+    let client_hello = collector.coalescer().parse_client_hello().unwrap().unwrap();
+    let sni_bytes = client_hello
+        .get_ext(ExtensionType::ServerName)
+        .unwrap()
+        .unwrap();
+    let sni = TlsServerName::from_extension_value(sni_bytes).unwrap();
+    assert_eq!(sni.as_ref(), "www.google.com");
+}
@@ -0,0 +1,64 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const DEFAULT_FILE_NAME: &str = "{name}-%Y%m%d-%H%M%S.log.zst";
+const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
+const DEFAULT_MAX_FILE_SIZE: u64 = 256 * 1024 * 1024;
+const DEFAULT_MAX_FILE_AGE: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileLogConfig {
+    pub(crate) directory: PathBuf,
+    pub(crate) file_name: String,
+    pub(crate) compression_level: i32,
+    pub(crate) max_file_size: u64,
+    pub(crate) max_file_age: Duration,
+    pub(crate) fsync_on_rotate: bool,
+}
+
+impl FileLogConfig {
+    pub fn new(directory: PathBuf) -> Self {
+        FileLogConfig {
+            directory,
+            file_name: DEFAULT_FILE_NAME.to_string(),
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_file_age: DEFAULT_MAX_FILE_AGE,
+            fsync_on_rotate: false,
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Set the file name template
+    ///
+    /// The `{name}` placeholder will be replaced by the logger name, and the strftime
+    /// format specifiers will be replaced by the local time when the file is created.
+    pub fn set_file_name(&mut self, name: String) {
+        self.file_name = name;
+    }
+
+    pub fn set_compression_level(&mut self, level: i32) {
+        self.compression_level = level;
+    }
+
+    /// Set the max uncompressed size of each file
+    pub fn set_max_file_size(&mut self, size: u64) {
+        self.max_file_size = size;
+    }
+
+    pub fn set_max_file_age(&mut self, age: Duration) {
+        self.max_file_age = age;
+    }
+
+    pub fn set_fsync_on_rotate(&mut self, enable: bool) {
+        self.fsync_on_rotate = enable;
+    }
+}
@@ -0,0 +1,187 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::cell::RefCell;
+use std::fmt::{Arguments, Write};
+
+use chrono::{Local, SecondsFormat};
+use itoa::Integer;
+use ryu::Float;
+use slog::{Error, KV, OwnedKVList, Record, Serializer};
+
+use g3_types::log::AsyncLogFormatter;
+
+thread_local! {
+    static TL_BUF: RefCell<String> = RefCell::new(String::with_capacity(128));
+}
+
+/// Format each record as a json line
+#[derive(Default)]
+pub struct FileLogFormatter {}
+
+impl AsyncLogFormatter<Vec<u8>> for FileLogFormatter {
+    fn format_slog(&self, record: &Record, logger_values: &OwnedKVList) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::with_capacity(1024);
+
+        buf.extend_from_slice(b"{\"ts\":\"");
+        let ts = Local::now().to_rfc3339_opts(SecondsFormat::Micros, false);
+        buf.extend_from_slice(ts.as_bytes());
+        buf.extend_from_slice(b"\",\"level\":\"");
+        buf.extend_from_slice(record.level().as_str().as_bytes());
+        buf.push(b'"');
+
+        let mut kv_formatter = FormatterKv(&mut buf);
+        logger_values.serialize(record, &mut kv_formatter)?;
+        record.kv().serialize(record, &mut kv_formatter)?;
+        kv_formatter.emit_arguments("msg".into(), record.msg())?;
+
+        buf.extend_from_slice(b"}\n");
+        Ok(buf)
+    }
+}
+
+struct FormatterKv<'a>(&'a mut Vec<u8>);
+
+impl FormatterKv<'_> {
+    fn emit_key(&mut self, key: slog::Key) -> slog::Result {
+        self.0.push(b',');
+        serde_json::to_writer(&mut *self.0, key.as_str()).map_err(std::io::Error::from)?;
+        self.0.push(b':');
+        Ok(())
+    }
+
+    fn emit_raw(&mut self, key: slog::Key, value: &str) -> slog::Result {
+        self.emit_key(key)?;
+        self.0.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+
+    fn emit_integer<T: Integer>(&mut self, key: slog::Key, value: T) -> slog::Result {
+        let mut buffer = itoa::Buffer::new();
+        let value_s = buffer.format(value);
+        self.emit_raw(key, value_s)
+    }
+
+    fn emit_float<T: Float>(&mut self, key: slog::Key, value: T) -> slog::Result {
+        let mut buffer = ryu::Buffer::new();
+        let value_s = buffer.format(value);
+        match value_s {
+            // not valid json numbers
+            "NaN" | "inf" | "-inf" => self.emit_str(key, value_s),
+            _ => self.emit_raw(key, value_s),
+        }
+    }
+}
+
+impl Serializer for FormatterKv<'_> {
+    impl_integer_by_itoa! {
+        /// Emit `usize`
+        usize => emit_usize
+    }
+    impl_integer_by_itoa! {
+        /// Emit `isize`
+        isize => emit_isize
+    }
+    impl_integer_by_itoa! {
+        /// Emit `u8`
+        u8 => emit_u8
+    }
+    impl_integer_by_itoa! {
+        /// Emit `i8`
+        i8 => emit_i8
+    }
+    impl_integer_by_itoa! {
+        /// Emit `u16`
+        u16 => emit_u16
+    }
+    impl_integer_by_itoa! {
+        /// Emit `i16`
+        i16 => emit_i16
+    }
+    impl_integer_by_itoa! {
+        /// Emit `u32`
+        u32 => emit_u32
+    }
+    impl_integer_by_itoa! {
+        /// Emit `i32`
+        i32 => emit_i32
+    }
+    impl_float_by_ryu! {
+        /// Emit `f32`
+        f32 => emit_f32
+    }
+    impl_integer_by_itoa! {
+        /// Emit `u64`
+        u64 => emit_u64
+    }
+    impl_integer_by_itoa! {
+        /// Emit `i64`
+        i64 => emit_i64
+    }
+    impl_float_by_ryu! {
+        /// Emit `f64`
+        f64 => emit_f64
+    }
+
+    fn emit_bool(&mut self, key: slog::Key, value: bool) -> slog::Result {
+        if value {
+            self.emit_raw(key, "true")
+        } else {
+            self.emit_raw(key, "false")
+        }
+    }
+
+    fn emit_char(&mut self, key: slog::Key, value: char) -> slog::Result {
+        self.emit_str(key, value.encode_utf8(&mut [0u8; 4]))
+    }
+
+    fn emit_none(&mut self, _key: slog::Key) -> slog::Result {
+        Ok(())
+    }
+
+    fn emit_str(&mut self, key: slog::Key, value: &str) -> slog::Result {
+        self.emit_key(key)?;
+        serde_json::to_writer(&mut *self.0, value).map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    fn emit_arguments(&mut self, key: slog::Key, value: &Arguments) -> slog::Result {
+        if let Some(s) = value.as_str() {
+            self.emit_str(key, s)
+        } else {
+            TL_BUF.with_borrow_mut(|buf| {
+                buf.clear();
+
+                buf.write_fmt(*value).unwrap();
+
+                self.emit_str(key, buf.as_str())
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_kv() {
+        let mut buf = Vec::new();
+        let mut kv_formatter = FormatterKv(&mut buf);
+
+        kv_formatter.emit_u8("a-key".into(), 8u8).unwrap();
+        kv_formatter.emit_bool("b".into(), true).unwrap();
+        kv_formatter
+            .emit_f64("c".into(), f64::NEG_INFINITY)
+            .unwrap();
+        kv_formatter.emit_f32("d".into(), -1.5f32).unwrap();
+        kv_formatter.emit_none("e".into()).unwrap();
+        kv_formatter.emit_str("f".into(), "x\"y\n").unwrap();
+        assert_eq!(
+            buf.as_slice(),
+            b",\"a-key\":8,\"b\":true,\"c\":\"-inf\",\"d\":-1.5,\"f\":\"x\\\"y\\n\""
+        );
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+
+use flume::{Receiver, RecvTimeoutError};
+
+use g3_types::log::{AsyncLogConfig, AsyncLogger, LogStats};
+
+#[macro_use]
+mod macros;
+
+mod config;
+pub use config::FileLogConfig;
+
+#[cfg(feature = "yaml")]
+mod yaml;
+
+mod format;
+pub use format::FileLogFormatter;
+
+mod writer;
+use writer::RotatingFileWriter;
+
+/// Create a logger which writes json lines to zstd compressed files
+///
+/// The `name` will be used to replace the `{name}` placeholder in the file name template.
+pub fn new_async_logger(
+    async_conf: &AsyncLogConfig,
+    file_conf: &Arc<FileLogConfig>,
+    name: &str,
+) -> AsyncLogger<Vec<u8>, FileLogFormatter> {
+    let (sender, receiver) = flume::bounded::<Vec<u8>>(async_conf.channel_capacity);
+
+    let stats = Arc::new(LogStats::default());
+
+    // there should be only one writer for each file
+    let io_thread = AsyncIoThread {
+        receiver,
+        writer: RotatingFileWriter::new(file_conf.clone(), name),
+        stats: Arc::clone(&stats),
+    };
+
+    let _detached_thread = std::thread::Builder::new()
+        .name(async_conf.thread_name.clone())
+        .spawn(move || {
+            io_thread.run_to_end();
+        });
+
+    AsyncLogger::new(sender, FileLogFormatter::default(), stats)
+}
+
+struct AsyncIoThread {
+    receiver: Receiver<Vec<u8>>,
+    writer: RotatingFileWriter,
+    stats: Arc<LogStats>,
+}
+
+impl AsyncIoThread {
+    fn run_to_end(mut self) {
+        loop {
+            let r = match self.writer.time_to_rotate() {
+                Some(timeout) => self.receiver.recv_timeout(timeout),
+                None => self
+                    .receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match r {
+                Ok(v) => {
+                    self.write(&v);
+                    while let Ok(v) = self.receiver.try_recv() {
+                        self.write(&v);
+                    }
+                    let _ = self.writer.flush();
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let _ = self.writer.rotate();
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        let _ = self.writer.rotate();
+    }
+
+    fn write(&mut self, v: &[u8]) {
+        match self.writer.write(v) {
+            Ok(_) => {
+                self.stats.io.add_passed();
+                self.stats.io.add_size(v.len());
+            }
+            Err(_) => self.stats.drop.add_peer_unreachable(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::{Logger, slog_info, slog_o};
+
+    #[test]
+    fn drop_on_full_queue() {
+        let (sender, receiver) = flume::bounded::<Vec<u8>>(1);
+        let stats = Arc::new(LogStats::default());
+        let drain = AsyncLogger::new(sender, FileLogFormatter::default(), stats.clone());
+        let logger = Logger::root(slog::IgnoreResult::new(drain), slog_o!());
+
+        slog_info!(logger, "first"; "seq" => 1);
+        assert_eq!(stats.drop.snapshot().channel_overflow, 0);
+        slog_info!(logger, "second"; "seq" => 2);
+        slog_info!(logger, "third"; "seq" => 3);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.io.total, 3);
+        assert_eq!(snapshot.drop.channel_overflow, 2);
+        assert_eq!(receiver.len(), 1);
+
+        drop(receiver);
+        slog_info!(logger, "fourth"; "seq" => 4);
+        assert_eq!(stats.drop.snapshot().channel_closed, 1);
+    }
+}
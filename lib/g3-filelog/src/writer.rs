@@ -0,0 +1,200 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use zstd::stream::write::Encoder;
+
+use super::FileLogConfig;
+
+struct OpenedFile {
+    encoder: Encoder<'static, File>,
+    written: u64,
+    opened_at: Instant,
+}
+
+/// Write to zstd compressed files, and rotate by size and age
+///
+/// Each file contains a single zstd frame, which will be closed when rotating.
+/// The new file will be created lazily at the next write.
+pub(crate) struct RotatingFileWriter {
+    config: Arc<FileLogConfig>,
+    name: String,
+    file: Option<OpenedFile>,
+}
+
+impl RotatingFileWriter {
+    pub(crate) fn new(config: Arc<FileLogConfig>, name: &str) -> Self {
+        RotatingFileWriter {
+            config,
+            name: name.replace('/', "_"),
+            file: None,
+        }
+    }
+
+    fn new_file_path(&self) -> PathBuf {
+        let datetime = Local::now();
+        let file_name = datetime
+            .format(&self.config.file_name)
+            .to_string()
+            .replace("{name}", &self.name);
+        self.config.directory.join(file_name)
+    }
+
+    fn open(&self) -> io::Result<OpenedFile> {
+        let path = self.new_file_path();
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        let mut file = options.open(&path);
+        let mut seq = 1;
+        while let Err(e) = &file {
+            if e.kind() != io::ErrorKind::AlreadyExists {
+                break;
+            }
+            // the file name may be the same if rotated in a single second
+            let mut path = path.clone().into_os_string();
+            path.push(format!(".{seq}"));
+            file = options.open(path);
+            seq += 1;
+        }
+
+        let encoder = Encoder::new(file?, self.config.compression_level)?;
+        Ok(OpenedFile {
+            encoder,
+            written: 0,
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Get the time left before the current file should be rotated by age
+    pub(crate) fn time_to_rotate(&self) -> Option<Duration> {
+        self.file.as_ref().map(|f| {
+            self.config
+                .max_file_age
+                .saturating_sub(f.opened_at.elapsed())
+        })
+    }
+
+    pub(crate) fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        if let Some(f) = &self.file {
+            if f.written + buf.len() as u64 > self.config.max_file_size
+                || f.opened_at.elapsed() >= self.config.max_file_age
+            {
+                self.rotate()?;
+            }
+        }
+
+        let f = match self.file.take() {
+            Some(f) => f,
+            None => self.open()?,
+        };
+        let f = self.file.insert(f);
+        f.encoder.write_all(buf)?;
+        f.written += buf.len() as u64;
+        Ok(())
+    }
+
+    /// Flush the compressed data to the file, the zstd frame will not be closed
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(f) => f.encoder.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Close the zstd frame and the current file
+    pub(crate) fn rotate(&mut self) -> io::Result<()> {
+        let Some(f) = self.file.take() else {
+            return Ok(());
+        };
+        let file = f.encoder.finish()?;
+        if self.config.fsync_on_rotate {
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("g3-filelog-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read_all(dir: &Path) -> Vec<String> {
+        let mut paths = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|p| {
+                let data = zstd::decode_all(File::open(p).unwrap()).unwrap();
+                String::from_utf8(data).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rotate_by_size() {
+        let dir = test_dir("size");
+        let mut config = FileLogConfig::new(dir.clone());
+        config.set_file_name("{name}.log.zst".to_string());
+        config.set_max_file_size(16);
+        config.set_fsync_on_rotate(true);
+        let mut writer = RotatingFileWriter::new(Arc::new(config), "task/test");
+        assert!(writer.time_to_rotate().is_none());
+
+        writer.write(b"line 1 0123456\n").unwrap();
+        writer.flush().unwrap();
+        assert!(writer.time_to_rotate().is_some());
+        // this will exceed the max file size and trigger a rotation
+        writer.write(b"line 2\n").unwrap();
+        writer.write(b"line 3\n").unwrap();
+        writer.rotate().unwrap();
+        assert!(writer.time_to_rotate().is_none());
+
+        let files = read_all(&dir);
+        assert_eq!(files, ["line 1 0123456\n", "line 2\nline 3\n"]);
+        assert!(dir.join("task_test.log.zst").exists());
+        assert!(dir.join("task_test.log.zst.1").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotate_forced() {
+        let dir = test_dir("forced");
+        let mut config = FileLogConfig::new(dir.clone());
+        config.set_file_name("{name}-%Y%m%d.log.zst".to_string());
+        let mut writer = RotatingFileWriter::new(Arc::new(config), "test");
+
+        writer.write(b"a\n").unwrap();
+        writer.flush().unwrap();
+        writer.write(b"b\n").unwrap();
+        writer.rotate().unwrap();
+        writer.write(b"c\n").unwrap();
+        writer.flush().unwrap();
+        writer.rotate().unwrap();
+        // nothing to rotate
+        writer.rotate().unwrap();
+
+        let files = read_all(&dir);
+        assert_eq!(files, ["a\nb\n", "c\n"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::path::Path;
+
+use anyhow::{Context, anyhow};
+use chrono::format::{Item, StrftimeItems};
+use yaml_rust::Yaml;
+
+use super::FileLogConfig;
+
+impl FileLogConfig {
+    pub fn parse_yaml(value: &Yaml, lookup_dir: &Path) -> anyhow::Result<Self> {
+        match value {
+            Yaml::Hash(map) => {
+                let directory = g3_yaml::hash_get_required(map, "directory")?;
+                let directory = g3_yaml::value::as_dir_path(directory, lookup_dir, true)
+                    .context("invalid directory value for key directory")?;
+                let mut config = FileLogConfig::new(directory);
+
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "directory" => Ok(()),
+                    "file_name" => {
+                        let name = g3_yaml::value::as_string(v)?;
+                        if name.is_empty() {
+                            return Err(anyhow!("empty file name template"));
+                        }
+                        if StrftimeItems::new(&name).any(|item| matches!(item, Item::Error)) {
+                            return Err(anyhow!("invalid strftime format in file name {name}"));
+                        }
+                        config.set_file_name(name);
+                        Ok(())
+                    }
+                    "compression_level" | "level" => {
+                        let level = g3_yaml::value::as_i32(v)?;
+                        if !zstd::compression_level_range().contains(&level) {
+                            return Err(anyhow!("out of range zstd compression level {level}"));
+                        }
+                        config.set_compression_level(level);
+                        Ok(())
+                    }
+                    "max_file_size" | "max_size" => {
+                        let size = g3_yaml::humanize::as_u64(v)
+                            .context(format!("invalid humanize u64 value for key {k}"))?;
+                        if size == 0 {
+                            return Err(anyhow!("max file size should not be 0"));
+                        }
+                        config.set_max_file_size(size);
+                        Ok(())
+                    }
+                    "max_file_age" | "max_age" => {
+                        let age = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        if age.is_zero() {
+                            return Err(anyhow!("max file age should not be 0"));
+                        }
+                        config.set_max_file_age(age);
+                        Ok(())
+                    }
+                    "fsync_on_rotate" => {
+                        let enable = g3_yaml::value::as_bool(v)?;
+                        config.set_fsync_on_rotate(enable);
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+
+                Ok(config)
+            }
+            Yaml::String(_) => {
+                let directory = g3_yaml::value::as_dir_path(value, lookup_dir, true)
+                    .context("invalid directory value")?;
+                Ok(FileLogConfig::new(directory))
+            }
+            _ => Err(anyhow!(
+                "yaml value type for 'FileLogConfig' should be 'map' or 'string'"
+            )),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const DEFAULT_BUCKET_BASE: u64 = 4;
+const DEFAULT_BUCKET_MAX: u64 = 1 << 30; // 1GiB
+
+/// The upper bounds of the buckets, an extra overflow bucket will always be added
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BucketHistogramConfig {
+    bounds: Arc<[u64]>,
+}
+
+impl Default for BucketHistogramConfig {
+    fn default() -> Self {
+        BucketHistogramConfig::powers_of(DEFAULT_BUCKET_BASE, DEFAULT_BUCKET_MAX)
+    }
+}
+
+impl BucketHistogramConfig {
+    /// Use the powers of `base` as the bounds, starting from 1 and up to `max`
+    pub fn powers_of(base: u64, max: u64) -> Self {
+        let base = base.max(2);
+        let mut bounds = Vec::new();
+        let mut bound = 1u64;
+        while bound <= max {
+            bounds.push(bound);
+            let Some(next) = bound.checked_mul(base) else {
+                break;
+            };
+            bound = next;
+        }
+        BucketHistogramConfig {
+            bounds: Arc::from(bounds),
+        }
+    }
+
+    pub fn with_bounds(mut bounds: Vec<u64>) -> Self {
+        bounds.sort_unstable();
+        bounds.dedup();
+        BucketHistogramConfig {
+            bounds: Arc::from(bounds),
+        }
+    }
+
+    #[inline]
+    pub fn bounds(&self) -> &[u64] {
+        &self.bounds
+    }
+}
+
+/// A histogram with fixed buckets, which can be shared between threads
+pub struct BucketHistogram {
+    bounds: Arc<[u64]>,
+    counts: Box<[AtomicU64]>,
+    sum: AtomicU64,
+}
+
+impl BucketHistogram {
+    pub fn new(config: &BucketHistogramConfig) -> Self {
+        let counts = (0..=config.bounds.len())
+            .map(|_| AtomicU64::new(0))
+            .collect();
+        BucketHistogram {
+            bounds: config.bounds.clone(),
+            counts,
+            sum: AtomicU64::new(0),
+        }
+    }
+
+    /// Record the value in the first bucket whose upper bound is not less than it
+    pub fn record(&self, value: u64) {
+        let i = self.bounds.partition_point(|bound| *bound < value);
+        self.counts[i].fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> BucketHistogramSnapshot {
+        BucketHistogramSnapshot {
+            bounds: self.bounds.clone(),
+            counts: self
+                .counts
+                .iter()
+                .map(|c| c.load(Ordering::Relaxed))
+                .collect(),
+            sum: self.sum.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BucketHistogramSnapshot {
+    bounds: Arc<[u64]>,
+    counts: Vec<u64>,
+    sum: u64,
+}
+
+impl BucketHistogramSnapshot {
+    #[inline]
+    pub fn bounds(&self) -> &[u64] {
+        &self.bounds
+    }
+
+    /// The counts of each bucket, the last one is the overflow bucket
+    #[inline]
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    #[inline]
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Iterate over the buckets, the upper bound will be None for the overflow bucket
+    pub fn foreach_bucket<F>(&self, mut call: F)
+    where
+        F: FnMut(Option<u64>, u64),
+    {
+        for (i, count) in self.counts.iter().enumerate() {
+            call(self.bounds.get(i).copied(), *count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bounds() {
+        let config = BucketHistogramConfig::default();
+        assert_eq!(config.bounds().len(), 16);
+        assert_eq!(config.bounds()[0], 1);
+        assert_eq!(config.bounds()[1], 4);
+        assert_eq!(config.bounds()[15], 1 << 30);
+    }
+
+    #[test]
+    fn record() {
+        let config = BucketHistogramConfig::with_bounds(vec![1024, 16, 256]);
+        assert_eq!(config.bounds(), &[16, 256, 1024]);
+
+        let histogram = BucketHistogram::new(&config);
+        for v in [0, 16, 17, 256, 1000, 1025, 4096] {
+            histogram.record(v);
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 7);
+        assert_eq!(snapshot.sum(), 6410);
+        let mut buckets = Vec::new();
+        snapshot.foreach_bucket(|bound, count| buckets.push((bound, count)));
+        assert_eq!(
+            buckets,
+            vec![(Some(16), 2), (Some(256), 2), (Some(1024), 1), (None, 2)]
+        );
+        assert_eq!(snapshot.counts(), &[2, 2, 1, 2]);
+    }
+}
@@ -0,0 +1,103 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+#![no_main]
+
+use std::io;
+use std::sync::LazyLock;
+
+use libfuzzer_sys::fuzz_target;
+use tokio::io::{AsyncReadExt, BufReader};
+use tokio::runtime::Runtime;
+
+use g3_http::{HttpBodyDecodeReader, HttpBodyReader};
+
+const BODY_LINE_MAX_LEN: usize = 32;
+const SMALL_MAX_CHUNK_SIZE: u64 = 16;
+
+static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+});
+
+#[derive(Clone, Copy)]
+struct Options {
+    strict: bool,
+    line_max_len: usize,
+    max_chunk_size: Option<u64>,
+    buf_size: usize,
+}
+
+impl Options {
+    /// bit 0: strict mode, bit 1: use a small max chunk size, bit 2-7: buffer size - 1
+    fn parse(b: u8) -> Self {
+        Options {
+            strict: b & 0x01 != 0,
+            line_max_len: BODY_LINE_MAX_LEN,
+            max_chunk_size: (b & 0x02 != 0).then_some(SMALL_MAX_CHUNK_SIZE),
+            buf_size: (b >> 2) as usize + 1,
+        }
+    }
+}
+
+async fn read_body(data: &[u8], opts: Options) -> io::Result<Vec<u8>> {
+    let mut buf_stream = BufReader::with_capacity(opts.buf_size, data);
+    let mut body_reader = HttpBodyReader::new_chunked(&mut buf_stream, opts.line_max_len);
+    body_reader.set_strict_chunked(opts.strict);
+    if let Some(max_size) = opts.max_chunk_size {
+        body_reader.set_max_chunk_size(max_size);
+    }
+    let mut output = Vec::new();
+    let r = body_reader.read_to_end(&mut output).await;
+    if opts.strict {
+        // the data returned before any error should be unchanged
+        assert!(data.starts_with(&output));
+    }
+    r?;
+    assert!(body_reader.finished());
+    Ok(output)
+}
+
+async fn decode_body(data: &[u8], opts: Options) -> io::Result<Vec<u8>> {
+    let mut buf_stream = BufReader::with_capacity(opts.buf_size, data);
+    let mut body_reader = HttpBodyDecodeReader::new_chunked(&mut buf_stream, opts.line_max_len);
+    body_reader.set_strict_chunked(opts.strict);
+    if let Some(max_size) = opts.max_chunk_size {
+        body_reader.set_max_chunk_size(max_size);
+    }
+    let mut output = Vec::new();
+    body_reader.read_to_end(&mut output).await?;
+    Ok(output)
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((b, body)) = data.split_first() else {
+        return;
+    };
+    let opts = Options::parse(*b);
+
+    RUNTIME.block_on(async move {
+        let decoded = decode_body(body, opts).await;
+        let Ok(output) = read_body(body, opts).await else {
+            return;
+        };
+
+        // the output should always be the canonical form,
+        // the lines may be 1 byte longer as bare LF is replaced by CRLF
+        let strict_opts = Options {
+            strict: true,
+            line_max_len: BODY_LINE_MAX_LEN + 1,
+            ..opts
+        };
+        let canonical = read_body(&output, strict_opts).await.unwrap();
+        assert_eq!(canonical, output);
+
+        if let Ok(decoded) = decoded {
+            let canonical_decoded = decode_body(&output, strict_opts).await.unwrap();
+            assert_eq!(canonical_decoded, decoded);
+        }
+    });
+});
@@ -3,58 +3,224 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::io::{self, IoSlice};
 use std::pin::Pin;
 use std::task::{Context, Poll, ready};
 
 use tokio::io::{AsyncBufRead, AsyncWrite};
 
-use g3_io_ext::{ROwnedStreamCopy, StreamCopyConfig, StreamCopyError};
+use g3_io_ext::{OwnedStreamCopy, StreamCopyConfig, StreamCopyError};
 
-use super::{HttpBodyReader, HttpBodyType, StreamToChunkedTransfer};
+use super::{
+    HttpBodyReaderOwned, HttpBodyType, HttpChunkExtensionPolicy, StreamToChunkedTransferOwned,
+};
 
 const NO_TRAILER_END_BUFFER: &[u8] = b"\r\n0\r\n\r\n";
 
-pub struct H1BodyToChunkedTransfer<'a, R, W> {
+/// Transfer the HTTP/1.x body in chunked encoding, which owns both the reader and the writer.
+///
+/// The reader and the writer may also be mutable references, see [`H1BodyToChunkedTransfer`].
+/// The owned version can be moved into a spawned task, or be stored along with other owned states.
+///
+/// All the transfer state is kept in this struct, so the transfer will be resumed if polled again
+/// after a previous [`Self::run`] future is dropped.
+///
+/// ```no_run
+/// # use tokio::io::{AsyncBufRead, AsyncWrite};
+/// # use g3_http::{H1BodyToChunkedTransferOwned, HttpBodyType};
+/// # async fn spawn<R, W>(reader: R, writer: W)
+/// # where
+/// #     R: AsyncBufRead + Send + Unpin + 'static,
+/// #     W: AsyncWrite + Send + Unpin + 'static,
+/// # {
+/// let mut transfer = H1BodyToChunkedTransferOwned::new(
+///     reader,
+///     writer,
+///     HttpBodyType::Chunked,
+///     4096,
+///     Default::default(),
+/// );
+/// let handle = tokio::spawn(async move {
+///     transfer.run().await?;
+///     Ok::<_, g3_io_ext::StreamCopyError>(transfer.into_parts())
+/// });
+/// # }
+/// ```
+pub struct H1BodyToChunkedTransferOwned<R, W> {
     body_type: HttpBodyType,
     copy_config: StreamCopyConfig,
-    state: ChunkedTransferState<'a, R, W>,
+    state: ChunkedTransferState<R, W>,
     total_write: u64,
+    flush_on_complete: bool,
     active: bool,
 }
 
-struct SendHead<'a, R, W> {
-    head: String,
+/// Transfer the HTTP/1.x body in chunked encoding, which borrows the reader and the writer
+pub type H1BodyToChunkedTransfer<'a, R, W> = H1BodyToChunkedTransferOwned<&'a mut R, &'a mut W>;
+
+/// Send the chunk head along with the first piece of body data in a single write
+struct SendHead<R, W> {
+    head: Vec<u8>,
+    data_size: usize,
+    data_staged: bool,
     offset: usize,
-    body_reader: HttpBodyReader<'a, R>,
-    writer: &'a mut W,
+    body_reader: HttpBodyReaderOwned<R>,
+    writer: W,
+}
+
+impl<R, W> SendHead<R, W>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    fn new(
+        head: String,
+        body_reader: HttpBodyReaderOwned<R>,
+        writer: W,
+        copy_config: &StreamCopyConfig,
+    ) -> Self {
+        SendHead {
+            head: head.into_bytes(),
+            data_size: copy_config.buffer_size(),
+            data_staged: false,
+            offset: 0,
+            body_reader,
+            writer,
+        }
+    }
+
+    /// Return the total number of bytes written, including the head and the body data.
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize, StreamCopyError>> {
+        if !self.data_staged && !self.writer.is_write_vectored() {
+            // stage the body data after the head, so they can be sent in one write
+            if let Some(data) =
+                ready!(self.body_reader.poll_fixed_data(cx)).map_err(StreamCopyError::ReadFailed)?
+            {
+                let nr = data.len().min(self.data_size);
+                self.head.extend_from_slice(&data[..nr]);
+                self.body_reader.consume_fixed_data(nr);
+            }
+            self.data_staged = true;
+        }
+
+        while self.offset < self.head.len() {
+            let head_left = self.head.len() - self.offset;
+            let nw = if self.data_staged {
+                ready!(Pin::new(&mut self.writer).poll_write(cx, &self.head[self.offset..]))
+                    .map_err(StreamCopyError::WriteFailed)?
+            } else {
+                let data = ready!(self.body_reader.poll_fixed_data(cx))
+                    .map_err(StreamCopyError::ReadFailed)?
+                    .unwrap_or_default();
+                let bufs = [
+                    IoSlice::new(&self.head[self.offset..]),
+                    IoSlice::new(&data[..data.len().min(self.data_size)]),
+                ];
+                ready!(Pin::new(&mut self.writer).poll_write_vectored(cx, &bufs))
+                    .map_err(StreamCopyError::WriteFailed)?
+            };
+            if nw > head_left {
+                self.body_reader.consume_fixed_data(nw - head_left);
+            }
+            self.offset += nw;
+        }
+        Poll::Ready(Ok(self.offset))
+    }
 }
 
-struct SendEnd<'a, W> {
+struct SendEnd<R, W> {
     offset: usize,
-    writer: &'a mut W,
+    reader: R,
+    writer: W,
+}
+
+/// Send the chunk head, the small fixed length body and the end in a single vectored write
+struct SendSmall<R, W> {
+    head: String,
+    body_len: usize,
+    offset: usize,
+    reader: R,
+    writer: W,
+}
+
+impl<R, W> SendSmall<R, W>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    /// Return `false` if we should fall back to the normal transfer.
+    ///
+    /// The body data will be kept in the reader buffer and only be consumed after all data sent,
+    /// so it's always safe to fall back before the first write.
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<Result<bool, StreamCopyError>> {
+        if self.offset == 0 && !self.writer.is_write_vectored() {
+            return Poll::Ready(Ok(false));
+        }
+
+        let total_len = self.head.len() + self.body_len + NO_TRAILER_END_BUFFER.len();
+        while self.offset < total_len {
+            let body = match Pin::new(&mut self.reader).poll_fill_buf(cx) {
+                Poll::Ready(Ok(buf)) if buf.len() >= self.body_len => &buf[..self.body_len],
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(StreamCopyError::ReadFailed(e))),
+                Poll::Ready(Ok(_)) | Poll::Pending if self.offset == 0 => {
+                    // the whole body is not available at once
+                    return Poll::Ready(Ok(false));
+                }
+                Poll::Ready(Ok(_)) => {
+                    return Poll::Ready(Err(StreamCopyError::ReadFailed(io::Error::other(
+                        "cached body data lost in reader buffer",
+                    ))));
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+
+            let mut skip = self.offset;
+            let mut bufs = [IoSlice::new(&[]); 3];
+            let mut count = 0;
+            for part in [self.head.as_bytes(), body, NO_TRAILER_END_BUFFER] {
+                if skip >= part.len() {
+                    skip -= part.len();
+                    continue;
+                }
+                bufs[count] = IoSlice::new(&part[skip..]);
+                skip = 0;
+                count += 1;
+            }
+            let nw = ready!(Pin::new(&mut self.writer).poll_write_vectored(cx, &bufs[..count]))
+                .map_err(StreamCopyError::WriteFailed)?;
+            self.offset += nw;
+        }
+
+        Pin::new(&mut self.reader).consume(self.body_len);
+        Poll::Ready(Ok(true))
+    }
 }
 
-enum ChunkedTransferState<'a, R, W> {
-    SendHead(SendHead<'a, R, W>),
-    Copy(ROwnedStreamCopy<'a, HttpBodyReader<'a, R>, W>),
-    SendNoTrailerEnd(SendEnd<'a, W>),
-    Encode(StreamToChunkedTransfer<'a, R, W>),
-    FlushEnd(&'a mut W),
-    End,
+/// Each state owns the reader and the writer, which will be moved to the next state
+enum ChunkedTransferState<R, W> {
+    SendSmall(SendSmall<R, W>),
+    SendHead(SendHead<R, W>),
+    Copy(OwnedStreamCopy<HttpBodyReaderOwned<R>, W>),
+    SendNoTrailerEnd(SendEnd<R, W>),
+    Encode(StreamToChunkedTransferOwned<R, W>),
+    FlushEnd(R, W),
+    End(R, W),
+    /// The reader and the writer are being moved to the next state
+    Moving,
 }
 
-impl<'a, R, W> H1BodyToChunkedTransfer<'a, R, W>
+impl<R, W> H1BodyToChunkedTransferOwned<R, W>
 where
     R: AsyncBufRead + Unpin,
     W: AsyncWrite + Unpin,
 {
     pub fn new(
-        reader: &'a mut R,
-        writer: &'a mut W,
+        reader: R,
+        writer: W,
         body_type: HttpBodyType,
         body_line_max_len: usize,
         copy_config: StreamCopyConfig,
-    ) -> H1BodyToChunkedTransfer<'a, R, W> {
+    ) -> Self {
         match body_type {
             HttpBodyType::ContentLength(len) => {
                 Self::new_fixed_length(reader, writer, len, copy_config)
@@ -66,101 +232,226 @@ where
         }
     }
 
-    pub fn new_read_until_end(
-        reader: &'a mut R,
-        writer: &'a mut W,
-        copy_config: StreamCopyConfig,
-    ) -> Self {
-        let encoder =
-            StreamToChunkedTransfer::new_with_no_trailer(reader, writer, copy_config.yield_size());
-        H1BodyToChunkedTransfer {
+    pub fn new_read_until_end(reader: R, writer: W, copy_config: StreamCopyConfig) -> Self {
+        let mut encoder = StreamToChunkedTransferOwned::new_with_no_trailer(
+            reader,
+            writer,
+            copy_config.yield_size(),
+        );
+        // the flush will be done in the FlushEnd state
+        encoder.skip_flush_on_finish();
+        H1BodyToChunkedTransferOwned {
             body_type: HttpBodyType::ReadUntilEnd,
             copy_config,
             state: ChunkedTransferState::Encode(encoder),
             total_write: 0,
+            flush_on_complete: true,
             active: false,
         }
     }
 
-    pub fn new_fixed_length(
-        reader: &'a mut R,
-        writer: &'a mut W,
-        len: u64,
-        copy_config: StreamCopyConfig,
-    ) -> Self {
+    pub fn new_fixed_length(reader: R, writer: W, len: u64, copy_config: StreamCopyConfig) -> Self {
         let state = if len == 0 {
             // just send 0 chunk size and empty trailer end
-            ChunkedTransferState::SendNoTrailerEnd(SendEnd { offset: 2, writer })
-        } else {
-            let head = format!("{len:x}\r\n");
-            let body_reader = HttpBodyReader::new_fixed_length(reader, len);
-            ChunkedTransferState::SendHead(SendHead {
-                head,
+            ChunkedTransferState::SendNoTrailerEnd(SendEnd {
+                offset: 2,
+                reader,
+                writer,
+            })
+        } else if len <= copy_config.buffer_size() as u64 {
+            ChunkedTransferState::SendSmall(SendSmall {
+                head: format!("{len:x}\r\n"),
+                body_len: len as usize,
                 offset: 0,
-                body_reader,
+                reader,
                 writer,
             })
+        } else {
+            let head = format!("{len:x}\r\n");
+            let body_reader = HttpBodyReaderOwned::new_fixed_length(reader, len);
+            ChunkedTransferState::SendHead(SendHead::new(head, body_reader, writer, &copy_config))
         };
-        H1BodyToChunkedTransfer {
+        H1BodyToChunkedTransferOwned {
             body_type: HttpBodyType::ContentLength(len),
             copy_config,
             state,
             total_write: 0,
+            flush_on_complete: true,
             active: false,
         }
     }
 
     pub fn new_chunked(
-        reader: &'a mut R,
-        writer: &'a mut W,
+        reader: R,
+        writer: W,
+        body_line_max_len: usize,
+        copy_config: StreamCopyConfig,
+    ) -> Self {
+        Self::new_chunked_with_extension_policy(
+            reader,
+            writer,
+            body_line_max_len,
+            HttpChunkExtensionPolicy::default(),
+            copy_config,
+        )
+    }
+
+    pub fn new_chunked_with_extension_policy(
+        reader: R,
+        writer: W,
+        body_line_max_len: usize,
+        extension_policy: HttpChunkExtensionPolicy,
+        copy_config: StreamCopyConfig,
+    ) -> Self {
+        Self::new_chunked_with_options(
+            reader,
+            writer,
+            body_line_max_len,
+            extension_policy,
+            false,
+            copy_config,
+        )
+    }
+
+    /// Transfer a chunked body, with the chunked framing validated strictly if `strict_chunked`.
+    ///
+    /// See [`HttpBodyReaderOwned::set_strict_chunked`] for the validation rules.
+    pub fn new_chunked_with_options(
+        reader: R,
+        writer: W,
         body_line_max_len: usize,
+        extension_policy: HttpChunkExtensionPolicy,
+        strict_chunked: bool,
         copy_config: StreamCopyConfig,
-    ) -> H1BodyToChunkedTransfer<'a, R, W> {
-        let body_reader = HttpBodyReader::new_chunked(reader, body_line_max_len);
-        let copy = ROwnedStreamCopy::new(body_reader, writer, copy_config);
-        H1BodyToChunkedTransfer {
+    ) -> Self {
+        let mut body_reader = HttpBodyReaderOwned::new_chunked_with_extension_policy(
+            reader,
+            body_line_max_len,
+            extension_policy,
+        );
+        body_reader.set_strict_chunked(strict_chunked);
+        let mut copy = OwnedStreamCopy::new(body_reader, writer, copy_config);
+        // the flush will be done in the FlushEnd state
+        copy.skip_flush_on_finish();
+        H1BodyToChunkedTransferOwned {
             body_type: HttpBodyType::Chunked,
             copy_config,
             state: ChunkedTransferState::Copy(copy),
             total_write: 0,
+            flush_on_complete: true,
             active: false,
         }
     }
 
     pub fn new_chunked_after_preview(
-        reader: &'a mut R,
-        writer: &'a mut W,
+        reader: R,
+        writer: W,
         left_chunk_size: u64,
         body_line_max_len: usize,
         copy_config: StreamCopyConfig,
-    ) -> H1BodyToChunkedTransfer<'a, R, W> {
+    ) -> Self {
         if left_chunk_size == 0 {
             return Self::new_chunked(reader, writer, body_line_max_len, copy_config);
         }
 
         let head = format!("{left_chunk_size:x}\r\n");
-        let body_reader =
-            HttpBodyReader::new_chunked_after_preview(reader, body_line_max_len, left_chunk_size);
-        let state = ChunkedTransferState::SendHead(SendHead {
-            head,
-            offset: 0,
+        let body_reader = HttpBodyReaderOwned::new_chunked_after_preview(
+            reader,
+            body_line_max_len,
+            left_chunk_size,
+        );
+        let state =
+            ChunkedTransferState::SendHead(SendHead::new(head, body_reader, writer, &copy_config));
+
+        H1BodyToChunkedTransferOwned {
+            body_type: HttpBodyType::Chunked,
+            copy_config,
+            state,
+            total_write: 0,
+            flush_on_complete: true,
+            active: false,
+        }
+    }
+
+    /// Send the left trailer fields after the last chunk size line has been read out
+    pub(super) fn new_chunked_trailer(
+        reader: R,
+        writer: W,
+        body_line_max_len: usize,
+        copy_config: StreamCopyConfig,
+    ) -> Self {
+        let body_reader = HttpBodyReaderOwned::new_trailer(reader, body_line_max_len);
+        let state = ChunkedTransferState::SendHead(SendHead::new(
+            "0\r\n".to_string(),
             body_reader,
             writer,
-        });
+            &copy_config,
+        ));
 
-        H1BodyToChunkedTransfer {
+        H1BodyToChunkedTransferOwned {
             body_type: HttpBodyType::Chunked,
             copy_config,
             state,
             total_write: 0,
+            flush_on_complete: true,
             active: false,
         }
     }
 
+    /// Run the transfer until all body data has been sent.
+    ///
+    /// It's safe to drop the returned future, and call this again to resume the transfer.
+    pub async fn run(&mut self) -> Result<(), StreamCopyError> {
+        self.await
+    }
+
+    /// Set whether to flush the writer before the transfer future resolves, default to true.
+    ///
+    /// Callers that run multiple transfers on the same writer can disable this,
+    /// and call [`Self::poll_flush_end`] or flush the writer once after all transfers.
+    pub fn set_flush_on_complete(&mut self, flush: bool) {
+        self.flush_on_complete = flush;
+    }
+
+    /// Flush the writer after all body data has been sent.
+    ///
+    /// This should only be called after the transfer future resolved successfully.
+    pub fn poll_flush_end(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), StreamCopyError>> {
+        match &mut self.state {
+            ChunkedTransferState::FlushEnd(_, writer) => {
+                ready!(Pin::new(writer).poll_flush(cx)).map_err(StreamCopyError::WriteFailed)?;
+                let old_state = std::mem::replace(&mut self.state, ChunkedTransferState::Moving);
+                let ChunkedTransferState::FlushEnd(reader, writer) = old_state else {
+                    unreachable!()
+                };
+                self.state = ChunkedTransferState::End(reader, writer);
+                Poll::Ready(Ok(()))
+            }
+            ChunkedTransferState::End(_, _) => Poll::Ready(Ok(())),
+            _ => Poll::Ready(Err(StreamCopyError::WriteFailed(io::Error::other(
+                "the body transfer has not finished yet",
+            )))),
+        }
+    }
+
+    fn poll_complete(
+        &mut self,
+        reader: R,
+        writer: W,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), StreamCopyError>> {
+        self.state = ChunkedTransferState::FlushEnd(reader, writer);
+        if self.flush_on_complete {
+            self.poll_flush_end(cx)
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
     pub fn finished(&self) -> bool {
         matches!(
             self.state,
-            ChunkedTransferState::FlushEnd(_) | ChunkedTransferState::End
+            ChunkedTransferState::FlushEnd(_, _) | ChunkedTransferState::End(_, _)
         )
     }
 
@@ -170,10 +461,14 @@ where
 
     pub fn no_cached_data(&self) -> bool {
         match &self.state {
-            ChunkedTransferState::SendHead(_) | ChunkedTransferState::SendNoTrailerEnd(_) => false,
+            ChunkedTransferState::SendSmall(_)
+            | ChunkedTransferState::SendHead(_)
+            | ChunkedTransferState::SendNoTrailerEnd(_) => false,
             ChunkedTransferState::Copy(copy) => copy.no_cached_data(),
             ChunkedTransferState::Encode(encode) => encode.no_cached_data(),
-            ChunkedTransferState::FlushEnd(_) | ChunkedTransferState::End => true,
+            ChunkedTransferState::FlushEnd(_, _)
+            | ChunkedTransferState::End(_, _)
+            | ChunkedTransferState::Moving => true,
         }
     }
 
@@ -185,9 +480,59 @@ where
         }
         self.active = false;
     }
+
+    fn get_ref(&self) -> (&R, &W) {
+        match &self.state {
+            ChunkedTransferState::SendSmall(send_small) => (&send_small.reader, &send_small.writer),
+            ChunkedTransferState::SendHead(send_head) => {
+                (send_head.body_reader.get_ref(), &send_head.writer)
+            }
+            ChunkedTransferState::Copy(copy) => {
+                let (body_reader, writer) = copy.get_ref();
+                (body_reader.get_ref(), writer)
+            }
+            ChunkedTransferState::SendNoTrailerEnd(send_end) => {
+                (&send_end.reader, &send_end.writer)
+            }
+            ChunkedTransferState::Encode(encode) => encode.get_ref(),
+            ChunkedTransferState::FlushEnd(reader, writer)
+            | ChunkedTransferState::End(reader, writer) => (reader, writer),
+            ChunkedTransferState::Moving => unreachable!(),
+        }
+    }
+
+    pub fn reader(&self) -> &R {
+        self.get_ref().0
+    }
+
+    pub fn writer(&self) -> &W {
+        self.get_ref().1
+    }
+
+    /// Get back the reader and the writer.
+    ///
+    /// The transfer state will be lost, so this should only be called after the transfer finished,
+    /// or if the transfer is going to be abandoned.
+    pub fn into_parts(self) -> (R, W) {
+        match self.state {
+            ChunkedTransferState::SendSmall(send_small) => (send_small.reader, send_small.writer),
+            ChunkedTransferState::SendHead(send_head) => {
+                (send_head.body_reader.into_reader(), send_head.writer)
+            }
+            ChunkedTransferState::Copy(copy) => {
+                let (body_reader, writer) = copy.into_parts();
+                (body_reader.into_reader(), writer)
+            }
+            ChunkedTransferState::SendNoTrailerEnd(send_end) => (send_end.reader, send_end.writer),
+            ChunkedTransferState::Encode(encode) => encode.into_parts(),
+            ChunkedTransferState::FlushEnd(reader, writer)
+            | ChunkedTransferState::End(reader, writer) => (reader, writer),
+            ChunkedTransferState::Moving => unreachable!(),
+        }
+    }
 }
 
-impl<R, W> Future for H1BodyToChunkedTransfer<'_, R, W>
+impl<R, W> Future for H1BodyToChunkedTransferOwned<R, W>
 where
     R: AsyncBufRead + Unpin,
     W: AsyncWrite + Unpin,
@@ -196,31 +541,67 @@ where
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match &mut self.state {
-            ChunkedTransferState::SendHead(send_head) => {
-                while send_head.offset < send_head.head.len() {
-                    let buf = &send_head.head.as_bytes()[send_head.offset..];
-                    let nw = ready!(Pin::new(&mut send_head.writer).poll_write(cx, buf))
-                        .map_err(StreamCopyError::WriteFailed)?;
-                    send_head.offset += nw;
+            ChunkedTransferState::SendSmall(send_small) => {
+                let r = send_small.poll_send(cx);
+                let written = send_small.offset;
+                let body_len = send_small.body_len;
+                if written > 0 {
+                    self.active = true;
+                }
+                match r {
+                    Poll::Pending => Poll::Pending,
+                    Poll::Ready(Ok(true)) => {
+                        let old_state =
+                            std::mem::replace(&mut self.state, ChunkedTransferState::Moving);
+                        let ChunkedTransferState::SendSmall(send_small) = old_state else {
+                            unreachable!()
+                        };
+                        self.total_write += (send_small.head.len() + body_len) as u64;
+                        self.poll_complete(send_small.reader, send_small.writer, cx)
+                    }
+                    Poll::Ready(Ok(false)) => {
+                        let old_state =
+                            std::mem::replace(&mut self.state, ChunkedTransferState::Moving);
+                        let ChunkedTransferState::SendSmall(send_small) = old_state else {
+                            unreachable!()
+                        };
+                        let body_reader = HttpBodyReaderOwned::new_fixed_length(
+                            send_small.reader,
+                            body_len as u64,
+                        );
+                        self.state = ChunkedTransferState::SendHead(SendHead::new(
+                            send_small.head,
+                            body_reader,
+                            send_small.writer,
+                            &self.copy_config,
+                        ));
+                        self.poll(cx)
+                    }
+                    Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
                 }
-                self.total_write += send_head.offset as u64;
+            }
+            ChunkedTransferState::SendHead(send_head) => {
+                let nw = ready!(send_head.poll_send(cx))?;
+                self.total_write += nw as u64;
                 self.active = true;
 
-                let old_state = std::mem::replace(&mut self.state, ChunkedTransferState::End);
+                let old_state = std::mem::replace(&mut self.state, ChunkedTransferState::Moving);
                 let ChunkedTransferState::SendHead(send_head) = old_state else {
                     unreachable!()
                 };
-                let copy = ROwnedStreamCopy::new(
-                    send_head.body_reader,
-                    send_head.writer,
-                    self.copy_config,
-                );
+                let mut copy =
+                    OwnedStreamCopy::new(send_head.body_reader, send_head.writer, self.copy_config);
+                copy.skip_flush_on_finish();
+                if let HttpBodyType::ContentLength(_) = self.body_type {
+                    // the hint of chunked body is only for the current chunk,
+                    // so only use it for fixed length body to avoid splitting the reads
+                    copy.set_remaining_hint(HttpBodyReaderOwned::remaining_hint);
+                }
                 self.state = ChunkedTransferState::Copy(copy);
                 self.poll(cx)
             }
             ChunkedTransferState::Copy(copy) => {
-                let mut copy = Pin::new(copy);
-                match copy.as_mut().poll(cx) {
+                match copy.poll_copy(cx) {
                     Poll::Pending => {
                         self.active |= copy.is_active();
                         return Poll::Pending;
@@ -231,19 +612,21 @@ where
                     }
                     Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
                 };
+                let old_state = std::mem::replace(&mut self.state, ChunkedTransferState::Moving);
+                let ChunkedTransferState::Copy(copy) = old_state else {
+                    unreachable!()
+                };
+                let (body_reader, writer) = copy.into_parts();
+                let reader = body_reader.into_reader();
                 if matches!(self.body_type, HttpBodyType::ContentLength(_)) {
-                    let old_state = std::mem::replace(&mut self.state, ChunkedTransferState::End);
-                    let ChunkedTransferState::Copy(copy) = old_state else {
-                        unreachable!()
-                    };
                     self.state = ChunkedTransferState::SendNoTrailerEnd(SendEnd {
                         offset: 0,
-                        writer: copy.writer(),
+                        reader,
+                        writer,
                     });
                     self.poll(cx)
                 } else {
-                    self.state = ChunkedTransferState::End;
-                    Poll::Ready(Ok(()))
+                    self.poll_complete(reader, writer, cx)
                 }
             }
             ChunkedTransferState::SendNoTrailerEnd(send_end) => {
@@ -253,13 +636,12 @@ where
                         .map_err(StreamCopyError::WriteFailed)?;
                     send_end.offset += nw;
                 }
-                let old_state = std::mem::replace(&mut self.state, ChunkedTransferState::End);
+                let old_state = std::mem::replace(&mut self.state, ChunkedTransferState::Moving);
                 let ChunkedTransferState::SendNoTrailerEnd(send_end) = old_state else {
                     unreachable!()
                 };
-                self.state = ChunkedTransferState::FlushEnd(send_end.writer);
                 self.active = true;
-                Poll::Ready(Ok(()))
+                self.poll_complete(send_end.reader, send_end.writer, cx)
             }
             ChunkedTransferState::Encode(encode) => {
                 let mut encode = Pin::new(encode);
@@ -271,17 +653,26 @@ where
                     Poll::Ready(Ok(n)) => {
                         self.total_write += n;
                         self.active = true;
-                        self.state = ChunkedTransferState::End;
-                        Poll::Ready(Ok(()))
+                        let old_state =
+                            std::mem::replace(&mut self.state, ChunkedTransferState::Moving);
+                        let ChunkedTransferState::Encode(encode) = old_state else {
+                            unreachable!()
+                        };
+                        let (reader, writer) = encode.into_parts();
+                        self.poll_complete(reader, writer, cx)
                     }
                     Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
                 }
             }
-            ChunkedTransferState::FlushEnd(writer) => {
-                ready!(Pin::new(writer).poll_flush(cx)).map_err(StreamCopyError::WriteFailed)?;
-                Poll::Ready(Ok(()))
+            ChunkedTransferState::FlushEnd(_, _) => {
+                if self.flush_on_complete {
+                    self.poll_flush_end(cx)
+                } else {
+                    Poll::Ready(Ok(()))
+                }
             }
-            ChunkedTransferState::End => Poll::Ready(Ok(())),
+            ChunkedTransferState::End(_, _) => Poll::Ready(Ok(())),
+            ChunkedTransferState::Moving => unreachable!(),
         }
     }
 }
@@ -291,6 +682,75 @@ mod test {
     use super::*;
     use tokio::io::BufReader;
 
+    struct CountingWriter {
+        vectored: bool,
+        write_count: usize,
+        flush_count: usize,
+        fail_flush: bool,
+        data: Vec<u8>,
+    }
+
+    impl CountingWriter {
+        fn new(vectored: bool) -> Self {
+            CountingWriter {
+                vectored,
+                write_count: 0,
+                flush_count: 0,
+                fail_flush: false,
+                data: Vec::new(),
+            }
+        }
+
+        fn new_flush_failed() -> Self {
+            let mut writer = CountingWriter::new(true);
+            writer.fail_flush = true;
+            writer
+        }
+    }
+
+    impl AsyncWrite for CountingWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.write_count += 1;
+            self.data.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_write_vectored(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            bufs: &[IoSlice<'_>],
+        ) -> Poll<io::Result<usize>> {
+            self.write_count += 1;
+            let mut len = 0;
+            for buf in bufs {
+                self.data.extend_from_slice(buf);
+                len += buf.len();
+            }
+            Poll::Ready(Ok(len))
+        }
+
+        fn is_write_vectored(&self) -> bool {
+            self.vectored
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.flush_count += 1;
+            if self.fail_flush {
+                Poll::Ready(Err(io::Error::other("flush failed")))
+            } else {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
     #[tokio::test]
     async fn single_to_end() {
         let content = b"test body";
@@ -466,4 +926,371 @@ mod test {
         assert_eq!(write_buf.len(), body_len);
         assert_eq!(&write_buf, &content[0..body_len]);
     }
+
+    #[tokio::test]
+    async fn small_content_length_single_write() {
+        let content = [b'a'; 100];
+        let mut buf_stream = BufReader::new(content.as_slice());
+
+        let mut exp_body = b"64\r\n".to_vec();
+        exp_body.extend_from_slice(&content);
+        exp_body.extend_from_slice(b"\r\n0\r\n\r\n");
+        let mut writer = CountingWriter::new(true);
+
+        let mut body_transfer = H1BodyToChunkedTransfer::new(
+            &mut buf_stream,
+            &mut writer,
+            HttpBodyType::ContentLength(100),
+            1024,
+            Default::default(),
+        );
+
+        (&mut body_transfer).await.unwrap();
+        assert!(body_transfer.finished());
+
+        assert_eq!(writer.write_count, 1);
+        assert_eq!(writer.data, exp_body);
+    }
+
+    #[tokio::test]
+    async fn small_content_length_not_vectored() {
+        let content = b"test bodyXXX";
+        let mut buf_stream = BufReader::new(content.as_slice());
+
+        let exp_body = b"9\r\ntest body\r\n0\r\n\r\n";
+        let mut writer = CountingWriter::new(false);
+
+        let mut body_transfer = H1BodyToChunkedTransfer::new(
+            &mut buf_stream,
+            &mut writer,
+            HttpBodyType::ContentLength(9),
+            1024,
+            Default::default(),
+        );
+
+        (&mut body_transfer).await.unwrap();
+        assert!(body_transfer.finished());
+
+        assert!(writer.write_count > 1);
+        assert_eq!(writer.data, exp_body);
+    }
+
+    #[tokio::test]
+    async fn small_content_length_split() {
+        let content1 = b"test body";
+        let content2 = b"- helloXXX";
+        let stream = tokio_test::io::Builder::new()
+            .read(content1)
+            .read(content2)
+            .build();
+        let mut buf_stream = BufReader::new(stream);
+
+        let exp_body = b"10\r\ntest body- hello\r\n0\r\n\r\n";
+        let mut writer = CountingWriter::new(true);
+
+        let mut body_transfer = H1BodyToChunkedTransfer::new(
+            &mut buf_stream,
+            &mut writer,
+            HttpBodyType::ContentLength(16),
+            1024,
+            Default::default(),
+        );
+
+        (&mut body_transfer).await.unwrap();
+        assert!(body_transfer.finished());
+
+        assert!(writer.write_count > 1);
+        assert_eq!(writer.data, exp_body);
+    }
+
+    #[tokio::test]
+    async fn large_content_length_coalesce_head() {
+        let content = [b'a'; 6000];
+        let mut exp_body = b"1770\r\n".to_vec();
+        exp_body.extend_from_slice(&content);
+        exp_body.extend_from_slice(b"\r\n0\r\n\r\n");
+
+        let mut copy_config = StreamCopyConfig::default();
+        copy_config.set_buffer_size(4096);
+
+        for vectored in [true, false] {
+            let mut buf_stream = BufReader::new(content.as_slice());
+            let mut writer = CountingWriter::new(vectored);
+
+            let mut body_transfer = H1BodyToChunkedTransfer::new(
+                &mut buf_stream,
+                &mut writer,
+                HttpBodyType::ContentLength(6000),
+                1024,
+                copy_config,
+            );
+
+            (&mut body_transfer).await.unwrap();
+            assert!(body_transfer.finished());
+
+            // head with the first 4096 bytes, the left 1904 bytes, and the end
+            assert_eq!(writer.write_count, 3);
+            assert_eq!(writer.data, exp_body);
+        }
+    }
+
+    #[tokio::test]
+    async fn chunked_after_preview() {
+        let content = b"world\r\n3\r\nabc\r\n0\r\n\r\nXXX";
+        let exp_body = b"5\r\nworld\r\n3\r\nabc\r\n0\r\n\r\n";
+
+        for vectored in [true, false] {
+            let stream = tokio_test::io::Builder::new()
+                .read(&content[..3])
+                .read(&content[3..])
+                .build();
+            let mut buf_stream = BufReader::new(stream);
+            let mut writer = CountingWriter::new(vectored);
+
+            let mut body_transfer = H1BodyToChunkedTransfer::new_chunked_after_preview(
+                &mut buf_stream,
+                &mut writer,
+                5,
+                1024,
+                Default::default(),
+            );
+
+            (&mut body_transfer).await.unwrap();
+            assert!(body_transfer.finished());
+
+            assert_eq!(writer.data, exp_body);
+        }
+    }
+
+    #[tokio::test]
+    async fn sha256_tee() {
+        use g3_io_ext::Sha256BufReader;
+
+        let content = b"The quick brown fox jumps over the lazy dogXXX";
+        let stream = tokio_test::io::Builder::new().read(content).build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut hash_stream = Sha256BufReader::new(&mut buf_stream);
+
+        let exp_body = b"2b\r\nThe quick brown fox jumps over the lazy dog\r\n0\r\n\r\n";
+        let mut write_buf = Vec::with_capacity(exp_body.len());
+
+        let mut body_transfer = H1BodyToChunkedTransfer::new(
+            &mut hash_stream,
+            &mut write_buf,
+            HttpBodyType::ContentLength(43),
+            1024,
+            Default::default(),
+        );
+
+        (&mut body_transfer).await.unwrap();
+        assert!(body_transfer.finished());
+        assert_eq!(&write_buf, exp_body);
+
+        let (digest, len) = hash_stream.finish();
+        assert_eq!(len, 43);
+        assert_eq!(
+            digest,
+            Some(hex_literal::hex!(
+                "d7a8fbb307d7809469ca9abcb0082e4f8d5651e46d3cdb762d02d0bf37c9e592"
+            ))
+        );
+    }
+
+    fn all_body_types() -> [(HttpBodyType, &'static [u8]); 5] {
+        [
+            (HttpBodyType::ReadUntilEnd, b"test body"),
+            (HttpBodyType::ContentLength(0), b""),
+            (HttpBodyType::ContentLength(9), b"test body"),
+            (HttpBodyType::ContentLength(6000), &[b'a'; 6000]),
+            (HttpBodyType::Chunked, b"4\r\ntest\r\n0\r\n\r\n"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn flush_on_complete() {
+        let mut copy_config = StreamCopyConfig::default();
+        copy_config.set_buffer_size(4096);
+
+        for (body_type, content) in all_body_types() {
+            let mut buf_stream = BufReader::new(content);
+            let mut writer = CountingWriter::new(true);
+
+            let mut body_transfer = H1BodyToChunkedTransfer::new(
+                &mut buf_stream,
+                &mut writer,
+                body_type,
+                1024,
+                copy_config,
+            );
+
+            (&mut body_transfer).await.unwrap();
+            assert!(body_transfer.finished());
+            assert!(writer.flush_count > 0, "no flush for {body_type:?}");
+            assert!(writer.data.ends_with(b"0\r\n\r\n"));
+        }
+    }
+
+    #[tokio::test]
+    async fn flush_failed() {
+        let mut copy_config = StreamCopyConfig::default();
+        copy_config.set_buffer_size(4096);
+
+        for (body_type, content) in all_body_types() {
+            let mut buf_stream = BufReader::new(content);
+            let mut writer = CountingWriter::new_flush_failed();
+
+            let body_transfer = H1BodyToChunkedTransfer::new(
+                &mut buf_stream,
+                &mut writer,
+                body_type,
+                1024,
+                copy_config,
+            );
+
+            let r = body_transfer.await;
+            assert!(
+                matches!(r, Err(StreamCopyError::WriteFailed(_))),
+                "flush error not returned for {body_type:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn flush_failed_after_preview() {
+        let content = b"world\r\n0\r\n\r\n";
+        let mut buf_stream = BufReader::new(content.as_slice());
+        let mut writer = CountingWriter::new_flush_failed();
+
+        let body_transfer = H1BodyToChunkedTransfer::new_chunked_after_preview(
+            &mut buf_stream,
+            &mut writer,
+            5,
+            1024,
+            Default::default(),
+        );
+
+        let r = body_transfer.await;
+        assert!(matches!(r, Err(StreamCopyError::WriteFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn flush_end_deferred() {
+        let mut copy_config = StreamCopyConfig::default();
+        copy_config.set_buffer_size(4096);
+
+        for (body_type, content) in all_body_types() {
+            let mut buf_stream = BufReader::new(content);
+            let mut writer = CountingWriter::new_flush_failed();
+
+            let mut body_transfer = H1BodyToChunkedTransfer::new(
+                &mut buf_stream,
+                &mut writer,
+                body_type,
+                1024,
+                copy_config,
+            );
+            body_transfer.set_flush_on_complete(false);
+
+            (&mut body_transfer).await.unwrap();
+            assert!(body_transfer.finished());
+
+            let r = std::future::poll_fn(|cx| body_transfer.poll_flush_end(cx)).await;
+            assert!(
+                matches!(r, Err(StreamCopyError::WriteFailed(_))),
+                "flush error not returned for {body_type:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn owned_split_chunked() {
+        let content1 = b"5\r\ntest\n\r\n4\r";
+        let content2 = b"\nbody\r\n0\r\nA: B\r\n\r\nXXX";
+        let stream = tokio_test::io::Builder::new()
+            .read(content1)
+            .read(content2)
+            .build();
+
+        let mut transfer = H1BodyToChunkedTransferOwned::new(
+            BufReader::new(stream),
+            Vec::new(),
+            HttpBodyType::Chunked,
+            64,
+            StreamCopyConfig::default(),
+        );
+        transfer.run().await.unwrap();
+        assert!(transfer.finished());
+
+        let (mut reader, write_buf) = transfer.into_parts();
+        assert_eq!(&write_buf, b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\nA: B\r\n\r\n");
+
+        let mut left = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut left)
+            .await
+            .unwrap();
+        assert_eq!(&left, b"XXX");
+    }
+
+    #[tokio::test]
+    async fn owned_spawned() {
+        let content = b"test bodyXXX";
+        let transfer = H1BodyToChunkedTransferOwned::new(
+            BufReader::new(content.as_slice()),
+            Vec::new(),
+            HttpBodyType::ContentLength(9),
+            1024,
+            StreamCopyConfig::default(),
+        );
+
+        let handle = tokio::spawn(async move {
+            let mut transfer = transfer;
+            transfer.run().await.map(|_| transfer.into_parts())
+        });
+        let (_, write_buf) = handle.await.unwrap().unwrap();
+        assert_eq!(&write_buf, b"9\r\ntest body\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn owned_resume_after_drop() {
+        let content1 = b"5\r\nte";
+        let content2 = b"st\n\r\n4\r\nbody\r\n0\r\n\r\n";
+        let mut copy_config = StreamCopyConfig::default();
+        copy_config.set_buffer_size(4096);
+
+        for body_type in [HttpBodyType::Chunked, HttpBodyType::ContentLength(24)] {
+            let stream = tokio_test::io::Builder::new()
+                .read(content1)
+                .wait(std::time::Duration::from_millis(10))
+                .read(content2)
+                .build();
+            let mut transfer = H1BodyToChunkedTransferOwned::new(
+                BufReader::new(stream),
+                Vec::new(),
+                body_type,
+                64,
+                copy_config,
+            );
+
+            let mut task = tokio_test::task::spawn(transfer.run());
+            assert!(task.poll().is_pending());
+            drop(task);
+            assert!(!transfer.finished());
+            assert!(!transfer.is_idle());
+
+            transfer.run().await.unwrap();
+            assert!(transfer.finished());
+            assert!(transfer.no_cached_data());
+
+            let (_, write_buf) = transfer.into_parts();
+            match body_type {
+                HttpBodyType::Chunked => {
+                    assert_eq!(&write_buf, b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\n\r\n")
+                }
+                _ => assert_eq!(
+                    &write_buf,
+                    b"18\r\n5\r\ntest\n\r\n4\r\nbody\r\n0\r\n\r\n\r\n0\r\n\r\n"
+                ),
+            }
+        }
+    }
 }
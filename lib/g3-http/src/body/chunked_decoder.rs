@@ -10,10 +10,14 @@ use std::task::{Context, Poll, ready};
 use bytes::BufMut;
 use tokio::io::{AsyncBufRead, AsyncRead, ReadBuf};
 
-use crate::parse::HttpChunkedLine;
+use super::{DEFAULT_MAX_CHUNK_SIZE, HttpChunkExtensionPolicy};
+use crate::parse::{HttpChunkedLine, HttpLineParseError};
 
 struct ChunkedDataDecodeReaderInternal {
     body_line_max_size: usize,
+    extension_policy: HttpChunkExtensionPolicy,
+    strict: bool,
+    max_chunk_size: u64,
     chunk_header: Vec<u8>,
     this_chunk_size: u64,
     left_chunk_size: u64,
@@ -23,9 +27,12 @@ struct ChunkedDataDecodeReaderInternal {
 }
 
 impl ChunkedDataDecodeReaderInternal {
-    fn new(body_line_max_size: usize) -> Self {
+    fn new(body_line_max_size: usize, extension_policy: HttpChunkExtensionPolicy) -> Self {
         ChunkedDataDecodeReaderInternal {
             body_line_max_size,
+            extension_policy,
+            strict: false,
+            max_chunk_size: DEFAULT_MAX_CHUNK_SIZE,
             chunk_header: Vec::with_capacity(32),
             this_chunk_size: 0,
             left_chunk_size: 0,
@@ -101,6 +108,12 @@ impl ChunkedDataDecodeReaderInternal {
                             continue;
                         }
                         b'\n' => {
+                            if self.strict {
+                                return Poll::Ready(Err(io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    HttpLineParseError::BareLineFeed,
+                                )));
+                            }
                             reader.as_mut().consume(1);
                             self.poll_chunk_end = true;
                             continue;
@@ -126,6 +139,12 @@ impl ChunkedDataDecodeReaderInternal {
                             }
                         }
                         b'\n' => {
+                            if self.strict {
+                                return Poll::Ready(Err(io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    HttpLineParseError::BareLineFeed,
+                                )));
+                            }
                             reader.as_mut().consume(1);
                             self.poll_chunk_end = true;
                             continue;
@@ -173,6 +192,12 @@ impl ChunkedDataDecodeReaderInternal {
 
                     match memchr::memchr(b'\n', r_buf) {
                         Some(p) => {
+                            if self.chunk_header.len() + p + 1 > self.body_line_max_size {
+                                return Poll::Ready(Err(io::Error::other(format!(
+                                    "chunk header line too long (> {})",
+                                    self.body_line_max_size
+                                ))));
+                            }
                             self.chunk_header.put_slice(&r_buf[0..=p]);
                             reader.as_mut().consume(p + 1);
                             break;
@@ -191,8 +216,26 @@ impl ChunkedDataDecodeReaderInternal {
                     }
                 }
 
-                let chunk_line = HttpChunkedLine::parse(&self.chunk_header)
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                let chunk_line = if self.strict {
+                    HttpChunkedLine::parse_strict(&self.chunk_header)
+                } else {
+                    HttpChunkedLine::parse(&self.chunk_header)
+                }
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if chunk_line.extension.is_some()
+                    && self.extension_policy == HttpChunkExtensionPolicy::Reject
+                {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        HttpLineParseError::ChunkExtensionNotAllowed,
+                    )));
+                }
+                if chunk_line.chunk_size > self.max_chunk_size {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        HttpLineParseError::ChunkTooLarge(self.max_chunk_size),
+                    )));
+                }
                 self.this_chunk_size = chunk_line.chunk_size;
                 self.left_chunk_size = chunk_line.chunk_size;
                 if self.left_chunk_size == 0 {
@@ -209,16 +252,42 @@ impl ChunkedDataDecodeReaderInternal {
 pub struct ChunkedDataDecodeReader<'a, R> {
     reader: &'a mut R,
     internal: ChunkedDataDecodeReaderInternal,
+    pending_error: Option<io::Error>,
 }
 
 impl<'a, R> ChunkedDataDecodeReader<'a, R> {
     pub fn new(reader: &'a mut R, body_line_max_size: usize) -> Self {
+        ChunkedDataDecodeReader::new_with_extension_policy(
+            reader,
+            body_line_max_size,
+            HttpChunkExtensionPolicy::default(),
+        )
+    }
+
+    /// The chunk extensions will always be dropped unless the policy is `Reject`,
+    /// as only the decoded data will be returned
+    pub fn new_with_extension_policy(
+        reader: &'a mut R,
+        body_line_max_size: usize,
+        extension_policy: HttpChunkExtensionPolicy,
+    ) -> Self {
         ChunkedDataDecodeReader {
             reader,
-            internal: ChunkedDataDecodeReaderInternal::new(body_line_max_size),
+            internal: ChunkedDataDecodeReaderInternal::new(body_line_max_size, extension_policy),
+            pending_error: None,
         }
     }
 
+    /// Set whether to validate the chunked framing strictly as defined in RFC 9112, default to false
+    pub fn set_strict_chunked(&mut self, strict: bool) {
+        self.internal.strict = strict;
+    }
+
+    /// Set the max size of a single chunk, default to 1GiB
+    pub fn set_max_chunk_size(&mut self, max_size: u64) {
+        self.internal.max_chunk_size = max_size;
+    }
+
     #[inline]
     pub fn into_reader(self) -> &'a mut R {
         self.reader
@@ -255,6 +324,9 @@ where
         buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
         let me = &mut *self;
+        if let Some(e) = me.pending_error.take() {
+            return Poll::Ready(Err(e));
+        }
 
         let old_remaining = buf.remaining();
         match me.internal.poll_decode(cx, Pin::new(&mut me.reader), buf) {
@@ -265,6 +337,11 @@ where
                     Poll::Pending
                 }
             }
+            Poll::Ready(Err(e)) if old_remaining > buf.remaining() => {
+                // return the decoded data first, and the error in the next read
+                me.pending_error = Some(e);
+                Poll::Ready(Ok(()))
+            }
             Poll::Ready(r) => Poll::Ready(r),
         }
     }
@@ -304,4 +381,85 @@ mod test {
         assert_eq!(&buf[0..len], b"test\nbody");
         assert!(body_deocder.finished());
     }
+
+    #[tokio::test]
+    async fn read_strict_chunked() {
+        for (content, error) in [
+            (
+                b"4\nbody\r\n0\r\n\r\n".as_slice(),
+                HttpLineParseError::BareLineFeed,
+            ),
+            (b"4\r\nbody\n0\r\n\r\n", HttpLineParseError::BareLineFeed),
+            (
+                b"4 \r\nbody\r\n0\r\n\r\n",
+                HttpLineParseError::ChunkSizeWhitespace,
+            ),
+            (
+                b"+4\r\nbody\r\n0\r\n\r\n",
+                HttpLineParseError::ChunkSizeSignPrefix,
+            ),
+            (
+                b"000000000000000004\r\nbody\r\n0\r\n\r\n",
+                HttpLineParseError::ChunkSizeTooLong,
+            ),
+        ] {
+            let mut buf_stream = BufReader::new(content);
+            let mut body_deocder = ChunkedDataDecodeReader::new(&mut buf_stream, 1024);
+            let mut buf = Vec::new();
+            body_deocder.read_to_end(&mut buf).await.unwrap();
+            assert_eq!(buf, b"body");
+
+            let mut buf_stream = BufReader::new(content);
+            let mut body_deocder = ChunkedDataDecodeReader::new(&mut buf_stream, 1024);
+            body_deocder.set_strict_chunked(true);
+            let e = body_deocder.read_to_end(&mut buf).await.unwrap_err();
+            let found = e
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<HttpLineParseError>())
+                .unwrap();
+            assert_eq!(found.to_string(), error.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn read_strict_data_before_error() {
+        let content = b"4\r\nbody\n0\r\n\r\n";
+        let mut buf_stream = BufReader::new(content.as_slice());
+        let mut body_deocder = ChunkedDataDecodeReader::new(&mut buf_stream, 1024);
+        body_deocder.set_strict_chunked(true);
+
+        let mut buf = [0u8; 32];
+        let len = body_deocder.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[0..len], b"body");
+        let e = body_deocder.read(&mut buf).await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(!body_deocder.finished());
+    }
+
+    #[tokio::test]
+    async fn read_chunk_too_large() {
+        let content = b"5\r\nbody1\r\n0\r\n\r\n";
+        let mut buf_stream = BufReader::new(content.as_slice());
+        let mut body_deocder = ChunkedDataDecodeReader::new(&mut buf_stream, 1024);
+        body_deocder.set_max_chunk_size(4);
+        let mut buf = Vec::new();
+        let e = body_deocder.read_to_end(&mut buf).await.unwrap_err();
+        let found = e
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<HttpLineParseError>())
+            .unwrap();
+        assert!(matches!(found, HttpLineParseError::ChunkTooLarge(4)));
+
+        let mut buf_stream = BufReader::new(content.as_slice());
+        let mut body_decoder = crate::HttpBodyDecodeReader::new_chunked(&mut buf_stream, 1024);
+        body_decoder.set_max_chunk_size(4);
+        let e = body_decoder.read_to_end(&mut buf).await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        let content = b"ffffffffffffffff\r\nbody";
+        let mut buf_stream = BufReader::new(content.as_slice());
+        let mut body_deocder = ChunkedDataDecodeReader::new(&mut buf_stream, 1024);
+        let e = body_deocder.read_to_end(&mut buf).await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
 }
@@ -0,0 +1,355 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use tokio::io::AsyncWrite;
+
+use g3_types::net::HttpHeaderMap;
+
+/// An `AsyncWrite` adapter that encodes all data written into it as a chunked body
+///
+/// Each write call will be sent as a single chunk, unless it's smaller than the coalesce size,
+/// in which case the data will be buffered until enough data is collected, or on flush.
+/// Zero-length writes will be ignored. The last chunk, along with the trailer fields if set,
+/// will be sent on shutdown.
+pub struct ChunkedWriteAdapter<W> {
+    inner: W,
+    coalesce_size: usize,
+    pending: Vec<u8>,
+    frame: Vec<u8>,
+    frame_offset: usize,
+    trailers: Option<HttpHeaderMap>,
+    terminated: bool,
+}
+
+impl<W> ChunkedWriteAdapter<W> {
+    pub fn new(inner: W) -> Self {
+        ChunkedWriteAdapter::with_coalesce_size(inner, 0)
+    }
+
+    /// Create the adapter that buffers writes smaller than `coalesce_size`
+    pub fn with_coalesce_size(inner: W, coalesce_size: usize) -> Self {
+        ChunkedWriteAdapter {
+            inner,
+            coalesce_size,
+            pending: Vec::with_capacity(coalesce_size),
+            frame: Vec::with_capacity(16),
+            frame_offset: 0,
+            trailers: None,
+            terminated: false,
+        }
+    }
+
+    pub fn with_trailers(mut self, trailers: HttpHeaderMap) -> Self {
+        self.trailers = Some(trailers);
+        self
+    }
+
+    /// Set the trailer fields, which should be called before shutdown
+    pub fn set_trailers(&mut self, trailers: HttpHeaderMap) {
+        self.trailers = Some(trailers);
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Get the inner writer, the buffered data that is not sent yet will be lost
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn stage_chunk(&mut self, data: &[u8]) {
+        let chunk_size = self.pending.len() + data.len();
+        let _ = write!(&mut self.frame, "{chunk_size:x}\r\n");
+        self.frame.extend_from_slice(&self.pending);
+        self.pending.clear();
+        self.frame.extend_from_slice(data);
+        self.frame.extend_from_slice(b"\r\n");
+    }
+
+    fn stage_end(&mut self) {
+        if !self.pending.is_empty() {
+            self.stage_chunk(&[]);
+        }
+        self.frame.extend_from_slice(b"0\r\n");
+        if let Some(trailers) = self.trailers.take() {
+            trailers.for_each(|name, value| value.write_to_buf(name, &mut self.frame));
+        }
+        self.frame.extend_from_slice(b"\r\n");
+        self.terminated = true;
+    }
+}
+
+impl<W> ChunkedWriteAdapter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Send out the staged frame data, the partial write will be resumed in the next call
+    fn poll_write_frame(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.frame_offset < self.frame.len() {
+            let nw =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.frame[self.frame_offset..]))?;
+            if nw == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "write zero byte into writer",
+                )));
+            }
+            self.frame_offset += nw;
+        }
+        self.frame.clear();
+        self.frame_offset = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W> AsyncWrite for ChunkedWriteAdapter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let me = &mut *self;
+
+        if me.terminated {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the chunked body has been ended",
+            )));
+        }
+        ready!(me.poll_write_frame(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        if me.pending.len() + buf.len() < me.coalesce_size {
+            me.pending.extend_from_slice(buf);
+            return Poll::Ready(Ok(buf.len()));
+        }
+
+        me.stage_chunk(buf);
+        // the data has been taken, so only the error matters here
+        if let Poll::Ready(Err(e)) = me.poll_write_frame(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let me = &mut *self;
+
+        ready!(me.poll_write_frame(cx))?;
+        if !me.pending.is_empty() {
+            me.stage_chunk(&[]);
+            ready!(me.poll_write_frame(cx))?;
+        }
+        Pin::new(&mut me.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let me = &mut *self;
+
+        ready!(me.poll_write_frame(cx))?;
+        if !me.terminated {
+            me.stage_end();
+            ready!(me.poll_write_frame(cx))?;
+        }
+        Pin::new(&mut me.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use g3_types::net::HttpHeaderValue;
+    use http::HeaderName;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+
+    use crate::{ChunkedDataDecodeReader, TrailerReader};
+
+    /// A writer which accepts at most `max_write` bytes in each call,
+    /// and returns pending randomly if `random_pending` is set
+    struct LimitedWriter {
+        max_write: usize,
+        random_pending: bool,
+        data: Vec<u8>,
+    }
+
+    impl LimitedWriter {
+        fn new(max_write: usize, random_pending: bool) -> Self {
+            LimitedWriter {
+                max_write,
+                random_pending,
+                data: Vec::new(),
+            }
+        }
+    }
+
+    impl AsyncWrite for LimitedWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            if self.random_pending && fastrand::bool() {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let len = buf.len().min(self.max_write);
+            self.data.extend_from_slice(&buf[..len]);
+            Poll::Ready(Ok(len))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    async fn decode(data: &[u8]) -> (Vec<u8>, HttpHeaderMap) {
+        let mut reader = BufReader::new(data);
+        let mut decoder = ChunkedDataDecodeReader::new(&mut reader, 1024);
+        let mut body = Vec::new();
+        decoder.read_to_end(&mut body).await.unwrap();
+        assert!(decoder.finished());
+        let trailers = TrailerReader::new(&mut reader, 1024).await.unwrap();
+        assert!(reader.buffer().is_empty());
+        (body, trailers)
+    }
+
+    #[tokio::test]
+    async fn chunk_per_write() {
+        let mut adapter = ChunkedWriteAdapter::new(Vec::new());
+        adapter.write_all(b"test\n").await.unwrap();
+        adapter.write_all(b"body").await.unwrap();
+        assert_eq!(adapter.get_ref(), b"5\r\ntest\n\r\n4\r\nbody\r\n");
+        adapter.shutdown().await.unwrap();
+        assert_eq!(adapter.get_ref(), b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\n\r\n");
+        assert!(adapter.write(b"more").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn coalesce_small_write() {
+        let mut adapter = ChunkedWriteAdapter::with_coalesce_size(Vec::new(), 8);
+        adapter.write_all(b"ab").await.unwrap();
+        adapter.write_all(b"cd").await.unwrap();
+        assert!(adapter.get_ref().is_empty());
+        adapter.flush().await.unwrap();
+        assert_eq!(adapter.get_ref(), b"4\r\nabcd\r\n");
+
+        adapter.write_all(b"ab").await.unwrap();
+        adapter.write_all(b"cdefgh").await.unwrap();
+        assert_eq!(adapter.get_ref(), b"4\r\nabcd\r\n8\r\nabcdefgh\r\n");
+        adapter.write_all(b"ij").await.unwrap();
+        adapter.shutdown().await.unwrap();
+        assert_eq!(
+            adapter.get_ref(),
+            b"4\r\nabcd\r\n8\r\nabcdefgh\r\n2\r\nij\r\n0\r\n\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn zero_length_write() {
+        let mut adapter = ChunkedWriteAdapter::new(Vec::new());
+        assert_eq!(adapter.write(b"").await.unwrap(), 0);
+        adapter.flush().await.unwrap();
+        assert!(adapter.get_ref().is_empty());
+        adapter.write_all(b"body").await.unwrap();
+        assert_eq!(adapter.write(b"").await.unwrap(), 0);
+        adapter.shutdown().await.unwrap();
+        assert_eq!(adapter.get_ref(), b"4\r\nbody\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn trailers() {
+        let mut trailers = HttpHeaderMap::default();
+        trailers.insert(
+            HeaderName::from_static("x-checksum"),
+            HttpHeaderValue::from_static("abc"),
+        );
+        let mut adapter = ChunkedWriteAdapter::new(Vec::new()).with_trailers(trailers.clone());
+        adapter.write_all(b"body").await.unwrap();
+        adapter.shutdown().await.unwrap();
+        assert_eq!(
+            adapter.get_ref(),
+            b"4\r\nbody\r\n0\r\nx-checksum: abc\r\n\r\n"
+        );
+
+        let mut adapter = ChunkedWriteAdapter::new(Vec::new());
+        adapter.set_trailers(trailers);
+        adapter.shutdown().await.unwrap();
+        assert_eq!(adapter.get_ref(), b"0\r\nx-checksum: abc\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn partial_write() {
+        for max_write in [1, 2, 3, 5, 64] {
+            let writer = LimitedWriter::new(max_write, true);
+            let mut adapter = ChunkedWriteAdapter::new(writer);
+            adapter.write_all(b"test\n").await.unwrap();
+            adapter.write_all(&[b'a'; 300]).await.unwrap();
+            adapter.shutdown().await.unwrap();
+
+            let (body, trailers) = decode(&adapter.get_ref().data).await;
+            assert_eq!(&body[..5], b"test\n");
+            assert_eq!(&body[5..], &[b'a'; 300]);
+            assert!(trailers.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn random_writes() {
+        for _ in 0..64 {
+            let writer = LimitedWriter::new(fastrand::usize(1..256), fastrand::bool());
+            let mut adapter =
+                ChunkedWriteAdapter::with_coalesce_size(writer, fastrand::usize(0..128));
+            let mut trailers = HttpHeaderMap::default();
+            if fastrand::bool() {
+                trailers.insert(
+                    HeaderName::from_static("x-trailer"),
+                    HttpHeaderValue::from_static("value"),
+                );
+                adapter.set_trailers(trailers.clone());
+            }
+
+            let mut expected = Vec::new();
+            for _ in 0..fastrand::usize(0..32) {
+                let mut data = vec![0u8; fastrand::usize(0..512)];
+                fastrand::fill(&mut data);
+                let nw = adapter.write(&data).await.unwrap();
+                assert_eq!(nw, data.len());
+                expected.extend_from_slice(&data);
+                if fastrand::u8(..) < 32 {
+                    adapter.flush().await.unwrap();
+                }
+            }
+            adapter.shutdown().await.unwrap();
+
+            let (body, decoded_trailers) = decode(&adapter.get_ref().data).await;
+            assert_eq!(body, expected);
+            assert_eq!(
+                decoded_trailers.get("x-trailer").map(|v| v.to_str()),
+                trailers.get("x-trailer").map(|v| v.to_str())
+            );
+        }
+    }
+}
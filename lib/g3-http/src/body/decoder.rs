@@ -11,7 +11,10 @@ use tokio::io::{AsyncBufRead, AsyncRead, ReadBuf};
 
 use g3_types::net::HttpHeaderMap;
 
-use crate::{ChunkedDataDecodeReader, HttpBodyType, TrailerReadError, TrailerReader};
+use crate::{
+    ChunkedDataDecodeReader, HttpBodyType, HttpChunkExtensionPolicy, TrailerReadError,
+    TrailerReader,
+};
 
 enum HttpBodyDecodeState<'a, R> {
     ReadUntilEnd(&'a mut R),
@@ -64,6 +67,37 @@ where
         ))
     }
 
+    pub fn new_chunked_with_extension_policy(
+        stream: &'a mut R,
+        body_line_max_size: usize,
+        extension_policy: HttpChunkExtensionPolicy,
+    ) -> Self {
+        HttpBodyDecodeReader::with_state(HttpBodyDecodeState::Chunked(
+            ChunkedDataDecodeReader::new_with_extension_policy(
+                stream,
+                body_line_max_size,
+                extension_policy,
+            ),
+        ))
+    }
+
+    /// Set whether to validate the chunked framing strictly as defined in RFC 9112, default to false
+    ///
+    /// Only the chunk size lines and chunk data endings will be checked, the trailer fields
+    /// will be read by `trailer()`.
+    pub fn set_strict_chunked(&mut self, strict: bool) {
+        if let Some(HttpBodyDecodeState::Chunked(decoder)) = &mut self.decode_state {
+            decoder.set_strict_chunked(strict);
+        }
+    }
+
+    /// Set the max size of a single chunk, default to 1GiB
+    pub fn set_max_chunk_size(&mut self, max_size: u64) {
+        if let Some(HttpBodyDecodeState::Chunked(decoder)) = &mut self.decode_state {
+            decoder.set_max_chunk_size(max_size);
+        }
+    }
+
     pub async fn trailer(
         &mut self,
         max_size: usize,
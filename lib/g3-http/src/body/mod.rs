@@ -3,6 +3,8 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::str::FromStr;
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum HttpBodyType {
     ContentLength(u64),
@@ -10,17 +12,51 @@ pub enum HttpBodyType {
     ReadUntilEnd,
 }
 
+/// The way to handle the extensions in chunk size lines
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HttpChunkExtensionPolicy {
+    /// Remove the extensions from the chunk size lines
+    Drop,
+    /// Keep the extensions as is
+    #[default]
+    Forward,
+    /// Return error if any extension is found
+    Reject,
+}
+
+impl FromStr for HttpChunkExtensionPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "drop" => Ok(HttpChunkExtensionPolicy::Drop),
+            "forward" => Ok(HttpChunkExtensionPolicy::Forward),
+            "reject" => Ok(HttpChunkExtensionPolicy::Reject),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The default max size of a single chunk in chunked body
+const DEFAULT_MAX_CHUNK_SIZE: u64 = 1 << 30; // 1GiB
+
 mod reader;
-pub use reader::HttpBodyReader;
+pub use reader::{HttpBodyReader, HttpBodyReaderOwned};
 
 mod decoder;
 pub use decoder::HttpBodyDecodeReader;
 
 mod body_to_chunked;
-pub use body_to_chunked::H1BodyToChunkedTransfer;
+pub use body_to_chunked::{H1BodyToChunkedTransfer, H1BodyToChunkedTransferOwned};
+
+mod previewable;
+pub use previewable::PreviewableBodyTransfer;
 
 mod stream_to_chunked;
-pub use stream_to_chunked::StreamToChunkedTransfer;
+pub use stream_to_chunked::{StreamToChunkedTransfer, StreamToChunkedTransferOwned};
+
+mod chunked_writer;
+pub use chunked_writer::ChunkedWriteAdapter;
 
 mod chunked_decoder;
 pub use chunked_decoder::ChunkedDataDecodeReader;
@@ -0,0 +1,700 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+
+use g3_io_ext::{StreamCopyConfig, StreamCopyError};
+
+use super::{ChunkedDataDecodeReader, H1BodyToChunkedTransfer, HttpBodyReader, HttpBodyType};
+
+enum PreviewReader<'a, R> {
+    /// no preview data will be read
+    Disabled(&'a mut R),
+    Plain(HttpBodyReader<'a, R>),
+    Chunked(ChunkedDataDecodeReader<'a, R>),
+}
+
+impl<R> PreviewReader<'_, R>
+where
+    R: AsyncBufRead + Unpin,
+{
+    fn finished(&self) -> bool {
+        match self {
+            PreviewReader::Disabled(_) => false,
+            PreviewReader::Plain(reader) => reader.finished(),
+            PreviewReader::Chunked(decoder) => decoder.finished(),
+        }
+    }
+
+    /// Get the end offset of the preview buffer, which will be smaller than the limit
+    /// if the body or the current chunk is known to be smaller than the preview window
+    fn preview_end(&self, offset: usize, limit: usize) -> usize {
+        let remaining = match self {
+            PreviewReader::Plain(reader) => reader.remaining_hint(),
+            PreviewReader::Chunked(decoder) => decoder.left_chunk_size().filter(|n| *n > 0),
+            PreviewReader::Disabled(_) => None,
+        };
+        match remaining.and_then(|n| usize::try_from(n).ok()) {
+            Some(n) => limit.min(offset.saturating_add(n)),
+            None => limit,
+        }
+    }
+}
+
+struct ResumeTransfer<'a, R, W> {
+    reader: PreviewReader<'a, R>,
+    writer: &'a mut W,
+    /// the chunk encoded preview data that should be sent before the left body
+    preview_chunk: Vec<u8>,
+    offset: usize,
+}
+
+enum PreviewableTransferState<'a, R, W> {
+    Preview(PreviewReader<'a, R>),
+    Resume(ResumeTransfer<'a, R, W>),
+    Transfer(H1BodyToChunkedTransfer<'a, R, W>),
+    End,
+}
+
+/// Transfer a HTTP/1.x body as chunked with a preview stage.
+///
+/// The preview data is read and cached first, the transfer can then be resumed to send the
+/// left body data, and the chunk boundary will be handled internally.
+pub struct PreviewableBodyTransfer<'a, R, W> {
+    body_type: HttpBodyType,
+    body_line_max_len: usize,
+    copy_config: StreamCopyConfig,
+    preview_limit: usize,
+    preview: Vec<u8>,
+    /// the end offset of the preview data, the cached data after it will be sent when resumed
+    preview_end: Option<usize>,
+    state: PreviewableTransferState<'a, R, W>,
+    active: bool,
+}
+
+impl<'a, R, W> PreviewableBodyTransfer<'a, R, W>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(
+        reader: &'a mut R,
+        body_type: HttpBodyType,
+        body_line_max_len: usize,
+        preview_limit: usize,
+        copy_config: StreamCopyConfig,
+    ) -> Self {
+        let preview_reader = match body_type {
+            HttpBodyType::Chunked => {
+                PreviewReader::Chunked(ChunkedDataDecodeReader::new(reader, body_line_max_len))
+            }
+            _ => PreviewReader::Plain(HttpBodyReader::new(reader, body_type, body_line_max_len)),
+        };
+        let preview_size = preview_reader.preview_end(0, preview_limit);
+        PreviewableBodyTransfer {
+            body_type,
+            body_line_max_len,
+            copy_config,
+            preview_limit,
+            preview: Vec::with_capacity(preview_size),
+            preview_end: None,
+            state: PreviewableTransferState::Preview(preview_reader),
+            active: false,
+        }
+    }
+
+    /// Create a transfer without the preview stage, the body will be sent as is if resumed
+    pub fn without_preview(
+        reader: &'a mut R,
+        body_type: HttpBodyType,
+        body_line_max_len: usize,
+        copy_config: StreamCopyConfig,
+    ) -> Self {
+        PreviewableBodyTransfer {
+            body_type,
+            body_line_max_len,
+            copy_config,
+            preview_limit: 0,
+            preview: Vec::new(),
+            preview_end: None,
+            state: PreviewableTransferState::Preview(PreviewReader::Disabled(reader)),
+            active: false,
+        }
+    }
+
+    /// Read more preview data into the preview buffer.
+    ///
+    /// Return `Ok(0)` if the preview limit has been reached or if the body has ended.
+    /// It's always safe to drop a pending read.
+    pub fn poll_read_preview(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let PreviewableTransferState::Preview(reader) = &mut self.state else {
+            return Poll::Ready(Ok(0));
+        };
+        let offset = self.preview.len();
+        if offset >= self.preview_limit || self.preview_end.is_some() || reader.finished() {
+            return Poll::Ready(Ok(0));
+        }
+        if let PreviewReader::Chunked(decoder) = reader {
+            // move on to the start of the next chunk data, and stop the read at the end of it,
+            // so the original chunk boundary will be kept when resumed with the preview data
+            let mut buf = ReadBuf::new(&mut []);
+            ready!(Pin::new(&mut *decoder).poll_read(cx, &mut buf))?;
+            if decoder.finished() {
+                return Poll::Ready(Ok(0));
+            }
+        }
+
+        let preview_end = reader.preview_end(offset, self.preview_limit);
+        self.preview.resize(preview_end, 0);
+        let mut buf = ReadBuf::new(&mut self.preview[offset..]);
+        let r = match reader {
+            PreviewReader::Disabled(_) => Poll::Ready(Ok(())),
+            PreviewReader::Plain(reader) => Pin::new(reader).poll_read(cx, &mut buf),
+            PreviewReader::Chunked(decoder) => Pin::new(decoder).poll_read(cx, &mut buf),
+        };
+        let nr = buf.filled().len();
+        self.preview.truncate(offset + nr);
+        ready!(r)?;
+        Poll::Ready(Ok(nr))
+    }
+
+    pub async fn read_preview(&mut self) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_read_preview(cx)).await
+    }
+
+    pub fn preview_data(&self) -> &[u8] {
+        match self.preview_end {
+            Some(end) => &self.preview[..end],
+            None => &self.preview,
+        }
+    }
+
+    /// Get the size of the preview data, which has been or will be sent in the preview stage
+    pub fn preview_size(&self) -> usize {
+        self.preview_data().len()
+    }
+
+    /// End the preview stage early at `end` of the data that has been read.
+    ///
+    /// No more preview data will be read, and the data after `end` will be sent first when resumed.
+    pub fn set_preview_end(&mut self, end: usize) {
+        self.preview_end = Some(end.min(self.preview.len()));
+    }
+
+    /// Check if all body data has been read in the preview stage.
+    ///
+    /// The trailer fields of a chunked body may be still left in the reader.
+    pub fn preview_finished(&self) -> bool {
+        match &self.state {
+            PreviewableTransferState::Preview(reader) => reader.finished(),
+            _ => false,
+        }
+    }
+
+    /// Get the preview data if all body data has been read in the preview stage.
+    ///
+    /// For chunked body, a reader for the left trailer fields will also be returned.
+    /// The transfer will be returned boxed in the error if not all body data has been read.
+    #[allow(clippy::type_complexity)]
+    pub fn into_whole_body(self) -> Result<(Vec<u8>, Option<HttpBodyReader<'a, R>>), Box<Self>> {
+        if !self.preview_finished() {
+            return Err(Box::new(self));
+        }
+        let PreviewableTransferState::Preview(reader) = self.state else {
+            unreachable!()
+        };
+        match reader {
+            PreviewReader::Disabled(_) | PreviewReader::Plain(_) => Ok((self.preview, None)),
+            PreviewReader::Chunked(decoder) => {
+                let trailer_reader =
+                    HttpBodyReader::new_trailer(decoder.into_reader(), self.body_line_max_len);
+                Ok((self.preview, Some(trailer_reader)))
+            }
+        }
+    }
+
+    /// Get the preview data and the reader for the left data of a non-chunked body
+    ///
+    /// The transfer will be returned boxed in the error if the body is chunked.
+    pub fn into_plain_body(self) -> Result<(Vec<u8>, HttpBodyReader<'a, R>), Box<Self>> {
+        if !matches!(
+            self.state,
+            PreviewableTransferState::Preview(PreviewReader::Plain(_))
+        ) {
+            return Err(Box::new(self));
+        }
+        let PreviewableTransferState::Preview(PreviewReader::Plain(reader)) = self.state else {
+            unreachable!()
+        };
+        Ok((self.preview, reader))
+    }
+
+    /// Resume to send the left body data after the preview data has been sent.
+    ///
+    /// The writer may be borrowed for a shorter lifetime than the reader.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transfer has already been resumed.
+    pub fn resume<'w>(self, writer: &'w mut W) -> PreviewableBodyTransfer<'w, R, W>
+    where
+        'a: 'w,
+    {
+        let PreviewableTransferState::Preview(reader) = self.state else {
+            panic!("the body transfer has already been resumed");
+        };
+        let preview_chunk = match self.preview_end {
+            Some(end) => encode_chunk(&self.preview[end..]),
+            None => Vec::new(),
+        };
+        PreviewableBodyTransfer {
+            body_type: self.body_type,
+            body_line_max_len: self.body_line_max_len,
+            copy_config: self.copy_config,
+            preview_limit: self.preview_limit,
+            preview: self.preview,
+            preview_end: self.preview_end,
+            state: PreviewableTransferState::Resume(ResumeTransfer {
+                reader,
+                writer,
+                preview_chunk,
+                offset: 0,
+            }),
+            active: false,
+        }
+    }
+
+    /// Resume to send all body data, including the preview data, to a new writer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transfer has already been resumed.
+    pub fn resume_with_preview<'w, W2>(
+        self,
+        writer: &'w mut W2,
+    ) -> PreviewableBodyTransfer<'w, R, W2>
+    where
+        'a: 'w,
+        W2: AsyncWrite + Unpin,
+    {
+        let PreviewableTransferState::Preview(reader) = self.state else {
+            panic!("the body transfer has already been resumed");
+        };
+        let preview_chunk = encode_chunk(&self.preview);
+        PreviewableBodyTransfer {
+            body_type: self.body_type,
+            body_line_max_len: self.body_line_max_len,
+            copy_config: self.copy_config,
+            preview_limit: self.preview_limit,
+            preview: self.preview,
+            preview_end: None,
+            state: PreviewableTransferState::Resume(ResumeTransfer {
+                reader,
+                writer,
+                preview_chunk,
+                offset: 0,
+            }),
+            active: false,
+        }
+    }
+
+    pub fn resumed(&self) -> bool {
+        !matches!(self.state, PreviewableTransferState::Preview(_))
+    }
+
+    pub fn finished(&self) -> bool {
+        match &self.state {
+            PreviewableTransferState::Transfer(transfer) => transfer.finished(),
+            _ => false,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        match &self.state {
+            PreviewableTransferState::Transfer(transfer) => transfer.is_idle(),
+            _ => !self.active,
+        }
+    }
+
+    pub fn no_cached_data(&self) -> bool {
+        match &self.state {
+            PreviewableTransferState::Resume(resume) => resume.offset >= resume.preview_chunk.len(),
+            PreviewableTransferState::Transfer(transfer) => transfer.no_cached_data(),
+            PreviewableTransferState::Preview(_) | PreviewableTransferState::End => true,
+        }
+    }
+
+    pub fn reset_active(&mut self) {
+        if let PreviewableTransferState::Transfer(transfer) = &mut self.state {
+            transfer.reset_active();
+        }
+        self.active = false;
+    }
+
+    fn poll_resume(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), StreamCopyError>> {
+        let PreviewableTransferState::Resume(resume) = &mut self.state else {
+            unreachable!()
+        };
+
+        while resume.offset < resume.preview_chunk.len() {
+            let buf = &resume.preview_chunk[resume.offset..];
+            let nw = ready!(Pin::new(&mut *resume.writer).poll_write(cx, buf))
+                .map_err(StreamCopyError::WriteFailed)?;
+            resume.offset += nw;
+            self.active = true;
+        }
+
+        if let PreviewReader::Chunked(decoder) = &mut resume.reader {
+            // move on to the start of the next chunk data, or to the trailer fields,
+            // as the preview may end at any position of the chunked encoding
+            let mut buf = ReadBuf::new(&mut []);
+            ready!(Pin::new(&mut *decoder).poll_read(cx, &mut buf))
+                .map_err(StreamCopyError::ReadFailed)?;
+        }
+
+        let old_state = std::mem::replace(&mut self.state, PreviewableTransferState::End);
+        let PreviewableTransferState::Resume(resume) = old_state else {
+            unreachable!()
+        };
+        let transfer = match resume.reader {
+            PreviewReader::Disabled(reader) => H1BodyToChunkedTransfer::new(
+                reader,
+                resume.writer,
+                self.body_type,
+                self.body_line_max_len,
+                self.copy_config,
+            ),
+            PreviewReader::Plain(reader) => {
+                let reader = reader.into_reader();
+                match self.body_type {
+                    HttpBodyType::ContentLength(len) => H1BodyToChunkedTransfer::new_fixed_length(
+                        reader,
+                        resume.writer,
+                        len - self.preview.len() as u64,
+                        self.copy_config,
+                    ),
+                    _ => H1BodyToChunkedTransfer::new_read_until_end(
+                        reader,
+                        resume.writer,
+                        self.copy_config,
+                    ),
+                }
+            }
+            PreviewReader::Chunked(decoder) => {
+                let left_chunk_size = decoder.left_chunk_size().unwrap_or_default();
+                if decoder.finished() {
+                    H1BodyToChunkedTransfer::new_chunked_trailer(
+                        decoder.into_reader(),
+                        resume.writer,
+                        self.body_line_max_len,
+                        self.copy_config,
+                    )
+                } else {
+                    H1BodyToChunkedTransfer::new_chunked_after_preview(
+                        decoder.into_reader(),
+                        resume.writer,
+                        left_chunk_size,
+                        self.body_line_max_len,
+                        self.copy_config,
+                    )
+                }
+            }
+        };
+        self.state = PreviewableTransferState::Transfer(transfer);
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn encode_chunk(data: &[u8]) -> Vec<u8> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    let mut buf = format!("{:x}\r\n", data.len()).into_bytes();
+    buf.extend_from_slice(data);
+    buf.extend_from_slice(b"\r\n");
+    buf
+}
+
+impl<R, W> Future for PreviewableBodyTransfer<'_, R, W>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    type Output = Result<(), StreamCopyError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.state {
+            PreviewableTransferState::Preview(_) => Poll::Ready(Err(StreamCopyError::ReadFailed(
+                io::Error::other("body transfer polled before resumed"),
+            ))),
+            PreviewableTransferState::Resume(_) => {
+                ready!(self.poll_resume(cx))?;
+                self.poll(cx)
+            }
+            PreviewableTransferState::Transfer(transfer) => Pin::new(transfer).poll(cx),
+            PreviewableTransferState::End => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, BufReader};
+
+    async fn preview_and_resume(
+        content: &[u8],
+        body_type: HttpBodyType,
+        preview_limit: usize,
+    ) -> (Vec<u8>, Vec<u8>) {
+        let stream = tokio_test::io::Builder::new().read(content).build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut write_buf = Vec::new();
+
+        let mut body_transfer = PreviewableBodyTransfer::new(
+            &mut buf_stream,
+            body_type,
+            1024,
+            preview_limit,
+            Default::default(),
+        );
+        while body_transfer.read_preview().await.unwrap() > 0 {}
+        let preview = body_transfer.preview_data().to_vec();
+        assert_eq!(body_transfer.preview_size(), preview.len());
+        assert!(!body_transfer.resumed());
+
+        let mut body_transfer = body_transfer.resume(&mut write_buf);
+        assert!(body_transfer.resumed());
+        (&mut body_transfer).await.unwrap();
+        assert!(body_transfer.finished());
+        (preview, write_buf)
+    }
+
+    #[tokio::test]
+    async fn content_length_preview_whole() {
+        let (preview, left) =
+            preview_and_resume(b"test bodyXXX", HttpBodyType::ContentLength(9), 9).await;
+        assert_eq!(preview, b"test body");
+        assert_eq!(left, b"0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn content_length_preview_presized() {
+        let stream = tokio_test::io::Builder::new()
+            .read(b"test ")
+            .read(b"body")
+            .build();
+        let mut buf_stream = BufReader::new(stream);
+
+        let mut body_transfer = PreviewableBodyTransfer::<_, Vec<u8>>::new(
+            &mut buf_stream,
+            HttpBodyType::ContentLength(9),
+            1024,
+            1024,
+            Default::default(),
+        );
+        assert_eq!(body_transfer.preview.capacity(), 9);
+        assert_eq!(body_transfer.read_preview().await.unwrap(), 5);
+        assert_eq!(body_transfer.read_preview().await.unwrap(), 4);
+        assert_eq!(body_transfer.read_preview().await.unwrap(), 0);
+        assert_eq!(body_transfer.preview.capacity(), 9);
+        assert!(body_transfer.preview_finished());
+        assert_eq!(body_transfer.preview_data(), b"test body");
+    }
+
+    #[tokio::test]
+    async fn read_until_end_zero_preview() {
+        let (preview, left) = preview_and_resume(b"test body", HttpBodyType::ReadUntilEnd, 0).await;
+        assert!(preview.is_empty());
+        assert_eq!(left, b"9\r\ntest body\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn chunked_preview_whole() {
+        let content = b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\nA: B\r\n\r\nXXX";
+        let (preview, left) = preview_and_resume(content, HttpBodyType::Chunked, 9).await;
+        assert_eq!(preview, b"test\nbody");
+        assert_eq!(left, b"0\r\nA: B\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn chunked_preview_chunk_boundary() {
+        let content = b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\n\r\nXXX";
+        let (preview, left) = preview_and_resume(content, HttpBodyType::Chunked, 5).await;
+        assert_eq!(preview, b"test\n");
+        assert_eq!(left, b"4\r\nbody\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn chunked_preview_chunk_boundary_split() {
+        // the chunk data end is not received along with the chunk data
+        let stream = tokio_test::io::Builder::new()
+            .read(b"5\r\ntest\n")
+            .wait(Duration::from_millis(1))
+            .read(b"\r\n4\r\nbody\r\n0\r\n\r\n")
+            .build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut write_buf = Vec::new();
+
+        let mut body_transfer = PreviewableBodyTransfer::new(
+            &mut buf_stream,
+            HttpBodyType::Chunked,
+            1024,
+            5,
+            Default::default(),
+        );
+        assert_eq!(body_transfer.read_preview().await.unwrap(), 5);
+        assert_eq!(body_transfer.read_preview().await.unwrap(), 0);
+        assert!(!body_transfer.preview_finished());
+
+        let mut body_transfer = body_transfer.resume(&mut write_buf);
+        (&mut body_transfer).await.unwrap();
+        assert!(body_transfer.finished());
+        assert_eq!(write_buf, b"4\r\nbody\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn chunked_preview_mid_chunk() {
+        let content = b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\n\r\nXXX";
+        let (preview, left) = preview_and_resume(content, HttpBodyType::Chunked, 7).await;
+        assert_eq!(preview, b"test\nbo");
+        assert_eq!(left, b"2\r\ndy\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn chunked_zero_preview() {
+        let content = b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\n\r\nXXX";
+        let (preview, left) = preview_and_resume(content, HttpBodyType::Chunked, 0).await;
+        assert!(preview.is_empty());
+        assert_eq!(left, b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn chunked_whole_body() {
+        let content = b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\nA: B\r\n\r\nXXX";
+        let stream = tokio_test::io::Builder::new().read(content).build();
+        let mut buf_stream = BufReader::new(stream);
+
+        let mut body_transfer = PreviewableBodyTransfer::<_, Vec<u8>>::new(
+            &mut buf_stream,
+            HttpBodyType::Chunked,
+            1024,
+            16,
+            Default::default(),
+        );
+        while body_transfer.read_preview().await.unwrap() > 0 {}
+        assert!(body_transfer.preview_finished());
+        let Ok((body, Some(mut trailer_reader))) = body_transfer.into_whole_body() else {
+            panic!("no whole body returned");
+        };
+        assert_eq!(body, b"test\nbody");
+
+        let mut trailer = Vec::new();
+        trailer_reader.read_to_end(&mut trailer).await.unwrap();
+        assert_eq!(trailer, b"A: B\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn resume_with_preview() {
+        let content = b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\n\r\nXXX";
+        let stream = tokio_test::io::Builder::new().read(content).build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut write_buf = Vec::new();
+
+        let mut body_transfer = PreviewableBodyTransfer::<_, Vec<u8>>::new(
+            &mut buf_stream,
+            HttpBodyType::Chunked,
+            1024,
+            7,
+            Default::default(),
+        );
+        while body_transfer.read_preview().await.unwrap() > 0 {}
+
+        let mut body_transfer = body_transfer.resume_with_preview(&mut write_buf);
+        (&mut body_transfer).await.unwrap();
+        assert!(body_transfer.finished());
+        assert_eq!(write_buf, b"7\r\ntest\nbo\r\n2\r\ndy\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn resume_with_zero_preview() {
+        let content = b"5\r\ntest\n\r\n0\r\n\r\n";
+        let stream = tokio_test::io::Builder::new().read(content).build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut write_buf = Vec::new();
+
+        let body_transfer = PreviewableBodyTransfer::<_, Vec<u8>>::new(
+            &mut buf_stream,
+            HttpBodyType::Chunked,
+            1024,
+            0,
+            Default::default(),
+        );
+        let mut body_transfer = body_transfer.resume_with_preview(&mut write_buf);
+        (&mut body_transfer).await.unwrap();
+        assert_eq!(write_buf, b"5\r\ntest\n\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn content_length_preview_end() {
+        let stream = tokio_test::io::Builder::new()
+            .read(b"test body")
+            .read(b" left")
+            .build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut write_buf = Vec::new();
+
+        let mut body_transfer = PreviewableBodyTransfer::new(
+            &mut buf_stream,
+            HttpBodyType::ContentLength(14),
+            1024,
+            1024,
+            Default::default(),
+        );
+        assert_eq!(body_transfer.read_preview().await.unwrap(), 9);
+        body_transfer.set_preview_end(5);
+        assert_eq!(body_transfer.read_preview().await.unwrap(), 0);
+        assert_eq!(body_transfer.preview_data(), b"test ");
+        assert_eq!(body_transfer.preview_size(), 5);
+        assert!(!body_transfer.preview_finished());
+
+        let mut body_transfer = body_transfer.resume(&mut write_buf);
+        (&mut body_transfer).await.unwrap();
+        assert!(body_transfer.finished());
+        assert_eq!(write_buf, b"4\r\nbody\r\n5\r\n left\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn chunked_preview_end_with_preview() {
+        let content = b"9\r\ntest body\r\n5\r\n left\r\n0\r\n\r\n";
+        let stream = tokio_test::io::Builder::new().read(content).build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut write_buf = Vec::new();
+
+        let mut body_transfer = PreviewableBodyTransfer::<_, Vec<u8>>::new(
+            &mut buf_stream,
+            HttpBodyType::Chunked,
+            1024,
+            1024,
+            Default::default(),
+        );
+        assert_eq!(body_transfer.read_preview().await.unwrap(), 9);
+        body_transfer.set_preview_end(4);
+        assert_eq!(body_transfer.preview_data(), b"test");
+
+        // all cached data should be sent if resumed with preview
+        let mut body_transfer = body_transfer.resume_with_preview(&mut write_buf);
+        (&mut body_transfer).await.unwrap();
+        assert!(body_transfer.finished());
+        assert_eq!(write_buf, b"9\r\ntest body\r\n5\r\n left\r\n0\r\n\r\n");
+    }
+}
@@ -3,15 +3,14 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::io;
+use std::io::{self, Write};
 use std::pin::Pin;
 use std::task::{Context, Poll, ready};
 
-use bytes::BufMut;
 use tokio::io::{AsyncBufRead, AsyncRead, ReadBuf};
 
-use super::HttpBodyType;
-use crate::HttpChunkedLine;
+use super::{DEFAULT_MAX_CHUNK_SIZE, HttpBodyType, HttpChunkExtensionPolicy};
+use crate::{HttpChunkedLine, HttpLineParseError};
 
 enum NextReadType {
     EndOfFile,
@@ -22,8 +21,15 @@ enum NextReadType {
     Trailer,
 }
 
-pub struct HttpBodyReader<'a, R> {
-    stream: &'a mut R,
+fn invalid_framing(e: HttpLineParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// HTTP body reader which owns the underlying stream.
+///
+/// The stream may also be a mutable reference, see [`HttpBodyReader`].
+pub struct HttpBodyReaderOwned<R> {
+    stream: R,
     body_type: HttpBodyType,
     next_read_type: NextReadType,
     body_line_max_len: usize,
@@ -31,131 +37,193 @@ pub struct HttpBodyReader<'a, R> {
     next_read_size: usize,
     left_total_size: u64,
 
-    chunk_size_line_cache: Vec<u8>,
-
-    trailer_line_length: usize,
-    trailer_last_char: u8,
+    extension_policy: HttpChunkExtensionPolicy,
+    strict_chunked: bool,
+    max_chunk_size: u64,
+    /// the chunk size line or trailer line that is being read
+    line_cache: Vec<u8>,
+    /// the canonical framing data that should be sent out before the next read
+    line_output: Vec<u8>,
+    line_output_offset: usize,
+    /// the error that should be returned on the next read, as data has been returned in this read
+    pending_error: Option<io::Error>,
 
     finished: bool,
     read_content_length: u64,
     current_chunk_size: u64,
 }
 
-impl<'a, R> HttpBodyReader<'a, R>
+/// HTTP body reader which borrows the underlying stream
+pub type HttpBodyReader<'a, R> = HttpBodyReaderOwned<&'a mut R>;
+
+impl<R> HttpBodyReaderOwned<R>
 where
     R: AsyncBufRead + Unpin,
 {
     const DEFAULT_LINE_SIZE: usize = 64;
 
-    pub fn new(stream: &'a mut R, body_type: HttpBodyType, body_line_max_len: usize) -> Self {
+    pub fn new(stream: R, body_type: HttpBodyType, body_line_max_len: usize) -> Self {
         match body_type {
-            HttpBodyType::ReadUntilEnd => HttpBodyReader::new_read_until_end(stream),
-            HttpBodyType::ContentLength(len) => HttpBodyReader::new_fixed_length(stream, len),
-            HttpBodyType::Chunked => HttpBodyReader::new_chunked(stream, body_line_max_len),
+            HttpBodyType::ReadUntilEnd => HttpBodyReaderOwned::new_read_until_end(stream),
+            HttpBodyType::ContentLength(len) => HttpBodyReaderOwned::new_fixed_length(stream, len),
+            HttpBodyType::Chunked => HttpBodyReaderOwned::new_chunked(stream, body_line_max_len),
         }
     }
 
-    pub fn new_read_until_end(stream: &'a mut R) -> Self {
-        let mut r = HttpBodyReader {
+    fn with_type(
+        stream: R,
+        body_type: HttpBodyType,
+        next_read_type: NextReadType,
+        body_line_max_len: usize,
+    ) -> Self {
+        let line_cache = match body_type {
+            HttpBodyType::Chunked => Vec::with_capacity(Self::DEFAULT_LINE_SIZE),
+            _ => Vec::new(),
+        };
+        HttpBodyReaderOwned {
             stream,
-            body_type: HttpBodyType::ReadUntilEnd,
-            next_read_type: NextReadType::UntilEnd,
-            body_line_max_len: 1024,
+            body_type,
+            next_read_type,
+            body_line_max_len,
             next_read_size: 0,
             left_total_size: 0,
-            chunk_size_line_cache: Vec::new(),
-            trailer_line_length: 0,
-            trailer_last_char: 0,
+            extension_policy: HttpChunkExtensionPolicy::default(),
+            strict_chunked: false,
+            max_chunk_size: DEFAULT_MAX_CHUNK_SIZE,
+            line_cache,
+            line_output: Vec::new(),
+            line_output_offset: 0,
+            pending_error: None,
             finished: false,
             read_content_length: 0,
             current_chunk_size: 0,
-        };
-        r.update_next_read_size();
-        r
+        }
     }
 
-    pub fn new_fixed_length(stream: &'a mut R, content_length: u64) -> Self {
-        let mut r = HttpBodyReader {
+    pub fn new_read_until_end(stream: R) -> Self {
+        HttpBodyReaderOwned::with_type(
             stream,
-            body_type: HttpBodyType::ContentLength(content_length),
-            next_read_type: NextReadType::FixedLength,
-            body_line_max_len: 1024,
-            next_read_size: 0,
-            left_total_size: content_length,
-            chunk_size_line_cache: Vec::new(),
-            trailer_line_length: 0,
-            trailer_last_char: 0,
-            finished: false,
-            read_content_length: 0,
-            current_chunk_size: 0,
-        };
+            HttpBodyType::ReadUntilEnd,
+            NextReadType::UntilEnd,
+            1024,
+        )
+    }
+
+    pub fn new_fixed_length(stream: R, content_length: u64) -> Self {
+        let mut r = HttpBodyReaderOwned::with_type(
+            stream,
+            HttpBodyType::ContentLength(content_length),
+            NextReadType::FixedLength,
+            1024,
+        );
+        r.left_total_size = content_length;
         r.update_next_read_size();
         r
     }
 
-    pub fn new_chunked(stream: &'a mut R, body_line_max_len: usize) -> Self {
-        let mut r = HttpBodyReader {
+    pub fn new_chunked(stream: R, body_line_max_len: usize) -> Self {
+        HttpBodyReaderOwned::new_chunked_with_extension_policy(
             stream,
-            body_type: HttpBodyType::Chunked,
-            next_read_type: NextReadType::ChunkSize,
             body_line_max_len,
-            next_read_size: 0,
-            left_total_size: 0,
-            chunk_size_line_cache: Vec::<u8>::with_capacity(Self::DEFAULT_LINE_SIZE),
-            trailer_line_length: 0,
-            trailer_last_char: 0,
-            finished: false,
-            read_content_length: 0,
-            current_chunk_size: 0,
-        };
-        r.update_next_read_size();
+            HttpChunkExtensionPolicy::default(),
+        )
+    }
+
+    pub fn new_chunked_with_extension_policy(
+        stream: R,
+        body_line_max_len: usize,
+        extension_policy: HttpChunkExtensionPolicy,
+    ) -> Self {
+        let mut r = HttpBodyReaderOwned::with_type(
+            stream,
+            HttpBodyType::Chunked,
+            NextReadType::ChunkSize,
+            body_line_max_len,
+        );
+        r.extension_policy = extension_policy;
         r
     }
 
-    pub fn new_trailer(stream: &'a mut R, body_line_max_len: usize) -> Self {
-        HttpBodyReader {
+    pub fn new_trailer(stream: R, body_line_max_len: usize) -> Self {
+        HttpBodyReaderOwned::with_type(
             stream,
-            body_type: HttpBodyType::Chunked,
-            next_read_type: NextReadType::Trailer,
+            HttpBodyType::Chunked,
+            NextReadType::Trailer,
             body_line_max_len,
-            next_read_size: 0,
-            left_total_size: 0,
-            chunk_size_line_cache: Vec::<u8>::with_capacity(Self::DEFAULT_LINE_SIZE),
-            trailer_line_length: 0,
-            trailer_last_char: 0,
-            finished: false,
-            read_content_length: 0,
-            current_chunk_size: 0,
-        }
+        )
     }
 
     pub fn new_chunked_after_preview(
-        stream: &'a mut R,
+        stream: R,
         body_line_max_len: usize,
         next_chunk_size: u64,
     ) -> Self {
-        let mut r = HttpBodyReader {
+        let mut r = HttpBodyReaderOwned::with_type(
             stream,
-            body_type: HttpBodyType::Chunked,
-            next_read_type: NextReadType::FixedLength,
+            HttpBodyType::Chunked,
+            NextReadType::FixedLength,
             body_line_max_len,
-            next_read_size: 0,
-            left_total_size: next_chunk_size,
-            chunk_size_line_cache: Vec::<u8>::with_capacity(Self::DEFAULT_LINE_SIZE),
-            trailer_line_length: 0,
-            trailer_last_char: 0,
-            finished: false,
-            read_content_length: 0,
-            current_chunk_size: next_chunk_size,
-        };
+        );
+        r.left_total_size = next_chunk_size;
+        r.current_chunk_size = next_chunk_size;
         r.update_next_read_size();
         r
     }
 
+    /// Set whether to validate the chunked framing strictly as defined in RFC 9112, default to false
+    ///
+    /// If strict, bare LF line endings, whitespace around chunk sizes, '+' prefixed or
+    /// overlong (> 16 hex digits) chunk sizes and missing final CRLF will be rejected.
+    /// If not, they will be accepted, and the output will be normalized to canonical CRLF framing.
+    pub fn set_strict_chunked(&mut self, strict: bool) {
+        self.strict_chunked = strict;
+    }
+
+    /// Set the max size of a single chunk, default to 1GiB
+    ///
+    /// A `ChunkTooLarge` framing error will be returned if the chunk size is larger than this.
+    pub fn set_max_chunk_size(&mut self, max_size: u64) {
+        self.max_chunk_size = max_size;
+    }
+
     pub fn finished(&self) -> bool {
         self.finished
     }
 
+    /// Get the number of bytes that can still be read out before the next framing boundary.
+    ///
+    /// For content-length body, this is the exact size of the left body, and will be 0 at the end.
+    /// For chunked body, this is the size of the left data in the current chunk, along with the
+    /// pending chunk size line, and will be None if not inside the chunk data.
+    /// For read-until-end body, this will always be None.
+    pub fn remaining_hint(&self) -> Option<u64> {
+        match self.next_read_type {
+            NextReadType::FixedLength => {
+                let pending_line = self.line_output.len() - self.line_output_offset;
+                Some(
+                    (pending_line as u64)
+                        .saturating_add(self.next_read_size as u64)
+                        .saturating_add(self.left_total_size),
+                )
+            }
+            NextReadType::EndOfFile => match self.body_type {
+                HttpBodyType::ContentLength(_) => Some(0),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.stream
+    }
+
+    #[inline]
+    pub fn into_reader(self) -> R {
+        self.stream
+    }
+
     fn update_next_read_size(&mut self) {
         const MAX_USIZE: usize = usize::MAX;
         debug_assert_eq!(self.next_read_size, 0);
@@ -170,7 +238,7 @@ where
 
     fn poll_eof(&mut self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
         let old_remaining = buf.remaining();
-        ready!(Pin::new(&mut *self.stream).poll_read(cx, buf))?;
+        ready!(Pin::new(&mut self.stream).poll_read(cx, buf))?;
         let nr = old_remaining - buf.remaining();
         if nr == 0 {
             // io closed, which indicate the end of body
@@ -181,10 +249,43 @@ where
         Poll::Ready(Ok(()))
     }
 
+    /// Poll the cached body data of the fixed length body or the current chunk without consuming.
+    ///
+    /// Return None if the next read is not for the body data, i.e. the chunk size line or trailer.
+    /// The returned data should be consumed by [`Self::consume_fixed_data`].
+    pub(super) fn poll_fixed_data(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<Option<&[u8]>>> {
+        if !matches!(self.next_read_type, NextReadType::FixedLength)
+            || self.line_output_offset < self.line_output.len()
+            || self.pending_error.is_some()
+        {
+            return Poll::Ready(Ok(None));
+        }
+
+        let cache = ready!(Pin::new(&mut self.stream).poll_fill_buf(cx))?;
+        if cache.is_empty() {
+            // io closed unexpectedly
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "reader closed while reading fixed length body",
+            )));
+        }
+        let len = cache.len().min(self.next_read_size);
+        Poll::Ready(Ok(Some(&cache[..len])))
+    }
+
+    /// Consume the data returned by [`Self::poll_fixed_data`]
+    pub(super) fn consume_fixed_data(&mut self, nr: usize) {
+        Pin::new(&mut self.stream).consume(nr);
+        self.fixed_data_read(nr);
+    }
+
     fn poll_fixed(&mut self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
         let buf_len = std::cmp::min(buf.remaining(), self.next_read_size);
         let mut limited_buf = ReadBuf::new(buf.initialize_unfilled_to(buf_len));
-        ready!(Pin::new(&mut *self.stream).poll_read(cx, &mut limited_buf))?;
+        ready!(Pin::new(&mut self.stream).poll_read(cx, &mut limited_buf))?;
         let nr = limited_buf.filled().len();
         if nr == 0 {
             // io closed unexpectedly
@@ -194,7 +295,11 @@ where
             )));
         }
         buf.advance(nr);
+        self.fixed_data_read(nr);
+        Poll::Ready(Ok(()))
+    }
 
+    fn fixed_data_read(&mut self, nr: usize) {
         self.read_content_length += nr as u64;
         self.next_read_size -= nr;
 
@@ -212,93 +317,100 @@ where
                 }
             }
         }
-
-        Poll::Ready(Ok(()))
     }
 
-    fn poll_chunk_size(
-        &mut self,
-        cx: &mut Context<'_>,
-        mut buf: &mut [u8],
-    ) -> Poll<io::Result<usize>> {
-        let mut reader = Pin::new(&mut self.stream);
-        let cache = ready!(reader.as_mut().poll_fill_buf(cx))?;
-        if cache.is_empty() {
-            return Poll::Ready(Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "reader closed while reading chunk line",
-            )));
-        }
-
-        // do not parse more data than really needed
-        let max_len = buf.len();
-        let cache = if max_len < cache.len() {
-            &cache[0..max_len]
-        } else {
-            cache
-        };
-
-        if matches!(self.chunk_size_line_cache.last(), Some(b'\r')) {
-            let next_char = cache[0];
-            reader.as_mut().consume(1);
-            self.check_chunk_size_last_char(next_char)?;
-            buf.put_u8(next_char);
-            Poll::Ready(Ok(1))
-        } else if let Some(offset) = memchr::memchr(b'\r', cache) {
-            let nw = offset + 1;
-            // check line size
-            if self.chunk_size_line_cache.len() + nw >= self.body_line_max_len {
+    /// Read in a whole line to the line cache, return false if the reader closed at the line start
+    fn poll_line(&mut self, cx: &mut Context<'_>, name: &'static str) -> Poll<io::Result<bool>> {
+        loop {
+            let mut reader = Pin::new(&mut self.stream);
+            let cache = ready!(reader.as_mut().poll_fill_buf(cx))?;
+            if cache.is_empty() {
+                if self.line_cache.is_empty() {
+                    return Poll::Ready(Ok(false));
+                }
                 return Poll::Ready(Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "chunk size line too long",
+                    io::ErrorKind::UnexpectedEof,
+                    format!("reader closed while reading {name}"),
                 )));
             }
-            let to_copy = &cache[0..nw];
-            buf.put_slice(to_copy);
-            self.chunk_size_line_cache.extend_from_slice(to_copy);
-            if cache.len() > nw {
-                // a small trick to speed up
-                let next_char = cache[nw];
-                reader.as_mut().consume(nw + 1);
-                self.check_chunk_size_last_char(next_char)?;
-                buf.put_u8(next_char);
-                Poll::Ready(Ok(nw + 1))
-            } else {
-                reader.as_mut().consume(nw);
-                Poll::Ready(Ok(nw))
-            }
-        } else {
-            let nw = cache.len();
+
+            let (to_copy, line_end) = match memchr::memchr(b'\n', cache) {
+                Some(offset) => (&cache[0..=offset], true),
+                None => (cache, false),
+            };
+            let nr = to_copy.len();
             // check line size
-            if self.chunk_size_line_cache.len() + nw >= self.body_line_max_len {
+            if self.line_cache.len() + nr >= self.body_line_max_len {
                 return Poll::Ready(Err(io::Error::new(
                     io::ErrorKind::InvalidData,
-                    "chunk size line too long",
+                    format!("{name} too long"),
                 )));
             }
-            buf.put_slice(cache);
-            self.chunk_size_line_cache.extend_from_slice(cache);
-            reader.as_mut().consume(nw);
-            Poll::Ready(Ok(nw))
+            self.line_cache.extend_from_slice(to_copy);
+            reader.consume(nr);
+            if line_end {
+                return Poll::Ready(Ok(true));
+            }
         }
     }
 
-    fn check_chunk_size_last_char(&mut self, char: u8) -> io::Result<()> {
-        if char != b'\n' {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "invalid chunk size line ending",
-            ));
+    fn poll_chunk_size(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if !ready!(self.poll_line(cx, "chunk size line"))? {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "reader closed while reading chunk size line",
+            )));
         }
-        self.chunk_size_line_cache.put_u8(b'\n');
-
         self.parse_chunk_size_and_update_next_read_type()?;
-        Ok(())
+        Poll::Ready(Ok(()))
     }
 
     fn parse_chunk_size_and_update_next_read_type(&mut self) -> io::Result<()> {
-        let chunk = HttpChunkedLine::parse(self.chunk_size_line_cache.as_slice())
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let line = self.line_cache.as_slice();
+        self.line_output.clear();
+        self.line_output_offset = 0;
+        let chunk = match HttpChunkedLine::parse_strict(line) {
+            Ok(chunk) if chunk.chunk_size > self.max_chunk_size => {
+                return Err(invalid_framing(HttpLineParseError::ChunkTooLarge(
+                    self.max_chunk_size,
+                )));
+            }
+            Ok(chunk) => {
+                match chunk.extension {
+                    Some(_) if self.extension_policy == HttpChunkExtensionPolicy::Drop => {
+                        let size_end = memchr::memchr(b';', line).unwrap_or_default();
+                        self.line_output.extend_from_slice(&line[..size_end]);
+                        self.line_output.extend_from_slice(b"\r\n");
+                    }
+                    _ => self.line_output.extend_from_slice(line),
+                }
+                chunk
+            }
+            Err(e) if self.strict_chunked => return Err(invalid_framing(e)),
+            Err(_) => {
+                // normalize the malformed line
+                let chunk = HttpChunkedLine::parse(line).map_err(invalid_framing)?;
+                if chunk.chunk_size > self.max_chunk_size {
+                    return Err(invalid_framing(HttpLineParseError::ChunkTooLarge(
+                        self.max_chunk_size,
+                    )));
+                }
+                let _ = write!(&mut self.line_output, "{:x}", chunk.chunk_size);
+                if let Some(ext) = chunk.extension {
+                    if self.extension_policy == HttpChunkExtensionPolicy::Forward {
+                        self.line_output.push(b';');
+                        self.line_output.extend_from_slice(ext.as_bytes());
+                    }
+                }
+                self.line_output.extend_from_slice(b"\r\n");
+                chunk
+            }
+        };
+        if chunk.extension.is_some() && self.extension_policy == HttpChunkExtensionPolicy::Reject {
+            return Err(invalid_framing(
+                HttpLineParseError::ChunkExtensionNotAllowed,
+            ));
+        }
         self.current_chunk_size = chunk.chunk_size;
         if chunk.chunk_size == 0 {
             self.next_read_type = NextReadType::Trailer;
@@ -307,19 +419,14 @@ where
             self.left_total_size = chunk.chunk_size;
             self.update_next_read_size();
         }
-        self.chunk_size_line_cache.clear(); // clear only if success
+        self.line_cache.clear(); // clear only if success
         Ok(())
     }
 
-    fn poll_chunk_data_end(
-        &mut self,
-        cx: &mut Context<'_>,
-        buf: &mut [u8],
-        char: u8,
-    ) -> Poll<io::Result<usize>> {
+    fn poll_chunk_data_end(&mut self, cx: &mut Context<'_>, char: u8) -> Poll<io::Result<()>> {
         debug_assert!(b"\r\n".contains(&char));
 
-        let mut reader = Pin::new(&mut *self.stream);
+        let mut reader = Pin::new(&mut self.stream);
         let cache = ready!(reader.as_mut().poll_fill_buf(cx))?;
         if cache.is_empty() {
             return Poll::Ready(Err(io::Error::new(
@@ -328,150 +435,78 @@ where
             )));
         }
 
-        // do not parse more data than really needed
-        let max_len = buf.len();
-        let cache = if max_len < cache.len() {
-            &cache[0..max_len]
-        } else {
-            cache
-        };
-
-        if cache[0] != char {
-            return Poll::Ready(Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "invalid chunk ending first char",
-            )));
-        }
-
-        buf[0] = char;
-        let mut nw: usize = 1;
-        match char {
-            b'\r' => {
-                if cache.len() > 1 {
-                    // a small trick to speed up
-                    let next_char = cache[1];
-                    reader.as_mut().consume(2);
-                    self.check_chunk_data_end_last_char(next_char)?;
-                    buf[1] = b'\n';
-                    nw = 2;
-                } else {
-                    reader.as_mut().consume(1);
-                    self.next_read_type = NextReadType::ChunkDataEnd(b'\n');
-                }
+        match (char, cache[0]) {
+            (b'\r', b'\r') => {
+                reader.consume(1);
+                self.next_read_type = NextReadType::ChunkDataEnd(b'\n');
+                return Poll::Ready(Ok(()));
             }
-            b'\n' => {
-                reader.as_mut().consume(1);
-                self.update_next_read_type_after_chunk_data_end();
+            (b'\r', b'\n') => {
+                if self.strict_chunked {
+                    return Poll::Ready(Err(invalid_framing(HttpLineParseError::BareLineFeed)));
+                }
+                reader.consume(1);
             }
-            _ => unreachable!(),
-        }
-
-        Poll::Ready(Ok(nw))
-    }
-
-    fn check_chunk_data_end_last_char(&mut self, char: u8) -> io::Result<()> {
-        if char != b'\n' {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "invalid chunk ending last char",
-            ));
-        }
-        self.update_next_read_type_after_chunk_data_end();
-        Ok(())
-    }
-
-    fn update_next_read_type_after_chunk_data_end(&mut self) {
-        self.next_read_type = NextReadType::ChunkSize;
-    }
-
-    fn poll_trailer(
-        &mut self,
-        cx: &mut Context<'_>,
-        mut buf: &mut [u8],
-    ) -> Poll<io::Result<usize>> {
-        let mut reader = Pin::new(&mut *self.stream);
-        let cache = ready!(reader.as_mut().poll_fill_buf(cx))?;
-        if cache.is_empty() {
-            return Poll::Ready(Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "reader closed while reading trailer",
-            )));
-        }
-
-        // do not parse more data than really needed
-        let max_len = buf.len();
-        let cache = if max_len < cache.len() {
-            &cache[0..max_len]
-        } else {
-            cache
-        };
-
-        if self.trailer_line_length != 0 && self.trailer_last_char == b'\r' {
-            let next_char = cache[0];
-            reader.as_mut().consume(1);
-            self.check_trailer_header_last_char(next_char)?;
-            buf.put_u8(next_char);
-            Poll::Ready(Ok(1))
-        } else if let Some(offset) = memchr::memchr(b'\r', cache) {
-            let nw = offset + 1;
-            // check line size
-            if self.trailer_line_length + nw >= self.body_line_max_len {
+            (b'\n', b'\n') => reader.consume(1),
+            (b'\r', _) => {
                 return Poll::Ready(Err(io::Error::new(
                     io::ErrorKind::InvalidData,
-                    "trailer line too long",
+                    "invalid chunk ending first char",
                 )));
             }
-            buf.put_slice(&cache[0..nw]);
-            self.trailer_last_char = b'\r';
-            self.trailer_line_length += offset;
-            if cache.len() > nw {
-                // a small trick to speed up
-                let next_char = cache[nw];
-                reader.as_mut().consume(nw + 1);
-                self.check_trailer_header_last_char(next_char)?;
-                buf.put_u8(next_char);
-                Poll::Ready(Ok(nw + 1))
-            } else {
-                reader.as_mut().consume(nw);
-                Poll::Ready(Ok(nw))
-            }
-        } else {
-            let nw = cache.len();
-            // check line size
-            if self.trailer_line_length + nw >= self.body_line_max_len {
+            _ => {
                 return Poll::Ready(Err(io::Error::new(
                     io::ErrorKind::InvalidData,
-                    "trailer line too long",
+                    "invalid chunk ending last char",
                 )));
             }
-            buf.put_slice(cache);
-            self.trailer_last_char = cache[nw - 1];
-            self.trailer_line_length += nw;
-            reader.as_mut().consume(nw);
-            Poll::Ready(Ok(nw))
         }
+
+        self.line_output.clear();
+        self.line_output.extend_from_slice(b"\r\n");
+        self.line_output_offset = 0;
+        self.next_read_type = NextReadType::ChunkSize;
+        Poll::Ready(Ok(()))
     }
 
-    fn check_trailer_header_last_char(&mut self, char: u8) -> io::Result<()> {
-        if char != b'\n' {
-            return Err(io::Error::new(
+    fn poll_trailer(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.line_output.clear();
+        self.line_output_offset = 0;
+        if !ready!(self.poll_line(cx, "trailer line"))? {
+            // the final CRLF is missing
+            if self.strict_chunked {
+                return Poll::Ready(Err(invalid_framing(HttpLineParseError::MissingFinalCrlf)));
+            }
+            self.line_output.extend_from_slice(b"\r\n");
+            self.next_read_type = NextReadType::EndOfFile;
+            return Poll::Ready(Ok(()));
+        }
+
+        let line = match self.line_cache.as_slice() {
+            [line @ .., b'\r', b'\n'] => line,
+            [line @ .., b'\n'] => {
+                if self.strict_chunked {
+                    return Poll::Ready(Err(invalid_framing(HttpLineParseError::BareLineFeed)));
+                }
+                line
+            }
+            _ => unreachable!(),
+        };
+        if memchr::memchr(b'\r', line).is_some() {
+            return Poll::Ready(Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 "invalid trailer line ending",
-            ));
+            )));
         }
-        self.update_next_read_type_after_trailer_header();
-        Ok(())
-    }
-
-    fn update_next_read_type_after_trailer_header(&mut self) {
-        self.next_read_type = if self.trailer_line_length == 0 {
+        self.line_output.extend_from_slice(line);
+        self.line_output.extend_from_slice(b"\r\n");
+        self.next_read_type = if line.is_empty() {
             NextReadType::EndOfFile
         } else {
-            // clear only when needed
-            self.trailer_last_char = 0;
-            self.trailer_line_length = 0;
             NextReadType::Trailer
         };
+        self.line_cache.clear();
+        Poll::Ready(Ok(()))
     }
 
     fn poll_chunked(
@@ -479,10 +514,23 @@ where
         cx: &mut Context<'_>,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
-        let expect_len = buf.remaining();
-        let mut offset: usize = 0;
+        if let Some(e) = self.pending_error.take() {
+            return Poll::Ready(Err(e));
+        }
+
+        let start_len = buf.filled().len();
         loop {
-            if offset >= expect_len {
+            if self.line_output_offset < self.line_output.len() {
+                let left = &self.line_output[self.line_output_offset..];
+                let to_copy = left.len().min(buf.remaining());
+                if to_copy == 0 {
+                    return Poll::Ready(Ok(()));
+                }
+                buf.put_slice(&left[..to_copy]);
+                self.line_output_offset += to_copy;
+                continue;
+            }
+            if buf.remaining() == 0 {
                 return Poll::Ready(Ok(()));
             }
 
@@ -491,43 +539,35 @@ where
                     self.finished = true;
                     return Poll::Ready(Ok(()));
                 }
-                NextReadType::ChunkSize => {
-                    self.as_mut().poll_chunk_size(cx, buf.initialize_unfilled())
-                }
-                NextReadType::FixedLength => {
-                    let mut inner_buf = ReadBuf::new(buf.initialize_unfilled());
-                    // use a wrapper buf to get filled size and keep the code clean
-                    self.as_mut()
-                        .poll_fixed(cx, &mut inner_buf)
-                        .map_ok(|_| inner_buf.filled().len())
-                }
-                NextReadType::ChunkDataEnd(char) => {
-                    self.as_mut()
-                        .poll_chunk_data_end(cx, buf.initialize_unfilled(), char)
-                }
-                NextReadType::Trailer => self.as_mut().poll_trailer(cx, buf.initialize_unfilled()),
+                NextReadType::ChunkSize => self.poll_chunk_size(cx),
+                NextReadType::FixedLength => self.poll_fixed(cx, buf),
+                NextReadType::ChunkDataEnd(char) => self.poll_chunk_data_end(cx, char),
+                NextReadType::Trailer => self.poll_trailer(cx),
                 _ => unreachable!(),
             };
             match ret {
                 Poll::Pending => {
-                    return if offset != 0 {
+                    return if buf.filled().len() > start_len {
                         Poll::Ready(Ok(()))
                     } else {
                         Poll::Pending
                     };
                 }
-                Poll::Ready(Ok(0)) => return Poll::Ready(Ok(())),
-                Poll::Ready(Ok(nr)) => {
-                    buf.advance(nr);
-                    offset += nr;
+                Poll::Ready(Ok(_)) => {}
+                Poll::Ready(Err(e)) => {
+                    if buf.filled().len() > start_len {
+                        // return the data first, and the error in the next read
+                        self.pending_error = Some(e);
+                        return Poll::Ready(Ok(()));
+                    }
+                    return Poll::Ready(Err(e));
                 }
-                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
             }
         }
     }
 }
 
-impl<R> AsyncRead for HttpBodyReader<'_, R>
+impl<R> AsyncRead for HttpBodyReaderOwned<R>
 where
     R: AsyncBufRead + Unpin,
 {
@@ -646,6 +686,105 @@ mod tests {
         assert!(body_reader.finished());
     }
 
+    #[tokio::test]
+    async fn remaining_hint_to_end() {
+        let stream = tokio_test::io::Builder::new().read(b"test body").build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut body_reader =
+            HttpBodyReader::new(&mut buf_stream, HttpBodyType::ReadUntilEnd, 1024);
+        assert_eq!(body_reader.remaining_hint(), None);
+
+        let mut buf = [0u8; 4];
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(len, 4);
+        assert_eq!(body_reader.remaining_hint(), None);
+    }
+
+    #[tokio::test]
+    async fn remaining_hint_content_length() {
+        let stream = tokio_test::io::Builder::new()
+            .read(b"hello world")
+            .read(b"test bodyxxxx")
+            .build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut body_reader =
+            HttpBodyReader::new(&mut buf_stream, HttpBodyType::ContentLength(20), 1024);
+        assert_eq!(body_reader.remaining_hint(), Some(20));
+
+        let mut buf = [0u8; 32];
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(len, 11);
+        assert_eq!(body_reader.remaining_hint(), Some(9));
+
+        let mut buf = [0u8; 4];
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(len, 4);
+        assert_eq!(body_reader.remaining_hint(), Some(5));
+
+        let mut buf = [0u8; 32];
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(len, 5);
+        assert_eq!(body_reader.remaining_hint(), Some(0));
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(len, 0);
+        assert_eq!(body_reader.remaining_hint(), Some(0));
+        assert!(body_reader.finished());
+    }
+
+    #[tokio::test]
+    async fn remaining_hint_chunked() {
+        let content = b"5\r\ntest\n\r\n4\r\nbody\r\n0\r\n\r\n";
+        let stream = tokio_test::io::Builder::new().read(content).build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut body_reader = HttpBodyReader::new_chunked(&mut buf_stream, 1024);
+        assert_eq!(body_reader.remaining_hint(), None);
+
+        // stop in the middle of the chunk size line
+        let mut buf = [0u8; 2];
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"5\r");
+        assert_eq!(body_reader.remaining_hint(), Some(6));
+
+        let mut buf = [0u8; 3];
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"\nte");
+        assert_eq!(body_reader.remaining_hint(), Some(3));
+
+        let mut buf = [0u8; 5];
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"st\n\r\n");
+        assert_eq!(body_reader.remaining_hint(), None);
+
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"4\r\nbo");
+        assert_eq!(body_reader.remaining_hint(), Some(2));
+
+        let mut left = Vec::new();
+        body_reader.read_to_end(&mut left).await.unwrap();
+        assert_eq!(left, b"dy\r\n0\r\n\r\n");
+        assert_eq!(body_reader.remaining_hint(), None);
+        assert!(body_reader.finished());
+    }
+
+    #[tokio::test]
+    async fn remaining_hint_chunked_after_preview() {
+        let stream = tokio_test::io::Builder::new()
+            .read(b"dy\r\n0\r\n\r\n")
+            .build();
+        let mut buf_stream = BufReader::new(stream);
+        let mut body_reader = HttpBodyReader::new_chunked_after_preview(&mut buf_stream, 1024, 2);
+        assert_eq!(body_reader.remaining_hint(), Some(2));
+
+        let mut buf = [0u8; 1];
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"d");
+        assert_eq!(body_reader.remaining_hint(), Some(1));
+
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"y");
+        assert_eq!(body_reader.remaining_hint(), None);
+    }
+
     #[tokio::test]
     async fn read_empty_chunked() {
         let body_len: usize = 5;
@@ -773,4 +912,384 @@ mod tests {
         assert_eq!(&buf[..len], b"\r\n");
         assert!(body_reader.finished);
     }
+
+    #[derive(Default)]
+    struct RandomChunkedBody {
+        raw: Vec<u8>,
+        dropped: Vec<u8>,
+        data: Vec<u8>,
+        trailer: Option<(&'static str, &'static str)>,
+        has_extension: bool,
+    }
+
+    fn random_chunked_body(rng: &mut fastrand::Rng, max_ext_len: usize) -> RandomChunkedBody {
+        let mut body = RandomChunkedBody::default();
+        let chunk_count = rng.usize(0..4);
+        for i in 0..=chunk_count {
+            let size = if i == chunk_count {
+                0
+            } else {
+                rng.usize(1..32)
+            };
+            let head = format!("{size:x}");
+            body.raw.extend_from_slice(head.as_bytes());
+            body.dropped.extend_from_slice(head.as_bytes());
+            if rng.bool() {
+                // the extension may be empty or contain any printable chars
+                body.raw.push(b';');
+                for _ in 0..rng.usize(0..=max_ext_len) {
+                    body.raw.push(rng.u8(0x20..0x7f));
+                }
+                body.has_extension = true;
+            }
+            body.raw.extend_from_slice(b"\r\n");
+            body.dropped.extend_from_slice(b"\r\n");
+            if size > 0 {
+                let data: Vec<u8> = std::iter::repeat_with(|| rng.alphanumeric() as u8)
+                    .take(size)
+                    .collect();
+                body.raw.extend_from_slice(&data);
+                body.raw.extend_from_slice(b"\r\n");
+                body.dropped.extend_from_slice(&data);
+                body.dropped.extend_from_slice(b"\r\n");
+                body.data.extend_from_slice(&data);
+            }
+        }
+        if rng.bool() {
+            body.raw.extend_from_slice(b"X-Trailer: value\r\n");
+            body.dropped.extend_from_slice(b"X-Trailer: value\r\n");
+            body.trailer = Some(("x-trailer", "value"));
+        }
+        body.raw.extend_from_slice(b"\r\n");
+        body.dropped.extend_from_slice(b"\r\n");
+        body
+    }
+
+    fn is_extension_not_allowed(e: &io::Error) -> bool {
+        matches!(
+            e.get_ref()
+                .and_then(|e| e.downcast_ref::<HttpLineParseError>()),
+            Some(HttpLineParseError::ChunkExtensionNotAllowed)
+        )
+    }
+
+    async fn read_body(
+        raw: &[u8],
+        buf_size: usize,
+        policy: HttpChunkExtensionPolicy,
+    ) -> io::Result<Vec<u8>> {
+        let mut buf_stream = BufReader::with_capacity(buf_size, raw);
+        let mut body_reader =
+            HttpBodyReader::new_chunked_with_extension_policy(&mut buf_stream, 1024, policy);
+        let mut output = Vec::new();
+        body_reader.read_to_end(&mut output).await?;
+        assert!(body_reader.finished());
+        Ok(output)
+    }
+
+    async fn decode_body(
+        raw: &[u8],
+        buf_size: usize,
+        policy: HttpChunkExtensionPolicy,
+    ) -> io::Result<(Vec<u8>, Option<String>)> {
+        let mut buf_stream = BufReader::with_capacity(buf_size, raw);
+        let mut body_reader = crate::HttpBodyDecodeReader::new_chunked_with_extension_policy(
+            &mut buf_stream,
+            1024,
+            policy,
+        );
+        let mut output = Vec::new();
+        body_reader.read_to_end(&mut output).await?;
+        let trailer = body_reader
+            .trailer(1024)
+            .await
+            .unwrap()
+            .map(|headers| headers.get("x-trailer").unwrap().to_str().to_string());
+        Ok((output, trailer))
+    }
+
+    #[tokio::test]
+    async fn random_chunk_extension_forward() {
+        let mut rng = fastrand::Rng::with_seed(0x5eed);
+        for _ in 0..256 {
+            let body = random_chunked_body(&mut rng, 128);
+            let buf_size = rng.usize(1..64);
+
+            let output = read_body(&body.raw, buf_size, HttpChunkExtensionPolicy::Forward)
+                .await
+                .unwrap();
+            assert_eq!(output, body.raw);
+
+            let (data, trailer) =
+                decode_body(&body.raw, buf_size, HttpChunkExtensionPolicy::Forward)
+                    .await
+                    .unwrap();
+            assert_eq!(data, body.data);
+            assert_eq!(trailer.as_deref(), body.trailer.map(|v| v.1));
+        }
+    }
+
+    #[tokio::test]
+    async fn random_chunk_extension_drop() {
+        let mut rng = fastrand::Rng::with_seed(0x5eed);
+        for _ in 0..256 {
+            let body = random_chunked_body(&mut rng, 128);
+            let buf_size = rng.usize(1..64);
+
+            let output = read_body(&body.raw, buf_size, HttpChunkExtensionPolicy::Drop)
+                .await
+                .unwrap();
+            assert_eq!(output, body.dropped);
+
+            let (data, trailer) = decode_body(&body.raw, buf_size, HttpChunkExtensionPolicy::Drop)
+                .await
+                .unwrap();
+            assert_eq!(data, body.data);
+            assert_eq!(trailer.as_deref(), body.trailer.map(|v| v.1));
+        }
+    }
+
+    #[tokio::test]
+    async fn random_chunk_extension_reject() {
+        let mut rng = fastrand::Rng::with_seed(0x5eed);
+        for _ in 0..256 {
+            let body = random_chunked_body(&mut rng, 128);
+            let buf_size = rng.usize(1..64);
+
+            let r = read_body(&body.raw, buf_size, HttpChunkExtensionPolicy::Reject).await;
+            let d = decode_body(&body.raw, buf_size, HttpChunkExtensionPolicy::Reject).await;
+            if body.has_extension {
+                assert!(is_extension_not_allowed(&r.unwrap_err()));
+                assert!(is_extension_not_allowed(&d.unwrap_err()));
+            } else {
+                assert_eq!(r.unwrap(), body.raw);
+                assert_eq!(d.unwrap().0, body.data);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn random_chunk_extension_too_long() {
+        let mut rng = fastrand::Rng::with_seed(0x5eed);
+        for _ in 0..64 {
+            let mut raw = b"5;".to_vec();
+            for _ in 0..rng.usize(1024..2048) {
+                raw.push(rng.u8(0x20..0x7f));
+            }
+            raw.extend_from_slice(b"\r\nhello\r\n0\r\n\r\n");
+            let buf_size = rng.usize(1..2048);
+
+            for policy in [
+                HttpChunkExtensionPolicy::Drop,
+                HttpChunkExtensionPolicy::Forward,
+                HttpChunkExtensionPolicy::Reject,
+            ] {
+                let e = read_body(&raw, buf_size, policy).await.unwrap_err();
+                assert!(!is_extension_not_allowed(&e));
+                let e = decode_body(&raw, buf_size, policy).await.unwrap_err();
+                assert!(!is_extension_not_allowed(&e));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn last_chunk_extension_with_trailer() {
+        let raw = b"4\r\nbody\r\n0;ext=\"a b\"\r\nA: B\r\n\r\nXX";
+
+        let output = read_body(raw, 8, HttpChunkExtensionPolicy::Forward)
+            .await
+            .unwrap();
+        assert_eq!(output, &raw[..raw.len() - 2]);
+        let output = read_body(raw, 8, HttpChunkExtensionPolicy::Drop)
+            .await
+            .unwrap();
+        assert_eq!(output, b"4\r\nbody\r\n0\r\nA: B\r\n\r\n");
+
+        let mut buf_stream = BufReader::with_capacity(8, raw.as_slice());
+        let mut body_reader = crate::HttpBodyDecodeReader::new_chunked(&mut buf_stream, 1024);
+        let mut data = Vec::new();
+        body_reader.read_to_end(&mut data).await.unwrap();
+        assert_eq!(data, b"body");
+        let trailer = body_reader.trailer(1024).await.unwrap().unwrap();
+        assert_eq!(trailer.get("a").unwrap().to_str(), "B");
+        assert!(body_reader.finished());
+    }
+    fn framing_error(e: &io::Error) -> Option<&HttpLineParseError> {
+        e.get_ref()
+            .and_then(|e| e.downcast_ref::<HttpLineParseError>())
+    }
+
+    async fn read_body_with_mode(raw: &[u8], buf_size: usize, strict: bool) -> io::Result<Vec<u8>> {
+        let mut buf_stream = BufReader::with_capacity(buf_size, raw);
+        let mut body_reader = HttpBodyReader::new_chunked(&mut buf_stream, 1024);
+        body_reader.set_strict_chunked(strict);
+        let mut output = Vec::new();
+        body_reader.read_to_end(&mut output).await?;
+        assert!(body_reader.finished());
+        Ok(output)
+    }
+
+    /// malformed chunked bodies, with the canonical output in lenient mode
+    const MALFORMED_CHUNKED_BODIES: &[(&[u8], &[u8])] = &[
+        (b"4\nbody\r\n0\r\n\r\n", b"4\r\nbody\r\n0\r\n\r\n"),
+        (b"4\r\nbody\n0\r\n\r\n", b"4\r\nbody\r\n0\r\n\r\n"),
+        (
+            b"4\r\nbody\r\n0\r\nA: B\n\n",
+            b"4\r\nbody\r\n0\r\nA: B\r\n\r\n",
+        ),
+        (b" 4 \r\nbody\r\n0\r\n\r\n", b"4\r\nbody\r\n0\r\n\r\n"),
+        (
+            b"4 ;a=b\r\nbody\r\n0\r\n\r\n",
+            b"4;a=b\r\nbody\r\n0\r\n\r\n",
+        ),
+        (b"+4\r\nbody\r\n0\r\n\r\n", b"4\r\nbody\r\n0\r\n\r\n"),
+        (
+            b"000000000000000004\r\nbody\r\n0\r\n\r\n",
+            b"4\r\nbody\r\n0\r\n\r\n",
+        ),
+        (b"4\r\nbody\r\n0\r\n", b"4\r\nbody\r\n0\r\n\r\n"),
+        (
+            b"4\r\nbody\r\n0\r\nA: B\r\n",
+            b"4\r\nbody\r\n0\r\nA: B\r\n\r\n",
+        ),
+    ];
+
+    #[tokio::test]
+    async fn malformed_chunked_lenient() {
+        for (raw, canonical) in MALFORMED_CHUNKED_BODIES {
+            for buf_size in [1, 3, 64] {
+                let output = read_body_with_mode(raw, buf_size, false).await.unwrap();
+                assert_eq!(output, *canonical);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_chunked_strict() {
+        let expected = [
+            HttpLineParseError::BareLineFeed,
+            HttpLineParseError::BareLineFeed,
+            HttpLineParseError::BareLineFeed,
+            HttpLineParseError::ChunkSizeWhitespace,
+            HttpLineParseError::ChunkSizeWhitespace,
+            HttpLineParseError::ChunkSizeSignPrefix,
+            HttpLineParseError::ChunkSizeTooLong,
+            HttpLineParseError::MissingFinalCrlf,
+            HttpLineParseError::MissingFinalCrlf,
+        ];
+        assert_eq!(expected.len(), MALFORMED_CHUNKED_BODIES.len());
+        for ((raw, canonical), error) in MALFORMED_CHUNKED_BODIES.iter().zip(expected) {
+            for buf_size in [1, 3, 64] {
+                let e = read_body_with_mode(raw, buf_size, true).await.unwrap_err();
+                assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+                let found = framing_error(&e).unwrap();
+                assert_eq!(found.to_string(), error.to_string());
+
+                // the canonical form should always be accepted
+                let output = read_body_with_mode(canonical, buf_size, true)
+                    .await
+                    .unwrap();
+                assert_eq!(output, *canonical);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_chunked_strict_data_before_error() {
+        let raw = b"4\r\nbody\n0\r\n\r\n";
+        let mut buf_stream = BufReader::new(raw.as_slice());
+        let mut body_reader = HttpBodyReader::new_chunked(&mut buf_stream, 1024);
+        body_reader.set_strict_chunked(true);
+
+        let mut buf = [0u8; 32];
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"4\r\nbody");
+        let e = body_reader.read(&mut buf).await.unwrap_err();
+        assert!(matches!(
+            framing_error(&e),
+            Some(HttpLineParseError::BareLineFeed)
+        ));
+        assert!(!body_reader.finished());
+    }
+
+    #[tokio::test]
+    async fn malformed_chunked_always_rejected() {
+        for raw in [
+            b"-4\r\nbody\r\n0\r\n\r\n".as_slice(),
+            b"4\r\r\nbody\r\n0\r\n\r\n",
+            b"4\r\nbodyX\r\n0\r\n\r\n",
+            b"10000000000000000\r\nbody\r\n0\r\n\r\n",
+            b"4\r\nbody\r\n0\r\nA: B\r\r\n\r\n",
+            b"4\r\nbody\r\n0\r\nA: B",
+        ] {
+            for strict in [false, true] {
+                assert!(read_body_with_mode(raw, 64, strict).await.is_err());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn chunk_too_large() {
+        for strict in [false, true] {
+            for raw in [
+                b"5\r\nbody1\r\n0\r\n\r\n".as_slice(),
+                b"ffffffffffffffff\r\nbody\r\n0\r\n\r\n",
+            ] {
+                let mut buf_stream = BufReader::with_capacity(3, raw);
+                let mut body_reader = HttpBodyReader::new_chunked(&mut buf_stream, 1024);
+                body_reader.set_strict_chunked(strict);
+                body_reader.set_max_chunk_size(4);
+                let mut output = Vec::new();
+                let e = body_reader.read_to_end(&mut output).await.unwrap_err();
+                assert!(matches!(
+                    framing_error(&e),
+                    Some(HttpLineParseError::ChunkTooLarge(4))
+                ));
+            }
+        }
+
+        let raw = b"ffffffffffffffff\r\nbody";
+        let mut buf_stream = BufReader::new(raw.as_slice());
+        let mut body_reader = HttpBodyReader::new_chunked(&mut buf_stream, 1024);
+        body_reader.set_max_chunk_size(u64::MAX);
+        let mut buf = [0u8; 22]; // only the chunk size line and the data
+        let len = body_reader.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"ffffffffffffffff\r\nbody");
+        assert_eq!(body_reader.remaining_hint(), Some(u64::MAX - 4));
+    }
+
+    #[tokio::test]
+    async fn chunk_size_line_edge() {
+        // empty size line
+        assert!(
+            read_body_with_mode(b"\r\nbody\r\n0\r\n\r\n", 64, false)
+                .await
+                .is_err()
+        );
+
+        // the max line length is exclusive
+        for buf_size in [1, 3, 64] {
+            let mut buf_stream =
+                BufReader::with_capacity(buf_size, b"4;abc\r\nbody\r\n0\r\n\r\n".as_slice());
+            let mut body_reader = HttpBodyReader::new_chunked(&mut buf_stream, 8);
+            let mut output = Vec::new();
+            body_reader.read_to_end(&mut output).await.unwrap();
+            assert_eq!(output, b"4;abc\r\nbody\r\n0\r\n\r\n");
+
+            let mut buf_stream =
+                BufReader::with_capacity(buf_size, b"4;abcd\r\nbody\r\n0\r\n\r\n".as_slice());
+            let mut body_reader = HttpBodyReader::new_chunked(&mut buf_stream, 8);
+            let e = body_reader.read_to_end(&mut output).await.unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        }
+
+        // CR without LF at the buffer boundary
+        for strict in [false, true] {
+            for buf_size in [1, 3, 5, 64] {
+                for raw in [b"4\r\nbody\rX0\r\n\r\n".as_slice(), b"4\r\nbody\r", b"4\r"] {
+                    assert!(read_body_with_mode(raw, buf_size, strict).await.is_err());
+                }
+            }
+        }
+    }
 }
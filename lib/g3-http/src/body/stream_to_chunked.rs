@@ -3,7 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::io::Write;
+use std::io::{IoSlice, Write};
 use std::pin::Pin;
 use std::task::{Context, Poll, ready};
 
@@ -20,6 +20,7 @@ struct ChunkedEncodeTransferInternal {
     static_offset: usize,
     total_write: u64,
     read_finished: bool,
+    flush_on_finish: bool,
     active: bool,
 }
 
@@ -34,6 +35,7 @@ impl ChunkedEncodeTransferInternal {
             static_offset: 0,
             total_write: 0,
             read_finished: false,
+            flush_on_finish: true,
             active: false,
         }
     }
@@ -77,21 +79,52 @@ impl ChunkedEncodeTransferInternal {
                 self.static_offset = 0;
                 self.this_chunk_size = chunk_size;
                 self.left_chunk_size = chunk_size;
+                if chunk_size > 0 && !writer.is_write_vectored() {
+                    // stage the chunk data after the chunk header, so they can be sent in one write
+                    self.static_header.extend_from_slice(data);
+                    reader.as_mut().consume(chunk_size);
+                    copy_this_round += chunk_size;
+                    self.left_chunk_size = 0;
+                }
             }
 
             while self.static_offset < self.static_header.len() {
-                let nw = ready!(
-                    writer
-                        .as_mut()
-                        .poll_write(cx, &self.static_header[self.static_offset..])
-                )
-                .map_err(StreamCopyError::WriteFailed)?;
+                let header_left = self.static_header.len() - self.static_offset;
+                let nw = if self.left_chunk_size > 0 {
+                    // send the chunk header along with the chunk data in a single vectored write
+                    let data = ready!(reader.as_mut().poll_fill_buf(cx))
+                        .map_err(StreamCopyError::ReadFailed)?;
+                    debug_assert!(self.left_chunk_size <= data.len());
+                    let bufs = [
+                        IoSlice::new(&self.static_header[self.static_offset..]),
+                        IoSlice::new(&data[..self.left_chunk_size]),
+                    ];
+                    ready!(writer.as_mut().poll_write_vectored(cx, &bufs))
+                        .map_err(StreamCopyError::WriteFailed)?
+                } else {
+                    ready!(
+                        writer
+                            .as_mut()
+                            .poll_write(cx, &self.static_header[self.static_offset..])
+                    )
+                    .map_err(StreamCopyError::WriteFailed)?
+                };
                 self.active = true;
-                self.static_offset += nw;
                 self.total_write += nw as u64;
+                if nw > header_left {
+                    let data_nw = nw - header_left;
+                    self.static_offset += header_left;
+                    reader.as_mut().consume(data_nw);
+                    copy_this_round += data_nw;
+                    self.left_chunk_size -= data_nw;
+                } else {
+                    self.static_offset += nw;
+                }
             }
             if self.read_finished {
-                ready!(writer.poll_flush(cx)).map_err(StreamCopyError::WriteFailed)?;
+                if self.flush_on_finish {
+                    ready!(writer.poll_flush(cx)).map_err(StreamCopyError::WriteFailed)?;
+                }
                 return Poll::Ready(Ok(self.total_write));
             }
 
@@ -148,30 +181,32 @@ impl ChunkedEncodeTransferInternal {
     }
 }
 
-pub struct StreamToChunkedTransfer<'a, R, W> {
-    reader: &'a mut R,
-    writer: &'a mut W,
+/// Chunked encode transfer which owns both the reader and the writer.
+///
+/// The reader and the writer may also be mutable references, see [`StreamToChunkedTransfer`].
+pub struct StreamToChunkedTransferOwned<R, W> {
+    reader: R,
+    writer: W,
     internal: ChunkedEncodeTransferInternal,
 }
 
-impl<'a, R, W> StreamToChunkedTransfer<'a, R, W> {
-    fn new(reader: &'a mut R, writer: &'a mut W, yield_size: usize, no_trailer: bool) -> Self {
-        StreamToChunkedTransfer {
+/// Chunked encode transfer which borrows the reader and the writer
+pub type StreamToChunkedTransfer<'a, R, W> = StreamToChunkedTransferOwned<&'a mut R, &'a mut W>;
+
+impl<R, W> StreamToChunkedTransferOwned<R, W> {
+    fn new(reader: R, writer: W, yield_size: usize, no_trailer: bool) -> Self {
+        StreamToChunkedTransferOwned {
             reader,
             writer,
             internal: ChunkedEncodeTransferInternal::new(yield_size, no_trailer),
         }
     }
 
-    pub fn new_with_no_trailer(reader: &'a mut R, writer: &'a mut W, yield_size: usize) -> Self {
+    pub fn new_with_no_trailer(reader: R, writer: W, yield_size: usize) -> Self {
         Self::new(reader, writer, yield_size, true)
     }
 
-    pub fn new_with_pending_trailer(
-        reader: &'a mut R,
-        writer: &'a mut W,
-        yield_size: usize,
-    ) -> Self {
+    pub fn new_with_pending_trailer(reader: R, writer: W, yield_size: usize) -> Self {
         Self::new(reader, writer, yield_size, false)
     }
 
@@ -194,9 +229,23 @@ impl<'a, R, W> StreamToChunkedTransfer<'a, R, W> {
     pub fn no_cached_data(&self) -> bool {
         self.internal.no_cached_data()
     }
+
+    /// Skip the flush of the writer after all data sent, the caller should do it instead
+    pub(super) fn skip_flush_on_finish(&mut self) {
+        self.internal.flush_on_finish = false;
+    }
+
+    pub fn get_ref(&self) -> (&R, &W) {
+        (&self.reader, &self.writer)
+    }
+
+    /// Get back the reader and the writer
+    pub fn into_parts(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
 }
 
-impl<R, W> Future for StreamToChunkedTransfer<'_, R, W>
+impl<R, W> Future for StreamToChunkedTransferOwned<R, W>
 where
     R: AsyncBufRead + Unpin,
     W: AsyncWrite + Unpin,
@@ -214,8 +263,162 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::io;
     use tokio::io::BufReader;
 
+    /// A writer which records the write calls, and accepts at most `max_write` bytes in each call
+    struct LimitedWriter {
+        vectored: bool,
+        max_write: usize,
+        write_count: usize,
+        data: Vec<u8>,
+    }
+
+    impl LimitedWriter {
+        fn new(vectored: bool, max_write: usize) -> Self {
+            LimitedWriter {
+                vectored,
+                max_write,
+                write_count: 0,
+                data: Vec::new(),
+            }
+        }
+    }
+
+    impl AsyncWrite for LimitedWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.write_count += 1;
+            let len = buf.len().min(self.max_write);
+            self.data.extend_from_slice(&buf[..len]);
+            Poll::Ready(Ok(len))
+        }
+
+        fn poll_write_vectored(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            bufs: &[IoSlice<'_>],
+        ) -> Poll<io::Result<usize>> {
+            self.write_count += 1;
+            let mut len = 0;
+            for buf in bufs {
+                let to_write = buf.len().min(self.max_write - len);
+                self.data.extend_from_slice(&buf[..to_write]);
+                len += to_write;
+                if len >= self.max_write {
+                    break;
+                }
+            }
+            Poll::Ready(Ok(len))
+        }
+
+        fn is_write_vectored(&self) -> bool {
+            self.vectored
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// The output of the old implementation, which writes the chunk header and data separately
+    fn expected_output(chunks: &[Vec<u8>], no_trailer: bool) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            if i > 0 {
+                out.extend_from_slice(b"\r\n");
+            }
+            let _ = write!(&mut out, "{:x}\r\n", chunk.len());
+            out.extend_from_slice(chunk);
+        }
+        if !chunks.is_empty() {
+            out.extend_from_slice(b"\r\n");
+        }
+        if no_trailer {
+            out.extend_from_slice(b"0\r\n\r\n");
+        } else {
+            out.extend_from_slice(b"0\r\n");
+        }
+        out
+    }
+
+    async fn encode_with(chunks: &[Vec<u8>], no_trailer: bool, writer: &mut LimitedWriter) -> u64 {
+        let mut builder = tokio_test::io::Builder::new();
+        for chunk in chunks {
+            builder.read(chunk);
+        }
+        let mut buf_stream = BufReader::new(builder.build());
+
+        let mut chunked_encoder = if no_trailer {
+            StreamToChunkedTransfer::new_with_no_trailer(&mut buf_stream, writer, 1024)
+        } else {
+            StreamToChunkedTransfer::new_with_pending_trailer(&mut buf_stream, writer, 1024)
+        };
+        let nw = (&mut chunked_encoder).await.unwrap();
+        assert!(chunked_encoder.finished());
+        assert!(chunked_encoder.no_cached_data());
+        nw
+    }
+
+    #[tokio::test]
+    async fn coalesce_write() {
+        let chunks = vec![b"test\n".to_vec(), vec![b'a'; 300], b"body".to_vec()];
+        for no_trailer in [true, false] {
+            let expected = expected_output(&chunks, no_trailer);
+            for vectored in [true, false] {
+                let mut writer = LimitedWriter::new(vectored, usize::MAX);
+                let nw = encode_with(&chunks, no_trailer, &mut writer).await;
+                assert_eq!(nw, expected.len() as u64);
+                assert_eq!(writer.data, expected);
+                // one write for each chunk, and one for the end
+                assert_eq!(writer.write_count, chunks.len() + 1);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn coalesce_partial_write() {
+        let chunks = vec![b"test\n".to_vec(), vec![b'a'; 300], b"body".to_vec()];
+        for no_trailer in [true, false] {
+            let expected = expected_output(&chunks, no_trailer);
+            for vectored in [true, false] {
+                for max_write in [1, 2, 3, 5, 7, 64] {
+                    let mut writer = LimitedWriter::new(vectored, max_write);
+                    let nw = encode_with(&chunks, no_trailer, &mut writer).await;
+                    assert_eq!(nw, expected.len() as u64);
+                    assert_eq!(writer.data, expected);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn coalesce_random() {
+        for _ in 0..32 {
+            let chunks: Vec<Vec<u8>> = (0..fastrand::usize(1..8))
+                .map(|_| {
+                    let mut chunk = vec![0u8; fastrand::usize(1..1024)];
+                    fastrand::fill(&mut chunk);
+                    chunk
+                })
+                .collect();
+            let no_trailer = fastrand::bool();
+            let expected = expected_output(&chunks, no_trailer);
+
+            let mut writer = LimitedWriter::new(fastrand::bool(), fastrand::usize(1..2048));
+            let nw = encode_with(&chunks, no_trailer, &mut writer).await;
+            assert_eq!(nw, expected.len() as u64);
+            assert_eq!(writer.data, expected);
+        }
+    }
+
     #[tokio::test]
     async fn encode_two_no_trailer() {
         let body_len: usize = 24;
@@ -12,7 +12,8 @@ use g3_io_ext::LimitedBufReadExt;
 use g3_types::net::{HttpHeaderMap, HttpHeaderValue};
 
 use super::HttpResponseParseError;
-use crate::{HttpHeaderLine, HttpLineParseError, HttpStatusLine};
+use crate::parse::{HttpHeaderBlockError, read_header_block};
+use crate::{HttpHeaderLine, HttpHeaderParsePolicy, HttpLineParseError, HttpStatusLine};
 
 pub struct HttpAdaptedResponse {
     pub version: Version,
@@ -36,6 +37,7 @@ impl HttpAdaptedResponse {
     pub async fn parse<R>(
         reader: &mut R,
         header_size: usize,
+        policy: &HttpHeaderParsePolicy,
     ) -> Result<Self, HttpResponseParseError>
     where
         R: AsyncBufRead + Unpin,
@@ -60,35 +62,25 @@ impl HttpAdaptedResponse {
 
         let mut rsp = HttpAdaptedResponse::build_from_status_line(&line_buf)?;
 
-        loop {
-            if read_size >= header_size {
-                return Err(HttpResponseParseError::TooLargeHeader(header_size));
+        read_header_block(reader, header_size, read_size, policy, |line| {
+            rsp.parse_header_line(line)
+        })
+        .await
+        .map_err(|e| match e {
+            HttpHeaderBlockError::Closed => HttpResponseParseError::RemoteClosed,
+            HttpHeaderBlockError::TooLargeHeader => {
+                HttpResponseParseError::TooLargeHeader(header_size)
             }
-            line_buf.clear();
-            let max_len = header_size - read_size;
-            let (found, nr) = reader
-                .limited_read_until(b'\n', max_len, &mut line_buf)
-                .await?;
-            if nr == 0 {
-                return Err(HttpResponseParseError::RemoteClosed);
+            HttpHeaderBlockError::TooLongLine => {
+                HttpResponseParseError::TooLongHeaderLine(policy.max_line_length)
             }
-            if !found {
-                return if nr < max_len {
-                    Err(HttpResponseParseError::RemoteClosed)
-                } else {
-                    Err(HttpResponseParseError::TooLargeHeader(header_size))
-                };
+            HttpHeaderBlockError::TooManyHeaders => {
+                HttpResponseParseError::TooManyHeaders(policy.max_count)
             }
-            read_size += nr;
-            if (line_buf.len() == 1 && line_buf[0] == b'\n')
-                || (line_buf.len() == 2 && line_buf[0] == b'\r' && line_buf[1] == b'\n')
-            {
-                // header end line
-                break;
-            }
-
-            rsp.parse_header_line(&line_buf)?;
-        }
+            HttpHeaderBlockError::FoldedLine => HttpResponseParseError::FoldedHeaderLine,
+            HttpHeaderBlockError::InvalidLine(e) => e,
+            HttpHeaderBlockError::IoFailed(e) => HttpResponseParseError::IoFailed(e),
+        })?;
 
         Ok(rsp)
     }
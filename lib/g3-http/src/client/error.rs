@@ -18,6 +18,12 @@ pub enum HttpResponseParseError {
     RemoteClosed,
     #[error("too large header, should be less than {0}")]
     TooLargeHeader(usize),
+    #[error("too long header line, should be no more than {0}")]
+    TooLongHeaderLine(usize),
+    #[error("too many headers, should be no more than {0}")]
+    TooManyHeaders(usize),
+    #[error("folded header line is not allowed")]
+    FoldedHeaderLine,
     #[error("invalid version {0:?}")]
     InvalidVersion(Version),
     #[error("invalid status line: {0}")]
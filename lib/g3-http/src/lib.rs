@@ -5,13 +5,16 @@
 
 mod parse;
 pub use parse::{
-    HttpChunkedLine, HttpHeaderLine, HttpLineParseError, HttpMethodLine, HttpStatusLine,
+    HttpChunkedLine, HttpHeaderFoldPolicy, HttpHeaderLine, HttpHeaderParsePolicy,
+    HttpLineParseError, HttpMethodLine, HttpStatusLine,
 };
 
 mod body;
 pub use body::{
-    ChunkedDataDecodeReader, H1BodyToChunkedTransfer, HttpBodyDecodeReader, HttpBodyReader,
-    HttpBodyType, StreamToChunkedTransfer, TrailerReadError, TrailerReader,
+    ChunkedDataDecodeReader, ChunkedWriteAdapter, H1BodyToChunkedTransfer,
+    H1BodyToChunkedTransferOwned, HttpBodyDecodeReader, HttpBodyReader, HttpBodyReaderOwned,
+    HttpBodyType, HttpChunkExtensionPolicy, PreviewableBodyTransfer, StreamToChunkedTransfer,
+    StreamToChunkedTransferOwned, TrailerReadError, TrailerReader,
 };
 
 pub mod client;
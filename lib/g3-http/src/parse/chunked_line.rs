@@ -3,43 +3,105 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use atoi::FromRadix16;
-
 use super::HttpLineParseError;
 
+const CHUNK_SIZE_MAX_HEX_DIGITS: usize = 16;
+
 pub struct HttpChunkedLine<'a> {
     pub chunk_size: u64,
     pub extension: Option<&'a str>,
 }
 
 impl<'a> HttpChunkedLine<'a> {
+    /// Parse the chunk size line leniently
+    ///
+    /// Bare LF line ending, whitespace around the chunk size, '+' prefixed chunk size and
+    /// zero padded chunk size longer than 16 hex digits are allowed.
     pub fn parse(buf: &'a [u8]) -> Result<HttpChunkedLine<'a>, HttpLineParseError> {
-        let (chunk_size, offset) = u64::from_radix_16(buf);
-        if offset == 0 {
-            return Err(HttpLineParseError::InvalidChunkSize);
-        }
+        Self::parse_with_mode(buf, false)
+    }
 
-        if buf.len() == offset {
-            return Err(HttpLineParseError::NotLongEnough);
+    /// Parse the chunk size line as defined in RFC 9112
+    pub fn parse_strict(buf: &'a [u8]) -> Result<HttpChunkedLine<'a>, HttpLineParseError> {
+        Self::parse_with_mode(buf, true)
+    }
+
+    fn parse_with_mode(
+        buf: &'a [u8],
+        strict: bool,
+    ) -> Result<HttpChunkedLine<'a>, HttpLineParseError> {
+        let line = match buf {
+            [line @ .., b'\r', b'\n'] => line,
+            [line @ .., b'\n'] => {
+                if strict {
+                    return Err(HttpLineParseError::BareLineFeed);
+                }
+                line
+            }
+            [line @ .., b'\r'] => line,
+            _ => return Err(HttpLineParseError::NotLongEnough),
+        };
+        if memchr::memchr(b'\r', line).is_some() {
+            return Err(HttpLineParseError::InvalidChunkSize);
         }
 
-        match buf[offset] {
-            b'\r' | b'\n' => Ok(HttpChunkedLine {
-                chunk_size,
-                extension: None,
-            }),
-            b';' => {
-                let extension = std::str::from_utf8(&buf[offset + 1..])
+        let (size, extension) = match memchr::memchr(b';', line) {
+            Some(p) => {
+                let extension = std::str::from_utf8(&line[p + 1..])
                     .map_err(HttpLineParseError::InvalidUtf8Encoding)?
                     .trim();
-                Ok(HttpChunkedLine {
-                    chunk_size,
-                    extension: Some(extension),
-                })
+                (&line[..p], Some(extension))
             }
-            _ => Err(HttpLineParseError::InvalidChunkSize),
+            None => (line, None),
+        };
+
+        let trimmed = trim_whitespace(size);
+        if trimmed.len() != size.len() && strict {
+            return Err(HttpLineParseError::ChunkSizeWhitespace);
         }
+        let size = match trimmed {
+            [b'+', left @ ..] => {
+                if strict {
+                    return Err(HttpLineParseError::ChunkSizeSignPrefix);
+                }
+                left
+            }
+            _ => trimmed,
+        };
+        if size.is_empty() || !size.iter().all(u8::is_ascii_hexdigit) {
+            return Err(HttpLineParseError::InvalidChunkSize);
+        }
+        if size.len() > CHUNK_SIZE_MAX_HEX_DIGITS {
+            if strict {
+                return Err(HttpLineParseError::ChunkSizeTooLong);
+            }
+            let zeros = size.iter().take_while(|c| **c == b'0').count();
+            if size.len() - zeros > CHUNK_SIZE_MAX_HEX_DIGITS {
+                return Err(HttpLineParseError::ChunkSizeTooLong);
+            }
+        }
+
+        // all hex digits and no overflow, checked above
+        let chunk_size = std::str::from_utf8(size)
+            .ok()
+            .and_then(|s| u64::from_str_radix(s, 16).ok())
+            .ok_or(HttpLineParseError::InvalidChunkSize)?;
+        Ok(HttpChunkedLine {
+            chunk_size,
+            extension,
+        })
+    }
+}
+
+/// Trim the SP and HTAB chars
+fn trim_whitespace(mut s: &[u8]) -> &[u8] {
+    while let [b' ' | b'\t', left @ ..] = s {
+        s = left;
+    }
+    while let [left @ .., b' ' | b'\t'] = s {
+        s = left;
     }
+    s
 }
 
 #[cfg(test)]
@@ -53,6 +115,9 @@ mod tests {
 
         let chunk = HttpChunkedLine::parse(b"1F\r\n").unwrap();
         assert_eq!(chunk.chunk_size, 0x1f);
+
+        let chunk = HttpChunkedLine::parse_strict(b"ffffffffffffffff\r\n").unwrap();
+        assert_eq!(chunk.chunk_size, u64::MAX);
     }
 
     #[test]
@@ -61,4 +126,65 @@ mod tests {
         assert_eq!(chunk.chunk_size, 1);
         assert_eq!(chunk.extension, Some("ieof"));
     }
+
+    #[test]
+    fn lenient() {
+        let chunk = HttpChunkedLine::parse(b"1F\n").unwrap();
+        assert_eq!(chunk.chunk_size, 0x1f);
+
+        let chunk = HttpChunkedLine::parse(b" 1F \r\n").unwrap();
+        assert_eq!(chunk.chunk_size, 0x1f);
+
+        let chunk = HttpChunkedLine::parse(b"+1F;a=b\r\n").unwrap();
+        assert_eq!(chunk.chunk_size, 0x1f);
+        assert_eq!(chunk.extension, Some("a=b"));
+
+        let chunk = HttpChunkedLine::parse(b"000000000000000001F\r\n").unwrap();
+        assert_eq!(chunk.chunk_size, 0x1f);
+
+        assert!(matches!(
+            HttpChunkedLine::parse(b"10000000000000000\r\n"),
+            Err(HttpLineParseError::ChunkSizeTooLong)
+        ));
+        assert!(matches!(
+            HttpChunkedLine::parse(b"-1\r\n"),
+            Err(HttpLineParseError::InvalidChunkSize)
+        ));
+        assert!(matches!(
+            HttpChunkedLine::parse(b"\r\n"),
+            Err(HttpLineParseError::InvalidChunkSize)
+        ));
+        assert!(matches!(
+            HttpChunkedLine::parse(b"1F\r;\r\n"),
+            Err(HttpLineParseError::InvalidChunkSize)
+        ));
+        assert!(matches!(
+            HttpChunkedLine::parse(b"1F"),
+            Err(HttpLineParseError::NotLongEnough)
+        ));
+    }
+
+    #[test]
+    fn strict() {
+        assert!(matches!(
+            HttpChunkedLine::parse_strict(b"1F\n"),
+            Err(HttpLineParseError::BareLineFeed)
+        ));
+        assert!(matches!(
+            HttpChunkedLine::parse_strict(b" 1F\r\n"),
+            Err(HttpLineParseError::ChunkSizeWhitespace)
+        ));
+        assert!(matches!(
+            HttpChunkedLine::parse_strict(b"1F ;a=b\r\n"),
+            Err(HttpLineParseError::ChunkSizeWhitespace)
+        ));
+        assert!(matches!(
+            HttpChunkedLine::parse_strict(b"+1F\r\n"),
+            Err(HttpLineParseError::ChunkSizeSignPrefix)
+        ));
+        assert!(matches!(
+            HttpChunkedLine::parse_strict(b"000000000000000001F\r\n"),
+            Err(HttpLineParseError::ChunkSizeTooLong)
+        ));
+    }
 }
@@ -27,4 +27,18 @@ pub enum HttpLineParseError {
     InvalidStatusCode,
     #[error("invalid chunk size")]
     InvalidChunkSize,
+    #[error("chunk extension not allowed")]
+    ChunkExtensionNotAllowed,
+    #[error("bare LF line ending")]
+    BareLineFeed,
+    #[error("whitespace around chunk size")]
+    ChunkSizeWhitespace,
+    #[error("sign prefixed chunk size")]
+    ChunkSizeSignPrefix,
+    #[error("chunk size too long (> 16 hex digits)")]
+    ChunkSizeTooLong,
+    #[error("chunk too large (> {0})")]
+    ChunkTooLarge(u64),
+    #[error("missing final CRLF of chunked body")]
+    MissingFinalCrlf,
 }
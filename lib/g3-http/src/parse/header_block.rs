@@ -0,0 +1,295 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io;
+use std::str::FromStr;
+
+use tokio::io::AsyncBufRead;
+
+use g3_io_ext::LimitedBufReadExt;
+
+const DEFAULT_MAX_LINE_LENGTH: usize = 16384;
+const DEFAULT_MAX_COUNT: usize = 256;
+
+/// The way to handle the obs-fold (folded header lines)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HttpHeaderFoldPolicy {
+    /// Return error if any folded header line is found
+    #[default]
+    Reject,
+    /// Replace each obs-fold with a single space, see RFC 7230 Section 3.2.4
+    Unfold,
+}
+
+impl FromStr for HttpHeaderFoldPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reject" => Ok(HttpHeaderFoldPolicy::Reject),
+            "unfold" => Ok(HttpHeaderFoldPolicy::Unfold),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The limits that should be applied when parsing the header lines
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HttpHeaderParsePolicy {
+    /// the max length of a single header line, after unfolded and not including the CRLF
+    pub max_line_length: usize,
+    /// the max number of header lines
+    pub max_count: usize,
+    pub fold_policy: HttpHeaderFoldPolicy,
+}
+
+impl Default for HttpHeaderParsePolicy {
+    fn default() -> Self {
+        HttpHeaderParsePolicy {
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            max_count: DEFAULT_MAX_COUNT,
+            fold_policy: HttpHeaderFoldPolicy::default(),
+        }
+    }
+}
+
+pub(crate) enum HttpHeaderBlockError<E> {
+    Closed,
+    TooLargeHeader,
+    TooLongLine,
+    TooManyHeaders,
+    FoldedLine,
+    InvalidLine(E),
+    IoFailed(io::Error),
+}
+
+fn trim_line_end(line: &[u8]) -> &[u8] {
+    match line {
+        [l @ .., b'\r', b'\n'] => l,
+        [l @ .., b'\n'] => l,
+        _ => line,
+    }
+}
+
+fn is_whitespace(b: &u8) -> bool {
+    *b == b' ' || *b == b'\t'
+}
+
+/// Read all the header lines after the start line, including the header end line.
+///
+/// `handle_line` will be called with each (unfolded) header line, without the trailing CRLF.
+pub(crate) async fn read_header_block<R, F, E>(
+    reader: &mut R,
+    header_size: usize,
+    mut read_size: usize,
+    policy: &HttpHeaderParsePolicy,
+    mut handle_line: F,
+) -> Result<(), HttpHeaderBlockError<E>>
+where
+    R: AsyncBufRead + Unpin,
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    let mut line_buf = Vec::<u8>::with_capacity(1024);
+    let mut header_line = Vec::<u8>::with_capacity(1024);
+    let mut header_count: usize = 0;
+    // the trailing CRLF is not counted in the line length
+    let line_max_size = policy.max_line_length.saturating_add(2);
+
+    loop {
+        if read_size >= header_size {
+            return Err(HttpHeaderBlockError::TooLargeHeader);
+        }
+        line_buf.clear();
+        let max_len = header_size - read_size;
+        let read_len = max_len.min(line_max_size);
+        let (found, nr) = reader
+            .limited_read_until(b'\n', read_len, &mut line_buf)
+            .await
+            .map_err(HttpHeaderBlockError::IoFailed)?;
+        if nr == 0 {
+            return Err(HttpHeaderBlockError::Closed);
+        }
+        if !found {
+            return if nr < read_len {
+                Err(HttpHeaderBlockError::Closed)
+            } else if line_max_size < max_len {
+                Err(HttpHeaderBlockError::TooLongLine)
+            } else {
+                Err(HttpHeaderBlockError::TooLargeHeader)
+            };
+        }
+        read_size += nr;
+
+        let line = trim_line_end(&line_buf);
+        if line.is_empty() {
+            // header end line
+            break;
+        }
+        if line.len() > policy.max_line_length {
+            return Err(HttpHeaderBlockError::TooLongLine);
+        }
+
+        if is_whitespace(&line[0]) {
+            // obs-fold, the line should be a continuation of the previous header line
+            if policy.fold_policy == HttpHeaderFoldPolicy::Reject || header_line.is_empty() {
+                return Err(HttpHeaderBlockError::FoldedLine);
+            }
+            while header_line.last().is_some_and(is_whitespace) {
+                header_line.pop();
+            }
+            let start = line
+                .iter()
+                .position(|b| !is_whitespace(b))
+                .unwrap_or(line.len());
+            header_line.push(b' ');
+            header_line.extend_from_slice(&line[start..]);
+            if header_line.len() > policy.max_line_length {
+                return Err(HttpHeaderBlockError::TooLongLine);
+            }
+            continue;
+        }
+
+        if !header_line.is_empty() {
+            handle_line(&header_line).map_err(HttpHeaderBlockError::InvalidLine)?;
+        }
+        header_count += 1;
+        if header_count > policy.max_count {
+            return Err(HttpHeaderBlockError::TooManyHeaders);
+        }
+        header_line.clear();
+        header_line.extend_from_slice(line);
+    }
+
+    if !header_line.is_empty() {
+        handle_line(&header_line).map_err(HttpHeaderBlockError::InvalidLine)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    async fn read_lines(
+        content: &[u8],
+        header_size: usize,
+        policy: &HttpHeaderParsePolicy,
+    ) -> Result<Vec<String>, HttpHeaderBlockError<()>> {
+        let mut reader = BufReader::new(content);
+        let mut lines = Vec::new();
+        read_header_block(&mut reader, header_size, 0, policy, |line| {
+            lines.push(String::from_utf8(line.to_vec()).unwrap());
+            Ok(())
+        })
+        .await?;
+        Ok(lines)
+    }
+
+    #[tokio::test]
+    async fn unfold() {
+        let policy = HttpHeaderParsePolicy {
+            fold_policy: HttpHeaderFoldPolicy::Unfold,
+            ..Default::default()
+        };
+        let content = b"A: 1\r\nB: 2 \r\n \t 3\r\n\t4\r\nC: 5\n\r\n";
+        let lines = read_lines(content, 1024, &policy).await.ok().unwrap();
+        assert_eq!(lines, vec!["A: 1", "B: 2 3 4", "C: 5"]);
+
+        // no previous header line to continue
+        let content = b" A: 1\r\n\r\n";
+        let r = read_lines(content, 1024, &policy).await;
+        assert!(matches!(r, Err(HttpHeaderBlockError::FoldedLine)));
+    }
+
+    #[tokio::test]
+    async fn reject_fold() {
+        let policy = HttpHeaderParsePolicy::default();
+        let content = b"A: 1\r\nB: 2\r\n 3\r\n\r\n";
+        let r = read_lines(content, 1024, &policy).await;
+        assert!(matches!(r, Err(HttpHeaderBlockError::FoldedLine)));
+    }
+
+    #[tokio::test]
+    async fn line_length() {
+        let policy = HttpHeaderParsePolicy {
+            max_line_length: 8,
+            fold_policy: HttpHeaderFoldPolicy::Unfold,
+            ..Default::default()
+        };
+        let lines = read_lines(b"A: 12345\r\n\r\n", 1024, &policy)
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(lines, vec!["A: 12345"]);
+
+        let r = read_lines(b"A: 123456\r\n\r\n", 1024, &policy).await;
+        assert!(matches!(r, Err(HttpHeaderBlockError::TooLongLine)));
+
+        // the line is too long after unfolded
+        let r = read_lines(b"A: 123\r\n 456\r\n\r\n", 1024, &policy).await;
+        assert!(matches!(r, Err(HttpHeaderBlockError::TooLongLine)));
+
+        // the total size limit should be checked first
+        let r = read_lines(b"A: 123456\r\n\r\n", 6, &policy).await;
+        assert!(matches!(r, Err(HttpHeaderBlockError::TooLargeHeader)));
+    }
+
+    #[tokio::test]
+    async fn header_count() {
+        let policy = HttpHeaderParsePolicy {
+            max_count: 4,
+            ..Default::default()
+        };
+        let mut content = Vec::new();
+        for i in 0..4 {
+            content.extend_from_slice(format!("X-{i}: {i}\r\n").as_bytes());
+        }
+        content.extend_from_slice(b"\r\n");
+        let lines = read_lines(&content, 1024, &policy).await.ok().unwrap();
+        assert_eq!(lines.len(), 4);
+
+        content.truncate(content.len() - 2);
+        content.extend_from_slice(b"X-4: 4\r\n\r\n");
+        let r = read_lines(&content, 1024, &policy).await;
+        assert!(matches!(r, Err(HttpHeaderBlockError::TooManyHeaders)));
+    }
+
+    #[tokio::test]
+    async fn random_blocks() {
+        let policy = HttpHeaderParsePolicy {
+            max_line_length: 64,
+            max_count: 16,
+            fold_policy: HttpHeaderFoldPolicy::Unfold,
+        };
+        for _ in 0..256 {
+            let mut content = Vec::new();
+            let count = fastrand::usize(1..24);
+            let mut expected_too_long = false;
+            for i in 0..count {
+                let value_len = fastrand::usize(0..80);
+                let value = "v".repeat(value_len);
+                let line = format!("X-{i:02}: {value}");
+                if line.len() > policy.max_line_length {
+                    expected_too_long = true;
+                }
+                content.extend_from_slice(line.as_bytes());
+                content.extend_from_slice(b"\r\n");
+            }
+            content.extend_from_slice(b"\r\n");
+
+            match read_lines(&content, 8192, &policy).await {
+                Ok(lines) => {
+                    assert!(count <= policy.max_count);
+                    assert!(!expected_too_long);
+                    assert_eq!(lines.len(), count);
+                }
+                Err(HttpHeaderBlockError::TooManyHeaders) => assert!(count > policy.max_count),
+                Err(HttpHeaderBlockError::TooLongLine) => assert!(expected_too_long),
+                Err(_) => unreachable!(),
+            }
+        }
+    }
+}
@@ -17,3 +17,7 @@ pub use method_line::HttpMethodLine;
 
 mod chunked_line;
 pub use chunked_line::HttpChunkedLine;
+
+mod header_block;
+pub(crate) use header_block::{HttpHeaderBlockError, read_header_block};
+pub use header_block::{HttpHeaderFoldPolicy, HttpHeaderParsePolicy};
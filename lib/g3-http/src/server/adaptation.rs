@@ -5,14 +5,15 @@
 
 use std::str::FromStr;
 
-use http::{HeaderName, Method, Uri, Version};
+use http::{HeaderName, Method, Uri, Version, header};
 use tokio::io::AsyncBufRead;
 
 use g3_io_ext::LimitedBufReadExt;
 use g3_types::net::{HttpHeaderMap, HttpHeaderValue};
 
 use super::HttpRequestParseError;
-use crate::{HttpHeaderLine, HttpLineParseError, HttpMethodLine};
+use crate::parse::{HttpHeaderBlockError, read_header_block};
+use crate::{HttpHeaderLine, HttpHeaderParsePolicy, HttpLineParseError, HttpMethodLine};
 
 pub struct HttpAdaptedRequest {
     pub method: Method,
@@ -20,6 +21,7 @@ pub struct HttpAdaptedRequest {
     pub version: Version,
     pub headers: HttpHeaderMap,
     pub content_length: Option<u64>,
+    pub has_transfer_encoding: bool,
 }
 
 impl HttpAdaptedRequest {
@@ -30,6 +32,38 @@ impl HttpAdaptedRequest {
             version,
             headers: HttpHeaderMap::default(),
             content_length: None,
+            has_transfer_encoding: false,
+        }
+    }
+
+    /// Check if both Transfer-Encoding and Content-Length are set,
+    /// which should be rejected as the ICAP server should fix the body framing headers
+    pub fn has_conflict_framing(&self) -> bool {
+        self.has_transfer_encoding && self.content_length.is_some()
+    }
+
+    /// Fix the body related headers, as no body will be sent after the adapted header
+    pub fn strip_body_headers(&mut self) {
+        // the client won't send the body, so never let the upstream wait for it
+        self.headers.remove(header::EXPECT);
+        self.has_transfer_encoding = false;
+        if let Some(v) = self.headers.get_mut(header::CONTENT_LENGTH) {
+            if self.content_length != Some(0) {
+                v.set_static_value("0");
+                self.content_length = Some(0);
+            }
+        }
+    }
+
+    /// Regenerate the Expect header, as the adapted body will be sent right after the adapted header
+    pub fn normalize_expect(&mut self) {
+        let Some(mut v) = self.headers.remove(header::EXPECT) else {
+            return;
+        };
+        // only 100-continue is defined, other expectations can not be fulfilled by us
+        if v.to_str().eq_ignore_ascii_case("100-continue") {
+            v.set_static_value("100-continue");
+            self.headers.insert(header::EXPECT, v);
         }
     }
 
@@ -37,6 +71,7 @@ impl HttpAdaptedRequest {
         reader: &mut R,
         header_size: usize,
         ignore_via: bool,
+        policy: &HttpHeaderParsePolicy,
     ) -> Result<Self, HttpRequestParseError>
     where
         R: AsyncBufRead + Unpin,
@@ -61,35 +96,25 @@ impl HttpAdaptedRequest {
 
         let mut req = HttpAdaptedRequest::build_from_method_line(&line_buf)?;
 
-        loop {
-            if read_size >= header_size {
-                return Err(HttpRequestParseError::TooLargeHeader(header_size));
-            }
-            line_buf.clear();
-            let max_len = header_size - read_size;
-            let (found, nr) = reader
-                .limited_read_until(b'\n', max_len, &mut line_buf)
-                .await?;
-            if nr == 0 {
-                return Err(HttpRequestParseError::ClientClosed);
+        read_header_block(reader, header_size, read_size, policy, |line| {
+            req.parse_header_line(line, ignore_via)
+        })
+        .await
+        .map_err(|e| match e {
+            HttpHeaderBlockError::Closed => HttpRequestParseError::ClientClosed,
+            HttpHeaderBlockError::TooLargeHeader => {
+                HttpRequestParseError::TooLargeHeader(header_size)
             }
-            if !found {
-                return if nr < max_len {
-                    Err(HttpRequestParseError::ClientClosed)
-                } else {
-                    Err(HttpRequestParseError::TooLargeHeader(header_size))
-                };
+            HttpHeaderBlockError::TooLongLine => {
+                HttpRequestParseError::TooLongHeaderLine(policy.max_line_length)
             }
-            read_size += nr;
-            if (line_buf.len() == 1 && line_buf[0] == b'\n')
-                || (line_buf.len() == 2 && line_buf[0] == b'\r' && line_buf[1] == b'\n')
-            {
-                // header end line
-                break;
+            HttpHeaderBlockError::TooManyHeaders => {
+                HttpRequestParseError::TooManyHeaders(policy.max_count)
             }
-
-            req.parse_header_line(&line_buf, ignore_via)?;
-        }
+            HttpHeaderBlockError::FoldedLine => HttpRequestParseError::FoldedHeaderLine,
+            HttpHeaderBlockError::InvalidLine(e) => e,
+            HttpHeaderBlockError::IoFailed(e) => HttpRequestParseError::IoFailed(e),
+        })?;
 
         Ok(req)
     }
@@ -143,6 +168,7 @@ impl HttpAdaptedRequest {
             }
             "transfer-encoding" => {
                 // this will always be chunked encoding
+                self.has_transfer_encoding = true;
                 return Ok(());
             }
             "via" => {
@@ -161,3 +187,76 @@ impl HttpAdaptedRequest {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    async fn parse(content: &[u8]) -> HttpAdaptedRequest {
+        let stream = tokio_test::io::Builder::new().read(content).build();
+        let mut buf_stream = BufReader::new(stream);
+        HttpAdaptedRequest::parse(
+            &mut buf_stream,
+            4096,
+            true,
+            &HttpHeaderParsePolicy::default(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn conflict_framing() {
+        let req = parse(
+            b"POST /test HTTP/1.1\r\nHost: example.net\r\n\
+              Content-Length: 4\r\nTransfer-Encoding: chunked\r\n\r\n",
+        )
+        .await;
+        assert_eq!(req.content_length, Some(4));
+        assert!(req.has_transfer_encoding);
+        assert!(req.has_conflict_framing());
+
+        let req = parse(b"POST /test HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n").await;
+        assert!(!req.has_conflict_framing());
+        assert!(!req.headers.contains_key(header::TRANSFER_ENCODING));
+    }
+
+    #[tokio::test]
+    async fn strip_body_headers() {
+        let mut req = parse(
+            b"POST /test HTTP/1.1\r\nHost: example.net\r\n\
+              Content-Length: 4\r\nExpect: 100-continue\r\n\r\n",
+        )
+        .await;
+        req.strip_body_headers();
+        assert_eq!(req.content_length, Some(0));
+        assert_eq!(
+            req.headers.get(header::CONTENT_LENGTH).unwrap().to_str(),
+            "0"
+        );
+        assert!(!req.headers.contains_key(header::EXPECT));
+
+        let mut req = parse(b"GET /test HTTP/1.1\r\nHost: example.net\r\n\r\n").await;
+        req.strip_body_headers();
+        assert!(req.content_length.is_none());
+        assert!(!req.headers.contains_key(header::CONTENT_LENGTH));
+    }
+
+    #[tokio::test]
+    async fn normalize_expect() {
+        let mut req =
+            parse(b"POST /test HTTP/1.1\r\nContent-Length: 4\r\nExpect: 100-Continue\r\n\r\n")
+                .await;
+        req.normalize_expect();
+        assert_eq!(
+            req.headers.get(header::EXPECT).unwrap().to_str(),
+            "100-continue"
+        );
+
+        let mut req =
+            parse(b"POST /test HTTP/1.1\r\nContent-Length: 4\r\nExpect: foo\r\n\r\n").await;
+        req.normalize_expect();
+        assert!(!req.headers.contains_key(header::EXPECT));
+    }
+}
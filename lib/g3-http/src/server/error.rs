@@ -16,6 +16,12 @@ pub enum HttpRequestParseError {
     ClientClosed,
     #[error("too large header, should be less than {0}")]
     TooLargeHeader(usize),
+    #[error("too long header line, should be no more than {0}")]
+    TooLongHeaderLine(usize),
+    #[error("too many headers, should be no more than {0}")]
+    TooManyHeaders(usize),
+    #[error("folded header line is not allowed")]
+    FoldedHeaderLine,
     #[error("invalid method line: {0}")]
     InvalidMethodLine(HttpLineParseError),
     #[error("unsupported method: {0}")]
@@ -54,7 +60,9 @@ impl HttpRequestParseError {
     pub fn status_code(&self) -> Option<StatusCode> {
         match self {
             HttpRequestParseError::IoFailed(_) | HttpRequestParseError::ClientClosed => None,
-            HttpRequestParseError::TooLargeHeader(_) => {
+            HttpRequestParseError::TooLargeHeader(_)
+            | HttpRequestParseError::TooLongHeaderLine(_)
+            | HttpRequestParseError::TooManyHeaders(_) => {
                 Some(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE)
             }
             HttpRequestParseError::UpgradeIsNotSupported
@@ -0,0 +1,193 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+//! Drive the h1 RESPMOD adapter against a mock ICAP server.
+//!
+//! The mock server selects the action by the service path:
+//!
+//! - `/pass` responds 204 after the preview data, so the original response is sent to the client
+//! - `/modify` responds 200 with a new HTTP response after receiving the whole body
+//! - `/error` responds 500
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{Context, anyhow};
+use http::{Request, Response, header};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::net::tcp::OwnedReadHalf;
+use url::Url;
+
+use g3_icap_client::respmod::IcapRespmodClient;
+use g3_icap_client::respmod::h1::{
+    IcapRespmodH1Params, IcapRespmodH1Session, RespmodAdaptationEndState,
+};
+use g3_icap_client::{IcapMethod, IcapServiceClient, IcapServiceConfig};
+
+const OPTIONS_RESPONSE: &[u8] = b"ICAP/1.0 200 OK\r\nMethods: RESPMOD\r\nISTag: \"mock\"\r\n\
+    Preview: 4\r\nEncapsulated: null-body=0\r\n\r\n";
+
+const PASS_RESPONSE: &[u8] =
+    b"ICAP/1.0 204 No Content\r\nISTag: \"mock\"\r\nEncapsulated: null-body=0\r\n\r\n";
+
+const MODIFY_RESPONSE: &[u8] = b"ICAP/1.0 200 OK\r\nISTag: \"mock\"\r\n\
+    Encapsulated: res-hdr=0, res-body=65\r\n\r\n\
+    HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 11\r\n\r\n\
+    b\r\nHELLO WORLD\r\n0\r\n\r\n";
+
+const ERROR_RESPONSE: &[u8] =
+    b"ICAP/1.0 500 Server Error\r\nISTag: \"mock\"\r\nEncapsulated: null-body=0\r\n\r\n";
+
+const ORIGINAL_BODY: &[u8] = b"hello world";
+
+/// Read a header block, return the lines without the ending empty line
+async fn read_header_block(r: &mut BufReader<OwnedReadHalf>) -> anyhow::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        if r.read_line(&mut line).await? == 0 {
+            return Err(anyhow!("connection closed"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(lines);
+        }
+        lines.push(line.to_string());
+    }
+}
+
+/// Read the chunked body, return whether the end of the body has been reached
+async fn read_chunked_body(r: &mut BufReader<OwnedReadHalf>) -> anyhow::Result<bool> {
+    loop {
+        let mut line = String::new();
+        if r.read_line(&mut line).await? == 0 {
+            return Err(anyhow!("connection closed"));
+        }
+        let (size, ext) = match line.trim_end().split_once(';') {
+            Some((size, ext)) => (size, ext.trim()),
+            None => (line.trim_end(), ""),
+        };
+        let size = usize::from_str_radix(size, 16).context("invalid chunk size")?;
+        if size == 0 {
+            read_header_block(r).await?;
+            return Ok(ext == "ieof");
+        }
+        let mut data = vec![0u8; size + 2];
+        r.read_exact(&mut data).await?;
+    }
+}
+
+async fn serve_connection(stream: tokio::net::TcpStream) -> anyhow::Result<()> {
+    let (r, mut w) = stream.into_split();
+    let mut r = BufReader::new(r);
+    loop {
+        let icap_header = read_header_block(&mut r).await?;
+        let request_line = icap_header
+            .first()
+            .ok_or_else(|| anyhow!("empty request"))?;
+        if request_line.starts_with("OPTIONS ") {
+            w.write_all(OPTIONS_RESPONSE).await?;
+            continue;
+        }
+
+        let encapsulated = icap_header
+            .iter()
+            .find_map(|line| line.strip_prefix("Encapsulated: "))
+            .ok_or_else(|| anyhow!("no Encapsulated header"))?;
+        for _ in 0..encapsulated.matches("-hdr=").count() {
+            read_header_block(&mut r).await?;
+        }
+        let mut body_finished = true;
+        let has_preview = icap_header.iter().any(|line| line.starts_with("Preview: "));
+        if encapsulated.contains("res-body=") {
+            let ieof = read_chunked_body(&mut r).await?;
+            body_finished = !has_preview || ieof;
+        }
+
+        if request_line.contains("/pass ") {
+            w.write_all(PASS_RESPONSE).await?;
+        } else if request_line.contains("/modify ") {
+            if !body_finished {
+                w.write_all(b"ICAP/1.0 100 Continue\r\n\r\n").await?;
+                read_chunked_body(&mut r).await?;
+            }
+            w.write_all(MODIFY_RESPONSE).await?;
+        } else {
+            w.write_all(ERROR_RESPONSE).await?;
+            return Ok(());
+        }
+    }
+}
+
+async fn start_mock_server() -> anyhow::Result<Url> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let _ = serve_connection(stream).await;
+            });
+        }
+    });
+    let url = Url::from_str(&format!("icap://{addr}/"))?;
+    Ok(url)
+}
+
+async fn run_session(base: &Url, service: &str) -> anyhow::Result<()> {
+    let url = base.join(service)?;
+    let config = IcapServiceConfig::new(IcapMethod::Respmod, url)?;
+    let client = IcapRespmodClient::new(Arc::new(IcapServiceClient::new(Arc::new(config))?));
+
+    let http_request = Request::get("http://example.net/index.html")
+        .header(header::HOST, "example.net")
+        .body(())?;
+    let orig_response = Response::builder()
+        .header(header::CONTENT_TYPE, "text/plain")
+        .header(header::CONTENT_LENGTH, ORIGINAL_BODY.len())
+        .body(())?;
+    let mut ups_body_reader = ORIGINAL_BODY;
+    let mut clt_writer = Vec::new();
+
+    let mut session = IcapRespmodH1Session::new(&client, IcapRespmodH1Params::default()).await?;
+    let r = session
+        .adapt(
+            &http_request,
+            &orig_response,
+            &mut ups_body_reader,
+            &mut clt_writer,
+        )
+        .await;
+    match r {
+        Ok(RespmodAdaptationEndState::OriginalTransferred) => {
+            println!("{service}: original response transferred");
+        }
+        Ok(RespmodAdaptationEndState::AdaptedTransferred(rsp)) => {
+            println!(
+                "{service}: adapted response transferred, status {}",
+                rsp.status()
+            );
+        }
+        Err(e) => {
+            println!(
+                "{service}: adaptation failed: {e}, client write started: {}",
+                session.run_state().clt_write_started()
+            );
+        }
+    }
+    if !clt_writer.is_empty() {
+        println!("{}", String::from_utf8_lossy(&clt_writer));
+    }
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let base = start_mock_server().await?;
+    for service in ["pass", "modify", "error"] {
+        run_session(&base, service).await?;
+    }
+    Ok(())
+}
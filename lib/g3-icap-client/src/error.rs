@@ -0,0 +1,44 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io;
+
+/// The peer side of an IO failure in the adaptation process
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IcapAdaptationPeer {
+    IcapServer,
+    HttpClient,
+    HttpUpstream,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IcapAdaptationIoDirection {
+    Read,
+    Write,
+}
+
+/// A borrowed view of the IO error inside an adaptation error
+#[derive(Debug)]
+pub struct IcapAdaptationIoError<'a> {
+    pub peer: IcapAdaptationPeer,
+    pub direction: IcapAdaptationIoDirection,
+    pub error: &'a io::Error,
+}
+
+impl IcapAdaptationIoError<'_> {
+    #[inline]
+    pub fn kind(&self) -> io::ErrorKind {
+        self.error.kind()
+    }
+
+    pub fn is_peer_reset(&self) -> bool {
+        matches!(
+            self.error.kind(),
+            io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+        )
+    }
+}
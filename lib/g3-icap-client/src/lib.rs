@@ -3,6 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+mod error;
 mod parse;
 mod reason;
 mod serialize;
@@ -14,7 +15,16 @@ pub mod respmod;
 mod options;
 pub use options::IcapServiceOptions;
 
+pub use error::{IcapAdaptationIoDirection, IcapAdaptationIoError, IcapAdaptationPeer};
+
 mod service;
 
+mod timing;
+pub use timing::{IcapTransactionPhase, IcapTransactionTiming};
+
 use service::{IcapClientConnection, IcapClientReader, IcapClientWriter};
-pub use service::{IcapMethod, IcapServiceClient, IcapServiceConfig};
+pub use service::{
+    IcapDebugCaptureConfig, IcapMethod, IcapPreviewMode, IcapPreviewPolicy, IcapPreviewRule,
+    IcapPreviewSize, IcapReplayConfig, IcapReplayMatch, IcapReplayUnmatchedAction,
+    IcapServiceClient, IcapServiceConfig, IcapTransferPolicy, IcapTransferPrecedence,
+};
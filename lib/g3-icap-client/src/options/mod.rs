@@ -8,6 +8,7 @@ pub(crate) use error::IcapOptionsParseError;
 
 mod response;
 pub use response::IcapServiceOptions;
+pub(crate) use response::path_extension;
 
 mod request;
 pub(crate) use request::IcapOptionsRequest;
@@ -26,6 +26,9 @@ pub struct IcapServiceOptions {
     pub(crate) support_204: bool,
     pub(crate) support_206: bool,
     pub(crate) preview_size: Option<usize>,
+    transfer_preview: Vec<String>,
+    transfer_ignore: Vec<String>,
+    transfer_complete: Vec<String>,
 }
 
 impl IcapServiceOptions {
@@ -40,6 +43,9 @@ impl IcapServiceOptions {
             support_204: false,
             support_206: false,
             preview_size: None,
+            transfer_preview: Vec::new(),
+            transfer_ignore: Vec::new(),
+            transfer_complete: Vec::new(),
         }
     }
 
@@ -54,9 +60,45 @@ impl IcapServiceOptions {
             support_204: false,
             support_206: false,
             preview_size: None,
+            transfer_preview: Vec::new(),
+            transfer_ignore: Vec::new(),
+            transfer_complete: Vec::new(),
         }
     }
 
+    /// The file extensions that should not be sent to the ICAP server, in lowercase
+    pub fn transfer_ignore(&self) -> &[String] {
+        &self.transfer_ignore
+    }
+
+    /// The file extensions that should be sent with the whole body, in lowercase
+    pub fn transfer_complete(&self) -> &[String] {
+        &self.transfer_complete
+    }
+
+    /// Check if the file extension of the path is in the Transfer-Ignore list
+    pub fn transfer_ignored(&self, path: &str) -> bool {
+        match path_extension(path) {
+            Some(ext) => self.transfer_ignored_extension(ext),
+            None => false,
+        }
+    }
+
+    /// Check if the file extension should not be sent to the ICAP server.
+    ///
+    /// The wildcard `*` in Transfer-Ignore matches all the extensions that are not
+    /// listed in Transfer-Preview or Transfer-Complete.
+    pub fn transfer_ignored_extension(&self, ext: &str) -> bool {
+        let find = |list: &[String]| list.iter().any(|v| v.eq_ignore_ascii_case(ext));
+        if find(&self.transfer_ignore) {
+            return true;
+        }
+        if self.transfer_ignore.iter().any(|v| v == "*") {
+            return !find(&self.transfer_preview) && !find(&self.transfer_complete);
+        }
+        false
+    }
+
     pub(crate) fn expired(&self) -> bool {
         if let Some(expire) = self.expire {
             Instant::now() >= expire
@@ -207,9 +249,28 @@ impl IcapServiceOptions {
                     .map_err(|_| IcapOptionsParseError::InvalidHeaderValue("Preview"))?;
                 self.preview_size = Some(size);
             }
+            "transfer-preview" => self.transfer_preview = parse_extension_list(header.value),
+            "transfer-ignore" => self.transfer_ignore = parse_extension_list(header.value),
+            "transfer-complete" => self.transfer_complete = parse_extension_list(header.value),
             _ => {}
         }
 
         Ok(())
     }
 }
+
+/// Get the file extension in the path of the HTTP request URL
+pub(crate) fn path_extension(path: &str) -> Option<&str> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    file_name.rsplit_once('.').map(|(_, ext)| ext)
+}
+
+fn parse_extension_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_ascii_lowercase())
+        .collect()
+}
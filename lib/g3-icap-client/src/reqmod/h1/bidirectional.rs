@@ -8,15 +8,23 @@ use std::sync::Arc;
 use anyhow::anyhow;
 use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite};
 
-use g3_http::{H1BodyToChunkedTransfer, HttpBodyDecodeReader, HttpBodyReader};
-use g3_io_ext::{IdleCheck, LimitedBufReadExt, StreamCopy, StreamCopyConfig, StreamCopyError};
+use g3_http::{
+    HttpBodyDecodeReader, HttpBodyReader, HttpHeaderParsePolicy, PreviewableBodyTransfer,
+};
+use g3_io_ext::{
+    IdleCheck, IdleDirection, IdleTracker, LimitedBufReadExt, StreamCopy, StreamCopyConfig,
+};
 
+use super::recv_request::recv_adapted_http_request;
 use super::{
-    H1ReqmodAdaptationError, HttpAdaptedRequest, HttpRequestForAdaptation,
-    HttpRequestUpstreamWriter, ReqmodAdaptationEndState, ReqmodAdaptationRunState,
+    H1ReqmodAdaptationError, HttpRequestForAdaptation, HttpRequestUpstreamWriter,
+    ReqmodAdaptationEndState, ReqmodAdaptationRunState,
 };
 use crate::reqmod::response::ReqmodResponse;
-use crate::{IcapClientReader, IcapClientWriter, IcapServiceClient};
+use crate::{
+    IcapClientReader, IcapClientWriter, IcapServiceClient, IcapTransactionPhase,
+    IcapTransactionTiming,
+};
 
 pub(super) struct BidirectionalRecvIcapResponse<'a, I: IdleCheck> {
     pub(super) icap_client: &'a Arc<IcapServiceClient>,
@@ -27,13 +35,14 @@ pub(super) struct BidirectionalRecvIcapResponse<'a, I: IdleCheck> {
 impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
     pub(super) async fn transfer_and_recv<CR>(
         self,
-        mut body_transfer: &mut H1BodyToChunkedTransfer<'_, CR, IcapClientWriter>,
+        mut body_transfer: &mut PreviewableBodyTransfer<'_, CR, IcapClientWriter>,
+        timing: &mut IcapTransactionTiming,
     ) -> Result<ReqmodResponse, H1ReqmodAdaptationError>
     where
         CR: AsyncBufRead + Unpin,
     {
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -41,33 +50,38 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
 
                 r = &mut body_transfer => {
                     return match r {
-                        Ok(_) => self.recv_icap_response().await,
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1ReqmodAdaptationError::HttpClientReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1ReqmodAdaptationError::IcapServerWriteFailed(e)),
+                        Ok(_) => {
+                            timing.mark(IcapTransactionPhase::SendBody);
+                            self.recv_icap_response(timing).await
+                        }
+                        Err(e) => Err(H1ReqmodAdaptationError::from_clt_to_icap_copy(e)),
                     };
                 }
                 r = self.icap_reader.fill_wait_data() => {
                     return match r {
-                        Ok(true) => self.recv_icap_response().await,
+                        Ok(true) => self.recv_icap_response(timing).await,
                         Ok(false) => Err(H1ReqmodAdaptationError::IcapServerConnectionClosed),
                         Err(e) => Err(H1ReqmodAdaptationError::IcapServerReadFailed(e)),
                     };
                 }
                 n = idle_interval.tick() => {
-                    if body_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if body_transfer.no_cached_data() {
-                                Err(H1ReqmodAdaptationError::HttpClientReadIdle)
-                            } else {
+                    let body_idle = body_transfer.is_idle().then(|| {
+                        if body_transfer.no_cached_data() {
+                            IdleDirection::ClientRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[body_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return if direction == IdleDirection::WriteBlocked {
                                 Err(H1ReqmodAdaptationError::IcapServerWriteIdle)
+                            } else {
+                                Err(H1ReqmodAdaptationError::HttpClientReadIdle)
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         body_transfer.reset_active();
                     }
 
@@ -79,23 +93,30 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
         }
     }
 
-    async fn recv_icap_response(self) -> Result<ReqmodResponse, H1ReqmodAdaptationError> {
+    async fn recv_icap_response(
+        self,
+        timing: &mut IcapTransactionTiming,
+    ) -> Result<ReqmodResponse, H1ReqmodAdaptationError> {
         let rsp = ReqmodResponse::parse(
             self.icap_reader,
             self.icap_client.config.icap_max_header_size,
             &self.icap_client.config.respond_shared_names,
         )
         .await?;
+        timing.mark(IcapTransactionPhase::WaitResponse);
         Ok(rsp)
     }
 }
 
 pub(super) struct BidirectionalRecvHttpRequest<'a, I: IdleCheck> {
     pub(super) http_body_line_max_size: usize,
+    pub(super) http_body_strict_chunked: bool,
     pub(super) http_req_add_no_via_header: bool,
     pub(super) copy_config: StreamCopyConfig,
     pub(super) idle_checker: &'a I,
     pub(crate) http_header_size: usize,
+    pub(crate) http_max_header_size: usize,
+    pub(crate) http_header_policy: HttpHeaderParsePolicy,
     pub(crate) icap_read_finished: bool,
 }
 
@@ -103,7 +124,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
     pub(super) async fn transfer<H, CR, UW>(
         &mut self,
         state: &mut ReqmodAdaptationRunState,
-        clt_body_transfer: &mut H1BodyToChunkedTransfer<'_, CR, IcapClientWriter>,
+        clt_body_transfer: &mut PreviewableBodyTransfer<'_, CR, IcapClientWriter>,
         orig_http_request: &H,
         icap_reader: &mut IcapClientReader,
         ups_writer: &mut UW,
@@ -113,12 +134,16 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
         CR: AsyncBufRead + Unpin,
         UW: HttpRequestUpstreamWriter<H> + Unpin,
     {
-        let http_req = HttpAdaptedRequest::parse(
+        let http_req = recv_adapted_http_request(
             icap_reader,
             self.http_header_size,
+            self.http_max_header_size,
             self.http_req_add_no_via_header,
+            &self.http_header_policy,
+            true,
         )
         .await?;
+        state.mark_icap_phase(IcapTransactionPhase::ParseAdaptedHeader);
         let body_content_length = http_req.content_length;
 
         let final_req = orig_http_request.adapt_with_body(http_req);
@@ -135,11 +160,13 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
             Some(expected) => {
                 let mut ups_body_reader =
                     HttpBodyDecodeReader::new_chunked(icap_reader, self.http_body_line_max_size);
+                ups_body_reader.set_strict_chunked(self.http_body_strict_chunked);
                 let mut ups_body_transfer =
                     StreamCopy::new(&mut ups_body_reader, ups_writer, &self.copy_config);
                 self.do_transfer(clt_body_transfer, &mut ups_body_transfer)
                     .await?;
 
+                state.mark_icap_phase(IcapTransactionPhase::RelayAdaptedBody);
                 state.mark_ups_send_all();
                 let copied = ups_body_transfer.copied_size();
                 if ups_body_reader.trailer(128).await.is_ok() {
@@ -156,11 +183,13 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
             None => {
                 let mut ups_body_reader =
                     HttpBodyReader::new_chunked(icap_reader, self.http_body_line_max_size);
+                ups_body_reader.set_strict_chunked(self.http_body_strict_chunked);
                 let mut ups_body_transfer =
                     StreamCopy::new(&mut ups_body_reader, ups_writer, &self.copy_config);
                 self.do_transfer(clt_body_transfer, &mut ups_body_transfer)
                     .await?;
 
+                state.mark_icap_phase(IcapTransactionPhase::RelayAdaptedBody);
                 state.mark_ups_send_all();
                 self.icap_read_finished = ups_body_transfer.finished();
 
@@ -171,7 +200,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
 
     async fn do_transfer<CR, IR, UW>(
         &self,
-        mut clt_body_transfer: &mut H1BodyToChunkedTransfer<'_, CR, IcapClientWriter>,
+        mut clt_body_transfer: &mut PreviewableBodyTransfer<'_, CR, IcapClientWriter>,
         mut ups_body_transfer: &mut StreamCopy<'_, IR, UW>,
     ) -> Result<(), H1ReqmodAdaptationError>
     where
@@ -180,7 +209,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
         UW: AsyncWrite + Unpin,
     {
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -189,42 +218,53 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
                         Ok(_) => {
                             match ups_body_transfer.await {
                                 Ok(_) => Ok(()),
-                                Err(StreamCopyError::ReadFailed(e)) => Err(H1ReqmodAdaptationError::IcapServerReadFailed(e)),
-                                Err(StreamCopyError::WriteFailed(e)) => Err(H1ReqmodAdaptationError::HttpUpstreamWriteFailed(e)),
+                                Err(e) => Err(H1ReqmodAdaptationError::from_icap_to_ups_copy(e)),
                             }
                         }
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1ReqmodAdaptationError::HttpClientReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1ReqmodAdaptationError::IcapServerWriteFailed(e)),
+                        Err(e) => Err(H1ReqmodAdaptationError::from_clt_to_icap_copy(e)),
                     };
                 }
                 r = &mut ups_body_transfer => {
                     return match r {
                         Ok(_) => Ok(()),
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1ReqmodAdaptationError::IcapServerReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1ReqmodAdaptationError::HttpUpstreamWriteFailed(e)),
+                        Err(e) => Err(H1ReqmodAdaptationError::from_icap_to_ups_copy(e)),
                     };
                 }
                 n = idle_interval.tick() => {
-                    if clt_body_transfer.is_idle() && ups_body_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if clt_body_transfer.is_idle() {
-                                if clt_body_transfer.no_cached_data() {
+                    let clt_body_idle = clt_body_transfer.is_idle().then(|| {
+                        if clt_body_transfer.no_cached_data() {
+                            IdleDirection::ClientRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    let ups_body_idle = ups_body_transfer.is_idle().then(|| {
+                        if ups_body_transfer.no_cached_data() {
+                            IdleDirection::IcapRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[clt_body_idle, ups_body_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return match direction {
+                                IdleDirection::ClientRead => {
                                     Err(H1ReqmodAdaptationError::HttpClientReadIdle)
-                                } else {
-                                    Err(H1ReqmodAdaptationError::IcapServerWriteIdle)
                                 }
-                            } else if ups_body_transfer.no_cached_data() {
-                                Err(H1ReqmodAdaptationError::IcapServerReadIdle)
-                            } else {
-                                Err(H1ReqmodAdaptationError::HttpUpstreamWriteIdle)
+                                IdleDirection::IcapRead => {
+                                    Err(H1ReqmodAdaptationError::IcapServerReadIdle)
+                                }
+                                _ => {
+                                    if clt_body_transfer.no_cached_data() {
+                                        Err(H1ReqmodAdaptationError::HttpUpstreamWriteIdle)
+                                    } else {
+                                        Err(H1ReqmodAdaptationError::IcapServerWriteIdle)
+                                    }
+                                }
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         clt_body_transfer.reset_active();
                         ups_body_transfer.reset_active();
                     }
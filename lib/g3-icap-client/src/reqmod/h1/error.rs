@@ -9,10 +9,11 @@ use thiserror::Error;
 
 use g3_http::client::HttpResponseParseError;
 use g3_http::server::HttpRequestParseError;
-use g3_io_ext::IdleForceQuitReason;
+use g3_io_ext::{IdleForceQuitReason, StreamCopyError};
 
 use crate::reason::IcapErrorReason;
 use crate::reqmod::IcapReqmodParseError;
+use crate::{IcapAdaptationIoDirection, IcapAdaptationIoError, IcapAdaptationPeer};
 
 #[derive(Debug, Error)]
 pub enum H1ReqmodAdaptationError {
@@ -28,6 +29,10 @@ pub enum H1ReqmodAdaptationError {
     InvalidIcapServerHttpResponse(#[from] HttpResponseParseError),
     #[error("invalid http request from icap server: {0}")]
     InvalidIcapServerHttpRequest(#[from] HttpRequestParseError),
+    #[error("too large http request header from icap server, should be less than {0}")]
+    IcapServerHttpRequestHeaderTooLarge(usize),
+    #[error("invalid http request framing from icap server: {0}")]
+    InvalidIcapServerHttpRequestFraming(&'static str),
     #[error("invalid http body from icap server: {0:?}")]
     InvalidHttpBodyFromIcapServer(anyhow::Error),
     #[error("error response from icap server: {0} ({1} {2})")]
@@ -53,3 +58,68 @@ pub enum H1ReqmodAdaptationError {
     #[error("not implemented feature: {0}")]
     NotImplemented(&'static str),
 }
+
+impl H1ReqmodAdaptationError {
+    /// map the error when copying http body from client to icap server
+    pub(super) fn from_clt_to_icap_copy(e: StreamCopyError) -> Self {
+        match e {
+            StreamCopyError::ReadFailed(e) => H1ReqmodAdaptationError::HttpClientReadFailed(e),
+            StreamCopyError::WriteFailed(e) => H1ReqmodAdaptationError::IcapServerWriteFailed(e),
+        }
+    }
+
+    /// map the error when copying http body from icap server to upstream
+    pub(super) fn from_icap_to_ups_copy(e: StreamCopyError) -> Self {
+        match e {
+            StreamCopyError::ReadFailed(e) => H1ReqmodAdaptationError::IcapServerReadFailed(e),
+            StreamCopyError::WriteFailed(e) => H1ReqmodAdaptationError::HttpUpstreamWriteFailed(e),
+        }
+    }
+
+    /// map the error when copying http body from client to upstream directly
+    pub(super) fn from_clt_to_ups_copy(e: StreamCopyError) -> Self {
+        match e {
+            StreamCopyError::ReadFailed(e) => H1ReqmodAdaptationError::HttpClientReadFailed(e),
+            StreamCopyError::WriteFailed(e) => H1ReqmodAdaptationError::HttpUpstreamWriteFailed(e),
+        }
+    }
+
+    pub fn io_error(&self) -> Option<IcapAdaptationIoError<'_>> {
+        let (peer, direction, error) = match self {
+            H1ReqmodAdaptationError::IcapServerWriteFailed(e) => (
+                IcapAdaptationPeer::IcapServer,
+                IcapAdaptationIoDirection::Write,
+                e,
+            ),
+            H1ReqmodAdaptationError::IcapServerReadFailed(e) => (
+                IcapAdaptationPeer::IcapServer,
+                IcapAdaptationIoDirection::Read,
+                e,
+            ),
+            H1ReqmodAdaptationError::HttpClientReadFailed(e) => (
+                IcapAdaptationPeer::HttpClient,
+                IcapAdaptationIoDirection::Read,
+                e,
+            ),
+            H1ReqmodAdaptationError::HttpUpstreamWriteFailed(e) => (
+                IcapAdaptationPeer::HttpUpstream,
+                IcapAdaptationIoDirection::Write,
+                e,
+            ),
+            _ => return None,
+        };
+        Some(IcapAdaptationIoError {
+            peer,
+            direction,
+            error,
+        })
+    }
+
+    pub fn error_kind(&self) -> Option<io::ErrorKind> {
+        self.io_error().map(|e| e.kind())
+    }
+
+    pub fn is_peer_reset(&self) -> bool {
+        self.io_error().map(|e| e.is_peer_reset()).unwrap_or(false)
+    }
+}
@@ -6,16 +6,17 @@
 use std::io::{IoSlice, Write};
 
 use bytes::BufMut;
-use tokio::io::{AsyncBufRead, AsyncWriteExt};
+use tokio::io::AsyncBufRead;
 
-use g3_http::{H1BodyToChunkedTransfer, HttpBodyReader, HttpBodyType};
-use g3_io_ext::{IdleCheck, LimitedWriteExt, StreamCopy, StreamCopyError};
+use g3_http::{HttpBodyReader, HttpBodyType, PreviewableBodyTransfer};
+use g3_io_ext::{IdleCheck, LimitedWriteExt, StreamCopy};
 
 use super::{
     BidirectionalRecvHttpRequest, BidirectionalRecvIcapResponse, H1ReqmodAdaptationError,
     HttpRequestAdapter, HttpRequestForAdaptation, HttpRequestUpstreamWriter,
     ReqmodAdaptationEndState, ReqmodAdaptationRunState,
 };
+use crate::IcapTransactionPhase;
 use crate::reason::IcapErrorReason;
 use crate::reqmod::IcapReqmodResponsePayload;
 use crate::reqmod::response::ReqmodResponse;
@@ -49,21 +50,22 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
 
         let chunk_start = format!("{:x}\r\n", clt_body.len());
 
-        let icap_w = &mut self.icap_connection.writer;
-        icap_w
-            .write_all_vectored([
+        self.send_replayable_request(
+            state,
+            &[
+                IcapTransactionPhase::SendHeader,
+                IcapTransactionPhase::SendBody,
+            ],
+            [
                 IoSlice::new(&icap_header),
                 IoSlice::new(&http_header),
                 IoSlice::new(chunk_start.as_bytes()),
                 IoSlice::new(&clt_body),
                 IoSlice::new(b"\r\n0\r\n\r\n"),
-            ])
-            .await
-            .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
-        icap_w
-            .flush()
-            .await
-            .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
+            ],
+            clt_body.len(),
+        )
+        .await?;
         self.icap_connection.mark_writer_finished();
 
         self.handle_small_body_response(state, http_request, ups_writer)
@@ -110,9 +112,11 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                 .await
                 .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
         }
+        state.mark_icap_phase(IcapTransactionPhase::SendHeader);
 
         self.recv_send_trailer(&mut trailer_reader).await?;
 
+        state.mark_icap_phase(IcapTransactionPhase::SendBody);
         state.clt_read_finished = true;
         self.icap_connection.mark_writer_finished();
 
@@ -142,8 +146,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                 r = &mut trailer_transfer => {
                     return match r {
                         Ok(_) => Ok(()),
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1ReqmodAdaptationError::HttpClientReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1ReqmodAdaptationError::IcapServerWriteFailed(e)),
+                        Err(e) => Err(H1ReqmodAdaptationError::from_clt_to_icap_copy(e)),
                     };
                 }
                 n = idle_interval.tick() => {
@@ -188,6 +191,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
             &self.icap_client.config.respond_shared_names,
         )
         .await?;
+        state.mark_icap_phase(IcapTransactionPhase::WaitResponse);
         let shared_headers = rsp.take_shared_headers();
         if !shared_headers.is_empty() {
             state.respond_shared_headers = Some(shared_headers);
@@ -267,21 +271,22 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
             .write_all_vectored([IoSlice::new(&icap_header), IoSlice::new(&http_header)])
             .await
             .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
+        state.mark_icap_phase(IcapTransactionPhase::SendHeader);
 
-        let mut body_transfer = H1BodyToChunkedTransfer::new(
+        let mut body_transfer = PreviewableBodyTransfer::without_preview(
             clt_body_io,
-            &mut self.icap_connection.writer,
             clt_body_type,
             self.http_body_line_max_size,
             self.copy_config,
-        );
+        )
+        .resume(&mut self.icap_connection.writer);
         let bidirectional_transfer = BidirectionalRecvIcapResponse {
             icap_client: &self.icap_client,
             icap_reader: &mut self.icap_connection.reader,
             idle_checker: &self.idle_checker,
         };
         let mut rsp = bidirectional_transfer
-            .transfer_and_recv(&mut body_transfer)
+            .transfer_and_recv(&mut body_transfer, &mut state.icap_timing)
             .await?;
         let shared_headers = rsp.take_shared_headers();
         if !shared_headers.is_empty() {
@@ -343,10 +348,13 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                 } else {
                     let mut bidirectional_transfer = BidirectionalRecvHttpRequest {
                         http_body_line_max_size: self.http_body_line_max_size,
+                        http_body_strict_chunked: self.icap_client.config.strict_chunked,
                         http_req_add_no_via_header: self.http_req_add_no_via_header,
                         copy_config: self.copy_config,
                         idle_checker: &self.idle_checker,
                         http_header_size: header_size,
+                        http_max_header_size: self.icap_client.config.http_max_request_header_size,
+                        http_header_policy: self.icap_client.config.http_header_policy,
                         icap_read_finished: false,
                     };
                     let r = bidirectional_transfer
@@ -359,6 +367,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                         )
                         .await?;
                     if body_transfer.finished() {
+                        state.mark_icap_phase(IcapTransactionPhase::SendBody);
                         state.clt_read_finished = true;
                         self.icap_connection.mark_writer_finished();
                         if bidirectional_transfer.icap_read_finished {
@@ -6,15 +6,14 @@
 use std::io::{IoSlice, Write};
 
 use bytes::BufMut;
-use tokio::io::AsyncWriteExt;
-
-use g3_io_ext::{IdleCheck, LimitedWriteExt};
+use g3_io_ext::IdleCheck;
 
 use super::{
     H1ReqmodAdaptationError, HttpRequestAdapter, HttpRequestForAdaptation,
     HttpRequestUpstreamWriter, ReqmodAdaptationEndState, ReqmodAdaptationMidState,
     ReqmodAdaptationRunState,
 };
+use crate::IcapTransactionPhase;
 use crate::reason::IcapErrorReason;
 use crate::reqmod::response::ReqmodResponse;
 use crate::reqmod::{IcapReqmodParseError, IcapReqmodResponsePayload};
@@ -52,15 +51,13 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         let http_header = http_request.serialize_for_adapter();
         let icap_header = self.build_header_only_request(http_request, http_header.len());
 
-        let icap_w = &mut self.icap_connection.writer;
-        icap_w
-            .write_all_vectored([IoSlice::new(&icap_header), IoSlice::new(&http_header)])
-            .await
-            .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
-        icap_w
-            .flush()
-            .await
-            .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
+        self.send_replayable_request(
+            state,
+            &[IcapTransactionPhase::SendHeader],
+            [IoSlice::new(&icap_header), IoSlice::new(&http_header)],
+            0,
+        )
+        .await?;
         self.icap_connection.mark_writer_finished();
 
         let mut rsp = ReqmodResponse::parse(
@@ -69,6 +66,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
             &self.icap_client.config.respond_shared_names,
         )
         .await?;
+        state.mark_icap_phase(IcapTransactionPhase::WaitResponse);
         let shared_headers = rsp.take_shared_headers();
         if !shared_headers.is_empty() {
             state.respond_shared_headers = Some(shared_headers);
@@ -133,6 +131,21 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
     }
 
     pub async fn xfer_connect<H>(
+        self,
+        state: &mut ReqmodAdaptationRunState,
+        http_request: &H,
+    ) -> Result<ReqmodAdaptationMidState<H>, H1ReqmodAdaptationError>
+    where
+        H: HttpRequestForAdaptation,
+    {
+        state.icap_timing = self.icap_timing;
+        let icap_client = self.icap_client.clone();
+        let r = self.xfer_connect_header(state, http_request).await;
+        icap_client.record_transaction_timing(&state.icap_timing);
+        r
+    }
+
+    async fn xfer_connect_header<H>(
         mut self,
         state: &mut ReqmodAdaptationRunState,
         http_request: &H,
@@ -143,15 +156,13 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         let http_header = http_request.serialize_for_adapter();
         let icap_header = self.build_header_only_request(http_request, http_header.len());
 
-        let icap_w = &mut self.icap_connection.writer;
-        icap_w
-            .write_all_vectored([IoSlice::new(&icap_header), IoSlice::new(&http_header)])
-            .await
-            .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
-        icap_w
-            .flush()
-            .await
-            .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
+        self.send_replayable_request(
+            state,
+            &[IcapTransactionPhase::SendHeader],
+            [IoSlice::new(&icap_header), IoSlice::new(&http_header)],
+            0,
+        )
+        .await?;
         self.icap_connection.mark_writer_finished();
 
         let mut rsp = ReqmodResponse::parse(
@@ -160,6 +171,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
             &self.icap_client.config.respond_shared_names,
         )
         .await?;
+        state.mark_icap_phase(IcapTransactionPhase::WaitResponse);
         let shared_headers = rsp.take_shared_headers();
         if !shared_headers.is_empty() {
             state.respond_shared_headers = Some(shared_headers);
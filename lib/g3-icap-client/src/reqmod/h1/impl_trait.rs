@@ -16,10 +16,20 @@ impl HttpRequestForAdaptation for HttpProxyClientRequest {
         &self.method
     }
 
+    fn uri_path(&self) -> &str {
+        self.uri.path()
+    }
+
     fn body_type(&self) -> Option<HttpBodyType> {
         self.body_type()
     }
 
+    fn content_type(&self) -> Option<&str> {
+        self.end_to_end_headers
+            .get(header::CONTENT_TYPE)
+            .map(|v| v.to_str())
+    }
+
     fn serialize_for_adapter(&self) -> Vec<u8> {
         self.serialize_for_adapter()
     }
@@ -46,10 +56,20 @@ impl HttpRequestForAdaptation for HttpTransparentRequest {
         &self.method
     }
 
+    fn uri_path(&self) -> &str {
+        self.uri.path()
+    }
+
     fn body_type(&self) -> Option<HttpBodyType> {
         self.body_type()
     }
 
+    fn content_type(&self) -> Option<&str> {
+        self.end_to_end_headers
+            .get(header::CONTENT_TYPE)
+            .map(|v| v.to_str())
+    }
+
     fn serialize_for_adapter(&self) -> Vec<u8> {
         self.serialize_for_adapter()
     }
@@ -3,6 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::fmt;
 use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -18,7 +19,10 @@ use g3_io_ext::{IdleCheck, StreamCopyConfig};
 use g3_types::net::HttpHeaderMap;
 
 use super::IcapReqmodClient;
-use crate::{IcapClientConnection, IcapServiceClient, IcapServiceOptions};
+use crate::{
+    IcapClientConnection, IcapServiceClient, IcapServiceOptions, IcapTransactionPhase,
+    IcapTransactionTiming,
+};
 
 mod error;
 pub use error::H1ReqmodAdaptationError;
@@ -35,12 +39,15 @@ pub use http_response::HttpAdapterErrorResponse;
 mod forward_body;
 mod forward_header;
 mod preview;
+mod replay;
 
 mod impl_trait;
 
 pub trait HttpRequestForAdaptation {
     fn method(&self) -> &Method;
+    fn uri_path(&self) -> &str;
     fn body_type(&self) -> Option<HttpBodyType>;
+    fn content_type(&self) -> Option<&str>;
     fn serialize_for_adapter(&self) -> Vec<u8>;
     fn append_upgrade_header(&self, buf: &mut Vec<u8>);
     fn adapt_with_body(&self, other: HttpAdaptedRequest) -> Self;
@@ -60,12 +67,15 @@ impl IcapReqmodClient {
         http_req_add_no_via_header: bool,
         idle_checker: I,
     ) -> anyhow::Result<HttpRequestAdapter<I>> {
+        let mut icap_timing = IcapTransactionTiming::new(Instant::now());
         let icap_client = self.inner.clone();
         let (icap_connection, icap_options) = icap_client.fetch_connection().await?;
+        icap_timing.mark(IcapTransactionPhase::Connect);
         Ok(HttpRequestAdapter {
             icap_client,
             icap_connection,
             icap_options,
+            icap_timing,
             copy_config,
             http_body_line_max_size,
             http_req_add_no_via_header,
@@ -80,6 +90,7 @@ pub struct HttpRequestAdapter<I: IdleCheck> {
     icap_client: Arc<IcapServiceClient>,
     icap_connection: IcapClientConnection,
     icap_options: Arc<IcapServiceOptions>,
+    icap_timing: IcapTransactionTiming,
     copy_config: StreamCopyConfig,
     http_body_line_max_size: usize,
     http_req_add_no_via_header: bool,
@@ -94,7 +105,11 @@ pub struct ReqmodAdaptationRunState {
     pub dur_ups_send_all: Option<Duration>,
     pub clt_read_finished: bool,
     pub ups_write_finished: bool,
+    pub icap_retried: bool,
+    /// the preview size used for the ICAP request, `None` if no preview is sent
+    pub preview_size: Option<usize>,
     pub(crate) respond_shared_headers: Option<HttpHeaderMap>,
+    icap_timing: IcapTransactionTiming,
 }
 
 impl ReqmodAdaptationRunState {
@@ -105,7 +120,10 @@ impl ReqmodAdaptationRunState {
             dur_ups_send_all: None,
             clt_read_finished: false,
             ups_write_finished: false,
+            icap_retried: false,
+            preview_size: None,
             respond_shared_headers: None,
+            icap_timing: IcapTransactionTiming::new(task_create_instant),
         }
     }
 
@@ -113,6 +131,16 @@ impl ReqmodAdaptationRunState {
         self.respond_shared_headers.take()
     }
 
+    /// Get the timing of each phase of the ICAP transaction
+    #[inline]
+    pub fn icap_timing(&self) -> &IcapTransactionTiming {
+        &self.icap_timing
+    }
+
+    pub(crate) fn mark_icap_phase(&mut self, phase: IcapTransactionPhase) {
+        self.icap_timing.mark(phase);
+    }
+
     pub(crate) fn mark_ups_send_header(&mut self) {
         self.dur_ups_send_header = Some(self.task_create_instant.elapsed());
     }
@@ -129,6 +157,12 @@ impl ReqmodAdaptationRunState {
 }
 
 impl<I: IdleCheck> HttpRequestAdapter<I> {
+    /// Set the task id, which will be used in the name of the debug capture files
+    pub fn set_task_id<T: fmt::Display>(&mut self, id: T) {
+        self.icap_client
+            .start_debug_capture(&mut self.icap_connection, id);
+    }
+
     pub fn set_client_addr(&mut self, addr: SocketAddr) {
         self.client_addr = Some(addr);
     }
@@ -146,11 +180,13 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         }
     }
 
-    fn preview_size(&self) -> Option<usize> {
-        if self.icap_client.config.disable_preview {
-            return None;
-        }
-        self.icap_options.preview_size
+    fn preview_size<H: HttpRequestForAdaptation>(&self, http_request: &H) -> Option<usize> {
+        self.icap_client.config.preview_size(
+            self.icap_options.preview_size,
+            http_request.method(),
+            http_request.content_type(),
+            http_request.uri_path(),
+        )
     }
 
     pub async fn xfer<H, CR, UW>(
@@ -160,6 +196,27 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         clt_body_io: Option<&mut CR>,
         ups_writer: &mut UW,
     ) -> Result<ReqmodAdaptationEndState<H>, H1ReqmodAdaptationError>
+    where
+        H: HttpRequestForAdaptation,
+        CR: AsyncBufRead + Unpin,
+        UW: HttpRequestUpstreamWriter<H> + Unpin,
+    {
+        state.icap_timing = self.icap_timing;
+        let icap_client = self.icap_client.clone();
+        let r = self
+            .xfer_by_body_type(state, http_request, clt_body_io, ups_writer)
+            .await;
+        icap_client.record_transaction_timing(&state.icap_timing);
+        r
+    }
+
+    async fn xfer_by_body_type<H, CR, UW>(
+        self,
+        state: &mut ReqmodAdaptationRunState,
+        http_request: &H,
+        clt_body_io: Option<&mut CR>,
+        ups_writer: &mut UW,
+    ) -> Result<ReqmodAdaptationEndState<H>, H1ReqmodAdaptationError>
     where
         H: HttpRequestForAdaptation,
         CR: AsyncBufRead + Unpin,
@@ -171,7 +228,8 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                     "no client http body io supplied while body type is not none",
                 ));
             };
-            if let Some(preview_size) = self.preview_size() {
+            state.preview_size = self.preview_size(http_request);
+            if let Some(preview_size) = state.preview_size {
                 self.xfer_with_preview(
                     state,
                     http_request,
@@ -213,7 +271,9 @@ pub struct ReqmodRecvHttpResponseBody {
 
 impl ReqmodRecvHttpResponseBody {
     pub fn body_reader(&mut self) -> HttpBodyReader<'_, impl AsyncBufRead + use<>> {
-        HttpBodyReader::new_chunked(&mut self.icap_connection.reader, 1024)
+        let mut body_reader = HttpBodyReader::new_chunked(&mut self.icap_connection.reader, 1024);
+        body_reader.set_strict_chunked(self.icap_client.config.strict_chunked);
+        body_reader
     }
 
     pub async fn save_connection(mut self) {
@@ -6,10 +6,10 @@
 use std::io::{IoSlice, Write};
 
 use bytes::BufMut;
-use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
 
-use g3_http::{ChunkedDataDecodeReader, H1BodyToChunkedTransfer, HttpBodyReader, HttpBodyType};
-use g3_io_ext::{IdleCheck, LimitedWriteExt, StreamCopy, StreamCopyError};
+use g3_http::{HttpBodyReader, HttpBodyType, PreviewableBodyTransfer};
+use g3_io_ext::{IdleCheck, StreamCopy};
 
 use super::{
     BidirectionalRecvHttpRequest, BidirectionalRecvIcapResponse, H1ReqmodAdaptationError,
@@ -18,7 +18,9 @@ use super::{
 };
 use crate::reason::IcapErrorReason;
 use crate::reqmod::IcapReqmodResponsePayload;
+use crate::reqmod::multipart::{MultipartPreviewScanner, MultipartScanResult};
 use crate::reqmod::response::ReqmodResponse;
+use crate::{IcapClientWriter, IcapPreviewMode, IcapTransactionPhase};
 
 impl<I: IdleCheck> HttpRequestAdapter<I> {
     fn build_preview_request(&self, http_header_len: usize, preview_size: usize) -> Vec<u8> {
@@ -48,73 +50,43 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         CR: AsyncBufRead + Unpin,
         UW: HttpRequestUpstreamWriter<H> + Unpin,
     {
-        let mut left_chunk_size = 0;
-        let preview_buf: Vec<u8>;
-        let clt_body_type = match clt_body_type {
-            HttpBodyType::ReadUntilEnd => {
-                let mut clt_body_reader = HttpBodyReader::new_read_until_end(clt_body_io);
-                preview_buf = self
-                    .read_preview_data(&mut clt_body_reader, preview_size)
-                    .await?;
-                if preview_buf.is_empty() {
-                    state.clt_read_finished = true;
+        let mut body_transfer = PreviewableBodyTransfer::new(
+            clt_body_io,
+            clt_body_type,
+            self.http_body_line_max_size,
+            preview_size,
+            self.copy_config,
+        );
+        let mut multipart_scanner = match self.icap_client.config.preview_mode {
+            IcapPreviewMode::Bytes => None,
+            IcapPreviewMode::MultipartParts => http_request
+                .content_type()
+                .and_then(MultipartPreviewScanner::new),
+        };
+        self.read_preview_data(&mut body_transfer, multipart_scanner.as_mut())
+            .await?;
+        let body_transfer = match body_transfer.into_whole_body() {
+            Ok((body, None)) => {
+                state.clt_read_finished = true;
+                if body.is_empty() && clt_body_type == HttpBodyType::ReadUntilEnd {
                     return self
                         .xfer_without_body(state, http_request, ups_writer)
                         .await;
                 }
-                if clt_body_reader.finished() {
-                    state.clt_read_finished = true;
-                    return self
-                        .xfer_small_body(state, http_request, preview_buf, ups_writer)
-                        .await;
-                }
-
-                HttpBodyType::ReadUntilEnd
+                return self
+                    .xfer_small_body(state, http_request, body, ups_writer)
+                    .await;
             }
-            HttpBodyType::ContentLength(n) => {
-                let mut clt_body_reader = HttpBodyReader::new_fixed_length(clt_body_io, n);
-                preview_buf = self
-                    .read_preview_data(&mut clt_body_reader, preview_size)
-                    .await?;
-                if clt_body_reader.finished() {
-                    state.clt_read_finished = true;
-                    return self
-                        .xfer_small_body(state, http_request, preview_buf, ups_writer)
-                        .await;
-                }
-
-                HttpBodyType::ContentLength(n - (preview_buf.len() as u64))
-            }
-            HttpBodyType::Chunked => {
-                let mut clt_body_decoder =
-                    ChunkedDataDecodeReader::new(clt_body_io, self.http_body_line_max_size);
-                preview_buf = self
-                    .read_preview_data(&mut clt_body_decoder, preview_size)
-                    .await?;
-                if clt_body_decoder.finished() {
-                    let trailer_reader =
-                        HttpBodyReader::new_trailer(clt_body_io, self.http_body_line_max_size);
-                    return self
-                        .xfer_small_body_chunked(
-                            state,
-                            http_request,
-                            preview_buf,
-                            trailer_reader,
-                            ups_writer,
-                        )
-                        .await;
-                }
-                left_chunk_size = clt_body_decoder.left_chunk_size().ok_or_else(|| {
-                    H1ReqmodAdaptationError::InternalServerError(
-                        "broken chunked encoding after preview read",
-                    )
-                })?;
-
-                HttpBodyType::Chunked
+            Ok((body, Some(trailer_reader))) => {
+                return self
+                    .xfer_small_body_chunked(state, http_request, body, trailer_reader, ups_writer)
+                    .await;
             }
+            Err(body_transfer) => *body_transfer,
         };
 
-        self.send_preview_data(http_request, &preview_buf).await?;
+        self.send_preview_data(state, http_request, body_transfer.preview_data())
+            .await?;
 
         let mut rsp = ReqmodResponse::parse(
             &mut self.icap_connection.reader,
@@ -129,33 +101,15 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
 
         match rsp.code {
             100 => {
-                let mut body_transfer = match clt_body_type {
-                    HttpBodyType::ReadUntilEnd => H1BodyToChunkedTransfer::new_read_until_end(
-                        clt_body_io,
-                        &mut self.icap_connection.writer,
-                        self.copy_config,
-                    ),
-                    HttpBodyType::ContentLength(len) => H1BodyToChunkedTransfer::new_fixed_length(
-                        clt_body_io,
-                        &mut self.icap_connection.writer,
-                        len,
-                        self.copy_config,
-                    ),
-                    HttpBodyType::Chunked => H1BodyToChunkedTransfer::new_chunked_after_preview(
-                        clt_body_io,
-                        &mut self.icap_connection.writer,
-                        left_chunk_size,
-                        self.http_body_line_max_size,
-                        self.copy_config,
-                    ),
-                };
+                state.mark_icap_phase(IcapTransactionPhase::WaitContinue);
+                let mut body_transfer = body_transfer.resume(&mut self.icap_connection.writer);
                 let bidirectional_transfer = BidirectionalRecvIcapResponse {
                     icap_client: &self.icap_client,
                     icap_reader: &mut self.icap_connection.reader,
                     idle_checker: &self.idle_checker,
                 };
                 let rsp = bidirectional_transfer
-                    .transfer_and_recv(&mut body_transfer)
+                    .transfer_and_recv(&mut body_transfer, &mut state.icap_timing)
                     .await?;
                 if body_transfer.finished() {
                     state.clt_read_finished = true;
@@ -213,10 +167,16 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                         } else {
                             let mut bidirectional_transfer = BidirectionalRecvHttpRequest {
                                 http_body_line_max_size: self.http_body_line_max_size,
+                                http_body_strict_chunked: self.icap_client.config.strict_chunked,
                                 http_req_add_no_via_header: self.http_req_add_no_via_header,
                                 copy_config: self.copy_config,
                                 idle_checker: &self.idle_checker,
                                 http_header_size: header_size,
+                                http_max_header_size: self
+                                    .icap_client
+                                    .config
+                                    .http_max_request_header_size,
+                                http_header_policy: self.icap_client.config.http_header_policy,
                                 icap_read_finished: false,
                             };
                             let r = bidirectional_transfer
@@ -229,6 +189,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                                 )
                                 .await?;
                             if body_transfer.finished() {
+                                state.mark_icap_phase(IcapTransactionPhase::SendBody);
                                 state.clt_read_finished = true;
                                 self.icap_connection.mark_writer_finished();
                                 if bidirectional_transfer.icap_read_finished {
@@ -262,6 +223,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                 }
             }
             204 => {
+                state.mark_icap_phase(IcapTransactionPhase::WaitResponse);
                 self.icap_connection.mark_writer_finished();
                 if rsp.payload == IcapReqmodResponsePayload::NoPayload {
                     self.icap_connection.mark_reader_finished();
@@ -273,24 +235,21 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                     .map_err(H1ReqmodAdaptationError::HttpUpstreamWriteFailed)?;
                 state.mark_ups_send_header();
 
-                match clt_body_type {
-                    HttpBodyType::ReadUntilEnd | HttpBodyType::ContentLength(_) => {
+                match body_transfer.into_plain_body() {
+                    Ok((preview_buf, clt_body_reader)) => {
                         self.send_original_plain_body_to_upstream(
                             rsp,
-                            clt_body_type,
-                            clt_body_io,
+                            clt_body_reader,
                             ups_writer,
                             preview_buf,
                         )
                         .await?;
                     }
-                    HttpBodyType::Chunked => {
+                    Err(body_transfer) => {
                         self.send_original_chunked_body_to_upstream(
                             rsp,
-                            clt_body_io,
+                            *body_transfer,
                             ups_writer,
-                            preview_buf,
-                            left_chunk_size,
                         )
                         .await?;
                     }
@@ -303,6 +262,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
             }
             206 => Err(H1ReqmodAdaptationError::NotImplemented("ICAP-REQMOD-206")),
             n if (200..300).contains(&n) => {
+                state.mark_icap_phase(IcapTransactionPhase::WaitResponse);
                 // FIXME we should stop send the pending HTTP body to ICAP server?
                 self.icap_connection.mark_writer_finished();
                 match rsp.payload {
@@ -359,31 +319,38 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         }
     }
 
-    async fn read_preview_data<R>(
+    async fn read_preview_data<CR>(
         &mut self,
-        reader: &mut R,
-        max_size: usize,
-    ) -> Result<Vec<u8>, H1ReqmodAdaptationError>
+        body_transfer: &mut PreviewableBodyTransfer<'_, CR, IcapClientWriter>,
+        mut multipart_scanner: Option<&mut MultipartPreviewScanner>,
+    ) -> Result<(), H1ReqmodAdaptationError>
     where
-        R: AsyncRead + Unpin,
+        CR: AsyncBufRead + Unpin,
     {
         let mut idle_interval = self.idle_checker.interval_timer();
         let mut is_active = false;
         let mut idle_count = 0;
 
-        let mut buf = vec![0u8; max_size];
-        let mut read_offset = 0;
-
-        while read_offset < max_size {
+        loop {
             tokio::select! {
                 biased;
 
-                r = reader.read(&mut buf[read_offset..]) => {
+                r = body_transfer.read_preview() => {
                     match r {
                         Ok(0) => break,
-                        Ok(n) => {
+                        Ok(_) => {
                             is_active = true;
-                            read_offset += n;
+                            if let Some(scanner) = &mut multipart_scanner {
+                                match scanner.scan(body_transfer.preview_data()) {
+                                    MultipartScanResult::Pending => {}
+                                    MultipartScanResult::FileData(end) => {
+                                        // end the preview before the file data
+                                        body_transfer.set_preview_end(end);
+                                        break;
+                                    }
+                                    MultipartScanResult::Plain => multipart_scanner = None,
+                                }
+                            }
                         }
                         Err(e) => {
                             return Err(H1ReqmodAdaptationError::HttpClientReadFailed(e));
@@ -410,12 +377,12 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
             }
         }
 
-        buf.truncate(read_offset);
-        Ok(buf)
+        Ok(())
     }
 
     async fn send_preview_data<H>(
         &mut self,
+        state: &mut ReqmodAdaptationRunState,
         http_request: &H,
         data: &[u8],
     ) -> Result<(), H1ReqmodAdaptationError>
@@ -428,28 +395,28 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
 
         let chunk_start = format!("{:x}\r\n", data.len());
 
-        let icap_w = &mut self.icap_connection.writer;
-        icap_w
-            .write_all_vectored([
+        self.send_replayable_request(
+            state,
+            &[
+                IcapTransactionPhase::SendHeader,
+                IcapTransactionPhase::SendPreview,
+            ],
+            [
                 IoSlice::new(&icap_header),
                 IoSlice::new(&http_header),
                 IoSlice::new(chunk_start.as_bytes()),
                 IoSlice::new(data),
                 IoSlice::new(b"\r\n0\r\n\r\n"),
-            ])
-            .await
-            .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
-        icap_w
-            .flush()
-            .await
-            .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)
+            ],
+            data.len(),
+        )
+        .await
     }
 
     async fn send_original_plain_body_to_upstream<CR, UW>(
         self,
         icap_rsp: ReqmodResponse,
-        clt_body_type: HttpBodyType,
-        clt_body_io: &mut CR,
+        mut clt_body_reader: HttpBodyReader<'_, CR>,
         ups_writer: &mut UW,
         preview_buf: Vec<u8>,
     ) -> Result<(), H1ReqmodAdaptationError>
@@ -466,8 +433,6 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
             .await
             .map_err(H1ReqmodAdaptationError::HttpUpstreamWriteFailed)?;
 
-        let mut clt_body_reader =
-            HttpBodyReader::new(clt_body_io, clt_body_type, self.http_body_line_max_size);
         let mut body_copy = StreamCopy::new(&mut clt_body_reader, ups_writer, &self.copy_config);
 
         let mut idle_interval = self.idle_checker.interval_timer();
@@ -480,8 +445,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                 r = &mut body_copy => {
                     return match r {
                         Ok(_) => Ok(()),
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1ReqmodAdaptationError::HttpClientReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1ReqmodAdaptationError::HttpUpstreamWriteFailed(e)),
+                        Err(e) => Err(H1ReqmodAdaptationError::from_clt_to_ups_copy(e)),
                     };
                 }
                 n = idle_interval.tick() => {
@@ -510,13 +474,11 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         }
     }
 
-    async fn send_original_chunked_body_to_upstream<CR, UW>(
+    async fn send_original_chunked_body_to_upstream<'a, CR, UW>(
         self,
         icap_rsp: ReqmodResponse,
-        clt_body_io: &mut CR,
-        ups_writer: &mut UW,
-        preview_buf: Vec<u8>,
-        left_chunk_size: u64,
+        body_transfer: PreviewableBodyTransfer<'a, CR, IcapClientWriter>,
+        ups_writer: &'a mut UW,
     ) -> Result<(), H1ReqmodAdaptationError>
     where
         CR: AsyncBufRead + Unpin,
@@ -526,23 +488,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
             self.icap_client.save_connection(self.icap_connection);
         }
 
-        let chunk_header = format!("{:x}\r\n", preview_buf.len());
-        ups_writer
-            .write_all_vectored([
-                IoSlice::new(chunk_header.as_bytes()),
-                IoSlice::new(&preview_buf),
-                IoSlice::new(b"\r\n"),
-            ])
-            .await
-            .map_err(H1ReqmodAdaptationError::HttpUpstreamWriteFailed)?;
-
-        let mut chunked_transfer = H1BodyToChunkedTransfer::new_chunked_after_preview(
-            clt_body_io,
-            ups_writer,
-            left_chunk_size,
-            self.http_body_line_max_size,
-            self.copy_config,
-        );
+        let mut chunked_transfer = body_transfer.resume_with_preview(ups_writer);
 
         let mut idle_interval = self.idle_checker.interval_timer();
         let mut idle_count = 0;
@@ -554,8 +500,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                 r = &mut chunked_transfer => {
                     return match r {
                         Ok(_) => Ok(()),
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1ReqmodAdaptationError::HttpClientReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1ReqmodAdaptationError::HttpUpstreamWriteFailed(e)),
+                        Err(e) => Err(H1ReqmodAdaptationError::from_clt_to_ups_copy(e)),
                     };
                 }
                 n = idle_interval.tick() => {
@@ -584,3 +529,226 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::pin::Pin;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use http::Version;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::time::Instant;
+    use url::Url;
+
+    use g3_http::server::HttpProxyClientRequest;
+    use g3_io_ext::{IdleForceQuitReason, IdleInterval, IdleWheel, StreamCopyConfig};
+    use g3_types::net::ConnectionPoolConfig;
+
+    use crate::reqmod::IcapReqmodClient;
+    use crate::{IcapMethod, IcapServiceClient, IcapServiceConfig, IcapTransactionTiming};
+
+    const CONTINUE_DELAY: Duration = Duration::from_millis(150);
+    const RESPONSE_DELAY: Duration = Duration::from_millis(200);
+    const BODY_DELAY: Duration = Duration::from_millis(120);
+    const TOLERANCE: Duration = Duration::from_millis(80);
+
+    const MOCK_OPTIONS_RESPONSE: &[u8] =
+        b"ICAP/1.0 200 OK\r\nMethods: REQMOD\r\nISTag: \"mock\"\r\nPreview: 4\r\n\
+        Encapsulated: null-body=0\r\n\r\n";
+
+    const ADAPTED_HTTP_HEADER: &str =
+        "POST /upload HTTP/1.1\r\nHost: example.net\r\nContent-Length: 11\r\n\r\n";
+
+    const CLIENT_REQUEST: &[u8] = b"POST http://example.net/upload HTTP/1.1\r\n\
+        Host: example.net\r\nContent-Length: 11\r\n\r\nhello world";
+
+    async fn read_until_end<R: AsyncBufRead + Unpin>(r: &mut R, end: &[u8]) -> Option<Vec<u8>> {
+        let mut buf = Vec::new();
+        while !buf.ends_with(end) {
+            match r.read_until(b'\n', &mut buf).await {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => {}
+            }
+        }
+        Some(buf)
+    }
+
+    /// A mock ICAP server which delays each phase of the REQMOD transaction
+    async fn start_mock_server() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let (r, mut w) = stream.into_split();
+                    let mut r = BufReader::new(r);
+                    loop {
+                        let Some(header) = read_until_end(&mut r, b"\r\n\r\n").await else {
+                            return;
+                        };
+                        if !header.starts_with(b"REQMOD ") {
+                            if w.write_all(MOCK_OPTIONS_RESPONSE).await.is_err() {
+                                return;
+                            }
+                            continue;
+                        }
+
+                        // the HTTP header and the preview data
+                        if read_until_end(&mut r, b"\r\n0\r\n\r\n").await.is_none() {
+                            return;
+                        }
+                        tokio::time::sleep(CONTINUE_DELAY).await;
+                        if w.write_all(b"ICAP/1.0 100 Continue\r\n\r\n").await.is_err() {
+                            return;
+                        }
+
+                        // the remaining HTTP body
+                        if read_until_end(&mut r, b"\r\n0\r\n\r\n").await.is_none() {
+                            return;
+                        }
+                        tokio::time::sleep(RESPONSE_DELAY).await;
+                        let rsp = format!(
+                            "ICAP/1.0 200 OK\r\nISTag: \"mock\"\r\n\
+                             Encapsulated: req-hdr=0, req-body={}\r\n\r\n\
+                             {ADAPTED_HTTP_HEADER}5\r\nHELLO\r\n",
+                            ADAPTED_HTTP_HEADER.len()
+                        );
+                        if w.write_all(rsp.as_bytes()).await.is_err() {
+                            return;
+                        }
+                        tokio::time::sleep(BODY_DELAY).await;
+                        if w.write_all(b"6\r\n WORLD\r\n0\r\n\r\n").await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        Url::from_str(&format!("icap://{addr}/reqmod")).unwrap()
+    }
+
+    struct MockIdleChecker(Arc<IdleWheel>);
+
+    impl IdleCheck for MockIdleChecker {
+        fn interval_timer(&self) -> IdleInterval {
+            self.0.register()
+        }
+
+        fn check_quit(&self, _idle_count: usize) -> bool {
+            false
+        }
+
+        fn check_force_quit(&self) -> Option<IdleForceQuitReason> {
+            None
+        }
+    }
+
+    #[derive(Default)]
+    struct MockUpstreamWriter {
+        buf: Vec<u8>,
+    }
+
+    impl AsyncWrite for MockUpstreamWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.buf).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl HttpRequestUpstreamWriter<HttpProxyClientRequest> for MockUpstreamWriter {
+        async fn send_request_header(&mut self, req: &HttpProxyClientRequest) -> io::Result<()> {
+            self.buf.extend_from_slice(&req.serialize_for_origin());
+            Ok(())
+        }
+    }
+
+    fn assert_phase(timing: &IcapTransactionTiming, phase: IcapTransactionPhase, delay: Duration) {
+        let dur = timing.phase_duration(phase).unwrap();
+        assert!(
+            dur >= delay && dur < delay + TOLERANCE,
+            "{} phase took {dur:?}, expected {delay:?}",
+            phase.as_str()
+        );
+    }
+
+    #[tokio::test]
+    async fn timing_breakdown() {
+        let url = start_mock_server().await;
+        let mut config = IcapServiceConfig::new(IcapMethod::Reqmod, url).unwrap();
+        config.connection_pool = ConnectionPoolConfig::new(4, 0);
+        let client = Arc::new(IcapServiceClient::new(Arc::new(config)).unwrap());
+        // wait for the options to be loaded in background, the connection will then be pooled
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.options().preview_size.is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let reqmod_client = IcapReqmodClient::new(client.clone());
+        let adapter = reqmod_client
+            .h1_adapter(
+                StreamCopyConfig::default(),
+                1024,
+                true,
+                MockIdleChecker(IdleWheel::spawn(Duration::from_secs(1))),
+            )
+            .await
+            .unwrap();
+
+        let mut clt_r = BufReader::new(CLIENT_REQUEST);
+        let mut version = Version::HTTP_11;
+        let http_request = HttpProxyClientRequest::parse_basic(&mut clt_r, 4096, &mut version)
+            .await
+            .unwrap();
+
+        let mut state = ReqmodAdaptationRunState::new(Instant::now());
+        let mut ups_writer = MockUpstreamWriter::default();
+        let r = adapter
+            .xfer(&mut state, &http_request, Some(&mut clt_r), &mut ups_writer)
+            .await;
+        assert!(matches!(
+            r,
+            Ok(ReqmodAdaptationEndState::AdaptedTransferred(_))
+        ));
+        assert_eq!(state.preview_size, Some(4));
+        assert!(ups_writer.buf.ends_with(b"\r\n\r\nHELLO WORLD"));
+
+        let timing = state.icap_timing();
+        assert_phase(timing, IcapTransactionPhase::Connect, Duration::ZERO);
+        assert_phase(timing, IcapTransactionPhase::WaitContinue, CONTINUE_DELAY);
+        assert_phase(timing, IcapTransactionPhase::WaitResponse, RESPONSE_DELAY);
+        assert_phase(timing, IcapTransactionPhase::RelayAdaptedBody, BODY_DELAY);
+        for phase in [
+            IcapTransactionPhase::SendHeader,
+            IcapTransactionPhase::SendPreview,
+            IcapTransactionPhase::SendBody,
+            IcapTransactionPhase::ParseAdaptedHeader,
+        ] {
+            assert_phase(timing, phase, Duration::ZERO);
+        }
+
+        let snap = client.phase_duration_snapshot(IcapTransactionPhase::WaitResponse);
+        assert_eq!(snap.count(), 1);
+        assert!(snap.sum() >= RESPONSE_DELAY.as_micros() as u64);
+    }
+}
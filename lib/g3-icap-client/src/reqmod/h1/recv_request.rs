@@ -4,18 +4,52 @@
  */
 
 use anyhow::anyhow;
-use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, AsyncWriteExt};
 
-use g3_http::{HttpBodyDecodeReader, HttpBodyReader};
-use g3_io_ext::{IdleCheck, StreamCopy, StreamCopyError};
+use g3_http::{HttpBodyDecodeReader, HttpBodyReader, HttpHeaderParsePolicy};
+use g3_io_ext::{IdleCheck, StreamCopy};
 
 use super::{
     H1ReqmodAdaptationError, HttpAdaptedRequest, HttpRequestAdapter, HttpRequestForAdaptation,
     HttpRequestUpstreamWriter, ReqmodAdaptationEndState, ReqmodAdaptationMidState,
     ReqmodAdaptationRunState,
 };
+use crate::IcapTransactionPhase;
 use crate::reqmod::response::ReqmodResponse;
 
+/// Receive the adapted http request header from the ICAP server,
+/// and fix the body related headers according to whether a body will follow
+pub(super) async fn recv_adapted_http_request<R>(
+    reader: &mut R,
+    header_size: usize,
+    max_header_size: usize,
+    ignore_via: bool,
+    policy: &HttpHeaderParsePolicy,
+    with_body: bool,
+) -> Result<HttpAdaptedRequest, H1ReqmodAdaptationError>
+where
+    R: AsyncBufRead + Unpin,
+{
+    if header_size > max_header_size {
+        return Err(H1ReqmodAdaptationError::IcapServerHttpRequestHeaderTooLarge(max_header_size));
+    }
+
+    let mut http_req = HttpAdaptedRequest::parse(reader, header_size, ignore_via, policy).await?;
+    if http_req.has_conflict_framing() {
+        return Err(
+            H1ReqmodAdaptationError::InvalidIcapServerHttpRequestFraming(
+                "both Transfer-Encoding and Content-Length are set",
+            ),
+        );
+    }
+    if with_body {
+        http_req.normalize_expect();
+    } else {
+        http_req.strip_body_headers();
+    }
+    Ok(http_req)
+}
+
 impl<I: IdleCheck> HttpRequestAdapter<I> {
     pub(super) async fn handle_original_http_request_without_body<H, UW>(
         self,
@@ -54,10 +88,13 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
     where
         H: HttpRequestForAdaptation,
     {
-        let http_req = HttpAdaptedRequest::parse(
+        let http_req = recv_adapted_http_request(
             &mut self.icap_connection.reader,
             http_header_size,
+            self.icap_client.config.http_max_request_header_size,
             self.http_req_add_no_via_header,
+            &self.icap_client.config.http_header_policy,
+            false,
         )
         .await?;
         self.icap_connection.mark_reader_finished();
@@ -82,12 +119,16 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         H: HttpRequestForAdaptation,
         UW: HttpRequestUpstreamWriter<H> + Unpin,
     {
-        let http_req = HttpAdaptedRequest::parse(
+        let http_req = recv_adapted_http_request(
             &mut self.icap_connection.reader,
             http_header_size,
+            self.icap_client.config.http_max_request_header_size,
             self.http_req_add_no_via_header,
+            &self.icap_client.config.http_header_policy,
+            false,
         )
         .await?;
+        state.mark_icap_phase(IcapTransactionPhase::ParseAdaptedHeader);
         self.icap_connection.mark_reader_finished();
         if icap_rsp.keep_alive {
             self.icap_client.save_connection(self.icap_connection);
@@ -120,12 +161,16 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         H: HttpRequestForAdaptation,
         UW: HttpRequestUpstreamWriter<H> + Unpin,
     {
-        let http_req = HttpAdaptedRequest::parse(
+        let http_req = recv_adapted_http_request(
             &mut self.icap_connection.reader,
             http_header_size,
+            self.icap_client.config.http_max_request_header_size,
             self.http_req_add_no_via_header,
+            &self.icap_client.config.http_header_policy,
+            true,
         )
         .await?;
+        state.mark_icap_phase(IcapTransactionPhase::ParseAdaptedHeader);
         let body_content_length = http_req.content_length;
 
         let final_req = orig_http_request.adapt_with_body(http_req);
@@ -144,10 +189,12 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                     &mut self.icap_connection.reader,
                     self.http_body_line_max_size,
                 );
+                body_reader.set_strict_chunked(self.icap_client.config.strict_chunked);
                 let mut body_copy =
                     StreamCopy::new(&mut body_reader, ups_writer, &self.copy_config);
                 Self::send_request_body(&self.idle_checker, &mut body_copy).await?;
 
+                state.mark_icap_phase(IcapTransactionPhase::RelayAdaptedBody);
                 state.mark_ups_send_all();
                 let copied = body_copy.copied_size();
 
@@ -170,10 +217,12 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                     &mut self.icap_connection.reader,
                     self.http_body_line_max_size,
                 );
+                body_reader.set_strict_chunked(self.icap_client.config.strict_chunked);
                 let mut body_copy =
                     StreamCopy::new(&mut body_reader, ups_writer, &self.copy_config);
                 Self::send_request_body(&self.idle_checker, &mut body_copy).await?;
 
+                state.mark_icap_phase(IcapTransactionPhase::RelayAdaptedBody);
                 state.mark_ups_send_all();
 
                 self.icap_connection.mark_reader_finished();
@@ -204,8 +253,7 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
                 r = &mut body_copy => {
                     return match r {
                         Ok(_) => Ok(()),
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1ReqmodAdaptationError::IcapServerReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1ReqmodAdaptationError::HttpUpstreamWriteFailed(e)),
+                        Err(e) => Err(H1ReqmodAdaptationError::from_icap_to_ups_copy(e)),
                     };
                 }
                 n = idle_interval.tick() => {
@@ -234,3 +282,238 @@ impl<I: IdleCheck> HttpRequestAdapter<I> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::pin::Pin;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use http::Version;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::time::Instant;
+    use url::Url;
+
+    use g3_http::server::HttpProxyClientRequest;
+    use g3_io_ext::{IdleForceQuitReason, IdleInterval, IdleWheel, StreamCopyConfig};
+
+    use crate::reqmod::IcapReqmodClient;
+    use crate::{IcapMethod, IcapServiceClient, IcapServiceConfig};
+
+    const MOCK_OPTIONS_RESPONSE: &[u8] =
+        b"ICAP/1.0 200 OK\r\nMethods: REQMOD\r\nISTag: \"mock\"\r\n\
+        Encapsulated: null-body=0\r\n\r\n";
+
+    const CLIENT_REQUEST: &[u8] = b"POST http://example.net/upload HTTP/1.1\r\n\
+        Host: example.net\r\nContent-Length: 11\r\nExpect: 100-continue\r\n\r\nhello world";
+
+    async fn start_mock_server(reqmod_rsp: Vec<u8>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let reqmod_rsp = Arc::new(reqmod_rsp);
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let reqmod_rsp = reqmod_rsp.clone();
+                tokio::spawn(async move {
+                    let (r, mut w) = stream.into_split();
+                    let mut r = BufReader::new(r);
+                    let mut req = Vec::new();
+                    loop {
+                        req.clear();
+                        loop {
+                            let offset = req.len();
+                            match r.read_until(b'\n', &mut req).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(_) => {}
+                            }
+                            if &req[offset..] == b"\r\n" {
+                                break;
+                            }
+                        }
+                        if req.starts_with(b"REQMOD ") {
+                            // the client request always has a body
+                            while !req.ends_with(b"\r\n0\r\n\r\n") {
+                                match r.read_until(b'\n', &mut req).await {
+                                    Ok(0) | Err(_) => return,
+                                    Ok(_) => {}
+                                }
+                            }
+                            if w.write_all(&reqmod_rsp).await.is_err() {
+                                return;
+                            }
+                        } else if w.write_all(MOCK_OPTIONS_RESPONSE).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        Url::from_str(&format!("icap://{addr}/reqmod")).unwrap()
+    }
+
+    fn reqmod_response(http_header: &str, http_body: Option<&str>) -> Vec<u8> {
+        let rsp = match http_body {
+            Some(body) => format!(
+                "ICAP/1.0 200 OK\r\nISTag: \"mock\"\r\nEncapsulated: req-hdr=0, req-body={}\r\n\r\n\
+                 {http_header}{:x}\r\n{body}\r\n0\r\n\r\n",
+                http_header.len(),
+                body.len(),
+            ),
+            None => format!(
+                "ICAP/1.0 200 OK\r\nISTag: \"mock\"\r\nEncapsulated: req-hdr=0, null-body={}\r\n\r\n\
+                 {http_header}",
+                http_header.len(),
+            ),
+        };
+        rsp.into_bytes()
+    }
+
+    struct MockIdleChecker(Arc<IdleWheel>);
+
+    impl IdleCheck for MockIdleChecker {
+        fn interval_timer(&self) -> IdleInterval {
+            self.0.register()
+        }
+
+        fn check_quit(&self, _idle_count: usize) -> bool {
+            false
+        }
+
+        fn check_force_quit(&self) -> Option<IdleForceQuitReason> {
+            None
+        }
+    }
+
+    #[derive(Default)]
+    struct MockUpstreamWriter {
+        buf: Vec<u8>,
+    }
+
+    impl AsyncWrite for MockUpstreamWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.buf).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl HttpRequestUpstreamWriter<HttpProxyClientRequest> for MockUpstreamWriter {
+        async fn send_request_header(&mut self, req: &HttpProxyClientRequest) -> io::Result<()> {
+            self.buf.extend_from_slice(&req.serialize_for_origin());
+            Ok(())
+        }
+    }
+
+    async fn run_adaptation(
+        reqmod_rsp: Vec<u8>,
+        set_config: impl FnOnce(&mut IcapServiceConfig),
+    ) -> (Result<(), H1ReqmodAdaptationError>, String) {
+        let url = start_mock_server(reqmod_rsp).await;
+        let mut config = IcapServiceConfig::new(IcapMethod::Reqmod, url).unwrap();
+        set_config(&mut config);
+        let client = IcapServiceClient::new(Arc::new(config)).unwrap();
+        let reqmod_client = IcapReqmodClient::new(Arc::new(client));
+        let adapter = reqmod_client
+            .h1_adapter(
+                StreamCopyConfig::default(),
+                1024,
+                true,
+                MockIdleChecker(IdleWheel::spawn(Duration::from_secs(1))),
+            )
+            .await
+            .unwrap();
+
+        let mut clt_r = BufReader::new(CLIENT_REQUEST);
+        let mut version = Version::HTTP_11;
+        let http_request = HttpProxyClientRequest::parse_basic(&mut clt_r, 4096, &mut version)
+            .await
+            .unwrap();
+
+        let mut state = ReqmodAdaptationRunState::new(Instant::now());
+        let mut ups_writer = MockUpstreamWriter::default();
+        let r = adapter
+            .xfer(&mut state, &http_request, Some(&mut clt_r), &mut ups_writer)
+            .await
+            .map(|_| ());
+        (r, String::from_utf8(ups_writer.buf).unwrap())
+    }
+
+    #[tokio::test]
+    async fn body_removed() {
+        let rsp = reqmod_response(
+            "POST /upload HTTP/1.1\r\nHost: example.net\r\nContent-Length: 11\r\n\
+             Expect: 100-continue\r\nX-Adapted: 1\r\n\r\n",
+            None,
+        );
+        let (r, sent) = run_adaptation(rsp, |_| {}).await;
+        r.unwrap();
+        assert!(sent.contains("\r\nContent-Length: 0\r\n"));
+        assert!(sent.contains("\r\nX-Adapted: 1\r\n"));
+        // the upstream should never wait for a body that never comes
+        assert!(!sent.contains("Expect"));
+        assert!(sent.ends_with("\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn transfer_encoding_with_content_length() {
+        let rsp = reqmod_response(
+            "POST /upload HTTP/1.1\r\nHost: example.net\r\nContent-Length: 11\r\n\
+             Transfer-Encoding: chunked\r\n\r\n",
+            Some("hello world"),
+        );
+        let (r, sent) = run_adaptation(rsp, |_| {}).await;
+        assert!(matches!(
+            r,
+            Err(H1ReqmodAdaptationError::InvalidIcapServerHttpRequestFraming(_))
+        ));
+        assert!(sent.is_empty());
+    }
+
+    #[tokio::test]
+    async fn expect_continue_with_body() {
+        let rsp = reqmod_response(
+            "POST /upload HTTP/1.1\r\nHost: example.net\r\nContent-Length: 5\r\n\
+             Expect: 100-Continue\r\n\r\n",
+            Some("HELLO"),
+        );
+        let (r, sent) = run_adaptation(rsp, |_| {}).await;
+        r.unwrap();
+        assert!(sent.contains("\r\nContent-Length: 5\r\n"));
+        assert!(sent.contains("\r\nExpect: 100-continue\r\n"));
+        assert!(sent.ends_with("\r\n\r\nHELLO"));
+    }
+
+    #[tokio::test]
+    async fn too_large_header() {
+        let rsp = reqmod_response(
+            "POST /upload HTTP/1.1\r\nHost: example.net\r\nContent-Length: 5\r\n\r\n",
+            Some("HELLO"),
+        );
+        let (r, sent) = run_adaptation(rsp, |config| {
+            config.set_http_max_request_header_size(32);
+        })
+        .await;
+        assert!(matches!(
+            r,
+            Err(H1ReqmodAdaptationError::IcapServerHttpRequestHeaderTooLarge(32))
+        ));
+        assert!(sent.is_empty());
+    }
+}
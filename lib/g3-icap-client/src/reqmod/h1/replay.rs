@@ -0,0 +1,341 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io::{self, IoSlice};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+use g3_io_ext::{IdleCheck, LimitedWriteExt};
+
+use super::{H1ReqmodAdaptationError, HttpRequestAdapter, ReqmodAdaptationRunState};
+use crate::IcapTransactionPhase;
+
+impl H1ReqmodAdaptationError {
+    /// check if the ICAP connection is closed before any response bytes received
+    fn is_early_close(&self) -> bool {
+        match self {
+            H1ReqmodAdaptationError::IcapServerWriteFailed(_) => true,
+            H1ReqmodAdaptationError::IcapServerConnectionClosed => true,
+            H1ReqmodAdaptationError::IcapServerReadFailed(e) => {
+                matches!(
+                    e.kind(),
+                    io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted
+                )
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<I: IdleCheck> HttpRequestAdapter<I> {
+    async fn send_request_and_wait<const N: usize>(
+        &mut self,
+        state: &mut ReqmodAdaptationRunState,
+        sent_phases: &[IcapTransactionPhase],
+        bufs: [IoSlice<'_>; N],
+    ) -> Result<(), H1ReqmodAdaptationError> {
+        let icap_w = &mut self.icap_connection.writer;
+        icap_w
+            .write_all_vectored(bufs)
+            .await
+            .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
+        icap_w
+            .flush()
+            .await
+            .map_err(H1ReqmodAdaptationError::IcapServerWriteFailed)?;
+        for phase in sent_phases {
+            state.mark_icap_phase(*phase);
+        }
+
+        // only peek the response here, it will be parsed later
+        let buf = self
+            .icap_connection
+            .reader
+            .fill_buf()
+            .await
+            .map_err(H1ReqmodAdaptationError::IcapServerReadFailed)?;
+        if buf.is_empty() {
+            Err(H1ReqmodAdaptationError::IcapServerConnectionClosed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Send the whole ICAP request, and wait until the response is available.
+    ///
+    /// The request will be sent again on a new ICAP connection for at most once, if the old one
+    /// is closed before any response bytes received, and the `body_size` bytes of HTTP body
+    /// included in the request is within the replay buffer size.
+    ///
+    /// The `sent_phases` will be marked as finished once the request is sent for the first time.
+    pub(super) async fn send_replayable_request<const N: usize>(
+        &mut self,
+        state: &mut ReqmodAdaptationRunState,
+        sent_phases: &[IcapTransactionPhase],
+        bufs: [IoSlice<'_>; N],
+        body_size: usize,
+    ) -> Result<(), H1ReqmodAdaptationError> {
+        let e = match self.send_request_and_wait(state, sent_phases, bufs).await {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+        if state.icap_retried
+            || body_size > self.icap_client.config.replay_buffer_size
+            || !e.is_early_close()
+        {
+            return Err(e);
+        }
+
+        // keep using the old options, as the request has been built with them
+        let Ok((icap_connection, _)) = self.icap_client.fetch_connection().await else {
+            return Err(e);
+        };
+        self.icap_connection = icap_connection;
+        self.icap_client.add_early_close_retry();
+        state.icap_retried = true;
+        self.send_request_and_wait(state, sent_phases, bufs).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::Pin;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use http::Version;
+    use tokio::io::{AsyncWrite, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::time::Instant;
+    use url::Url;
+
+    use g3_http::server::HttpProxyClientRequest;
+    use g3_io_ext::{IdleForceQuitReason, IdleInterval, IdleWheel, StreamCopyConfig};
+
+    use super::super::HttpRequestUpstreamWriter;
+    use crate::reqmod::IcapReqmodClient;
+    use crate::{IcapMethod, IcapServiceClient, IcapServiceConfig};
+
+    const MOCK_OPTIONS_RESPONSE: &[u8] =
+        b"ICAP/1.0 200 OK\r\nMethods: REQMOD\r\nISTag: \"mock\"\r\nPreview: 1024\r\n\
+        Encapsulated: null-body=0\r\n\r\n";
+
+    const MOCK_REQMOD_RESPONSE: &[u8] = b"ICAP/1.0 200 OK\r\nISTag: \"mock\"\r\n\
+        Encapsulated: req-hdr=0, req-body=78\r\n\r\n\
+        POST /upload HTTP/1.1\r\nHost: example.net\r\nContent-Length: 11\r\nX-Adapted: 1\r\n\r\n\
+        b\r\nHELLO WORLD\r\n0\r\n\r\n";
+
+    const CLIENT_REQUEST: &[u8] = b"POST http://example.net/upload HTTP/1.1\r\n\
+        Host: example.net\r\nContent-Length: 11\r\n\r\nhello world";
+
+    struct MockIcapServer {
+        /// close the connection without response for the first `drop_count` REQMOD requests
+        drop_count: usize,
+        reqmod_received: Mutex<Vec<Vec<u8>>>,
+    }
+
+    async fn start_mock_server(drop_count: usize) -> (Url, Arc<MockIcapServer>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mock = Arc::new(MockIcapServer {
+            drop_count,
+            reqmod_received: Mutex::new(Vec::new()),
+        });
+
+        let server = mock.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let server = server.clone();
+                tokio::spawn(async move {
+                    let (r, mut w) = stream.into_split();
+                    let mut r = BufReader::new(r);
+                    let mut req = Vec::new();
+                    loop {
+                        req.clear();
+                        loop {
+                            let offset = req.len();
+                            match r.read_until(b'\n', &mut req).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(_) => {}
+                            }
+                            if &req[offset..] == b"\r\n" {
+                                break;
+                            }
+                        }
+                        if req.starts_with(b"REQMOD ") {
+                            // the client request always has a body
+                            while !req.ends_with(b"\r\n0\r\n\r\n") {
+                                match r.read_until(b'\n', &mut req).await {
+                                    Ok(0) | Err(_) => return,
+                                    Ok(_) => {}
+                                }
+                            }
+                            let count = {
+                                let mut received = server.reqmod_received.lock().unwrap();
+                                received.push(req.clone());
+                                received.len()
+                            };
+                            if count <= server.drop_count {
+                                return;
+                            }
+                            if w.write_all(MOCK_REQMOD_RESPONSE).await.is_err() {
+                                return;
+                            }
+                        } else if w.write_all(MOCK_OPTIONS_RESPONSE).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        let url = Url::from_str(&format!("icap://{addr}/reqmod")).unwrap();
+        (url, mock)
+    }
+
+    struct MockIdleChecker(Arc<IdleWheel>);
+
+    impl IdleCheck for MockIdleChecker {
+        fn interval_timer(&self) -> IdleInterval {
+            self.0.register()
+        }
+
+        fn check_quit(&self, _idle_count: usize) -> bool {
+            false
+        }
+
+        fn check_force_quit(&self) -> Option<IdleForceQuitReason> {
+            None
+        }
+    }
+
+    #[derive(Default)]
+    struct MockUpstreamWriter {
+        buf: Vec<u8>,
+    }
+
+    impl AsyncWrite for MockUpstreamWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.buf).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl HttpRequestUpstreamWriter<HttpProxyClientRequest> for MockUpstreamWriter {
+        async fn send_request_header(&mut self, req: &HttpProxyClientRequest) -> io::Result<()> {
+            self.buf.extend_from_slice(&req.serialize_for_origin());
+            Ok(())
+        }
+    }
+
+    async fn run_adaptation(
+        url: Url,
+        set_config: impl FnOnce(&mut IcapServiceConfig),
+    ) -> (
+        Result<(), H1ReqmodAdaptationError>,
+        ReqmodAdaptationRunState,
+        String,
+        Arc<IcapServiceClient>,
+    ) {
+        let mut config = IcapServiceConfig::new(IcapMethod::Reqmod, url).unwrap();
+        set_config(&mut config);
+        let client = Arc::new(IcapServiceClient::new(Arc::new(config)).unwrap());
+        // wait for the options to be loaded in background
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.options().preview_size.is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let reqmod_client = IcapReqmodClient::new(client.clone());
+        let adapter = reqmod_client
+            .h1_adapter(
+                StreamCopyConfig::default(),
+                1024,
+                true,
+                MockIdleChecker(IdleWheel::spawn(Duration::from_secs(1))),
+            )
+            .await
+            .unwrap();
+
+        let mut clt_r = BufReader::new(CLIENT_REQUEST);
+        let mut version = Version::HTTP_11;
+        let http_request = HttpProxyClientRequest::parse_basic(&mut clt_r, 4096, &mut version)
+            .await
+            .unwrap();
+
+        let mut state = ReqmodAdaptationRunState::new(Instant::now());
+        let mut ups_writer = MockUpstreamWriter::default();
+        let r = adapter
+            .xfer(&mut state, &http_request, Some(&mut clt_r), &mut ups_writer)
+            .await
+            .map(|_| ());
+        let sent = String::from_utf8(ups_writer.buf).unwrap();
+        (r, state, sent, client)
+    }
+
+    #[tokio::test]
+    async fn retry_on_early_close() {
+        let (url, mock) = start_mock_server(1).await;
+        let (r, state, sent, client) = run_adaptation(url, |_| {}).await;
+        r.unwrap();
+        assert!(state.icap_retried);
+        assert_eq!(client.early_close_retries(), 1);
+        assert!(sent.contains("\r\nX-Adapted: 1\r\n"));
+        assert!(sent.ends_with("\r\n\r\nHELLO WORLD"));
+
+        // the whole transaction should be replayed on the new connection
+        let received = mock.reqmod_received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0], received[1]);
+        assert!(received[1].ends_with(b"\r\nb\r\nhello world\r\n0\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn retry_only_once() {
+        let (url, mock) = start_mock_server(2).await;
+        let (r, state, sent, client) = run_adaptation(url, |_| {}).await;
+        assert!(matches!(
+            r,
+            Err(H1ReqmodAdaptationError::IcapServerConnectionClosed)
+        ));
+        assert!(state.icap_retried);
+        assert_eq!(client.early_close_retries(), 1);
+        assert!(sent.is_empty());
+        assert_eq!(mock.reqmod_received.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn body_exceeds_replay_buffer() {
+        let (url, mock) = start_mock_server(1).await;
+        let (r, state, sent, client) = run_adaptation(url, |config| {
+            config.set_replay_buffer_size(8);
+        })
+        .await;
+        assert!(matches!(
+            r,
+            Err(H1ReqmodAdaptationError::IcapServerConnectionClosed)
+        ));
+        assert!(!state.icap_retried);
+        assert_eq!(client.early_close_retries(), 0);
+        assert!(sent.is_empty());
+        assert_eq!(mock.reqmod_received.lock().unwrap().len(), 1);
+    }
+}
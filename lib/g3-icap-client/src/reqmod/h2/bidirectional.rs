@@ -14,8 +14,9 @@ use g3_h2::{
     H2StreamFromChunkedTransfer, H2StreamFromChunkedTransferError, H2StreamToChunkedTransfer,
     H2StreamToChunkedTransferError, RequestExt,
 };
+use g3_http::HttpHeaderParsePolicy;
 use g3_http::server::HttpAdaptedRequest;
-use g3_io_ext::{IdleCheck, LimitedBufReadExt, StreamCopyConfig};
+use g3_io_ext::{IdleCheck, IdleDirection, IdleTracker, LimitedBufReadExt, StreamCopyConfig};
 
 use super::recv_request::recv_ups_response_head_after_transfer;
 use super::{H2ReqmodAdaptationError, ReqmodAdaptationEndState, ReqmodAdaptationRunState};
@@ -34,7 +35,7 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
         mut body_transfer: &mut H2StreamToChunkedTransfer<'_, IcapClientWriter>,
     ) -> Result<ReqmodResponse, H2ReqmodAdaptationError> {
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -56,20 +57,23 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
                     };
                 }
                 n = idle_interval.tick() => {
-                    if body_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if body_transfer.no_cached_data() {
-                                Err(H2ReqmodAdaptationError::HttpClientReadIdle)
-                            } else {
+                    let body_idle = body_transfer.is_idle().then(|| {
+                        if body_transfer.no_cached_data() {
+                            IdleDirection::ClientRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[body_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return if direction == IdleDirection::WriteBlocked {
                                 Err(H2ReqmodAdaptationError::IcapServerWriteIdle)
+                            } else {
+                                Err(H2ReqmodAdaptationError::HttpClientReadIdle)
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         body_transfer.reset_active();
                     }
 
@@ -101,6 +105,7 @@ pub(super) struct BidirectionalRecvHttpRequest<'a, I: IdleCheck> {
     pub(super) http_req_add_no_via_header: bool,
     pub(super) idle_checker: &'a I,
     pub(super) http_header_size: usize,
+    pub(super) http_header_policy: HttpHeaderParsePolicy,
     pub(super) icap_read_finished: bool,
 }
 
@@ -116,6 +121,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
             self.icap_reader,
             self.http_header_size,
             self.http_req_add_no_via_header,
+            &self.http_header_policy,
         )
         .await?;
 
@@ -134,7 +140,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
         );
 
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -186,26 +192,40 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
                     };
                 }
                 n = idle_interval.tick() => {
-                    if clt_body_transfer.is_idle() && ups_body_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if clt_body_transfer.is_idle() {
-                                if clt_body_transfer.no_cached_data() {
+                    let clt_body_idle = clt_body_transfer.is_idle().then(|| {
+                        if clt_body_transfer.no_cached_data() {
+                            IdleDirection::ClientRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    let ups_body_idle = ups_body_transfer.is_idle().then(|| {
+                        if ups_body_transfer.no_cached_data() {
+                            IdleDirection::IcapRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[clt_body_idle, ups_body_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return match direction {
+                                IdleDirection::ClientRead => {
                                     Err(H2ReqmodAdaptationError::HttpClientReadIdle)
-                                } else {
-                                    Err(H2ReqmodAdaptationError::IcapServerWriteIdle)
                                 }
-                            } else if ups_body_transfer.no_cached_data() {
-                                Err(H2ReqmodAdaptationError::IcapServerReadIdle)
-                            } else {
-                                Err(H2ReqmodAdaptationError::HttpUpstreamWriteIdle)
+                                IdleDirection::IcapRead => {
+                                    Err(H2ReqmodAdaptationError::IcapServerReadIdle)
+                                }
+                                _ => {
+                                    if clt_body_transfer.no_cached_data() {
+                                        Err(H2ReqmodAdaptationError::HttpUpstreamWriteIdle)
+                                    } else {
+                                        Err(H2ReqmodAdaptationError::IcapServerWriteIdle)
+                                    }
+                                }
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         clt_body_transfer.reset_active();
                         ups_body_transfer.reset_active();
                     }
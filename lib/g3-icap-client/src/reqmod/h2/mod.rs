@@ -3,6 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::fmt;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -11,7 +12,7 @@ use bytes::{BufMut, Bytes};
 use h2::client::SendRequest;
 use h2::ext::Protocol;
 use h2::{RecvStream, SendStream};
-use http::{Extensions, Request, Response};
+use http::{Extensions, Request, Response, header};
 use tokio::time::Instant;
 
 use g3_h2::H2StreamFromChunkedTransfer;
@@ -85,6 +86,8 @@ pub struct ReqmodAdaptationRunState {
     pub dur_ups_send_header: Option<Duration>,
     pub dur_ups_send_all: Option<Duration>,
     pub dur_ups_recv_header: Option<Duration>,
+    /// the preview size used for the ICAP request, `None` if no preview is sent
+    pub preview_size: Option<usize>,
     pub(crate) respond_shared_headers: Option<HttpHeaderMap>,
 }
 
@@ -95,6 +98,7 @@ impl ReqmodAdaptationRunState {
             dur_ups_send_header: None,
             dur_ups_send_all: None,
             dur_ups_recv_header: None,
+            preview_size: None,
             respond_shared_headers: None,
         }
     }
@@ -121,6 +125,12 @@ impl ReqmodAdaptationRunState {
 }
 
 impl<I: IdleCheck> H2RequestAdapter<I> {
+    /// Set the task id, which will be used in the name of the debug capture files
+    pub fn set_task_id<T: fmt::Display>(&mut self, id: T) {
+        self.icap_client
+            .start_debug_capture(&mut self.icap_connection, id);
+    }
+
     pub fn set_client_addr(&mut self, addr: SocketAddr) {
         self.client_addr = Some(addr);
     }
@@ -146,11 +156,17 @@ impl<I: IdleCheck> H2RequestAdapter<I> {
         }
     }
 
-    fn preview_size(&self) -> Option<usize> {
-        if self.icap_client.config.disable_preview {
-            return None;
-        }
-        self.icap_options.preview_size
+    fn preview_size(&self, http_request: &Request<()>) -> Option<usize> {
+        let content_type = http_request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok());
+        self.icap_client.config.preview_size(
+            self.icap_options.preview_size,
+            http_request.method(),
+            content_type,
+            http_request.uri().path(),
+        )
     }
 
     pub async fn xfer(
@@ -163,18 +179,21 @@ impl<I: IdleCheck> H2RequestAdapter<I> {
         if clt_body.is_end_stream() {
             self.xfer_without_body(state, http_request, ups_send_request)
                 .await
-        } else if let Some(preview_size) = self.preview_size() {
-            self.xfer_with_preview(
-                state,
-                http_request,
-                clt_body,
-                ups_send_request,
-                preview_size,
-            )
-            .await
         } else {
-            self.xfer_without_preview(state, http_request, clt_body, ups_send_request)
+            state.preview_size = self.preview_size(&http_request);
+            if let Some(preview_size) = state.preview_size {
+                self.xfer_with_preview(
+                    state,
+                    http_request,
+                    clt_body,
+                    ups_send_request,
+                    preview_size,
+                )
                 .await
+            } else {
+                self.xfer_without_preview(state, http_request, clt_body, ups_send_request)
+                    .await
+            }
         }
     }
 }
@@ -175,6 +175,7 @@ impl<I: IdleCheck> H2RequestAdapter<I> {
                                 http_req_add_no_via_header: self.http_req_add_no_via_header,
                                 idle_checker: &self.idle_checker,
                                 http_header_size: header_size,
+                                http_header_policy: self.icap_client.config.http_header_policy,
                                 icap_read_finished: false,
                             };
                             let r = bidirectional_transfer
@@ -162,6 +162,7 @@ impl<I: IdleCheck> H2RequestAdapter<I> {
             &mut self.icap_connection.reader,
             http_header_size,
             self.http_req_add_no_via_header,
+            &self.icap_client.config.http_header_policy,
         )
         .await?;
         self.icap_connection.mark_reader_finished();
@@ -187,6 +188,7 @@ impl<I: IdleCheck> H2RequestAdapter<I> {
             &mut self.icap_connection.reader,
             http_header_size,
             self.http_req_add_no_via_header,
+            &self.icap_client.config.http_header_policy,
         )
         .await?;
         self.icap_connection.mark_reader_finished();
@@ -224,6 +226,7 @@ impl<I: IdleCheck> H2RequestAdapter<I> {
             &mut self.icap_connection.reader,
             http_header_size,
             self.http_req_add_no_via_header,
+            &self.icap_client.config.http_header_policy,
         )
         .await?;
 
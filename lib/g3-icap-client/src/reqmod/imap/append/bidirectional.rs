@@ -7,10 +7,11 @@ use std::sync::Arc;
 
 use tokio::io::{AsyncRead, AsyncWrite, BufWriter};
 
-use g3_http::HttpBodyDecodeReader;
 use g3_http::server::HttpAdaptedRequest;
+use g3_http::{HttpBodyDecodeReader, HttpHeaderParsePolicy};
 use g3_io_ext::{
-    IdleCheck, LimitedBufReadExt, LimitedWriteExt, StreamCopy, StreamCopyConfig, StreamCopyError,
+    IdleCheck, IdleDirection, IdleTracker, LimitedBufReadExt, LimitedWriteExt, StreamCopy,
+    StreamCopyConfig, StreamCopyError,
 };
 
 use super::ImapAdaptationError;
@@ -33,7 +34,7 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
         CR: AsyncRead + Unpin,
     {
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -61,20 +62,23 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
                     };
                 }
                 n = idle_interval.tick() => {
-                    if msg_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if msg_transfer.no_cached_data() {
-                                Err(ImapAdaptationError::ImapClientReadIdle)
-                            } else {
+                    let msg_idle = msg_transfer.is_idle().then(|| {
+                        if msg_transfer.no_cached_data() {
+                            IdleDirection::ClientRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[msg_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return if direction == IdleDirection::WriteBlocked {
                                 Err(ImapAdaptationError::IcapServerWriteIdle)
+                            } else {
+                                Err(ImapAdaptationError::ImapClientReadIdle)
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         msg_transfer.reset_active();
                     }
 
@@ -111,6 +115,7 @@ pub(super) struct BidirectionalRecvHttpRequest<'a, I: IdleCheck> {
     pub(super) copy_config: StreamCopyConfig,
     pub(super) idle_checker: &'a I,
     pub(super) http_header_size: usize,
+    pub(super) http_header_policy: HttpHeaderParsePolicy,
     pub(super) imap_message_size: u64,
     pub(super) icap_read_finished: bool,
 }
@@ -126,8 +131,13 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
         CR: AsyncRead + Unpin,
         UW: AsyncWrite + Unpin,
     {
-        let http_req =
-            HttpAdaptedRequest::parse(self.icap_reader, self.http_header_size, true).await?;
+        let http_req = HttpAdaptedRequest::parse(
+            self.icap_reader,
+            self.http_header_size,
+            true,
+            &self.http_header_policy,
+        )
+        .await?;
         if let Some(len) = http_req.content_length {
             if len != self.imap_message_size {
                 return Err(ImapAdaptationError::MessageSizeNotMatch);
@@ -141,7 +151,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
             StreamCopy::new(&mut ups_body_reader, &mut ups_buf_writer, &self.copy_config);
 
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -189,26 +199,40 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
                     };
                 }
                 _ = idle_interval.tick() => {
-                    if clt_msg_transfer.is_idle() && ups_msg_transfer.is_idle() {
-                        idle_count += 1;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if clt_msg_transfer.is_idle() {
-                                if clt_msg_transfer.no_cached_data() {
+                    let clt_msg_idle = clt_msg_transfer.is_idle().then(|| {
+                        if clt_msg_transfer.no_cached_data() {
+                            IdleDirection::ClientRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    let ups_msg_idle = ups_msg_transfer.is_idle().then(|| {
+                        if ups_msg_transfer.no_cached_data() {
+                            IdleDirection::IcapRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(1, &[clt_msg_idle, ups_msg_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return match direction {
+                                IdleDirection::ClientRead => {
                                     Err(ImapAdaptationError::ImapClientReadIdle)
-                                } else {
-                                    Err(ImapAdaptationError::IcapServerWriteIdle)
                                 }
-                            } else if ups_msg_transfer.no_cached_data() {
-                                Err(ImapAdaptationError::IcapServerReadIdle)
-                            } else {
-                                Err(ImapAdaptationError::ImapUpstreamWriteIdle)
+                                IdleDirection::IcapRead => {
+                                    Err(ImapAdaptationError::IcapServerReadIdle)
+                                }
+                                _ => {
+                                    if clt_msg_transfer.no_cached_data() {
+                                        Err(ImapAdaptationError::ImapUpstreamWriteIdle)
+                                    } else {
+                                        Err(ImapAdaptationError::IcapServerWriteIdle)
+                                    }
+                                }
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         clt_msg_transfer.reset_active();
                         ups_msg_transfer.reset_active();
                     }
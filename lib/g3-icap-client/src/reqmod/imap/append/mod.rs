@@ -213,6 +213,7 @@ impl<I: IdleCheck> ImapMessageAdapter<I> {
                         copy_config: self.copy_config,
                         idle_checker: &self.idle_checker,
                         http_header_size: header_size,
+                        http_header_policy: self.icap_client.config.http_header_policy,
                         imap_message_size: self.literal_size,
                         icap_read_finished: false,
                     };
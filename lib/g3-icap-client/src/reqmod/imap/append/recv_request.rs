@@ -20,9 +20,13 @@ impl<I: IdleCheck> ImapMessageAdapter<I> {
         icap_rsp: ReqmodResponse,
         http_header_size: usize,
     ) -> Result<ReqmodAdaptationEndState, ImapAdaptationError> {
-        let _http_req =
-            HttpAdaptedRequest::parse(&mut self.icap_connection.reader, http_header_size, true)
-                .await?;
+        let _http_req = HttpAdaptedRequest::parse(
+            &mut self.icap_connection.reader,
+            http_header_size,
+            true,
+            &self.icap_client.config.http_header_policy,
+        )
+        .await?;
         self.icap_connection.mark_reader_finished();
         if icap_rsp.keep_alive {
             self.icap_client.save_connection(self.icap_connection);
@@ -44,9 +48,13 @@ impl<I: IdleCheck> ImapMessageAdapter<I> {
     where
         UW: AsyncWrite + Unpin,
     {
-        let http_req =
-            HttpAdaptedRequest::parse(&mut self.icap_connection.reader, http_header_size, true)
-                .await?;
+        let http_req = HttpAdaptedRequest::parse(
+            &mut self.icap_connection.reader,
+            http_header_size,
+            true,
+            &self.icap_client.config.http_header_policy,
+        )
+        .await?;
         if let Some(len) = http_req.content_length {
             if len != self.literal_size {
                 return Err(ImapAdaptationError::MessageSizeNotMatch);
@@ -15,6 +15,8 @@ use payload::IcapReqmodResponsePayload;
 
 mod response;
 
+mod multipart;
+
 pub mod h1;
 pub mod h2;
 
@@ -34,6 +36,6 @@ impl IcapReqmodClient {
     }
 
     pub fn bypass(&self) -> bool {
-        self.inner.config.bypass
+        self.inner.bypass()
     }
 }
@@ -0,0 +1,317 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use memchr::memmem;
+
+const MAX_BOUNDARY_LEN: usize = 70;
+const MAX_LINE_LEN: usize = 4096;
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum MultipartScanResult {
+    /// more data is needed
+    Pending,
+    /// the preview should end at this offset, which is the start of the first file part data
+    FileData(usize),
+    /// no file part can be found, or the body is malformed, the plain byte preview should be used
+    Plain,
+}
+
+enum ScanState {
+    Preamble,
+    DelimiterLine,
+    PartHeaders { is_file: bool },
+    PartBody,
+    FileData(usize),
+    Plain,
+}
+
+/// Find the start of the first file part data in a multipart/form-data body.
+///
+/// The body data should be fed incrementally, and the data that has been scanned won't be
+/// scanned again.
+pub(crate) struct MultipartPreviewScanner {
+    /// the delimiter with the leading CRLF, which is `\r\n--<boundary>`
+    delimiter: Vec<u8>,
+    state: ScanState,
+    offset: usize,
+}
+
+impl MultipartPreviewScanner {
+    /// Create a scanner if the content type is multipart/form-data with a valid boundary
+    pub(crate) fn new(content_type: &str) -> Option<Self> {
+        let mut iter = content_type.split(';');
+        let media_type = iter.next()?.trim();
+        if !media_type.eq_ignore_ascii_case("multipart/form-data") {
+            return None;
+        }
+
+        let boundary = iter.find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            if !name.trim().eq_ignore_ascii_case("boundary") {
+                return None;
+            }
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            Some(value)
+        })?;
+        if boundary.is_empty() || boundary.len() > MAX_BOUNDARY_LEN {
+            return None;
+        }
+
+        let mut delimiter = Vec::with_capacity(boundary.len() + 4);
+        delimiter.extend_from_slice(b"\r\n--");
+        delimiter.extend_from_slice(boundary.as_bytes());
+        Some(MultipartPreviewScanner {
+            delimiter,
+            state: ScanState::Preamble,
+            offset: 0,
+        })
+    }
+
+    /// Scan the body data, which should contain all the data that has been read so far
+    pub(crate) fn scan(&mut self, data: &[u8]) -> MultipartScanResult {
+        loop {
+            match self.state {
+                ScanState::Preamble => {
+                    // the first delimiter may be at the very start of the body, without the CRLF
+                    let first_delimiter = &self.delimiter[2..];
+                    if self.offset == 0 {
+                        if data.starts_with(first_delimiter) {
+                            self.offset = first_delimiter.len();
+                            self.state = ScanState::DelimiterLine;
+                            continue;
+                        }
+                        if first_delimiter.starts_with(data) {
+                            return MultipartScanResult::Pending;
+                        }
+                    }
+                    if !self.find_delimiter(data) {
+                        return MultipartScanResult::Pending;
+                    }
+                }
+                ScanState::DelimiterLine => {
+                    if data[self.offset..].starts_with(b"--") {
+                        // the close delimiter
+                        self.state = ScanState::Plain;
+                        continue;
+                    }
+                    let Some(line) = self.next_line(data) else {
+                        return self.pending_line(data);
+                    };
+                    if line.iter().all(|c| matches!(c, b' ' | b'\t')) {
+                        self.state = ScanState::PartHeaders { is_file: false };
+                    } else {
+                        self.state = ScanState::Plain;
+                    }
+                }
+                ScanState::PartHeaders { is_file } => {
+                    let Some(line) = self.next_line(data) else {
+                        return self.pending_line(data);
+                    };
+                    if line.is_empty() {
+                        if is_file {
+                            self.state = ScanState::FileData(self.offset);
+                        } else {
+                            self.state = ScanState::PartBody;
+                        }
+                        continue;
+                    }
+                    match parse_header_line(line) {
+                        Some(true) => self.state = ScanState::PartHeaders { is_file: true },
+                        Some(false) => {}
+                        None => self.state = ScanState::Plain,
+                    }
+                }
+                ScanState::PartBody => {
+                    if !self.find_delimiter(data) {
+                        return MultipartScanResult::Pending;
+                    }
+                }
+                ScanState::FileData(offset) => return MultipartScanResult::FileData(offset),
+                ScanState::Plain => return MultipartScanResult::Plain,
+            }
+        }
+    }
+
+    /// Move to the end of the next delimiter, or keep the data that may be the start of it
+    fn find_delimiter(&mut self, data: &[u8]) -> bool {
+        let left = &data[self.offset..];
+        if let Some(p) = memmem::find(left, &self.delimiter) {
+            self.offset += p + self.delimiter.len();
+            self.state = ScanState::DelimiterLine;
+            true
+        } else {
+            let keep = self.delimiter.len() - 1;
+            self.offset = self.offset.max(data.len().saturating_sub(keep));
+            false
+        }
+    }
+
+    /// Get the next CRLF terminated line, and move to the start of the following line
+    fn next_line<'a>(&mut self, data: &'a [u8]) -> Option<&'a [u8]> {
+        let left = &data[self.offset..];
+        let p = memmem::find(left, b"\r\n")?;
+        self.offset += p + 2;
+        Some(&left[..p])
+    }
+
+    fn pending_line(&mut self, data: &[u8]) -> MultipartScanResult {
+        if data.len() - self.offset > MAX_LINE_LEN {
+            self.state = ScanState::Plain;
+            MultipartScanResult::Plain
+        } else {
+            MultipartScanResult::Pending
+        }
+    }
+}
+
+/// Check if the part header line marks a file part, return None if the line is malformed
+fn parse_header_line(line: &[u8]) -> Option<bool> {
+    if line.len() > MAX_LINE_LEN || line.contains(&b'\n') {
+        return None;
+    }
+    let line = std::str::from_utf8(line).ok()?;
+    let (name, value) = line.split_once(':')?;
+    if !name.trim().eq_ignore_ascii_case("content-disposition") {
+        return Some(false);
+    }
+    let is_file = value.split(';').skip(1).any(|param| {
+        param.split_once('=').is_some_and(|(name, _)| {
+            let name = name.trim();
+            name.eq_ignore_ascii_case("filename") || name.eq_ignore_ascii_case("filename*")
+        })
+    });
+    Some(is_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_TYPE: &str = "multipart/form-data; boundary=\"xyz\"";
+
+    const BODY: &[u8] = b"--xyz\r\n\
+        Content-Disposition: form-data; name=\"a\"\r\n\
+        \r\n\
+        value a\r\n\
+        --xyz\r\n\
+        Content-Disposition: form-data; name=\"b\"\r\n\
+        \r\n\
+        value b\r\n\
+        --xyz\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        file data\r\n\
+        --xyz--\r\n";
+
+    fn file_data_start() -> usize {
+        memmem::find(BODY, b"file data").unwrap()
+    }
+
+    #[test]
+    fn content_type() {
+        assert!(MultipartPreviewScanner::new("multipart/form-data; boundary=xyz").is_some());
+        assert!(MultipartPreviewScanner::new("Multipart/Form-Data;Boundary=\"x y\"").is_some());
+        assert!(MultipartPreviewScanner::new("multipart/form-data").is_none());
+        assert!(MultipartPreviewScanner::new("multipart/form-data; boundary=").is_none());
+        assert!(MultipartPreviewScanner::new("multipart/mixed; boundary=xyz").is_none());
+        assert!(MultipartPreviewScanner::new("text/plain").is_none());
+    }
+
+    #[test]
+    fn whole() {
+        let mut scanner = MultipartPreviewScanner::new(CONTENT_TYPE).unwrap();
+        assert_eq!(
+            scanner.scan(BODY),
+            MultipartScanResult::FileData(file_data_start())
+        );
+    }
+
+    #[test]
+    fn streaming() {
+        // feed the body byte by byte
+        let mut scanner = MultipartPreviewScanner::new(CONTENT_TYPE).unwrap();
+        let end = file_data_start();
+        for i in 1..end {
+            assert_eq!(scanner.scan(&BODY[..i]), MultipartScanResult::Pending);
+        }
+        assert_eq!(
+            scanner.scan(&BODY[..end]),
+            MultipartScanResult::FileData(end)
+        );
+        assert_eq!(scanner.scan(BODY), MultipartScanResult::FileData(end));
+    }
+
+    #[test]
+    fn no_file_part() {
+        let body = b"--xyz\r\n\
+            Content-Disposition: form-data; name=\"a\"\r\n\
+            \r\n\
+            value a\r\n\
+            --xyz--\r\n";
+        let mut scanner = MultipartPreviewScanner::new(CONTENT_TYPE).unwrap();
+        assert_eq!(scanner.scan(body), MultipartScanResult::Plain);
+
+        // no terminal boundary
+        let mut scanner = MultipartPreviewScanner::new(CONTENT_TYPE).unwrap();
+        assert_eq!(
+            scanner.scan(&body[..body.len() - 9]),
+            MultipartScanResult::Pending
+        );
+    }
+
+    #[test]
+    fn preamble() {
+        let mut body = b"preamble\r\n".to_vec();
+        body.extend_from_slice(BODY);
+        let mut scanner = MultipartPreviewScanner::new(CONTENT_TYPE).unwrap();
+        assert_eq!(
+            scanner.scan(&body),
+            MultipartScanResult::FileData(file_data_start() + 10)
+        );
+    }
+
+    #[test]
+    fn large_field_part() {
+        let mut body = b"--xyz\r\n\
+            Content-Disposition: form-data; name=\"a\"\r\n\
+            \r\n"
+            .to_vec();
+        body.resize(body.len() + 64 * 1024, b'a');
+        body.extend_from_slice(&BODY[5..]);
+
+        let cap = 4096;
+        let mut scanner = MultipartPreviewScanner::new(CONTENT_TYPE).unwrap();
+        for end in (1..=cap).step_by(100) {
+            assert_eq!(scanner.scan(&body[..end]), MultipartScanResult::Pending);
+        }
+    }
+
+    #[test]
+    fn malformed_boundary() {
+        let body = b"--xyz invalid\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+            \r\n\
+            file data\r\n";
+        let mut scanner = MultipartPreviewScanner::new(CONTENT_TYPE).unwrap();
+        assert_eq!(scanner.scan(body), MultipartScanResult::Plain);
+
+        let body = b"--xyz\r\n\
+            invalid header line\r\n\
+            \r\n\
+            file data\r\n";
+        let mut scanner = MultipartPreviewScanner::new(CONTENT_TYPE).unwrap();
+        assert_eq!(scanner.scan(body), MultipartScanResult::Plain);
+
+        let mut body = b"--xyz\r\n".to_vec();
+        body.resize(body.len() + MAX_LINE_LEN + 1, b'a');
+        let mut scanner = MultipartPreviewScanner::new(CONTENT_TYPE).unwrap();
+        assert_eq!(scanner.scan(&body), MultipartScanResult::Plain);
+    }
+}
@@ -8,8 +8,10 @@ use std::sync::Arc;
 use tokio::io::{AsyncBufRead, AsyncWrite, BufWriter};
 
 use g3_http::server::HttpAdaptedRequest;
-use g3_http::{HttpBodyDecodeReader, StreamToChunkedTransfer};
-use g3_io_ext::{IdleCheck, LimitedBufReadExt, StreamCopyConfig, StreamCopyError};
+use g3_http::{HttpBodyDecodeReader, HttpHeaderParsePolicy, StreamToChunkedTransfer};
+use g3_io_ext::{
+    IdleCheck, IdleDirection, IdleTracker, LimitedBufReadExt, StreamCopyConfig, StreamCopyError,
+};
 use g3_smtp_proto::io::TextDataEncodeTransfer;
 
 use super::SmtpAdaptationError;
@@ -32,7 +34,7 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
         CR: AsyncBufRead + Unpin,
     {
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -53,20 +55,23 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
                     };
                 }
                 n = idle_interval.tick() => {
-                    if msg_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if msg_transfer.no_cached_data() {
-                                Err(SmtpAdaptationError::SmtpClientReadIdle)
-                            } else {
+                    let msg_idle = msg_transfer.is_idle().then(|| {
+                        if msg_transfer.no_cached_data() {
+                            IdleDirection::ClientRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[msg_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return if direction == IdleDirection::WriteBlocked {
                                 Err(SmtpAdaptationError::IcapServerWriteIdle)
+                            } else {
+                                Err(SmtpAdaptationError::SmtpClientReadIdle)
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         msg_transfer.reset_active();
                     }
 
@@ -103,6 +108,7 @@ pub(super) struct BidirectionalRecvHttpRequest<'a, I: IdleCheck> {
     pub(super) copy_config: StreamCopyConfig,
     pub(super) idle_checker: &'a I,
     pub(super) http_header_size: usize,
+    pub(super) http_header_policy: HttpHeaderParsePolicy,
     pub(super) icap_read_finished: bool,
 }
 
@@ -121,8 +127,13 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
         CR: AsyncBufRead + Unpin,
         UW: AsyncWrite + Unpin,
     {
-        let _http_req =
-            HttpAdaptedRequest::parse(self.icap_reader, self.http_header_size, true).await?;
+        let _http_req = HttpAdaptedRequest::parse(
+            self.icap_reader,
+            self.http_header_size,
+            true,
+            &self.http_header_policy,
+        )
+        .await?;
         // TODO check request content type?
 
         let mut ups_body_reader = HttpBodyDecodeReader::new_chunked(self.icap_reader, 256);
@@ -134,7 +145,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
         );
 
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -171,26 +182,40 @@ impl<I: IdleCheck> BidirectionalRecvHttpRequest<'_, I> {
                     };
                 }
                 n = idle_interval.tick() => {
-                    if clt_msg_transfer.is_idle() && ups_msg_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if clt_msg_transfer.is_idle() {
-                                if clt_msg_transfer.no_cached_data() {
+                    let clt_msg_idle = clt_msg_transfer.is_idle().then(|| {
+                        if clt_msg_transfer.no_cached_data() {
+                            IdleDirection::ClientRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    let ups_msg_idle = ups_msg_transfer.is_idle().then(|| {
+                        if ups_msg_transfer.no_cached_data() {
+                            IdleDirection::IcapRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[clt_msg_idle, ups_msg_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return match direction {
+                                IdleDirection::ClientRead => {
                                     Err(SmtpAdaptationError::SmtpClientReadIdle)
-                                } else {
-                                    Err(SmtpAdaptationError::IcapServerWriteIdle)
                                 }
-                            } else if ups_msg_transfer.no_cached_data() {
-                                Err(SmtpAdaptationError::IcapServerReadIdle)
-                            } else {
-                                Err(SmtpAdaptationError::SmtpUpstreamWriteIdle)
+                                IdleDirection::IcapRead => {
+                                    Err(SmtpAdaptationError::IcapServerReadIdle)
+                                }
+                                _ => {
+                                    if clt_msg_transfer.no_cached_data() {
+                                        Err(SmtpAdaptationError::SmtpUpstreamWriteIdle)
+                                    } else {
+                                        Err(SmtpAdaptationError::IcapServerWriteIdle)
+                                    }
+                                }
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         clt_msg_transfer.reset_active();
                         ups_msg_transfer.reset_active();
                     }
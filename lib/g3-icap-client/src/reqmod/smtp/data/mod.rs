@@ -108,6 +108,7 @@ impl<I: IdleCheck> SmtpMessageAdapter<I> {
                         copy_config: self.copy_config,
                         idle_checker: &self.idle_checker,
                         http_header_size: header_size,
+                        http_header_policy: self.icap_client.config.http_header_policy,
                         icap_read_finished: false,
                     };
                     let r = bidirectional_transfer
@@ -21,9 +21,13 @@ impl<I: IdleCheck> SmtpMessageAdapter<I> {
         icap_rsp: ReqmodResponse,
         http_header_size: usize,
     ) -> Result<ReqmodAdaptationEndState, SmtpAdaptationError> {
-        let _http_req =
-            HttpAdaptedRequest::parse(&mut self.icap_connection.reader, http_header_size, true)
-                .await?;
+        let _http_req = HttpAdaptedRequest::parse(
+            &mut self.icap_connection.reader,
+            http_header_size,
+            true,
+            &self.icap_client.config.http_header_policy,
+        )
+        .await?;
         self.icap_connection.mark_reader_finished();
         if icap_rsp.keep_alive {
             self.icap_client.save_connection(self.icap_connection);
@@ -45,9 +49,13 @@ impl<I: IdleCheck> SmtpMessageAdapter<I> {
     where
         UW: AsyncWrite + Unpin,
     {
-        let _http_req =
-            HttpAdaptedRequest::parse(&mut self.icap_connection.reader, http_header_size, true)
-                .await?;
+        let _http_req = HttpAdaptedRequest::parse(
+            &mut self.icap_connection.reader,
+            http_header_size,
+            true,
+            &self.icap_client.config.http_header_policy,
+        )
+        .await?;
         // TODO check request content type?
 
         let mut body_reader =
@@ -3,6 +3,7 @@
  * Copyright 2024-2025 ByteDance and/or its affiliates.
  */
 
+use std::fmt;
 use std::io::Write;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -54,6 +55,12 @@ pub struct SmtpMessageAdapter<I: IdleCheck> {
 }
 
 impl<I: IdleCheck> SmtpMessageAdapter<I> {
+    /// Set the task id, which will be used in the name of the debug capture files
+    pub fn set_task_id<T: fmt::Display>(&mut self, id: T) {
+        self.icap_client
+            .start_debug_capture(&mut self.icap_connection, id);
+    }
+
     pub fn set_client_addr(&mut self, addr: SocketAddr) {
         self.client_addr = Some(addr);
     }
@@ -8,15 +8,22 @@ use std::sync::Arc;
 use anyhow::anyhow;
 use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite};
 
-use g3_http::{H1BodyToChunkedTransfer, HttpBodyDecodeReader, HttpBodyReader};
-use g3_io_ext::{IdleCheck, LimitedBufReadExt, StreamCopy, StreamCopyConfig, StreamCopyError};
+use g3_http::{
+    HttpBodyDecodeReader, HttpBodyReader, HttpHeaderParsePolicy, PreviewableBodyTransfer,
+};
+use g3_io_ext::{
+    IdleCheck, IdleDirection, IdleTracker, LimitedBufReadExt, StreamCopy, StreamCopyConfig,
+};
 
 use super::{
     H1RespmodAdaptationError, HttpAdaptedResponse, HttpResponseClientWriter,
     HttpResponseForAdaptation, RespmodAdaptationEndState, RespmodAdaptationRunState,
 };
 use crate::respmod::response::RespmodResponse;
-use crate::{IcapClientReader, IcapClientWriter, IcapServiceClient};
+use crate::{
+    IcapClientReader, IcapClientWriter, IcapServiceClient, IcapTransactionPhase,
+    IcapTransactionTiming,
+};
 
 pub(super) struct BidirectionalRecvIcapResponse<'a, I: IdleCheck> {
     pub(super) icap_client: &'a Arc<IcapServiceClient>,
@@ -27,13 +34,14 @@ pub(super) struct BidirectionalRecvIcapResponse<'a, I: IdleCheck> {
 impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
     pub(super) async fn transfer_and_recv<UR>(
         self,
-        mut body_transfer: &mut H1BodyToChunkedTransfer<'_, UR, IcapClientWriter>,
+        mut body_transfer: &mut PreviewableBodyTransfer<'_, UR, IcapClientWriter>,
+        timing: &mut IcapTransactionTiming,
     ) -> Result<RespmodResponse, H1RespmodAdaptationError>
     where
         UR: AsyncBufRead + Unpin,
     {
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -41,33 +49,38 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
 
                 r = &mut body_transfer => {
                     return match r {
-                        Ok(_) => self.recv_icap_response().await,
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1RespmodAdaptationError::HttpUpstreamReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1RespmodAdaptationError::IcapServerWriteFailed(e)),
+                        Ok(_) => {
+                            timing.mark(IcapTransactionPhase::SendBody);
+                            self.recv_icap_response(timing).await
+                        }
+                        Err(e) => Err(H1RespmodAdaptationError::from_ups_to_icap_copy(e)),
                     };
                 }
                 r = self.icap_reader.fill_wait_data() => {
                     return match r {
-                        Ok(true) => self.recv_icap_response().await,
+                        Ok(true) => self.recv_icap_response(timing).await,
                         Ok(false) => Err(H1RespmodAdaptationError::IcapServerConnectionClosed),
                         Err(e) => Err(H1RespmodAdaptationError::IcapServerReadFailed(e)),
                     };
                 }
                 n = idle_interval.tick() => {
-                    if body_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if body_transfer.no_cached_data() {
-                                Err(H1RespmodAdaptationError::HttpUpstreamReadIdle)
-                            } else {
+                    let body_idle = body_transfer.is_idle().then(|| {
+                        if body_transfer.no_cached_data() {
+                            IdleDirection::UpstreamRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[body_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return if direction == IdleDirection::WriteBlocked {
                                 Err(H1RespmodAdaptationError::IcapServerWriteIdle)
+                            } else {
+                                Err(H1RespmodAdaptationError::HttpUpstreamReadIdle)
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         body_transfer.reset_active();
                     }
 
@@ -79,21 +92,27 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
         }
     }
 
-    async fn recv_icap_response(self) -> Result<RespmodResponse, H1RespmodAdaptationError> {
+    async fn recv_icap_response(
+        self,
+        timing: &mut IcapTransactionTiming,
+    ) -> Result<RespmodResponse, H1RespmodAdaptationError> {
         let rsp = RespmodResponse::parse(
             self.icap_reader,
             self.icap_client.config.icap_max_header_size,
         )
         .await?;
+        timing.mark(IcapTransactionPhase::WaitResponse);
         Ok(rsp)
     }
 }
 
 pub(super) struct BidirectionalRecvHttpResponse<'a, I: IdleCheck> {
     pub(super) http_body_line_max_size: usize,
+    pub(super) http_body_strict_chunked: bool,
     pub(super) copy_config: StreamCopyConfig,
     pub(super) idle_checker: &'a I,
     pub(super) http_header_size: usize,
+    pub(super) http_header_policy: HttpHeaderParsePolicy,
     pub(super) icap_read_finished: bool,
 }
 
@@ -101,7 +120,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpResponse<'_, I> {
     pub(super) async fn transfer<H, UR, CW>(
         &mut self,
         state: &mut RespmodAdaptationRunState,
-        ups_body_transfer: &mut H1BodyToChunkedTransfer<'_, UR, IcapClientWriter>,
+        ups_body_transfer: &mut PreviewableBodyTransfer<'_, UR, IcapClientWriter>,
         orig_http_response: &H,
         icap_reader: &mut IcapClientReader,
         clt_writer: &mut CW,
@@ -111,7 +130,13 @@ impl<I: IdleCheck> BidirectionalRecvHttpResponse<'_, I> {
         UR: AsyncBufRead + Unpin,
         CW: HttpResponseClientWriter<H> + Unpin,
     {
-        let http_rsp = HttpAdaptedResponse::parse(icap_reader, self.http_header_size).await?;
+        let http_rsp = HttpAdaptedResponse::parse(
+            icap_reader,
+            self.http_header_size,
+            &self.http_header_policy,
+        )
+        .await?;
+        state.mark_icap_phase(IcapTransactionPhase::ParseAdaptedHeader);
         let body_content_length = http_rsp.content_length;
 
         let final_rsp = orig_http_response.adapt_with_body(http_rsp);
@@ -129,11 +154,13 @@ impl<I: IdleCheck> BidirectionalRecvHttpResponse<'_, I> {
             Some(expected) => {
                 let mut clt_body_reader =
                     HttpBodyDecodeReader::new_chunked(icap_reader, self.http_body_line_max_size);
+                clt_body_reader.set_strict_chunked(self.http_body_strict_chunked);
                 let mut clt_body_transfer =
                     StreamCopy::new(&mut clt_body_reader, clt_writer, &self.copy_config);
                 self.do_transfer(ups_body_transfer, &mut clt_body_transfer)
                     .await?;
 
+                state.mark_icap_phase(IcapTransactionPhase::RelayAdaptedBody);
                 state.mark_clt_send_all();
                 let copied = clt_body_transfer.copied_size();
                 if clt_body_reader.trailer(128).await.is_ok() {
@@ -150,11 +177,13 @@ impl<I: IdleCheck> BidirectionalRecvHttpResponse<'_, I> {
             None => {
                 let mut clt_body_reader =
                     HttpBodyReader::new_chunked(icap_reader, self.http_body_line_max_size);
+                clt_body_reader.set_strict_chunked(self.http_body_strict_chunked);
                 let mut clt_body_transfer =
                     StreamCopy::new(&mut clt_body_reader, clt_writer, &self.copy_config);
                 self.do_transfer(ups_body_transfer, &mut clt_body_transfer)
                     .await?;
 
+                state.mark_icap_phase(IcapTransactionPhase::RelayAdaptedBody);
                 state.mark_clt_send_all();
                 self.icap_read_finished = clt_body_transfer.finished();
 
@@ -165,7 +194,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpResponse<'_, I> {
 
     async fn do_transfer<UR, IR, CW>(
         &self,
-        mut ups_body_transfer: &mut H1BodyToChunkedTransfer<'_, UR, IcapClientWriter>,
+        mut ups_body_transfer: &mut PreviewableBodyTransfer<'_, UR, IcapClientWriter>,
         mut clt_body_transfer: &mut StreamCopy<'_, IR, CW>,
     ) -> Result<(), H1RespmodAdaptationError>
     where
@@ -174,7 +203,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpResponse<'_, I> {
         CW: AsyncWrite + Unpin,
     {
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -183,42 +212,53 @@ impl<I: IdleCheck> BidirectionalRecvHttpResponse<'_, I> {
                         Ok(_) => {
                             match clt_body_transfer.await {
                                 Ok(_) => Ok(()),
-                                Err(StreamCopyError::ReadFailed(e)) => Err(H1RespmodAdaptationError::IcapServerReadFailed(e)),
-                                Err(StreamCopyError::WriteFailed(e)) => Err(H1RespmodAdaptationError::HttpClientWriteFailed(e)),
+                                Err(e) => Err(H1RespmodAdaptationError::from_icap_to_clt_copy(e)),
                             }
                         }
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1RespmodAdaptationError::HttpUpstreamReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1RespmodAdaptationError::IcapServerWriteFailed(e)),
+                        Err(e) => Err(H1RespmodAdaptationError::from_ups_to_icap_copy(e)),
                     };
                 }
                 r = &mut clt_body_transfer => {
                     return match r {
                         Ok(_) => Ok(()),
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1RespmodAdaptationError::IcapServerReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1RespmodAdaptationError::HttpClientWriteFailed(e)),
+                        Err(e) => Err(H1RespmodAdaptationError::from_icap_to_clt_copy(e)),
                     };
                 }
                 n = idle_interval.tick() => {
-                    if ups_body_transfer.is_idle() && clt_body_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if ups_body_transfer.is_idle() {
-                                if ups_body_transfer.no_cached_data() {
+                    let ups_body_idle = ups_body_transfer.is_idle().then(|| {
+                        if ups_body_transfer.no_cached_data() {
+                            IdleDirection::UpstreamRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    let clt_body_idle = clt_body_transfer.is_idle().then(|| {
+                        if clt_body_transfer.no_cached_data() {
+                            IdleDirection::IcapRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[ups_body_idle, clt_body_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return match direction {
+                                IdleDirection::UpstreamRead => {
                                     Err(H1RespmodAdaptationError::HttpUpstreamReadIdle)
-                                } else {
-                                    Err(H1RespmodAdaptationError::IcapServerWriteIdle)
                                 }
-                            } else if clt_body_transfer.no_cached_data() {
-                                Err(H1RespmodAdaptationError::IcapServerReadIdle)
-                            } else {
-                                Err(H1RespmodAdaptationError::HttpClientWriteIdle)
+                                IdleDirection::IcapRead => {
+                                    Err(H1RespmodAdaptationError::IcapServerReadIdle)
+                                }
+                                _ => {
+                                    if ups_body_transfer.no_cached_data() {
+                                        Err(H1RespmodAdaptationError::HttpClientWriteIdle)
+                                    } else {
+                                        Err(H1RespmodAdaptationError::IcapServerWriteIdle)
+                                    }
+                                }
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         ups_body_transfer.reset_active();
                         clt_body_transfer.reset_active();
                     }
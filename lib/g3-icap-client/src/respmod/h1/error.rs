@@ -8,10 +8,11 @@ use std::io;
 use thiserror::Error;
 
 use g3_http::client::HttpResponseParseError;
-use g3_io_ext::IdleForceQuitReason;
+use g3_io_ext::{IdleForceQuitReason, StreamCopyError};
 
 use crate::reason::IcapErrorReason;
 use crate::respmod::IcapRespmodParseError;
+use crate::{IcapAdaptationIoDirection, IcapAdaptationIoError, IcapAdaptationPeer};
 
 #[derive(Debug, Error)]
 pub enum H1RespmodAdaptationError {
@@ -50,3 +51,124 @@ pub enum H1RespmodAdaptationError {
     #[error("not implemented feature: {0}")]
     NotImplemented(&'static str),
 }
+
+impl H1RespmodAdaptationError {
+    /// map the error when copying http body from upstream to icap server
+    pub(super) fn from_ups_to_icap_copy(e: StreamCopyError) -> Self {
+        match e {
+            StreamCopyError::ReadFailed(e) => H1RespmodAdaptationError::HttpUpstreamReadFailed(e),
+            StreamCopyError::WriteFailed(e) => H1RespmodAdaptationError::IcapServerWriteFailed(e),
+        }
+    }
+
+    /// map the error when copying http body from icap server to client
+    pub(super) fn from_icap_to_clt_copy(e: StreamCopyError) -> Self {
+        match e {
+            StreamCopyError::ReadFailed(e) => H1RespmodAdaptationError::IcapServerReadFailed(e),
+            StreamCopyError::WriteFailed(e) => H1RespmodAdaptationError::HttpClientWriteFailed(e),
+        }
+    }
+
+    /// map the error when copying http body from upstream to client directly
+    pub(super) fn from_ups_to_clt_copy(e: StreamCopyError) -> Self {
+        match e {
+            StreamCopyError::ReadFailed(e) => H1RespmodAdaptationError::HttpUpstreamReadFailed(e),
+            StreamCopyError::WriteFailed(e) => H1RespmodAdaptationError::HttpClientWriteFailed(e),
+        }
+    }
+
+    pub fn io_error(&self) -> Option<IcapAdaptationIoError<'_>> {
+        let (peer, direction, error) = match self {
+            H1RespmodAdaptationError::IcapServerWriteFailed(e) => (
+                IcapAdaptationPeer::IcapServer,
+                IcapAdaptationIoDirection::Write,
+                e,
+            ),
+            H1RespmodAdaptationError::IcapServerReadFailed(e) => (
+                IcapAdaptationPeer::IcapServer,
+                IcapAdaptationIoDirection::Read,
+                e,
+            ),
+            H1RespmodAdaptationError::HttpUpstreamReadFailed(e) => (
+                IcapAdaptationPeer::HttpUpstream,
+                IcapAdaptationIoDirection::Read,
+                e,
+            ),
+            H1RespmodAdaptationError::HttpClientWriteFailed(e) => (
+                IcapAdaptationPeer::HttpClient,
+                IcapAdaptationIoDirection::Write,
+                e,
+            ),
+            _ => return None,
+        };
+        Some(IcapAdaptationIoError {
+            peer,
+            direction,
+            error,
+        })
+    }
+
+    pub fn error_kind(&self) -> Option<io::ErrorKind> {
+        self.io_error().map(|e| e.kind())
+    }
+
+    pub fn is_peer_reset(&self) -> bool {
+        self.io_error().map(|e| e.is_peer_reset()).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_kind() {
+        let e = H1RespmodAdaptationError::from_ups_to_icap_copy(StreamCopyError::ReadFailed(
+            io::Error::from(io::ErrorKind::TimedOut),
+        ));
+        let io_err = e.io_error().unwrap();
+        assert_eq!(io_err.peer, IcapAdaptationPeer::HttpUpstream);
+        assert_eq!(io_err.direction, IcapAdaptationIoDirection::Read);
+        assert_eq!(e.error_kind(), Some(io::ErrorKind::TimedOut));
+        assert!(!e.is_peer_reset());
+
+        let e = H1RespmodAdaptationError::from_ups_to_icap_copy(StreamCopyError::WriteFailed(
+            io::Error::from(io::ErrorKind::BrokenPipe),
+        ));
+        let io_err = e.io_error().unwrap();
+        assert_eq!(io_err.peer, IcapAdaptationPeer::IcapServer);
+        assert_eq!(io_err.direction, IcapAdaptationIoDirection::Write);
+        assert!(e.is_peer_reset());
+    }
+
+    #[test]
+    fn recv_kind() {
+        let e = H1RespmodAdaptationError::from_icap_to_clt_copy(StreamCopyError::ReadFailed(
+            io::Error::from(io::ErrorKind::InvalidData),
+        ));
+        let io_err = e.io_error().unwrap();
+        assert_eq!(io_err.peer, IcapAdaptationPeer::IcapServer);
+        assert_eq!(io_err.direction, IcapAdaptationIoDirection::Read);
+        assert_eq!(e.error_kind(), Some(io::ErrorKind::InvalidData));
+
+        let e = H1RespmodAdaptationError::from_icap_to_clt_copy(StreamCopyError::WriteFailed(
+            io::Error::from(io::ErrorKind::ConnectionReset),
+        ));
+        let io_err = e.io_error().unwrap();
+        assert_eq!(io_err.peer, IcapAdaptationPeer::HttpClient);
+        assert_eq!(io_err.direction, IcapAdaptationIoDirection::Write);
+        assert!(e.is_peer_reset());
+
+        let e = H1RespmodAdaptationError::from_ups_to_clt_copy(StreamCopyError::ReadFailed(
+            io::Error::from(io::ErrorKind::ConnectionReset),
+        ));
+        assert_eq!(e.io_error().unwrap().peer, IcapAdaptationPeer::HttpUpstream);
+        assert!(e.is_peer_reset());
+
+        assert!(
+            H1RespmodAdaptationError::IcapServerConnectionClosed
+                .error_kind()
+                .is_none()
+        );
+    }
+}
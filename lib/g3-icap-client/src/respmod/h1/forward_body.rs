@@ -8,18 +8,18 @@ use std::io::{IoSlice, Write};
 use bytes::BufMut;
 use tokio::io::{AsyncBufRead, AsyncWriteExt};
 
-use g3_http::{H1BodyToChunkedTransfer, HttpBodyReader, HttpBodyType};
-use g3_io_ext::{IdleCheck, LimitedWriteExt, StreamCopy, StreamCopyError};
+use g3_http::{HttpBodyReader, PreviewableBodyTransfer};
+use g3_io_ext::{IdleCheck, LimitedWriteExt, StreamCopy};
 
 use super::{
     BidirectionalRecvHttpResponse, BidirectionalRecvIcapResponse, H1RespmodAdaptationError,
-    HttpResponseAdapter, HttpResponseClientWriter, HttpResponseForAdaptation,
-    RespmodAdaptationEndState, RespmodAdaptationRunState,
+    HttpRequestForRespmod, HttpResponseAdapter, HttpResponseClientWriter,
+    HttpResponseForAdaptation, RespmodAdaptationEndState, RespmodAdaptationRunState,
 };
 use crate::reason::IcapErrorReason;
-use crate::reqmod::h1::HttpRequestForAdaptation;
 use crate::respmod::IcapRespmodResponsePayload;
 use crate::respmod::response::RespmodResponse;
+use crate::{IcapClientWriter, IcapTransactionPhase};
 
 impl<I: IdleCheck> HttpResponseAdapter<I> {
     fn build_forward_all_request(
@@ -48,7 +48,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         clt_writer: &mut CW,
     ) -> Result<RespmodAdaptationEndState<H>, H1RespmodAdaptationError>
     where
-        R: HttpRequestForAdaptation,
+        R: HttpRequestForRespmod,
         H: HttpResponseForAdaptation,
         CW: HttpResponseClientWriter<H> + Unpin,
     {
@@ -75,6 +75,8 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
             .flush()
             .await
             .map_err(H1RespmodAdaptationError::IcapServerWriteFailed)?;
+        state.mark_icap_phase(IcapTransactionPhase::SendHeader);
+        state.mark_icap_phase(IcapTransactionPhase::SendBody);
         self.icap_connection.mark_writer_finished();
 
         self.handle_small_body_response(state, http_response, clt_writer)
@@ -91,7 +93,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         clt_writer: &mut CW,
     ) -> Result<RespmodAdaptationEndState<H>, H1RespmodAdaptationError>
     where
-        R: HttpRequestForAdaptation,
+        R: HttpRequestForRespmod,
         H: HttpResponseForAdaptation,
         UR: AsyncBufRead + Unpin,
         CW: HttpResponseClientWriter<H> + Unpin,
@@ -127,9 +129,11 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                 .await
                 .map_err(H1RespmodAdaptationError::IcapServerWriteFailed)?;
         }
+        state.mark_icap_phase(IcapTransactionPhase::SendHeader);
 
         self.recv_send_trailer(&mut trailer_reader).await?;
 
+        state.mark_icap_phase(IcapTransactionPhase::SendBody);
         state.mark_ups_recv_all();
         self.icap_connection.mark_writer_finished();
 
@@ -159,8 +163,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                 r = &mut trailer_transfer => {
                     return match r {
                         Ok(_) => Ok(()),
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1RespmodAdaptationError::HttpUpstreamReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1RespmodAdaptationError::IcapServerWriteFailed(e)),
+                        Err(e) => Err(H1RespmodAdaptationError::from_ups_to_icap_copy(e)),
                     };
                 }
                 n = idle_interval.tick() => {
@@ -204,6 +207,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
             self.icap_client.config.icap_max_header_size,
         )
         .await?;
+        state.mark_icap_phase(IcapTransactionPhase::WaitResponse);
         match rsp.code {
             204 | 206 => {
                 return Err(H1RespmodAdaptationError::IcapServerErrorResponse(
@@ -255,12 +259,11 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         state: &mut RespmodAdaptationRunState,
         http_request: &R,
         http_response: &H,
-        ups_body_type: HttpBodyType,
-        ups_body_io: &mut UR,
+        body_transfer: PreviewableBodyTransfer<'_, UR, IcapClientWriter>,
         clt_writer: &mut CW,
     ) -> Result<RespmodAdaptationEndState<H>, H1RespmodAdaptationError>
     where
-        R: HttpRequestForAdaptation,
+        R: HttpRequestForRespmod,
         H: HttpResponseForAdaptation,
         UR: AsyncBufRead + Unpin,
         CW: HttpResponseClientWriter<H> + Unpin,
@@ -279,21 +282,16 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
             ])
             .await
             .map_err(H1RespmodAdaptationError::IcapServerWriteFailed)?;
+        state.mark_icap_phase(IcapTransactionPhase::SendHeader);
 
-        let mut body_transfer = H1BodyToChunkedTransfer::new(
-            ups_body_io,
-            &mut self.icap_connection.writer,
-            ups_body_type,
-            self.http_body_line_max_size,
-            self.copy_config,
-        );
+        let mut body_transfer = body_transfer.resume(&mut self.icap_connection.writer);
         let bidirectional_transfer = BidirectionalRecvIcapResponse {
             icap_client: &self.icap_client,
             icap_reader: &mut self.icap_connection.reader,
             idle_checker: &self.idle_checker,
         };
         let rsp = bidirectional_transfer
-            .transfer_and_recv(&mut body_transfer)
+            .transfer_and_recv(&mut body_transfer, &mut state.icap_timing)
             .await?;
         if body_transfer.finished() {
             state.mark_ups_recv_all();
@@ -351,9 +349,11 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                 } else {
                     let mut bidirectional_transfer = BidirectionalRecvHttpResponse {
                         http_body_line_max_size: self.http_body_line_max_size,
+                        http_body_strict_chunked: self.icap_client.config.strict_chunked,
                         copy_config: self.copy_config,
                         idle_checker: &self.idle_checker,
                         http_header_size: header_size,
+                        http_header_policy: self.icap_client.config.http_header_policy,
                         icap_read_finished: false,
                     };
                     let r = bidirectional_transfer
@@ -366,6 +366,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                         )
                         .await?;
                     if body_transfer.finished() {
+                        state.mark_icap_phase(IcapTransactionPhase::SendBody);
                         state.mark_ups_recv_all();
                         self.icap_connection.mark_writer_finished();
                         if bidirectional_transfer.icap_read_finished {
@@ -11,11 +11,11 @@ use tokio::io::AsyncWriteExt;
 use g3_io_ext::{IdleCheck, LimitedWriteExt};
 
 use super::{
-    H1RespmodAdaptationError, HttpResponseAdapter, HttpResponseClientWriter,
+    H1RespmodAdaptationError, HttpRequestForRespmod, HttpResponseAdapter, HttpResponseClientWriter,
     HttpResponseForAdaptation, RespmodAdaptationEndState, RespmodAdaptationRunState,
 };
+use crate::IcapTransactionPhase;
 use crate::reason::IcapErrorReason;
-use crate::reqmod::h1::HttpRequestForAdaptation;
 use crate::respmod::IcapRespmodResponsePayload;
 use crate::respmod::response::RespmodResponse;
 
@@ -48,7 +48,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         clt_writer: &mut CW,
     ) -> Result<RespmodAdaptationEndState<H>, H1RespmodAdaptationError>
     where
-        R: HttpRequestForAdaptation,
+        R: HttpRequestForRespmod,
         H: HttpResponseForAdaptation,
         CW: HttpResponseClientWriter<H> + Unpin,
     {
@@ -70,6 +70,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
             .flush()
             .await
             .map_err(H1RespmodAdaptationError::IcapServerWriteFailed)?;
+        state.mark_icap_phase(IcapTransactionPhase::SendHeader);
         self.icap_connection.mark_writer_finished();
 
         let rsp = RespmodResponse::parse(
@@ -77,6 +78,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
             self.icap_client.config.icap_max_header_size,
         )
         .await?;
+        state.mark_icap_phase(IcapTransactionPhase::WaitResponse);
 
         match rsp.code {
             204 => {
@@ -3,21 +3,30 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::io;
+use std::io::{self, Write};
 
-use http::Method;
+use bytes::BufMut;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode, header};
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 use g3_http::HttpBodyType;
 use g3_http::client::{HttpForwardRemoteResponse, HttpTransparentResponse};
 
-use super::{HttpAdaptedResponse, HttpResponseClientWriter, HttpResponseForAdaptation};
+use super::{
+    HttpAdaptedResponse, HttpRequestForRespmod, HttpResponseClientWriter, HttpResponseForAdaptation,
+};
 
 impl HttpResponseForAdaptation for HttpForwardRemoteResponse {
     fn body_type(&self, method: &Method) -> Option<HttpBodyType> {
         self.body_type(method)
     }
 
+    fn content_type(&self) -> Option<&str> {
+        self.end_to_end_headers
+            .get(header::CONTENT_TYPE)
+            .map(|v| v.to_str())
+    }
+
     fn serialize_for_client(&self) -> Vec<u8> {
         self.serialize()
     }
@@ -40,6 +49,12 @@ impl HttpResponseForAdaptation for HttpTransparentResponse {
         self.body_type(method)
     }
 
+    fn content_type(&self) -> Option<&str> {
+        self.end_to_end_headers
+            .get(header::CONTENT_TYPE)
+            .map(|v| v.to_str())
+    }
+
     fn serialize_for_client(&self) -> Vec<u8> {
         self.serialize()
     }
@@ -57,6 +72,157 @@ impl HttpResponseForAdaptation for HttpTransparentResponse {
     }
 }
 
+fn is_hop_by_hop_header(name: &HeaderName) -> bool {
+    matches!(
+        name.as_str(),
+        "connection"
+            | "keep-alive"
+            | "proxy-connection"
+            | "te"
+            | "trailer"
+            | "transfer-encoding"
+            | "upgrade"
+    )
+}
+
+fn write_headers(buf: &mut Vec<u8>, headers: &HeaderMap, end_to_end_only: bool) {
+    for (name, value) in headers {
+        if end_to_end_only && is_hop_by_hop_header(name) {
+            continue;
+        }
+        buf.put_slice(name.as_str().as_bytes());
+        buf.put_slice(b": ");
+        buf.put_slice(value.as_bytes());
+        buf.put_slice(b"\r\n");
+    }
+    buf.put_slice(b"\r\n");
+}
+
+/// The body of the request is not used in RESPMOD, only the header will be sent to the ICAP server
+impl HttpRequestForRespmod for Request<()> {
+    fn method(&self) -> &Method {
+        self.method()
+    }
+
+    fn uri_path(&self) -> &str {
+        self.uri().path()
+    }
+
+    fn serialize_for_adapter(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(1024);
+        let _ = write!(
+            buf,
+            "{} {} {:?}\r\n",
+            self.method(),
+            self.uri(),
+            self.version()
+        );
+        write_headers(&mut buf, self.headers(), true);
+        buf
+    }
+}
+
+fn serialize_response(rsp: &Response<()>, end_to_end_only: bool) -> Vec<u8> {
+    let mut buf = Vec::<u8>::with_capacity(1024);
+    let status = rsp.status();
+    let _ = write!(
+        buf,
+        "{:?} {} {}\r\n",
+        rsp.version(),
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    );
+    write_headers(&mut buf, rsp.headers(), end_to_end_only);
+    buf
+}
+
+fn adapt_response(orig: &Response<()>, adapted: HttpAdaptedResponse) -> Response<()> {
+    let mut headers = HeaderMap::from(adapted.headers);
+    for (name, value) in orig.headers() {
+        if is_hop_by_hop_header(name) && *name != header::TRANSFER_ENCODING {
+            headers.append(name, value.clone());
+        }
+    }
+    let mut rsp = Response::new(());
+    *rsp.version_mut() = adapted.version;
+    *rsp.status_mut() = adapted.status;
+    *rsp.headers_mut() = headers;
+    rsp
+}
+
+/// The body type is detected from the status code and the headers, and the adapted response will
+/// use chunked transfer encoding if no Content-Length is returned by the ICAP server
+impl HttpResponseForAdaptation for Response<()> {
+    fn body_type(&self, method: &Method) -> Option<HttpBodyType> {
+        let status = self.status();
+        if status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+            || method.eq(&Method::HEAD)
+        {
+            return None;
+        }
+
+        let headers = self.headers();
+        if let Some(v) = headers.get_all(header::TRANSFER_ENCODING).iter().last() {
+            let chunked = v
+                .to_str()
+                .ok()
+                .and_then(|s| s.rsplit(',').next())
+                .map(|s| s.trim().eq_ignore_ascii_case("chunked"))
+                .unwrap_or(false);
+            return if chunked {
+                Some(HttpBodyType::Chunked)
+            } else {
+                Some(HttpBodyType::ReadUntilEnd)
+            };
+        }
+        let content_length = headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.parse::<u64>().ok());
+        match content_length {
+            Some(0) => None,
+            Some(n) => Some(HttpBodyType::ContentLength(n)),
+            None => Some(HttpBodyType::ReadUntilEnd),
+        }
+    }
+
+    fn content_type(&self) -> Option<&str> {
+        self.headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+    }
+
+    fn serialize_for_client(&self) -> Vec<u8> {
+        serialize_response(self, false)
+    }
+
+    fn serialize_for_adapter(&self) -> Vec<u8> {
+        serialize_response(self, true)
+    }
+
+    fn adapt_with_body(&self, other: HttpAdaptedResponse) -> Self {
+        let chunked = other.content_length.is_none();
+        let mut rsp = adapt_response(self, other);
+        if chunked {
+            rsp.headers_mut().remove(header::CONTENT_LENGTH);
+            rsp.headers_mut().insert(
+                header::TRANSFER_ENCODING,
+                HeaderValue::from_static("chunked"),
+            );
+        }
+        rsp
+    }
+
+    fn adapt_without_body(&self, other: HttpAdaptedResponse) -> Self {
+        let mut rsp = adapt_response(self, other);
+        rsp.headers_mut()
+            .insert(header::CONTENT_LENGTH, HeaderValue::from_static("0"));
+        rsp
+    }
+}
+
 impl<W, H> HttpResponseClientWriter<H> for W
 where
     W: AsyncWrite + Send + Unpin,
@@ -67,3 +233,99 @@ where
         self.write_all(&head).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use http::Version;
+
+    use g3_types::net::{HttpHeaderMap, HttpHeaderValue};
+
+    #[test]
+    fn response_body_type() {
+        let rsp = Response::builder()
+            .header(header::CONTENT_LENGTH, "11")
+            .body(())
+            .unwrap();
+        assert_eq!(
+            rsp.body_type(&Method::GET),
+            Some(HttpBodyType::ContentLength(11))
+        );
+        assert_eq!(rsp.body_type(&Method::HEAD), None);
+
+        let rsp = Response::builder()
+            .header(header::TRANSFER_ENCODING, "gzip, chunked")
+            .body(())
+            .unwrap();
+        assert_eq!(rsp.body_type(&Method::GET), Some(HttpBodyType::Chunked));
+
+        let rsp = Response::builder().body(()).unwrap();
+        assert_eq!(
+            rsp.body_type(&Method::GET),
+            Some(HttpBodyType::ReadUntilEnd)
+        );
+
+        let rsp = Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(())
+            .unwrap();
+        assert_eq!(rsp.body_type(&Method::GET), None);
+    }
+
+    #[test]
+    fn response_adapt() {
+        let rsp = Response::builder()
+            .header(header::CONTENT_LENGTH, "11")
+            .header(header::CONNECTION, "keep-alive")
+            .body(())
+            .unwrap();
+        assert_eq!(
+            rsp.serialize_for_client(),
+            b"HTTP/1.1 200 OK\r\ncontent-length: 11\r\nconnection: keep-alive\r\n\r\n"
+        );
+        assert_eq!(
+            rsp.serialize_for_adapter(),
+            b"HTTP/1.1 200 OK\r\ncontent-length: 11\r\n\r\n"
+        );
+
+        let mut headers = HttpHeaderMap::default();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HttpHeaderValue::from_str("text/plain").unwrap(),
+        );
+        let adapted = HttpAdaptedResponse {
+            version: Version::HTTP_11,
+            status: StatusCode::FORBIDDEN,
+            reason: "Forbidden".to_string(),
+            headers,
+            content_length: None,
+        };
+        let adapted = rsp.adapt_with_body(adapted);
+        assert_eq!(adapted.status(), StatusCode::FORBIDDEN);
+        assert_eq!(adapted.body_type(&Method::GET), Some(HttpBodyType::Chunked));
+        assert_eq!(
+            adapted
+                .headers()
+                .get(header::CONNECTION)
+                .unwrap()
+                .as_bytes(),
+            b"keep-alive"
+        );
+    }
+
+    #[test]
+    fn request_serialize() {
+        let req = Request::builder()
+            .uri("http://example.net/index.html")
+            .header(header::HOST, "example.net")
+            .header(header::TE, "trailers")
+            .body(())
+            .unwrap();
+        assert_eq!(
+            HttpRequestForRespmod::serialize_for_adapter(&req),
+            b"GET http://example.net/index.html HTTP/1.1\r\nhost: example.net\r\n\r\n"
+        );
+    }
+}
@@ -3,6 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::fmt;
 use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -12,14 +13,17 @@ use http::Method;
 use tokio::io::{AsyncBufRead, AsyncWrite};
 use tokio::time::Instant;
 
-use g3_http::HttpBodyType;
 use g3_http::client::HttpAdaptedResponse;
+use g3_http::{HttpBodyType, PreviewableBodyTransfer};
 use g3_io_ext::{IdleCheck, StreamCopyConfig};
 use g3_types::net::HttpHeaderMap;
 
 use super::IcapRespmodClient;
 use crate::reqmod::h1::HttpRequestForAdaptation;
-use crate::{IcapClientConnection, IcapServiceClient, IcapServiceOptions};
+use crate::{
+    IcapClientConnection, IcapServiceClient, IcapServiceOptions, IcapTransactionPhase,
+    IcapTransactionTiming,
+};
 
 mod error;
 pub use error::H1RespmodAdaptationError;
@@ -35,14 +39,43 @@ mod preview;
 
 mod impl_trait;
 
+mod session;
+pub use session::{IcapRespmodH1Params, IcapRespmodH1Session, RespmodIdleChecker};
+
+/// The original HTTP request, which will be sent to the ICAP server along with the response
+pub trait HttpRequestForRespmod {
+    fn method(&self) -> &Method;
+    fn uri_path(&self) -> &str;
+    fn serialize_for_adapter(&self) -> Vec<u8>;
+}
+
+impl<T: HttpRequestForAdaptation> HttpRequestForRespmod for T {
+    fn method(&self) -> &Method {
+        HttpRequestForAdaptation::method(self)
+    }
+
+    fn uri_path(&self) -> &str {
+        HttpRequestForAdaptation::uri_path(self)
+    }
+
+    fn serialize_for_adapter(&self) -> Vec<u8> {
+        HttpRequestForAdaptation::serialize_for_adapter(self)
+    }
+}
+
+/// The HTTP response to be adapted
 pub trait HttpResponseForAdaptation {
     fn body_type(&self, method: &Method) -> Option<HttpBodyType>;
+    fn content_type(&self) -> Option<&str>;
     fn serialize_for_client(&self) -> Vec<u8>;
     fn serialize_for_adapter(&self) -> Vec<u8>;
     fn adapt_with_body(&self, other: HttpAdaptedResponse) -> Self;
     fn adapt_without_body(&self, other: HttpAdaptedResponse) -> Self;
 }
 
+/// The writer to send the final HTTP response to the client
+///
+/// It's implemented for all `AsyncWrite` types, which sends the response header as is.
 #[allow(async_fn_in_trait)]
 pub trait HttpResponseClientWriter<H: HttpResponseForAdaptation>: AsyncWrite {
     async fn send_response_header(&mut self, req: &H) -> io::Result<()>;
@@ -55,12 +88,15 @@ impl IcapRespmodClient {
         http_body_line_max_size: usize,
         idle_checker: I,
     ) -> anyhow::Result<HttpResponseAdapter<I>> {
+        let mut icap_timing = IcapTransactionTiming::new(Instant::now());
         let icap_client = self.inner.clone();
         let (icap_connection, icap_options) = icap_client.fetch_connection().await?;
+        icap_timing.mark(IcapTransactionPhase::Connect);
         Ok(HttpResponseAdapter {
             icap_client,
             icap_connection,
             icap_options,
+            icap_timing,
             copy_config,
             http_body_line_max_size,
             idle_checker,
@@ -75,6 +111,7 @@ pub struct HttpResponseAdapter<I: IdleCheck> {
     icap_client: Arc<IcapServiceClient>,
     icap_connection: IcapClientConnection,
     icap_options: Arc<IcapServiceOptions>,
+    icap_timing: IcapTransactionTiming,
     copy_config: StreamCopyConfig,
     http_body_line_max_size: usize,
     idle_checker: I,
@@ -86,12 +123,14 @@ pub struct HttpResponseAdapter<I: IdleCheck> {
 pub struct RespmodAdaptationRunState {
     task_create_instant: Instant,
     dur_ups_recv_header: Duration,
-    pub dur_ups_recv_all: Option<Duration>,
-    pub dur_clt_send_header: Option<Duration>,
-    pub dur_clt_send_all: Option<Duration>,
-    pub ups_read_finished: bool,
-    pub clt_write_started: bool,
-    pub clt_write_finished: bool,
+    dur_ups_recv_all: Option<Duration>,
+    dur_clt_send_header: Option<Duration>,
+    dur_clt_send_all: Option<Duration>,
+    ups_read_finished: bool,
+    clt_write_started: bool,
+    clt_write_finished: bool,
+    preview_size: Option<usize>,
+    icap_timing: IcapTransactionTiming,
 }
 
 impl RespmodAdaptationRunState {
@@ -105,9 +144,63 @@ impl RespmodAdaptationRunState {
             ups_read_finished: false,
             clt_write_started: false,
             clt_write_finished: false,
+            preview_size: None,
+            icap_timing: IcapTransactionTiming::new(task_create_instant),
         }
     }
 
+    /// Get the time spent to receive the whole upstream response, from the creation of the task
+    #[inline]
+    pub fn dur_ups_recv_all(&self) -> Option<Duration> {
+        self.dur_ups_recv_all
+    }
+
+    /// Get the time spent to send the response header to client, from the creation of the task
+    #[inline]
+    pub fn dur_clt_send_header(&self) -> Option<Duration> {
+        self.dur_clt_send_header
+    }
+
+    /// Get the time spent to send the whole response to client, from the creation of the task
+    #[inline]
+    pub fn dur_clt_send_all(&self) -> Option<Duration> {
+        self.dur_clt_send_all
+    }
+
+    /// Check if the upstream response body has been read to the end
+    #[inline]
+    pub fn ups_read_finished(&self) -> bool {
+        self.ups_read_finished
+    }
+
+    /// Check if any response data has been sent to the client
+    #[inline]
+    pub fn clt_write_started(&self) -> bool {
+        self.clt_write_started
+    }
+
+    /// Check if the whole response has been sent to the client
+    #[inline]
+    pub fn clt_write_finished(&self) -> bool {
+        self.clt_write_finished
+    }
+
+    /// Get the preview size used for the ICAP request, `None` if no preview is sent
+    #[inline]
+    pub fn preview_size(&self) -> Option<usize> {
+        self.preview_size
+    }
+
+    /// Get the timing of each phase of the ICAP transaction
+    #[inline]
+    pub fn icap_timing(&self) -> &IcapTransactionTiming {
+        &self.icap_timing
+    }
+
+    pub(crate) fn mark_icap_phase(&mut self, phase: IcapTransactionPhase) {
+        self.icap_timing.mark(phase);
+    }
+
     pub(crate) fn mark_ups_recv_no_body(&mut self) {
         self.dur_ups_recv_all = Some(self.dur_ups_recv_header);
         self.ups_read_finished = true;
@@ -138,6 +231,12 @@ impl RespmodAdaptationRunState {
 }
 
 impl<I: IdleCheck> HttpResponseAdapter<I> {
+    /// Set the task id, which will be used in the name of the debug capture files
+    pub fn set_task_id<T: fmt::Display>(&mut self, id: T) {
+        self.icap_client
+            .start_debug_capture(&mut self.icap_connection, id);
+    }
+
     pub fn set_client_addr(&mut self, addr: SocketAddr) {
         self.client_addr = Some(addr);
     }
@@ -162,11 +261,17 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         }
     }
 
-    fn preview_size(&self) -> Option<usize> {
-        if self.icap_client.config.disable_preview {
-            return None;
-        }
-        self.icap_options.preview_size
+    fn preview_size<R, H>(&self, http_request: &R, http_response: &H) -> Option<usize>
+    where
+        R: HttpRequestForRespmod,
+        H: HttpResponseForAdaptation,
+    {
+        self.icap_client.config.preview_size(
+            self.icap_options.preview_size,
+            http_request.method(),
+            http_response.content_type(),
+            http_request.uri_path(),
+        )
     }
 
     pub async fn xfer<R, H, UR, CW>(
@@ -178,13 +283,37 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         clt_writer: &mut CW,
     ) -> Result<RespmodAdaptationEndState<H>, H1RespmodAdaptationError>
     where
-        R: HttpRequestForAdaptation,
+        R: HttpRequestForRespmod,
+        H: HttpResponseForAdaptation,
+        UR: AsyncBufRead + Unpin,
+        CW: HttpResponseClientWriter<H> + Unpin,
+    {
+        state.icap_timing = self.icap_timing;
+        let icap_client = self.icap_client.clone();
+        let r = self
+            .xfer_by_body_type(state, http_request, http_response, ups_body_io, clt_writer)
+            .await;
+        icap_client.record_transaction_timing(&state.icap_timing);
+        r
+    }
+
+    async fn xfer_by_body_type<R, H, UR, CW>(
+        self,
+        state: &mut RespmodAdaptationRunState,
+        http_request: &R,
+        http_response: &H,
+        ups_body_io: &mut UR,
+        clt_writer: &mut CW,
+    ) -> Result<RespmodAdaptationEndState<H>, H1RespmodAdaptationError>
+    where
+        R: HttpRequestForRespmod,
         H: HttpResponseForAdaptation,
         UR: AsyncBufRead + Unpin,
         CW: HttpResponseClientWriter<H> + Unpin,
     {
         if let Some(body_type) = http_response.body_type(http_request.method()) {
-            if let Some(preview_size) = self.preview_size() {
+            state.preview_size = self.preview_size(http_request, http_response);
+            if let Some(preview_size) = state.preview_size {
                 self.xfer_with_preview(
                     state,
                     http_request,
@@ -196,12 +325,17 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                 )
                 .await
             } else {
+                let body_transfer = PreviewableBodyTransfer::without_preview(
+                    ups_body_io,
+                    body_type,
+                    self.http_body_line_max_size,
+                    self.copy_config,
+                );
                 self.xfer_without_preview(
                     state,
                     http_request,
                     http_response,
-                    body_type,
-                    ups_body_io,
+                    body_transfer,
                     clt_writer,
                 )
                 .await
@@ -214,7 +348,10 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
     }
 }
 
+/// The end state of a successful RESPMOD adaptation
 pub enum RespmodAdaptationEndState<H: HttpResponseForAdaptation> {
+    /// The original response has been sent to the client, as no modification is needed
     OriginalTransferred,
+    /// The adapted response has been sent to the client
     AdaptedTransferred(H),
 }
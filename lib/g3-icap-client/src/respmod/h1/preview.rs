@@ -5,25 +5,24 @@
 
 use std::future::poll_fn;
 use std::io::{IoSlice, Write};
-use std::pin::Pin;
 use std::task::Poll;
 use std::time::Duration;
 
 use bytes::BufMut;
-use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
 
-use g3_http::{ChunkedDataDecodeReader, H1BodyToChunkedTransfer, HttpBodyReader, HttpBodyType};
-use g3_io_ext::{IdleCheck, LimitedWriteExt, StreamCopy, StreamCopyError};
+use g3_http::{HttpBodyReader, HttpBodyType, PreviewableBodyTransfer};
+use g3_io_ext::{IdleCheck, LimitedWriteExt, StreamCopy};
 
 use super::{
     BidirectionalRecvHttpResponse, BidirectionalRecvIcapResponse, H1RespmodAdaptationError,
-    HttpResponseAdapter, HttpResponseClientWriter, HttpResponseForAdaptation,
-    RespmodAdaptationEndState, RespmodAdaptationRunState,
+    HttpRequestForRespmod, HttpResponseAdapter, HttpResponseClientWriter,
+    HttpResponseForAdaptation, RespmodAdaptationEndState, RespmodAdaptationRunState,
 };
 use crate::reason::IcapErrorReason;
-use crate::reqmod::h1::HttpRequestForAdaptation;
 use crate::respmod::IcapRespmodResponsePayload;
 use crate::respmod::response::RespmodResponse;
+use crate::{IcapClientWriter, IcapTransactionPhase};
 
 impl<I: IdleCheck> HttpResponseAdapter<I> {
     fn build_preview_request(
@@ -57,153 +56,67 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         preview_size: usize,
     ) -> Result<RespmodAdaptationEndState<H>, H1RespmodAdaptationError>
     where
-        R: HttpRequestForAdaptation,
+        R: HttpRequestForRespmod,
         H: HttpResponseForAdaptation,
         UR: AsyncBufRead + Unpin,
         CW: HttpResponseClientWriter<H> + Unpin,
     {
-        let mut left_chunk_size = 0;
-        let preview_buf: Vec<u8>;
-        let ups_body_type = match ups_body_type {
-            HttpBodyType::ReadUntilEnd => {
-                let mut ups_body_reader = HttpBodyReader::new_read_until_end(ups_body_io);
-                match self
-                    .read_plain_preview_data(
-                        &mut ups_body_reader,
-                        preview_size,
-                        self.icap_client.config.preview_data_read_timeout,
-                    )
-                    .await
-                {
-                    Ok(Some(buf)) => preview_buf = buf,
-                    Ok(None) => {
-                        return self
-                            .xfer_without_preview(
-                                state,
-                                http_request,
-                                http_response,
-                                ups_body_type,
-                                ups_body_io,
-                                clt_writer,
-                            )
-                            .await;
-                    }
-                    Err(e) => return Err(e),
-                }
-                if ups_body_reader.finished() {
-                    if preview_buf.is_empty() {
-                        state.mark_ups_recv_no_body();
-                        return self
-                            .xfer_without_body(state, http_request, http_response, clt_writer)
-                            .await;
-                    } else {
-                        state.mark_ups_recv_all();
-                        return self
-                            .xfer_small_body(
-                                state,
-                                http_request,
-                                http_response,
-                                preview_buf,
-                                clt_writer,
-                            )
-                            .await;
-                    }
-                }
-
-                HttpBodyType::ReadUntilEnd
-            }
-            HttpBodyType::ContentLength(n) => {
-                let mut ups_body_reader = HttpBodyReader::new_fixed_length(ups_body_io, n);
-                match self
-                    .read_plain_preview_data(
-                        &mut ups_body_reader,
-                        preview_size,
-                        self.icap_client.config.preview_data_read_timeout,
-                    )
-                    .await
-                {
-                    Ok(Some(buf)) => preview_buf = buf,
-                    Ok(None) => {
-                        return self
-                            .xfer_without_preview(
-                                state,
-                                http_request,
-                                http_response,
-                                ups_body_type,
-                                ups_body_io,
-                                clt_writer,
-                            )
-                            .await;
-                    }
-                    Err(e) => return Err(e),
-                }
-                if ups_body_reader.finished() {
-                    state.mark_ups_recv_all();
+        let mut body_transfer = PreviewableBodyTransfer::new(
+            ups_body_io,
+            ups_body_type,
+            self.http_body_line_max_size,
+            preview_size,
+            self.copy_config,
+        );
+        if !self
+            .read_preview_data(
+                &mut body_transfer,
+                self.icap_client.config.preview_data_read_timeout,
+            )
+            .await?
+        {
+            return self
+                .xfer_without_preview(
+                    state,
+                    http_request,
+                    http_response,
+                    body_transfer,
+                    clt_writer,
+                )
+                .await;
+        }
+        let body_transfer = match body_transfer.into_whole_body() {
+            Ok((body, None)) => {
+                if body.is_empty() && ups_body_type == HttpBodyType::ReadUntilEnd {
+                    state.mark_ups_recv_no_body();
                     return self
-                        .xfer_small_body(
-                            state,
-                            http_request,
-                            http_response,
-                            preview_buf,
-                            clt_writer,
-                        )
+                        .xfer_without_body(state, http_request, http_response, clt_writer)
                         .await;
                 }
-
-                HttpBodyType::ContentLength(n - (preview_buf.len() as u64))
+                state.mark_ups_recv_all();
+                return self
+                    .xfer_small_body(state, http_request, http_response, body, clt_writer)
+                    .await;
             }
-            HttpBodyType::Chunked => {
-                let mut ups_body_reader =
-                    ChunkedDataDecodeReader::new(ups_body_io, self.http_body_line_max_size);
-                match self
-                    .read_chunked_preview_data(
-                        &mut ups_body_reader,
-                        preview_size,
-                        self.icap_client.config.preview_data_read_timeout,
-                    )
-                    .await
-                {
-                    Ok(Some(buf)) => preview_buf = buf,
-                    Ok(None) => {
-                        return self
-                            .xfer_without_preview(
-                                state,
-                                http_request,
-                                http_response,
-                                ups_body_type,
-                                ups_body_io,
-                                clt_writer,
-                            )
-                            .await;
-                    }
-                    Err(e) => return Err(e),
-                }
-                if ups_body_reader.finished() {
-                    let trailer_reader =
-                        HttpBodyReader::new_trailer(ups_body_io, self.http_body_line_max_size);
-                    return self
-                        .xfer_small_body_chunked(
-                            state,
-                            http_request,
-                            http_response,
-                            preview_buf,
-                            trailer_reader,
-                            clt_writer,
-                        )
-                        .await;
-                }
-                left_chunk_size = ups_body_reader.left_chunk_size().ok_or_else(|| {
-                    H1RespmodAdaptationError::InternalServerError(
-                        "broken chunked encoding after preview read",
+            Ok((body, Some(trailer_reader))) => {
+                return self
+                    .xfer_small_body_chunked(
+                        state,
+                        http_request,
+                        http_response,
+                        body,
+                        trailer_reader,
+                        clt_writer,
                     )
-                })?;
-
-                HttpBodyType::Chunked
+                    .await;
             }
+            Err(body_transfer) => *body_transfer,
         };
 
-        self.send_preview_data(http_request, http_response, &preview_buf)
+        self.send_preview_data(http_request, http_response, body_transfer.preview_data())
             .await?;
+        state.mark_icap_phase(IcapTransactionPhase::SendHeader);
+        state.mark_icap_phase(IcapTransactionPhase::SendPreview);
 
         let rsp = RespmodResponse::parse(
             &mut self.icap_connection.reader,
@@ -213,33 +126,15 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
 
         match rsp.code {
             100 => {
-                let mut body_transfer = match ups_body_type {
-                    HttpBodyType::ReadUntilEnd => H1BodyToChunkedTransfer::new_read_until_end(
-                        ups_body_io,
-                        &mut self.icap_connection.writer,
-                        self.copy_config,
-                    ),
-                    HttpBodyType::ContentLength(len) => H1BodyToChunkedTransfer::new_fixed_length(
-                        ups_body_io,
-                        &mut self.icap_connection.writer,
-                        len,
-                        self.copy_config,
-                    ),
-                    HttpBodyType::Chunked => H1BodyToChunkedTransfer::new_chunked_after_preview(
-                        ups_body_io,
-                        &mut self.icap_connection.writer,
-                        left_chunk_size,
-                        self.http_body_line_max_size,
-                        self.copy_config,
-                    ),
-                };
+                state.mark_icap_phase(IcapTransactionPhase::WaitContinue);
+                let mut body_transfer = body_transfer.resume(&mut self.icap_connection.writer);
                 let bidirectional_transfer = BidirectionalRecvIcapResponse {
                     icap_client: &self.icap_client,
                     icap_reader: &mut self.icap_connection.reader,
                     idle_checker: &self.idle_checker,
                 };
                 let rsp = bidirectional_transfer
-                    .transfer_and_recv(&mut body_transfer)
+                    .transfer_and_recv(&mut body_transfer, &mut state.icap_timing)
                     .await?;
                 if body_transfer.finished() {
                     state.mark_ups_recv_all();
@@ -298,9 +193,11 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                             let icap_keepalive = rsp.keep_alive;
                             let mut bidirectional_transfer = BidirectionalRecvHttpResponse {
                                 http_body_line_max_size: self.http_body_line_max_size,
+                                http_body_strict_chunked: self.icap_client.config.strict_chunked,
                                 copy_config: self.copy_config,
                                 idle_checker: &self.idle_checker,
                                 http_header_size: header_size,
+                                http_header_policy: self.icap_client.config.http_header_policy,
                                 icap_read_finished: false,
                             };
                             let r = bidirectional_transfer
@@ -313,6 +210,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                                 )
                                 .await?;
                             if body_transfer.finished() {
+                                state.mark_icap_phase(IcapTransactionPhase::SendBody);
                                 state.mark_ups_recv_all();
                                 self.icap_connection.mark_writer_finished();
                                 if bidirectional_transfer.icap_read_finished {
@@ -328,6 +226,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                 }
             }
             204 => {
+                state.mark_icap_phase(IcapTransactionPhase::WaitResponse);
                 self.icap_connection.mark_writer_finished();
                 if rsp.payload == IcapRespmodResponsePayload::NoPayload {
                     self.icap_connection.mark_reader_finished();
@@ -340,26 +239,19 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                     .map_err(H1RespmodAdaptationError::HttpClientWriteFailed)?;
                 state.mark_clt_send_header();
 
-                match ups_body_type {
-                    HttpBodyType::ReadUntilEnd | HttpBodyType::ContentLength(_) => {
+                match body_transfer.into_plain_body() {
+                    Ok((preview_buf, ups_body_reader)) => {
                         self.send_original_plain_body_to_client(
                             rsp,
-                            ups_body_type,
-                            ups_body_io,
+                            ups_body_reader,
                             clt_writer,
                             preview_buf,
                         )
                         .await?;
                     }
-                    HttpBodyType::Chunked => {
-                        self.send_original_chunked_body_to_client(
-                            rsp,
-                            ups_body_io,
-                            clt_writer,
-                            preview_buf,
-                            left_chunk_size,
-                        )
-                        .await?;
+                    Err(body_transfer) => {
+                        self.send_original_chunked_body_to_client(rsp, *body_transfer, clt_writer)
+                            .await?;
                     }
                 }
 
@@ -370,6 +262,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
             }
             206 => Err(H1RespmodAdaptationError::NotImplemented("ICAP-REQMOD-206")),
             n if (200..300).contains(&n) => {
+                state.mark_icap_phase(IcapTransactionPhase::WaitResponse);
                 // FIXME we should stop send the pending HTTP body to ICAP server?
                 self.icap_connection.mark_writer_finished();
                 match rsp.payload {
@@ -416,120 +309,35 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         }
     }
 
-    async fn read_plain_preview_data<R>(
+    /// Return `false` if no preview data is received within `timeout`
+    async fn read_preview_data<UR>(
         &mut self,
-        reader: &mut R,
-        max_size: usize,
+        body_transfer: &mut PreviewableBodyTransfer<'_, UR, IcapClientWriter>,
         timeout: Duration,
-    ) -> Result<Option<Vec<u8>>, H1RespmodAdaptationError>
+    ) -> Result<bool, H1RespmodAdaptationError>
     where
-        R: AsyncRead + Unpin,
+        UR: AsyncBufRead + Unpin,
     {
-        let mut buf = vec![0u8; max_size];
-        let mut read_offset;
-        match tokio::time::timeout(timeout, reader.read(&mut buf)).await {
-            Ok(Ok(n)) => read_offset = n,
+        match tokio::time::timeout(timeout, body_transfer.read_preview()).await {
+            Ok(Ok(_)) => {}
             Ok(Err(e)) => return Err(H1RespmodAdaptationError::HttpUpstreamReadFailed(e)),
-            Err(_) => return Ok(None),
+            Err(_) => return Ok(false),
         }
 
-        let mut pin_reader = Pin::new(reader);
-        while read_offset < max_size {
-            let mut read_buf = ReadBuf::new(&mut buf[read_offset..]);
-            match poll_fn(
-                |cx| match pin_reader.as_mut().poll_read(cx, &mut read_buf) {
-                    Poll::Ready(Ok(_)) => Poll::Ready(Ok(Some(read_buf.filled().len()))),
-                    Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
-                    Poll::Pending => Poll::Ready(Ok(None)),
-                },
-            )
+        // only read the data that is already available
+        loop {
+            match poll_fn(|cx| match body_transfer.poll_read_preview(cx) {
+                Poll::Ready(r) => Poll::Ready(r.map(Some)),
+                Poll::Pending => Poll::Ready(Ok(None)),
+            })
             .await
             {
-                Ok(Some(0)) => break,
-                Ok(Some(n)) => read_offset += n,
-                Ok(None) => break,
+                Ok(Some(0)) | Ok(None) => break,
+                Ok(Some(_)) => {}
                 Err(e) => return Err(H1RespmodAdaptationError::HttpUpstreamReadFailed(e)),
             }
         }
-
-        buf.truncate(read_offset);
-        Ok(Some(buf))
-    }
-
-    async fn read_chunked_preview_data<R>(
-        &mut self,
-        reader: &mut ChunkedDataDecodeReader<'_, R>,
-        max_size: usize,
-        timeout: Duration,
-    ) -> Result<Option<Vec<u8>>, H1RespmodAdaptationError>
-    where
-        R: AsyncBufRead + Unpin,
-    {
-        let mut buf = vec![0u8; max_size];
-        let mut read_offset;
-        match tokio::time::timeout(timeout, reader.read(&mut buf)).await {
-            Ok(Ok(n)) => read_offset = n,
-            Ok(Err(e)) => return Err(H1RespmodAdaptationError::HttpUpstreamReadFailed(e)),
-            Err(_) => return Ok(None),
-        }
-
-        let mut pin_reader = Pin::new(reader);
-        let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
-        let mut is_active = false;
-
-        while read_offset < max_size {
-            let mut read_buf = ReadBuf::new(&mut buf[read_offset..]);
-
-            let pin_read = poll_fn(
-                |cx| match pin_reader.as_mut().poll_read(cx, &mut read_buf) {
-                    Poll::Ready(Ok(_)) => Poll::Ready(Ok(Some(read_buf.filled().len()))),
-                    Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
-                    Poll::Pending => {
-                        if pin_reader.pending_cancel_safe() {
-                            Poll::Ready(Ok(None))
-                        } else {
-                            Poll::Pending
-                        }
-                    }
-                },
-            );
-
-            tokio::select! {
-                biased;
-
-                r = pin_read => {
-                    match r {
-                        Ok(Some(0)) => break,
-                        Ok(Some(n)) => {
-                            is_active = true;
-                            read_offset += n;
-                        },
-                        Ok(None) => break,
-                        Err(e) => return Err(H1RespmodAdaptationError::HttpUpstreamReadFailed(e)),
-                    }
-                }
-                n = idle_interval.tick() => {
-                    if !is_active {
-                        idle_count += n;
-
-                        if self.idle_checker.check_quit(idle_count) {
-                            return Err(H1RespmodAdaptationError::HttpUpstreamReadIdle);
-                        }
-                    } else {
-                        idle_count = 0;
-                        is_active = false;
-                    }
-
-                    if let Some(reason) = self.idle_checker.check_force_quit() {
-                        return Err(H1RespmodAdaptationError::IdleForceQuit(reason));
-                    }
-                }
-            }
-        }
-
-        buf.truncate(read_offset);
-        Ok(Some(buf))
+        Ok(true)
     }
 
     async fn send_preview_data<R, H>(
@@ -539,7 +347,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         data: &[u8],
     ) -> Result<(), H1RespmodAdaptationError>
     where
-        R: HttpRequestForAdaptation,
+        R: HttpRequestForRespmod,
         H: HttpResponseForAdaptation,
     {
         let http_req_header = http_request.serialize_for_adapter();
@@ -571,8 +379,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
     async fn send_original_plain_body_to_client<CR, UW>(
         self,
         icap_rsp: RespmodResponse,
-        ups_body_type: HttpBodyType,
-        ups_body_io: &mut CR,
+        mut ups_body_reader: HttpBodyReader<'_, CR>,
         clt_writer: &mut UW,
         preview_buf: Vec<u8>,
     ) -> Result<(), H1RespmodAdaptationError>
@@ -589,9 +396,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
             .await
             .map_err(H1RespmodAdaptationError::HttpClientWriteFailed)?;
 
-        let mut clt_body_reader =
-            HttpBodyReader::new(ups_body_io, ups_body_type, self.http_body_line_max_size);
-        let mut body_copy = StreamCopy::new(&mut clt_body_reader, clt_writer, &self.copy_config);
+        let mut body_copy = StreamCopy::new(&mut ups_body_reader, clt_writer, &self.copy_config);
 
         let mut idle_interval = self.idle_checker.interval_timer();
         let mut idle_count = 0;
@@ -603,8 +408,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                 r = &mut body_copy => {
                     return match r {
                         Ok(_) => Ok(()),
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1RespmodAdaptationError::HttpUpstreamReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1RespmodAdaptationError::HttpClientWriteFailed(e)),
+                        Err(e) => Err(H1RespmodAdaptationError::from_ups_to_clt_copy(e)),
                     };
                 }
                 n = idle_interval.tick() => {
@@ -633,13 +437,11 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         }
     }
 
-    async fn send_original_chunked_body_to_client<CR, UW>(
+    async fn send_original_chunked_body_to_client<'a, CR, UW>(
         self,
         icap_rsp: RespmodResponse,
-        ups_body_io: &mut CR,
-        clt_writer: &mut UW,
-        preview_buf: Vec<u8>,
-        left_chunk_size: u64,
+        body_transfer: PreviewableBodyTransfer<'a, CR, IcapClientWriter>,
+        clt_writer: &'a mut UW,
     ) -> Result<(), H1RespmodAdaptationError>
     where
         CR: AsyncBufRead + Unpin,
@@ -649,23 +451,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
             self.icap_client.save_connection(self.icap_connection);
         }
 
-        let chunk_header = format!("{:x}\r\n", preview_buf.len());
-        clt_writer
-            .write_all_vectored([
-                IoSlice::new(chunk_header.as_bytes()),
-                IoSlice::new(&preview_buf),
-                IoSlice::new(b"\r\n"),
-            ])
-            .await
-            .map_err(H1RespmodAdaptationError::HttpClientWriteFailed)?;
-
-        let mut chunked_transfer = H1BodyToChunkedTransfer::new_chunked_after_preview(
-            ups_body_io,
-            clt_writer,
-            left_chunk_size,
-            self.http_body_line_max_size,
-            self.copy_config,
-        );
+        let mut chunked_transfer = body_transfer.resume_with_preview(clt_writer);
 
         let mut idle_interval = self.idle_checker.interval_timer();
         let mut idle_count = 0;
@@ -677,8 +463,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                 r = &mut chunked_transfer => {
                     return match r {
                         Ok(_) => Ok(()),
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1RespmodAdaptationError::HttpUpstreamReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1RespmodAdaptationError::HttpClientWriteFailed(e)),
+                        Err(e) => Err(H1RespmodAdaptationError::from_ups_to_clt_copy(e)),
                     };
                 }
                 n = idle_interval.tick() => {
@@ -7,12 +7,13 @@ use anyhow::anyhow;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 
 use g3_http::{HttpBodyDecodeReader, HttpBodyReader};
-use g3_io_ext::{IdleCheck, StreamCopy, StreamCopyError};
+use g3_io_ext::{IdleCheck, StreamCopy};
 
 use super::{
     H1RespmodAdaptationError, HttpAdaptedResponse, HttpResponseAdapter, HttpResponseClientWriter,
     HttpResponseForAdaptation, RespmodAdaptationEndState, RespmodAdaptationRunState,
 };
+use crate::IcapTransactionPhase;
 use crate::reason::IcapErrorReason;
 use crate::respmod::response::RespmodResponse;
 
@@ -77,8 +78,13 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         H: HttpResponseForAdaptation,
         CW: HttpResponseClientWriter<H> + Unpin,
     {
-        let http_rsp =
-            HttpAdaptedResponse::parse(&mut self.icap_connection.reader, http_header_size).await?;
+        let http_rsp = HttpAdaptedResponse::parse(
+            &mut self.icap_connection.reader,
+            http_header_size,
+            &self.icap_client.config.http_header_policy,
+        )
+        .await?;
+        state.mark_icap_phase(IcapTransactionPhase::ParseAdaptedHeader);
         self.icap_connection.mark_reader_finished();
         if icap_rsp.keep_alive {
             self.icap_client.save_connection(self.icap_connection);
@@ -112,8 +118,13 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
         H: HttpResponseForAdaptation,
         CW: HttpResponseClientWriter<H> + Unpin,
     {
-        let http_rsp =
-            HttpAdaptedResponse::parse(&mut self.icap_connection.reader, http_header_size).await?;
+        let http_rsp = HttpAdaptedResponse::parse(
+            &mut self.icap_connection.reader,
+            http_header_size,
+            &self.icap_client.config.http_header_policy,
+        )
+        .await?;
+        state.mark_icap_phase(IcapTransactionPhase::ParseAdaptedHeader);
         let body_content_length = http_rsp.content_length;
 
         let final_rsp = orig_http_response.adapt_with_body(http_rsp);
@@ -133,10 +144,12 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                     &mut self.icap_connection.reader,
                     self.http_body_line_max_size,
                 );
+                body_reader.set_strict_chunked(self.icap_client.config.strict_chunked);
                 let mut body_copy =
                     StreamCopy::new(&mut body_reader, clt_writer, &self.copy_config);
                 Self::send_response_body(&self.idle_checker, &mut body_copy).await?;
 
+                state.mark_icap_phase(IcapTransactionPhase::RelayAdaptedBody);
                 state.mark_clt_send_all();
                 let copied = body_copy.copied_size();
 
@@ -159,10 +172,12 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                     &mut self.icap_connection.reader,
                     self.http_body_line_max_size,
                 );
+                body_reader.set_strict_chunked(self.icap_client.config.strict_chunked);
                 let mut body_copy =
                     StreamCopy::new(&mut body_reader, clt_writer, &self.copy_config);
                 Self::send_response_body(&self.idle_checker, &mut body_copy).await?;
 
+                state.mark_icap_phase(IcapTransactionPhase::RelayAdaptedBody);
                 state.mark_clt_send_all();
                 self.icap_connection.mark_reader_finished();
                 if icap_rsp.keep_alive {
@@ -191,8 +206,7 @@ impl<I: IdleCheck> HttpResponseAdapter<I> {
                 r = &mut body_copy => {
                     return match r {
                         Ok(_) => Ok(()),
-                        Err(StreamCopyError::ReadFailed(e)) => Err(H1RespmodAdaptationError::IcapServerReadFailed(e)),
-                        Err(StreamCopyError::WriteFailed(e)) => Err(H1RespmodAdaptationError::HttpClientWriteFailed(e)),
+                        Err(e) => Err(H1RespmodAdaptationError::from_icap_to_clt_copy(e)),
                     };
                 }
                 n = idle_interval.tick() => {
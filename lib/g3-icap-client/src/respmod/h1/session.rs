@@ -0,0 +1,198 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::AsyncBufRead;
+use tokio::time::Instant;
+
+use g3_io_ext::{IdleCheck, IdleForceQuitReason, IdleInterval, IdleWheel, StreamCopyConfig};
+
+use super::{
+    H1RespmodAdaptationError, HttpRequestForRespmod, HttpResponseAdapter, HttpResponseClientWriter,
+    HttpResponseForAdaptation, RespmodAdaptationEndState, RespmodAdaptationRunState,
+};
+use crate::respmod::IcapRespmodClient;
+
+const DEFAULT_HTTP_BODY_LINE_MAX_SIZE: usize = 8192;
+const DEFAULT_IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_MAX_IDLE_COUNT: usize = 5;
+
+/// The parameters to create a [`IcapRespmodH1Session`]
+#[derive(Clone)]
+pub struct IcapRespmodH1Params {
+    copy_config: StreamCopyConfig,
+    http_body_line_max_size: usize,
+    idle_check_interval: Duration,
+    max_idle_count: usize,
+    client_addr: Option<SocketAddr>,
+    client_username: Option<Arc<str>>,
+}
+
+impl Default for IcapRespmodH1Params {
+    fn default() -> Self {
+        IcapRespmodH1Params {
+            copy_config: StreamCopyConfig::default(),
+            http_body_line_max_size: DEFAULT_HTTP_BODY_LINE_MAX_SIZE,
+            idle_check_interval: DEFAULT_IDLE_CHECK_INTERVAL,
+            max_idle_count: DEFAULT_MAX_IDLE_COUNT,
+            client_addr: None,
+            client_username: None,
+        }
+    }
+}
+
+impl IcapRespmodH1Params {
+    pub fn set_copy_config(&mut self, config: StreamCopyConfig) {
+        self.copy_config = config;
+    }
+
+    /// Set the max line size when parsing chunked HTTP body
+    pub fn set_http_body_line_max_size(&mut self, size: usize) {
+        self.http_body_line_max_size = size;
+    }
+
+    /// Set the idle check values for the default idle checker
+    ///
+    /// The session will fail if no data is transferred in `interval * max_count`.
+    pub fn set_idle_check(&mut self, interval: Duration, max_count: usize) {
+        self.idle_check_interval = interval;
+        self.max_idle_count = max_count;
+    }
+
+    /// Set the client address, which will be sent to the ICAP server in `X-Client-IP` header
+    pub fn set_client_addr(&mut self, addr: SocketAddr) {
+        self.client_addr = Some(addr);
+    }
+
+    /// Set the client username, which will be sent to the ICAP server in `X-Client-Username` header
+    pub fn set_client_username(&mut self, user: Arc<str>) {
+        self.client_username = Some(user);
+    }
+}
+
+/// The idle checker used by [`IcapRespmodH1Session::new`]
+pub struct RespmodIdleChecker {
+    idle_wheel: Arc<IdleWheel>,
+    max_idle_count: usize,
+}
+
+impl IdleCheck for RespmodIdleChecker {
+    fn interval_timer(&self) -> IdleInterval {
+        self.idle_wheel.register()
+    }
+
+    fn check_quit(&self, idle_count: usize) -> bool {
+        idle_count > self.max_idle_count
+    }
+
+    fn check_force_quit(&self) -> Option<IdleForceQuitReason> {
+        None
+    }
+}
+
+/// A single RESPMOD adaptation of an HTTP/1.x response
+///
+/// This is the supported entry to drive the h1 RESPMOD adapter with custom reader / writer types:
+///
+/// - the original request should implement [`HttpRequestForRespmod`], which is already done for
+///   `http::Request<()>`
+/// - the original response should implement [`HttpResponseForAdaptation`], which is already done
+///   for `http::Response<()>`
+/// - the client writer should implement [`HttpResponseClientWriter`], which is already done for
+///   all `AsyncWrite` types
+pub struct IcapRespmodH1Session<I: IdleCheck> {
+    adapter: Option<HttpResponseAdapter<I>>,
+    run_state: RespmodAdaptationRunState,
+}
+
+impl IcapRespmodH1Session<RespmodIdleChecker> {
+    /// Create a new session, with a ready connection to the ICAP server
+    ///
+    /// An error will be returned if no connection is available, the caller should check
+    /// [`IcapRespmodClient::bypass`] to decide whether to send the original response directly.
+    pub async fn new(
+        client: &IcapRespmodClient,
+        params: IcapRespmodH1Params,
+    ) -> anyhow::Result<Self> {
+        let idle_checker = RespmodIdleChecker {
+            idle_wheel: IdleWheel::spawn(params.idle_check_interval),
+            max_idle_count: params.max_idle_count,
+        };
+        Self::with_idle_checker(client, params, idle_checker).await
+    }
+}
+
+impl<I: IdleCheck> IcapRespmodH1Session<I> {
+    /// Create a new session with a custom idle checker
+    ///
+    /// The idle check values in `params` will be ignored.
+    pub async fn with_idle_checker(
+        client: &IcapRespmodClient,
+        params: IcapRespmodH1Params,
+        idle_checker: I,
+    ) -> anyhow::Result<Self> {
+        let mut adapter = client
+            .h1_adapter(
+                params.copy_config,
+                params.http_body_line_max_size,
+                idle_checker,
+            )
+            .await?;
+        if let Some(addr) = params.client_addr {
+            adapter.set_client_addr(addr);
+        }
+        if let Some(user) = params.client_username {
+            adapter.set_client_username(user);
+        }
+        Ok(IcapRespmodH1Session {
+            adapter: Some(adapter),
+            run_state: RespmodAdaptationRunState::new(Instant::now(), Duration::ZERO),
+        })
+    }
+
+    /// Get the run state, the durations in it are counted from the creation of the session
+    pub fn run_state(&self) -> &RespmodAdaptationRunState {
+        &self.run_state
+    }
+
+    /// Adapt the original response and send the final one to the client
+    ///
+    /// The header of `orig_response` should have been received, and the body will be read from
+    /// `ups_body_reader`. The session can only be used once.
+    ///
+    /// If an error is returned, check [`RespmodAdaptationRunState::clt_write_started`] to see
+    /// whether an error response can still be sent to the client.
+    pub async fn adapt<R, H, UR, CW>(
+        &mut self,
+        http_request: &R,
+        orig_response: &H,
+        ups_body_reader: &mut UR,
+        clt_writer: &mut CW,
+    ) -> Result<RespmodAdaptationEndState<H>, H1RespmodAdaptationError>
+    where
+        R: HttpRequestForRespmod,
+        H: HttpResponseForAdaptation,
+        UR: AsyncBufRead + Unpin,
+        CW: HttpResponseClientWriter<H> + Unpin,
+    {
+        let Some(adapter) = self.adapter.take() else {
+            return Err(H1RespmodAdaptationError::InternalServerError(
+                "the respmod session has already been used",
+            ));
+        };
+        adapter
+            .xfer(
+                &mut self.run_state,
+                http_request,
+                orig_response,
+                ups_body_reader,
+                clt_writer,
+            )
+            .await
+    }
+}
@@ -11,7 +11,8 @@ use g3_h2::{
     H2StreamFromChunkedTransfer, H2StreamFromChunkedTransferError, H2StreamToChunkedTransfer,
     H2StreamToChunkedTransferError, ResponseExt,
 };
-use g3_io_ext::{IdleCheck, LimitedBufReadExt, StreamCopyConfig};
+use g3_http::HttpHeaderParsePolicy;
+use g3_io_ext::{IdleCheck, IdleDirection, IdleTracker, LimitedBufReadExt, StreamCopyConfig};
 
 use super::{
     H2RespmodAdaptationError, H2SendResponseToClient, HttpAdaptedResponse,
@@ -33,7 +34,7 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
         mut body_transfer: &mut H2StreamToChunkedTransfer<'_, IcapClientWriter>,
     ) -> Result<RespmodResponse, H2RespmodAdaptationError> {
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -55,20 +56,23 @@ impl<I: IdleCheck> BidirectionalRecvIcapResponse<'_, I> {
                     };
                 }
                 n = idle_interval.tick() => {
-                    if body_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if body_transfer.no_cached_data() {
-                                Err(H2RespmodAdaptationError::HttpUpstreamReadIdle)
-                            } else {
+                    let body_idle = body_transfer.is_idle().then(|| {
+                        if body_transfer.no_cached_data() {
+                            IdleDirection::UpstreamRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[body_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return if direction == IdleDirection::WriteBlocked {
                                 Err(H2RespmodAdaptationError::IcapServerWriteIdle)
+                            } else {
+                                Err(H2RespmodAdaptationError::HttpUpstreamReadIdle)
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         body_transfer.reset_active();
                     }
 
@@ -112,6 +116,7 @@ pub(super) struct BidirectionalRecvHttpResponse<'a, I: IdleCheck> {
     pub(super) http_trailer_max_size: usize,
     pub(super) idle_checker: &'a I,
     pub(super) http_header_size: usize,
+    pub(super) http_header_policy: HttpHeaderParsePolicy,
     pub(super) icap_read_finished: bool,
 }
 
@@ -126,7 +131,12 @@ impl<I: IdleCheck> BidirectionalRecvHttpResponse<'_, I> {
     where
         CW: H2SendResponseToClient,
     {
-        let http_rsp = HttpAdaptedResponse::parse(self.icap_reader, self.http_header_size).await?;
+        let http_rsp = HttpAdaptedResponse::parse(
+            self.icap_reader,
+            self.http_header_size,
+            &self.http_header_policy,
+        )
+        .await?;
 
         let final_rsp = orig_http_response.adapt_to(&http_rsp);
         state.mark_clt_send_start();
@@ -144,7 +154,7 @@ impl<I: IdleCheck> BidirectionalRecvHttpResponse<'_, I> {
         );
 
         let mut idle_interval = self.idle_checker.interval_timer();
-        let mut idle_count = 0;
+        let mut idle_tracker = IdleTracker::default();
 
         loop {
             tokio::select! {
@@ -182,26 +192,40 @@ impl<I: IdleCheck> BidirectionalRecvHttpResponse<'_, I> {
                     };
                 }
                 n = idle_interval.tick() => {
-                    if ups_body_transfer.is_idle() && adp_body_transfer.is_idle() {
-                        idle_count += n;
-
-                        let quit = self.idle_checker.check_quit(idle_count);
-                        if quit {
-                            return if ups_body_transfer.is_idle() {
-                                if ups_body_transfer.no_cached_data() {
+                    let ups_body_idle = ups_body_transfer.is_idle().then(|| {
+                        if ups_body_transfer.no_cached_data() {
+                            IdleDirection::UpstreamRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    let adp_body_idle = adp_body_transfer.is_idle().then(|| {
+                        if adp_body_transfer.no_cached_data() {
+                            IdleDirection::IcapRead
+                        } else {
+                            IdleDirection::WriteBlocked
+                        }
+                    });
+                    if idle_tracker.record(n, &[ups_body_idle, adp_body_idle]) {
+                        let quit = self.idle_checker.check_quit_tracked(&idle_tracker);
+                        if let Some(direction) = quit {
+                            return match direction {
+                                IdleDirection::UpstreamRead => {
                                     Err(H2RespmodAdaptationError::HttpUpstreamReadIdle)
-                                } else {
-                                    Err(H2RespmodAdaptationError::IcapServerWriteIdle)
                                 }
-                            } else if adp_body_transfer.no_cached_data() {
-                                Err(H2RespmodAdaptationError::IcapServerReadIdle)
-                            } else {
-                                Err(H2RespmodAdaptationError::HttpClientWriteIdle)
+                                IdleDirection::IcapRead => {
+                                    Err(H2RespmodAdaptationError::IcapServerReadIdle)
+                                }
+                                _ => {
+                                    if ups_body_transfer.no_cached_data() {
+                                        Err(H2RespmodAdaptationError::HttpClientWriteIdle)
+                                    } else {
+                                        Err(H2RespmodAdaptationError::IcapServerWriteIdle)
+                                    }
+                                }
                             };
                         }
                     } else {
-                        idle_count = 0;
-
                         ups_body_transfer.reset_active();
                         adp_body_transfer.reset_active();
                     }
@@ -3,13 +3,14 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::fmt;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
 use bytes::{BufMut, Bytes};
 use h2::{RecvStream, SendStream};
-use http::{Request, Response};
+use http::{Request, Response, header};
 use tokio::time::Instant;
 
 use g3_http::client::HttpAdaptedResponse;
@@ -86,6 +87,8 @@ pub struct RespmodAdaptationRunState {
     pub dur_clt_send_header: Option<Duration>,
     pub dur_clt_send_all: Option<Duration>,
     pub clt_write_started: bool,
+    /// the preview size used for the ICAP request, `None` if no preview is sent
+    pub preview_size: Option<usize>,
 }
 
 impl RespmodAdaptationRunState {
@@ -97,6 +100,7 @@ impl RespmodAdaptationRunState {
             dur_clt_send_header: None,
             dur_clt_send_all: None,
             clt_write_started: false,
+            preview_size: None,
         }
     }
 
@@ -126,6 +130,12 @@ impl RespmodAdaptationRunState {
 }
 
 impl<I: IdleCheck> H2ResponseAdapter<I> {
+    /// Set the task id, which will be used in the name of the debug capture files
+    pub fn set_task_id<T: fmt::Display>(&mut self, id: T) {
+        self.icap_client
+            .start_debug_capture(&mut self.icap_connection, id);
+    }
+
     pub fn set_client_addr(&mut self, addr: SocketAddr) {
         self.client_addr = Some(addr);
     }
@@ -151,11 +161,21 @@ impl<I: IdleCheck> H2ResponseAdapter<I> {
         }
     }
 
-    fn preview_size(&self) -> Option<usize> {
-        if self.icap_client.config.disable_preview {
-            return None;
-        }
-        self.icap_options.preview_size
+    fn preview_size(
+        &self,
+        http_request: &Request<()>,
+        http_response: &Response<()>,
+    ) -> Option<usize> {
+        let content_type = http_response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok());
+        self.icap_client.config.preview_size(
+            self.icap_options.preview_size,
+            http_request.method(),
+            content_type,
+            http_request.uri().path(),
+        )
     }
 
     pub async fn xfer<CW>(
@@ -173,25 +193,28 @@ impl<I: IdleCheck> H2ResponseAdapter<I> {
             state.mark_ups_recv_no_body();
             self.xfer_without_body(state, http_request, http_response, clt_send_response)
                 .await
-        } else if let Some(preview_size) = self.preview_size() {
-            self.xfer_with_preview(
-                state,
-                http_request,
-                http_response,
-                ups_body,
-                clt_send_response,
-                preview_size,
-            )
-            .await
         } else {
-            self.xfer_without_preview(
-                state,
-                http_request,
-                http_response,
-                ups_body,
-                clt_send_response,
-            )
-            .await
+            state.preview_size = self.preview_size(http_request, &http_response);
+            if let Some(preview_size) = state.preview_size {
+                self.xfer_with_preview(
+                    state,
+                    http_request,
+                    http_response,
+                    ups_body,
+                    clt_send_response,
+                    preview_size,
+                )
+                .await
+            } else {
+                self.xfer_without_preview(
+                    state,
+                    http_request,
+                    http_response,
+                    ups_body,
+                    clt_send_response,
+                )
+                .await
+            }
         }
     }
 }
@@ -186,6 +186,7 @@ impl<I: IdleCheck> H2ResponseAdapter<I> {
                                 http_trailer_max_size: self.http_trailer_max_size,
                                 idle_checker: &self.idle_checker,
                                 http_header_size: header_size,
+                                http_header_policy: self.icap_client.config.http_header_policy,
                                 icap_read_finished: false,
                             };
                             let r = bidirectional_transfer
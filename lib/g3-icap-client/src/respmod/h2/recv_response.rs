@@ -162,8 +162,12 @@ impl<I: IdleCheck> H2ResponseAdapter<I> {
     where
         CW: H2SendResponseToClient,
     {
-        let http_rsp =
-            HttpAdaptedResponse::parse(&mut self.icap_connection.reader, http_header_size).await?;
+        let http_rsp = HttpAdaptedResponse::parse(
+            &mut self.icap_connection.reader,
+            http_header_size,
+            &self.icap_client.config.http_header_policy,
+        )
+        .await?;
         self.icap_connection.mark_reader_finished();
         if icap_rsp.keep_alive {
             self.icap_client.save_connection(self.icap_connection);
@@ -191,8 +195,12 @@ impl<I: IdleCheck> H2ResponseAdapter<I> {
     where
         CW: H2SendResponseToClient,
     {
-        let http_rsp =
-            HttpAdaptedResponse::parse(&mut self.icap_connection.reader, http_header_size).await?;
+        let http_rsp = HttpAdaptedResponse::parse(
+            &mut self.icap_connection.reader,
+            http_header_size,
+            &self.icap_client.config.http_header_policy,
+        )
+        .await?;
 
         let final_rsp = orig_http_response.adapt_to(&http_rsp);
         state.mark_clt_send_start();
@@ -29,6 +29,12 @@ impl IcapRespmodClient {
     }
 
     pub fn bypass(&self) -> bool {
-        self.inner.config.bypass
+        self.inner.bypass()
+    }
+
+    /// Check if the response should be relayed directly without adaptation,
+    /// as it's excluded by the Transfer-Ignore list or the local transfer policy
+    pub fn transfer_ignored(&self, path: &str, content_type: Option<&str>) -> bool {
+        self.inner.check_transfer_ignored(path, content_type)
     }
 }
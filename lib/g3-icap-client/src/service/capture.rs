@@ -0,0 +1,170 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::fmt;
+use std::io::IoSlice;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use rand::distr::{Bernoulli, Distribution};
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use super::{IcapMethod, IcapServiceState};
+
+const CAPTURE_QUEUE_SIZE: usize = 256;
+
+#[derive(Clone, Debug)]
+pub struct IcapDebugCaptureConfig {
+    pub(crate) dir: PathBuf,
+    pub(crate) sample_ratio: Bernoulli,
+    pub(crate) max_file_size: usize,
+}
+
+impl IcapDebugCaptureConfig {
+    pub fn new(dir: PathBuf) -> Self {
+        IcapDebugCaptureConfig {
+            dir,
+            sample_ratio: Bernoulli::new(1.0).unwrap(),
+            max_file_size: 16 * 1024 * 1024,
+        }
+    }
+
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn set_sample_ratio(&mut self, ratio: Bernoulli) {
+        self.sample_ratio = ratio;
+    }
+
+    /// Set the max size of each capture file, the data after that will be discarded
+    pub fn set_max_file_size(&mut self, size: usize) {
+        self.max_file_size = size;
+    }
+
+    pub(super) fn sample(&self) -> bool {
+        self.sample_ratio.sample(&mut rand::rng())
+    }
+
+    /// Start the capture of a sampled ICAP transaction.
+    ///
+    /// The files will be named as `<timestamp>-<task id>-<method>-{request,response}`.
+    pub(super) fn start<T: fmt::Display>(
+        &self,
+        method: IcapMethod,
+        task_id: T,
+        state: &Arc<IcapServiceState>,
+    ) -> (IcapCaptureSink, IcapCaptureSink) {
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let prefix = format!(
+            "{}.{:06}-{task_id}-{}",
+            time.as_secs(),
+            time.subsec_micros(),
+            method.as_str().to_lowercase()
+        );
+        let request = self.spawn_sink(format!("{prefix}-request"), state);
+        let response = self.spawn_sink(format!("{prefix}-response"), state);
+        (request, response)
+    }
+
+    fn spawn_sink(&self, file_name: String, state: &Arc<IcapServiceState>) -> IcapCaptureSink {
+        let (sender, receiver) = flume::bounded(CAPTURE_QUEUE_SIZE);
+        let dropped = Arc::new(AtomicBool::new(false));
+        let writer = IcapCaptureFileWriter {
+            path: self.dir.join(file_name),
+            receiver,
+            dropped: dropped.clone(),
+        };
+        tokio::spawn(writer.into_running());
+        IcapCaptureSink {
+            sender,
+            remaining: self.max_file_size,
+            dropped,
+            state: state.clone(),
+        }
+    }
+}
+
+/// The sending side of a capture file, which will never block the ICAP transaction
+pub(crate) struct IcapCaptureSink {
+    sender: flume::Sender<Bytes>,
+    remaining: usize,
+    dropped: Arc<AtomicBool>,
+    state: Arc<IcapServiceState>,
+}
+
+impl IcapCaptureSink {
+    /// Tee the data to the capture file, return false if the capture should be stopped
+    pub(crate) fn tee(&mut self, data: &[u8]) -> bool {
+        if self.remaining == 0 || data.is_empty() {
+            return true;
+        }
+        let len = data.len().min(self.remaining);
+        match self.sender.try_send(Bytes::copy_from_slice(&data[..len])) {
+            Ok(_) => {
+                self.remaining -= len;
+                true
+            }
+            Err(flume::TrySendError::Full(_)) => {
+                self.dropped.store(true, Ordering::Relaxed);
+                self.state.add_debug_capture_dropped();
+                false
+            }
+            Err(flume::TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    pub(crate) fn tee_vectored(&mut self, bufs: &[IoSlice<'_>], mut len: usize) -> bool {
+        for buf in bufs {
+            if len == 0 {
+                break;
+            }
+            let n = buf.len().min(len);
+            if !self.tee(&buf[..n]) {
+                return false;
+            }
+            len -= n;
+        }
+        true
+    }
+}
+
+struct IcapCaptureFileWriter {
+    path: PathBuf,
+    receiver: flume::Receiver<Bytes>,
+    dropped: Arc<AtomicBool>,
+}
+
+impl IcapCaptureFileWriter {
+    async fn into_running(self) {
+        let Ok(file) = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&self.path)
+            .await
+        else {
+            return;
+        };
+        let mut writer = BufWriter::new(file);
+        while let Ok(data) = self.receiver.recv_async().await {
+            if writer.write_all(&data).await.is_err() {
+                return;
+            }
+        }
+        let _ = writer.shutdown().await;
+        drop(writer);
+
+        if self.dropped.load(Ordering::Relaxed) {
+            // the capture is incomplete, so no need to keep it
+            let _ = tokio::fs::remove_file(&self.path).await;
+        }
+    }
+}
@@ -3,22 +3,27 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::fmt;
 use std::sync::Arc;
 
 use anyhow::anyhow;
 use tokio::sync::oneshot;
 
+use g3_histogram::BucketHistogramSnapshot;
+
 use super::{
-    IcapClientConnection, IcapConnector, IcapServiceClientCommand, IcapServiceConfig,
-    IcapServicePool,
+    IcapClientConnection, IcapConnector, IcapDebugCaptureConfig, IcapServiceClientCommand,
+    IcapServiceConfig, IcapServicePool, IcapServiceState,
 };
 use crate::options::{IcapOptionsRequest, IcapServiceOptions};
+use crate::timing::{IcapTransactionPhase, IcapTransactionTiming};
 
 pub struct IcapServiceClient {
     pub(crate) config: Arc<IcapServiceConfig>,
     pub(crate) partial_request_header: Vec<u8>,
     cmd_sender: flume::Sender<IcapServiceClientCommand>,
     conn_creator: Arc<IcapConnector>,
+    state: Arc<IcapServiceState>,
 }
 
 impl IcapServiceClient {
@@ -26,7 +31,16 @@ impl IcapServiceClient {
         let (cmd_sender, cmd_receiver) = flume::unbounded();
         let conn_creator = IcapConnector::new(config.clone())?;
         let conn_creator = Arc::new(conn_creator);
-        let pool = IcapServicePool::new(config.clone(), cmd_receiver, conn_creator.clone());
+        let state = Arc::new(IcapServiceState::new(
+            config.method,
+            config.debug_capture.clone(),
+        ));
+        let pool = IcapServicePool::new(
+            config.clone(),
+            state.clone(),
+            cmd_receiver,
+            conn_creator.clone(),
+        );
         tokio::spawn(pool.into_running());
         let partial_request_header = config.build_request_header();
         Ok(IcapServiceClient {
@@ -34,10 +48,94 @@ impl IcapServiceClient {
             partial_request_header,
             cmd_sender,
             conn_creator,
+            state,
         })
     }
 
-    async fn fetch_from_pool(&self) -> Option<(IcapClientConnection, Arc<IcapServiceOptions>)> {
+    /// Get the latest service options
+    pub fn options(&self) -> Arc<IcapServiceOptions> {
+        self.state.options()
+    }
+
+    /// Get the count of failed OPTIONS refresh
+    pub fn options_refresh_failures(&self) -> u64 {
+        self.state.options_refresh_failures()
+    }
+
+    /// Get the debug capture config that is currently in use
+    pub fn debug_capture(&self) -> Option<Arc<IcapDebugCaptureConfig>> {
+        self.state.debug_capture()
+    }
+
+    /// Start or stop the debug capture at runtime, which only applies to new transactions.
+    ///
+    /// The value in the service config will be used again if the client is recreated on reload.
+    pub fn set_debug_capture(&self, capture: Option<IcapDebugCaptureConfig>) {
+        self.state.set_debug_capture(capture);
+    }
+
+    /// Get the count of debug captures that are dropped because of backpressure
+    pub fn debug_capture_dropped(&self) -> u64 {
+        self.state.debug_capture_dropped()
+    }
+
+    /// Get the count of transactions that are sent again on a new connection,
+    /// as the old one is closed before any response bytes received
+    pub fn early_close_retries(&self) -> u64 {
+        self.state.early_close_retries()
+    }
+
+    pub(crate) fn add_early_close_retry(&self) {
+        self.state.add_early_close_retry();
+    }
+
+    /// Get the count of transactions that skipped the ICAP server by the transfer policy
+    pub fn transfer_ignored(&self) -> u64 {
+        self.state.transfer_ignored()
+    }
+
+    /// Get the duration distribution of the phase of all finished h1 transactions,
+    /// in microseconds
+    pub fn phase_duration_snapshot(&self, phase: IcapTransactionPhase) -> BucketHistogramSnapshot {
+        self.state.timing_stats().snapshot(phase)
+    }
+
+    pub(crate) fn record_transaction_timing(&self, timing: &IcapTransactionTiming) {
+        self.state.record_timing(timing);
+    }
+
+    /// Check if the transaction should skip the ICAP server by the transfer policy,
+    /// the latest options will be used, so the changes only apply to new transactions
+    pub(crate) fn check_transfer_ignored(&self, path: &str, content_type: Option<&str>) -> bool {
+        let options = self.state.options();
+        if self
+            .config
+            .transfer_policy
+            .transfer_ignored(&options, path, content_type)
+        {
+            self.state.add_transfer_ignored();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Check if the ICAP service is in the unavailable mode, which is set when
+    /// a 503 response is received for OPTIONS request
+    pub fn unavailable(&self) -> bool {
+        self.state.unavailable()
+    }
+
+    /// Check if we should skip the adaptation when failed to use the ICAP service
+    pub fn bypass(&self) -> bool {
+        if self.state.unavailable() {
+            self.config.unavailable_fail_open
+        } else {
+            self.config.bypass
+        }
+    }
+
+    async fn fetch_from_pool(&self) -> Option<IcapClientConnection> {
         let (rsp_sender, rsp_receiver) = oneshot::channel();
         let cmd = IcapServiceClientCommand::FetchConnection(rsp_sender);
         if self.cmd_sender.send_async(cmd).await.is_ok() {
@@ -50,8 +148,13 @@ impl IcapServiceClient {
     pub async fn fetch_connection(
         &self,
     ) -> anyhow::Result<(IcapClientConnection, Arc<IcapServiceOptions>)> {
+        if self.state.unavailable() {
+            return Err(anyhow!("ICAP service is temporarily unavailable"));
+        }
+
         if let Some(conn) = self.fetch_from_pool().await {
-            return Ok(conn);
+            // new transactions will always use the latest options
+            return Ok((conn, self.state.options()));
         }
 
         let mut conn = self
@@ -68,14 +171,352 @@ impl IcapServiceClient {
             .map_err(|e| anyhow!("failed to get icap service options: {e}"))?;
 
         conn.mark_io_inuse();
-        Ok((conn, Arc::new(options)))
+        let options = Arc::new(options);
+        self.state.update_options(options.clone());
+        Ok((conn, options))
+    }
+
+    /// Start the debug capture on the connection if enabled and the transaction is sampled
+    pub(crate) fn start_debug_capture<T: fmt::Display>(
+        &self,
+        conn: &mut IcapClientConnection,
+        task_id: T,
+    ) {
+        if let Some(capture) = self.state.debug_capture() {
+            if capture.sample() {
+                let (request, response) = capture.start(self.config.method, task_id, &self.state);
+                conn.set_capture(request, response);
+            }
+        }
     }
 
-    pub fn save_connection(&self, conn: IcapClientConnection) {
+    pub fn save_connection(&self, mut conn: IcapClientConnection) {
         if conn.reusable() {
+            conn.stop_capture();
             let _ = self
                 .cmd_sender
                 .try_send(IcapServiceClientCommand::SaveConnection(conn));
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicU16, AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use rand::distr::Bernoulli;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use url::Url;
+
+    use g3_http::H1BodyToChunkedTransfer;
+    use g3_io_ext::StreamCopyConfig;
+    use g3_types::net::ConnectionPoolConfig;
+
+    use crate::IcapMethod;
+
+    const MOCK_REQMOD_RESPONSE: &[u8] =
+        b"ICAP/1.0 204 No Content\r\nISTag: \"mock\"\r\nEncapsulated: null-body=0\r\n\r\n";
+
+    struct MockIcapServer {
+        status: AtomicU16,
+        preview_size: AtomicUsize,
+        reqmod_received: Mutex<Vec<u8>>,
+    }
+
+    impl MockIcapServer {
+        fn response(&self) -> String {
+            match self.status.load(Ordering::Relaxed) {
+                200 => format!(
+                    "ICAP/1.0 200 OK\r\nMethods: REQMOD\r\nISTag: \"mock\"\r\nAllow: 204\r\n\
+                     Preview: {}\r\nTransfer-Ignore: jpg, PNG\r\nEncapsulated: null-body=0\r\n\r\n",
+                    self.preview_size.load(Ordering::Relaxed)
+                ),
+                code => format!(
+                    "ICAP/1.0 {code} Service Unavailable\r\nISTag: \"mock\"\r\n\
+                     Encapsulated: null-body=0\r\n\r\n"
+                ),
+            }
+        }
+    }
+
+    async fn start_mock_server(status: u16, preview_size: usize) -> (Url, Arc<MockIcapServer>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mock = Arc::new(MockIcapServer {
+            status: AtomicU16::new(status),
+            preview_size: AtomicUsize::new(preview_size),
+            reqmod_received: Mutex::new(Vec::new()),
+        });
+
+        let server = mock.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let server = server.clone();
+                tokio::spawn(async move {
+                    let (r, mut w) = stream.into_split();
+                    let mut r = BufReader::new(r);
+                    let mut req = Vec::new();
+                    loop {
+                        req.clear();
+                        let mut header_end = false;
+                        loop {
+                            let offset = req.len();
+                            match r.read_until(b'\n', &mut req).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(_) => {}
+                            }
+                            if header_end {
+                                // the REQMOD request body should be ended with a zero chunk
+                                if req.ends_with(b"\r\n0\r\n\r\n") {
+                                    break;
+                                }
+                            } else if &req[offset..] == b"\r\n" {
+                                if !req.starts_with(b"REQMOD ") {
+                                    break;
+                                }
+                                header_end = true;
+                            }
+                        }
+                        let rsp = if req.starts_with(b"REQMOD ") {
+                            *server.reqmod_received.lock().unwrap() = req.clone();
+                            MOCK_REQMOD_RESPONSE.to_vec()
+                        } else {
+                            server.response().into_bytes()
+                        };
+                        if w.write_all(&rsp).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        let url = Url::from_str(&format!("icap://{addr}/reqmod")).unwrap();
+        (url, mock)
+    }
+
+    fn new_client(url: Url, f: impl FnOnce(&mut IcapServiceConfig)) -> IcapServiceClient {
+        let mut config = IcapServiceConfig::new(IcapMethod::Reqmod, url).unwrap();
+        config.connection_pool = ConnectionPoolConfig::new(4, 0);
+        config.set_options_refresh_interval(Duration::from_millis(100));
+        f(&mut config);
+        IcapServiceClient::new(Arc::new(config)).unwrap()
+    }
+
+    async fn wait_until(f: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !f() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn refresh_preview_size() {
+        let (url, mock) = start_mock_server(200, 1024).await;
+        let client = new_client(url, |_| {});
+
+        wait_until(|| client.options().preview_size == Some(1024)).await;
+        let (_conn, old_options) = client.fetch_connection().await.unwrap();
+        assert_eq!(old_options.preview_size, Some(1024));
+        assert!(old_options.support_204);
+        assert!(old_options.transfer_ignored("/a/b.png?v=1"));
+        assert!(!old_options.transfer_ignored("/a/b.html"));
+
+        mock.preview_size.store(2048, Ordering::Relaxed);
+        wait_until(|| client.options().preview_size == Some(2048)).await;
+        let (_conn, new_options) = client.fetch_connection().await.unwrap();
+        assert_eq!(new_options.preview_size, Some(2048));
+        // the options of in-flight transactions won't change
+        assert_eq!(old_options.preview_size, Some(1024));
+        assert_eq!(client.options_refresh_failures(), 0);
+    }
+
+    #[tokio::test]
+    async fn unavailable_bypass() {
+        let (url, mock) = start_mock_server(503, 1024).await;
+        let client = new_client(url, |config| {
+            config.set_unavailable_bypass_time(Duration::from_secs(60));
+        });
+
+        wait_until(|| client.unavailable()).await;
+        assert!(client.options_refresh_failures() > 0);
+        assert!(client.bypass());
+        assert!(client.fetch_connection().await.is_err());
+
+        mock.status.store(200, Ordering::Relaxed);
+        wait_until(|| !client.unavailable()).await;
+        assert!(!client.bypass());
+        assert_eq!(client.options().preview_size, Some(1024));
+    }
+
+    #[tokio::test]
+    async fn unavailable_fail_closed() {
+        let (url, _mock) = start_mock_server(503, 1024).await;
+        let client = new_client(url, |config| {
+            config.set_bypass(true);
+            config.set_unavailable_bypass_time(Duration::from_secs(60));
+            config.set_unavailable_fail_open(false);
+        });
+
+        wait_until(|| client.unavailable()).await;
+        assert!(!client.bypass());
+        assert!(client.fetch_connection().await.is_err());
+    }
+
+    fn capture_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("g3-icap-capture-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read_capture_file(dir: &Path, suffix: &str) -> Option<Vec<u8>> {
+        let entry = std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .find(|e| e.file_name().to_string_lossy().ends_with(suffix))?;
+        std::fs::read(entry.path()).ok()
+    }
+
+    async fn run_reqmod(client: &IcapServiceClient, task_id: &str) {
+        let (mut conn, _options) = client.fetch_connection().await.unwrap();
+        client.start_debug_capture(&mut conn, task_id);
+
+        let http_header =
+            b"POST /upload HTTP/1.1\r\nHost: example.net\r\nContent-Length: 11\r\n\r\n";
+        let mut header = client.partial_request_header.clone();
+        write!(
+            header,
+            "Encapsulated: req-hdr=0, req-body={}\r\n\r\n",
+            http_header.len()
+        )
+        .unwrap();
+        header.extend_from_slice(http_header);
+        conn.writer.write_all(&header).await.unwrap();
+
+        let mut body = BufReader::new(b"hello world".as_slice());
+        H1BodyToChunkedTransfer::new_fixed_length(
+            &mut body,
+            &mut conn.writer,
+            11,
+            StreamCopyConfig::default(),
+        )
+        .await
+        .unwrap();
+        conn.mark_writer_finished();
+
+        let mut rsp = vec![0u8; MOCK_REQMOD_RESPONSE.len()];
+        conn.reader.read_exact(&mut rsp).await.unwrap();
+        assert_eq!(rsp, MOCK_REQMOD_RESPONSE);
+        conn.mark_reader_finished();
+        client.save_connection(conn);
+    }
+
+    #[tokio::test]
+    async fn debug_capture() {
+        let dir = capture_dir("sampled");
+        let (url, mock) = start_mock_server(200, 1024).await;
+        let client = new_client(url, |config| {
+            config.set_debug_capture(IcapDebugCaptureConfig::new(dir.clone()));
+        });
+
+        run_reqmod(&client, "task-1").await;
+        let received = mock.reqmod_received.lock().unwrap().clone();
+        assert!(!received.is_empty());
+
+        wait_until(|| read_capture_file(&dir, "-task-1-reqmod-request") == Some(received.clone()))
+            .await;
+        wait_until(|| {
+            read_capture_file(&dir, "-task-1-reqmod-response")
+                == Some(MOCK_REQMOD_RESPONSE.to_vec())
+        })
+        .await;
+        assert_eq!(client.debug_capture_dropped(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn debug_capture_not_sampled() {
+        let dir = capture_dir("not-sampled");
+        let (url, _mock) = start_mock_server(200, 1024).await;
+        let client = new_client(url, |config| {
+            let mut capture = IcapDebugCaptureConfig::new(dir.clone());
+            capture.set_sample_ratio(Bernoulli::new(0.0).unwrap());
+            config.set_debug_capture(capture);
+        });
+
+        run_reqmod(&client, "task-2").await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn debug_capture_size_limit() {
+        let dir = capture_dir("size-limit");
+        let (url, _mock) = start_mock_server(200, 1024).await;
+        let client = new_client(url, |config| {
+            let mut capture = IcapDebugCaptureConfig::new(dir.clone());
+            capture.set_max_file_size(16);
+            config.set_debug_capture(capture);
+        });
+
+        run_reqmod(&client, "task-3").await;
+        wait_until(|| {
+            read_capture_file(&dir, "-task-3-reqmod-response")
+                == Some(MOCK_REQMOD_RESPONSE[..16].to_vec())
+        })
+        .await;
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn debug_capture_runtime_switch() {
+        let dir = capture_dir("runtime-switch");
+        let (url, mock) = start_mock_server(200, 1024).await;
+        let client = new_client(url, |_| {});
+        assert!(client.debug_capture().is_none());
+
+        run_reqmod(&client, "task-4").await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        // start on the running client, the pooled connection will be reused
+        client.set_debug_capture(Some(IcapDebugCaptureConfig::new(dir.clone())));
+        assert_eq!(client.debug_capture().unwrap().dir(), dir.as_path());
+        run_reqmod(&client, "task-5").await;
+        let received = mock.reqmod_received.lock().unwrap().clone();
+        assert!(!received.is_empty());
+        wait_until(|| read_capture_file(&dir, "-task-5-reqmod-request") == Some(received.clone()))
+            .await;
+        wait_until(|| {
+            read_capture_file(&dir, "-task-5-reqmod-response")
+                == Some(MOCK_REQMOD_RESPONSE.to_vec())
+        })
+        .await;
+
+        // stop it again
+        client.set_debug_capture(None);
+        assert!(client.debug_capture().is_none());
+        run_reqmod(&client, "task-6").await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        assert!(read_capture_file(&dir, "-task-6-reqmod-request").is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -5,13 +5,15 @@
 
 use std::collections::BTreeSet;
 use std::io::Write;
+use std::str::FromStr;
 use std::time::Duration;
 
 use anyhow::anyhow;
-use http::HeaderName;
+use http::{HeaderName, Method};
 use rustls_pki_types::ServerName;
 use url::Url;
 
+use g3_http::{HttpHeaderFoldPolicy, HttpHeaderParsePolicy};
 use g3_types::net::{
     ConnectionPoolConfig, HttpAuth, RustlsClientConfigBuilder, TcpKeepAliveConfig, UpstreamAddr,
 };
@@ -19,7 +21,45 @@ use g3_types::net::{
 #[cfg(feature = "yaml")]
 mod yaml;
 
-use super::IcapMethod;
+mod preview_policy;
+pub use preview_policy::{IcapPreviewPolicy, IcapPreviewRule, IcapPreviewSize};
+
+mod transfer_policy;
+pub use transfer_policy::{IcapTransferPolicy, IcapTransferPrecedence};
+
+use super::{IcapDebugCaptureConfig, IcapMethod, IcapReplayConfig};
+
+/// How to select the HTTP body data to be sent in the REQMOD preview
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum IcapPreviewMode {
+    /// Send the body bytes up to the preview size
+    #[default]
+    Bytes,
+    /// End the preview at the start of the first file part data for multipart/form-data bodies,
+    /// so all the field parts and the file part metadata will be sent
+    MultipartParts,
+}
+
+impl IcapPreviewMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IcapPreviewMode::Bytes => "bytes",
+            IcapPreviewMode::MultipartParts => "multipart_parts",
+        }
+    }
+}
+
+impl FromStr for IcapPreviewMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bytes" => Ok(IcapPreviewMode::Bytes),
+            "multipart_parts" | "multipart" => Ok(IcapPreviewMode::MultipartParts),
+            _ => Err(()),
+        }
+    }
+}
 
 pub struct IcapServiceConfig {
     pub(crate) method: IcapMethod,
@@ -33,10 +73,23 @@ pub struct IcapServiceConfig {
     pub(crate) tcp_keepalive: TcpKeepAliveConfig,
     pub(crate) icap_206_enable: bool,
     pub(crate) icap_max_header_size: usize,
+    pub(crate) http_max_request_header_size: usize,
+    pub(crate) http_header_policy: HttpHeaderParsePolicy,
+    pub(crate) strict_chunked: bool,
     pub(crate) disable_preview: bool,
+    pub(crate) preview_mode: IcapPreviewMode,
+    pub(crate) preview_policy: IcapPreviewPolicy,
     pub(crate) preview_data_read_timeout: Duration,
+    pub(crate) transfer_policy: IcapTransferPolicy,
+    pub(crate) replay_buffer_size: usize,
     pub(crate) respond_shared_names: BTreeSet<String>,
     pub(crate) bypass: bool,
+    pub(crate) options_refresh_interval: Option<Duration>,
+    pub(crate) options_refresh_jitter: Duration,
+    pub(crate) unavailable_bypass_time: Option<Duration>,
+    pub(crate) unavailable_fail_open: bool,
+    pub(crate) debug_capture: Option<IcapDebugCaptureConfig>,
+    pub(crate) replay: Option<IcapReplayConfig>,
 }
 
 impl IcapServiceConfig {
@@ -72,10 +125,23 @@ impl IcapServiceConfig {
             tcp_keepalive: TcpKeepAliveConfig::default_enabled(),
             icap_206_enable: false,
             icap_max_header_size: 8192,
+            http_max_request_header_size: 65536,
+            http_header_policy: HttpHeaderParsePolicy::default(),
+            strict_chunked: false,
             disable_preview: false,
+            preview_mode: IcapPreviewMode::default(),
+            preview_policy: IcapPreviewPolicy::default(),
             preview_data_read_timeout: Duration::from_secs(4),
+            transfer_policy: IcapTransferPolicy::default(),
+            replay_buffer_size: 65536,
             respond_shared_names: BTreeSet::new(),
             bypass: false,
+            options_refresh_interval: None,
+            options_refresh_jitter: Duration::ZERO,
+            unavailable_bypass_time: None,
+            unavailable_fail_open: true,
+            debug_capture: None,
+            replay: None,
         })
     }
 
@@ -87,6 +153,11 @@ impl IcapServiceConfig {
         self.tls_client = Some(config);
     }
 
+    #[inline]
+    pub fn tls_client(&self) -> Option<&RustlsClientConfigBuilder> {
+        self.tls_client.as_ref()
+    }
+
     pub fn set_tls_name(&mut self, name: ServerName<'static>) {
         self.tls_name = name;
     }
@@ -95,18 +166,112 @@ impl IcapServiceConfig {
         self.icap_max_header_size = max_size;
     }
 
+    /// Set the max size of the adapted HTTP request header, which will be sent to the upstream
+    pub fn set_http_max_request_header_size(&mut self, max_size: usize) {
+        self.http_max_request_header_size = max_size;
+    }
+
+    /// Set the max length of a single header line in the adapted HTTP request / response
+    pub fn set_http_max_header_line_length(&mut self, max_len: usize) {
+        self.http_header_policy.max_line_length = max_len;
+    }
+
+    /// Set the max number of headers in the adapted HTTP request / response
+    pub fn set_http_max_header_count(&mut self, max_count: usize) {
+        self.http_header_policy.max_count = max_count;
+    }
+
+    /// Set how to handle folded header lines in the adapted HTTP request / response
+    pub fn set_http_header_fold_policy(&mut self, policy: HttpHeaderFoldPolicy) {
+        self.http_header_policy.fold_policy = policy;
+    }
+
+    /// Set whether to validate the chunked framing of the adapted HTTP body strictly as defined in RFC 9112
+    pub fn set_strict_chunked(&mut self, strict: bool) {
+        self.strict_chunked = strict;
+    }
+
+    /// Set how to select the preview data for REQMOD requests
+    pub fn set_preview_mode(&mut self, mode: IcapPreviewMode) {
+        self.preview_mode = mode;
+    }
+
+    /// Set the rules to select the preview size for each transaction
+    pub fn set_preview_policy(&mut self, policy: IcapPreviewPolicy) {
+        self.preview_policy = policy;
+    }
+
     pub fn set_preview_data_read_timeout(&mut self, time: Duration) {
         self.preview_data_read_timeout = time;
     }
 
+    /// Set the rules to select the responses that should not be sent to the ICAP server
+    pub fn set_transfer_policy(&mut self, policy: IcapTransferPolicy) {
+        self.transfer_policy = policy;
+    }
+
+    /// Set the max size of the HTTP body that can be kept in memory and sent again,
+    /// if the ICAP connection is closed before any response bytes received
+    pub fn set_replay_buffer_size(&mut self, size: usize) {
+        self.replay_buffer_size = size;
+    }
+
     pub fn set_bypass(&mut self, bypass: bool) {
         self.bypass = bypass;
     }
 
+    /// Refresh the OPTIONS periodically, in addition to the refresh on Options-TTL expiration
+    pub fn set_options_refresh_interval(&mut self, interval: Duration) {
+        self.options_refresh_interval = Some(interval);
+    }
+
+    /// Set the max random delay that will be added to each periodic OPTIONS refresh
+    pub fn set_options_refresh_jitter(&mut self, jitter: Duration) {
+        self.options_refresh_jitter = jitter;
+    }
+
+    /// Enter the unavailable mode for `time` if a 503 response is received for OPTIONS
+    pub fn set_unavailable_bypass_time(&mut self, time: Duration) {
+        self.unavailable_bypass_time = Some(time);
+    }
+
+    /// Set whether we should bypass the adaptation or fail the request in unavailable mode
+    pub fn set_unavailable_fail_open(&mut self, fail_open: bool) {
+        self.unavailable_fail_open = fail_open;
+    }
+
+    /// Dump the ICAP request and response of sampled transactions to files for debugging
+    pub fn set_debug_capture(&mut self, config: IcapDebugCaptureConfig) {
+        self.debug_capture = Some(config);
+    }
+
+    /// Replay the recorded ICAP transactions instead of connecting to the ICAP server
+    pub fn set_replay(&mut self, config: IcapReplayConfig) {
+        self.replay = Some(config);
+    }
+
     pub fn add_respond_shared_name(&mut self, name: HeaderName) {
         self.respond_shared_names.insert(name.as_str().to_string());
     }
 
+    /// Get the preview size to use for the transaction, `None` means no preview.
+    ///
+    /// `options_max` is the preview size advertised by the ICAP server in the OPTIONS response.
+    pub(crate) fn preview_size(
+        &self,
+        options_max: Option<usize>,
+        method: &Method,
+        content_type: Option<&str>,
+        path: &str,
+    ) -> Option<usize> {
+        if self.disable_preview {
+            return None;
+        }
+        let max_size = options_max?;
+        self.preview_policy
+            .preview_size(max_size, method, content_type, path)
+    }
+
     pub(crate) fn build_request_header(&self) -> Vec<u8> {
         let mut header = Vec::with_capacity(1024);
         self.write_header(&mut header, self.method.as_str());
@@ -0,0 +1,265 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use http::Method;
+
+/// The preview size to use for the matched transactions
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IcapPreviewSize {
+    /// Preview at most this number of bytes, 0 means no preview
+    Bytes(usize),
+    /// Preview the entire body, up to the max size advertised by the ICAP server
+    Full,
+}
+
+impl FromStr for IcapPreviewSize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "full" | "all" => Ok(IcapPreviewSize::Full),
+            "none" | "disable" => Ok(IcapPreviewSize::Bytes(0)),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) enum ContentTypeMatch {
+    /// match the full mime type, like text/plain
+    Exact(String),
+    /// match the top level type, like video/*
+    TopLevel(String),
+    Any,
+}
+
+impl ContentTypeMatch {
+    pub(super) fn is_match(&self, mime: &str) -> bool {
+        match self {
+            ContentTypeMatch::Exact(s) => mime == s,
+            ContentTypeMatch::TopLevel(s) => {
+                mime.split_once('/').map(|(t, _)| t == s).unwrap_or(false)
+            }
+            ContentTypeMatch::Any => true,
+        }
+    }
+}
+
+impl FromStr for ContentTypeMatch {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let Some((t, sub)) = s.split_once('/') else {
+            return Err(anyhow!("no '/' found in content type {s}"));
+        };
+        if t.is_empty() || sub.is_empty() || sub.contains('/') {
+            return Err(anyhow!("invalid content type {s}"));
+        }
+        match (t, sub) {
+            ("*", "*") => Ok(ContentTypeMatch::Any),
+            ("*", _) => Err(anyhow!("wildcard top level type is only allowed in */*")),
+            (_, "*") => Ok(ContentTypeMatch::TopLevel(t.to_string())),
+            _ => {
+                if t.contains('*') || sub.contains('*') {
+                    Err(anyhow!("invalid wildcard in content type {s}"))
+                } else {
+                    Ok(ContentTypeMatch::Exact(s))
+                }
+            }
+        }
+    }
+}
+
+/// A preview policy rule, all the configured matchers should match to select the preview size
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IcapPreviewRule {
+    methods: Vec<Method>,
+    content_types: Vec<ContentTypeMatch>,
+    url_suffixes: Vec<String>,
+    size: IcapPreviewSize,
+}
+
+impl IcapPreviewRule {
+    pub fn new(size: IcapPreviewSize) -> Self {
+        IcapPreviewRule {
+            methods: Vec::new(),
+            content_types: Vec::new(),
+            url_suffixes: Vec::new(),
+            size,
+        }
+    }
+
+    pub fn add_method(&mut self, method: Method) {
+        self.methods.push(method);
+    }
+
+    /// Add a content type matcher, which should be a full mime type or in `type/*` form
+    pub fn add_content_type(&mut self, s: &str) -> anyhow::Result<()> {
+        let m = ContentTypeMatch::from_str(s)?;
+        self.content_types.push(m);
+        Ok(())
+    }
+
+    /// Add a suffix to match the path of the HTTP request URL, case-insensitive
+    pub fn add_url_suffix(&mut self, s: &str) -> anyhow::Result<()> {
+        if s.is_empty() {
+            return Err(anyhow!("empty url suffix"));
+        }
+        self.url_suffixes.push(s.to_lowercase());
+        Ok(())
+    }
+
+    fn is_match(&self, method: &Method, content_type: Option<&str>, path: &str) -> bool {
+        if !self.methods.is_empty() && !self.methods.contains(method) {
+            return false;
+        }
+        if !self.content_types.is_empty() {
+            let Some(content_type) = content_type else {
+                return false;
+            };
+            let mime = content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase();
+            if !self.content_types.iter().any(|m| m.is_match(&mime)) {
+                return false;
+            }
+        }
+        if !self.url_suffixes.is_empty() {
+            let path = path.to_lowercase();
+            if !self.url_suffixes.iter().any(|s| path.ends_with(s.as_str())) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// The ordered list of rules to select the preview size for each transaction
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IcapPreviewPolicy {
+    rules: Vec<IcapPreviewRule>,
+}
+
+impl IcapPreviewPolicy {
+    pub fn push_rule(&mut self, rule: IcapPreviewRule) {
+        self.rules.push(rule);
+    }
+
+    /// Get the preview size for the transaction, which will be clamped to the max preview size
+    /// advertised by the ICAP server in the OPTIONS response.
+    ///
+    /// The max preview size will be used if no rule matched. `None` means no preview.
+    pub(crate) fn preview_size(
+        &self,
+        max_size: usize,
+        method: &Method,
+        content_type: Option<&str>,
+        path: &str,
+    ) -> Option<usize> {
+        let Some(rule) = self
+            .rules
+            .iter()
+            .find(|r| r.is_match(method, content_type, path))
+        else {
+            return Some(max_size);
+        };
+        match rule.size {
+            IcapPreviewSize::Bytes(0) => None,
+            IcapPreviewSize::Bytes(n) => Some(n.min(max_size)),
+            IcapPreviewSize::Full => Some(max_size),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_policy() -> IcapPreviewPolicy {
+        let mut policy = IcapPreviewPolicy::default();
+
+        let mut rule = IcapPreviewRule::new(IcapPreviewSize::Bytes(0));
+        rule.add_content_type("application/json").unwrap();
+        policy.push_rule(rule);
+
+        let mut rule = IcapPreviewRule::new(IcapPreviewSize::Bytes(4096));
+        rule.add_content_type("video/*").unwrap();
+        rule.add_content_type("audio/*").unwrap();
+        policy.push_rule(rule);
+
+        let mut rule = IcapPreviewRule::new(IcapPreviewSize::Full);
+        rule.add_method(Method::POST);
+        rule.add_url_suffix(".php").unwrap();
+        policy.push_rule(rule);
+
+        policy
+    }
+
+    #[test]
+    fn content_type_no_preview() {
+        let policy = build_policy();
+        assert_eq!(
+            policy.preview_size(
+                1024,
+                &Method::POST,
+                Some("Application/JSON; charset=utf-8"),
+                "/api"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn unmatched_default() {
+        let policy = build_policy();
+        assert_eq!(
+            policy.preview_size(1024, &Method::GET, Some("text/html"), "/index.php"),
+            Some(1024)
+        );
+        assert_eq!(
+            policy.preview_size(1024, &Method::GET, None, "/"),
+            Some(1024)
+        );
+        assert_eq!(
+            IcapPreviewPolicy::default().preview_size(2048, &Method::GET, None, "/"),
+            Some(2048)
+        );
+    }
+
+    #[test]
+    fn clamp_to_options_max() {
+        let policy = build_policy();
+        assert_eq!(
+            policy.preview_size(1024, &Method::GET, Some("video/mp4"), "/a.mp4"),
+            Some(1024)
+        );
+        assert_eq!(
+            policy.preview_size(8192, &Method::GET, Some("audio/ogg"), "/a.ogg"),
+            Some(4096)
+        );
+        assert_eq!(
+            policy.preview_size(8192, &Method::POST, None, "/Upload.PHP"),
+            Some(8192)
+        );
+    }
+
+    #[test]
+    fn invalid_matcher() {
+        let mut rule = IcapPreviewRule::new(IcapPreviewSize::Full);
+        assert!(rule.add_content_type("video").is_err());
+        assert!(rule.add_content_type("*/mp4").is_err());
+        assert!(rule.add_content_type("vid*/mp4").is_err());
+        assert!(rule.add_content_type("video/").is_err());
+        assert!(rule.add_content_type("*/*").is_ok());
+        assert!(rule.add_url_suffix("").is_err());
+    }
+}
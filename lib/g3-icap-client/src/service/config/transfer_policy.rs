@@ -0,0 +1,249 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use super::preview_policy::ContentTypeMatch;
+use crate::IcapServiceOptions;
+
+/// Which match result to use if both the url extension and the content type matched
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum IcapTransferPrecedence {
+    #[default]
+    Extension,
+    ContentType,
+}
+
+impl FromStr for IcapTransferPrecedence {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "extension" | "ext" | "url_extension" => Ok(IcapTransferPrecedence::Extension),
+            "content_type" | "content-type" | "mime" => Ok(IcapTransferPrecedence::ContentType),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Select the responses that should be relayed without sending to the ICAP server.
+///
+/// The local lists take precedence over the Transfer-* lists in the OPTIONS response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IcapTransferPolicy {
+    use_server_lists: bool,
+    ignore_extensions: BTreeSet<String>,
+    send_extensions: BTreeSet<String>,
+    ignore_content_types: Vec<ContentTypeMatch>,
+    send_content_types: Vec<ContentTypeMatch>,
+    precedence: IcapTransferPrecedence,
+}
+
+impl Default for IcapTransferPolicy {
+    fn default() -> Self {
+        IcapTransferPolicy {
+            use_server_lists: true,
+            ignore_extensions: BTreeSet::new(),
+            send_extensions: BTreeSet::new(),
+            ignore_content_types: Vec::new(),
+            send_content_types: Vec::new(),
+            precedence: IcapTransferPrecedence::default(),
+        }
+    }
+}
+
+fn check_extension(ext: &str) -> anyhow::Result<String> {
+    let ext = ext.trim().trim_start_matches('.');
+    if ext.is_empty() {
+        return Err(anyhow!("empty file extension"));
+    }
+    Ok(ext.to_lowercase())
+}
+
+impl IcapTransferPolicy {
+    /// Set whether to use the Transfer-* lists in the OPTIONS response
+    pub fn set_use_server_lists(&mut self, enable: bool) {
+        self.use_server_lists = enable;
+    }
+
+    pub fn add_ignore_extension(&mut self, ext: &str) -> anyhow::Result<()> {
+        let ext = check_extension(ext)?;
+        self.ignore_extensions.insert(ext);
+        Ok(())
+    }
+
+    /// Add a file extension that should always be sent, even if ignored by the ICAP server
+    pub fn add_send_extension(&mut self, ext: &str) -> anyhow::Result<()> {
+        let ext = check_extension(ext)?;
+        self.send_extensions.insert(ext);
+        Ok(())
+    }
+
+    /// Add a content type matcher, which should be a full mime type or in `type/*` form
+    pub fn add_ignore_content_type(&mut self, s: &str) -> anyhow::Result<()> {
+        let m = ContentTypeMatch::from_str(s)?;
+        self.ignore_content_types.push(m);
+        Ok(())
+    }
+
+    /// Add a content type matcher for responses that should always be sent
+    pub fn add_send_content_type(&mut self, s: &str) -> anyhow::Result<()> {
+        let m = ContentTypeMatch::from_str(s)?;
+        self.send_content_types.push(m);
+        Ok(())
+    }
+
+    pub fn set_precedence(&mut self, precedence: IcapTransferPrecedence) {
+        self.precedence = precedence;
+    }
+
+    fn match_extension(&self, options: &IcapServiceOptions, path: &str) -> Option<bool> {
+        let ext = crate::options::path_extension(path)?.to_lowercase();
+        if self.send_extensions.contains(&ext) {
+            return Some(false);
+        }
+        if self.ignore_extensions.contains(&ext) {
+            return Some(true);
+        }
+        if self.use_server_lists && options.transfer_ignored_extension(&ext) {
+            return Some(true);
+        }
+        None
+    }
+
+    fn match_content_type(&self, content_type: Option<&str>) -> Option<bool> {
+        if self.send_content_types.is_empty() && self.ignore_content_types.is_empty() {
+            return None;
+        }
+        let mime = content_type?
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        if self.send_content_types.iter().any(|m| m.is_match(&mime)) {
+            return Some(false);
+        }
+        if self.ignore_content_types.iter().any(|m| m.is_match(&mime)) {
+            return Some(true);
+        }
+        None
+    }
+
+    /// Check if the transaction should skip the ICAP server, the options should be the
+    /// ones fetched at the start of the transaction
+    pub(crate) fn transfer_ignored(
+        &self,
+        options: &IcapServiceOptions,
+        path: &str,
+        content_type: Option<&str>,
+    ) -> bool {
+        let r = match self.precedence {
+            IcapTransferPrecedence::Extension => self
+                .match_extension(options, path)
+                .or_else(|| self.match_content_type(content_type)),
+            IcapTransferPrecedence::ContentType => self
+                .match_content_type(content_type)
+                .or_else(|| self.match_extension(options, path)),
+        };
+        r.unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IcapMethod;
+
+    async fn server_options(transfer_headers: &str) -> IcapServiceOptions {
+        let rsp = format!(
+            "ICAP/1.0 200 OK\r\nMethods: RESPMOD\r\nISTag: \"mock\"\r\n{transfer_headers}\
+             Encapsulated: null-body=0\r\n\r\n"
+        );
+        let mut reader = rsp.as_bytes();
+        IcapServiceOptions::parse(&mut reader, IcapMethod::Respmod, 4096)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn extension_match() {
+        let options = server_options("Transfer-Ignore: MP4, png\r\n").await;
+        let policy = IcapTransferPolicy::default();
+        assert!(policy.transfer_ignored(&options, "/a/b.mp4", None));
+        assert!(policy.transfer_ignored(&options, "/a/b.PNG?v=1", Some("text/html")));
+        assert!(!policy.transfer_ignored(&options, "/a/b.html", Some("video/mp4")));
+        assert!(!policy.transfer_ignored(&options, "/a/png", None));
+
+        let mut policy = IcapTransferPolicy::default();
+        policy.set_use_server_lists(false);
+        assert!(!policy.transfer_ignored(&options, "/a/b.mp4", None));
+        policy.add_ignore_extension(".MKV").unwrap();
+        assert!(policy.transfer_ignored(&options, "/a/b.mkv", None));
+        assert!(policy.add_ignore_extension(".").is_err());
+    }
+
+    #[tokio::test]
+    async fn wildcard_extension_match() {
+        let options = server_options(
+            "Transfer-Preview: html\r\nTransfer-Ignore: *\r\nTransfer-Complete: exe\r\n",
+        )
+        .await;
+        let policy = IcapTransferPolicy::default();
+        assert!(policy.transfer_ignored(&options, "/a/b.mp4", None));
+        assert!(!policy.transfer_ignored(&options, "/a/b.html", None));
+        assert!(!policy.transfer_ignored(&options, "/a/b.exe", None));
+        assert!(!policy.transfer_ignored(&options, "/a/b", None));
+    }
+
+    #[tokio::test]
+    async fn content_type_match() {
+        let options = server_options("").await;
+        let mut policy = IcapTransferPolicy::default();
+        policy.add_ignore_content_type("video/*").unwrap();
+        policy.add_ignore_content_type("image/png").unwrap();
+        policy.add_send_content_type("video/x-flv").unwrap();
+        assert!(policy.transfer_ignored(&options, "/a", Some("Video/MP4")));
+        assert!(policy.transfer_ignored(&options, "/a", Some("image/png; q=1")));
+        assert!(!policy.transfer_ignored(&options, "/a", Some("image/jpeg")));
+        assert!(!policy.transfer_ignored(&options, "/a", Some("video/x-flv")));
+        assert!(!policy.transfer_ignored(&options, "/a", None));
+    }
+
+    #[tokio::test]
+    async fn local_override() {
+        let options = server_options("Transfer-Ignore: jpg, exe\r\n").await;
+        let mut policy = IcapTransferPolicy::default();
+        policy.add_send_extension("exe").unwrap();
+        assert!(policy.transfer_ignored(&options, "/a.jpg", None));
+        assert!(!policy.transfer_ignored(&options, "/setup.EXE", None));
+    }
+
+    #[tokio::test]
+    async fn precedence() {
+        let options = server_options("Transfer-Ignore: bin\r\n").await;
+        let mut policy = IcapTransferPolicy::default();
+        policy
+            .add_send_content_type("application/octet-stream")
+            .unwrap();
+        policy.add_ignore_content_type("image/*").unwrap();
+        policy.add_send_extension("svg").unwrap();
+
+        let octet = Some("application/octet-stream");
+        assert!(policy.transfer_ignored(&options, "/a.bin", octet));
+        assert!(!policy.transfer_ignored(&options, "/a.svg", Some("image/svg+xml")));
+        // fallback to the content type if the extension is not matched
+        assert!(policy.transfer_ignored(&options, "/a.gif", Some("image/gif")));
+
+        policy.set_precedence(IcapTransferPrecedence::ContentType);
+        assert!(!policy.transfer_ignored(&options, "/a.bin", octet));
+        assert!(policy.transfer_ignored(&options, "/a.svg", Some("image/svg+xml")));
+        // fallback to the extension if the content type is not matched
+        assert!(policy.transfer_ignored(&options, "/a.bin", Some("text/plain")));
+    }
+}
@@ -3,14 +3,267 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use anyhow::{Context, anyhow};
 use url::Url;
 use yaml_rust::{Yaml, yaml};
 
-use super::{IcapMethod, IcapServiceConfig};
+use g3_http::HttpHeaderFoldPolicy;
+
+use super::{
+    IcapDebugCaptureConfig, IcapMethod, IcapPreviewMode, IcapPreviewPolicy, IcapPreviewRule,
+    IcapPreviewSize, IcapServiceConfig, IcapTransferPolicy, IcapTransferPrecedence,
+};
+use crate::{IcapReplayConfig, IcapReplayMatch, IcapReplayUnmatchedAction};
+
+impl IcapDebugCaptureConfig {
+    fn parse_dir(v: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<PathBuf> {
+        match lookup_dir {
+            Some(dir) => g3_yaml::value::as_dir_path(v, dir, true),
+            None => g3_yaml::value::as_absolute_path(v),
+        }
+    }
+
+    fn parse_yaml(value: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<Self> {
+        match value {
+            Yaml::Hash(map) => {
+                const KEY_DIR: &str = "dir";
+                let dir = g3_yaml::hash_get_required(map, KEY_DIR)?;
+                let dir = Self::parse_dir(dir, lookup_dir)
+                    .context(format!("invalid dir path value for key {KEY_DIR}"))?;
+                let mut config = IcapDebugCaptureConfig::new(dir);
+
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    KEY_DIR => Ok(()),
+                    "sample_ratio" | "ratio" => {
+                        let ratio = g3_yaml::value::as_random_ratio(v)
+                            .context(format!("invalid random ratio value for key {k}"))?;
+                        config.set_sample_ratio(ratio);
+                        Ok(())
+                    }
+                    "max_file_size" => {
+                        let size = g3_yaml::humanize::as_usize(v)
+                            .context(format!("invalid humanize usize value for key {k}"))?;
+                        config.set_max_file_size(size);
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+                Ok(config)
+            }
+            Yaml::String(_) => {
+                let dir = Self::parse_dir(value, lookup_dir)?;
+                Ok(IcapDebugCaptureConfig::new(dir))
+            }
+            _ => Err(anyhow!(
+                "yaml value type for 'icap debug capture config' should be 'map' or 'dir path str'"
+            )),
+        }
+    }
+}
+
+impl IcapReplayConfig {
+    fn parse_dir(v: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<PathBuf> {
+        match lookup_dir {
+            Some(dir) => g3_yaml::value::as_dir_path(v, dir, false),
+            None => g3_yaml::value::as_absolute_path(v),
+        }
+    }
+
+    fn parse_yaml(value: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<Self> {
+        match value {
+            Yaml::Hash(map) => {
+                const KEY_DIR: &str = "dir";
+                let dir = g3_yaml::hash_get_required(map, KEY_DIR)?;
+                let dir = Self::parse_dir(dir, lookup_dir)
+                    .context(format!("invalid dir path value for key {KEY_DIR}"))?;
+                let mut config = IcapReplayConfig::new(dir);
+
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    KEY_DIR => Ok(()),
+                    "match" | "match_by" => {
+                        let s = g3_yaml::value::as_string(v)?;
+                        let match_by = IcapReplayMatch::from_str(&s)
+                            .map_err(|_| anyhow!("invalid replay match value for key {k}"))?;
+                        config.set_match_by(match_by);
+                        Ok(())
+                    }
+                    "unmatched" | "unmatched_action" => {
+                        let s = g3_yaml::value::as_string(v)?;
+                        let action = IcapReplayUnmatchedAction::from_str(&s)
+                            .map_err(|_| anyhow!("invalid unmatched action value for key {k}"))?;
+                        config.set_unmatched_action(action);
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+                Ok(config)
+            }
+            Yaml::String(_) => {
+                let dir = Self::parse_dir(value, lookup_dir)?;
+                Ok(IcapReplayConfig::new(dir))
+            }
+            _ => Err(anyhow!(
+                "yaml value type for 'icap replay config' should be 'map' or 'dir path str'"
+            )),
+        }
+    }
+}
+
+impl IcapPreviewSize {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        if let Yaml::String(s) = v {
+            if let Ok(size) = IcapPreviewSize::from_str(s) {
+                return Ok(size);
+            }
+        }
+        let size = g3_yaml::humanize::as_usize(v)?;
+        Ok(IcapPreviewSize::Bytes(size))
+    }
+}
+
+impl IcapPreviewRule {
+    fn parse_yaml(value: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = value else {
+            return Err(anyhow!(
+                "yaml value type for 'icap preview rule' should be 'map'"
+            ));
+        };
+
+        const KEY_SIZE: &str = "preview_size";
+        let size = g3_yaml::hash_get_required(map, KEY_SIZE)?;
+        let size = IcapPreviewSize::parse_yaml(size)
+            .context(format!("invalid preview size value for key {KEY_SIZE}"))?;
+        let mut rule = IcapPreviewRule::new(size);
+
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            KEY_SIZE => Ok(()),
+            "method" | "methods" => {
+                let add_method = |rule: &mut IcapPreviewRule, v: &Yaml| -> anyhow::Result<()> {
+                    let s = g3_yaml::value::as_string(v)?;
+                    let method = http::Method::from_str(&s.to_uppercase())
+                        .map_err(|e| anyhow!("invalid http method {s}: {e}"))?;
+                    rule.add_method(method);
+                    Ok(())
+                };
+                if let Yaml::Array(seq) = v {
+                    for (i, v) in seq.iter().enumerate() {
+                        add_method(&mut rule, v)
+                            .context(format!("invalid http method value for key {k}#{i}"))?;
+                    }
+                } else {
+                    add_method(&mut rule, v)
+                        .context(format!("invalid http method value for key {k}"))?;
+                }
+                Ok(())
+            }
+            "content_type" | "content_types" => {
+                if let Yaml::Array(seq) = v {
+                    for (i, v) in seq.iter().enumerate() {
+                        let s = g3_yaml::value::as_string(v)?;
+                        rule.add_content_type(&s)
+                            .context(format!("invalid content type value for key {k}#{i}"))?;
+                    }
+                } else {
+                    let s = g3_yaml::value::as_string(v)?;
+                    rule.add_content_type(&s)
+                        .context(format!("invalid content type value for key {k}"))?;
+                }
+                Ok(())
+            }
+            "url_suffix" | "url_suffixes" => {
+                if let Yaml::Array(seq) = v {
+                    for (i, v) in seq.iter().enumerate() {
+                        let s = g3_yaml::value::as_string(v)?;
+                        rule.add_url_suffix(&s)
+                            .context(format!("invalid url suffix value for key {k}#{i}"))?;
+                    }
+                } else {
+                    let s = g3_yaml::value::as_string(v)?;
+                    rule.add_url_suffix(&s)
+                        .context(format!("invalid url suffix value for key {k}"))?;
+                }
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        Ok(rule)
+    }
+}
+
+impl IcapPreviewPolicy {
+    fn parse_yaml(value: &Yaml) -> anyhow::Result<Self> {
+        let mut policy = IcapPreviewPolicy::default();
+        if let Yaml::Array(seq) = value {
+            for (i, v) in seq.iter().enumerate() {
+                let rule = IcapPreviewRule::parse_yaml(v)
+                    .context(format!("invalid icap preview rule value for #{i}"))?;
+                policy.push_rule(rule);
+            }
+        } else {
+            let rule = IcapPreviewRule::parse_yaml(value)?;
+            policy.push_rule(rule);
+        }
+        Ok(policy)
+    }
+}
+
+impl IcapTransferPolicy {
+    fn parse_yaml(value: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = value else {
+            return Err(anyhow!(
+                "yaml value type for 'icap transfer policy' should be 'map'"
+            ));
+        };
+
+        fn add_all<F>(k: &str, v: &Yaml, mut add: F) -> anyhow::Result<()>
+        where
+            F: FnMut(&str) -> anyhow::Result<()>,
+        {
+            if let Yaml::Array(seq) = v {
+                for (i, v) in seq.iter().enumerate() {
+                    let s = g3_yaml::value::as_string(v)?;
+                    add(&s).context(format!("invalid value for key {k}#{i}"))?;
+                }
+            } else {
+                let s = g3_yaml::value::as_string(v)?;
+                add(&s).context(format!("invalid value for key {k}"))?;
+            }
+            Ok(())
+        }
+
+        let mut policy = IcapTransferPolicy::default();
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "use_server_lists" | "use_options" => {
+                let enable = g3_yaml::value::as_bool(v)?;
+                policy.set_use_server_lists(enable);
+                Ok(())
+            }
+            "ignore_extension" | "ignore_extensions" => {
+                add_all(k, v, |s| policy.add_ignore_extension(s))
+            }
+            "send_extension" | "send_extensions" => add_all(k, v, |s| policy.add_send_extension(s)),
+            "ignore_content_type" | "ignore_content_types" => {
+                add_all(k, v, |s| policy.add_ignore_content_type(s))
+            }
+            "send_content_type" | "send_content_types" => {
+                add_all(k, v, |s| policy.add_send_content_type(s))
+            }
+            "precedence" => {
+                let s = g3_yaml::value::as_string(v)?;
+                let precedence = IcapTransferPrecedence::from_str(&s)
+                    .map_err(|_| anyhow!("invalid transfer precedence value for key {k}"))?;
+                policy.set_precedence(precedence);
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+        Ok(policy)
+    }
+}
 
 impl IcapServiceConfig {
     fn parse_yaml(
@@ -57,16 +310,81 @@ impl IcapServiceConfig {
                 config.set_icap_max_header_size(size);
                 Ok(())
             }
+            "max_request_header_size" => {
+                let size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                if size == 0 {
+                    return Err(anyhow!("zero value is not allowed for key {k}"));
+                }
+                config.set_http_max_request_header_size(size);
+                Ok(())
+            }
+            "max_header_line_length" => {
+                let len = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                if len == 0 {
+                    return Err(anyhow!("zero value is not allowed for key {k}"));
+                }
+                config.set_http_max_header_line_length(len);
+                Ok(())
+            }
+            "max_header_count" => {
+                let count = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                if count == 0 {
+                    return Err(anyhow!("zero value is not allowed for key {k}"));
+                }
+                config.set_http_max_header_count(count);
+                Ok(())
+            }
+            "fold_policy" => {
+                let s = g3_yaml::value::as_string(v)?;
+                let policy = HttpHeaderFoldPolicy::from_str(&s)
+                    .map_err(|_| anyhow!("invalid header fold policy value for key {k}"))?;
+                config.set_http_header_fold_policy(policy);
+                Ok(())
+            }
+            "strict_chunked" => {
+                let strict = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                config.set_strict_chunked(strict);
+                Ok(())
+            }
             "disable_preview" | "no_preview" => {
                 config.disable_preview = g3_yaml::value::as_bool(v)?;
                 Ok(())
             }
+            "preview_mode" => {
+                let s = g3_yaml::value::as_string(v)?;
+                let mode = IcapPreviewMode::from_str(&s)
+                    .map_err(|_| anyhow!("invalid preview mode value for key {k}"))?;
+                config.set_preview_mode(mode);
+                Ok(())
+            }
+            "preview_policy" => {
+                let policy = IcapPreviewPolicy::parse_yaml(v)
+                    .context(format!("invalid icap preview policy value for key {k}"))?;
+                config.set_preview_policy(policy);
+                Ok(())
+            }
+            "transfer_policy" => {
+                let policy = IcapTransferPolicy::parse_yaml(v)
+                    .context(format!("invalid icap transfer policy value for key {k}"))?;
+                config.set_transfer_policy(policy);
+                Ok(())
+            }
             "preview_data_read_timeout" => {
                 let time = g3_yaml::humanize::as_duration(v)
                     .context(format!("invalid humanize duration value for key {k}"))?;
                 config.set_preview_data_read_timeout(time);
                 Ok(())
             }
+            "replay_buffer_size" => {
+                let size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                config.set_replay_buffer_size(size);
+                Ok(())
+            }
             "respond_shared_names" => {
                 if let Yaml::Array(seq) = v {
                     for (i, v) in seq.iter().enumerate() {
@@ -86,6 +404,45 @@ impl IcapServiceConfig {
                 config.set_bypass(bypass);
                 Ok(())
             }
+            "options_refresh_interval" => {
+                let interval = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                if interval.is_zero() {
+                    return Err(anyhow!("zero value is not allowed for key {k}"));
+                }
+                config.set_options_refresh_interval(interval);
+                Ok(())
+            }
+            "options_refresh_jitter" => {
+                let jitter = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                config.set_options_refresh_jitter(jitter);
+                Ok(())
+            }
+            "unavailable_bypass_time" | "service_unavailable_bypass_time" => {
+                let time = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                config.set_unavailable_bypass_time(time);
+                Ok(())
+            }
+            "unavailable_fail_open" | "service_unavailable_fail_open" => {
+                let fail_open = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                config.set_unavailable_fail_open(fail_open);
+                Ok(())
+            }
+            "debug_capture" => {
+                let capture = IcapDebugCaptureConfig::parse_yaml(v, lookup_dir)
+                    .context(format!("invalid debug capture config value for key {k}"))?;
+                config.set_debug_capture(capture);
+                Ok(())
+            }
+            "replay" => {
+                let replay = IcapReplayConfig::parse_yaml(v, lookup_dir)
+                    .context(format!("invalid replay config value for key {k}"))?;
+                config.set_replay(replay);
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         })?;
 
@@ -3,13 +3,15 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::io;
+use std::io::{self, IoSlice};
 use std::net::SocketAddr;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{self, Poll, ready};
 use std::time::Duration;
 
 use anyhow::Context;
-use tokio::io::BufReader;
+use tokio::io::{AsyncRead, AsyncWrite, BufReader, ReadBuf};
 use tokio::net::TcpStream;
 use tokio::sync::oneshot;
 use tokio_rustls::TlsConnector;
@@ -18,11 +20,170 @@ use g3_io_ext::rustls::{MaybeTlsStreamReadHalf, MaybeTlsStreamWriteHalf};
 use g3_io_ext::{AsyncStream, LimitedBufReadExt};
 use g3_types::net::{Host, RustlsClientConfig};
 
-use super::IcapServiceConfig;
-use crate::IcapServiceOptions;
+use super::{
+    IcapCaptureSink, IcapReplayReadHalf, IcapReplayStore, IcapReplayWriteHalf, IcapServiceConfig,
+};
 
-pub type IcapClientWriter = MaybeTlsStreamWriteHalf<TcpStream>;
-pub type IcapClientReader = BufReader<MaybeTlsStreamReadHalf<TcpStream>>;
+enum IcapTransportWriteHalf {
+    Tcp(MaybeTlsStreamWriteHalf<TcpStream>),
+    Replay(IcapReplayWriteHalf),
+}
+
+impl AsyncWrite for IcapTransportWriteHalf {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            IcapTransportWriteHalf::Tcp(w) => Pin::new(w).poll_write(cx, buf),
+            IcapTransportWriteHalf::Replay(w) => Pin::new(w).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            IcapTransportWriteHalf::Tcp(w) => Pin::new(w).poll_flush(cx),
+            IcapTransportWriteHalf::Replay(w) => Pin::new(w).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            IcapTransportWriteHalf::Tcp(w) => Pin::new(w).poll_shutdown(cx),
+            IcapTransportWriteHalf::Replay(w) => Pin::new(w).poll_shutdown(cx),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            IcapTransportWriteHalf::Tcp(w) => Pin::new(w).poll_write_vectored(cx, bufs),
+            IcapTransportWriteHalf::Replay(w) => Pin::new(w).poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            IcapTransportWriteHalf::Tcp(w) => w.is_write_vectored(),
+            IcapTransportWriteHalf::Replay(w) => w.is_write_vectored(),
+        }
+    }
+}
+
+enum IcapTransportReadHalf {
+    Tcp(MaybeTlsStreamReadHalf<TcpStream>),
+    Replay(IcapReplayReadHalf),
+}
+
+impl AsyncRead for IcapTransportReadHalf {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            IcapTransportReadHalf::Tcp(r) => Pin::new(r).poll_read(cx, buf),
+            IcapTransportReadHalf::Replay(r) => Pin::new(r).poll_read(cx, buf),
+        }
+    }
+}
+
+pub struct IcapClientWriter {
+    inner: IcapTransportWriteHalf,
+    capture: Option<IcapCaptureSink>,
+}
+
+impl IcapClientWriter {
+    fn new(inner: IcapTransportWriteHalf) -> Self {
+        IcapClientWriter {
+            inner,
+            capture: None,
+        }
+    }
+}
+
+impl AsyncWrite for IcapClientWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let r = ready!(Pin::new(&mut self.inner).poll_write(cx, buf));
+        if let Ok(n) = &r {
+            if let Some(capture) = &mut self.capture {
+                if !capture.tee(&buf[..*n]) {
+                    self.capture = None;
+                }
+            }
+        }
+        Poll::Ready(r)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let r = ready!(Pin::new(&mut self.inner).poll_write_vectored(cx, bufs));
+        if let Ok(n) = &r {
+            if let Some(capture) = &mut self.capture {
+                if !capture.tee_vectored(bufs, *n) {
+                    self.capture = None;
+                }
+            }
+        }
+        Poll::Ready(r)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+pub struct IcapClientReadHalf {
+    inner: IcapTransportReadHalf,
+    capture: Option<IcapCaptureSink>,
+}
+
+impl IcapClientReadHalf {
+    fn new(inner: IcapTransportReadHalf) -> Self {
+        IcapClientReadHalf {
+            inner,
+            capture: None,
+        }
+    }
+}
+
+impl AsyncRead for IcapClientReadHalf {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let offset = buf.filled().len();
+        ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+        if let Some(capture) = &mut self.capture {
+            if !capture.tee(&buf.filled()[offset..]) {
+                self.capture = None;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+pub type IcapClientReader = BufReader<IcapClientReadHalf>;
 
 pub struct IcapClientConnection {
     pub reader: IcapClientReader,
@@ -33,10 +194,10 @@ pub struct IcapClientConnection {
 }
 
 impl IcapClientConnection {
-    fn new(reader: IcapClientReader, writer: IcapClientWriter) -> Self {
+    fn new(reader: IcapTransportReadHalf, writer: IcapTransportWriteHalf) -> Self {
         IcapClientConnection {
-            reader,
-            writer,
+            reader: BufReader::new(IcapClientReadHalf::new(reader)),
+            writer: IcapClientWriter::new(writer),
             reader_clean: true,
             writer_clean: true,
             reused_connection: false,
@@ -63,11 +224,22 @@ impl IcapClientConnection {
     pub(super) fn reusable(&self) -> bool {
         self.reader_clean && self.writer_clean
     }
+
+    pub(super) fn set_capture(&mut self, request: IcapCaptureSink, response: IcapCaptureSink) {
+        self.writer.capture = Some(request);
+        self.reader.get_mut().capture = Some(response);
+    }
+
+    pub(super) fn stop_capture(&mut self) {
+        self.writer.capture = None;
+        self.reader.get_mut().capture = None;
+    }
 }
 
 pub(super) struct IcapConnector {
     config: Arc<IcapServiceConfig>,
     tls_client: Option<RustlsClientConfig>,
+    replay: Option<Arc<IcapReplayStore>>,
 }
 
 impl IcapConnector {
@@ -81,7 +253,19 @@ impl IcapConnector {
             }
             None => None,
         };
-        Ok(IcapConnector { config, tls_client })
+        let replay = match &config.replay {
+            Some(replay) => {
+                let store = IcapReplayStore::load(replay, config.method)
+                    .context("failed to load ICAP replay records")?;
+                Some(Arc::new(store))
+            }
+            None => None,
+        };
+        Ok(IcapConnector {
+            config,
+            tls_client,
+            replay,
+        })
     }
 
     async fn select_peer_addr(&self) -> io::Result<SocketAddr> {
@@ -98,6 +282,14 @@ impl IcapConnector {
     }
 
     pub(super) async fn create(&self) -> io::Result<IcapClientConnection> {
+        if let Some(store) = &self.replay {
+            let (r, w) = store.new_connection();
+            return Ok(IcapClientConnection::new(
+                IcapTransportReadHalf::Replay(r),
+                IcapTransportWriteHalf::Replay(w),
+            ));
+        }
+
         let peer = self.select_peer_addr().await?;
         let socket = g3_socket::tcp::new_socket_to(
             peer.ip(),
@@ -119,8 +311,8 @@ impl IcapConnector {
                 Ok(Ok(tls_stream)) => {
                     let (r, w) = tls_stream.into_split();
                     Ok(IcapClientConnection::new(
-                        BufReader::new(MaybeTlsStreamReadHalf::Tls(r)),
-                        MaybeTlsStreamWriteHalf::Tls(w),
+                        IcapTransportReadHalf::Tcp(MaybeTlsStreamReadHalf::Tls(r)),
+                        IcapTransportWriteHalf::Tcp(MaybeTlsStreamWriteHalf::Tls(w)),
                     ))
                 }
                 Ok(Err(e)) => Err(e),
@@ -132,27 +324,20 @@ impl IcapConnector {
         } else {
             let (r, w) = stream.into_split();
             Ok(IcapClientConnection::new(
-                BufReader::new(MaybeTlsStreamReadHalf::Plain(r)),
-                MaybeTlsStreamWriteHalf::Plain(w),
+                IcapTransportReadHalf::Tcp(MaybeTlsStreamReadHalf::Plain(r)),
+                IcapTransportWriteHalf::Tcp(MaybeTlsStreamWriteHalf::Plain(w)),
             ))
         }
     }
 }
 
 pub(super) struct IcapConnectionPollRequest {
-    client_sender: oneshot::Sender<(IcapClientConnection, Arc<IcapServiceOptions>)>,
-    options: Arc<IcapServiceOptions>,
+    client_sender: oneshot::Sender<IcapClientConnection>,
 }
 
 impl IcapConnectionPollRequest {
-    pub(super) fn new(
-        client_sender: oneshot::Sender<(IcapClientConnection, Arc<IcapServiceOptions>)>,
-        options: Arc<IcapServiceOptions>,
-    ) -> Self {
-        IcapConnectionPollRequest {
-            client_sender,
-            options,
-        }
+    pub(super) fn new(client_sender: oneshot::Sender<IcapClientConnection>) -> Self {
+        IcapConnectionPollRequest { client_sender }
     }
 }
 
@@ -184,12 +369,8 @@ impl IcapConnectionEofPoller {
             _ = idle_sleep => {}
             r = self.req_receiver.recv_async() => {
                 if let Ok(req) = r {
-                    let IcapConnectionPollRequest {
-                        client_sender,
-                        options,
-                    } = req;
                     self.conn.reused_connection = true;
-                    let _ = client_sender.send((self.conn, options));
+                    let _ = req.client_sender.send(self.conn);
                 }
             }
         }
@@ -4,12 +4,26 @@
  */
 
 mod config;
-pub use config::IcapServiceConfig;
+pub use config::{
+    IcapPreviewMode, IcapPreviewPolicy, IcapPreviewRule, IcapPreviewSize, IcapServiceConfig,
+    IcapTransferPolicy, IcapTransferPrecedence,
+};
+
+mod capture;
+use capture::IcapCaptureSink;
+pub use capture::IcapDebugCaptureConfig;
+
+mod replay;
+pub use replay::{IcapReplayConfig, IcapReplayMatch, IcapReplayUnmatchedAction};
+use replay::{IcapReplayReadHalf, IcapReplayStore, IcapReplayWriteHalf};
 
 mod connection;
 pub(super) use connection::{IcapClientConnection, IcapClientReader, IcapClientWriter};
 use connection::{IcapConnectionEofPoller, IcapConnectionPollRequest, IcapConnector};
 
+mod state;
+use state::IcapServiceState;
+
 mod client;
 pub use client::IcapServiceClient;
 
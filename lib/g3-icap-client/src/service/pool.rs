@@ -3,36 +3,41 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
 use tokio::sync::{mpsc, oneshot};
-use tokio::time::Interval;
+use tokio::time::{Instant, Interval, Sleep};
 
 use super::{
     IcapClientConnection, IcapConnectionEofPoller, IcapConnectionPollRequest, IcapConnector,
-    IcapServiceConfig,
+    IcapServiceConfig, IcapServiceState,
 };
-use crate::options::{IcapOptionsRequest, IcapServiceOptions};
+use crate::options::{IcapOptionsParseError, IcapOptionsRequest, IcapServiceOptions};
 
 const POOL_CMD_CHANNEL_SIZE: usize = 16;
 
 pub(super) enum IcapServiceClientCommand {
-    FetchConnection(oneshot::Sender<(IcapClientConnection, Arc<IcapServiceOptions>)>),
+    FetchConnection(oneshot::Sender<IcapClientConnection>),
     SaveConnection(IcapClientConnection),
 }
 
 enum IcapServicePoolCommand {
     UpdateOptions(IcapServiceOptions),
+    OptionsRefreshFailed(IcapOptionsParseError),
     SaveConnection(IcapClientConnection),
     CreateConnection,
 }
 
 pub(super) struct IcapServicePool {
     config: Arc<IcapServiceConfig>,
-    options: Arc<IcapServiceOptions>,
+    state: Arc<IcapServiceState>,
     connector: Arc<IcapConnector>,
     check_interval: Interval,
+    refresh_sleep: Pin<Box<Sleep>>,
+    options_refreshing: bool,
     client_cmd_receiver: flume::Receiver<IcapServiceClientCommand>,
     pool_cmd_sender: mpsc::Sender<IcapServicePoolCommand>,
     pool_cmd_receiver: mpsc::Receiver<IcapServicePoolCommand>,
@@ -44,19 +49,22 @@ pub(super) struct IcapServicePool {
 impl IcapServicePool {
     pub(super) fn new(
         config: Arc<IcapServiceConfig>,
+        state: Arc<IcapServiceState>,
         client_cmd_receiver: flume::Receiver<IcapServiceClientCommand>,
         connector: Arc<IcapConnector>,
     ) -> Self {
-        let options = Arc::new(IcapServiceOptions::new_expired(config.method));
+        let refresh_sleep = Box::pin(tokio::time::sleep_until(next_refresh_instant(&config)));
         let check_interval = tokio::time::interval(config.connection_pool.check_interval());
         let (pool_cmd_sender, pool_cmd_receiver) = mpsc::channel(POOL_CMD_CHANNEL_SIZE);
         let (conn_req_sender, conn_req_receiver) =
             flume::bounded(config.connection_pool.max_idle_count());
         IcapServicePool {
             config,
-            options,
+            state,
             connector,
             check_interval,
+            refresh_sleep,
+            options_refreshing: false,
             client_cmd_receiver,
             pool_cmd_sender,
             pool_cmd_receiver,
@@ -78,6 +86,12 @@ impl IcapServicePool {
                 _ = self.check_interval.tick() => {
                     self.check();
                 }
+                _ = &mut self.refresh_sleep, if self.config.options_refresh_interval.is_some() => {
+                    self.refresh_options();
+                    self.refresh_sleep
+                        .as_mut()
+                        .reset(next_refresh_instant(&self.config));
+                }
                 r = self.client_cmd_receiver.recv_async() => {
                     match r {
                         Ok(cmd) => self.handle_client_cmd(cmd),
@@ -95,30 +109,8 @@ impl IcapServicePool {
     }
 
     fn check(&mut self) {
-        if self.options.expired() {
-            let pool_sender = self.pool_cmd_sender.clone();
-            let conn_creator = self.connector.clone();
-            let config = self.config.clone();
-            tokio::spawn(async move {
-                if let Ok(mut conn) = conn_creator.create().await {
-                    conn.mark_io_inuse();
-                    let req = IcapOptionsRequest::new(config.as_ref());
-                    if let Ok(options) = req
-                        .get_options(&mut conn, config.icap_max_header_size)
-                        .await
-                    {
-                        if pool_sender
-                            .send(IcapServicePoolCommand::UpdateOptions(options))
-                            .await
-                            .is_ok()
-                        {
-                            let _ = pool_sender
-                                .send(IcapServicePoolCommand::SaveConnection(conn))
-                                .await;
-                        }
-                    }
-                }
-            });
+        if self.state.options().expired() {
+            self.refresh_options();
         }
 
         let current_idle_count = self.idle_conn_count();
@@ -130,24 +122,67 @@ impl IcapServicePool {
         }
     }
 
+    fn refresh_options(&mut self) {
+        if self.options_refreshing {
+            return;
+        }
+        self.options_refreshing = true;
+
+        let pool_sender = self.pool_cmd_sender.clone();
+        let conn_creator = self.connector.clone();
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            let mut conn = match conn_creator.create().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    let _ = pool_sender
+                        .send(IcapServicePoolCommand::OptionsRefreshFailed(e.into()))
+                        .await;
+                    return;
+                }
+            };
+            conn.mark_io_inuse();
+            let req = IcapOptionsRequest::new(config.as_ref());
+            match req
+                .get_options(&mut conn, config.icap_max_header_size)
+                .await
+            {
+                Ok(options) => {
+                    if pool_sender
+                        .send(IcapServicePoolCommand::UpdateOptions(options))
+                        .await
+                        .is_ok()
+                    {
+                        let _ = pool_sender
+                            .send(IcapServicePoolCommand::SaveConnection(conn))
+                            .await;
+                    }
+                }
+                Err(e) => {
+                    let _ = pool_sender
+                        .send(IcapServicePoolCommand::OptionsRefreshFailed(e))
+                        .await;
+                }
+            }
+        });
+    }
+
     fn handle_client_cmd(&mut self, cmd: IcapServiceClientCommand) {
         match cmd {
             IcapServiceClientCommand::FetchConnection(sender) => {
                 if self.idle_conn_count() > 0 {
                     // there maybe race condition, so we have fallback at client side
                     let req_sender = self.conn_req_sender.clone();
-                    let options = self.options.clone();
                     tokio::spawn(async move {
                         let _ = req_sender
-                            .send_async(IcapConnectionPollRequest::new(sender, options))
+                            .send_async(IcapConnectionPollRequest::new(sender))
                             .await;
                     });
                 } else {
                     let conn_creator = self.connector.clone();
-                    let options = self.options.clone();
                     tokio::spawn(async move {
                         if let Ok(conn) = conn_creator.create().await {
-                            let _ = sender.send((conn, options));
+                            let _ = sender.send(conn);
                         }
                     });
                 }
@@ -163,7 +198,20 @@ impl IcapServicePool {
     fn handle_pool_cmd(&mut self, cmd: IcapServicePoolCommand) {
         match cmd {
             IcapServicePoolCommand::SaveConnection(conn) => self.save_connection(conn),
-            IcapServicePoolCommand::UpdateOptions(options) => self.options = Arc::new(options),
+            IcapServicePoolCommand::UpdateOptions(options) => {
+                self.options_refreshing = false;
+                self.state.update_options(Arc::new(options));
+            }
+            IcapServicePoolCommand::OptionsRefreshFailed(e) => {
+                // keep using the previous options
+                self.options_refreshing = false;
+                self.state.add_options_refresh_failure();
+                if let IcapOptionsParseError::RequestFailed(503, _) = e {
+                    if let Some(time) = self.config.unavailable_bypass_time {
+                        self.state.set_unavailable(time);
+                    }
+                }
+            }
             IcapServicePoolCommand::CreateConnection => self.create(),
         }
     }
@@ -206,3 +254,17 @@ impl IcapServicePool {
         });
     }
 }
+
+fn next_refresh_instant(config: &IcapServiceConfig) -> Instant {
+    let Some(interval) = config.options_refresh_interval else {
+        // the timer won't be polled if not enabled
+        return Instant::now() + Duration::from_secs(86400);
+    };
+    let jitter_millis = config.options_refresh_jitter.as_millis() as u64;
+    let jitter = if jitter_millis > 0 {
+        Duration::from_millis(fastrand::u64(0..=jitter_millis))
+    } else {
+        Duration::ZERO
+    };
+    Instant::now() + interval + jitter
+}
@@ -0,0 +1,831 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{self, Poll, Waker};
+
+use anyhow::{Context, anyhow};
+use bytes::{Buf, Bytes};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::IcapMethod;
+
+const OPTIONS_RESPONSE_FILE: &str = "options-response";
+const REQUEST_FILE_SUFFIX: &str = "-request";
+const RESPONSE_FILE_SUFFIX: &str = "-response";
+
+const MAX_REQUEST_HEAD_SIZE: usize = 1024 * 1024;
+const MAX_CHUNK_LINE_SIZE: usize = 4096;
+
+const NO_CONTENT_RESPONSE: &[u8] =
+    b"ICAP/1.0 204 No Content\r\nISTag: \"replay\"\r\nEncapsulated: null-body=0\r\n\r\n";
+const NOT_MATCHED_RESPONSE: &[u8] =
+    b"ICAP/1.0 500 Replay Not Matched\r\nISTag: \"replay\"\r\nEncapsulated: null-body=0\r\n\r\n";
+
+/// How to match the ICAP requests with the recorded transactions
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum IcapReplayMatch {
+    /// Match on the method and the URL of the encapsulated HTTP request
+    #[default]
+    RequestLine,
+    /// Match on the whole encapsulated HTTP header(s)
+    Header,
+}
+
+impl IcapReplayMatch {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IcapReplayMatch::RequestLine => "request_line",
+            IcapReplayMatch::Header => "header",
+        }
+    }
+}
+
+impl FromStr for IcapReplayMatch {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "request_line" | "url" => Ok(IcapReplayMatch::RequestLine),
+            "header" | "headers" => Ok(IcapReplayMatch::Header),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What to reply if no recorded transaction matches the ICAP request
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum IcapReplayUnmatchedAction {
+    /// Reply 204 so the original message will be used, if 204 is allowed for the request
+    #[default]
+    Bypass,
+    /// Reply an ICAP 500 error response
+    Error,
+}
+
+impl IcapReplayUnmatchedAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IcapReplayUnmatchedAction::Bypass => "bypass",
+            IcapReplayUnmatchedAction::Error => "error",
+        }
+    }
+}
+
+impl FromStr for IcapReplayUnmatchedAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bypass" => Ok(IcapReplayUnmatchedAction::Bypass),
+            "error" | "fail" => Ok(IcapReplayUnmatchedAction::Error),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IcapReplayConfig {
+    pub(crate) dir: PathBuf,
+    pub(crate) match_by: IcapReplayMatch,
+    pub(crate) unmatched: IcapReplayUnmatchedAction,
+}
+
+impl IcapReplayConfig {
+    pub fn new(dir: PathBuf) -> Self {
+        IcapReplayConfig {
+            dir,
+            match_by: IcapReplayMatch::default(),
+            unmatched: IcapReplayUnmatchedAction::default(),
+        }
+    }
+
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn set_match_by(&mut self, match_by: IcapReplayMatch) {
+        self.match_by = match_by;
+    }
+
+    pub fn set_unmatched_action(&mut self, action: IcapReplayUnmatchedAction) {
+        self.unmatched = action;
+    }
+}
+
+/// The recorded transactions loaded from the replay directory.
+///
+/// The record files are in the same format as the debug capture files, a transaction is recorded
+/// in the `<name>-request` and `<name>-response` files, and the OPTIONS response is recorded in
+/// the `options-response` file.
+pub(super) struct IcapReplayStore {
+    match_by: IcapReplayMatch,
+    unmatched: IcapReplayUnmatchedAction,
+    options: Bytes,
+    records: HashMap<Vec<u8>, Bytes>,
+}
+
+impl IcapReplayStore {
+    pub(super) fn load(config: &IcapReplayConfig, method: IcapMethod) -> anyhow::Result<Self> {
+        let mut names = Vec::new();
+        let dir = std::fs::read_dir(&config.dir)
+            .map_err(|e| anyhow!("failed to open dir {}: {e}", config.dir.display()))?;
+        for entry in dir {
+            let entry = entry.map_err(|e| anyhow!("failed to read dir entry: {e}"))?;
+            if let Some(name) = entry.file_name().to_str() {
+                names.push(name.to_string());
+            }
+        }
+        // the first one in file name order will be used if there are duplicate records
+        names.sort();
+
+        let mut options = None;
+        let mut records = HashMap::new();
+        for name in names {
+            if name == OPTIONS_RESPONSE_FILE {
+                let data = std::fs::read(config.dir.join(&name))
+                    .context(format!("failed to read file {name}"))?;
+                options = Some(Bytes::from(data));
+                continue;
+            }
+            let Some(prefix) = name.strip_suffix(REQUEST_FILE_SUFFIX) else {
+                continue;
+            };
+            let request = std::fs::read(config.dir.join(&name))
+                .context(format!("failed to read file {name}"))?;
+            let head = match IcapRequestHead::parse(&request) {
+                Ok(Some(head)) => head,
+                Ok(None) => return Err(anyhow!("incomplete ICAP request head in file {name}")),
+                Err(e) => return Err(anyhow!("invalid ICAP request in file {name}: {e}")),
+            };
+            let response_name = format!("{prefix}{RESPONSE_FILE_SUFFIX}");
+            let response = std::fs::read(config.dir.join(&response_name))
+                .context(format!("failed to read file {response_name}"))?;
+            records
+                .entry(head.match_key(&request, config.match_by))
+                .or_insert_with(|| Bytes::from(response));
+        }
+
+        let options = options.unwrap_or_else(|| {
+            Bytes::from(format!(
+                "ICAP/1.0 200 OK\r\nMethods: {}\r\nISTag: \"replay\"\r\nAllow: 204\r\n\
+                 Encapsulated: null-body=0\r\n\r\n",
+                method.as_str()
+            ))
+        });
+        Ok(IcapReplayStore {
+            match_by: config.match_by,
+            unmatched: config.unmatched,
+            options,
+            records,
+        })
+    }
+
+    fn select(&self, request: &[u8], head: &IcapRequestHead) -> Bytes {
+        if head.is_options {
+            return self.options.clone();
+        }
+        if let Some(rsp) = self.records.get(&head.match_key(request, self.match_by)) {
+            return rsp.clone();
+        }
+        match self.unmatched {
+            IcapReplayUnmatchedAction::Bypass if head.allow_204 => {
+                Bytes::from_static(NO_CONTENT_RESPONSE)
+            }
+            _ => Bytes::from_static(NOT_MATCHED_RESPONSE),
+        }
+    }
+
+    pub(super) fn new_connection(self: &Arc<Self>) -> (IcapReplayReadHalf, IcapReplayWriteHalf) {
+        let shared = Arc::new(Mutex::new(ReplayShared::default()));
+        let r = IcapReplayReadHalf {
+            shared: shared.clone(),
+        };
+        let w = IcapReplayWriteHalf {
+            store: self.clone(),
+            shared,
+            state: ReplayRequestState::Head(Vec::new()),
+        };
+        (r, w)
+    }
+}
+
+/// The ICAP header and the encapsulated HTTP header(s) of a request
+struct IcapRequestHead {
+    icap_header_size: usize,
+    size: usize,
+    is_options: bool,
+    has_body: bool,
+    preview: bool,
+    allow_204: bool,
+}
+
+impl IcapRequestHead {
+    /// Parse the request head, `None` will be returned if more data is needed
+    fn parse(buf: &[u8]) -> Result<Option<Self>, &'static str> {
+        let Some(p) = memchr::memmem::find(buf, b"\r\n\r\n") else {
+            return Ok(None);
+        };
+        let icap_header_size = p + 4;
+        let header = std::str::from_utf8(&buf[..p]).map_err(|_| "invalid ICAP header")?;
+
+        let mut lines = header.split("\r\n");
+        let method_line = lines.next().unwrap_or_default();
+        let is_options = method_line.starts_with("OPTIONS ");
+        let mut encapsulated = None;
+        let mut preview = false;
+        let mut allow_204 = false;
+        for line in lines {
+            let Some((name, value)) = line.split_once(':') else {
+                return Err("invalid ICAP header line");
+            };
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("encapsulated") {
+                encapsulated = Some(value);
+            } else if name.eq_ignore_ascii_case("preview") {
+                preview = true;
+            } else if name.eq_ignore_ascii_case("allow") {
+                allow_204 = value.split(',').any(|v| v.trim() == "204");
+            }
+        }
+
+        let encapsulated = match encapsulated {
+            Some(v) => v,
+            // the OPTIONS request sent by us has no body and no Encapsulated header
+            None if is_options => "null-body=0",
+            None => return Err("no Encapsulated header found"),
+        };
+        // the last one is always the body part
+        let Some((name, offset)) = encapsulated
+            .rsplit(',')
+            .next()
+            .and_then(|s| s.trim().split_once('='))
+        else {
+            return Err("invalid Encapsulated header");
+        };
+        let offset = usize::from_str(offset).map_err(|_| "invalid Encapsulated offset")?;
+
+        let size = icap_header_size + offset;
+        if buf.len() < size {
+            return Ok(None);
+        }
+        Ok(Some(IcapRequestHead {
+            icap_header_size,
+            size,
+            is_options,
+            has_body: name != "null-body",
+            // 204 is always allowed in response to the preview
+            allow_204: allow_204 || preview,
+            preview,
+        }))
+    }
+
+    fn match_key(&self, buf: &[u8], match_by: IcapReplayMatch) -> Vec<u8> {
+        let method = buf.split(|c| *c == b' ').next().unwrap_or_default();
+        let encapsulated = &buf[self.icap_header_size..self.size];
+
+        let mut key = Vec::with_capacity(method.len() + encapsulated.len() + 1);
+        key.extend_from_slice(method);
+        key.push(b' ');
+        match match_by {
+            IcapReplayMatch::RequestLine => {
+                let mut lines = encapsulated.split(|c| *c == b'\n').map(trim_cr);
+                let request_line = lines.next().unwrap_or_default();
+                let mut parts = request_line.split(|c| *c == b' ');
+                let http_method = parts.next().unwrap_or_default();
+                let target = parts.next().unwrap_or_default();
+                key.extend_from_slice(http_method);
+                key.push(b' ');
+                if target.starts_with(b"/") {
+                    // add the host for origin form targets
+                    let host = lines
+                        .take_while(|line| !line.is_empty())
+                        .find_map(|line| {
+                            let p = memchr::memchr(b':', line)?;
+                            line[..p]
+                                .eq_ignore_ascii_case(b"host")
+                                .then(|| line[p + 1..].trim_ascii())
+                        })
+                        .unwrap_or_default();
+                    key.extend_from_slice(host);
+                }
+                key.extend_from_slice(target);
+            }
+            IcapReplayMatch::Header => key.extend_from_slice(encapsulated),
+        }
+        key
+    }
+}
+
+fn trim_cr(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+#[derive(Default)]
+enum ChunkState {
+    #[default]
+    Size,
+    Data(u64),
+    Trailer,
+}
+
+/// Find the end of the chunked encapsulated body in the ICAP request
+#[derive(Default)]
+struct ChunkedBodyTracker {
+    state: ChunkState,
+    line: Vec<u8>,
+    ieof: bool,
+}
+
+impl ChunkedBodyTracker {
+    /// Feed the request data, return the size of the consumed data if the body is ended
+    fn feed(&mut self, data: &[u8]) -> Result<Option<usize>, &'static str> {
+        let mut offset = 0;
+        while offset < data.len() {
+            let left = &data[offset..];
+            match self.state {
+                ChunkState::Size | ChunkState::Trailer => {
+                    let Some(p) = memchr::memchr(b'\n', left) else {
+                        self.line.extend_from_slice(left);
+                        if self.line.len() > MAX_CHUNK_LINE_SIZE {
+                            return Err("too long chunk line");
+                        }
+                        return Ok(None);
+                    };
+                    self.line.extend_from_slice(&left[..p]);
+                    offset += p + 1;
+                    let line = std::mem::take(&mut self.line);
+                    let line = trim_cr(&line);
+
+                    if matches!(self.state, ChunkState::Trailer) {
+                        if line.is_empty() {
+                            return Ok(Some(offset));
+                        }
+                        continue;
+                    }
+
+                    let (size, ext) = match memchr::memchr(b';', line) {
+                        Some(p) => (&line[..p], &line[p + 1..]),
+                        None => (line, &[][..]),
+                    };
+                    let size = std::str::from_utf8(size.trim_ascii())
+                        .ok()
+                        .and_then(|s| u64::from_str_radix(s, 16).ok())
+                        .ok_or("invalid chunk size")?;
+                    if size == 0 {
+                        self.ieof = ext.trim_ascii().eq_ignore_ascii_case(b"ieof");
+                        self.state = ChunkState::Trailer;
+                    } else {
+                        // with the ending CRLF
+                        self.state = ChunkState::Data(size + 2);
+                    }
+                }
+                ChunkState::Data(n) => {
+                    let len = n.min(left.len() as u64);
+                    offset += len as usize;
+                    if len == n {
+                        self.state = ChunkState::Size;
+                    } else {
+                        self.state = ChunkState::Data(n - len);
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+enum ReplayRequestState {
+    Head(Vec<u8>),
+    Body {
+        tracker: ChunkedBodyTracker,
+        /// the remaining body will be sent after the preview if 100-continue is replied
+        wait_continue: bool,
+    },
+}
+
+#[derive(Default)]
+struct ReplayShared {
+    responses: VecDeque<Bytes>,
+    read_waker: Option<Waker>,
+    closed: bool,
+}
+
+impl ReplayShared {
+    fn push_response(&mut self, rsp: Bytes) {
+        self.responses.push_back(rsp);
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// The read half of the replay transport, which reads the recorded response of each request
+pub(super) struct IcapReplayReadHalf {
+    shared: Arc<Mutex<ReplayShared>>,
+}
+
+impl AsyncRead for IcapReplayReadHalf {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut shared = self.shared.lock().unwrap();
+        while let Some(rsp) = shared.responses.front_mut() {
+            if rsp.is_empty() {
+                shared.responses.pop_front();
+                continue;
+            }
+            let len = rsp.len().min(buf.remaining());
+            buf.put_slice(&rsp[..len]);
+            rsp.advance(len);
+            return Poll::Ready(Ok(()));
+        }
+        if shared.closed {
+            return Poll::Ready(Ok(()));
+        }
+        shared.read_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// The write half of the replay transport, which selects the recorded response for each request
+pub(super) struct IcapReplayWriteHalf {
+    store: Arc<IcapReplayStore>,
+    shared: Arc<Mutex<ReplayShared>>,
+    state: ReplayRequestState,
+}
+
+impl IcapReplayWriteHalf {
+    fn feed(&mut self, mut data: &[u8]) -> Result<(), &'static str> {
+        while !data.is_empty() {
+            match &mut self.state {
+                ReplayRequestState::Head(buf) => {
+                    let buffered = buf.len();
+                    buf.extend_from_slice(data);
+                    let Some(head) = IcapRequestHead::parse(buf)? else {
+                        if buf.len() > MAX_REQUEST_HEAD_SIZE {
+                            return Err("too large ICAP request head");
+                        }
+                        return Ok(());
+                    };
+                    let rsp = self.store.select(buf, &head);
+                    data = &data[head.size - buffered..];
+
+                    let wait_continue = head.preview && rsp.starts_with(b"ICAP/1.0 100 ");
+                    self.shared.lock().unwrap().push_response(rsp);
+                    self.state = if head.has_body {
+                        ReplayRequestState::Body {
+                            tracker: ChunkedBodyTracker::default(),
+                            wait_continue,
+                        }
+                    } else {
+                        ReplayRequestState::Head(Vec::new())
+                    };
+                }
+                ReplayRequestState::Body {
+                    tracker,
+                    wait_continue,
+                } => {
+                    let Some(len) = tracker.feed(data)? else {
+                        return Ok(());
+                    };
+                    data = &data[len..];
+                    if *wait_continue && !tracker.ieof {
+                        *tracker = ChunkedBodyTracker::default();
+                        *wait_continue = false;
+                    } else {
+                        self.state = ReplayRequestState::Head(Vec::new());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AsyncWrite for IcapReplayWriteHalf {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.feed(buf) {
+            Ok(_) => Poll::Ready(Ok(buf.len())),
+            Err(e) => {
+                self.shared.lock().unwrap().close();
+                Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, e)))
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        self.shared.lock().unwrap().close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for IcapReplayWriteHalf {
+    fn drop(&mut self) {
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use http::Version;
+    use tokio::io::BufReader;
+    use tokio::time::Instant;
+    use url::Url;
+
+    use g3_http::server::HttpProxyClientRequest;
+    use g3_io_ext::{IdleCheck, IdleForceQuitReason, IdleInterval, IdleWheel, StreamCopyConfig};
+
+    use crate::reqmod::IcapReqmodClient;
+    use crate::reqmod::h1::{
+        H1ReqmodAdaptationError, HttpRequestUpstreamWriter, ReqmodAdaptationRunState,
+    };
+    use crate::{IcapServiceClient, IcapServiceConfig};
+
+    const OPTIONS_RESPONSE: &str = "ICAP/1.0 200 OK\r\nMethods: REQMOD\r\nISTag: \"replay\"\r\n\
+        Allow: 204\r\nPreview: 4\r\nEncapsulated: null-body=0\r\n\r\n";
+
+    const ADAPTED_HTTP_HEADER: &str =
+        "POST /adapted HTTP/1.1\r\nHost: example.net\r\nContent-Length: 11\r\nX-Adapted: 1\r\n\r\n";
+
+    fn recorded_request(path: &str) -> String {
+        let http_header =
+            format!("POST {path} HTTP/1.1\r\nHost: example.net\r\nContent-Length: 11\r\n\r\n");
+        format!(
+            "REQMOD icap://replay.local:1344/reqmod ICAP/1.0\r\nHost: replay.local\r\n\
+             Encapsulated: req-hdr=0, req-body={}\r\nPreview: 4\r\n\r\n\
+             {http_header}4\r\nhell\r\n0\r\n\r\n",
+            http_header.len()
+        )
+    }
+
+    fn recording_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("g3-icap-replay-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let write = |name: &str, data: &str| std::fs::write(dir.join(name), data).unwrap();
+        write(OPTIONS_RESPONSE_FILE, OPTIONS_RESPONSE);
+        write("no-change-request", &recorded_request("/no-change"));
+        write(
+            "no-change-response",
+            "ICAP/1.0 204 No Content\r\nISTag: \"replay\"\r\nEncapsulated: null-body=0\r\n\r\n",
+        );
+        write("adapted-request", &recorded_request("/adapted"));
+        write(
+            "adapted-response",
+            &format!(
+                "ICAP/1.0 100 Continue\r\n\r\nICAP/1.0 200 OK\r\nISTag: \"replay\"\r\n\
+                 Encapsulated: req-hdr=0, req-body={}\r\n\r\n\
+                 {ADAPTED_HTTP_HEADER}b\r\nHELLO WORLD\r\n0\r\n\r\n",
+                ADAPTED_HTTP_HEADER.len()
+            ),
+        );
+        write("malformed-request", &recorded_request("/malformed"));
+        write("malformed-response", "NOT AN ICAP RESPONSE\r\n\r\n");
+        dir
+    }
+
+    struct MockIdleChecker(Arc<IdleWheel>);
+
+    impl IdleCheck for MockIdleChecker {
+        fn interval_timer(&self) -> IdleInterval {
+            self.0.register()
+        }
+
+        fn check_quit(&self, _idle_count: usize) -> bool {
+            false
+        }
+
+        fn check_force_quit(&self) -> Option<IdleForceQuitReason> {
+            None
+        }
+    }
+
+    #[derive(Default)]
+    struct MockUpstreamWriter {
+        buf: Vec<u8>,
+    }
+
+    impl AsyncWrite for MockUpstreamWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut task::Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.buf).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: Pin<&mut Self>,
+            _cx: &mut task::Context<'_>,
+        ) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl HttpRequestUpstreamWriter<HttpProxyClientRequest> for MockUpstreamWriter {
+        async fn send_request_header(&mut self, req: &HttpProxyClientRequest) -> io::Result<()> {
+            self.buf.extend_from_slice(&req.serialize_for_origin());
+            Ok(())
+        }
+    }
+
+    async fn new_client(
+        dir: &Path,
+        unmatched: IcapReplayUnmatchedAction,
+    ) -> Arc<IcapServiceClient> {
+        let url = Url::parse("icap://replay.local:1344/reqmod").unwrap();
+        let mut config = IcapServiceConfig::new(IcapMethod::Reqmod, url).unwrap();
+        let mut replay = IcapReplayConfig::new(dir.to_path_buf());
+        replay.set_unmatched_action(unmatched);
+        config.set_replay(replay);
+        let client = Arc::new(IcapServiceClient::new(Arc::new(config)).unwrap());
+
+        // wait for the recorded options to be loaded in background
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.options().preview_size.is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        client
+    }
+
+    async fn run_adaptation(
+        client: &Arc<IcapServiceClient>,
+        path: &str,
+    ) -> (Result<(), H1ReqmodAdaptationError>, String) {
+        let reqmod_client = IcapReqmodClient::new(client.clone());
+        let adapter = reqmod_client
+            .h1_adapter(
+                StreamCopyConfig::default(),
+                1024,
+                true,
+                MockIdleChecker(IdleWheel::spawn(Duration::from_secs(1))),
+            )
+            .await
+            .unwrap();
+
+        let client_request = format!(
+            "POST http://example.net{path} HTTP/1.1\r\n\
+             Host: example.net\r\nContent-Length: 11\r\n\r\nhello world"
+        );
+        let mut clt_r = BufReader::new(client_request.as_bytes());
+        let mut version = Version::HTTP_11;
+        let http_request = HttpProxyClientRequest::parse_basic(&mut clt_r, 4096, &mut version)
+            .await
+            .unwrap();
+
+        let mut state = ReqmodAdaptationRunState::new(Instant::now());
+        let mut ups_writer = MockUpstreamWriter::default();
+        let r = adapter
+            .xfer(&mut state, &http_request, Some(&mut clt_r), &mut ups_writer)
+            .await
+            .map(|_| ());
+        (r, String::from_utf8(ups_writer.buf).unwrap())
+    }
+
+    #[test]
+    fn request_head() {
+        let request = recorded_request("/a/b");
+        let head = IcapRequestHead::parse(request.as_bytes()).unwrap().unwrap();
+        assert!(head.has_body);
+        assert!(head.preview);
+        assert!(head.allow_204);
+        assert!(!head.is_options);
+        assert!(request[head.size..].starts_with("4\r\nhell\r\n"));
+        assert_eq!(
+            head.match_key(request.as_bytes(), IcapReplayMatch::RequestLine),
+            b"REQMOD POST example.net/a/b"
+        );
+        let key = head.match_key(request.as_bytes(), IcapReplayMatch::Header);
+        assert!(key.starts_with(b"REQMOD POST /a/b HTTP/1.1\r\nHost: example.net\r\n"));
+        assert!(key.ends_with(b"\r\n\r\n"));
+
+        // the encapsulated header is not complete
+        assert!(
+            IcapRequestHead::parse(&request.as_bytes()[..head.size - 1])
+                .unwrap()
+                .is_none()
+        );
+
+        let request = "OPTIONS icap://replay.local/reqmod ICAP/1.0\r\n\
+            Host: replay.local\r\nEncapsulated: null-body=0\r\n\r\n";
+        let head = IcapRequestHead::parse(request.as_bytes()).unwrap().unwrap();
+        assert!(head.is_options);
+        assert!(!head.has_body);
+        assert_eq!(head.size, request.len());
+
+        let request = "OPTIONS icap://replay.local/reqmod ICAP/1.0\r\n\
+            Host: replay.local\r\nAllow: 204\r\n\r\n";
+        let head = IcapRequestHead::parse(request.as_bytes()).unwrap().unwrap();
+        assert!(head.is_options);
+        assert!(!head.has_body);
+        assert_eq!(head.size, request.len());
+
+        assert!(IcapRequestHead::parse(b"REQMOD icap://a/b ICAP/1.0\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn chunked_body_end() {
+        let mut tracker = ChunkedBodyTracker::default();
+        assert_eq!(tracker.feed(b"b\r\nhello").unwrap(), None);
+        assert_eq!(tracker.feed(b" world\r\n0").unwrap(), None);
+        assert_eq!(tracker.feed(b"; ieof\r\n\r\nREQMOD").unwrap(), Some(10));
+        assert!(tracker.ieof);
+
+        // with trailer
+        let mut tracker = ChunkedBodyTracker::default();
+        let data = b"3\r\nabc\r\n0\r\nX-Trailer: 1\r\n\r\n";
+        assert_eq!(tracker.feed(data).unwrap(), Some(data.len()));
+        assert!(!tracker.ieof);
+
+        let mut tracker = ChunkedBodyTracker::default();
+        assert!(tracker.feed(b"xyz\r\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn replay_recorded() {
+        let dir = recording_dir("recorded");
+        let client = new_client(&dir, IcapReplayUnmatchedAction::Error).await;
+
+        let (r, sent) = run_adaptation(&client, "/no-change").await;
+        r.unwrap();
+        assert!(sent.starts_with("POST /no-change HTTP/1.1\r\n"));
+        assert!(!sent.contains("X-Adapted"));
+        assert!(sent.ends_with("\r\n\r\nhello world"));
+
+        let (r, sent) = run_adaptation(&client, "/adapted").await;
+        r.unwrap();
+        assert!(sent.contains("\r\nX-Adapted: 1\r\n"));
+        assert!(sent.ends_with("\r\n\r\nHELLO WORLD"));
+
+        let (r, sent) = run_adaptation(&client, "/malformed").await;
+        assert!(r.is_err());
+        assert!(sent.is_empty());
+
+        let (r, sent) = run_adaptation(&client, "/unknown").await;
+        assert!(matches!(
+            r,
+            Err(H1ReqmodAdaptationError::IcapServerErrorResponse(_, 500, _))
+        ));
+        assert!(sent.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn replay_unmatched_bypass() {
+        let dir = recording_dir("bypass");
+        let client = new_client(&dir, IcapReplayUnmatchedAction::Bypass).await;
+
+        let (r, sent) = run_adaptation(&client, "/unknown").await;
+        r.unwrap();
+        assert!(sent.starts_with("POST /unknown HTTP/1.1\r\n"));
+        assert!(sent.ends_with("\r\n\r\nhello world"));
+
+        // the recorded ones should still be used
+        let (r, sent) = run_adaptation(&client, "/adapted").await;
+        r.unwrap();
+        assert!(sent.ends_with("\r\n\r\nHELLO WORLD"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
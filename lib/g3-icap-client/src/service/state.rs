@@ -0,0 +1,115 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use arc_swap::{ArcSwap, ArcSwapOption};
+use tokio::time::Instant;
+
+use super::{IcapDebugCaptureConfig, IcapMethod};
+use crate::options::IcapServiceOptions;
+use crate::timing::{IcapTransactionTiming, IcapTransactionTimingStats};
+
+/// The service state shared between the client and the connection pool
+pub(super) struct IcapServiceState {
+    options: ArcSwap<IcapServiceOptions>,
+    unavailable_until: ArcSwapOption<Instant>,
+    debug_capture: ArcSwapOption<IcapDebugCaptureConfig>,
+    options_refresh_failures: AtomicU64,
+    debug_capture_dropped: AtomicU64,
+    early_close_retries: AtomicU64,
+    transfer_ignored: AtomicU64,
+    timing_stats: IcapTransactionTimingStats,
+}
+
+impl IcapServiceState {
+    pub(super) fn new(method: IcapMethod, debug_capture: Option<IcapDebugCaptureConfig>) -> Self {
+        IcapServiceState {
+            options: ArcSwap::from_pointee(IcapServiceOptions::new_expired(method)),
+            unavailable_until: ArcSwapOption::empty(),
+            debug_capture: ArcSwapOption::from_pointee(debug_capture),
+            options_refresh_failures: AtomicU64::new(0),
+            debug_capture_dropped: AtomicU64::new(0),
+            early_close_retries: AtomicU64::new(0),
+            transfer_ignored: AtomicU64::new(0),
+            timing_stats: IcapTransactionTimingStats::new(),
+        }
+    }
+
+    /// Get the current options, which should be kept unchanged during the whole transaction
+    pub(super) fn options(&self) -> Arc<IcapServiceOptions> {
+        self.options.load_full()
+    }
+
+    pub(super) fn update_options(&self, options: Arc<IcapServiceOptions>) {
+        self.options.store(options);
+        self.unavailable_until.store(None);
+    }
+
+    pub(super) fn add_options_refresh_failure(&self) {
+        self.options_refresh_failures
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn options_refresh_failures(&self) -> u64 {
+        self.options_refresh_failures.load(Ordering::Relaxed)
+    }
+
+    /// Get the current debug capture config, which may be changed at runtime
+    pub(super) fn debug_capture(&self) -> Option<Arc<IcapDebugCaptureConfig>> {
+        self.debug_capture.load_full()
+    }
+
+    pub(super) fn set_debug_capture(&self, capture: Option<IcapDebugCaptureConfig>) {
+        self.debug_capture.store(capture.map(Arc::new));
+    }
+
+    pub(super) fn add_debug_capture_dropped(&self) {
+        self.debug_capture_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn debug_capture_dropped(&self) -> u64 {
+        self.debug_capture_dropped.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn add_early_close_retry(&self) {
+        self.early_close_retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn early_close_retries(&self) -> u64 {
+        self.early_close_retries.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn add_transfer_ignored(&self) {
+        self.transfer_ignored.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn transfer_ignored(&self) -> u64 {
+        self.transfer_ignored.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub(super) fn timing_stats(&self) -> &IcapTransactionTimingStats {
+        &self.timing_stats
+    }
+
+    pub(super) fn record_timing(&self, timing: &IcapTransactionTiming) {
+        self.timing_stats.record(timing);
+    }
+
+    pub(super) fn set_unavailable(&self, time: Duration) {
+        self.unavailable_until
+            .store(Some(Arc::new(Instant::now() + time)));
+    }
+
+    pub(super) fn unavailable(&self) -> bool {
+        match &*self.unavailable_until.load() {
+            Some(until) => Instant::now() < **until,
+            None => false,
+        }
+    }
+}
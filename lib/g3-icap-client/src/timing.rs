@@ -0,0 +1,180 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use g3_histogram::{BucketHistogram, BucketHistogramConfig, BucketHistogramSnapshot};
+
+const PHASE_COUNT: usize = 8;
+
+/// The phases of an ICAP transaction, in the order they should happen
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IcapTransactionPhase {
+    /// fetch an idle connection from the pool, or connect to the ICAP server
+    Connect,
+    /// send the ICAP request header along with the encapsulated HTTP header
+    SendHeader,
+    /// send the preview data
+    SendPreview,
+    /// wait for the 100-continue response after the preview
+    WaitContinue,
+    /// send the remaining HTTP body to the ICAP server
+    SendBody,
+    /// wait for the final ICAP response header
+    WaitResponse,
+    /// parse the adapted HTTP header
+    ParseAdaptedHeader,
+    /// relay the adapted HTTP body to the peer
+    RelayAdaptedBody,
+}
+
+impl IcapTransactionPhase {
+    pub const ALL: [IcapTransactionPhase; PHASE_COUNT] = [
+        IcapTransactionPhase::Connect,
+        IcapTransactionPhase::SendHeader,
+        IcapTransactionPhase::SendPreview,
+        IcapTransactionPhase::WaitContinue,
+        IcapTransactionPhase::SendBody,
+        IcapTransactionPhase::WaitResponse,
+        IcapTransactionPhase::ParseAdaptedHeader,
+        IcapTransactionPhase::RelayAdaptedBody,
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            IcapTransactionPhase::Connect => "connect",
+            IcapTransactionPhase::SendHeader => "send_header",
+            IcapTransactionPhase::SendPreview => "send_preview",
+            IcapTransactionPhase::WaitContinue => "wait_continue",
+            IcapTransactionPhase::SendBody => "send_body",
+            IcapTransactionPhase::WaitResponse => "wait_response",
+            IcapTransactionPhase::ParseAdaptedHeader => "parse_adapted_header",
+            IcapTransactionPhase::RelayAdaptedBody => "relay_adapted_body",
+        }
+    }
+}
+
+/// The end time of each phase of an ICAP transaction
+///
+/// The duration of a phase is counted from the end of the last finished phase before it.
+/// Phases that are finished by a single write will have the same end time, so the time spent
+/// will be accounted to the first one of them.
+#[derive(Clone, Copy, Debug)]
+pub struct IcapTransactionTiming {
+    start: Instant,
+    ends: [Option<Instant>; PHASE_COUNT],
+}
+
+impl IcapTransactionTiming {
+    pub fn new(start: Instant) -> Self {
+        IcapTransactionTiming {
+            start,
+            ends: [None; PHASE_COUNT],
+        }
+    }
+
+    /// Record the end time of the phase, only the first call for each phase takes effect
+    pub(crate) fn mark(&mut self, phase: IcapTransactionPhase) {
+        let end = &mut self.ends[phase as usize];
+        if end.is_none() {
+            *end = Some(Instant::now());
+        }
+    }
+
+    #[inline]
+    pub fn is_finished(&self, phase: IcapTransactionPhase) -> bool {
+        self.ends[phase as usize].is_some()
+    }
+
+    /// Get the time spent in the phase, `None` if the phase is not finished
+    pub fn phase_duration(&self, phase: IcapTransactionPhase) -> Option<Duration> {
+        let i = phase as usize;
+        let end = self.ends[i]?;
+        let begin = self.ends[..i]
+            .iter()
+            .rev()
+            .find_map(|v| *v)
+            .unwrap_or(self.start);
+        Some(end.saturating_duration_since(begin))
+    }
+
+    /// Iterate over all the finished phases
+    pub fn iter(&self) -> impl Iterator<Item = (IcapTransactionPhase, Duration)> + '_ {
+        IcapTransactionPhase::ALL
+            .into_iter()
+            .filter_map(|phase| self.phase_duration(phase).map(|d| (phase, d)))
+    }
+}
+
+/// The duration distribution of each phase, in microseconds
+pub(crate) struct IcapTransactionTimingStats {
+    phases: [BucketHistogram; PHASE_COUNT],
+}
+
+impl IcapTransactionTimingStats {
+    pub(crate) fn new() -> Self {
+        // 1us to about 68s
+        let config = BucketHistogramConfig::powers_of(4, 1 << 36);
+        IcapTransactionTimingStats {
+            phases: std::array::from_fn(|_| BucketHistogram::new(&config)),
+        }
+    }
+
+    pub(crate) fn record(&self, timing: &IcapTransactionTiming) {
+        for (phase, dur) in timing.iter() {
+            let us = u64::try_from(dur.as_micros()).unwrap_or(u64::MAX);
+            self.phases[phase as usize].record(us);
+        }
+    }
+
+    pub(crate) fn snapshot(&self, phase: IcapTransactionPhase) -> BucketHistogramSnapshot {
+        self.phases[phase as usize].snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phase_duration() {
+        let start = Instant::now();
+        let mut timing = IcapTransactionTiming::new(start);
+        timing.ends[0] = Some(start + Duration::from_millis(10));
+        timing.ends[1] = Some(start + Duration::from_millis(15));
+        timing.ends[5] = Some(start + Duration::from_millis(40));
+        assert_eq!(
+            timing.phase_duration(IcapTransactionPhase::Connect),
+            Some(Duration::from_millis(10))
+        );
+        assert_eq!(
+            timing.phase_duration(IcapTransactionPhase::SendHeader),
+            Some(Duration::from_millis(5))
+        );
+        assert!(!timing.is_finished(IcapTransactionPhase::SendPreview));
+        // counted from the end of the last finished phase
+        assert_eq!(
+            timing.phase_duration(IcapTransactionPhase::WaitResponse),
+            Some(Duration::from_millis(25))
+        );
+        assert_eq!(timing.iter().count(), 3);
+
+        // the first mark takes effect
+        timing.mark(IcapTransactionPhase::Connect);
+        assert_eq!(
+            timing.phase_duration(IcapTransactionPhase::Connect),
+            Some(Duration::from_millis(10))
+        );
+
+        let stats = IcapTransactionTimingStats::new();
+        stats.record(&timing);
+        let snap = stats.snapshot(IcapTransactionPhase::SendHeader);
+        assert_eq!(snap.count(), 1);
+        assert_eq!(snap.sum(), 5000);
+        assert_eq!(stats.snapshot(IcapTransactionPhase::SendPreview).count(), 0);
+    }
+}
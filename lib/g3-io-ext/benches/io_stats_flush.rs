@@ -0,0 +1,140 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+#![feature(test)]
+
+extern crate test;
+use test::Bencher;
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use g3_io_ext::{AsyncUdpSend, LimitedSendStats, LimitedUdpSend};
+use g3_io_sys::udp::SendMsgHdr;
+
+const PACKET_SIZE: usize = 1000;
+
+struct DiscardSend;
+
+impl AsyncUdpSend for DiscardSend {
+    fn poll_send_to(
+        &mut self,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+        _target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_send(&mut self, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_sendmsg<const C: usize>(
+        &mut self,
+        _cx: &mut Context<'_>,
+        hdr: &SendMsgHdr<'_, C>,
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(hdr.iov.iter().map(|v| v.len()).sum()))
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+    ))]
+    fn poll_batch_sendmsg<const C: usize>(
+        &mut self,
+        _cx: &mut Context<'_>,
+        msgs: &mut [SendMsgHdr<'_, C>],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(msgs.len()))
+    }
+
+    #[cfg(target_os = "macos")]
+    fn poll_batch_sendmsg_x<const C: usize>(
+        &mut self,
+        _cx: &mut Context<'_>,
+        msgs: &mut [SendMsgHdr<'_, C>],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(msgs.len()))
+    }
+}
+
+#[derive(Default)]
+struct SharedStats {
+    packets: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// The task, server, user and user site stats are all updated for each packet
+struct WrapperStats {
+    all: Vec<Arc<SharedStats>>,
+}
+
+impl LimitedSendStats for WrapperStats {
+    fn add_send_bytes(&self, size: usize) {
+        for s in &self.all {
+            s.bytes.fetch_add(size as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn add_send_packets(&self, n: usize) {
+        for s in &self.all {
+            s.packets.fetch_add(n as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+fn new_send(flush_interval: Duration) -> LimitedUdpSend<DiscardSend> {
+    let stats = WrapperStats {
+        all: (0..4).map(|_| Arc::new(SharedStats::default())).collect(),
+    };
+    let mut send = LimitedUdpSend::local_limited(DiscardSend, 0, 0, 0, Arc::new(stats));
+    send.set_stats_flush_interval(flush_interval);
+    send
+}
+
+fn send_packet(send: &mut LimitedUdpSend<DiscardSend>, buf: &[u8], target: SocketAddr) {
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+    let Poll::Ready(Ok(nw)) = send.poll_send_to(&mut cx, buf, target) else {
+        unreachable!()
+    };
+    assert_eq!(nw, buf.len());
+}
+
+fn new_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap()
+}
+
+#[bench]
+fn write_through(b: &mut Bencher) {
+    let rt = new_runtime();
+    let _guard = rt.enter();
+    let mut send = new_send(Duration::ZERO);
+    let buf = [0u8; PACKET_SIZE];
+    let target = SocketAddr::from(([127, 0, 0, 1], 53));
+    b.iter(|| send_packet(&mut send, &buf, target));
+}
+
+#[bench]
+fn buffered(b: &mut Bencher) {
+    let rt = new_runtime();
+    let _guard = rt.enter();
+    let mut send = new_send(Duration::from_millis(100));
+    let buf = [0u8; PACKET_SIZE];
+    let target = SocketAddr::from(([127, 0, 0, 1], 53));
+    b.iter(|| send_packet(&mut send, &buf, target));
+}
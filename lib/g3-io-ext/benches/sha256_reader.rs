@@ -0,0 +1,51 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+#![feature(test)]
+
+extern crate test;
+use test::Bencher;
+
+use futures_util::FutureExt;
+
+use g3_io_ext::Sha256BufReader;
+
+const BODY_SIZE: usize = 1024 * 1024;
+
+fn copy_plain(body: &[u8]) {
+    let mut reader = body;
+    let mut writer = tokio::io::sink();
+    let n = tokio::io::copy_buf(&mut reader, &mut writer)
+        .now_or_never()
+        .unwrap()
+        .unwrap();
+    assert_eq!(n, BODY_SIZE as u64);
+}
+
+fn copy_hashed(body: &[u8]) {
+    let mut reader = Sha256BufReader::new(body);
+    let mut writer = tokio::io::sink();
+    let n = tokio::io::copy_buf(&mut reader, &mut writer)
+        .now_or_never()
+        .unwrap()
+        .unwrap();
+    assert_eq!(n, BODY_SIZE as u64);
+    let (digest, _) = reader.finish();
+    assert!(digest.is_some());
+}
+
+#[bench]
+fn plain_copy(b: &mut Bencher) {
+    let body = vec![b'x'; BODY_SIZE];
+    b.bytes = BODY_SIZE as u64;
+    b.iter(|| copy_plain(&body));
+}
+
+#[bench]
+fn sha256_copy(b: &mut Bencher) {
+    let body = vec![b'x'; BODY_SIZE];
+    b.bytes = BODY_SIZE as u64;
+    b.iter(|| copy_hashed(&body));
+}
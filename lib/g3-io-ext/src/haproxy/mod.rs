@@ -12,7 +12,7 @@ mod v1;
 pub use v1::ProxyProtocolV1Reader;
 
 mod v2;
-pub use v2::ProxyProtocolV2Reader;
+pub use v2::{PP2_TYPE_ALPN, PP2_TYPE_AUTHORITY, ProxyProtocolV2Reader};
 
 pub struct ProxyAddr {
     pub src_addr: SocketAddr,
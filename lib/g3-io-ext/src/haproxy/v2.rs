@@ -27,10 +27,19 @@ const PROTOCOL_UNSPEC: u8 = 0x00;
 const PROTOCOL_STREAM: u8 = 0x01;
 const PROTOCOL_DGRAM: u8 = 0x02;
 
+const INET_ADDR_LEN: usize = 12;
+const INET6_ADDR_LEN: usize = 36;
+const UNIX_ADDR_LEN: usize = 216;
+
+pub const PP2_TYPE_ALPN: u8 = 0x01;
+pub const PP2_TYPE_AUTHORITY: u8 = 0x02;
+
 pub struct ProxyProtocolV2Reader {
     timeout: Duration,
     hdr_buf: [u8; PROXY_HDR_V2_LEN],
     data_buf: Box<[u8; PROXY_DATA_V2_MAX_LEN]>,
+    tlv_offset: usize,
+    data_len: usize,
 }
 
 impl ProxyProtocolV2Reader {
@@ -39,6 +48,8 @@ impl ProxyProtocolV2Reader {
             timeout,
             hdr_buf: Default::default(),
             data_buf: Box::new([0u8; PROXY_DATA_V2_MAX_LEN]),
+            tlv_offset: 0,
+            data_len: 0,
         }
     }
 
@@ -54,6 +65,14 @@ impl ProxyProtocolV2Reader {
             Ok(Err(e)) => return Err(e),
             Err(_) => return Err(ProxyProtocolReadError::ReadTimeout),
         };
+        self.data_len = data_len;
+        self.tlv_offset = match self.family() {
+            FAMILY_INET => INET_ADDR_LEN,
+            FAMILY_INET6 => INET6_ADDR_LEN,
+            FAMILY_UNIX => UNIX_ADDR_LEN,
+            _ => 0,
+        }
+        .min(data_len);
 
         match self.command() {
             COMMAND_PROXY => {}
@@ -84,11 +103,11 @@ impl ProxyProtocolV2Reader {
     }
 
     fn get_inet_addr(&self, data_len: usize) -> Result<ProxyAddr, ProxyProtocolReadError> {
-        if data_len < 12 {
+        if data_len < INET_ADDR_LEN {
             return Err(ProxyProtocolReadError::InvalidDataLength(data_len));
         }
 
-        let b = &self.data_buf[0..12];
+        let b = &self.data_buf[0..INET_ADDR_LEN];
         let src_addr = Ipv4Addr::from([b[0], b[1], b[2], b[3]]);
         let dst_addr = Ipv4Addr::from([b[4], b[5], b[6], b[7]]);
         let src_port = u16::from_be_bytes([b[8], b[9]]);
@@ -101,11 +120,11 @@ impl ProxyProtocolV2Reader {
     }
 
     fn get_inet6_addr(&self, data_len: usize) -> Result<ProxyAddr, ProxyProtocolReadError> {
-        if data_len < 36 {
+        if data_len < INET6_ADDR_LEN {
             return Err(ProxyProtocolReadError::InvalidDataLength(data_len));
         }
 
-        let b = &self.data_buf[0..36];
+        let b = &self.data_buf[0..INET6_ADDR_LEN];
         let src_addr = Ipv6Addr::from([
             b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7], b[8], b[9], b[10], b[11], b[12], b[13],
             b[14], b[15],
@@ -123,6 +142,25 @@ impl ProxyProtocolV2Reader {
         })
     }
 
+    /// Get the value of the first TLV with type `tlv_type` in the last read header
+    ///
+    /// The TLVs after a malformed one will be ignored.
+    pub fn tlv(&self, tlv_type: u8) -> Option<&[u8]> {
+        let mut left = &self.data_buf[self.tlv_offset..self.data_len];
+        while left.len() >= 3 {
+            let len = u16::from_be_bytes([left[1], left[2]]) as usize;
+            let end = 3 + len;
+            if left.len() < end {
+                return None;
+            }
+            if left[0] == tlv_type {
+                return Some(&left[3..end]);
+            }
+            left = &left[end..];
+        }
+        None
+    }
+
     async fn read_in_data<R>(&mut self, reader: &mut R) -> Result<usize, ProxyProtocolReadError>
     where
         R: AsyncRead + Unpin,
@@ -178,7 +216,7 @@ impl ProxyProtocolV2Reader {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use g3_types::net::{ProxyProtocolEncoder, ProxyProtocolVersion};
+    use g3_types::net::{ProxyProtocolEncoder, ProxyProtocolV2Encoder, ProxyProtocolVersion};
     use std::str::FromStr;
 
     async fn run_t(client: SocketAddr, server: SocketAddr) {
@@ -212,4 +250,34 @@ mod tests {
 
         run_t(client, server).await;
     }
+
+    #[tokio::test]
+    async fn t_tlv() {
+        let client = SocketAddr::from_str("192.168.0.1:56324").unwrap();
+        let server = SocketAddr::from_str("192.168.0.11:443").unwrap();
+
+        let mut encoder = ProxyProtocolV2Encoder::new_tcp(client, server).unwrap();
+        encoder.push_tlv(0xE0, b"custom").unwrap();
+        encoder
+            .push_tlv(PP2_TYPE_AUTHORITY, b"www.example.net")
+            .unwrap();
+        encoder.push_tlv(PP2_TYPE_ALPN, b"h2").unwrap();
+        let encoded = encoder.finalize();
+
+        let mut stream = tokio_test::io::Builder::new().read(encoded).build();
+
+        let mut reader = ProxyProtocolV2Reader::new(Duration::from_secs(1));
+        let addr = reader
+            .read_proxy_protocol_v2_for_tcp(&mut stream)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(addr.src_addr, client);
+        assert_eq!(
+            reader.tlv(PP2_TYPE_AUTHORITY),
+            Some(b"www.example.net".as_slice())
+        );
+        assert_eq!(reader.tlv(PP2_TYPE_ALPN), Some(b"h2".as_slice()));
+        assert!(reader.tlv(0x05).is_none());
+    }
 }
@@ -6,6 +6,7 @@
 mod cache;
 mod limit;
 mod listen;
+mod stats;
 mod stream;
 mod time;
 mod udp;
@@ -16,6 +17,7 @@ pub use cache::{
 };
 pub use limit::*;
 pub use listen::*;
+pub use stats::{io_stats_flush_interval, set_io_stats_flush_interval};
 pub use stream::*;
 pub use time::*;
 pub use udp::*;
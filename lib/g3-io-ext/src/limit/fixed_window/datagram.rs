@@ -61,12 +61,12 @@ impl LocalDatagramLimiter {
         }
 
         // do packet limit first. The first packet will always pass.
-        if self.max_packets > 0 && self.cur_packets > self.max_packets {
+        if self.max_packets > 0 && self.cur_packets >= self.max_packets {
             return DatagramLimitAction::DelayFor(self.window.delay(cur_millis));
         }
 
         // always allow the first packet to pass
-        if self.max_bytes > 0 && self.cur_bytes > 0 && self.cur_bytes + buf_size >= self.max_bytes {
+        if self.max_bytes > 0 && self.cur_bytes > 0 && self.cur_bytes + buf_size > self.max_bytes {
             return DatagramLimitAction::DelayFor(self.window.delay(cur_millis));
         }
         // the real advance size should be set via set_advance_size() method by caller
@@ -115,6 +115,12 @@ impl LocalDatagramLimiter {
                 Err(insert_index) => insert_index,
             };
         }
+
+        // the whole batch should wait for the next window if the budget is not enough,
+        // the batch will only be split if it is larger than the budget of a whole window
+        if pkt_count < total_size_v.len() && self.cur_packets > 0 {
+            return DatagramLimitAction::DelayFor(self.window.delay(cur_millis));
+        }
         // the real advance size should be set via set_advance_size() method by caller
 
         DatagramLimitAction::Advance(pkt_count)
@@ -126,3 +132,62 @@ impl LocalDatagramLimiter {
         self.cur_bytes += size;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_packet() {
+        let mut limit = LocalDatagramLimiter::new(10, 2, 1000);
+        assert_eq!(limit.check_packet(0, 600), DatagramLimitAction::Advance(1));
+        limit.set_advance(1, 600);
+        assert_eq!(limit.check_packet(10, 400), DatagramLimitAction::Advance(1));
+        limit.set_advance(1, 400);
+        // packet limit reached
+        assert_eq!(
+            limit.check_packet(20, 1),
+            DatagramLimitAction::DelayFor(1004)
+        );
+        // new time slice, the first packet always pass
+        assert_eq!(
+            limit.check_packet(1024, 1200),
+            DatagramLimitAction::Advance(1)
+        );
+        limit.set_advance(1, 1200);
+        assert_eq!(
+            limit.check_packet(1030, 1),
+            DatagramLimitAction::DelayFor(1018)
+        );
+    }
+
+    #[test]
+    fn whole_batch() {
+        let mut limit = LocalDatagramLimiter::new(10, 0, 1000);
+        assert_eq!(
+            limit.check_packets(0, &[200, 400, 600]),
+            DatagramLimitAction::Advance(3)
+        );
+        limit.set_advance(3, 600);
+        // only part of the batch fits, the whole batch should wait
+        assert_eq!(
+            limit.check_packets(10, &[200, 400, 600]),
+            DatagramLimitAction::DelayFor(1014)
+        );
+        assert_eq!(
+            limit.check_packets(20, &[200, 400]),
+            DatagramLimitAction::Advance(2)
+        );
+        limit.set_advance(2, 400);
+        // batch larger than the window budget will be split in a new time slice
+        assert_eq!(
+            limit.check_packets(1024, &[600, 1200, 1800]),
+            DatagramLimitAction::Advance(1)
+        );
+        limit.set_advance(1, 600);
+        assert_eq!(
+            limit.check_packets(1030, &[600, 1200]),
+            DatagramLimitAction::DelayFor(1018)
+        );
+    }
+}
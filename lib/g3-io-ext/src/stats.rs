@@ -0,0 +1,120 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+static IO_STATS_FLUSH_INTERVAL_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// Set the default interval for flushing the locally buffered io counts into the shared stats
+///
+/// It only takes effect for io objects created after this call. The counts will be written
+/// through at each io if set to zero, which is the default.
+pub fn set_io_stats_flush_interval(interval: Duration) {
+    let micros = u64::try_from(interval.as_micros()).unwrap_or(u64::MAX);
+    IO_STATS_FLUSH_INTERVAL_MICROS.store(micros, Ordering::Relaxed);
+}
+
+pub fn io_stats_flush_interval() -> Duration {
+    Duration::from_micros(IO_STATS_FLUSH_INTERVAL_MICROS.load(Ordering::Relaxed))
+}
+
+/// The io counts buffered in the owner of an io object
+///
+/// The shared stats are usually updated by many tasks on different threads, so updating them
+/// at each io will make the cache lines bounce between cpu cores. With a non-zero flush interval,
+/// the counts will be accumulated here and be flushed into the shared stats:
+///
+///  - at the first io after the flush interval has elapsed
+///  - when the io object becomes idle, i.e. the read / recv returns pending
+///  - when the writer is flushed or shutdown
+///  - when the io object is dropped
+///
+/// So the shared stats are still monotonic, and they will be exact after the io object is dropped.
+pub(crate) struct BufferedIoCount {
+    interval: Duration,
+    last_flush: Instant,
+    packets: usize,
+    bytes: usize,
+}
+
+impl Default for BufferedIoCount {
+    fn default() -> Self {
+        BufferedIoCount::new(io_stats_flush_interval())
+    }
+}
+
+impl BufferedIoCount {
+    pub(crate) fn new(interval: Duration) -> Self {
+        BufferedIoCount {
+            interval,
+            last_flush: Instant::now(),
+            packets: 0,
+            bytes: 0,
+        }
+    }
+
+    pub(crate) fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Add the counts of a finished io, and return the ones that should be flushed now
+    #[inline]
+    pub(crate) fn add(&mut self, packets: usize, bytes: usize) -> Option<(usize, usize)> {
+        if self.interval.is_zero() {
+            return Some((packets, bytes));
+        }
+
+        self.packets += packets;
+        self.bytes += bytes;
+        let now = Instant::now();
+        if now.saturating_duration_since(self.last_flush) >= self.interval {
+            self.last_flush = now;
+            self.take()
+        } else {
+            None
+        }
+    }
+
+    /// Take out all the buffered counts
+    pub(crate) fn take(&mut self) -> Option<(usize, usize)> {
+        if self.packets == 0 && self.bytes == 0 {
+            return None;
+        }
+        let packets = std::mem::take(&mut self.packets);
+        let bytes = std::mem::take(&mut self.bytes);
+        Some((packets, bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_through() {
+        let mut count = BufferedIoCount::new(Duration::ZERO);
+        assert_eq!(count.add(1, 100), Some((1, 100)));
+        assert_eq!(count.add(0, 0), Some((0, 0)));
+        assert!(count.take().is_none());
+    }
+
+    #[test]
+    fn buffered() {
+        let mut count = BufferedIoCount::new(Duration::from_millis(10));
+        assert!(count.add(1, 100).is_none());
+        assert!(count.add(2, 200).is_none());
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(count.add(1, 100), Some((4, 400)));
+        assert!(count.take().is_none());
+
+        assert!(count.add(1, 10).is_none());
+        assert_eq!(count.take(), Some((1, 10)));
+        assert!(count.take().is_none());
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker, ready};
+
+use openssl::sha::Sha256;
+use pin_project_lite::pin_project;
+use tokio::io::{AsyncBufRead, AsyncRead, ReadBuf};
+
+pub const SHA256_DIGEST_LENGTH: usize = 32;
+
+pin_project! {
+    /// A reader that updates a SHA-256 context with all the data read or consumed through it
+    pub struct Sha256BufReader<R> {
+        #[pin]
+        inner: R,
+        hasher: Sha256,
+        hashed_bytes: u64,
+        broken: bool,
+    }
+}
+
+impl<R> Sha256BufReader<R> {
+    pub fn new(inner: R) -> Self {
+        Sha256BufReader {
+            inner,
+            hasher: Sha256::new(),
+            hashed_bytes: 0,
+            broken: false,
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Get the number of bytes that have been hashed
+    pub fn hashed_bytes(&self) -> u64 {
+        self.hashed_bytes
+    }
+
+    /// Finish the hashing and return the digest with the number of hashed bytes.
+    ///
+    /// The returned digest will be `None` if some consumed data can not be hashed.
+    pub fn finish(self) -> (Option<[u8; SHA256_DIGEST_LENGTH]>, u64) {
+        if self.broken {
+            (None, self.hashed_bytes)
+        } else {
+            (Some(self.hasher.finish()), self.hashed_bytes)
+        }
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for Sha256BufReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let me = self.project();
+        let filled = buf.filled().len();
+        ready!(me.inner.poll_read(cx, buf))?;
+        let data = &buf.filled()[filled..];
+        me.hasher.update(data);
+        *me.hashed_bytes += data.len() as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncBufRead> AsyncBufRead for Sha256BufReader<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.project().inner.poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        if amt == 0 {
+            return;
+        }
+        let mut me = self.project();
+        // the data to be consumed has already been returned by the previous poll_fill_buf call,
+        // so we can get it again from the internal buffer without doing any real IO
+        let mut cx = Context::from_waker(Waker::noop());
+        match me.inner.as_mut().poll_fill_buf(&mut cx) {
+            Poll::Ready(Ok(buf)) if buf.len() >= amt => {
+                me.hasher.update(&buf[..amt]);
+                *me.hashed_bytes += amt as u64;
+            }
+            _ => *me.broken = true,
+        }
+        me.inner.consume(amt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+    const TEST_BODY: &[u8] = b"The quick brown fox jumps over the lazy dog";
+    const TEST_BODY_DIGEST: [u8; SHA256_DIGEST_LENGTH] =
+        hex_literal::hex!("d7a8fbb307d7809469ca9abcb0082e4f8d5651e46d3cdb762d02d0bf37c9e592");
+
+    #[tokio::test]
+    async fn read() {
+        let stream = tokio_test::io::Builder::new()
+            .read(&TEST_BODY[..10])
+            .read(&TEST_BODY[10..])
+            .build();
+        let mut reader = Sha256BufReader::new(stream);
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, TEST_BODY);
+
+        let (digest, len) = reader.finish();
+        assert_eq!(digest, Some(TEST_BODY_DIGEST));
+        assert_eq!(len, TEST_BODY.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn consume() {
+        let mut content = TEST_BODY.to_vec();
+        content.extend_from_slice(b"trailing data");
+        let stream = tokio_test::io::Builder::new().read(&content).build();
+        let mut buf_reader = BufReader::with_capacity(8, stream);
+
+        let mut reader = Sha256BufReader::new(&mut buf_reader);
+        let mut left = TEST_BODY.len();
+        while left > 0 {
+            let buf = reader.fill_buf().await.unwrap();
+            let amt = buf.len().min(left).min(5);
+            reader.consume(amt);
+            left -= amt;
+        }
+        let (digest, len) = reader.finish();
+        assert_eq!(digest, Some(TEST_BODY_DIGEST));
+        assert_eq!(len, TEST_BODY.len() as u64);
+
+        // the data left should not be hashed
+        let mut left = Vec::new();
+        buf_reader.read_to_end(&mut left).await.unwrap();
+        assert_eq!(left, b"trailing data");
+    }
+
+    #[tokio::test]
+    async fn partial() {
+        let stream = tokio_test::io::Builder::new()
+            .read(&TEST_BODY[..10])
+            .read_error(io::Error::other("reset"))
+            .build();
+        let mut reader = Sha256BufReader::new(BufReader::new(stream));
+        let mut buf = Vec::new();
+        assert!(reader.read_to_end(&mut buf).await.is_err());
+        let (digest, len) = reader.finish();
+        assert_ne!(digest, Some(TEST_BODY_DIGEST));
+        assert_eq!(len, 10);
+    }
+}
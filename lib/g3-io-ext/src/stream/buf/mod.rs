@@ -15,4 +15,9 @@ pub use once::OnceBufReader;
 mod copy;
 pub use copy::BufReadCopy;
 
+#[cfg(feature = "openssl")]
+mod digest;
+#[cfg(feature = "openssl")]
+pub use digest::{SHA256_DIGEST_LENGTH, Sha256BufReader};
+
 const DEFAULT_BUF_SIZE: usize = 8 * 1024;
@@ -3,13 +3,18 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::fmt;
 use std::io;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
 
 use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
 
+use super::{CopyMemoryAccountant, CopyMemoryPermit};
+
 const DEFAULT_COPY_BUFFER_SIZE: usize = 16 * 1024; // 16KB
 const MINIMAL_COPY_BUFFER_SIZE: usize = 4 * 1024; // 4KB
 const MINIMAL_READ_BUFFER_SIZE: usize = 256; // 256B
@@ -59,7 +64,19 @@ pub enum StreamCopyError {
     WriteFailed(io::Error),
 }
 
-#[derive(Debug)]
+/// Record the transfer when a stream copy finished successfully
+pub trait StreamCopyRecorder {
+    /// `first_byte` is the time from the start of the copy to the first successful read,
+    /// which will be None if no data has been read.
+    fn record_transfer(&self, size: u64, first_byte: Option<Duration>, total: Duration);
+}
+pub type ArcStreamCopyRecorder = Arc<dyn StreamCopyRecorder + Send + Sync>;
+
+/// Get the number of bytes that can still be read out from the reader, if known.
+///
+/// `Some(0)` should only be returned if the reader has reached its end.
+pub type StreamCopyRemainingHint<R> = fn(&R) -> Option<u64>;
+
 struct StreamCopyBuffer {
     read_done: bool,
     buf: Box<[u8]>,
@@ -69,31 +86,75 @@ struct StreamCopyBuffer {
     total_read: u64,
     total_write: u64,
     need_flush: bool,
+    flush_on_finish: bool,
     active: bool,
+    write_blocked: bool,
+    poll_start_write: u64,
+    create_time: Instant,
+    first_read_time: Option<Instant>,
+    recorder: Option<ArcStreamCopyRecorder>,
+    _memory: CopyMemoryPermit,
+}
+
+impl fmt::Debug for StreamCopyBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamCopyBuffer")
+            .field("read_done", &self.read_done)
+            .field("buf_size", &self.buf.len())
+            .field("yield_size", &self.yield_size)
+            .field("r_off", &self.r_off)
+            .field("w_off", &self.w_off)
+            .field("total_read", &self.total_read)
+            .field("total_write", &self.total_write)
+            .field("need_flush", &self.need_flush)
+            .field("flush_on_finish", &self.flush_on_finish)
+            .field("active", &self.active)
+            .field("write_blocked", &self.write_blocked)
+            .finish_non_exhaustive()
+    }
 }
 
 impl StreamCopyBuffer {
     fn new(config: &StreamCopyConfig) -> Self {
+        Self::with_accountant(config, super::global_copy_memory())
+    }
+
+    fn with_accountant(
+        config: &StreamCopyConfig,
+        accountant: &'static CopyMemoryAccountant,
+    ) -> Self {
+        // the buffer size may be downgraded if the copy memory budget is exhausted
+        let memory = accountant.acquire(config.buffer_size);
         StreamCopyBuffer {
             read_done: false,
-            buf: vec![0; config.buffer_size].into_boxed_slice(),
+            buf: vec![0; memory.size()].into_boxed_slice(),
             yield_size: config.yield_size,
             r_off: 0,
             w_off: 0,
             total_read: 0,
             total_write: 0,
             need_flush: false,
+            flush_on_finish: true,
             active: false,
+            write_blocked: false,
+            poll_start_write: 0,
+            create_time: Instant::now(),
+            first_read_time: None,
+            recorder: None,
+            _memory: memory,
         }
     }
 
     fn with_data(config: &StreamCopyConfig, mut buf: Vec<u8>) -> Self {
         let r_off = buf.len();
-        if buf.capacity() < config.buffer_size {
-            buf.resize(config.buffer_size, 0);
+        let create_time = Instant::now();
+        let mut memory = super::global_copy_memory().acquire(config.buffer_size);
+        if buf.capacity() < memory.size() {
+            buf.resize(memory.size(), 0);
         } else {
             buf.resize(buf.capacity(), 0);
         }
+        memory.set_size(buf.len());
         StreamCopyBuffer {
             read_done: false,
             buf: buf.into_boxed_slice(),
@@ -103,7 +164,14 @@ impl StreamCopyBuffer {
             total_read: 0,
             total_write: 0,
             need_flush: false,
+            flush_on_finish: true,
             active: true, // as we have data
+            write_blocked: false,
+            poll_start_write: 0,
+            create_time,
+            first_read_time: Some(create_time),
+            recorder: None,
+            _memory: memory,
         }
     }
 
@@ -111,11 +179,18 @@ impl StreamCopyBuffer {
         &mut self,
         cx: &mut Context<'_>,
         reader: Pin<&mut R>,
+        remaining_hint: Option<StreamCopyRemainingHint<R>>,
     ) -> Poll<io::Result<()>>
     where
         R: AsyncRead + ?Sized,
     {
-        let mut read_buf = ReadBuf::new(&mut self.buf[self.r_off..]);
+        let mut read_end = self.buf.len();
+        if let Some(remaining) = remaining_hint.and_then(|f| f(&*reader)) {
+            // never ask for more than what's left in the reader
+            let remaining = usize::try_from(remaining).unwrap_or(usize::MAX);
+            read_end = read_end.min(self.r_off.saturating_add(remaining));
+        }
+        let mut read_buf = ReadBuf::new(&mut self.buf[self.r_off..read_end]);
         let res = reader.poll_read(cx, &mut read_buf);
         if let Poll::Ready(Ok(_)) = res {
             let nr = read_buf.filled().len();
@@ -125,6 +200,9 @@ impl StreamCopyBuffer {
                 self.r_off += nr;
                 self.total_read += nr as u64;
                 self.active = true;
+                if self.first_read_time.is_none() {
+                    self.first_read_time = Some(Instant::now());
+                }
             }
         }
         res
@@ -149,12 +227,15 @@ impl StreamCopyBuffer {
         cx: &mut Context<'_>,
         reader: Pin<&mut R>,
         writer: Pin<&mut W>,
+        remaining_hint: Option<StreamCopyRemainingHint<R>>,
     ) -> Poll<Result<usize, StreamCopyError>>
     where
         R: AsyncRead + ?Sized,
         W: AsyncWrite + ?Sized,
     {
-        match writer.poll_write(cx, &self.buf[self.w_off..self.r_off]) {
+        let r = writer.poll_write(cx, &self.buf[self.w_off..self.r_off]);
+        self.write_blocked = r.is_pending();
+        match r {
             Poll::Pending => {
                 // Top up the buffer towards full if we can read a bit more
                 // data - this should improve the chances of a large write
@@ -162,7 +243,7 @@ impl StreamCopyBuffer {
                     self.check_move_cache();
                     if self.r_off + MINIMAL_READ_BUFFER_SIZE <= self.buf.len() {
                         // avoid too small read
-                        ready!(self.poll_fill_buf(cx, reader))
+                        ready!(self.poll_fill_buf(cx, reader, remaining_hint))
                             .map_err(StreamCopyError::ReadFailed)?;
                     }
                 }
@@ -188,11 +269,13 @@ impl StreamCopyBuffer {
         cx: &mut Context<'_>,
         mut reader: Pin<&mut R>,
         mut writer: Pin<&mut W>,
+        remaining_hint: Option<StreamCopyRemainingHint<R>>,
     ) -> Poll<Result<u64, StreamCopyError>>
     where
         R: AsyncRead + ?Sized,
         W: AsyncWrite + ?Sized,
     {
+        self.poll_start_write = self.total_write;
         let mut copy_this_round = 0usize;
         loop {
             if !self.read_done {
@@ -207,7 +290,7 @@ impl StreamCopyBuffer {
                 }
                 if self.r_off < self.buf.len() {
                     // read first
-                    match self.poll_fill_buf(cx, reader.as_mut()) {
+                    match self.poll_fill_buf(cx, reader.as_mut(), remaining_hint) {
                         Poll::Ready(Ok(_)) => {}
                         Poll::Ready(Err(e)) => {
                             return Poll::Ready(Err(StreamCopyError::ReadFailed(e)));
@@ -232,16 +315,22 @@ impl StreamCopyBuffer {
             // If our buffer has some data, let's write it out!
             while self.w_off < self.r_off {
                 // return if write blocked. no need to try flush
-                let i = ready!(self.poll_write_buf(cx, reader.as_mut(), writer.as_mut()))?;
+                let i = ready!(self.poll_write_buf(
+                    cx,
+                    reader.as_mut(),
+                    writer.as_mut(),
+                    remaining_hint
+                ))?;
                 copy_this_round += i;
             }
 
             // If we've seen EOF and written all the data, flush out the
             // data and finish the transfer.
             if self.read_done {
-                if self.need_flush {
+                if self.need_flush && self.flush_on_finish {
                     ready!(writer.as_mut().poll_flush(cx)).map_err(StreamCopyError::WriteFailed)?;
                 }
+                self.record_finished();
                 return Poll::Ready(Ok(self.total_write));
             }
 
@@ -253,6 +342,20 @@ impl StreamCopyBuffer {
         }
     }
 
+    #[inline]
+    fn copied_since_last_poll(&self) -> u64 {
+        self.total_write - self.poll_start_write
+    }
+
+    fn record_finished(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            let first_byte = self
+                .first_read_time
+                .map(|t| t.duration_since(self.create_time));
+            recorder.record_transfer(self.total_write, first_byte, self.create_time.elapsed());
+        }
+    }
+
     pub async fn write_flush<W>(&mut self, writer: &mut W) -> Result<(), StreamCopyError>
     where
         W: AsyncWrite + Unpin + ?Sized,
@@ -337,11 +440,50 @@ where
         !self.buf.active
     }
 
+    /// Reset the active flag, which will be set again if any data is read or written in later
+    /// polls. This should be used in idle checks.
     #[inline]
     pub fn reset_active(&mut self) {
         self.buf.active = false;
     }
 
+    /// Poll the copy without going through the `Future` impl, so many copies can be driven
+    /// manually in a single task.
+    ///
+    /// The total copied size will be returned when all data has been copied and flushed.
+    /// The copy should not be polled again after it returned `Poll::Ready`.
+    pub fn poll_copy(&mut self, cx: &mut Context<'_>) -> Poll<Result<u64, StreamCopyError>> {
+        self.buf.poll_copy(
+            cx,
+            Pin::new(&mut *self.reader),
+            Pin::new(&mut *self.writer),
+            None,
+        )
+    }
+
+    /// Get the size of data written in the last call of `poll_copy`
+    #[inline]
+    pub fn copied_since_last_poll(&self) -> u64 {
+        self.buf.copied_since_last_poll()
+    }
+
+    /// Check if the reader has reached EOF, the cached data may still be pending to write
+    #[inline]
+    pub fn read_eof(&self) -> bool {
+        self.buf.read_done
+    }
+
+    /// Check if the last write to the writer returned `Poll::Pending`
+    #[inline]
+    pub fn write_blocked(&self) -> bool {
+        self.buf.write_blocked
+    }
+
+    /// Set the recorder to be called when the copy finished successfully
+    pub fn set_recorder(&mut self, recorder: ArcStreamCopyRecorder) {
+        self.buf.recorder = Some(recorder);
+    }
+
     pub async fn write_flush(&mut self) -> Result<(), StreamCopyError> {
         self.buf.write_flush(&mut self.writer).await
     }
@@ -355,30 +497,35 @@ where
     type Output = Result<u64, StreamCopyError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<u64, StreamCopyError>> {
-        let me = &mut *self;
-
-        me.buf
-            .poll_copy(cx, Pin::new(&mut *me.reader), Pin::new(&mut *me.writer))
+        self.poll_copy(cx)
     }
 }
 
+/// Stream copy which owns both the reader and the writer.
+///
+/// The writer may also be a mutable reference, see [`ROwnedStreamCopy`].
 #[derive(Debug)]
-pub struct ROwnedStreamCopy<'a, R, W: ?Sized> {
+pub struct OwnedStreamCopy<R, W> {
     reader: R,
-    writer: &'a mut W,
+    writer: W,
     buf: StreamCopyBuffer,
+    remaining_hint: Option<StreamCopyRemainingHint<R>>,
 }
 
-impl<'a, R, W> ROwnedStreamCopy<'a, R, W>
+/// Stream copy which owns the reader and borrows the writer
+pub type ROwnedStreamCopy<'a, R, W> = OwnedStreamCopy<R, &'a mut W>;
+
+impl<R, W> OwnedStreamCopy<R, W>
 where
     R: AsyncRead + Unpin,
-    W: AsyncWrite + Unpin + ?Sized,
+    W: AsyncWrite + Unpin,
 {
-    pub fn new(reader: R, writer: &'a mut W, config: StreamCopyConfig) -> Self {
-        ROwnedStreamCopy {
+    pub fn new(reader: R, writer: W, config: StreamCopyConfig) -> Self {
+        OwnedStreamCopy {
             reader,
             writer,
             buf: StreamCopyBuffer::new(&config),
+            remaining_hint: None,
         }
     }
 
@@ -407,31 +554,273 @@ where
         !self.buf.active
     }
 
+    /// Reset the active flag, which will be set again if any data is read or written in later
+    /// polls. This should be used in idle checks.
     #[inline]
     pub fn reset_active(&mut self) {
         self.buf.active = false;
     }
 
+    /// Poll the copy without going through the `Future` impl, so many copies can be driven
+    /// manually in a single task.
+    ///
+    /// The total copied size will be returned when all data has been copied and flushed.
+    /// The copy should not be polled again after it returned `Poll::Ready`.
+    pub fn poll_copy(&mut self, cx: &mut Context<'_>) -> Poll<Result<u64, StreamCopyError>> {
+        self.buf.poll_copy(
+            cx,
+            Pin::new(&mut self.reader),
+            Pin::new(&mut self.writer),
+            self.remaining_hint,
+        )
+    }
+
+    /// Get the size of data written in the last call of `poll_copy`
+    #[inline]
+    pub fn copied_since_last_poll(&self) -> u64 {
+        self.buf.copied_since_last_poll()
+    }
+
+    /// Check if the reader has reached EOF, the cached data may still be pending to write
+    #[inline]
+    pub fn read_eof(&self) -> bool {
+        self.buf.read_done
+    }
+
+    /// Check if the last write to the writer returned `Poll::Pending`
+    #[inline]
+    pub fn write_blocked(&self) -> bool {
+        self.buf.write_blocked
+    }
+
+    /// Set the recorder to be called when the copy finished successfully
+    pub fn set_recorder(&mut self, recorder: ArcStreamCopyRecorder) {
+        self.buf.recorder = Some(recorder);
+    }
+
     pub async fn write_flush(&mut self) -> Result<(), StreamCopyError> {
         self.buf.write_flush(&mut self.writer).await
     }
 
-    pub fn writer(self) -> &'a mut W {
+    pub fn writer(self) -> W {
         self.writer
     }
+
+    pub fn get_ref(&self) -> (&R, &W) {
+        (&self.reader, &self.writer)
+    }
+
+    /// Get back the reader and the writer
+    pub fn into_parts(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+
+    /// Skip the flush of the writer after all data copied, the caller should do it instead
+    pub fn skip_flush_on_finish(&mut self) {
+        self.buf.flush_on_finish = false;
+    }
+
+    /// Set the function to get the remaining size of the reader, which will be used to limit
+    /// the size of each read
+    pub fn set_remaining_hint(&mut self, hint: StreamCopyRemainingHint<R>) {
+        self.remaining_hint = Some(hint);
+    }
 }
 
-impl<R, W> Future for ROwnedStreamCopy<'_, R, W>
+impl<R, W> Future for OwnedStreamCopy<R, W>
 where
     R: AsyncRead + Unpin,
-    W: AsyncWrite + Unpin + ?Sized,
+    W: AsyncWrite + Unpin,
 {
     type Output = Result<u64, StreamCopyError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<u64, StreamCopyError>> {
-        let me = &mut *self;
+        self.poll_copy(cx)
+    }
+}
 
-        me.buf
-            .poll_copy(cx, Pin::new(&mut me.reader), Pin::new(&mut *me.writer))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct TestRecorder {
+        records: Mutex<Vec<(u64, Option<Duration>, Duration)>>,
+    }
+
+    impl StreamCopyRecorder for TestRecorder {
+        fn record_transfer(&self, size: u64, first_byte: Option<Duration>, total: Duration) {
+            self.records.lock().unwrap().push((size, first_byte, total));
+        }
+    }
+
+    #[tokio::test]
+    async fn record_transfer() {
+        let recorder = Arc::new(TestRecorder::default());
+
+        let mut reader = tokio_test::io::Builder::new()
+            .wait(Duration::from_millis(10))
+            .read(b"test")
+            .read(b" body")
+            .build();
+        let mut writer = Vec::new();
+        let mut copy = StreamCopy::new(&mut reader, &mut writer, &Default::default());
+        copy.set_recorder(recorder.clone());
+        assert_eq!((&mut copy).await.unwrap(), 9);
+        assert_eq!(writer, b"test body");
+
+        let mut reader = tokio_test::io::Builder::new().build();
+        let mut writer = Vec::new();
+        let mut copy = ROwnedStreamCopy::new(&mut reader, &mut writer, Default::default());
+        copy.set_recorder(recorder.clone());
+        assert_eq!((&mut copy).await.unwrap(), 0);
+
+        let records = recorder.records.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        let (size, first_byte, total) = records[0];
+        assert_eq!(size, 9);
+        let first_byte = first_byte.unwrap();
+        assert!(first_byte >= Duration::from_millis(10));
+        assert!(total >= first_byte);
+        let (size, first_byte, _) = records[1];
+        assert_eq!(size, 0);
+        assert!(first_byte.is_none());
+    }
+
+    struct SizedReader {
+        data: Vec<u8>,
+        offset: usize,
+        asked: Vec<usize>,
+    }
+
+    impl SizedReader {
+        fn remaining(&self) -> Option<u64> {
+            Some((self.data.len() - self.offset) as u64)
+        }
+    }
+
+    impl AsyncRead for SizedReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            self.asked.push(buf.remaining());
+            let offset = self.offset;
+            let to_copy = buf.remaining().min(self.data.len() - offset);
+            buf.put_slice(&self.data[offset..offset + to_copy]);
+            self.offset += to_copy;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn remaining_hint() {
+        let reader = SizedReader {
+            data: vec![b'x'; 5000],
+            offset: 0,
+            asked: Vec::new(),
+        };
+        let mut writer = Vec::new();
+        let mut config = StreamCopyConfig::default();
+        config.set_buffer_size(4096);
+        let mut copy = ROwnedStreamCopy::new(reader, &mut writer, config);
+        copy.set_remaining_hint(SizedReader::remaining);
+        assert_eq!((&mut copy).await.unwrap(), 5000);
+        assert_eq!(copy.reader.asked, [4096, 904, 0]);
+        assert_eq!(writer.len(), 5000);
+    }
+
+    #[tokio::test]
+    async fn poll_round_robin() {
+        let mut reader1 = tokio_test::io::Builder::new()
+            .read(b"first")
+            .wait(Duration::from_millis(10))
+            .read(b" copy")
+            .build();
+        let mut writer1 = Vec::new();
+        let mut copy1 = StreamCopy::new(&mut reader1, &mut writer1, &Default::default());
+
+        let mut reader2 = tokio_test::io::Builder::new()
+            .read(b"second")
+            .read(b" copy data")
+            .build();
+        let mut writer2 = Vec::new();
+        let mut copy2 = ROwnedStreamCopy::new(&mut reader2, &mut writer2, Default::default());
+
+        let mut r1 = None;
+        let mut r2 = None;
+        let mut copied1 = 0;
+        let mut copied2 = 0;
+        std::future::poll_fn(|cx| {
+            if r1.is_none() {
+                let r = copy1.poll_copy(cx);
+                copied1 += copy1.copied_since_last_poll();
+                assert!(!copy1.write_blocked());
+                if let Poll::Ready(r) = r {
+                    r1 = Some(r.unwrap());
+                }
+            }
+            if r2.is_none() {
+                let r = copy2.poll_copy(cx);
+                copied2 += copy2.copied_since_last_poll();
+                if let Poll::Ready(r) = r {
+                    r2 = Some(r.unwrap());
+                }
+            }
+            if r1.is_some() && r2.is_some() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        assert_eq!(r1, Some(10));
+        assert_eq!(copied1, 10);
+        assert!(copy1.read_eof());
+        assert!(copy1.finished());
+        assert_eq!(r2, Some(16));
+        assert_eq!(copied2, 16);
+        assert!(copy2.read_eof());
+        assert!(copy2.finished());
+        drop(copy1);
+        drop(copy2);
+        assert_eq!(writer1, b"first copy");
+        assert_eq!(writer2, b"second copy data");
+    }
+
+    #[tokio::test]
+    async fn copy_memory() {
+        let accountant = Box::leak(Box::new(CopyMemoryAccountant::new()));
+        accountant.set_max_total(32 * 1024);
+        accountant.set_min_buffer_size(1024);
+
+        let config = StreamCopyConfig::default();
+        let mut bufs: Vec<StreamCopyBuffer> = (0..10)
+            .map(|_| StreamCopyBuffer::with_accountant(&config, accountant))
+            .collect();
+        assert_eq!(bufs[1].buf.len(), DEFAULT_COPY_BUFFER_SIZE);
+        assert_eq!(bufs[2].buf.len(), 1024);
+        assert_eq!(accountant.downgraded(), 8);
+        assert_eq!(accountant.used(), 2 * DEFAULT_COPY_BUFFER_SIZE + 8 * 1024);
+
+        // the memory should also be released if the copy failed
+        let mut reader = tokio_test::io::Builder::new()
+            .read(b"test")
+            .read_error(io::Error::other("test error"))
+            .build();
+        let mut writer = Vec::new();
+        let buf = &mut bufs[9];
+        let r = std::future::poll_fn(|cx| {
+            buf.poll_copy(cx, Pin::new(&mut reader), Pin::new(&mut writer), None)
+        })
+        .await;
+        assert!(matches!(r, Err(StreamCopyError::ReadFailed(_))));
+        assert_eq!(writer, b"test");
+
+        drop(bufs);
+        assert_eq!(accountant.used(), 0);
     }
 }
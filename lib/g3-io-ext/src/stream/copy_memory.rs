@@ -0,0 +1,268 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+const DEFAULT_MIN_BUFFER_SIZE: usize = 4 * 1024; // 4KB
+
+static GLOBAL_COPY_MEMORY: CopyMemoryAccountant = CopyMemoryAccountant::new();
+
+/// Get the daemon level accountant for the memory used by stream copy buffers
+pub fn global_copy_memory() -> &'static CopyMemoryAccountant {
+    &GLOBAL_COPY_MEMORY
+}
+
+/// What to do with new tasks if the copy memory budget is exhausted
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CopyMemoryExhaustedPolicy {
+    /// Allocate the copy buffers of new tasks with the minimum buffer size
+    #[default]
+    Downgrade,
+    /// Reject new connections at accept time
+    Reject,
+}
+
+impl CopyMemoryExhaustedPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CopyMemoryExhaustedPolicy::Downgrade => "downgrade",
+            CopyMemoryExhaustedPolicy::Reject => "reject",
+        }
+    }
+}
+
+/// Account the memory used by the stream copy buffers, with an optional total limit
+pub struct CopyMemoryAccountant {
+    used: AtomicUsize,
+    max_total: AtomicUsize,
+    min_buffer_size: AtomicUsize,
+    reject: AtomicBool,
+    downgraded: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl Default for CopyMemoryAccountant {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CopyMemoryAccountant {
+    pub const fn new() -> Self {
+        CopyMemoryAccountant {
+            used: AtomicUsize::new(0),
+            max_total: AtomicUsize::new(0),
+            min_buffer_size: AtomicUsize::new(DEFAULT_MIN_BUFFER_SIZE),
+            reject: AtomicBool::new(false),
+            downgraded: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Set the max total copy memory, 0 means no limit
+    pub fn set_max_total(&self, max_total: usize) {
+        self.max_total.store(max_total, Ordering::Relaxed);
+    }
+
+    /// Set the buffer size to fall back to if the budget is exhausted
+    pub fn set_min_buffer_size(&self, size: usize) {
+        self.min_buffer_size.store(size, Ordering::Relaxed);
+    }
+
+    pub fn set_exhausted_policy(&self, policy: CopyMemoryExhaustedPolicy) {
+        self.reject.store(
+            policy == CopyMemoryExhaustedPolicy::Reject,
+            Ordering::Relaxed,
+        );
+    }
+
+    /// The total size of the copy buffers that are still alive
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// How many copy buffers have been allocated with the minimum buffer size
+    #[inline]
+    pub fn downgraded(&self) -> u64 {
+        self.downgraded.load(Ordering::Relaxed)
+    }
+
+    /// How many connections have been rejected at accept time
+    #[inline]
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    fn exhausted(&self, used: usize) -> bool {
+        let max_total = self.max_total.load(Ordering::Relaxed);
+        max_total > 0 && used > max_total
+    }
+
+    /// Check if a new connection can be accepted.
+    ///
+    /// This will always return true unless the reject policy is in use and the budget is exhausted.
+    pub fn check_accept(&self) -> bool {
+        if !self.reject.load(Ordering::Relaxed) {
+            return true;
+        }
+        let max_total = self.max_total.load(Ordering::Relaxed);
+        if max_total > 0 && self.used() >= max_total {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Register a copy buffer of `size`, the returned permit will have a smaller size if it's
+    /// downgraded.
+    ///
+    /// The tasks that have already been accepted will always get the full size with the reject
+    /// policy, so the budget may be exceeded by the tasks in the accept queue.
+    pub fn acquire(&'static self, size: usize) -> CopyMemoryPermit {
+        let used = self.used.fetch_add(size, Ordering::Relaxed) + size;
+        if !self.exhausted(used) || self.reject.load(Ordering::Relaxed) {
+            return CopyMemoryPermit {
+                accountant: self,
+                size,
+            };
+        }
+
+        let min_size = self.min_buffer_size.load(Ordering::Relaxed).min(size);
+        if min_size < size {
+            self.used.fetch_sub(size - min_size, Ordering::Relaxed);
+            self.downgraded.fetch_add(1, Ordering::Relaxed);
+        }
+        CopyMemoryPermit {
+            accountant: self,
+            size: min_size,
+        }
+    }
+}
+
+/// The registered size of a copy buffer, which will be released on drop
+pub struct CopyMemoryPermit {
+    accountant: &'static CopyMemoryAccountant,
+    size: usize,
+}
+
+impl CopyMemoryPermit {
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Update the registered size to the real size of an already allocated buffer
+    pub fn set_size(&mut self, size: usize) {
+        if size > self.size {
+            self.accountant
+                .used
+                .fetch_add(size - self.size, Ordering::Relaxed);
+        } else {
+            self.accountant
+                .used
+                .fetch_sub(self.size - size, Ordering::Relaxed);
+        }
+        self.size = size;
+    }
+}
+
+impl Drop for CopyMemoryPermit {
+    fn drop(&mut self) {
+        self.accountant.used.fetch_sub(self.size, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_accountant(
+        max_total: usize,
+        policy: CopyMemoryExhaustedPolicy,
+    ) -> &'static CopyMemoryAccountant {
+        let accountant = Box::leak(Box::new(CopyMemoryAccountant::new()));
+        accountant.set_max_total(max_total);
+        accountant.set_min_buffer_size(1024);
+        accountant.set_exhausted_policy(policy);
+        accountant
+    }
+
+    #[test]
+    fn downgrade() {
+        let accountant = new_accountant(64 * 1024, CopyMemoryExhaustedPolicy::Downgrade);
+
+        let mut permits = Vec::new();
+        for _ in 0..100 {
+            assert!(accountant.check_accept());
+            permits.push(accountant.acquire(16 * 1024));
+        }
+        let full = permits.iter().filter(|p| p.size() == 16 * 1024).count();
+        assert_eq!(full, 4);
+        assert!(permits[4..].iter().all(|p| p.size() == 1024));
+        assert_eq!(accountant.downgraded(), 96);
+        assert_eq!(accountant.rejected(), 0);
+        assert_eq!(accountant.used(), 4 * 16 * 1024 + 96 * 1024);
+
+        // the full size will be used again after the budget is released
+        permits.truncate(2);
+        assert_eq!(accountant.acquire(16 * 1024).size(), 16 * 1024);
+
+        drop(permits);
+        assert_eq!(accountant.used(), 0);
+    }
+
+    #[test]
+    fn reject() {
+        let accountant = new_accountant(64 * 1024, CopyMemoryExhaustedPolicy::Reject);
+
+        let mut permits = Vec::new();
+        let mut rejected = 0;
+        for _ in 0..100 {
+            if accountant.check_accept() {
+                permits.push(accountant.acquire(16 * 1024));
+            } else {
+                rejected += 1;
+            }
+        }
+        assert_eq!(permits.len(), 4);
+        assert_eq!(rejected, 96);
+        assert_eq!(accountant.rejected(), 96);
+        assert_eq!(accountant.downgraded(), 0);
+
+        permits.pop();
+        assert!(accountant.check_accept());
+
+        drop(permits);
+        assert_eq!(accountant.used(), 0);
+    }
+
+    #[test]
+    fn no_limit() {
+        let accountant = new_accountant(0, CopyMemoryExhaustedPolicy::Reject);
+        let permits: Vec<_> = (0..100)
+            .map(|_| {
+                assert!(accountant.check_accept());
+                accountant.acquire(16 * 1024)
+            })
+            .collect();
+        assert_eq!(accountant.used(), 100 * 16 * 1024);
+        drop(permits);
+        assert_eq!(accountant.used(), 0);
+    }
+
+    #[test]
+    fn set_size() {
+        let accountant = new_accountant(0, CopyMemoryExhaustedPolicy::Downgrade);
+        let mut permit = accountant.acquire(4096);
+        permit.set_size(8192);
+        assert_eq!(accountant.used(), 8192);
+        permit.set_size(1024);
+        assert_eq!(accountant.used(), 1024);
+        drop(permit);
+        assert_eq!(accountant.used(), 0);
+    }
+}
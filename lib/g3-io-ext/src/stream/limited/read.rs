@@ -16,6 +16,7 @@ use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::time::{Instant, Sleep};
 
 use crate::limit::{GlobalLimitGroup, GlobalStreamLimit, StreamLimitAction, StreamLimiter};
+use crate::stats::BufferedIoCount;
 use crate::stream::AsyncStream;
 
 pub trait LimitedReaderStats {
@@ -35,6 +36,7 @@ pub(crate) struct LimitedReaderState {
     started: Instant,
     limit: StreamLimiter,
     stats: ArcLimitedReaderStats,
+    buffered: BufferedIoCount,
 }
 
 impl LimitedReaderState {
@@ -44,6 +46,7 @@ impl LimitedReaderState {
             started: Instant::now(),
             limit: StreamLimiter::default(),
             stats,
+            buffered: BufferedIoCount::default(),
         }
     }
 
@@ -57,6 +60,7 @@ impl LimitedReaderState {
             started: Instant::now(),
             limit: StreamLimiter::with_local(shift_millis, max_bytes),
             stats,
+            buffered: BufferedIoCount::default(),
         }
     }
 
@@ -73,9 +77,28 @@ impl LimitedReaderState {
     }
 
     pub(crate) fn reset_stats(&mut self, stats: ArcLimitedReaderStats) {
+        self.flush_stats();
         self.stats = stats;
     }
 
+    #[inline]
+    pub(crate) fn set_stats_flush_interval(&mut self, interval: Duration) {
+        self.buffered.set_interval(interval);
+    }
+
+    #[inline]
+    fn add_read_bytes(&mut self, size: usize) {
+        if let Some((_, size)) = self.buffered.add(0, size) {
+            self.stats.add_read_bytes(size);
+        }
+    }
+
+    pub(crate) fn flush_stats(&mut self) {
+        if let Some((_, size)) = self.buffered.take() {
+            self.stats.add_read_bytes(size);
+        }
+    }
+
     pub(crate) fn reset_local_limit(&mut self, shift_millis: u8, max_bytes: usize) {
         let dur_millis = self.started.elapsed().as_millis() as u64;
         self.limit.reset_local(shift_millis, max_bytes, dur_millis);
@@ -100,7 +123,7 @@ impl LimitedReaderState {
                             let nr = limited_buf.filled().len();
                             self.limit.set_advance(nr);
                             buf.advance(nr);
-                            self.stats.add_read_bytes(nr);
+                            self.add_read_bytes(nr);
                             Poll::Ready(Ok(()))
                         }
                         Poll::Ready(Err(e)) => {
@@ -109,6 +132,7 @@ impl LimitedReaderState {
                         }
                         Poll::Pending => {
                             self.limit.release_global();
+                            self.flush_stats();
                             Poll::Pending
                         }
                     }
@@ -138,14 +162,23 @@ impl LimitedReaderState {
             }
         } else {
             let old_filled_len = buf.filled().len();
-            ready!(reader.poll_read(cx, buf))?;
+            if reader.poll_read(cx, buf)?.is_pending() {
+                self.flush_stats();
+                return Poll::Pending;
+            }
             let nr = buf.filled().len() - old_filled_len;
-            self.stats.add_read_bytes(nr);
+            self.add_read_bytes(nr);
             Poll::Ready(Ok(()))
         }
     }
 }
 
+impl Drop for LimitedReaderState {
+    fn drop(&mut self) {
+        self.flush_stats();
+    }
+}
+
 pin_project! {
     pub struct LimitedReader<R> {
         #[pin]
@@ -195,6 +228,20 @@ impl<R> LimitedReader<R> {
         self.state.reset_stats(stats);
     }
 
+    /// Set the interval for flushing the buffered counts into the stats
+    ///
+    /// The default value is set by [crate::set_io_stats_flush_interval].
+    #[inline]
+    pub fn set_stats_flush_interval(&mut self, interval: Duration) {
+        self.state.set_stats_flush_interval(interval);
+    }
+
+    /// Flush the buffered counts into the stats
+    #[inline]
+    pub fn flush_stats(&mut self) {
+        self.state.flush_stats();
+    }
+
     #[inline]
     pub fn reset_local_limit(&mut self, shift_millis: u8, max_bytes: usize) {
         self.state.reset_local_limit(shift_millis, max_bytes);
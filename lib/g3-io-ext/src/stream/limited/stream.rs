@@ -8,6 +8,7 @@ use std::io::IoSlice;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use pin_project_lite::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
@@ -75,6 +76,20 @@ impl<S> LimitedStream<S> {
         self.writer_state.reset_stats(stats);
     }
 
+    /// Set the interval for flushing the buffered counts into the stats
+    ///
+    /// The default value is set by [crate::set_io_stats_flush_interval].
+    pub fn set_stats_flush_interval(&mut self, interval: Duration) {
+        self.reader_state.set_stats_flush_interval(interval);
+        self.writer_state.set_stats_flush_interval(interval);
+    }
+
+    /// Flush the buffered counts into the stats
+    pub fn flush_stats(&mut self) {
+        self.reader_state.flush_stats();
+        self.writer_state.flush_stats();
+    }
+
     pub fn reset_local_limit(
         &mut self,
         shift_millis: u8,
@@ -87,6 +102,10 @@ impl<S> LimitedStream<S> {
             .reset_local_limit(shift_millis, write_max_bytes);
     }
 
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
     pub fn into_inner(self) -> S {
         self.inner
     }
@@ -115,12 +134,16 @@ impl<W: AsyncWrite> AsyncWrite for LimitedStream<W> {
 
     #[inline]
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        self.project().inner.poll_flush(cx)
+        let this = self.project();
+        this.writer_state.flush_stats();
+        this.inner.poll_flush(cx)
     }
 
     #[inline]
     fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        self.project().inner.poll_shutdown(cx)
+        let this = self.project();
+        this.writer_state.flush_stats();
+        this.inner.poll_shutdown(cx)
     }
 
     fn poll_write_vectored(
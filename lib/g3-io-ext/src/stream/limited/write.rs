@@ -16,6 +16,7 @@ use tokio::io::AsyncWrite;
 use tokio::time::{Instant, Sleep};
 
 use crate::limit::{GlobalLimitGroup, GlobalStreamLimit, StreamLimitAction, StreamLimiter};
+use crate::stats::BufferedIoCount;
 
 pub trait LimitedWriterStats {
     fn add_write_bytes(&self, size: usize);
@@ -34,6 +35,7 @@ pub(crate) struct LimitedWriterState {
     started: Instant,
     limit: StreamLimiter,
     stats: ArcLimitedWriterStats,
+    buffered: BufferedIoCount,
 }
 
 impl LimitedWriterState {
@@ -43,6 +45,7 @@ impl LimitedWriterState {
             started: Instant::now(),
             limit: StreamLimiter::default(),
             stats,
+            buffered: BufferedIoCount::default(),
         }
     }
 
@@ -56,6 +59,7 @@ impl LimitedWriterState {
             started: Instant::now(),
             limit: StreamLimiter::with_local(shift_millis, max_bytes),
             stats,
+            buffered: BufferedIoCount::default(),
         }
     }
 
@@ -72,9 +76,28 @@ impl LimitedWriterState {
     }
 
     pub(crate) fn reset_stats(&mut self, stats: ArcLimitedWriterStats) {
+        self.flush_stats();
         self.stats = stats;
     }
 
+    #[inline]
+    pub(crate) fn set_stats_flush_interval(&mut self, interval: Duration) {
+        self.buffered.set_interval(interval);
+    }
+
+    #[inline]
+    fn add_write_bytes(&mut self, size: usize) {
+        if let Some((_, size)) = self.buffered.add(0, size) {
+            self.stats.add_write_bytes(size);
+        }
+    }
+
+    pub(crate) fn flush_stats(&mut self) {
+        if let Some((_, size)) = self.buffered.take() {
+            self.stats.add_write_bytes(size);
+        }
+    }
+
     pub(crate) fn reset_local_limit(&mut self, shift_millis: u8, max_bytes: usize) {
         let dur_millis = self.started.elapsed().as_millis() as u64;
         self.limit.reset_local(shift_millis, max_bytes, dur_millis);
@@ -100,7 +123,7 @@ impl LimitedWriterState {
                 StreamLimitAction::AdvanceBy(len) => match writer.poll_write(cx, &buf[..len]) {
                     Poll::Ready(Ok(nw)) => {
                         self.limit.set_advance(nw);
-                        self.stats.add_write_bytes(nw);
+                        self.add_write_bytes(nw);
                         Poll::Ready(Ok(nw))
                     }
                     Poll::Ready(Err(e)) => {
@@ -137,12 +160,18 @@ impl LimitedWriterState {
             }
         } else {
             let nw = ready!(writer.poll_write(cx, buf))?;
-            self.stats.add_write_bytes(nw);
+            self.add_write_bytes(nw);
             Poll::Ready(Ok(nw))
         }
     }
 }
 
+impl Drop for LimitedWriterState {
+    fn drop(&mut self) {
+        self.flush_stats();
+    }
+}
+
 pin_project! {
     pub struct LimitedWriter<W> {
         #[pin]
@@ -192,6 +221,20 @@ impl<W: AsyncWrite> LimitedWriter<W> {
         self.state.reset_stats(stats)
     }
 
+    /// Set the interval for flushing the buffered counts into the stats
+    ///
+    /// The default value is set by [crate::set_io_stats_flush_interval].
+    #[inline]
+    pub fn set_stats_flush_interval(&mut self, interval: Duration) {
+        self.state.set_stats_flush_interval(interval);
+    }
+
+    /// Flush the buffered counts into the stats
+    #[inline]
+    pub fn flush_stats(&mut self) {
+        self.state.flush_stats();
+    }
+
     #[inline]
     pub fn reset_local_limit(&mut self, shift_millis: u8, max_bytes: usize) {
         self.state.reset_local_limit(shift_millis, max_bytes)
@@ -214,12 +257,16 @@ impl<W: AsyncWrite> AsyncWrite for LimitedWriter<W> {
 
     #[inline]
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        self.project().inner.poll_flush(cx)
+        let this = self.project();
+        this.state.flush_stats();
+        this.inner.poll_flush(cx)
     }
 
     #[inline]
     fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        self.project().inner.poll_shutdown(cx)
+        let this = self.project();
+        this.state.flush_stats();
+        this.inner.poll_shutdown(cx)
     }
 
     fn poll_write_vectored(
@@ -246,3 +293,40 @@ impl<W: AsyncWrite> AsyncWrite for LimitedWriter<W> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use tokio::io::AsyncWriteExt;
+
+    #[derive(Default)]
+    struct WriteStats {
+        bytes: AtomicU64,
+    }
+
+    impl LimitedWriterStats for WriteStats {
+        fn add_write_bytes(&self, size: usize) {
+            self.bytes.fetch_add(size as u64, Ordering::Relaxed);
+        }
+    }
+
+    #[tokio::test]
+    async fn buffered_stats() {
+        let stats = Arc::new(WriteStats::default());
+        let mut writer = LimitedWriter::new(tokio::io::sink(), stats.clone());
+        writer.set_stats_flush_interval(Duration::from_secs(3600));
+
+        for _ in 0..100 {
+            writer.write_all(&[0u8; 1000]).await.unwrap();
+        }
+        assert_eq!(stats.bytes.load(Ordering::Relaxed), 0);
+        writer.flush().await.unwrap();
+        assert_eq!(stats.bytes.load(Ordering::Relaxed), 100_000);
+
+        writer.write_all(&[0u8; 1000]).await.unwrap();
+        drop(writer);
+        assert_eq!(stats.bytes.load(Ordering::Relaxed), 101_000);
+    }
+}
@@ -10,10 +10,20 @@ mod limited;
 pub use limited::*;
 
 mod copy;
-pub use copy::{ROwnedStreamCopy, StreamCopy, StreamCopyConfig, StreamCopyError};
+pub use copy::{
+    ArcStreamCopyRecorder, OwnedStreamCopy, ROwnedStreamCopy, StreamCopy, StreamCopyConfig,
+    StreamCopyError, StreamCopyRecorder, StreamCopyRemainingHint,
+};
+
+mod copy_memory;
+pub use copy_memory::{
+    CopyMemoryAccountant, CopyMemoryExhaustedPolicy, CopyMemoryPermit, global_copy_memory,
+};
 
 mod buf;
 pub use buf::{BufReadCopy, FlexBufReader, LimitedBufReader, OnceBufReader};
+#[cfg(feature = "openssl")]
+pub use buf::{SHA256_DIGEST_LENGTH, Sha256BufReader};
 
 mod line_recv_buf;
 pub use line_recv_buf::{LineRecvBuf, RecvLineError};
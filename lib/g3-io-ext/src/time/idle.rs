@@ -3,6 +3,7 @@
  * Copyright 2025 ByteDance and/or its affiliates.
  */
 
+use std::fmt;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -10,16 +11,37 @@ use tokio::time::{Instant, Interval};
 
 pub struct IdleWheel {
     interval: Duration,
+    jitter_percent: u8,
 }
 
 impl IdleWheel {
     pub fn spawn(interval: Duration) -> Arc<IdleWheel> {
-        Arc::new(IdleWheel { interval })
+        IdleWheel::spawn_with_jitter(interval, 0)
+    }
+
+    /// Spawn with a jitter percentage of the interval, which will be added randomly to the first
+    /// tick of each registered timer, so the timers of the tasks started at the same time will
+    /// not fire in lockstep. The percentage will be capped at 100.
+    pub fn spawn_with_jitter(interval: Duration, jitter_percent: u8) -> Arc<IdleWheel> {
+        Arc::new(IdleWheel {
+            interval,
+            jitter_percent: jitter_percent.min(100),
+        })
+    }
+
+    fn jitter_delay(&self) -> Duration {
+        if self.jitter_percent == 0 {
+            return Duration::ZERO;
+        }
+        let window = self.interval.as_nanos() * self.jitter_percent as u128 / 100;
+        let window = u64::try_from(window).unwrap_or(u64::MAX);
+        Duration::from_nanos(fastrand::u64(0..=window))
     }
 
     pub fn register(&self) -> IdleInterval {
+        let start = Instant::now() + self.interval + self.jitter_delay();
         IdleInterval {
-            interval: tokio::time::interval_at(Instant::now() + self.interval, self.interval),
+            interval: tokio::time::interval_at(start, self.interval),
         }
     }
 }
@@ -45,8 +67,220 @@ pub enum IdleForceQuitReason {
     ServerQuit,
 }
 
+/// The side which is found idle at an idle check tick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdleDirection {
+    ClientRead,
+    UpstreamRead,
+    IcapRead,
+    WriteBlocked,
+}
+
+impl IdleDirection {
+    const ALL: [IdleDirection; 4] = [
+        IdleDirection::ClientRead,
+        IdleDirection::UpstreamRead,
+        IdleDirection::IcapRead,
+        IdleDirection::WriteBlocked,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdleDirection::ClientRead => "ClientRead",
+            IdleDirection::UpstreamRead => "UpstreamRead",
+            IdleDirection::IcapRead => "IcapRead",
+            IdleDirection::WriteBlocked => "WriteBlocked",
+        }
+    }
+}
+
+impl fmt::Display for IdleDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Track the idle count, and how long each side has been idle
+#[derive(Default)]
+pub struct IdleTracker {
+    idle_count: usize,
+    direction_count: [usize; 4],
+}
+
+impl IdleTracker {
+    /// Record an idle check tick with the state of each side, `None` means the side is active.
+    ///
+    /// The idle count will be increased if all sides are idle, or reset if not. The idle ticks of
+    /// each idle side are recorded in both cases, so we can tell which one went idle first.
+    /// A side will be counted only once even if it is reported more than once.
+    ///
+    /// Return true if all sides are idle.
+    pub fn record(&mut self, n: usize, sides: &[Option<IdleDirection>]) -> bool {
+        let mut direction_count = [0; 4];
+        for d in sides.iter().flatten() {
+            let i = *d as usize;
+            direction_count[i] = self.direction_count[i] + n;
+        }
+        self.direction_count = direction_count;
+
+        if sides.iter().all(|s| s.is_some()) {
+            self.idle_count += n;
+            true
+        } else {
+            self.idle_count = 0;
+            false
+        }
+    }
+
+    #[inline]
+    pub fn idle_count(&self) -> usize {
+        self.idle_count
+    }
+
+    /// Get the side that has been idle for the most ticks, which is the one that went idle first.
+    /// The first one in the declaration order of [IdleDirection] will be returned for a tie.
+    pub fn dominant_direction(&self) -> Option<IdleDirection> {
+        let mut dominant: Option<IdleDirection> = None;
+        for d in IdleDirection::ALL {
+            let count = self.direction_count[d as usize];
+            if count == 0 {
+                continue;
+            }
+            match dominant {
+                Some(v) if self.direction_count[v as usize] >= count => {}
+                _ => dominant = Some(d),
+            }
+        }
+        dominant
+    }
+}
+
 pub trait IdleCheck {
     fn interval_timer(&self) -> IdleInterval;
     fn check_quit(&self, idle_count: usize) -> bool;
     fn check_force_quit(&self) -> Option<IdleForceQuitReason>;
+
+    /// Check if we should quit, and return the dominant idle side if so
+    fn check_quit_tracked(&self, tracker: &IdleTracker) -> Option<IdleDirection> {
+        if self.check_quit(tracker.idle_count()) {
+            tracker.dominant_direction()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_spread() {
+        let wheel = IdleWheel::spawn(Duration::from_secs(10));
+        assert_eq!(wheel.jitter_delay(), Duration::ZERO);
+
+        let wheel = IdleWheel::spawn_with_jitter(Duration::from_secs(10), 20);
+        let window = Duration::from_secs(2);
+        let mut min = window;
+        let mut max = Duration::ZERO;
+        for _ in 0..1000 {
+            let delay = wheel.jitter_delay();
+            assert!(delay <= window);
+            min = min.min(delay);
+            max = max.max(delay);
+        }
+        assert!(min < window / 4);
+        assert!(max > window * 3 / 4);
+
+        let wheel = IdleWheel::spawn_with_jitter(Duration::from_secs(1), 200);
+        assert_eq!(wheel.jitter_percent, 100);
+    }
+
+    #[tokio::test]
+    async fn jitter_tick() {
+        let wheel = IdleWheel::spawn_with_jitter(Duration::from_millis(20), 50);
+        let mut interval = wheel.register();
+        assert_eq!(interval.period(), Duration::from_millis(20));
+
+        let time_start = Instant::now();
+        assert_eq!(interval.tick().await, 1);
+        let elapsed = time_start.elapsed();
+        assert!(elapsed >= Duration::from_millis(20));
+        assert!(elapsed < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn dominant_direction() {
+        let mut tracker = IdleTracker::default();
+        assert!(tracker.dominant_direction().is_none());
+
+        // the upstream went idle first
+        assert!(!tracker.record(1, &[None, Some(IdleDirection::UpstreamRead)]));
+        assert!(!tracker.record(1, &[None, Some(IdleDirection::UpstreamRead)]));
+        assert_eq!(tracker.idle_count(), 0);
+        let sides = [
+            Some(IdleDirection::ClientRead),
+            Some(IdleDirection::UpstreamRead),
+        ];
+        assert!(tracker.record(1, &sides));
+        assert!(tracker.record(1, &sides));
+        assert_eq!(tracker.idle_count(), 2);
+        assert_eq!(
+            tracker.dominant_direction(),
+            Some(IdleDirection::UpstreamRead)
+        );
+
+        // active again
+        assert!(!tracker.record(1, &[None, None]));
+        assert_eq!(tracker.idle_count(), 0);
+        assert!(tracker.dominant_direction().is_none());
+
+        let sides = [
+            Some(IdleDirection::WriteBlocked),
+            Some(IdleDirection::IcapRead),
+        ];
+        assert!(tracker.record(2, &sides));
+        assert_eq!(tracker.dominant_direction(), Some(IdleDirection::IcapRead));
+
+        // the write blocked side is counted once
+        let sides = [
+            Some(IdleDirection::WriteBlocked),
+            Some(IdleDirection::WriteBlocked),
+        ];
+        assert!(tracker.record(1, &sides));
+        assert_eq!(tracker.idle_count(), 3);
+        assert_eq!(
+            tracker.dominant_direction(),
+            Some(IdleDirection::WriteBlocked)
+        );
+    }
+
+    struct MockIdleChecker(usize);
+
+    impl IdleCheck for MockIdleChecker {
+        fn interval_timer(&self) -> IdleInterval {
+            IdleWheel::spawn(Duration::from_secs(1)).register()
+        }
+
+        fn check_quit(&self, idle_count: usize) -> bool {
+            idle_count > self.0
+        }
+
+        fn check_force_quit(&self) -> Option<IdleForceQuitReason> {
+            None
+        }
+    }
+
+    #[test]
+    fn check_quit_tracked() {
+        let checker = MockIdleChecker(1);
+        let mut tracker = IdleTracker::default();
+        tracker.record(1, &[Some(IdleDirection::ClientRead)]);
+        assert!(checker.check_quit_tracked(&tracker).is_none());
+        tracker.record(1, &[Some(IdleDirection::ClientRead)]);
+        assert_eq!(
+            checker.check_quit_tracked(&tracker),
+            Some(IdleDirection::ClientRead)
+        );
+    }
 }
@@ -7,4 +7,6 @@ mod optional_interval;
 pub use optional_interval::OptionalInterval;
 
 mod idle;
-pub use idle::{IdleCheck, IdleForceQuitReason, IdleInterval, IdleWheel};
+pub use idle::{
+    IdleCheck, IdleDirection, IdleForceQuitReason, IdleInterval, IdleTracker, IdleWheel,
+};
@@ -0,0 +1,145 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::sync::Arc;
+
+use super::LimitedUdpRelayConfig;
+
+pub trait UdpBatchSizeStats {
+    fn set_batch_size(&self, size: usize);
+}
+pub type ArcUdpBatchSizeStats = Arc<dyn UdpBatchSizeStats + Send + Sync>;
+
+/// Adapt the recv batch size to the observed queue depth
+///
+/// The batch size will be doubled after `grow_threshold` consecutive polls that fill the whole
+/// batch, and will be halved after `shrink_threshold` consecutive polls that fill at most half
+/// of it, always within the configured min and max size.
+pub(super) struct UdpBatchSizer {
+    min: usize,
+    max: usize,
+    grow_threshold: usize,
+    shrink_threshold: usize,
+    current: usize,
+    full_polls: usize,
+    sparse_polls: usize,
+    stats: Option<ArcUdpBatchSizeStats>,
+}
+
+impl UdpBatchSizer {
+    pub(super) fn new(config: &LimitedUdpRelayConfig) -> Self {
+        let max = config.batch_size.max(1);
+        let min = config.batch_size_min.clamp(1, max);
+        UdpBatchSizer {
+            min,
+            max,
+            grow_threshold: config.batch_grow_threshold,
+            shrink_threshold: config.batch_shrink_threshold,
+            current: min,
+            full_polls: 0,
+            sparse_polls: 0,
+            stats: None,
+        }
+    }
+
+    #[inline]
+    pub(super) fn current(&self) -> usize {
+        self.current
+    }
+
+    pub(super) fn set_stats(&mut self, stats: ArcUdpBatchSizeStats) {
+        stats.set_batch_size(self.current);
+        self.stats = Some(stats);
+    }
+
+    /// Record the count of packets received in a poll for a whole new batch
+    pub(super) fn record(&mut self, received: usize) {
+        if received >= self.current {
+            self.sparse_polls = 0;
+            self.full_polls += 1;
+            if self.full_polls >= self.grow_threshold {
+                self.full_polls = 0;
+                self.update((self.current * 2).min(self.max));
+            }
+        } else if received * 2 <= self.current {
+            self.full_polls = 0;
+            self.sparse_polls += 1;
+            if self.sparse_polls >= self.shrink_threshold {
+                self.sparse_polls = 0;
+                self.update((self.current / 2).max(self.min));
+            }
+        } else {
+            self.full_polls = 0;
+            self.sparse_polls = 0;
+        }
+    }
+
+    fn update(&mut self, size: usize) {
+        if size == self.current {
+            return;
+        }
+        self.current = size;
+        if let Some(stats) = &self.stats {
+            stats.set_batch_size(size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_sizer(min: usize, max: usize) -> UdpBatchSizer {
+        let mut config = LimitedUdpRelayConfig::default();
+        config.set_batch_size(max);
+        config.set_batch_size_min(min);
+        config.set_batch_grow_threshold(2);
+        config.set_batch_shrink_threshold(4);
+        UdpBatchSizer::new(&config)
+    }
+
+    #[test]
+    fn grow_and_shrink() {
+        let mut sizer = new_sizer(8, 64);
+        assert_eq!(sizer.current(), 8);
+
+        for expected in [8, 16, 16, 32, 32, 64, 64, 64] {
+            sizer.record(sizer.current());
+            assert_eq!(sizer.current(), expected);
+        }
+
+        // more than half filled, keep the current size
+        for _ in 0..10 {
+            sizer.record(40);
+        }
+        assert_eq!(sizer.current(), 64);
+
+        for _ in 0..4 {
+            sizer.record(1);
+        }
+        assert_eq!(sizer.current(), 32);
+        for _ in 0..100 {
+            sizer.record(1);
+        }
+        assert_eq!(sizer.current(), 8);
+    }
+
+    #[test]
+    fn interrupted_streak() {
+        let mut sizer = new_sizer(8, 64);
+        sizer.record(8);
+        sizer.record(2);
+        sizer.record(8);
+        assert_eq!(sizer.current(), 8);
+        sizer.record(8);
+        assert_eq!(sizer.current(), 16);
+    }
+
+    #[test]
+    fn min_larger_than_max() {
+        let sizer = new_sizer(32, 16);
+        assert_eq!(sizer.current(), 16);
+    }
+}
@@ -3,13 +3,13 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::io::IoSliceMut;
+use std::io::{self, IoSliceMut};
 use std::pin::Pin;
 use std::task::{Context, Poll, ready};
 
 use thiserror::Error;
 
-use super::LimitedUdpRelayConfig;
+use super::{ArcUdpBatchSizeStats, LimitedUdpRelayConfig, UdpBatchSizer, UdpDuplicateFilter};
 
 mod client;
 mod remote;
@@ -197,6 +197,15 @@ impl<T: UdpCopyRemoteRecv + ?Sized> UdpCopyRecv for RemoteRecv<'_, T> {
     }
 }
 
+/// The batch sender should return `Poll::Pending` instead of 0 if no packet could be sent,
+/// or the copy loop would never make progress
+fn no_packet_sent() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::WriteZero,
+        "no packet accepted by the batch sender",
+    )
+}
+
 trait UdpCopySend {
     fn poll_send_packet(
         &mut self,
@@ -254,9 +263,12 @@ impl<T: UdpCopyClientSend + ?Sized> UdpCopySend for ClientSend<'_, T> {
         cx: &mut Context<'_>,
         packets: &[UdpCopyPacket],
     ) -> Poll<Result<usize, UdpCopyError>> {
-        self.0
-            .poll_send_packets(cx, packets)
-            .map_err(UdpCopyError::ClientError)
+        match ready!(self.0.poll_send_packets(cx, packets)) {
+            Ok(0) if !packets.is_empty() => Poll::Ready(Err(UdpCopyError::ClientError(
+                UdpCopyClientError::SendFailed(no_packet_sent()),
+            ))),
+            r => Poll::Ready(r.map_err(UdpCopyError::ClientError)),
+        }
     }
 }
 
@@ -287,15 +299,22 @@ impl<T: UdpCopyRemoteSend + ?Sized> UdpCopySend for RemoteSend<'_, T> {
         cx: &mut Context<'_>,
         packets: &[UdpCopyPacket],
     ) -> Poll<Result<usize, UdpCopyError>> {
-        self.0
-            .poll_send_packets(cx, packets)
-            .map_err(UdpCopyError::RemoteError)
+        match ready!(self.0.poll_send_packets(cx, packets)) {
+            Ok(0) if !packets.is_empty() => Poll::Ready(Err(UdpCopyError::RemoteError(
+                UdpCopyRemoteError::SendFailed(no_packet_sent()),
+            ))),
+            r => Poll::Ready(r.map_err(UdpCopyError::RemoteError)),
+        }
     }
 }
 
 struct UdpCopyBuffer {
     config: LimitedUdpRelayConfig,
+    max_hdr_size: usize,
+    batch_sizer: UdpBatchSizer,
+    /// the packets will be kept after the batch size shrinks, and reused when it grows again
     packets: Vec<UdpCopyPacket>,
+    dup_filter: Option<UdpDuplicateFilter>,
     send_start: usize,
     send_end: usize,
     recv_done: bool,
@@ -305,10 +324,15 @@ struct UdpCopyBuffer {
 
 impl UdpCopyBuffer {
     fn new(max_hdr_size: usize, config: LimitedUdpRelayConfig) -> Self {
-        let packets = vec![UdpCopyPacket::new(max_hdr_size, config.packet_size); config.batch_size];
+        let batch_sizer = UdpBatchSizer::new(&config);
+        let packets =
+            vec![UdpCopyPacket::new(max_hdr_size, config.packet_size); batch_sizer.current()];
         UdpCopyBuffer {
             config,
+            max_hdr_size,
+            batch_sizer,
             packets,
+            dup_filter: None,
             send_start: 0,
             send_end: 0,
             recv_done: false,
@@ -329,13 +353,16 @@ impl UdpCopyBuffer {
     {
         let mut copy_this_round = 0usize;
         loop {
-            if !self.recv_done && self.send_end < self.packets.len() {
-                match receiver.poll_recv_packets(cx, &mut self.packets[self.send_end..]) {
+            let batch_size = self.batch_size();
+            if !self.recv_done && self.send_end < batch_size {
+                match receiver.poll_recv_packets(cx, &mut self.packets[self.send_end..batch_size]) {
                     Poll::Ready(Ok(count)) => {
                         if count == 0 {
                             self.recv_done = true;
+                        } else if self.send_end == 0 {
+                            self.batch_sizer.record(count);
                         }
-                        self.send_end += count;
+                        self.send_end += self.filter_duplicate(self.send_end, count);
                         self.active = true;
                     }
                     Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
@@ -350,11 +377,14 @@ impl UdpCopyBuffer {
             while self.send_end > self.send_start {
                 let packets = &self.packets[self.send_start..self.send_end];
                 let count = ready!(sender.poll_send_packets(cx, packets))?;
-                copy_this_round += packets
+                let count = count.min(packets.len());
+                let nw = packets
                     .iter()
                     .take(count)
                     .map(|p| p.buf_data_end - p.buf_data_off)
                     .sum::<usize>();
+                copy_this_round += nw;
+                self.total += nw as u64;
                 self.send_start += count;
                 self.active = true;
             }
@@ -372,6 +402,34 @@ impl UdpCopyBuffer {
         }
     }
 
+    /// Get the current batch size, and make sure there are enough packets for it
+    fn batch_size(&mut self) -> usize {
+        let batch_size = self.batch_sizer.current();
+        if self.packets.len() < batch_size {
+            let (max_hdr_size, packet_size) = (self.max_hdr_size, self.config.packet_size);
+            self.packets
+                .resize_with(batch_size, || UdpCopyPacket::new(max_hdr_size, packet_size));
+        }
+        batch_size
+    }
+
+    /// Move the non-duplicate ones of the new received packets to the front,
+    /// and return the count of them
+    fn filter_duplicate(&mut self, start: usize, count: usize) -> usize {
+        let Some(filter) = &mut self.dup_filter else {
+            return count;
+        };
+        let mut kept = 0;
+        for i in start..start + count {
+            if filter.check_duplicate(self.packets[i].payload()) {
+                continue;
+            }
+            self.packets.swap(start + kept, i);
+            kept += 1;
+        }
+        kept
+    }
+
     fn is_idle(&self) -> bool {
         !self.active
     }
@@ -379,6 +437,21 @@ impl UdpCopyBuffer {
     fn reset_active(&mut self) {
         self.active = false;
     }
+
+    fn set_duplicate_filter(&mut self, filter: UdpDuplicateFilter) {
+        self.dup_filter = Some(filter);
+    }
+
+    fn set_batch_size_stats(&mut self, stats: ArcUdpBatchSizeStats) {
+        self.batch_sizer.set_stats(stats);
+    }
+
+    fn duplicate_dropped(&self) -> u64 {
+        self.dup_filter
+            .as_ref()
+            .map(|f| f.dropped())
+            .unwrap_or_default()
+    }
 }
 
 pub struct UdpCopyClientToRemote<'a, C: ?Sized, R: ?Sized> {
@@ -410,6 +483,29 @@ where
     pub fn reset_active(&mut self) {
         self.buffer.reset_active()
     }
+
+    /// Get the current recv batch size
+    #[inline]
+    pub fn batch_size(&self) -> usize {
+        self.buffer.batch_sizer.current()
+    }
+
+    /// Set the gauge to be updated when the recv batch size changes
+    #[inline]
+    pub fn set_batch_size_stats(&mut self, stats: ArcUdpBatchSizeStats) {
+        self.buffer.set_batch_size_stats(stats)
+    }
+
+    /// Drop the duplicate packets from the client before sending to the remote
+    #[inline]
+    pub fn set_duplicate_filter(&mut self, filter: UdpDuplicateFilter) {
+        self.buffer.set_duplicate_filter(filter)
+    }
+
+    #[inline]
+    pub fn duplicate_dropped(&self) -> u64 {
+        self.buffer.duplicate_dropped()
+    }
 }
 
 impl<C, R> Future for UdpCopyClientToRemote<'_, C, R>
@@ -455,6 +551,24 @@ where
     pub fn reset_active(&mut self) {
         self.buffer.reset_active()
     }
+
+    /// Get the current recv batch size
+    #[inline]
+    pub fn batch_size(&self) -> usize {
+        self.buffer.batch_sizer.current()
+    }
+
+    /// Set the gauge to be updated when the recv batch size changes
+    #[inline]
+    pub fn set_batch_size_stats(&mut self, stats: ArcUdpBatchSizeStats) {
+        self.buffer.set_batch_size_stats(stats)
+    }
+
+    /// Get the client side sender, which can be used to send extra packets to the client
+    #[inline]
+    pub fn client_mut(&mut self) -> &mut C {
+        self.client
+    }
 }
 
 impl<C, R> Future for UdpCopyRemoteToClient<'_, C, R>
@@ -470,3 +584,348 @@ where
             .poll_batch_copy(cx, RemoteRecv(&mut *me.remote), ClientSend(&mut *me.client))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    use crate::udp::{UdpBatchSizeStats, UdpDuplicateFilterConfig};
+
+    struct MockClientRecv {
+        queue: VecDeque<Vec<u8>>,
+    }
+
+    impl MockClientRecv {
+        fn new(count: usize) -> Self {
+            MockClientRecv {
+                queue: (0..count)
+                    .map(|i| format!("packet {i}").into_bytes())
+                    .collect(),
+            }
+        }
+    }
+
+    impl UdpCopyClientRecv for MockClientRecv {
+        fn max_hdr_len(&self) -> usize {
+            0
+        }
+
+        fn poll_recv_packet(
+            &mut self,
+            _cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<Result<(usize, usize), UdpCopyClientError>> {
+            let Some(data) = self.queue.pop_front() else {
+                return Poll::Ready(Ok((0, 0)));
+            };
+            buf[..data.len()].copy_from_slice(&data);
+            Poll::Ready(Ok((0, data.len())))
+        }
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "macos",
+            target_os = "solaris",
+        ))]
+        fn poll_recv_packets(
+            &mut self,
+            _cx: &mut Context<'_>,
+            packets: &mut [UdpCopyPacket],
+        ) -> Poll<Result<usize, UdpCopyClientError>> {
+            let mut count = 0;
+            for p in packets.iter_mut() {
+                let Some(data) = self.queue.pop_front() else {
+                    break;
+                };
+                p.buf[..data.len()].copy_from_slice(&data);
+                p.set_offset(0);
+                p.set_length(data.len());
+                count += 1;
+            }
+            Poll::Ready(Ok(count))
+        }
+    }
+
+    /// accept only one packet per poll, and return pending on every other poll
+    #[derive(Default)]
+    struct MockRemoteSend {
+        sent: Vec<Vec<u8>>,
+        blocked: bool,
+    }
+
+    impl UdpCopyRemoteSend for MockRemoteSend {
+        fn poll_send_packet(
+            &mut self,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, UdpCopyRemoteError>> {
+            self.sent.push(buf.to_vec());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "macos",
+            target_os = "solaris",
+        ))]
+        fn poll_send_packets(
+            &mut self,
+            cx: &mut Context<'_>,
+            packets: &[UdpCopyPacket],
+        ) -> Poll<Result<usize, UdpCopyRemoteError>> {
+            if self.blocked {
+                self.blocked = false;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.blocked = true;
+            self.sent.push(packets[0].payload().to_vec());
+            Poll::Ready(Ok(1))
+        }
+    }
+
+    #[tokio::test]
+    async fn partial_batch_send_no_duplicate() {
+        let mut client = MockClientRecv::new(20);
+        let expected: Vec<Vec<u8>> = client.queue.iter().cloned().collect();
+        let mut remote = MockRemoteSend::default();
+
+        let c_to_r =
+            UdpCopyClientToRemote::new(&mut client, &mut remote, LimitedUdpRelayConfig::default());
+        let total = c_to_r.await.unwrap();
+
+        assert_eq!(remote.sent, expected);
+        assert_eq!(total, expected.iter().map(|v| v.len() as u64).sum::<u64>());
+    }
+
+    /// return the packets in bursts, each poll gets at most the burst size of packets
+    struct MockBurstClientRecv {
+        bursts: VecDeque<usize>,
+        received: usize,
+    }
+
+    impl UdpCopyClientRecv for MockBurstClientRecv {
+        fn max_hdr_len(&self) -> usize {
+            0
+        }
+
+        fn poll_recv_packet(
+            &mut self,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<Result<(usize, usize), UdpCopyClientError>> {
+            let mut packet = UdpCopyPacket::new(0, buf.len());
+            let count = ready!(self.poll_recv_packets(cx, std::slice::from_mut(&mut packet)))?;
+            let len = if count > 0 { packet.buf_data_end } else { 0 };
+            buf[..len].copy_from_slice(packet.payload());
+            Poll::Ready(Ok((0, len)))
+        }
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "macos",
+            target_os = "solaris",
+        ))]
+        fn poll_recv_packets(
+            &mut self,
+            _cx: &mut Context<'_>,
+            packets: &mut [UdpCopyPacket],
+        ) -> Poll<Result<usize, UdpCopyClientError>> {
+            let Some(burst) = self.bursts.pop_front() else {
+                return Poll::Ready(Ok(0));
+            };
+            let count = burst.min(packets.len());
+            for p in packets.iter_mut().take(count) {
+                let data = format!("packet {}", self.received).into_bytes();
+                p.buf[..data.len()].copy_from_slice(&data);
+                p.set_offset(0);
+                p.set_length(data.len());
+                self.received += 1;
+            }
+            Poll::Ready(Ok(count))
+        }
+    }
+
+    #[derive(Default)]
+    struct MockRemoteSendAll {
+        sent: Vec<Vec<u8>>,
+    }
+
+    impl UdpCopyRemoteSend for MockRemoteSendAll {
+        fn poll_send_packet(
+            &mut self,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, UdpCopyRemoteError>> {
+            self.sent.push(buf.to_vec());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "macos",
+            target_os = "solaris",
+        ))]
+        fn poll_send_packets(
+            &mut self,
+            _cx: &mut Context<'_>,
+            packets: &[UdpCopyPacket],
+        ) -> Poll<Result<usize, UdpCopyRemoteError>> {
+            for p in packets {
+                self.sent.push(p.payload().to_vec());
+            }
+            Poll::Ready(Ok(packets.len()))
+        }
+    }
+
+    #[derive(Default)]
+    struct BatchSizeHistory(Mutex<Vec<usize>>);
+
+    impl UdpBatchSizeStats for BatchSizeHistory {
+        fn set_batch_size(&self, size: usize) {
+            self.0.lock().unwrap().push(size);
+        }
+    }
+
+    #[tokio::test]
+    async fn adaptive_batch_size() {
+        let mut bursts = VecDeque::new();
+        bursts.extend([usize::MAX; 10]);
+        bursts.extend([1; 64]);
+        let mut client = MockBurstClientRecv {
+            bursts,
+            received: 0,
+        };
+        let mut remote = MockRemoteSendAll::default();
+
+        let mut config = LimitedUdpRelayConfig::default();
+        config.set_batch_size(64);
+        config.set_batch_size_min(8);
+        config.set_batch_grow_threshold(2);
+        config.set_batch_shrink_threshold(16);
+        let history = Arc::new(BatchSizeHistory::default());
+        let mut c_to_r = UdpCopyClientToRemote::new(&mut client, &mut remote, config);
+        c_to_r.set_batch_size_stats(history.clone());
+        assert_eq!(c_to_r.batch_size(), 8);
+        (&mut c_to_r).await.unwrap();
+        assert_eq!(c_to_r.batch_size(), 8);
+
+        // grow to the max with full batches, and shrink back with sparse ones
+        let history = history.0.lock().unwrap();
+        assert_eq!(*history, vec![8, 16, 32, 64, 32, 16, 8]);
+
+        // all packets are sent in order
+        let received = client.received;
+        assert_eq!(received, 8 + 8 + 16 + 16 + 32 + 32 + 64 * 4 + 64);
+        assert_eq!(remote.sent.len(), received);
+        for (i, data) in remote.sent.iter().enumerate() {
+            assert_eq!(data, format!("packet {i}").as_bytes());
+        }
+    }
+
+    async fn copy_with_filter(config: UdpDuplicateFilterConfig) -> (Vec<Vec<u8>>, u64) {
+        let mut client = MockClientRecv {
+            queue: vec![b"a".to_vec(), b"b".to_vec(), b"a".to_vec(), b"a".to_vec()].into(),
+        };
+        let mut remote = MockRemoteSend::default();
+
+        let mut c_to_r =
+            UdpCopyClientToRemote::new(&mut client, &mut remote, LimitedUdpRelayConfig::default());
+        c_to_r.set_duplicate_filter(UdpDuplicateFilter::new(config));
+        (&mut c_to_r).await.unwrap();
+        let dropped = c_to_r.duplicate_dropped();
+        (remote.sent, dropped)
+    }
+
+    #[tokio::test]
+    async fn duplicate_within_horizon() {
+        let (sent, dropped) = copy_with_filter(UdpDuplicateFilterConfig::default()).await;
+        assert_eq!(sent, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(dropped, 2);
+    }
+
+    #[tokio::test]
+    async fn duplicate_outside_horizon() {
+        let mut config = UdpDuplicateFilterConfig::default();
+        config.set_horizon(std::time::Duration::ZERO);
+        let (sent, dropped) = copy_with_filter(config).await;
+        assert_eq!(
+            sent,
+            vec![b"a".to_vec(), b"b".to_vec(), b"a".to_vec(), b"a".to_vec()]
+        );
+        assert_eq!(dropped, 0);
+    }
+
+    /// a broken batch sender which accepts no packet but reports no error
+    struct MockZeroRemoteSend;
+
+    impl UdpCopyRemoteSend for MockZeroRemoteSend {
+        fn poll_send_packet(
+            &mut self,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, UdpCopyRemoteError>> {
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "macos",
+            target_os = "solaris",
+        ))]
+        fn poll_send_packets(
+            &mut self,
+            _cx: &mut Context<'_>,
+            _packets: &[UdpCopyPacket],
+        ) -> Poll<Result<usize, UdpCopyRemoteError>> {
+            Poll::Ready(Ok(0))
+        }
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+        target_os = "solaris",
+    ))]
+    #[tokio::test]
+    async fn zero_batch_send() {
+        let mut client = MockClientRecv::new(4);
+        let mut remote = MockZeroRemoteSend;
+
+        let c_to_r =
+            UdpCopyClientToRemote::new(&mut client, &mut remote, LimitedUdpRelayConfig::default());
+        let r = tokio::time::timeout(std::time::Duration::from_secs(1), c_to_r)
+            .await
+            .unwrap();
+        assert!(matches!(
+            r,
+            Err(UdpCopyError::RemoteError(UdpCopyRemoteError::SendFailed(_)))
+        ));
+    }
+}
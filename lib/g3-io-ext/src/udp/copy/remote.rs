@@ -70,6 +70,12 @@ pub trait UdpCopyRemoteSend {
         buf: &[u8],
     ) -> Poll<Result<usize, UdpCopyRemoteError>>;
 
+    /// return the number of packets that have been consumed from the front of `packets`,
+    /// which should be greater than 0 if `packets` is not empty,
+    /// or it will be treated as a send error.
+    ///
+    /// If no packet could be accepted for now, register the waker and return `Poll::Pending`.
+    /// Only the remaining packets will be passed in on the next call.
     #[cfg(any(
         target_os = "linux",
         target_os = "android",
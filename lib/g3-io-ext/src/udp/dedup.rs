@@ -0,0 +1,180 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ahash::RandomState;
+
+const DEFAULT_WINDOW_SIZE: usize = 16;
+const DEFAULT_HORIZON: Duration = Duration::from_secs(1);
+const MAXIMUM_WINDOW_SIZE: usize = 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UdpDuplicateFilterConfig {
+    window_size: usize,
+    horizon: Duration,
+}
+
+impl Default for UdpDuplicateFilterConfig {
+    fn default() -> Self {
+        UdpDuplicateFilterConfig {
+            window_size: DEFAULT_WINDOW_SIZE,
+            horizon: DEFAULT_HORIZON,
+        }
+    }
+}
+
+impl UdpDuplicateFilterConfig {
+    /// Set how many recent packets will be remembered
+    pub fn set_window_size(&mut self, size: usize) {
+        self.window_size = size.clamp(1, MAXIMUM_WINDOW_SIZE);
+    }
+
+    #[inline]
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Set how long a packet will be remembered
+    pub fn set_horizon(&mut self, horizon: Duration) {
+        self.horizon = horizon;
+    }
+
+    #[inline]
+    pub fn horizon(&self) -> Duration {
+        self.horizon
+    }
+}
+
+pub trait UdpDuplicateStats {
+    fn add_duplicate_dropped(&self);
+}
+pub type ArcUdpDuplicateStats = Arc<dyn UdpDuplicateStats + Send + Sync>;
+
+/// Detect the exact duplicates of recent packets, in the order of arrival or not
+pub struct UdpDuplicateFilter {
+    config: UdpDuplicateFilterConfig,
+    hasher: RandomState,
+    recent: VecDeque<(u64, Instant)>,
+    dropped: u64,
+    stats: Option<ArcUdpDuplicateStats>,
+}
+
+impl UdpDuplicateFilter {
+    pub fn new(config: UdpDuplicateFilterConfig) -> Self {
+        UdpDuplicateFilter {
+            config,
+            hasher: RandomState::new(),
+            recent: VecDeque::with_capacity(config.window_size),
+            dropped: 0,
+            stats: None,
+        }
+    }
+
+    pub fn with_stats(mut self, stats: ArcUdpDuplicateStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Get the number of packets that have been detected as duplicate
+    #[inline]
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Check if the packet is a duplicate one, and remember it if not.
+    ///
+    /// The key should contain all the fields that make up a packet, such as the payload and the destination.
+    pub fn check_duplicate<T: Hash>(&mut self, key: T) -> bool {
+        self.check_duplicate_at(key, Instant::now())
+    }
+
+    fn check_duplicate_at<T: Hash>(&mut self, key: T, now: Instant) -> bool {
+        while let Some((_, time)) = self.recent.front() {
+            if now.duration_since(*time) < self.config.horizon {
+                break;
+            }
+            self.recent.pop_front();
+        }
+
+        let hash = self.hasher.hash_one(key);
+        if self.recent.iter().any(|(v, _)| *v == hash) {
+            self.dropped += 1;
+            if let Some(stats) = &self.stats {
+                stats.add_duplicate_dropped();
+            }
+            return true;
+        }
+
+        if self.recent.len() >= self.config.window_size {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((hash, now));
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn within_horizon() {
+        let mut filter = UdpDuplicateFilter::new(UdpDuplicateFilterConfig::default());
+        let now = Instant::now();
+
+        assert!(!filter.check_duplicate_at(b"a".as_slice(), now));
+        assert!(!filter.check_duplicate_at(b"b".as_slice(), now));
+        // out of order duplicate
+        assert!(filter.check_duplicate_at(b"a".as_slice(), now));
+        assert!(filter.check_duplicate_at(b"b".as_slice(), now));
+        assert!(filter.check_duplicate_at(b"a".as_slice(), now));
+        assert_eq!(filter.dropped(), 3);
+    }
+
+    #[test]
+    fn outside_horizon() {
+        let mut config = UdpDuplicateFilterConfig::default();
+        config.set_horizon(Duration::from_millis(100));
+        let mut filter = UdpDuplicateFilter::new(config);
+        let now = Instant::now();
+
+        assert!(!filter.check_duplicate_at(b"a".as_slice(), now));
+        let now = now + Duration::from_millis(50);
+        assert!(filter.check_duplicate_at(b"a".as_slice(), now));
+        // the horizon starts from the first copy
+        let now = now + Duration::from_millis(50);
+        assert!(!filter.check_duplicate_at(b"a".as_slice(), now));
+        assert_eq!(filter.dropped(), 1);
+    }
+
+    #[test]
+    fn window_size() {
+        let mut config = UdpDuplicateFilterConfig::default();
+        config.set_window_size(2);
+        let mut filter = UdpDuplicateFilter::new(config);
+        let now = Instant::now();
+
+        assert!(!filter.check_duplicate_at(b"a".as_slice(), now));
+        assert!(!filter.check_duplicate_at(b"b".as_slice(), now));
+        assert!(!filter.check_duplicate_at(b"c".as_slice(), now));
+        // evicted by c
+        assert!(!filter.check_duplicate_at(b"a".as_slice(), now));
+        assert!(filter.check_duplicate_at(b"c".as_slice(), now));
+    }
+
+    #[test]
+    fn with_destination() {
+        let mut filter = UdpDuplicateFilter::new(UdpDuplicateFilterConfig::default());
+        let now = Instant::now();
+
+        assert!(!filter.check_duplicate_at((b"a".as_slice(), 53u16), now));
+        assert!(!filter.check_duplicate_at((b"a".as_slice(), 54u16), now));
+        assert!(filter.check_duplicate_at((b"a".as_slice(), 53u16), now));
+    }
+}
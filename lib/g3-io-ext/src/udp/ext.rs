@@ -10,7 +10,9 @@ use std::task::{Context, Poll, ready};
 use tokio::io::Interest;
 use tokio::net::UdpSocket;
 
-use g3_io_sys::udp::{RecvAncillaryBuffer, RecvMsgHdr, SendMsgHdr, recvmsg, sendmsg};
+use g3_io_sys::udp::{
+    RecvAncillaryBuffer, RecvMsgHdr, SendMsgHdr, UdpSocketError, recv_error, recvmsg, sendmsg,
+};
 
 thread_local! {
     static RECV_ANCILLARY_BUFFER: RefCell<RecvAncillaryBuffer> = const { RefCell::new(RecvAncillaryBuffer::new()) };
@@ -69,6 +71,12 @@ pub trait UdpSocketExt {
         cx: &mut Context<'_>,
         hdr_v: &mut [RecvMsgHdr<'_, C>],
     ) -> Poll<io::Result<usize>>;
+
+    /// Take one error from the socket error queue without blocking.
+    ///
+    /// This will always return `Ok(None)` if IP_RECVERR / IPV6_RECVERR is not enabled,
+    /// or if the platform has no socket error queue.
+    fn try_recv_err(&self) -> io::Result<Option<UdpSocketError>>;
 }
 
 impl UdpSocketExt for UdpSocket {
@@ -109,6 +117,8 @@ impl UdpSocketExt for UdpSocket {
                 match self.try_io(Interest::READABLE, || recvmsg(self, &mut msghdr)) {
                     Ok(nr) => {
                         hdr.n_recv = nr;
+                        #[cfg(unix)]
+                        hdr.set_msg_flags(msghdr.msg_flags);
                         control_buf.parse_msg(msghdr, hdr)?;
                         return Poll::Ready(Ok(()));
                     }
@@ -141,7 +151,12 @@ impl UdpSocketExt for UdpSocket {
             loop {
                 ready!(self.poll_send_ready(cx))?;
                 match self.try_io(Interest::WRITABLE, || {
-                    g3_io_sys::udp::sendmmsg(self, msgvec)
+                    let count = g3_io_sys::udp::sendmmsg(self, msgvec)?;
+                    if count == 0 && !msgvec.is_empty() {
+                        // clear the write readiness and wait for the socket to be writable again
+                        return Err(io::Error::from(io::ErrorKind::WouldBlock));
+                    }
+                    Ok(count)
                 }) {
                     Ok(count) => {
                         for (m, h) in msgs.iter_mut().take(count).zip(msgvec) {
@@ -171,7 +186,12 @@ impl UdpSocketExt for UdpSocket {
             loop {
                 ready!(self.poll_send_ready(cx))?;
                 match self.try_io(Interest::WRITABLE, || {
-                    g3_io_sys::udp::sendmsg_x(self, msgvec)
+                    let count = g3_io_sys::udp::sendmsg_x(self, msgvec)?;
+                    if count == 0 && !msgvec.is_empty() {
+                        // clear the write readiness and wait for the socket to be writable again
+                        return Err(io::Error::from(io::ErrorKind::WouldBlock));
+                    }
+                    Ok(count)
                 }) {
                     Ok(count) => {
                         for m in msgs.iter_mut().take(count) {
@@ -213,6 +233,7 @@ impl UdpSocketExt for UdpSocket {
                     Ok(count) => {
                         for (m, h) in hdr_v.iter_mut().take(count).zip(msgvec) {
                             m.n_recv = h.msg_len as usize;
+                            m.set_msg_flags(h.msg_hdr.msg_flags);
                             if h.msg_hdr.msg_control.is_null() {
                                 continue;
                             }
@@ -253,6 +274,7 @@ impl UdpSocketExt for UdpSocket {
                     Ok(count) => {
                         for (m, h) in hdr_v.iter_mut().take(count).zip(msgvec) {
                             m.n_recv = h.msg_datalen;
+                            m.set_msg_flags(h.msg_flags);
                             if h.msg_control.is_null() {
                                 continue;
                             }
@@ -277,6 +299,11 @@ impl UdpSocketExt for UdpSocket {
             }
         })
     }
+
+    fn try_recv_err(&self) -> io::Result<Option<UdpSocketError>> {
+        // read the error queue directly, so the readiness of the socket will not be cleared
+        recv_error(self)
+    }
 }
 
 #[cfg(test)]
@@ -339,6 +366,77 @@ mod tests {
         assert_eq!(&recv_msg1[..msg_1.len()], msg_1);
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn msg_truncated() {
+        let s_sock = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let s_addr = s_sock.local_addr().unwrap();
+
+        let c_sock = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        c_sock.connect(&s_addr).await.unwrap();
+
+        let msg_1 = [b'a'; 64];
+        let msg_2 = b"abcd";
+        c_sock.send(&msg_1).await.unwrap();
+        c_sock.send(msg_2).await.unwrap();
+
+        let mut recv_msg1 = [0u8; 16];
+        let mut hdr = RecvMsgHdr::new([IoSliceMut::new(&mut recv_msg1)]);
+        poll_fn(|cx| s_sock.poll_recvmsg(cx, &mut hdr))
+            .await
+            .unwrap();
+        assert_eq!(hdr.n_recv, 16);
+        assert!(hdr.truncated());
+
+        let mut recv_msg2 = [0u8; 16];
+        let mut hdr = RecvMsgHdr::new([IoSliceMut::new(&mut recv_msg2)]);
+        poll_fn(|cx| s_sock.poll_recvmsg(cx, &mut hdr))
+            .await
+            .unwrap();
+        assert_eq!(hdr.n_recv, msg_2.len());
+        assert!(!hdr.truncated());
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+        target_os = "solaris",
+    ))]
+    #[tokio::test]
+    async fn batch_msg_truncated() {
+        let s_sock = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let s_addr = s_sock.local_addr().unwrap();
+
+        let c_sock = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        c_sock.connect(&s_addr).await.unwrap();
+
+        let msg_1 = b"abcd";
+        let msg_2 = [b'a'; 64];
+        c_sock.send(msg_1).await.unwrap();
+        c_sock.send(&msg_2).await.unwrap();
+
+        let mut recv_msg1 = [0u8; 16];
+        let mut recv_msg2 = [0u8; 16];
+        let mut hdr_v = [
+            RecvMsgHdr::new([IoSliceMut::new(&mut recv_msg1)]),
+            RecvMsgHdr::new([IoSliceMut::new(&mut recv_msg2)]),
+        ];
+        let mut count = 0;
+        while count < 2 {
+            count += poll_fn(|cx| s_sock.poll_batch_recvmsg(cx, &mut hdr_v[count..]))
+                .await
+                .unwrap();
+        }
+        assert_eq!(hdr_v[0].n_recv, msg_1.len());
+        assert!(!hdr_v[0].truncated());
+        assert_eq!(hdr_v[1].n_recv, 16);
+        assert!(hdr_v[1].truncated());
+    }
+
     #[tokio::test]
     async fn recv_ancillary_v4() {
         let listen_config = UdpListenConfig::new(SocketAddr::from_str("0.0.0.0:0").unwrap());
@@ -757,4 +855,86 @@ mod tests {
         assert!(hdr.interface_id().is_some());
         assert_eq!(&recv_msg2[..msg_2.len()], msg_2);
     }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn reply_src_ip_v4() {
+        use crate::{AsyncUdpRecv, AsyncUdpSend};
+
+        let (s_sock, s_addr) = g3_socket::udp::new_std_bind_lazy_connect(
+            Some(IpAddr::from_str("0.0.0.0").unwrap()),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let s_sock = UdpSocket::from_std(s_sock).unwrap();
+        let (mut s_r, mut s_w) = crate::split_udp(s_sock);
+
+        let c_sock = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let c_addr = c_sock.local_addr().unwrap();
+
+        for target_ip in ["127.0.0.1", "127.0.0.2"] {
+            let target_ip = IpAddr::from_str(target_ip).unwrap();
+            let target_s_addr = SocketAddr::new(target_ip, s_addr.port());
+            c_sock.send_to(b"ping", target_s_addr).await.unwrap();
+
+            let mut buf = [0u8; 16];
+            let (nr, peer_addr, local_ip) = poll_fn(|cx| s_r.poll_recv_from_to(cx, &mut buf))
+                .await
+                .unwrap();
+            assert_eq!(&buf[..nr], b"ping");
+            assert_eq!(peer_addr, c_addr);
+            assert_eq!(local_ip, Some(target_ip));
+
+            let mut hdr = SendMsgHdr::new([IoSlice::new(b"pong")], Some(peer_addr));
+            let nw = poll_fn(|cx| s_w.poll_sendmsg_from(cx, &mut hdr, target_ip))
+                .await
+                .unwrap();
+            assert_eq!(nw, 4);
+
+            let (nr, reply_addr) = c_sock.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..nr], b"pong");
+            assert_eq!(reply_addr, target_s_addr);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn reply_src_ip_mapped_v4() {
+        use crate::{AsyncUdpRecv, AsyncUdpSend};
+
+        let (s_sock, s_addr) =
+            g3_socket::udp::new_std_bind_lazy_connect(None, Default::default(), Default::default())
+                .unwrap();
+        let s_sock = UdpSocket::from_std(s_sock).unwrap();
+        let (mut s_r, mut s_w) = crate::split_udp(s_sock);
+
+        let c_sock = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let c_addr = c_sock.local_addr().unwrap();
+
+        for target_ip in ["127.0.0.1", "127.0.0.2"] {
+            let target_ip = IpAddr::from_str(target_ip).unwrap();
+            let target_s_addr = SocketAddr::new(target_ip, s_addr.port());
+            c_sock.send_to(b"ping", target_s_addr).await.unwrap();
+
+            let mut buf = [0u8; 16];
+            let (nr, peer_addr, local_ip) = poll_fn(|cx| s_r.poll_recv_from_to(cx, &mut buf))
+                .await
+                .unwrap();
+            assert_eq!(&buf[..nr], b"ping");
+            assert_eq!(peer_addr.to_canonical(), c_addr);
+            let local_ip = local_ip.unwrap();
+            assert_eq!(local_ip.to_canonical(), target_ip);
+
+            let mut hdr = SendMsgHdr::new([IoSlice::new(b"pong")], Some(peer_addr));
+            let nw = poll_fn(|cx| s_w.poll_sendmsg_from(cx, &mut hdr, local_ip))
+                .await
+                .unwrap();
+            assert_eq!(nw, 4);
+
+            let (nr, reply_addr) = c_sock.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..nr], b"pong");
+            assert_eq!(reply_addr, target_s_addr);
+        }
+    }
 }
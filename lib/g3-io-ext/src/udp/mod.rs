@@ -12,13 +12,26 @@ pub use ext::*;
 mod recv;
 mod send;
 
+mod dedup;
+pub use dedup::{
+    ArcUdpDuplicateStats, UdpDuplicateFilter, UdpDuplicateFilterConfig, UdpDuplicateStats,
+};
+
+mod oversize;
+pub use oversize::{ArcUdpOversizeStats, UdpOversizePolicy, UdpOversizeStats};
+
+mod batch;
+use batch::UdpBatchSizer;
+pub use batch::{ArcUdpBatchSizeStats, UdpBatchSizeStats};
+
 pub use recv::{AsyncUdpRecv, LimitedUdpRecv};
 pub use send::{AsyncUdpSend, LimitedUdpSend};
 
 mod relay;
 pub use relay::{
-    UdpRelayClientError, UdpRelayClientRecv, UdpRelayClientSend, UdpRelayPacket,
-    UdpRelayPacketMeta, UdpRelayRemoteError, UdpRelayRemoteRecv, UdpRelayRemoteSend,
+    UdpRelayCapture, UdpRelayClientError, UdpRelayClientRecv, UdpRelayClientSend,
+    UdpRelayDirection, UdpRelayPacket, UdpRelayPacketMeta, UdpRelayPacketTap, UdpRelayRemoteError,
+    UdpRelayRemoteRecv, UdpRelayRemoteSend,
 };
 pub use relay::{UdpRelayClientToRemote, UdpRelayError, UdpRelayRemoteToClient};
 
@@ -38,6 +51,8 @@ pub use split::{
 const DEFAULT_UDP_PACKET_SIZE: usize = 4096; // at least for DNS with extension
 const DEFAULT_UDP_RELAY_YIELD_SIZE: usize = 1024 * 1024; // 1MB
 const DEFAULT_UDP_BATCH_SIZE: usize = 8;
+const DEFAULT_UDP_BATCH_GROW_THRESHOLD: usize = 2;
+const DEFAULT_UDP_BATCH_SHRINK_THRESHOLD: usize = 16;
 const MINIMUM_UDP_PACKET_SIZE: usize = 512;
 const MAXIMUM_UDP_PACKET_SIZE: usize = 64 * 1024;
 const MINIMUM_UDP_RELAY_YIELD_SIZE: usize = 256 * 1024;
@@ -45,16 +60,26 @@ const MINIMUM_UDP_RELAY_YIELD_SIZE: usize = 256 * 1024;
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct LimitedUdpRelayConfig {
     packet_size: usize,
+    packet_size_max: usize,
+    oversize_policy: UdpOversizePolicy,
     yield_size: usize,
     batch_size: usize,
+    batch_size_min: usize,
+    batch_grow_threshold: usize,
+    batch_shrink_threshold: usize,
 }
 
 impl Default for LimitedUdpRelayConfig {
     fn default() -> Self {
         LimitedUdpRelayConfig {
             packet_size: DEFAULT_UDP_PACKET_SIZE,
+            packet_size_max: MAXIMUM_UDP_PACKET_SIZE,
+            oversize_policy: UdpOversizePolicy::ForwardTruncated,
             yield_size: DEFAULT_UDP_RELAY_YIELD_SIZE,
             batch_size: DEFAULT_UDP_BATCH_SIZE,
+            batch_size_min: DEFAULT_UDP_BATCH_SIZE,
+            batch_grow_threshold: DEFAULT_UDP_BATCH_GROW_THRESHOLD,
+            batch_shrink_threshold: DEFAULT_UDP_BATCH_SHRINK_THRESHOLD,
         }
     }
 }
@@ -69,11 +94,47 @@ impl LimitedUdpRelayConfig {
         self.packet_size
     }
 
+    /// Set the ceiling of the packet size, which is only used by the grow oversize policy
+    pub fn set_packet_size_max(&mut self, packet_size: usize) {
+        self.packet_size_max = packet_size.clamp(MINIMUM_UDP_PACKET_SIZE, MAXIMUM_UDP_PACKET_SIZE)
+    }
+
+    #[inline]
+    pub fn packet_size_max(&self) -> usize {
+        self.packet_size_max.max(self.packet_size)
+    }
+
+    /// Set how to handle the received datagrams that are larger than the packet size
+    pub fn set_oversize_policy(&mut self, policy: UdpOversizePolicy) {
+        self.oversize_policy = policy;
+    }
+
+    #[inline]
+    pub fn oversize_policy(&self) -> UdpOversizePolicy {
+        self.oversize_policy
+    }
+
     pub fn set_yield_size(&mut self, yield_size: usize) {
         self.yield_size = yield_size.max(MINIMUM_UDP_RELAY_YIELD_SIZE);
     }
 
+    /// Set the max batch size
     pub fn set_batch_size(&mut self, batch_size: usize) {
         self.batch_size = batch_size;
     }
+
+    /// Set the initial and min batch size, it will be capped by the max batch size
+    pub fn set_batch_size_min(&mut self, batch_size: usize) {
+        self.batch_size_min = batch_size;
+    }
+
+    /// Set how many consecutive full batches will make the batch size grow
+    pub fn set_batch_grow_threshold(&mut self, count: usize) {
+        self.batch_grow_threshold = count.max(1);
+    }
+
+    /// Set how many consecutive at most half filled batches will make the batch size shrink
+    pub fn set_batch_shrink_threshold(&mut self, count: usize) {
+        self.batch_shrink_threshold = count.max(1);
+    }
 }
@@ -0,0 +1,38 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The way to handle datagrams that are larger than the packet buffer
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum UdpOversizePolicy {
+    /// drop the truncated packet
+    Drop,
+    /// forward the truncated packet as is
+    #[default]
+    ForwardTruncated,
+    /// drop the truncated packet, and grow the packet buffer for the following packets
+    Grow,
+}
+
+impl FromStr for UdpOversizePolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "drop" => Ok(UdpOversizePolicy::Drop),
+            "forward_truncated" | "forward" | "truncate" => Ok(UdpOversizePolicy::ForwardTruncated),
+            "grow" => Ok(UdpOversizePolicy::Grow),
+            _ => Err(()),
+        }
+    }
+}
+
+pub trait UdpOversizeStats {
+    fn add_truncated(&self);
+    fn add_oversize_dropped(&self);
+}
+pub type ArcUdpOversizeStats = Arc<dyn UdpOversizeStats + Send + Sync>;
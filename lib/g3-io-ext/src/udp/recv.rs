@@ -4,7 +4,8 @@
  */
 
 use std::io;
-use std::net::SocketAddr;
+use std::io::IoSliceMut;
+use std::net::{IpAddr, SocketAddr};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll, ready};
@@ -13,9 +14,10 @@ use std::time::Duration;
 use futures_util::FutureExt;
 use tokio::time::{Instant, Sleep};
 
-use g3_io_sys::udp::RecvMsgHdr;
+use g3_io_sys::udp::{RecvMsgHdr, UdpSocketError};
 
 use crate::limit::{DatagramLimitAction, DatagramLimiter};
+use crate::stats::BufferedIoCount;
 use crate::{ArcLimitedRecvStats, GlobalDatagramLimit};
 
 pub trait AsyncUdpRecv {
@@ -33,6 +35,26 @@ pub trait AsyncUdpRecv {
         hdr: &mut RecvMsgHdr<'_, C>,
     ) -> Poll<io::Result<()>>;
 
+    /// Receive a packet with its peer address and the local destination ip address.
+    ///
+    /// The destination ip will only be available if IP_PKTINFO / IPV6_PKTINFO is
+    /// enabled on the socket, which is the case for sockets bound to the unspecified address.
+    fn poll_recv_from_to(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr, Option<IpAddr>)>> {
+        let mut hdr = RecvMsgHdr::new([IoSliceMut::new(buf)]);
+        ready!(self.poll_recvmsg(cx, &mut hdr))?;
+        let Some(peer_addr) = hdr.src_addr() else {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no peer address returned",
+            )));
+        };
+        Poll::Ready(Ok((hdr.n_recv, peer_addr, hdr.dst_ip())))
+    }
+
     #[cfg(any(
         target_os = "linux",
         target_os = "android",
@@ -47,6 +69,13 @@ pub trait AsyncUdpRecv {
         cx: &mut Context<'_>,
         hdr_v: &mut [RecvMsgHdr<'_, C>],
     ) -> Poll<io::Result<usize>>;
+
+    /// Take one error from the socket error queue without blocking.
+    ///
+    /// It should be called after a recv error to get the details, such as ICMP errors.
+    fn try_recv_err(&mut self) -> io::Result<Option<UdpSocketError>> {
+        Ok(None)
+    }
 }
 
 pub struct LimitedUdpRecv<T> {
@@ -54,7 +83,12 @@ pub struct LimitedUdpRecv<T> {
     delay: Pin<Box<Sleep>>,
     started: Instant,
     limit: DatagramLimiter,
+    throttled_since: Option<Instant>,
     stats: ArcLimitedRecvStats,
+    buffered: BufferedIoCount,
+    buffered_overhead: BufferedIoCount,
+    header_len: Option<fn(&[u8]) -> usize>,
+    limit_payload: bool,
 }
 
 impl<T: AsyncUdpRecv> LimitedUdpRecv<T> {
@@ -70,7 +104,12 @@ impl<T: AsyncUdpRecv> LimitedUdpRecv<T> {
             delay: Box::pin(tokio::time::sleep(Duration::from_millis(0))),
             started: Instant::now(),
             limit: DatagramLimiter::with_local(shift_millis, max_packets, max_bytes),
+            throttled_since: None,
             stats,
+            buffered: BufferedIoCount::default(),
+            buffered_overhead: BufferedIoCount::default(),
+            header_len: None,
+            limit_payload: false,
         }
     }
 
@@ -87,8 +126,127 @@ impl<T: AsyncUdpRecv> LimitedUdpRecv<T> {
     }
 
     pub fn reset_stats(&mut self, stats: ArcLimitedRecvStats) {
+        self.flush_stats();
         self.stats = stats;
     }
+
+    /// Set the interval for flushing the buffered counts into the stats
+    ///
+    /// The default value is set by [crate::set_io_stats_flush_interval].
+    pub fn set_stats_flush_interval(&mut self, interval: Duration) {
+        self.buffered.set_interval(interval);
+        self.buffered_overhead.set_interval(interval);
+    }
+
+    /// Set the function to get the protocol header length of each received packet
+    ///
+    /// The header bytes will be counted as overhead bytes instead of recv bytes in the stats.
+    /// Only the first io slice will be checked for the `recvmsg` like methods.
+    pub fn set_header_len_fn(&mut self, f: fn(&[u8]) -> usize) {
+        self.header_len = Some(f);
+    }
+
+    /// Set whether the speed limit should only count the payload bytes
+    ///
+    /// The default is to count all the bytes on the wire, including the protocol header.
+    pub fn set_limit_payload_only(&mut self, enable: bool) {
+        self.limit_payload = enable;
+    }
+
+    fn poll_throttled<O>(&mut self, cx: &mut Context<'_>, until: Instant) -> Poll<O> {
+        self.flush_stats();
+        if self.throttled_since.is_none() {
+            self.throttled_since = Some(Instant::now());
+        }
+        self.delay.as_mut().reset(until);
+        match self.delay.poll_unpin(cx) {
+            Poll::Ready(_) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn end_throttled(&mut self) {
+        if let Some(since) = self.throttled_since.take() {
+            self.stats.add_recv_throttled(since.elapsed());
+        }
+    }
+}
+
+impl<T> LimitedUdpRecv<T> {
+    #[inline]
+    fn header_size(&self, buf: &[u8]) -> usize {
+        match self.header_len {
+            Some(f) => f(buf),
+            None => 0,
+        }
+    }
+
+    fn recvmsg_header_size<const C: usize>(&self, hdr: &RecvMsgHdr<'_, C>) -> usize {
+        match hdr.iov.first() {
+            Some(iov) => self.header_size(&iov[..hdr.n_recv.min(iov.len())]),
+            None => 0,
+        }
+    }
+
+    /// Get the total received bytes and header bytes of the received messages
+    fn recvmsg_size<const C: usize>(&self, hdr_v: &[RecvMsgHdr<'_, C>]) -> (usize, usize) {
+        hdr_v.iter().fold((0, 0), |(len, overhead), hdr| {
+            (len + hdr.n_recv, overhead + self.recvmsg_header_size(hdr))
+        })
+    }
+
+    #[inline]
+    fn limit_size(&self, size: usize, overhead: usize) -> usize {
+        if self.limit_payload {
+            size.saturating_sub(overhead)
+        } else {
+            size
+        }
+    }
+
+    /// Add the counts of received packets, the overhead bytes are included in `bytes`
+    #[inline]
+    fn add_recv(&mut self, packets: usize, bytes: usize, overhead: usize) {
+        let overhead = overhead.min(bytes);
+        if let Some((packets, bytes)) = self.buffered.add(packets, bytes - overhead) {
+            self.stats.add_recv_packets(packets);
+            self.stats.add_recv_bytes(bytes);
+        }
+        if overhead > 0 {
+            if let Some((_, size)) = self.buffered_overhead.add(0, overhead) {
+                self.stats.add_recv_overhead_bytes(size);
+            }
+        }
+    }
+
+    /// Flush the buffered counts at once if there is no more packet to receive
+    #[inline]
+    fn flush_if_pending<O>(&mut self, poll: Poll<O>) -> Poll<O> {
+        if poll.is_pending() {
+            self.flush_stats();
+        }
+        poll
+    }
+
+    /// Flush the buffered counts into the stats
+    pub fn flush_stats(&mut self) {
+        if let Some((packets, bytes)) = self.buffered.take() {
+            self.stats.add_recv_packets(packets);
+            self.stats.add_recv_bytes(bytes);
+        }
+        if let Some((_, size)) = self.buffered_overhead.take() {
+            self.stats.add_recv_overhead_bytes(size);
+        }
+    }
+}
+
+impl<T> Drop for LimitedUdpRecv<T> {
+    fn drop(&mut self) {
+        self.flush_stats();
+    }
 }
 
 impl<T> AsyncUdpRecv for LimitedUdpRecv<T>
@@ -105,9 +263,11 @@ where
             match self.limit.check_packet(dur_millis, buf.len()) {
                 DatagramLimitAction::Advance(_) => match self.inner.poll_recv_from(cx, buf) {
                     Poll::Ready(Ok((nr, addr))) => {
-                        self.limit.set_advance(1, nr);
-                        self.stats.add_recv_packet();
-                        self.stats.add_recv_bytes(nr);
+                        self.end_throttled();
+                        let overhead = self.header_size(&buf[..nr]);
+                        let limit_size = self.limit_size(nr, overhead);
+                        self.limit.set_advance(1, limit_size);
+                        self.add_recv(1, nr, overhead);
                         Poll::Ready(Ok((nr, addr)))
                     }
                     Poll::Ready(Err(e)) => {
@@ -116,36 +276,21 @@ where
                     }
                     Poll::Pending => {
                         self.limit.release_global();
+                        self.flush_stats();
                         Poll::Pending
                     }
                 },
-                DatagramLimitAction::DelayUntil(t) => {
-                    self.delay.as_mut().reset(t);
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
-                }
+                DatagramLimitAction::DelayUntil(t) => self.poll_throttled(cx, t),
                 DatagramLimitAction::DelayFor(ms) => {
-                    self.delay
-                        .as_mut()
-                        .reset(self.started + Duration::from_millis(dur_millis + ms));
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
+                    let t = self.started + Duration::from_millis(dur_millis + ms);
+                    self.poll_throttled(cx, t)
                 }
             }
         } else {
-            let (nr, addr) = ready!(self.inner.poll_recv_from(cx, buf))?;
-            self.stats.add_recv_packet();
-            self.stats.add_recv_bytes(nr);
+            let r = self.inner.poll_recv_from(cx, buf);
+            let (nr, addr) = ready!(self.flush_if_pending(r))?;
+            let overhead = self.header_size(&buf[..nr]);
+            self.add_recv(1, nr, overhead);
             Poll::Ready(Ok((nr, addr)))
         }
     }
@@ -156,9 +301,11 @@ where
             match self.limit.check_packet(dur_millis, buf.len()) {
                 DatagramLimitAction::Advance(_) => match self.inner.poll_recv(cx, buf) {
                     Poll::Ready(Ok(nr)) => {
-                        self.limit.set_advance(1, nr);
-                        self.stats.add_recv_packet();
-                        self.stats.add_recv_bytes(nr);
+                        self.end_throttled();
+                        let overhead = self.header_size(&buf[..nr]);
+                        let limit_size = self.limit_size(nr, overhead);
+                        self.limit.set_advance(1, limit_size);
+                        self.add_recv(1, nr, overhead);
                         Poll::Ready(Ok(nr))
                     }
                     Poll::Ready(Err(e)) => {
@@ -167,36 +314,21 @@ where
                     }
                     Poll::Pending => {
                         self.limit.release_global();
+                        self.flush_stats();
                         Poll::Pending
                     }
                 },
-                DatagramLimitAction::DelayUntil(t) => {
-                    self.delay.as_mut().reset(t);
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
-                }
+                DatagramLimitAction::DelayUntil(t) => self.poll_throttled(cx, t),
                 DatagramLimitAction::DelayFor(ms) => {
-                    self.delay
-                        .as_mut()
-                        .reset(self.started + Duration::from_millis(dur_millis + ms));
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
+                    let t = self.started + Duration::from_millis(dur_millis + ms);
+                    self.poll_throttled(cx, t)
                 }
             }
         } else {
-            let nr = ready!(self.inner.poll_recv(cx, buf))?;
-            self.stats.add_recv_packet();
-            self.stats.add_recv_bytes(nr);
+            let r = self.inner.poll_recv(cx, buf);
+            let nr = ready!(self.flush_if_pending(r))?;
+            let overhead = self.header_size(&buf[..nr]);
+            self.add_recv(1, nr, overhead);
             Poll::Ready(Ok(nr))
         }
     }
@@ -212,9 +344,11 @@ where
             match self.limit.check_packet(dur_millis, total_size) {
                 DatagramLimitAction::Advance(_) => match self.inner.poll_recvmsg(cx, hdr) {
                     Poll::Ready(Ok(_)) => {
-                        self.limit.set_advance(1, hdr.n_recv);
-                        self.stats.add_recv_packet();
-                        self.stats.add_recv_bytes(hdr.n_recv);
+                        self.end_throttled();
+                        let overhead = self.recvmsg_header_size(hdr);
+                        let limit_size = self.limit_size(hdr.n_recv, overhead);
+                        self.limit.set_advance(1, limit_size);
+                        self.add_recv(1, hdr.n_recv, overhead);
                         Poll::Ready(Ok(()))
                     }
                     Poll::Ready(Err(e)) => {
@@ -223,36 +357,21 @@ where
                     }
                     Poll::Pending => {
                         self.limit.release_global();
+                        self.flush_stats();
                         Poll::Pending
                     }
                 },
-                DatagramLimitAction::DelayUntil(t) => {
-                    self.delay.as_mut().reset(t);
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
-                }
+                DatagramLimitAction::DelayUntil(t) => self.poll_throttled(cx, t),
                 DatagramLimitAction::DelayFor(ms) => {
-                    self.delay
-                        .as_mut()
-                        .reset(self.started + Duration::from_millis(dur_millis + ms));
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
+                    let t = self.started + Duration::from_millis(dur_millis + ms);
+                    self.poll_throttled(cx, t)
                 }
             }
         } else {
-            ready!(self.inner.poll_recvmsg(cx, hdr))?;
-            self.stats.add_recv_packet();
-            self.stats.add_recv_bytes(hdr.n_recv);
+            let r = self.inner.poll_recvmsg(cx, hdr);
+            ready!(self.flush_if_pending(r))?;
+            let overhead = self.recvmsg_header_size(hdr);
+            self.add_recv(1, hdr.n_recv, overhead);
             Poll::Ready(Ok(()))
         }
     }
@@ -285,10 +404,11 @@ where
                 DatagramLimitAction::Advance(n) => {
                     match self.inner.poll_batch_recvmsg(cx, &mut hdr_v[0..n]) {
                         Poll::Ready(Ok(count)) => {
-                            let len = hdr_v.iter().take(count).map(|h| h.n_recv).sum();
-                            self.limit.set_advance(count, len);
-                            self.stats.add_recv_packets(count);
-                            self.stats.add_recv_bytes(len);
+                            self.end_throttled();
+                            let (len, overhead) = self.recvmsg_size(&hdr_v[..count]);
+                            let limit_size = self.limit_size(len, overhead);
+                            self.limit.set_advance(count, limit_size);
+                            self.add_recv(count, len, overhead);
                             Poll::Ready(Ok(count))
                         }
                         Poll::Ready(Err(e)) => {
@@ -297,39 +417,26 @@ where
                         }
                         Poll::Pending => {
                             self.limit.release_global();
+                            self.flush_stats();
                             Poll::Pending
                         }
                     }
                 }
-                DatagramLimitAction::DelayUntil(t) => {
-                    self.delay.as_mut().reset(t);
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
-                }
+                DatagramLimitAction::DelayUntil(t) => self.poll_throttled(cx, t),
                 DatagramLimitAction::DelayFor(ms) => {
-                    self.delay
-                        .as_mut()
-                        .reset(self.started + Duration::from_millis(dur_millis + ms));
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
+                    let t = self.started + Duration::from_millis(dur_millis + ms);
+                    self.poll_throttled(cx, t)
                 }
             }
         } else {
-            let count = ready!(self.inner.poll_batch_recvmsg(cx, hdr_v))?;
-            self.stats.add_recv_packets(count);
-            self.stats
-                .add_recv_bytes(hdr_v.iter().take(count).map(|h| h.n_recv).sum());
+            let r = self.inner.poll_batch_recvmsg(cx, hdr_v);
+            let count = ready!(self.flush_if_pending(r))?;
+            let (len, overhead) = self.recvmsg_size(&hdr_v[..count]);
+            self.add_recv(count, len, overhead);
             Poll::Ready(Ok(count))
         }
     }
+    fn try_recv_err(&mut self) -> io::Result<Option<UdpSocketError>> {
+        self.inner.try_recv_err()
+    }
 }
@@ -0,0 +1,330 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::cell::UnsafeCell;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::Notify;
+
+use g3_types::net::UpstreamAddr;
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+const PCAP_VERSION_MAJOR: u16 = 2;
+const PCAP_VERSION_MINOR: u16 = 4;
+const PCAP_SNAP_LEN: u32 = 262144;
+/// LINKTYPE_USER0, the packet data starts with our own pseudo header
+const PCAP_LINK_TYPE: u32 = 147;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UdpRelayDirection {
+    ClientToRemote,
+    RemoteToClient,
+}
+
+impl UdpRelayDirection {
+    fn pcap_code(&self) -> u8 {
+        match self {
+            UdpRelayDirection::ClientToRemote => 1,
+            UdpRelayDirection::RemoteToClient => 2,
+        }
+    }
+}
+
+/// Hook that will be called for each relayed packet
+pub trait UdpRelayPacketTap: Send + Sync {
+    /// `ups` is the target address for client to remote packets,
+    /// and the source address for remote to client packets
+    fn tap(&self, direction: UdpRelayDirection, ups: &UpstreamAddr, payload: &[u8]);
+}
+
+struct UdpCapturedPacket {
+    time: Duration,
+    direction: UdpRelayDirection,
+    client: SocketAddr,
+    ups: UpstreamAddr,
+    data_offset: usize,
+    data_len: usize,
+}
+
+impl UdpCapturedPacket {
+    fn write_pcap_record<W: Write>(&self, w: &mut W, payload: &[u8]) -> io::Result<()> {
+        let client = self.client.to_string();
+        let ups = self.ups.to_string();
+        let hdr_len = 2 + 2 + client.len() + 2 + ups.len();
+        let orig_len = hdr_len + payload.len();
+        let incl_len = orig_len.min(PCAP_SNAP_LEN as usize);
+
+        w.write_all(&(self.time.as_secs() as u32).to_le_bytes())?;
+        w.write_all(&self.time.subsec_micros().to_le_bytes())?;
+        w.write_all(&(incl_len as u32).to_le_bytes())?;
+        w.write_all(&(orig_len as u32).to_le_bytes())?;
+
+        w.write_all(&[self.direction.pcap_code(), 0])?;
+        w.write_all(&(client.len() as u16).to_be_bytes())?;
+        w.write_all(client.as_bytes())?;
+        w.write_all(&(ups.len() as u16).to_be_bytes())?;
+        w.write_all(ups.as_bytes())?;
+        w.write_all(&payload[..incl_len - hdr_len])
+    }
+}
+
+/// A bounded in-memory capture of sampled udp relay packets.
+///
+/// Both the packet slots and the payload storage are preallocated, and are bounded by
+/// `max_packets` and `max_bytes`. The sampling and the store of packets are wait-free,
+/// samples will be dropped if the capture is full.
+pub struct UdpRelayCapture {
+    ratio: f64,
+    slots: Box<[OnceLock<UdpCapturedPacket>]>,
+    next: AtomicUsize,
+    data: Box<[UnsafeCell<u8>]>,
+    data_used: AtomicUsize,
+    dropped: AtomicU64,
+    stopped: AtomicBool,
+    done: Notify,
+}
+
+// SAFETY: the payload storage is only written in ranges reserved by the atomic `data_used`,
+// which never overlap, and a range is only read after the slot that refers to it has been set
+unsafe impl Sync for UdpRelayCapture {}
+
+impl UdpRelayCapture {
+    pub fn new(ratio: f64, max_packets: usize, max_bytes: usize) -> Self {
+        let slots = (0..max_packets).map(|_| OnceLock::new()).collect();
+        let data = vec![0u8; max_bytes].into_boxed_slice();
+        // SAFETY: UnsafeCell<u8> has the same in-memory representation as u8
+        let data = unsafe { Box::from_raw(Box::into_raw(data) as *mut [UnsafeCell<u8>]) };
+        UdpRelayCapture {
+            ratio,
+            slots,
+            next: AtomicUsize::new(0),
+            data,
+            data_used: AtomicUsize::new(0),
+            dropped: AtomicU64::new(0),
+            stopped: AtomicBool::new(false),
+            done: Notify::new(),
+        }
+    }
+
+    /// Sample the packet, return true if it has been captured
+    pub fn sample(
+        &self,
+        direction: UdpRelayDirection,
+        client: SocketAddr,
+        ups: &UpstreamAddr,
+        payload: &[u8],
+    ) -> bool {
+        if self.stopped.load(Ordering::Relaxed) {
+            return false;
+        }
+        if self.ratio < 1.0 && fastrand::f64() >= self.ratio {
+            return false;
+        }
+
+        let index = self.next.fetch_add(1, Ordering::AcqRel);
+        let Some(slot) = self.slots.get(index) else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        };
+        let data_offset = self.data_used.fetch_add(payload.len(), Ordering::Relaxed);
+        let Some(data) = data_offset
+            .checked_add(payload.len())
+            .and_then(|end| self.data.get(data_offset..end))
+        else {
+            // the byte budget is exhausted, the reserved slot will be left empty
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            self.stop();
+            return false;
+        };
+        // SAFETY: the range is reserved for this packet only
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                payload.as_ptr(),
+                UnsafeCell::raw_get(data.as_ptr()),
+                payload.len(),
+            );
+        }
+
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let packet = UdpCapturedPacket {
+            time,
+            direction,
+            client,
+            ups: ups.clone(),
+            data_offset,
+            data_len: payload.len(),
+        };
+        if slot.set(packet).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        if index + 1 == self.slots.len() {
+            self.done.notify_one();
+        }
+        true
+    }
+
+    /// Stop the capture, new packets will no longer be sampled
+    pub fn stop(&self) {
+        if !self.stopped.swap(true, Ordering::Relaxed) {
+            self.done.notify_one();
+        }
+    }
+
+    /// Wait until the capture is full or stopped
+    pub async fn wait_done(&self) {
+        self.done.notified().await;
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Get the count of samples that have been dropped
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Get the count of captured packets
+    pub fn captured(&self) -> usize {
+        self.slots.iter().filter(|s| s.get().is_some()).count()
+    }
+
+    fn payload(&self, packet: &UdpCapturedPacket) -> &[u8] {
+        let data = &self.data[packet.data_offset..packet.data_offset + packet.data_len];
+        // SAFETY: the range has been filled before the slot is set, and will not be written again
+        unsafe { std::slice::from_raw_parts(UnsafeCell::raw_get(data.as_ptr()), data.len()) }
+    }
+
+    /// Write all captured packets in pcap format.
+    ///
+    /// Each packet starts with a pseudo header:
+    ///
+    ///   - direction: u8, 1 for client to remote, 2 for remote to client
+    ///   - reserved: u8
+    ///   - client address length: u16 in big endian, followed by the client address string
+    ///   - upstream address length: u16 in big endian, followed by the upstream address string
+    ///
+    /// Return the number of packets written.
+    pub fn write_pcap<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        w.write_all(&PCAP_MAGIC.to_le_bytes())?;
+        w.write_all(&PCAP_VERSION_MAJOR.to_le_bytes())?;
+        w.write_all(&PCAP_VERSION_MINOR.to_le_bytes())?;
+        w.write_all(&0i32.to_le_bytes())?; // thiszone
+        w.write_all(&0u32.to_le_bytes())?; // sigfigs
+        w.write_all(&PCAP_SNAP_LEN.to_le_bytes())?;
+        w.write_all(&PCAP_LINK_TYPE.to_le_bytes())?;
+
+        let mut count = 0;
+        for slot in self.slots.iter() {
+            // the slot may have been reserved but not yet filled
+            if let Some(packet) = slot.get() {
+                packet.write_pcap_record(w, self.payload(packet))?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn pcap_format() {
+        let client = SocketAddr::from_str("192.0.2.1:5353").unwrap();
+        let ups = UpstreamAddr::from_str("www.example.net:53").unwrap();
+        let capture = UdpRelayCapture::new(1.0, 2, 1024);
+        assert!(capture.sample(UdpRelayDirection::ClientToRemote, client, &ups, b"ping"));
+        assert!(capture.sample(UdpRelayDirection::RemoteToClient, client, &ups, b"pong!"));
+        assert!(!capture.sample(UdpRelayDirection::ClientToRemote, client, &ups, b"ping"));
+        assert_eq!(capture.dropped(), 1);
+        assert_eq!(capture.captured(), 2);
+
+        let mut data = Vec::new();
+        assert_eq!(capture.write_pcap(&mut data).unwrap(), 2);
+
+        assert_eq!(read_u32(&data, 0), PCAP_MAGIC);
+        assert_eq!(&data[4..8], &[2, 0, 4, 0]);
+        assert_eq!(read_u32(&data, 16), PCAP_SNAP_LEN);
+        assert_eq!(read_u32(&data, 20), PCAP_LINK_TYPE);
+
+        let mut offset = 24;
+        for (code, payload) in [(1u8, b"ping".as_slice()), (2, b"pong!")] {
+            let incl_len = read_u32(&data, offset + 8) as usize;
+            let orig_len = read_u32(&data, offset + 12) as usize;
+            assert_eq!(incl_len, orig_len);
+            let record = &data[offset + 16..offset + 16 + incl_len];
+            assert_eq!(record[0], code);
+
+            let client_len = u16::from_be_bytes([record[2], record[3]]) as usize;
+            assert_eq!(&record[4..4 + client_len], b"192.0.2.1:5353");
+            let ups_off = 4 + client_len;
+            let ups_len = u16::from_be_bytes([record[ups_off], record[ups_off + 1]]) as usize;
+            let ups_end = ups_off + 2 + ups_len;
+            assert_eq!(&record[ups_off + 2..ups_end], b"www.example.net:53");
+            assert_eq!(&record[ups_end..], payload);
+
+            offset += 16 + incl_len;
+        }
+        assert_eq!(offset, data.len());
+    }
+
+    #[test]
+    fn sample_ratio() {
+        let client = SocketAddr::from_str("192.0.2.1:5353").unwrap();
+        let ups = UpstreamAddr::from_str("192.0.2.2:53").unwrap();
+        let total = 100_000;
+        let capture = UdpRelayCapture::new(0.1, total, total);
+        let mut sampled = 0;
+        for _ in 0..total {
+            if capture.sample(UdpRelayDirection::ClientToRemote, client, &ups, b"x") {
+                sampled += 1;
+            }
+        }
+        assert_eq!(capture.captured(), sampled);
+        assert!((9_000..=11_000).contains(&sampled), "sampled {sampled}");
+    }
+
+    #[tokio::test]
+    async fn stop() {
+        let client = SocketAddr::from_str("192.0.2.1:5353").unwrap();
+        let ups = UpstreamAddr::from_str("192.0.2.2:53").unwrap();
+        let capture = UdpRelayCapture::new(1.0, 8, 1024);
+        assert!(capture.sample(UdpRelayDirection::ClientToRemote, client, &ups, b"x"));
+        capture.stop();
+        capture.wait_done().await;
+        assert!(!capture.sample(UdpRelayDirection::ClientToRemote, client, &ups, b"x"));
+        assert_eq!(capture.captured(), 1);
+    }
+
+    #[tokio::test]
+    async fn byte_budget() {
+        let client = SocketAddr::from_str("192.0.2.1:5353").unwrap();
+        let ups = UpstreamAddr::from_str("192.0.2.2:53").unwrap();
+        let capture = UdpRelayCapture::new(1.0, 8, 10);
+        assert!(capture.sample(UdpRelayDirection::ClientToRemote, client, &ups, b"1234"));
+        assert!(capture.sample(UdpRelayDirection::RemoteToClient, client, &ups, b"5678"));
+        assert!(!capture.sample(UdpRelayDirection::ClientToRemote, client, &ups, b"abcd"));
+        // the capture should be done once the byte budget is exhausted
+        capture.wait_done().await;
+        assert!(!capture.sample(UdpRelayDirection::ClientToRemote, client, &ups, b"x"));
+        assert_eq!(capture.dropped(), 1);
+        assert_eq!(capture.captured(), 2);
+
+        let mut data = Vec::new();
+        assert_eq!(capture.write_pcap(&mut data).unwrap(), 2);
+        assert!(data.ends_with(b"5678"));
+    }
+}
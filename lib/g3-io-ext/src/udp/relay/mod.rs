@@ -3,19 +3,25 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
-use std::io::IoSliceMut;
+use std::io::{self, IoSliceMut};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll, ready};
 
 use thiserror::Error;
 
 use g3_types::net::UpstreamAddr;
 
-use super::LimitedUdpRelayConfig;
+use super::{
+    ArcUdpBatchSizeStats, ArcUdpOversizeStats, LimitedUdpRelayConfig, UdpBatchSizer,
+    UdpDuplicateFilter, UdpOversizePolicy,
+};
 
+mod capture;
 mod client;
 mod remote;
 
+pub use capture::{UdpRelayCapture, UdpRelayDirection, UdpRelayPacketTap};
 pub use client::{UdpRelayClientError, UdpRelayClientRecv, UdpRelayClientSend};
 pub use remote::{UdpRelayRemoteError, UdpRelayRemoteRecv, UdpRelayRemoteSend};
 
@@ -25,6 +31,7 @@ pub struct UdpRelayPacket {
     buf_data_off: usize,
     buf_data_end: usize,
     ups: UpstreamAddr,
+    truncated: bool,
 }
 
 impl UdpRelayPacket {
@@ -35,6 +42,7 @@ impl UdpRelayPacket {
             buf_data_off: 0,
             buf_data_end: 0,
             ups: UpstreamAddr::empty(),
+            truncated: false,
         }
     }
 
@@ -68,6 +76,12 @@ impl UdpRelayPacket {
         &self.ups
     }
 
+    /// Check if the datagram has been truncated as it is larger than the buffer
+    #[inline]
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
     #[inline]
     pub fn payload(&self) -> &[u8] {
         &self.buf[self.buf_data_off..self.buf_data_end]
@@ -79,6 +93,7 @@ pub struct UdpRelayPacketMeta {
     data_off: usize,
     data_len: usize,
     ups: UpstreamAddr,
+    truncated: bool,
 }
 
 impl UdpRelayPacketMeta {
@@ -88,15 +103,23 @@ impl UdpRelayPacketMeta {
             data_off,
             data_len,
             ups,
+            truncated: false,
         }
     }
 
+    /// Mark the datagram as truncated, which should be reported by the recv call
+    pub fn with_truncated(mut self, truncated: bool) -> Self {
+        self.truncated = truncated;
+        self
+    }
+
     pub fn set_packet(self, p: &mut UdpRelayPacket) {
         let iov_advance =
             unsafe { usize::try_from(self.iov_base.offset_from(p.buf().as_ptr())).unwrap() };
         p.set_offset(iov_advance + self.data_off);
         p.set_length(iov_advance + self.data_len);
         p.set_upstream(self.ups);
+        p.truncated = self.truncated;
     }
 }
 
@@ -154,6 +177,7 @@ impl<T: UdpRelayClientRecv + ?Sized> UdpRelayRecv for ClientRecv<'_, T> {
         packet.buf_data_off = off;
         packet.buf_data_end = nr;
         packet.ups = ups;
+        packet.truncated = false;
         Poll::Ready(Ok(nr))
     }
 
@@ -193,6 +217,7 @@ impl<T: UdpRelayRemoteRecv + ?Sized> UdpRelayRecv for RemoteRecv<'_, T> {
         packet.buf_data_off = off;
         packet.buf_data_end = nr;
         packet.ups = ups;
+        packet.truncated = false;
         Poll::Ready(Ok(nr))
     }
 
@@ -216,6 +241,14 @@ impl<T: UdpRelayRemoteRecv + ?Sized> UdpRelayRecv for RemoteRecv<'_, T> {
     }
 }
 
+/// The batch sender should return `Poll::Pending` instead of 0 if no packet could be sent,
+/// or the relay loop would never make progress
+const NO_PACKET_SENT: &str = "no packet accepted by the batch sender";
+
+fn no_packet_sent() -> io::Error {
+    io::Error::new(io::ErrorKind::WriteZero, NO_PACKET_SENT)
+}
+
 trait UdpRelaySend {
     fn poll_send_packet(
         &mut self,
@@ -273,9 +306,12 @@ impl<T: UdpRelayClientSend + ?Sized> UdpRelaySend for ClientSend<'_, T> {
         cx: &mut Context<'_>,
         packets: &[UdpRelayPacket],
     ) -> Poll<Result<usize, UdpRelayError>> {
-        self.0
-            .poll_send_packets(cx, packets)
-            .map_err(UdpRelayError::ClientError)
+        match ready!(self.0.poll_send_packets(cx, packets)) {
+            Ok(0) if !packets.is_empty() => Poll::Ready(Err(UdpRelayError::ClientError(
+                UdpRelayClientError::SendFailed(no_packet_sent()),
+            ))),
+            r => Poll::Ready(r.map_err(UdpRelayError::ClientError)),
+        }
     }
 }
 
@@ -297,15 +333,31 @@ impl<T: UdpRelayRemoteSend + ?Sized> UdpRelaySend for RemoteSend<'_, T> {
         cx: &mut Context<'_>,
         packets: &[UdpRelayPacket],
     ) -> Poll<Result<usize, UdpRelayError>> {
-        self.0
-            .poll_send_packets(cx, packets)
-            .map_err(|e| UdpRelayError::RemoteError(None, e))
+        match ready!(self.0.poll_send_packets(cx, packets)) {
+            Ok(0) if !packets.is_empty() => Poll::Ready(Err(UdpRelayError::RemoteError(
+                Some(packets[0].ups.clone()),
+                UdpRelayRemoteError::InternalServerError(NO_PACKET_SENT),
+            ))),
+            r => Poll::Ready(r.map_err(|e| UdpRelayError::RemoteError(None, e))),
+        }
     }
 }
 
 struct UdpRelayBuffer {
     config: LimitedUdpRelayConfig,
+    direction: UdpRelayDirection,
+    tap: Option<Arc<dyn UdpRelayPacketTap>>,
+    max_hdr_size: usize,
+    /// the current packet size, which may grow if the oversize policy is grow
+    packet_size: usize,
+    grow_pending: bool,
+    batch_sizer: UdpBatchSizer,
+    /// the packets will be kept after the batch size shrinks, and reused when it grows again
     packets: Vec<UdpRelayPacket>,
+    dup_filter: Option<UdpDuplicateFilter>,
+    oversize_stats: Option<ArcUdpOversizeStats>,
+    truncated: u64,
+    oversize_dropped: u64,
     send_start: usize,
     send_end: usize,
     recv_done: bool,
@@ -314,12 +366,27 @@ struct UdpRelayBuffer {
 }
 
 impl UdpRelayBuffer {
-    fn new(max_hdr_size: usize, config: LimitedUdpRelayConfig) -> Self {
+    fn new(
+        max_hdr_size: usize,
+        config: LimitedUdpRelayConfig,
+        direction: UdpRelayDirection,
+    ) -> Self {
+        let batch_sizer = UdpBatchSizer::new(&config);
         let packets =
-            vec![UdpRelayPacket::new(max_hdr_size, config.packet_size); config.batch_size];
+            vec![UdpRelayPacket::new(max_hdr_size, config.packet_size); batch_sizer.current()];
         UdpRelayBuffer {
             config,
+            direction,
+            tap: None,
+            max_hdr_size,
+            packet_size: config.packet_size,
+            grow_pending: false,
+            batch_sizer,
             packets,
+            dup_filter: None,
+            oversize_stats: None,
+            truncated: 0,
+            oversize_dropped: 0,
             send_start: 0,
             send_end: 0,
             recv_done: false,
@@ -340,13 +407,17 @@ impl UdpRelayBuffer {
     {
         let mut copy_this_round = 0usize;
         loop {
-            if !self.recv_done && self.send_end < self.packets.len() {
-                match receiver.poll_recv_packets(cx, &mut self.packets[self.send_end..]) {
+            let batch_size = self.batch_size();
+            if !self.recv_done && self.send_end < batch_size {
+                match receiver.poll_recv_packets(cx, &mut self.packets[self.send_end..batch_size]) {
                     Poll::Ready(Ok(count)) => {
                         if count == 0 {
                             self.recv_done = true;
+                        } else if self.send_end == 0 {
+                            self.batch_sizer.record(count);
                         }
-                        self.send_end += count;
+                        let count = self.filter_oversize(self.send_end, count);
+                        self.send_end += self.filter_duplicate(self.send_end, count);
                         self.active = true;
                     }
                     Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
@@ -361,11 +432,19 @@ impl UdpRelayBuffer {
             while self.send_end > self.send_start {
                 let packets = &self.packets[self.send_start..self.send_end];
                 let count = ready!(sender.poll_send_packets(cx, packets))?;
-                copy_this_round += packets
+                let count = count.min(packets.len());
+                if let Some(tap) = &self.tap {
+                    for p in packets.iter().take(count) {
+                        tap.tap(self.direction, &p.ups, p.payload());
+                    }
+                }
+                let nw = packets
                     .iter()
                     .take(count)
                     .map(|p| p.buf_data_end - p.buf_data_off)
                     .sum::<usize>();
+                copy_this_round += nw;
+                self.total += nw as u64;
                 self.send_start += count;
                 self.active = true;
             }
@@ -383,6 +462,86 @@ impl UdpRelayBuffer {
         }
     }
 
+    /// Get the current batch size, and make sure there are enough packets for it
+    fn batch_size(&mut self) -> usize {
+        let batch_size = self.batch_sizer.current();
+        if self.grow_pending && self.send_end == 0 {
+            // no packet is waiting to be sent, so all of them can be replaced by bigger ones
+            self.packets.clear();
+            self.grow_pending = false;
+        }
+        if self.packets.len() < batch_size {
+            let (max_hdr_size, packet_size) = (self.max_hdr_size, self.packet_size);
+            self.packets.resize_with(batch_size, || {
+                UdpRelayPacket::new(max_hdr_size, packet_size)
+            });
+        }
+        batch_size
+    }
+
+    /// Apply the oversize policy to the truncated ones of the new received packets,
+    /// move the kept ones to the front, and return the count of them
+    fn filter_oversize(&mut self, start: usize, count: usize) -> usize {
+        let mut kept = 0;
+        for i in start..start + count {
+            if self.packets[i].truncated {
+                self.truncated += 1;
+                if let Some(stats) = &self.oversize_stats {
+                    stats.add_truncated();
+                }
+                match self.config.oversize_policy() {
+                    UdpOversizePolicy::ForwardTruncated => {}
+                    UdpOversizePolicy::Drop => {
+                        self.drop_oversize();
+                        continue;
+                    }
+                    UdpOversizePolicy::Grow => {
+                        self.grow_packet_size();
+                        self.drop_oversize();
+                        continue;
+                    }
+                }
+            }
+            self.packets.swap(start + kept, i);
+            kept += 1;
+        }
+        kept
+    }
+
+    fn drop_oversize(&mut self) {
+        self.oversize_dropped += 1;
+        if let Some(stats) = &self.oversize_stats {
+            stats.add_oversize_dropped();
+        }
+    }
+
+    /// Double the packet size up to the ceiling, the new size will be used in later receives
+    fn grow_packet_size(&mut self) {
+        let max_size = self.config.packet_size_max();
+        if self.packet_size < max_size {
+            self.packet_size = (self.packet_size * 2).min(max_size);
+            self.grow_pending = true;
+        }
+    }
+
+    /// Move the non-duplicate ones of the new received packets to the front,
+    /// and return the count of them
+    fn filter_duplicate(&mut self, start: usize, count: usize) -> usize {
+        let Some(filter) = &mut self.dup_filter else {
+            return count;
+        };
+        let mut kept = 0;
+        for i in start..start + count {
+            let p = &self.packets[i];
+            if filter.check_duplicate((p.payload(), &p.ups)) {
+                continue;
+            }
+            self.packets.swap(start + kept, i);
+            kept += 1;
+        }
+        kept
+    }
+
     fn is_idle(&self) -> bool {
         !self.active
     }
@@ -390,6 +549,29 @@ impl UdpRelayBuffer {
     fn reset_active(&mut self) {
         self.active = false;
     }
+
+    fn set_tap(&mut self, tap: Arc<dyn UdpRelayPacketTap>) {
+        self.tap = Some(tap);
+    }
+
+    fn set_duplicate_filter(&mut self, filter: UdpDuplicateFilter) {
+        self.dup_filter = Some(filter);
+    }
+
+    fn set_oversize_stats(&mut self, stats: ArcUdpOversizeStats) {
+        self.oversize_stats = Some(stats);
+    }
+
+    fn set_batch_size_stats(&mut self, stats: ArcUdpBatchSizeStats) {
+        self.batch_sizer.set_stats(stats);
+    }
+
+    fn duplicate_dropped(&self) -> u64 {
+        self.dup_filter
+            .as_ref()
+            .map(|f| f.dropped())
+            .unwrap_or_default()
+    }
 }
 
 pub struct UdpRelayClientToRemote<'a, C: ?Sized, R: ?Sized> {
@@ -404,7 +586,11 @@ where
     R: UdpRelayRemoteSend + ?Sized,
 {
     pub fn new(client: &'a mut C, remote: &'a mut R, config: LimitedUdpRelayConfig) -> Self {
-        let buffer = UdpRelayBuffer::new(client.max_hdr_len(), config);
+        let buffer = UdpRelayBuffer::new(
+            client.max_hdr_len(),
+            config,
+            UdpRelayDirection::ClientToRemote,
+        );
         UdpRelayClientToRemote {
             client,
             remote,
@@ -421,6 +607,59 @@ where
     pub fn reset_active(&mut self) {
         self.buffer.reset_active()
     }
+
+    /// Get the current recv batch size
+    #[inline]
+    pub fn batch_size(&self) -> usize {
+        self.buffer.batch_sizer.current()
+    }
+
+    /// Set the gauge to be updated when the recv batch size changes
+    #[inline]
+    pub fn set_batch_size_stats(&mut self, stats: ArcUdpBatchSizeStats) {
+        self.buffer.set_batch_size_stats(stats)
+    }
+
+    /// Set a hook to be called for each packet that has been relayed
+    #[inline]
+    pub fn set_packet_tap(&mut self, tap: Arc<dyn UdpRelayPacketTap>) {
+        self.buffer.set_tap(tap)
+    }
+
+    /// Set the counters to be updated when truncated packets are received
+    #[inline]
+    pub fn set_oversize_stats(&mut self, stats: ArcUdpOversizeStats) {
+        self.buffer.set_oversize_stats(stats)
+    }
+
+    /// Get the count of the received packets that have been truncated
+    #[inline]
+    pub fn truncated(&self) -> u64 {
+        self.buffer.truncated
+    }
+
+    /// Get the count of the truncated packets that have been dropped by the oversize policy
+    #[inline]
+    pub fn oversize_dropped(&self) -> u64 {
+        self.buffer.oversize_dropped
+    }
+
+    /// Get the current packet size, which may grow if the oversize policy is grow
+    #[inline]
+    pub fn packet_size(&self) -> usize {
+        self.buffer.packet_size
+    }
+
+    /// Drop the duplicate packets from the client before sending to the remote
+    #[inline]
+    pub fn set_duplicate_filter(&mut self, filter: UdpDuplicateFilter) {
+        self.buffer.set_duplicate_filter(filter)
+    }
+
+    #[inline]
+    pub fn duplicate_dropped(&self) -> u64 {
+        self.buffer.duplicate_dropped()
+    }
 }
 
 impl<C, R> Future for UdpRelayClientToRemote<'_, C, R>
@@ -449,7 +688,11 @@ where
     R: UdpRelayRemoteRecv + ?Sized,
 {
     pub fn new(client: &'a mut C, remote: &'a mut R, config: LimitedUdpRelayConfig) -> Self {
-        let buffer = UdpRelayBuffer::new(remote.max_hdr_len(), config);
+        let buffer = UdpRelayBuffer::new(
+            remote.max_hdr_len(),
+            config,
+            UdpRelayDirection::RemoteToClient,
+        );
         UdpRelayRemoteToClient {
             client,
             remote,
@@ -466,6 +709,54 @@ where
     pub fn reset_active(&mut self) {
         self.buffer.reset_active()
     }
+
+    /// Get the current recv batch size
+    #[inline]
+    pub fn batch_size(&self) -> usize {
+        self.buffer.batch_sizer.current()
+    }
+
+    /// Set the gauge to be updated when the recv batch size changes
+    #[inline]
+    pub fn set_batch_size_stats(&mut self, stats: ArcUdpBatchSizeStats) {
+        self.buffer.set_batch_size_stats(stats)
+    }
+
+    /// Set a hook to be called for each packet that has been relayed
+    #[inline]
+    pub fn set_packet_tap(&mut self, tap: Arc<dyn UdpRelayPacketTap>) {
+        self.buffer.set_tap(tap)
+    }
+
+    /// Set the counters to be updated when truncated packets are received
+    #[inline]
+    pub fn set_oversize_stats(&mut self, stats: ArcUdpOversizeStats) {
+        self.buffer.set_oversize_stats(stats)
+    }
+
+    /// Get the count of the received packets that have been truncated
+    #[inline]
+    pub fn truncated(&self) -> u64 {
+        self.buffer.truncated
+    }
+
+    /// Get the count of the truncated packets that have been dropped by the oversize policy
+    #[inline]
+    pub fn oversize_dropped(&self) -> u64 {
+        self.buffer.oversize_dropped
+    }
+
+    /// Get the current packet size, which may grow if the oversize policy is grow
+    #[inline]
+    pub fn packet_size(&self) -> usize {
+        self.buffer.packet_size
+    }
+
+    /// Get the client side sender, which can be used to send extra packets to the client
+    #[inline]
+    pub fn client_mut(&mut self) -> &mut C {
+        self.client
+    }
 }
 
 impl<C, R> Future for UdpRelayRemoteToClient<'_, C, R>
@@ -481,3 +772,375 @@ where
             .poll_batch_relay(cx, RemoteRecv(me.remote), ClientSend(me.client))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::str::FromStr;
+
+    struct MockClientRecv {
+        queue: VecDeque<(Vec<u8>, UpstreamAddr)>,
+    }
+
+    impl MockClientRecv {
+        fn new(count: usize) -> Self {
+            MockClientRecv {
+                queue: (0..count)
+                    .map(|i| {
+                        let ups =
+                            UpstreamAddr::from_str(&format!("127.0.0.{}:53", i % 4 + 1)).unwrap();
+                        (format!("packet {i}").into_bytes(), ups)
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    impl UdpRelayClientRecv for MockClientRecv {
+        fn max_hdr_len(&self) -> usize {
+            0
+        }
+
+        fn poll_recv_packet(
+            &mut self,
+            _cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<Result<(usize, usize, UpstreamAddr), UdpRelayClientError>> {
+            let Some((data, ups)) = self.queue.pop_front() else {
+                return Poll::Ready(Ok((0, 0, UpstreamAddr::empty())));
+            };
+            buf[..data.len()].copy_from_slice(&data);
+            Poll::Ready(Ok((0, data.len(), ups)))
+        }
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "macos",
+            target_os = "solaris",
+        ))]
+        fn poll_recv_packets(
+            &mut self,
+            _cx: &mut Context<'_>,
+            packets: &mut [UdpRelayPacket],
+        ) -> Poll<Result<usize, UdpRelayClientError>> {
+            let mut count = 0;
+            for p in packets.iter_mut() {
+                let Some((data, ups)) = self.queue.pop_front() else {
+                    break;
+                };
+                p.buf[..data.len()].copy_from_slice(&data);
+                p.set_offset(0);
+                p.set_length(data.len());
+                p.set_upstream(ups);
+                count += 1;
+            }
+            Poll::Ready(Ok(count))
+        }
+    }
+
+    /// accept only one packet per poll, and return pending on every other poll
+    #[derive(Default)]
+    struct MockRemoteSend {
+        sent: Vec<(Vec<u8>, UpstreamAddr)>,
+        blocked: bool,
+    }
+
+    impl UdpRelayRemoteSend for MockRemoteSend {
+        fn poll_send_packet(
+            &mut self,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+            to: &UpstreamAddr,
+        ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+            self.sent.push((buf.to_vec(), to.clone()));
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_send_packets(
+            &mut self,
+            cx: &mut Context<'_>,
+            packets: &[UdpRelayPacket],
+        ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+            if self.blocked {
+                self.blocked = false;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.blocked = true;
+            let p = &packets[0];
+            self.sent.push((p.payload().to_vec(), p.upstream().clone()));
+            Poll::Ready(Ok(1))
+        }
+    }
+
+    #[tokio::test]
+    async fn partial_batch_send_no_duplicate() {
+        let mut client = MockClientRecv::new(20);
+        let expected: Vec<(Vec<u8>, UpstreamAddr)> = client.queue.iter().cloned().collect();
+        let mut remote = MockRemoteSend::default();
+
+        let c_to_r =
+            UdpRelayClientToRemote::new(&mut client, &mut remote, LimitedUdpRelayConfig::default());
+        let total = c_to_r.await.unwrap();
+
+        assert_eq!(remote.sent, expected);
+        assert_eq!(
+            total,
+            expected.iter().map(|(v, _)| v.len() as u64).sum::<u64>()
+        );
+    }
+
+    /// a broken batch sender which accepts no packet but reports no error
+    struct MockZeroRemoteSend;
+
+    impl UdpRelayRemoteSend for MockZeroRemoteSend {
+        fn poll_send_packet(
+            &mut self,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+            _to: &UpstreamAddr,
+        ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_send_packets(
+            &mut self,
+            _cx: &mut Context<'_>,
+            _packets: &[UdpRelayPacket],
+        ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+            Poll::Ready(Ok(0))
+        }
+    }
+
+    #[tokio::test]
+    async fn zero_batch_send() {
+        let mut client = MockClientRecv::new(4);
+        let mut remote = MockZeroRemoteSend;
+
+        let c_to_r =
+            UdpRelayClientToRemote::new(&mut client, &mut remote, LimitedUdpRelayConfig::default());
+        let r = tokio::time::timeout(std::time::Duration::from_secs(1), c_to_r)
+            .await
+            .unwrap();
+        assert!(matches!(
+            r,
+            Err(UdpRelayError::RemoteError(
+                Some(_),
+                UdpRelayRemoteError::InternalServerError(NO_PACKET_SENT)
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn duplicate_filter() {
+        let ups1 = UpstreamAddr::from_str("127.0.0.1:53").unwrap();
+        let ups2 = UpstreamAddr::from_str("127.0.0.2:53").unwrap();
+        let mut client = MockClientRecv {
+            queue: vec![
+                (b"a".to_vec(), ups1.clone()),
+                (b"a".to_vec(), ups2.clone()),
+                (b"a".to_vec(), ups1.clone()),
+                (b"a".to_vec(), ups1.clone()),
+            ]
+            .into(),
+        };
+        let mut remote = MockRemoteSend::default();
+
+        let mut c_to_r =
+            UdpRelayClientToRemote::new(&mut client, &mut remote, LimitedUdpRelayConfig::default());
+        c_to_r.set_duplicate_filter(UdpDuplicateFilter::new(
+            crate::udp::UdpDuplicateFilterConfig::default(),
+        ));
+        (&mut c_to_r).await.unwrap();
+        assert_eq!(c_to_r.duplicate_dropped(), 2);
+
+        // the same payload to another destination is not a duplicate
+        assert_eq!(
+            remote.sent,
+            vec![(b"a".to_vec(), ups1), (b"a".to_vec(), ups2)]
+        );
+    }
+
+    /// return at most one packet on each poll, and report truncation like recvmsg
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+        target_os = "solaris",
+    ))]
+    struct MockOversizeClientRecv {
+        queue: VecDeque<Vec<u8>>,
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+        target_os = "solaris",
+    ))]
+    impl UdpRelayClientRecv for MockOversizeClientRecv {
+        fn max_hdr_len(&self) -> usize {
+            0
+        }
+
+        fn poll_recv_packet(
+            &mut self,
+            _cx: &mut Context<'_>,
+            _buf: &mut [u8],
+        ) -> Poll<Result<(usize, usize, UpstreamAddr), UdpRelayClientError>> {
+            unreachable!()
+        }
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "macos",
+            target_os = "solaris",
+        ))]
+        fn poll_recv_packets(
+            &mut self,
+            _cx: &mut Context<'_>,
+            packets: &mut [UdpRelayPacket],
+        ) -> Poll<Result<usize, UdpRelayClientError>> {
+            let Some(data) = self.queue.pop_front() else {
+                return Poll::Ready(Ok(0));
+            };
+            let p = &mut packets[0];
+            let len = data.len().min(p.buf.len());
+            p.buf[..len].copy_from_slice(&data[..len]);
+            p.set_offset(0);
+            p.set_length(len);
+            p.set_upstream(UpstreamAddr::from_str("127.0.0.1:53").unwrap());
+            p.truncated = data.len() > len;
+            Poll::Ready(Ok(1))
+        }
+    }
+
+    /// relay a big datagram between small ones twice, with packet size 512,
+    /// and return the sent payloads, the truncated count, the dropped count and the final packet size
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+        target_os = "solaris",
+    ))]
+    async fn relay_oversize(
+        policy: UdpOversizePolicy,
+        packet_size_max: usize,
+    ) -> (Vec<Vec<u8>>, u64, u64, usize) {
+        let mut client = MockOversizeClientRecv {
+            queue: vec![
+                b"a".to_vec(),
+                vec![b'b'; 1024],
+                vec![b'c'; 1024],
+                b"d".to_vec(),
+            ]
+            .into(),
+        };
+        let mut remote = MockRemoteSend::default();
+
+        let mut config = LimitedUdpRelayConfig::default();
+        config.set_packet_size(512);
+        config.set_packet_size_max(packet_size_max);
+        config.set_oversize_policy(policy);
+        let mut c_to_r = UdpRelayClientToRemote::new(&mut client, &mut remote, config);
+        (&mut c_to_r).await.unwrap();
+        let truncated = c_to_r.truncated();
+        let dropped = c_to_r.oversize_dropped();
+        let packet_size = c_to_r.packet_size();
+
+        let sent = remote.sent.into_iter().map(|(v, _)| v).collect();
+        (sent, truncated, dropped, packet_size)
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+        target_os = "solaris",
+    ))]
+    #[tokio::test]
+    async fn oversize_forward_truncated() {
+        let (sent, truncated, dropped, packet_size) =
+            relay_oversize(UdpOversizePolicy::ForwardTruncated, 4096).await;
+        assert_eq!(
+            sent,
+            vec![
+                b"a".to_vec(),
+                vec![b'b'; 512],
+                vec![b'c'; 512],
+                b"d".to_vec()
+            ]
+        );
+        assert_eq!(truncated, 2);
+        assert_eq!(dropped, 0);
+        assert_eq!(packet_size, 512);
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+        target_os = "solaris",
+    ))]
+    #[tokio::test]
+    async fn oversize_drop() {
+        let (sent, truncated, dropped, packet_size) =
+            relay_oversize(UdpOversizePolicy::Drop, 4096).await;
+        assert_eq!(sent, vec![b"a".to_vec(), b"d".to_vec()]);
+        assert_eq!(truncated, 2);
+        assert_eq!(dropped, 2);
+        assert_eq!(packet_size, 512);
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+        target_os = "solaris",
+    ))]
+    #[tokio::test]
+    async fn oversize_grow() {
+        let (sent, truncated, dropped, packet_size) =
+            relay_oversize(UdpOversizePolicy::Grow, 4096).await;
+        // the first big one is dropped, and the second one fits in the grown buffer
+        assert_eq!(sent, vec![b"a".to_vec(), vec![b'c'; 1024], b"d".to_vec()]);
+        assert_eq!(truncated, 1);
+        assert_eq!(dropped, 1);
+        assert_eq!(packet_size, 1024);
+
+        // no growth beyond the ceiling
+        let (sent, truncated, dropped, packet_size) =
+            relay_oversize(UdpOversizePolicy::Grow, 512).await;
+        assert_eq!(sent, vec![b"a".to_vec(), b"d".to_vec()]);
+        assert_eq!(truncated, 2);
+        assert_eq!(dropped, 2);
+        assert_eq!(packet_size, 512);
+    }
+}
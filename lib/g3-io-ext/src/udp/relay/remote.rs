@@ -38,6 +38,8 @@ pub enum UdpRelayRemoteError {
     RemoteSessionClosed(SocketAddr, SocketAddr),
     #[error("remote session error: {0:?}")]
     RemoteSessionError(SocketAddr, SocketAddr, io::Error),
+    #[error("port unreachable: (bind: {0}, remote: {1})")]
+    PortUnreachable(SocketAddr, SocketAddr),
     #[error("internal server error: {0}")]
     InternalServerError(&'static str),
 }
@@ -78,6 +80,12 @@ pub trait UdpRelayRemoteSend {
         to: &UpstreamAddr,
     ) -> Poll<Result<usize, UdpRelayRemoteError>>;
 
+    /// return the number of packets that have been consumed from the front of `packets`,
+    /// which should be greater than 0 if `packets` is not empty,
+    /// or it will be treated as a send error.
+    ///
+    /// If no packet could be accepted for now, register the waker and return `Poll::Pending`.
+    /// Only the remaining packets will be passed in on the next call.
     fn poll_send_packets(
         &mut self,
         cx: &mut Context<'_>,
@@ -4,7 +4,7 @@
  */
 
 use std::io;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll, ready};
@@ -16,6 +16,7 @@ use tokio::time::{Instant, Sleep};
 use g3_io_sys::udp::SendMsgHdr;
 
 use crate::limit::{DatagramLimitAction, DatagramLimiter};
+use crate::stats::BufferedIoCount;
 use crate::{ArcLimitedSendStats, GlobalDatagramLimit};
 
 pub trait AsyncUdpSend {
@@ -34,6 +35,18 @@ pub trait AsyncUdpSend {
         hdr: &SendMsgHdr<'_, C>,
     ) -> Poll<io::Result<usize>>;
 
+    /// Send a message with the source ip address set by IP_PKTINFO / IPV6_PKTINFO,
+    /// which should be used when replying on sockets bound to the unspecified address
+    fn poll_sendmsg_from<const C: usize>(
+        &mut self,
+        cx: &mut Context<'_>,
+        hdr: &mut SendMsgHdr<'_, C>,
+        src_ip: IpAddr,
+    ) -> Poll<io::Result<usize>> {
+        hdr.set_src_ip(Some(src_ip));
+        self.poll_sendmsg(cx, hdr)
+    }
+
     #[cfg(any(
         target_os = "linux",
         target_os = "android",
@@ -61,7 +74,12 @@ pub struct LimitedUdpSend<T> {
     delay: Pin<Box<Sleep>>,
     started: Instant,
     limit: DatagramLimiter,
+    throttled_since: Option<Instant>,
     stats: ArcLimitedSendStats,
+    buffered: BufferedIoCount,
+    buffered_overhead: BufferedIoCount,
+    header_iov: usize,
+    limit_payload: bool,
 }
 
 impl<T: AsyncUdpSend> LimitedUdpSend<T> {
@@ -77,7 +95,12 @@ impl<T: AsyncUdpSend> LimitedUdpSend<T> {
             delay: Box::pin(tokio::time::sleep(Duration::from_millis(0))),
             started: Instant::now(),
             limit: DatagramLimiter::with_local(shift_millis, max_packets, max_bytes),
+            throttled_since: None,
             stats,
+            buffered: BufferedIoCount::default(),
+            buffered_overhead: BufferedIoCount::default(),
+            header_iov: 0,
+            limit_payload: false,
         }
     }
 
@@ -90,8 +113,108 @@ impl<T: AsyncUdpSend> LimitedUdpSend<T> {
     }
 
     pub fn reset_stats(&mut self, stats: ArcLimitedSendStats) {
+        self.flush_stats();
         self.stats = stats;
     }
+
+    /// Set the interval for flushing the buffered counts into the stats
+    ///
+    /// The default value is set by [crate::set_io_stats_flush_interval].
+    pub fn set_stats_flush_interval(&mut self, interval: Duration) {
+        self.buffered.set_interval(interval);
+        self.buffered_overhead.set_interval(interval);
+    }
+
+    /// Set how many leading io slices in each message contain only the protocol header
+    ///
+    /// The bytes in these io slices will be counted as overhead bytes instead of send bytes
+    /// in the stats. It only takes effect for the `sendmsg` like methods.
+    pub fn set_header_iov(&mut self, count: usize) {
+        self.header_iov = count;
+    }
+
+    /// Set whether the speed limit should only count the payload bytes
+    ///
+    /// The default is to count all the bytes on the wire, including the protocol header.
+    pub fn set_limit_payload_only(&mut self, enable: bool) {
+        self.limit_payload = enable;
+    }
+
+    fn poll_throttled<O>(&mut self, cx: &mut Context<'_>, until: Instant) -> Poll<O> {
+        self.flush_stats();
+        if self.throttled_since.is_none() {
+            self.throttled_since = Some(Instant::now());
+        }
+        self.delay.as_mut().reset(until);
+        match self.delay.poll_unpin(cx) {
+            Poll::Ready(_) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn end_throttled(&mut self) {
+        if let Some(since) = self.throttled_since.take() {
+            self.stats.add_send_throttled(since.elapsed());
+        }
+    }
+}
+
+impl<T> LimitedUdpSend<T> {
+    #[inline]
+    fn header_size<const C: usize>(&self, hdr: &SendMsgHdr<'_, C>) -> usize {
+        hdr.iov.iter().take(self.header_iov).map(|v| v.len()).sum()
+    }
+
+    /// Get the total sent bytes and header bytes of the sent messages
+    fn sent_size<const C: usize>(&self, msgs: &[SendMsgHdr<'_, C>]) -> (usize, usize) {
+        msgs.iter().fold((0, 0), |(len, overhead), msg| {
+            (len + msg.n_send, overhead + self.header_size(msg))
+        })
+    }
+
+    #[inline]
+    fn limit_size(&self, size: usize, overhead: usize) -> usize {
+        if self.limit_payload {
+            size.saturating_sub(overhead)
+        } else {
+            size
+        }
+    }
+
+    /// Add the counts of sent packets, the overhead bytes are included in `bytes`
+    #[inline]
+    fn add_send(&mut self, packets: usize, bytes: usize, overhead: usize) {
+        let overhead = overhead.min(bytes);
+        if let Some((packets, bytes)) = self.buffered.add(packets, bytes - overhead) {
+            self.stats.add_send_packets(packets);
+            self.stats.add_send_bytes(bytes);
+        }
+        if overhead > 0 {
+            if let Some((_, size)) = self.buffered_overhead.add(0, overhead) {
+                self.stats.add_send_overhead_bytes(size);
+            }
+        }
+    }
+
+    /// Flush the buffered counts into the stats
+    pub fn flush_stats(&mut self) {
+        if let Some((packets, bytes)) = self.buffered.take() {
+            self.stats.add_send_packets(packets);
+            self.stats.add_send_bytes(bytes);
+        }
+        if let Some((_, size)) = self.buffered_overhead.take() {
+            self.stats.add_send_overhead_bytes(size);
+        }
+    }
+}
+
+impl<T> Drop for LimitedUdpSend<T> {
+    fn drop(&mut self) {
+        self.flush_stats();
+    }
 }
 
 impl<T> AsyncUdpSend for LimitedUdpSend<T>
@@ -109,9 +232,9 @@ where
             match self.limit.check_packet(dur_millis, buf.len()) {
                 DatagramLimitAction::Advance(_) => match self.inner.poll_send_to(cx, buf, target) {
                     Poll::Ready(Ok(nw)) => {
+                        self.end_throttled();
                         self.limit.set_advance(1, nw);
-                        self.stats.add_send_packet();
-                        self.stats.add_send_bytes(nw);
+                        self.add_send(1, nw, 0);
                         Poll::Ready(Ok(nw))
                     }
                     Poll::Ready(Err(e)) => {
@@ -123,33 +246,15 @@ where
                         Poll::Pending
                     }
                 },
-                DatagramLimitAction::DelayUntil(t) => {
-                    self.delay.as_mut().reset(t);
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
-                }
+                DatagramLimitAction::DelayUntil(t) => self.poll_throttled(cx, t),
                 DatagramLimitAction::DelayFor(ms) => {
-                    self.delay
-                        .as_mut()
-                        .reset(self.started + Duration::from_millis(dur_millis + ms));
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
+                    let t = self.started + Duration::from_millis(dur_millis + ms);
+                    self.poll_throttled(cx, t)
                 }
             }
         } else {
             let nw = ready!(self.inner.poll_send_to(cx, buf, target))?;
-            self.stats.add_send_packet();
-            self.stats.add_send_bytes(nw);
+            self.add_send(1, nw, 0);
             Poll::Ready(Ok(nw))
         }
     }
@@ -160,9 +265,9 @@ where
             match self.limit.check_packet(dur_millis, buf.len()) {
                 DatagramLimitAction::Advance(_) => match self.inner.poll_send(cx, buf) {
                     Poll::Ready(Ok(nw)) => {
+                        self.end_throttled();
                         self.limit.set_advance(1, nw);
-                        self.stats.add_send_packet();
-                        self.stats.add_send_bytes(nw);
+                        self.add_send(1, nw, 0);
                         Poll::Ready(Ok(nw))
                     }
                     Poll::Ready(Err(e)) => {
@@ -174,33 +279,15 @@ where
                         Poll::Pending
                     }
                 },
-                DatagramLimitAction::DelayUntil(t) => {
-                    self.delay.as_mut().reset(t);
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
-                }
+                DatagramLimitAction::DelayUntil(t) => self.poll_throttled(cx, t),
                 DatagramLimitAction::DelayFor(ms) => {
-                    self.delay
-                        .as_mut()
-                        .reset(self.started + Duration::from_millis(dur_millis + ms));
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
+                    let t = self.started + Duration::from_millis(dur_millis + ms);
+                    self.poll_throttled(cx, t)
                 }
             }
         } else {
             let nw = ready!(self.inner.poll_send(cx, buf))?;
-            self.stats.add_send_packet();
-            self.stats.add_send_bytes(nw);
+            self.add_send(1, nw, 0);
             Poll::Ready(Ok(nw))
         }
     }
@@ -210,15 +297,18 @@ where
         cx: &mut Context<'_>,
         hdr: &SendMsgHdr<'_, C>,
     ) -> Poll<io::Result<usize>> {
+        let overhead = self.header_size(hdr);
         if self.limit.is_set() {
             let dur_millis = self.started.elapsed().as_millis() as u64;
             let len = hdr.iov.iter().map(|v| v.len()).sum();
-            match self.limit.check_packet(dur_millis, len) {
+            let limit_size = self.limit_size(len, overhead);
+            match self.limit.check_packet(dur_millis, limit_size) {
                 DatagramLimitAction::Advance(_) => match self.inner.poll_sendmsg(cx, hdr) {
                     Poll::Ready(Ok(nw)) => {
-                        self.limit.set_advance(1, nw);
-                        self.stats.add_send_packet();
-                        self.stats.add_send_bytes(nw);
+                        self.end_throttled();
+                        let limit_size = self.limit_size(nw, overhead);
+                        self.limit.set_advance(1, limit_size);
+                        self.add_send(1, nw, overhead);
                         Poll::Ready(Ok(nw))
                     }
                     Poll::Ready(Err(e)) => {
@@ -230,33 +320,15 @@ where
                         Poll::Pending
                     }
                 },
-                DatagramLimitAction::DelayUntil(t) => {
-                    self.delay.as_mut().reset(t);
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
-                }
+                DatagramLimitAction::DelayUntil(t) => self.poll_throttled(cx, t),
                 DatagramLimitAction::DelayFor(ms) => {
-                    self.delay
-                        .as_mut()
-                        .reset(self.started + Duration::from_millis(dur_millis + ms));
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
+                    let t = self.started + Duration::from_millis(dur_millis + ms);
+                    self.poll_throttled(cx, t)
                 }
             }
         } else {
             let nw = ready!(self.inner.poll_sendmsg(cx, hdr))?;
-            self.stats.add_send_packet();
-            self.stats.add_send_bytes(nw);
+            self.add_send(1, nw, overhead);
             Poll::Ready(Ok(nw))
         }
     }
@@ -281,17 +353,20 @@ where
             let mut total_size_v = SmallVec::<[usize; 32]>::with_capacity(msgs.len());
             let mut total_size = 0;
             for msg in msgs.iter() {
-                total_size += msg.iov.iter().map(|v| v.len()).sum::<usize>();
+                let len = msg.iov.iter().map(|v| v.len()).sum::<usize>();
+                total_size += self.limit_size(len, self.header_size(msg));
                 total_size_v.push(total_size);
             }
             match self.limit.check_packets(dur_millis, total_size_v.as_ref()) {
                 DatagramLimitAction::Advance(n) => {
                     match self.inner.poll_batch_sendmsg(cx, &mut msgs[0..n]) {
                         Poll::Ready(Ok(count)) => {
-                            let len = msgs.iter().take(count).map(|v| v.n_send).sum();
-                            self.limit.set_advance(count, len);
-                            self.stats.add_send_packets(count);
-                            self.stats.add_send_bytes(len);
+                            self.end_throttled();
+                            // only the accepted messages should be counted
+                            let (len, overhead) = self.sent_size(&msgs[..count]);
+                            let limit_size = self.limit_size(len, overhead);
+                            self.limit.set_advance(count, limit_size);
+                            self.add_send(count, len, overhead);
                             Poll::Ready(Ok(count))
                         }
                         Poll::Ready(Err(e)) => {
@@ -304,34 +379,16 @@ where
                         }
                     }
                 }
-                DatagramLimitAction::DelayUntil(t) => {
-                    self.delay.as_mut().reset(t);
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
-                }
+                DatagramLimitAction::DelayUntil(t) => self.poll_throttled(cx, t),
                 DatagramLimitAction::DelayFor(ms) => {
-                    self.delay
-                        .as_mut()
-                        .reset(self.started + Duration::from_millis(dur_millis + ms));
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
+                    let t = self.started + Duration::from_millis(dur_millis + ms);
+                    self.poll_throttled(cx, t)
                 }
             }
         } else {
             let count = ready!(self.inner.poll_batch_sendmsg(cx, msgs))?;
-            self.stats.add_send_packets(count);
-            self.stats
-                .add_send_bytes(msgs.iter().take(count).map(|h| h.n_send).sum());
+            let (len, overhead) = self.sent_size(&msgs[..count]);
+            self.add_send(count, len, overhead);
             Poll::Ready(Ok(count))
         }
     }
@@ -349,17 +406,20 @@ where
             let mut total_size_v = SmallVec::<[usize; 32]>::with_capacity(msgs.len());
             let mut total_size = 0;
             for msg in msgs.iter() {
-                total_size += msg.iov.iter().map(|v| v.len()).sum::<usize>();
+                let len = msg.iov.iter().map(|v| v.len()).sum::<usize>();
+                total_size += self.limit_size(len, self.header_size(msg));
                 total_size_v.push(total_size);
             }
             match self.limit.check_packets(dur_millis, total_size_v.as_ref()) {
                 DatagramLimitAction::Advance(n) => {
                     match self.inner.poll_batch_sendmsg_x(cx, &mut msgs[0..n]) {
                         Poll::Ready(Ok(count)) => {
-                            let len = msgs.iter().take(count).map(|v| v.n_send).sum();
-                            self.limit.set_advance(count, len);
-                            self.stats.add_send_packets(count);
-                            self.stats.add_send_bytes(len);
+                            self.end_throttled();
+                            // only the accepted messages should be counted
+                            let (len, overhead) = self.sent_size(&msgs[..count]);
+                            let limit_size = self.limit_size(len, overhead);
+                            self.limit.set_advance(count, limit_size);
+                            self.add_send(count, len, overhead);
                             Poll::Ready(Ok(count))
                         }
                         Poll::Ready(Err(e)) => {
@@ -372,35 +432,314 @@ where
                         }
                     }
                 }
-                DatagramLimitAction::DelayUntil(t) => {
-                    self.delay.as_mut().reset(t);
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
-                }
+                DatagramLimitAction::DelayUntil(t) => self.poll_throttled(cx, t),
                 DatagramLimitAction::DelayFor(ms) => {
-                    self.delay
-                        .as_mut()
-                        .reset(self.started + Duration::from_millis(dur_millis + ms));
-                    match self.delay.poll_unpin(cx) {
-                        Poll::Ready(_) => {
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
-                        Poll::Pending => Poll::Pending,
-                    }
+                    let t = self.started + Duration::from_millis(dur_millis + ms);
+                    self.poll_throttled(cx, t)
                 }
             }
         } else {
             let count = ready!(self.inner.poll_batch_sendmsg_x(cx, msgs))?;
-            self.stats.add_send_packets(count);
-            self.stats
-                .add_send_bytes(msgs.iter().take(count).map(|h| h.n_send).sum());
+            let (len, overhead) = self.sent_size(&msgs[..count]);
+            self.add_send(count, len, overhead);
             Poll::Ready(Ok(count))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use std::io::IoSlice;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use crate::LimitedSendStats;
+
+    const PACKET_SIZE: usize = 1000;
+    const TOTAL_SIZE: usize = 3_000_000;
+    // 1MB/s, with 256ms time slice
+    const SHIFT_MILLIS: u8 = 8;
+    const MAX_BYTES: usize = 256_000;
+
+    struct DiscardSend;
+
+    impl AsyncUdpSend for DiscardSend {
+        fn poll_send_to(
+            &mut self,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+            _target: SocketAddr,
+        ) -> Poll<io::Result<usize>> {
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_send(&mut self, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_sendmsg<const C: usize>(
+            &mut self,
+            _cx: &mut Context<'_>,
+            hdr: &SendMsgHdr<'_, C>,
+        ) -> Poll<io::Result<usize>> {
+            Poll::Ready(Ok(hdr.iov.iter().map(|v| v.len()).sum()))
+        }
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "solaris",
+        ))]
+        fn poll_batch_sendmsg<const C: usize>(
+            &mut self,
+            _cx: &mut Context<'_>,
+            msgs: &mut [SendMsgHdr<'_, C>],
+        ) -> Poll<io::Result<usize>> {
+            for msg in msgs.iter_mut() {
+                msg.n_send = msg.iov.iter().map(|v| v.len()).sum();
+            }
+            Poll::Ready(Ok(msgs.len()))
+        }
+
+        #[cfg(target_os = "macos")]
+        fn poll_batch_sendmsg_x<const C: usize>(
+            &mut self,
+            _cx: &mut Context<'_>,
+            msgs: &mut [SendMsgHdr<'_, C>],
+        ) -> Poll<io::Result<usize>> {
+            for msg in msgs.iter_mut() {
+                msg.n_send = msg.iov.iter().map(|v| v.len()).sum();
+            }
+            Poll::Ready(Ok(msgs.len()))
+        }
+    }
+
+    #[derive(Default)]
+    struct SendStats {
+        bytes: AtomicU64,
+        packets: AtomicU64,
+        overhead_bytes: AtomicU64,
+        throttled_millis: AtomicU64,
+    }
+
+    impl LimitedSendStats for SendStats {
+        fn add_send_bytes(&self, size: usize) {
+            self.bytes.fetch_add(size as u64, Ordering::Relaxed);
+        }
+
+        fn add_send_packets(&self, n: usize) {
+            self.packets.fetch_add(n as u64, Ordering::Relaxed);
+        }
+
+        fn add_send_overhead_bytes(&self, size: usize) {
+            self.overhead_bytes
+                .fetch_add(size as u64, Ordering::Relaxed);
+        }
+
+        fn add_send_throttled(&self, time: Duration) {
+            self.throttled_millis
+                .fetch_add(time.as_millis() as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn check_rate(stats: &SendStats, elapsed: Duration) {
+        assert_eq!(stats.bytes.load(Ordering::Relaxed), TOTAL_SIZE as u64);
+        let rate = TOTAL_SIZE as f64 / elapsed.as_secs_f64();
+        assert!(
+            (900_000.0..1_100_000.0).contains(&rate),
+            "rate {rate} is out of range"
+        );
+        let throttled = stats.throttled_millis.load(Ordering::Relaxed);
+        assert!(throttled > elapsed.as_millis() as u64 / 2);
+    }
+
+    #[tokio::test]
+    async fn speed_limit_single() {
+        let stats = Arc::new(SendStats::default());
+        let mut send =
+            LimitedUdpSend::local_limited(DiscardSend, SHIFT_MILLIS, 0, MAX_BYTES, stats.clone());
+        let target = SocketAddr::from_str("127.0.0.1:53").unwrap();
+        let buf = [0u8; PACKET_SIZE];
+
+        let time_start = Instant::now();
+        let mut total = 0;
+        while total < TOTAL_SIZE {
+            total += poll_fn(|cx| send.poll_send_to(cx, &buf, target))
+                .await
+                .unwrap();
+        }
+        check_rate(&stats, time_start.elapsed());
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+    ))]
+    #[tokio::test]
+    async fn speed_limit_batch() {
+        let stats = Arc::new(SendStats::default());
+        let mut send =
+            LimitedUdpSend::local_limited(DiscardSend, SHIFT_MILLIS, 0, MAX_BYTES, stats.clone());
+        let buf = [0u8; PACKET_SIZE];
+
+        let time_start = Instant::now();
+        let mut total = 0;
+        while total < TOTAL_SIZE {
+            let mut msgs: [SendMsgHdr<'_, 1>; 8] =
+                std::array::from_fn(|_| SendMsgHdr::new([IoSlice::new(&buf)], None));
+            let count = poll_fn(|cx| send.poll_batch_sendmsg(cx, &mut msgs))
+                .await
+                .unwrap();
+            assert_eq!(count, msgs.len());
+            total += count * PACKET_SIZE;
+        }
+        check_rate(&stats, time_start.elapsed());
+    }
+
+    /// Accept at most 2 messages in each batch
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+    ))]
+    struct PartialBatchSend;
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+    ))]
+    impl AsyncUdpSend for PartialBatchSend {
+        fn poll_send_to(
+            &mut self,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+            target: SocketAddr,
+        ) -> Poll<io::Result<usize>> {
+            DiscardSend.poll_send_to(cx, buf, target)
+        }
+
+        fn poll_send(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            DiscardSend.poll_send(cx, buf)
+        }
+
+        fn poll_sendmsg<const C: usize>(
+            &mut self,
+            cx: &mut Context<'_>,
+            hdr: &SendMsgHdr<'_, C>,
+        ) -> Poll<io::Result<usize>> {
+            DiscardSend.poll_sendmsg(cx, hdr)
+        }
+
+        fn poll_batch_sendmsg<const C: usize>(
+            &mut self,
+            cx: &mut Context<'_>,
+            msgs: &mut [SendMsgHdr<'_, C>],
+        ) -> Poll<io::Result<usize>> {
+            let n = msgs.len().min(2);
+            DiscardSend.poll_batch_sendmsg(cx, &mut msgs[..n])
+        }
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+    ))]
+    #[tokio::test]
+    async fn overhead_split() {
+        let stats = Arc::new(SendStats::default());
+        let mut send = LimitedUdpSend::local_limited(PartialBatchSend, 0, 0, 0, stats.clone());
+        send.set_header_iov(1);
+        let header = [0u8; 10];
+        let payloads: [&[u8]; 3] = [&[0u8; 100], &[0u8; 200], &[0u8; 300]];
+
+        let hdr = SendMsgHdr::new([IoSlice::new(&header), IoSlice::new(payloads[0])], None);
+        let nw = poll_fn(|cx| send.poll_sendmsg(cx, &hdr)).await.unwrap();
+        assert_eq!(nw, 110);
+        assert_eq!(stats.bytes.load(Ordering::Relaxed), 100);
+        assert_eq!(stats.overhead_bytes.load(Ordering::Relaxed), 10);
+
+        // only the first 2 messages are accepted
+        let mut msgs =
+            payloads.map(|p| SendMsgHdr::new([IoSlice::new(&header), IoSlice::new(p)], None));
+        let count = poll_fn(|cx| send.poll_batch_sendmsg(cx, &mut msgs))
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(stats.packets.load(Ordering::Relaxed), 3);
+        assert_eq!(stats.bytes.load(Ordering::Relaxed), 400);
+        assert_eq!(stats.overhead_bytes.load(Ordering::Relaxed), 30);
+
+        assert_eq!(send.limit_size(110, 10), 110);
+        send.set_limit_payload_only(true);
+        assert_eq!(send.limit_size(110, 10), 100);
+    }
+
+    #[tokio::test]
+    async fn buffered_stats_converge() {
+        const TASK_COUNT: usize = 4;
+        const PACKET_COUNT: usize = 1000;
+
+        let stats = Arc::new(SendStats::default());
+        let target = SocketAddr::from_str("127.0.0.1:53").unwrap();
+
+        let mut tasks = Vec::with_capacity(TASK_COUNT);
+        for _ in 0..TASK_COUNT {
+            let mut send = LimitedUdpSend::local_limited(DiscardSend, 0, 0, 0, stats.clone());
+            send.set_stats_flush_interval(Duration::from_secs(3600));
+            tasks.push(tokio::spawn(async move {
+                let buf = [0u8; PACKET_SIZE];
+                for _ in 0..PACKET_COUNT {
+                    poll_fn(|cx| send.poll_send_to(cx, &buf, target))
+                        .await
+                        .unwrap();
+                }
+                send
+            }));
+        }
+
+        let mut sends = Vec::with_capacity(TASK_COUNT);
+        for task in tasks {
+            sends.push(task.await.unwrap());
+        }
+        // still buffered in the tasks
+        assert_eq!(stats.bytes.load(Ordering::Relaxed), 0);
+
+        let mut send = sends.pop().unwrap();
+        send.flush_stats();
+        assert_eq!(
+            stats.bytes.load(Ordering::Relaxed),
+            (PACKET_COUNT * PACKET_SIZE) as u64
+        );
+
+        drop(sends);
+        assert_eq!(
+            stats.bytes.load(Ordering::Relaxed),
+            (TASK_COUNT * PACKET_COUNT * PACKET_SIZE) as u64
+        );
+        drop(send);
+        assert_eq!(
+            stats.bytes.load(Ordering::Relaxed),
+            (TASK_COUNT * PACKET_COUNT * PACKET_SIZE) as u64
+        );
+    }
+}
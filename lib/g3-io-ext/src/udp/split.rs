@@ -12,7 +12,7 @@ use std::{fmt, io};
 use tokio::io::ReadBuf;
 use tokio::net::UdpSocket;
 
-use g3_io_sys::udp::{RecvMsgHdr, SendMsgHdr};
+use g3_io_sys::udp::{RecvMsgHdr, SendMsgHdr, UdpSocketError};
 
 use super::{AsyncUdpRecv, AsyncUdpSend, UdpSocketExt};
 
@@ -158,4 +158,7 @@ impl AsyncUdpRecv for RecvHalf {
     ) -> Poll<io::Result<usize>> {
         self.0.poll_batch_recvmsg(cx, hdr_v)
     }
+    fn try_recv_err(&mut self) -> io::Result<Option<UdpSocketError>> {
+        self.0.try_recv_err()
+    }
 }
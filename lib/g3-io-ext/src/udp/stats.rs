@@ -4,6 +4,7 @@
  */
 
 use std::sync::Arc;
+use std::time::Duration;
 
 pub trait LimitedRecvStats {
     fn add_recv_bytes(&self, size: usize);
@@ -11,6 +12,10 @@ pub trait LimitedRecvStats {
         self.add_recv_packets(1);
     }
     fn add_recv_packets(&self, n: usize);
+    /// Add the protocol header bytes, which are not counted in the recv bytes
+    fn add_recv_overhead_bytes(&self, _size: usize) {}
+    /// Add the time the recv has been delayed by the speed limit
+    fn add_recv_throttled(&self, _time: Duration) {}
 }
 pub type ArcLimitedRecvStats = Arc<dyn LimitedRecvStats + Send + Sync>;
 
@@ -20,5 +25,9 @@ pub trait LimitedSendStats {
         self.add_send_packets(1);
     }
     fn add_send_packets(&self, n: usize);
+    /// Add the protocol header bytes, which are not counted in the send bytes
+    fn add_send_overhead_bytes(&self, _size: usize) {}
+    /// Add the time the send has been delayed by the speed limit
+    fn add_send_throttled(&self, _time: Duration) {}
 }
 pub type ArcLimitedSendStats = Arc<dyn LimitedSendStats + Send + Sync>;
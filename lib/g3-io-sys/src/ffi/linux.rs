@@ -0,0 +1,21 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+// https://github.com/torvalds/linux/blob/master/include/uapi/linux/errqueue.h
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct sock_extended_err {
+    pub ee_errno: u32,
+    pub ee_origin: u8,
+    pub ee_type: u8,
+    pub ee_code: u8,
+    pub ee_pad: u8,
+    pub ee_info: u32,
+    pub ee_data: u32,
+}
+
+pub const SO_EE_ORIGIN_LOCAL: u8 = 1;
+pub const SO_EE_ORIGIN_ICMP: u8 = 2;
+pub const SO_EE_ORIGIN_ICMP6: u8 = 3;
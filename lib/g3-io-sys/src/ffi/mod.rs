@@ -7,3 +7,8 @@
 mod macos;
 #[cfg(target_os = "macos")]
 pub use macos::*;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod linux;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use linux::*;
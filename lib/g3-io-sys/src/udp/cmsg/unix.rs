@@ -5,6 +5,8 @@
 
 use std::io;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6};
 
 use super::{RecvAncillaryBuffer, RecvAncillaryData};
 
@@ -130,6 +132,23 @@ impl RecvAncillaryBuffer {
                         let ip4 = Ipv4Addr::from(u32::from_be(ipaddr.s_addr));
                         data.set_recv_dst_addr(IpAddr::V4(ip4));
                     }
+                    #[cfg(any(target_os = "linux", target_os = "android"))]
+                    libc::IP_ORIGDSTADDR => {
+                        if payload.len() < size_of::<libc::sockaddr_in>() {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "no enough msg data for struct sockaddr_in",
+                            ));
+                        }
+                        let addr4: &libc::sockaddr_in = unsafe {
+                            (payload.as_ptr() as *const libc::sockaddr_in)
+                                .as_ref()
+                                .unwrap()
+                        };
+                        let ip4 = Ipv4Addr::from(u32::from_be(addr4.sin_addr.s_addr));
+                        let port = u16::from_be(addr4.sin_port);
+                        data.set_recv_orig_dst_addr(SocketAddr::V4(SocketAddrV4::new(ip4, port)));
+                    }
                     _ => {}
                 },
                 libc::IPPROTO_IPV6 => match hdr.cmsg_type {
@@ -150,6 +169,32 @@ impl RecvAncillaryBuffer {
                         let ip6 = Ipv6Addr::from(pktinfo.ipi6_addr.s6_addr);
                         data.set_recv_dst_addr(IpAddr::V6(ip6));
                     }
+                    #[cfg(any(target_os = "linux", target_os = "android"))]
+                    libc::IPV6_ORIGDSTADDR => {
+                        if payload.len() < size_of::<libc::sockaddr_in6>() {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "no enough msg data for struct sockaddr_in6",
+                            ));
+                        }
+                        let addr6: &libc::sockaddr_in6 = unsafe {
+                            (payload.as_ptr() as *const libc::sockaddr_in6)
+                                .as_ref()
+                                .unwrap()
+                        };
+                        let ip6 = Ipv6Addr::from(addr6.sin6_addr.s6_addr);
+                        let port = u16::from_be(addr6.sin6_port);
+                        let addr = match ip6.to_ipv4_mapped() {
+                            Some(ip4) => SocketAddr::V4(SocketAddrV4::new(ip4, port)),
+                            None => SocketAddr::V6(SocketAddrV6::new(
+                                ip6,
+                                port,
+                                addr6.sin6_flowinfo,
+                                addr6.sin6_scope_id,
+                            )),
+                        };
+                        data.set_recv_orig_dst_addr(addr);
+                    }
                     _ => {}
                 },
                 _ => {}
@@ -159,3 +204,229 @@ impl RecvAncillaryBuffer {
         Ok(())
     }
 }
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::*;
+    use std::io::IoSliceMut;
+    use std::net::UdpSocket;
+    use std::os::fd::AsRawFd;
+    use std::time::Duration;
+
+    use crate::udp::{RecvMsgHdr, recvmsg};
+
+    #[derive(Default)]
+    struct AncillaryData {
+        interface: Option<u32>,
+        dst_ip: Option<IpAddr>,
+        orig_dst_addr: Option<SocketAddr>,
+    }
+
+    impl RecvAncillaryData for AncillaryData {
+        fn set_recv_interface(&mut self, id: u32) {
+            self.interface = Some(id);
+        }
+
+        fn set_recv_dst_addr(&mut self, addr: IpAddr) {
+            self.dst_ip = Some(addr);
+        }
+
+        fn set_recv_orig_dst_addr(&mut self, addr: SocketAddr) {
+            self.orig_dst_addr = Some(addr);
+        }
+
+        fn set_timestamp(&mut self, _ts: Duration) {}
+    }
+
+    #[repr(C, align(8))]
+    struct ControlBuf {
+        buf: [u8; 256],
+        len: usize,
+    }
+
+    impl ControlBuf {
+        fn new() -> Self {
+            ControlBuf {
+                buf: [0u8; 256],
+                len: 0,
+            }
+        }
+
+        fn push<T>(&mut self, level: libc::c_int, ty: libc::c_int, payload: &T) {
+            let hdr = libc::cmsghdr {
+                cmsg_len: cmsg_len(size_of::<T>()) as _,
+                cmsg_level: level,
+                cmsg_type: ty,
+            };
+            let p = self.buf[self.len..].as_mut_ptr();
+            unsafe {
+                std::ptr::write_unaligned(p as *mut libc::cmsghdr, hdr);
+                std::ptr::copy_nonoverlapping(
+                    payload as *const T as *const u8,
+                    p.add(CMSG_HDR_SIZE),
+                    size_of::<T>(),
+                );
+            }
+            self.len += cmsg_space(size_of::<T>());
+        }
+
+        fn parse(&self) -> io::Result<AncillaryData> {
+            let mut data = AncillaryData::default();
+            RecvAncillaryBuffer::parse_buf(&self.buf[..self.len], &mut data)?;
+            Ok(data)
+        }
+    }
+
+    fn sockaddr_in(addr: SocketAddrV4) -> libc::sockaddr_in {
+        let mut v: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+        v.sin_family = libc::AF_INET as _;
+        v.sin_port = addr.port().to_be();
+        v.sin_addr.s_addr = u32::from(*addr.ip()).to_be();
+        v
+    }
+
+    fn sockaddr_in6(addr: SocketAddrV6) -> libc::sockaddr_in6 {
+        let mut v: libc::sockaddr_in6 = unsafe { std::mem::zeroed() };
+        v.sin6_family = libc::AF_INET6 as _;
+        v.sin6_port = addr.port().to_be();
+        v.sin6_flowinfo = addr.flowinfo();
+        v.sin6_addr.s6_addr = addr.ip().octets();
+        v.sin6_scope_id = addr.scope_id();
+        v
+    }
+
+    #[test]
+    fn orig_dst_v4() {
+        let addr = SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 1), 53);
+        let mut buf = ControlBuf::new();
+        buf.push(libc::IPPROTO_IP, libc::IP_ORIGDSTADDR, &sockaddr_in(addr));
+        let data = buf.parse().unwrap();
+        assert_eq!(data.orig_dst_addr, Some(SocketAddr::V4(addr)));
+        assert!(data.dst_ip.is_none());
+    }
+
+    #[test]
+    fn orig_dst_v6() {
+        let addr = SocketAddrV6::new(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1), 53, 0, 0);
+        let mut buf = ControlBuf::new();
+        buf.push(
+            libc::IPPROTO_IPV6,
+            libc::IPV6_ORIGDSTADDR,
+            &sockaddr_in6(addr),
+        );
+        let data = buf.parse().unwrap();
+        assert_eq!(data.orig_dst_addr, Some(SocketAddr::V6(addr)));
+
+        // the scope id should be kept for link local addresses
+        let addr = SocketAddrV6::new(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1), 53, 0, 2);
+        let mut buf = ControlBuf::new();
+        buf.push(
+            libc::IPPROTO_IPV6,
+            libc::IPV6_ORIGDSTADDR,
+            &sockaddr_in6(addr),
+        );
+        let data = buf.parse().unwrap();
+        assert_eq!(data.orig_dst_addr, Some(SocketAddr::V6(addr)));
+    }
+
+    #[test]
+    fn orig_dst_v4_mapped() {
+        let ip4 = Ipv4Addr::new(192, 0, 2, 1);
+        let addr = SocketAddrV6::new(ip4.to_ipv6_mapped(), 53, 0, 0);
+        let mut buf = ControlBuf::new();
+        buf.push(
+            libc::IPPROTO_IPV6,
+            libc::IPV6_ORIGDSTADDR,
+            &sockaddr_in6(addr),
+        );
+        let data = buf.parse().unwrap();
+        assert_eq!(
+            data.orig_dst_addr,
+            Some(SocketAddr::V4(SocketAddrV4::new(ip4, 53)))
+        );
+    }
+
+    #[test]
+    fn orig_dst_with_pktinfo() {
+        let addr = SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 1), 53);
+        let mut pktinfo: libc::in_pktinfo = unsafe { std::mem::zeroed() };
+        pktinfo.ipi_ifindex = 3;
+        pktinfo.ipi_addr.s_addr = u32::from(Ipv4Addr::new(198, 51, 100, 1)).to_be();
+
+        let mut buf = ControlBuf::new();
+        buf.push(libc::IPPROTO_IP, libc::IP_PKTINFO, &pktinfo);
+        buf.push(libc::IPPROTO_IP, libc::IP_ORIGDSTADDR, &sockaddr_in(addr));
+        let data = buf.parse().unwrap();
+        assert_eq!(data.interface, Some(3));
+        assert_eq!(
+            data.dst_ip,
+            Some(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)))
+        );
+        assert_eq!(data.orig_dst_addr, Some(SocketAddr::V4(addr)));
+    }
+
+    #[test]
+    fn orig_dst_too_short() {
+        let mut buf = ControlBuf::new();
+        buf.push(libc::IPPROTO_IP, libc::IP_ORIGDSTADDR, &[0u8; 4]);
+        assert!(buf.parse().is_err());
+
+        let mut buf = ControlBuf::new();
+        buf.push(libc::IPPROTO_IPV6, libc::IPV6_ORIGDSTADDR, &[0u8; 16]);
+        assert!(buf.parse().is_err());
+    }
+
+    fn enable_recv_orig_dst(socket: &UdpSocket, level: libc::c_int, name: libc::c_int) {
+        let enable: libc::c_int = 1;
+        let r = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                level,
+                name,
+                &enable as *const libc::c_int as *const libc::c_void,
+                size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        assert_eq!(r, 0, "setsockopt: {}", io::Error::last_os_error());
+    }
+
+    fn recv_orig_dst(socket: &UdpSocket) -> Option<SocketAddr> {
+        let mut buf = [0u8; 16];
+        let hdr = RecvMsgHdr::new([IoSliceMut::new(&mut buf)]);
+        let mut control_buf = RecvAncillaryBuffer::new();
+        let mut msghdr = unsafe { hdr.to_msghdr(&mut control_buf) };
+        let nr = recvmsg(socket, &mut msghdr).unwrap();
+        assert_eq!(nr, 4);
+
+        let mut data = AncillaryData::default();
+        control_buf.parse_msg(msghdr, &mut data).unwrap();
+        data.orig_dst_addr
+    }
+
+    #[test]
+    fn orig_dst_dual_stack() {
+        let Ok(socket) = UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0)) else {
+            return; // ipv6 not available
+        };
+        socket
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        enable_recv_orig_dst(&socket, libc::IPPROTO_IP, libc::IP_RECVORIGDSTADDR);
+        enable_recv_orig_dst(&socket, libc::IPPROTO_IPV6, libc::IPV6_RECVORIGDSTADDR);
+        let port = socket.local_addr().unwrap().port();
+
+        // ipv4 packets on the dual-stack socket
+        let dst4 = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port));
+        let client4 = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        client4.send_to(b"ping", dst4).unwrap();
+        assert_eq!(recv_orig_dst(&socket), Some(dst4));
+
+        // ipv6 packets on the dual-stack socket
+        let dst6 = SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::LOCALHOST, port, 0, 0));
+        let Ok(client6) = UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)) else {
+            return; // ipv6 loopback not available
+        };
+        client6.send_to(b"ping", dst6).unwrap();
+        assert_eq!(recv_orig_dst(&socket), Some(dst6));
+    }
+}
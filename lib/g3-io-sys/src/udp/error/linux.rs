@@ -0,0 +1,155 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::AsRawFd;
+use std::{io, mem, ptr};
+
+use super::{UdpSocketError, UdpSocketErrorOrigin};
+use crate::RawSocketAddr;
+use crate::ffi::{SO_EE_ORIGIN_ICMP, SO_EE_ORIGIN_ICMP6, SO_EE_ORIGIN_LOCAL, sock_extended_err};
+
+#[repr(align(8))]
+struct ControlBuffer {
+    buf: [u8; 256],
+}
+
+/// Receive one error from the socket error queue without blocking.
+///
+/// IP_RECVERR / IPV6_RECVERR should be enabled on the socket, or nothing will be queued.
+/// Return `Ok(None)` if the error queue is empty.
+pub fn recv_error<T: AsRawFd>(fd: &T) -> io::Result<Option<UdpSocketError>> {
+    let mut c_addr = RawSocketAddr::default();
+    let mut control_buf = ControlBuffer { buf: [0u8; 256] };
+
+    let mut msghdr = unsafe {
+        let (c_addr_ptr, c_addr_len) = c_addr.get_ptr_and_size();
+        let mut h = mem::zeroed::<libc::msghdr>();
+        h.msg_name = c_addr_ptr;
+        h.msg_namelen = c_addr_len as _;
+        // the payload of the original packet is not needed
+        h.msg_iov = ptr::null_mut();
+        h.msg_iovlen = 0;
+        h.msg_control = control_buf.buf.as_mut_ptr() as _;
+        h.msg_controllen = control_buf.buf.len() as _;
+        h
+    };
+
+    let r = unsafe {
+        libc::recvmsg(
+            fd.as_raw_fd(),
+            ptr::from_mut(&mut msghdr),
+            libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT,
+        )
+    };
+    if r < 0 {
+        let e = io::Error::last_os_error();
+        return if e.kind() == io::ErrorKind::WouldBlock {
+            Ok(None)
+        } else {
+            Err(e)
+        };
+    }
+
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msghdr) };
+    while !cmsg.is_null() {
+        let hdr = unsafe { &*cmsg };
+        let is_recv_err = matches!(
+            (hdr.cmsg_level, hdr.cmsg_type),
+            (libc::IPPROTO_IP, libc::IP_RECVERR) | (libc::IPPROTO_IPV6, libc::IPV6_RECVERR)
+        );
+        if is_recv_err {
+            let ee =
+                unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const sock_extended_err) };
+            // the offender address is placed right after the struct, see SO_EE_OFFENDER
+            let offender = unsafe {
+                let p = (libc::CMSG_DATA(cmsg) as *const sock_extended_err).add(1);
+                parse_offender(p as *const libc::sockaddr)
+            };
+            let origin = match ee.ee_origin {
+                SO_EE_ORIGIN_LOCAL => UdpSocketErrorOrigin::Local,
+                SO_EE_ORIGIN_ICMP => UdpSocketErrorOrigin::Icmp,
+                SO_EE_ORIGIN_ICMP6 => UdpSocketErrorOrigin::Icmp6,
+                n => UdpSocketErrorOrigin::Other(n),
+            };
+            return Ok(Some(UdpSocketError {
+                dst_addr: c_addr.to_std(),
+                offender,
+                origin,
+                errno: ee.ee_errno as i32,
+                icmp_type: ee.ee_type,
+                icmp_code: ee.ee_code,
+            }));
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(&msghdr, cmsg) };
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "no extended error found in the error queue message",
+    ))
+}
+
+unsafe fn parse_offender(p: *const libc::sockaddr) -> Option<IpAddr> {
+    let family = unsafe { ptr::read_unaligned(ptr::addr_of!((*p).sa_family)) };
+    match family as libc::c_int {
+        libc::AF_INET => {
+            let a4 = unsafe { ptr::read_unaligned(p as *const libc::sockaddr_in) };
+            Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(a4.sin_addr.s_addr))))
+        }
+        libc::AF_INET6 => {
+            let a6 = unsafe { ptr::read_unaligned(p as *const libc::sockaddr_in6) };
+            Some(IpAddr::V6(Ipv6Addr::from(a6.sin6_addr.s6_addr)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::UdpSocket;
+
+    #[test]
+    fn empty_queue() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert!(recv_error(&socket).unwrap().is_none());
+    }
+
+    #[test]
+    fn port_unreachable() {
+        // find a local port that is not listened
+        let closed = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        unsafe {
+            let enable: libc::c_int = 1;
+            let r = libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_IP,
+                libc::IP_RECVERR,
+                ptr::from_ref(&enable).cast(),
+                size_of::<libc::c_int>() as libc::socklen_t,
+            );
+            assert_eq!(r, 0);
+        }
+        socket.send_to(b"ping", closed_addr).unwrap();
+
+        let mut err = None;
+        for _ in 0..100 {
+            err = recv_error(&socket).unwrap();
+            if err.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let err = err.unwrap();
+        assert!(err.is_port_unreachable());
+        assert_eq!(err.dst_addr, Some(closed_addr));
+        assert_eq!(err.errno, libc::ECONNREFUSED);
+    }
+}
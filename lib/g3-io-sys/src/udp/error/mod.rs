@@ -0,0 +1,145 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::net::{IpAddr, SocketAddr};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod linux;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use linux::recv_error;
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+mod other;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub use other::recv_error;
+
+const ICMP_DEST_UNREACH: u8 = 3;
+const ICMP_TIME_EXCEEDED: u8 = 11;
+const ICMP_PORT_UNREACH: u8 = 3;
+const ICMP_FRAG_NEEDED: u8 = 4;
+const ICMP6_DST_UNREACH: u8 = 1;
+const ICMP6_PACKET_TOO_BIG: u8 = 2;
+const ICMP6_TIME_EXCEEDED: u8 = 3;
+const ICMP6_DST_UNREACH_NOPORT: u8 = 4;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UdpSocketErrorOrigin {
+    Local,
+    Icmp,
+    Icmp6,
+    Other(u8),
+}
+
+/// The classified type of ICMP / ICMPv6 errors
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UdpIcmpErrorKind {
+    PortUnreachable,
+    /// destination unreachable errors other than port unreachable
+    HostUnreachable,
+    PacketTooBig,
+    TimeExceeded,
+    Other,
+}
+
+/// An error queued on the socket for a previously sent packet
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UdpSocketError {
+    /// The destination address of the packet that caused this error
+    pub dst_addr: Option<SocketAddr>,
+    /// The address of the node that generated this error
+    pub offender: Option<IpAddr>,
+    pub origin: UdpSocketErrorOrigin,
+    pub errno: i32,
+    pub icmp_type: u8,
+    pub icmp_code: u8,
+}
+
+impl UdpSocketError {
+    pub fn is_icmp(&self) -> bool {
+        matches!(
+            self.origin,
+            UdpSocketErrorOrigin::Icmp | UdpSocketErrorOrigin::Icmp6
+        )
+    }
+
+    pub fn icmp_kind(&self) -> Option<UdpIcmpErrorKind> {
+        let kind = match self.origin {
+            UdpSocketErrorOrigin::Icmp => match (self.icmp_type, self.icmp_code) {
+                (ICMP_DEST_UNREACH, ICMP_PORT_UNREACH) => UdpIcmpErrorKind::PortUnreachable,
+                (ICMP_DEST_UNREACH, ICMP_FRAG_NEEDED) => UdpIcmpErrorKind::PacketTooBig,
+                (ICMP_DEST_UNREACH, _) => UdpIcmpErrorKind::HostUnreachable,
+                (ICMP_TIME_EXCEEDED, _) => UdpIcmpErrorKind::TimeExceeded,
+                _ => UdpIcmpErrorKind::Other,
+            },
+            UdpSocketErrorOrigin::Icmp6 => match (self.icmp_type, self.icmp_code) {
+                (ICMP6_DST_UNREACH, ICMP6_DST_UNREACH_NOPORT) => UdpIcmpErrorKind::PortUnreachable,
+                (ICMP6_DST_UNREACH, _) => UdpIcmpErrorKind::HostUnreachable,
+                (ICMP6_PACKET_TOO_BIG, _) => UdpIcmpErrorKind::PacketTooBig,
+                (ICMP6_TIME_EXCEEDED, _) => UdpIcmpErrorKind::TimeExceeded,
+                _ => UdpIcmpErrorKind::Other,
+            },
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// Whether this is an ICMP / ICMPv6 port unreachable error
+    pub fn is_port_unreachable(&self) -> bool {
+        self.icmp_kind() == Some(UdpIcmpErrorKind::PortUnreachable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn icmp_error(origin: UdpSocketErrorOrigin, icmp_type: u8, icmp_code: u8) -> UdpSocketError {
+        UdpSocketError {
+            dst_addr: None,
+            offender: None,
+            origin,
+            errno: 0,
+            icmp_type,
+            icmp_code,
+        }
+    }
+
+    #[test]
+    fn port_unreachable() {
+        assert!(icmp_error(UdpSocketErrorOrigin::Icmp, 3, 3).is_port_unreachable());
+        assert!(!icmp_error(UdpSocketErrorOrigin::Icmp, 3, 1).is_port_unreachable());
+        assert!(icmp_error(UdpSocketErrorOrigin::Icmp6, 1, 4).is_port_unreachable());
+        assert!(!icmp_error(UdpSocketErrorOrigin::Icmp6, 3, 3).is_port_unreachable());
+        assert!(!icmp_error(UdpSocketErrorOrigin::Local, 3, 3).is_port_unreachable());
+    }
+
+    #[test]
+    fn icmp_kind() {
+        assert_eq!(
+            icmp_error(UdpSocketErrorOrigin::Icmp, 3, 4).icmp_kind(),
+            Some(UdpIcmpErrorKind::PacketTooBig)
+        );
+        assert_eq!(
+            icmp_error(UdpSocketErrorOrigin::Icmp, 3, 1).icmp_kind(),
+            Some(UdpIcmpErrorKind::HostUnreachable)
+        );
+        assert_eq!(
+            icmp_error(UdpSocketErrorOrigin::Icmp, 11, 0).icmp_kind(),
+            Some(UdpIcmpErrorKind::TimeExceeded)
+        );
+        assert_eq!(
+            icmp_error(UdpSocketErrorOrigin::Icmp6, 2, 0).icmp_kind(),
+            Some(UdpIcmpErrorKind::PacketTooBig)
+        );
+        assert_eq!(
+            icmp_error(UdpSocketErrorOrigin::Icmp6, 128, 0).icmp_kind(),
+            Some(UdpIcmpErrorKind::Other)
+        );
+        assert_eq!(
+            icmp_error(UdpSocketErrorOrigin::Local, 0, 0).icmp_kind(),
+            None
+        );
+    }
+}
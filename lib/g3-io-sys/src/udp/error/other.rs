@@ -0,0 +1,22 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::io;
+
+use super::UdpSocketError;
+
+/// The socket error queue is not supported on this platform, so nothing will be returned
+#[cfg(unix)]
+pub fn recv_error<T: std::os::fd::AsRawFd>(_fd: &T) -> io::Result<Option<UdpSocketError>> {
+    Ok(None)
+}
+
+/// The socket error queue is not supported on this platform, so nothing will be returned
+#[cfg(windows)]
+pub fn recv_error<T: std::os::windows::io::AsRawSocket>(
+    _socket: &T,
+) -> io::Result<Option<UdpSocketError>> {
+    Ok(None)
+}
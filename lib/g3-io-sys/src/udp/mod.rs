@@ -13,5 +13,8 @@ pub use recv::*;
 mod send;
 pub use send::*;
 
+mod error;
+pub use error::{UdpIcmpErrorKind, UdpSocketError, UdpSocketErrorOrigin, recv_error};
+
 mod ext;
 pub use ext::UdpSocketExt;
@@ -45,8 +45,10 @@ pub use buf::with_recvmsg_x_buf;
 pub struct RecvMsgHdr<'a, const C: usize> {
     pub iov: [IoSliceMut<'a>; C],
     pub n_recv: usize,
+    truncated: bool,
     c_addr: UnsafeCell<RawSocketAddr>,
     dst_ip: Option<IpAddr>,
+    orig_dst_addr: Option<SocketAddr>,
     interface_id: Option<u32>,
 }
 
@@ -59,6 +61,10 @@ impl<const C: usize> RecvAncillaryData for RecvMsgHdr<'_, C> {
         self.dst_ip = Some(addr);
     }
 
+    fn set_recv_orig_dst_addr(&mut self, addr: SocketAddr) {
+        self.orig_dst_addr = Some(addr);
+    }
+
     fn set_timestamp(&mut self, _ts: Duration) {}
 }
 
@@ -67,12 +73,20 @@ impl<'a, const C: usize> RecvMsgHdr<'a, C> {
         RecvMsgHdr {
             iov,
             n_recv: 0,
+            truncated: false,
             c_addr: UnsafeCell::new(RawSocketAddr::default()),
             dst_ip: None,
+            orig_dst_addr: None,
             interface_id: None,
         }
     }
 
+    /// Check if the datagram has been truncated as it is larger than the iov buffers
+    #[inline]
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
     pub fn src_addr(&self) -> Option<SocketAddr> {
         let c_addr = unsafe { &*self.c_addr.get() };
         c_addr.to_std()
@@ -89,6 +103,12 @@ impl<'a, const C: usize> RecvMsgHdr<'a, C> {
             .unwrap_or(local_addr)
     }
 
+    /// The original destination address of packets received on transparent sockets
+    #[inline]
+    pub fn orig_dst_addr(&self) -> Option<SocketAddr> {
+        self.orig_dst_addr
+    }
+
     #[inline]
     pub fn interface_id(&self) -> Option<u32> {
         self.interface_id
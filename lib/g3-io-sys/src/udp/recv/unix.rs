@@ -10,6 +10,11 @@ use super::RecvMsgHdr;
 use crate::udp::RecvAncillaryBuffer;
 
 impl<const C: usize> RecvMsgHdr<'_, C> {
+    /// Set the flags returned in the msghdr by the kernel
+    pub fn set_msg_flags(&mut self, flags: libc::c_int) {
+        self.truncated = flags & libc::MSG_TRUNC != 0;
+    }
+
     /// # Safety
     ///
     /// `self` should not be dropped before the returned value
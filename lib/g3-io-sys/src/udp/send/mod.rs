@@ -5,7 +5,7 @@
 
 use std::cell::UnsafeCell;
 use std::io::IoSlice;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 
 use crate::RawSocketAddr;
 
@@ -43,6 +43,10 @@ pub use buf::with_sendmsg_x_buf;
 pub struct SendMsgHdr<'a, const C: usize> {
     pub iov: [IoSlice<'a>; C],
     c_addr: Option<UnsafeCell<RawSocketAddr>>,
+    #[cfg_attr(windows, allow(dead_code))]
+    src_ip: Option<IpAddr>,
+    #[cfg(unix)]
+    control: UnsafeCell<SendAncillaryBuffer>,
     pub n_send: usize,
 }
 
@@ -52,9 +56,21 @@ impl<'a, const C: usize> SendMsgHdr<'a, C> {
         SendMsgHdr {
             iov,
             c_addr,
+            src_ip: None,
+            #[cfg(unix)]
+            control: UnsafeCell::new(SendAncillaryBuffer::default()),
             n_send: 0,
         }
     }
+
+    /// Set the source ip address of the packet.
+    ///
+    /// This is useful when replying on sockets bound to the unspecified address,
+    /// the value should be the destination ip of the received packet, which can be
+    /// got from `RecvMsgHdr::dst_ip()`. It will be ignored on Windows.
+    pub fn set_src_ip(&mut self, ip: Option<IpAddr>) {
+        self.src_ip = ip;
+    }
 }
 
 impl<'a, const C: usize> AsRef<[IoSlice<'a>]> for SendMsgHdr<'a, C> {
@@ -3,12 +3,116 @@
  * Copyright 2025 ByteDance and/or its affiliates.
  */
 
+use std::net::IpAddr;
 use std::os::fd::AsRawFd;
 use std::{io, mem, ptr};
 
 use super::SendMsgHdr;
 
+const fn cmsg_space(length: usize) -> usize {
+    unsafe { libc::CMSG_SPACE(length as _) as usize }
+}
+
+const SEND_CONTROL_BUF_SIZE: usize = cmsg_space(size_of::<libc::in6_pktinfo>());
+
+#[repr(C, align(8))]
+pub(super) struct SendAncillaryBuffer([u8; SEND_CONTROL_BUF_SIZE]);
+
+impl Default for SendAncillaryBuffer {
+    fn default() -> Self {
+        SendAncillaryBuffer([0u8; SEND_CONTROL_BUF_SIZE])
+    }
+}
+
 impl<'a, const C: usize> SendMsgHdr<'a, C> {
+    /// Fill the source ip control message into the control buffer
+    ///
+    /// # Safety
+    ///
+    /// `self` should not be dropped before the msghdr
+    unsafe fn set_src_ip_control(&self, h: &mut libc::msghdr) {
+        let Some(ip) = self.src_ip else {
+            return;
+        };
+        // use IPV6_PKTINFO for ipv6 sockets, which may be sending to ipv4 mapped addresses
+        let is_ipv6_socket = match &self.c_addr {
+            Some(v) => unsafe { (*v.get()).to_std() }
+                .map(|addr| addr.is_ipv6())
+                .unwrap_or(ip.is_ipv6()),
+            None => ip.is_ipv6(),
+        };
+        let src_ip = if is_ipv6_socket {
+            match ip {
+                IpAddr::V4(ip4) => IpAddr::V6(ip4.to_ipv6_mapped()),
+                IpAddr::V6(ip6) => IpAddr::V6(ip6),
+            }
+        } else {
+            match ip {
+                IpAddr::V4(ip4) => IpAddr::V4(ip4),
+                IpAddr::V6(ip6) => match ip6.to_ipv4_mapped() {
+                    Some(ip4) => IpAddr::V4(ip4),
+                    // not a valid source ip for ipv4 sockets
+                    None => return,
+                },
+            }
+        };
+
+        unsafe {
+            let control = &mut *self.control.get();
+            control.0.fill(0);
+            h.msg_control = control.0.as_mut_ptr() as _;
+            h.msg_controllen = SEND_CONTROL_BUF_SIZE as _;
+
+            let cmsg = libc::CMSG_FIRSTHDR(h);
+            match src_ip {
+                IpAddr::V4(ip4) => {
+                    let addr = libc::in_addr {
+                        s_addr: u32::from_ne_bytes(ip4.octets()),
+                    };
+                    #[cfg(not(any(
+                        target_os = "freebsd",
+                        target_os = "openbsd",
+                        target_os = "dragonfly"
+                    )))]
+                    {
+                        let mut pktinfo = mem::zeroed::<libc::in_pktinfo>();
+                        pktinfo.ipi_spec_dst = addr;
+                        (*cmsg).cmsg_level = libc::IPPROTO_IP;
+                        (*cmsg).cmsg_type = libc::IP_PKTINFO;
+                        (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<libc::in_pktinfo>() as _) as _;
+                        ptr::write_unaligned(
+                            libc::CMSG_DATA(cmsg) as *mut libc::in_pktinfo,
+                            pktinfo,
+                        );
+                        h.msg_controllen = cmsg_space(size_of::<libc::in_pktinfo>()) as _;
+                    }
+                    #[cfg(any(
+                        target_os = "freebsd",
+                        target_os = "openbsd",
+                        target_os = "dragonfly"
+                    ))]
+                    {
+                        // IP_SENDSRCADDR has the same value as IP_RECVDSTADDR
+                        (*cmsg).cmsg_level = libc::IPPROTO_IP;
+                        (*cmsg).cmsg_type = libc::IP_RECVDSTADDR;
+                        (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<libc::in_addr>() as _) as _;
+                        ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::in_addr, addr);
+                        h.msg_controllen = cmsg_space(size_of::<libc::in_addr>()) as _;
+                    }
+                }
+                IpAddr::V6(ip6) => {
+                    let mut pktinfo = mem::zeroed::<libc::in6_pktinfo>();
+                    pktinfo.ipi6_addr.s6_addr = ip6.octets();
+                    (*cmsg).cmsg_level = libc::IPPROTO_IPV6;
+                    (*cmsg).cmsg_type = libc::IPV6_PKTINFO;
+                    (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<libc::in6_pktinfo>() as _) as _;
+                    ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::in6_pktinfo, pktinfo);
+                    h.msg_controllen = cmsg_space(size_of::<libc::in6_pktinfo>()) as _;
+                }
+            }
+        }
+    }
+
     /// # Safety
     ///
     /// `self` should not be dropped before the returned value
@@ -27,6 +131,7 @@ impl<'a, const C: usize> SendMsgHdr<'a, C> {
             h.msg_namelen = c_addr_len as _;
             h.msg_iov = self.iov.as_ptr() as _;
             h.msg_iovlen = C as _;
+            self.set_src_ip_control(&mut h);
             h
         }
     }
@@ -128,12 +128,28 @@ pub fn as_tcp_misc_sock_opts(v: &Value) -> anyhow::Result<TcpMiscSockOpts> {
                     let ca = crate::value::as_string(v)?;
                     config.set_congestion_control(ca);
                 }
-                #[cfg(target_os = "linux")]
-                "netfilter_mark" | "mark" => {
+                #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+                "netfilter_mark" | "fwmark" | "mark" => {
                     let mark = crate::value::as_u32(v)
                         .context(format!("invalid u32 value for key {k}"))?;
                     config.netfilter_mark = Some(mark);
                 }
+                #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+                "netfilter_mark" | "fwmark" | "mark" => {
+                    return Err(anyhow!("socket mark is not supported on this platform"));
+                }
+                #[cfg(any(target_os = "linux", target_os = "macos"))]
+                "notsent_lowat" | "not_sent_low_watermark" => {
+                    let lowat = crate::value::as_u32(v)
+                        .context(format!("invalid u32 value for key {k}"))?;
+                    config.notsent_lowat = Some(lowat);
+                }
+                #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+                "notsent_lowat" | "not_sent_low_watermark" => {
+                    return Err(anyhow!(
+                        "TCP_NOTSENT_LOWAT is not supported on this platform"
+                    ));
+                }
                 _ => return Err(anyhow!("invalid key {k}")),
             }
         }
@@ -35,12 +35,16 @@ pub fn as_udp_misc_sock_opts(v: &Value) -> anyhow::Result<UdpMiscSockOpts> {
                         crate::value::as_u8(v).context(format!("invalid u8 value for key {k}"))?;
                     config.traffic_class = Some(class);
                 }
-                #[cfg(target_os = "linux")]
-                "netfilter_mark" | "mark" => {
+                #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+                "netfilter_mark" | "fwmark" | "mark" => {
                     let mark = crate::value::as_u32(v)
                         .context(format!("invalid u32 value for key {k}"))?;
                     config.netfilter_mark = Some(mark);
                 }
+                #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+                "netfilter_mark" | "fwmark" | "mark" => {
+                    return Err(anyhow!("socket mark is not supported on this platform"));
+                }
                 _ => return Err(anyhow!("invalid key {k}")),
             }
         }
@@ -8,7 +8,7 @@
 use std::ptr;
 
 use libc::{c_int, c_long, c_void};
-use openssl_sys::{SSL, SSL_CTX};
+use openssl_sys::{SSL, SSL_CTX, SSL_SESSION};
 
 pub const ASYNC_ERR: c_int = 0;
 pub const ASYNC_NO_JOBS: c_int = 1;
@@ -20,6 +20,41 @@ pub const ASYNC_STATUS_ERR: c_int = 1;
 pub const ASYNC_STATUS_OK: c_int = 2;
 pub const ASYNC_STATUS_EAGAIN: c_int = 3;
 
+pub const SSL_AD_PROTOCOL_VERSION: c_int = 70;
+
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_EARLY_DATA_NOT_SENT: c_int = 0;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_EARLY_DATA_REJECTED: c_int = 1;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_EARLY_DATA_ACCEPTED: c_int = 2;
+
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_FATAL_ERR_MALLOC: c_int = 0;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_FATAL_ERR_OTHER: c_int = 1;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_NONE: c_int = 2;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_EMPTY: c_int = 3;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_NO_DECRYPT: c_int = 4;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_SUCCESS: c_int = 5;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_SUCCESS_RENEW: c_int = 6;
+
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_RETURN_ABORT: c_int = 0;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_RETURN_IGNORE: c_int = 1;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_RETURN_IGNORE_RENEW: c_int = 2;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_RETURN_USE: c_int = 3;
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub const SSL_TICKET_RETURN_USE_RENEW: c_int = 4;
+
 #[allow(non_camel_case_types)]
 pub enum ASYNC_JOB {}
 
@@ -35,6 +70,24 @@ pub type ASYNC_callback_fn = Option<unsafe extern "C" fn(arg: *mut c_void) -> c_
 pub type SSL_async_callback_fn =
     Option<unsafe extern "C" fn(s: *mut SSL, arg: *mut c_void) -> c_int>;
 
+#[allow(non_camel_case_types)]
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub type SSL_CTX_generate_session_ticket_fn =
+    Option<unsafe extern "C" fn(s: *mut SSL, arg: *mut c_void) -> c_int>;
+
+#[allow(non_camel_case_types)]
+#[cfg(not(any(boringssl, awslc, libressl)))]
+pub type SSL_CTX_decrypt_session_ticket_fn = Option<
+    unsafe extern "C" fn(
+        s: *mut SSL,
+        ss: *mut SSL_SESSION,
+        keyname: *const u8,
+        keyname_length: usize,
+        status: c_int,
+        arg: *mut c_void,
+    ) -> c_int,
+>;
+
 unsafe extern "C" {
     pub fn ASYNC_is_capable() -> c_int;
     pub fn ASYNC_init_thread(max_size: usize, init_size: usize) -> c_int;
@@ -125,4 +178,27 @@ unsafe extern "C" {
     pub fn SSL_set_async_callback_arg(s: *mut SSL, arg: *mut c_void) -> c_int;
     #[cfg(ossl300)]
     pub fn SSL_get_async_status(s: *mut SSL) -> c_int;
+
+    #[cfg(not(any(boringssl, awslc, libressl)))]
+    pub fn SSL_get_early_data_status(s: *const SSL) -> c_int;
+
+    #[cfg(not(any(boringssl, awslc, libressl)))]
+    pub fn SSL_CTX_set_session_ticket_cb(
+        ctx: *mut SSL_CTX,
+        gen_cb: SSL_CTX_generate_session_ticket_fn,
+        dec_cb: SSL_CTX_decrypt_session_ticket_fn,
+        arg: *mut c_void,
+    ) -> c_int;
+    #[cfg(not(any(boringssl, awslc, libressl)))]
+    pub fn SSL_SESSION_set1_ticket_appdata(
+        ss: *mut SSL_SESSION,
+        data: *const c_void,
+        len: usize,
+    ) -> c_int;
+    #[cfg(not(any(boringssl, awslc, libressl)))]
+    pub fn SSL_SESSION_get0_ticket_appdata(
+        ss: *mut SSL_SESSION,
+        data: *mut *mut c_void,
+        len: *mut usize,
+    ) -> c_int;
 }
@@ -13,4 +13,12 @@ mod ssl;
 pub use ssl::SslAsyncModeExt;
 #[cfg(not(libressl))]
 pub use ssl::SslLazyAcceptor;
-pub use ssl::{SslAcceptor, SslConnector, SslError, SslInfoCallbackWhere, SslStream};
+pub use ssl::{
+    SslAcceptor, SslConnector, SslError, SslHandshakeAlert, SslHandshakeError,
+    SslHandshakeErrorReason, SslHandshakeFaultSide, SslInfoCallbackWhere, SslStream,
+};
+#[cfg(not(any(awslc, boringssl, libressl)))]
+pub use ssl::{
+    SslSessionTicketHandler, SslTicketAction, SslTicketStatus, set_protocol_version_floor,
+    set_session_ticket_handler,
+};
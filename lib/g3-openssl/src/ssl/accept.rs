@@ -10,7 +10,9 @@ use std::task::{Context, Poll};
 use std::time::Duration;
 
 use openssl::error::ErrorStack;
-use openssl::ssl::{self, ErrorCode, Ssl};
+#[cfg(not(any(boringssl, awslc, libressl)))]
+use openssl::foreign_types::ForeignTypeRef;
+use openssl::ssl::{self, ErrorCode, Ssl, SslRef};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::time::Sleep;
 
@@ -41,18 +43,32 @@ impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
             sleep_future: Box::pin(sleep_future),
         })
     }
+
+    #[inline]
+    pub fn ssl(&self) -> &SslRef {
+        self.inner.ssl()
+    }
+
+    #[inline]
+    pub fn ssl_mut(&mut self) -> &mut SslRef {
+        self.inner.ssl_mut()
+    }
 }
 
 impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
-    pub fn poll_accept(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    fn poll_timeout(&mut self, cx: &mut Context<'_>) -> Poll<io::Error> {
         match Pin::new(&mut self.sleep_future).poll(cx) {
-            Poll::Pending => {}
-            Poll::Ready(_) => {
-                return Poll::Ready(Err(io::Error::new(
-                    io::ErrorKind::TimedOut,
-                    "ssl accept timed out",
-                )));
-            }
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(_) => Poll::Ready(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "ssl accept timed out",
+            )),
+        }
+    }
+
+    pub fn poll_accept(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Poll::Ready(e) = self.poll_timeout(cx) {
+            return Poll::Ready(Err(e));
         }
 
         self.inner.get_mut().set_cx(cx);
@@ -61,9 +77,9 @@ impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
             Ok(_) => Poll::Ready(Ok(())),
             Err(e) => match e.code() {
                 ErrorCode::WANT_READ | ErrorCode::WANT_WRITE => Poll::Pending,
-                _ => Poll::Ready(Err(e
-                    .into_io_error()
-                    .unwrap_or_else(|e| e.build_io_error(SslErrorAction::Accept)))),
+                _ => Poll::Ready(Err(
+                    e.build_handshake_io_error(SslErrorAction::Accept, self.inner.ssl())
+                )),
             },
         }
     }
@@ -73,3 +89,43 @@ impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
         Ok(SslStream::new(self.inner))
     }
 }
+
+#[cfg(not(any(boringssl, awslc, libressl)))]
+impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
+    /// Read the early data sent before the client Finished message
+    ///
+    /// `Ok(0)` will be returned if there is no more early data, or if it has been rejected,
+    /// then `accept` should be called to complete the handshake.
+    pub fn poll_read_early_data(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if let Poll::Ready(e) = self.poll_timeout(cx) {
+            return Poll::Ready(Err(e));
+        }
+
+        self.inner.get_mut().set_cx(cx);
+
+        match self.inner.read_early_data(buf) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(e) => match e.code() {
+                ErrorCode::WANT_READ | ErrorCode::WANT_WRITE => Poll::Pending,
+                _ => Poll::Ready(Err(e.build_handshake_io_error(
+                    SslErrorAction::ReadEarlyData,
+                    self.inner.ssl(),
+                ))),
+            },
+        }
+    }
+
+    pub async fn read_early_data(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_read_early_data(cx, buf)).await
+    }
+
+    /// Check if the early data is accepted, the result is only valid after the early data read
+    pub fn early_data_accepted(&self) -> bool {
+        let status = unsafe { crate::ffi::SSL_get_early_data_status(self.inner.ssl().as_ptr()) };
+        status == crate::ffi::SSL_EARLY_DATA_ACCEPTED
+    }
+}
@@ -10,7 +10,8 @@ use std::task::{Context, Poll, ready};
 use std::time::Duration;
 
 use openssl::error::ErrorStack;
-use openssl::ssl::{self, ErrorCode, Ssl};
+use openssl::foreign_types::ForeignTypeRef;
+use openssl::ssl::{self, ErrorCode, Ssl, SslRef};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::time::Sleep;
 
@@ -52,6 +53,16 @@ impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
             wait_async_job: false,
         })
     }
+
+    #[inline]
+    pub fn ssl(&self) -> &SslRef {
+        self.inner.ssl()
+    }
+
+    #[inline]
+    pub fn ssl_mut(&mut self) -> &mut SslRef {
+        self.inner.ssl_mut()
+    }
 }
 
 impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
@@ -74,9 +85,9 @@ impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
                 ErrorCode::WANT_READ | ErrorCode::WANT_WRITE => Poll::Pending,
                 ErrorCode::WANT_ASYNC => async_engine.poll_ready(self.inner.ssl(), cx),
                 ErrorCode::WANT_ASYNC_JOB => Poll::Ready(Ok(())),
-                _ => Poll::Ready(Err(e
-                    .into_io_error()
-                    .unwrap_or_else(|e| e.build_io_error(SslErrorAction::Accept)))),
+                _ => Poll::Ready(Err(
+                    e.build_handshake_io_error(SslErrorAction::Accept, self.inner.ssl())
+                )),
             },
         }
     }
@@ -122,9 +133,9 @@ impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
                         return Poll::Pending;
                     }
                     _ => {
-                        return Poll::Ready(Err(e
-                            .into_io_error()
-                            .unwrap_or_else(|e| e.build_io_error(SslErrorAction::Accept))));
+                        return Poll::Ready(Err(
+                            e.build_handshake_io_error(SslErrorAction::Accept, self.inner.ssl())
+                        ));
                     }
                 },
             }
@@ -144,3 +155,79 @@ impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
         Ok(SslStream::new(self.inner, self.async_engine))
     }
 }
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SslAcceptor<S> {
+    /// Read the early data sent before the client Finished message
+    ///
+    /// `Ok(0)` will be returned if there is no more early data, or if it has been rejected,
+    /// then `accept` should be called to complete the handshake.
+    pub fn poll_read_early_data(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.inner.get_mut().set_cx(cx);
+        #[cfg(ossl300)]
+        if let Some(async_engine) = &self.async_engine {
+            async_engine.set_cx(cx);
+        }
+
+        loop {
+            if self.wait_async_job {
+                ready!(self.poll_wait_async_job(cx))?;
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "ssl accept timed out",
+                )));
+            }
+
+            match Pin::new(&mut self.sleep_future).poll(cx) {
+                Poll::Pending => break,
+                Poll::Ready(_) => self.wait_async_job = true,
+            }
+        }
+
+        loop {
+            match self.inner.read_early_data(buf) {
+                Ok(n) => return Poll::Ready(Ok(n)),
+                Err(e) => match e.code() {
+                    ErrorCode::WANT_READ | ErrorCode::WANT_WRITE => return Poll::Pending,
+                    ErrorCode::WANT_ASYNC => {
+                        if let Some(async_engine) = &mut self.async_engine {
+                            ready!(async_engine.poll_ready(self.inner.ssl(), cx))?
+                        } else {
+                            return Poll::Ready(Err(io::Error::other(
+                                "async engine poller is not set",
+                            )));
+                        }
+                    }
+                    ErrorCode::WANT_ASYNC_JOB => {
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
+                    _ => {
+                        return Poll::Ready(Err(e.build_handshake_io_error(
+                            SslErrorAction::ReadEarlyData,
+                            self.inner.ssl(),
+                        )));
+                    }
+                },
+            }
+        }
+    }
+
+    /// Read the early data sent before the client Finished message
+    ///
+    /// # Cancellation
+    ///
+    /// Not supported, the same as `accept`.
+    pub async fn read_early_data(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_read_early_data(cx, buf)).await
+    }
+
+    /// Check if the early data is accepted, the result is only valid after the early data read
+    pub fn early_data_accepted(&self) -> bool {
+        let status = unsafe { crate::ffi::SSL_get_early_data_status(self.inner.ssl().as_ptr()) };
+        status == crate::ffi::SSL_EARLY_DATA_ACCEPTED
+    }
+}
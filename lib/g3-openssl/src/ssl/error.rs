@@ -6,10 +6,14 @@
 use std::error::Error;
 use std::{fmt, io};
 
-use openssl::ssl;
+use openssl::ssl::{self, SslRef};
+
+use super::SslHandshakeError;
 
 pub(crate) trait ConvertSslError {
     fn build_io_error(self, action: SslErrorAction) -> io::Error;
+    /// Build the io error with the handshake error classification attached
+    fn build_handshake_io_error(self, action: SslErrorAction, ssl: &SslRef) -> io::Error;
 }
 
 #[derive(Debug)]
@@ -17,6 +21,8 @@ pub(crate) enum SslErrorAction {
     Accept,
     Connect,
     Read,
+    #[cfg(not(any(boringssl, awslc, libressl)))]
+    ReadEarlyData,
     Write,
     Shutdown,
 }
@@ -27,6 +33,8 @@ impl SslErrorAction {
             SslErrorAction::Accept => "accept",
             SslErrorAction::Connect => "connect",
             SslErrorAction::Read => "read",
+            #[cfg(not(any(boringssl, awslc, libressl)))]
+            SslErrorAction::ReadEarlyData => "read early data",
             SslErrorAction::Write => "write",
             SslErrorAction::Shutdown => "shutdown",
         }
@@ -37,12 +45,42 @@ impl SslErrorAction {
 pub struct SslError {
     action: SslErrorAction,
     inner: ssl::Error,
+    handshake_error: Option<SslHandshakeError>,
+}
+
+impl SslError {
+    /// Get the classification of the handshake error, only set for the server side handshake
+    pub fn handshake_error(&self) -> Option<&SslHandshakeError> {
+        self.handshake_error.as_ref()
+    }
 }
 
 impl ConvertSslError for ssl::Error {
     fn build_io_error(self, action: SslErrorAction) -> io::Error {
-        self.into_io_error()
-            .unwrap_or_else(|e| io::Error::other(SslError { action, inner: e }))
+        self.into_io_error().unwrap_or_else(|e| {
+            io::Error::other(SslError {
+                action,
+                inner: e,
+                handshake_error: None,
+            })
+        })
+    }
+
+    fn build_handshake_io_error(self, action: SslErrorAction, ssl: &SslRef) -> io::Error {
+        let handshake_error = SslHandshakeError::classify(&self, ssl);
+        // keep the kind of the underlying io error
+        let kind = self
+            .io_error()
+            .map(|e| e.kind())
+            .unwrap_or(io::ErrorKind::Other);
+        io::Error::new(
+            kind,
+            SslError {
+                action,
+                inner: self,
+                handshake_error: Some(handshake_error),
+            },
+        )
     }
 }
 
@@ -0,0 +1,573 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::{fmt, io};
+
+use openssl::error::ErrorStack;
+use openssl::ssl::{self, ErrorCode, SslRef};
+
+use super::SslError;
+
+/// The offset of the alert description in the error reason code when an alert is received
+const SSL_AD_REASON_OFFSET: i32 = 1000;
+
+/// The classified reason of a failed server side TLS handshake
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SslHandshakeErrorReason {
+    /// the handshake is not finished before the timeout
+    TimedOut,
+    /// the client closed the connection in the middle of the handshake
+    ClientClosed,
+    /// the underlying io failed for reasons other than connection close
+    IoFailed,
+    /// a fatal alert has been received from the client
+    AlertReceived,
+    /// no cipher suite is shared with the client
+    NoSharedCipher,
+    /// no protocol version is shared with the client, or it's not a TLS client
+    UnsupportedProtocol,
+    /// the client certificate failed to verify
+    PeerCertInvalid,
+    /// no client certificate is sent while it is required
+    PeerCertMissing,
+    /// local errors, such as failures in callbacks
+    InternalError,
+    /// the error can not be classified, check the raw error string
+    Unknown,
+}
+
+impl SslHandshakeErrorReason {
+    pub const ALL: [SslHandshakeErrorReason; 10] = [
+        SslHandshakeErrorReason::TimedOut,
+        SslHandshakeErrorReason::ClientClosed,
+        SslHandshakeErrorReason::IoFailed,
+        SslHandshakeErrorReason::AlertReceived,
+        SslHandshakeErrorReason::NoSharedCipher,
+        SslHandshakeErrorReason::UnsupportedProtocol,
+        SslHandshakeErrorReason::PeerCertInvalid,
+        SslHandshakeErrorReason::PeerCertMissing,
+        SslHandshakeErrorReason::InternalError,
+        SslHandshakeErrorReason::Unknown,
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            SslHandshakeErrorReason::TimedOut => "timed_out",
+            SslHandshakeErrorReason::ClientClosed => "client_closed",
+            SslHandshakeErrorReason::IoFailed => "io_failed",
+            SslHandshakeErrorReason::AlertReceived => "alert_received",
+            SslHandshakeErrorReason::NoSharedCipher => "no_shared_cipher",
+            SslHandshakeErrorReason::UnsupportedProtocol => "unsupported_protocol",
+            SslHandshakeErrorReason::PeerCertInvalid => "peer_cert_invalid",
+            SslHandshakeErrorReason::PeerCertMissing => "peer_cert_missing",
+            SslHandshakeErrorReason::InternalError => "internal_error",
+            SslHandshakeErrorReason::Unknown => "unknown",
+        }
+    }
+
+    /// Get the index of this reason in [`Self::ALL`]
+    pub const fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Which side should be blamed for the handshake failure
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SslHandshakeFaultSide {
+    Client,
+    Server,
+    Unknown,
+}
+
+impl SslHandshakeFaultSide {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            SslHandshakeFaultSide::Client => "client",
+            SslHandshakeFaultSide::Server => "server",
+            SslHandshakeFaultSide::Unknown => "unknown",
+        }
+    }
+}
+
+/// The fatal TLS alert that has been sent or received in the failed handshake
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SslHandshakeAlert {
+    description: u8,
+    sent: bool,
+}
+
+impl SslHandshakeAlert {
+    const fn sent(description: u8) -> Self {
+        SslHandshakeAlert {
+            description,
+            sent: true,
+        }
+    }
+
+    const fn received(description: u8) -> Self {
+        SslHandshakeAlert {
+            description,
+            sent: false,
+        }
+    }
+
+    #[inline]
+    pub fn description(&self) -> u8 {
+        self.description
+    }
+
+    /// Return true if the alert is sent by us, or false if it is received from the peer
+    #[inline]
+    pub fn is_sent(&self) -> bool {
+        self.sent
+    }
+
+    /// Get the name of the alert description as defined in the RFCs
+    pub fn name(&self) -> &'static str {
+        match self.description {
+            0 => "close_notify",
+            10 => "unexpected_message",
+            20 => "bad_record_mac",
+            21 => "decryption_failed",
+            22 => "record_overflow",
+            30 => "decompression_failure",
+            40 => "handshake_failure",
+            41 => "no_certificate",
+            42 => "bad_certificate",
+            43 => "unsupported_certificate",
+            44 => "certificate_revoked",
+            45 => "certificate_expired",
+            46 => "certificate_unknown",
+            47 => "illegal_parameter",
+            48 => "unknown_ca",
+            49 => "access_denied",
+            50 => "decode_error",
+            51 => "decrypt_error",
+            60 => "export_restriction",
+            70 => "protocol_version",
+            71 => "insufficient_security",
+            80 => "internal_error",
+            86 => "inappropriate_fallback",
+            90 => "user_canceled",
+            100 => "no_renegotiation",
+            109 => "missing_extension",
+            110 => "unsupported_extension",
+            111 => "certificate_unobtainable",
+            112 => "unrecognized_name",
+            113 => "bad_certificate_status_response",
+            114 => "bad_certificate_hash_value",
+            115 => "unknown_psk_identity",
+            116 => "certificate_required",
+            120 => "no_application_protocol",
+            _ => "unknown",
+        }
+    }
+
+    /// Check if the alert is about the certificate sent by the receiver of it
+    fn is_certificate_related(&self) -> bool {
+        matches!(self.description, 42..=46 | 48 | 113 | 114)
+    }
+}
+
+/// The classification of a failed server side TLS handshake
+#[derive(Clone, Debug)]
+pub struct SslHandshakeError {
+    reason: SslHandshakeErrorReason,
+    alert: Option<SslHandshakeAlert>,
+    phase: Option<&'static str>,
+    raw_error: Option<String>,
+}
+
+impl SslHandshakeError {
+    fn new(reason: SslHandshakeErrorReason, phase: Option<&'static str>) -> Self {
+        SslHandshakeError {
+            reason,
+            alert: None,
+            phase,
+            raw_error: None,
+        }
+    }
+
+    /// Classify the handshake error by the error queue and the current SSL state
+    pub(crate) fn classify(e: &ssl::Error, ssl: &SslRef) -> Self {
+        let phase = Some(ssl.state_string_long());
+        if let Some(io_e) = e.io_error() {
+            return Self::classify_io_error(io_e, phase);
+        }
+
+        let mut handshake_error = match e.ssl_error() {
+            Some(stack) => Self::classify_error_stack(stack, ssl, phase),
+            // closed by the peer, with or without close_notify
+            None if matches!(e.code(), ErrorCode::SYSCALL | ErrorCode::ZERO_RETURN) => {
+                Self::new(SslHandshakeErrorReason::ClientClosed, phase)
+            }
+            None => Self::new(SslHandshakeErrorReason::Unknown, phase),
+        };
+        if handshake_error.reason == SslHandshakeErrorReason::Unknown {
+            handshake_error.raw_error = Some(e.to_string());
+        }
+        handshake_error
+    }
+
+    fn classify_io_error(e: &io::Error, phase: Option<&'static str>) -> Self {
+        let reason = match e.kind() {
+            io::ErrorKind::TimedOut => SslHandshakeErrorReason::TimedOut,
+            io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe => SslHandshakeErrorReason::ClientClosed,
+            _ => {
+                let mut handshake_error = Self::new(SslHandshakeErrorReason::IoFailed, phase);
+                handshake_error.raw_error = Some(e.to_string());
+                return handshake_error;
+            }
+        };
+        Self::new(reason, phase)
+    }
+
+    fn classify_error_stack(stack: &ErrorStack, ssl: &SslRef, phase: Option<&'static str>) -> Self {
+        for err in stack.errors() {
+            if err.library() != Some("SSL routines") {
+                continue;
+            }
+
+            let code = err.reason_code();
+            if (SSL_AD_REASON_OFFSET..SSL_AD_REASON_OFFSET + 256).contains(&code) {
+                let mut handshake_error = Self::new(SslHandshakeErrorReason::AlertReceived, phase);
+                handshake_error.alert = Some(SslHandshakeAlert::received(
+                    (code - SSL_AD_REASON_OFFSET) as u8,
+                ));
+                return handshake_error;
+            }
+
+            let Some(reason) = err.reason() else {
+                continue;
+            };
+            // BoringSSL and AWS-LC use the upper case reason names
+            let (reason, alert) = match reason.to_ascii_lowercase().replace('_', " ").as_str() {
+                "no shared cipher" => (SslHandshakeErrorReason::NoSharedCipher, Some(40)),
+                "unsupported protocol" | "version too low" | "wrong version number" => {
+                    (SslHandshakeErrorReason::UnsupportedProtocol, Some(70))
+                }
+                "unknown protocol" | "http request" | "https proxy request" => {
+                    (SslHandshakeErrorReason::UnsupportedProtocol, None)
+                }
+                "certificate verify failed" => (
+                    SslHandshakeErrorReason::PeerCertInvalid,
+                    Some(verify_result_alert(ssl)),
+                ),
+                "peer did not return a certificate" => {
+                    let alert = if ssl.version_str() == "TLSv1.3" {
+                        116
+                    } else {
+                        40
+                    };
+                    (SslHandshakeErrorReason::PeerCertMissing, Some(alert))
+                }
+                "unexpected eof while reading" => (SslHandshakeErrorReason::ClientClosed, None),
+                "callback failed" | "cert cb error" | "internal error" => {
+                    (SslHandshakeErrorReason::InternalError, Some(80))
+                }
+                _ => continue,
+            };
+            let mut handshake_error = Self::new(reason, phase);
+            handshake_error.alert = alert.map(SslHandshakeAlert::sent);
+            return handshake_error;
+        }
+        Self::new(SslHandshakeErrorReason::Unknown, phase)
+    }
+
+    /// Get the classification from the error returned by the accept methods of
+    /// [`SslAcceptor`](crate::SslAcceptor) and [`SslLazyAcceptor`](crate::SslLazyAcceptor)
+    ///
+    /// Errors not generated from the SSL handshake will be classified by the error kind.
+    pub fn from_io_error(e: &io::Error) -> Self {
+        if let Some(ssl_e) = e.get_ref().and_then(|e| e.downcast_ref::<SslError>()) {
+            if let Some(handshake_error) = ssl_e.handshake_error() {
+                return handshake_error.clone();
+            }
+        }
+        Self::classify_io_error(e, None)
+    }
+
+    #[inline]
+    pub fn reason(&self) -> SslHandshakeErrorReason {
+        self.reason
+    }
+
+    #[inline]
+    pub fn alert(&self) -> Option<SslHandshakeAlert> {
+        self.alert
+    }
+
+    /// Get the handshake state when the error occurred
+    #[inline]
+    pub fn phase(&self) -> Option<&'static str> {
+        self.phase
+    }
+
+    /// Get the raw error string, only set if the reason is unknown or io failed
+    #[inline]
+    pub fn raw_error(&self) -> Option<&str> {
+        self.raw_error.as_deref()
+    }
+
+    pub fn fault_side(&self) -> SslHandshakeFaultSide {
+        match self.reason {
+            SslHandshakeErrorReason::TimedOut
+            | SslHandshakeErrorReason::ClientClosed
+            | SslHandshakeErrorReason::NoSharedCipher
+            | SslHandshakeErrorReason::UnsupportedProtocol
+            | SslHandshakeErrorReason::PeerCertInvalid
+            | SslHandshakeErrorReason::PeerCertMissing => SslHandshakeFaultSide::Client,
+            SslHandshakeErrorReason::AlertReceived => match self.alert {
+                // the client rejected our certificate
+                Some(alert) if alert.is_certificate_related() => SslHandshakeFaultSide::Server,
+                _ => SslHandshakeFaultSide::Client,
+            },
+            SslHandshakeErrorReason::InternalError => SslHandshakeFaultSide::Server,
+            SslHandshakeErrorReason::IoFailed | SslHandshakeErrorReason::Unknown => {
+                SslHandshakeFaultSide::Unknown
+            }
+        }
+    }
+
+    /// Check if the error is transient, so the same client may succeed if it retries
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self.reason,
+            SslHandshakeErrorReason::TimedOut
+                | SslHandshakeErrorReason::ClientClosed
+                | SslHandshakeErrorReason::IoFailed
+        )
+    }
+}
+
+impl fmt::Display for SslHandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.reason.as_str())?;
+        if let Some(alert) = self.alert {
+            let direction = if alert.sent { "sent" } else { "received" };
+            write!(f, ", {direction} alert {}", alert.name())?;
+        }
+        if let Some(phase) = self.phase {
+            write!(f, ", phase: {phase}")?;
+        }
+        if let Some(raw_error) = &self.raw_error {
+            write!(f, ": {raw_error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the alert that will be sent for the certificate verify result, see `ssl_x509err2alert`
+fn verify_result_alert(ssl: &SslRef) -> u8 {
+    use openssl_sys::{
+        X509_V_ERR_CERT_HAS_EXPIRED, X509_V_ERR_CERT_NOT_YET_VALID, X509_V_ERR_CERT_REVOKED,
+        X509_V_ERR_CERT_SIGNATURE_FAILURE, X509_V_ERR_CERT_UNTRUSTED, X509_V_ERR_CRL_HAS_EXPIRED,
+        X509_V_ERR_CRL_NOT_YET_VALID, X509_V_ERR_DEPTH_ZERO_SELF_SIGNED_CERT,
+        X509_V_ERR_SELF_SIGNED_CERT_IN_CHAIN, X509_V_ERR_UNABLE_TO_DECRYPT_CERT_SIGNATURE,
+        X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT, X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT_LOCALLY,
+    };
+
+    match ssl.verify_result().as_raw() {
+        X509_V_ERR_CERT_HAS_EXPIRED | X509_V_ERR_CRL_HAS_EXPIRED => 45,
+        X509_V_ERR_CERT_REVOKED => 44,
+        X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT
+        | X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT_LOCALLY
+        | X509_V_ERR_DEPTH_ZERO_SELF_SIGNED_CERT
+        | X509_V_ERR_SELF_SIGNED_CERT_IN_CHAIN
+        | X509_V_ERR_CERT_UNTRUSTED => 48,
+        X509_V_ERR_CERT_NOT_YET_VALID
+        | X509_V_ERR_CRL_NOT_YET_VALID
+        | X509_V_ERR_CERT_SIGNATURE_FAILURE
+        | X509_V_ERR_UNABLE_TO_DECRYPT_CERT_SIGNATURE => 42,
+        _ => 46,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::ssl::{Ssl, SslContext, SslContextBuilder, SslMethod, SslVerifyMode, SslVersion};
+    use openssl::x509::{X509, X509Builder, X509NameBuilder};
+
+    use crate::SslAcceptor;
+
+    fn server_cert(expired: bool) -> (X509, PKey<Private>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let mut name_builder = X509NameBuilder::new().unwrap();
+        name_builder
+            .append_entry_by_nid(Nid::COMMONNAME, "test.example.net")
+            .unwrap();
+        let name = name_builder.build();
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        if expired {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as libc::time_t;
+            builder
+                .set_not_before(&Asn1Time::from_unix(now - 2 * 86400).unwrap())
+                .unwrap();
+            builder
+                .set_not_after(&Asn1Time::from_unix(now - 86400).unwrap())
+                .unwrap();
+        } else {
+            builder
+                .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+                .unwrap();
+            builder
+                .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+                .unwrap();
+        }
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        (builder.build(), key)
+    }
+
+    fn server_context(cert: &X509, key: &PKey<Private>) -> SslContext {
+        let mut builder = SslContext::builder(SslMethod::tls_server()).unwrap();
+        builder.set_certificate(cert).unwrap();
+        builder.set_private_key(key).unwrap();
+        builder
+            .set_max_proto_version(Some(SslVersion::TLS1_2))
+            .unwrap();
+        builder
+            .set_cipher_list("ECDHE-ECDSA-AES128-GCM-SHA256")
+            .unwrap();
+        builder.build()
+    }
+
+    /// Run the client in a new thread, and return the server side handshake error
+    fn accept_failed<F>(ssl_context: &SslContext, client: F) -> SslHandshakeError
+    where
+        F: FnOnce(UnixStream) + Send + 'static,
+    {
+        let (server_sock, client_sock) = UnixStream::pair().unwrap();
+        let client_handle = std::thread::spawn(move || client(client_sock));
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let e = rt.block_on(async {
+            server_sock.set_nonblocking(true).unwrap();
+            let stream = tokio::net::UnixStream::from_std(server_sock).unwrap();
+            let ssl = Ssl::new(ssl_context).unwrap();
+            let acceptor = SslAcceptor::new(ssl, stream, Duration::from_secs(4)).unwrap();
+            let Err(e) = acceptor.accept().await else {
+                panic!("the handshake should fail");
+            };
+            e
+        });
+        client_handle.join().unwrap();
+        SslHandshakeError::from_io_error(&e)
+    }
+
+    fn client_connect(builder: SslContextBuilder, sock: UnixStream) {
+        let ctx = builder.build();
+        let mut ssl = Ssl::new(&ctx).unwrap();
+        ssl.set_hostname("test.example.net").unwrap();
+        ssl.set_connect_state();
+        let mut stream = ssl::SslStream::new(ssl, sock).unwrap();
+        assert!(stream.connect().is_err());
+    }
+
+    #[test]
+    fn client_closed() {
+        let (cert, key) = server_cert(false);
+        let ssl_context = server_context(&cert, &key);
+        let e = accept_failed(&ssl_context, |mut sock| {
+            // part of the ClientHello record header
+            sock.write_all(&[0x16, 0x03, 0x01, 0x00]).unwrap();
+            drop(sock);
+        });
+        assert_eq!(e.reason(), SslHandshakeErrorReason::ClientClosed);
+        assert_eq!(e.fault_side(), SslHandshakeFaultSide::Client);
+        assert!(e.alert().is_none());
+        assert!(e.phase().is_some());
+        assert!(e.raw_error().is_none());
+        assert!(e.retryable());
+    }
+
+    #[test]
+    fn no_shared_cipher() {
+        let (cert, key) = server_cert(false);
+        let ssl_context = server_context(&cert, &key);
+        let e = accept_failed(&ssl_context, |sock| {
+            let mut builder = SslContext::builder(SslMethod::tls_client()).unwrap();
+            builder.set_verify(SslVerifyMode::NONE);
+            builder
+                .set_max_proto_version(Some(SslVersion::TLS1_2))
+                .unwrap();
+            builder.set_cipher_list("AES256-SHA").unwrap();
+            client_connect(builder, sock);
+        });
+        assert_eq!(e.reason(), SslHandshakeErrorReason::NoSharedCipher);
+        assert_eq!(e.fault_side(), SslHandshakeFaultSide::Client);
+        let alert = e.alert().unwrap();
+        assert!(alert.is_sent());
+        assert_eq!(alert.name(), "handshake_failure");
+        assert!(!e.retryable());
+    }
+
+    #[test]
+    fn expired_server_cert() {
+        let (cert, key) = server_cert(true);
+        let ssl_context = server_context(&cert, &key);
+        let e = accept_failed(&ssl_context, move |mut sock| {
+            let mut builder = SslContext::builder(SslMethod::tls_client()).unwrap();
+            builder.set_verify(SslVerifyMode::PEER);
+            builder.cert_store_mut().add_cert(cert).unwrap();
+            client_connect(builder, sock.try_clone().unwrap());
+            // wait for the server to close the connection
+            let mut buf = Vec::new();
+            let _ = sock.read_to_end(&mut buf);
+        });
+        assert_eq!(e.reason(), SslHandshakeErrorReason::AlertReceived);
+        assert_eq!(e.fault_side(), SslHandshakeFaultSide::Server);
+        let alert = e.alert().unwrap();
+        assert!(!alert.is_sent());
+        assert_eq!(alert.name(), "certificate_expired");
+        assert!(!e.retryable());
+    }
+
+    #[test]
+    fn unknown_io_error() {
+        let e = SslHandshakeError::from_io_error(&io::Error::other("async engine failure"));
+        assert_eq!(e.reason(), SslHandshakeErrorReason::IoFailed);
+        assert_eq!(e.fault_side(), SslHandshakeFaultSide::Unknown);
+        assert_eq!(e.raw_error(), Some("async engine failure"));
+        assert!(e.phase().is_none());
+
+        let e = SslHandshakeError::from_io_error(&io::Error::new(
+            io::ErrorKind::TimedOut,
+            "ssl accept timed out",
+        ));
+        assert_eq!(e.reason(), SslHandshakeErrorReason::TimedOut);
+        assert!(e.raw_error().is_none());
+    }
+
+    #[test]
+    fn alert_name() {
+        assert_eq!(SslHandshakeAlert::received(48).name(), "unknown_ca");
+        assert_eq!(SslHandshakeAlert::sent(116).name(), "certificate_required");
+        assert_eq!(SslHandshakeAlert::sent(255).name(), "unknown");
+    }
+}
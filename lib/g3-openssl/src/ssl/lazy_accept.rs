@@ -36,9 +36,9 @@ impl<S: AsyncRead + AsyncWrite + Unpin> SslLazyAcceptor<S> {
                 ErrorCode::WANT_CLIENT_HELLO_CB => Poll::Ready(Ok(())),
                 #[cfg(any(awslc, boringssl))]
                 ErrorCode::PENDING_CERTIFICATE => Poll::Ready(Ok(())),
-                _ => Poll::Ready(Err(e
-                    .into_io_error()
-                    .unwrap_or_else(|e| e.build_io_error(SslErrorAction::Accept)))),
+                _ => Poll::Ready(Err(
+                    e.build_handshake_io_error(SslErrorAction::Accept, self.inner.ssl())
+                )),
             },
         }
     }
@@ -7,6 +7,11 @@ mod error;
 pub use error::SslError;
 use error::{ConvertSslError, SslErrorAction};
 
+mod handshake_error;
+pub use handshake_error::{
+    SslHandshakeAlert, SslHandshakeError, SslHandshakeErrorReason, SslHandshakeFaultSide,
+};
+
 mod wrapper;
 use wrapper::SslIoWrapper;
 
@@ -40,3 +45,15 @@ pub use connect::SslConnector;
 
 mod types;
 pub use types::SslInfoCallbackWhere;
+
+#[cfg(not(any(awslc, boringssl, libressl)))]
+mod session_ticket;
+#[cfg(not(any(awslc, boringssl, libressl)))]
+pub use session_ticket::{
+    SslSessionTicketHandler, SslTicketAction, SslTicketStatus, set_session_ticket_handler,
+};
+
+#[cfg(not(any(awslc, boringssl, libressl)))]
+mod version_floor;
+#[cfg(not(any(awslc, boringssl, libressl)))]
+pub use version_floor::set_protocol_version_floor;
@@ -0,0 +1,188 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use std::ptr;
+use std::sync::{Arc, OnceLock};
+
+use libc::{c_int, c_void};
+use openssl::error::ErrorStack;
+use openssl::ex_data::Index;
+use openssl::foreign_types::ForeignTypeRef;
+use openssl::ssl::{SslContext, SslContextBuilder, SslRef};
+use openssl_sys::{SSL, SSL_SESSION};
+
+use crate::ffi;
+
+/// The decrypt status of the session ticket sent by the client
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SslTicketStatus {
+    /// No ticket was sent
+    None,
+    /// An empty ticket was sent
+    Empty,
+    /// The ticket could not be decrypted
+    NoDecrypt,
+    /// The ticket was decrypted successfully
+    Success,
+    /// The ticket was decrypted successfully but should be renewed
+    SuccessRenew,
+}
+
+impl SslTicketStatus {
+    fn from_raw(status: c_int) -> Option<Self> {
+        match status {
+            ffi::SSL_TICKET_NONE => Some(SslTicketStatus::None),
+            ffi::SSL_TICKET_EMPTY => Some(SslTicketStatus::Empty),
+            ffi::SSL_TICKET_NO_DECRYPT => Some(SslTicketStatus::NoDecrypt),
+            ffi::SSL_TICKET_SUCCESS => Some(SslTicketStatus::Success),
+            ffi::SSL_TICKET_SUCCESS_RENEW => Some(SslTicketStatus::SuccessRenew),
+            _ => None,
+        }
+    }
+
+    /// The action that OpenSSL would take if no callback is set
+    pub fn default_action(&self) -> SslTicketAction {
+        match self {
+            SslTicketStatus::None => SslTicketAction::Ignore,
+            SslTicketStatus::Empty | SslTicketStatus::NoDecrypt => SslTicketAction::IgnoreRenew,
+            SslTicketStatus::Success => SslTicketAction::Use,
+            SslTicketStatus::SuccessRenew => SslTicketAction::UseRenew,
+        }
+    }
+}
+
+/// The action to take on the session ticket sent by the client
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SslTicketAction {
+    /// Abort the handshake
+    Abort,
+    /// Do not use the ticket and do not send a renewed ticket
+    Ignore,
+    /// Do not use the ticket, a full handshake will be done and a new ticket will be sent
+    IgnoreRenew,
+    /// Use the ticket and do not send a renewed ticket
+    Use,
+    /// Use the ticket and send a renewed ticket
+    UseRenew,
+}
+
+impl SslTicketAction {
+    fn as_raw(&self) -> c_int {
+        match self {
+            SslTicketAction::Abort => ffi::SSL_TICKET_RETURN_ABORT,
+            SslTicketAction::Ignore => ffi::SSL_TICKET_RETURN_IGNORE,
+            SslTicketAction::IgnoreRenew => ffi::SSL_TICKET_RETURN_IGNORE_RENEW,
+            SslTicketAction::Use => ffi::SSL_TICKET_RETURN_USE,
+            SslTicketAction::UseRenew => ffi::SSL_TICKET_RETURN_USE_RENEW,
+        }
+    }
+}
+
+/// Hooks to attach application data to session tickets and to check it on resumption
+pub trait SslSessionTicketHandler: Send + Sync {
+    /// Get the application data that should be stored in the new session ticket
+    fn ticket_app_data(&self, ssl: &mut SslRef) -> Option<Vec<u8>>;
+
+    /// Check the session ticket sent by the client.
+    ///
+    /// `app_data` will be None if the ticket is not decrypted or contains no application data.
+    fn check_ticket(
+        &self,
+        ssl: &mut SslRef,
+        status: SslTicketStatus,
+        app_data: Option<&[u8]>,
+    ) -> SslTicketAction;
+}
+
+type TicketHandlerIndex = Index<SslContext, Arc<dyn SslSessionTicketHandler>>;
+
+static TICKET_HANDLER_INDEX: OnceLock<TicketHandlerIndex> = OnceLock::new();
+
+fn ticket_handler_index() -> Result<TicketHandlerIndex, ErrorStack> {
+    if let Some(index) = TICKET_HANDLER_INDEX.get() {
+        return Ok(*index);
+    }
+    let index = SslContext::new_ex_index()?;
+    Ok(*TICKET_HANDLER_INDEX.get_or_init(|| index))
+}
+
+fn get_ticket_handler(ssl: &SslRef) -> Option<Arc<dyn SslSessionTicketHandler>> {
+    let index = TICKET_HANDLER_INDEX.get()?;
+    ssl.ssl_context().ex_data(*index).cloned()
+}
+
+/// Set the session ticket handler for the ssl context.
+///
+/// This works with both the default ticket keys and the ones set by the ticket key callback.
+pub fn set_session_ticket_handler(
+    builder: &mut SslContextBuilder,
+    handler: Arc<dyn SslSessionTicketHandler>,
+) -> Result<(), ErrorStack> {
+    let index = ticket_handler_index()?;
+    builder.set_ex_data(index, handler);
+    let r = unsafe {
+        ffi::SSL_CTX_set_session_ticket_cb(
+            builder.as_ptr(),
+            Some(generate_ticket_cb),
+            Some(decrypt_ticket_cb),
+            ptr::null_mut(),
+        )
+    };
+    if r == 1 {
+        Ok(())
+    } else {
+        Err(ErrorStack::get())
+    }
+}
+
+unsafe extern "C" fn generate_ticket_cb(s: *mut SSL, _arg: *mut c_void) -> c_int {
+    let ssl = unsafe { SslRef::from_ptr_mut(s) };
+    let Some(handler) = get_ticket_handler(ssl) else {
+        return 1;
+    };
+    let Some(data) = handler.ticket_app_data(ssl) else {
+        return 1;
+    };
+    let Some(session) = ssl.session() else {
+        return 0;
+    };
+    unsafe {
+        ffi::SSL_SESSION_set1_ticket_appdata(session.as_ptr(), data.as_ptr().cast(), data.len())
+    }
+}
+
+unsafe extern "C" fn decrypt_ticket_cb(
+    s: *mut SSL,
+    ss: *mut SSL_SESSION,
+    _keyname: *const u8,
+    _keyname_length: usize,
+    status: c_int,
+    _arg: *mut c_void,
+) -> c_int {
+    let Some(status) = SslTicketStatus::from_raw(status) else {
+        // fatal errors
+        return ffi::SSL_TICKET_RETURN_ABORT;
+    };
+    let ssl = unsafe { SslRef::from_ptr_mut(s) };
+    let Some(handler) = get_ticket_handler(ssl) else {
+        return status.default_action().as_raw();
+    };
+
+    let mut app_data: Option<&[u8]> = None;
+    if matches!(
+        status,
+        SslTicketStatus::Success | SslTicketStatus::SuccessRenew
+    ) && !ss.is_null()
+    {
+        let mut data: *mut c_void = ptr::null_mut();
+        let mut len: usize = 0;
+        let r = unsafe { ffi::SSL_SESSION_get0_ticket_appdata(ss, &mut data, &mut len) };
+        if r == 1 && !data.is_null() && len > 0 {
+            app_data = Some(unsafe { std::slice::from_raw_parts(data as *const u8, len) });
+        }
+    }
+
+    handler.check_ticket(ssl, status, app_data).as_raw()
+}
@@ -9,7 +9,7 @@ use std::pin::Pin;
 use std::task::ready;
 use std::task::{Context, Poll};
 
-use openssl::ssl::{self, ErrorCode, SslRef};
+use openssl::ssl::{self, ErrorCode, ShutdownState, SslRef};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
 #[cfg(feature = "async-job")]
@@ -73,6 +73,11 @@ impl<S> SslStream<S> {
 }
 
 impl<S: AsyncRead + AsyncWrite + Unpin> SslStream<S> {
+    /// Check if the close_notify alert has been sent to the peer
+    pub fn close_notify_sent(&mut self) -> bool {
+        self.inner.get_shutdown().contains(ShutdownState::SENT)
+    }
+
     fn poll_read_unpin(
         &mut self,
         cx: &mut Context<'_>,
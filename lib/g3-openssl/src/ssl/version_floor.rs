@@ -0,0 +1,132 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use libc::{c_int, c_void};
+use openssl::error::ErrorStack;
+use openssl::ssl::{SslContextBuilder, SslVersion};
+use openssl_sys::SSL;
+
+use crate::ffi;
+
+/// Reject the clients that negotiated a protocol version lower than `version`.
+///
+/// The check is done in the servername callback, which is called after the version negotiation,
+/// so the min protocol version of the context can be set lower than `version`. The handshake will
+/// be aborted with a fatal protocol_version alert if the check fails.
+///
+/// This will replace the servername callback of the context.
+pub fn set_protocol_version_floor(
+    builder: &mut SslContextBuilder,
+    version: SslVersion,
+) -> Result<(), ErrorStack> {
+    let floor = version_number(version);
+    // the version number is passed as the callback arg, so no extra data need to be freed
+    let r = unsafe {
+        openssl_sys::SSL_CTX_set_tlsext_servername_arg(builder.as_ptr(), floor as usize as _);
+        openssl_sys::SSL_CTX_set_tlsext_servername_callback(
+            builder.as_ptr(),
+            Some(version_floor_cb),
+        )
+    };
+    if r == 1 {
+        Ok(())
+    } else {
+        Err(ErrorStack::get())
+    }
+}
+
+fn version_number(version: SslVersion) -> c_int {
+    if version == SslVersion::TLS1_3 {
+        openssl_sys::TLS1_3_VERSION
+    } else if version == SslVersion::TLS1_2 {
+        openssl_sys::TLS1_2_VERSION
+    } else if version == SslVersion::TLS1_1 {
+        openssl_sys::TLS1_1_VERSION
+    } else if version == SslVersion::TLS1 {
+        openssl_sys::TLS1_VERSION
+    } else {
+        openssl_sys::SSL3_VERSION
+    }
+}
+
+unsafe extern "C" fn version_floor_cb(s: *mut SSL, al: *mut c_int, arg: *mut c_void) -> c_int {
+    let floor = arg as usize as c_int;
+    let version = unsafe { openssl_sys::SSL_version(s) };
+    if version < floor {
+        unsafe { *al = ffi::SSL_AD_PROTOCOL_VERSION };
+        openssl_sys::SSL_TLSEXT_ERR_ALERT_FATAL
+    } else {
+        openssl_sys::SSL_TLSEXT_ERR_OK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+    use openssl::ssl::{Ssl, SslConnector, SslContext, SslMethod, SslVerifyMode};
+    use openssl::x509::X509Builder;
+
+    fn server_context(floor: Option<SslVersion>) -> SslContext {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let mut cert = X509Builder::new().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        cert.sign(&key, MessageDigest::sha256()).unwrap();
+
+        let mut builder = SslContext::builder(SslMethod::tls_server()).unwrap();
+        builder.set_certificate(&cert.build()).unwrap();
+        builder.set_private_key(&key).unwrap();
+        builder
+            .set_min_proto_version(Some(SslVersion::TLS1_2))
+            .unwrap();
+        if let Some(version) = floor {
+            set_protocol_version_floor(&mut builder, version).unwrap();
+        }
+        builder.build()
+    }
+
+    fn handshake(ssl_context: &SslContext, version: SslVersion) -> Result<(), String> {
+        let (server_sock, client_sock) = UnixStream::pair().unwrap();
+        let ssl = Ssl::new(ssl_context).unwrap();
+        let server = std::thread::spawn(move || ssl.accept(server_sock).is_ok());
+
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        builder.set_min_proto_version(Some(version)).unwrap();
+        builder.set_max_proto_version(Some(version)).unwrap();
+        let connector = builder.build();
+        let client_r = connector
+            .connect("test.example.net", client_sock)
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+
+        assert_eq!(server.join().unwrap(), client_r.is_ok());
+        client_r
+    }
+
+    #[test]
+    fn version_floor() {
+        let ssl_context = server_context(None);
+        assert!(handshake(&ssl_context, SslVersion::TLS1_2).is_ok());
+        assert!(handshake(&ssl_context, SslVersion::TLS1_3).is_ok());
+
+        let ssl_context = server_context(Some(SslVersion::TLS1_3));
+        let e = handshake(&ssl_context, SslVersion::TLS1_2).unwrap_err();
+        assert!(e.contains("protocol version"), "unexpected error: {e}");
+        assert!(handshake(&ssl_context, SslVersion::TLS1_3).is_ok());
+    }
+}
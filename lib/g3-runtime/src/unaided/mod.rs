@@ -148,6 +148,11 @@ impl UnaidedRuntimeConfig {
         }
     }
 
+    /// Get the number of worker runtimes to be started
+    pub fn worker_count(&self) -> usize {
+        self.thread_number_total.get() / self.thread_number_per_rt.get()
+    }
+
     pub fn check(&mut self) -> anyhow::Result<()> {
         let threads_per_rt = self.thread_number_per_rt.get();
         if self.thread_number_total.get() % threads_per_rt != 0 {
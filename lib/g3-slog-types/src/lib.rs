@@ -9,6 +9,9 @@ pub use datetime::LtDateTime;
 mod duration;
 pub use duration::LtDuration;
 
+mod io;
+pub use io::LtIoErrorKind;
+
 mod net;
 pub use net::{LtHost, LtIpAddr, LtUpstreamAddr};
 
@@ -21,6 +21,9 @@ impl Value for LtBindAddr {
         match self.0 {
             BindAddr::None => serializer.emit_none(key),
             BindAddr::Ip(ip) => LtIpAddr(ip).serialize(_record, key, serializer),
+            BindAddr::IpPair(ip4, ip6) => {
+                serializer.emit_arguments(key, &format_args!("{ip4},{ip6}"))
+            }
             #[cfg(any(
                 target_os = "linux",
                 target_os = "android",
@@ -28,6 +28,8 @@ pub enum BindAddr {
     #[default]
     None,
     Ip(IpAddr),
+    /// Use different ip addresses for ipv4 and ipv6 sockets
+    IpPair(Ipv4Addr, Ipv6Addr),
     #[cfg(any(
         target_os = "linux",
         target_os = "android",
@@ -51,6 +53,29 @@ impl BindAddr {
         }
     }
 
+    /// Get the bind ip for sockets of the specified address family
+    pub fn ip_of_family(&self, family: AddressFamily) -> Option<IpAddr> {
+        match self {
+            BindAddr::Ip(ip) => (AddressFamily::from(ip) == family).then_some(*ip),
+            BindAddr::IpPair(ip4, ip6) => match family {
+                AddressFamily::Ipv4 => Some(IpAddr::V4(*ip4)),
+                AddressFamily::Ipv6 => Some(IpAddr::V6(*ip6)),
+            },
+            _ => None,
+        }
+    }
+
+    /// Check if a socket of the specified address family can be bound with this address
+    pub fn check_family(&self, family: AddressFamily) -> io::Result<()> {
+        match self {
+            BindAddr::Ip(ip) if AddressFamily::from(ip) != family => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no bind ip configured for {family} socket, the only bind ip is {ip}"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     pub(crate) fn bind_tcp_for_connect(
         &self,
         socket: &Socket,
@@ -58,30 +83,32 @@ impl BindAddr {
     ) -> io::Result<()> {
         match self {
             BindAddr::None => Ok(()),
-            BindAddr::Ip(ip) => {
-                if AddressFamily::from(ip) != peer_family {
+            BindAddr::Ip(_) | BindAddr::IpPair(_, _) => {
+                let Some(ip) = self.ip_of_family(peer_family) else {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidInput,
                         "bind_ip should be of the same family with peer ip",
                     ));
-                }
+                };
                 #[cfg(any(target_os = "linux", target_os = "android"))]
                 set_bind_address_no_port(socket, true)?;
                 #[cfg(windows)]
                 set_reuse_unicastport(socket, true)?;
-                let addr: SockAddr = SocketAddr::new(*ip, 0).into();
+                let addr: SockAddr = SocketAddr::new(ip, 0).into();
                 socket.bind(&addr)
             }
-            #[cfg(any(target_os = "linux", target_os = "android"))]
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "macos",
+                target_os = "illumos",
+                target_os = "solaris"
+            ))]
             BindAddr::Interface(iface) => {
+                #[cfg(any(target_os = "linux", target_os = "android"))]
                 set_bind_address_no_port(socket, true)?;
-                socket.bind_device(Some(iface.c_bytes()))
+                bind_to_interface(socket, iface, peer_family)
             }
-            #[cfg(any(target_os = "macos", target_os = "illumos", target_os = "solaris"))]
-            BindAddr::Interface(iface) => match peer_family {
-                AddressFamily::Ipv4 => socket.bind_device_by_index_v4(Some(iface.id())),
-                AddressFamily::Ipv6 => socket.bind_device_by_index_v6(Some(iface.id())),
-            },
         }
     }
 
@@ -92,29 +119,31 @@ impl BindAddr {
     ) -> io::Result<()> {
         match self {
             BindAddr::None => Ok(()),
-            BindAddr::Ip(ip) => {
-                if AddressFamily::from(ip) != peer_family {
+            BindAddr::Ip(_) | BindAddr::IpPair(_, _) => {
+                let Some(ip) = self.ip_of_family(peer_family) else {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidInput,
                         "bind_ip should be of the same family with peer ip",
                     ));
-                }
+                };
                 #[cfg(any(target_os = "linux", target_os = "android"))]
                 set_bind_address_no_port(socket, true)?;
                 // SO_REUSE_UNICASTPORT is not available for UDP socket on Windows
-                let addr: SockAddr = SocketAddr::new(*ip, 0).into();
+                let addr: SockAddr = SocketAddr::new(ip, 0).into();
                 socket.bind(&addr)
             }
-            #[cfg(any(target_os = "linux", target_os = "android"))]
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "macos",
+                target_os = "illumos",
+                target_os = "solaris"
+            ))]
             BindAddr::Interface(iface) => {
+                #[cfg(any(target_os = "linux", target_os = "android"))]
                 set_bind_address_no_port(socket, true)?;
-                socket.bind_device(Some(iface.c_bytes()))
+                bind_to_interface(socket, iface, peer_family)
             }
-            #[cfg(any(target_os = "macos", target_os = "illumos", target_os = "solaris"))]
-            BindAddr::Interface(iface) => match peer_family {
-                AddressFamily::Ipv4 => socket.bind_device_by_index_v4(Some(iface.id())),
-                AddressFamily::Ipv6 => socket.bind_device_by_index_v6(Some(iface.id())),
-            },
         }
     }
 
@@ -124,28 +153,51 @@ impl BindAddr {
                 AddressFamily::Ipv4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
                 AddressFamily::Ipv6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
             },
-            BindAddr::Ip(ip) => *ip,
-            #[cfg(any(target_os = "linux", target_os = "android"))]
+            BindAddr::Ip(_) | BindAddr::IpPair(_, _) => {
+                self.check_family(family)?;
+                self.ip_of_family(family).unwrap()
+            }
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "macos",
+                target_os = "illumos",
+                target_os = "solaris"
+            ))]
             BindAddr::Interface(iface) => {
-                socket.bind_device(Some(iface.c_bytes()))?;
+                bind_to_interface(socket, iface, family)?;
                 match family {
                     AddressFamily::Ipv4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
                     AddressFamily::Ipv6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
                 }
             }
-            #[cfg(any(target_os = "macos", target_os = "illumos", target_os = "solaris"))]
-            BindAddr::Interface(iface) => match family {
-                AddressFamily::Ipv4 => {
-                    socket.bind_device_by_index_v4(Some(iface.id()))?;
-                    IpAddr::V4(Ipv4Addr::UNSPECIFIED)
-                }
-                AddressFamily::Ipv6 => {
-                    socket.bind_device_by_index_v6(Some(iface.id()))?;
-                    IpAddr::V6(Ipv6Addr::UNSPECIFIED)
-                }
-            },
         };
         let bind_addr = SockAddr::from(SocketAddr::new(bind_ip, 0));
         socket.bind(&bind_addr)
     }
 }
+
+/// Bind the socket to the network interface, which can also be a VRF master device on Linux.
+///
+/// This should be called before bind if the bind address is only available in the VRF.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn bind_to_interface(
+    socket: &Socket,
+    iface: &Interface,
+    _family: AddressFamily,
+) -> io::Result<()> {
+    socket.bind_device(Some(iface.c_bytes()))
+}
+
+/// Bind the socket to the network interface.
+#[cfg(any(target_os = "macos", target_os = "illumos", target_os = "solaris"))]
+pub(crate) fn bind_to_interface(
+    socket: &Socket,
+    iface: &Interface,
+    family: AddressFamily,
+) -> io::Result<()> {
+    match family {
+        AddressFamily::Ipv4 => socket.bind_device_by_index_v4(Some(iface.id())),
+        AddressFamily::Ipv6 => socket.bind_device_by_index_v6(Some(iface.id())),
+    }
+}
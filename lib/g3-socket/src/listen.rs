@@ -38,6 +38,30 @@ pub(super) fn set_only_v6(socket: &Socket, addr: SocketAddr, enable: bool) -> io
     }
 }
 
+#[cfg(target_os = "linux")]
+pub(super) fn set_udp_transparent(
+    socket: &Socket,
+    addr: SocketAddr,
+    ipv6_only: Option<bool>,
+) -> io::Result<()> {
+    match addr.ip() {
+        IpAddr::V4(_) => {
+            socket.set_ip_transparent_v4(true)?;
+            crate::sockopt::set_ip_recv_orig_dst_addr(socket, true)
+        }
+        IpAddr::V6(v6) => {
+            crate::sockopt::set_ip_transparent_v6(socket, true)?;
+            crate::sockopt::set_ipv6_recv_orig_dst_addr(socket, true)?;
+            if v6.is_unspecified() && ipv6_only != Some(true) {
+                // ipv4 packets may also be received on the dual-stack wildcard socket
+                socket.set_ip_transparent_v4(true)?;
+                crate::sockopt::set_ip_recv_orig_dst_addr(socket, true)?;
+            }
+            Ok(())
+        }
+    }
+}
+
 #[cfg(unix)]
 pub(super) fn set_udp_recv_pktinfo(socket: &Socket, addr: SocketAddr) -> io::Result<()> {
     match addr.ip() {
@@ -77,14 +101,14 @@ pub(super) fn set_udp_recv_pktinfo(
                 return Ok(());
             }
 
-            match ipv6_only {
-                Some(true) => {}
-                Some(false) => {
-                    crate::sockopt::set_recv_ip_pktinfo(socket, true)?;
-                }
-                None => {
-                    // ipv6_only default to true on Windows
-                }
+            let ipv6_only = match ipv6_only {
+                Some(enable) => enable,
+                // get the real value, as the default may be changed by the system config
+                None => socket.only_v6()?,
+            };
+            if !ipv6_only {
+                // ipv4 packets may also be received on the dual-stack wildcard socket
+                crate::sockopt::set_recv_ip_pktinfo(socket, true)?;
             }
             crate::sockopt::set_recv_ipv6_pktinfo(socket, true)
         }
@@ -5,10 +5,11 @@
 
 use std::io;
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use socket2::Socket;
 
-use g3_types::net::{SocketBufferConfig, TcpMiscSockOpts, UdpMiscSockOpts};
+use g3_types::net::{SocketBufferConfig, TcpKeepAliveConfig, TcpMiscSockOpts, UdpMiscSockOpts};
 
 use crate::util::AddressFamily;
 
@@ -17,6 +18,16 @@ mod unix;
 #[cfg(windows)]
 mod windows;
 
+#[cfg(target_os = "linux")]
+fn set_socket_mark(socket: &Socket, mark: u32) -> io::Result<()> {
+    super::sockopt::set_mark(socket, mark)
+}
+
+#[cfg(target_os = "freebsd")]
+fn set_socket_mark(socket: &Socket, mark: u32) -> io::Result<()> {
+    super::sockopt::set_user_cookie(socket, mark)
+}
+
 #[derive(Debug)]
 pub struct RawSocket {
     inner: Option<Socket>,
@@ -84,13 +95,32 @@ impl RawSocket {
         if let Some(ca) = misc_opts.congestion_control() {
             socket.set_tcp_congestion(ca)?;
         }
-        #[cfg(target_os = "linux")]
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
         if let Some(mark) = misc_opts.netfilter_mark {
-            socket.set_mark(mark)?;
+            set_socket_mark(socket, mark)?;
+        }
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        if let Some(lowat) = misc_opts.notsent_lowat {
+            super::sockopt::set_tcp_notsent_lowat(socket, lowat)?;
+        }
+        Ok(())
+    }
+
+    /// Enable tcp keepalive on an already connected socket, do nothing if not enabled in config
+    pub fn set_tcp_keepalive(&self, config: &TcpKeepAliveConfig) -> io::Result<()> {
+        let socket = self.get_inner()?;
+        if let Some(setting) = crate::tcp::enable_tcp_keepalive(config) {
+            socket.set_tcp_keepalive(&setting)?;
         }
         Ok(())
     }
 
+    /// Set SO_LINGER to zero, so a RST will be sent to the peer when the socket is closed
+    pub fn set_tcp_reset_on_close(&self) -> io::Result<()> {
+        let socket = self.get_inner()?;
+        socket.set_linger(Some(Duration::ZERO))
+    }
+
     #[cfg(any(target_os = "linux", target_os = "android"))]
     pub fn trigger_tcp_quick_ack(&self) -> io::Result<()> {
         let socket = self.get_inner()?;
@@ -103,6 +133,39 @@ impl RawSocket {
         super::sockopt::get_incoming_cpu(socket)
     }
 
+    /// Get the smoothed RTT from TCP_INFO
+    #[cfg(target_os = "linux")]
+    pub fn tcp_rtt(&self) -> io::Result<Duration> {
+        let socket = self.get_inner()?;
+        super::sockopt::get_tcp_rtt(socket)
+    }
+
+    /// Get the TCP_NOTSENT_LOWAT value
+    #[cfg(target_os = "linux")]
+    pub fn tcp_notsent_lowat(&self) -> io::Result<u32> {
+        let socket = self.get_inner()?;
+        super::sockopt::get_tcp_notsent_lowat(socket)
+    }
+
+    /// Get the original destination address of a connection redirected by netfilter NAT.
+    ///
+    /// SO_ORIGINAL_DST will be tried first, and IP6T_SO_ORIGINAL_DST will be used as fallback
+    /// for IPv6 sockets.
+    #[cfg(target_os = "linux")]
+    pub fn tcp_original_dst(&self) -> io::Result<SocketAddr> {
+        let socket = self.get_inner()?;
+        match super::sockopt::get_original_dst_v4(socket) {
+            Ok(addr) => Ok(SocketAddr::V4(addr)),
+            Err(e) => {
+                if socket.local_addr()?.is_ipv6() {
+                    super::sockopt::get_original_dst_v6(socket).map(SocketAddr::V6)
+                } else {
+                    Err(e)
+                }
+            }
+        }
+    }
+
     pub fn set_udp_misc_opts(
         &self,
         local_addr: SocketAddr,
@@ -143,9 +206,23 @@ impl RawSocket {
             }
         }
 
-        #[cfg(target_os = "linux")]
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
         if let Some(mark) = misc_opts.netfilter_mark {
-            socket.set_mark(mark)?;
+            set_socket_mark(socket, mark)?;
+        }
+        #[cfg(target_os = "linux")]
+        if misc_opts.recv_error == Some(true) {
+            match local_addr {
+                SocketAddr::V4(_) => super::sockopt::set_ip_recv_err(socket, true)?,
+                SocketAddr::V6(s6) => {
+                    super::sockopt::set_ipv6_recv_err(socket, true)?;
+                    // ipv4 mapped peers on dual stack sockets
+                    if s6.ip().is_unspecified() && !socket.only_v6()? {
+                        super::sockopt::set_ip_recv_err(socket, true)?;
+                    }
+                }
+            }
+            super::sockopt::set_select_err_queue(socket, true)?;
         }
         Ok(())
     }
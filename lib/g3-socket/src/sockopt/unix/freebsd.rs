@@ -19,3 +19,43 @@ pub(crate) fn set_tcp_reuseport_lb_numa_current_domain<T: AsRawFd>(fd: &T) -> io
         Ok(())
     }
 }
+
+/// Set SO_USER_COOKIE, which can be matched by ipfw / pf like the fwmark on Linux
+pub(crate) fn set_user_cookie<T: AsRawFd>(fd: &T, cookie: u32) -> io::Result<()> {
+    unsafe {
+        super::setsockopt(
+            fd.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_USER_COOKIE,
+            cookie,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::MaybeUninit;
+    use std::net::UdpSocket;
+
+    #[test]
+    fn user_cookie() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        set_user_cookie(&socket, 0x1234).unwrap();
+
+        let mut cookie = MaybeUninit::<u32>::uninit();
+        let mut len = size_of::<u32>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_USER_COOKIE,
+                cookie.as_mut_ptr().cast(),
+                &mut len,
+            )
+        };
+        assert_eq!(ret, 0);
+        assert_eq!(unsafe { cookie.assume_init() }, 0x1234);
+    }
+}
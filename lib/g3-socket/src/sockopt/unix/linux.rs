@@ -5,7 +5,11 @@
 
 use std::io;
 use std::mem::MaybeUninit;
+#[cfg(target_os = "linux")]
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
 use std::os::unix::io::AsRawFd;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
 
 use libc::{c_int, socklen_t};
 
@@ -36,6 +40,23 @@ pub(crate) fn set_bind_address_no_port<T: AsRawFd>(fd: &T, enable: bool) -> io::
     }
 }
 
+/// Set SO_MARK, which requires CAP_NET_ADMIN
+#[cfg(target_os = "linux")]
+pub(crate) fn set_mark<T: AsRawFd>(fd: &T, mark: u32) -> io::Result<()> {
+    unsafe {
+        super::setsockopt(fd.as_raw_fd(), libc::SOL_SOCKET, libc::SO_MARK, mark).map_err(|e| {
+            if e.kind() == io::ErrorKind::PermissionDenied {
+                io::Error::new(
+                    e.kind(),
+                    format!("failed to set socket mark {mark}, CAP_NET_ADMIN is required: {e}"),
+                )
+            } else {
+                e
+            }
+        })
+    }
+}
+
 pub(crate) fn set_ip_transparent_v6<T: AsRawFd>(fd: &T, enable: bool) -> io::Result<()> {
     unsafe {
         super::setsockopt(
@@ -48,6 +69,67 @@ pub(crate) fn set_ip_transparent_v6<T: AsRawFd>(fd: &T, enable: bool) -> io::Res
     }
 }
 
+pub(crate) fn set_ip_recv_orig_dst_addr<T: AsRawFd>(fd: &T, enable: bool) -> io::Result<()> {
+    unsafe {
+        super::setsockopt(
+            fd.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_RECVORIGDSTADDR,
+            enable as c_int,
+        )?;
+        Ok(())
+    }
+}
+
+pub(crate) fn set_ipv6_recv_orig_dst_addr<T: AsRawFd>(fd: &T, enable: bool) -> io::Result<()> {
+    unsafe {
+        super::setsockopt(
+            fd.as_raw_fd(),
+            libc::IPPROTO_IPV6,
+            libc::IPV6_RECVORIGDSTADDR,
+            enable as c_int,
+        )?;
+        Ok(())
+    }
+}
+
+pub(crate) fn set_ip_recv_err<T: AsRawFd>(fd: &T, enable: bool) -> io::Result<()> {
+    unsafe {
+        super::setsockopt(
+            fd.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_RECVERR,
+            enable as c_int,
+        )?;
+        Ok(())
+    }
+}
+
+pub(crate) fn set_ipv6_recv_err<T: AsRawFd>(fd: &T, enable: bool) -> io::Result<()> {
+    unsafe {
+        super::setsockopt(
+            fd.as_raw_fd(),
+            libc::IPPROTO_IPV6,
+            libc::IPV6_RECVERR,
+            enable as c_int,
+        )?;
+        Ok(())
+    }
+}
+
+/// Also report EPOLLPRI for queued errors, so the readable waiters will be woken up
+pub(crate) fn set_select_err_queue<T: AsRawFd>(fd: &T, enable: bool) -> io::Result<()> {
+    unsafe {
+        super::setsockopt(
+            fd.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_SELECT_ERR_QUEUE,
+            enable as c_int,
+        )?;
+        Ok(())
+    }
+}
+
 pub(crate) fn set_incoming_cpu<T: AsRawFd>(fd: &T, cpu_id: usize) -> io::Result<()> {
     let cpu_id = i32::try_from(cpu_id)
         .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "out of range cpu id"))?;
@@ -68,3 +150,90 @@ pub(crate) fn get_incoming_cpu<T: AsRawFd>(fd: &T) -> io::Result<usize> {
         usize::try_from(cpu_id).map_err(|e| io::Error::other(format!("invalid cpu id: {e}")))
     }
 }
+
+#[cfg(target_os = "linux")]
+pub(crate) fn get_tcp_rtt<T: AsRawFd>(fd: &T) -> io::Result<Duration> {
+    unsafe {
+        // the kernel may fill only part of the struct, so it should be zeroed first
+        let mut info: libc::tcp_info = std::mem::zeroed();
+        let mut len = size_of::<libc::tcp_info>() as socklen_t;
+        let ret = libc::getsockopt(
+            fd.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            (&mut info as *mut libc::tcp_info).cast(),
+            &mut len,
+        );
+        if ret == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Duration::from_micros(info.tcpi_rtt as u64))
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn get_tcp_notsent_lowat<T: AsRawFd>(fd: &T) -> io::Result<u32> {
+    unsafe {
+        let lowat: c_int = getsockopt(fd.as_raw_fd(), libc::IPPROTO_TCP, libc::TCP_NOTSENT_LOWAT)?;
+        Ok(lowat as u32)
+    }
+}
+
+/// Get the original destination address of a REDIRECT-ed IPv4 connection
+#[cfg(target_os = "linux")]
+pub(crate) fn get_original_dst_v4<T: AsRawFd>(fd: &T) -> io::Result<SocketAddrV4> {
+    unsafe {
+        let addr: libc::sockaddr_in =
+            getsockopt(fd.as_raw_fd(), libc::SOL_IP, libc::SO_ORIGINAL_DST)?;
+        Ok(SocketAddrV4::new(
+            Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)),
+            u16::from_be(addr.sin_port),
+        ))
+    }
+}
+
+/// Get the original destination address of a REDIRECT-ed IPv6 connection
+#[cfg(target_os = "linux")]
+pub(crate) fn get_original_dst_v6<T: AsRawFd>(fd: &T) -> io::Result<SocketAddrV6> {
+    unsafe {
+        let addr: libc::sockaddr_in6 =
+            getsockopt(fd.as_raw_fd(), libc::SOL_IPV6, libc::IP6T_SO_ORIGINAL_DST)?;
+        Ok(SocketAddrV6::new(
+            Ipv6Addr::from(addr.sin6_addr.s6_addr),
+            u16::from_be(addr.sin6_port),
+            addr.sin6_flowinfo,
+            addr.sin6_scope_id,
+        ))
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::net::{TcpStream, UdpSocket};
+
+    fn check_mark<T: AsRawFd>(fd: &T) {
+        match set_mark(fd, 0x1234) {
+            Ok(_) => {
+                let mark: u32 =
+                    unsafe { getsockopt(fd.as_raw_fd(), libc::SOL_SOCKET, libc::SO_MARK) }.unwrap();
+                assert_eq!(mark, 0x1234);
+            }
+            Err(e) => {
+                // not running with CAP_NET_ADMIN
+                assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+                assert!(e.to_string().contains("CAP_NET_ADMIN"));
+            }
+        }
+    }
+
+    #[test]
+    fn socket_mark() {
+        let udp_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        check_mark(&udp_socket);
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tcp_stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        check_mark(&tcp_stream);
+    }
+}
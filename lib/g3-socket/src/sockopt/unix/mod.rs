@@ -12,13 +12,19 @@ use libc::{c_int, c_void, socklen_t};
 mod linux;
 #[cfg(any(target_os = "linux", target_os = "android"))]
 pub(crate) use linux::{
-    get_incoming_cpu, set_bind_address_no_port, set_incoming_cpu, set_ip_transparent_v6,
+    get_incoming_cpu, set_bind_address_no_port, set_incoming_cpu, set_ip_recv_err,
+    set_ip_recv_orig_dst_addr, set_ip_transparent_v6, set_ipv6_recv_err,
+    set_ipv6_recv_orig_dst_addr, set_select_err_queue,
+};
+#[cfg(target_os = "linux")]
+pub(crate) use linux::{
+    get_original_dst_v4, get_original_dst_v6, get_tcp_notsent_lowat, get_tcp_rtt, set_mark,
 };
 
 #[cfg(target_os = "freebsd")]
 mod freebsd;
 #[cfg(target_os = "freebsd")]
-pub(crate) use freebsd::set_tcp_reuseport_lb_numa_current_domain;
+pub(crate) use freebsd::{set_tcp_reuseport_lb_numa_current_domain, set_user_cookie};
 
 unsafe fn setsockopt<T>(fd: c_int, level: c_int, name: c_int, value: T) -> io::Result<()>
 where
@@ -103,3 +109,16 @@ pub(crate) fn set_recv_ip_pktinfo<T: AsRawFd>(fd: &T, enable: bool) -> io::Resul
         Ok(())
     }
 }
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(crate) fn set_tcp_notsent_lowat<T: AsRawFd>(fd: &T, lowat: u32) -> io::Result<()> {
+    unsafe {
+        setsockopt(
+            fd.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_NOTSENT_LOWAT,
+            lowat as c_int,
+        )?;
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@
 
 use std::io;
 use std::os::windows::io::AsRawSocket;
+use std::ptr;
 
 use windows_sys::Win32::Networking::WinSock;
 
@@ -22,6 +23,30 @@ where
     }
 }
 
+/// Set whether an ICMP port unreachable message should be reported as WSAECONNRESET by the
+/// next recv call on this UDP socket, which is the default behavior on Windows
+pub(crate) fn set_udp_connreset<T: AsRawSocket>(socket: &T, enable: bool) -> io::Result<()> {
+    let value = enable as u32;
+    let mut len = 0;
+    let rc = unsafe {
+        WinSock::WSAIoctl(
+            socket.as_raw_socket() as _,
+            WinSock::SIO_UDP_CONNRESET,
+            &value as *const _ as *const _,
+            size_of_val(&value) as u32,
+            ptr::null_mut(),
+            0,
+            &mut len,
+            ptr::null_mut(),
+            None,
+        )
+    };
+    if rc == WinSock::SOCKET_ERROR {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
 pub(crate) fn set_reuse_unicastport<T: AsRawSocket>(socket: &T, enable: bool) -> io::Result<()> {
     unsafe {
         setsockopt(
@@ -10,6 +10,14 @@ use socket2::{Domain, SockAddr, Socket, TcpKeepalive, Type};
 use tokio::net::{TcpListener, TcpSocket};
 
 use g3_compat::CpuAffinity;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+use g3_types::net::Interface;
 use g3_types::net::{TcpKeepAliveConfig, TcpListenConfig, TcpMiscSockOpts};
 
 use super::util::AddressFamily;
@@ -42,9 +50,15 @@ pub fn new_std_listener(config: &TcpListenConfig) -> io::Result<std::net::TcpLis
     }
     let bind_addr: SockAddr = addr.into();
     socket.bind(&bind_addr)?;
-    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "illumos",
+        target_os = "solaris"
+    ))]
     if let Some(iface) = config.interface() {
-        socket.bind_device(Some(iface.c_bytes()))?;
+        crate::bind::bind_to_interface(&socket, iface, family)?;
     }
 
     if let Some(keepalive_config) = config.keepalive() {
@@ -53,13 +67,6 @@ pub fn new_std_listener(config: &TcpListenConfig) -> io::Result<std::net::TcpLis
         }
     }
 
-    #[cfg(any(target_os = "macos", target_os = "illumos", target_os = "solaris"))]
-    if let Some(iface) = config.interface() {
-        match family {
-            AddressFamily::Ipv4 => socket.bind_device_by_index_v4(Some(iface.id()))?,
-            AddressFamily::Ipv6 => socket.bind_device_by_index_v6(Some(iface.id()))?,
-        }
-    }
     socket.listen(config.backlog() as i32)?;
     Ok(std::net::TcpListener::from(socket))
 }
@@ -70,9 +77,57 @@ pub fn new_std_socket_to(
     keepalive: &TcpKeepAliveConfig,
     misc_opts: &TcpMiscSockOpts,
     default_set_nodelay: bool,
+) -> io::Result<std::net::TcpStream> {
+    let socket = new_tcp_socket(AddressFamily::from(&peer_ip))?;
+    bind_socket_to(
+        socket,
+        peer_ip,
+        bind,
+        keepalive,
+        misc_opts,
+        default_set_nodelay,
+    )
+}
+
+/// Like `new_std_socket_to`, but the socket will be bound to the device before bind,
+/// which can be a VRF master device on Linux.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+pub fn new_std_socket_to_in_device(
+    peer_ip: IpAddr,
+    bind: &BindAddr,
+    device: &Interface,
+    keepalive: &TcpKeepAliveConfig,
+    misc_opts: &TcpMiscSockOpts,
+    default_set_nodelay: bool,
 ) -> io::Result<std::net::TcpStream> {
     let peer_family = AddressFamily::from(&peer_ip);
     let socket = new_tcp_socket(peer_family)?;
+    crate::bind::bind_to_interface(&socket, device, peer_family)?;
+    bind_socket_to(
+        socket,
+        peer_ip,
+        bind,
+        keepalive,
+        misc_opts,
+        default_set_nodelay,
+    )
+}
+
+fn bind_socket_to(
+    socket: Socket,
+    peer_ip: IpAddr,
+    bind: &BindAddr,
+    keepalive: &TcpKeepAliveConfig,
+    misc_opts: &TcpMiscSockOpts,
+    default_set_nodelay: bool,
+) -> io::Result<std::net::TcpStream> {
+    let peer_family = AddressFamily::from(&peer_ip);
     bind.bind_tcp_for_connect(&socket, peer_family)?;
 
     if let Some(setting) = enable_tcp_keepalive(keepalive) {
@@ -84,7 +139,7 @@ pub fn new_std_socket_to(
 }
 
 #[cfg(not(target_os = "openbsd"))]
-fn enable_tcp_keepalive(config: &TcpKeepAliveConfig) -> Option<TcpKeepalive> {
+pub(crate) fn enable_tcp_keepalive(config: &TcpKeepAliveConfig) -> Option<TcpKeepalive> {
     if config.is_enabled() {
         let mut setting = TcpKeepalive::new().with_time(config.idle_time());
         if let Some(interval) = config.probe_interval() {
@@ -100,7 +155,7 @@ fn enable_tcp_keepalive(config: &TcpKeepAliveConfig) -> Option<TcpKeepalive> {
 }
 
 #[cfg(target_os = "openbsd")]
-fn enable_tcp_keepalive(config: &TcpKeepAliveConfig) -> Option<TcpKeepalive> {
+pub(crate) fn enable_tcp_keepalive(config: &TcpKeepAliveConfig) -> Option<TcpKeepalive> {
     if config.is_enabled() {
         let keepalive = TcpKeepalive::new().with_time(config.idle_time());
         Some(keepalive)
@@ -146,6 +201,32 @@ pub fn new_socket_to(
     Ok(TcpSocket::from_std_stream(socket))
 }
 
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+pub fn new_socket_to_in_device(
+    peer_ip: IpAddr,
+    bind: &BindAddr,
+    device: &Interface,
+    keepalive: &TcpKeepAliveConfig,
+    misc_opts: &TcpMiscSockOpts,
+    default_set_nodelay: bool,
+) -> io::Result<TcpSocket> {
+    let socket = new_std_socket_to_in_device(
+        peer_ip,
+        bind,
+        device,
+        keepalive,
+        misc_opts,
+        default_set_nodelay,
+    )?;
+    Ok(TcpSocket::from_std_stream(socket))
+}
+
 #[cfg(target_os = "linux")]
 pub fn try_listen_on_local_cpu(
     listener: &std::net::TcpListener,
@@ -213,6 +294,43 @@ mod tests {
         assert_eq!(connect_addr, accepted_addr);
     }
 
+    #[tokio::test]
+    async fn keepalive_after_connect() {
+        use socket2::SockRef;
+
+        let listen_config =
+            TcpListenConfig::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0));
+        let listen_socket = new_listen_to(&listen_config).unwrap();
+        let listen_addr = listen_socket.local_addr().unwrap();
+
+        let accept_task = tokio::spawn(async move {
+            let (stream, _) = listen_socket.accept().await.unwrap();
+            stream
+        });
+
+        let connect_sock = new_socket_to(
+            listen_addr.ip(),
+            &BindAddr::None,
+            &TcpKeepAliveConfig::default(),
+            &TcpMiscSockOpts::default(),
+            true,
+        )
+        .unwrap();
+        let connected_stream = connect_sock.connect(listen_addr).await.unwrap();
+        let _accepted_stream = accept_task.await.unwrap();
+        assert!(!SockRef::from(&connected_stream).keepalive().unwrap());
+
+        let raw_socket = RawSocket::from(&connected_stream);
+        raw_socket
+            .set_tcp_keepalive(&TcpKeepAliveConfig::default())
+            .unwrap();
+        assert!(!SockRef::from(&connected_stream).keepalive().unwrap());
+        raw_socket
+            .set_tcp_keepalive(&TcpKeepAliveConfig::default_enabled())
+            .unwrap();
+        assert!(SockRef::from(&connected_stream).keepalive().unwrap());
+    }
+
     #[tokio::test]
     async fn bind_connect() {
         let listen_config =
@@ -239,4 +357,105 @@ mod tests {
         let accepted_addr = accept_task.await.unwrap();
         assert_eq!(connect_addr, accepted_addr);
     }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn socket_mark() {
+        use socket2::SockRef;
+
+        let mut misc_opts = TcpMiscSockOpts::default();
+        misc_opts.netfilter_mark = Some(0x1234);
+
+        let socket = match new_std_socket_to(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            &BindAddr::None,
+            &TcpKeepAliveConfig::default(),
+            &misc_opts,
+            true,
+        ) {
+            Ok(socket) => socket,
+            Err(e) => {
+                // not running with CAP_NET_ADMIN
+                assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+                assert!(e.to_string().contains("CAP_NET_ADMIN"));
+                return;
+            }
+        };
+        assert_eq!(SockRef::from(&socket).mark().unwrap(), 0x1234);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn connect_rtt() {
+        let listen_config =
+            TcpListenConfig::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0));
+        let listen_socket = new_listen_to(&listen_config).unwrap();
+        let listen_addr = listen_socket.local_addr().unwrap();
+
+        let accept_task = tokio::spawn(async move {
+            let (stream, _) = listen_socket.accept().await.unwrap();
+            stream
+        });
+
+        let connect_sock = new_socket_to(
+            listen_addr.ip(),
+            &BindAddr::None,
+            &TcpKeepAliveConfig::default(),
+            &TcpMiscSockOpts::default(),
+            true,
+        )
+        .unwrap();
+        let connected_stream = connect_sock.connect(listen_addr).await.unwrap();
+        let _accepted_stream = accept_task.await.unwrap();
+        let rtt = RawSocket::from(&connected_stream).tcp_rtt().unwrap();
+        assert!(rtt > std::time::Duration::ZERO);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn per_direction_misc_opts() {
+        use socket2::SockRef;
+
+        let listen_config =
+            TcpListenConfig::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0));
+        let listen_socket = new_listen_to(&listen_config).unwrap();
+        let listen_addr = listen_socket.local_addr().unwrap();
+
+        let accept_task = tokio::spawn(async move {
+            let (stream, _) = listen_socket.accept().await.unwrap();
+            stream
+        });
+
+        let connect_sock = new_socket_to(
+            listen_addr.ip(),
+            &BindAddr::None,
+            &TcpKeepAliveConfig::default(),
+            &TcpMiscSockOpts::default(),
+            true,
+        )
+        .unwrap();
+        let ups_stream = connect_sock.connect(listen_addr).await.unwrap();
+        let clt_stream = accept_task.await.unwrap();
+
+        let mut clt_opts = TcpMiscSockOpts::default();
+        clt_opts.no_delay = Some(true);
+        clt_opts.notsent_lowat = Some(16384);
+        RawSocket::from(&clt_stream)
+            .set_tcp_misc_opts(AddressFamily::Ipv4, &clt_opts, false)
+            .unwrap();
+        // applied after connect, as the escaper provided socket is already connected
+        let mut ups_opts = TcpMiscSockOpts::default();
+        ups_opts.no_delay = Some(false);
+        ups_opts.notsent_lowat = Some(4096);
+        RawSocket::from(&ups_stream)
+            .set_tcp_misc_opts(AddressFamily::Ipv4, &ups_opts, false)
+            .unwrap();
+
+        assert!(SockRef::from(&clt_stream).tcp_nodelay().unwrap());
+        assert!(!SockRef::from(&ups_stream).tcp_nodelay().unwrap());
+        let clt_lowat = RawSocket::from(&clt_stream).tcp_notsent_lowat().unwrap();
+        assert_eq!(clt_lowat, 16384);
+        let ups_lowat = RawSocket::from(&ups_stream).tcp_notsent_lowat().unwrap();
+        assert_eq!(ups_lowat, 4096);
+    }
 }
@@ -8,6 +8,14 @@ use std::net::{IpAddr, Ipv6Addr, SocketAddr, UdpSocket};
 
 use socket2::{Domain, SockAddr, Socket, Type};
 
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+use g3_types::net::Interface;
 use g3_types::net::{PortRange, SocketBufferConfig, UdpListenConfig, UdpMiscSockOpts};
 
 use super::util::AddressFamily;
@@ -18,10 +26,40 @@ pub fn new_std_socket_to(
     bind: &BindAddr,
     buf_conf: SocketBufferConfig,
     misc_opts: UdpMiscSockOpts,
+) -> io::Result<UdpSocket> {
+    let socket = new_udp_socket(AddressFamily::from(&peer_addr), buf_conf)?;
+    bind_socket_to(socket, peer_addr, bind, misc_opts)
+}
+
+/// Like `new_std_socket_to`, but the socket will be bound to the device before bind,
+/// which can be a VRF master device on Linux.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+pub fn new_std_socket_to_in_device(
+    peer_addr: SocketAddr,
+    bind: &BindAddr,
+    device: &Interface,
+    buf_conf: SocketBufferConfig,
+    misc_opts: UdpMiscSockOpts,
 ) -> io::Result<UdpSocket> {
     let peer_family = AddressFamily::from(&peer_addr);
     let socket = new_udp_socket(peer_family, buf_conf)?;
-    bind.bind_udp_for_connect(&socket, peer_family)?;
+    crate::bind::bind_to_interface(&socket, device, peer_family)?;
+    bind_socket_to(socket, peer_addr, bind, misc_opts)
+}
+
+fn bind_socket_to(
+    socket: Socket,
+    peer_addr: SocketAddr,
+    bind: &BindAddr,
+    misc_opts: UdpMiscSockOpts,
+) -> io::Result<UdpSocket> {
+    bind.bind_udp_for_connect(&socket, AddressFamily::from(&peer_addr))?;
     // use peer_addr here as the socket is not listen socket
     RawSocket::from(&socket).set_udp_misc_opts(peer_addr, misc_opts)?;
     Ok(UdpSocket::from(socket))
@@ -37,6 +75,11 @@ pub fn new_std_bind_lazy_connect(
         None => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
     };
     let socket = new_udp_socket(AddressFamily::from(&bind_addr), buf_conf)?;
+    // enable pktinfo so the reply source address can be selected on wildcard sockets
+    #[cfg(unix)]
+    super::listen::set_udp_recv_pktinfo(&socket, bind_addr)?;
+    #[cfg(windows)]
+    set_lazy_connect_dual_stack(&socket, bind_addr)?;
     let bind_addr = SockAddr::from(bind_addr);
     socket.bind(&bind_addr)?;
     let socket = UdpSocket::from(socket);
@@ -52,12 +95,92 @@ pub fn new_std_in_range_bind_lazy_connect(
     buf_conf: SocketBufferConfig,
     misc_opts: UdpMiscSockOpts,
 ) -> io::Result<(UdpSocket, SocketAddr)> {
-    let port_start = port.start();
-    let port_end = port.end();
+    let socket = new_udp_socket(AddressFamily::from(&bind_ip), buf_conf)?;
+    #[cfg(unix)]
+    super::listen::set_udp_recv_pktinfo(&socket, SocketAddr::new(bind_ip, 0))?;
+    #[cfg(windows)]
+    set_lazy_connect_dual_stack(&socket, SocketAddr::new(bind_ip, 0))?;
 
-    debug_assert!(port_start < port_end);
+    bind_in_range(socket, bind_ip, port, misc_opts)
+}
 
+/// Like `new_std_in_range_bind_lazy_connect`, but try to bind to `preferred_port` first.
+///
+/// If `port` is None, any non-zero preferred port is allowed, and the fallback port will be selected by the OS.
+/// If `fallback` is false, error will be returned if the preferred port is out of range or not available.
+pub fn new_std_in_range_bind_lazy_connect_prefer(
+    bind_ip: IpAddr,
+    port: Option<PortRange>,
+    preferred_port: u16,
+    fallback: bool,
+    buf_conf: SocketBufferConfig,
+    misc_opts: UdpMiscSockOpts,
+) -> io::Result<(UdpSocket, SocketAddr)> {
     let socket = new_udp_socket(AddressFamily::from(&bind_ip), buf_conf)?;
+    #[cfg(unix)]
+    super::listen::set_udp_recv_pktinfo(&socket, SocketAddr::new(bind_ip, 0))?;
+    #[cfg(windows)]
+    set_lazy_connect_dual_stack(&socket, SocketAddr::new(bind_ip, 0))?;
+
+    let in_range = match port {
+        Some(range) => range.contains(preferred_port),
+        None => preferred_port != 0,
+    };
+    if in_range {
+        let bind_addr: SockAddr = SocketAddr::new(bind_ip, preferred_port).into();
+        match socket.bind(&bind_addr) {
+            Ok(_) => return finish_lazy_connect(socket, misc_opts),
+            Err(e) => {
+                if !fallback {
+                    return Err(e);
+                }
+            }
+        }
+    } else if !fallback {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            "the preferred port is out of the specified range",
+        ));
+    }
+
+    match port {
+        Some(range) => bind_in_range(socket, bind_ip, range, misc_opts),
+        None => {
+            let bind_addr: SockAddr = SocketAddr::new(bind_ip, 0).into();
+            socket.bind(&bind_addr)?;
+            finish_lazy_connect(socket, misc_opts)
+        }
+    }
+}
+
+/// The wildcard ipv6 socket is ipv6 only by default on Windows, make it dual-stack like on unix,
+/// so ipv4 peers can also be reached. This should be called before bind.
+#[cfg(windows)]
+fn set_lazy_connect_dual_stack(socket: &Socket, bind_addr: SocketAddr) -> io::Result<()> {
+    super::listen::set_only_v6(socket, bind_addr, false)?;
+    super::listen::set_udp_recv_pktinfo(socket, bind_addr, Some(false))
+}
+
+fn finish_lazy_connect(
+    socket: Socket,
+    misc_opts: UdpMiscSockOpts,
+) -> io::Result<(UdpSocket, SocketAddr)> {
+    let socket = UdpSocket::from(socket);
+    let listen_addr = socket.local_addr()?;
+    RawSocket::from(&socket).set_udp_misc_opts(listen_addr, misc_opts)?;
+    Ok((socket, listen_addr))
+}
+
+fn bind_in_range(
+    socket: Socket,
+    bind_ip: IpAddr,
+    port: PortRange,
+    misc_opts: UdpMiscSockOpts,
+) -> io::Result<(UdpSocket, SocketAddr)> {
+    let port_start = port.start();
+    let port_end = port.end();
+
+    debug_assert!(port_start <= port_end);
 
     // like what's has been done in dante/sockd/sockd_request.c
     let tries = port.count().min(10);
@@ -65,20 +188,14 @@ pub fn new_std_in_range_bind_lazy_connect(
         let port = fastrand::u16(port_start..=port_end);
         let bind_addr: SockAddr = SocketAddr::new(bind_ip, port).into();
         if socket.bind(&bind_addr).is_ok() {
-            let socket = UdpSocket::from(socket);
-            let listen_addr = socket.local_addr()?;
-            RawSocket::from(&socket).set_udp_misc_opts(listen_addr, misc_opts)?;
-            return Ok((socket, listen_addr));
+            return finish_lazy_connect(socket, misc_opts);
         }
     }
 
     for port in port_start..=port_end {
         let bind_addr: SockAddr = SocketAddr::new(bind_ip, port).into();
         if socket.bind(&bind_addr).is_ok() {
-            let socket = UdpSocket::from(socket);
-            let listen_addr = socket.local_addr()?;
-            RawSocket::from(&socket).set_udp_misc_opts(listen_addr, misc_opts)?;
-            return Ok((socket, listen_addr));
+            return finish_lazy_connect(socket, misc_opts);
         }
     }
 
@@ -94,7 +211,40 @@ pub fn new_std_bind_relay(
     buf_conf: SocketBufferConfig,
     misc_opts: UdpMiscSockOpts,
 ) -> io::Result<(UdpSocket, SocketAddr)> {
+    // never fallback to the wildcard address of the other family
+    bind.check_family(family)?;
+    let socket = new_udp_socket(family, buf_conf)?;
+    bind_relay(socket, bind, family, misc_opts)
+}
+
+/// Like `new_std_bind_relay`, but the socket will be bound to the device before bind,
+/// which can be a VRF master device on Linux.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+pub fn new_std_bind_relay_in_device(
+    bind: &BindAddr,
+    device: &Interface,
+    family: AddressFamily,
+    buf_conf: SocketBufferConfig,
+    misc_opts: UdpMiscSockOpts,
+) -> io::Result<(UdpSocket, SocketAddr)> {
+    bind.check_family(family)?;
     let socket = new_udp_socket(family, buf_conf)?;
+    crate::bind::bind_to_interface(&socket, device, family)?;
+    bind_relay(socket, bind, family, misc_opts)
+}
+
+fn bind_relay(
+    socket: Socket,
+    bind: &BindAddr,
+    family: AddressFamily,
+    misc_opts: UdpMiscSockOpts,
+) -> io::Result<(UdpSocket, SocketAddr)> {
     bind.bind_for_relay(&socket, family)?;
     let socket = UdpSocket::from(socket);
     let listen_addr = socket.local_addr()?;
@@ -112,23 +262,27 @@ pub fn new_std_bind_listen(config: &UdpListenConfig) -> io::Result<UdpSocket> {
     if let Some(enable) = config.is_ipv6only() {
         super::listen::set_only_v6(&socket, addr, enable)?;
     }
+    #[cfg(target_os = "linux")]
+    if config.transparent() {
+        super::listen::set_udp_transparent(&socket, addr, config.is_ipv6only())?;
+    }
+    // set all the ipv6 related options before bind, or WSAEINVAL will be returned
+    #[cfg(windows)]
+    super::listen::set_udp_recv_pktinfo(&socket, addr, config.is_ipv6only())?;
     let bind_addr = SockAddr::from(addr);
     socket.bind(&bind_addr)?;
-    #[cfg(any(target_os = "linux", target_os = "android"))]
-    if let Some(iface) = config.interface() {
-        socket.bind_device(Some(iface.c_bytes()))?;
-    }
-    #[cfg(any(target_os = "macos", target_os = "illumos", target_os = "solaris"))]
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "illumos",
+        target_os = "solaris"
+    ))]
     if let Some(iface) = config.interface() {
-        match family {
-            AddressFamily::Ipv4 => socket.bind_device_by_index_v4(Some(iface.id()))?,
-            AddressFamily::Ipv6 => socket.bind_device_by_index_v6(Some(iface.id()))?,
-        }
+        crate::bind::bind_to_interface(&socket, iface, family)?;
     }
     #[cfg(unix)]
     super::listen::set_udp_recv_pktinfo(&socket, addr)?;
-    #[cfg(windows)]
-    super::listen::set_udp_recv_pktinfo(&socket, addr, config.is_ipv6only())?;
     RawSocket::from(&socket).set_udp_misc_opts(addr, config.socket_misc_opts())?;
     Ok(UdpSocket::from(socket))
 }
@@ -141,18 +295,54 @@ pub fn new_std_rebind_listen(config: &UdpListenConfig, addr: SocketAddr) -> io::
     if let Some(enable) = config.is_ipv6only() {
         super::listen::set_only_v6(&socket, addr, enable)?;
     }
+    #[cfg(target_os = "linux")]
+    if config.transparent() {
+        super::listen::set_udp_transparent(&socket, addr, config.is_ipv6only())?;
+    }
+    // set all the ipv6 related options before bind, or WSAEINVAL will be returned
+    #[cfg(windows)]
+    super::listen::set_udp_recv_pktinfo(&socket, addr, config.is_ipv6only())?;
     let bind_addr = SockAddr::from(addr);
     socket.bind(&bind_addr)?;
     #[cfg(unix)]
     super::listen::set_udp_recv_pktinfo(&socket, addr)?;
-    #[cfg(windows)]
-    super::listen::set_udp_recv_pktinfo(&socket, addr, config.is_ipv6only())?;
     RawSocket::from(&socket).set_udp_misc_opts(addr, config.socket_misc_opts())?;
     Ok(UdpSocket::from(socket))
 }
 
+/// Create a socket bound to the non-local `local_addr` and connected to `peer_addr`,
+/// so reply packets of a transparent proxy can be sent with the original destination address.
+#[cfg(target_os = "linux")]
+pub fn new_std_transparent_reply(
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+    buf_conf: SocketBufferConfig,
+    misc_opts: UdpMiscSockOpts,
+) -> io::Result<UdpSocket> {
+    let family = AddressFamily::from(&local_addr);
+    if AddressFamily::from(&peer_addr) != family {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "mismatched address family for local and peer address",
+        ));
+    }
+    let socket = new_udp_socket(family, buf_conf)?;
+    socket.set_reuse_address(true)?;
+    match family {
+        AddressFamily::Ipv4 => socket.set_ip_transparent_v4(true)?,
+        AddressFamily::Ipv6 => crate::sockopt::set_ip_transparent_v6(&socket, true)?,
+    }
+    socket.bind(&SockAddr::from(local_addr))?;
+    socket.connect(&SockAddr::from(peer_addr))?;
+    RawSocket::from(&socket).set_udp_misc_opts(peer_addr, misc_opts)?;
+    Ok(UdpSocket::from(socket))
+}
+
 fn new_udp_socket(family: AddressFamily, buf_conf: SocketBufferConfig) -> io::Result<Socket> {
     let socket = new_nonblocking_udp_socket(family)?;
+    // do not let the ICMP port unreachable message of a previous send break the next recv
+    #[cfg(windows)]
+    crate::sockopt::set_udp_connreset(&socket, false)?;
     RawSocket::from(&socket).set_buf_opts(buf_conf)?;
     Ok(socket)
 }
@@ -240,6 +430,211 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bind_in_range_prefer() {
+        let range = PortRange::new(61000, 65000);
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let (socket1, local_addr) = new_std_in_range_bind_lazy_connect(
+            ip,
+            range,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let used_port = local_addr.port();
+
+        // find an available port in range
+        let (socket2, local_addr) = new_std_in_range_bind_lazy_connect(
+            ip,
+            range,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let free_port = local_addr.port();
+        drop(socket2);
+
+        let (_socket, local_addr) = new_std_in_range_bind_lazy_connect_prefer(
+            ip,
+            Some(range),
+            free_port,
+            false,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(local_addr.port(), free_port);
+
+        // the port is in use
+        let (_socket, local_addr) = new_std_in_range_bind_lazy_connect_prefer(
+            ip,
+            Some(range),
+            used_port,
+            true,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        assert_ne!(local_addr.port(), used_port);
+        assert!(range.contains(local_addr.port()));
+        let e = new_std_in_range_bind_lazy_connect_prefer(
+            ip,
+            Some(range),
+            used_port,
+            false,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::AddrInUse);
+
+        // the port is out of range
+        let (_socket, local_addr) = new_std_in_range_bind_lazy_connect_prefer(
+            ip,
+            Some(range),
+            60000,
+            true,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        assert!(range.contains(local_addr.port()));
+        let e = new_std_in_range_bind_lazy_connect_prefer(
+            ip,
+            Some(range),
+            60000,
+            false,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::AddrNotAvailable);
+        drop(socket1);
+    }
+
+    #[test]
+    fn bind_relay() {
+        let bind = BindAddr::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let (_socket, local_addr) = new_std_bind_relay(
+            &bind,
+            AddressFamily::Ipv4,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(local_addr.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_ne!(local_addr.port(), 0);
+
+        let e = new_std_bind_relay(
+            &bind,
+            AddressFamily::Ipv6,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+        let bind = BindAddr::IpPair(Ipv4Addr::LOCALHOST, Ipv6Addr::LOCALHOST);
+        let (_socket, local_addr) = new_std_bind_relay(
+            &bind,
+            AddressFamily::Ipv4,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(local_addr.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn socket_mark() {
+        use socket2::SockRef;
+
+        let mut misc_opts = UdpMiscSockOpts::default();
+        misc_opts.netfilter_mark = Some(0x1234);
+
+        let peer_addr = SocketAddr::from_str("127.0.0.1:514").unwrap();
+        let socket = match new_std_socket_to(
+            peer_addr,
+            &BindAddr::None,
+            SocketBufferConfig::default(),
+            misc_opts,
+        ) {
+            Ok(socket) => socket,
+            Err(e) => {
+                // not running with CAP_NET_ADMIN
+                assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+                assert!(e.to_string().contains("CAP_NET_ADMIN"));
+                return;
+            }
+        };
+        assert_eq!(SockRef::from(&socket).mark().unwrap(), 0x1234);
+
+        let bind = BindAddr::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let (socket, _) = new_std_bind_relay(
+            &bind,
+            AddressFamily::Ipv4,
+            SocketBufferConfig::default(),
+            misc_opts,
+        )
+        .unwrap();
+        assert_eq!(SockRef::from(&socket).mark().unwrap(), 0x1234);
+
+        let (socket, _) = new_std_in_range_bind_lazy_connect(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            PortRange::new(61000, 65000),
+            SocketBufferConfig::default(),
+            misc_opts,
+        )
+        .unwrap();
+        assert_eq!(SockRef::from(&socket).mark().unwrap(), 0x1234);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn bind_device() {
+        use socket2::SockRef;
+
+        let device = Interface::from_str("lo").unwrap();
+        let peer_addr = SocketAddr::from_str("127.0.0.1:514").unwrap();
+        let socket = match new_std_socket_to_in_device(
+            peer_addr,
+            &BindAddr::None,
+            &device,
+            SocketBufferConfig::default(),
+            Default::default(),
+        ) {
+            Ok(socket) => socket,
+            Err(e) => {
+                // not running with CAP_NET_RAW on old kernels
+                assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+                return;
+            }
+        };
+        let bound = SockRef::from(&socket).device().unwrap();
+        assert_eq!(bound.as_deref(), Some(b"lo".as_slice()));
+
+        let bind = BindAddr::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let (socket, local_addr) = new_std_bind_relay_in_device(
+            &bind,
+            &device,
+            AddressFamily::Ipv4,
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(local_addr.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let bound = SockRef::from(&socket).device().unwrap();
+        assert_eq!(bound.as_deref(), Some(b"lo".as_slice()));
+
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.send_to(b"test", peer.local_addr().unwrap()).unwrap();
+        let mut buf = [0u8; 16];
+        let (len, from) = peer.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"test");
+        assert_eq!(from, local_addr);
+    }
+
     #[cfg(not(target_os = "openbsd"))]
     #[test]
     fn listen() {
@@ -337,4 +732,91 @@ mod tests {
         assert_ne!(local_addr.port(), 0);
         drop(socket);
     }
+
+    #[cfg(windows)]
+    #[test]
+    fn listen_windows() {
+        let mut config = UdpListenConfig::default();
+
+        // ipv6 only by default
+        let socket = new_std_bind_listen(&config).unwrap();
+        assert!(socket2::SockRef::from(&socket).only_v6().unwrap());
+        drop(socket);
+
+        config.set_ipv6_only(false);
+        let socket = new_std_bind_listen(&config).unwrap();
+        assert!(!socket2::SockRef::from(&socket).only_v6().unwrap());
+        let local_addr = socket.local_addr().unwrap();
+        assert_ne!(local_addr.port(), 0);
+        drop(socket);
+
+        config.set_socket_address(SocketAddr::from_str("127.0.0.1:0").unwrap());
+        let socket = new_std_bind_listen(&config).unwrap();
+        let local_addr = socket.local_addr().unwrap();
+        assert_eq!(local_addr.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        drop(socket);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn bind_to_ip_windows() {
+        let (socket, local_addr) =
+            new_std_bind_lazy_connect(None, SocketBufferConfig::default(), Default::default())
+                .unwrap();
+        assert_ne!(local_addr.port(), 0);
+        // dual-stack like on unix
+        assert!(!socket2::SockRef::from(&socket).only_v6().unwrap());
+        let peer_addr = SocketAddr::new(IpAddr::V6(Ipv4Addr::LOCALHOST.to_ipv6_mapped()), 514);
+        socket.connect(peer_addr).unwrap();
+
+        let (_socket, local_addr) = new_std_in_range_bind_lazy_connect(
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            PortRange::new(61000, 65000),
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        assert!(local_addr.port() >= 61000);
+    }
+
+    #[cfg(windows)]
+    #[tokio::test]
+    async fn loopback_round_trip_windows() {
+        let config = {
+            let mut config = UdpListenConfig::default();
+            config.set_socket_address(SocketAddr::from_str("127.0.0.1:0").unwrap());
+            config
+        };
+        let server = new_std_bind_listen(&config).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let server = tokio::net::UdpSocket::from_std(server).unwrap();
+
+        // get a closed port
+        let closed = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let (client, _) = new_std_bind_lazy_connect(
+            Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            SocketBufferConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let client = tokio::net::UdpSocket::from_std(client).unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        // the ICMP port unreachable message should not break the following recv
+        server.send_to(b"lost", closed_addr).await.unwrap();
+
+        client.send_to(b"ping", server_addr).await.unwrap();
+        let mut buf = [0u8; 16];
+        let (len, peer) = server.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"ping");
+        assert_eq!(peer, client_addr);
+
+        server.send_to(b"pong", peer).await.unwrap();
+        let (len, peer) = client.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"pong");
+        assert_eq!(peer, server_addr);
+    }
 }
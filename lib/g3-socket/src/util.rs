@@ -4,6 +4,8 @@
  */
 
 use std::fmt;
+#[cfg(unix)]
+use std::io;
 use std::net::{IpAddr, SocketAddr};
 
 use socket2::Domain;
@@ -59,6 +61,39 @@ pub fn native_socket_addr(orig: SocketAddr) -> SocketAddr {
     }
 }
 
+/// Get the host name of the ip address by reverse lookup, using the system resolver.
+///
+/// This is a blocking call, and an error will be returned if there is no name for the ip address.
+#[cfg(unix)]
+pub fn lookup_host_name(ip: IpAddr) -> io::Result<String> {
+    use std::ffi::CStr;
+
+    const NI_MAXHOST: usize = 1025;
+
+    let addr = socket2::SockAddr::from(SocketAddr::new(ip, 0));
+    let mut host = [0 as libc::c_char; NI_MAXHOST];
+    let ret = unsafe {
+        libc::getnameinfo(
+            addr.as_ptr().cast(),
+            addr.len(),
+            host.as_mut_ptr(),
+            NI_MAXHOST as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    if ret != 0 {
+        let reason = unsafe { CStr::from_ptr(libc::gai_strerror(ret)) };
+        return Err(io::Error::other(format!(
+            "getnameinfo failed: {}",
+            reason.to_string_lossy()
+        )));
+    }
+    let name = unsafe { CStr::from_ptr(host.as_ptr()) };
+    Ok(name.to_string_lossy().into_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,6 +44,13 @@ impl Socks5Reply {
         }
     }
 
+    /// Get the failure reply for the reply code
+    ///
+    /// The code should not be 0x00, as the bound address is not available here.
+    pub fn failure(code: u8) -> Self {
+        Socks5Reply::new(code, SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))
+    }
+
     fn code(&self) -> u8 {
         match self {
             Socks5Reply::Succeeded(_) => 0x00,
@@ -17,6 +17,27 @@ pub(crate) const UDP_HEADER_LEN_IPV6: usize = 22;
 pub struct UdpInput {}
 
 impl UdpInput {
+    /// Get the length of the socks5 udp header at the start of the packet
+    ///
+    /// Only the address type and the domain length will be checked,
+    /// 0 will be returned if the header is incomplete.
+    pub fn calc_header_len(buf: &[u8]) -> usize {
+        if buf.len() < 5 {
+            return 0;
+        }
+        let header_len = match buf[3] {
+            0x01 => UDP_HEADER_LEN_IPV4,
+            0x03 => 4 + 1 + buf[4] as usize + 2,
+            0x04 => UDP_HEADER_LEN_IPV6,
+            _ => return 0,
+        };
+        if buf.len() < header_len {
+            0
+        } else {
+            header_len
+        }
+    }
+
     pub fn parse_header(buf: &[u8]) -> Result<(usize, UpstreamAddr), SocksUdpPacketError> {
         let len = buf.len();
         if len <= 8 {
@@ -167,3 +188,29 @@ impl Default for SocksUdpHeader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_len() {
+        let mut header = SocksUdpHeader::default();
+        for ups in [
+            UpstreamAddr::from_ip_and_port(IpAddr::V4(Ipv4Addr::LOCALHOST), 53),
+            UpstreamAddr::from_ip_and_port(IpAddr::V6(Ipv6Addr::LOCALHOST), 53),
+            UpstreamAddr::from_host_str_and_port("www.example.net", 443).unwrap(),
+        ] {
+            let mut buf = header.encode(&ups).to_vec();
+            let expected = UdpOutput::calc_header_len(&ups);
+            assert_eq!(buf.len(), expected);
+            assert_eq!(UdpInput::calc_header_len(&buf), expected);
+            buf.extend_from_slice(b"payload");
+            assert_eq!(UdpInput::calc_header_len(&buf), expected);
+            let (off, _) = UdpInput::parse_header(&buf).unwrap();
+            assert_eq!(off, expected);
+            assert_eq!(UdpInput::calc_header_len(&buf[..expected - 1]), 0);
+        }
+        assert_eq!(UdpInput::calc_header_len(&[0, 0, 0, 0x05, 0, 0, 0]), 0);
+    }
+}
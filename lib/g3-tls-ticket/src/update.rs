@@ -88,6 +88,8 @@ impl TicketKeyUpdate {
     }
 
     async fn check_roll_ticket(&mut self, remote_source: Option<&TicketSource>) {
+        self.check_retired_keys();
+
         let mut roll_local = true;
         if let Some(source) = &remote_source {
             match source.fetch_remote_keys().await {
@@ -128,7 +130,15 @@ impl TicketKeyUpdate {
     }
 
     fn update_encrypt_key(&mut self, key: OpensslTicketKey, now: Instant) {
-        let old_key = self.ticketer.encrypt_key();
+        let local_roll_time = Duration::from_secs((key.lifetime() >> 1) as u64);
+        self.local_roll_at = now + local_roll_time;
+        let key = Arc::new(key);
+        self.ticketer.add_decrypt_key(key.clone());
+        let old_key = self.ticketer.swap_encrypt_key(key);
+        self.expire_old_key(old_key);
+    }
+
+    fn expire_old_key(&mut self, old_key: Arc<OpensslTicketKey>) {
         let old_key_name = old_key.name();
         if !self.expire_set.contains(&old_key_name) {
             // maybe a local generated key, or a remote enc key but not in dec list
@@ -136,10 +146,19 @@ impl TicketKeyUpdate {
             self.expire_set.insert(old_key_name);
             self.expire_queue.insert(old_key_name, expire_time);
         }
-        let local_roll_time = Duration::from_secs((key.lifetime() >> 1) as u64);
-        self.local_roll_at = now + local_roll_time;
-        let key = Arc::new(key);
-        self.ticketer.set_encrypt_key(key.clone());
-        self.ticketer.add_decrypt_key(key);
+    }
+
+    /// Expire the old keys that have been replaced by a forced rotation
+    fn check_retired_keys(&mut self) {
+        let retired_keys = self.ticketer.take_retired_keys();
+        if retired_keys.is_empty() {
+            return;
+        }
+        for old_key in retired_keys {
+            self.expire_old_key(old_key);
+        }
+        let lifetime = self.ticketer.encrypt_key().lifetime();
+        let local_roll_time = Duration::from_secs((lifetime >> 1) as u64);
+        self.local_roll_at = Instant::now() + local_roll_time;
     }
 }
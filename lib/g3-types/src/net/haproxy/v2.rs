@@ -36,6 +36,15 @@ const BYTE14_TCP6: u8 = AF_INET6 | PROTO_STREAM;
 // const V2_HEADER_TCP4: &[u8] = concat_bytes!(V2_MAGIC_HEADER, &[BYTE_13_PROXY, BYTE14_TCP4, 0x00, 12]);
 // const V2_HEADER_TCP6: &[u8] = concat_bytes!(V2_MAGIC_HEADER, &[BYTE_13_PROXY, BYTE14_TCP6, 0x00, 36]);
 
+const PP2_TYPE_ALPN: u8 = 0x01;
+const PP2_TYPE_AUTHORITY: u8 = 0x02;
+const PP2_TYPE_SSL: u8 = 0x20;
+const PP2_SUBTYPE_SSL_VERSION: u8 = 0x21;
+const PP2_SUBTYPE_SSL_CIPHER: u8 = 0x23;
+
+const PP2_CLIENT_SSL: u8 = 0x01;
+const PP2_CLIENT_CERT_CONN: u8 = 0x02;
+
 const PP2_TYPE_CUSTOM_UPSTREAM: u8 = 0xE0;
 const PP2_TYPE_CUSTOM_TLS_NAME: u8 = 0xE1;
 const PP2_TYPE_CUSTOM_USERNAME: u8 = 0xE2;
@@ -114,6 +123,49 @@ impl ProxyProtocolV2Encoder {
         Ok(())
     }
 
+    pub fn push_alpn(&mut self, protocol: &[u8]) -> Result<(), ProxyProtocolEncodeError> {
+        self.push_tlv(PP2_TYPE_ALPN, protocol)
+    }
+
+    pub fn push_authority(&mut self, host_name: &str) -> Result<(), ProxyProtocolEncodeError> {
+        self.push_tlv(PP2_TYPE_AUTHORITY, host_name.as_bytes())
+    }
+
+    /// Push the PP2_TYPE_SSL TLV, with the version and cipher sub-TLVs.
+    ///
+    /// `cert_verified` should be set if the client has presented a certificate in the connection.
+    pub fn push_ssl(
+        &mut self,
+        cert_verified: Option<bool>,
+        version: &str,
+        cipher: &str,
+    ) -> Result<(), ProxyProtocolEncodeError> {
+        let mut client = PP2_CLIENT_SSL;
+        // the verify field is zero only if the client cert has been successfully verified
+        let mut verify = 1u32;
+        if let Some(verified) = cert_verified {
+            client |= PP2_CLIENT_CERT_CONN;
+            if verified {
+                verify = 0;
+            }
+        }
+
+        let v_len = 5 + 3 + version.len() + 3 + cipher.len();
+        let len = u16::try_from(v_len).map_err(ProxyProtocolEncodeError::InvalidU16Length)?;
+        let offset = self.len;
+        if offset + 3 + v_len > V2_BUF_CAP {
+            return Err(ProxyProtocolEncodeError::TotalLengthOverflow);
+        }
+        self.buf[offset] = PP2_TYPE_SSL;
+        self.buf[offset + 1..offset + 3].copy_from_slice(&len.to_be_bytes());
+        self.buf[offset + 3] = client;
+        self.buf[offset + 4..offset + 8].copy_from_slice(&verify.to_be_bytes());
+        self.len = offset + 8;
+        // the total length has been checked, so the sub TLVs won't fail
+        self.push_tlv(PP2_SUBTYPE_SSL_VERSION, version.as_bytes())?;
+        self.push_tlv(PP2_SUBTYPE_SSL_CIPHER, cipher.as_bytes())
+    }
+
     pub fn push_upstream(
         &mut self,
         upstream: &UpstreamAddr,
@@ -205,6 +257,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn t_tcp4_tls() {
+        let client = SocketAddr::from_str("192.168.0.1:56324").unwrap();
+        let server = SocketAddr::from_str("192.168.0.11:443").unwrap();
+
+        let mut encoder = ProxyProtocolV2Encoder::new_tcp(client, server).unwrap();
+        encoder.push_alpn(b"h2").unwrap();
+        encoder.push_authority("a.net").unwrap();
+        encoder.push_ssl(None, "TLSv1.3", "X").unwrap();
+        assert_eq!(
+            encoder.finalize(),
+            b"\x0d\x0a\x0d\x0a\x00\x0d\x0a\x51\x55\x49\x54\x0a\
+              \x21\x11\x00\x2F\
+              \xC0\xA8\x00\x01\
+              \xC0\xA8\x00\x0B\
+              \xDC\x04\x01\xBB\
+              \x01\x00\x02h2\
+              \x02\x00\x05a.net\
+              \x20\x00\x13\x01\x00\x00\x00\x01\
+              \x21\x00\x07TLSv1.3\
+              \x23\x00\x01X"
+        );
+
+        let mut encoder = ProxyProtocolV2Encoder::new_tcp(client, server).unwrap();
+        encoder.push_ssl(Some(true), "TLSv1.2", "X").unwrap();
+        let data = encoder.finalize();
+        assert_eq!(&data[28..36], b"\x20\x00\x13\x03\x00\x00\x00\x00");
+
+        let mut encoder = ProxyProtocolV2Encoder::new_tcp(client, server).unwrap();
+        let cipher = "X".repeat(V2_BUF_CAP);
+        assert!(encoder.push_ssl(None, "TLSv1.3", &cipher).is_err());
+        assert_eq!(encoder.finalize().len(), 28);
+    }
+
     #[test]
     fn t_tcp6() {
         let client = SocketAddr::from_str("[2001:db8::1]:56324").unwrap();
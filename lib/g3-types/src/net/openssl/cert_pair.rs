@@ -4,11 +4,12 @@
  */
 
 use anyhow::anyhow;
-use openssl::pkey::{PKey, Private};
+use openssl::pkey::{Id, PKey, Private};
 use openssl::ssl::SslContextBuilder;
 use openssl::x509::X509;
 
 use super::OpensslSessionIdContext;
+use crate::net::TlsCertStatus;
 
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
 pub struct OpensslCertificatePair {
@@ -28,10 +29,30 @@ impl OpensslCertificatePair {
         Ok(())
     }
 
+    /// Check the certificates and the private key, and get the status of the leaf certificate
+    pub fn cert_status(&self) -> anyhow::Result<TlsCertStatus> {
+        let mut certs = Vec::with_capacity(self.chain_certs.len() + 1);
+        for cert in std::iter::once(&self.leaf_cert).chain(self.chain_certs.iter()) {
+            let cert =
+                X509::from_der(cert.as_slice()).map_err(|e| anyhow!("invalid certificate: {e}"))?;
+            certs.push(cert);
+        }
+        let key = PKey::private_key_from_der(self.key.as_slice())
+            .map_err(|e| anyhow!("invalid private key: {e}"))?;
+        TlsCertStatus::check_openssl(&certs, &key)
+    }
+
     pub fn is_set(&self) -> bool {
         !self.leaf_cert.is_empty()
     }
 
+    /// Get the key type of the private key
+    pub fn key_id(&self) -> Option<Id> {
+        PKey::private_key_from_der(self.key.as_slice())
+            .ok()
+            .map(|key| key.id())
+    }
+
     pub fn set_certificates(&mut self, certs: Vec<X509>) -> anyhow::Result<()> {
         let certs_len = certs.len();
 
@@ -21,7 +21,7 @@ use openssl::x509::store::X509StoreBuilder;
 
 use super::{OpensslCertificatePair, OpensslProtocol, OpensslTlcpCertificatePair};
 use crate::net::tls::AlpnProtocol;
-use crate::net::{Host, TlsAlpn, TlsServerName, TlsVersion, UpstreamAddr};
+use crate::net::{Host, TlsAlpn, TlsCertStatus, TlsServerName, TlsVersion, UpstreamAddr};
 
 mod intercept;
 pub use intercept::{OpensslInterceptionClientConfig, OpensslInterceptionClientConfigBuilder};
@@ -105,6 +105,7 @@ pub struct OpensslClientConfigBuilder {
     no_default_ca_certs: bool,
     client_cert_pair: Option<OpensslCertificatePair>,
     client_tlcp_cert_pair: Option<OpensslTlcpCertificatePair>,
+    allow_expired: bool,
     handshake_timeout: Duration,
     session_cache: OpensslSessionCacheConfig,
     supported_groups: String,
@@ -130,6 +131,7 @@ impl Default for OpensslClientConfigBuilder {
             no_default_ca_certs: false,
             client_cert_pair: None,
             client_tlcp_cert_pair: None,
+            allow_expired: false,
             handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
             session_cache: OpensslSessionCacheConfig::default(),
             supported_groups: String::default(),
@@ -236,6 +238,24 @@ impl OpensslClientConfigBuilder {
         self.client_tlcp_cert_pair.replace(pair)
     }
 
+    pub fn set_allow_expired(&mut self, allow: bool) {
+        self.allow_expired = allow;
+    }
+
+    /// Get the status of all the client certificates
+    pub fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::with_capacity(3);
+        if let Some(cert_pair) = &self.client_cert_pair {
+            all.push(cert_pair.cert_status()?);
+        }
+        if let Some(tlcp_cert_pair) = &self.client_tlcp_cert_pair {
+            all.extend(tlcp_cert_pair.cert_status()?);
+        }
+        all.iter_mut()
+            .for_each(|s| s.set_allow_expired(self.allow_expired));
+        Ok(all)
+    }
+
     #[inline]
     pub fn set_no_session_cache(&mut self) {
         self.session_cache.set_no_session_cache();
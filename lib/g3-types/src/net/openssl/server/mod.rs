@@ -18,7 +18,7 @@ use openssl::x509::X509;
 use openssl::x509::store::X509StoreBuilder;
 
 use super::{OpensslCertificatePair, OpensslTlcpCertificatePair};
-use crate::net::{AlpnProtocol, RollingTicketer};
+use crate::net::{AlpnProtocol, RollingTicketer, TlsCertStatus};
 
 mod intercept;
 pub use intercept::{OpensslInterceptionServerConfig, OpensslInterceptionServerConfigBuilder};
@@ -44,6 +44,7 @@ pub struct OpensslServerConfig {
 pub struct OpensslServerConfigBuilder {
     cert_pairs: Vec<OpensslCertificatePair>,
     tlcp_cert_pairs: Vec<OpensslTlcpCertificatePair>,
+    allow_expired: bool,
     client_auth: bool,
     client_auth_certs: Vec<Vec<u8>>,
     session_id_context: String,
@@ -57,6 +58,7 @@ impl OpensslServerConfigBuilder {
         OpensslServerConfigBuilder {
             cert_pairs: Vec::with_capacity(1),
             tlcp_cert_pairs: Vec::with_capacity(1),
+            allow_expired: false,
             client_auth: false,
             client_auth_certs: Vec::new(),
             session_id_context: String::new(),
@@ -129,6 +131,30 @@ impl OpensslServerConfigBuilder {
         self.accept_timeout = timeout;
     }
 
+    pub fn set_allow_expired(&mut self, allow: bool) {
+        self.allow_expired = allow;
+    }
+
+    /// Get the status of all the server certificates
+    pub fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::with_capacity(self.cert_pairs.len() + self.tlcp_cert_pairs.len() * 2);
+        for (i, cert_pair) in self.cert_pairs.iter().enumerate() {
+            let status = cert_pair
+                .cert_status()
+                .context(format!("invalid cert pair #{i}"))?;
+            all.push(status);
+        }
+        for (i, cert_pair) in self.tlcp_cert_pairs.iter().enumerate() {
+            let status = cert_pair
+                .cert_status()
+                .context(format!("invalid tlcp cert pair #{i}"))?;
+            all.extend(status);
+        }
+        all.iter_mut()
+            .for_each(|s| s.set_allow_expired(self.allow_expired));
+        Ok(all)
+    }
+
     #[cfg(not(tongsuo))]
     fn build_tls_acceptor(
         &self,
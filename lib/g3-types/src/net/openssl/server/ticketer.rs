@@ -28,9 +28,12 @@ impl RollingTicketer<OpensslTicketKey> {
         cipher_ctx: &mut CipherCtxRef,
         hmac_ctx: &mut HMacCtxRef,
     ) -> Result<TicketKeyStatus, ErrorStack> {
-        self.enc_key
+        let status = self
+            .enc_key
             .load()
-            .encrypt_init(key_name, iv, cipher_ctx, hmac_ctx)
+            .encrypt_init(key_name, iv, cipher_ctx, hmac_ctx)?;
+        self.add_issued();
+        Ok(status)
     }
 
     pub fn decrypt_init(
@@ -40,7 +43,7 @@ impl RollingTicketer<OpensslTicketKey> {
         cipher_ctx: &mut CipherCtxRef,
         hmac_ctx: &mut HMacCtxRef,
     ) -> Result<TicketKeyStatus, ErrorStack> {
-        let Some(key) = self.get_decrypt_key(key_name) else {
+        let Some(key) = self.resume_decrypt_key(key_name) else {
             return Ok(TicketKeyStatus::FAILED);
         };
 
@@ -62,7 +65,10 @@ impl ProducesTickets for RollingTicketer<OpensslTicketKey> {
 
     fn encrypt(&self, plain: &[u8]) -> Option<Vec<u8>> {
         match self.enc_key.load().encrypt(plain) {
-            Ok(d) => Some(d),
+            Ok(d) => {
+                self.add_issued();
+                Some(d)
+            }
             Err(e) => {
                 warn!("ticket encrypt failed: {e}");
                 None
@@ -71,7 +77,7 @@ impl ProducesTickets for RollingTicketer<OpensslTicketKey> {
     }
 
     fn decrypt(&self, cipher: &[u8]) -> Option<Vec<u8>> {
-        self.get_decrypt_key(cipher).and_then(|key| {
+        self.resume_decrypt_key(cipher).and_then(|key| {
             key.decrypt(cipher).unwrap_or_else(|e| {
                 warn!("ticket decrypt failed: {e}");
                 None
@@ -86,3 +92,160 @@ impl fmt::Debug for RollingTicketer<OpensslTicketKey> {
         f.debug_struct("RollingTicketer based on OpenSSL").finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::ssl::{
+        Ssl, SslConnector, SslContext, SslMethod, SslSession, SslVerifyMode, SslVersion,
+    };
+    use openssl::x509::{X509, X509Builder, X509NameBuilder};
+
+    use crate::net::RollingTicketKey;
+
+    fn generate_cert() -> (X509, PKey<Private>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, "localhost")
+            .unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        (builder.build(), key)
+    }
+
+    fn build_server(ticketer: Arc<RollingTicketer<OpensslTicketKey>>) -> SslContext {
+        let (cert, key) = generate_cert();
+        let mut builder = SslContext::builder(SslMethod::tls_server()).unwrap();
+        builder.set_certificate(&cert).unwrap();
+        builder.set_private_key(&key).unwrap();
+        // tickets are sent along with the handshake in TLS 1.2
+        builder
+            .set_max_proto_version(Some(SslVersion::TLS1_2))
+            .unwrap();
+        builder
+            .set_ticket_key_callback(move |_ssl, name, iv, cipher_ctx, hmac_ctx, is_enc| {
+                if is_enc {
+                    ticketer.encrypt_init(name, iv, cipher_ctx, hmac_ctx)
+                } else {
+                    ticketer.decrypt_init(name, iv, cipher_ctx, hmac_ctx)
+                }
+            })
+            .unwrap();
+        builder.build()
+    }
+
+    struct Client {
+        connector: SslConnector,
+        listener: TcpListener,
+        server: SslContext,
+    }
+
+    impl Client {
+        /// Connect to the server, and return the new session and whether the old one is reused
+        fn connect(&self, session: Option<&SslSession>) -> (SslSession, bool) {
+            let addr = self.listener.local_addr().unwrap();
+            let server = self.server.clone();
+            let listener = self.listener.try_clone().unwrap();
+            let server_thread = std::thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                let ssl = Ssl::new(&server).unwrap();
+                let _ = ssl.accept(stream);
+            });
+
+            let mut ssl = self
+                .connector
+                .configure()
+                .unwrap()
+                .into_ssl("localhost")
+                .unwrap();
+            if let Some(session) = session {
+                unsafe { ssl.set_session(session).unwrap() };
+            }
+            let mut stream = ssl.connect(TcpStream::connect(addr).unwrap()).unwrap();
+            server_thread.join().unwrap();
+
+            let reused = stream.ssl().session_reused();
+            let session = stream.ssl().session().unwrap().to_owned();
+            // the session will be marked as not resumable if closed without a close notify
+            let _ = stream.shutdown();
+            (session, reused)
+        }
+    }
+
+    #[test]
+    fn resume_across_rotation() {
+        let ticketer = Arc::new(RollingTicketer::new(
+            OpensslTicketKey::new_random(300).unwrap(),
+        ));
+
+        let mut connector = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        connector.set_verify(SslVerifyMode::NONE);
+        connector
+            .set_max_proto_version(Some(SslVersion::TLS1_2))
+            .unwrap();
+        let client = Client {
+            connector: connector.build(),
+            listener: TcpListener::bind("127.0.0.1:0").unwrap(),
+            server: build_server(ticketer.clone()),
+        };
+
+        let (session1, reused) = client.connect(None);
+        assert!(!reused);
+        let snapshot = ticketer.snapshot();
+        assert_eq!(snapshot.issued, 1);
+        assert_eq!(snapshot.resumed_current, 0);
+
+        let old_key = ticketer.encrypt_key();
+        ticketer.force_rotate().unwrap();
+        assert_ne!(ticketer.encrypt_key().name(), old_key.name());
+        let retired = ticketer.take_retired_keys();
+        assert_eq!(retired.len(), 1);
+        assert_eq!(retired[0].name(), old_key.name());
+        assert!(ticketer.take_retired_keys().is_empty());
+
+        // the old key is still valid for decryption, and a new ticket will be issued
+        let (session2, reused) = client.connect(Some(&session1));
+        assert!(reused);
+        let snapshot = ticketer.snapshot();
+        assert_eq!(snapshot.resumed_current, 0);
+        assert_eq!(snapshot.resumed_previous, 1);
+        assert_eq!(snapshot.issued, 2);
+
+        let (_, reused) = client.connect(Some(&session2));
+        assert!(reused);
+        let snapshot = ticketer.snapshot();
+        assert_eq!(snapshot.resumed_current, 1);
+        assert_eq!(snapshot.resumed_previous, 1);
+
+        // expire the old key
+        ticketer.del_decrypt_key(old_key.name());
+        let (_, reused) = client.connect(Some(&session1));
+        assert!(!reused);
+        let snapshot = ticketer.snapshot();
+        assert_eq!(snapshot.rejected_unknown_key, 1);
+        assert_eq!(snapshot.resumed_current, 1);
+        assert_eq!(snapshot.resumed_previous, 1);
+    }
+}
@@ -11,6 +11,7 @@ use openssl::x509::X509;
 
 #[cfg(tongsuo)]
 use super::OpensslSessionIdContext;
+use crate::net::TlsCertStatus;
 
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
 pub struct OpensslTlcpCertificatePair {
@@ -38,6 +39,19 @@ impl OpensslTlcpCertificatePair {
         Ok(())
     }
 
+    /// Check the sign and enc certificates with their private keys, and get the status of them
+    pub fn cert_status(&self) -> anyhow::Result<[TlsCertStatus; 2]> {
+        let check = |cert: &[u8], key: &[u8]| {
+            let cert = X509::from_der(cert).map_err(|e| anyhow!("invalid certificate: {e}"))?;
+            let key =
+                PKey::private_key_from_der(key).map_err(|e| anyhow!("invalid private key: {e}"))?;
+            TlsCertStatus::check_openssl(&[cert], &key)
+        };
+        let sign = check(&self.sign_leaf_cert, &self.sign_key)?;
+        let enc = check(&self.enc_leaf_cert, &self.enc_key)?;
+        Ok([sign, enc])
+    }
+
     pub fn set_sign_certificates(&mut self, certs: Vec<X509>) -> anyhow::Result<()> {
         let mut certs_iter = certs.into_iter();
         let leaf_cert = certs_iter
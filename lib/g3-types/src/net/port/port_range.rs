@@ -3,6 +3,7 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::fmt;
 use std::str::FromStr;
 
 use anyhow::anyhow;
@@ -36,6 +37,11 @@ impl PortRange {
         self.end
     }
 
+    #[inline]
+    pub fn contains(&self, port: u16) -> bool {
+        self.start <= port && port <= self.end
+    }
+
     pub fn check(&self) -> anyhow::Result<()> {
         if self.start == 0 {
             return Err(anyhow!("the start port should not be 0"));
@@ -51,6 +57,12 @@ impl PortRange {
     }
 }
 
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
 impl FromStr for PortRange {
     type Err = anyhow::Error;
 
@@ -83,6 +95,12 @@ mod tests {
 
         let range = PortRange::from_str("61000 - 62000").unwrap();
         assert_eq!(r, range);
+
+        assert!(r.contains(61000));
+        assert!(r.contains(62000));
+        assert!(!r.contains(60999));
+        assert!(!r.contains(62001));
+        assert_eq!(r.to_string(), "61000-62000");
     }
 
     #[test]
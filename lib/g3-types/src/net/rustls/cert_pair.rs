@@ -4,8 +4,15 @@
  */
 
 use anyhow::anyhow;
+#[cfg(feature = "openssl")]
+use openssl::pkey::PKey;
+#[cfg(feature = "openssl")]
+use openssl::x509::X509;
 use rustls_pki_types::{CertificateDer, PrivateKeyDer};
 
+#[cfg(feature = "openssl")]
+use crate::net::TlsCertStatus;
+
 #[derive(Default)]
 pub struct RustlsCertificatePairBuilder {
     certs: Vec<CertificateDer<'static>>,
@@ -63,6 +70,20 @@ impl RustlsCertificatePair {
         &self.key
     }
 
+    /// Check the certificates and the private key, and get the status of the leaf certificate
+    #[cfg(feature = "openssl")]
+    pub fn cert_status(&self) -> anyhow::Result<TlsCertStatus> {
+        let mut certs = Vec::with_capacity(self.certs.len());
+        for cert in &self.certs {
+            let cert =
+                X509::from_der(cert.as_ref()).map_err(|e| anyhow!("invalid certificate: {e}"))?;
+            certs.push(cert);
+        }
+        let key = PKey::private_key_from_der(self.key.secret_der())
+            .map_err(|e| anyhow!("invalid private key: {e}"))?;
+        TlsCertStatus::check_openssl(&certs, &key)
+    }
+
     pub fn into_inner(self) -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
         (self.certs, self.key)
     }
@@ -14,6 +14,8 @@ use rustls::{ClientConfig, RootCertStore};
 use rustls_pki_types::CertificateDer;
 
 use super::RustlsCertificatePair;
+#[cfg(feature = "openssl")]
+use crate::net::TlsCertStatus;
 use crate::net::tls::AlpnProtocol;
 
 const MINIMAL_HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(100);
@@ -38,6 +40,7 @@ pub struct RustlsClientConfigBuilder {
     disable_sni: bool,
     max_fragment_size: Option<usize>,
     client_cert_pair: Option<RustlsCertificatePair>,
+    allow_expired: bool,
     ca_certs: Vec<CertificateDer<'static>>,
     no_default_ca_certs: bool,
     use_builtin_ca_certs: bool,
@@ -51,6 +54,7 @@ impl Default for RustlsClientConfigBuilder {
             disable_sni: false,
             max_fragment_size: None,
             client_cert_pair: None,
+            allow_expired: false,
             ca_certs: vec![],
             no_default_ca_certs: false,
             use_builtin_ca_certs: false,
@@ -84,6 +88,21 @@ impl RustlsClientConfigBuilder {
         self.client_cert_pair.replace(pair)
     }
 
+    pub fn set_allow_expired(&mut self, allow: bool) {
+        self.allow_expired = allow;
+    }
+
+    /// Get the status of the client certificate
+    #[cfg(feature = "openssl")]
+    pub fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let Some(cert_pair) = &self.client_cert_pair else {
+            return Ok(Vec::new());
+        };
+        let mut status = cert_pair.cert_status()?;
+        status.set_allow_expired(self.allow_expired);
+        Ok(vec![status])
+    }
+
     pub fn set_ca_certificates(&mut self, certs: Vec<CertificateDer<'static>>) {
         self.ca_certs = certs;
     }
@@ -18,7 +18,7 @@ use super::{
 };
 use crate::net::tls::AlpnProtocol;
 #[cfg(feature = "openssl")]
-use crate::net::{OpensslTicketKey, RollingTicketer};
+use crate::net::{OpensslTicketKey, RollingTicketer, TlsCertStatus};
 
 #[derive(Clone)]
 pub struct RustlsServerConfig {
@@ -36,6 +36,7 @@ pub struct RustlsQuicServerConfig {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct RustlsServerConfigBuilder {
     cert_pairs: Vec<RustlsCertificatePair>,
+    allow_expired: bool,
     client_auth: bool,
     client_auth_certs: Option<Vec<CertificateDer<'static>>>,
     use_session_ticket: bool,
@@ -47,6 +48,7 @@ impl RustlsServerConfigBuilder {
     pub fn empty() -> Self {
         RustlsServerConfigBuilder {
             cert_pairs: Vec::with_capacity(1),
+            allow_expired: false,
             client_auth: false,
             client_auth_certs: None,
             use_session_ticket: true,
@@ -87,6 +89,24 @@ impl RustlsServerConfigBuilder {
         self.cert_pairs.push(cert_pair);
     }
 
+    pub fn set_allow_expired(&mut self, allow: bool) {
+        self.allow_expired = allow;
+    }
+
+    /// Get the status of all the server certificates
+    #[cfg(feature = "openssl")]
+    pub fn cert_status(&self) -> anyhow::Result<Vec<TlsCertStatus>> {
+        let mut all = Vec::with_capacity(self.cert_pairs.len());
+        for (i, cert_pair) in self.cert_pairs.iter().enumerate() {
+            let mut status = cert_pair
+                .cert_status()
+                .context(format!("invalid cert pair #{i}"))?;
+            status.set_allow_expired(self.allow_expired);
+            all.push(status);
+        }
+        Ok(all)
+    }
+
     #[inline]
     pub fn set_accept_timeout(&mut self, timeout: Duration) {
         self.accept_timeout = timeout;
@@ -29,8 +29,12 @@ pub struct TcpMiscSockOpts {
         target_os = "illumos"
     ))]
     congestion_control: Option<Arc<str>>,
-    #[cfg(target_os = "linux")]
+    /// SO_MARK on Linux, or SO_USER_COOKIE on FreeBSD
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
     pub netfilter_mark: Option<u32>,
+    /// TCP_NOTSENT_LOWAT, the max size of the unsent data in the kernel send buffer
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub notsent_lowat: Option<u32>,
 }
 
 impl TcpMiscSockOpts {
@@ -54,6 +58,47 @@ impl TcpMiscSockOpts {
         self.congestion_control.as_ref().map(|v| v.as_bytes())
     }
 
+    /// Set the DSCP value, which fills the upper 6 bits of IPv4 TOS and IPv6 traffic class
+    ///
+    /// The value should be no more than 63, or the extra bits will be lost.
+    /// Only IPv4 TOS will be set on Windows, as IPv6 traffic class is not supported there.
+    pub fn set_dscp(&mut self, dscp: u8) {
+        let tos = dscp << 2;
+        self.type_of_service = Some(tos);
+        #[cfg(not(windows))]
+        {
+            self.traffic_class = Some(tos);
+        }
+    }
+
+    /// Merge the values set in self over the ones in base, field by field
+    #[must_use]
+    pub fn merge_over(&self, base: &Self) -> Self {
+        TcpMiscSockOpts {
+            no_delay: self.no_delay.or(base.no_delay),
+            max_segment_size: self.max_segment_size.or(base.max_segment_size),
+            time_to_live: self.time_to_live.or(base.time_to_live),
+            hop_limit: self.hop_limit.or(base.hop_limit),
+            type_of_service: self.type_of_service.or(base.type_of_service),
+            #[cfg(not(windows))]
+            traffic_class: self.traffic_class.or(base.traffic_class),
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "freebsd",
+                target_os = "solaris",
+                target_os = "illumos"
+            ))]
+            congestion_control: self
+                .congestion_control
+                .clone()
+                .or(base.congestion_control.clone()),
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+            netfilter_mark: self.netfilter_mark.or(base.netfilter_mark),
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            notsent_lowat: self.notsent_lowat.or(base.notsent_lowat),
+        }
+    }
+
     #[must_use]
     pub fn adjust_to(&self, other: &Self) -> Self {
         let no_delay = match (self.no_delay, other.no_delay) {
@@ -80,8 +125,10 @@ impl TcpMiscSockOpts {
                 .congestion_control
                 .clone()
                 .or(self.congestion_control.clone()),
-            #[cfg(target_os = "linux")]
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
             netfilter_mark: other.netfilter_mark.or(self.netfilter_mark),
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            notsent_lowat: self.notsent_lowat.existed_min(other.notsent_lowat),
         }
     }
 }
@@ -0,0 +1,169 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+#[cfg(feature = "openssl")]
+use anyhow::anyhow;
+#[cfg(feature = "openssl")]
+use openssl::asn1::Asn1Time;
+#[cfg(feature = "openssl")]
+use openssl::nid::Nid;
+#[cfg(feature = "openssl")]
+use openssl::pkey::{PKeyRef, Private};
+#[cfg(feature = "openssl")]
+use openssl::x509::{X509, X509Ref, X509VerifyResult};
+
+/// The validity status of a loaded leaf certificate
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TlsCertStatus {
+    subject: String,
+    not_after: i64,
+    allow_expired: bool,
+}
+
+impl TlsCertStatus {
+    pub fn new(subject: String, not_after: i64) -> Self {
+        TlsCertStatus {
+            subject,
+            not_after,
+            allow_expired: false,
+        }
+    }
+
+    #[inline]
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// the not after time, as unix timestamp
+    #[inline]
+    pub fn not_after(&self) -> i64 {
+        self.not_after
+    }
+
+    #[inline]
+    pub fn allow_expired(&self) -> bool {
+        self.allow_expired
+    }
+
+    pub fn set_allow_expired(&mut self, allow: bool) {
+        self.allow_expired = allow;
+    }
+
+    /// Check the certificate chain and the private key, and get the status of the leaf certificate.
+    ///
+    /// The certs should be in order, the leaf certificate first, and each following certificate
+    /// should be the issuer of the previous one. The validity period is not checked here.
+    #[cfg(feature = "openssl")]
+    pub fn check_openssl(certs: &[X509], key: &PKeyRef<Private>) -> anyhow::Result<Self> {
+        let Some(leaf) = certs.first() else {
+            return Err(anyhow!("no certificate found"));
+        };
+
+        let public_key = leaf
+            .public_key()
+            .map_err(|e| anyhow!("failed to get public key of the certificate: {e}"))?;
+        if !public_key.public_eq(key) {
+            return Err(anyhow!(
+                "the private key does not match the certificate {}",
+                openssl_cert_subject(leaf)
+            ));
+        }
+
+        for (i, pair) in certs.windows(2).enumerate() {
+            if pair[1].issued(&pair[0]) != X509VerifyResult::OK {
+                return Err(anyhow!(
+                    "chain certificate #{i} {} is not the issuer of {}",
+                    openssl_cert_subject(&pair[1]),
+                    openssl_cert_subject(&pair[0])
+                ));
+            }
+        }
+
+        let epoch = Asn1Time::from_unix(0).map_err(|e| anyhow!("failed to get epoch time: {e}"))?;
+        let diff = epoch
+            .diff(leaf.not_after())
+            .map_err(|e| anyhow!("invalid not after time in certificate: {e}"))?;
+        let not_after = diff.days as i64 * 86400 + diff.secs as i64;
+
+        Ok(TlsCertStatus::new(openssl_cert_subject(leaf), not_after))
+    }
+}
+
+#[cfg(feature = "openssl")]
+fn openssl_cert_subject(cert: &X509Ref) -> String {
+    let name = cert.subject_name();
+    name.entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .and_then(|e| e.data().as_utf8().ok())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| format!("{name:?}"))
+}
+
+#[cfg(all(test, feature = "openssl"))]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::x509::{X509Builder, X509NameBuilder};
+
+    fn generate_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn generate_cert(
+        cn: &str,
+        key: &PKey<Private>,
+        issuer: Option<(&X509, &PKey<Private>)>,
+    ) -> X509 {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        let serial = BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap();
+        builder.set_serial_number(&serial).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::from_unix(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::from_unix(86400).unwrap())
+            .unwrap();
+        match issuer {
+            Some((ca_cert, ca_key)) => {
+                builder.set_issuer_name(ca_cert.subject_name()).unwrap();
+                builder.sign(ca_key, MessageDigest::sha256()).unwrap();
+            }
+            None => {
+                builder.set_issuer_name(&name).unwrap();
+                builder.sign(key, MessageDigest::sha256()).unwrap();
+            }
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn check_openssl() {
+        let ca_key = generate_key();
+        let ca_cert = generate_cert("test ca", &ca_key, None);
+        let key = generate_key();
+        let cert = generate_cert("example.com", &key, Some((&ca_cert, &ca_key)));
+
+        let status = TlsCertStatus::check_openssl(&[cert.clone(), ca_cert.clone()], &key).unwrap();
+        assert_eq!(status.subject(), "example.com");
+        assert_eq!(status.not_after(), 86400);
+        assert!(!status.allow_expired());
+
+        assert!(TlsCertStatus::check_openssl(std::slice::from_ref(&cert), &ca_key).is_err());
+        assert!(TlsCertStatus::check_openssl(&[ca_cert.clone(), cert], &ca_key).is_err());
+        assert!(TlsCertStatus::check_openssl(&[], &key).is_err());
+    }
+}
@@ -15,13 +15,16 @@ pub use service_type::TlsServiceType;
 mod cert_usage;
 pub use cert_usage::TlsCertUsage;
 
+mod cert_status;
+pub use cert_status::TlsCertStatus;
+
 mod ticket_name;
 pub use ticket_name::{TICKET_KEY_NAME_LENGTH, TicketKeyName};
 
 mod ticketer;
 pub use ticketer::{
-    RollingTicketKey, RollingTicketer, TICKET_AES_IV_LENGTH, TICKET_AES_KEY_LENGTH,
-    TICKET_HMAC_KEY_LENGTH,
+    RollingTicketKey, RollingTicketer, RollingTicketerSnapshot, TICKET_AES_IV_LENGTH,
+    TICKET_AES_KEY_LENGTH, TICKET_HMAC_KEY_LENGTH,
 };
 
 mod version;
@@ -3,6 +3,8 @@
  * Copyright 2024-2025 ByteDance and/or its affiliates.
  */
 
+use std::net::IpAddr;
+use std::str::FromStr;
 use std::str::Utf8Error;
 use std::sync::Arc;
 use std::{fmt, str};
@@ -25,6 +27,10 @@ pub enum TlsServerNameError {
     InvalidNameLength(usize),
     #[error("invalid host name: {0}")]
     InvalidHostName(Utf8Error),
+    #[error("invalid character {0:?} in host name")]
+    InvalidHostChar(char),
+    #[error("invalid label in host name")]
+    InvalidHostLabel,
 }
 
 #[derive(Clone)]
@@ -61,6 +67,51 @@ impl TlsServerName {
             host_name: Arc::from(host_name),
         })
     }
+
+    /// Validate the host name and convert it to the form used for host matching.
+    ///
+    /// A single trailing dot is stripped and all letters are converted to lowercase.
+    /// IP literals are converted to the canonical form, and any other value should be
+    /// a valid DNS name.
+    pub fn normalize(&self) -> Result<TlsServerName, TlsServerNameError> {
+        let name = self.host_name.as_ref();
+        if let Some(c) = name.chars().find(|c| c.is_control()) {
+            return Err(TlsServerNameError::InvalidHostChar(c));
+        }
+        let name = name.strip_suffix('.').unwrap_or(name);
+        if let Ok(ip) = IpAddr::from_str(name) {
+            return Ok(TlsServerName {
+                host_name: Arc::from(ip.to_string()),
+            });
+        }
+
+        for label in name.split('.') {
+            if label.is_empty() || label.len() > 63 {
+                return Err(TlsServerNameError::InvalidHostLabel);
+            }
+            if let Some(c) = label
+                .chars()
+                .find(|c| !c.is_ascii_alphanumeric() && *c != '-' && *c != '_')
+            {
+                return Err(TlsServerNameError::InvalidHostChar(c));
+            }
+            if label.starts_with('-') || label.ends_with('-') {
+                return Err(TlsServerNameError::InvalidHostLabel);
+            }
+        }
+
+        if name.len() == self.host_name.len() && !name.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Ok(self.clone());
+        }
+        Ok(TlsServerName {
+            host_name: Arc::from(name.to_ascii_lowercase()),
+        })
+    }
+
+    /// Get the IP address if the host name is an IP literal
+    pub fn ip_literal(&self) -> Option<IpAddr> {
+        IpAddr::from_str(&self.host_name).ok()
+    }
 }
 
 impl AsRef<str> for TlsServerName {
@@ -124,4 +175,71 @@ mod tests {
         ];
         assert!(TlsServerName::from_extension_value(data).is_err());
     }
+
+    fn server_name(name: &str) -> TlsServerName {
+        TlsServerName {
+            host_name: Arc::from(name),
+        }
+    }
+
+    #[test]
+    fn normalize_domain() {
+        for (name, expected) in [
+            ("example.net", "example.net"),
+            ("Example.NET", "example.net"),
+            ("example.net.", "example.net"),
+            ("WWW.Example.net.", "www.example.net"),
+            ("_acme.example-1.net", "_acme.example-1.net"),
+            ("localhost", "localhost"),
+        ] {
+            let sni = server_name(name).normalize().unwrap();
+            assert_eq!(sni.as_ref(), expected);
+            assert!(sni.ip_literal().is_none());
+        }
+    }
+
+    #[test]
+    fn normalize_ip() {
+        for (name, expected) in [
+            ("192.0.2.1", "192.0.2.1"),
+            ("192.0.2.1.", "192.0.2.1"),
+            ("2001:db8::1", "2001:db8::1"),
+            ("2001:DB8:0::1", "2001:db8::1"),
+        ] {
+            let sni = server_name(name).normalize().unwrap();
+            assert_eq!(sni.as_ref(), expected);
+            assert_eq!(sni.ip_literal(), Some(IpAddr::from_str(expected).unwrap()));
+        }
+    }
+
+    #[test]
+    fn normalize_invalid() {
+        for name in [
+            "",
+            ".",
+            "example.net..",
+            ".example.net",
+            "example..net",
+            "example.net\0",
+            "exam\tple.net",
+            "example.net\x7f",
+            "exa mple.net",
+            "*.example.net",
+            "example.net:443",
+            "[2001:db8::1]",
+            "ex\u{e4}mple.net",
+            "-example.net",
+            "example-.net",
+        ] {
+            assert!(
+                server_name(name).normalize().is_err(),
+                "{name:?} should be invalid"
+            );
+        }
+
+        let label = "a".repeat(64);
+        assert!(server_name(&format!("{label}.net")).normalize().is_err());
+        let label = "a".repeat(63);
+        assert!(server_name(&format!("{label}.net")).normalize().is_ok());
+    }
 }
@@ -3,7 +3,9 @@
  * Copyright 2024-2025 ByteDance and/or its affiliates.
  */
 
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 use arc_swap::ArcSwap;
 use rustc_hash::{FxBuildHasher, FxHashMap};
@@ -20,9 +22,34 @@ pub trait RollingTicketKey: Sized {
     fn lifetime(&self) -> u32;
 }
 
+#[derive(Default)]
+struct RollingTicketerStats {
+    issued: AtomicU64,
+    resumed_current: AtomicU64,
+    resumed_previous: AtomicU64,
+    rejected_unknown_key: AtomicU64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RollingTicketerSnapshot {
+    /// tickets issued, always with the current encrypt key
+    pub issued: u64,
+    /// resumptions with tickets encrypted by the current encrypt key
+    pub resumed_current: u64,
+    /// resumptions with tickets encrypted by a previous key that is still valid for decryption
+    pub resumed_previous: u64,
+    /// resumptions rejected as the key of the ticket is unknown or expired
+    pub rejected_unknown_key: u64,
+    /// time since the current encrypt key is in use
+    pub encrypt_key_age: Duration,
+}
+
 pub struct RollingTicketer<K: RollingTicketKey> {
     dec_keys: RwLock<FxHashMap<TicketKeyName, Arc<K>>>,
     pub(crate) enc_key: ArcSwap<K>,
+    enc_key_set_at: Mutex<Instant>,
+    retired_keys: Mutex<Vec<Arc<K>>>,
+    stats: RollingTicketerStats,
 }
 
 impl<K: RollingTicketKey> RollingTicketer<K> {
@@ -32,6 +59,9 @@ impl<K: RollingTicketKey> RollingTicketer<K> {
         let ticketer = RollingTicketer {
             dec_keys,
             enc_key: ArcSwap::new(key.clone()),
+            enc_key_set_at: Mutex::new(Instant::now()),
+            retired_keys: Mutex::new(Vec::new()),
+            stats: RollingTicketerStats::default(),
         };
         ticketer.add_decrypt_key(key);
         ticketer
@@ -44,6 +74,26 @@ impl<K: RollingTicketKey> RollingTicketer<K> {
         self.dec_keys.read().unwrap().get(&key_name).cloned()
     }
 
+    /// Get the decrypt key for a ticket to resume, and count the result
+    pub fn resume_decrypt_key(&self, name: &[u8]) -> Option<Arc<K>> {
+        let Some(key) = self.get_decrypt_key(name) else {
+            self.stats
+                .rejected_unknown_key
+                .fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        if key.name() == self.enc_key.load().name() {
+            self.stats.resumed_current.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.stats.resumed_previous.fetch_add(1, Ordering::Relaxed);
+        }
+        Some(key)
+    }
+
+    pub fn add_issued(&self) {
+        self.stats.issued.fetch_add(1, Ordering::Relaxed);
+    }
+
     pub fn add_decrypt_key(&self, key: Arc<K>) {
         let name = key.name();
         self.dec_keys.write().unwrap().insert(name, key);
@@ -58,6 +108,47 @@ impl<K: RollingTicketKey> RollingTicketer<K> {
     }
 
     pub fn set_encrypt_key(&self, key: Arc<K>) {
-        self.enc_key.store(key);
+        self.swap_encrypt_key(key);
+    }
+
+    /// Set the new encrypt key and return the old one
+    ///
+    /// The new key should be added as a decrypt key before calling this.
+    pub fn swap_encrypt_key(&self, key: Arc<K>) -> Arc<K> {
+        let old_key = self.enc_key.swap(key);
+        *self.enc_key_set_at.lock().unwrap() = Instant::now();
+        old_key
+    }
+
+    /// Replace the encrypt key with a new random one right now
+    ///
+    /// The old key is still valid for decryption, and will be returned by
+    /// [`Self::take_retired_keys`] so the updater can expire it later.
+    pub fn force_rotate(&self) -> anyhow::Result<()> {
+        let lifetime = self.enc_key.load().lifetime();
+        let key = Arc::new(K::new_random(lifetime)?);
+        self.add_decrypt_key(key.clone());
+        let old_key = self.swap_encrypt_key(key);
+        self.retired_keys.lock().unwrap().push(old_key);
+        Ok(())
+    }
+
+    /// Take the old encrypt keys that have been replaced by [`Self::force_rotate`]
+    pub fn take_retired_keys(&self) -> Vec<Arc<K>> {
+        std::mem::take(&mut *self.retired_keys.lock().unwrap())
+    }
+
+    pub fn encrypt_key_age(&self) -> Duration {
+        self.enc_key_set_at.lock().unwrap().elapsed()
+    }
+
+    pub fn snapshot(&self) -> RollingTicketerSnapshot {
+        RollingTicketerSnapshot {
+            issued: self.stats.issued.load(Ordering::Relaxed),
+            resumed_current: self.stats.resumed_current.load(Ordering::Relaxed),
+            resumed_previous: self.stats.resumed_previous.load(Ordering::Relaxed),
+            rejected_unknown_key: self.stats.rejected_unknown_key.load(Ordering::Relaxed),
+            encrypt_key_age: self.encrypt_key_age(),
+        }
     }
 }
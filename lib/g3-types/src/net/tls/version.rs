@@ -10,7 +10,7 @@ use anyhow::anyhow;
 #[cfg(feature = "openssl")]
 use openssl::ssl::SslVersion;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum TlsVersion {
     TLS1_0,
     TLS1_1,
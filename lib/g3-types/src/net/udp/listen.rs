@@ -4,6 +4,7 @@
  */
 
 use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
 
 use anyhow::anyhow;
 use num_traits::ToPrimitive;
@@ -31,10 +32,13 @@ pub struct UdpListenConfig {
     interface: Option<Interface>,
     #[cfg(not(target_os = "openbsd"))]
     ipv6only: Option<bool>,
+    #[cfg(target_os = "linux")]
+    transparent: bool,
     buf_conf: SocketBufferConfig,
     misc_opts: UdpMiscSockOpts,
     instance: usize,
     scale: usize,
+    drain_timeout: Duration,
 }
 
 impl Default for UdpListenConfig {
@@ -57,10 +61,13 @@ impl UdpListenConfig {
             interface: None,
             #[cfg(not(target_os = "openbsd"))]
             ipv6only: None,
+            #[cfg(target_os = "linux")]
+            transparent: false,
             buf_conf: SocketBufferConfig::default(),
             misc_opts: UdpMiscSockOpts::default(),
             instance: 1,
             scale: 0,
+            drain_timeout: Duration::ZERO,
         }
     }
 
@@ -114,11 +121,24 @@ impl UdpListenConfig {
         self.ipv6only
     }
 
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn transparent(&self) -> bool {
+        self.transparent
+    }
+
     #[inline]
     pub fn instance(&self) -> usize {
         self.instance.max(self.scale)
     }
 
+    /// Get how long to keep draining the datagrams buffered in the socket on shutdown,
+    /// zero means no drain
+    #[inline]
+    pub fn drain_timeout(&self) -> Duration {
+        self.drain_timeout
+    }
+
     #[inline]
     pub fn set_socket_address(&mut self, addr: SocketAddr) {
         self.address = addr;
@@ -157,6 +177,12 @@ impl UdpListenConfig {
         self.ipv6only = Some(ipv6only);
     }
 
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn set_transparent(&mut self) {
+        self.transparent = true;
+    }
+
     pub fn set_instance(&mut self, instance: usize) {
         if instance == 0 {
             self.instance = 1;
@@ -165,6 +191,11 @@ impl UdpListenConfig {
         }
     }
 
+    #[inline]
+    pub fn set_drain_timeout(&mut self, timeout: Duration) {
+        self.drain_timeout = timeout;
+    }
+
     pub fn set_scale(&mut self, scale: f64) -> anyhow::Result<()> {
         if let Ok(p) = std::thread::available_parallelism() {
             let v = (p.get() as f64) * scale;
@@ -12,8 +12,12 @@ pub struct UdpMiscSockOpts {
     pub type_of_service: Option<u8>,
     #[cfg(not(windows))]
     pub traffic_class: Option<u8>,
-    #[cfg(target_os = "linux")]
+    /// SO_MARK on Linux, or SO_USER_COOKIE on FreeBSD
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
     pub netfilter_mark: Option<u32>,
+    /// enable IP_RECVERR / IPV6_RECVERR to receive ICMP errors from the socket error queue
+    #[cfg(target_os = "linux")]
+    pub recv_error: Option<bool>,
 }
 
 impl UdpMiscSockOpts {
@@ -25,8 +29,10 @@ impl UdpMiscSockOpts {
             type_of_service: other.type_of_service.or(self.type_of_service),
             #[cfg(not(windows))]
             traffic_class: other.traffic_class.or(self.traffic_class),
-            #[cfg(target_os = "linux")]
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
             netfilter_mark: other.netfilter_mark.or(self.netfilter_mark),
+            #[cfg(target_os = "linux")]
+            recv_error: other.recv_error.or(self.recv_error),
         }
     }
 }
@@ -59,6 +59,11 @@ impl<T> HostMatch<T> {
     }
 
     pub fn get(&self, host: &Host) -> Option<&T> {
+        self.get_matched(host).or(self.default.as_ref())
+    }
+
+    /// Get the value matched by host rules, the default value will not be used
+    pub fn get_matched(&self, host: &Host) -> Option<&T> {
         match host {
             Host::Ip(ip) => {
                 if let Some(ht) = &self.exact_ip {
@@ -82,7 +87,7 @@ impl<T> HostMatch<T> {
                 }
             }
         }
-        self.default.as_ref()
+        None
     }
 
     #[inline]
@@ -90,6 +95,17 @@ impl<T> HostMatch<T> {
         self.default.as_ref()
     }
 
+    /// Iterate over all the values, including the default one.
+    /// The same value may be returned more than once if it's added for multiple hosts.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.exact_domain
+            .iter()
+            .flat_map(|ht| ht.values())
+            .chain(self.exact_ip.iter().flat_map(|ht| ht.values()))
+            .chain(self.child_domain.iter().flat_map(|trie| trie.values()))
+            .chain(self.default.iter())
+    }
+
     pub fn is_empty(&self) -> bool {
         self.exact_domain.is_none()
             && self.exact_ip.is_none()
@@ -13,6 +13,7 @@ mod util;
 
 pub mod humanize;
 pub mod key;
+pub mod schema;
 pub mod value;
 
 pub use callback::YamlMapCallback;
@@ -0,0 +1,175 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ * Copyright 2025 ByteDance and/or its affiliates.
+ */
+
+use anyhow::anyhow;
+
+/// The kind of the value accepted by a yaml config key
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum YamlValueKind {
+    Bool,
+    Integer,
+    Float,
+    /// integer value, or string value with humanize unit suffix
+    HumanizeInteger,
+    /// integer / float value in seconds, or string value with humanize unit suffix
+    HumanizeDuration,
+    String,
+    Seq,
+    Map,
+    /// nested config object, with the object type name
+    Object(&'static str),
+}
+
+impl YamlValueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            YamlValueKind::Bool => "bool",
+            YamlValueKind::Integer => "integer",
+            YamlValueKind::Float => "float",
+            YamlValueKind::HumanizeInteger => "humanize_integer",
+            YamlValueKind::HumanizeDuration => "humanize_duration",
+            YamlValueKind::String => "string",
+            YamlValueKind::Seq => "seq",
+            YamlValueKind::Map => "map",
+            YamlValueKind::Object(_) => "object",
+        }
+    }
+}
+
+/// The description of a yaml config key
+#[derive(Clone, Copy, Debug)]
+pub struct YamlKeySchema {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub kind: YamlValueKind,
+    pub default: Option<&'static str>,
+    /// the key that should be used instead, if this key is deprecated
+    pub replaced_by: Option<&'static str>,
+    /// a sample yaml value that should be accepted
+    pub example: &'static str,
+}
+
+impl YamlKeySchema {
+    pub const fn new(name: &'static str, kind: YamlValueKind, example: &'static str) -> Self {
+        YamlKeySchema {
+            name,
+            aliases: &[],
+            kind,
+            default: None,
+            replaced_by: None,
+            example,
+        }
+    }
+
+    pub const fn aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    pub const fn default_value(mut self, default: &'static str) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub const fn deprecated(mut self, replaced_by: &'static str) -> Self {
+        self.replaced_by = Some(replaced_by);
+        self
+    }
+
+    #[inline]
+    pub fn is_deprecated(&self) -> bool {
+        self.replaced_by.is_some()
+    }
+
+    fn matches(&self, key: &str) -> bool {
+        self.name == key || self.aliases.contains(&key)
+    }
+}
+
+/// The description of all keys accepted by a yaml map config
+#[derive(Clone, Copy, Debug)]
+pub struct YamlMapSchema {
+    pub name: &'static str,
+    pub keys: &'static [YamlKeySchema],
+}
+
+impl YamlMapSchema {
+    pub const fn new(name: &'static str, keys: &'static [YamlKeySchema]) -> Self {
+        YamlMapSchema { name, keys }
+    }
+
+    /// Find the key schema for the normalized key
+    pub fn lookup(&self, key: &str) -> Option<&'static YamlKeySchema> {
+        self.keys.iter().find(|s| s.matches(key))
+    }
+
+    /// Get the canonical name of the raw config key
+    pub fn canonical_key(&self, key: &str) -> anyhow::Result<&'static str> {
+        let normalized = crate::key::normalize(key);
+        self.lookup(&normalized)
+            .map(|s| s.name)
+            .ok_or_else(|| anyhow!("invalid key {key}"))
+    }
+
+    #[cfg(feature = "schema")]
+    pub fn to_json(&self) -> serde_json::Value {
+        let keys = self
+            .keys
+            .iter()
+            .map(|s| {
+                let mut map = serde_json::Map::new();
+                map.insert("name".to_string(), s.name.into());
+                map.insert("aliases".to_string(), s.aliases.into());
+                map.insert("kind".to_string(), s.kind.as_str().into());
+                if let YamlValueKind::Object(object) = s.kind {
+                    map.insert("object".to_string(), object.into());
+                }
+                if let Some(default) = s.default {
+                    map.insert("default".to_string(), default.into());
+                }
+                map.insert("deprecated".to_string(), s.is_deprecated().into());
+                if let Some(replaced_by) = s.replaced_by {
+                    map.insert("replaced_by".to_string(), replaced_by.into());
+                }
+                map.insert("example".to_string(), s.example.into());
+                serde_json::Value::Object(map)
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "name": self.name,
+            "keys": keys,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &[YamlKeySchema] = &[
+        YamlKeySchema::new("name", YamlValueKind::String, "test"),
+        YamlKeySchema::new("max_connections", YamlValueKind::Integer, "10")
+            .aliases(&["max_conn"])
+            .default_value("0"),
+        YamlKeySchema::new("conn_limit", YamlValueKind::Map, "{}").deprecated("speed_limit"),
+    ];
+    const SCHEMA: YamlMapSchema = YamlMapSchema::new("test", KEYS);
+
+    #[test]
+    fn lookup() {
+        assert_eq!(SCHEMA.lookup("name").unwrap().name, "name");
+        assert_eq!(SCHEMA.lookup("max_conn").unwrap().name, "max_connections");
+        assert!(SCHEMA.lookup("max-conn").is_none());
+        assert!(SCHEMA.lookup("conn_limit").unwrap().is_deprecated());
+        assert!(SCHEMA.lookup("unknown").is_none());
+    }
+
+    #[test]
+    fn canonical_key() {
+        assert_eq!(SCHEMA.canonical_key("max-conn").unwrap(), "max_connections");
+        assert_eq!(SCHEMA.canonical_key("Name").unwrap(), "name");
+        assert!(SCHEMA.canonical_key("unknown").is_err());
+    }
+}
@@ -9,7 +9,7 @@ use std::str::FromStr;
 use anyhow::{Context, anyhow};
 use yaml_rust::Yaml;
 
-use g3_histogram::{HistogramMetricsConfig, Quantile};
+use g3_histogram::{BucketHistogramConfig, HistogramMetricsConfig, Quantile};
 
 pub fn as_quantile(value: &Yaml) -> anyhow::Result<Quantile> {
     match value {
@@ -76,6 +76,41 @@ pub fn as_histogram_metrics_config(value: &Yaml) -> anyhow::Result<HistogramMetr
     }
 }
 
+pub fn as_bucket_histogram_config(value: &Yaml) -> anyhow::Result<BucketHistogramConfig> {
+    match value {
+        Yaml::Array(seq) => {
+            let mut bounds = Vec::with_capacity(seq.len());
+            for (i, v) in seq.iter().enumerate() {
+                let bound = crate::humanize::as_u64(v)
+                    .context(format!("invalid bucket bound value for element #{i}"))?;
+                bounds.push(bound);
+            }
+            Ok(BucketHistogramConfig::with_bounds(bounds))
+        }
+        Yaml::Hash(map) => {
+            let mut base = 4;
+            let mut max = 1 << 30;
+            crate::foreach_kv(map, |k, v| match crate::key::normalize(k).as_str() {
+                "base" => {
+                    base = crate::value::as_u64(v)
+                        .context(format!("invalid u64 value for key {k}"))?;
+                    Ok(())
+                }
+                "max" => {
+                    max = crate::humanize::as_u64(v)
+                        .context(format!("invalid humanize u64 value for key {k}"))?;
+                    Ok(())
+                }
+                _ => Err(anyhow!("invalid key {k}")),
+            })?;
+            Ok(BucketHistogramConfig::powers_of(base, max))
+        }
+        _ => Err(anyhow!(
+            "the yaml value type for 'bucket histogram config' should be 'seq' or 'map'"
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,4 +218,26 @@ mod tests {
         let yaml = Yaml::Null;
         assert!(as_histogram_metrics_config(&yaml).is_err());
     }
+
+    #[test]
+    fn as_bucket_histogram_config_ok() {
+        let yaml = yaml_doc!("- 1K\n- 16\n- 1M");
+        let config = as_bucket_histogram_config(&yaml).unwrap();
+        assert_eq!(config.bounds(), &[16, 1000, 1000 * 1000]);
+
+        let yaml = yaml_doc!("base: 16\nmax: 1MiB");
+        let config = as_bucket_histogram_config(&yaml).unwrap();
+        assert_eq!(config.bounds(), &[1, 16, 256, 4096, 65536, 1 << 20]);
+
+        let yaml = yaml_doc!("{}");
+        let config = as_bucket_histogram_config(&yaml).unwrap();
+        assert_eq!(config, BucketHistogramConfig::default());
+    }
+
+    #[test]
+    fn as_bucket_histogram_config_err() {
+        assert!(as_bucket_histogram_config(&yaml_str!("1K")).is_err());
+        assert!(as_bucket_histogram_config(&yaml_doc!("- a")).is_err());
+        assert!(as_bucket_histogram_config(&yaml_doc!("step: 4")).is_err());
+    }
 }
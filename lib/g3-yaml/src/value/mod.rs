@@ -43,7 +43,9 @@ pub mod acl_set;
 #[cfg(feature = "histogram")]
 mod histogram;
 #[cfg(feature = "histogram")]
-pub use histogram::{as_histogram_metrics_config, as_quantile, as_quantile_list};
+pub use histogram::{
+    as_bucket_histogram_config, as_histogram_metrics_config, as_quantile, as_quantile_list,
+};
 
 #[cfg(feature = "regex")]
 mod regex;
@@ -3,12 +3,17 @@
  * Copyright 2023-2025 ByteDance and/or its affiliates.
  */
 
+use std::str::FromStr;
+
 use yaml_rust::Yaml;
 
 use anyhow::{Context, anyhow};
 use g3_types::net::ProxyProtocolVersion;
 
 pub fn as_proxy_protocol_version(value: &Yaml) -> anyhow::Result<ProxyProtocolVersion> {
+    if let Yaml::String(s) = value {
+        return ProxyProtocolVersion::from_str(s);
+    }
     let v =
         crate::value::as_u8(value).context("ProxyProtocolVersion should be a valid u8 value")?;
     match v {
@@ -35,6 +40,12 @@ mod tests {
             as_proxy_protocol_version(&yaml).unwrap(),
             ProxyProtocolVersion::V2
         );
+
+        let yaml = yaml_str!("v2");
+        assert_eq!(
+            as_proxy_protocol_version(&yaml).unwrap(),
+            ProxyProtocolVersion::V2
+        );
     }
 
     #[test]
@@ -42,6 +53,9 @@ mod tests {
         let yaml = yaml_str!("3"); // Invalid version
         assert!(as_proxy_protocol_version(&yaml).is_err());
 
+        let yaml = yaml_str!("v3"); // Invalid version
+        assert!(as_proxy_protocol_version(&yaml).is_err());
+
         let yaml = Yaml::Integer(256); // Beyond u8 range
         assert!(as_proxy_protocol_version(&yaml).is_err());
 
@@ -299,13 +299,28 @@ pub fn as_tcp_misc_sock_opts(v: &Yaml) -> anyhow::Result<TcpMiscSockOpts> {
                 config.set_congestion_control(ca);
                 Ok(())
             }
-            #[cfg(target_os = "linux")]
-            "netfilter_mark" | "mark" => {
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+            "netfilter_mark" | "fwmark" | "mark" => {
                 let mark =
                     crate::value::as_u32(v).context(format!("invalid u32 value for key {k}"))?;
                 config.netfilter_mark = Some(mark);
                 Ok(())
             }
+            #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+            "netfilter_mark" | "fwmark" | "mark" => {
+                Err(anyhow!("socket mark is not supported on this platform"))
+            }
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            "notsent_lowat" | "not_sent_low_watermark" => {
+                let lowat = crate::humanize::as_u32(v)
+                    .context(format!("invalid humanize u32 value for key {k}"))?;
+                config.notsent_lowat = Some(lowat);
+                Ok(())
+            }
+            #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+            "notsent_lowat" | "not_sent_low_watermark" => Err(anyhow!(
+                "TCP_NOTSENT_LOWAT is not supported on this platform"
+            )),
             _ => Err(anyhow!("invalid key {k}")),
         })?;
 
@@ -593,8 +608,22 @@ mod tests {
         assert_eq!(config.max_segment_size, default_config.max_segment_size);
         assert_eq!(config.time_to_live, default_config.time_to_live);
         assert_eq!(config.type_of_service, default_config.type_of_service);
-        #[cfg(target_os = "linux")]
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
         assert_eq!(config.netfilter_mark, default_config.netfilter_mark);
+
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        {
+            let yaml = yaml_doc!("mark: 100");
+            let config = as_tcp_misc_sock_opts(&yaml).unwrap();
+            assert_eq!(config.netfilter_mark, Some(100));
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        {
+            let yaml = yaml_doc!("notsent_lowat: 16Ki");
+            let config = as_tcp_misc_sock_opts(&yaml).unwrap();
+            assert_eq!(config.notsent_lowat, Some(16 * 1024));
+        }
     }
 
     #[test]
@@ -613,5 +642,26 @@ mod tests {
 
         let yaml = yaml_doc!("type_of_service: \"not_u8\"");
         assert!(as_tcp_misc_sock_opts(&yaml).is_err());
+
+        let yaml = yaml_doc!("netfilter_mark: -1");
+        assert!(as_tcp_misc_sock_opts(&yaml).is_err());
+
+        // the mark should never be ignored silently
+        #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+        {
+            let yaml = yaml_doc!("fwmark: 100");
+            let e = as_tcp_misc_sock_opts(&yaml).unwrap_err();
+            assert!(format!("{e:#}").contains("not supported"));
+        }
+
+        let yaml = yaml_doc!("notsent_lowat: -1");
+        assert!(as_tcp_misc_sock_opts(&yaml).is_err());
+
+        // should be rejected at parse time if not supported
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        {
+            let yaml = yaml_doc!("notsent_lowat: 16384");
+            assert!(as_tcp_misc_sock_opts(&yaml).is_err());
+        }
     }
 }
@@ -40,13 +40,24 @@ pub fn as_udp_misc_sock_opts(v: &Yaml) -> anyhow::Result<UdpMiscSockOpts> {
                 config.traffic_class = Some(class);
                 Ok(())
             }
-            #[cfg(target_os = "linux")]
-            "netfilter_mark" | "mark" => {
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+            "netfilter_mark" | "fwmark" | "mark" => {
                 let mark =
                     crate::value::as_u32(v).context(format!("invalid u32 value for key {k}"))?;
                 config.netfilter_mark = Some(mark);
                 Ok(())
             }
+            #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+            "netfilter_mark" | "fwmark" | "mark" => {
+                Err(anyhow!("socket mark is not supported on this platform"))
+            }
+            #[cfg(target_os = "linux")]
+            "recv_error" | "recv_icmp_error" => {
+                let enable =
+                    crate::value::as_bool(v).context(format!("invalid bool value for key {k}"))?;
+                config.recv_error = Some(enable);
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         })?;
 
@@ -165,6 +176,12 @@ pub fn as_udp_listen_config(value: &Yaml) -> anyhow::Result<UdpListenConfig> {
                 }
                 "scale" => set_udp_listen_scale(&mut config, v)
                     .context(format!("invalid scale value for key {k}")),
+                "drain_timeout" | "udp_drain_timeout" => {
+                    let timeout = crate::humanize::as_duration(v)
+                        .context(format!("invalid humanize duration value for key {k}"))?;
+                    config.set_drain_timeout(timeout);
+                    Ok(())
+                }
                 _ => Err(anyhow!("invalid key {k}")),
             })?;
         }
@@ -206,8 +223,30 @@ mod tests {
         assert_eq!(config.type_of_service, Some(20));
         #[cfg(not(windows))]
         assert!(config.traffic_class.is_none());
-        #[cfg(target_os = "linux")]
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
         assert!(config.netfilter_mark.is_none());
+        #[cfg(target_os = "linux")]
+        assert!(config.recv_error.is_none());
+
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        {
+            let yaml = yaml_doc!("fwmark: 0x10");
+            let config = as_udp_misc_sock_opts(&yaml).unwrap();
+            assert_eq!(config.netfilter_mark, Some(0x10));
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+        {
+            let yaml = yaml_doc!("mark: 16");
+            let e = as_udp_misc_sock_opts(&yaml).unwrap_err();
+            assert!(format!("{e:#}").contains("not supported"));
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let yaml = yaml_doc!("recv_icmp_error: true");
+            let config = as_udp_misc_sock_opts(&yaml).unwrap();
+            assert_eq!(config.recv_error, Some(true));
+        }
     }
 
     #[test]
@@ -233,6 +272,9 @@ mod tests {
         let yaml = yaml_str!("mark: -1"); // out of range for u32
         assert!(as_udp_misc_sock_opts(&yaml).is_err());
 
+        let yaml = yaml_str!("recv_error: 'a string'");
+        assert!(as_udp_misc_sock_opts(&yaml).is_err());
+
         let yaml = yaml_str!("a string");
         assert!(as_udp_misc_sock_opts(&yaml).is_err());
 
@@ -335,6 +377,14 @@ mod tests {
         expected_config.set_scale(1.5).unwrap();
         assert_eq!(parsed_config, expected_config);
 
+        // Drain timeout
+        let yaml = yaml_doc!("addr: 127.0.0.1:1003\nudp_drain_timeout: 2s");
+        let parsed_config = as_udp_listen_config(&yaml).unwrap();
+        assert_eq!(
+            parsed_config.drain_timeout(),
+            std::time::Duration::from_secs(2)
+        );
+
         // Interface config
         #[cfg(any(
             target_os = "linux",
@@ -377,6 +427,10 @@ mod tests {
         let yaml = yaml_doc!("addr: 127.0.0.1:1006\nfoo: bar");
         assert!(as_udp_listen_config(&yaml).is_err());
 
+        // Invalid drain timeout
+        let yaml = yaml_doc!("addr: 127.0.0.1:1007\ndrain_timeout: abc");
+        assert!(as_udp_listen_config(&yaml).is_err());
+
         // Invalid address
         let yaml = yaml_doc!("addr: 12345");
         assert!(as_udp_listen_config(&yaml).is_err());
@@ -278,6 +278,11 @@ fn set_openssl_tls_client_config_builder(
                 builder.set_tlcp_cert_pair(pair);
                 Ok(())
             }
+            "allow_expired" => {
+                let allow = crate::value::as_bool(v)?;
+                builder.set_allow_expired(allow);
+                Ok(())
+            }
             "ca_certificate" | "ca_cert" | "server_auth_certificate" | "server_auth_cert" => {
                 let certs = as_openssl_certificates(v, lookup_dir)
                     .context(format!("invalid certificates value for key {k}"))?;
@@ -566,6 +571,11 @@ pub fn as_openssl_tls_server_config_builder(
                     .context(format!("invalid value for key {k}"))?;
                 builder.set_client_auth_certificates(certs)
             }
+            "allow_expired" => {
+                let allow = crate::value::as_bool(v)?;
+                builder.set_allow_expired(allow);
+                Ok(())
+            }
             "handshake_timeout" | "negotiation_timeout" | "accept_timeout" => {
                 let timeout = crate::humanize::as_duration(v)
                     .context(format!("invalid humanize duration value for key {k}"))?;
@@ -169,6 +169,11 @@ pub fn as_rustls_client_config_builder(
                 builder.set_cert_pair(pair);
                 Ok(())
             }
+            "allow_expired" => {
+                let allow = crate::value::as_bool(v)?;
+                builder.set_allow_expired(allow);
+                Ok(())
+            }
             "ca_certificate" | "ca_cert" | "server_auth_certificate" | "server_auth_cert" => {
                 let certs = as_rustls_certificates(v, lookup_dir)
                     .context(format!("invalid certificates value for key {k}"))?;
@@ -250,6 +255,11 @@ pub fn as_rustls_server_config_builder(
                 cert_pair_builder.set_key(key);
                 Ok(())
             }
+            "allow_expired" => {
+                let allow = crate::value::as_bool(v)?;
+                builder.set_allow_expired(allow);
+                Ok(())
+            }
             "enable_client_auth" => {
                 let enable = crate::value::as_bool(v)?;
                 if enable {
@@ -745,6 +755,7 @@ mod tests {
                     {}
                 private_key: |-
                     {}
+                allow_expired: true
             "#,
             cert_path.display(),
             key_path.display()
@@ -753,6 +764,7 @@ mod tests {
         let builder = as_rustls_server_config_builder(&yaml[0], None).unwrap();
         let mut expected = RustlsServerConfigBuilder::empty();
         expected.push_cert_pair(cert_pair3);
+        expected.set_allow_expired(true);
         assert_eq!(builder, expected);
     }
 